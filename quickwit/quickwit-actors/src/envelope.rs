@@ -22,6 +22,7 @@ use std::fmt;
 
 use async_trait::async_trait;
 use tokio::sync::oneshot;
+use tracing::{info_span, Instrument, Span};
 
 use crate::scheduler::NoAdvanceTimeGuard;
 use crate::{Actor, ActorContext, ActorExitStatus, Handler};
@@ -36,6 +37,9 @@ use crate::{Actor, ActorContext, ActorExitStatus, Handler};
 
 pub struct Envelope<A> {
     handler_envelope: Box<dyn EnvelopeT<A>>,
+    /// Span in which the message was sent. The message is handled in a child span of it, so
+    /// that the work of the actor is traced as part of the request that caused it.
+    sender_span: Span,
     _no_advance_time_guard: Option<NoAdvanceTimeGuard>,
 }
 
@@ -61,7 +65,21 @@ impl<A: Actor> Envelope<A> {
         actor: &mut A,
         ctx: &ActorContext<A>,
     ) -> Result<(), ActorExitStatus> {
-        self.handler_envelope.handle_message(actor, ctx).await?;
+        // Messages sent outside of any span, such as the ones of the actor loops, are not traced.
+        if self.sender_span.is_none() {
+            self.handler_envelope.handle_message(actor, ctx).await?;
+            return Ok(());
+        }
+        let span = info_span!(
+            parent: &self.sender_span,
+            "actor_message",
+            actor = ctx.actor_instance_id(),
+            message = self.handler_envelope.message_type_name(),
+        );
+        self.handler_envelope
+            .handle_message(actor, ctx)
+            .instrument(span)
+            .await?;
         Ok(())
     }
 }
@@ -77,6 +95,9 @@ impl<A: Actor> fmt::Debug for Envelope<A> {
 trait EnvelopeT<A: Actor>: Send + Sync {
     fn debug_msg(&self) -> String;
 
+    /// Returns the name of the type of the message.
+    fn message_type_name(&self) -> &'static str;
+
     /// Returns the message as a boxed any.
     ///
     /// This method is only useful in unit tests.
@@ -105,6 +126,10 @@ where
         }
     }
 
+    fn message_type_name(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
+
     fn message(&mut self) -> Box<dyn Any> {
         if let Some((_, message)) = self.take() {
             Box::new(message)
@@ -141,6 +166,7 @@ where
     let handler_envelope = Some((response_tx, msg));
     let envelope = Envelope {
         handler_envelope: Box::new(handler_envelope),
+        sender_span: Span::current(),
         _no_advance_time_guard: no_advance_time_guard,
     };
     (envelope, response_rx)
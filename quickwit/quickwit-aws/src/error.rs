@@ -25,13 +25,15 @@ use std::{fmt, io};
 use rusoto_core::RusotoError;
 #[cfg(feature = "kinesis")]
 use rusoto_kinesis::{
-    CreateStreamError, DeleteStreamError, DescribeStreamError, GetRecordsError,
-    GetShardIteratorError, ListShardsError, ListStreamsError, MergeShardsError, SplitShardError,
+    CreateStreamError, DeleteStreamError, DescribeStreamConsumerError, DescribeStreamError,
+    DescribeStreamSummaryError, GetRecordsError, GetShardIteratorError, ListShardsError,
+    ListStreamsError, MergeShardsError, RegisterStreamConsumerError, SplitShardError,
+    SubscribeToShardError,
 };
 use rusoto_s3::{
     AbortMultipartUploadError, CompleteMultipartUploadError, CreateMultipartUploadError,
-    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError, PutObjectError,
-    UploadPartError,
+    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError,
+    ListMultipartUploadsError, ListObjectsV2Error, PutObjectError, UploadPartError,
 };
 
 use crate::retry::Retryable;
@@ -133,6 +135,18 @@ impl Retryable for HeadObjectError {
     }
 }
 
+impl Retryable for ListObjectsV2Error {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
+
+impl Retryable for ListMultipartUploadsError {
+    fn is_retryable(&self) -> bool {
+        false
+    }
+}
+
 #[cfg(feature = "kinesis")]
 impl Retryable for GetRecordsError {
     fn is_retryable(&self) -> bool {
@@ -202,3 +216,31 @@ impl Retryable for SplitShardError {
         matches!(self, SplitShardError::LimitExceeded(_))
     }
 }
+
+#[cfg(feature = "kinesis")]
+impl Retryable for DescribeStreamSummaryError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, DescribeStreamSummaryError::LimitExceeded(_))
+    }
+}
+
+#[cfg(feature = "kinesis")]
+impl Retryable for RegisterStreamConsumerError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, RegisterStreamConsumerError::LimitExceeded(_))
+    }
+}
+
+#[cfg(feature = "kinesis")]
+impl Retryable for DescribeStreamConsumerError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, DescribeStreamConsumerError::LimitExceeded(_))
+    }
+}
+
+#[cfg(feature = "kinesis")]
+impl Retryable for SubscribeToShardError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, SubscribeToShardError::LimitExceeded(_))
+    }
+}
@@ -0,0 +1,449 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use byte_unit::Byte;
+use clap::{arg, ArgMatches, Command};
+use colored::Colorize;
+use quickwit_common::GREEN_COLOR;
+use quickwit_config::RolloverConditions;
+use quickwit_metastore::{IndexAlias, IndexAliasAction};
+use quickwit_rest_client::rest_client::{QuickwitClient, Transport};
+use reqwest::Url;
+use tabled::{Table, Tabled};
+use tracing::debug;
+
+use crate::{cluster_endpoint_arg, make_table};
+
+pub fn build_alias_command<'a>() -> Command<'a> {
+    Command::new("alias")
+        .about("Manages index aliases: add, remove, swap, rollover, delete, list...")
+        .arg(cluster_endpoint_arg())
+        .subcommand(
+            Command::new("add")
+                .about("Makes an alias point to an index, creating the alias if it does not exist.")
+                .args(&[
+                    arg!(--alias <ALIAS> "ID of the alias")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the index to add to the alias")
+                        .display_order(2),
+                ])
+            )
+        .subcommand(
+            Command::new("remove")
+                .about("Removes an index from an alias. The alias is deleted once it points to no index.")
+                .args(&[
+                    arg!(--alias <ALIAS> "ID of the alias")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the index to remove from the alias")
+                        .display_order(2),
+                ])
+            )
+        .subcommand(
+            Command::new("swap")
+                .about("Atomically replaces an index of an alias with another index: searches targeting the alias never see both or neither of them.")
+                .args(&[
+                    arg!(--alias <ALIAS> "ID of the alias")
+                        .display_order(1),
+                    arg!(--from <FROM_INDEX> "ID of the index the alias points to")
+                        .display_order(2),
+                    arg!(--to <TO_INDEX> "ID of the index the alias should point to instead")
+                        .display_order(3),
+                ])
+            )
+        .subcommand(
+            Command::new("rollover")
+                .about("Rolls a write alias over to a new index if the index it points to meets any of the conditions, or unconditionally if no condition is given.")
+                .args(&[
+                    arg!(--alias <ALIAS> "ID of the write alias")
+                        .display_order(1),
+                    arg!(--"max-age" <MAX_AGE> "Maximum age of the index, such as `1 day`")
+                        .required(false)
+                        .display_order(2),
+                    arg!(--"max-docs" <MAX_DOCS> "Maximum number of published documents of the index")
+                        .required(false)
+                        .display_order(3),
+                    arg!(--"max-size" <MAX_SIZE> "Maximum uncompressed size of the published documents of the index, such as `50 GB`")
+                        .required(false)
+                        .display_order(4),
+                    arg!(--"dry-run" "Evaluates the conditions without rolling the alias over.")
+                        .required(false)
+                        .display_order(5),
+                ])
+            )
+        .subcommand(
+            Command::new("delete")
+                .about("Deletes an alias. The indexes it points to are left untouched.")
+                .args(&[
+                    arg!(--alias <ALIAS> "ID of the alias")
+                        .display_order(1),
+                ])
+            )
+        .subcommand(
+            Command::new("list")
+                .about("Lists the aliases and the indexes they point to.")
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UpdateAliasArgs {
+    pub cluster_endpoint: Url,
+    pub actions: Vec<IndexAliasAction>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RolloverAliasArgs {
+    pub cluster_endpoint: Url,
+    pub alias_id: String,
+    pub conditions: RolloverConditions,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DeleteAliasArgs {
+    pub cluster_endpoint: Url,
+    pub alias_id: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ListAliasesArgs {
+    pub cluster_endpoint: Url,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AliasCliCommand {
+    Update(UpdateAliasArgs),
+    Rollover(RolloverAliasArgs),
+    Delete(DeleteAliasArgs),
+    List(ListAliasesArgs),
+}
+
+impl AliasCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
+        match subcommand {
+            "add" => Self::parse_add_args(submatches),
+            "remove" => Self::parse_remove_args(submatches),
+            "swap" => Self::parse_swap_args(submatches),
+            "rollover" => Self::parse_rollover_args(submatches),
+            "delete" => Self::parse_delete_args(submatches),
+            "list" => Self::parse_list_args(submatches),
+            _ => bail!("Subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_add_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        let alias_id = parse_required_arg(matches, "alias");
+        let index_id = parse_required_arg(matches, "index");
+        Ok(Self::Update(UpdateAliasArgs {
+            cluster_endpoint,
+            actions: vec![IndexAliasAction::Add { alias_id, index_id }],
+        }))
+    }
+
+    fn parse_remove_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        let alias_id = parse_required_arg(matches, "alias");
+        let index_id = parse_required_arg(matches, "index");
+        Ok(Self::Update(UpdateAliasArgs {
+            cluster_endpoint,
+            actions: vec![IndexAliasAction::Remove { alias_id, index_id }],
+        }))
+    }
+
+    fn parse_swap_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        let alias_id = parse_required_arg(matches, "alias");
+        let from_index_id = parse_required_arg(matches, "from");
+        let to_index_id = parse_required_arg(matches, "to");
+        Ok(Self::Update(UpdateAliasArgs {
+            cluster_endpoint,
+            actions: vec![
+                IndexAliasAction::Remove {
+                    alias_id: alias_id.clone(),
+                    index_id: from_index_id,
+                },
+                IndexAliasAction::Add {
+                    alias_id,
+                    index_id: to_index_id,
+                },
+            ],
+        }))
+    }
+
+    fn parse_rollover_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        let alias_id = parse_required_arg(matches, "alias");
+        let max_age = matches.value_of("max-age").map(String::from);
+        let max_docs = matches
+            .value_of("max-docs")
+            .map(u64::from_str)
+            .transpose()
+            .context("Failed to parse `--max-docs`.")?;
+        let max_size = matches
+            .value_of("max-size")
+            .map(Byte::from_str)
+            .transpose()
+            .context("Failed to parse `--max-size`.")?;
+        let conditions = RolloverConditions {
+            max_age,
+            max_docs,
+            max_size,
+        };
+        conditions.validate()?;
+        let dry_run = matches.is_present("dry-run");
+        Ok(Self::Rollover(RolloverAliasArgs {
+            cluster_endpoint,
+            alias_id,
+            conditions,
+            dry_run,
+        }))
+    }
+
+    fn parse_delete_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        let alias_id = parse_required_arg(matches, "alias");
+        Ok(Self::Delete(DeleteAliasArgs {
+            cluster_endpoint,
+            alias_id,
+        }))
+    }
+
+    fn parse_list_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = parse_cluster_endpoint(matches)?;
+        Ok(Self::List(ListAliasesArgs { cluster_endpoint }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Update(args) => update_alias_cli(args).await,
+            Self::Rollover(args) => rollover_alias_cli(args).await,
+            Self::Delete(args) => delete_alias_cli(args).await,
+            Self::List(args) => list_aliases_cli(args).await,
+        }
+    }
+}
+
+fn parse_cluster_endpoint(matches: &ArgMatches) -> anyhow::Result<Url> {
+    let cluster_endpoint = matches
+        .value_of("endpoint")
+        .map(Url::from_str)
+        .expect("`endpoint` is a required arg.")?;
+    Ok(cluster_endpoint)
+}
+
+fn parse_required_arg(matches: &ArgMatches, arg_name: &str) -> String {
+    matches
+        .value_of(arg_name)
+        .map(String::from)
+        .unwrap_or_else(|| panic!("`{arg_name}` is a required arg."))
+}
+
+async fn update_alias_cli(args: UpdateAliasArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "update-alias");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let index_aliases = qw_client.aliases().update(args.actions).await?;
+    println!("{} Alias successfully updated.", "✔".color(GREEN_COLOR));
+    println!("\n{}\n", make_list_aliases_table(index_aliases));
+    Ok(())
+}
+
+async fn rollover_alias_cli(args: RolloverAliasArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "rollover-alias");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let rollover_report = qw_client
+        .aliases()
+        .rollover(&args.alias_id, &args.conditions, args.dry_run)
+        .await?;
+    let old_index_id = rollover_report
+        .old_index_id
+        .unwrap_or_else(|| "none".to_string());
+    match (rollover_report.rolled_over, rollover_report.dry_run) {
+        (true, false) => println!(
+            "{} Alias `{}` successfully rolled over from `{old_index_id}` to `{}`.",
+            "✔".color(GREEN_COLOR),
+            rollover_report.alias_id,
+            rollover_report.new_index_id
+        ),
+        (true, true) => println!(
+            "Alias `{}` would be rolled over from `{old_index_id}` to `{}`.",
+            rollover_report.alias_id, rollover_report.new_index_id
+        ),
+        (false, _) => println!(
+            "Alias `{}` was not rolled over: index `{old_index_id}` meets none of the conditions.",
+            rollover_report.alias_id
+        ),
+    }
+    if !rollover_report.met_conditions.is_empty() {
+        println!(
+            "Met conditions: {}",
+            rollover_report.met_conditions.join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn delete_alias_cli(args: DeleteAliasArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "delete-alias");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    qw_client.aliases().delete(&args.alias_id).await?;
+    println!(
+        "{} Alias `{}` successfully deleted.",
+        "✔".color(GREEN_COLOR),
+        args.alias_id
+    );
+    Ok(())
+}
+
+async fn list_aliases_cli(args: ListAliasesArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "list-aliases");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let index_aliases = qw_client.aliases().list().await?;
+    println!("\n{}\n", make_list_aliases_table(index_aliases));
+    Ok(())
+}
+
+fn make_list_aliases_table(index_aliases: Vec<IndexAlias>) -> Table {
+    let rows = index_aliases.into_iter().map(|index_alias| AliasRow {
+        alias_id: index_alias.alias_id,
+        index_ids: index_alias.index_ids.join(", "),
+    });
+    make_table("Aliases", rows, false)
+}
+
+#[derive(Tabled)]
+struct AliasRow {
+    #[tabled(rename = "Alias ID")]
+    alias_id: String,
+    #[tabled(rename = "Index IDs")]
+    index_ids: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_swap_alias_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "alias",
+            "swap",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--alias",
+            "logs-current",
+            "--from",
+            "logs-v1",
+            "--to",
+            "logs-v2",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Alias(AliasCliCommand::Update(UpdateAliasArgs {
+            cluster_endpoint: Url::from_str("https://quickwit-cluster.io").unwrap(),
+            actions: vec![
+                IndexAliasAction::Remove {
+                    alias_id: "logs-current".to_string(),
+                    index_id: "logs-v1".to_string(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: "logs-current".to_string(),
+                    index_id: "logs-v2".to_string(),
+                },
+            ],
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rollover_alias_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "alias",
+            "rollover",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--alias",
+            "logs",
+            "--max-age",
+            "1 day",
+            "--max-size",
+            "50 GB",
+            "--dry-run",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Alias(AliasCliCommand::Rollover(RolloverAliasArgs {
+            cluster_endpoint: Url::from_str("https://quickwit-cluster.io").unwrap(),
+            alias_id: "logs".to_string(),
+            conditions: RolloverConditions {
+                max_age: Some("1 day".to_string()),
+                max_docs: None,
+                max_size: Some(Byte::from_bytes(50_000_000_000)),
+            },
+            dry_run: true,
+        }));
+        assert_eq!(command, expected_command);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "alias",
+            "rollover",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--alias",
+            "logs",
+            "--max-age",
+            "forever",
+        ])?;
+        assert!(CliCommand::parse_cli_args(&matches).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_delete_alias_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "alias",
+            "delete",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--alias",
+            "logs-current",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Alias(AliasCliCommand::Delete(DeleteAliasArgs {
+            cluster_endpoint: Url::from_str("https://quickwit-cluster.io").unwrap(),
+            alias_id: "logs-current".to_string(),
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+}
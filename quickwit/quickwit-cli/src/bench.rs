@@ -0,0 +1,630 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use bytes::Bytes;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use quickwit_rest_client::rest_client::QuickwitClient;
+use quickwit_serve::SearchRequestQueryString;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use thousands::Separable;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::task::JoinHandle;
+
+use crate::stats::percentile;
+
+/// Words the synthetic text values are made of.
+const VOCABULARY: [&str; 32] = [
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliett",
+    "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango",
+    "uniform", "victor", "whiskey", "xray", "yankee", "zulu", "error", "warning", "info",
+    "request", "response", "timeout",
+];
+
+/// Maximum number of values generated for an array field.
+const MAX_ARRAY_LEN: usize = 3;
+
+/// Synthetic datetime values are spread over the hour preceding the benchmark.
+const DATETIME_SPREAD_SECS: i64 = 3_600;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DateTimeEncoding {
+    Rfc3339,
+    UnixTimestamp,
+}
+
+#[derive(Debug, PartialEq)]
+enum FieldKind {
+    Text,
+    I64,
+    U64,
+    F64,
+    Bool,
+    DateTime(DateTimeEncoding),
+    Ip,
+    Bytes,
+    Json,
+    GeoPoint,
+    Vector(usize),
+    Object(Vec<GeneratedField>),
+}
+
+#[derive(Debug, PartialEq)]
+struct GeneratedField {
+    name: String,
+    kind: FieldKind,
+    is_array: bool,
+}
+
+/// Generates random documents matching the field mappings of an index.
+pub struct DocGenerator {
+    fields: Vec<GeneratedField>,
+    rng: StdRng,
+}
+
+impl DocGenerator {
+    /// Creates a generator from the field mappings of a doc mapping, in their serialized form.
+    pub fn new(field_mappings: &JsonValue, seed: u64) -> anyhow::Result<Self> {
+        let fields = parse_field_mappings(field_mappings)?;
+        if fields.is_empty() {
+            bail!(
+                "The doc mapping does not define any field: use `--input-path` to replay a \
+                 corpus instead."
+            );
+        }
+        Ok(Self {
+            fields,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    pub fn generate_doc(&mut self) -> JsonValue {
+        let now = OffsetDateTime::now_utc();
+        generate_object(&self.fields, now, &mut self.rng)
+    }
+}
+
+fn parse_field_mappings(field_mappings: &JsonValue) -> anyhow::Result<Vec<GeneratedField>> {
+    let Some(field_mappings) = field_mappings.as_array() else {
+        return Ok(Vec::new());
+    };
+    field_mappings.iter().map(parse_field_mapping).collect()
+}
+
+fn parse_field_mapping(field_mapping: &JsonValue) -> anyhow::Result<GeneratedField> {
+    let name = field_mapping["name"]
+        .as_str()
+        .context("Field mapping is missing a name.")?
+        .to_string();
+    let type_str = field_mapping["type"]
+        .as_str()
+        .with_context(|| format!("Field mapping `{name}` is missing a type."))?;
+
+    let (kind, is_array) = match type_str {
+        "object" => (
+            FieldKind::Object(parse_field_mappings(&field_mapping["field_mappings"])?),
+            false,
+        ),
+        "nested" => (
+            FieldKind::Object(parse_field_mappings(&field_mapping["field_mappings"])?),
+            true,
+        ),
+        "geo_point" => (FieldKind::GeoPoint, false),
+        "array<geo_point>" => (FieldKind::GeoPoint, true),
+        _ => {
+            if let Some(dims_str) = type_str
+                .strip_prefix("vector<f32,")
+                .and_then(|type_str| type_str.strip_suffix('>'))
+            {
+                let dims = dims_str.trim().parse::<usize>()?;
+                (FieldKind::Vector(dims), false)
+            } else if let Some(primitive_type_str) = type_str
+                .strip_prefix("array<")
+                .and_then(|type_str| type_str.strip_suffix('>'))
+            {
+                (
+                    parse_primitive_kind(&name, primitive_type_str, field_mapping)?,
+                    true,
+                )
+            } else {
+                (parse_primitive_kind(&name, type_str, field_mapping)?, false)
+            }
+        }
+    };
+    Ok(GeneratedField {
+        name,
+        kind,
+        is_array,
+    })
+}
+
+fn parse_primitive_kind(
+    name: &str,
+    type_str: &str,
+    field_mapping: &JsonValue,
+) -> anyhow::Result<FieldKind> {
+    let kind = match type_str {
+        "text" => FieldKind::Text,
+        "i64" => FieldKind::I64,
+        "u64" => FieldKind::U64,
+        "f64" => FieldKind::F64,
+        "bool" => FieldKind::Bool,
+        "ip" => FieldKind::Ip,
+        "bytes" => FieldKind::Bytes,
+        "json" => FieldKind::Json,
+        "datetime" => {
+            let input_formats: Vec<&str> = match field_mapping["input_formats"].as_array() {
+                Some(input_formats) => input_formats
+                    .iter()
+                    .filter_map(|input_format| input_format.as_str())
+                    .collect(),
+                None => vec!["rfc3339", "unix_timestamp"],
+            };
+            if input_formats.contains(&"unix_timestamp") {
+                FieldKind::DateTime(DateTimeEncoding::UnixTimestamp)
+            } else if input_formats.contains(&"rfc3339") {
+                FieldKind::DateTime(DateTimeEncoding::Rfc3339)
+            } else {
+                bail!(
+                    "Datetime field `{name}` only accepts custom input formats: use \
+                     `--input-path` to replay a corpus instead."
+                );
+            }
+        }
+        _ => bail!("Field `{name}` has an unsupported type `{type_str}`."),
+    };
+    Ok(kind)
+}
+
+fn generate_object(fields: &[GeneratedField], now: OffsetDateTime, rng: &mut StdRng) -> JsonValue {
+    let mut doc = JsonMap::with_capacity(fields.len());
+
+    for field in fields {
+        let value = if field.is_array {
+            let num_values = rng.gen_range(1..=MAX_ARRAY_LEN);
+            let values = (0..num_values)
+                .map(|_| generate_value(&field.kind, now, rng))
+                .collect();
+            JsonValue::Array(values)
+        } else {
+            generate_value(&field.kind, now, rng)
+        };
+        doc.insert(field.name.clone(), value);
+    }
+    JsonValue::Object(doc)
+}
+
+fn generate_value(kind: &FieldKind, now: OffsetDateTime, rng: &mut StdRng) -> JsonValue {
+    match kind {
+        FieldKind::Text => {
+            let num_words = rng.gen_range(1..=8);
+            let words: Vec<&str> = (0..num_words).map(|_| random_word(rng)).collect();
+            json!(words.join(" "))
+        }
+        FieldKind::I64 => json!(rng.gen_range(-1_000_000i64..1_000_000)),
+        FieldKind::U64 => json!(rng.gen_range(0u64..1_000_000)),
+        FieldKind::F64 => json!(rng.gen_range(0.0f64..1_000.0)),
+        FieldKind::Bool => json!(rng.gen_bool(0.5)),
+        FieldKind::DateTime(encoding) => {
+            let date_time = now - time::Duration::seconds(rng.gen_range(0..DATETIME_SPREAD_SECS));
+            match encoding {
+                DateTimeEncoding::UnixTimestamp => json!(date_time.unix_timestamp()),
+                DateTimeEncoding::Rfc3339 => json!(date_time
+                    .format(&Rfc3339)
+                    .expect("RFC 3339 formatting should never fail.")),
+            }
+        }
+        FieldKind::Ip => {
+            let octets: [u8; 3] = rng.gen();
+            json!(format!("10.{}.{}.{}", octets[0], octets[1], octets[2]))
+        }
+        FieldKind::Bytes => {
+            let bytes: [u8; 16] = rng.gen();
+            json!(BASE64_STANDARD.encode(bytes))
+        }
+        FieldKind::Json => json!({
+            "id": rng.gen_range(0u64..1_000),
+            "label": random_word(rng),
+        }),
+        FieldKind::GeoPoint => json!({
+            "lat": rng.gen_range(-90.0f64..90.0),
+            "lon": rng.gen_range(-180.0f64..180.0),
+        }),
+        FieldKind::Vector(dims) => {
+            let values: Vec<f32> = (0..*dims).map(|_| rng.gen_range(-1.0f32..1.0)).collect();
+            json!(values)
+        }
+        FieldKind::Object(fields) => generate_object(fields, now, rng),
+    }
+}
+
+fn random_word(rng: &mut StdRng) -> &'static str {
+    VOCABULARY[rng.gen_range(0..VOCABULARY.len())]
+}
+
+/// Source of the documents sent to the ingest API.
+pub enum DocSource {
+    Synthetic(DocGenerator),
+    Corpus(Lines<BufReader<tokio::fs::File>>),
+}
+
+impl DocSource {
+    pub async fn open_corpus(corpus_path: &Path) -> anyhow::Result<Self> {
+        let file = tokio::fs::File::open(corpus_path)
+            .await
+            .with_context(|| format!("Failed to open corpus `{}`.", corpus_path.display()))?;
+        Ok(Self::Corpus(BufReader::new(file).lines()))
+    }
+
+    /// Returns an NDJSON batch of at most `max_num_docs` documents and its number of documents,
+    /// or `None` once the source is exhausted.
+    async fn next_batch(&mut self, max_num_docs: usize) -> anyhow::Result<Option<(Bytes, usize)>> {
+        let mut batch = Vec::new();
+        let mut num_docs = 0;
+
+        while num_docs < max_num_docs {
+            match self {
+                Self::Synthetic(doc_generator) => {
+                    serde_json::to_writer(&mut batch, &doc_generator.generate_doc())?;
+                }
+                Self::Corpus(lines) => {
+                    let Some(line) = lines.next_line().await? else {
+                        break;
+                    };
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    batch.extend_from_slice(line.as_bytes());
+                }
+            }
+            batch.push(b'\n');
+            num_docs += 1;
+        }
+        if num_docs == 0 {
+            return Ok(None);
+        }
+        Ok(Some((Bytes::from(batch), num_docs)))
+    }
+}
+
+/// Parses a query log: one query per line, either a JSON object holding the parameters of the
+/// search REST API or a raw query.
+pub fn parse_query_log(query_log: &str) -> anyhow::Result<Vec<String>> {
+    let mut queries = Vec::new();
+
+    for (line_idx, line) in query_log.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        parse_query_log_line(line)
+            .with_context(|| format!("Invalid query on line {} of the query log.", line_idx + 1))?;
+        queries.push(line.to_string());
+    }
+    if queries.is_empty() {
+        bail!("The query log is empty.");
+    }
+    Ok(queries)
+}
+
+fn parse_query_log_line(line: &str) -> anyhow::Result<SearchRequestQueryString> {
+    // Raw queries go through the deserializer too so that they get the API defaults.
+    let search_request = if line.starts_with('{') {
+        serde_json::from_str(line)?
+    } else {
+        serde_json::from_value(json!({ "query": line }))?
+    };
+    Ok(search_request)
+}
+
+/// Throughput and latency of a series of requests.
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    num_requests: u64,
+    num_errors: u64,
+    num_items: u64,
+    num_bytes: u64,
+    latencies_micros: Vec<u64>,
+    elapsed: Duration,
+    first_error_opt: Option<String>,
+}
+
+impl BenchReport {
+    fn record<E: ToString>(
+        &mut self,
+        num_items: u64,
+        num_bytes: u64,
+        latency: Duration,
+        result: Result<(), E>,
+    ) {
+        self.num_requests += 1;
+        self.latencies_micros.push(latency.as_micros() as u64);
+
+        match result {
+            Ok(()) => {
+                self.num_items += num_items;
+                self.num_bytes += num_bytes;
+            }
+            Err(error) => {
+                self.num_errors += 1;
+                if self.first_error_opt.is_none() {
+                    self.first_error_opt = Some(error.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn print(&self, title: &str, item_name: &str) {
+        let elapsed_secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        println!("{title}");
+        let mut throughput = format!(
+            "  {} {item_name} in {:.1}s: {} {item_name}/s",
+            self.num_items.separate_with_commas(),
+            self.elapsed.as_secs_f64(),
+            ((self.num_items as f64 / elapsed_secs) as u64).separate_with_commas(),
+        );
+        if self.num_bytes > 0 {
+            throughput += &format!(
+                ", {:.2} MB/s",
+                self.num_bytes as f64 / 1_000_000f64 / elapsed_secs
+            );
+        }
+        println!("{throughput}");
+        println!(
+            "  {} requests ({} errors): {:.1} requests/s",
+            self.num_requests.separate_with_commas(),
+            self.num_errors.separate_with_commas(),
+            self.num_requests as f64 / elapsed_secs
+        );
+        if !self.latencies_micros.is_empty() {
+            let mut latencies_micros = self.latencies_micros.clone();
+            latencies_micros.sort_unstable();
+            let latency_ms = |percent: usize| percentile(&latencies_micros, percent) / 1_000f32;
+            println!(
+                "  latency: p50 {:.1}ms, p90 {:.1}ms, p99 {:.1}ms, max {:.1}ms",
+                latency_ms(50),
+                latency_ms(90),
+                latency_ms(99),
+                latency_ms(100)
+            );
+        }
+        if let Some(first_error) = &self.first_error_opt {
+            println!("  first error: {first_error}");
+        }
+    }
+}
+
+/// Waits until the instant at which the request sending the `num_sent`-th item is due.
+async fn wait_for_rate(start: Instant, num_sent: u64, rate_opt: Option<f64>) {
+    if let Some(rate) = rate_opt {
+        let due_at = start + Duration::from_secs_f64(num_sent as f64 / rate);
+        tokio::time::sleep_until(due_at.into()).await;
+    }
+}
+
+/// Sends the documents of `doc_source` to the ingest API, with at most `concurrency` requests in
+/// flight and at most `rate_opt` documents per second.
+pub async fn run_ingest_bench(
+    qw_client: Arc<QuickwitClient>,
+    index_id: &str,
+    mut doc_source: DocSource,
+    num_docs_opt: Option<u64>,
+    batch_size: usize,
+    rate_opt: Option<f64>,
+    concurrency: usize,
+) -> anyhow::Result<BenchReport> {
+    type IngestTask = JoinHandle<(u64, u64, Duration, anyhow::Result<()>)>;
+
+    let mut report = BenchReport::default();
+    let mut in_flight_tasks: FuturesUnordered<IngestTask> = FuturesUnordered::new();
+    let mut num_docs_sent = 0;
+    let mut is_source_exhausted = false;
+    let start = Instant::now();
+
+    loop {
+        while !is_source_exhausted && in_flight_tasks.len() < concurrency {
+            let max_num_docs = match num_docs_opt {
+                Some(num_docs) => (num_docs - num_docs_sent).min(batch_size as u64) as usize,
+                None => batch_size,
+            };
+            if max_num_docs == 0 {
+                is_source_exhausted = true;
+                break;
+            }
+            let Some((batch, num_docs)) = doc_source.next_batch(max_num_docs).await? else {
+                is_source_exhausted = true;
+                break;
+            };
+            wait_for_rate(start, num_docs_sent, rate_opt).await;
+            num_docs_sent += num_docs as u64;
+
+            let qw_client = qw_client.clone();
+            let index_id = index_id.to_string();
+            let ingest_task = tokio::spawn(async move {
+                let num_bytes = batch.len() as u64;
+                let request_start = Instant::now();
+                let ingest_res = qw_client
+                    .ingest_batch(&index_id, batch, None)
+                    .await
+                    .map_err(anyhow::Error::from);
+                (
+                    num_docs as u64,
+                    num_bytes,
+                    request_start.elapsed(),
+                    ingest_res,
+                )
+            });
+            in_flight_tasks.push(ingest_task);
+        }
+        let Some(task_res) = in_flight_tasks.next().await else {
+            break;
+        };
+        let (num_docs, num_bytes, latency, ingest_res) = task_res?;
+        report.record(num_docs, num_bytes, latency, ingest_res);
+    }
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+/// Replays the queries of a query log against the search API, cycling through the log until
+/// `num_queries` queries have been sent.
+pub async fn run_search_bench(
+    qw_client: Arc<QuickwitClient>,
+    index_id: &str,
+    queries: &[String],
+    num_queries: u64,
+    rate_opt: Option<f64>,
+    concurrency: usize,
+) -> anyhow::Result<BenchReport> {
+    type SearchTask = JoinHandle<(u64, Duration, anyhow::Result<()>)>;
+
+    let mut report = BenchReport::default();
+    let mut in_flight_tasks: FuturesUnordered<SearchTask> = FuturesUnordered::new();
+    let mut num_queries_sent = 0;
+    let start = Instant::now();
+
+    loop {
+        while num_queries_sent < num_queries && in_flight_tasks.len() < concurrency {
+            let query = &queries[num_queries_sent as usize % queries.len()];
+            let search_request = parse_query_log_line(query)?;
+            wait_for_rate(start, num_queries_sent, rate_opt).await;
+            num_queries_sent += 1;
+
+            let qw_client = qw_client.clone();
+            let index_id = index_id.to_string();
+            let search_task = tokio::spawn(async move {
+                let request_start = Instant::now();
+                let search_res = qw_client.search(&index_id, search_request).await;
+                let latency = request_start.elapsed();
+                match search_res {
+                    Ok(search_response) => (search_response.num_hits, latency, Ok(())),
+                    Err(error) => (0, latency, Err(anyhow::Error::from(error))),
+                }
+            });
+            in_flight_tasks.push(search_task);
+        }
+        let Some(task_res) = in_flight_tasks.next().await else {
+            break;
+        };
+        let (num_hits, latency, search_res) = task_res?;
+        report.record(num_hits, 0, latency, search_res);
+    }
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_generator() {
+        let field_mappings = json!([
+            {"name": "timestamp", "type": "datetime", "input_formats": ["unix_timestamp"]},
+            {"name": "created_at", "type": "datetime", "input_formats": ["rfc3339"]},
+            {"name": "body", "type": "text"},
+            {"name": "tags", "type": "array<text>"},
+            {"name": "status", "type": "u64"},
+            {"name": "latency", "type": "f64"},
+            {"name": "client_ip", "type": "ip"},
+            {"name": "location", "type": "geo_point"},
+            {"name": "embedding", "type": "vector<f32, 4>"},
+            {
+                "name": "resource",
+                "type": "object",
+                "field_mappings": [{"name": "service", "type": "text"}]
+            },
+        ]);
+        let mut doc_generator = DocGenerator::new(&field_mappings, 42).unwrap();
+        let doc = doc_generator.generate_doc();
+
+        assert!(doc["timestamp"].is_i64());
+        let created_at = doc["created_at"].as_str().unwrap();
+        assert!(created_at.contains('T') && created_at.ends_with('Z'));
+        assert!(doc["body"].is_string());
+        let tags = doc["tags"].as_array().unwrap();
+        assert!((1..=MAX_ARRAY_LEN).contains(&tags.len()));
+        assert!(doc["status"].is_u64());
+        assert!(doc["latency"].is_f64());
+        assert!(doc["client_ip"]
+            .as_str()
+            .unwrap()
+            .parse::<std::net::Ipv4Addr>()
+            .is_ok());
+        assert!(doc["location"]["lat"].is_f64());
+        assert_eq!(doc["embedding"].as_array().unwrap().len(), 4);
+        assert!(doc["resource"]["service"].is_string());
+    }
+
+    #[test]
+    fn test_doc_generator_rejects_custom_datetime_formats() {
+        let field_mappings = json!([
+            {"name": "timestamp", "type": "datetime", "input_formats": ["%Y-%m-%d %H:%M:%S"]},
+        ]);
+        let error = DocGenerator::new(&field_mappings, 42).err().unwrap();
+        assert!(error.to_string().contains("custom input formats"));
+    }
+
+    #[tokio::test]
+    async fn test_doc_source_batches() {
+        let field_mappings = json!([{"name": "body", "type": "text"}]);
+        let doc_generator = DocGenerator::new(&field_mappings, 42).unwrap();
+        let mut doc_source = DocSource::Synthetic(doc_generator);
+        let (batch, num_docs) = doc_source.next_batch(3).await.unwrap().unwrap();
+        assert_eq!(num_docs, 3);
+        assert_eq!(batch.split(|byte| *byte == b'\n').count(), 4);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let corpus_path = temp_dir.path().join("corpus.ndjson");
+        std::fs::write(&corpus_path, "{\"body\": \"foo\"}\n\n{\"body\": \"bar\"}\n").unwrap();
+        let mut doc_source = DocSource::open_corpus(&corpus_path).await.unwrap();
+        let (batch, num_docs) = doc_source.next_batch(10).await.unwrap().unwrap();
+        assert_eq!(num_docs, 2);
+        assert_eq!(&batch[..], b"{\"body\": \"foo\"}\n{\"body\": \"bar\"}\n");
+        assert!(doc_source.next_batch(10).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_query_log() {
+        let query_log = "severity_text:ERROR\n\n{\"query\": \"body:timeout\", \"max_hits\": 5}\n";
+        let queries = parse_query_log(query_log).unwrap();
+        assert_eq!(queries.len(), 2);
+
+        let search_request = parse_query_log_line(&queries[0]).unwrap();
+        assert_eq!(search_request.query, "severity_text:ERROR");
+        assert_eq!(search_request.max_hits, 20);
+
+        let search_request = parse_query_log_line(&queries[1]).unwrap();
+        assert_eq!(search_request.query, "body:timeout");
+        assert_eq!(search_request.max_hits, 5);
+
+        parse_query_log("").unwrap_err();
+        parse_query_log("{\"max_hits\": \"five\"}").unwrap_err();
+    }
+}
@@ -21,10 +21,15 @@ use anyhow::bail;
 use clap::{arg, Arg, ArgMatches, Command};
 use tracing::Level;
 
+use crate::alias::{build_alias_command, AliasCliCommand};
+use crate::config::{build_config_command, ConfigCliCommand};
+use crate::doctor::{build_doctor_command, DoctorCliCommand};
 use crate::index::{build_index_command, IndexCliCommand};
+use crate::node::{build_node_command, NodeCliCommand};
 use crate::service::{build_run_command, RunCliCommand};
 use crate::source::{build_source_command, SourceCliCommand};
 use crate::split::{build_split_command, SplitCliCommand};
+use crate::sql::{build_sql_command, SqlCliCommand};
 use crate::tool::{build_tool_command, ToolCliCommand};
 
 pub fn build_cli<'a>() -> Command<'a> {
@@ -49,6 +54,11 @@ pub fn build_cli<'a>() -> Command<'a> {
         .subcommand(build_source_command().display_order(3))
         .subcommand(build_split_command().display_order(4))
         .subcommand(build_tool_command().display_order(5))
+        .subcommand(build_sql_command().display_order(6))
+        .subcommand(build_node_command().display_order(7))
+        .subcommand(build_doctor_command().display_order(8))
+        .subcommand(build_config_command().display_order(9))
+        .subcommand(build_alias_command().display_order(10))
         .arg_required_else_help(true)
         .disable_help_subcommand(true)
         .subcommand_required(true)
@@ -60,7 +70,12 @@ pub enum CliCommand {
     Index(IndexCliCommand),
     Split(SplitCliCommand),
     Source(SourceCliCommand),
+    Sql(SqlCliCommand),
     Tool(ToolCliCommand),
+    Node(NodeCliCommand),
+    Doctor(DoctorCliCommand),
+    Config(ConfigCliCommand),
+    Alias(AliasCliCommand),
 }
 
 impl CliCommand {
@@ -70,7 +85,12 @@ impl CliCommand {
             CliCommand::Index(subcommand) => subcommand.default_log_level(),
             CliCommand::Source(_) => Level::ERROR,
             CliCommand::Split(_) => Level::ERROR,
+            CliCommand::Sql(_) => Level::ERROR,
             CliCommand::Tool(_) => Level::ERROR,
+            CliCommand::Node(_) => Level::ERROR,
+            CliCommand::Doctor(_) => Level::ERROR,
+            CliCommand::Config(_) => Level::ERROR,
+            CliCommand::Alias(_) => Level::ERROR,
         }
     }
 
@@ -83,7 +103,12 @@ impl CliCommand {
             "run" => RunCliCommand::parse_cli_args(submatches).map(CliCommand::Run),
             "source" => SourceCliCommand::parse_cli_args(submatches).map(CliCommand::Source),
             "split" => SplitCliCommand::parse_cli_args(submatches).map(CliCommand::Split),
+            "sql" => SqlCliCommand::parse_cli_args(submatches).map(CliCommand::Sql),
             "tool" => ToolCliCommand::parse_cli_args(submatches).map(CliCommand::Tool),
+            "node" => NodeCliCommand::parse_cli_args(submatches).map(CliCommand::Node),
+            "doctor" => DoctorCliCommand::parse_cli_args(submatches).map(CliCommand::Doctor),
+            "config" => ConfigCliCommand::parse_cli_args(submatches).map(CliCommand::Config),
+            "alias" => AliasCliCommand::parse_cli_args(submatches).map(CliCommand::Alias),
             _ => bail!("Subcommand `{}` is not implemented.", subcommand),
         }
     }
@@ -94,7 +119,12 @@ impl CliCommand {
             CliCommand::Run(subcommand) => subcommand.execute().await,
             CliCommand::Source(subcommand) => subcommand.execute().await,
             CliCommand::Split(subcommand) => subcommand.execute().await,
+            CliCommand::Sql(subcommand) => subcommand.execute().await,
             CliCommand::Tool(subcommand) => subcommand.execute().await,
+            CliCommand::Node(subcommand) => subcommand.execute().await,
+            CliCommand::Doctor(subcommand) => subcommand.execute().await,
+            CliCommand::Config(subcommand) => subcommand.execute().await,
+            CliCommand::Alias(subcommand) => subcommand.execute().await,
         }
     }
 }
@@ -0,0 +1,207 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use anyhow::{bail, Context};
+use clap::{arg, ArgMatches, Command};
+use colored::Colorize;
+use quickwit_common::uri::Uri;
+use quickwit_common::{GREEN_COLOR, RED_COLOR};
+use quickwit_config::{validate_index_config, ConfigError, ConfigFormat, QuickwitConfig};
+use quickwit_storage::load_file;
+use tracing::debug;
+
+/// Index root URI used to validate an index config when no node config is provided.
+const DEFAULT_INDEX_ROOT_URI: &str = "qwdata/indexes";
+
+pub fn build_config_command<'a>() -> Command<'a> {
+    Command::new("config")
+        .about("Validates node and index configs.")
+        .subcommand(
+            Command::new("validate")
+                .about("Validates a node config and/or an index config, reporting each error with the path of the offending field.")
+                .args(&[
+                    arg!(--"node-config" <NODE_CONFIG> "Location of the node config file.")
+                        .display_order(1)
+                        .required(false),
+                    arg!(--"index-config" <INDEX_CONFIG> "Location of the index config file.")
+                        .display_order(2)
+                        .required(false),
+                    arg!(--"show-effective" "Prints the configs once environment variables are substituted and default values are applied.")
+                        .display_order(3)
+                        .required(false),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ValidateConfigArgs {
+    pub node_config_uri_opt: Option<Uri>,
+    pub index_config_uri_opt: Option<Uri>,
+    pub show_effective: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ConfigCliCommand {
+    Validate(ValidateConfigArgs),
+}
+
+impl ConfigCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
+        match subcommand {
+            "validate" => Self::parse_validate_args(submatches),
+            _ => bail!("Subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_validate_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let node_config_uri_opt = matches
+            .value_of("node-config")
+            .map(Uri::from_str)
+            .transpose()?;
+        let index_config_uri_opt = matches
+            .value_of("index-config")
+            .map(Uri::from_str)
+            .transpose()?;
+        if node_config_uri_opt.is_none() && index_config_uri_opt.is_none() {
+            bail!("At least one of `--node-config` or `--index-config` must be provided.");
+        }
+        let show_effective = matches.is_present("show-effective");
+        Ok(Self::Validate(ValidateConfigArgs {
+            node_config_uri_opt,
+            index_config_uri_opt,
+            show_effective,
+        }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Validate(args) => validate_config_cli(args).await,
+        }
+    }
+}
+
+async fn validate_config_cli(args: ValidateConfigArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "validate-config");
+    let mut num_invalid_configs = 0;
+    let mut default_index_root_uri = Uri::from_str(DEFAULT_INDEX_ROOT_URI)?;
+
+    if let Some(node_config_uri) = &args.node_config_uri_opt {
+        println!("❯ Validating node config `{node_config_uri}`...");
+        let (config_format, config_content) = read_config_file(node_config_uri).await?;
+        match QuickwitConfig::validate(config_format, &config_content).await {
+            Ok(mut config) => {
+                print_valid();
+                if args.show_effective {
+                    // We need to hide sensitive information from metastore URI.
+                    config.metastore_uri =
+                        Uri::from_well_formed(config.metastore_uri.as_redacted_str());
+                    println!("{}", serde_json::to_string_pretty(&config)?);
+                }
+                default_index_root_uri = config.default_index_root_uri;
+            }
+            Err(error) => {
+                print_invalid(&error);
+                num_invalid_configs += 1;
+            }
+        }
+    }
+    if let Some(index_config_uri) = &args.index_config_uri_opt {
+        println!("❯ Validating index config `{index_config_uri}`...");
+        let (config_format, config_content) = read_config_file(index_config_uri).await?;
+        match validate_index_config(config_format, &config_content, &default_index_root_uri) {
+            Ok(index_config) => {
+                print_valid();
+                if args.show_effective {
+                    println!("{}", serde_json::to_string_pretty(&index_config)?);
+                }
+            }
+            Err(error) => {
+                print_invalid(&error);
+                num_invalid_configs += 1;
+            }
+        }
+    }
+    if num_invalid_configs > 0 {
+        bail!("Found {num_invalid_configs} invalid config(s).");
+    }
+    Ok(())
+}
+
+async fn read_config_file(config_uri: &Uri) -> anyhow::Result<(ConfigFormat, Vec<u8>)> {
+    let config_format = ConfigFormat::sniff_from_uri(config_uri)?;
+    let config_content = load_file(config_uri)
+        .await
+        .with_context(|| format!("Failed to load config file `{config_uri}`."))?;
+    Ok((config_format, config_content.to_vec()))
+}
+
+fn print_valid() {
+    println!("{} Config is valid.", "✔".color(GREEN_COLOR));
+}
+
+fn print_invalid(error: &ConfigError) {
+    println!("{} Config is invalid.", "✖".color(RED_COLOR));
+
+    if let Some(path) = &error.path_opt {
+        println!("  field: `{path}`");
+    }
+    println!("  error: {}", error.message);
+
+    if let Some(suggestion) = &error.suggestion_opt {
+        println!("  hint: {suggestion}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_validate_config_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "config",
+            "validate",
+            "--node-config",
+            "/config/quickwit.yaml",
+            "--index-config",
+            "/config/hdfs-logs.yaml",
+            "--show-effective",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Config(ConfigCliCommand::Validate(ValidateConfigArgs {
+            node_config_uri_opt: Some(Uri::from_str("file:///config/quickwit.yaml").unwrap()),
+            index_config_uri_opt: Some(Uri::from_str("file:///config/hdfs-logs.yaml").unwrap()),
+            show_effective: true,
+        }));
+        assert_eq!(command, expected_command);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec!["config", "validate"])?;
+        assert!(CliCommand::parse_cli_args(&matches).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,665 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context};
+use clap::{arg, ArgMatches, Command};
+use colored::{Color, Colorize};
+use humantime::format_duration;
+use quickwit_common::uri::Uri;
+use quickwit_common::{GREEN_COLOR, RED_COLOR};
+use quickwit_config::{build_doc_mapper, IndexConfig, QuickwitConfig};
+use quickwit_metastore::{quickwit_metastore_uri_resolver, IndexMetadata};
+use quickwit_rest_client::rest_client::{QuickwitClient, Transport};
+use quickwit_storage::{quickwit_storage_uri_resolver, Storage};
+use reqwest::Url;
+use tracing::debug;
+
+use crate::{cluster_endpoint_arg, config_cli_arg, load_quickwit_config};
+
+/// Latency above which a metastore or storage round-trip is reported as slow.
+const SLOW_LATENCY_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Maximum duration of the cluster state request.
+const CLUSTER_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name of the file written, read, and deleted to probe a storage or the data directory.
+const PROBE_FILE_NAME: &str = ".quickwit-doctor-probe";
+
+pub fn build_doctor_command<'a>() -> Command<'a> {
+    Command::new("doctor")
+        .about("Diagnoses the configuration of a node and the state of its cluster.")
+        .long_about("Checks the node config, the data directory, the connectivity and permissions to the metastore and to every storage used by the indexes, the index configs, and the cluster membership and version skew. Each problem is reported along with a suggested fix.")
+        .arg(config_cli_arg())
+        .arg(cluster_endpoint_arg())
+        .arg(
+            arg!(--"skip-cluster" "Skips the cluster checks, for instance when no node is running yet.")
+                .required(false),
+        )
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DoctorCliCommand {
+    pub config_uri: Uri,
+    pub cluster_endpoint: Url,
+    pub skip_cluster: bool,
+}
+
+impl DoctorCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let config_uri = matches
+            .value_of("config")
+            .map(Uri::from_str)
+            .expect("`config` is a required arg.")?;
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let skip_cluster = matches.is_present("skip-cluster");
+        Ok(DoctorCliCommand {
+            config_uri,
+            cluster_endpoint,
+            skip_cluster,
+        })
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        debug!(args=?self, "doctor");
+        println!("❯ Diagnosing Quickwit setup...");
+        let findings = run_diagnostics(&self).await;
+
+        for finding in &findings {
+            println!("{finding}");
+        }
+        let num_errors = count_findings(&findings, Severity::Error);
+        let num_warnings = count_findings(&findings, Severity::Warning);
+        println!();
+
+        if num_errors > 0 {
+            bail!("Found {num_errors} error(s) and {num_warnings} warning(s).");
+        }
+        println!(
+            "{} No errors found, {num_warnings} warning(s).",
+            "✔".color(GREEN_COLOR)
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// Outcome of a single diagnostic check.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub check: String,
+    pub message: String,
+    /// Suggested fix, for warnings and errors.
+    pub hint_opt: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: impl ToString, message: impl ToString) -> Self {
+        Self {
+            severity: Severity::Ok,
+            check: check.to_string(),
+            message: message.to_string(),
+            hint_opt: None,
+        }
+    }
+
+    fn warning(check: impl ToString, message: impl ToString, hint: impl ToString) -> Self {
+        Self {
+            severity: Severity::Warning,
+            check: check.to_string(),
+            message: message.to_string(),
+            hint_opt: Some(hint.to_string()),
+        }
+    }
+
+    fn error(check: impl ToString, message: impl ToString, hint: impl ToString) -> Self {
+        Self {
+            severity: Severity::Error,
+            check: check.to_string(),
+            message: message.to_string(),
+            hint_opt: Some(hint.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (symbol, color) = match self.severity {
+            Severity::Ok => ("✔", GREEN_COLOR),
+            Severity::Warning => ("⚠", Color::Yellow),
+            Severity::Error => ("✖", RED_COLOR),
+        };
+        write!(
+            f,
+            " {} {}: {}",
+            symbol.color(color),
+            self.check.bold(),
+            self.message
+        )?;
+        if let Some(hint) = &self.hint_opt {
+            write!(f, "\n     {} {hint}", "→".color(color))?;
+        }
+        Ok(())
+    }
+}
+
+fn count_findings(findings: &[Finding], severity: Severity) -> usize {
+    findings
+        .iter()
+        .filter(|finding| finding.severity == severity)
+        .count()
+}
+
+async fn run_diagnostics(args: &DoctorCliCommand) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    match load_quickwit_config(&args.config_uri).await {
+        Ok(config) => {
+            findings.push(Finding::ok(
+                "node config",
+                format!("Loaded `{}`.", args.config_uri),
+            ));
+            findings.push(check_data_dir(&config.data_dir_path).await);
+            check_metastore_and_storages(&config, &mut findings).await;
+
+            if !args.skip_cluster {
+                check_cluster(&args.cluster_endpoint, Some(&config), &mut findings).await;
+            }
+        }
+        Err(error) => {
+            findings.push(Finding::error(
+                "node config",
+                format!("{error:#}"),
+                "Fix the node config, see https://quickwit.io/docs/configuration/node-config. The \
+                 metastore, storage, and index checks are skipped.",
+            ));
+            if !args.skip_cluster {
+                check_cluster(&args.cluster_endpoint, None, &mut findings).await;
+            }
+        }
+    }
+    findings
+}
+
+async fn check_data_dir(data_dir_path: &Path) -> Finding {
+    let check = "data directory";
+    let probe_path = data_dir_path.join(PROBE_FILE_NAME);
+    let probe_res = async {
+        tokio::fs::create_dir_all(data_dir_path).await?;
+        tokio::fs::write(&probe_path, b"quickwit").await?;
+        tokio::fs::remove_file(&probe_path).await
+    }
+    .await;
+    match probe_res {
+        Ok(()) => Finding::ok(check, format!("`{}` is writable.", data_dir_path.display())),
+        Err(error) => Finding::error(
+            check,
+            format!("Failed to write to `{}`: {error}.", data_dir_path.display()),
+            "Make sure the user running Quickwit can create and write files in `data_dir`, or \
+             point `data_dir` to another directory.",
+        ),
+    }
+}
+
+async fn check_metastore_and_storages(config: &QuickwitConfig, findings: &mut Vec<Finding>) {
+    let check = "metastore";
+    let metastore = match quickwit_metastore_uri_resolver()
+        .resolve(&config.metastore_uri)
+        .await
+    {
+        Ok(metastore) => metastore,
+        Err(error) => {
+            findings.push(Finding::error(
+                check,
+                format!("Failed to open `{}`: {error}.", config.metastore_uri),
+                "Check `metastore_uri` and the metastore credentials. For a PostgreSQL metastore, \
+                 make sure the database exists and accepts connections from this host.",
+            ));
+            return;
+        }
+    };
+    let start = Instant::now();
+    if let Err(error) = metastore.check_connectivity().await {
+        findings.push(Finding::error(
+            check,
+            format!(
+                "Failed to connect to `{}`: {error:#}.",
+                config.metastore_uri
+            ),
+            "Check that the metastore is reachable from this host and that the credentials grant \
+             read and write access.",
+        ));
+        return;
+    }
+    let indexes_metadatas = match metastore.list_indexes_metadatas().await {
+        Ok(indexes_metadatas) => indexes_metadatas,
+        Err(error) => {
+            findings.push(Finding::error(
+                check,
+                format!("Failed to list the indexes: {error}."),
+                "Check that the metastore credentials grant read access. If the metastore was \
+                 written by a more recent version of Quickwit, upgrade this node.",
+            ));
+            return;
+        }
+    };
+    let elapsed = start.elapsed();
+    let message = format!(
+        "Connected to `{}` and listed {} index(es) in {}.",
+        config.metastore_uri,
+        indexes_metadatas.len(),
+        format_latency(elapsed)
+    );
+    findings.push(latency_finding(check, message, elapsed));
+
+    for index_metadata in &indexes_metadatas {
+        findings.push(match check_index_config(&index_metadata.index_config) {
+            Ok(()) => Finding::ok(
+                format!("index `{}`", index_metadata.index_id()),
+                "Index config is valid.",
+            ),
+            Err(error) => Finding::error(
+                format!("index `{}`", index_metadata.index_id()),
+                format!("Invalid index config: {error:#}."),
+                "Update the index config, see https://quickwit.io/docs/configuration/index-config.",
+            ),
+        });
+    }
+    for (storage_uri, users) in storage_uris(config, &indexes_metadatas) {
+        findings.push(check_storage(&storage_uri, &users).await);
+    }
+}
+
+/// Validates the parts of an index config that are only interpreted when the index is used.
+fn check_index_config(index_config: &IndexConfig) -> anyhow::Result<()> {
+    build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+        .context("Failed to build doc mapper")?;
+    if let Some(retention_policy) = &index_config.retention_policy {
+        retention_policy
+            .retention_period()
+            .context("Invalid retention period")?;
+        retention_policy
+            .evaluation_schedule()
+            .context("Invalid retention evaluation schedule")?;
+    }
+    Ok(())
+}
+
+/// Returns the URIs of the storages used by the node and the indexes, along with their users.
+fn storage_uris(
+    config: &QuickwitConfig,
+    indexes_metadatas: &[IndexMetadata],
+) -> Vec<(Uri, Vec<String>)> {
+    let mut storage_uris: BTreeMap<String, (Uri, Vec<String>)> = BTreeMap::new();
+    let mut add_storage_uri = |uri: &Uri, user: String| {
+        storage_uris
+            .entry(uri.as_str().to_string())
+            .or_insert_with(|| (uri.clone(), Vec::new()))
+            .1
+            .push(user);
+    };
+    add_storage_uri(
+        &config.default_index_root_uri,
+        "default index root".to_string(),
+    );
+    for index_metadata in indexes_metadatas {
+        let index_id = index_metadata.index_id();
+        add_storage_uri(index_metadata.index_uri(), format!("index `{index_id}`"));
+
+        if let Some(archive_uri) = index_metadata
+            .index_config
+            .retention_policy
+            .as_ref()
+            .and_then(|retention_policy| retention_policy.archive_uri())
+        {
+            add_storage_uri(archive_uri, format!("archive of index `{index_id}`"));
+        }
+    }
+    storage_uris.into_values().collect()
+}
+
+async fn check_storage(storage_uri: &Uri, users: &[String]) -> Finding {
+    let check = format!("storage `{storage_uri}`");
+    let storage = match quickwit_storage_uri_resolver().resolve(storage_uri) {
+        Ok(storage) => storage,
+        Err(error) => {
+            return Finding::error(
+                check,
+                format!("Failed to resolve storage: {error}."),
+                "Check the URI and make sure this binary was built with support for its protocol.",
+            );
+        }
+    };
+    match probe_storage(&*storage).await {
+        Ok(probe) => {
+            let message = format!(
+                "Read, write, and delete permissions granted, round-trip in {} (used by {}).",
+                format_latency(probe.elapsed),
+                users.join(", ")
+            );
+            latency_finding(check, message, probe.elapsed)
+        }
+        Err(error) => Finding::error(
+            check,
+            format!("{error:#} (used by {}).", users.join(", ")),
+            "Check the storage credentials (environment variables, instance profile, ...) and \
+             make sure they grant list, read, write, and delete permissions on this URI.",
+        ),
+    }
+}
+
+#[derive(Debug)]
+struct StorageProbe {
+    elapsed: Duration,
+}
+
+/// Checks the connectivity to a storage and the permissions granted on it by writing, reading
+/// back, and deleting a small file.
+async fn probe_storage(storage: &dyn Storage) -> anyhow::Result<StorageProbe> {
+    let start = Instant::now();
+    let probe_path = PathBuf::from(PROBE_FILE_NAME);
+    let probe_payload = b"quickwit".to_vec();
+
+    storage
+        .check_connectivity()
+        .await
+        .context("Failed to connect to storage")?;
+    storage
+        .put(&probe_path, Box::new(probe_payload.clone()))
+        .await
+        .context("Failed to write to storage")?;
+    let payload = storage
+        .get_all(&probe_path)
+        .await
+        .context("Failed to read from storage")?;
+    if payload.as_slice() != probe_payload.as_slice() {
+        bail!("File read back from storage differs from the file written");
+    }
+    storage
+        .delete(&probe_path)
+        .await
+        .context("Failed to delete from storage")?;
+    Ok(StorageProbe {
+        elapsed: start.elapsed(),
+    })
+}
+
+fn latency_finding(check: impl ToString, message: String, elapsed: Duration) -> Finding {
+    if elapsed > SLOW_LATENCY_THRESHOLD {
+        Finding::warning(
+            check,
+            message,
+            "Latency is high: check that this host runs in the same region as the service and \
+             that no proxy or network rule slows down the traffic.",
+        )
+    } else {
+        Finding::ok(check, message)
+    }
+}
+
+async fn check_cluster(
+    cluster_endpoint: &Url,
+    config_opt: Option<&QuickwitConfig>,
+    findings: &mut Vec<Finding>,
+) {
+    let check = "cluster";
+    let qw_client = QuickwitClient::new(Transport::new(cluster_endpoint.clone()));
+    let cluster_snapshot = match tokio::time::timeout(CLUSTER_REQUEST_TIMEOUT, qw_client.cluster())
+        .await
+    {
+        Ok(Ok(cluster_snapshot)) => cluster_snapshot,
+        Ok(Err(error)) => {
+            findings.push(Finding::error(
+                check,
+                format!("Failed to fetch the cluster state from `{cluster_endpoint}`: {error}."),
+                "Start a node, or point `--endpoint` to a running node. Use `--skip-cluster` \
+                     to run the other checks only.",
+            ));
+            return;
+        }
+        Err(_) => {
+            findings.push(Finding::error(
+                check,
+                format!("`{cluster_endpoint}` did not respond within {CLUSTER_REQUEST_TIMEOUT:?}."),
+                "Check that the REST port of the node is reachable from this host.",
+            ));
+            return;
+        }
+    };
+    if let Some(config) = config_opt {
+        if config.cluster_id != cluster_snapshot.cluster_id {
+            findings.push(Finding::warning(
+                check,
+                format!(
+                    "`{cluster_endpoint}` belongs to cluster `{}`, but the node config targets \
+                     cluster `{}`.",
+                    cluster_snapshot.cluster_id, config.cluster_id
+                ),
+                "Nodes only gossip with nodes sharing their `cluster_id`: align the node configs.",
+            ));
+        }
+    }
+    let num_live_nodes = cluster_snapshot.live_nodes.len();
+    let num_ready_nodes = cluster_snapshot.ready_nodes.len();
+    findings.push(Finding::ok(
+        check,
+        format!(
+            "Cluster `{}` has {num_live_nodes} live node(s), {num_ready_nodes} of which are ready.",
+            cluster_snapshot.cluster_id
+        ),
+    ));
+    let not_ready_node_ids: Vec<&str> = cluster_snapshot
+        .live_nodes
+        .difference(&cluster_snapshot.ready_nodes)
+        .map(|node_id| node_id.id.as_str())
+        .collect();
+    if !not_ready_node_ids.is_empty() {
+        findings.push(Finding::warning(
+            check,
+            format!("Nodes not ready: {}.", sorted_join(not_ready_node_ids)),
+            "Check the logs of these nodes: a node is not ready until it can reach the metastore.",
+        ));
+    }
+    let dead_node_ids: Vec<&str> = cluster_snapshot
+        .dead_nodes
+        .iter()
+        .map(|node_id| node_id.id.as_str())
+        .collect();
+    if !dead_node_ids.is_empty() {
+        findings.push(Finding::warning(
+            check,
+            format!("Nodes flagged as dead: {}.", sorted_join(dead_node_ids)),
+            "If these nodes are running, make sure the gossip port (UDP) is open between all the \
+             nodes and that `gossip_advertise_address` is reachable by the other nodes.",
+        ));
+    }
+    let node_versions: Vec<(String, Option<String>)> = cluster_snapshot
+        .live_nodes
+        .iter()
+        .map(|node_id| {
+            let version_opt = cluster_snapshot.node_version(node_id).map(str::to_string);
+            (node_id.id.clone(), version_opt)
+        })
+        .collect();
+    findings.push(check_version_skew(
+        env!("CARGO_PKG_VERSION"),
+        &node_versions,
+    ));
+}
+
+/// Checks that all the live nodes run the same version as this binary.
+fn check_version_skew(cli_version: &str, node_versions: &[(String, Option<String>)]) -> Finding {
+    let check = "versions";
+    let mut node_ids_per_version: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+    for (node_id, version_opt) in node_versions {
+        // Nodes running a version that does not advertise it predate this check.
+        let version = version_opt.as_deref().unwrap_or("unknown");
+        node_ids_per_version
+            .entry(version)
+            .or_default()
+            .push(node_id.as_str());
+    }
+    if node_ids_per_version.len() > 1 {
+        let versions_str = node_ids_per_version
+            .iter_mut()
+            .map(|(version, node_ids)| {
+                node_ids.sort_unstable();
+                format!("{version} ({})", node_ids.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Finding::warning(
+            check,
+            format!("Nodes run different versions: {versions_str}."),
+            "Mixed versions are only supported during a rolling upgrade: upgrade all the nodes \
+             to the same version.",
+        );
+    }
+    match node_ids_per_version.into_keys().next() {
+        Some(version) if version != cli_version => Finding::warning(
+            check,
+            format!("Nodes run version {version}, this binary is version {cli_version}."),
+            "Use a CLI matching the version of the cluster.",
+        ),
+        Some(version) => Finding::ok(check, format!("All nodes run version {version}.")),
+        None => Finding::ok(check, "No live node to check."),
+    }
+}
+
+fn sorted_join(mut items: Vec<&str>) -> String {
+    items.sort_unstable();
+    items.join(", ")
+}
+
+fn format_latency(elapsed: Duration) -> String {
+    format_duration(Duration::from_millis(elapsed.as_millis() as u64)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::RetentionPolicy;
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_doctor_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "doctor",
+            "--config",
+            "/config.yaml",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--skip-cluster",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Doctor(DoctorCliCommand {
+            config_uri: Uri::from_str("file:///config.yaml").unwrap(),
+            cluster_endpoint: Url::from_str("https://quickwit-cluster.io").unwrap(),
+            skip_cluster: true,
+        });
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_probe_storage() {
+        let storage = RamStorage::default();
+        probe_storage(&storage).await.unwrap();
+        assert!(!storage.exists(Path::new(PROBE_FILE_NAME)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_check_data_dir() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir_path = temp_dir.path().join("qwdata");
+        let finding = check_data_dir(&data_dir_path).await;
+        assert_eq!(finding.severity, Severity::Ok);
+        assert!(data_dir_path.exists());
+        assert!(!data_dir_path.join(PROBE_FILE_NAME).exists());
+
+        let data_file_path = temp_dir.path().join("file");
+        std::fs::write(&data_file_path, b"").unwrap();
+        let finding = check_data_dir(&data_file_path).await;
+        assert_eq!(finding.severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_check_index_config() {
+        let mut index_config = IndexConfig::for_test("test-index", "s3://quickwit-indexes/test");
+        check_index_config(&index_config).unwrap();
+
+        index_config.retention_policy = Some(RetentionPolicy::new(
+            "forever and a day".to_string(),
+            "hourly".to_string(),
+        ));
+        let error = check_index_config(&index_config).unwrap_err();
+        assert!(error.to_string().contains("retention period"));
+    }
+
+    #[test]
+    fn test_check_version_skew() {
+        let finding = check_version_skew("0.4.0", &[]);
+        assert_eq!(finding.severity, Severity::Ok);
+
+        let node_versions = vec![
+            ("node-1".to_string(), Some("0.4.0".to_string())),
+            ("node-2".to_string(), Some("0.4.0".to_string())),
+        ];
+        let finding = check_version_skew("0.4.0", &node_versions);
+        assert_eq!(finding.severity, Severity::Ok);
+
+        let finding = check_version_skew("0.5.0", &node_versions);
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(
+            finding.message,
+            "Nodes run version 0.4.0, this binary is version 0.5.0."
+        );
+
+        let node_versions = vec![
+            ("node-3".to_string(), None),
+            ("node-2".to_string(), Some("0.4.0".to_string())),
+            ("node-1".to_string(), Some("0.4.0".to_string())),
+        ];
+        let finding = check_version_skew("0.4.0", &node_versions);
+        assert_eq!(finding.severity, Severity::Warning);
+        assert_eq!(
+            finding.message,
+            "Nodes run different versions: 0.4.0 (node-1, node-2), unknown (node-3)."
+        );
+    }
+}
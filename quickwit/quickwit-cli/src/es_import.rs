@@ -0,0 +1,623 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers to import an Elasticsearch or OpenSearch index into Quickwit: translation of the
+//! Elasticsearch mapping into a Quickwit doc mapping, a minimal scroll client, and the checkpoint
+//! file that makes it possible to resume an interrupted import.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use reqwest::{Client, Method, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+/// Result of the translation of an Elasticsearch mapping into a Quickwit doc mapping.
+#[derive(Debug, Default, PartialEq)]
+pub struct MappingTranslation {
+    /// Quickwit doc mapping mode derived from the `dynamic` setting of the mapping.
+    pub mode: &'static str,
+    pub field_mappings: Vec<JsonValue>,
+    pub field_aliases: BTreeMap<String, String>,
+    /// Top-level text fields tokenized with the default tokenizer.
+    pub default_search_fields: Vec<String>,
+    /// Fields that could not be translated along with the reason why.
+    pub skipped_fields: Vec<(String, String)>,
+}
+
+impl MappingTranslation {
+    /// Builds the index config of the target index.
+    pub fn to_index_config(
+        &self,
+        index_id: &str,
+        timestamp_field_opt: Option<&str>,
+    ) -> anyhow::Result<JsonValue> {
+        let mut doc_mapping = json!({
+            "mode": self.mode,
+            "field_mappings": self.field_mappings,
+        });
+        if !self.field_aliases.is_empty() {
+            doc_mapping["field_aliases"] = json!(self.field_aliases);
+        }
+        if let Some(timestamp_field) = timestamp_field_opt {
+            let is_datetime_field = self.field_mappings.iter().any(|field_mapping| {
+                field_mapping["name"] == timestamp_field && field_mapping["type"] == "datetime"
+            });
+            if !is_datetime_field {
+                bail!(
+                    "Timestamp field `{timestamp_field}` must be a top-level field of type `date` \
+                     or `date_nanos` in the Elasticsearch mapping."
+                );
+            }
+            doc_mapping["timestamp_field"] = json!(timestamp_field);
+        }
+        Ok(json!({
+            "version": "0.4",
+            "index_id": index_id,
+            "doc_mapping": doc_mapping,
+            "search_settings": {
+                "default_search_fields": self.default_search_fields,
+            },
+        }))
+    }
+}
+
+/// Translates the `mappings` object of an Elasticsearch index into Quickwit field mappings.
+pub fn translate_es_mapping(es_mapping: &JsonValue) -> MappingTranslation {
+    let mode = match es_mapping.get("dynamic") {
+        Some(JsonValue::String(dynamic)) if dynamic == "strict" => "strict",
+        Some(JsonValue::String(dynamic)) if dynamic == "false" => "lenient",
+        Some(JsonValue::Bool(false)) => "lenient",
+        _ => "dynamic",
+    };
+    let mut translation = MappingTranslation {
+        mode,
+        ..Default::default()
+    };
+    if let Some(JsonValue::Object(properties)) = es_mapping.get("properties") {
+        let field_mappings = translate_properties(properties, "", &mut translation);
+        translation.field_mappings = field_mappings;
+    }
+    translation.default_search_fields = translation
+        .field_mappings
+        .iter()
+        .filter(|field_mapping| {
+            field_mapping["type"] == "text" && field_mapping["tokenizer"] == "default"
+        })
+        .filter_map(|field_mapping| field_mapping["name"].as_str().map(str::to_string))
+        .collect();
+    translation
+}
+
+fn translate_properties(
+    properties: &JsonMap<String, JsonValue>,
+    path_prefix: &str,
+    translation: &mut MappingTranslation,
+) -> Vec<JsonValue> {
+    let mut field_mappings = Vec::with_capacity(properties.len());
+    // Fields are translated in a deterministic order, regardless of the JSON map implementation.
+    let properties: BTreeMap<&String, &JsonValue> = properties.iter().collect();
+
+    for (field_name, property) in properties {
+        let field_path = format!("{path_prefix}{field_name}");
+        let es_type = match property.get("type").and_then(JsonValue::as_str) {
+            Some(es_type) => es_type,
+            None if property.get("properties").is_some() => "object",
+            None => {
+                translation
+                    .skipped_fields
+                    .push((field_path, "missing field type".to_string()));
+                continue;
+            }
+        };
+        let field_mapping = match es_type {
+            "text" | "match_only_text" => json!({
+                "name": field_name,
+                "type": "text",
+                "tokenizer": "default",
+                "record": "position",
+            }),
+            "keyword" | "constant_keyword" | "wildcard" => json!({
+                "name": field_name,
+                "type": "text",
+                "tokenizer": "raw",
+            }),
+            "long" | "integer" | "short" | "byte" => json!({
+                "name": field_name,
+                "type": "i64",
+                "fast": true,
+            }),
+            "unsigned_long" => json!({
+                "name": field_name,
+                "type": "u64",
+                "fast": true,
+            }),
+            "double" | "float" | "half_float" | "scaled_float" => json!({
+                "name": field_name,
+                "type": "f64",
+                "fast": true,
+            }),
+            "boolean" => json!({
+                "name": field_name,
+                "type": "bool",
+                "fast": true,
+            }),
+            "date" | "date_nanos" => json!({
+                "name": field_name,
+                "type": "datetime",
+                "input_formats": ["rfc3339", "iso8601", "unix_timestamp"],
+                "fast": true,
+            }),
+            "ip" => json!({
+                "name": field_name,
+                "type": "ip",
+                "fast": true,
+            }),
+            "binary" => json!({
+                "name": field_name,
+                "type": "bytes",
+            }),
+            "geo_point" => json!({
+                "name": field_name,
+                "type": "geo_point",
+            }),
+            "flattened" => json!({
+                "name": field_name,
+                "type": "json",
+            }),
+            "object" | "nested" => {
+                let Some(JsonValue::Object(sub_properties)) = property.get("properties") else {
+                    field_mappings.push(json!({
+                        "name": field_name,
+                        "type": "json",
+                    }));
+                    continue;
+                };
+                let sub_field_mappings =
+                    translate_properties(sub_properties, &format!("{field_path}."), translation);
+                if sub_field_mappings.is_empty() {
+                    continue;
+                }
+                json!({
+                    "name": field_name,
+                    "type": es_type,
+                    "field_mappings": sub_field_mappings,
+                })
+            }
+            "dense_vector" => {
+                let Some(dims) = property.get("dims").and_then(JsonValue::as_u64) else {
+                    translation
+                        .skipped_fields
+                        .push((field_path, "missing vector dimensions".to_string()));
+                    continue;
+                };
+                json!({
+                    "name": field_name,
+                    "type": format!("vector<f32, {dims}>"),
+                })
+            }
+            "alias" => {
+                if let Some(target_path) = property.get("path").and_then(JsonValue::as_str) {
+                    translation
+                        .field_aliases
+                        .insert(field_path, target_path.to_string());
+                } else {
+                    translation
+                        .skipped_fields
+                        .push((field_path, "missing alias path".to_string()));
+                }
+                continue;
+            }
+            _ => {
+                translation
+                    .skipped_fields
+                    .push((field_path, format!("unsupported type `{es_type}`")));
+                continue;
+            }
+        };
+        field_mappings.push(field_mapping);
+    }
+    field_mappings
+}
+
+/// A page of documents returned by the scroll API.
+#[derive(Debug)]
+pub struct ScrollPage {
+    pub scroll_id: String,
+    /// Total number of documents to import, only reported by the first page.
+    pub total_num_docs_opt: Option<u64>,
+    pub docs: Vec<JsonValue>,
+}
+
+/// Minimal Elasticsearch and OpenSearch client exposing the mapping and scroll APIs.
+pub struct EsClient {
+    base_url: Url,
+    client: Client,
+    credentials_opt: Option<(String, Option<String>)>,
+}
+
+impl EsClient {
+    /// Creates a client for the cluster at `endpoint`. Credentials passed in the endpoint URL are
+    /// sent using basic authentication.
+    pub fn new(mut endpoint: Url) -> Self {
+        let credentials_opt = if endpoint.username().is_empty() {
+            None
+        } else {
+            let username = endpoint.username().to_string();
+            let password_opt = endpoint.password().map(str::to_string);
+            let _ = endpoint.set_username("");
+            let _ = endpoint.set_password(None);
+            Some((username, password_opt))
+        };
+        Self {
+            base_url: endpoint,
+            client: Client::new(),
+            credentials_opt,
+        }
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body_opt: Option<JsonValue>,
+    ) -> anyhow::Result<JsonValue> {
+        let url = self
+            .base_url
+            .join(path)
+            .with_context(|| format!("Failed to build Elasticsearch URL for `{path}`."))?;
+        let mut request_builder = self.client.request(method, url);
+        if let Some((username, password_opt)) = &self.credentials_opt {
+            request_builder = request_builder.basic_auth(username, password_opt.as_ref());
+        }
+        if let Some(body) = body_opt {
+            request_builder = request_builder.json(&body);
+        }
+        let response = request_builder
+            .send()
+            .await
+            .context("Failed to send request to Elasticsearch.")?;
+        let status = response.status();
+        let response_json: JsonValue = response
+            .json()
+            .await
+            .context("Failed to parse Elasticsearch response.")?;
+        if !status.is_success() {
+            bail!("Elasticsearch request `{path}` failed with status {status}: {response_json}");
+        }
+        Ok(response_json)
+    }
+
+    /// Returns the `mappings` object of the index. If `es_index` is a pattern or an alias
+    /// matching several indexes, the properties of their mappings are merged.
+    pub async fn get_mapping(&self, es_index: &str) -> anyhow::Result<JsonValue> {
+        let response = self
+            .send(Method::GET, &format!("{es_index}/_mapping"), None)
+            .await?;
+        let Some(indexes) = response.as_object() else {
+            bail!("Unexpected Elasticsearch mapping response: {response}");
+        };
+        let mut mappings_iter = indexes
+            .values()
+            .filter_map(|index_mapping| index_mapping.get("mappings"));
+        let Some(mut mapping) = mappings_iter.next().cloned() else {
+            bail!("Index `{es_index}` does not exist or has no mapping.");
+        };
+        for other_mapping in mappings_iter {
+            let Some(JsonValue::Object(other_properties)) = other_mapping.get("properties") else {
+                continue;
+            };
+            let properties = mapping
+                .as_object_mut()
+                .expect("Mapping should be an object.")
+                .entry("properties")
+                .or_insert_with(|| json!({}));
+            if let JsonValue::Object(properties) = properties {
+                for (field_name, property) in other_properties {
+                    properties
+                        .entry(field_name.clone())
+                        .or_insert_with(|| property.clone());
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// Opens a scroll over all the documents of the index and returns its first page.
+    pub async fn start_scroll(
+        &self,
+        es_index: &str,
+        batch_size: usize,
+        keep_alive: &str,
+    ) -> anyhow::Result<ScrollPage> {
+        let body = json!({
+            "size": batch_size,
+            "sort": ["_doc"],
+            "track_total_hits": true,
+        });
+        let response = self
+            .send(
+                Method::POST,
+                &format!("{es_index}/_search?scroll={keep_alive}"),
+                Some(body),
+            )
+            .await?;
+        parse_scroll_page(response)
+    }
+
+    /// Fetches the page following the one that returned `scroll_id`.
+    pub async fn next_scroll_page(
+        &self,
+        scroll_id: &str,
+        keep_alive: &str,
+    ) -> anyhow::Result<ScrollPage> {
+        let body = json!({
+            "scroll": keep_alive,
+            "scroll_id": scroll_id,
+        });
+        let response = self
+            .send(Method::POST, "_search/scroll", Some(body))
+            .await
+            .context(
+                "Failed to fetch the next page of documents. The scroll may have expired, in \
+                 which case the import cannot be resumed.",
+            )?;
+        parse_scroll_page(response)
+    }
+
+    /// Releases the resources held by the scroll.
+    pub async fn clear_scroll(&self, scroll_id: &str) -> anyhow::Result<()> {
+        let body = json!({ "scroll_id": scroll_id });
+        self.send(Method::DELETE, "_search/scroll", Some(body))
+            .await?;
+        Ok(())
+    }
+}
+
+fn parse_scroll_page(mut response: JsonValue) -> anyhow::Result<ScrollPage> {
+    let scroll_id = response["_scroll_id"]
+        .as_str()
+        .context("Elasticsearch response is missing the scroll ID.")?
+        .to_string();
+    // Elasticsearch 6 reports the total as a number, later versions as an object.
+    let total_num_docs_opt = response["hits"]["total"]["value"]
+        .as_u64()
+        .or_else(|| response["hits"]["total"].as_u64());
+    let JsonValue::Array(hits) = response["hits"]["hits"].take() else {
+        bail!("Elasticsearch response is missing the hits.");
+    };
+    let docs = hits
+        .into_iter()
+        .map(|mut hit| match hit["_source"].take() {
+            JsonValue::Null => bail!(
+                "Document `{}` has no `_source`. Documents can only be imported from indexes \
+                 storing their source.",
+                hit["_id"]
+            ),
+            source => Ok(source),
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(ScrollPage {
+        scroll_id,
+        total_num_docs_opt,
+        docs,
+    })
+}
+
+/// Progress of an import, saved after each batch of documents is ingested so that an
+/// interrupted import can be resumed as long as its scroll has not expired.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ImportCheckpoint {
+    pub es_index: String,
+    pub index_id: String,
+    pub scroll_id: String,
+    pub num_docs_imported: u64,
+    pub total_num_docs_opt: Option<u64>,
+}
+
+impl ImportCheckpoint {
+    /// Loads the checkpoint stored at `path`, if any.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let checkpoint_json = std::fs::read(path)
+            .with_context(|| format!("Failed to read checkpoint file `{}`.", path.display()))?;
+        let checkpoint = serde_json::from_slice(&checkpoint_json)
+            .with_context(|| format!("Failed to parse checkpoint file `{}`.", path.display()))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Saves the checkpoint atomically at `path`.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let checkpoint_json = serde_json::to_vec_pretty(self)?;
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, checkpoint_json)
+            .with_context(|| format!("Failed to write checkpoint file `{}`.", path.display()))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to write checkpoint file `{}`.", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_es_mapping() {
+        let es_mapping = json!({
+            "dynamic": "strict",
+            "properties": {
+                "@timestamp": {"type": "date"},
+                "message": {"type": "text", "fields": {"keyword": {"type": "keyword"}}},
+                "service": {"type": "keyword"},
+                "status": {"type": "integer"},
+                "duration": {"type": "float"},
+                "client": {
+                    "properties": {
+                        "ip": {"type": "ip"},
+                        "location": {"type": "geo_point"},
+                        "shape": {"type": "geo_shape"},
+                    }
+                },
+                "labels": {"type": "flattened"},
+                "svc": {"type": "alias", "path": "service"},
+                "embedding": {"type": "dense_vector", "dims": 3},
+                "percolate": {"type": "percolator"},
+            }
+        });
+        let translation = translate_es_mapping(&es_mapping);
+        assert_eq!(translation.mode, "strict");
+        assert_eq!(
+            translation.field_mappings,
+            vec![
+                json!({
+                    "name": "@timestamp",
+                    "type": "datetime",
+                    "input_formats": ["rfc3339", "iso8601", "unix_timestamp"],
+                    "fast": true,
+                }),
+                json!({
+                    "name": "client",
+                    "type": "object",
+                    "field_mappings": [
+                        {"name": "ip", "type": "ip", "fast": true},
+                        {"name": "location", "type": "geo_point"},
+                    ],
+                }),
+                json!({"name": "duration", "type": "f64", "fast": true}),
+                json!({"name": "embedding", "type": "vector<f32, 3>"}),
+                json!({"name": "labels", "type": "json"}),
+                json!({
+                    "name": "message",
+                    "type": "text",
+                    "tokenizer": "default",
+                    "record": "position",
+                }),
+                json!({"name": "service", "type": "text", "tokenizer": "raw"}),
+                json!({"name": "status", "type": "i64", "fast": true}),
+            ]
+        );
+        assert_eq!(
+            translation.field_aliases,
+            BTreeMap::from([("svc".to_string(), "service".to_string())])
+        );
+        assert_eq!(
+            translation.default_search_fields,
+            vec!["message".to_string()]
+        );
+        assert_eq!(
+            translation.skipped_fields,
+            vec![
+                (
+                    "client.shape".to_string(),
+                    "unsupported type `geo_shape`".to_string()
+                ),
+                (
+                    "percolate".to_string(),
+                    "unsupported type `percolator`".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mapping_translation_to_index_config() {
+        let es_mapping = json!({
+            "properties": {
+                "timestamp": {"type": "date_nanos"},
+                "body": {"type": "text"},
+            }
+        });
+        let translation = translate_es_mapping(&es_mapping);
+        assert_eq!(translation.mode, "dynamic");
+
+        let index_config = translation
+            .to_index_config("my-index", Some("timestamp"))
+            .unwrap();
+        assert_eq!(index_config["index_id"], "my-index");
+        assert_eq!(index_config["doc_mapping"]["timestamp_field"], "timestamp");
+        assert_eq!(
+            index_config["search_settings"]["default_search_fields"],
+            json!(["body"])
+        );
+        let index_config_bytes = serde_json::to_vec(&index_config).unwrap();
+        quickwit_config::load_index_config_from_user_config(
+            quickwit_config::ConfigFormat::Json,
+            &index_config_bytes,
+            &quickwit_common::uri::Uri::from_well_formed("s3://my-bucket/indexes"),
+        )
+        .unwrap();
+
+        let error = translation
+            .to_index_config("my-index", Some("body"))
+            .unwrap_err();
+        assert!(error.to_string().contains("must be a top-level field"));
+    }
+
+    #[test]
+    fn test_parse_scroll_page() {
+        let response = json!({
+            "_scroll_id": "scroll-1",
+            "hits": {
+                "total": {"value": 2, "relation": "eq"},
+                "hits": [
+                    {"_id": "1", "_source": {"body": "foo"}},
+                    {"_id": "2", "_source": {"body": "bar"}},
+                ]
+            }
+        });
+        let scroll_page = parse_scroll_page(response).unwrap();
+        assert_eq!(scroll_page.scroll_id, "scroll-1");
+        assert_eq!(scroll_page.total_num_docs_opt, Some(2));
+        assert_eq!(
+            scroll_page.docs,
+            vec![json!({"body": "foo"}), json!({"body": "bar"})]
+        );
+
+        let response = json!({
+            "_scroll_id": "scroll-2",
+            "hits": {"total": 1, "hits": [{"_id": "1"}]}
+        });
+        let error = parse_scroll_page(response).unwrap_err();
+        assert!(error.to_string().contains("has no `_source`"));
+    }
+
+    #[test]
+    fn test_import_checkpoint() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let checkpoint_path = temp_dir.path().join("checkpoint.json");
+        assert!(ImportCheckpoint::load(&checkpoint_path).unwrap().is_none());
+
+        let checkpoint = ImportCheckpoint {
+            es_index: "logs".to_string(),
+            index_id: "my-index".to_string(),
+            scroll_id: "scroll-1".to_string(),
+            num_docs_imported: 1000,
+            total_num_docs_opt: Some(2000),
+        };
+        checkpoint.save(&checkpoint_path).unwrap();
+        assert_eq!(
+            ImportCheckpoint::load(&checkpoint_path).unwrap(),
+            Some(checkpoint)
+        );
+    }
+}
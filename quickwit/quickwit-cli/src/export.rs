@@ -0,0 +1,579 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Writers used by `quickwit index export` to serialize search hits into NDJSON, CSV, or Parquet
+//! files.
+//!
+//! CSV and Parquet files are tabular: nested objects are flattened into columns named after the
+//! path of their fields (e.g. `resource.service`), and arrays are serialized as JSON strings. The
+//! columns are determined by the `--fields` option or, by default, by the first batch of hits.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tracing::warn;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(export_format_str: &str) -> anyhow::Result<Self> {
+        match export_format_str {
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => bail!(
+                "Failed to parse export format `{export_format_str}`. Supported formats are: \
+                 `csv`, `ndjson`, and `parquet`."
+            ),
+        }
+    }
+}
+
+/// Writes batches of documents to a file.
+pub trait DocWriter {
+    fn write_docs(&mut self, docs: &[JsonValue]) -> anyhow::Result<()>;
+
+    /// Flushes the remaining data and writes the footer of the file, if any.
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+/// Creates a writer serializing documents into `file`. If `fields_opt` is set, only these fields
+/// are exported.
+pub fn doc_writer(
+    export_format: ExportFormat,
+    file: File,
+    fields_opt: Option<Vec<String>>,
+) -> Box<dyn DocWriter> {
+    let output = BufWriter::new(file);
+    match export_format {
+        ExportFormat::Csv => Box::new(CsvDocWriter {
+            csv_writer: csv::Writer::from_writer(output),
+            columns_opt: fields_opt,
+            is_header_written: false,
+        }),
+        ExportFormat::Ndjson => Box::new(NdjsonDocWriter { output, fields_opt }),
+        ExportFormat::Parquet => Box::new(ParquetDocWriter {
+            output_opt: Some(output),
+            file_writer_opt: None,
+            fields_opt,
+            columns: Vec::new(),
+            num_mismatched_values: 0,
+        }),
+    }
+}
+
+struct NdjsonDocWriter {
+    output: BufWriter<File>,
+    fields_opt: Option<Vec<String>>,
+}
+
+impl DocWriter for NdjsonDocWriter {
+    fn write_docs(&mut self, docs: &[JsonValue]) -> anyhow::Result<()> {
+        for doc in docs {
+            if let Some(fields) = &self.fields_opt {
+                let flattened_doc = flatten_doc(doc);
+                let projected_doc: JsonMap<String, JsonValue> = fields
+                    .iter()
+                    .filter_map(|field| {
+                        flattened_doc
+                            .get(field)
+                            .map(|value| (field.clone(), value.clone()))
+                    })
+                    .collect();
+                serde_json::to_writer(&mut self.output, &projected_doc)?;
+            } else {
+                serde_json::to_writer(&mut self.output, doc)?;
+            }
+            self.output.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.output.flush()?;
+        Ok(())
+    }
+}
+
+struct CsvDocWriter {
+    csv_writer: csv::Writer<BufWriter<File>>,
+    columns_opt: Option<Vec<String>>,
+    is_header_written: bool,
+}
+
+impl CsvDocWriter {
+    fn write_header(
+        &mut self,
+        flattened_docs: &[BTreeMap<String, JsonValue>],
+    ) -> anyhow::Result<()> {
+        if self.is_header_written {
+            return Ok(());
+        }
+        let columns = self
+            .columns_opt
+            .get_or_insert_with(|| infer_columns(flattened_docs));
+        if !columns.is_empty() {
+            self.csv_writer.write_record(columns.iter())?;
+        }
+        self.is_header_written = true;
+        Ok(())
+    }
+}
+
+impl DocWriter for CsvDocWriter {
+    fn write_docs(&mut self, docs: &[JsonValue]) -> anyhow::Result<()> {
+        let flattened_docs: Vec<BTreeMap<String, JsonValue>> =
+            docs.iter().map(flatten_doc).collect();
+        self.write_header(&flattened_docs)?;
+        let columns = self
+            .columns_opt
+            .as_ref()
+            .expect("The columns should be known once the header is written.");
+
+        for flattened_doc in &flattened_docs {
+            let record = columns
+                .iter()
+                .map(|column| match flattened_doc.get(column) {
+                    None | Some(JsonValue::Null) => String::new(),
+                    Some(JsonValue::String(value)) => value.clone(),
+                    Some(value) => value.to_string(),
+                });
+            self.csv_writer.write_record(record)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.write_header(&[])?;
+        self.csv_writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ColumnType {
+    Boolean,
+    Double,
+    Int64,
+    Utf8,
+}
+
+impl ColumnType {
+    fn infer<'a>(values: impl Iterator<Item = &'a JsonValue>) -> Self {
+        let mut column_type_opt: Option<ColumnType> = None;
+
+        for value in values {
+            let value_type = match value {
+                JsonValue::Null => continue,
+                JsonValue::Bool(_) => ColumnType::Boolean,
+                JsonValue::Number(number) if number.is_i64() => ColumnType::Int64,
+                JsonValue::Number(_) => ColumnType::Double,
+                _ => ColumnType::Utf8,
+            };
+            column_type_opt = match (column_type_opt, value_type) {
+                (None, value_type) => Some(value_type),
+                (Some(column_type), value_type) if column_type == value_type => Some(column_type),
+                (Some(ColumnType::Int64), ColumnType::Double)
+                | (Some(ColumnType::Double), ColumnType::Int64) => Some(ColumnType::Double),
+                _ => Some(ColumnType::Utf8),
+            };
+        }
+        column_type_opt.unwrap_or(ColumnType::Utf8)
+    }
+
+    fn parquet_type(&self) -> &'static str {
+        match self {
+            ColumnType::Boolean => "BOOLEAN",
+            ColumnType::Double => "DOUBLE",
+            ColumnType::Int64 => "INT64",
+            ColumnType::Utf8 => "BYTE_ARRAY",
+        }
+    }
+}
+
+struct ParquetDocWriter {
+    // Moved into the file writer once the schema is known, i.e. when the first batch is written.
+    output_opt: Option<BufWriter<File>>,
+    file_writer_opt: Option<SerializedFileWriter<BufWriter<File>>>,
+    fields_opt: Option<Vec<String>>,
+    columns: Vec<(String, ColumnType)>,
+    // Values that do not match the type of their column, written as nulls.
+    num_mismatched_values: u64,
+}
+
+impl ParquetDocWriter {
+    fn file_writer(
+        &mut self,
+        flattened_docs: &[BTreeMap<String, JsonValue>],
+    ) -> anyhow::Result<&mut SerializedFileWriter<BufWriter<File>>> {
+        if self.file_writer_opt.is_none() {
+            let column_names = match &self.fields_opt {
+                Some(fields) => fields.clone(),
+                None => infer_columns(flattened_docs),
+            };
+            if column_names.is_empty() {
+                bail!("Failed to infer the columns of the Parquet file: no fields to export.");
+            }
+            self.columns = column_names
+                .into_iter()
+                .map(|column_name| {
+                    let column_type = ColumnType::infer(
+                        flattened_docs
+                            .iter()
+                            .filter_map(|flattened_doc| flattened_doc.get(&column_name)),
+                    );
+                    (column_name, column_type)
+                })
+                .collect();
+            let schema_fields: String = self
+                .columns
+                .iter()
+                .map(|(column_name, column_type)| {
+                    let annotation = if *column_type == ColumnType::Utf8 {
+                        " (UTF8)"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        "OPTIONAL {} {}{annotation};",
+                        column_type.parquet_type(),
+                        parquet_column_name(column_name)
+                    )
+                })
+                .collect();
+            let schema = parse_message_type(&format!("message schema {{ {schema_fields} }}"))
+                .context("Failed to build Parquet schema.")?;
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let output = self
+                .output_opt
+                .take()
+                .expect("The output should be available until the file writer is created.");
+            let file_writer =
+                SerializedFileWriter::new(output, Arc::new(schema), Arc::new(properties))?;
+            self.file_writer_opt = Some(file_writer);
+        }
+        Ok(self
+            .file_writer_opt
+            .as_mut()
+            .expect("The file writer should be initialized."))
+    }
+}
+
+impl DocWriter for ParquetDocWriter {
+    fn write_docs(&mut self, docs: &[JsonValue]) -> anyhow::Result<()> {
+        if docs.is_empty() {
+            return Ok(());
+        }
+        let flattened_docs: Vec<BTreeMap<String, JsonValue>> =
+            docs.iter().map(flatten_doc).collect();
+        self.file_writer(&flattened_docs)?;
+        let columns = self.columns.clone();
+        let file_writer = self
+            .file_writer_opt
+            .as_mut()
+            .expect("The file writer should be initialized.");
+        let mut num_mismatched_values = 0;
+        // Each batch of documents is written as a row group.
+        let mut row_group_writer = file_writer.next_row_group()?;
+
+        for (column_name, column_type) in &columns {
+            let mut column_writer = row_group_writer
+                .next_column()?
+                .context("The row group should have a writer for each column.")?;
+            let values = flattened_docs
+                .iter()
+                .map(|flattened_doc| flattened_doc.get(column_name).unwrap_or(&JsonValue::Null));
+            let mut def_levels = Vec::with_capacity(flattened_docs.len());
+            let mut push_def_level = |value: &JsonValue, is_defined: bool| {
+                if !is_defined && !value.is_null() {
+                    num_mismatched_values += 1;
+                }
+                def_levels.push(is_defined as i16);
+                is_defined
+            };
+            match (column_writer.untyped(), column_type) {
+                (ColumnWriter::BoolColumnWriter(typed_writer), ColumnType::Boolean) => {
+                    let typed_values: Vec<bool> = values
+                        .filter_map(|value| {
+                            let typed_value_opt = value.as_bool();
+                            push_def_level(value, typed_value_opt.is_some());
+                            typed_value_opt
+                        })
+                        .collect();
+                    typed_writer.write_batch(&typed_values, Some(&def_levels), None)?;
+                }
+                (ColumnWriter::DoubleColumnWriter(typed_writer), ColumnType::Double) => {
+                    let typed_values: Vec<f64> = values
+                        .filter_map(|value| {
+                            let typed_value_opt = value.as_f64();
+                            push_def_level(value, typed_value_opt.is_some());
+                            typed_value_opt
+                        })
+                        .collect();
+                    typed_writer.write_batch(&typed_values, Some(&def_levels), None)?;
+                }
+                (ColumnWriter::Int64ColumnWriter(typed_writer), ColumnType::Int64) => {
+                    let typed_values: Vec<i64> = values
+                        .filter_map(|value| {
+                            let typed_value_opt = value.as_i64();
+                            push_def_level(value, typed_value_opt.is_some());
+                            typed_value_opt
+                        })
+                        .collect();
+                    typed_writer.write_batch(&typed_values, Some(&def_levels), None)?;
+                }
+                (ColumnWriter::ByteArrayColumnWriter(typed_writer), ColumnType::Utf8) => {
+                    let typed_values: Vec<ByteArray> = values
+                        .filter_map(|value| {
+                            let typed_value_opt = match value {
+                                JsonValue::Null => None,
+                                JsonValue::String(value) => Some(ByteArray::from(value.as_str())),
+                                value => Some(ByteArray::from(value.to_string().into_bytes())),
+                            };
+                            push_def_level(value, typed_value_opt.is_some());
+                            typed_value_opt
+                        })
+                        .collect();
+                    typed_writer.write_batch(&typed_values, Some(&def_levels), None)?;
+                }
+                _ => bail!("Parquet column `{column_name}` does not have the expected type."),
+            }
+            column_writer.close()?;
+        }
+        row_group_writer.close()?;
+        self.num_mismatched_values += num_mismatched_values;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        if self.file_writer_opt.is_none() {
+            // No document was exported: the file still gets a schema, derived from the fields.
+            self.file_writer(&[])?;
+        }
+        if self.num_mismatched_values > 0 {
+            warn!(
+                num_mismatched_values = self.num_mismatched_values,
+                "Some values did not match the type of their column and were exported as nulls."
+            );
+        }
+        let file_writer = self
+            .file_writer_opt
+            .take()
+            .expect("The file writer should be initialized.");
+        file_writer.close()?;
+        Ok(())
+    }
+}
+
+/// Flattens the nested objects of a document into a map keyed by the paths of the fields.
+fn flatten_doc(doc: &JsonValue) -> BTreeMap<String, JsonValue> {
+    let mut flattened_doc = BTreeMap::new();
+    if let JsonValue::Object(doc_map) = doc {
+        flatten_object(doc_map, "", &mut flattened_doc);
+    }
+    flattened_doc
+}
+
+fn flatten_object(
+    object: &JsonMap<String, JsonValue>,
+    path_prefix: &str,
+    flattened_doc: &mut BTreeMap<String, JsonValue>,
+) {
+    for (key, value) in object {
+        let path = format!("{path_prefix}{key}");
+        match value {
+            JsonValue::Object(sub_object) => {
+                flatten_object(sub_object, &format!("{path}."), flattened_doc)
+            }
+            value => {
+                flattened_doc.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+fn infer_columns(flattened_docs: &[BTreeMap<String, JsonValue>]) -> Vec<String> {
+    let columns: BTreeSet<&String> = flattened_docs
+        .iter()
+        .flat_map(|flattened_doc| flattened_doc.keys())
+        .collect();
+    columns.into_iter().cloned().collect()
+}
+
+/// Replaces the characters the Parquet schema parser treats as delimiters.
+fn parquet_column_name(column_name: &str) -> String {
+    column_name
+        .chars()
+        .map(|character| {
+            if character.is_whitespace() || ";{}()=,".contains(character) {
+                '_'
+            } else {
+                character
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+    use serde_json::json;
+
+    use super::*;
+
+    fn test_docs() -> Vec<JsonValue> {
+        vec![
+            json!({"severity": "INFO", "status": 200, "latency": 1.5, "resource": {"service": "api"}}),
+            json!({"severity": "ERROR", "status": 500, "latency": 2, "tags": ["a", "b"]}),
+        ]
+    }
+
+    #[test]
+    fn test_export_ndjson() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("export.ndjson");
+
+        let mut writer = doc_writer(
+            ExportFormat::Ndjson,
+            File::create(&file_path).unwrap(),
+            Some(vec!["resource.service".to_string(), "severity".to_string()]),
+        );
+        writer.write_docs(&test_docs()).unwrap();
+        writer.finish().unwrap();
+
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        assert_eq!(
+            content,
+            "{\"resource.service\":\"api\",\"severity\":\"INFO\"}\n{\"severity\":\"ERROR\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("export.csv");
+
+        let mut writer = doc_writer(ExportFormat::Csv, File::create(&file_path).unwrap(), None);
+        let docs = test_docs();
+        writer.write_docs(&docs[..1]).unwrap();
+        writer.write_docs(&docs[1..]).unwrap();
+        writer.finish().unwrap();
+
+        // The columns are inferred from the first batch.
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        assert_eq!(
+            content,
+            "latency,resource.service,severity,status\n1.5,api,INFO,200\n2,,ERROR,500\n"
+        );
+    }
+
+    #[test]
+    fn test_export_parquet() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("export.parquet");
+
+        let mut writer = doc_writer(
+            ExportFormat::Parquet,
+            File::create(&file_path).unwrap(),
+            None,
+        );
+        let mut docs = test_docs();
+        writer.write_docs(&docs).unwrap();
+        docs[0]["status"] = json!("not-a-number");
+        writer.write_docs(&docs[..1]).unwrap();
+        writer.finish().unwrap();
+
+        let file_reader = SerializedFileReader::new(File::open(&file_path).unwrap()).unwrap();
+        assert_eq!(file_reader.metadata().num_row_groups(), 2);
+        let rows: Vec<Vec<(String, Field)>> = file_reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.get_column_iter()
+                    .map(|(name, field)| (name.clone(), field.clone()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[0],
+            vec![
+                ("latency".to_string(), Field::Double(1.5)),
+                (
+                    "resource.service".to_string(),
+                    Field::Str("api".to_string())
+                ),
+                ("severity".to_string(), Field::Str("INFO".to_string())),
+                ("status".to_string(), Field::Long(200)),
+                ("tags".to_string(), Field::Null),
+            ]
+        );
+        assert_eq!(rows[1][0], ("latency".to_string(), Field::Double(2.0)));
+        assert_eq!(
+            rows[1][4],
+            ("tags".to_string(), Field::Str("[\"a\",\"b\"]".to_string()))
+        );
+        // Values that do not match the type of their column are exported as nulls.
+        assert_eq!(rows[2][3], ("status".to_string(), Field::Null));
+    }
+
+    #[test]
+    fn test_export_parquet_without_docs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("export.parquet");
+
+        let writer = doc_writer(
+            ExportFormat::Parquet,
+            File::create(&file_path).unwrap(),
+            None,
+        );
+        let error = writer.finish().unwrap_err();
+        assert!(error.to_string().contains("no fields to export"));
+
+        let writer = doc_writer(
+            ExportFormat::Parquet,
+            File::create(&file_path).unwrap(),
+            Some(vec!["severity".to_string()]),
+        );
+        writer.finish().unwrap();
+        let file_reader = SerializedFileReader::new(File::open(&file_path).unwrap()).unwrap();
+        assert_eq!(file_reader.metadata().file_metadata().num_rows(), 0);
+    }
+}
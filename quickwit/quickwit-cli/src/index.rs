@@ -35,7 +35,7 @@ use indicatif::{ProgressBar, ProgressStyle};
 use itertools::Itertools;
 use quickwit_actors::{ActorHandle, ObservationType};
 use quickwit_common::uri::Uri;
-use quickwit_common::GREEN_COLOR;
+use quickwit_common::{GREEN_COLOR, RED_COLOR};
 use quickwit_config::{ConfigFormat, IndexConfig};
 use quickwit_indexing::models::IndexingStatistics;
 use quickwit_indexing::IndexingPipeline;
@@ -45,7 +45,7 @@ use quickwit_rest_client::models::IngestSource;
 use quickwit_rest_client::rest_client::{IngestEvent, QuickwitClient, Transport};
 use quickwit_search::SearchResponseRest;
 use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString, SortByField};
-use quickwit_storage::load_file;
+use quickwit_storage::{load_file, quickwit_storage_uri_resolver, FilePayload};
 use quickwit_telemetry::payload::TelemetryEvent;
 use reqwest::Url;
 use tabled::object::{Columns, Segment};
@@ -53,6 +53,7 @@ use tabled::{Alignment, Concat, Format, Modify, Panel, Rotate, Style, Table, Tab
 use thousands::Separable;
 use tracing::{debug, Level};
 
+use crate::export::{doc_writer, ExportFormat};
 use crate::stats::{mean, percentile, std_deviation};
 use crate::{cluster_endpoint_arg, make_table, prompt_confirmation, THROUGHPUT_WINDOW_SIZE};
 
@@ -82,8 +83,22 @@ pub fn build_index_command<'a>() -> Command<'a> {
                 ])
             )
         .subcommand(
-            Command::new("delete")
+            Command::new("clone")
                 .display_order(3)
+                .about("Clones an index: copies its config and splits under a new index ID.")
+                .long_about("Creates a new index with the same config as the source index and copies all its published splits. Sources, checkpoints, and delete tasks are not cloned.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the source index")
+                        .display_order(1),
+                    arg!(--"target-index" <TARGET_INDEX> "ID of the new index")
+                        .display_order(2),
+                    arg!(--"target-index-uri" <TARGET_INDEX_URI> "URI of the new index. Defaults to a sibling of the source index URI.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("delete")
+                .display_order(4)
                 .alias("del")
                 .about("Deletes an index.")
                 .long_about("Deletes an index. This operation is destructive and cannot be undone, proceed with caution.")
@@ -96,7 +111,7 @@ pub fn build_index_command<'a>() -> Command<'a> {
             )
         .subcommand(
             Command::new("describe")
-                .display_order(4)
+                .display_order(5)
                 .about("Displays descriptive statistics of an index.")
                 .long_about("Displays descriptive statistics of an index. Displayed statistics are: number of published splits, number of documents, splits min/max timestamps, size of splits.")
                 .args(&[
@@ -107,13 +122,13 @@ pub fn build_index_command<'a>() -> Command<'a> {
         .subcommand(
             Command::new("list")
                 .alias("ls")
-                .display_order(5)
+                .display_order(6)
                 .about("List indexes.")
                 .arg(cluster_endpoint_arg())
             )
         .subcommand(
             Command::new("ingest")
-                .display_order(6)
+                .display_order(7)
                 .about("Ingest NDJSON documents with the ingest API.")
                 .long_about("Reads NDJSON documents from a file or streamed from stdin and sends them into ingest API.")
                 .args(&[
@@ -125,7 +140,7 @@ pub fn build_index_command<'a>() -> Command<'a> {
             )
         .subcommand(
             Command::new("search")
-                .display_order(7)
+                .display_order(8)
                 .about("Searches an index.")
                 .args(&[
                     arg!(--index <INDEX> "ID of the target index")
@@ -153,6 +168,119 @@ pub fn build_index_command<'a>() -> Command<'a> {
                         .required(false),
                 ])
             )
+        .subcommand(
+            Command::new("reconcile")
+                .display_order(9)
+                .about("Reconciles the storage of an index with the metastore.")
+                .long_about("Lists the split files stored in the index storage and reports the files unknown to the metastore (orphans) and the published splits whose file is missing. Orphan files can optionally be deleted.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"delete-orphans" "Deletes the split files unknown to the metastore. This operation is destructive and cannot be undone, proceed with caution.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("migrate-storage-layout")
+                .display_order(9)
+                .about("Migrates the split files of an index to the content-addressed storage layout.")
+                .long_about("Switches the index to the content-addressed split storage layout and rewrites its published splits under a file named by the hash of their content. The split files of the previous layout are deleted by the garbage collection. The migration can be interrupted and resumed.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                ])
+            )
+        .subcommand(
+            Command::new("rehydrate")
+                .display_order(10)
+                .about("Rehydrates the archived splits of an index.")
+                .long_about("Moves the archived splits overlapping the time range from the archive storage of the index back to the index storage, making them searchable again.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Rehydrates the splits containing documents after that timestamp.")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Rehydrates the splits containing documents before that timestamp.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("backup")
+                .display_order(11)
+                .about("Backs up an index.")
+                .long_about("Writes the index metadata, including its doc mapping and settings, and the metadata of its published splits to the target storage, optionally along with the split files.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--"target-uri" <TARGET_URI> "URI of the backup.")
+                        .display_order(2),
+                    arg!(--"include-split-files" "Copies the split files to the backup. Without them, the backup can only be restored into a storage that already holds the split files.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("restore")
+                .display_order(12)
+                .about("Restores an index from a backup.")
+                .long_about("Creates an index from the config recorded in the backup and publishes the splits of the backup. Sources other than the default ingest sources, checkpoints, and delete tasks are not restored.")
+                .args(&[
+                    arg!(--"backup-uri" <BACKUP_URI> "URI of the backup.")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the restored index. Defaults to the ID of the backed up index.")
+                        .required(false),
+                    arg!(--"index-uri" <INDEX_URI> "URI of the restored index. Defaults to the URI of the backed up index.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("mount")
+                .display_order(12)
+                .about("Mounts a read-only index from a snapshot.")
+                .long_about("Creates a read-only index from the config and the splits recorded in a snapshot, either an index backup or the index directory of a file-backed metastore. The split files are searched in place, without being copied.")
+                .args(&[
+                    arg!(--"snapshot-uri" <SNAPSHOT_URI> "URI of the snapshot.")
+                        .display_order(1),
+                    arg!(--index <INDEX> "ID of the mounted index. Defaults to the ID of the snapshotted index.")
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("refresh")
+                .display_order(12)
+                .about("Refreshes a mounted index.")
+                .long_about("Reloads the snapshot a mounted index was mounted from and publishes or removes the splits that changed since the previous refresh.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                ])
+            )
+        .subcommand(
+            Command::new("export")
+                .display_order(13)
+                .about("Exports the documents matching a query to a file.")
+                .args(&[
+                    arg!(--index <INDEX> "ID of the target index")
+                        .display_order(1),
+                    arg!(--query <QUERY> "Query expressed in natural query language ((barack AND obama) OR \"president of united states\"). Learn more on https://quickwit.io/docs/reference/search-language."),
+                    arg!(--format <FORMAT> "Output format. Possible values are `ndjson`, `csv`, and `parquet`.")
+                        .default_value("ndjson")
+                        .required(false),
+                    arg!(--"output-uri" <OUTPUT_URI> "Local or remote URI of the exported file."),
+                    arg!(--fields <FIELD_NAME> "List of fields to export. Defaults to all the fields. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .multiple_values(true)
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Filters out documents before that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Filters out documents after that timestamp (time-series indexes only).")
+                        .required(false),
+                    arg!(--"batch-size" <BATCH_SIZE> "Number of documents fetched per request.")
+                        .default_value("1000")
+                        .required(false),
+                ])
+            )
         .arg_required_else_help(true)
 }
 
@@ -163,6 +291,14 @@ pub struct ClearIndexArgs {
     pub assume_yes: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct CloneIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+    pub target_index_id: String,
+    pub target_index_uri: Option<Uri>,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct CreateIndexArgs {
     pub cluster_endpoint: Url,
@@ -207,19 +343,93 @@ pub struct DeleteIndexArgs {
     pub assume_yes: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReconcileIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+    pub delete_orphans: bool,
+    pub assume_yes: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MigrateStorageLayoutArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RehydrateIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct BackupIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+    pub target_uri: Uri,
+    pub include_split_files: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RestoreIndexArgs {
+    pub cluster_endpoint: Url,
+    pub backup_uri: Uri,
+    pub index_id: Option<String>,
+    pub index_uri: Option<Uri>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MountIndexArgs {
+    pub cluster_endpoint: Url,
+    pub snapshot_uri: Uri,
+    pub index_id: Option<String>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RefreshIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct ListIndexesArgs {
     pub cluster_endpoint: Url,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct ExportIndexArgs {
+    pub cluster_endpoint: Url,
+    pub index_id: String,
+    pub query: String,
+    pub export_format: ExportFormat,
+    pub output_uri: Uri,
+    pub fields: Option<Vec<String>>,
+    pub search_fields: Option<Vec<String>>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+    pub batch_size: u64,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum IndexCliCommand {
+    Backup(BackupIndexArgs),
     Clear(ClearIndexArgs),
+    Clone(CloneIndexArgs),
     Create(CreateIndexArgs),
     Delete(DeleteIndexArgs),
     Describe(DescribeIndexArgs),
+    Export(ExportIndexArgs),
     Ingest(IngestDocsArgs),
     List(ListIndexesArgs),
+    MigrateStorageLayout(MigrateStorageLayoutArgs),
+    Mount(MountIndexArgs),
+    Reconcile(ReconcileIndexArgs),
+    Refresh(RefreshIndexArgs),
+    Rehydrate(RehydrateIndexArgs),
+    Restore(RestoreIndexArgs),
     Search(SearchIndexArgs),
 }
 
@@ -236,12 +446,21 @@ impl IndexCliCommand {
             .subcommand()
             .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
         match subcommand {
+            "backup" => Self::parse_backup_args(submatches),
             "clear" => Self::parse_clear_args(submatches),
+            "clone" => Self::parse_clone_args(submatches),
             "create" => Self::parse_create_args(submatches),
             "delete" => Self::parse_delete_args(submatches),
             "describe" => Self::parse_describe_args(submatches),
+            "export" => Self::parse_export_args(submatches),
             "ingest" => Self::parse_ingest_args(submatches),
             "list" => Self::parse_list_args(submatches),
+            "migrate-storage-layout" => Self::parse_migrate_storage_layout_args(submatches),
+            "mount" => Self::parse_mount_args(submatches),
+            "reconcile" => Self::parse_reconcile_args(submatches),
+            "refresh" => Self::parse_refresh_args(submatches),
+            "rehydrate" => Self::parse_rehydrate_args(submatches),
+            "restore" => Self::parse_restore_args(submatches),
             "search" => Self::parse_search_args(submatches),
             _ => bail!("Index subcommand `{}` is not implemented.", subcommand),
         }
@@ -264,6 +483,53 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_backup_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let target_uri = matches
+            .value_of("target-uri")
+            .map(Uri::from_str)
+            .expect("`target-uri` is a required arg.")?;
+        let include_split_files = matches.is_present("include-split-files");
+        Ok(Self::Backup(BackupIndexArgs {
+            cluster_endpoint,
+            index_id,
+            target_uri,
+            include_split_files,
+        }))
+    }
+
+    fn parse_clone_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let target_index_id = matches
+            .value_of("target-index")
+            .expect("`target-index` is a required arg.")
+            .to_string();
+        let target_index_uri = matches
+            .value_of("target-index-uri")
+            .map(Uri::from_str)
+            .transpose()?;
+        Ok(Self::Clone(CloneIndexArgs {
+            cluster_endpoint,
+            index_id,
+            target_index_id,
+            target_index_uri,
+        }))
+    }
+
     fn parse_create_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let cluster_endpoint = matches
             .value_of("endpoint")
@@ -380,6 +646,58 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_export_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let query = matches
+            .value_of("query")
+            .context("`query` is a required arg.")?
+            .to_string();
+        let export_format = matches
+            .value_of("format")
+            .map(ExportFormat::from_str)
+            .expect("`format` should have a default value.")?;
+        let output_uri = matches
+            .value_of("output-uri")
+            .map(Uri::from_str)
+            .expect("`output-uri` is a required arg.")?;
+        let fields = matches
+            .values_of("fields")
+            .map(|values| values.map(|value| value.to_string()).collect());
+        let search_fields = matches
+            .values_of("search-fields")
+            .map(|values| values.map(|value| value.to_string()).collect());
+        let start_timestamp = if matches.is_present("start-timestamp") {
+            Some(matches.value_of_t::<i64>("start-timestamp")?)
+        } else {
+            None
+        };
+        let end_timestamp = if matches.is_present("end-timestamp") {
+            Some(matches.value_of_t::<i64>("end-timestamp")?)
+        } else {
+            None
+        };
+        let batch_size = matches.value_of_t::<u64>("batch-size")?;
+        Ok(Self::Export(ExportIndexArgs {
+            cluster_endpoint,
+            index_id,
+            query,
+            export_format,
+            output_uri,
+            fields,
+            search_fields,
+            start_timestamp,
+            end_timestamp,
+            batch_size,
+        }))
+    }
+
     fn parse_delete_args(matches: &ArgMatches) -> anyhow::Result<Self> {
         let cluster_endpoint = matches
             .value_of("endpoint")
@@ -399,14 +717,138 @@ impl IndexCliCommand {
         }))
     }
 
+    fn parse_reconcile_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let delete_orphans = matches.is_present("delete-orphans");
+        let assume_yes = matches.is_present("yes");
+        Ok(Self::Reconcile(ReconcileIndexArgs {
+            cluster_endpoint,
+            index_id,
+            delete_orphans,
+            assume_yes,
+        }))
+    }
+
+    fn parse_migrate_storage_layout_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        Ok(Self::MigrateStorageLayout(MigrateStorageLayoutArgs {
+            cluster_endpoint,
+            index_id,
+        }))
+    }
+
+    fn parse_rehydrate_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        let start_timestamp = if matches.is_present("start-timestamp") {
+            Some(matches.value_of_t::<i64>("start-timestamp")?)
+        } else {
+            None
+        };
+        let end_timestamp = if matches.is_present("end-timestamp") {
+            Some(matches.value_of_t::<i64>("end-timestamp")?)
+        } else {
+            None
+        };
+        Ok(Self::Rehydrate(RehydrateIndexArgs {
+            cluster_endpoint,
+            index_id,
+            start_timestamp,
+            end_timestamp,
+        }))
+    }
+
+    fn parse_restore_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let backup_uri = matches
+            .value_of("backup-uri")
+            .map(Uri::from_str)
+            .expect("`backup-uri` is a required arg.")?;
+        let index_id = matches.value_of("index").map(ToString::to_string);
+        let index_uri = matches
+            .value_of("index-uri")
+            .map(Uri::from_str)
+            .transpose()?;
+        Ok(Self::Restore(RestoreIndexArgs {
+            cluster_endpoint,
+            backup_uri,
+            index_id,
+            index_uri,
+        }))
+    }
+
+    fn parse_mount_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let snapshot_uri = matches
+            .value_of("snapshot-uri")
+            .map(Uri::from_str)
+            .expect("`snapshot-uri` is a required arg.")?;
+        let index_id = matches.value_of("index").map(ToString::to_string);
+        Ok(Self::Mount(MountIndexArgs {
+            cluster_endpoint,
+            snapshot_uri,
+            index_id,
+        }))
+    }
+
+    fn parse_refresh_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let index_id = matches
+            .value_of("index")
+            .expect("`index` is a required arg.")
+            .to_string();
+        Ok(Self::Refresh(RefreshIndexArgs {
+            cluster_endpoint,
+            index_id,
+        }))
+    }
+
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
+            Self::Backup(args) => backup_index_cli(args).await,
             Self::Clear(args) => clear_index_cli(args).await,
+            Self::Clone(args) => clone_index_cli(args).await,
             Self::Create(args) => create_index_cli(args).await,
             Self::Delete(args) => delete_index_cli(args).await,
             Self::Describe(args) => describe_index_cli(args).await,
+            Self::Export(args) => export_index_cli(args).await,
             Self::Ingest(args) => ingest_docs_cli(args).await,
             Self::List(args) => list_index_cli(args).await,
+            Self::MigrateStorageLayout(args) => migrate_storage_layout_cli(args).await,
+            Self::Mount(args) => mount_index_cli(args).await,
+            Self::Reconcile(args) => reconcile_index_cli(args).await,
+            Self::Refresh(args) => refresh_index_cli(args).await,
+            Self::Rehydrate(args) => rehydrate_index_cli(args).await,
+            Self::Restore(args) => restore_index_cli(args).await,
             Self::Search(args) => search_index_cli(args).await,
         }
     }
@@ -432,6 +874,107 @@ pub async fn clear_index_cli(args: ClearIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn backup_index_cli(args: BackupIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "backup-index");
+    println!("❯ Backing up index...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let backup_summary = qw_client
+        .indexes()
+        .backup(&args.index_id, &args.target_uri, args.include_split_files)
+        .await?;
+    println!(
+        "{} Index `{}` successfully backed up to `{}`: {} split(s), {} document(s), {}{}.",
+        "✔".color(GREEN_COLOR),
+        args.index_id,
+        backup_summary.backup_uri,
+        backup_summary.num_splits,
+        backup_summary.num_docs,
+        Byte::from_bytes(backup_summary.num_bytes as u128).get_appropriate_unit(false),
+        if backup_summary.includes_split_files {
+            ""
+        } else {
+            " (split files not included)"
+        }
+    );
+    Ok(())
+}
+
+pub async fn restore_index_cli(args: RestoreIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "restore-index");
+    println!("❯ Restoring index...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let index_metadata = qw_client
+        .indexes()
+        .restore(
+            &args.backup_uri,
+            args.index_id.as_deref(),
+            args.index_uri.as_ref(),
+        )
+        .await?;
+    println!(
+        "{} Index `{}` successfully restored at `{}`.",
+        "✔".color(GREEN_COLOR),
+        index_metadata.index_id(),
+        index_metadata.index_uri()
+    );
+    Ok(())
+}
+
+pub async fn mount_index_cli(args: MountIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "mount-index");
+    println!("❯ Mounting index...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let index_metadata = qw_client
+        .indexes()
+        .mount(&args.snapshot_uri, args.index_id.as_deref())
+        .await?;
+    println!(
+        "{} Index `{}` successfully mounted from `{}`.",
+        "✔".color(GREEN_COLOR),
+        index_metadata.index_id(),
+        args.snapshot_uri
+    );
+    Ok(())
+}
+
+pub async fn refresh_index_cli(args: RefreshIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "refresh-index");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let refresh_summary = qw_client.indexes().refresh_mount(&args.index_id).await?;
+    println!(
+        "{} Index `{}` successfully refreshed: {} split(s) added, {} split(s) removed.",
+        "✔".color(GREEN_COLOR),
+        args.index_id,
+        refresh_summary.num_added_splits,
+        refresh_summary.num_removed_splits
+    );
+    Ok(())
+}
+
+pub async fn clone_index_cli(args: CloneIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "clone-index");
+    println!("❯ Cloning index...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let target_index_uri_opt = args.target_index_uri.as_ref().map(Uri::as_str);
+    let index_metadata = qw_client
+        .indexes()
+        .clone(&args.index_id, &args.target_index_id, target_index_uri_opt)
+        .await?;
+    println!(
+        "{} Index `{}` successfully cloned into `{}` at `{}`.",
+        "✔".color(GREEN_COLOR),
+        args.index_id,
+        args.target_index_id,
+        index_metadata.index_uri()
+    );
+    Ok(())
+}
+
 pub async fn create_index_cli(args: CreateIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "create-index");
     println!("❯ Creating index...");
@@ -839,6 +1382,75 @@ pub async fn search_index_cli(args: SearchIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Duration the searched splits are pinned between two pages of an export.
+const EXPORT_SCROLL_TTL: &str = "5m";
+
+pub async fn export_index_cli(args: ExportIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "export-index");
+    println!("❯ Exporting documents to {}...", args.output_uri);
+    let output_dir_uri = args
+        .output_uri
+        .parent()
+        .with_context(|| format!("URI `{}` is not a valid file URI.", args.output_uri))?;
+    let output_file_name = args
+        .output_uri
+        .file_name()
+        .with_context(|| format!("URI `{}` is not a valid file URI.", args.output_uri))?
+        .to_path_buf();
+    let output_storage = quickwit_storage_uri_resolver().resolve(&output_dir_uri)?;
+
+    // The documents are written to a local file first, then uploaded to the target storage.
+    let temp_dir = tempfile::tempdir()?;
+    let temp_path = temp_dir.path().join(&output_file_name);
+    let mut doc_writer = doc_writer(
+        args.export_format,
+        std::fs::File::create(&temp_path)?,
+        args.fields,
+    );
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let search_request = SearchRequestQueryString {
+        query: args.query,
+        search_fields: args.search_fields,
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+        max_hits: args.batch_size,
+        scroll: Some(EXPORT_SCROLL_TTL.to_string()),
+        ..Default::default()
+    };
+    let mut search_response = qw_client.search(&args.index_id, search_request).await?;
+    let progress_bar = ProgressBar::new(search_response.num_hits);
+    progress_bar.set_style(
+        ProgressStyle::with_template("{spinner:.blue} [{elapsed_precise}] {pos}/{len} documents")
+            .expect("Progress style should always be valid."),
+    );
+    let mut num_docs_exported = 0;
+
+    while !search_response.hits.is_empty() {
+        doc_writer.write_docs(&search_response.hits)?;
+        num_docs_exported += search_response.hits.len() as u64;
+        progress_bar.set_position(num_docs_exported);
+
+        let Some(scroll_id) = search_response.scroll_id.as_deref() else {
+            break;
+        };
+        search_response = qw_client.scroll(scroll_id, Some(EXPORT_SCROLL_TTL)).await?;
+    }
+    doc_writer.finish()?;
+    progress_bar.finish();
+
+    let payload = FilePayload::open(temp_path).await?;
+    output_storage
+        .put(&output_file_name, Box::new(payload))
+        .await?;
+    println!(
+        "{} Exported {} documents successfully.",
+        "✔".color(GREEN_COLOR),
+        num_docs_exported.separate_with_commas()
+    );
+    Ok(())
+}
+
 pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     debug!(args=?args, "delete-index");
     println!("❯ Deleting index...");
@@ -875,6 +1487,117 @@ pub async fn delete_index_cli(args: DeleteIndexArgs) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub async fn reconcile_index_cli(args: ReconcileIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "reconcile-index");
+    if args.delete_orphans && !args.assume_yes {
+        let prompt = format!(
+            "This operation will delete the split files of the index `{}` unknown to the \
+             metastore. Do you want to proceed?",
+            args.index_id
+        );
+        if !prompt_confirmation(&prompt, false) {
+            return Ok(());
+        }
+    }
+    println!("❯ Reconciling index storage...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let report = qw_client
+        .indexes()
+        .reconcile(&args.index_id, args.delete_orphans)
+        .await?;
+    if report.is_consistent() {
+        println!(
+            "{} Index storage is consistent with the metastore.",
+            "✔".color(GREEN_COLOR)
+        );
+        return Ok(());
+    }
+    if !report.orphan_files.is_empty() {
+        println!(
+            "Found {} orphan split file(s) ({}) unknown to the metastore:",
+            report.orphan_files.len(),
+            Byte::from_bytes(report.orphan_num_bytes() as u128).get_appropriate_unit(false)
+        );
+        for file_entry in &report.orphan_files {
+            println!(" - {}", file_entry.file_name);
+        }
+    }
+    if !report.missing_split_ids.is_empty() {
+        println!(
+            "Found {} published split(s) whose file is missing from the index storage:",
+            report.missing_split_ids.len()
+        );
+        for split_id in &report.missing_split_ids {
+            println!(" - {split_id}");
+        }
+    }
+    if args.delete_orphans {
+        if report.deleted_orphan_files.len() == report.orphan_files.len() {
+            println!(
+                "{} Deleted {} orphan split file(s).",
+                "✔".color(GREEN_COLOR),
+                report.deleted_orphan_files.len()
+            );
+        } else {
+            println!(
+                "{} Deleted {} out of {} orphan split file(s).",
+                "✘".color(RED_COLOR),
+                report.deleted_orphan_files.len(),
+                report.orphan_files.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+pub async fn migrate_storage_layout_cli(args: MigrateStorageLayoutArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "migrate-storage-layout");
+    println!("❯ Migrating split files to the content-addressed storage layout...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let migration_summary = qw_client
+        .indexes()
+        .migrate_split_storage_layout(&args.index_id)
+        .await?;
+    println!(
+        "{} Index `{}` successfully migrated: {} split(s) migrated, {} of which reused an \
+         existing split file, {} uploaded.",
+        "✔".color(GREEN_COLOR),
+        args.index_id,
+        migration_summary.num_migrated_splits,
+        migration_summary.num_deduplicated_splits,
+        Byte::from_bytes(migration_summary.num_uploaded_bytes as u128).get_appropriate_unit(false)
+    );
+    Ok(())
+}
+
+pub async fn rehydrate_index_cli(args: RehydrateIndexArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "rehydrate-index");
+    println!("❯ Rehydrating archived splits...");
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let splits = qw_client
+        .indexes()
+        .rehydrate(&args.index_id, args.start_timestamp, args.end_timestamp)
+        .await?;
+    if splits.is_empty() {
+        println!("No archived split overlaps the time range.");
+        return Ok(());
+    }
+    let num_bytes: u64 = splits
+        .iter()
+        .map(|split_metadata| split_metadata.footer_offsets.end)
+        .sum();
+    println!(
+        "{} Rehydrated {} split(s) ({}).",
+        "✔".color(GREEN_COLOR),
+        splits.len(),
+        Byte::from_bytes(num_bytes as u128).get_appropriate_unit(false)
+    );
+    Ok(())
+}
+
 /// Starts a tokio task that displays the indexing statistics
 /// every once in awhile.
 pub async fn start_statistics_reporting_loop(
@@ -40,13 +40,21 @@ use tabled::object::Rows;
 use tabled::{Alignment, Header, Modify, Style, Table, Tabled};
 use tracing::info;
 
+pub mod alias;
+pub mod bench;
 pub mod cli;
+pub mod config;
+pub mod doctor;
+pub mod es_import;
+pub mod export;
 pub mod index;
 #[cfg(feature = "jemalloc")]
 pub mod jemalloc;
+pub mod node;
 pub mod service;
 pub mod source;
 pub mod split;
+pub mod sql;
 pub mod stats;
 pub mod tool;
 
@@ -103,6 +111,7 @@ pub fn parse_duration_with_unit(duration_with_unit_str: &str) -> anyhow::Result<
 
 pub fn start_actor_runtimes(services: &HashSet<QuickwitService>) -> anyhow::Result<()> {
     if services.contains(&QuickwitService::Indexer)
+        || services.contains(&QuickwitService::Merger)
         || services.contains(&QuickwitService::Janitor)
         || services.contains(&QuickwitService::ControlPlane)
     {
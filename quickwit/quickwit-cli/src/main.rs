@@ -38,7 +38,7 @@ use tonic::metadata::MetadataMap;
 use tracing::Level;
 use tracing_subscriber::fmt::time::UtcTime;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::EnvFilter;
+use tracing_subscriber::{reload, EnvFilter};
 
 fn setup_logging_and_tracing(
     level: Level,
@@ -56,6 +56,14 @@ fn setup_logging_and_tracing(
         .map(|_| EnvFilter::from_default_env())
         .or_else(|_| EnvFilter::try_new(format!("quickwit={level}")))
         .context("Failed to set up tracing env filter.")?;
+    // The env filter is wrapped in a reload layer so that the `log_level` of the node config can
+    // be changed at runtime.
+    let (env_filter, env_filter_reload_handle) = reload::Layer::new(env_filter);
+    quickwit_common::set_log_filter_reloader(move |directives| {
+        let env_filter = EnvFilter::try_new(directives)?;
+        env_filter_reload_handle.reload(env_filter)?;
+        Ok(())
+    });
     global::set_text_map_propagator(TraceContextPropagator::new());
     let registry = tracing_subscriber::registry().with(env_filter);
     let event_format = tracing_subscriber::fmt::format()
@@ -177,13 +185,18 @@ mod tests {
     use std::time::Duration;
 
     use quickwit_cli::cli::{build_cli, CliCommand};
+    use quickwit_cli::export::ExportFormat;
     use quickwit_cli::index::{
-        ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, IndexCliCommand,
-        IngestDocsArgs, SearchIndexArgs,
+        BackupIndexArgs, ClearIndexArgs, CloneIndexArgs, CreateIndexArgs, DeleteIndexArgs,
+        DescribeIndexArgs, ExportIndexArgs, IndexCliCommand, IngestDocsArgs,
+        MigrateStorageLayoutArgs, MountIndexArgs, ReconcileIndexArgs, RefreshIndexArgs,
+        RehydrateIndexArgs, RestoreIndexArgs, SearchIndexArgs,
     };
     use quickwit_cli::split::{DescribeSplitArgs, SplitCliCommand};
+    use quickwit_cli::sql::SqlCliCommand;
     use quickwit_cli::tool::{
-        ExtractSplitArgs, GarbageCollectIndexArgs, LocalIngestDocsArgs, MergeArgs, ToolCliCommand,
+        BenchArgs, ExtractSplitArgs, GarbageCollectIndexArgs, ImportEsArgs, LocalIngestDocsArgs,
+        LocalSearchArgs, MergeArgs, ToolCliCommand,
     };
     use quickwit_common::uri::Uri;
     use reqwest::Url;
@@ -215,6 +228,48 @@ mod tests {
         assert_eq!(command, expected_cmd);
     }
 
+    #[test]
+    fn test_parse_clone_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "clone",
+            "--index",
+            "wikipedia",
+            "--target-index",
+            "wikipedia-copy",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Clone(CloneIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia".to_string(),
+            target_index_id: "wikipedia-copy".to_string(),
+            target_index_uri: None,
+        }));
+        assert_eq!(command, expected_cmd);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "clone",
+            "--index",
+            "wikipedia",
+            "--target-index",
+            "wikipedia-copy",
+            "--target-index-uri",
+            "s3://my-bucket/wikipedia-copy",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Clone(CloneIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia".to_string(),
+            target_index_id: "wikipedia-copy".to_string(),
+            target_index_uri: Some(Uri::from_well_formed("s3://my-bucket/wikipedia-copy")),
+        }));
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_create_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -335,6 +390,25 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_sql_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "sql",
+            "--query",
+            "SELECT COUNT(*) FROM wikipedia",
+            "--endpoint",
+            "http://127.0.0.1:8000",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Sql(SqlCliCommand {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:8000").unwrap(),
+            query: "SELECT COUNT(*) FROM wikipedia".to_string(),
+        });
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_search_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -409,6 +483,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_export_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "export",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "severity_text:ERROR",
+            "--output-uri",
+            "s3://quickwit-exports/errors.csv",
+            "--format",
+            "csv",
+            "--fields",
+            "timestamp",
+            "body",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Export(ExportIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "hdfs-logs".to_string(),
+            query: "severity_text:ERROR".to_string(),
+            export_format: ExportFormat::Csv,
+            output_uri: Uri::from_well_formed("s3://quickwit-exports/errors.csv"),
+            fields: Some(vec!["timestamp".to_string(), "body".to_string()]),
+            search_fields: None,
+            start_timestamp: None,
+            end_timestamp: None,
+            batch_size: 1000,
+        }));
+        assert_eq!(command, expected_cmd);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "export",
+            "--index",
+            "hdfs-logs",
+            "--query",
+            "*",
+            "--output-uri",
+            "s3://quickwit-exports/errors.ndjson",
+            "--format",
+            "xlsx",
+        ])?;
+        CliCommand::parse_cli_args(&matches).unwrap_err();
+        Ok(())
+    }
+
     #[test]
     fn test_parse_delete_args() {
         let app = build_cli().no_binary_name(true);
@@ -440,6 +564,148 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_reconcile_index_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(["index", "reconcile", "--index", "wikipedia"])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Reconcile(ReconcileIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia".to_string(),
+            delete_orphans: false,
+            assume_yes: false,
+        }));
+        assert_eq!(command, expected_cmd);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "reconcile",
+            "--index",
+            "wikipedia",
+            "--delete-orphans",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Index(IndexCliCommand::Reconcile(ReconcileIndexArgs {
+                delete_orphans: true,
+                ..
+            }))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_migrate_storage_layout_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches =
+            app.try_get_matches_from(["index", "migrate-storage-layout", "--index", "wikipedia"])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::MigrateStorageLayout(
+            MigrateStorageLayoutArgs {
+                cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+                index_id: "wikipedia".to_string(),
+            },
+        ));
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_backup_and_restore_index_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "backup",
+            "--index",
+            "wikipedia",
+            "--target-uri",
+            "s3://backups/wikipedia",
+            "--include-split-files",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Backup(BackupIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia".to_string(),
+            target_uri: Uri::from_well_formed("s3://backups/wikipedia"),
+            include_split_files: true,
+        }));
+        assert_eq!(command, expected_cmd);
+
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "restore",
+            "--backup-uri",
+            "s3://backups/wikipedia",
+            "--index",
+            "wikipedia-restored",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Restore(RestoreIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            backup_uri: Uri::from_well_formed("s3://backups/wikipedia"),
+            index_id: Some("wikipedia-restored".to_string()),
+            index_uri: None,
+        }));
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_mount_and_refresh_index_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "mount",
+            "--snapshot-uri",
+            "s3://shared-bucket/indexes/wikipedia",
+            "--index",
+            "wikipedia-replica",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Mount(MountIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            snapshot_uri: Uri::from_well_formed("s3://shared-bucket/indexes/wikipedia"),
+            index_id: Some("wikipedia-replica".to_string()),
+        }));
+        assert_eq!(command, expected_cmd);
+
+        let app = build_cli().no_binary_name(true);
+        let matches =
+            app.try_get_matches_from(["index", "refresh", "--index", "wikipedia-replica"])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Refresh(RefreshIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia-replica".to_string(),
+        }));
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rehydrate_index_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "index",
+            "rehydrate",
+            "--index",
+            "wikipedia",
+            "--start-timestamp",
+            "1672531200",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_cmd = CliCommand::Index(IndexCliCommand::Rehydrate(RehydrateIndexArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "wikipedia".to_string(),
+            start_timestamp: Some(1672531200),
+            end_timestamp: None,
+        }));
+        assert_eq!(command, expected_cmd);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_describe_index_args() {
         let app = build_cli().no_binary_name(true);
@@ -556,6 +822,113 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_local_search_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "tool",
+            "search-local",
+            "--index-config",
+            "/index-config.yaml",
+            "--splits",
+            "/splits/split-1.split",
+            "s3://my-bucket/splits/split-2.split",
+            "--query",
+            "severity_text:ERROR",
+            "--start-timestamp",
+            "1672531200",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Tool(ToolCliCommand::LocalSearch(LocalSearchArgs {
+            index_config_uri: Uri::from_str("file:///index-config.yaml").unwrap(),
+            split_uris: vec![
+                Uri::from_str("file:///splits/split-1.split").unwrap(),
+                Uri::from_str("s3://my-bucket/splits/split-2.split").unwrap(),
+            ],
+            query: "severity_text:ERROR".to_string(),
+            aggregation: None,
+            max_hits: 20,
+            start_offset: 0,
+            search_fields: None,
+            snippet_fields: None,
+            start_timestamp: Some(1672531200),
+            end_timestamp: None,
+            sort_by_score: false,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_bench_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "tool",
+            "bench",
+            "--index",
+            "hdfs-logs",
+            "--num-docs",
+            "100000",
+            "--ingest-rate",
+            "5000",
+            "--query-log",
+            "/tmp/queries.txt",
+            "--concurrency",
+            "8",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Tool(ToolCliCommand::Bench(BenchArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            index_id: "hdfs-logs".to_string(),
+            num_docs_opt: Some(100_000),
+            input_path_opt: None,
+            ingest_rate_opt: Some(5_000),
+            batch_size: 1000,
+            query_log_path_opt: Some(PathBuf::from("/tmp/queries.txt")),
+            num_queries_opt: None,
+            search_rate_opt: None,
+            concurrency: 8,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_import_es_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "tool",
+            "import-es",
+            "--es-endpoint",
+            "http://localhost:9200",
+            "--es-index",
+            "logs-*",
+            "--index",
+            "logs",
+            "--timestamp-field",
+            "@timestamp",
+            "--checkpoint-file",
+            "/tmp/import-checkpoint.json",
+            "--yes",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Tool(ToolCliCommand::ImportEs(ImportEsArgs {
+            cluster_endpoint: Url::from_str("http://127.0.0.1:7280").unwrap(),
+            es_endpoint: Url::from_str("http://localhost:9200").unwrap(),
+            es_index: "logs-*".to_string(),
+            index_id: "logs".to_string(),
+            index_config_uri_opt: None,
+            timestamp_field_opt: Some("@timestamp".to_string()),
+            batch_size: 1000,
+            scroll_keep_alive: "30m".to_string(),
+            checkpoint_path_opt: Some(PathBuf::from("/tmp/import-checkpoint.json")),
+            dry_run: false,
+            assume_yes: true,
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+
     #[test]
     fn test_parse_merge_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
@@ -0,0 +1,144 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::bail;
+use clap::{arg, ArgMatches, Command};
+use colored::Colorize;
+use quickwit_cluster::NodeDrainStatus;
+use quickwit_common::GREEN_COLOR;
+use quickwit_rest_client::rest_client::{QuickwitClient, Transport};
+use reqwest::Url;
+use tracing::debug;
+
+use crate::cluster_endpoint_arg;
+
+const DRAIN_POLLING_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn build_node_command<'a>() -> Command<'a> {
+    Command::new("node")
+        .about("Manages nodes: drains...")
+        .arg(cluster_endpoint_arg())
+        .subcommand(
+            Command::new("drain")
+                .about("Drains a node before decommissioning it: the node stops accepting new documents, publishes its in-flight splits, and leaves the indexing plan and the search routing. The endpoint must target the node to drain.")
+                .args(&[
+                    arg!(--"node-id" <NODE_ID> "ID of the node to drain")
+                        .display_order(1)
+                        .required(true),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DrainNodeArgs {
+    pub cluster_endpoint: Url,
+    pub node_id: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum NodeCliCommand {
+    Drain(DrainNodeArgs),
+}
+
+impl NodeCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .subcommand()
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
+        match subcommand {
+            "drain" => Self::parse_drain_args(submatches),
+            _ => bail!("Subcommand `{}` is not implemented.", subcommand),
+        }
+    }
+
+    fn parse_drain_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        let node_id = matches
+            .value_of("node-id")
+            .map(String::from)
+            .expect("`node-id` is a required arg.");
+        Ok(Self::Drain(DrainNodeArgs {
+            cluster_endpoint,
+            node_id,
+        }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Drain(args) => drain_node_cli(args).await,
+        }
+    }
+}
+
+async fn drain_node_cli(args: DrainNodeArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "drain-node");
+    println!("❯ Draining node `{}`...", args.node_id);
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let mut node_drain_state = qw_client.drain_node(&args.node_id).await?;
+
+    while node_drain_state.status != NodeDrainStatus::Drained {
+        println!(
+            "  {} pending ingest records, {} pipelines with unpublished data",
+            node_drain_state.num_pending_ingest_records,
+            node_drain_state.num_pipelines_with_in_flight_data
+        );
+        tokio::time::sleep(DRAIN_POLLING_INTERVAL).await;
+        node_drain_state = qw_client.node_drain_state(&args.node_id).await?;
+    }
+    println!(
+        "{} Node `{}` drained, it is ready to be terminated.",
+        "✔".color(GREEN_COLOR),
+        args.node_id
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{build_cli, CliCommand};
+
+    #[test]
+    fn test_parse_drain_node_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "node",
+            "drain",
+            "--endpoint",
+            "https://quickwit-cluster.io",
+            "--node-id",
+            "indexer-1",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        let expected_command = CliCommand::Node(NodeCliCommand::Drain(DrainNodeArgs {
+            cluster_endpoint: Url::from_str("https://quickwit-cluster.io").unwrap(),
+            node_id: "indexer-1".to_string(),
+        }));
+        assert_eq!(command, expected_command);
+        Ok(())
+    }
+}
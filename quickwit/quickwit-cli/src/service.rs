@@ -80,7 +80,7 @@ impl RunCliCommand {
         quickwit_telemetry::send_telemetry_event(telemetry_event).await;
         // TODO move in serve quickwit?
         start_actor_runtimes(&config.enabled_services)?;
-        serve_quickwit(config).await?;
+        serve_quickwit(config, Some(self.config_uri.clone())).await?;
         Ok(())
     }
 }
@@ -22,11 +22,15 @@ use std::str::FromStr;
 use anyhow::{bail, Context};
 use clap::{arg, ArgMatches, Command};
 use colored::Colorize;
+use humansize::{format_size, DECIMAL};
 use itertools::Itertools;
+use quickwit_common::uri::Uri;
 use quickwit_common::GREEN_COLOR;
+use quickwit_directories::{inspect_split, SplitInspection};
 use quickwit_metastore::{Split, SplitState};
 use quickwit_rest_client::rest_client::{QuickwitClient, Transport};
 use quickwit_serve::ListSplitsQueryParams;
+use quickwit_storage::load_file;
 use reqwest::Url;
 use tabled::{Table, Tabled};
 use time::{format_description, Date, OffsetDateTime, PrimitiveDateTime};
@@ -71,7 +75,7 @@ pub fn build_split_command<'a>() -> Command<'a> {
             )
         .subcommand(
             Command::new("describe")
-                .about("Displays metadata about a split.")
+                .about("Displays metadata about a split, along with statistics about its content read directly from storage.")
                 .alias("desc")
                 .args(&[
                     arg!(--index <INDEX> "ID of the target index")
@@ -81,6 +85,15 @@ pub fn build_split_command<'a>() -> Command<'a> {
                     arg!(--verbose "Displays additional metadata about the hotcache."),
                 ])
             )
+        .subcommand(
+            Command::new("inspect")
+                .about("Displays statistics about the content of a split file.")
+                .args(&[
+                    arg!(--"split-file" <SPLIT_FILE_URI> "Local or remote URI of the split file")
+                        .display_order(1),
+                    arg!(--verbose "Displays additional metadata about the hotcache."),
+                ])
+            )
         .subcommand(
             Command::new("mark-for-deletion")
                 .about("Marks one or multiple splits of an index for deletion.")
@@ -151,11 +164,18 @@ pub struct DescribeSplitArgs {
     pub verbose: bool,
 }
 
+#[derive(Debug, Eq, PartialEq)]
+pub struct InspectSplitArgs {
+    pub split_file_uri: Uri,
+    pub verbose: bool,
+}
+
 #[derive(Debug, PartialEq)]
 pub enum SplitCliCommand {
     List(ListSplitArgs),
     MarkForDeletion(MarkForDeletionArgs),
     Describe(DescribeSplitArgs),
+    Inspect(InspectSplitArgs),
 }
 
 impl SplitCliCommand {
@@ -165,6 +185,7 @@ impl SplitCliCommand {
             .ok_or_else(|| anyhow::anyhow!("Failed to parse sub-matches."))?;
         match subcommand {
             "describe" => Self::parse_describe_args(submatches),
+            "inspect" => Self::parse_inspect_args(submatches),
             "list" => Self::parse_list_args(submatches),
             "mark-for-deletion" => Self::parse_mark_for_deletion_args(submatches),
             _ => bail!("Subcommand `{}` is not implemented.", subcommand),
@@ -276,11 +297,25 @@ impl SplitCliCommand {
         }))
     }
 
+    fn parse_inspect_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let split_file_uri = matches
+            .value_of("split-file")
+            .map(Uri::from_str)
+            .expect("`split-file` is a required arg.")?;
+        let verbose = matches.is_present("verbose");
+
+        Ok(Self::Inspect(InspectSplitArgs {
+            split_file_uri,
+            verbose,
+        }))
+    }
+
     pub async fn execute(self) -> anyhow::Result<()> {
         match self {
             Self::List(args) => list_split_cli(args).await,
             Self::MarkForDeletion(args) => mark_splits_for_deletion_cli(args).await,
             Self::Describe(args) => describe_split_cli(args).await,
+            Self::Inspect(args) => inspect_split_cli(args).await,
         }
     }
 }
@@ -364,41 +399,113 @@ async fn describe_split_cli(args: DescribeSplitArgs) -> anyhow::Result<()> {
             )
         })?;
 
+    let tags = if split.split_metadata.tags.is_empty() {
+        "-".to_string()
+    } else {
+        split.split_metadata.tags.iter().join(", ")
+    };
+    let split_file = split.split_metadata.split_file();
     println!("{}", make_split_table(&[split], "Split"));
+    println!("Tags: {tags}");
 
-    // TODO: if we have access to the storage, we could fetch that.
-    // let split_file = PathBuf::from(format!("{}.split", args.split_id));
-    // let (split_footer, _) = read_split_footer(index_storage, &split_file).await?;
-    // let stats = BundleDirectory::get_stats_split(split_footer.clone())?;
-    // let hotcache_bytes = get_hotcache_from_split(split_footer)?;
-
-    // let mut file_rows = Vec::new();
+    let index_metadata = qw_client.indexes().get(&args.index_id).await?;
+    let split_file_uri = index_metadata.index_uri().join(split_file)?;
+    let split_data = load_file(&split_file_uri)
+        .await
+        .with_context(|| format!("Failed to load split file `{split_file_uri}`."))?;
+    let split_inspection = inspect_split(split_data)?;
+    print_split_inspection(&split_inspection, args.verbose);
+    Ok(())
+}
 
-    // for (path, size) in stats {
-    //     file_rows.push(FileRow {
-    //         file_name: path.to_str().unwrap().to_string(),
-    //         size: format_size(size, DECIMAL),
-    //     });
-    // }
-    // println!(
-    //     "{}",
-    //     make_table("Files in Split", file_rows.into_iter(), false)
-    // );
-    // if args.verbose {
-    //     let mut hotcache_files = Vec::new();
-    //     let hotcache_stats = HotDirectory::get_stats_per_file(hotcache_bytes)?;
-    //     for (path, size) in hotcache_stats {
-    //         hotcache_files.push(FileRow {
-    //             file_name: path.to_str().unwrap().to_string(),
-    //             size: format_size(size, DECIMAL),
-    //         });
-    //     }
-    //     let hotcache_table = make_table("Files in Hotcache", hotcache_files.into_iter(), false);
-    //     println!("{hotcache_table}");
-    // }
+async fn inspect_split_cli(args: InspectSplitArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "inspect-split");
+    let split_data = load_file(&args.split_file_uri)
+        .await
+        .with_context(|| format!("Failed to load split file `{}`.", args.split_file_uri))?;
+    let split_inspection = inspect_split(split_data)?;
+    print_split_inspection(&split_inspection, args.verbose);
     Ok(())
 }
 
+#[derive(Tabled)]
+struct FieldRow {
+    #[tabled(rename = "Field")]
+    field_name: String,
+    #[tabled(rename = "Size")]
+    size: String,
+    #[tabled(rename = "Term dict")]
+    term_dictionary_size: String,
+    #[tabled(rename = "Postings")]
+    postings_size: String,
+    #[tabled(rename = "Positions")]
+    positions_size: String,
+    #[tabled(rename = "Fast field")]
+    fast_field_size: String,
+    #[tabled(rename = "Fieldnorms")]
+    fieldnorms_size: String,
+    #[tabled(rename = "Num terms")]
+    num_terms: u64,
+    #[tabled(rename = "Num tokens")]
+    num_tokens: u64,
+}
+
+fn print_split_inspection(split_inspection: &SplitInspection, verbose: bool) {
+    println!(
+        "Split size: {}",
+        format_size(split_inspection.split_num_bytes, DECIMAL)
+    );
+    println!("Num docs: {}", split_inspection.num_docs);
+    println!("Num segments: {}", split_inspection.num_segments);
+    println!(
+        "Doc store size: {}",
+        format_size(split_inspection.doc_store_num_bytes, DECIMAL)
+    );
+    println!(
+        "Hotcache size: {}",
+        format_size(split_inspection.hotcache_num_bytes, DECIMAL)
+    );
+    let file_rows = split_inspection
+        .files
+        .iter()
+        .map(|(file_name, num_bytes)| FileRow {
+            file_name: file_name.clone(),
+            size: format_size(*num_bytes, DECIMAL),
+        });
+    println!("{}", make_table("Files in Split", file_rows, false));
+
+    let field_rows = split_inspection
+        .fields
+        .iter()
+        .map(|field_inspection| FieldRow {
+            field_name: field_inspection.field_name.clone(),
+            size: format_size(field_inspection.num_bytes(), DECIMAL),
+            term_dictionary_size: format_size(field_inspection.term_dictionary_num_bytes, DECIMAL),
+            postings_size: format_size(field_inspection.postings_num_bytes, DECIMAL),
+            positions_size: format_size(field_inspection.positions_num_bytes, DECIMAL),
+            fast_field_size: format_size(field_inspection.fast_field_num_bytes, DECIMAL),
+            fieldnorms_size: format_size(field_inspection.fieldnorms_num_bytes, DECIMAL),
+            num_terms: field_inspection.num_terms,
+            num_tokens: field_inspection.num_tokens,
+        });
+    println!("{}", make_table("Fields", field_rows, false));
+
+    if verbose {
+        let hotcache_file_rows =
+            split_inspection
+                .hotcache_files
+                .iter()
+                .map(|(file_name, num_bytes)| FileRow {
+                    file_name: file_name.clone(),
+                    size: format_size(*num_bytes, DECIMAL),
+                });
+        println!(
+            "{}",
+            make_table("Files in Hotcache", hotcache_file_rows, false)
+        );
+    }
+}
+
 fn make_split_table(splits: &[Split], title: &str) -> Table {
     let rows = splits
         .iter()
@@ -605,6 +712,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_parse_split_inspect_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from(vec![
+            "split",
+            "inspect",
+            "--split-file",
+            "s3://my-bucket/my-index/ABC.split",
+            "--verbose",
+        ])?;
+        let command = CliCommand::parse_cli_args(&matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::Split(SplitCliCommand::Inspect(InspectSplitArgs {
+                split_file_uri,
+                verbose: true,
+            })) if split_file_uri == Uri::from_well_formed("s3://my-bucket/my-index/ABC.split")
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_date() {
         assert_eq!(
@@ -0,0 +1,82 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::str::FromStr;
+
+use anyhow::Context;
+use clap::{arg, ArgMatches, Command};
+use quickwit_rest_client::rest_client::{QuickwitClient, Transport};
+use quickwit_search::SqlResponse;
+use quickwit_serve::SqlRequest;
+use reqwest::Url;
+use tracing::debug;
+
+use crate::cluster_endpoint_arg;
+
+pub fn build_sql_command<'a>() -> Command<'a> {
+    Command::new("sql")
+        .about("Runs a SQL query on an index.")
+        .arg(cluster_endpoint_arg())
+        .arg(
+            arg!(--query <QUERY> "SQL query over one index, e.g. \"SELECT service, COUNT(*) FROM logs WHERE level = 'ERROR' GROUP BY service\".")
+                .display_order(2),
+        )
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SqlCliCommand {
+    pub cluster_endpoint: Url,
+    pub query: String,
+}
+
+impl SqlCliCommand {
+    pub fn parse_cli_args(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let query = matches
+            .value_of("query")
+            .context("`query` is a required arg.")?
+            .to_string();
+        let cluster_endpoint = matches
+            .value_of("endpoint")
+            .map(Url::from_str)
+            .expect("`endpoint` is a required arg.")?;
+        Ok(SqlCliCommand {
+            cluster_endpoint,
+            query,
+        })
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        debug!(args=?self, "sql");
+        let sql_response = sql(self).await?;
+        let sql_response_json = serde_json::to_string_pretty(&sql_response)?;
+        println!("{sql_response_json}");
+        Ok(())
+    }
+}
+
+pub async fn sql(args: SqlCliCommand) -> anyhow::Result<SqlResponse> {
+    let sql_request = SqlRequest {
+        query: args.query,
+        ..Default::default()
+    };
+    let transport = Transport::new(args.cluster_endpoint);
+    let qw_client = QuickwitClient::new(transport);
+    let sql_response = qw_client.sql(sql_request).await?;
+    Ok(sql_response)
+}
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
@@ -41,8 +41,9 @@ use tracing::{debug, error, info};
 
 use crate::error::{ClusterError, ClusterResult};
 use crate::member::{
-    build_cluster_members, ClusterMember, ENABLED_SERVICES_KEY, GRPC_ADVERTISE_ADDR_KEY,
-    INDEXING_TASK_PREFIX, INDEXING_TASK_SEPARATOR,
+    build_cluster_members, ClusterMember, NodeDrainStatus, AVAILABILITY_ZONE_KEY, DRAIN_STATUS_KEY,
+    ENABLED_SERVICES_KEY, GRPC_ADVERTISE_ADDR_KEY, INDEXING_TASK_PREFIX, INDEXING_TASK_SEPARATOR,
+    INGEST_LAG_PREFIX, QUICKWIT_VERSION_KEY,
 };
 use crate::QuickwitService;
 
@@ -86,11 +87,17 @@ pub struct Cluster {
     stop: Arc<AtomicBool>,
 }
 
+// Drained nodes are never ready so that they are removed from the search and ingest routing.
 fn is_ready_predicate(node_state: &NodeState) -> bool {
-    node_state
-        .get(HEALTH_KEY)
-        .map(|health_value| health_value == HEALTH_VALUE_READY)
-        .unwrap_or(false)
+    let is_drained = node_state
+        .get(DRAIN_STATUS_KEY)
+        .map(|drain_status| drain_status == NodeDrainStatus::Drained.as_str())
+        .unwrap_or(false);
+    !is_drained
+        && node_state
+            .get(HEALTH_KEY)
+            .map(|health_value| health_value == HEALTH_VALUE_READY)
+            .unwrap_or(false)
 }
 
 impl Cluster {
@@ -112,6 +119,7 @@ impl Cluster {
             gossip_listen_addr = %gossip_listen_addr,
             gossip_advertise_addr = %self_node.gossip_advertise_addr,
             grpc_advertise_addr = %self_node.grpc_advertise_addr,
+            availability_zone = ?self_node.availability_zone,
             peer_seed_addrs = %peer_seed_addrs.join(", "),
             "Joining cluster."
         );
@@ -126,30 +134,34 @@ impl Cluster {
             is_ready_predicate: Some(Box::new(is_ready_predicate)),
             marked_for_deletion_grace_period: MARKED_FOR_DELETION_GRACE_PERIOD,
         };
-        let chitchat_handle = spawn_chitchat(
-            chitchat_config,
-            vec![
-                (
-                    GRPC_ADVERTISE_ADDR_KEY.to_string(),
-                    self_node.grpc_advertise_addr.to_string(),
-                ),
-                (
-                    ENABLED_SERVICES_KEY.to_string(),
-                    self_node
-                        .enabled_services
-                        .iter()
-                        .map(|service| service.as_str())
-                        .join(","),
-                ),
-                (HEALTH_KEY.to_string(), HEALTH_VALUE_NOT_READY.to_string()),
-            ],
-            transport,
-        )
-        .await
-        .map_err(|cause| ClusterError::UDPPortBindingError {
-            listen_addr: gossip_listen_addr,
-            cause: cause.to_string(),
-        })?;
+        let mut initial_key_values = vec![
+            (
+                GRPC_ADVERTISE_ADDR_KEY.to_string(),
+                self_node.grpc_advertise_addr.to_string(),
+            ),
+            (
+                ENABLED_SERVICES_KEY.to_string(),
+                self_node
+                    .enabled_services
+                    .iter()
+                    .map(|service| service.as_str())
+                    .join(","),
+            ),
+            (HEALTH_KEY.to_string(), HEALTH_VALUE_NOT_READY.to_string()),
+            (
+                QUICKWIT_VERSION_KEY.to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+        ];
+        if let Some(availability_zone) = &self_node.availability_zone {
+            initial_key_values.push((AVAILABILITY_ZONE_KEY.to_string(), availability_zone.clone()));
+        }
+        let chitchat_handle = spawn_chitchat(chitchat_config, initial_key_values, transport)
+            .await
+            .map_err(|cause| ClusterError::UDPPortBindingError {
+                listen_addr: gossip_listen_addr,
+                cause: cause.to_string(),
+            })?;
         let chitchat = chitchat_handle.chitchat();
 
         let (members_sender, members_receiver) = watch::channel(Vec::new());
@@ -314,6 +326,12 @@ impl Cluster {
         self.set_key_value(HEALTH_KEY, health_value).await
     }
 
+    /// Sets the drain status of the node. Drained nodes are not ready anymore.
+    pub async fn set_self_node_drain_status(&self, drain_status: NodeDrainStatus) {
+        self.set_key_value(DRAIN_STATUS_KEY, drain_status.as_str())
+            .await
+    }
+
     /// Returns true if self is ready.
     pub async fn is_self_node_ready(&self) -> bool {
         let chitchat = self.chitchat_handle.chitchat();
@@ -358,6 +376,37 @@ impl Cluster {
         }
         Ok(())
     }
+
+    /// Updates the ingest lags of the indexes in chitchat state. Each lag is stored in a key as
+    /// follows:
+    /// - key: `{INGEST_LAG_PREFIX}{INDEXING_TASK_SEPARATOR}{index_id}`
+    /// - value: Number of records not read by the ingest API pipelines of the index yet.
+    /// Keys present in chitchat state but not in the given `ingest_lags`, and the keys of the
+    /// indexes without lag, are marked for deletion.
+    pub async fn update_self_node_ingest_lags(&self, ingest_lags: &HashMap<String, u64>) {
+        let chitchat = self.chitchat_handle.chitchat();
+        let mut chitchat_guard = chitchat.lock().await;
+        let mut obsolete_ingest_lag_keys: HashSet<String> = chitchat_guard
+            .self_node_state()
+            .iter_key_values(|key, _| key.starts_with(INGEST_LAG_PREFIX))
+            .map(|(key, _)| key.to_string())
+            .collect();
+        for (index_id, ingest_lag) in ingest_lags {
+            if *ingest_lag == 0 {
+                continue;
+            }
+            let key = format!("{INGEST_LAG_PREFIX}{INDEXING_TASK_SEPARATOR}{index_id}");
+            obsolete_ingest_lag_keys.remove(&key);
+            chitchat_guard
+                .self_node_state()
+                .set(key, ingest_lag.to_string());
+        }
+        for obsolete_ingest_lag_key in obsolete_ingest_lag_keys {
+            chitchat_guard
+                .self_node_state()
+                .mark_for_deletion(&obsolete_ingest_lag_key);
+        }
+    }
 }
 
 // Not used within the code, used for documentation.
@@ -408,6 +457,16 @@ pub struct ClusterSnapshot {
     pub dead_nodes: HashSet<NodeId>,
 }
 
+impl ClusterSnapshot {
+    /// Returns the Quickwit version advertised by a node, if the node is known and advertises it.
+    pub fn node_version(&self, node_id: &NodeId) -> Option<&str> {
+        self.chitchat_state_snapshot
+            .node_states
+            .get(&node_id.id)?
+            .get(QUICKWIT_VERSION_KEY)
+    }
+}
+
 /// Compute the gRPC port from the chitchat listen address for tests.
 pub fn grpc_addr_from_listen_addr_for_test(listen_addr: SocketAddr) -> SocketAddr {
     let grpc_port = listen_addr.port() + 1u16;
@@ -522,6 +581,10 @@ mod tests {
             self_node_state_not_ready.get(HEALTH_KEY).unwrap(),
             HEALTH_VALUE_NOT_READY
         );
+        assert_eq!(
+            cluster_snapshot.node_version(&cluster.node_id),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
         cluster.set_self_node_ready(true).await;
         assert!(cluster.is_self_node_ready().await);
         assert_eq!(cluster.ready_members_from_chitchat_state().await.len(), 1);
@@ -563,6 +626,9 @@ mod tests {
         cluster2
             .set_key_value(GRPC_ADVERTISE_ADDR_KEY, "127.0.0.1:1001")
             .await;
+        cluster2
+            .set_key_value(AVAILABILITY_ZONE_KEY, "us-east-1b")
+            .await;
         cluster2
             .update_self_node_indexing_tasks(&[indexing_task.clone(), indexing_task.clone()])
             .await
@@ -585,10 +651,15 @@ mod tests {
             HashSet::from_iter([QuickwitService::Indexer])
         );
         assert!(member_node_1.indexing_tasks.is_empty());
+        assert!(member_node_1.availability_zone.is_none());
         assert_eq!(
             member_node_2.grpc_advertise_addr,
             ([127, 0, 0, 1], 1001).into()
         );
+        assert_eq!(
+            member_node_2.availability_zone.as_deref(),
+            Some("us-east-1b")
+        );
         assert_eq!(
             member_node_2.enabled_services,
             HashSet::from_iter([QuickwitService::Indexer, QuickwitService::Metastore].into_iter())
@@ -750,6 +821,26 @@ mod tests {
         let member = cluster1.ready_members_from_chitchat_state().await;
         assert_eq!(member[0].indexing_tasks.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_cluster_ingest_lags() {
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        let ingest_lags = HashMap::from([("index-1".to_string(), 100), ("index-2".to_string(), 0)]);
+        cluster.update_self_node_ingest_lags(&ingest_lags).await;
+
+        let members = cluster.ready_members_from_chitchat_state().await;
+        assert_eq!(
+            members[0].ingest_lags,
+            HashMap::from([("index-1".to_string(), 100)])
+        );
+        cluster.update_self_node_ingest_lags(&HashMap::new()).await;
+
+        let members = cluster.ready_members_from_chitchat_state().await;
+        assert!(members[0].ingest_lags.is_empty());
+    }
     #[tokio::test]
     async fn test_cluster_available_searcher() -> anyhow::Result<()> {
         quickwit_common::setup_logging_for_tests();
@@ -1198,4 +1289,50 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_cluster_with_node_being_drained() -> anyhow::Result<()> {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster1 =
+            create_cluster_for_test(Vec::new(), &["searcher", "indexer"], &transport, true).await?;
+        let node_1 = cluster1.gossip_listen_addr.to_string();
+        let cluster2 =
+            create_cluster_for_test(vec![node_1], &["indexer"], &transport, true).await?;
+        let wait_secs = Duration::from_secs(30);
+
+        cluster1
+            .wait_for_members(|members| members.len() == 2, wait_secs)
+            .await
+            .unwrap();
+        // A draining node is still ready.
+        cluster2
+            .set_self_node_drain_status(NodeDrainStatus::Draining)
+            .await;
+        wait_until_predicate(
+            || async {
+                cluster1
+                    .ready_members_from_chitchat_state()
+                    .await
+                    .iter()
+                    .any(|member| member.drain_status == NodeDrainStatus::Draining)
+            },
+            wait_secs,
+            Duration::from_millis(100),
+        )
+        .await
+        .unwrap();
+        assert!(cluster2.is_self_node_ready().await);
+
+        // A drained node is removed from the ready members.
+        cluster2
+            .set_self_node_drain_status(NodeDrainStatus::Drained)
+            .await;
+        cluster1
+            .wait_for_members(|members| members.len() == 1, wait_secs)
+            .await
+            .unwrap();
+        assert!(!cluster2.is_self_node_ready().await);
+        Ok(())
+    }
 }
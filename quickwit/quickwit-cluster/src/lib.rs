@@ -26,7 +26,7 @@ mod member;
 use std::collections::HashSet;
 use std::sync::Arc;
 
-use chitchat::transport::UdpTransport;
+use chitchat::transport::Transport;
 use chitchat::FailureDetectorConfig;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::QuickwitConfig;
@@ -36,7 +36,7 @@ pub use crate::cluster::{
     Cluster, ClusterSnapshot, NodeIdSchema,
 };
 pub use crate::error::{ClusterError, ClusterResult};
-pub use crate::member::ClusterMember;
+pub use crate::member::{ClusterMember, NodeDrainStatus};
 
 fn unix_timestamp() -> u64 {
     let duration_since_epoch = std::time::SystemTime::now()
@@ -48,6 +48,7 @@ fn unix_timestamp() -> u64 {
 pub async fn start_cluster_service(
     quickwit_config: &QuickwitConfig,
     enabled_services: &HashSet<QuickwitService>,
+    transport: &dyn Transport,
 ) -> anyhow::Result<Arc<Cluster>> {
     let self_node = ClusterMember::new(
         quickwit_config.node_id.clone(),
@@ -56,7 +57,8 @@ pub async fn start_cluster_service(
         quickwit_config.gossip_advertise_addr,
         quickwit_config.grpc_advertise_addr,
         Vec::new(),
-    );
+    )
+    .with_availability_zone(quickwit_config.availability_zone.clone());
 
     let cluster = Cluster::join(
         self_node,
@@ -64,7 +66,7 @@ pub async fn start_cluster_service(
         quickwit_config.cluster_id.clone(),
         quickwit_config.peer_seed_addrs().await?,
         FailureDetectorConfig::default(),
-        &UdpTransport,
+        transport,
     )
     .await?;
 
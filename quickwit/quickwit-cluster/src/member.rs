@@ -17,13 +17,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::net::SocketAddr;
+use std::str::FromStr;
 
 use anyhow::anyhow;
 use chitchat::{ClusterStateSnapshot, NodeId, NodeState};
 use itertools::Itertools;
 use quickwit_proto::indexing_api::IndexingTask;
+use serde::{Deserialize, Serialize};
 use tracing::{error, warn};
 
 use crate::QuickwitService;
@@ -31,10 +34,60 @@ use crate::QuickwitService;
 // Keys used to store member's data in chitchat state.
 pub(crate) const GRPC_ADVERTISE_ADDR_KEY: &str = "grpc_advertise_addr";
 pub(crate) const ENABLED_SERVICES_KEY: &str = "enabled_services";
+pub(crate) const AVAILABILITY_ZONE_KEY: &str = "availability_zone";
+pub(crate) const DRAIN_STATUS_KEY: &str = "drain_status";
+pub(crate) const QUICKWIT_VERSION_KEY: &str = "quickwit_version";
 // An indexing task key is formatted as
 // `{INDEXING_TASK_PREFIX}{INDEXING_TASK_SEPARATOR}{index_id}{INDEXING_TASK_SEPARATOR}{source_id}`.
 pub(crate) const INDEXING_TASK_PREFIX: &str = "indexing_task";
 pub(crate) const INDEXING_TASK_SEPARATOR: char = ':';
+// An ingest lag key is formatted as `{INGEST_LAG_PREFIX}{INDEXING_TASK_SEPARATOR}{index_id}`.
+pub(crate) const INGEST_LAG_PREFIX: &str = "ingest_lag";
+
+/// Drain status of a node. A node is drained before being decommissioned so that no in-flight
+/// data is lost: the node stops accepting new documents, its indexing pipelines are rescheduled
+/// on other indexers once they have published their in-flight splits, and it is finally removed
+/// from the search and ingest routing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeDrainStatus {
+    /// The node is not being drained.
+    #[default]
+    Active,
+    /// The node rejects new documents and waits for its in-flight data to be published.
+    Draining,
+    /// The node does not hold any in-flight data anymore and can be safely terminated.
+    Drained,
+}
+
+impl NodeDrainStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeDrainStatus::Active => "active",
+            NodeDrainStatus::Draining => "draining",
+            NodeDrainStatus::Drained => "drained",
+        }
+    }
+}
+
+impl fmt::Display for NodeDrainStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for NodeDrainStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(drain_status_str: &str) -> Result<Self, Self::Err> {
+        match drain_status_str {
+            "active" => Ok(NodeDrainStatus::Active),
+            "draining" => Ok(NodeDrainStatus::Draining),
+            "drained" => Ok(NodeDrainStatus::Drained),
+            _ => Err(anyhow!("Unknown node drain status `{drain_status_str}`.")),
+        }
+    }
+}
 
 /// Cluster member.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -58,6 +111,13 @@ pub struct ClusterMember {
     /// None if the node is not an indexer or the indexer has not yet started some indexing
     /// pipelines.
     pub indexing_tasks: Vec<IndexingTask>,
+    /// Availability zone (or rack) of the node, if configured.
+    pub availability_zone: Option<String>,
+    /// Drain status of the node.
+    pub drain_status: NodeDrainStatus,
+    /// Number of records ingested into each index on the node and not read by its ingest API
+    /// pipelines yet, keyed by index ID. The indexes without lag are omitted.
+    pub ingest_lags: HashMap<String, u64>,
 }
 
 impl ClusterMember {
@@ -76,9 +136,18 @@ impl ClusterMember {
             gossip_advertise_addr,
             grpc_advertise_addr,
             indexing_tasks,
+            availability_zone: None,
+            drain_status: NodeDrainStatus::Active,
+            ingest_lags: HashMap::new(),
         }
     }
 
+    /// Sets the availability zone of the member.
+    pub fn with_availability_zone(mut self, availability_zone: Option<String>) -> Self {
+        self.availability_zone = availability_zone;
+        self
+    }
+
     pub fn chitchat_id(&self) -> String {
         format!("{}/{}", self.node_id, self.start_timestamp)
     }
@@ -152,14 +221,24 @@ pub(crate) fn build_cluster_member(
         )
     })?;
     let start_timestamp = start_timestamp_str.parse()?;
-    Ok(ClusterMember::new(
+    let availability_zone = node_state.get(AVAILABILITY_ZONE_KEY).map(str::to_string);
+    let drain_status = node_state
+        .get(DRAIN_STATUS_KEY)
+        .map(|drain_status_str| drain_status_str.parse())
+        .transpose()?
+        .unwrap_or_default();
+    let mut member = ClusterMember::new(
         node_id.to_string(),
         start_timestamp,
         enabled_services,
         chitchat_node.gossip_public_address,
         grpc_advertise_addr,
         indexing_tasks,
-    ))
+    )
+    .with_availability_zone(availability_zone);
+    member.drain_status = drain_status;
+    member.ingest_lags = parse_ingest_lags(node_state, &chitchat_node.id);
+    Ok(member)
 }
 
 // Parses indexing task key into the pair (index_id, source_id).
@@ -214,6 +293,30 @@ pub(crate) fn parse_indexing_tasks(node_state: &NodeState, node_id: &str) -> Vec
         .collect()
 }
 
+/// Parses the ingest lags serialized in keys formatted as `INGEST_LAG_PREFIX:index_id`. Malformed
+/// keys and values are ignored, just warnings are emitted.
+pub(crate) fn parse_ingest_lags(node_state: &NodeState, node_id: &str) -> HashMap<String, u64> {
+    node_state
+        .iter_key_values(|key, _| key.starts_with(INGEST_LAG_PREFIX))
+        .filter_map(|(key, versioned_value)| {
+            let index_id_opt = key
+                .strip_prefix(INGEST_LAG_PREFIX)
+                .and_then(|key| key.strip_prefix(INDEXING_TASK_SEPARATOR));
+            match (index_id_opt, versioned_value.value.parse::<u64>()) {
+                (Some(index_id), Ok(ingest_lag)) => Some((index_id.to_string(), ingest_lag)),
+                _ => {
+                    warn!(
+                        node_id=%node_id,
+                        key=%key,
+                        "Malformatted ingest lag key and value on node."
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 fn parse_enabled_services_str(
     enabled_services_str: &str,
     node_id: &str,
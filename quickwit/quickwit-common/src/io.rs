@@ -141,6 +141,11 @@ impl IoControls {
         self
     }
 
+    /// Updates the throughput limit of these `IoControls` and of all their clones.
+    pub fn update_throughput_limit(&self, throughput: f64) {
+        self.throughput_limiter.set_speed_limit(throughput);
+    }
+
     pub fn set_bytes_counter(mut self, bytes_counter: IntCounter) -> Self {
         self.bytes_counter = bytes_counter;
         self
@@ -27,6 +27,7 @@ mod file_entry;
 pub mod fs;
 pub mod io;
 mod kill_switch;
+mod log_filter;
 pub mod metrics;
 pub mod net;
 mod progress;
@@ -52,6 +53,7 @@ pub use checklist::{
 pub use coolid::new_coolid;
 pub use file_entry::FileEntry;
 pub use kill_switch::KillSwitch;
+pub use log_filter::{reload_log_filter, set_log_filter_reloader};
 pub use progress::{Progress, ProtectedZoneGuard};
 use tracing::{error, info};
 
@@ -74,6 +76,15 @@ pub fn split_file(split_id: &str) -> String {
     format!("{split_id}.split")
 }
 
+/// Directory of the split files named after the hash of their content, relative to the index URI.
+pub const CONTENT_ADDRESSED_SPLIT_DIR: &str = "splits";
+
+/// Returns the path of a split file named after the hash of its content, relative to the index
+/// URI.
+pub fn content_addressed_split_file(content_hash: &str) -> String {
+    format!("{CONTENT_ADDRESSED_SPLIT_DIR}/{content_hash}.split")
+}
+
 pub fn get_from_env<T: FromStr + Debug>(key: &str, default_value: T) -> T {
     if let Ok(value_str) = std::env::var(key) {
         if let Ok(value) = T::from_str(&value_str) {
@@ -131,6 +142,28 @@ pub fn is_false(value: &bool) -> bool {
     !*value
 }
 
+/// Returns whether the index ID matches the pattern, in which `*` matches any sequence of
+/// characters.
+pub fn index_id_matches_pattern(index_id: &str, index_pattern: &str) -> bool {
+    let mut parts = index_pattern.split('*');
+    let prefix = parts.next().unwrap_or_default();
+    let Some(mut remaining) = index_id.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    // Without `*`, the pattern is the index ID itself.
+    let Some(suffix) = parts.pop() else {
+        return remaining.is_empty();
+    };
+    for part in parts {
+        let Some(part_start) = remaining.find(part) else {
+            return false;
+        };
+        remaining = &remaining[part_start + part.len()..];
+    }
+    remaining.len() >= suffix.len() && remaining.ends_with(suffix)
+}
+
 pub fn no_color() -> bool {
     matches!(env::var("NO_COLOR"), Ok(value) if !value.is_empty())
 }
@@ -190,6 +223,25 @@ mod tests {
         assert_eq!(super::get_from_env(TEST_KEY, 10), 10);
     }
 
+    #[test]
+    fn test_index_id_matches_pattern() {
+        assert!(index_id_matches_pattern("logs", "logs"));
+        assert!(!index_id_matches_pattern("logs-eu", "logs"));
+        assert!(index_id_matches_pattern("logs", "*"));
+        assert!(index_id_matches_pattern("logs-eu", "logs-*"));
+        assert!(index_id_matches_pattern("logs-", "logs-*"));
+        assert!(!index_id_matches_pattern("traces-eu", "logs-*"));
+        assert!(index_id_matches_pattern("app-logs", "*-logs"));
+        assert!(index_id_matches_pattern("app-logs-eu", "app-*-eu"));
+        assert!(index_id_matches_pattern("app-eu", "app*-eu"));
+        assert!(!index_id_matches_pattern("app-eu", "app-*-eu"));
+        assert!(index_id_matches_pattern("app-prod-logs-eu", "app-*-logs-*"));
+        assert!(!index_id_matches_pattern(
+            "app-prod-traces-eu",
+            "app-*-logs-*"
+        ));
+    }
+
     #[test]
     fn test_truncate_str() {
         assert_eq!(truncate_str("", 0), "");
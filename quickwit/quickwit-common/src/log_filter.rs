@@ -0,0 +1,65 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Changes the log filter of the process at runtime. The binary installs the reloader once the
+//! tracing subscriber is set up, and the node applies the `log_level` of its config with it.
+
+use anyhow::Context;
+use once_cell::sync::OnceCell;
+
+type LogFilterReloader = Box<dyn Fn(&str) -> anyhow::Result<()> + Send + Sync>;
+
+static LOG_FILTER_RELOADER: OnceCell<LogFilterReloader> = OnceCell::new();
+
+/// Installs the function replacing the log filter of the tracing subscriber. Only the first call
+/// has an effect.
+pub fn set_log_filter_reloader<F>(reloader: F)
+where F: Fn(&str) -> anyhow::Result<()> + Send + Sync + 'static {
+    let _ = LOG_FILTER_RELOADER.set(Box::new(reloader));
+}
+
+/// Replaces the log filter of the process with the given directives, e.g.
+/// `info,quickwit=debug`.
+pub fn reload_log_filter(directives: &str) -> anyhow::Result<()> {
+    let reloader = LOG_FILTER_RELOADER
+        .get()
+        .context("The log filter cannot be changed at runtime.")?;
+    reloader(directives).with_context(|| format!("Failed to apply log filter `{directives}`."))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_reload_log_filter() {
+        static DIRECTIVES: Mutex<String> = Mutex::new(String::new());
+
+        set_log_filter_reloader(|directives| {
+            anyhow::ensure!(!directives.is_empty(), "Empty log filter.");
+            *DIRECTIVES.lock().unwrap() = directives.to_string();
+            Ok(())
+        });
+        reload_log_filter("quickwit=debug").unwrap();
+        assert_eq!(*DIRECTIVES.lock().unwrap(), "quickwit=debug");
+        reload_log_filter("").unwrap_err();
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use humantime::{format_duration, parse_duration};
+use serde::{Deserialize, Serialize};
+
+use crate::{validate_identifier, ConfigFormat};
+
+/// Minimum interval between two evaluations of the same alert rule.
+const MIN_EVALUATION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of hits sent along with a notification.
+const MAX_NUM_SAMPLE_HITS: usize = 100;
+
+/// An alert rule is a standing query evaluated periodically over a sliding time window. When the
+/// number of documents matching the query meets the rule condition, a notification carrying the
+/// matched count and a sample of the hits is posted to the rule webhook.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "VersionedAlertRule")]
+#[serde(try_from = "VersionedAlertRule")]
+pub struct AlertRule {
+    pub rule_id: String,
+    pub description: Option<String>,
+    pub index_id: String,
+    pub query: String,
+    pub search_fields: Vec<String>,
+    pub window: Duration,
+    pub evaluation_interval: Duration,
+    pub condition: AlertCondition,
+    pub num_sample_hits: usize,
+    pub webhook: AlertWebhookConfig,
+    pub enabled: bool,
+}
+
+impl AlertRule {
+    fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("Alert rule ID", &self.rule_id)?;
+        validate_identifier("Index ID", &self.index_id)?;
+
+        if self.query.trim().is_empty() {
+            bail!(
+                "The query of alert rule `{}` must not be empty.",
+                self.rule_id
+            );
+        }
+        if self.window.is_zero() {
+            bail!(
+                "The time window of alert rule `{}` must be strictly positive.",
+                self.rule_id
+            );
+        }
+        if self.evaluation_interval < MIN_EVALUATION_INTERVAL {
+            bail!(
+                "The evaluation interval of alert rule `{}` must be at least {}.",
+                self.rule_id,
+                format_duration(MIN_EVALUATION_INTERVAL)
+            );
+        }
+        if self.num_sample_hits > MAX_NUM_SAMPLE_HITS {
+            bail!(
+                "Alert rule `{}` cannot send more than {MAX_NUM_SAMPLE_HITS} sample hits.",
+                self.rule_id
+            );
+        }
+        let webhook_url = &self.webhook.url;
+        if !webhook_url.starts_with("http://") && !webhook_url.starts_with("https://") {
+            bail!(
+                "The webhook URL `{webhook_url}` of alert rule `{}` must use the `http` or \
+                 `https` scheme.",
+                self.rule_id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Condition on the number of documents matching the query of an alert rule over its time window
+/// under which the rule fires.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AlertCondition {
+    pub comparison: AlertComparison,
+    pub threshold: u64,
+}
+
+impl AlertCondition {
+    /// Returns whether the number of matched documents meets the condition.
+    pub fn is_met(&self, num_hits: u64) -> bool {
+        match self.comparison {
+            AlertComparison::GreaterThan => num_hits > self.threshold,
+            AlertComparison::GreaterThanOrEqual => num_hits >= self.threshold,
+            AlertComparison::LessThan => num_hits < self.threshold,
+            AlertComparison::LessThanOrEqual => num_hits <= self.threshold,
+        }
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self.comparison {
+            AlertComparison::GreaterThan => ">",
+            AlertComparison::GreaterThanOrEqual => ">=",
+            AlertComparison::LessThan => "<",
+            AlertComparison::LessThanOrEqual => "<=",
+        };
+        write!(formatter, "num_hits {operator} {}", self.threshold)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub enum AlertComparison {
+    #[serde(rename = "gt")]
+    GreaterThan,
+    #[serde(rename = "gte")]
+    GreaterThanOrEqual,
+    #[serde(rename = "lt")]
+    LessThan,
+    #[serde(rename = "lte")]
+    LessThanOrEqual,
+}
+
+/// Endpoint the notifications of an alert rule are posted to.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AlertWebhookConfig {
+    pub url: String,
+    /// Additional headers sent with each notification, for instance to authenticate against
+    /// the receiving endpoint.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Parses and validates an [`AlertRule`] as supplied by a user with a given [`ConfigFormat`].
+pub fn load_alert_rule_from_user_config(
+    config_format: ConfigFormat,
+    config_content: &[u8],
+) -> anyhow::Result<AlertRule> {
+    let versioned_alert_rule: VersionedAlertRule = config_format.parse(config_content)?;
+    versioned_alert_rule.try_into()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(tag = "version")]
+pub(crate) enum VersionedAlertRule {
+    #[serde(rename = "0.4")]
+    V0_4(AlertRuleV0_4),
+}
+
+fn default_evaluation_interval() -> String {
+    "1m".to_string()
+}
+
+fn default_num_sample_hits() -> usize {
+    5
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRuleV0_4 {
+    pub rule_id: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub index_id: String,
+    pub query: String,
+    /// Fields searched when the query does not target a field explicitly. Defaults to the
+    /// default search fields of the index.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub search_fields: Vec<String>,
+    /// Time window ending at evaluation time over which the matching documents are counted,
+    /// expressed in a human-friendly way (`5m`, `1 hour`, ...). Requires the index to have a
+    /// timestamp field.
+    pub window: String,
+    /// Interval between two evaluations of the rule (`30s`, `5m`, ...).
+    #[serde(default = "default_evaluation_interval")]
+    pub evaluation_interval: String,
+    pub condition: AlertCondition,
+    /// Number of matching documents sent along with the notification.
+    #[serde(default = "default_num_sample_hits")]
+    pub num_sample_hits: usize,
+    pub webhook: AlertWebhookConfig,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl From<AlertRule> for VersionedAlertRule {
+    fn from(alert_rule: AlertRule) -> Self {
+        VersionedAlertRule::V0_4(AlertRuleV0_4 {
+            rule_id: alert_rule.rule_id,
+            description: alert_rule.description,
+            index_id: alert_rule.index_id,
+            query: alert_rule.query,
+            search_fields: alert_rule.search_fields,
+            window: format_duration(alert_rule.window).to_string(),
+            evaluation_interval: format_duration(alert_rule.evaluation_interval).to_string(),
+            condition: alert_rule.condition,
+            num_sample_hits: alert_rule.num_sample_hits,
+            webhook: alert_rule.webhook,
+            enabled: alert_rule.enabled,
+        })
+    }
+}
+
+impl TryFrom<VersionedAlertRule> for AlertRule {
+    type Error = anyhow::Error;
+
+    fn try_from(versioned_alert_rule: VersionedAlertRule) -> anyhow::Result<Self> {
+        let v0_4 = match versioned_alert_rule {
+            VersionedAlertRule::V0_4(v0_4) => v0_4,
+        };
+        let window = parse_duration(&v0_4.window)
+            .with_context(|| format!("Failed to parse alert rule window `{}`.", v0_4.window))?;
+        let evaluation_interval = parse_duration(&v0_4.evaluation_interval).with_context(|| {
+            format!(
+                "Failed to parse alert rule evaluation interval `{}`.",
+                v0_4.evaluation_interval
+            )
+        })?;
+        let alert_rule = AlertRule {
+            rule_id: v0_4.rule_id,
+            description: v0_4.description,
+            index_id: v0_4.index_id,
+            query: v0_4.query,
+            search_fields: v0_4.search_fields,
+            window,
+            evaluation_interval,
+            condition: v0_4.condition,
+            num_sample_hits: v0_4.num_sample_hits,
+            webhook: v0_4.webhook,
+            enabled: v0_4.enabled,
+        };
+        alert_rule.validate()?;
+        Ok(alert_rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_rule_serde() {
+        let alert_rule_yaml = r#"
+            version: 0.4
+            rule_id: too-many-errors
+            index_id: app-logs
+            query: "severity_text:ERROR"
+            window: 5m
+            condition:
+              comparison: gt
+              threshold: 100
+            webhook:
+              url: https://hooks.example.com/alerts
+              headers:
+                Authorization: Bearer my-token
+        "#;
+        let alert_rule =
+            load_alert_rule_from_user_config(ConfigFormat::Yaml, alert_rule_yaml.as_bytes())
+                .unwrap();
+        assert_eq!(alert_rule.rule_id, "too-many-errors");
+        assert_eq!(alert_rule.window, Duration::from_secs(300));
+        assert_eq!(alert_rule.evaluation_interval, Duration::from_secs(60));
+        assert_eq!(alert_rule.num_sample_hits, 5);
+        assert!(alert_rule.enabled);
+        assert_eq!(
+            alert_rule.webhook.headers.get("Authorization").unwrap(),
+            "Bearer my-token"
+        );
+        let alert_rule_json = serde_json::to_string(&alert_rule).unwrap();
+        let deserialized_alert_rule: AlertRule = serde_json::from_str(&alert_rule_json).unwrap();
+        assert_eq!(deserialized_alert_rule, alert_rule);
+    }
+
+    #[test]
+    fn test_alert_rule_validation() {
+        let alert_rule_yaml = r#"
+            version: 0.4
+            rule_id: too-many-errors
+            index_id: app-logs
+            query: "severity_text:ERROR"
+            window: 5m
+            evaluation_interval: 1s
+            condition:
+              comparison: gt
+              threshold: 100
+            webhook:
+              url: https://hooks.example.com/alerts
+        "#;
+        let error =
+            load_alert_rule_from_user_config(ConfigFormat::Yaml, alert_rule_yaml.as_bytes())
+                .unwrap_err();
+        assert!(error.to_string().contains("must be at least 10s"));
+
+        let alert_rule_yaml = r#"
+            version: 0.4
+            rule_id: too-many-errors
+            index_id: app-logs
+            query: "severity_text:ERROR"
+            window: 5m
+            condition:
+              comparison: gt
+              threshold: 100
+            webhook:
+              url: ftp://hooks.example.com/alerts
+        "#;
+        let error =
+            load_alert_rule_from_user_config(ConfigFormat::Yaml, alert_rule_yaml.as_bytes())
+                .unwrap_err();
+        assert!(error.to_string().contains("must use the `http` or `https`"));
+    }
+
+    #[test]
+    fn test_alert_condition_is_met() {
+        let condition = AlertCondition {
+            comparison: AlertComparison::GreaterThan,
+            threshold: 10,
+        };
+        assert!(!condition.is_met(10));
+        assert!(condition.is_met(11));
+
+        let condition = AlertCondition {
+            comparison: AlertComparison::LessThanOrEqual,
+            threshold: 0,
+        };
+        assert!(condition.is_met(0));
+        assert!(!condition.is_met(1));
+        assert_eq!(condition.to_string(), "num_hits <= 0");
+    }
+}
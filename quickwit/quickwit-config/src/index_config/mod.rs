@@ -19,24 +19,26 @@
 
 pub(crate) mod serialize;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use byte_unit::Byte;
 use chrono::Utc;
 use cron::Schedule;
 use humantime::parse_duration;
+use quickwit_common::is_false;
 use quickwit_common::uri::Uri;
 use quickwit_doc_mapper::{
-    DefaultDocMapper, DefaultDocMapperBuilder, DocMapper, FieldMappingEntry, ModeType,
-    QuickwitJsonOptions,
+    DefaultDocMapper, DefaultDocMapperBuilder, DocMapper, DynamicTypeHint, FieldMappingEntry,
+    ModeType, QueryOperator, QuickwitJsonOptions, TokenizerEntry,
 };
 use serde::{Deserialize, Serialize};
-pub use serialize::load_index_config_from_user_config;
+use serde_json::Value as JsonValue;
+pub use serialize::{load_index_config_from_user_config, validate_index_config};
 
 use crate::index_config::serialize::VersionedIndexConfig;
 use crate::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
@@ -62,20 +64,180 @@ pub struct DocMapping {
     #[schema(value_type = Vec<String>)]
     #[serde(default)]
     pub tag_fields: BTreeSet<String>,
+    /// Numeric fast fields whose range of values is recorded in the split metadata, so that the
+    /// searches can skip the splits that cannot match their range clauses.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub range_pruning_fields: BTreeSet<String>,
+    /// High-cardinality text fields whose terms are recorded in a bloom filter in the split
+    /// metadata, so that the searches can skip the splits that cannot hold their exact terms.
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub bloom_filter_fields: BTreeSet<String>,
     #[serde(default)]
     pub store_source: bool,
     #[serde(default)]
     pub timestamp_field: Option<String>,
+    /// Name of the field uniquely identifying a document. Used in conjunction with
+    /// `indexing_settings.deduplication_window_secs` to drop duplicate documents.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_unique_id_field: Option<String>,
+    /// Accepts upserts through the ingest API. Requires `doc_unique_id_field` and
+    /// `timestamp_field`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub enable_upserts: bool,
+    /// Alternate names of the fields, mapping each alias to the path of the field it refers to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_aliases: BTreeMap<String, String>,
+    /// Custom tokenizers which the text and JSON fields can refer to.
+    #[schema(value_type = Vec<Object>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokenizers: Vec<TokenizerEntry>,
     #[serde(default)]
     pub mode: ModeType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dynamic_mapping: Option<QuickwitJsonOptions>,
+    /// Types enforced on the dynamically mapped fields whose path matches a pattern.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_type_hints: Vec<DynamicTypeHint>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub partition_key: Option<String>,
     #[schema(value_type = u32)]
     #[serde(default = "DefaultDocMapper::default_max_num_partitions")]
     pub max_num_partitions: NonZeroU32,
+    /// Version of the doc mapping, incremented by the metastore every time the doc mapping of the
+    /// index is updated. Splits record the version of the doc mapping they were indexed with.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub doc_mapping_version: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// ID of the internal index holding the query log. IDs starting with an underscore are reserved
+/// for internal indexes.
+pub const QUERY_LOG_INDEX_ID: &str = "_quickwit_queries";
+
+/// Field mapping parameters that only affect the splits indexed after a doc mapping update and
+/// can therefore be changed freely.
+const UPDATABLE_FIELD_MAPPING_PARAMS: [&str; 7] = [
+    "description",
+    "tokenizer",
+    "record",
+    "fieldnorms",
+    "stored",
+    "default_value",
+    "on_missing",
+];
+
+/// Checks that `new_doc_mapping` is an additive evolution of `current_doc_mapping`, i.e. that the
+/// splits indexed with the current doc mapping can still be searched with the new one.
+///
+/// Fields can be added or removed (deprecated), custom tokenizers can be added, and the
+/// parameters listed in [`UPDATABLE_FIELD_MAPPING_PARAMS`] can be changed. Changing the type or
+/// the indexing parameters of an existing field, the timestamp field, the doc unique id field,
+/// the mode, or an existing custom tokenizer is rejected.
+pub fn validate_doc_mapping_update(
+    current_doc_mapping: &DocMapping,
+    new_doc_mapping: &DocMapping,
+) -> anyhow::Result<()> {
+    if current_doc_mapping.timestamp_field != new_doc_mapping.timestamp_field {
+        bail!("The timestamp field of an index cannot be changed.");
+    }
+    if current_doc_mapping.doc_unique_id_field != new_doc_mapping.doc_unique_id_field {
+        bail!("The doc unique id field of an index cannot be changed.");
+    }
+    if current_doc_mapping.mode != new_doc_mapping.mode {
+        bail!("The mode of an index cannot be changed.");
+    }
+    for current_tokenizer in &current_doc_mapping.tokenizers {
+        if !new_doc_mapping.tokenizers.contains(current_tokenizer) {
+            bail!(
+                "Custom tokenizer `{}` cannot be modified or removed.",
+                current_tokenizer.name
+            );
+        }
+    }
+    let current_field_mappings = field_mappings_to_json(&current_doc_mapping.field_mappings)?;
+    let new_field_mappings = field_mappings_to_json(&new_doc_mapping.field_mappings)?;
+    validate_field_mappings_update(&current_field_mappings, &new_field_mappings, "")
+}
+
+fn field_mappings_to_json(field_mappings: &[FieldMappingEntry]) -> anyhow::Result<Vec<JsonValue>> {
+    field_mappings
+        .iter()
+        .map(|field_mapping| {
+            serde_json::to_value(field_mapping).context("Failed to serialize field mapping.")
+        })
+        .collect()
+}
+
+fn validate_field_mappings_update(
+    current_field_mappings: &[JsonValue],
+    new_field_mappings: &[JsonValue],
+    path_prefix: &str,
+) -> anyhow::Result<()> {
+    for current_field_mapping in current_field_mappings {
+        let field_name = current_field_mapping["name"].as_str().unwrap_or_default();
+        let Some(new_field_mapping) = new_field_mappings
+            .iter()
+            .find(|new_field_mapping| new_field_mapping["name"] == current_field_mapping["name"])
+        else {
+            // The field is deprecated.
+            continue;
+        };
+        let field_path = format!("{path_prefix}{field_name}");
+
+        if current_field_mapping["type"] != new_field_mapping["type"] {
+            bail!("The type of field `{field_path}` cannot be changed.");
+        }
+        let (Some(current_params), Some(new_params)) = (
+            current_field_mapping.as_object(),
+            new_field_mapping.as_object(),
+        ) else {
+            continue;
+        };
+        for param_name in current_params.keys().chain(new_params.keys()) {
+            if param_name == "name"
+                || param_name == "type"
+                || UPDATABLE_FIELD_MAPPING_PARAMS.contains(&param_name.as_str())
+            {
+                continue;
+            }
+            let current_param = current_params.get(param_name);
+            let new_param = new_params.get(param_name);
+
+            if param_name == "field_mappings" {
+                let empty_field_mappings = Vec::new();
+                let current_sub_field_mappings = current_param
+                    .and_then(JsonValue::as_array)
+                    .unwrap_or(&empty_field_mappings);
+                let new_sub_field_mappings = new_param
+                    .and_then(JsonValue::as_array)
+                    .unwrap_or(&empty_field_mappings);
+                validate_field_mappings_update(
+                    current_sub_field_mappings,
+                    new_sub_field_mappings,
+                    &format!("{field_path}."),
+                )?;
+                continue;
+            }
+            if current_param != new_param {
+                bail!("Parameter `{param_name}` of field `{field_path}` cannot be changed.");
+            }
+        }
+    }
+    Ok(())
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
@@ -129,6 +291,12 @@ pub struct IndexingSettings {
     #[schema(default = 60)]
     #[serde(default = "IndexingSettings::default_commit_timeout_secs")]
     pub commit_timeout_secs: usize,
+    /// When set, the commit timeout adapts to the ingestion rate: the indexer commits as soon as
+    /// it has not received any document for `min_commit_timeout_secs` seconds, and keeps
+    /// accumulating documents up to `commit_timeout_secs` while documents keep flowing in.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_commit_timeout_secs: Option<usize>,
     #[schema(default = 8)]
     #[serde(default = "IndexingSettings::default_docstore_compression_level")]
     pub docstore_compression_level: i32,
@@ -146,6 +314,38 @@ pub struct IndexingSettings {
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
     pub resources: IndexingResources,
+    /// Number of split builders working in parallel within an indexing pipeline. Documents are
+    /// routed to the split builders by hash of `split_builder_routing_field`, and the splits they
+    /// produce are published together under a single checkpoint update.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_split_builders: Option<usize>,
+    /// Field whose value is hashed to route documents to split builders. Documents are routed in
+    /// a round-robin fashion when it is not set or missing from a document.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_builder_routing_field: Option<String>,
+    /// When set, the indexer drops the documents whose `doc_unique_id_field` value was already
+    /// seen by the pipeline within the last `deduplication_window_secs` seconds.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deduplication_window_secs: Option<usize>,
+    /// Custom key-value metadata attached to every split produced by the indexing pipelines of
+    /// the index, e.g. the version of the pipeline. Search requests can return it along with the
+    /// hits with the `include_split_metadata` parameter.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub split_metadata: BTreeMap<String, String>,
+    /// When set, the documents rejected by the indexing pipelines because they could not be
+    /// parsed, transformed, or lacked a required field are routed to this destination along with
+    /// the reason of their rejection.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_letter: Option<DeadLetterConfig>,
+    /// Naming scheme of the split files in the storage of the index.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "SplitStorageLayout::is_split_id")]
+    pub split_storage_layout: SplitStorageLayout,
 }
 
 impl IndexingSettings {
@@ -153,6 +353,20 @@ impl IndexingSettings {
         Duration::from_secs(self.commit_timeout_secs as u64)
     }
 
+    pub fn num_split_builders(&self) -> usize {
+        self.num_split_builders.unwrap_or(1)
+    }
+
+    pub fn min_commit_timeout(&self) -> Option<Duration> {
+        self.min_commit_timeout_secs
+            .map(|min_commit_timeout_secs| Duration::from_secs(min_commit_timeout_secs as u64))
+    }
+
+    pub fn deduplication_window(&self) -> Option<Duration> {
+        self.deduplication_window_secs
+            .map(|deduplication_window_secs| Duration::from_secs(deduplication_window_secs as u64))
+    }
+
     fn default_commit_timeout_secs() -> usize {
         60
     }
@@ -182,20 +396,115 @@ impl Default for IndexingSettings {
     fn default() -> Self {
         Self {
             commit_timeout_secs: Self::default_commit_timeout_secs(),
+            min_commit_timeout_secs: None,
             docstore_blocksize: Self::default_docstore_blocksize(),
             docstore_compression_level: Self::default_docstore_compression_level(),
             split_num_docs_target: Self::default_split_num_docs_target(),
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
+            num_split_builders: None,
+            split_builder_routing_field: None,
+            deduplication_window_secs: None,
+            split_metadata: BTreeMap::new(),
+            dead_letter: None,
+            split_storage_layout: SplitStorageLayout::default(),
         }
     }
 }
 
+/// Naming scheme of the split files in the storage of an index.
+#[derive(
+    Clone, Copy, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitStorageLayout {
+    /// The split files are named after their split ID: `{split_id}.split`.
+    #[default]
+    SplitId,
+    /// The split files are named after the SHA-256 hash of their content:
+    /// `splits/{content_hash}.split`. A file is never overwritten with different content, so
+    /// interrupted uploads and retries cannot corrupt a published split, and identical splits
+    /// share a single file.
+    ContentAddressed,
+}
+
+impl SplitStorageLayout {
+    fn is_split_id(&self) -> bool {
+        *self == SplitStorageLayout::SplitId
+    }
+}
+
+/// Destination of the documents rejected by the indexing pipelines of an index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DeadLetterConfig {
+    /// The rejected documents are ingested into another index through the ingest API.
+    Index { index_id: String },
+    /// The rejected documents are written as NDJSON files under a storage prefix.
+    Storage {
+        #[schema(value_type = String)]
+        uri: Uri,
+    },
+    /// The rejected documents are produced to a Kafka topic.
+    Kafka {
+        topic: String,
+        /// Kafka client configuration parameters.
+        #[schema(value_type = Object)]
+        #[serde(default = "serde_json::Value::default")]
+        #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+        client_params: JsonValue,
+    },
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct SearchSettings {
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Allows the queries to contain regex clauses, which scan the term dictionaries of the
+    /// fields they target and can be expensive.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub enable_regex_queries: bool,
+    /// Operator combining the clauses of the queries that are not joined by an explicit `AND` or
+    /// `OR`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "QueryOperator::is_and")]
+    pub default_operator: QueryOperator,
+    /// Maximum number of clauses of a query. The queries exceeding it are rejected.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_query_clauses: Option<usize>,
+    /// Maximum number of terms a regex or fuzzy clause may match in a split segment. It can only
+    /// lower the `max_term_expansions` limit of the searchers.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_term_expansions: Option<usize>,
+    /// Field the hits are sorted by when the search request does not specify any sort, prefixed
+    /// with `-` for a descending order.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_sort_by_field: Option<String>,
+}
+
+/// Action applied to the splits evicted by a retention policy.
+#[derive(
+    Clone, Copy, Debug, Default, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAction {
+    /// The evicted splits are marked for deletion and eventually garbage collected.
+    #[default]
+    Delete,
+    /// The evicted splits are moved to the archive storage of the index. They are no longer
+    /// searchable but can be rehydrated later.
+    Archive,
+}
+
+impl RetentionAction {
+    fn is_delete(&self) -> bool {
+        *self == RetentionAction::Delete
+    }
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -204,7 +513,22 @@ pub struct RetentionPolicy {
     /// Duration of time for which the splits should be retained, expressed in a human-friendly way
     /// (`1 hour`, `3 days`, `a week`, ...).
     #[serde(rename = "period")]
-    retention_period: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retention_period: Option<String>,
+
+    /// Maximum total size of the published splits of the index, expressed in a human-friendly way
+    /// (`500 GB`, `2 TB`, ...). Beyond this size, the oldest splits are evicted first.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_size: Option<Byte>,
+
+    /// Maximum number of published splits of the index. Beyond this number, the oldest splits
+    /// are evicted first.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_num_splits: Option<usize>,
 
     /// Defines the frequency at which the retention policy is evaluated and applied, expressed in
     /// a human-friendly way (`hourly`, `daily`, ...) or as a cron expression (`0 0 * * * *`,
@@ -212,27 +536,85 @@ pub struct RetentionPolicy {
     #[serde(default = "RetentionPolicy::default_schedule")]
     #[serde(rename = "schedule")]
     evaluation_schedule: String,
+
+    /// Action applied to the evicted splits: `delete` (default) or `archive`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "RetentionAction::is_delete")]
+    action: RetentionAction,
+
+    /// URI of the storage to which the evicted splits are moved when the action is `archive`, and
+    /// from which they are rehydrated.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive_uri: Option<Uri>,
 }
 
 impl RetentionPolicy {
     pub fn new(retention_period: String, evaluation_schedule: String) -> Self {
         Self {
-            retention_period,
+            retention_period: Some(retention_period),
+            max_size: None,
+            max_num_splits: None,
+            evaluation_schedule,
+            action: RetentionAction::Delete,
+            archive_uri: None,
+        }
+    }
+
+    /// Creates a retention policy that only bounds the total size and/or number of splits of an
+    /// index.
+    pub fn with_size_limits(
+        max_size: Option<Byte>,
+        max_num_splits: Option<usize>,
+        evaluation_schedule: String,
+    ) -> Self {
+        Self {
+            retention_period: None,
+            max_size,
+            max_num_splits,
             evaluation_schedule,
+            action: RetentionAction::Delete,
+            archive_uri: None,
         }
     }
 
+    /// Makes the policy move the evicted splits to the storage at `archive_uri` instead of
+    /// deleting them.
+    pub fn with_archive_action(mut self, archive_uri: Uri) -> Self {
+        self.action = RetentionAction::Archive;
+        self.archive_uri = Some(archive_uri);
+        self
+    }
+
     fn default_schedule() -> String {
         "hourly".to_string()
     }
 
-    pub fn retention_period(&self) -> anyhow::Result<Duration> {
-        parse_duration(&self.retention_period).with_context(|| {
-            format!(
-                "Failed to parse retention period `{}`.",
-                self.retention_period
-            )
-        })
+    pub fn retention_period(&self) -> anyhow::Result<Option<Duration>> {
+        let Some(retention_period) = &self.retention_period else {
+            return Ok(None);
+        };
+        let retention_period = parse_duration(retention_period)
+            .with_context(|| format!("Failed to parse retention period `{retention_period}`."))?;
+        Ok(Some(retention_period))
+    }
+
+    /// Returns the maximum total size of the published splits in bytes, if any.
+    pub fn max_size_in_bytes(&self) -> Option<u64> {
+        self.max_size.map(|max_size| max_size.get_bytes() as u64)
+    }
+
+    pub fn max_num_splits(&self) -> Option<usize> {
+        self.max_num_splits
+    }
+
+    pub fn action(&self) -> RetentionAction {
+        self.action
+    }
+
+    pub fn archive_uri(&self) -> Option<&Uri> {
+        self.archive_uri.as_ref()
     }
 
     pub fn evaluation_schedule(&self) -> anyhow::Result<Schedule> {
@@ -259,12 +641,219 @@ impl RetentionPolicy {
     }
 
     fn validate(&self) -> anyhow::Result<()> {
+        if self.retention_period.is_none()
+            && self.max_size.is_none()
+            && self.max_num_splits.is_none()
+        {
+            anyhow::bail!(
+                "The retention policy must define at least one of `period`, `max_size`, or \
+                 `max_num_splits`."
+            );
+        }
+        if self.action == RetentionAction::Archive && self.archive_uri.is_none() {
+            anyhow::bail!("The retention policy action `archive` requires an `archive_uri`.");
+        }
         self.retention_period()?;
         self.evaluation_schedule()?;
         Ok(())
     }
 }
 
+/// Garbage collection settings of an index. Unset settings fall back to the janitor defaults.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct GarbageCollectionSettings {
+    /// Duration for which the splits marked for deletion are kept before their files are deleted,
+    /// expressed in a human-friendly way (`30 seconds`, `2 minutes`, `7 days`, ...).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deletion_grace_period: Option<String>,
+
+    /// Minimum duration between two garbage collection runs on the index, expressed in a
+    /// human-friendly way (`1 minute`, `1 hour`, ...). It cannot be lower than one minute.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_interval: Option<String>,
+
+    /// Maximum number of batches of split files deleted concurrently.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_deletions: Option<usize>,
+
+    /// Pauses the garbage collection of the index: the splits marked for deletion and their
+    /// files are kept until garbage collection is resumed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub paused: bool,
+}
+
+impl GarbageCollectionSettings {
+    /// Minimum interval between two garbage collection runs, which is also the frequency at which
+    /// the janitor garbage collector wakes up.
+    pub const MIN_RUN_INTERVAL: Duration = Duration::from_secs(60);
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn deletion_grace_period(&self) -> anyhow::Result<Option<Duration>> {
+        let Some(deletion_grace_period) = &self.deletion_grace_period else {
+            return Ok(None);
+        };
+        let deletion_grace_period = parse_duration(deletion_grace_period).with_context(|| {
+            format!("Failed to parse deletion grace period `{deletion_grace_period}`.")
+        })?;
+        Ok(Some(deletion_grace_period))
+    }
+
+    pub fn run_interval(&self) -> anyhow::Result<Option<Duration>> {
+        let Some(run_interval) = &self.run_interval else {
+            return Ok(None);
+        };
+        let run_interval = parse_duration(run_interval)
+            .with_context(|| format!("Failed to parse GC run interval `{run_interval}`."))?;
+        Ok(Some(run_interval))
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        self.deletion_grace_period()?;
+
+        if let Some(run_interval) = self.run_interval()? {
+            if run_interval < Self::MIN_RUN_INTERVAL {
+                bail!(
+                    "The garbage collection run interval must be greater than or equal to 1 \
+                     minute."
+                );
+            }
+        }
+        if self.max_concurrent_deletions == Some(0) {
+            bail!("`max_concurrent_deletions` must be strictly positive.");
+        }
+        Ok(())
+    }
+}
+
+/// Function aggregating the values of a source field over the documents of a rollup group.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RollupFunction {
+    /// Number of documents of the group. It does not read any field.
+    Count,
+    Sum,
+    Min,
+    Max,
+}
+
+/// Metric computed for every rollup group and stored in the field `name` of the rollup index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RollupMetric {
+    pub name: String,
+    pub function: RollupFunction,
+    /// Numeric fast field of the source index the metric aggregates. Required by every function
+    /// but `count`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+/// Declares an index as the rollup of another index: the documents of the source index are
+/// periodically grouped by time interval and `group_by` fields, and each group is aggregated into
+/// a single document of the rollup index. Only the functions whose results can themselves be
+/// re-aggregated are supported, averages are obtained by dividing a sum by a count.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RollupConfig {
+    pub source_index_id: String,
+
+    /// Width of the time intervals the documents are grouped by, expressed in a human-friendly way
+    /// (`1 minute`, `1 hour`, ...). It must be a whole number of seconds.
+    pub interval: String,
+
+    /// Fast fields of the source index the documents are grouped by. The rollup index must
+    /// declare fields with the same names.
+    #[serde(default)]
+    pub group_by: Vec<String>,
+
+    pub metrics: Vec<RollupMetric>,
+
+    /// Duration waited after the end of an interval before rolling it up, so that the documents
+    /// of the interval have been published in the source index. Documents published later are
+    /// not reflected in the rollup.
+    #[serde(default = "RollupConfig::default_delay")]
+    pub delay: String,
+
+    /// Maximum number of distinct values of each `group_by` field within an interval.
+    #[serde(default = "RollupConfig::default_max_groups")]
+    pub max_groups: u32,
+}
+
+impl RollupConfig {
+    fn default_delay() -> String {
+        "1 minute".to_string()
+    }
+
+    fn default_max_groups() -> u32 {
+        10_000
+    }
+
+    pub fn interval(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.interval)
+            .with_context(|| format!("Failed to parse rollup interval `{}`.", self.interval))
+    }
+
+    pub fn delay(&self) -> anyhow::Result<Duration> {
+        parse_duration(&self.delay)
+            .with_context(|| format!("Failed to parse rollup delay `{}`.", self.delay))
+    }
+
+    /// Validates the rollup against the doc mapper of the rollup index `index_id`.
+    fn validate(&self, index_id: &str, doc_mapper: &dyn DocMapper) -> anyhow::Result<()> {
+        if self.source_index_id == index_id {
+            bail!("Index `{index_id}` cannot be the rollup of itself.");
+        }
+        let interval = self.interval()?;
+
+        if interval.as_secs() == 0 || interval.subsec_nanos() != 0 {
+            bail!("The rollup interval must be a whole number of seconds.");
+        }
+        self.delay()?;
+
+        if self.max_groups == 0 {
+            bail!("`max_groups` must be strictly positive.");
+        }
+        if self.metrics.is_empty() {
+            bail!("The rollup must declare at least one metric.");
+        }
+        if doc_mapper.timestamp_field_name().is_none() {
+            bail!("The rollup index must declare a timestamp field.");
+        }
+        let mut output_field_names = HashSet::new();
+
+        for field_name in self
+            .group_by
+            .iter()
+            .chain(self.metrics.iter().map(|metric| &metric.name))
+        {
+            if !output_field_names.insert(field_name.as_str()) {
+                bail!("Rollup field `{field_name}` is declared more than once.");
+            }
+            if doc_mapper.schema().get_field(field_name).is_err() {
+                bail!("Rollup field `{field_name}` is not declared in the doc mapping.");
+            }
+        }
+        for metric in &self.metrics {
+            if metric.function != RollupFunction::Count && metric.field.is_none() {
+                bail!(
+                    "Rollup metric `{}` must specify the field it aggregates.",
+                    metric.name
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Prepends an `@` char at the start of the cron expression if necessary:
 /// `hourly` -> `@hourly`
 fn prepend_at_char(schedule: &str) -> String {
@@ -290,9 +879,22 @@ pub struct IndexConfig {
     pub indexing_settings: IndexingSettings,
     pub search_settings: SearchSettings,
     pub retention_policy: Option<RetentionPolicy>,
+    pub garbage_collection: GarbageCollectionSettings,
+    /// URI of the metadata snapshot the index was mounted from. Mounted indexes are read-only:
+    /// their splits live in another cluster's storage and are never indexed, merged, or
+    /// deleted by this cluster.
+    pub mounted_from: Option<Uri>,
+    /// Makes the index the rollup of another index, maintained by the searchers.
+    pub rollup: Option<RollupConfig>,
 }
 
 impl IndexConfig {
+    /// Returns whether the index was mounted from an external metadata snapshot and is therefore
+    /// read-only.
+    pub fn is_mounted(&self) -> bool {
+        self.mounted_from.is_some()
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     pub fn for_test(index_id: &str, index_uri: &str) -> Self {
         let index_uri = Uri::from_str(index_uri).unwrap();
@@ -366,6 +968,8 @@ impl IndexConfig {
                 r#"attributes.server"#.to_string(),
                 r#"attributes.server\.status"#.to_string(),
             ],
+            enable_regex_queries: false,
+            ..Default::default()
         };
         IndexConfig {
             index_id: index_id.to_string(),
@@ -374,6 +978,9 @@ impl IndexConfig {
             indexing_settings,
             search_settings,
             retention_policy: Default::default(),
+            garbage_collection: Default::default(),
+            mounted_from: None,
+            rollup: None,
         }
     }
 }
@@ -424,12 +1031,20 @@ impl TestableForRegression for IndexConfig {
                 .into_iter()
                 .map(|tag_field| tag_field.to_string())
                 .collect::<BTreeSet<String>>(),
+            range_pruning_fields: BTreeSet::new(),
+            bloom_filter_fields: BTreeSet::new(),
             store_source: true,
             mode: ModeType::Dynamic,
             dynamic_mapping: None,
+            dynamic_type_hints: Vec::new(),
             partition_key: Some("tenant".to_string()),
             max_num_partitions: NonZeroU32::new(100).unwrap(),
             timestamp_field: Some("timestamp".to_string()),
+            doc_unique_id_field: None,
+            enable_upserts: false,
+            field_aliases: BTreeMap::new(),
+            tokenizers: Vec::new(),
+            doc_mapping_version: 0,
         };
         let retention_policy = Some(RetentionPolicy::new(
             "90 days".to_string(),
@@ -454,6 +1069,8 @@ impl TestableForRegression for IndexConfig {
         };
         let search_settings = SearchSettings {
             default_search_fields: vec!["message".to_string()],
+            enable_regex_queries: false,
+            ..Default::default()
         };
         IndexConfig {
             index_id: "my-index".to_string(),
@@ -462,6 +1079,9 @@ impl TestableForRegression for IndexConfig {
             indexing_settings,
             retention_policy,
             search_settings,
+            garbage_collection: GarbageCollectionSettings::default(),
+            mounted_from: None,
+            rollup: None,
         }
     }
 
@@ -499,13 +1119,26 @@ pub fn build_doc_mapper(
     let builder = DefaultDocMapperBuilder {
         store_source: doc_mapping.store_source,
         default_search_fields: search_settings.default_search_fields.clone(),
+        enable_regex_queries: search_settings.enable_regex_queries,
+        default_operator: search_settings.default_operator,
+        max_query_clauses: search_settings.max_query_clauses,
+        max_term_expansions: search_settings.max_term_expansions,
+        default_sort_by_field: search_settings.default_sort_by_field.clone(),
         timestamp_field: doc_mapping.timestamp_field.clone(),
+        doc_unique_id_field: doc_mapping.doc_unique_id_field.clone(),
+        enable_upserts: doc_mapping.enable_upserts,
         field_mappings: doc_mapping.field_mappings.clone(),
+        field_aliases: doc_mapping.field_aliases.clone(),
+        tokenizers: doc_mapping.tokenizers.clone(),
         tag_fields: doc_mapping.tag_fields.iter().cloned().collect(),
+        range_pruning_fields: doc_mapping.range_pruning_fields.iter().cloned().collect(),
+        bloom_filter_fields: doc_mapping.bloom_filter_fields.iter().cloned().collect(),
         mode: doc_mapping.mode,
         dynamic_mapping: doc_mapping.dynamic_mapping.clone(),
+        dynamic_type_hints: doc_mapping.dynamic_type_hints.clone(),
         partition_key: doc_mapping.partition_key.clone(),
         max_num_partitions: doc_mapping.max_num_partitions,
+        doc_mapping_version: doc_mapping.doc_mapping_version,
     };
     Ok(Arc::new(builder.try_build()?))
 }
@@ -557,8 +1190,12 @@ mod tests {
             vec!["tenant_id".to_string()]
         );
         let expected_retention_policy = RetentionPolicy {
-            retention_period: "90 days".to_string(),
+            retention_period: Some("90 days".to_string()),
+            max_size: None,
+            max_num_splits: None,
             evaluation_schedule: "daily".to_string(),
+            action: RetentionAction::Delete,
+            archive_uri: None,
         };
         assert_eq!(
             index_config.retention_policy.unwrap(),
@@ -591,6 +1228,8 @@ mod tests {
             index_config.search_settings,
             SearchSettings {
                 default_search_fields: vec!["severity_text".to_string(), "body".to_string()],
+                enable_regex_queries: false,
+                ..Default::default()
             }
         );
     }
@@ -633,6 +1272,8 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    enable_regex_queries: false,
+                    ..Default::default()
                 }
             );
         }
@@ -667,6 +1308,8 @@ mod tests {
                 index_config.search_settings,
                 SearchSettings {
                     default_search_fields: vec!["body".to_string()],
+                    enable_regex_queries: false,
+                    ..Default::default()
                 }
             );
         }
@@ -701,6 +1344,53 @@ mod tests {
         assert_eq!(minimal_config.doc_mapping.mode, ModeType::Lenient);
     }
 
+    #[test]
+    fn test_index_config_with_search_settings() {
+        let config_yaml = r#"
+            version: 0.4
+            index_id: hdfs-logs
+            index_uri: "s3://my-index"
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+                - name: timestamp
+                  type: datetime
+                  fast: true
+            search_settings:
+              default_search_fields: [body]
+              default_operator: or
+              max_query_clauses: 64
+              max_term_expansions: 100
+              default_sort_by_field: -timestamp
+        "#;
+        let index_config: IndexConfig = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://my-index"),
+        )
+        .unwrap();
+        assert_eq!(
+            index_config.search_settings,
+            SearchSettings {
+                default_search_fields: vec!["body".to_string()],
+                enable_regex_queries: false,
+                default_operator: QueryOperator::Or,
+                max_query_clauses: Some(64),
+                max_term_expansions: Some(100),
+                default_sort_by_field: Some("-timestamp".to_string()),
+            }
+        );
+        let invalid_config_yaml = config_yaml.replace("-timestamp", "-body");
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            invalid_config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://my-index"),
+        )
+        .unwrap_err();
+        assert!(format!("{error:#}").contains("default_sort_by_field"));
+    }
+
     #[test]
     fn test_index_config_with_malformed_maturation_duration() {
         let config_yaml = r#"
@@ -726,11 +1416,88 @@ mod tests {
             .contains("Failed to parse human-readable duration `x`"));
     }
 
+    #[test]
+    fn test_dead_letter_config_deserialization() {
+        {
+            let dead_letter_yaml = r#"
+                type: index
+                index_id: rejected-docs
+            "#;
+            assert_eq!(
+                serde_yaml::from_str::<DeadLetterConfig>(dead_letter_yaml).unwrap(),
+                DeadLetterConfig::Index {
+                    index_id: "rejected-docs".to_string()
+                }
+            );
+        }
+        {
+            let dead_letter_yaml = r#"
+                type: storage
+                uri: s3://my-bucket/rejected-docs
+            "#;
+            assert_eq!(
+                serde_yaml::from_str::<DeadLetterConfig>(dead_letter_yaml).unwrap(),
+                DeadLetterConfig::Storage {
+                    uri: Uri::from_well_formed("s3://my-bucket/rejected-docs")
+                }
+            );
+        }
+        {
+            let dead_letter_yaml = r#"
+                type: kafka
+                topic: rejected-docs
+                client_params:
+                    bootstrap.servers: localhost:9092
+            "#;
+            assert_eq!(
+                serde_yaml::from_str::<DeadLetterConfig>(dead_letter_yaml).unwrap(),
+                DeadLetterConfig::Kafka {
+                    topic: "rejected-docs".to_string(),
+                    client_params: serde_json::json!({"bootstrap.servers": "localhost:9092"}),
+                }
+            );
+        }
+        {
+            let dead_letter_yaml = r#"
+                type: index
+                topic: rejected-docs
+            "#;
+            serde_yaml::from_str::<DeadLetterConfig>(dead_letter_yaml).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_split_storage_layout_serialization() {
+        let indexing_settings: IndexingSettings = serde_yaml::from_str("{}").unwrap();
+        assert_eq!(
+            indexing_settings.split_storage_layout,
+            SplitStorageLayout::SplitId
+        );
+        let indexing_settings_json = serde_json::to_value(&indexing_settings).unwrap();
+        assert!(indexing_settings_json.get("split_storage_layout").is_none());
+
+        let indexing_settings: IndexingSettings =
+            serde_yaml::from_str("split_storage_layout: content_addressed").unwrap();
+        assert_eq!(
+            indexing_settings.split_storage_layout,
+            SplitStorageLayout::ContentAddressed
+        );
+        let indexing_settings_json = serde_json::to_value(&indexing_settings).unwrap();
+        assert_eq!(
+            indexing_settings_json["split_storage_layout"],
+            "content_addressed"
+        );
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
-            retention_period: "90 days".to_string(),
+            retention_period: Some("90 days".to_string()),
+            max_size: None,
+            max_num_splits: None,
             evaluation_schedule: "hourly".to_string(),
+            action: RetentionAction::Delete,
+            archive_uri: None,
         };
         let retention_policy_yaml = serde_yaml::to_string(&retention_policy).unwrap();
         assert_eq!(
@@ -749,8 +1516,12 @@ mod tests {
                 serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
 
             let expected_retention_policy = RetentionPolicy {
-                retention_period: "90 days".to_string(),
+                retention_period: Some("90 days".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             assert_eq!(retention_policy, expected_retention_policy);
         }
@@ -763,10 +1534,52 @@ mod tests {
                 serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
 
             let expected_retention_policy = RetentionPolicy {
-                retention_period: "90 days".to_string(),
+                retention_period: Some("90 days".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "daily".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
+            };
+            assert_eq!(retention_policy, expected_retention_policy);
+        }
+        {
+            let retention_policy_yaml = r#"
+            max_size: 2 TB
+            max_num_splits: 1000
+        "#;
+            let retention_policy =
+                serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
+
+            let expected_retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_size: Some(Byte::from_bytes(2_000_000_000_000)),
+                max_num_splits: Some(1000),
+                evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             assert_eq!(retention_policy, expected_retention_policy);
+            assert_eq!(retention_policy.retention_period().unwrap(), None);
+            assert_eq!(
+                retention_policy.max_size_in_bytes(),
+                Some(2_000_000_000_000)
+            );
+            assert_eq!(retention_policy.max_num_splits(), Some(1000));
+        }
+        {
+            let retention_policy_yaml = r#"
+            period: 90 days
+            action: archive
+            archive_uri: s3://cold-bucket/hdfs-logs
+        "#;
+            let retention_policy =
+                serde_yaml::from_str::<RetentionPolicy>(retention_policy_yaml).unwrap();
+            assert_eq!(retention_policy.action(), RetentionAction::Archive);
+            assert_eq!(
+                retention_policy.archive_uri().unwrap(),
+                &Uri::from_well_formed("s3://cold-bucket/hdfs-logs")
+            );
         }
     }
 
@@ -774,17 +1587,25 @@ mod tests {
     fn test_parse_retention_policy_period() {
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             assert_eq!(
                 retention_policy.retention_period().unwrap(),
-                Duration::from_secs(3600)
+                Some(Duration::from_secs(3600))
             );
             {
                 let retention_policy = RetentionPolicy {
-                    retention_period: "foo".to_string(),
+                    retention_period: Some("foo".to_string()),
+                    max_size: None,
+                    max_num_splits: None,
                     evaluation_schedule: "hourly".to_string(),
+                    action: RetentionAction::Delete,
+                    archive_uri: None,
                 };
                 assert_eq!(
                     retention_policy.retention_period().unwrap_err().to_string(),
@@ -807,8 +1628,12 @@ mod tests {
         let hourly_schedule = Schedule::from_str("@hourly").unwrap();
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "@hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             assert_eq!(
                 retention_policy.evaluation_schedule().unwrap(),
@@ -817,8 +1642,12 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             assert_eq!(
                 retention_policy.evaluation_schedule().unwrap(),
@@ -827,8 +1656,12 @@ mod tests {
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "0 * * * * *".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             let evaluation_schedule = retention_policy.evaluation_schedule().unwrap();
             assert_eq!(evaluation_schedule.seconds().count(), 1);
@@ -840,25 +1673,77 @@ mod tests {
     fn test_retention_policy_validate() {
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             retention_policy.validate().unwrap();
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "foo".to_string(),
+                retention_period: Some("foo".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             retention_policy.validate().unwrap_err();
         }
         {
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: "foo".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
             retention_policy.validate().unwrap_err();
         }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_size: None,
+                max_num_splits: None,
+                evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
+            };
+            let validation_error = retention_policy.validate().unwrap_err().to_string();
+            assert!(validation_error.contains("must define at least one of"));
+        }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: None,
+                max_size: None,
+                max_num_splits: Some(10),
+                evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
+            };
+            retention_policy.validate().unwrap();
+        }
+        {
+            let retention_policy = RetentionPolicy {
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
+                evaluation_schedule: "hourly".to_string(),
+                action: RetentionAction::Archive,
+                archive_uri: None,
+            };
+            let validation_error = retention_policy.validate().unwrap_err().to_string();
+            assert!(validation_error.contains("requires an `archive_uri`"));
+        }
+        {
+            let retention_policy = RetentionPolicy::new("1 hour".to_string(), "hourly".to_string())
+                .with_archive_action(Uri::from_well_formed("s3://cold-bucket/hdfs-logs"));
+            retention_policy.validate().unwrap();
+        }
     }
 
     #[test]
@@ -866,8 +1751,12 @@ mod tests {
         let schedule_test_helper_fn = |schedule_str: &str| {
             let hourly_schedule = Schedule::from_str(&prepend_at_char(schedule_str)).unwrap();
             let retention_policy = RetentionPolicy {
-                retention_period: "1 hour".to_string(),
+                retention_period: Some("1 hour".to_string()),
+                max_size: None,
+                max_num_splits: None,
                 evaluation_schedule: schedule_str.to_string(),
+                action: RetentionAction::Delete,
+                archive_uri: None,
             };
 
             let next_evaluation_duration = chrono::Duration::nanoseconds(
@@ -22,9 +22,11 @@ use quickwit_common::uri::Uri;
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
+use crate::validation::{parse_versioned_config, ConfigError};
 use crate::{
-    build_doc_mapper, validate_identifier, ConfigFormat, DocMapping, IndexConfig, IndexingSettings,
-    RetentionPolicy, SearchSettings,
+    build_doc_mapper, validate_identifier, ConfigFormat, DeadLetterConfig, DocMapping,
+    GarbageCollectionSettings, IndexConfig, IndexingSettings, RetentionPolicy, RollupConfig,
+    SearchSettings, QUERY_LOG_INDEX_ID,
 };
 
 /// Alias for the latest serialization format.
@@ -57,6 +59,20 @@ pub fn load_index_config_from_user_config(
     index_config_for_serialization.validate_and_build(Some(default_index_root_uri))
 }
 
+/// Same as [`load_index_config_from_user_config`] but reports deserialization errors with the path
+/// of the offending field.
+pub fn validate_index_config(
+    config_format: ConfigFormat,
+    config_content: &[u8],
+    default_index_root_uri: &Uri,
+) -> Result<IndexConfig, ConfigError> {
+    let index_config_for_serialization: IndexConfigForSerialization =
+        parse_versioned_config(config_format, config_content, &["0.4"])?;
+    let index_config =
+        index_config_for_serialization.validate_and_build(Some(default_index_root_uri))?;
+    Ok(index_config)
+}
+
 impl IndexConfigForSerialization {
     fn index_uri_or_fallback_to_default(
         &self,
@@ -80,14 +96,19 @@ impl IndexConfigForSerialization {
         self,
         default_index_root_uri: Option<&Uri>,
     ) -> anyhow::Result<IndexConfig> {
-        validate_identifier("Index ID", &self.index_id)?;
+        // Internal indexes are the only ones whose ID may start with an underscore.
+        if self.index_id != QUERY_LOG_INDEX_ID {
+            validate_identifier("Index ID", &self.index_id)?;
+        }
 
         let index_uri = self.index_uri_or_fallback_to_default(default_index_root_uri)?;
 
         if let Some(retention_policy) = &self.retention_policy {
             retention_policy.validate()?;
 
-            if self.doc_mapping.timestamp_field.is_none() {
+            if retention_policy.retention_period.is_some()
+                && self.doc_mapping.timestamp_field.is_none()
+            {
                 anyhow::bail!(
                     "Failed to validate index config. The retention policy requires a timestamp \
                      field, but the indexing settings do not declare one."
@@ -95,13 +116,68 @@ impl IndexConfigForSerialization {
             }
         }
 
+        self.garbage_collection
+            .validate()
+            .context("Failed to validate index config garbage collection settings.")?;
+
         // Note: this needs a deep refactoring to separate the doc mapping configuration,
         // and doc mapper implementations.
         // TODO see if we should store the byproducton the IndexConfig.
-        build_doc_mapper(&self.doc_mapping, &self.search_settings)?;
+        let doc_mapper = build_doc_mapper(&self.doc_mapping, &self.search_settings)?;
 
         self.indexing_settings.merge_policy.validate()?;
 
+        if self.indexing_settings.num_split_builders == Some(0) {
+            anyhow::bail!(
+                "Failed to validate index config. `num_split_builders` must be strictly positive."
+            );
+        }
+        if let Some(routing_field) = &self.indexing_settings.split_builder_routing_field {
+            if doc_mapper.schema().get_field(routing_field).is_err() {
+                anyhow::bail!(
+                    "Failed to validate index config. Split builder routing field `{}` is not \
+                     declared in the doc mapping.",
+                    routing_field
+                );
+            }
+        }
+        if let Some(min_commit_timeout_secs) = self.indexing_settings.min_commit_timeout_secs {
+            if min_commit_timeout_secs == 0
+                || min_commit_timeout_secs > self.indexing_settings.commit_timeout_secs
+            {
+                anyhow::bail!(
+                    "Failed to validate index config. `min_commit_timeout_secs` must be strictly \
+                     positive and lower than or equal to `commit_timeout_secs`."
+                );
+            }
+        }
+
+        if self.indexing_settings.deduplication_window_secs.is_some()
+            && self.doc_mapping.doc_unique_id_field.is_none()
+        {
+            anyhow::bail!(
+                "Failed to validate index config. Deduplication requires a doc unique ID field, \
+                 but the doc mapping does not declare one."
+            );
+        }
+
+        if let Some(DeadLetterConfig::Index { index_id }) = &self.indexing_settings.dead_letter {
+            validate_identifier("Dead letter index ID", index_id)?;
+
+            if *index_id == self.index_id {
+                anyhow::bail!(
+                    "Failed to validate index config. An index cannot be its own dead letter \
+                     index."
+                );
+            }
+        }
+
+        if let Some(rollup) = &self.rollup {
+            rollup
+                .validate(&self.index_id, &*doc_mapper)
+                .context("Failed to validate index config rollup.")?;
+        }
+
         Ok(IndexConfig {
             index_id: self.index_id,
             index_uri,
@@ -109,6 +185,9 @@ impl IndexConfigForSerialization {
             indexing_settings: self.indexing_settings,
             search_settings: self.search_settings,
             retention_policy: self.retention_policy,
+            garbage_collection: self.garbage_collection,
+            mounted_from: self.mounted_from,
+            rollup: self.rollup,
         })
     }
 }
@@ -144,6 +223,16 @@ pub struct IndexConfigV0_4 {
     #[serde(rename = "retention")]
     #[serde(default)]
     pub retention_policy: Option<RetentionPolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "GarbageCollectionSettings::is_default")]
+    pub garbage_collection: GarbageCollectionSettings,
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mounted_from: Option<Uri>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollup: Option<RollupConfig>,
 }
 
 impl From<IndexConfig> for IndexConfigV0_4 {
@@ -155,12 +244,17 @@ impl From<IndexConfig> for IndexConfigV0_4 {
             indexing_settings: index_config.indexing_settings,
             search_settings: index_config.search_settings,
             retention_policy: index_config.retention_policy,
+            garbage_collection: index_config.garbage_collection,
+            mounted_from: index_config.mounted_from,
+            rollup: index_config.rollup,
         }
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use super::*;
     use crate::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
 
@@ -211,10 +305,10 @@ mod test {
         // Not yet invalid, but we modify it right after this.
         let mut invalid_index_config: IndexConfigForSerialization =
             minimal_index_config_for_serialization();
-        invalid_index_config.retention_policy = Some(RetentionPolicy {
-            retention_period: "90 days".to_string(),
-            evaluation_schedule: "hourly".to_string(),
-        });
+        invalid_index_config.retention_policy = Some(RetentionPolicy::new(
+            "90 days".to_string(),
+            "hourly".to_string(),
+        ));
         let validation_err = invalid_index_config
             .validate_and_build(None)
             .unwrap_err()
@@ -222,6 +316,230 @@ mod test {
         assert!(validation_err.contains("The retention policy requires a timestamp field"));
     }
 
+    #[test]
+    fn test_validate_garbage_collection_settings() {
+        let index_config_yaml = r#"
+            index_id: hdfs-logs
+            doc_mapping:
+                field_mappings:
+                    - name: body
+                      type: text
+            garbage_collection:
+                deletion_grace_period: 7 days
+                run_interval: 1 hour
+                max_concurrent_deletions: 4
+                paused: true
+        "#;
+        let index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        let index_config = index_config
+            .validate_and_build(Some(&Uri::from_well_formed("s3://indexes")))
+            .unwrap();
+        let gc_settings = &index_config.garbage_collection;
+        assert_eq!(
+            gc_settings.deletion_grace_period().unwrap(),
+            Some(Duration::from_secs(7 * 24 * 3_600))
+        );
+        assert_eq!(
+            gc_settings.run_interval().unwrap(),
+            Some(Duration::from_secs(3_600))
+        );
+        assert_eq!(gc_settings.max_concurrent_deletions, Some(4));
+        assert!(gc_settings.paused);
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        invalid_index_config.garbage_collection.run_interval = Some("10 seconds".to_string());
+        let validation_err = invalid_index_config.validate_and_build(None).unwrap_err();
+        assert!(format!("{validation_err:#}").contains("must be greater than or equal to 1 minute"));
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        invalid_index_config
+            .garbage_collection
+            .deletion_grace_period = Some("forever".to_string());
+        invalid_index_config.validate_and_build(None).unwrap_err();
+
+        // Default settings are not serialized to keep index configs backward compatible.
+        let index_config_json =
+            serde_json::to_value(minimal_index_config_for_serialization()).unwrap();
+        assert!(index_config_json.get("garbage_collection").is_none());
+    }
+
+    #[test]
+    fn test_validate_internal_index_id() {
+        let mut index_config = minimal_index_config_for_serialization();
+        index_config.index_id = "_my-index".to_string();
+        index_config.clone().validate_and_build(None).unwrap_err();
+
+        index_config.index_id = QUERY_LOG_INDEX_ID.to_string();
+        index_config.validate_and_build(None).unwrap();
+    }
+
+    #[test]
+    fn test_validate_deduplication_window() {
+        let mut invalid_index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        invalid_index_config
+            .indexing_settings
+            .deduplication_window_secs = Some(3_600);
+        let validation_err = invalid_index_config
+            .clone()
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert!(validation_err.contains("Deduplication requires a doc unique ID field"));
+
+        let mut valid_index_config = invalid_index_config;
+        valid_index_config.doc_mapping.doc_unique_id_field = Some("body".to_string());
+        let index_config = valid_index_config.validate_and_build(None).unwrap();
+        assert_eq!(
+            index_config.indexing_settings.deduplication_window(),
+            Some(Duration::from_secs(3_600))
+        );
+    }
+
+    #[test]
+    fn test_validate_min_commit_timeout() {
+        let mut index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        index_config.indexing_settings.commit_timeout_secs = 60;
+        index_config.indexing_settings.min_commit_timeout_secs = Some(61);
+        let validation_err = index_config
+            .clone()
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert!(validation_err.contains("`min_commit_timeout_secs` must be strictly positive"));
+
+        index_config.indexing_settings.min_commit_timeout_secs = Some(0);
+        index_config.clone().validate_and_build(None).unwrap_err();
+
+        index_config.indexing_settings.min_commit_timeout_secs = Some(5);
+        let index_config = index_config.validate_and_build(None).unwrap();
+        assert_eq!(
+            index_config.indexing_settings.min_commit_timeout(),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_validate_dead_letter() {
+        let mut index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        index_config.indexing_settings.dead_letter = Some(DeadLetterConfig::Index {
+            index_id: index_config.index_id.clone(),
+        });
+        let validation_err = index_config
+            .clone()
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert!(validation_err.contains("cannot be its own dead letter index"));
+
+        index_config.indexing_settings.dead_letter = Some(DeadLetterConfig::Index {
+            index_id: "rejected-docs".to_string(),
+        });
+        let index_config = index_config.validate_and_build(None).unwrap();
+        assert_eq!(
+            index_config.indexing_settings.dead_letter,
+            Some(DeadLetterConfig::Index {
+                index_id: "rejected-docs".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_split_builders() {
+        let mut index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        index_config.indexing_settings.num_split_builders = Some(0);
+        let validation_err = index_config
+            .clone()
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert!(validation_err.contains("`num_split_builders` must be strictly positive"));
+
+        index_config.indexing_settings.num_split_builders = Some(4);
+        index_config.indexing_settings.split_builder_routing_field = Some("tenant_id".to_string());
+        let validation_err = index_config
+            .clone()
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert!(validation_err.contains("routing field `tenant_id` is not declared"));
+
+        index_config.indexing_settings.split_builder_routing_field = Some("body".to_string());
+        let index_config = index_config.validate_and_build(None).unwrap();
+        assert_eq!(index_config.indexing_settings.num_split_builders(), 4);
+    }
+
+    #[test]
+    fn test_validate_rollup() {
+        let index_config_yaml = r#"
+            index_id: hdfs-logs-rollup
+            doc_mapping:
+                field_mappings:
+                    - name: timestamp
+                      type: datetime
+                      fast: true
+                    - name: service
+                      type: text
+                      tokenizer: raw
+                    - name: num_logs
+                      type: u64
+                    - name: max_latency
+                      type: f64
+                timestamp_field: timestamp
+            rollup:
+                source_index_id: hdfs-logs
+                interval: 1 minute
+                group_by: [service]
+                metrics:
+                    - name: num_logs
+                      function: count
+                    - name: max_latency
+                      function: max
+                      field: latency
+        "#;
+        let index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        let index_config = index_config
+            .validate_and_build(Some(&Uri::from_well_formed("s3://indexes")))
+            .unwrap();
+        let rollup = index_config.rollup.as_ref().unwrap();
+        assert_eq!(rollup.interval().unwrap(), Duration::from_secs(60));
+        assert_eq!(rollup.delay().unwrap(), Duration::from_secs(60));
+        assert_eq!(rollup.max_groups, 10_000);
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        invalid_index_config.rollup.as_mut().unwrap().metrics[1].field = None;
+        let validation_err = invalid_index_config.validate_and_build(None).unwrap_err();
+        assert!(format!("{validation_err:#}")
+            .contains("metric `max_latency` must specify the field it aggregates"));
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        invalid_index_config.rollup.as_mut().unwrap().group_by = vec!["status".to_string()];
+        let validation_err = invalid_index_config.validate_and_build(None).unwrap_err();
+        assert!(format!("{validation_err:#}")
+            .contains("field `status` is not declared in the doc mapping"));
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        invalid_index_config.rollup.as_mut().unwrap().interval = "1500 ms".to_string();
+        let validation_err = invalid_index_config.validate_and_build(None).unwrap_err();
+        assert!(format!("{validation_err:#}").contains("whole number of seconds"));
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            serde_yaml::from_str(index_config_yaml).unwrap();
+        invalid_index_config.doc_mapping.timestamp_field = None;
+        let validation_err = invalid_index_config.validate_and_build(None).unwrap_err();
+        assert!(format!("{validation_err:#}").contains("must declare a timestamp field"));
+    }
+
     #[test]
     fn test_minimal_index_config_missing_root_uri_no_default_uri() {
         let config_yaml = r#"
@@ -252,4 +570,38 @@ mod test {
             assert_eq!(index_config.index_uri.as_str(), "s3://mybucket/hdfs-logs");
         }
     }
+
+    #[test]
+    fn test_validate_index_config_reports_field_path() {
+        let config_yaml = r#"
+            version: 0.4
+            index_id: hdfs-logs
+            doc_mapping: {}
+            indexing_settings:
+              commit_timeout_secs: soon
+        "#;
+        let error = validate_index_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://mybucket"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.path_opt.as_deref(),
+            Some("indexing_settings.commit_timeout_secs")
+        );
+
+        let config_yaml = r#"
+            version: 0.4
+            index_id: hdfs-logs
+            doc_mapping: {}
+        "#;
+        let index_config = validate_index_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://mybucket"),
+        )
+        .unwrap();
+        assert_eq!(index_config.index_uri.as_str(), "s3://mybucket/hdfs-logs");
+    }
 }
@@ -0,0 +1,557 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use byte_unit::Byte;
+use humantime::parse_duration;
+use quickwit_common::index_id_matches_pattern;
+use quickwit_common::uri::Uri;
+use serde::{Deserialize, Serialize};
+
+use crate::index_config::serialize::IndexConfigV0_4;
+use crate::{
+    validate_identifier, ConfigFormat, DocMapping, GarbageCollectionSettings, IndexConfig,
+    IndexingSettings, RetentionPolicy, SearchSettings,
+};
+
+/// Index ID used to check that a template yields valid index configs.
+const VALIDATION_INDEX_ID: &str = "index-template-validation";
+
+/// An index template holds the config of the indexes created automatically when documents are
+/// ingested into an index that does not exist yet and whose ID matches one of the template
+/// patterns.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "VersionedIndexTemplate")]
+#[serde(try_from = "VersionedIndexTemplate")]
+pub struct IndexTemplate {
+    pub template_id: String,
+    pub index_id_patterns: Vec<String>,
+    pub priority: usize,
+    pub description: Option<String>,
+    pub index_root_uri: Option<Uri>,
+    pub doc_mapping: DocMapping,
+    pub indexing_settings: IndexingSettings,
+    pub search_settings: SearchSettings,
+    pub retention_policy: Option<RetentionPolicy>,
+    pub rollover_policy: Option<RolloverPolicy>,
+}
+
+impl IndexTemplate {
+    /// Returns whether the index ID matches one of the template patterns.
+    pub fn matches(&self, index_id: &str) -> bool {
+        self.index_id_patterns
+            .iter()
+            .any(|index_id_pattern| index_id_matches_pattern(index_id, index_id_pattern))
+    }
+
+    /// Builds the config of the index `index_id` from the template. The index URI is the
+    /// concatenation of the template root URI, or `default_index_root_uri` if the template does
+    /// not specify one, and the index ID.
+    pub fn apply_template(
+        &self,
+        index_id: String,
+        default_index_root_uri: &Uri,
+    ) -> anyhow::Result<IndexConfig> {
+        let index_root_uri = self
+            .index_root_uri
+            .as_ref()
+            .unwrap_or(default_index_root_uri);
+        let index_uri = index_root_uri.join(&index_id)?;
+        let index_config = IndexConfigV0_4 {
+            index_id,
+            index_uri: Some(index_uri),
+            doc_mapping: self.doc_mapping.clone(),
+            indexing_settings: self.indexing_settings.clone(),
+            search_settings: self.search_settings.clone(),
+            retention_policy: self.retention_policy.clone(),
+            garbage_collection: GarbageCollectionSettings::default(),
+            mounted_from: None,
+            rollup: None,
+        };
+        index_config.validate_and_build(None)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        validate_identifier("Index template ID", &self.template_id)?;
+
+        if self.index_id_patterns.is_empty() {
+            bail!(
+                "Index template `{}` must declare at least one index ID pattern.",
+                self.template_id
+            );
+        }
+        for index_id_pattern in &self.index_id_patterns {
+            let is_valid_pattern = !index_id_pattern.is_empty()
+                && index_id_pattern.chars().all(|character| {
+                    character.is_ascii_alphanumeric() || matches!(character, '-' | '_' | '*')
+                });
+            if !is_valid_pattern {
+                bail!(
+                    "Index ID pattern `{index_id_pattern}` is invalid. Patterns may only contain \
+                     alphanumeric characters, `-`, `_`, and the `*` wildcard."
+                );
+            }
+        }
+        let default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        self.apply_template(VALIDATION_INDEX_ID.to_string(), &default_index_root_uri)
+            .map_err(|error| {
+                anyhow::anyhow!("Index template `{}` is invalid: {error}", self.template_id)
+            })?;
+
+        if let Some(rollover_policy) = &self.rollover_policy {
+            validate_identifier("Index alias ID", &rollover_policy.write_alias)?;
+
+            if rollover_policy.conditions.is_empty() {
+                bail!(
+                    "The rollover policy of index template `{}` must define at least one of \
+                     `max_age`, `max_docs`, or `max_size`.",
+                    self.template_id
+                );
+            }
+            rollover_policy.conditions.validate()?;
+
+            let first_index_id = rollover_policy.first_index_id();
+            if !self.matches(&first_index_id) {
+                bail!(
+                    "The indexes rolled over by index template `{}`, such as `{first_index_id}`, \
+                     must match the template patterns.",
+                    self.template_id
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A rollover policy keeps a write alias pointed at the most recent index of a series of indexes
+/// created from the template, and rolls the alias over to a new index of the series when the
+/// current one meets any of the rollover conditions.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RolloverPolicy {
+    /// Alias pointing at the index of the series currently written to. The indexes of the series
+    /// are named after the alias followed by a six-digit sequence number (`logs-000001`,
+    /// `logs-000002`, ...).
+    pub write_alias: String,
+    pub conditions: RolloverConditions,
+}
+
+impl RolloverPolicy {
+    /// Returns the ID of the first index of the series.
+    pub fn first_index_id(&self) -> String {
+        first_rollover_index_id(&self.write_alias)
+    }
+}
+
+/// Returns the ID of the first index of the series of indexes rolled over behind the write alias.
+pub fn first_rollover_index_id(write_alias: &str) -> String {
+    format!("{write_alias}-000001")
+}
+
+/// Conditions under which the index a write alias points at is rolled over. The index is rolled
+/// over as soon as it meets any of them.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RolloverConditions {
+    /// Maximum age of the index, expressed in a human-friendly way (`1 hour`, `3 days`, `a
+    /// week`, ...).
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// Maximum number of published documents of the index.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_docs: Option<u64>,
+    /// Maximum total uncompressed size of the published documents of the index, expressed in a
+    /// human-friendly way (`50 GB`, `1 TB`, ...).
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<Byte>,
+}
+
+impl RolloverConditions {
+    /// Returns whether no condition is defined.
+    pub fn is_empty(&self) -> bool {
+        self.max_age.is_none() && self.max_docs.is_none() && self.max_size.is_none()
+    }
+
+    pub fn max_age(&self) -> anyhow::Result<Option<Duration>> {
+        let Some(max_age) = &self.max_age else {
+            return Ok(None);
+        };
+        let max_age = parse_duration(max_age)
+            .with_context(|| format!("Failed to parse rollover max age `{max_age}`."))?;
+        Ok(Some(max_age))
+    }
+
+    pub fn max_size_in_bytes(&self) -> Option<u64> {
+        self.max_size.map(|max_size| max_size.get_bytes() as u64)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.max_age()?;
+        Ok(())
+    }
+}
+
+/// Returns the template with the highest priority among the templates matching the index ID.
+/// Ties are broken in favor of the template with the lowest ID.
+pub fn find_matching_index_template<'a>(
+    index_templates: &'a [IndexTemplate],
+    index_id: &str,
+) -> Option<&'a IndexTemplate> {
+    index_templates
+        .iter()
+        .filter(|index_template| index_template.matches(index_id))
+        .min_by(|left, right| {
+            right
+                .priority
+                .cmp(&left.priority)
+                .then_with(|| left.template_id.cmp(&right.template_id))
+        })
+}
+
+/// Parses and validates an [`IndexTemplate`] as supplied by a user with a given
+/// [`ConfigFormat`].
+pub fn load_index_template_from_user_config(
+    config_format: ConfigFormat,
+    config_content: &[u8],
+) -> anyhow::Result<IndexTemplate> {
+    let versioned_index_template: VersionedIndexTemplate = config_format.parse(config_content)?;
+    versioned_index_template.try_into()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(tag = "version")]
+pub(crate) enum VersionedIndexTemplate {
+    #[serde(rename = "0.4")]
+    V0_4(IndexTemplateV0_4),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IndexTemplateV0_4 {
+    pub template_id: String,
+    /// Patterns of the IDs of the indexes created from the template, in which `*` matches any
+    /// sequence of characters.
+    pub index_id_patterns: Vec<String>,
+    /// When several templates match an index ID, the template with the highest priority is
+    /// applied.
+    #[serde(default)]
+    pub priority: usize,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Root URI of the indexes created from the template. Defaults to the default index root URI
+    /// of the node creating the index.
+    #[schema(value_type = String)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_root_uri: Option<Uri>,
+    pub doc_mapping: DocMapping,
+    #[serde(default)]
+    pub indexing_settings: IndexingSettings,
+    #[serde(default)]
+    pub search_settings: SearchSettings,
+    #[serde(rename = "retention")]
+    #[serde(default)]
+    pub retention_policy: Option<RetentionPolicy>,
+    #[serde(rename = "rollover")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollover_policy: Option<RolloverPolicy>,
+}
+
+impl From<IndexTemplate> for VersionedIndexTemplate {
+    fn from(index_template: IndexTemplate) -> Self {
+        VersionedIndexTemplate::V0_4(IndexTemplateV0_4 {
+            template_id: index_template.template_id,
+            index_id_patterns: index_template.index_id_patterns,
+            priority: index_template.priority,
+            description: index_template.description,
+            index_root_uri: index_template.index_root_uri,
+            doc_mapping: index_template.doc_mapping,
+            indexing_settings: index_template.indexing_settings,
+            search_settings: index_template.search_settings,
+            retention_policy: index_template.retention_policy,
+            rollover_policy: index_template.rollover_policy,
+        })
+    }
+}
+
+impl TryFrom<VersionedIndexTemplate> for IndexTemplate {
+    type Error = anyhow::Error;
+
+    fn try_from(versioned_index_template: VersionedIndexTemplate) -> anyhow::Result<Self> {
+        let v0_4 = match versioned_index_template {
+            VersionedIndexTemplate::V0_4(v0_4) => v0_4,
+        };
+        let index_template = IndexTemplate {
+            template_id: v0_4.template_id,
+            index_id_patterns: v0_4.index_id_patterns,
+            priority: v0_4.priority,
+            description: v0_4.description,
+            index_root_uri: v0_4.index_root_uri,
+            doc_mapping: v0_4.doc_mapping,
+            indexing_settings: v0_4.indexing_settings,
+            search_settings: v0_4.search_settings,
+            retention_policy: v0_4.retention_policy,
+            rollover_policy: v0_4.rollover_policy,
+        };
+        index_template.validate()?;
+        Ok(index_template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_template_for_test(template_id: &str, index_id_pattern: &str) -> IndexTemplate {
+        let index_template_yaml = format!(
+            r#"
+            version: 0.4
+            template_id: {template_id}
+            index_id_patterns: ["{index_id_pattern}"]
+            doc_mapping:
+              field_mappings:
+                - name: timestamp
+                  type: datetime
+                  fast: true
+                - name: body
+                  type: text
+              timestamp_field: timestamp
+            search_settings:
+              default_search_fields: [body]
+            retention:
+              period: 7 days
+              schedule: daily
+            "#
+        );
+        load_index_template_from_user_config(ConfigFormat::Yaml, index_template_yaml.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_index_template_apply_template() {
+        let index_template = index_template_for_test("logs", "logs-*");
+        assert!(index_template.matches("logs-myapp-2024.06"));
+        assert!(!index_template.matches("traces-myapp"));
+
+        let default_index_root_uri = Uri::from_well_formed("s3://quickwit-indexes");
+        let index_config = index_template
+            .apply_template("logs-myapp".to_string(), &default_index_root_uri)
+            .unwrap();
+        assert_eq!(index_config.index_id, "logs-myapp");
+        assert_eq!(index_config.index_uri, "s3://quickwit-indexes/logs-myapp");
+        assert_eq!(index_config.doc_mapping, index_template.doc_mapping);
+        assert_eq!(index_config.search_settings.default_search_fields, ["body"]);
+        assert_eq!(
+            index_config.retention_policy,
+            index_template.retention_policy
+        );
+
+        let mut index_template = index_template;
+        index_template.index_root_uri = Some(Uri::from_well_formed("s3://logs-bucket/indexes"));
+        let index_config = index_template
+            .apply_template("logs-myapp".to_string(), &default_index_root_uri)
+            .unwrap();
+        assert_eq!(
+            index_config.index_uri,
+            "s3://logs-bucket/indexes/logs-myapp"
+        );
+    }
+
+    #[test]
+    fn test_index_template_serde() {
+        let index_template = index_template_for_test("logs", "logs-*");
+        let index_template_json = serde_json::to_string(&index_template).unwrap();
+        let deserialized_index_template: IndexTemplate =
+            serde_json::from_str(&index_template_json).unwrap();
+        assert_eq!(deserialized_index_template, index_template);
+    }
+
+    #[test]
+    fn test_index_template_validation() {
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: []
+            doc_mapping: {}
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("at least one index ID pattern"));
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs.*]
+            doc_mapping: {}
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Index ID pattern `logs.*` is invalid"));
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            retention:
+              period: 7 days
+              schedule: daily
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("requires a timestamp field"));
+    }
+
+    #[test]
+    fn test_index_template_rollover_policy() {
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            rollover:
+              write_alias: logs
+              conditions:
+                max_age: 1 day
+                max_docs: 1000000
+                max_size: 10 GB
+        "#;
+        let index_template = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap();
+        let rollover_policy = index_template.rollover_policy.as_ref().unwrap();
+        assert_eq!(rollover_policy.first_index_id(), "logs-000001");
+        assert_eq!(
+            rollover_policy.conditions.max_age().unwrap(),
+            Some(Duration::from_secs(24 * 3_600))
+        );
+        assert_eq!(rollover_policy.conditions.max_docs, Some(1_000_000));
+        assert_eq!(
+            rollover_policy.conditions.max_size_in_bytes(),
+            Some(10_000_000_000)
+        );
+        let index_template_json = serde_json::to_string(&index_template).unwrap();
+        let deserialized_index_template: IndexTemplate =
+            serde_json::from_str(&index_template_json).unwrap();
+        assert_eq!(deserialized_index_template, index_template);
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            rollover:
+              write_alias: logs
+              conditions: {}
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("at least one of `max_age`"));
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            rollover:
+              write_alias: traces
+              conditions:
+                max_docs: 1000000
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("must match the template patterns"));
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            rollover:
+              write_alias: logs
+              conditions:
+                max_age: forever
+        "#;
+        let error = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Failed to parse rollover max age"));
+    }
+
+    #[test]
+    fn test_find_matching_index_template() {
+        let mut logs_template = index_template_for_test("logs", "logs-*");
+        let mut myapp_template = index_template_for_test("myapp", "logs-myapp-*");
+        assert!(find_matching_index_template(&[], "logs-myapp-2024.06").is_none());
+
+        let index_templates = [logs_template.clone(), myapp_template.clone()];
+        let matching_template =
+            find_matching_index_template(&index_templates, "logs-myapp-2024.06").unwrap();
+        assert_eq!(matching_template.template_id, "logs");
+        assert!(find_matching_index_template(&index_templates, "traces").is_none());
+
+        myapp_template.priority = 10;
+        let index_templates = [logs_template.clone(), myapp_template.clone()];
+        let matching_template =
+            find_matching_index_template(&index_templates, "logs-myapp-2024.06").unwrap();
+        assert_eq!(matching_template.template_id, "myapp");
+        let matching_template =
+            find_matching_index_template(&index_templates, "logs-otherapp").unwrap();
+        assert_eq!(matching_template.template_id, "logs");
+
+        logs_template.priority = 20;
+        let index_templates = [logs_template, myapp_template];
+        let matching_template =
+            find_matching_index_template(&index_templates, "logs-myapp-2024.06").unwrap();
+        assert_eq!(matching_template.template_id, "logs");
+    }
+}
@@ -27,62 +27,107 @@ use once_cell::sync::OnceCell;
 use quickwit_common::uri::Uri;
 use regex::Regex;
 
+mod alert_rule;
 mod config_value;
 mod index_config;
+mod index_template;
 pub mod merge_policy_config;
 mod quickwit_config;
 mod qw_env_vars;
 pub mod service;
 mod source_config;
 mod templating;
+mod validation;
 
+pub use alert_rule::{
+    load_alert_rule_from_user_config, AlertComparison, AlertCondition, AlertRule,
+    AlertWebhookConfig,
+};
+use alert_rule::{AlertRuleV0_4, VersionedAlertRule};
 // We export that one for backward compatibility.
 // See #2048
 use index_config::serialize::{IndexConfigV0_4, VersionedIndexConfig};
 pub use index_config::{
-    build_doc_mapper, load_index_config_from_user_config, DocMapping, IndexConfig,
-    IndexingResources, IndexingSettings, RetentionPolicy, SearchSettings,
+    build_doc_mapper, load_index_config_from_user_config, validate_doc_mapping_update,
+    validate_index_config, DeadLetterConfig, DocMapping, GarbageCollectionSettings, IndexConfig,
+    IndexingResources, IndexingSettings, RetentionAction, RetentionPolicy, RollupConfig,
+    RollupFunction, RollupMetric, SearchSettings, SplitStorageLayout, QUERY_LOG_INDEX_ID,
+};
+pub use index_template::{
+    find_matching_index_template, first_rollover_index_id, load_index_template_from_user_config,
+    IndexTemplate, RolloverConditions, RolloverPolicy,
 };
+use index_template::{IndexTemplateV0_4, VersionedIndexTemplate};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Value as JsonValue;
 pub use source_config::{
-    load_source_config_from_user_config, FileSourceParams, KafkaSourceParams, KinesisSourceParams,
-    PulsarSourceAuth, PulsarSourceParams, RegionOrEndpoint, SourceConfig, SourceParams,
+    load_source_config_from_user_config, AvroOptions, CsvOptions, FileSourceParams,
+    IndexSourceParams, KafkaSourceParams, KinesisSourceParams, ProtobufOptions, PulsarSourceAuth,
+    PulsarSourceParams, PulsarSubscriptionType, RegionOrEndpoint, SourceConfig, SourceParams,
     TransformConfig, VecSourceParams, VoidSourceParams, CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID,
 };
 use tracing::warn;
 
 use crate::merge_policy_config::{
     ConstWriteAmplificationMergePolicyConfig, MergePolicyConfig, StableLogMergePolicyConfig,
+    TimeTieredMergePolicyConfig,
 };
 pub use crate::quickwit_config::{
-    IndexerConfig, IngestApiConfig, JaegerConfig, QuickwitConfig, SearcherConfig,
+    ApiKeyScope, AuditLogConfig, AuditLogSinkConfig, AuthConfig, CorsConfig,
+    EndpointRateLimitsConfig, FieldMaskActionConfig, FieldMaskConfig, GrpcTlsConfig, IndexerConfig,
+    IngestApiConfig, JaegerConfig, OidcConfig, OidcRoleMapping, PermissionConfig, QueryLogConfig,
+    QuickwitConfig, QuotaConfig, QuotaExceededAction, QuotasConfig, RateLimitConfig,
+    RateLimitsConfig, RemoteClusterConfig, RestConfig, RoleConfig, SearchAdmissionConfig,
+    SearcherConfig, SecurityHeadersConfig, TraceSamplingConfig, TraceSamplingRule,
     DEFAULT_QW_CONFIG_PATH,
 };
 use crate::source_config::serialize::{SourceConfigV0_4, VersionedSourceConfig};
+pub use crate::validation::ConfigError;
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(
     IndexingResources,
     IndexingSettings,
+    DeadLetterConfig,
+    SplitStorageLayout,
     SearchSettings,
     RetentionPolicy,
+    RetentionAction,
+    GarbageCollectionSettings,
+    RollupConfig,
+    RollupMetric,
+    RollupFunction,
     MergePolicyConfig,
     DocMapping,
     VersionedSourceConfig,
     SourceConfigV0_4,
     VersionedIndexConfig,
     IndexConfigV0_4,
+    VersionedIndexTemplate,
+    IndexTemplateV0_4,
+    RolloverPolicy,
+    RolloverConditions,
+    VersionedAlertRule,
+    AlertRuleV0_4,
+    AlertCondition,
+    AlertComparison,
+    AlertWebhookConfig,
     SourceParams,
     FileSourceParams,
+    CsvOptions,
+    IndexSourceParams,
     KafkaSourceParams,
+    AvroOptions,
+    ProtobufOptions,
     KinesisSourceParams,
     PulsarSourceParams,
     PulsarSourceAuth,
+    PulsarSubscriptionType,
     RegionOrEndpoint,
     ConstWriteAmplificationMergePolicyConfig,
     StableLogMergePolicyConfig,
+    TimeTieredMergePolicyConfig,
     TransformConfig,
     VecSourceParams,
     VoidSourceParams,
@@ -77,6 +77,52 @@ pub struct StableLogMergePolicyConfig {
     pub maturation_period: Duration,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimeTieredMergePolicyConfig {
+    /// Width of the time buckets, expressed in a human-friendly way (`1 hour`, `1 day`, ...).
+    /// Only splits whose time range starts within the same bucket are merged together.
+    #[schema(value_type = String)]
+    #[serde(default = "default_time_bucket")]
+    #[serde(deserialize_with = "parse_human_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub time_bucket: Duration,
+    /// Number of splits to merge together in a single merge operation.
+    #[serde(default = "default_merge_factor")]
+    pub merge_factor: usize,
+    /// Maximum number of splits that can be merged together in a single merge operation.
+    #[serde(default = "default_max_merge_factor")]
+    pub max_merge_factor: usize,
+    /// Maximum number of merges that a given split should undergo.
+    #[serde(default = "default_max_merge_ops")]
+    pub max_merge_ops: usize,
+    /// Duration relative to `split.created_timestamp` after which a split
+    /// becomes mature.
+    /// If `now() >= split.created_timestamp + maturation_period` then
+    /// the split is mature.
+    #[schema(value_type = String)]
+    #[serde(default = "default_maturation_period")]
+    #[serde(deserialize_with = "parse_human_duration")]
+    #[serde(serialize_with = "serialize_duration")]
+    pub maturation_period: Duration,
+}
+
+impl Default for TimeTieredMergePolicyConfig {
+    fn default() -> Self {
+        TimeTieredMergePolicyConfig {
+            time_bucket: default_time_bucket(),
+            merge_factor: default_merge_factor(),
+            max_merge_factor: default_max_merge_factor(),
+            max_merge_ops: default_max_merge_ops(),
+            maturation_period: default_maturation_period(),
+        }
+    }
+}
+
+fn default_time_bucket() -> Duration {
+    Duration::from_secs(24 * 3600)
+}
+
 fn default_merge_factor() -> usize {
     10
 }
@@ -136,6 +182,8 @@ pub enum MergePolicyConfig {
     #[serde(rename = "stable_log")]
     #[serde(alias = "default")]
     StableLog(StableLogMergePolicyConfig),
+    #[serde(rename = "time_tiered")]
+    TimeTiered(TimeTieredMergePolicyConfig),
 }
 
 impl Default for MergePolicyConfig {
@@ -154,6 +202,14 @@ impl MergePolicyConfig {
                 (config.merge_factor, config.max_merge_factor)
             }
             MergePolicyConfig::StableLog(config) => (config.merge_factor, config.max_merge_factor),
+            MergePolicyConfig::TimeTiered(config) => {
+                if config.time_bucket.as_secs() == 0 {
+                    anyhow::bail!(
+                        "Index config merge policy `time_bucket` must be at least one second."
+                    );
+                }
+                (config.merge_factor, config.max_merge_factor)
+            }
         };
         if max_merge_factor < merge_factor {
             anyhow::bail!(
@@ -164,3 +220,42 @@ impl MergePolicyConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_tiered_merge_policy_config_serde() {
+        let merge_policy_yaml = r#"
+            type: time_tiered
+            time_bucket: 1 hour
+            merge_factor: 5
+        "#;
+        let merge_policy_config: MergePolicyConfig =
+            serde_yaml::from_str(merge_policy_yaml).unwrap();
+        let expected_merge_policy_config =
+            MergePolicyConfig::TimeTiered(TimeTieredMergePolicyConfig {
+                time_bucket: Duration::from_secs(3600),
+                merge_factor: 5,
+                ..Default::default()
+            });
+        assert_eq!(merge_policy_config, expected_merge_policy_config);
+        merge_policy_config.validate().unwrap();
+
+        let merge_policy_yaml = serde_yaml::to_string(&merge_policy_config).unwrap();
+        assert_eq!(
+            serde_yaml::from_str::<MergePolicyConfig>(&merge_policy_yaml).unwrap(),
+            merge_policy_config
+        );
+    }
+
+    #[test]
+    fn test_time_tiered_merge_policy_config_validate() {
+        let merge_policy_config = MergePolicyConfig::TimeTiered(TimeTieredMergePolicyConfig {
+            time_bucket: Duration::from_millis(10),
+            ..Default::default()
+        });
+        merge_policy_config.validate().unwrap_err();
+    }
+}
@@ -19,23 +19,26 @@
 
 mod serialize;
 
-use std::collections::{HashMap, HashSet};
-use std::env;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::num::NonZeroU64;
 use std::path::PathBuf;
 use std::time::Duration;
+use std::{env, fmt};
 
 use anyhow::bail;
 use byte_unit::Byte;
+use quickwit_common::index_id_matches_pattern;
 use quickwit_common::net::HostAddr;
 use quickwit_common::uri::Uri;
 use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::quickwit_config::serialize::load_quickwit_config_with_env;
+use crate::quickwit_config::serialize::{
+    load_quickwit_config_with_env, validate_quickwit_config_with_env,
+};
 use crate::service::QuickwitService;
-use crate::ConfigFormat;
+use crate::{ConfigError, ConfigFormat};
 
 pub const DEFAULT_QW_CONFIG_PATH: &str = "config/quickwit.yaml";
 
@@ -48,10 +51,24 @@ pub struct IndexerConfig {
     pub split_store_max_num_splits: usize,
     #[serde(default = "IndexerConfig::default_max_concurrent_split_uploads")]
     pub max_concurrent_split_uploads: usize,
-    /// Enables the OpenTelemetry exporter endpoint to ingest logs and traces via the OpenTelemetry
-    /// Protocol (OTLP).
+    /// Memory budget shared by the indexing pipelines of the node. When the splits being built
+    /// approach this limit, the indexers commit early to release memory.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_indexing_memory_usage: Option<Byte>,
+    /// Enables the OpenTelemetry exporter endpoint to ingest logs, traces, and metrics via the
+    /// OpenTelemetry Protocol (OTLP).
     #[serde(default = "IndexerConfig::default_enable_otlp_endpoint")]
     pub enable_otlp_endpoint: bool,
+    /// Enables the Prometheus remote-write endpoint to ingest metrics into the
+    /// `prometheus-metrics-v0` index.
+    #[serde(default = "IndexerConfig::default_enable_prometheus_endpoint")]
+    pub enable_prometheus_endpoint: bool,
+    /// Tail-based sampling of the spans received by the OTLP endpoint. Disabled by default: all
+    /// the spans are indexed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_sampling: Option<TraceSamplingConfig>,
 }
 
 impl IndexerConfig {
@@ -59,6 +76,10 @@ impl IndexerConfig {
         !(cfg!(feature = "test") || cfg!(feature = "testsuite"))
     }
 
+    fn default_enable_prometheus_endpoint() -> bool {
+        !(cfg!(feature = "test") || cfg!(feature = "testsuite"))
+    }
+
     fn default_max_concurrent_split_uploads() -> usize {
         12
     }
@@ -75,9 +96,12 @@ impl IndexerConfig {
     pub fn for_test() -> anyhow::Result<Self> {
         let indexer_config = IndexerConfig {
             enable_otlp_endpoint: true,
+            enable_prometheus_endpoint: true,
             split_store_max_num_bytes: Byte::from_bytes(1_000_000),
             split_store_max_num_splits: 3,
             max_concurrent_split_uploads: 4,
+            max_indexing_memory_usage: None,
+            trace_sampling: None,
         };
         Ok(indexer_config)
     }
@@ -87,13 +111,85 @@ impl Default for IndexerConfig {
     fn default() -> Self {
         Self {
             enable_otlp_endpoint: Self::default_enable_otlp_endpoint(),
+            enable_prometheus_endpoint: Self::default_enable_prometheus_endpoint(),
             split_store_max_num_bytes: Self::default_split_store_max_num_bytes(),
             split_store_max_num_splits: Self::default_split_store_max_num_splits(),
             max_concurrent_split_uploads: Self::default_max_concurrent_split_uploads(),
+            max_indexing_memory_usage: None,
+            trace_sampling: None,
         }
     }
 }
 
+/// Spans are buffered per trace for `decision_wait_secs`, then the whole trace is either indexed
+/// or dropped. A trace is kept if one of its spans has an error status, lasts longer than
+/// `latency_threshold_millis`, or matches one of the `rules`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraceSamplingConfig {
+    #[serde(default = "TraceSamplingConfig::default_decision_wait_secs")]
+    pub decision_wait_secs: NonZeroU64,
+    /// Maximum number of buffered spans. Beyond that, the sampling decision of the oldest traces
+    /// is taken early.
+    #[serde(default = "TraceSamplingConfig::default_max_buffered_spans")]
+    pub max_buffered_spans: usize,
+    #[serde(default = "TraceSamplingConfig::default_keep_errors")]
+    pub keep_errors: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_threshold_millis: Option<u64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<TraceSamplingRule>,
+}
+
+impl TraceSamplingConfig {
+    fn default_decision_wait_secs() -> NonZeroU64 {
+        NonZeroU64::new(10).unwrap()
+    }
+
+    fn default_max_buffered_spans() -> usize {
+        1_000_000
+    }
+
+    fn default_keep_errors() -> bool {
+        true
+    }
+
+    pub fn decision_wait(&self) -> Duration {
+        Duration::from_secs(self.decision_wait_secs.get())
+    }
+}
+
+impl Default for TraceSamplingConfig {
+    fn default() -> Self {
+        Self {
+            decision_wait_secs: Self::default_decision_wait_secs(),
+            max_buffered_spans: Self::default_max_buffered_spans(),
+            keep_errors: Self::default_keep_errors(),
+            latency_threshold_millis: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Keeps the traces containing at least one span that matches all the criteria of the rule.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraceSamplingRule {
+    pub name: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span_name: Option<String>,
+    /// Span attributes and their expected values.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, String>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SearcherConfig {
@@ -101,10 +197,36 @@ pub struct SearcherConfig {
     pub fast_field_cache_capacity: Byte,
     #[serde(default = "SearcherConfig::default_split_footer_cache_capacity")]
     pub split_footer_cache_capacity: Byte,
+    /// Capacity of the cache of the leaf search responses of single splits. Zero disables it.
+    #[serde(default = "SearcherConfig::default_leaf_search_cache_capacity")]
+    pub leaf_search_cache_capacity: Byte,
+    /// Capacity of the cache of the opened searchers of splits, which keep the term
+    /// dictionaries and fast fields decoded by the previous searches. Zero disables it.
+    #[serde(default = "SearcherConfig::default_split_searcher_cache_capacity")]
+    pub split_searcher_cache_capacity: Byte,
     #[serde(default = "SearcherConfig::default_max_num_concurrent_split_searches")]
     pub max_num_concurrent_split_searches: usize,
     #[serde(default = "SearcherConfig::default_max_num_concurrent_split_streams")]
     pub max_num_concurrent_split_streams: usize,
+    #[serde(default = "SearcherConfig::default_max_term_expansions")]
+    pub max_term_expansions: usize,
+    /// Number of candidate searchers of each split, picked by rendezvous hashing. The leaf
+    /// requests of a split are sent to the least loaded candidate and retried on another one.
+    #[serde(default = "SearcherConfig::default_split_replication_factor")]
+    pub split_replication_factor: usize,
+    /// Delay after which a leaf search request still running is hedged, i.e. also sent to
+    /// another candidate searcher of its splits. The first successful response wins. Disabled
+    /// by default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leaf_search_hedging_delay_millis: Option<u64>,
+    /// Remote Quickwit clusters searched along with the local cluster by cross-cluster searches.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remote_clusters: Vec<RemoteClusterConfig>,
+    /// Limits on the concurrent searches coordinated by the searcher.
+    #[serde(default)]
+    pub admission: SearchAdmissionConfig,
 }
 
 impl SearcherConfig {
@@ -116,6 +238,14 @@ impl SearcherConfig {
         Byte::from_bytes(500_000_000) // 500M
     }
 
+    fn default_leaf_search_cache_capacity() -> Byte {
+        Byte::from_bytes(64_000_000) // 64M
+    }
+
+    fn default_split_searcher_cache_capacity() -> Byte {
+        Byte::from_bytes(256_000_000) // 256M
+    }
+
     fn default_max_num_concurrent_split_searches() -> usize {
         100
     }
@@ -123,6 +253,19 @@ impl SearcherConfig {
     fn default_max_num_concurrent_split_streams() -> usize {
         100
     }
+
+    fn default_max_term_expansions() -> usize {
+        1_000
+    }
+
+    fn default_split_replication_factor() -> usize {
+        2
+    }
+
+    pub fn leaf_search_hedging_delay(&self) -> Option<Duration> {
+        self.leaf_search_hedging_delay_millis
+            .map(Duration::from_millis)
+    }
 }
 
 impl Default for SearcherConfig {
@@ -130,12 +273,93 @@ impl Default for SearcherConfig {
         Self {
             fast_field_cache_capacity: Self::default_fast_field_cache_capacity(),
             split_footer_cache_capacity: Self::default_split_footer_cache_capacity(),
+            leaf_search_cache_capacity: Self::default_leaf_search_cache_capacity(),
+            split_searcher_cache_capacity: Self::default_split_searcher_cache_capacity(),
             max_num_concurrent_split_streams: Self::default_max_num_concurrent_split_streams(),
             max_num_concurrent_split_searches: Self::default_max_num_concurrent_split_searches(),
+            max_term_expansions: Self::default_max_term_expansions(),
+            split_replication_factor: Self::default_split_replication_factor(),
+            leaf_search_hedging_delay_millis: None,
+            remote_clusters: Vec::new(),
+            admission: SearchAdmissionConfig::default(),
         }
     }
 }
 
+/// Limits on the concurrent searches coordinated by a searcher. Searches beyond the limits wait
+/// in a queue, where interactive searches are admitted before batch searches, and are rejected
+/// when the queue is full or after waiting for too long.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchAdmissionConfig {
+    /// Maximum number of concurrent searches, interactive and batch.
+    #[serde(default = "SearchAdmissionConfig::default_max_concurrent_searches")]
+    pub max_concurrent_searches: usize,
+    /// Maximum number of concurrent batch searches, i.e. search streams and async searches.
+    #[serde(default = "SearchAdmissionConfig::default_max_concurrent_batch_searches")]
+    pub max_concurrent_batch_searches: usize,
+    /// Maximum number of concurrent searches targeting the same index. Unlimited by default.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_searches_per_index: Option<usize>,
+    /// Maximum number of searches waiting to be admitted.
+    #[serde(default = "SearchAdmissionConfig::default_max_queued_searches")]
+    pub max_queued_searches: usize,
+    /// Maximum duration a search waits to be admitted.
+    #[serde(default = "SearchAdmissionConfig::default_max_queue_wait_secs")]
+    pub max_queue_wait_secs: u64,
+}
+
+impl SearchAdmissionConfig {
+    fn default_max_concurrent_searches() -> usize {
+        100
+    }
+
+    fn default_max_concurrent_batch_searches() -> usize {
+        10
+    }
+
+    fn default_max_queued_searches() -> usize {
+        1_000
+    }
+
+    fn default_max_queue_wait_secs() -> u64 {
+        30
+    }
+}
+
+impl Default for SearchAdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_searches: Self::default_max_concurrent_searches(),
+            max_concurrent_batch_searches: Self::default_max_concurrent_batch_searches(),
+            max_concurrent_searches_per_index: None,
+            max_queued_searches: Self::default_max_queued_searches(),
+            max_queue_wait_secs: Self::default_max_queue_wait_secs(),
+        }
+    }
+}
+
+/// A remote Quickwit cluster searched by cross-cluster searches.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteClusterConfig {
+    /// Name of the cluster, reported in the errors of cross-cluster searches.
+    pub name: String,
+    /// gRPC endpoint of the searchers of the cluster, e.g. `http://searcher.eu-west.local:7281`.
+    pub grpc_endpoint: String,
+    /// Patterns of the IDs of the indexes searched on the cluster, e.g. `logs-*`. Searches on
+    /// other indexes are not forwarded to the cluster.
+    #[serde(default = "RemoteClusterConfig::default_index_patterns")]
+    pub index_patterns: Vec<String>,
+}
+
+impl RemoteClusterConfig {
+    fn default_index_patterns() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct IngestApiConfig {
@@ -143,6 +367,25 @@ pub struct IngestApiConfig {
     pub max_queue_memory_usage: Byte,
     #[serde(default = "IngestApiConfig::default_max_queue_disk_usage")]
     pub max_queue_disk_usage: Byte,
+    #[serde(default = "IngestApiConfig::default_max_decompressed_body_size")]
+    pub max_decompressed_body_size: Byte,
+    /// Number of peer indexers the documents ingested with `ack=replicas` are replicated to
+    /// before the request is acknowledged.
+    #[serde(default = "IngestApiConfig::default_replication_factor")]
+    pub replication_factor: usize,
+    /// How long the replicated documents are retained by the peer indexers. The leader is expected
+    /// to have indexed and published the documents within this period.
+    #[serde(default = "IngestApiConfig::default_replica_retention_secs")]
+    pub replica_retention_secs: u64,
+    /// How long the idempotency keys of the ingested batches are retained. The retries of a batch
+    /// within this period are dropped.
+    #[serde(default = "IngestApiConfig::default_idempotency_key_retention_secs")]
+    pub idempotency_key_retention_secs: u64,
+    /// Maximum number of shards the documents ingested into an index are spread over on each
+    /// indexer, each shard being indexed by its own pipeline. The control plane adds shards to the
+    /// indexes lagging behind, up to this number. The default of one shard disables the scaling.
+    #[serde(default = "IngestApiConfig::default_max_num_shards_per_indexer")]
+    pub max_num_shards_per_indexer: usize,
 }
 
 impl IngestApiConfig {
@@ -153,6 +396,34 @@ impl IngestApiConfig {
     fn default_max_queue_disk_usage() -> Byte {
         Byte::from_bytes(4 * 1024 * 1024 * 1024) // 4 GiB // TODO maybe we want more?
     }
+
+    fn default_max_decompressed_body_size() -> Byte {
+        Byte::from_bytes(100 * 1024 * 1024) // 100 MiB
+    }
+
+    fn default_replication_factor() -> usize {
+        1
+    }
+
+    fn default_replica_retention_secs() -> u64 {
+        3600 // 1 hour
+    }
+
+    fn default_idempotency_key_retention_secs() -> u64 {
+        3600 // 1 hour
+    }
+
+    fn default_max_num_shards_per_indexer() -> usize {
+        1
+    }
+
+    pub fn replica_retention(&self) -> Duration {
+        Duration::from_secs(self.replica_retention_secs)
+    }
+
+    pub fn idempotency_key_retention(&self) -> Duration {
+        Duration::from_secs(self.idempotency_key_retention_secs)
+    }
 }
 
 impl Default for IngestApiConfig {
@@ -160,6 +431,11 @@ impl Default for IngestApiConfig {
         Self {
             max_queue_memory_usage: Self::default_max_queue_memory_usage(),
             max_queue_disk_usage: Self::default_max_queue_disk_usage(),
+            max_decompressed_body_size: Self::default_max_decompressed_body_size(),
+            replication_factor: Self::default_replication_factor(),
+            replica_retention_secs: Self::default_replica_retention_secs(),
+            idempotency_key_retention_secs: Self::default_idempotency_key_retention_secs(),
+            max_num_shards_per_indexer: Self::default_max_num_shards_per_indexer(),
         }
     }
 }
@@ -187,6 +463,10 @@ pub struct JaegerConfig {
     /// The maximum number of spans that can be retrieved in a single request.
     #[serde(default = "JaegerConfig::default_max_fetch_spans")]
     pub max_fetch_spans: NonZeroU64,
+    /// The maximum number of spans scanned per hour of traces when computing the service
+    /// dependency graph (`get_dependencies` operation).
+    #[serde(default = "JaegerConfig::default_max_dependency_spans")]
+    pub max_dependency_spans: NonZeroU64,
 }
 
 impl JaegerConfig {
@@ -213,6 +493,10 @@ impl JaegerConfig {
     fn default_max_fetch_spans() -> NonZeroU64 {
         NonZeroU64::new(10_000).unwrap() // 10k spans
     }
+
+    fn default_max_dependency_spans() -> NonZeroU64 {
+        NonZeroU64::new(1_000_000).unwrap() // 1M spans
+    }
 }
 
 impl Default for JaegerConfig {
@@ -222,14 +506,546 @@ impl Default for JaegerConfig {
             lookback_period_hours: Self::default_lookback_period_hours(),
             max_trace_duration_secs: Self::default_max_trace_duration_secs(),
             max_fetch_spans: Self::default_max_fetch_spans(),
+            max_dependency_spans: Self::default_max_dependency_spans(),
+        }
+    }
+}
+
+/// Operations an API key can be granted.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Serialize,
+    Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Ingesting documents into indexes.
+    Ingest,
+    /// Searching indexes.
+    Search,
+    /// Managing indexes, sources, and API keys. Implies the other scopes.
+    Admin,
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let scope_str = match self {
+            Self::Ingest => "ingest",
+            Self::Search => "search",
+            Self::Admin => "admin",
+        };
+        write!(formatter, "{scope_str}")
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Requires the REST and gRPC requests to carry an API key granting the requested operation.
+    #[serde(default)]
+    pub enable_api_keys: bool,
+    /// Admin API key accepted in addition to the keys stored in the metastore, used to create the
+    /// first keys. Never serialized.
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub bootstrap_admin_key: Option<String>,
+    /// Secret shared by the nodes of the cluster, which they present to each other when calling
+    /// the internal gRPC services. Never serialized.
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub cluster_secret: Option<String>,
+    /// Interval at which the API keys are reloaded from the metastore.
+    #[serde(default = "AuthConfig::default_api_keys_refresh_interval_secs")]
+    api_keys_refresh_interval_secs: NonZeroU64,
+    /// Validation of the JWTs issued by an OpenID Connect provider. Requests are authorized with
+    /// either API keys or JWTs when set.
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+    /// Named sets of permissions, assignable to API keys and to the roles carried by JWTs.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<RoleConfig>,
+}
+
+impl AuthConfig {
+    /// Returns whether the requests must be authenticated.
+    pub fn is_enabled(&self) -> bool {
+        self.enable_api_keys || self.oidc.is_some()
+    }
+
+    pub fn api_keys_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.api_keys_refresh_interval_secs.get())
+    }
+
+    fn default_api_keys_refresh_interval_secs() -> NonZeroU64 {
+        NonZeroU64::new(10).unwrap()
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enable_api_keys: false,
+            bootstrap_admin_key: None,
+            cluster_secret: None,
+            api_keys_refresh_interval_secs: Self::default_api_keys_refresh_interval_secs(),
+            oidc: None,
+            roles: Vec::new(),
         }
     }
 }
 
+/// Role granting a set of permissions, each allowing some operations on the indexes matching a
+/// set of patterns.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleConfig {
+    pub name: String,
+    pub permissions: Vec<PermissionConfig>,
+    /// Fields masked in the documents returned by the searches of the role bearers.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub field_masks: Vec<FieldMaskConfig>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionConfig {
+    /// Operations allowed by the permission.
+    pub scopes: BTreeSet<ApiKeyScope>,
+    /// Patterns of the index IDs the operations are allowed on. Defaults to all the indexes.
+    #[serde(default = "PermissionConfig::default_index_patterns")]
+    pub index_patterns: Vec<String>,
+}
+
+impl PermissionConfig {
+    fn default_index_patterns() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+}
+
+/// Masks a field of the documents returned by the searches on the indexes matching a set of
+/// patterns.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMaskConfig {
+    /// Path of the masked field. The fields of objects are separated by dots, e.g. `user.email`.
+    pub field: String,
+    #[serde(default)]
+    pub action: FieldMaskActionConfig,
+    /// Patterns of the index IDs the mask applies to. Defaults to all the indexes.
+    #[serde(default = "FieldMaskConfig::default_index_patterns")]
+    pub index_patterns: Vec<String>,
+}
+
+impl FieldMaskConfig {
+    fn default_index_patterns() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldMaskActionConfig {
+    /// Removes the field from the documents.
+    #[default]
+    Exclude,
+    /// Replaces the value of the field with its hash, so that documents sharing the same value
+    /// can still be correlated.
+    Hash,
+}
+
+/// Mutual TLS settings of the cluster-internal gRPC traffic.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GrpcTlsConfig {
+    /// Path of the PEM-encoded certificate chain of the node, presented both as a server and as a
+    /// client.
+    pub cert_path: PathBuf,
+    /// Path of the PEM-encoded private key of the node.
+    pub key_path: PathBuf,
+    /// Path of the PEM-encoded certificates of the authorities the certificates of the other
+    /// nodes must be issued by.
+    pub ca_cert_path: PathBuf,
+    /// Name the certificates of the other nodes must be valid for, since nodes are reached by IP
+    /// address.
+    #[serde(default = "GrpcTlsConfig::default_server_name")]
+    pub server_name: String,
+    /// SPIFFE IDs the client certificates of the other nodes must carry one of. A trailing `*`
+    /// matches any suffix. When empty, any certificate issued by the authorities is accepted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_spiffe_ids: Vec<String>,
+    /// Interval at which the certificate files are checked for changes, so that rotated
+    /// certificates are picked up without restarting the node.
+    #[serde(default = "GrpcTlsConfig::default_reload_interval_secs")]
+    reload_interval_secs: NonZeroU64,
+}
+
+impl GrpcTlsConfig {
+    pub fn reload_interval(&self) -> Duration {
+        Duration::from_secs(self.reload_interval_secs.get())
+    }
+
+    fn default_server_name() -> String {
+        "quickwit".to_string()
+    }
+
+    fn default_reload_interval_secs() -> NonZeroU64 {
+        NonZeroU64::new(60).unwrap()
+    }
+}
+
+/// Rate limits of the REST ingest and search endpoints, enforced by each node independently.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitsConfig {
+    #[serde(default)]
+    pub ingest: EndpointRateLimitsConfig,
+    #[serde(default)]
+    pub search: EndpointRateLimitsConfig,
+}
+
+impl RateLimitsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.ingest.is_enabled() || self.search.is_enabled()
+    }
+}
+
+/// Rate limits applied to the requests of an endpoint family, keyed by the API key, the client IP
+/// address, and the target index of the requests.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndpointRateLimitsConfig {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_api_key: Option<RateLimitConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_ip: Option<RateLimitConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_index: Option<RateLimitConfig>,
+}
+
+impl EndpointRateLimitsConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.per_api_key.is_some() || self.per_ip.is_some() || self.per_index.is_some()
+    }
+}
+
+/// Token-bucket rate limit. Each bucket holds one second worth of tokens, which bounds bursts.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_sec: Option<NonZeroU64>,
+    /// Limit on the request body bytes, as declared by the `Content-Length` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_per_sec: Option<Byte>,
+}
+
+/// Audit log of the REST API calls.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuditLogConfig {
+    /// Destination of the audit events.
+    pub sink: AuditLogSinkConfig,
+    /// Kinds of calls to audit. Defaults to all of them.
+    #[serde(default = "AuditLogConfig::default_actions")]
+    pub actions: BTreeSet<ApiKeyScope>,
+    /// Patterns of the index IDs whose calls are audited. The calls whose indexes cannot be told
+    /// from the request path are always audited. Defaults to all the indexes.
+    #[serde(default = "AuditLogConfig::default_index_patterns")]
+    pub index_patterns: Vec<String>,
+    /// Whether successful calls are audited, in addition to the failed ones.
+    #[serde(default = "AuditLogConfig::default_include_successful")]
+    pub include_successful: bool,
+}
+
+impl AuditLogConfig {
+    fn default_actions() -> BTreeSet<ApiKeyScope> {
+        BTreeSet::from([ApiKeyScope::Ingest, ApiKeyScope::Search, ApiKeyScope::Admin])
+    }
+
+    fn default_index_patterns() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+
+    fn default_include_successful() -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditLogSinkConfig {
+    /// Appends the events as JSON lines to a local file.
+    File { path: PathBuf },
+    /// Ingests the events into a Quickwit index, created on startup if it does not exist.
+    Index {
+        #[serde(default = "AuditLogSinkConfig::default_index_id")]
+        index_id: String,
+    },
+}
+
+impl AuditLogSinkConfig {
+    fn default_index_id() -> String {
+        "quickwit-audit-log".to_string()
+    }
+}
+
+/// Log of the root searches, ingested into the internal `_quickwit_queries` index.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QueryLogConfig {
+    /// Searches taking longer than this threshold are logged with extended details: the full
+    /// search request and the IDs of the searched splits.
+    #[serde(default = "QueryLogConfig::default_slow_query_threshold_ms")]
+    pub slow_query_threshold_ms: u64,
+}
+
+impl QueryLogConfig {
+    fn default_slow_query_threshold_ms() -> u64 {
+        1_000
+    }
+}
+
+impl Default for QueryLogConfig {
+    fn default() -> Self {
+        Self {
+            slow_query_threshold_ms: Self::default_slow_query_threshold_ms(),
+        }
+    }
+}
+
+/// Headers added by the REST server to its responses.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RestConfig {
+    /// Cross-origin resource sharing policy. Cross-origin requests are not allowed when not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    #[serde(default)]
+    pub security_headers: SecurityHeadersConfig,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins allowed to query the REST API, e.g. `https://app.example.com`, or `*` for any
+    /// origin.
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "CorsConfig::default_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    #[serde(default = "CorsConfig::default_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+    /// Whether the browser may send credentials (cookies, `Authorization` headers) with
+    /// cross-origin requests. Cannot be combined with the `*` origin.
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// Duration for which the browser may cache the response to a preflight request.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+}
+
+impl CorsConfig {
+    fn default_allowed_methods() -> Vec<String> {
+        ["GET", "POST", "PUT", "DELETE"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn default_allowed_headers() -> Vec<String> {
+        ["authorization", "content-type"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Returns whether any origin is allowed.
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+/// Standard security headers. When enabled, `X-Content-Type-Options: nosniff`,
+/// `X-Frame-Options: DENY`, and `Referrer-Policy: no-referrer` are added to the responses.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecurityHeadersConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sets the `Strict-Transport-Security` header, for nodes served behind a TLS-terminating
+    /// proxy.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hsts_max_age_secs: Option<u64>,
+    /// Sets the `Content-Security-Policy` header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_security_policy: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcConfig {
+    /// URL of the OpenID Connect provider, which must match the `iss` claim of the tokens.
+    pub issuer_url: String,
+    /// Audience the tokens must be issued for, matched against their `aud` claim.
+    pub audience: String,
+    /// URL of the JSON Web Key Set of the provider. Discovered from
+    /// `<issuer_url>/.well-known/openid-configuration` when not set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jwks_uri: Option<String>,
+    /// Claim holding the roles of the token bearer. Nested claims are separated by dots, for
+    /// instance `realm_access.roles`.
+    #[serde(default = "OidcConfig::default_roles_claim")]
+    pub roles_claim: String,
+    /// Permissions granted to the bearers of each role.
+    #[serde(default)]
+    pub role_mappings: Vec<OidcRoleMapping>,
+    /// Interval at which the JSON Web Key Set is reloaded, so that rotated keys are picked up.
+    #[serde(default = "OidcConfig::default_jwks_refresh_interval_secs")]
+    jwks_refresh_interval_secs: NonZeroU64,
+}
+
+impl OidcConfig {
+    pub fn jwks_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.jwks_refresh_interval_secs.get())
+    }
+
+    fn default_roles_claim() -> String {
+        "roles".to_string()
+    }
+
+    fn default_jwks_refresh_interval_secs() -> NonZeroU64 {
+        NonZeroU64::new(300).unwrap()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OidcRoleMapping {
+    /// Role, as it appears in the roles claim of the tokens.
+    pub role: String,
+    /// Operations granted to the role.
+    pub scopes: BTreeSet<ApiKeyScope>,
+    /// Patterns of the index IDs the role grants access to. Defaults to all the indexes.
+    #[serde(default = "OidcRoleMapping::default_index_patterns")]
+    pub index_patterns: Vec<String>,
+}
+
+impl OidcRoleMapping {
+    fn default_index_patterns() -> Vec<String> {
+        vec!["*".to_string()]
+    }
+}
+
+/// Quotas on the volume of documents ingested per day and stored by the indexes.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuotasConfig {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<QuotaConfig>,
+    /// Interval at which the stored bytes of the indexes are recomputed from the metastore.
+    #[serde(default = "QuotasConfig::default_usage_refresh_interval_secs")]
+    usage_refresh_interval_secs: NonZeroU64,
+}
+
+impl QuotasConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    pub fn usage_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.usage_refresh_interval_secs.get())
+    }
+
+    fn default_usage_refresh_interval_secs() -> NonZeroU64 {
+        NonZeroU64::new(60).unwrap()
+    }
+}
+
+impl Default for QuotasConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            usage_refresh_interval_secs: Self::default_usage_refresh_interval_secs(),
+        }
+    }
+}
+
+/// Quota applying to the indexes whose ID matches one of the patterns. The first matching rule
+/// applies.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuotaConfig {
+    /// Tenant owning the matching indexes. When set, the usage of all the matching indexes counts
+    /// against a single quota. Otherwise, each matching index has its own quota.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub index_id_patterns: Vec<String>,
+    /// Limit on the bytes ingested per UTC day.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ingest_bytes_per_day: Option<Byte>,
+    /// Limit on the bytes of the published splits.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_stored_bytes: Option<Byte>,
+    #[serde(default)]
+    pub on_exceeded: QuotaExceededAction,
+    /// Ingest throughput allowed once the quota is exceeded, with the `throttle` action.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttled_bytes_per_sec: Option<Byte>,
+}
+
+impl QuotaConfig {
+    /// Returns whether the index ID matches one of the quota patterns.
+    pub fn matches(&self, index_id: &str) -> bool {
+        self.index_id_patterns
+            .iter()
+            .any(|index_id_pattern| index_id_matches_pattern(index_id, index_id_pattern))
+    }
+}
+
+/// Behavior of the ingest API once a quota is exceeded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaExceededAction {
+    /// The ingest requests are rejected.
+    #[default]
+    Reject,
+    /// The ingest requests are accepted at the throttled throughput.
+    Throttle,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct QuickwitConfig {
     pub cluster_id: String,
     pub node_id: String,
+    /// Availability zone (or rack) of the node, used to spread the indexing workload of an index
+    /// across zones.
+    pub availability_zone: Option<String>,
     pub enabled_services: HashSet<QuickwitService>,
     pub rest_listen_addr: SocketAddr,
     pub gossip_listen_addr: SocketAddr,
@@ -240,10 +1056,20 @@ pub struct QuickwitConfig {
     pub data_dir_path: PathBuf,
     pub metastore_uri: Uri,
     pub default_index_root_uri: Uri,
+    /// Log filter of the node, with the syntax of the `RUST_LOG` environment variable. It can be
+    /// changed without restarting the node.
+    pub log_level: Option<String>,
     pub indexer_config: IndexerConfig,
     pub searcher_config: SearcherConfig,
     pub ingest_api_config: IngestApiConfig,
     pub jaeger_config: JaegerConfig,
+    pub auth_config: AuthConfig,
+    pub grpc_tls_config: Option<GrpcTlsConfig>,
+    pub rate_limits_config: RateLimitsConfig,
+    pub audit_log_config: Option<AuditLogConfig>,
+    pub query_log_config: Option<QueryLogConfig>,
+    pub rest_config: RestConfig,
+    pub quotas_config: QuotasConfig,
 }
 
 impl QuickwitConfig {
@@ -261,6 +1087,17 @@ impl QuickwitConfig {
         Ok(config)
     }
 
+    /// Parses and validates a [`QuickwitConfig`] like [`QuickwitConfig::load`], but reports
+    /// errors with the path of the offending field and, when possible, a suggestion. The data dir
+    /// is not required to exist.
+    pub async fn validate(
+        config_format: ConfigFormat,
+        config_content: &[u8],
+    ) -> Result<Self, ConfigError> {
+        let env_vars = env::vars().collect::<HashMap<_, _>>();
+        validate_quickwit_config_with_env(config_format, config_content, &env_vars).await
+    }
+
     /// Returns the list of peer seed addresses. The addresses MUST NOT be resolved. Otherwise, the
     /// DNS-based discovery mechanism implemented in Chitchat will not work correctly.
     pub async fn peer_seed_addrs(&self) -> anyhow::Result<Vec<String>> {
@@ -33,6 +33,9 @@ pub enum QuickwitService {
     Searcher,
     Janitor,
     Metastore,
+    /// Merges the splits published by the indexers, so that the indexers do not spend CPU and
+    /// disk on merges.
+    Merger,
 }
 
 impl QuickwitService {
@@ -43,12 +46,21 @@ impl QuickwitService {
             QuickwitService::Searcher => "searcher",
             QuickwitService::Janitor => "janitor",
             QuickwitService::Metastore => "metastore",
+            QuickwitService::Merger => "merger",
         }
     }
 
     pub fn supported_services() -> HashSet<QuickwitService> {
         all::<QuickwitService>().collect()
     }
+
+    /// Returns the services enabled when the node config does not list them. The merger service
+    /// is opt-in: enabling it moves the merges of every indexer to the merger nodes.
+    pub fn default_services() -> HashSet<QuickwitService> {
+        all::<QuickwitService>()
+            .filter(|service| *service != QuickwitService::Merger)
+            .collect()
+    }
 }
 
 impl Display for QuickwitService {
@@ -67,6 +79,7 @@ impl FromStr for QuickwitService {
             "searcher" => Ok(QuickwitService::Searcher),
             "janitor" => Ok(QuickwitService::Janitor),
             "metastore" => Ok(QuickwitService::Metastore),
+            "merger" => Ok(QuickwitService::Merger),
             _ => {
                 bail!(
                     "Failed to parse service `{service_str}`. Supported services are: `{}`.",
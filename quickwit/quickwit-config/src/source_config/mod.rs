@@ -84,6 +84,7 @@ impl SourceConfig {
     pub fn source_type(&self) -> &str {
         match self.source_params {
             SourceParams::File(_) => "file",
+            SourceParams::Index(_) => "index",
             SourceParams::Kafka(_) => "kafka",
             SourceParams::Kinesis(_) => "kinesis",
             SourceParams::Vec(_) => "vec",
@@ -98,6 +99,7 @@ impl SourceConfig {
     pub fn params(&self) -> JsonValue {
         match &self.source_params {
             SourceParams::File(params) => serde_json::to_value(params),
+            SourceParams::Index(params) => serde_json::to_value(params),
             SourceParams::Kafka(params) => serde_json::to_value(params),
             SourceParams::Kinesis(params) => serde_json::to_value(params),
             SourceParams::Vec(params) => serde_json::to_value(params),
@@ -158,6 +160,8 @@ impl TestableForRegression for SourceConfig {
                 client_log_level: None,
                 client_params: serde_json::json!({}),
                 enable_backfill_mode: false,
+                avro: None,
+                protobuf: None,
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
@@ -176,6 +180,8 @@ impl TestableForRegression for SourceConfig {
 pub enum SourceParams {
     #[serde(rename = "file")]
     File(FileSourceParams),
+    #[serde(rename = "index")]
+    Index(IndexSourceParams),
     #[serde(rename = "kafka")]
     Kafka(KafkaSourceParams),
     #[serde(rename = "kinesis")]
@@ -215,6 +221,38 @@ pub struct FileSourceParams {
     #[serde(default)]
     #[serde(deserialize_with = "absolute_filepath_from_str")]
     pub filepath: Option<PathBuf>, //< If None read from stdin.
+    /// When set, the file is read as CSV instead of NDJSON.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csv: Option<CsvOptions>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CsvOptions {
+    /// Names of the columns. When not set, the first line of the file is used as header.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<String>>,
+    /// Field delimiter, `,` by default.
+    #[schema(value_type = String)]
+    #[serde(default = "CsvOptions::default_delimiter")]
+    pub delimiter: char,
+}
+
+impl CsvOptions {
+    fn default_delimiter() -> char {
+        ','
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            delimiter: Self::default_delimiter(),
+        }
+    }
 }
 
 // Deserializing a filepath string into an absolute filepath.
@@ -233,11 +271,15 @@ impl FileSourceParams {
     pub fn file<P: AsRef<Path>>(filepath: P) -> Self {
         FileSourceParams {
             filepath: Some(filepath.as_ref().to_path_buf()),
+            csv: None,
         }
     }
 
     pub fn stdin() -> Self {
-        FileSourceParams { filepath: None }
+        FileSourceParams {
+            filepath: None,
+            csv: None,
+        }
     }
 }
 
@@ -259,6 +301,43 @@ pub struct KafkaSourceParams {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_false")]
     pub enable_backfill_mode: bool,
+    /// When set, messages are decoded as Avro records instead of JSON objects.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avro: Option<AvroOptions>,
+    /// When set, messages are decoded as Protobuf messages instead of JSON objects.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protobuf: Option<ProtobufOptions>,
+}
+
+/// Decoding options of Avro messages encoded with the Confluent wire format, i.e. prefixed with
+/// the ID of their writer schema in a schema registry.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AvroOptions {
+    /// URL of the Confluent-compatible schema registry resolving the writer schemas.
+    pub schema_registry_url: String,
+    /// Username for the basic authentication of the schema registry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_registry_username: Option<String>,
+    /// Password for the basic authentication of the schema registry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_registry_password: Option<String>,
+}
+
+/// Decoding options of binary Protobuf messages.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProtobufOptions {
+    /// URI of the file descriptor set describing the messages, as produced by
+    /// `protoc --include_imports --descriptor_set_out`.
+    #[schema(value_type = String)]
+    pub descriptor_set_uri: Uri,
+    /// Fully qualified name of the message type, e.g. `my.package.Event`.
+    pub message_type: String,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -277,6 +356,11 @@ pub struct KinesisSourceParams {
     /// When backfill mode is enabled, the source exits after reaching the end of the stream.
     #[serde(skip_serializing_if = "is_false")]
     pub enable_backfill_mode: bool,
+    /// When set, the source registers an enhanced fan-out consumer with this name on the stream,
+    /// or reuses the existing one, and the records are pushed to the source with
+    /// `SubscribeToShard` instead of being polled with `GetRecords`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enhanced_fan_out_consumer_name: Option<String>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
@@ -287,6 +371,8 @@ struct KinesisSourceParamsInner {
     pub endpoint: Option<String>,
     #[serde(default)]
     pub enable_backfill_mode: bool,
+    #[serde(default)]
+    pub enhanced_fan_out_consumer_name: Option<String>,
 }
 
 impl TryFrom<KinesisSourceParamsInner> for KinesisSourceParams {
@@ -306,10 +392,20 @@ impl TryFrom<KinesisSourceParamsInner> for KinesisSourceParams {
             stream_name: value.stream_name,
             region_or_endpoint,
             enable_backfill_mode: value.enable_backfill_mode,
+            enhanced_fan_out_consumer_name: value.enhanced_fan_out_consumer_name,
         })
     }
 }
 
+/// Reads the documents stored in the published splits of another index, for instance to reindex
+/// them with a different doc mapping.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IndexSourceParams {
+    /// ID of the index to read the documents from.
+    pub index_id: String,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct VecSourceParams {
@@ -341,6 +437,36 @@ pub struct PulsarSourceParams {
     #[serde(default, with = "serde_yaml::with::singleton_map")]
     /// Authentication for pulsar.
     pub authentication: Option<PulsarSourceAuth>,
+    /// When set, messages are decoded as Protobuf messages instead of JSON objects.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protobuf: Option<ProtobufOptions>,
+    /// Subscription mode of the source consumers.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "PulsarSubscriptionType::is_failover")]
+    pub subscription_type: PulsarSubscriptionType,
+    /// URL of the Pulsar admin REST API. When set, messages published with an Avro or JSON
+    /// schema are decoded with the topic schema fetched from the Pulsar schema registry.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_url: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PulsarSubscriptionType {
+    /// A single consumer receives the messages of each topic partition, preserving their order.
+    #[default]
+    Failover,
+    /// The messages are distributed across the consumers by key, preserving the order of the
+    /// messages sharing the same key.
+    KeyShared,
+}
+
+impl PulsarSubscriptionType {
+    fn is_failover(&self) -> bool {
+        *self == PulsarSubscriptionType::Failover
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
@@ -390,9 +516,9 @@ impl TransformConfig {
         let timezone_str = self.timezone_opt.as_deref().unwrap_or("UTC");
         let timezone = TimeZone::parse(timezone_str).with_context(|| {
             format!(
-            "Failed to parse timezone: `{timezone_str}`. Timezone must be a valid name \
+                "Failed to parse timezone: `{timezone_str}`. Timezone must be a valid name \
             in the TZ database: https://en.wikipedia.org/wiki/List_of_tz_database_time_zones"
-        )
+            )
         })?;
         // Append "\n." to the script to return the entire document and not only the modified
         // fields.
@@ -466,6 +592,8 @@ mod tests {
                 client_log_level: None,
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                avro: None,
+                protobuf: None,
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
@@ -484,6 +612,8 @@ mod tests {
                 client_log_level: None,
                 client_params: json!(null),
                 enable_backfill_mode: false,
+                avro: None,
+                protobuf: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -498,6 +628,8 @@ mod tests {
                 client_log_level: Some("info".to_string()),
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                avro: None,
+                protobuf: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -521,6 +653,8 @@ mod tests {
                     client_log_level: None,
                     client_params: json!(null),
                     enable_backfill_mode: false,
+                    avro: None,
+                    protobuf: None,
                 }
             );
         }
@@ -539,6 +673,52 @@ mod tests {
                     client_log_level: Some("info".to_string()),
                     client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                     enable_backfill_mode: true,
+                    avro: None,
+                    protobuf: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    topic: my-topic
+                    avro:
+                        schema_registry_url: http://localhost:8081
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap(),
+                KafkaSourceParams {
+                    topic: "my-topic".to_string(),
+                    client_log_level: None,
+                    client_params: json!(null),
+                    enable_backfill_mode: false,
+                    avro: Some(AvroOptions {
+                        schema_registry_url: "http://localhost:8081".to_string(),
+                        schema_registry_username: None,
+                        schema_registry_password: None,
+                    }),
+                    protobuf: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    topic: my-topic
+                    protobuf:
+                        descriptor_set_uri: s3://my-bucket/events.desc
+                        message_type: my.package.Event
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap(),
+                KafkaSourceParams {
+                    topic: "my-topic".to_string(),
+                    client_log_level: None,
+                    client_params: json!(null),
+                    enable_backfill_mode: false,
+                    avro: None,
+                    protobuf: Some(ProtobufOptions {
+                        descriptor_set_uri: Uri::from_well_formed("s3://my-bucket/events.desc"),
+                        message_type: "my.package.Event".to_string(),
+                    }),
                 }
             );
         }
@@ -561,6 +741,7 @@ mod tests {
                 stream_name: "emr-cluster-logs".to_string(),
                 region_or_endpoint: None,
                 enable_backfill_mode: false,
+                enhanced_fan_out_consumer_name: None,
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
@@ -676,9 +857,27 @@ mod tests {
             "#;
             load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
                 .unwrap_err();
-            // TODO: uncomment asserts once distributed indexing is activated for pulsar.
-            // assert_eq!(source_config.desired_num_pipelines(), 3);
-            // assert_eq!(source_config.max_num_pipelines_per_indexer(), 3);
+        }
+        {
+            let content = r#"
+            {
+                "version": "0.4",
+                "source_id": "hdfs-logs-pulsar-source",
+                "desired_num_pipelines": 3,
+                "max_num_pipelines_per_indexer": 3,
+                "source_type": "pulsar",
+                "params": {
+                    "topics": ["my-topic"],
+                    "address": "http://localhost:6650",
+                    "subscription_type": "key_shared"
+                }
+            }
+            "#;
+            let source_config =
+                load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
+                    .unwrap();
+            assert_eq!(source_config.desired_num_pipelines.get(), 3);
+            assert_eq!(source_config.max_num_pipelines_per_indexer.get(), 3);
         }
     }
 
@@ -693,8 +892,69 @@ mod tests {
             assert_eq!(
                 file_params.filepath.unwrap().as_path(),
                 uri.filepath().unwrap()
-            )
+            );
+            assert!(file_params.csv.is_none());
         }
+        {
+            let yaml = r#"
+                filepath: source-path.csv
+                csv:
+                  columns: [timestamp, body]
+                  delimiter: ";"
+            "#;
+            let file_params = serde_yaml::from_str::<FileSourceParams>(yaml).unwrap();
+            assert_eq!(
+                file_params.csv.unwrap(),
+                CsvOptions {
+                    columns: Some(vec!["timestamp".to_string(), "body".to_string()]),
+                    delimiter: ';',
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                filepath: source-path.csv
+                csv: {}
+            "#;
+            let file_params = serde_yaml::from_str::<FileSourceParams>(yaml).unwrap();
+            assert_eq!(file_params.csv.unwrap(), CsvOptions::default());
+        }
+    }
+
+    #[test]
+    fn test_index_source_config_deserialization() {
+        let source_config_json = r#"{
+            "version": "0.4",
+            "source_id": "reindex-source",
+            "source_type": "index",
+            "params": {
+                "index_id": "my-index"
+            }
+        }"#;
+        let source_config =
+            load_source_config_from_user_config(ConfigFormat::Json, source_config_json.as_bytes())
+                .unwrap();
+        assert_eq!(source_config.source_type(), "index");
+        assert_eq!(
+            source_config.source_params,
+            SourceParams::Index(IndexSourceParams {
+                index_id: "my-index".to_string(),
+            })
+        );
+
+        let invalid_source_config_json = r#"{
+            "version": "0.4",
+            "source_id": "reindex-source",
+            "source_type": "index",
+            "params": {
+                "index_id": "my index"
+            }
+        }"#;
+        load_source_config_from_user_config(
+            ConfigFormat::Json,
+            invalid_source_config_json.as_bytes(),
+        )
+        .unwrap_err();
     }
 
     #[test]
@@ -704,6 +964,7 @@ mod tests {
                 stream_name: "my-stream".to_string(),
                 region_or_endpoint: None,
                 enable_backfill_mode: false,
+                enhanced_fan_out_consumer_name: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -717,6 +978,7 @@ mod tests {
                 stream_name: "my-stream".to_string(),
                 region_or_endpoint: Some(RegionOrEndpoint::Region("us-west-1".to_string())),
                 enable_backfill_mode: false,
+                enhanced_fan_out_consumer_name: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -732,6 +994,7 @@ mod tests {
                     "https://localhost:4566".to_string(),
                 )),
                 enable_backfill_mode: false,
+                enhanced_fan_out_consumer_name: None,
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -756,6 +1019,7 @@ mod tests {
                     stream_name: "my-stream".to_string(),
                     region_or_endpoint: None,
                     enable_backfill_mode: false,
+                    enhanced_fan_out_consumer_name: None,
                 }
             );
         }
@@ -771,6 +1035,22 @@ mod tests {
                     stream_name: "my-stream".to_string(),
                     region_or_endpoint: Some(RegionOrEndpoint::Region("us-west-1".to_string())),
                     enable_backfill_mode: true,
+                    enhanced_fan_out_consumer_name: None,
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    stream_name: my-stream
+                    enhanced_fan_out_consumer_name: quickwit
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<KinesisSourceParams>(yaml).unwrap(),
+                KinesisSourceParams {
+                    stream_name: "my-stream".to_string(),
+                    region_or_endpoint: None,
+                    enable_backfill_mode: false,
+                    enhanced_fan_out_consumer_name: Some("quickwit".to_string()),
                 }
             );
         }
@@ -801,6 +1081,9 @@ mod tests {
                     address: "pulsar://localhost:6560".to_string(),
                     consumer_name: "my-pulsar-consumer".to_string(),
                     authentication: None,
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    admin_url: None,
                 }
             );
         }
@@ -821,6 +1104,9 @@ mod tests {
                     address: "pulsar://localhost:6560".to_string(),
                     consumer_name: "my-pulsar-consumer".to_string(),
                     authentication: Some(PulsarSourceAuth::Token("my-token".to_string())),
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    admin_url: None,
                 }
             );
         }
@@ -848,6 +1134,9 @@ mod tests {
                         audience: None,
                         scope: None,
                     }),
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    admin_url: None,
                 }
             );
         }
@@ -877,6 +1166,31 @@ mod tests {
                         audience: Some("my-audience".to_string()),
                         scope: Some("read+write".to_string()),
                     }),
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    admin_url: None,
+                }
+            );
+        }
+
+        {
+            let yaml = r#"
+                    topics:
+                        - my-topic
+                    address: pulsar://localhost:6560
+                    subscription_type: key_shared
+                    admin_url: http://localhost:8080
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<PulsarSourceParams>(yaml).unwrap(),
+                PulsarSourceParams {
+                    topics: vec!["my-topic".to_string()],
+                    address: "pulsar://localhost:6560".to_string(),
+                    consumer_name: default_consumer_name(),
+                    authentication: None,
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::KeyShared,
+                    admin_url: Some("http://localhost:8080".to_string()),
                 }
             );
         }
@@ -903,6 +1217,9 @@ mod tests {
                     address: "pulsar://localhost:6560".to_string(),
                     consumer_name: default_consumer_name(),
                     authentication: None,
+                    protobuf: None,
+                    subscription_type: PulsarSubscriptionType::Failover,
+                    admin_url: None,
                 }
             );
         }
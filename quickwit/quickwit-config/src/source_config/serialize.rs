@@ -24,8 +24,8 @@ use serde::{Deserialize, Serialize};
 
 use super::TransformConfig;
 use crate::{
-    validate_identifier, ConfigFormat, SourceConfig, SourceParams, CLI_INGEST_SOURCE_ID,
-    INGEST_API_SOURCE_ID,
+    validate_identifier, ConfigFormat, PulsarSubscriptionType, SourceConfig, SourceParams,
+    CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID,
 };
 
 type SourceConfigForSerialization = SourceConfigV0_4;
@@ -87,8 +87,38 @@ impl SourceConfigForSerialization {
                         self.source_id
                     )
                 }
+                if let Some(csv_options) = &file_params.csv {
+                    if !csv_options.delimiter.is_ascii() {
+                        bail!(
+                            "CSV delimiter `{}` of source `{}` must be an ASCII character.",
+                            csv_options.delimiter,
+                            self.source_id
+                        )
+                    }
+                }
+            }
+            SourceParams::Index(index_params) => {
+                validate_identifier("Index ID", &index_params.index_id)?;
+            }
+            SourceParams::Kafka(kafka_params) => {
+                if kafka_params.avro.is_some() && kafka_params.protobuf.is_some() {
+                    bail!(
+                        "Source `{}` cannot decode messages as both Avro and Protobuf.",
+                        self.source_id
+                    )
+                }
+                if let Some(avro_options) = &kafka_params.avro {
+                    let url = &avro_options.schema_registry_url;
+                    if !url.starts_with("http://") && !url.starts_with("https://") {
+                        bail!(
+                            "Schema registry URL `{}` of source `{}` must be an HTTP(S) URL.",
+                            avro_options.schema_registry_url,
+                            self.source_id
+                        )
+                    }
+                }
             }
-            SourceParams::Kafka(_) | SourceParams::Kinesis(_) | SourceParams::Pulsar(_) => {
+            SourceParams::Kinesis(_) | SourceParams::Pulsar(_) => {
                 // TODO consider any validation opportunity
             }
             SourceParams::Vec(_)
@@ -98,9 +128,11 @@ impl SourceConfigForSerialization {
         }
         match &self.source_params {
             SourceParams::Kafka(_) => {}
+            SourceParams::Pulsar(pulsar_params)
+                if pulsar_params.subscription_type == PulsarSubscriptionType::KeyShared => {}
             _ => {
                 if self.desired_num_pipelines > 1 || self.max_num_pipelines_per_indexer > 1 {
-                    bail!("Quickwit currently supports multiple pipelines only for Kafka sources. Open an issue https://github.com/quickwit-oss/quickwit/issues if you need the feature for other source types.");
+                    bail!("Quickwit currently supports multiple pipelines only for Kafka sources and Pulsar sources with a `key_shared` subscription. Open an issue https://github.com/quickwit-oss/quickwit/issues if you need the feature for other source types.");
                 }
             }
         }
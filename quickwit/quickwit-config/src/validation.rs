@@ -0,0 +1,247 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use crate::ConfigFormat;
+
+/// Error reported when validating a config file. Deserialization errors carry the path of the
+/// offending field, e.g. `indexer.split_store_max_num_bytes`, and, for unknown fields or
+/// variants, the closest known one.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ConfigError {
+    pub path_opt: Option<String>,
+    pub message: String,
+    pub suggestion_opt: Option<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = &self.path_opt {
+            write!(f, "`{path}`: ")?;
+        }
+        write!(f, "{}", self.message)?;
+
+        if let Some(suggestion) = &self.suggestion_opt {
+            write!(f, " {suggestion}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<anyhow::Error> for ConfigError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            path_opt: None,
+            message: format!("{error:#}"),
+            suggestion_opt: None,
+        }
+    }
+}
+
+/// Parses a config file holding a `version` field into `T`, the config of that version, keeping
+/// track of the path of the field being deserialized.
+pub(crate) fn parse_versioned_config<T: DeserializeOwned>(
+    config_format: ConfigFormat,
+    config_content: &[u8],
+    supported_versions: &[&str],
+) -> Result<T, ConfigError> {
+    let mut config_value: JsonValue = config_format.parse(config_content)?;
+    let latest_version = supported_versions
+        .last()
+        .expect("At least one config version should be supported.");
+    let version_value = config_value
+        .as_object_mut()
+        .and_then(|config_object| config_object.remove("version"))
+        .ok_or_else(|| ConfigError {
+            path_opt: Some("version".to_string()),
+            message: "missing field `version`".to_string(),
+            suggestion_opt: Some(format!("Add `version: {latest_version}`.")),
+        })?;
+    // YAML and TOML parse `0.4` as a number.
+    let version = match version_value {
+        JsonValue::String(version) => version,
+        JsonValue::Number(version) => version.to_string(),
+        _ => String::new(),
+    };
+    if !supported_versions.contains(&version.as_str()) {
+        return Err(ConfigError {
+            path_opt: Some("version".to_string()),
+            message: format!("unsupported version `{version}`"),
+            suggestion_opt: Some(format!(
+                "Supported versions are: {}.",
+                supported_versions.join(", ")
+            )),
+        });
+    }
+    serde_path_to_error::deserialize(config_value).map_err(|error| {
+        let path = error.path().to_string();
+        let message = error.into_inner().to_string();
+        let suggestion_opt = suggest_known_name(&message);
+        ConfigError {
+            path_opt: (path != ".").then_some(path),
+            message,
+            suggestion_opt,
+        }
+    })
+}
+
+/// Suggests the closest known name when `message` reports an unknown field or variant with
+/// serde's wording: ``unknown field `x`, expected one of `a`, `b` ``.
+fn suggest_known_name(message: &str) -> Option<String> {
+    let unknown_and_expected = message
+        .strip_prefix("unknown field `")
+        .or_else(|| message.strip_prefix("unknown variant `"))?;
+    let (unknown_name, expected_names) = unknown_and_expected.split_once("`, expected ")?;
+    let (distance, closest_name) = expected_names
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|expected_name| (edit_distance(unknown_name, expected_name), expected_name))
+        .min()?;
+    if distance > 2.max(unknown_name.len() / 3) {
+        return None;
+    }
+    Some(format!("Did you mean `{closest_name}`?"))
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right_chars: Vec<char> = right.chars().collect();
+    let mut distances: Vec<usize> = (0..=right_chars.len()).collect();
+
+    for (left_idx, left_char) in left.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = left_idx + 1;
+
+        for (right_idx, right_char) in right_chars.iter().enumerate() {
+            let substitution_cost = previous_diagonal + usize::from(left_char != *right_char);
+            previous_diagonal = distances[right_idx + 1];
+            distances[right_idx + 1] = substitution_cost
+                .min(distances[right_idx] + 1)
+                .min(previous_diagonal + 1);
+        }
+    }
+    distances[right_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct TestConfig {
+        #[serde(default)]
+        searcher: TestSearcherConfig,
+    }
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct TestSearcherConfig {
+        #[serde(default)]
+        max_num_concurrent_split_searches: usize,
+        #[serde(default)]
+        max_num_concurrent_split_streams: usize,
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("searcher", "searcher"), 0);
+        assert_eq!(edit_distance("seacher", "searcher"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_suggest_known_name() {
+        assert_eq!(
+            suggest_known_name("unknown field `seacher`, expected one of `indexer`, `searcher`"),
+            Some("Did you mean `searcher`?".to_string())
+        );
+        assert_eq!(
+            suggest_known_name("unknown variant `yamll`, expected `yaml`"),
+            Some("Did you mean `yaml`?".to_string())
+        );
+        assert_eq!(
+            suggest_known_name("unknown field `foo`, expected one of `indexer`, `searcher`"),
+            None
+        );
+        assert_eq!(suggest_known_name("missing field `metastore_uri`"), None);
+    }
+
+    #[test]
+    fn test_parse_versioned_config() {
+        let config_content = br#"
+            version: 0.4
+            searcher:
+                max_num_concurrent_split_searches: 10
+        "#;
+        parse_versioned_config::<TestConfig>(ConfigFormat::Yaml, config_content, &["0.4"]).unwrap();
+
+        let config_content = br#"
+            version: 0.3
+        "#;
+        let error =
+            parse_versioned_config::<TestConfig>(ConfigFormat::Yaml, config_content, &["0.4"])
+                .unwrap_err();
+        assert_eq!(error.path_opt.as_deref(), Some("version"));
+        assert_eq!(error.message, "unsupported version `0.3`");
+
+        let config_content = br#"
+            version: 0.4
+            searcher:
+                max_num_concurrent_split_searches: ten
+        "#;
+        let error =
+            parse_versioned_config::<TestConfig>(ConfigFormat::Yaml, config_content, &["0.4"])
+                .unwrap_err();
+        assert_eq!(
+            error.path_opt.as_deref(),
+            Some("searcher.max_num_concurrent_split_searches")
+        );
+        assert!(error.message.starts_with("invalid type: string \"ten\""));
+
+        let config_content = br#"
+            version: 0.4
+            searcher:
+                max_num_concurrent_split_search: 10
+        "#;
+        let error =
+            parse_versioned_config::<TestConfig>(ConfigFormat::Yaml, config_content, &["0.4"])
+                .unwrap_err();
+        assert!(error
+            .message
+            .starts_with("unknown field `max_num_concurrent_split_search`"));
+        assert_eq!(
+            error.suggestion_opt.as_deref(),
+            Some("Did you mean `max_num_concurrent_split_searches`?")
+        );
+    }
+}
@@ -66,7 +66,7 @@ mod tests {
     use quickwit_config::service::QuickwitService;
     use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
     use quickwit_grpc_clients::ControlPlaneGrpcClient;
-    use quickwit_metastore::MockMetastore;
+    use quickwit_metastore::{Lease, MockMetastore};
     use quickwit_proto::control_plane_api::control_plane_service_server::ControlPlaneServiceServer;
     use quickwit_proto::tonic::transport::Server;
     use tokio::sync::watch;
@@ -98,6 +98,15 @@ mod tests {
         metastore
             .expect_list_indexes_metadatas()
             .returning(move || Ok(Vec::new()));
+        metastore
+            .expect_acquire_lease()
+            .returning(|lease_id, holder_id, _lease_duration_secs| {
+                Ok(Lease {
+                    lease_id: lease_id.to_string(),
+                    holder_id: holder_id.to_string(),
+                    expire_timestamp: 0,
+                })
+            });
         let transport = ChannelTransport::default();
         let cluster = Arc::new(
             create_cluster_for_test(Vec::new(), &["control_plane", "indexer"], &transport, true)
@@ -108,6 +117,7 @@ mod tests {
             cluster,
             Arc::new(metastore),
             ServiceClientPool::new(HashMap::new()),
+            1,
         );
         let (_, scheduler_handler) = universe.spawn_builder().spawn(scheduler);
         let control_plane_grpc_addr_port = quickwit_common::net::find_available_tcp_port().unwrap();
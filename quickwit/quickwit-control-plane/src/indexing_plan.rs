@@ -29,12 +29,17 @@ use quickwit_proto::indexing_api::IndexingTask;
 use serde::Serialize;
 
 /// A [`PhysicalIndexingPlan`] defines the list of indexing tasks
-/// each indexer, identified by its node ID, should run.
+/// each indexer, identified by its node ID, should run, and the list of
+/// merge tasks each merger should run.
 /// TODO(fmassot): a metastore version number will be attached to the plan
 /// to identify if the plan is up to date with the metastore.
 #[derive(Debug, PartialEq, Clone, Serialize, Default)]
 pub struct PhysicalIndexingPlan {
     indexing_tasks_per_node_id: HashMap<String, Vec<IndexingTask>>,
+    /// A merge task merges the splits of an (index, source) published by all the indexers.
+    /// Empty when there is no merger in the cluster, in which case each indexer merges
+    /// its own splits.
+    merge_tasks_per_node_id: HashMap<String, Vec<IndexingTask>>,
 }
 
 impl PhysicalIndexingPlan {
@@ -45,6 +50,7 @@ impl PhysicalIndexingPlan {
             .collect();
         Self {
             indexing_tasks_per_node_id,
+            merge_tasks_per_node_id: HashMap::new(),
         }
     }
 
@@ -85,6 +91,16 @@ impl PhysicalIndexingPlan {
         &self.indexing_tasks_per_node_id
     }
 
+    /// Returns the hashmap of (node ID, merge tasks).
+    pub fn merge_tasks_per_node(&self) -> &HashMap<String, Vec<IndexingTask>> {
+        &self.merge_tasks_per_node_id
+    }
+
+    /// Returns whether the splits are merged by mergers instead of the indexers.
+    pub fn offload_merges(&self) -> bool {
+        !self.merge_tasks_per_node_id.is_empty()
+    }
+
     /// Returns the mean number of indexing tasks per node. It returns 0 if there is no node.
     pub fn num_indexing_tasks_mean_per_node(&self) -> f32 {
         let num_nodes = self.indexing_tasks_per_node_id.len();
@@ -126,21 +142,21 @@ impl PhysicalIndexingPlan {
 /// Builds a [`PhysicalIndexingPlan`] by assigning each indexing tasks to a node ID.
 /// The algorithm first sort indexing tasks by (index_id, source_id).
 /// Then for each indexing tasks, it performs the following steps:
-/// 1. Sort node by rendez-vous hashing to make the assignment stable (it makes it
-///    deterministic too). This is not bullet proof as the node score has an impact
-///    on the assignment too.
-/// 2. Select node candidates that can run the task, see [`select_node_candidates`]
-///    function.
-/// 3. For each node, compute a score for this task, the higher, the better, see
+/// 1. Sort node by rendez-vous hashing to make the assignment stable (it makes it deterministic
+///    too). This is not bullet proof as the node score has an impact on the assignment too.
+/// 2. Select node candidates that can run the task, see [`select_node_candidates`] function.
+/// 3. For each node, compute the load of its availability zone for the task's index, see
+///    `compute_zone_load` function, and a score for this task, the higher, the better, see
 ///    `compute_node_score` function.
-/// 4. Select the best node (highest score) and assign the task to
-///    this node.
+/// 4. Select the best node (lowest zone load, then highest score) and assign the task to this node.
+///    This spreads the indexing tasks of an index across availability zones.
 /// Additional notes(fmassot): it's nice to have the cluster members as they contain the running
 /// tasks. We can potentially use this info to assign an indexing task to a node running the same
 /// task.
 pub(crate) fn build_physical_indexing_plan(
     indexers: &[ClusterMember],
     source_configs: &HashMap<IndexSourceId, SourceConfig>,
+    num_ingest_shards: &HashMap<String, usize>,
     mut indexing_tasks: Vec<IndexingTask>,
 ) -> PhysicalIndexingPlan {
     // Sort by (index_id, source_id) to make the algorithm deterministic.
@@ -151,6 +167,15 @@ pub(crate) fn build_physical_indexing_plan(
         .iter()
         .map(|indexer| indexer.node_id.to_string())
         .collect_vec();
+    let availability_zones: HashMap<&str, Option<&str>> = indexers
+        .iter()
+        .map(|indexer| {
+            (
+                indexer.node_id.as_str(),
+                indexer.availability_zone.as_deref(),
+            )
+        })
+        .collect();
 
     // Build the plan.
     let mut plan = PhysicalIndexingPlan::new(node_ids.clone());
@@ -160,7 +185,17 @@ pub(crate) fn build_physical_indexing_plan(
             // TODO(fmassot): remove this lame allocation to access the source...
             .get(&IndexSourceId::from(indexing_task.clone()))
             .expect("SourceConfig should always be present.");
-        let candidates = select_node_candidates(&node_ids, &plan, source_config, &indexing_task);
+        let max_num_pipelines_per_indexer = max_num_pipelines_per_indexer(
+            source_config,
+            &indexing_task.index_id,
+            num_ingest_shards,
+        );
+        let candidates = select_node_candidates(
+            &node_ids,
+            &plan,
+            max_num_pipelines_per_indexer,
+            &indexing_task,
+        );
 
         // It's theoretically possible to have no candidate as all indexers can already
         // have more than `max_num_pipelines_per_indexer` assigned for a given source.
@@ -168,12 +203,20 @@ pub(crate) fn build_physical_indexing_plan(
         // `build_indexing_plan`, we make sure to always respect the constraint
         // `max_num_pipelines_per_indexer` by limiting the number of indexing tasks per
         // source.
-        let best_node_score_opt =
-            candidates
+        let best_node_score_opt = candidates
             .iter()
             .rev() //< we use the reverse iterator, because in case of a tie, max picks the last element.
             // we want the first one in order to maximize affinity.
-            .map(|&node_id| NodeScore { node_id, score: compute_node_score(node_id, &plan) })
+            .map(|&node_id| NodeScore {
+                node_id,
+                zone_load: compute_zone_load(
+                    node_id,
+                    &indexing_task.index_id,
+                    &availability_zones,
+                    &plan,
+                ),
+                score: compute_node_score(node_id, &plan),
+            })
             .max();
         if let Some(best_node_score) = best_node_score_opt {
             plan.assign_indexing_task(best_node_score.node_id.to_string(), indexing_task);
@@ -184,23 +227,71 @@ pub(crate) fn build_physical_indexing_plan(
     plan
 }
 
+/// Assigns a merge task to a merger for each (index, source) of the plan indexing tasks.
+/// Mergers are sorted by rendez-vous hashing to make the assignment stable, then the merger
+/// with the fewest merge tasks is picked. If there is no merger, the plan is left untouched and
+/// the indexers keep merging their own splits.
+pub(crate) fn assign_merge_tasks(plan: &mut PhysicalIndexingPlan, mergers: &[ClusterMember]) {
+    plan.merge_tasks_per_node_id.clear();
+    if mergers.is_empty() {
+        return;
+    }
+    let mut node_ids = mergers
+        .iter()
+        .map(|merger| merger.node_id.to_string())
+        .collect_vec();
+    let mut num_merge_tasks_per_node_id: HashMap<String, usize> = node_ids
+        .iter()
+        .map(|node_id| (node_id.clone(), 0))
+        .collect();
+    let merge_tasks = plan
+        .indexing_tasks_per_node_id
+        .values()
+        .flatten()
+        .cloned()
+        .sorted_by(|left, right| {
+            (&left.index_id, &left.source_id).cmp(&(&right.index_id, &right.source_id))
+        })
+        .dedup()
+        .collect_vec();
+    for merge_task in merge_tasks {
+        sort_by_rendez_vous_hash(&mut node_ids, &merge_task);
+        // `min_by_key` returns the first merger in case of a tie.
+        let node_id = node_ids
+            .iter()
+            .min_by_key(|node_id| num_merge_tasks_per_node_id[*node_id])
+            .expect("There should be at least one merger.");
+        *num_merge_tasks_per_node_id.get_mut(node_id).unwrap() += 1;
+        plan.merge_tasks_per_node_id
+            .entry(node_id.clone())
+            .or_default()
+            .push(merge_task);
+    }
+}
+
 struct NodeScore<'a> {
     node_id: &'a str,
+    zone_load: f32,
     score: f32,
 }
 
 impl<'a> PartialEq for NodeScore<'a> {
     fn eq(&self, other: &Self) -> bool {
-        self.score == other.score
+        self.zone_load == other.zone_load && self.score == other.score
     }
 }
 
 impl<'a> Eq for NodeScore<'a> {}
 
-// Sort by score and then node_id.
+// Sort by zone load (the lower, the better), then score and node_id.
 impl<'a> PartialOrd for NodeScore<'a> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        (self.score, self.node_id).partial_cmp(&(other.score, other.node_id))
+        match other.zone_load.partial_cmp(&self.zone_load)? {
+            Ordering::Equal => {
+                (self.score, self.node_id).partial_cmp(&(other.score, other.node_id))
+            }
+            ordering => Some(ordering),
+        }
     }
 }
 
@@ -210,6 +301,21 @@ impl<'a> Ord for NodeScore<'a> {
     }
 }
 
+/// Returns the maximum number of pipelines of the source an indexer can run. For the ingest API
+/// source, this is the number of ingest shards of the index, every indexer running one pipeline
+/// per shard.
+fn max_num_pipelines_per_indexer(
+    source_config: &SourceConfig,
+    index_id: &str,
+    num_ingest_shards: &HashMap<String, usize>,
+) -> usize {
+    if source_config.source_id == INGEST_API_SOURCE_ID {
+        num_ingest_shards.get(index_id).copied().unwrap_or(1)
+    } else {
+        source_config.max_num_pipelines_per_indexer.get()
+    }
+}
+
 /// Returns node candidates IDs that can run the given [`IndexingTask`].
 /// The selection is overly simple: a node will match unless it has
 /// been already assigned the `max_num_pipelines_per_indexer` of tasks for
@@ -217,7 +323,7 @@ impl<'a> Ord for NodeScore<'a> {
 fn select_node_candidates<'a>(
     node_ids: &'a [String],
     physical_plan: &PhysicalIndexingPlan,
-    source_config: &SourceConfig,
+    max_num_pipelines_per_indexer: usize,
     indexing_task: &IndexingTask,
 ) -> Vec<&'a str> {
     node_ids
@@ -228,7 +334,7 @@ fn select_node_candidates<'a>(
                 node_id,
                 &indexing_task.index_id,
                 &indexing_task.source_id,
-            ) < source_config.max_num_pipelines_per_indexer.get()
+            ) < max_num_pipelines_per_indexer
         })
         .collect_vec()
 }
@@ -242,6 +348,40 @@ fn compute_node_score(node_id: &str, physical_plan: &PhysicalIndexingPlan) -> f3
         - physical_plan.num_indexing_tasks_for_node(node_id) as f32
 }
 
+/// Returns the number of indexing tasks of the given index already assigned to the nodes of the
+/// availability zone of the given node, divided by the number of nodes in this zone. Nodes without
+/// availability zone are considered to be in the same zone, so the zone load does not influence
+/// the assignment when no zone is configured.
+fn compute_zone_load(
+    node_id: &str,
+    index_id: &str,
+    availability_zones: &HashMap<&str, Option<&str>>,
+    physical_plan: &PhysicalIndexingPlan,
+) -> f32 {
+    let availability_zone = availability_zones.get(node_id).copied().flatten();
+    let mut num_nodes_in_zone = 0;
+    let mut num_index_tasks_in_zone = 0;
+
+    for (other_node_id, indexing_tasks) in physical_plan.indexing_tasks_per_node() {
+        let other_availability_zone = availability_zones
+            .get(other_node_id.as_str())
+            .copied()
+            .flatten();
+        if other_availability_zone != availability_zone {
+            continue;
+        }
+        num_nodes_in_zone += 1;
+        num_index_tasks_in_zone += indexing_tasks
+            .iter()
+            .filter(|indexing_task| indexing_task.index_id == index_id)
+            .count();
+    }
+    if num_nodes_in_zone == 0 {
+        return 0f32;
+    }
+    num_index_tasks_in_zone as f32 / num_nodes_in_zone as f32
+}
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub(crate) struct IndexSourceId {
     pub index_id: String,
@@ -263,7 +403,8 @@ impl From<IndexingTask> for IndexSourceId {
 /// - For each source, `num_tasks` are added to the plan with `num_tasks =
 ///   min(desired_num_pipelines`, `max_num_pipelines_per_indexer * num_indexers)`. The `min` ensures
 ///   that a cluster is always able to run all the tasks.
-/// - For the ingest API source, it creates one indexing task per indexer. Indeed, an indexer is not
+/// - For the ingest API source, it creates one indexing task per indexer and per ingest shard of
+///   the index, see `num_ingest_shards`, which defaults to one shard. Indeed, an indexer is not
 ///   able to forward documents received by the ingest API to the indexer that is running the
 ///   corresponding indexing pipeline. To make ingestion easier for the user, we starts ingest
 ///   pipelines on all indexers. TODO(fmassot): remove this rule once Quickwit has the ability to
@@ -273,6 +414,7 @@ impl From<IndexingTask> for IndexSourceId {
 pub(crate) fn build_indexing_plan(
     indexers: &[ClusterMember],
     source_configs: &HashMap<IndexSourceId, SourceConfig>,
+    num_ingest_shards: &HashMap<String, usize>,
 ) -> Vec<IndexingTask> {
     let mut indexing_tasks: Vec<IndexingTask> = Vec::new();
     for (index_source_id, source_config) in source_configs
@@ -290,7 +432,11 @@ pub(crate) fn build_indexing_plan(
             continue;
         }
         let num_pipelines = if source_config.source_id == INGEST_API_SOURCE_ID {
-            indexers.len()
+            max_num_pipelines_per_indexer(
+                source_config,
+                &index_source_id.index_id,
+                num_ingest_shards,
+            ) * indexers.len()
         } else {
             // The num desired pipelines is constrained by the number of indexer and the maximum
             // of pipelines that can run on each indexer.
@@ -328,7 +474,9 @@ mod tests {
     use rand::seq::SliceRandom;
     use serde_json::json;
 
-    use super::{build_physical_indexing_plan, IndexSourceId};
+    use super::{
+        assign_merge_tasks, build_physical_indexing_plan, IndexSourceId, PhysicalIndexingPlan,
+    };
     use crate::indexing_plan::build_indexing_plan;
 
     fn kafka_source_params_for_test() -> SourceParams {
@@ -339,6 +487,8 @@ mod tests {
                 "bootstrap.servers": "localhost:9092",
             }),
             enable_backfill_mode: true,
+            avro: None,
+            protobuf: None,
         })
     }
 
@@ -396,7 +546,7 @@ mod tests {
             },
         );
 
-        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map);
+        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map, &HashMap::new());
 
         assert_eq!(indexing_tasks.len(), 3);
         for indexing_task in indexing_tasks {
@@ -430,7 +580,7 @@ mod tests {
             },
         );
 
-        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map);
+        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map, &HashMap::new());
 
         assert_eq!(indexing_tasks.len(), 4);
         for indexing_task in indexing_tasks {
@@ -444,6 +594,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_indexing_plan_with_ingest_shards() {
+        let indexers = cluster_members_for_test(3, QuickwitService::Indexer);
+        let mut source_configs_map = HashMap::new();
+        let index_source_id = IndexSourceId {
+            index_id: "ingest-api-index".to_string(),
+            source_id: INGEST_API_SOURCE_ID.to_string(),
+        };
+        source_configs_map.insert(
+            index_source_id.clone(),
+            SourceConfig {
+                source_id: index_source_id.source_id.to_string(),
+                max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+                desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+                enabled: true,
+                source_params: SourceParams::IngestApi,
+                transform_config: None,
+            },
+        );
+        let num_ingest_shards = HashMap::from([("ingest-api-index".to_string(), 2)]);
+        let indexing_tasks =
+            build_indexing_plan(&indexers, &source_configs_map, &num_ingest_shards);
+        assert_eq!(indexing_tasks.len(), 6);
+
+        let physical_plan = build_physical_indexing_plan(
+            &indexers,
+            &source_configs_map,
+            &num_ingest_shards,
+            indexing_tasks,
+        );
+        // Every indexer runs one pipeline per shard.
+        for indexer in &indexers {
+            assert_eq!(
+                physical_plan.num_indexing_tasks_for(
+                    &indexer.node_id,
+                    "ingest-api-index",
+                    INGEST_API_SOURCE_ID
+                ),
+                2
+            );
+        }
+    }
+
     #[test]
     fn test_build_indexing_plan_with_sources_to_ignore() {
         let indexers = cluster_members_for_test(4, QuickwitService::Indexer);
@@ -467,7 +660,7 @@ mod tests {
                 max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
                 desired_num_pipelines: NonZeroUsize::new(3).unwrap(),
                 enabled: true,
-                source_params: SourceParams::File(FileSourceParams { filepath: None }),
+                source_params: SourceParams::File(FileSourceParams::stdin()),
                 transform_config: None,
             },
         );
@@ -493,7 +686,7 @@ mod tests {
                 transform_config: None,
             },
         );
-        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map);
+        let indexing_tasks = build_indexing_plan(&indexers, &source_configs_map, &HashMap::new());
 
         assert_eq!(indexing_tasks.len(), 0);
     }
@@ -553,8 +746,12 @@ mod tests {
         }
 
         let indexers = cluster_members_for_test(2, QuickwitService::Indexer);
-        let physical_plan =
-            build_physical_indexing_plan(&indexers, &source_configs_map, indexing_tasks.clone());
+        let physical_plan = build_physical_indexing_plan(
+            &indexers,
+            &source_configs_map,
+            &HashMap::new(),
+            indexing_tasks.clone(),
+        );
         assert_eq!(physical_plan.indexing_tasks_per_node_id.len(), 2);
         let indexer_1_tasks = physical_plan
             .indexing_tasks_per_node_id
@@ -584,6 +781,52 @@ mod tests {
         assert_eq!(indexer_2_tasks, &expected_indexer_2_tasks);
     }
 
+    #[test]
+    fn test_assign_merge_tasks() {
+        let mut indexing_tasks = Vec::new();
+        for index_id in ["1", "2", "3"] {
+            for _ in 0..2 {
+                indexing_tasks.push(IndexingTask {
+                    index_id: index_id.to_string(),
+                    source_id: "0".to_string(),
+                });
+            }
+        }
+        let mut physical_plan = PhysicalIndexingPlan::default();
+        for (idx, indexing_task) in indexing_tasks.into_iter().enumerate() {
+            physical_plan.assign_indexing_task(format!("indexer-{}", idx % 2), indexing_task);
+        }
+        assign_merge_tasks(&mut physical_plan, &[]);
+        assert!(!physical_plan.offload_merges());
+
+        let mergers = cluster_members_for_test(2, QuickwitService::Merger);
+        assign_merge_tasks(&mut physical_plan, &mergers);
+        assert!(physical_plan.offload_merges());
+        let merge_tasks_per_node = physical_plan.merge_tasks_per_node();
+        assert_eq!(merge_tasks_per_node.len(), 2);
+        let mut merge_tasks = merge_tasks_per_node
+            .values()
+            .flatten()
+            .map(|merge_task| merge_task.index_id.as_str())
+            .collect_vec();
+        merge_tasks.sort();
+        assert_eq!(merge_tasks, ["1", "2", "3"]);
+        let mut num_merge_tasks_per_node = merge_tasks_per_node
+            .values()
+            .map(|merge_tasks| merge_tasks.len())
+            .collect_vec();
+        num_merge_tasks_per_node.sort();
+        assert_eq!(num_merge_tasks_per_node, [1, 2]);
+
+        // The assignment is stable.
+        let expected_merge_tasks_per_node = physical_plan.merge_tasks_per_node().clone();
+        assign_merge_tasks(&mut physical_plan, &mergers);
+        assert_eq!(
+            physical_plan.merge_tasks_per_node(),
+            &expected_merge_tasks_per_node
+        );
+    }
+
     #[test]
     fn test_build_physical_indexing_plan_with_not_enough_indexers() {
         quickwit_common::setup_logging_for_tests();
@@ -619,11 +862,74 @@ mod tests {
         let indexers = cluster_members_for_test(1, QuickwitService::Indexer);
         // This case should never happens but we just check that the plan building is resilient
         // enough, it will ignore the tasks that cannot be allocated.
-        let physical_plan =
-            build_physical_indexing_plan(&indexers, &source_configs_map, indexing_tasks);
+        let physical_plan = build_physical_indexing_plan(
+            &indexers,
+            &source_configs_map,
+            &HashMap::new(),
+            indexing_tasks,
+        );
         assert_eq!(physical_plan.num_indexing_tasks(), 1);
     }
 
+    #[test]
+    fn test_build_physical_indexing_plan_spreads_index_across_availability_zones() {
+        let mut source_configs_map = HashMap::new();
+        let mut indexing_tasks = Vec::new();
+
+        for index_idx in 0..10 {
+            let index_source_id = IndexSourceId {
+                index_id: format!("test-index-{index_idx}"),
+                source_id: "source".to_string(),
+            };
+            source_configs_map.insert(
+                index_source_id.clone(),
+                SourceConfig {
+                    source_id: index_source_id.source_id.clone(),
+                    max_num_pipelines_per_indexer: NonZeroUsize::new(2).unwrap(),
+                    desired_num_pipelines: NonZeroUsize::new(2).unwrap(),
+                    enabled: true,
+                    source_params: kafka_source_params_for_test(),
+                    transform_config: None,
+                },
+            );
+            for _ in 0..2 {
+                indexing_tasks.push(IndexingTask {
+                    index_id: index_source_id.index_id.clone(),
+                    source_id: index_source_id.source_id.clone(),
+                });
+            }
+        }
+        let indexers = cluster_members_for_test(4, QuickwitService::Indexer)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, indexer)| {
+                let availability_zone = if idx < 2 { "zone-a" } else { "zone-b" };
+                indexer.with_availability_zone(Some(availability_zone.to_string()))
+            })
+            .collect_vec();
+        let physical_plan = build_physical_indexing_plan(
+            &indexers,
+            &source_configs_map,
+            &HashMap::new(),
+            indexing_tasks,
+        );
+        assert_eq!(physical_plan.num_indexing_tasks(), 20);
+
+        for index_idx in 0..10 {
+            let index_id = format!("test-index-{index_idx}");
+            let availability_zones: HashSet<&str> = indexers
+                .iter()
+                .filter(|indexer| {
+                    physical_plan.indexing_tasks_per_node()[&indexer.node_id]
+                        .iter()
+                        .any(|indexing_task| indexing_task.index_id == index_id)
+                })
+                .flat_map(|indexer| indexer.availability_zone.as_deref())
+                .collect();
+            assert_eq!(availability_zones.len(), 2);
+        }
+    }
+
     proptest! {
         #[test]
         fn test_building_indexing_tasks_and_physical_plan(num_indexers in 1usize..50usize, index_id_sources in proptest::collection::vec(gen_kafka_source(), 1..20)) {
@@ -634,13 +940,13 @@ mod tests {
                     (IndexSourceId { index_id, source_id: source_config.source_id.to_string() }, source_config)
                 })
                 .collect();
-            let mut indexing_tasks = build_indexing_plan(&indexers, &source_configs);
+            let mut indexing_tasks = build_indexing_plan(&indexers, &source_configs, &HashMap::new());
             let num_indexing_tasks = indexing_tasks.len();
             assert_eq!(indexing_tasks.len(), count_indexing_tasks_count_for_test(indexers.len(), &source_configs));
-            let physical_indexing_plan = build_physical_indexing_plan(&indexers, &source_configs, indexing_tasks.clone());
+            let physical_indexing_plan = build_physical_indexing_plan(&indexers, &source_configs, &HashMap::new(), indexing_tasks.clone());
             indexing_tasks.shuffle(&mut rand::thread_rng());
             indexers.shuffle(&mut rand::thread_rng());
-            let physical_indexing_plan_with_shuffle = build_physical_indexing_plan(&indexers, &source_configs, indexing_tasks.clone());
+            let physical_indexing_plan_with_shuffle = build_physical_indexing_plan(&indexers, &source_configs, &HashMap::new(), indexing_tasks.clone());
             assert_eq!(physical_indexing_plan, physical_indexing_plan_with_shuffle);
             // All indexing tasks must have been assigned to an indexer.
             assert_eq!(physical_indexing_plan.num_indexing_tasks(), num_indexing_tasks);
@@ -84,10 +84,16 @@ pub async fn start_control_plane_service(
     universe: &Universe,
     cluster: Arc<Cluster>,
     metastore: Arc<dyn Metastore>,
+    max_num_shards_per_indexer: usize,
 ) -> anyhow::Result<Mailbox<IndexingScheduler>> {
     let indexing_service_client_pool =
         ServiceClientPool::create_and_update_members(cluster.ready_member_change_watcher()).await?;
-    let scheduler = IndexingScheduler::new(cluster, metastore, indexing_service_client_pool);
+    let scheduler = IndexingScheduler::new(
+        cluster,
+        metastore,
+        indexing_service_client_pool,
+        max_num_shards_per_indexer,
+    );
     let (scheduler_mailbox, _) = universe.spawn_builder().spawn(scheduler);
     Ok(scheduler_mailbox)
 }
@@ -27,9 +27,9 @@ use anyhow::Context;
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, HEARTBEAT};
-use quickwit_cluster::{Cluster, ClusterMember};
+use quickwit_cluster::{Cluster, ClusterMember, NodeDrainStatus};
 use quickwit_config::service::QuickwitService;
-use quickwit_config::SourceConfig;
+use quickwit_config::{SourceConfig, INGEST_API_SOURCE_ID};
 use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
 use quickwit_indexing::indexing_client::IndexingServiceClient;
 use quickwit_metastore::Metastore;
@@ -38,7 +38,8 @@ use serde::Serialize;
 use tracing::{debug, error, info, warn};
 
 use crate::indexing_plan::{
-    build_indexing_plan, build_physical_indexing_plan, IndexSourceId, PhysicalIndexingPlan,
+    assign_merge_tasks, build_indexing_plan, build_physical_indexing_plan, IndexSourceId,
+    PhysicalIndexingPlan,
 };
 use crate::{NotifyIndexChangeRequest, NotifyIndexChangeResponse};
 
@@ -54,13 +55,40 @@ const MIN_DURATION_BETWEEN_SCHEDULING: Duration = if cfg!(any(test, feature = "t
     Duration::from_secs(30)
 };
 
+/// ID of the metastore lease held by the control plane acting as leader.
+const LEADER_LEASE_ID: &str = "control-plane-leader";
+
+/// Duration of the leader lease. When the leader stops renewing its lease, another control plane
+/// takes over once the lease expires.
+const LEADER_LEASE_DURATION: Duration = Duration::from_secs(15);
+
+const RENEW_LEADER_LEASE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of ingested documents not yet read by the ingest API pipelines of an index on an indexer
+/// above which the index is considered to lag behind.
+const SCALE_UP_INGEST_LAG: u64 = 50_000;
+
+/// How long an index must lag behind before a shard is added to it.
+const SCALE_UP_DELAY: Duration = Duration::from_secs(60);
+
+/// Number of ingested documents not yet read by the ingest API pipelines of an index on every
+/// indexer below which the index is considered to keep up.
+const SCALE_DOWN_INGEST_LAG: u64 = 1_000;
+
+/// How long an index must keep up before a shard is removed from it.
+const SCALE_DOWN_DELAY: Duration = Duration::from_secs(10 * 60);
+
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct IndexingSchedulerState {
+    /// Whether this control plane is the leader, i.e. the one scheduling the indexing tasks.
+    pub is_leader: bool,
     pub num_applied_physical_indexing_plan: usize,
     pub num_schedule_indexing_plan: usize,
     pub last_applied_physical_plan: Option<PhysicalIndexingPlan>,
     #[serde(skip)]
     pub last_applied_plan_timestamp: Option<Instant>,
+    /// Number of ingest shards of the indexes spread over more than one shard.
+    pub num_ingest_shards: HashMap<String, usize>,
 }
 
 /// The [`IndexingScheduler`] is responsible for scheduling indexing tasks to indexers.
@@ -97,10 +125,21 @@ pub struct IndexingSchedulerState {
 /// Finally, in order to give the time for each indexer to run their indexing tasks, the control
 /// phase will wait at least [`MIN_DURATION_BETWEEN_SCHEDULING`] before comparing the desired
 /// plan with the running plan.
+///
+/// Several control planes can run in a cluster, but only the leader schedules indexing tasks. The
+/// leader is the holder of the metastore lease [`LEADER_LEASE_ID`], which every control plane
+/// tries to acquire or renew every [`RENEW_LEADER_LEASE_INTERVAL`]. When the leader fails, the
+/// lease expires after [`LEADER_LEASE_DURATION`] and another control plane acquires it and
+/// takes over the scheduling.
 pub struct IndexingScheduler {
     cluster: Arc<Cluster>,
     metastore: Arc<dyn Metastore>,
     indexing_client_pool: ServiceClientPool<IndexingServiceClient>,
+    /// Instant until which the leader lease of this control plane is valid, if it holds it.
+    leader_lease_deadline_opt: Option<Instant>,
+    /// Adjusts the number of ingest shards of the indexes in the control loop, a change triggering
+    /// a scheduling.
+    ingest_shards_scaler: IngestShardsScaler,
     state: IndexingSchedulerState,
 }
 
@@ -130,6 +169,9 @@ impl Actor for IndexingScheduler {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.renew_leader_lease().await;
+        ctx.schedule_self_msg(RENEW_LEADER_LEASE_INTERVAL, RenewLeaderLeaseLoop)
+            .await;
         self.handle(RefreshPlanLoop, ctx).await?;
         ctx.schedule_self_msg(HEARTBEAT, ControlPlanLoop).await;
         Ok(())
@@ -141,16 +183,75 @@ impl IndexingScheduler {
         cluster: Arc<Cluster>,
         metastore: Arc<dyn Metastore>,
         indexing_client_pool: ServiceClientPool<IndexingServiceClient>,
+        max_num_shards_per_indexer: usize,
     ) -> Self {
         Self {
             cluster,
             metastore,
             indexing_client_pool,
+            leader_lease_deadline_opt: None,
+            ingest_shards_scaler: IngestShardsScaler::new(max_num_shards_per_indexer),
             state: IndexingSchedulerState::default(),
         }
     }
 
+    fn is_leader(&self) -> bool {
+        self.leader_lease_deadline_opt
+            .map(|leader_lease_deadline| Instant::now() < leader_lease_deadline)
+            .unwrap_or(false)
+    }
+
+    /// Acquires or renews the leader lease. A control plane that loses the leadership forgets its
+    /// last applied plan, so that it builds a new plan if it becomes the leader again.
+    async fn renew_leader_lease(&mut self) {
+        let was_leader = self.is_leader();
+        let node_id = &self.cluster.node_id.id;
+        let request_instant = Instant::now();
+
+        match self
+            .metastore
+            .acquire_lease(LEADER_LEASE_ID, node_id, LEADER_LEASE_DURATION.as_secs())
+            .await
+        {
+            Ok(lease) if lease.is_held_by(node_id) => {
+                // The metastore measures the lease duration from the time it receives the request,
+                // in whole seconds, so the lease is considered valid for a bit less locally.
+                self.leader_lease_deadline_opt =
+                    Some(request_instant + LEADER_LEASE_DURATION - Duration::from_secs(1));
+            }
+            Ok(lease) => {
+                debug!(
+                    leader_id=%lease.holder_id,
+                    "Control plane leader lease is held by another node."
+                );
+                self.leader_lease_deadline_opt = None;
+            }
+            Err(error) => {
+                // The lease remains valid until its deadline.
+                warn!(error=?error, "Failed to renew the control plane leader lease.");
+            }
+        }
+        let is_leader = self.is_leader();
+
+        if is_leader && !was_leader {
+            info!(node_id=%node_id, "Control plane became leader.");
+            // Resume from the shards of the previous leader rather than scaling down all indexes.
+            let indexers = self.get_indexers_from_cluster_state().await;
+            self.state.num_ingest_shards = self
+                .ingest_shards_scaler
+                .running_num_ingest_shards(&indexers);
+        } else if !is_leader && was_leader {
+            warn!(node_id=%node_id, "Control plane lost leadership.");
+            self.state.last_applied_physical_plan = None;
+            self.state.last_applied_plan_timestamp = None;
+        }
+        self.state.is_leader = is_leader;
+    }
+
     async fn schedule_indexing_plan_if_needed(&mut self) -> anyhow::Result<()> {
+        if !self.is_leader() {
+            return Ok(());
+        }
         let indexers: Vec<ClusterMember> = self.get_indexers_from_cluster_state().await;
         if indexers.is_empty() {
             warn!("No indexer available, cannot schedule an indexing plan.");
@@ -158,20 +259,31 @@ impl IndexingScheduler {
         };
         let source_configs: HashMap<IndexSourceId, SourceConfig> =
             self.fetch_source_configs().await?;
-        let indexing_tasks = build_indexing_plan(&indexers, &source_configs);
-        let new_physical_plan =
-            build_physical_indexing_plan(&indexers, &source_configs, indexing_tasks);
+        let mergers: Vec<ClusterMember> = self.get_mergers_from_cluster_state().await;
+        let num_ingest_shards = &self.state.num_ingest_shards;
+        let indexing_tasks = build_indexing_plan(&indexers, &source_configs, num_ingest_shards);
+        let mut new_physical_plan = build_physical_indexing_plan(
+            &indexers,
+            &source_configs,
+            num_ingest_shards,
+            indexing_tasks,
+        );
+        assign_merge_tasks(&mut new_physical_plan, &mergers);
         if let Some(last_applied_plan) = &self.state.last_applied_physical_plan {
             let plans_diff = get_indexing_plans_diff(
                 last_applied_plan.indexing_tasks_per_node(),
                 new_physical_plan.indexing_tasks_per_node(),
             );
             // No need to apply the new plan as it is the same as the old one.
-            if plans_diff.is_empty() {
+            if plans_diff.is_empty()
+                && last_applied_plan.merge_tasks_per_node()
+                    == new_physical_plan.merge_tasks_per_node()
+            {
                 return Ok(());
             }
         }
-        self.apply_physical_indexing_plan(&indexers, new_physical_plan)
+        let members = indexers.into_iter().chain(mergers).collect_vec();
+        self.apply_physical_indexing_plan(&members, new_physical_plan)
             .await;
         self.state.num_schedule_indexing_plan += 1;
         Ok(())
@@ -201,9 +313,24 @@ impl IndexingScheduler {
 
     /// Checks if the last applied plan corresponds to the running indexing tasks present in the
     /// chitchat cluster state. If true, do nothing.
-    /// - If node IDs differ, schedule a new indexing plan.
+    /// - If the number of ingest shards of an index changes, schedule a new indexing plan.
+    /// - If node IDs or mergers differ, schedule a new indexing plan.
     /// - If indexing tasks differ, apply again the last plan.
     async fn control_running_plan(&mut self) -> anyhow::Result<()> {
+        if !self.is_leader() {
+            return Ok(());
+        }
+        let indexers = self.get_indexers_from_cluster_state().await;
+
+        if self.ingest_shards_scaler.update_num_ingest_shards(
+            &mut self.state.num_ingest_shards,
+            &indexers,
+            Instant::now(),
+        ) {
+            info!(num_ingest_shards=?self.state.num_ingest_shards, "Number of ingest shards changed: schedule an indexing plan.");
+            self.schedule_indexing_plan_if_needed().await?;
+            return Ok(());
+        }
         let last_applied_plan =
             if let Some(last_applied_plan) = self.state.last_applied_physical_plan.as_ref() {
                 last_applied_plan
@@ -223,7 +350,6 @@ impl IndexingScheduler {
             }
         }
 
-        let indexers = self.get_indexers_from_cluster_state().await;
         let running_indexing_tasks_by_node_id: HashMap<String, Vec<IndexingTask>> = indexers
             .iter()
             .map(|cluster_member| {
@@ -238,43 +364,90 @@ impl IndexingScheduler {
             &running_indexing_tasks_by_node_id,
             last_applied_plan.indexing_tasks_per_node(),
         );
+        // The merge tasks are assigned deterministically, so a different assignment means that
+        // mergers joined or left the cluster.
+        let mergers = self.get_mergers_from_cluster_state().await;
+        let mut reassigned_plan = last_applied_plan.clone();
+        assign_merge_tasks(&mut reassigned_plan, &mergers);
+        let has_same_mergers =
+            reassigned_plan.merge_tasks_per_node() == last_applied_plan.merge_tasks_per_node();
+
         if !indexing_plans_diff.has_same_nodes() {
             info!(plans_diff=?indexing_plans_diff, "Running plan and last applied plan node IDs differ: schedule an indexing plan.");
             self.schedule_indexing_plan_if_needed().await?;
+        } else if !has_same_mergers {
+            info!("Mergers differ from the last applied plan: schedule an indexing plan.");
+            self.schedule_indexing_plan_if_needed().await?;
         } else if !indexing_plans_diff.has_same_tasks() {
             // Some nodes may have not received their tasks, apply it again.
             info!(plans_diff=?indexing_plans_diff, "Running tasks and last applied tasks differ: reapply last plan.");
-            self.apply_physical_indexing_plan(&indexers, last_applied_plan.clone())
+            let members = indexers.into_iter().chain(mergers).collect_vec();
+            self.apply_physical_indexing_plan(&members, last_applied_plan.clone())
                 .await;
         }
         Ok(())
     }
 
+    /// Returns the indexers that can be assigned indexing tasks. Draining indexers are excluded so
+    /// that their indexing tasks are rescheduled on other indexers.
     async fn get_indexers_from_cluster_state(&self) -> Vec<ClusterMember> {
         self.cluster
             .ready_members_from_chitchat_state()
             .await
             .into_iter()
-            .filter(|member| member.enabled_services.contains(&QuickwitService::Indexer))
+            .filter(|member| {
+                member.enabled_services.contains(&QuickwitService::Indexer)
+                    && member.drain_status == NodeDrainStatus::Active
+            })
+            .collect_vec()
+    }
+
+    /// Returns the mergers that can be assigned merge tasks. Draining mergers are excluded.
+    async fn get_mergers_from_cluster_state(&self) -> Vec<ClusterMember> {
+        self.cluster
+            .ready_members_from_chitchat_state()
+            .await
+            .into_iter()
+            .filter(|member| {
+                member.enabled_services.contains(&QuickwitService::Merger)
+                    && member.drain_status == NodeDrainStatus::Active
+            })
             .collect_vec()
     }
 
     async fn apply_physical_indexing_plan(
         &mut self,
-        indexers: &[ClusterMember],
+        members: &[ClusterMember],
         new_physical_plan: PhysicalIndexingPlan,
     ) {
         debug!("Apply physical indexing plan: {:?}", new_physical_plan);
-        for (node_id, indexing_tasks) in new_physical_plan.indexing_tasks_per_node() {
-            let indexer = indexers
-                .iter()
-                .find(|indexer| &indexer.node_id == node_id)
-                .expect("This should never happen as the plan was built from these indexers.");
+        let node_ids: HashSet<&String> = new_physical_plan
+            .indexing_tasks_per_node()
+            .keys()
+            .chain(new_physical_plan.merge_tasks_per_node().keys())
+            .collect();
+        for node_id in node_ids {
+            let Some(indexer) = members.iter().find(|member| &member.node_id == node_id) else {
+                error!(node_id=%node_id, "Node of the plan not found in the cluster members.");
+                continue;
+            };
+            let indexing_tasks = new_physical_plan
+                .indexing_tasks_per_node()
+                .get(node_id)
+                .cloned()
+                .unwrap_or_default();
+            let merge_tasks = new_physical_plan
+                .merge_tasks_per_node()
+                .get(node_id)
+                .cloned()
+                .unwrap_or_default();
             match self.indexing_client_pool.get(indexer.grpc_advertise_addr) {
                 Some(mut indexing_client) => {
                     if let Err(error) = indexing_client
                         .apply_indexing_plan(ApplyIndexingPlanRequest {
-                            indexing_tasks: indexing_tasks.clone(),
+                            indexing_tasks,
+                            merge_tasks,
+                            offload_merges: new_physical_plan.offload_merges(),
                         })
                         .await
                     {
@@ -331,6 +504,33 @@ impl Handler<ControlPlanLoop> for IndexingScheduler {
     }
 }
 
+#[derive(Debug)]
+struct RenewLeaderLeaseLoop;
+
+#[async_trait]
+impl Handler<RenewLeaderLeaseLoop> for IndexingScheduler {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: RenewLeaderLeaseLoop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let was_leader = self.is_leader();
+        self.renew_leader_lease().await;
+
+        // A new leader schedules right away rather than waiting for the next control loop.
+        if self.is_leader() && !was_leader {
+            if let Err(error) = self.schedule_indexing_plan_if_needed().await {
+                error!("Error when scheduling indexing plan: `{}`.", error);
+            }
+        }
+        ctx.schedule_self_msg(RENEW_LEADER_LEASE_INTERVAL, RenewLeaderLeaseLoop)
+            .await;
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 struct RefreshPlanLoop;
 
@@ -352,6 +552,132 @@ impl Handler<RefreshPlanLoop> for IndexingScheduler {
     }
 }
 
+/// Adjusts the number of ingest shards of the indexes to their ingestion lag, published by the
+/// indexers in the cluster state. The documents ingested into an index with the ingest API can be
+/// spread over several shards on each indexer, each shard being indexed by its own pipeline. A
+/// shard is added to an index whose lag exceeds [`SCALE_UP_INGEST_LAG`] on an indexer for
+/// [`SCALE_UP_DELAY`], up to `max_num_shards_per_indexer`, and one is removed once the lag stays
+/// under [`SCALE_DOWN_INGEST_LAG`] on all indexers for [`SCALE_DOWN_DELAY`].
+struct IngestShardsScaler {
+    max_num_shards_per_indexer: usize,
+    /// Instant since which each index lags behind.
+    lagging_since: HashMap<String, Instant>,
+    /// Instant since which each index keeps up.
+    keeping_up_since: HashMap<String, Instant>,
+}
+
+impl IngestShardsScaler {
+    fn new(max_num_shards_per_indexer: usize) -> Self {
+        Self {
+            max_num_shards_per_indexer: max_num_shards_per_indexer.max(1),
+            lagging_since: HashMap::new(),
+            keeping_up_since: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of ingest API pipelines of the indexes running on the indexers, for the
+    /// indexes running more than one pipeline per indexer.
+    fn running_num_ingest_shards(&self, indexers: &[ClusterMember]) -> HashMap<String, usize> {
+        let mut num_ingest_shards: HashMap<String, usize> = HashMap::new();
+
+        for indexer in indexers {
+            let indexer_num_ingest_shards = indexer
+                .indexing_tasks
+                .iter()
+                .filter(|indexing_task| indexing_task.source_id == INGEST_API_SOURCE_ID)
+                .map(|indexing_task| &indexing_task.index_id)
+                .counts();
+            for (index_id, num_shards) in indexer_num_ingest_shards {
+                let num_shards = num_shards.min(self.max_num_shards_per_indexer);
+                let max_num_shards = num_ingest_shards.entry(index_id.clone()).or_default();
+                *max_num_shards = (*max_num_shards).max(num_shards);
+            }
+        }
+        num_ingest_shards.retain(|_, num_shards| *num_shards > 1);
+        num_ingest_shards
+    }
+
+    /// Adds or removes one shard to the indexes that have been lagging behind or keeping up long
+    /// enough. Returns whether the number of shards of an index changed.
+    fn update_num_ingest_shards(
+        &mut self,
+        num_ingest_shards: &mut HashMap<String, usize>,
+        indexers: &[ClusterMember],
+        now: Instant,
+    ) -> bool {
+        if self.max_num_shards_per_indexer == 1 {
+            return false;
+        }
+        let mut ingest_lags: HashMap<&str, u64> = num_ingest_shards
+            .keys()
+            .map(|index_id| (index_id.as_str(), 0))
+            .collect();
+        for indexer in indexers {
+            for (index_id, ingest_lag) in &indexer.ingest_lags {
+                let max_ingest_lag = ingest_lags.entry(index_id.as_str()).or_default();
+                *max_ingest_lag = (*max_ingest_lag).max(*ingest_lag);
+            }
+        }
+        self.lagging_since
+            .retain(|index_id, _| ingest_lags.contains_key(index_id.as_str()));
+        self.keeping_up_since
+            .retain(|index_id, _| ingest_lags.contains_key(index_id.as_str()));
+        let mut has_changed = false;
+
+        for (index_id, ingest_lag) in ingest_lags {
+            let num_shards = num_ingest_shards.get(index_id).copied().unwrap_or(1);
+
+            let new_num_shards = if ingest_lag >= SCALE_UP_INGEST_LAG {
+                self.keeping_up_since.remove(index_id);
+                let lagging_since = *self
+                    .lagging_since
+                    .entry(index_id.to_string())
+                    .or_insert(now);
+                if num_shards < self.max_num_shards_per_indexer
+                    && now.duration_since(lagging_since) >= SCALE_UP_DELAY
+                {
+                    self.lagging_since.remove(index_id);
+                    num_shards + 1
+                } else {
+                    num_shards
+                }
+            } else if ingest_lag <= SCALE_DOWN_INGEST_LAG {
+                self.lagging_since.remove(index_id);
+                let keeping_up_since = *self
+                    .keeping_up_since
+                    .entry(index_id.to_string())
+                    .or_insert(now);
+                if num_shards > 1 && now.duration_since(keeping_up_since) >= SCALE_DOWN_DELAY {
+                    self.keeping_up_since.remove(index_id);
+                    num_shards - 1
+                } else {
+                    num_shards
+                }
+            } else {
+                self.lagging_since.remove(index_id);
+                self.keeping_up_since.remove(index_id);
+                num_shards
+            };
+            if new_num_shards == num_shards {
+                continue;
+            }
+            info!(
+                index_id=%index_id,
+                ingest_lag=%ingest_lag,
+                num_shards=%new_num_shards,
+                "Scale ingest shards."
+            );
+            if new_num_shards > 1 {
+                num_ingest_shards.insert(index_id.to_string(), new_num_shards);
+            } else {
+                num_ingest_shards.remove(index_id);
+            }
+            has_changed = true;
+        }
+        has_changed
+    }
+}
+
 struct IndexingPlansDiff<'a> {
     pub missing_node_ids: HashSet<&'a str>,
     pub unplanned_node_ids: HashSet<&'a str>,
@@ -515,26 +841,43 @@ fn get_indexing_tasks_diff<'a>(
 mod tests {
     use std::collections::{HashMap, HashSet};
     use std::num::NonZeroUsize;
-    use std::sync::Arc;
-    use std::time::Duration;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     use chitchat::transport::ChannelTransport;
     use quickwit_actors::{ActorHandle, Inbox, Universe, HEARTBEAT};
-    use quickwit_cluster::{create_cluster_for_test, grpc_addr_from_listen_addr_for_test, Cluster};
+    use quickwit_cluster::{
+        create_cluster_for_test, grpc_addr_from_listen_addr_for_test, Cluster, ClusterMember,
+    };
     use quickwit_common::test_utils::wait_until_predicate;
-    use quickwit_config::{KafkaSourceParams, SourceConfig, SourceParams};
+    use quickwit_config::service::QuickwitService;
+    use quickwit_config::{KafkaSourceParams, SourceConfig, SourceParams, INGEST_API_SOURCE_ID};
     use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
     use quickwit_indexing::indexing_client::IndexingServiceClient;
     use quickwit_indexing::IndexingService;
-    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_metastore::{IndexMetadata, Lease, MockMetastore};
     use quickwit_proto::indexing_api::{ApplyIndexingPlanRequest, IndexingTask};
     use serde_json::json;
 
     use super::IndexingScheduler;
     use crate::scheduler::{
-        get_indexing_plans_diff, MIN_DURATION_BETWEEN_SCHEDULING, REFRESH_PLAN_LOOP_INTERVAL,
+        get_indexing_plans_diff, IngestShardsScaler, MIN_DURATION_BETWEEN_SCHEDULING,
+        REFRESH_PLAN_LOOP_INTERVAL, RENEW_LEADER_LEASE_INTERVAL, SCALE_DOWN_DELAY, SCALE_UP_DELAY,
     };
 
+    /// Grants the leader lease to the holder in `leader_id`.
+    fn expect_acquire_leader_lease(metastore: &mut MockMetastore, leader_id: Arc<Mutex<String>>) {
+        metastore.expect_acquire_lease().returning(
+            move |lease_id, _holder_id, _lease_duration_secs| {
+                Ok(Lease {
+                    lease_id: lease_id.to_string(),
+                    holder_id: leader_id.lock().unwrap().clone(),
+                    expire_timestamp: 0,
+                })
+            },
+        );
+    }
+
     fn index_metadata_for_test(
         index_id: &str,
         source_id: &str,
@@ -555,6 +898,8 @@ mod tests {
                     "bootstrap.servers": "localhost:9092",
                 }),
                 enable_backfill_mode: true,
+                avro: None,
+                protobuf: None,
             }),
             transform_config: None,
         };
@@ -580,6 +925,8 @@ mod tests {
         metastore
             .expect_list_indexes_metadatas()
             .returning(move || Ok(vec![index_metadata_2.clone(), index_metadata_1.clone()]));
+        let leader_id = Arc::new(Mutex::new(cluster.node_id.id.clone()));
+        expect_acquire_leader_lease(&mut metastore, leader_id);
         let mut indexer_inboxes = Vec::new();
         let mut indexing_clients = Vec::new();
         for indexer in indexers {
@@ -592,7 +939,7 @@ mod tests {
         }
         let indexing_client_pool = ServiceClientPool::for_clients_list(indexing_clients);
         let indexing_scheduler =
-            IndexingScheduler::new(cluster, Arc::new(metastore), indexing_client_pool);
+            IndexingScheduler::new(cluster, Arc::new(metastore), indexing_client_pool, 1);
         let (_, scheduler_handler) = universe.spawn_builder().spawn(indexing_scheduler);
         (indexer_inboxes, scheduler_handler)
     }
@@ -669,6 +1016,72 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_scheduler_leader_election() {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["indexer", "control_plane"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let universe = Universe::with_accelerated_time();
+        let index_metadata = index_metadata_for_test("test-index", "source-1", 1, 1);
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_list_indexes_metadatas()
+            .returning(move || Ok(vec![index_metadata.clone()]));
+        let leader_id = Arc::new(Mutex::new("other-control-plane".to_string()));
+        expect_acquire_leader_lease(&mut metastore, leader_id.clone());
+
+        let (indexing_service_mailbox, indexing_service_inbox) = universe.create_test_mailbox();
+        let client_grpc_addr = grpc_addr_from_listen_addr_for_test(cluster.gossip_listen_addr);
+        let indexing_client =
+            IndexingServiceClient::from_service(indexing_service_mailbox, client_grpc_addr);
+        let indexing_client_pool = ServiceClientPool::for_clients_list(vec![indexing_client]);
+        let indexing_scheduler = IndexingScheduler::new(
+            cluster.clone(),
+            Arc::new(metastore),
+            indexing_client_pool,
+            1,
+        );
+        let (_, scheduler_handler) = universe.spawn_builder().spawn(indexing_scheduler);
+
+        // Another control plane is the leader: no plan is applied.
+        universe.sleep(HEARTBEAT * 2).await;
+        let scheduler_state = scheduler_handler.process_pending_and_observe().await;
+        assert!(!scheduler_state.is_leader);
+        assert_eq!(scheduler_state.num_applied_physical_indexing_plan, 0);
+        assert!(indexing_service_inbox
+            .drain_for_test_typed::<ApplyIndexingPlanRequest>()
+            .is_empty());
+
+        // The leader lease expired and the control plane acquired it.
+        *leader_id.lock().unwrap() = cluster.node_id.id.clone();
+        universe.sleep(RENEW_LEADER_LEASE_INTERVAL).await;
+        let scheduler_state = scheduler_handler.process_pending_and_observe().await;
+        assert!(scheduler_state.is_leader);
+        assert_eq!(scheduler_state.num_schedule_indexing_plan, 1);
+        assert!(!indexing_service_inbox
+            .drain_for_test_typed::<ApplyIndexingPlanRequest>()
+            .is_empty());
+
+        // The control plane lost the leader lease: it forgets its plan and stops scheduling.
+        *leader_id.lock().unwrap() = "other-control-plane".to_string();
+        universe.sleep(RENEW_LEADER_LEASE_INTERVAL).await;
+        let scheduler_state = scheduler_handler.process_pending_and_observe().await;
+        assert!(!scheduler_state.is_leader);
+        assert!(scheduler_state.last_applied_physical_plan.is_none());
+        indexing_service_inbox.drain_for_test_typed::<ApplyIndexingPlanRequest>();
+
+        universe.sleep(HEARTBEAT * 2).await;
+        scheduler_handler.process_pending_and_observe().await;
+        assert!(indexing_service_inbox
+            .drain_for_test_typed::<ApplyIndexingPlanRequest>()
+            .is_empty());
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_scheduler_scheduling_no_indexer() {
         quickwit_common::setup_logging_for_tests();
@@ -952,4 +1365,107 @@ mod tests {
             );
         }
     }
+
+    fn indexer_for_test(node_id: &str, ingest_lags: &[(&str, u64)]) -> ClusterMember {
+        let addr = ([127, 0, 0, 1], 7280).into();
+        let mut indexer = ClusterMember::new(
+            node_id.to_string(),
+            0,
+            HashSet::from([QuickwitService::Indexer]),
+            addr,
+            addr,
+            Vec::new(),
+        );
+        indexer.ingest_lags = ingest_lags
+            .iter()
+            .map(|(index_id, ingest_lag)| (index_id.to_string(), *ingest_lag))
+            .collect();
+        indexer
+    }
+
+    #[test]
+    fn test_ingest_shards_scaler() {
+        let mut scaler = IngestShardsScaler::new(2);
+        let mut num_ingest_shards = HashMap::new();
+        let start = Instant::now();
+
+        // The index lags behind on one indexer.
+        let indexers = [
+            indexer_for_test("indexer-1", &[("index-1", 100_000)]),
+            indexer_for_test("indexer-2", &[("index-1", 10)]),
+        ];
+        assert!(!scaler.update_num_ingest_shards(&mut num_ingest_shards, &indexers, start));
+        assert!(!scaler.update_num_ingest_shards(
+            &mut num_ingest_shards,
+            &indexers,
+            start + SCALE_UP_DELAY / 2
+        ));
+        assert!(scaler.update_num_ingest_shards(
+            &mut num_ingest_shards,
+            &indexers,
+            start + SCALE_UP_DELAY
+        ));
+        assert_eq!(
+            num_ingest_shards,
+            HashMap::from([("index-1".to_string(), 2)])
+        );
+
+        // The number of shards is capped.
+        let now = start + SCALE_UP_DELAY * 3;
+        assert!(!scaler.update_num_ingest_shards(&mut num_ingest_shards, &indexers, now));
+        assert_eq!(num_ingest_shards["index-1"], 2);
+
+        // The index keeps up, including when the indexers stop reporting its lag.
+        let indexers = [
+            indexer_for_test("indexer-1", &[]),
+            indexer_for_test("indexer-2", &[("index-1", 10)]),
+        ];
+        assert!(!scaler.update_num_ingest_shards(&mut num_ingest_shards, &indexers, now));
+        assert!(!scaler.update_num_ingest_shards(
+            &mut num_ingest_shards,
+            &indexers,
+            now + SCALE_DOWN_DELAY / 2
+        ));
+        assert!(scaler.update_num_ingest_shards(
+            &mut num_ingest_shards,
+            &indexers,
+            now + SCALE_DOWN_DELAY
+        ));
+        assert!(num_ingest_shards.is_empty());
+    }
+
+    #[test]
+    fn test_ingest_shards_scaler_disabled() {
+        let mut scaler = IngestShardsScaler::new(1);
+        let mut num_ingest_shards = HashMap::new();
+        let indexers = [indexer_for_test("indexer-1", &[("index-1", 100_000)])];
+        let start = Instant::now();
+
+        assert!(!scaler.update_num_ingest_shards(&mut num_ingest_shards, &indexers, start));
+        assert!(!scaler.update_num_ingest_shards(
+            &mut num_ingest_shards,
+            &indexers,
+            start + SCALE_UP_DELAY
+        ));
+        assert!(num_ingest_shards.is_empty());
+    }
+
+    #[test]
+    fn test_running_num_ingest_shards() {
+        let scaler = IngestShardsScaler::new(3);
+        let ingest_task = IndexingTask {
+            index_id: "index-1".to_string(),
+            source_id: INGEST_API_SOURCE_ID.to_string(),
+        };
+        let mut indexer_1 = indexer_for_test("indexer-1", &[]);
+        indexer_1.indexing_tasks = vec![ingest_task.clone(); 2];
+        let mut indexer_2 = indexer_for_test("indexer-2", &[]);
+        indexer_2.indexing_tasks = vec![ingest_task];
+
+        let num_ingest_shards = scaler.running_num_ingest_shards(&[indexer_1, indexer_2]);
+        assert_eq!(
+            num_ingest_shards,
+            HashMap::from([("index-1".to_string(), 2)])
+        );
+    }
 }
@@ -0,0 +1,76 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_common::uri::Uri;
+use quickwit_metastore::{IndexMetadata, SplitMetadata};
+use serde::{Deserialize, Serialize};
+
+/// Name of the manifest file of an index backup. The manifest is written last, so a backup
+/// without a manifest is incomplete.
+pub const BACKUP_MANIFEST_FILE_NAME: &str = "backup-manifest.json";
+
+/// Describes an index backup. The manifest is stored at the root of the backup, next to the split
+/// files if they are part of the backup.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexBackupManifest {
+    /// Time at which the backup was created.
+    pub create_timestamp: i64,
+    /// Metadata of the backed up index, including its doc mapping, settings, and sources.
+    pub index_metadata: IndexMetadata,
+    /// Metadata of the published splits of the index at the time of the backup.
+    pub splits: Vec<SplitMetadata>,
+    /// Whether the split files are stored in the backup.
+    pub includes_split_files: bool,
+}
+
+/// Summary of an index backup returned to the caller.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct IndexBackupSummary {
+    /// URI of the backup.
+    #[schema(value_type = String)]
+    pub backup_uri: Uri,
+    /// Number of splits recorded in the backup.
+    pub num_splits: usize,
+    /// Number of documents of the splits recorded in the backup.
+    pub num_docs: usize,
+    /// Total size of the splits recorded in the backup, in bytes.
+    pub num_bytes: u64,
+    /// Whether the split files are stored in the backup.
+    pub includes_split_files: bool,
+}
+
+impl IndexBackupSummary {
+    pub(crate) fn new(backup_uri: Uri, manifest: &IndexBackupManifest) -> Self {
+        Self {
+            backup_uri,
+            num_splits: manifest.splits.len(),
+            num_docs: manifest
+                .splits
+                .iter()
+                .map(|split_metadata| split_metadata.num_docs)
+                .sum(),
+            num_bytes: manifest
+                .splits
+                .iter()
+                .map(|split_metadata| split_metadata.footer_offsets.end)
+                .sum(),
+            includes_split_files: manifest.includes_split_files,
+        }
+    }
+}
@@ -17,28 +17,45 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
+use futures::{StreamExt, TryStreamExt};
 use quickwit_common::fs::{empty_dir, get_cache_directory_path};
+use quickwit_common::uri::Uri;
 use quickwit_common::FileEntry;
-use quickwit_config::{validate_identifier, IndexConfig, QuickwitConfig, SourceConfig};
+use quickwit_config::{
+    validate_identifier, IndexConfig, QuickwitConfig, SourceConfig, SplitStorageLayout,
+};
 use quickwit_indexing::actors::INDEXING_DIR_NAME;
-use quickwit_indexing::check_source_connectivity;
+use quickwit_indexing::{check_source_connectivity, new_split_id};
 use quickwit_janitor::{
-    delete_splits_with_files, run_garbage_collect, SplitDeletionError, SplitRemovalInfo,
+    copy_split_files, delete_splits_with_files, reconcile_index_storage, rehydrate_splits,
+    run_garbage_collect, SplitDeletionError, SplitRemovalInfo, StorageReconciliationReport,
 };
 use quickwit_metastore::{
     quickwit_metastore_uri_resolver, IndexMetadata, ListSplitsQuery, Metastore, MetastoreError,
     SplitMetadata, SplitState,
 };
 use quickwit_proto::{ServiceError, ServiceErrorCode};
-use quickwit_storage::{quickwit_storage_uri_resolver, StorageResolverError, StorageUriResolver};
+use quickwit_storage::{
+    quickwit_storage_uri_resolver, FilePayload, PutPayload, Storage, StoragePayload,
+    StorageResolverError, StorageUriResolver,
+};
 use thiserror::Error;
+use time::OffsetDateTime;
 use tracing::{error, info};
 
+use crate::backup::{IndexBackupManifest, IndexBackupSummary, BACKUP_MANIFEST_FILE_NAME};
+use crate::mount::{load_index_snapshot, MountedIndexRefreshSummary};
+use crate::storage_layout::SplitStorageLayoutMigrationSummary;
+
+/// Maximum number of split files copied concurrently when cloning an index.
+const MAX_CONCURRENT_SPLIT_COPIES: usize = 8;
+
 #[derive(Error, Debug)]
 pub enum IndexServiceError {
     #[error("Failed to resolve the storage `{0}`.")]
@@ -109,6 +126,18 @@ impl IndexService {
             .await
             .map_err(IndexServiceError::InvalidConfig)?;
 
+        // Indexes and aliases share the same namespace.
+        let index_aliases = self.metastore.list_index_aliases().await?;
+        if index_aliases
+            .iter()
+            .any(|index_alias| index_alias.alias_id == index_config.index_id)
+        {
+            return Err(IndexServiceError::InvalidIdentifier(format!(
+                "index ID `{}` is already used by an index alias",
+                index_config.index_id
+            )));
+        }
+
         // Delete existing index if it exists.
         if overwrite {
             match self.delete_index(&index_config.index_id, false).await {
@@ -137,10 +166,58 @@ impl IndexService {
         Ok(index_metadata)
     }
 
+    /// Updates the config of the index `index_id`.
+    ///
+    /// The doc mapping can only evolve additively: fields can be added or removed, and the
+    /// parameters that do not affect the existing splits, such as the tokenizer of a text field,
+    /// can be changed. The new doc mapping applies to the splits indexed after the update.
+    pub async fn update_index(
+        &self,
+        index_id: &str,
+        mut index_config: IndexConfig,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        if index_config.index_id != index_id {
+            return Err(IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                "The index ID of the config `{}` does not match the index ID `{index_id}`",
+                index_config.index_id
+            )));
+        }
+        // The mount of an index is managed by `mount_index` and cannot be changed by an update.
+        index_config.mounted_from = self
+            .metastore
+            .index_metadata(index_id)
+            .await?
+            .into_index_config()
+            .mounted_from;
+        self.metastore.update_index_config(index_config).await?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        Ok(index_metadata)
+    }
+
+    /// Pauses or resumes the periodic garbage collection of the index `index_id`.
+    pub async fn toggle_garbage_collection(
+        &self,
+        index_id: &str,
+        enable: bool,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        let mut index_config = self
+            .metastore
+            .index_metadata(index_id)
+            .await?
+            .into_index_config();
+        index_config.garbage_collection.paused = !enable;
+        self.metastore.update_index_config(index_config).await?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        Ok(index_metadata)
+    }
+
     /// Deletes the index specified with `index_id`.
     /// This is equivalent to running `rm -rf <index path>` for a local index or
     /// `aws s3 rm --recursive <index path>` for a remote Amazon S3 index.
     ///
+    /// Mounted indexes are unmounted: their metadata is deleted but their split files, owned by
+    /// another cluster, are left untouched.
+    ///
     /// * `index_id` - The target index Id.
     /// * `dry_run` - Should this only return a list of affected files without performing deletion.
     pub async fn delete_index(
@@ -148,14 +225,18 @@ impl IndexService {
         index_id: &str,
         dry_run: bool,
     ) -> Result<Vec<FileEntry>, IndexServiceError> {
-        let index_uri = self
+        let index_config = self
             .metastore
             .index_metadata(index_id)
             .await?
-            .into_index_config()
-            .index_uri
-            .clone();
-        let storage = self.storage_resolver.resolve(&index_uri)?;
+            .into_index_config();
+        if index_config.is_mounted() {
+            if !dry_run {
+                self.unmount_index(index_id).await?;
+            }
+            return Ok(Vec::new());
+        }
+        let storage = self.storage_resolver.resolve(&index_config.index_uri)?;
 
         if dry_run {
             let all_splits = self
@@ -171,9 +252,14 @@ impl IndexService {
             return Ok(file_entries_to_delete);
         }
 
-        // Schedule staged and published splits for deletion.
-        let query = ListSplitsQuery::for_index(index_id)
-            .with_split_states([SplitState::Staged, SplitState::Published]);
+        self.delete_archived_split_files(&index_config).await?;
+
+        // Schedule staged, published, and archived splits for deletion.
+        let query = ListSplitsQuery::for_index(index_id).with_split_states([
+            SplitState::Staged,
+            SplitState::Published,
+            SplitState::Archived,
+        ]);
         let splits = self.metastore.list_splits(query).await?;
         let split_ids = splits
             .iter()
@@ -222,6 +308,9 @@ impl IndexService {
             .index_metadata(index_id)
             .await?
             .into_index_config();
+        if index_config.is_mounted() {
+            anyhow::bail!("Index `{index_id}` is mounted and read-only.");
+        }
         let storage = self.storage_resolver.resolve(&index_config.index_uri)?;
 
         let deleted_entries = run_garbage_collect(
@@ -232,6 +321,10 @@ impl IndexService {
             // deletion_grace_period of zero, so that a cli call directly deletes splits after
             // marking to be deleted.
             Duration::ZERO,
+            index_config
+                .garbage_collection
+                .max_concurrent_deletions
+                .unwrap_or(1),
             dry_run,
             None,
         )
@@ -240,6 +333,239 @@ impl IndexService {
         Ok(deleted_entries)
     }
 
+    /// Diffs the split files stored in the index storage against the splits recorded in the
+    /// metastore and reports orphan files and published splits whose file is missing.
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `delete_orphans` - Whether the orphan files should be deleted from the storage.
+    pub async fn reconcile_index_storage(
+        &self,
+        index_id: &str,
+        delete_orphans: bool,
+    ) -> Result<StorageReconciliationReport, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        if delete_orphans {
+            check_not_mounted(index_metadata.index_config())?;
+        }
+        let storage = self.storage_resolver.resolve(index_metadata.index_uri())?;
+        let report =
+            reconcile_index_storage(index_id, storage, self.metastore.clone(), delete_orphans)
+                .await
+                .map_err(|error| IndexServiceError::Internal(format!("{error:#}")))?;
+        Ok(report)
+    }
+
+    /// Migrates the index `index_id` to the content-addressed split storage layout by applying
+    /// the following actions:
+    /// - switch the index to the content-addressed layout, so that the splits indexed and merged
+    ///   from now on are named after their content.
+    /// - copy the file of each published split named after its split ID under the hash of its
+    ///   content, unless the index storage already holds it.
+    /// - replace each of these splits in the metastore with the same split under a new split ID
+    ///   and with its content hash.
+    ///
+    /// The replaced splits are marked for deletion and their files are deleted by the garbage
+    /// collector. The splits are replaced one at a time, so the migration can be interrupted and
+    /// resumed at any time. The indexing pipelines running during the migration keep naming their
+    /// splits after their split ID until they restart, and running the migration again migrates
+    /// these splits.
+    ///
+    /// * `index_id` - The target index Id.
+    pub async fn migrate_split_storage_layout(
+        &self,
+        index_id: &str,
+    ) -> Result<SplitStorageLayoutMigrationSummary, IndexServiceError> {
+        let mut index_config = self
+            .metastore
+            .index_metadata(index_id)
+            .await?
+            .into_index_config();
+        check_not_mounted(&index_config)?;
+
+        if index_config.indexing_settings.split_storage_layout
+            != SplitStorageLayout::ContentAddressed
+        {
+            index_config.indexing_settings.split_storage_layout =
+                SplitStorageLayout::ContentAddressed;
+            self.metastore
+                .update_index_config(index_config.clone())
+                .await?;
+        }
+        let storage = self.storage_resolver.resolve(&index_config.index_uri)?;
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+        let splits: Vec<SplitMetadata> = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .filter(|split_metadata| split_metadata.content_hash.is_none())
+            .collect();
+        let scratch_dir = tempfile::tempdir().map_err(|error| {
+            IndexServiceError::Internal(format!("Failed to create scratch directory: {error}"))
+        })?;
+        let mut summary = SplitStorageLayoutMigrationSummary::default();
+
+        for split in splits {
+            let split_file = split.split_file();
+            let scratch_path = scratch_dir.path().join(&split_file);
+            storage
+                .copy_to_file(Path::new(&split_file), &scratch_path)
+                .await
+                .map_err(|error| {
+                    IndexServiceError::Internal(format!(
+                        "Failed to download split `{split_file}`: {error}"
+                    ))
+                })?;
+            let payload = FilePayload::open(scratch_path.clone())
+                .await
+                .map_err(|error| IndexServiceError::Internal(error.to_string()))?;
+            let content_hash = payload
+                .content_hash()
+                .await
+                .map_err(|error| IndexServiceError::Internal(error.to_string()))?;
+
+            let mut migrated_split_metadata = split.clone();
+            migrated_split_metadata.split_id = new_split_id();
+            migrated_split_metadata.content_hash = Some(content_hash);
+            let migrated_split_id = migrated_split_metadata.split_id.clone();
+            let migrated_split_file = migrated_split_metadata.split_file();
+            self.metastore
+                .stage_splits(index_id, vec![migrated_split_metadata])
+                .await?;
+
+            // A split file named after its content is complete once it exists, whether it was
+            // uploaded by an earlier run of the migration or belongs to another split.
+            let migrated_split_exists = storage
+                .exists(Path::new(&migrated_split_file))
+                .await
+                .map_err(|error| IndexServiceError::Internal(error.to_string()))?;
+            if migrated_split_exists {
+                summary.num_deduplicated_splits += 1;
+            } else {
+                summary.num_uploaded_bytes += payload.len();
+                storage
+                    .put(Path::new(&migrated_split_file), Box::new(payload))
+                    .await
+                    .map_err(|error| {
+                        IndexServiceError::Internal(format!(
+                            "Failed to upload split `{migrated_split_id}`: {error}"
+                        ))
+                    })?;
+            }
+            tokio::fs::remove_file(&scratch_path)
+                .await
+                .map_err(|error| IndexServiceError::Internal(error.to_string()))?;
+            self.metastore
+                .publish_splits(
+                    index_id,
+                    &[migrated_split_id.as_str()],
+                    &[split.split_id()],
+                    None,
+                )
+                .await?;
+            summary.num_migrated_splits += 1;
+        }
+        info!(
+            index_id = %index_id,
+            num_migrated_splits = summary.num_migrated_splits,
+            num_deduplicated_splits = summary.num_deduplicated_splits,
+            "Index successfully migrated to the content-addressed split storage layout."
+        );
+        Ok(summary)
+    }
+
+    /// Moves the archived splits of the index `index_id` that overlap the time range
+    /// `[start_timestamp, end_timestamp)` from the archive storage of the index back to the index
+    /// storage, making them searchable again. Returns the rehydrated splits.
+    ///
+    /// * `index_id` - The target index Id.
+    /// * `start_timestamp_opt` - The start of the time range, unbounded if `None`.
+    /// * `end_timestamp_opt` - The end of the time range, unbounded if `None`.
+    pub async fn rehydrate_splits(
+        &self,
+        index_id: &str,
+        start_timestamp_opt: Option<i64>,
+        end_timestamp_opt: Option<i64>,
+    ) -> Result<Vec<SplitMetadata>, IndexServiceError> {
+        let index_config = self
+            .metastore
+            .index_metadata(index_id)
+            .await?
+            .into_index_config();
+        check_not_mounted(&index_config)?;
+        let Some(archive_uri) = index_config
+            .retention_policy
+            .as_ref()
+            .and_then(|retention_policy| retention_policy.archive_uri())
+        else {
+            return Err(IndexServiceError::OperationNotAllowed(format!(
+                "index `{index_id}` does not have an archive storage"
+            )));
+        };
+        let index_storage = self.storage_resolver.resolve(&index_config.index_uri)?;
+        let archive_storage = self.storage_resolver.resolve(archive_uri)?;
+
+        let mut query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Archived);
+        if let Some(start_timestamp) = start_timestamp_opt {
+            query = query.with_time_range_start_gte(start_timestamp);
+        }
+        if let Some(end_timestamp) = end_timestamp_opt {
+            query = query.with_time_range_end_lt(end_timestamp);
+        }
+        let splits: Vec<SplitMetadata> = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        rehydrate_splits(
+            index_id,
+            self.metastore.clone(),
+            index_storage,
+            archive_storage,
+            &splits,
+        )
+        .await
+        .map_err(|error| IndexServiceError::Internal(format!("{error:#}")))?;
+        Ok(splits)
+    }
+
+    /// Deletes the files of the archived splits of the index from its archive storage.
+    async fn delete_archived_split_files(
+        &self,
+        index_config: &IndexConfig,
+    ) -> Result<(), IndexServiceError> {
+        let Some(archive_uri) = index_config
+            .retention_policy
+            .as_ref()
+            .and_then(|retention_policy| retention_policy.archive_uri())
+        else {
+            return Ok(());
+        };
+        let archive_storage = self.storage_resolver.resolve(archive_uri)?;
+        let query = ListSplitsQuery::for_index(&index_config.index_id)
+            .with_split_state(SplitState::Archived);
+        let split_files: Vec<String> = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .iter()
+            .map(|split| split.split_metadata.split_file())
+            .collect();
+        let split_paths: Vec<&Path> = split_files.iter().map(Path::new).collect();
+        archive_storage
+            .bulk_delete(&split_paths)
+            .await
+            .map_err(|error| {
+                IndexServiceError::Internal(format!(
+                    "Failed to delete archived split files: {error}"
+                ))
+            })?;
+        Ok(())
+    }
+
     /// Clears the index by applying the following actions:
     /// - mark all splits for deletion in the metastore.
     /// - delete the files of all splits marked for deletion using garbage collection.
@@ -251,7 +577,10 @@ impl IndexService {
     /// * `storage_resolver` - A storage resolver object to access the storage.
     pub async fn clear_index(&self, index_id: &str) -> Result<(), IndexServiceError> {
         let index_metadata = self.metastore.index_metadata(index_id).await?;
+        check_not_mounted(index_metadata.index_config())?;
         let storage = self.storage_resolver.resolve(index_metadata.index_uri())?;
+        self.delete_archived_split_files(&index_metadata.index_config)
+            .await?;
         let splits = self.metastore.list_all_splits(index_id).await?;
         let split_ids: Vec<&str> = splits.iter().map(|split| split.split_id()).collect();
         self.metastore
@@ -276,6 +605,537 @@ impl IndexService {
         Ok(())
     }
 
+    /// Clones the index `source_index_id` under the new index ID `target_index_id` by applying
+    /// the following actions:
+    /// - create the target index with the same doc mapping, indexing, search and retention
+    ///   settings as the source index.
+    /// - copy the files of all published splits to the target index storage.
+    /// - stage and publish the copied splits under new split IDs in the metastore.
+    ///
+    /// The split files are copied concurrently, straight from the source index storage to the
+    /// target index storage. The split files named after their content are copied under the same
+    /// name and only if the target index storage does not already hold them. The delete tasks of
+    /// the source index are copied as well, so that the documents of the cloned splits not deleted
+    /// yet are deleted in the target index too. If the clone fails, the target index is deleted.
+    ///
+    /// Sources other than the default ingest sources and source checkpoints are not cloned.
+    ///
+    /// * `source_index_id` - The index to clone.
+    /// * `target_index_id` - The ID of the new index.
+    /// * `target_index_uri_opt` - The URI of the new index. Defaults to a sibling of the source
+    ///   index URI named after `target_index_id`.
+    pub async fn clone_index(
+        &self,
+        source_index_id: &str,
+        target_index_id: &str,
+        target_index_uri_opt: Option<Uri>,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        validate_identifier("Index ID", target_index_id).map_err(|_| {
+            IndexServiceError::InvalidIdentifier(format!("Invalid index ID: `{target_index_id}`"))
+        })?;
+        let source_index_config = self
+            .metastore
+            .index_metadata(source_index_id)
+            .await?
+            .into_index_config();
+        let target_index_uri = match target_index_uri_opt {
+            Some(target_index_uri) => target_index_uri,
+            None => source_index_config
+                .index_uri
+                .parent()
+                .and_then(|parent_uri| parent_uri.join(target_index_id).ok())
+                .ok_or_else(|| {
+                    IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                        "Failed to derive the target index URI from `{}`. Please specify it \
+                         explicitly.",
+                        source_index_config.index_uri
+                    ))
+                })?,
+        };
+        if target_index_uri == source_index_config.index_uri {
+            return Err(IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                "The target index URI must differ from the source index URI `{}`.",
+                source_index_config.index_uri
+            )));
+        }
+        let source_storage = self
+            .storage_resolver
+            .resolve(&source_index_config.index_uri)?;
+        let target_storage = self.storage_resolver.resolve(&target_index_uri)?;
+
+        let mut target_index_config = source_index_config;
+        target_index_config.index_id = target_index_id.to_string();
+        target_index_config.index_uri = target_index_uri;
+        target_index_config.mounted_from = None;
+        self.create_index(target_index_config, false).await?;
+
+        let num_splits = match self
+            .clone_index_content(
+                source_index_id,
+                target_index_id,
+                source_storage,
+                target_storage,
+            )
+            .await
+        {
+            Ok(num_splits) => num_splits,
+            Err(clone_error) => {
+                error!(
+                    source_index_id=%source_index_id,
+                    target_index_id=%target_index_id,
+                    error=?clone_error,
+                    "Failed to clone index, deleting the target index."
+                );
+                if let Err(delete_error) = self.delete_index(target_index_id, false).await {
+                    error!(
+                        target_index_id=%target_index_id,
+                        error=?delete_error,
+                        "Failed to delete the partially cloned index."
+                    );
+                }
+                return Err(clone_error);
+            }
+        };
+        info!(
+            source_index_id = %source_index_id,
+            target_index_id = %target_index_id,
+            num_splits = num_splits,
+            "Index successfully cloned."
+        );
+        let target_index_metadata = self.metastore.index_metadata(target_index_id).await?;
+        Ok(target_index_metadata)
+    }
+
+    /// Copies the delete tasks and the published splits of the source index to the target index,
+    /// and returns the number of splits copied.
+    async fn clone_index_content(
+        &self,
+        source_index_id: &str,
+        target_index_id: &str,
+        source_storage: Arc<dyn Storage>,
+        target_storage: Arc<dyn Storage>,
+    ) -> Result<usize, IndexServiceError> {
+        // The target delete tasks get new opstamps: keep the opstamps of the source and target
+        // delete tasks, in increasing order, to translate the delete opstamps of the splits.
+        let mut source_delete_tasks = self.metastore.list_delete_tasks(source_index_id, 0).await?;
+        source_delete_tasks.sort_by_key(|delete_task| delete_task.opstamp);
+        let mut delete_opstamps: Vec<(u64, u64)> = Vec::with_capacity(source_delete_tasks.len());
+
+        for source_delete_task in source_delete_tasks {
+            let Some(mut delete_query) = source_delete_task.delete_query else {
+                continue;
+            };
+            delete_query.index_id = target_index_id.to_string();
+            let target_delete_task = self.metastore.create_delete_task(delete_query).await?;
+            delete_opstamps.push((source_delete_task.opstamp, target_delete_task.opstamp));
+        }
+        let query =
+            ListSplitsQuery::for_index(source_index_id).with_split_state(SplitState::Published);
+        let source_splits = self.metastore.list_splits(query).await?;
+        if source_splits.is_empty() {
+            return Ok(0);
+        }
+        let mut split_files = Vec::with_capacity(source_splits.len());
+        let mut target_splits_metadata = Vec::with_capacity(source_splits.len());
+
+        for source_split in source_splits {
+            let source_split_file = source_split.split_metadata.split_file();
+            let mut target_split_metadata = source_split.split_metadata;
+            target_split_metadata.split_id = new_split_id();
+            target_split_metadata.index_id = target_index_id.to_string();
+            // The delete tasks applied to the source split count as applied to the target split.
+            let source_delete_opstamp = target_split_metadata.delete_opstamp;
+            target_split_metadata.delete_opstamp = delete_opstamps
+                .iter()
+                .take_while(|(source_opstamp, _)| *source_opstamp <= source_delete_opstamp)
+                .last()
+                .map(|(_, target_opstamp)| *target_opstamp)
+                .unwrap_or(0);
+            split_files.push((source_split_file, target_split_metadata.split_file()));
+            target_splits_metadata.push(target_split_metadata);
+        }
+        let target_split_ids: Vec<String> = target_splits_metadata
+            .iter()
+            .map(|split_metadata| split_metadata.split_id.clone())
+            .collect();
+        self.metastore
+            .stage_splits(target_index_id, target_splits_metadata)
+            .await?;
+
+        futures::stream::iter(split_files)
+            .map(|(source_split_file, target_split_file)| {
+                copy_split_file(
+                    source_storage.clone(),
+                    &*target_storage,
+                    source_split_file,
+                    target_split_file,
+                )
+            })
+            .buffer_unordered(MAX_CONCURRENT_SPLIT_COPIES)
+            .try_collect::<()>()
+            .await?;
+
+        let target_split_ids_ref: Vec<&str> = target_split_ids.iter().map(String::as_str).collect();
+        self.metastore
+            .publish_splits(target_index_id, &target_split_ids_ref, &[], None)
+            .await?;
+        Ok(target_split_ids.len())
+    }
+
+    /// Backs up the index `index_id` to the storage at `backup_uri` by applying the following
+    /// actions:
+    /// - copy the files of all published splits to the backup storage, if `include_split_files`
+    ///   is set.
+    /// - write a manifest holding the index metadata and the metadata of the published splits.
+    ///
+    /// * `index_id` - The index to back up.
+    /// * `backup_uri` - The URI of the backup. It must not already hold a backup.
+    /// * `include_split_files` - Whether the split files are copied to the backup.
+    pub async fn backup_index(
+        &self,
+        index_id: &str,
+        backup_uri: Uri,
+        include_split_files: bool,
+    ) -> Result<IndexBackupSummary, IndexServiceError> {
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        let backup_storage = self.storage_resolver.resolve(&backup_uri)?;
+        let manifest_path = Path::new(BACKUP_MANIFEST_FILE_NAME);
+
+        if backup_storage
+            .exists(manifest_path)
+            .await
+            .map_err(|error| IndexServiceError::Internal(error.to_string()))?
+        {
+            return Err(IndexServiceError::OperationNotAllowed(format!(
+                "a backup already exists at `{backup_uri}`"
+            )));
+        }
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+        let splits: Vec<SplitMetadata> = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+
+        if include_split_files {
+            let index_storage = self.storage_resolver.resolve(index_metadata.index_uri())?;
+            copy_split_files(&*index_storage, &*backup_storage, &splits)
+                .await
+                .map_err(|error| IndexServiceError::Internal(format!("{error:#}")))?;
+        }
+        let manifest = IndexBackupManifest {
+            create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            index_metadata,
+            splits,
+            includes_split_files: include_split_files,
+        };
+        let manifest_json =
+            serde_json::to_vec_pretty(&manifest).expect("Serialization should never fail.");
+        backup_storage
+            .put(manifest_path, Box::new(manifest_json))
+            .await
+            .map_err(|error| {
+                IndexServiceError::Internal(format!("Failed to write backup manifest: {error}"))
+            })?;
+        let summary = IndexBackupSummary::new(backup_uri, &manifest);
+        info!(
+            index_id = %index_id,
+            backup_uri = %summary.backup_uri,
+            num_splits = summary.num_splits,
+            "Index successfully backed up."
+        );
+        Ok(summary)
+    }
+
+    /// Restores the index backed up at `backup_uri` by applying the following actions:
+    /// - create the index with the doc mapping, indexing, search, and retention settings recorded
+    ///   in the backup.
+    /// - copy the split files from the backup to the index storage, if they are part of the
+    ///   backup. Otherwise, the split files must already be present in the index storage.
+    /// - stage and publish the splits recorded in the backup in the metastore.
+    ///
+    /// Sources other than the default ingest sources, source checkpoints, and delete tasks are not
+    /// restored.
+    ///
+    /// * `backup_uri` - The URI of the backup.
+    /// * `index_id_opt` - The ID of the restored index. Defaults to the ID of the backed up index.
+    /// * `index_uri_opt` - The URI of the restored index. Defaults to the URI of the backed up
+    ///   index.
+    pub async fn restore_index(
+        &self,
+        backup_uri: &Uri,
+        index_id_opt: Option<String>,
+        index_uri_opt: Option<Uri>,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        let backup_storage = self.storage_resolver.resolve(backup_uri)?;
+        let manifest_bytes = backup_storage
+            .get_all(Path::new(BACKUP_MANIFEST_FILE_NAME))
+            .await
+            .map_err(|error| {
+                IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                    "Failed to read backup manifest at `{backup_uri}`: {error}"
+                ))
+            })?;
+        let manifest: IndexBackupManifest = serde_json::from_slice(manifest_bytes.as_slice())
+            .map_err(|error| {
+                IndexServiceError::InvalidConfig(anyhow::anyhow!(
+                    "Failed to parse backup manifest at `{backup_uri}`: {error}"
+                ))
+            })?;
+        let mut index_config = manifest.index_metadata.into_index_config();
+        index_config.mounted_from = None;
+        if let Some(index_id) = index_id_opt {
+            validate_identifier("Index ID", &index_id).map_err(|_| {
+                IndexServiceError::InvalidIdentifier(format!("Invalid index ID: `{index_id}`"))
+            })?;
+            index_config.index_id = index_id;
+        }
+        if let Some(index_uri) = index_uri_opt {
+            index_config.index_uri = index_uri;
+        }
+        let index_id = index_config.index_id.clone();
+        let index_storage = self.storage_resolver.resolve(&index_config.index_uri)?;
+        let split_ids: Vec<&str> = manifest
+            .splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect();
+
+        if !manifest.includes_split_files {
+            for split in &manifest.splits {
+                let split_file = split.split_file();
+                let split_exists = index_storage
+                    .exists(Path::new(&split_file))
+                    .await
+                    .map_err(|error| IndexServiceError::Internal(error.to_string()))?;
+                if !split_exists {
+                    return Err(IndexServiceError::OperationNotAllowed(format!(
+                        "the backup does not include the split files and split file \
+                         `{split_file}` is missing from the index storage `{}`",
+                        index_config.index_uri
+                    )));
+                }
+            }
+        }
+        self.create_index(index_config, false).await?;
+
+        if manifest.includes_split_files {
+            copy_split_files(&*backup_storage, &*index_storage, &manifest.splits)
+                .await
+                .map_err(|error| IndexServiceError::Internal(format!("{error:#}")))?;
+        }
+        let splits: Vec<SplitMetadata> = manifest
+            .splits
+            .iter()
+            .cloned()
+            .map(|mut split_metadata| {
+                split_metadata.index_id = index_id.clone();
+                // The restored index starts with an empty delete task log.
+                split_metadata.delete_opstamp = 0;
+                split_metadata
+            })
+            .collect();
+        if !splits.is_empty() {
+            self.metastore.stage_splits(&index_id, splits).await?;
+            self.metastore
+                .publish_splits(&index_id, &split_ids, &[], None)
+                .await?;
+        }
+        info!(
+            index_id = %index_id,
+            backup_uri = %backup_uri,
+            num_splits = split_ids.len(),
+            "Index successfully restored."
+        );
+        let index_metadata = self.metastore.index_metadata(&index_id).await?;
+        Ok(index_metadata)
+    }
+
+    /// Mounts the index snapshotted at `snapshot_uri` as a read-only index by applying the
+    /// following actions:
+    /// - create the index with the doc mapping and settings recorded in the snapshot and the
+    ///   storage of the snapshotted splits as index URI.
+    /// - stage and publish the splits recorded in the snapshot in the metastore.
+    ///
+    /// The snapshot is either an index backup or the index directory of a file-backed metastore.
+    /// No split file is copied: the splits are searched in place. Mounted indexes have no source,
+    /// reject deletes, and are ignored by the janitor.
+    ///
+    /// * `snapshot_uri` - The URI of the snapshot.
+    /// * `index_id_opt` - The ID of the mounted index. Defaults to the ID of the snapshotted index.
+    pub async fn mount_index(
+        &self,
+        snapshot_uri: &Uri,
+        index_id_opt: Option<String>,
+    ) -> Result<IndexMetadata, IndexServiceError> {
+        let snapshot = load_index_snapshot(&self.storage_resolver, snapshot_uri)
+            .await
+            .map_err(IndexServiceError::InvalidConfig)?;
+        let mut index_config = snapshot.index_metadata.into_index_config();
+        if let Some(index_id) = index_id_opt {
+            validate_identifier("Index ID", &index_id).map_err(|_| {
+                IndexServiceError::InvalidIdentifier(format!("Invalid index ID: `{index_id}`"))
+            })?;
+            index_config.index_id = index_id;
+        }
+        index_config.index_uri = snapshot.split_storage_uri;
+        index_config.mounted_from = Some(snapshot_uri.clone());
+        validate_storage_uri(&self.storage_resolver, &index_config)
+            .await
+            .map_err(IndexServiceError::InvalidConfig)?;
+
+        let index_id = index_config.index_id.clone();
+        self.metastore.create_index(index_config).await?;
+        let split_ids: Vec<&str> = snapshot
+            .splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect();
+        self.publish_mounted_splits(&index_id, &snapshot.splits, &split_ids, &[])
+            .await?;
+        info!(
+            index_id = %index_id,
+            snapshot_uri = %snapshot_uri,
+            num_splits = split_ids.len(),
+            "Index successfully mounted."
+        );
+        let index_metadata = self.metastore.index_metadata(&index_id).await?;
+        Ok(index_metadata)
+    }
+
+    /// Reloads the snapshot of the mounted index `index_id` and applies the changes that occurred
+    /// since the index was mounted or last refreshed: the doc mapping and settings are updated,
+    /// the new splits are published, and the splits that no longer exist, for instance because
+    /// they were merged or deleted by the cluster owning the index, are removed from the
+    /// metastore.
+    pub async fn refresh_mounted_index(
+        &self,
+        index_id: &str,
+    ) -> Result<MountedIndexRefreshSummary, IndexServiceError> {
+        let current_index_config = self
+            .metastore
+            .index_metadata(index_id)
+            .await?
+            .into_index_config();
+        let Some(snapshot_uri) = current_index_config.mounted_from.clone() else {
+            return Err(IndexServiceError::OperationNotAllowed(format!(
+                "index `{index_id}` is not mounted"
+            )));
+        };
+        let snapshot = load_index_snapshot(&self.storage_resolver, &snapshot_uri)
+            .await
+            .map_err(IndexServiceError::InvalidConfig)?;
+        let mut index_config = snapshot.index_metadata.into_index_config();
+        index_config.index_id = index_id.to_string();
+        index_config.index_uri = current_index_config.index_uri;
+        index_config.mounted_from = current_index_config.mounted_from;
+        self.metastore.update_index_config(index_config).await?;
+
+        let mounted_splits = self.metastore.list_all_splits(index_id).await?;
+        let mounted_split_ids: HashSet<&str> = mounted_splits
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        let snapshot_split_ids: HashSet<&str> = snapshot
+            .splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect();
+        let new_splits: Vec<SplitMetadata> = snapshot
+            .splits
+            .iter()
+            .filter(|split_metadata| !mounted_split_ids.contains(split_metadata.split_id()))
+            .cloned()
+            .collect();
+        let new_split_ids: Vec<&str> = new_splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect();
+        let stale_split_ids: Vec<&str> = mounted_split_ids
+            .difference(&snapshot_split_ids)
+            .copied()
+            .collect();
+        let removed_split_ids: Vec<&str> = mounted_splits
+            .iter()
+            .filter(|split| {
+                split.split_state == SplitState::Published
+                    && !snapshot_split_ids.contains(split.split_id())
+            })
+            .map(|split| split.split_id())
+            .collect();
+        self.publish_mounted_splits(index_id, &new_splits, &new_split_ids, &removed_split_ids)
+            .await?;
+        if !stale_split_ids.is_empty() {
+            self.metastore
+                .mark_splits_for_deletion(index_id, &stale_split_ids)
+                .await?;
+            self.metastore
+                .delete_splits(index_id, &stale_split_ids)
+                .await?;
+        }
+        let summary = MountedIndexRefreshSummary {
+            snapshot_uri,
+            num_added_splits: new_split_ids.len(),
+            num_removed_splits: removed_split_ids.len(),
+        };
+        info!(
+            index_id = %index_id,
+            snapshot_uri = %summary.snapshot_uri,
+            num_added_splits = summary.num_added_splits,
+            num_removed_splits = summary.num_removed_splits,
+            "Mounted index successfully refreshed."
+        );
+        Ok(summary)
+    }
+
+    /// Stages and publishes the splits of a mounted index, replacing the splits
+    /// `replaced_split_ids`.
+    async fn publish_mounted_splits(
+        &self,
+        index_id: &str,
+        splits: &[SplitMetadata],
+        split_ids: &[&str],
+        replaced_split_ids: &[&str],
+    ) -> Result<(), IndexServiceError> {
+        if split_ids.is_empty() && replaced_split_ids.is_empty() {
+            return Ok(());
+        }
+        let splits: Vec<SplitMetadata> = splits
+            .iter()
+            .cloned()
+            .map(|mut split_metadata| {
+                split_metadata.index_id = index_id.to_string();
+                // Mounted indexes do not have delete tasks.
+                split_metadata.delete_opstamp = 0;
+                split_metadata
+            })
+            .collect();
+        if !splits.is_empty() {
+            self.metastore.stage_splits(index_id, splits).await?;
+        }
+        self.metastore
+            .publish_splits(index_id, split_ids, replaced_split_ids, None)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the metadata of the mounted index `index_id` without touching its split files.
+    async fn unmount_index(&self, index_id: &str) -> Result<(), IndexServiceError> {
+        let splits = self.metastore.list_all_splits(index_id).await?;
+        let split_ids: Vec<&str> = splits.iter().map(|split| split.split_id()).collect();
+        if !split_ids.is_empty() {
+            self.metastore
+                .mark_splits_for_deletion(index_id, &split_ids)
+                .await?;
+            self.metastore.delete_splits(index_id, &split_ids).await?;
+        }
+        self.metastore.delete_index(index_id).await?;
+        info!(index_id = %index_id, "Index successfully unmounted.");
+        Ok(())
+    }
+
     /// Creates a source config for index `index_id`.
     pub async fn create_source(
         &self,
@@ -289,6 +1149,8 @@ impl IndexService {
         validate_identifier("Source ID", &source_id).map_err(|_| {
             IndexServiceError::InvalidIdentifier(format!("Invalid source ID: `{source_id}`"))
         })?;
+        let index_metadata = self.metastore.index_metadata(index_id).await?;
+        check_not_mounted(index_metadata.index_config())?;
         check_source_connectivity(&source_config)
             .await
             .map_err(IndexServiceError::InvalidConfig)?;
@@ -335,6 +1197,51 @@ impl IndexService {
     }
 }
 
+/// Returns an error if the index is mounted, and therefore read-only.
+fn check_not_mounted(index_config: &IndexConfig) -> Result<(), IndexServiceError> {
+    if index_config.is_mounted() {
+        return Err(IndexServiceError::OperationNotAllowed(format!(
+            "index `{}` is mounted and read-only",
+            index_config.index_id
+        )));
+    }
+    Ok(())
+}
+
+/// Copies a split file from the source storage to the target storage, unless the file is named
+/// after its content and the target storage already holds it.
+async fn copy_split_file(
+    source_storage: Arc<dyn Storage>,
+    target_storage: &dyn Storage,
+    source_split_file: String,
+    target_split_file: String,
+) -> Result<(), IndexServiceError> {
+    if target_split_file == source_split_file
+        && target_storage
+            .exists(Path::new(&target_split_file))
+            .await
+            .map_err(|error| IndexServiceError::Internal(error.to_string()))?
+    {
+        return Ok(());
+    }
+    let payload = StoragePayload::open(source_storage, Path::new(&source_split_file))
+        .await
+        .map_err(|error| {
+            IndexServiceError::Internal(format!(
+                "Failed to read split file `{source_split_file}`: {error}"
+            ))
+        })?;
+    target_storage
+        .put(Path::new(&target_split_file), Box::new(payload))
+        .await
+        .map_err(|error| {
+            IndexServiceError::Internal(format!(
+                "Failed to copy split file `{source_split_file}` to `{target_split_file}`: {error}"
+            ))
+        })?;
+    Ok(())
+}
+
 /// Clears the cache directory of a given source.
 ///
 /// * `data_dir_path` - Path to directory where data (tmp data, splits kept for caching purpose) is
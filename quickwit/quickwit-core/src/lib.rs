@@ -19,22 +19,33 @@
 
 #![deny(clippy::disallowed_methods)]
 
+mod backup;
 mod index;
+mod mount;
+mod storage_layout;
 
+pub use backup::{IndexBackupManifest, IndexBackupSummary, BACKUP_MANIFEST_FILE_NAME};
 pub use index::{
     clear_cache_directory, remove_indexing_directory, validate_storage_uri, IndexService,
     IndexServiceError,
 };
+pub use mount::MountedIndexRefreshSummary;
+pub use storage_layout::SplitStorageLayoutMigrationSummary;
 
 #[cfg(test)]
 mod tests {
     use std::path::Path;
+    use std::time::Duration;
 
-    use quickwit_common::FileEntry;
+    use quickwit_common::uri::Uri;
+    use quickwit_common::{split_file, FileEntry};
+    use quickwit_config::SplitStorageLayout;
     use quickwit_indexing::TestSandbox;
+    use quickwit_metastore::{ListSplitsQuery, MetastoreError, Split, SplitState};
+    use quickwit_proto::metastore_api::DeleteQuery;
     use quickwit_storage::StorageUriResolver;
 
-    use crate::IndexService;
+    use crate::{IndexService, IndexServiceError, SplitStorageLayoutMigrationSummary};
 
     #[tokio::test]
     async fn test_file_entry_from_split_and_index_delete() -> anyhow::Result<()> {
@@ -77,4 +88,455 @@ mod tests {
         test_sandbox.assert_quit().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_clone_index() -> anyhow::Result<()> {
+        let index_id = "test-clone-index";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "second doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let index_service =
+            IndexService::new(metastore.clone(), test_sandbox.storage_uri_resolver());
+
+        let target_index_metadata = index_service
+            .clone_index(index_id, "test-clone-index-target", None)
+            .await?;
+        assert_eq!(target_index_metadata.index_id(), "test-clone-index-target");
+        assert_eq!(
+            target_index_metadata.index_uri().as_str(),
+            "ram://quickwit-test-indexes/test-clone-index-target"
+        );
+        let source_splits = metastore.list_all_splits(index_id).await?;
+        let target_splits = metastore.list_all_splits("test-clone-index-target").await?;
+        assert_eq!(target_splits.len(), 2);
+
+        let target_storage = test_sandbox
+            .storage_uri_resolver()
+            .resolve(target_index_metadata.index_uri())?;
+        for target_split in &target_splits {
+            assert_eq!(target_split.split_state, SplitState::Published);
+            assert!(source_splits
+                .iter()
+                .all(|source_split| source_split.split_id() != target_split.split_id()));
+            let split_num_bytes = target_storage
+                .file_num_bytes(Path::new(&split_file(target_split.split_id())))
+                .await?;
+            assert_eq!(
+                split_num_bytes,
+                target_split.split_metadata.footer_offsets.end
+            );
+        }
+        let num_docs = |splits: &[Split]| -> usize {
+            splits
+                .iter()
+                .map(|split| split.split_metadata.num_docs)
+                .sum()
+        };
+        assert_eq!(num_docs(&source_splits), num_docs(&target_splits));
+
+        let error = index_service
+            .clone_index(index_id, "test-clone-index-target", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })
+        ));
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clone_index_copies_delete_tasks() -> anyhow::Result<()> {
+        let index_id = "test-clone-index-delete-tasks";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let delete_query = |index_id: &str, query: &str| DeleteQuery {
+            index_id: index_id.to_string(),
+            start_timestamp: None,
+            end_timestamp: None,
+            query: query.to_string(),
+            search_fields: Vec::new(),
+        };
+        let first_delete_task = metastore
+            .create_delete_task(delete_query(index_id, "body:first"))
+            .await?;
+        metastore
+            .create_delete_task(delete_query(index_id, "body:second"))
+            .await?;
+        // The first delete task has been applied to the split, the second one has not.
+        let source_split_ids: Vec<String> = metastore
+            .list_all_splits(index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_id().to_string())
+            .collect();
+        let source_split_ids_ref: Vec<&str> = source_split_ids.iter().map(String::as_str).collect();
+        metastore
+            .update_splits_delete_opstamp(
+                index_id,
+                &source_split_ids_ref,
+                first_delete_task.opstamp,
+            )
+            .await?;
+
+        let index_service =
+            IndexService::new(metastore.clone(), test_sandbox.storage_uri_resolver());
+        let target_index_id = "test-clone-index-delete-tasks-target";
+        index_service
+            .clone_index(index_id, target_index_id, None)
+            .await?;
+
+        let target_delete_tasks = metastore.list_delete_tasks(target_index_id, 0).await?;
+        assert_eq!(target_delete_tasks.len(), 2);
+        let target_delete_queries: Vec<DeleteQuery> = target_delete_tasks
+            .iter()
+            .map(|delete_task| delete_task.delete_query.clone().unwrap())
+            .collect();
+        assert_eq!(
+            target_delete_queries,
+            vec![
+                delete_query(target_index_id, "body:first"),
+                delete_query(target_index_id, "body:second")
+            ]
+        );
+        let target_splits = metastore.list_all_splits(target_index_id).await?;
+        assert_eq!(target_splits.len(), 1);
+        assert_eq!(
+            target_splits[0].split_metadata.delete_opstamp,
+            target_delete_tasks[0].opstamp
+        );
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clone_index_deletes_target_index_on_error() -> anyhow::Result<()> {
+        let index_id = "test-clone-index-rollback";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "second doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let source_index_metadata = metastore.index_metadata(index_id).await?;
+        let source_storage = test_sandbox
+            .storage_uri_resolver()
+            .resolve(source_index_metadata.index_uri())?;
+        // The file of one of the source splits is missing, so the copy fails.
+        let source_splits = metastore.list_all_splits(index_id).await?;
+        source_storage
+            .delete(Path::new(&source_splits[0].split_metadata.split_file()))
+            .await?;
+
+        let index_service =
+            IndexService::new(metastore.clone(), test_sandbox.storage_uri_resolver());
+        let target_index_id = "test-clone-index-rollback-target";
+        index_service
+            .clone_index(index_id, target_index_id, None)
+            .await
+            .unwrap_err();
+
+        let error = metastore.index_metadata(target_index_id).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migrate_split_storage_layout() -> anyhow::Result<()> {
+        let index_id = "test-migrate-split-storage-layout";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "second doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let index_service =
+            IndexService::new(metastore.clone(), test_sandbox.storage_uri_resolver());
+        let source_splits = metastore.list_all_splits(index_id).await?;
+
+        let summary = index_service
+            .migrate_split_storage_layout(index_id)
+            .await?;
+        assert_eq!(summary.num_migrated_splits, 2);
+        assert_eq!(summary.num_deduplicated_splits, 0);
+        assert!(summary.num_uploaded_bytes > 0);
+
+        let index_config = metastore.index_metadata(index_id).await?.into_index_config();
+        assert_eq!(
+            index_config.indexing_settings.split_storage_layout,
+            SplitStorageLayout::ContentAddressed
+        );
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+        let migrated_splits = metastore.list_splits(query).await?;
+        assert_eq!(migrated_splits.len(), 2);
+
+        let storage = test_sandbox.storage();
+        for migrated_split in &migrated_splits {
+            let content_hash = migrated_split.split_metadata.content_hash.as_ref().unwrap();
+            let split_file_path = format!("splits/{content_hash}.split");
+            let split_num_bytes = storage.file_num_bytes(Path::new(&split_file_path)).await?;
+            assert_eq!(
+                split_num_bytes,
+                migrated_split.split_metadata.footer_offsets.end
+            );
+        }
+        for source_split in &source_splits {
+            let source_split = metastore
+                .list_all_splits(index_id)
+                .await?
+                .into_iter()
+                .find(|split| split.split_id() == source_split.split_id())
+                .unwrap();
+            assert_eq!(source_split.split_state, SplitState::MarkedForDeletion);
+        }
+        let summary = index_service
+            .migrate_split_storage_layout(index_id)
+            .await?;
+        assert_eq!(summary, SplitStorageLayoutMigrationSummary::default());
+
+        // The garbage collector deletes the files of the replaced splits.
+        let removal_info = index_service
+            .garbage_collect_index(index_id, Duration::ZERO, false)
+            .await?;
+        assert_eq!(removal_info.removed_split_entries.len(), 2);
+        for source_split in &source_splits {
+            assert!(!storage
+                .exists(Path::new(&split_file(source_split.split_id())))
+                .await?);
+        }
+        for migrated_split in &migrated_splits {
+            assert!(storage
+                .exists(Path::new(&migrated_split.split_metadata.split_file()))
+                .await?);
+        }
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_index() -> anyhow::Result<()> {
+        let index_id = "test-backup-index";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let storage_resolver = test_sandbox.storage_uri_resolver();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver.clone());
+
+        let backup_uri = Uri::from_well_formed("ram:///backups/test-backup-index");
+        let backup_summary = index_service
+            .backup_index(index_id, backup_uri.clone(), true)
+            .await?;
+        assert_eq!(backup_summary.num_splits, 1);
+        assert_eq!(backup_summary.num_docs, 1);
+        assert!(backup_summary.includes_split_files);
+
+        let error = index_service
+            .backup_index(index_id, backup_uri.clone(), true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::OperationNotAllowed(_)));
+
+        let restored_index_uri = Uri::from_well_formed("ram:///restored/test-restored-index");
+        let restored_index_metadata = index_service
+            .restore_index(
+                &backup_uri,
+                Some("test-restored-index".to_string()),
+                Some(restored_index_uri.clone()),
+            )
+            .await?;
+        assert_eq!(restored_index_metadata.index_id(), "test-restored-index");
+        assert_eq!(restored_index_metadata.index_uri(), &restored_index_uri);
+
+        let restored_splits = metastore.list_all_splits("test-restored-index").await?;
+        assert_eq!(restored_splits.len(), 1);
+        assert_eq!(restored_splits[0].split_state, SplitState::Published);
+        assert_eq!(restored_splits[0].split_metadata.num_docs, 1);
+
+        let restored_storage = storage_resolver.resolve(&restored_index_uri)?;
+        let split_file_path = split_file(restored_splits[0].split_id());
+        assert!(restored_storage.exists(Path::new(&split_file_path)).await?);
+
+        // Restoring a backup without split files requires the split files to be present in the
+        // index storage.
+        let manifest_only_backup_uri = Uri::from_well_formed("ram:///backups/manifest-only");
+        index_service
+            .backup_index(index_id, manifest_only_backup_uri.clone(), false)
+            .await?;
+        let error = index_service
+            .restore_index(
+                &manifest_only_backup_uri,
+                Some("test-missing-splits-index".to_string()),
+                Some(Uri::from_well_formed("ram:///restored/missing-splits")),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::OperationNotAllowed(_)));
+        assert!(metastore
+            .index_metadata("test-missing-splits-index")
+            .await
+            .is_err());
+
+        index_service
+            .restore_index(
+                &manifest_only_backup_uri,
+                Some("test-restored-in-place-index".to_string()),
+                None,
+            )
+            .await?;
+        let restored_splits = metastore
+            .list_all_splits("test-restored-in-place-index")
+            .await?;
+        assert_eq!(restored_splits.len(), 1);
+
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mount_refresh_and_unmount_index() -> anyhow::Result<()> {
+        let index_id = "test-mount-index";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "first doc"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let storage_resolver = test_sandbox.storage_uri_resolver();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver.clone());
+        let index_uri = metastore
+            .index_metadata(index_id)
+            .await?
+            .index_uri()
+            .clone();
+
+        // The index directory of a file-backed metastore is a valid snapshot.
+        let mounted_index_metadata = index_service
+            .mount_index(&index_uri, Some("test-mounted-index".to_string()))
+            .await?;
+        assert_eq!(mounted_index_metadata.index_id(), "test-mounted-index");
+        assert_eq!(mounted_index_metadata.index_uri(), &index_uri);
+        assert_eq!(
+            mounted_index_metadata.index_config.mounted_from,
+            Some(index_uri.clone())
+        );
+        assert!(mounted_index_metadata.sources.is_empty());
+
+        let mounted_splits = metastore.list_all_splits("test-mounted-index").await?;
+        assert_eq!(mounted_splits.len(), 1);
+        assert_eq!(mounted_splits[0].split_state, SplitState::Published);
+
+        let error = index_service
+            .clear_index("test-mounted-index")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::OperationNotAllowed(_)));
+
+        let error = index_service
+            .refresh_mounted_index(index_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IndexServiceError::OperationNotAllowed(_)));
+
+        // New splits of the snapshotted index are published and removed splits are dropped.
+        let first_split_id = mounted_splits[0].split_id().to_string();
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "second doc"})])
+            .await?;
+        metastore
+            .mark_splits_for_deletion(index_id, &[&first_split_id])
+            .await?;
+        let refresh_summary = index_service
+            .refresh_mounted_index("test-mounted-index")
+            .await?;
+        assert_eq!(refresh_summary.num_added_splits, 1);
+        assert_eq!(refresh_summary.num_removed_splits, 1);
+
+        let mounted_splits = metastore.list_all_splits("test-mounted-index").await?;
+        assert_eq!(mounted_splits.len(), 1);
+        assert_ne!(mounted_splits[0].split_id(), first_split_id);
+
+        // Unmounting leaves the split files in place.
+        let deleted_entries = index_service
+            .delete_index("test-mounted-index", false)
+            .await?;
+        assert!(deleted_entries.is_empty());
+        assert!(metastore
+            .index_metadata("test-mounted-index")
+            .await
+            .is_err());
+
+        let storage = storage_resolver.resolve(&index_uri)?;
+        let split_file_path = split_file(mounted_splits[0].split_id());
+        assert!(storage.exists(Path::new(&split_file_path)).await?);
+
+        // A backup without split files is a valid snapshot too.
+        let backup_uri = Uri::from_well_formed("ram:///backups/test-mount-index");
+        index_service
+            .backup_index(index_id, backup_uri.clone(), false)
+            .await?;
+        let mounted_index_metadata = index_service.mount_index(&backup_uri, None).await;
+        // The snapshotted index ID is already taken.
+        assert!(matches!(
+            mounted_index_metadata.unwrap_err(),
+            IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })
+        ));
+        let mounted_index_metadata = index_service
+            .mount_index(&backup_uri, Some("test-mounted-backup".to_string()))
+            .await?;
+        assert_eq!(mounted_index_metadata.index_uri(), &index_uri);
+        assert_eq!(
+            metastore
+                .list_all_splits("test-mounted-backup")
+                .await?
+                .len(),
+            1
+        );
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
 }
@@ -0,0 +1,104 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use anyhow::Context;
+use quickwit_common::uri::Uri;
+use quickwit_metastore::file_backed_metastore::file_backed_index::FileBackedIndex;
+use quickwit_metastore::{IndexMetadata, SplitMetadata, SplitState};
+use quickwit_storage::StorageUriResolver;
+use serde::{Deserialize, Serialize};
+
+use crate::backup::{IndexBackupManifest, BACKUP_MANIFEST_FILE_NAME};
+
+/// Name of the index metadata file written by the file-backed metastore in the index directory.
+const FILE_BACKED_INDEX_FILE_NAME: &str = "metastore.json";
+
+/// Metadata snapshot of an index owned by another cluster.
+#[derive(Clone, Debug)]
+pub(crate) struct IndexSnapshot {
+    /// Metadata of the snapshotted index.
+    pub index_metadata: IndexMetadata,
+    /// URI of the storage holding the split files of the snapshotted index.
+    pub split_storage_uri: Uri,
+    /// Metadata of the published splits of the snapshotted index.
+    pub splits: Vec<SplitMetadata>,
+}
+
+/// Loads the metadata snapshot stored at `snapshot_uri`. The snapshot is either a backup manifest
+/// or the index metadata file of a file-backed metastore.
+pub(crate) async fn load_index_snapshot(
+    storage_resolver: &StorageUriResolver,
+    snapshot_uri: &Uri,
+) -> anyhow::Result<IndexSnapshot> {
+    let storage = storage_resolver.resolve(snapshot_uri)?;
+    let manifest_path = Path::new(BACKUP_MANIFEST_FILE_NAME);
+
+    if storage.exists(manifest_path).await? {
+        let manifest_bytes = storage.get_all(manifest_path).await?;
+        let manifest: IndexBackupManifest = serde_json::from_slice(manifest_bytes.as_slice())
+            .with_context(|| format!("Failed to parse backup manifest at `{snapshot_uri}`."))?;
+        let split_storage_uri = if manifest.includes_split_files {
+            snapshot_uri.clone()
+        } else {
+            manifest.index_metadata.index_uri().clone()
+        };
+        return Ok(IndexSnapshot {
+            index_metadata: manifest.index_metadata,
+            split_storage_uri,
+            splits: manifest.splits,
+        });
+    }
+    let index_bytes = storage
+        .get_all(Path::new(FILE_BACKED_INDEX_FILE_NAME))
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to find a backup manifest or an index metadata file at `{snapshot_uri}`."
+            )
+        })?;
+    let file_backed_index: FileBackedIndex = serde_json::from_slice(index_bytes.as_slice())
+        .with_context(|| format!("Failed to parse index metadata file at `{snapshot_uri}`."))?;
+    let index_metadata = file_backed_index.metadata().clone();
+    let mut splits: Vec<SplitMetadata> = file_backed_index
+        .splits()
+        .values()
+        .filter(|split| split.split_state == SplitState::Published)
+        .map(|split| split.split_metadata.clone())
+        .collect();
+    splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+    Ok(IndexSnapshot {
+        split_storage_uri: index_metadata.index_uri().clone(),
+        index_metadata,
+        splits,
+    })
+}
+
+/// Summary of the refresh of a mounted index returned to the caller.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct MountedIndexRefreshSummary {
+    /// URI of the snapshot the index is mounted from.
+    #[schema(value_type = String)]
+    pub snapshot_uri: Uri,
+    /// Number of splits published since the previous refresh.
+    pub num_added_splits: usize,
+    /// Number of splits removed since the previous refresh.
+    pub num_removed_splits: usize,
+}
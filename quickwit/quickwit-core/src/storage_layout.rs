@@ -0,0 +1,33 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// Summary of the migration of an index to the content-addressed split storage layout returned
+/// to the caller.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SplitStorageLayoutMigrationSummary {
+    /// Number of published splits replaced by splits named after their content.
+    pub num_migrated_splits: usize,
+    /// Number of migrated splits whose content was already stored in the index storage, so their
+    /// file was not uploaded again.
+    pub num_deduplicated_splits: usize,
+    /// Total size of the split files uploaded under their content hash, in bytes.
+    pub num_uploaded_bytes: u64,
+}
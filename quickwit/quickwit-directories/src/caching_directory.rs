@@ -49,6 +49,11 @@ impl CachingDirectory {
             )),
         }
     }
+
+    /// Returns the number of bytes held by the cache of the directory.
+    pub fn num_bytes(&self) -> u64 {
+        self.cache.num_bytes()
+    }
 }
 
 impl fmt::Debug for CachingDirectory {
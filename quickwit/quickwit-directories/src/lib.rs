@@ -26,6 +26,7 @@
 //! - The `CachingDirectory` wraps a Directory with a dynamic cache.
 //! - The `DebugDirectory` acts as a proxy to another directory to instrument it and record all of
 //!   its IO.
+//! - `inspect_split` computes statistics about the content of a split file.
 #![warn(missing_docs)]
 #![deny(clippy::disallowed_methods)]
 
@@ -33,6 +34,7 @@ mod bundle_directory;
 mod caching_directory;
 mod debug_proxy_directory;
 mod hot_directory;
+mod split_inspection;
 mod storage_directory;
 mod union_directory;
 
@@ -40,6 +42,7 @@ pub use self::bundle_directory::{get_hotcache_from_split, read_split_footer, Bun
 pub use self::caching_directory::CachingDirectory;
 pub use self::debug_proxy_directory::{DebugProxyDirectory, ReadOperation};
 pub use self::hot_directory::{write_hotcache, HotDirectory};
+pub use self::split_inspection::{inspect_split, FieldInspection, SplitInspection};
 pub use self::storage_directory::StorageDirectory;
 pub use self::union_directory::UnionDirectory;
 
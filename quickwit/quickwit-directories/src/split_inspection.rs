@@ -0,0 +1,253 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::Context;
+use quickwit_storage::OwnedBytes;
+use serde::Serialize;
+use tantivy::directory::FileSlice;
+use tantivy::{Index, IndexReader, ReloadPolicy};
+
+use crate::{get_hotcache_from_split, BundleDirectory, HotDirectory};
+
+/// Disk usage and term dictionary statistics of a field of a split, summed over its segments.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct FieldInspection {
+    /// Name of the field.
+    pub field_name: String,
+    /// Size of the term dictionary of the field, in bytes.
+    pub term_dictionary_num_bytes: u64,
+    /// Size of the posting lists of the field, in bytes.
+    pub postings_num_bytes: u64,
+    /// Size of the positions of the field, in bytes.
+    pub positions_num_bytes: u64,
+    /// Size of the fast field column of the field, in bytes.
+    pub fast_field_num_bytes: u64,
+    /// Size of the field norms of the field, in bytes.
+    pub fieldnorms_num_bytes: u64,
+    /// Number of terms in the term dictionary of the field.
+    pub num_terms: u64,
+    /// Number of tokens indexed for the field.
+    pub num_tokens: u64,
+}
+
+impl FieldInspection {
+    /// Returns the total size of the field, in bytes.
+    pub fn num_bytes(&self) -> u64 {
+        self.term_dictionary_num_bytes
+            + self.postings_num_bytes
+            + self.positions_num_bytes
+            + self.fast_field_num_bytes
+            + self.fieldnorms_num_bytes
+    }
+}
+
+/// Statistics about the content of a split file.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct SplitInspection {
+    /// Size of the split file, in bytes.
+    pub split_num_bytes: u64,
+    /// Size of the hotcache, in bytes.
+    pub hotcache_num_bytes: u64,
+    /// Size of the doc store, in bytes.
+    pub doc_store_num_bytes: u64,
+    /// Number of documents in the split.
+    pub num_docs: u64,
+    /// Number of segments of the underlying tantivy index.
+    pub num_segments: usize,
+    /// Files bundled in the split, and their sizes in bytes.
+    pub files: Vec<(String, u64)>,
+    /// Files cached in the hotcache, and their cached sizes in bytes.
+    pub hotcache_files: Vec<(String, u64)>,
+    /// Per-field statistics, ordered by decreasing size.
+    pub fields: Vec<FieldInspection>,
+}
+
+/// Opens the split file and computes statistics about its content: the size of its files, the
+/// disk usage of each field, and the size of the term dictionary of each indexed field.
+pub fn inspect_split(split_data: OwnedBytes) -> anyhow::Result<SplitInspection> {
+    let split_num_bytes = split_data.len() as u64;
+    let files = BundleDirectory::get_stats_split(split_data.clone())
+        .context("Failed to read bundle metadata.")?
+        .into_iter()
+        .map(|(path, num_bytes)| (path.to_string_lossy().to_string(), num_bytes))
+        .collect();
+    let hotcache_bytes =
+        get_hotcache_from_split(split_data.clone()).context("Failed to read hotcache.")?;
+    let hotcache_num_bytes = hotcache_bytes.len() as u64;
+    let hotcache_files = HotDirectory::get_stats_per_file(hotcache_bytes.clone())
+        .context("Failed to read hotcache.")?
+        .into_iter()
+        .map(|(path, num_bytes)| (path.to_string_lossy().to_string(), num_bytes as u64))
+        .collect();
+
+    let bundle_directory = BundleDirectory::open_split(FileSlice::new(Arc::new(split_data)))
+        .context("Failed to read bundle metadata.")?;
+    let hot_directory = HotDirectory::open(bundle_directory, hotcache_bytes)?;
+    let index = Index::open(hot_directory).context("Failed to open split index.")?;
+    let schema = index.schema();
+    let reader: IndexReader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = reader.searcher();
+    let space_usage = searcher.space_usage()?;
+
+    let mut fields: BTreeMap<String, FieldInspection> = BTreeMap::new();
+    let mut doc_store_num_bytes = 0;
+
+    for segment_space_usage in space_usage.segments() {
+        doc_store_num_bytes += segment_space_usage.store().total().get_bytes();
+
+        for (field, field_usage) in segment_space_usage.termdict().fields() {
+            field_inspection(&mut fields, schema.get_field_name(*field))
+                .term_dictionary_num_bytes += field_usage.total().get_bytes();
+        }
+        for (field, field_usage) in segment_space_usage.postings().fields() {
+            field_inspection(&mut fields, schema.get_field_name(*field)).postings_num_bytes +=
+                field_usage.total().get_bytes();
+        }
+        for (field, field_usage) in segment_space_usage.positions().fields() {
+            field_inspection(&mut fields, schema.get_field_name(*field)).positions_num_bytes +=
+                field_usage.total().get_bytes();
+        }
+        for (field, field_usage) in segment_space_usage.fast_fields().fields() {
+            field_inspection(&mut fields, schema.get_field_name(*field)).fast_field_num_bytes +=
+                field_usage.total().get_bytes();
+        }
+        for (field, field_usage) in segment_space_usage.fieldnorms().fields() {
+            field_inspection(&mut fields, schema.get_field_name(*field)).fieldnorms_num_bytes +=
+                field_usage.total().get_bytes();
+        }
+    }
+    for (field, field_entry) in schema.fields() {
+        if !field_entry.is_indexed() {
+            continue;
+        }
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let field_inspection = field_inspection(&mut fields, field_entry.name());
+            field_inspection.num_terms += inverted_index.terms().num_terms() as u64;
+            field_inspection.num_tokens += inverted_index.total_num_tokens();
+        }
+    }
+    let mut fields: Vec<FieldInspection> = fields.into_values().collect();
+    fields.sort_by_key(|field_inspection| std::cmp::Reverse(field_inspection.num_bytes()));
+
+    Ok(SplitInspection {
+        split_num_bytes,
+        hotcache_num_bytes,
+        doc_store_num_bytes,
+        num_docs: searcher.num_docs(),
+        num_segments: searcher.segment_readers().len(),
+        files,
+        hotcache_files,
+        fields,
+    })
+}
+
+fn field_inspection<'a>(
+    fields: &'a mut BTreeMap<String, FieldInspection>,
+    field_name: &str,
+) -> &'a mut FieldInspection {
+    fields
+        .entry(field_name.to_string())
+        .or_insert_with(|| FieldInspection {
+            field_name: field_name.to_string(),
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use quickwit_storage::{PutPayload, SplitPayloadBuilder};
+    use tantivy::directory::MmapDirectory;
+    use tantivy::doc;
+    use tantivy::schema::{Schema, FAST, STORED, TEXT};
+
+    use super::*;
+    use crate::write_hotcache;
+
+    #[tokio::test]
+    async fn test_inspect_split() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut schema_builder = Schema::builder();
+        let body_field = schema_builder.add_text_field("body", TEXT | STORED);
+        let timestamp_field = schema_builder.add_u64_field("timestamp", FAST);
+        let index = Index::create_in_dir(temp_dir.path(), schema_builder.build())?;
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        index_writer.add_document(doc!(
+            body_field => "hello happy tax payer",
+            timestamp_field => 1u64,
+        ))?;
+        index_writer.add_document(doc!(
+            body_field => "hello",
+            timestamp_field => 2u64,
+        ))?;
+        index_writer.commit()?;
+        index_writer.wait_merging_threads()?;
+
+        let mut hotcache_bytes = Vec::new();
+        write_hotcache(MmapDirectory::open(temp_dir.path())?, &mut hotcache_bytes)?;
+        hotcache_bytes.flush()?;
+        let file_paths: Vec<_> = std::fs::read_dir(temp_dir.path())?
+            .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+            .collect::<Result<_, _>>()?;
+        let split_data = SplitPayloadBuilder::get_split_payload(&file_paths, &hotcache_bytes)?
+            .read_all()
+            .await?;
+
+        let split_inspection = inspect_split(split_data.clone())?;
+        assert_eq!(split_inspection.split_num_bytes, split_data.len() as u64);
+        assert_eq!(split_inspection.num_docs, 2);
+        assert_eq!(split_inspection.num_segments, 1);
+        assert_eq!(
+            split_inspection.hotcache_num_bytes,
+            hotcache_bytes.len() as u64
+        );
+        assert!(split_inspection.doc_store_num_bytes > 0);
+        assert!(split_inspection
+            .files
+            .iter()
+            .any(|(file_name, _)| file_name == "meta.json"));
+
+        let body_inspection = split_inspection
+            .fields
+            .iter()
+            .find(|field_inspection| field_inspection.field_name == "body")
+            .unwrap();
+        assert_eq!(body_inspection.num_terms, 4);
+        assert_eq!(body_inspection.num_tokens, 5);
+        assert!(body_inspection.term_dictionary_num_bytes > 0);
+        assert!(body_inspection.postings_num_bytes > 0);
+
+        let timestamp_inspection = split_inspection
+            .fields
+            .iter()
+            .find(|field_inspection| field_inspection.field_name == "timestamp")
+            .unwrap();
+        assert_eq!(timestamp_inspection.num_terms, 0);
+        assert!(timestamp_inspection.fast_field_num_bytes > 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,363 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+use siphasher::sip::SipHasher;
+use tantivy::query::QueryParserError as TantivyQueryParserError;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
+
+use crate::term_automaton::extract_automaton_clauses;
+use crate::QueryParserError;
+
+/// Bloom filters over the terms of the bloom filter fields of a split, keyed by field name.
+pub type BloomFilters = BTreeMap<String, BloomFilter>;
+
+/// Target false positive rate of the bloom filters.
+pub const BLOOM_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// Maximum size of the bloom filter of a field within a split, about 6,800 terms at the target
+/// false positive rate. Bloom filters are stored in the split metadata, which the metastore keeps
+/// for every split and returns on every split listing, so splits holding more distinct terms for a
+/// field do not get one.
+pub const MAX_BLOOM_FILTER_NUM_BYTES: usize = 8 * 1024;
+
+/// Set of bits answering "definitely absent" or "maybe present" for a term.
+///
+/// Terms are hashed with SipHash, whose output is stable across platforms and versions, and the
+/// bit positions are derived from two hashes by double hashing.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(into = "BloomFilterForSerialization")]
+#[serde(try_from = "BloomFilterForSerialization")]
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: Vec<u64>,
+}
+
+impl std::fmt::Debug for BloomFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BloomFilter")
+            .field("num_hashes", &self.num_hashes)
+            .field("num_bits", &self.num_bits())
+            .finish()
+    }
+}
+
+impl BloomFilter {
+    /// Creates an empty bloom filter sized for `num_terms` terms at the target false positive
+    /// rate, or returns `None` if it would exceed [`MAX_BLOOM_FILTER_NUM_BYTES`].
+    pub fn with_capacity(num_terms: usize) -> Option<BloomFilter> {
+        let num_terms = num_terms.max(1) as f64;
+        let ln_2 = std::f64::consts::LN_2;
+        let optimal_num_bits =
+            (-num_terms * BLOOM_FILTER_FALSE_POSITIVE_RATE.ln() / (ln_2 * ln_2)).ceil() as usize;
+        let num_words = (optimal_num_bits + 63) / 64;
+        if num_words * 8 > MAX_BLOOM_FILTER_NUM_BYTES {
+            return None;
+        }
+        let num_bits = (num_words * 64) as f64;
+        let num_hashes = ((num_bits / num_terms) * ln_2).round().clamp(1.0, 16.0) as u32;
+        Some(BloomFilter {
+            num_hashes,
+            bits: vec![0u64; num_words],
+        })
+    }
+
+    fn num_bits(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    fn bit_positions(&self, term: &[u8]) -> impl Iterator<Item = u64> {
+        let hash = |key: u64| {
+            let mut hasher = SipHasher::new_with_keys(key, key);
+            hasher.write(term);
+            hasher.finish()
+        };
+        let first_hash = hash(0);
+        let second_hash = hash(1) | 1;
+        let num_bits = self.num_bits();
+
+        (0..self.num_hashes as u64).map(move |hash_ord| {
+            first_hash.wrapping_add(hash_ord.wrapping_mul(second_hash)) % num_bits
+        })
+    }
+
+    /// Adds a term to the bloom filter.
+    pub fn insert(&mut self, term: &[u8]) {
+        for bit_position in self.bit_positions(term) {
+            self.bits[(bit_position / 64) as usize] |= 1 << (bit_position % 64);
+        }
+    }
+
+    /// Returns `false` if the term was never added to the bloom filter, `true` if it may have
+    /// been.
+    pub fn may_contain(&self, term: &[u8]) -> bool {
+        self.bit_positions(term).all(|bit_position| {
+            self.bits[(bit_position / 64) as usize] & (1 << (bit_position % 64)) != 0
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BloomFilterForSerialization {
+    num_hashes: u32,
+    /// Little-endian bytes of the bit words, base64-encoded.
+    bits: String,
+}
+
+impl From<BloomFilter> for BloomFilterForSerialization {
+    fn from(bloom_filter: BloomFilter) -> Self {
+        let bytes: Vec<u8> = bloom_filter
+            .bits
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        BloomFilterForSerialization {
+            num_hashes: bloom_filter.num_hashes,
+            bits: BASE64_STANDARD.encode(bytes),
+        }
+    }
+}
+
+impl TryFrom<BloomFilterForSerialization> for BloomFilter {
+    type Error = String;
+
+    fn try_from(serialized: BloomFilterForSerialization) -> Result<Self, Self::Error> {
+        let bytes = BASE64_STANDARD
+            .decode(serialized.bits)
+            .map_err(|error| format!("Invalid bloom filter bits: {error}."))?;
+        if bytes.is_empty() || bytes.len() % 8 != 0 || serialized.num_hashes == 0 {
+            return Err("Invalid bloom filter.".to_string());
+        }
+        let bits = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("Chunks are 8 bytes long.")))
+            .collect();
+        Ok(BloomFilter {
+            num_hashes: serialized.num_hashes,
+            bits,
+        })
+    }
+}
+
+/// Represents a predicate over the bloom filters of a split.
+///
+/// If the predicate evaluates to false for the bloom filters of a split, we are guaranteed that
+/// no document of the split matches the query.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BloomFilterAst {
+    And(Vec<BloomFilterAst>),
+    Or(Vec<BloomFilterAst>),
+    /// The field holds exactly this term.
+    Term {
+        field: String,
+        value: String,
+    },
+}
+
+impl BloomFilterAst {
+    /// Evaluates the predicate over the bloom filters of a split. The fields without a bloom
+    /// filter are assumed to hold any term.
+    pub fn evaluate(&self, bloom_filters: &BloomFilters) -> bool {
+        match self {
+            BloomFilterAst::And(children) => {
+                children.iter().all(|child| child.evaluate(bloom_filters))
+            }
+            BloomFilterAst::Or(children) => {
+                children.iter().any(|child| child.evaluate(bloom_filters))
+            }
+            BloomFilterAst::Term { field, value } => {
+                bloom_filters.get(field).map_or(true, |bloom_filter| {
+                    bloom_filter.may_contain(value.as_bytes())
+                })
+            }
+        }
+    }
+}
+
+/// Extracts from a user query a predicate over the bloom filters of a split, or returns `None` if
+/// the query does not require any exact term.
+pub fn extract_bloom_filter_from_query(
+    user_query: &str,
+) -> Result<Option<BloomFilterAst>, QueryParserError> {
+    let (query, _) = extract_automaton_clauses(user_query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&query)
+        .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
+    Ok(collect_bloom_filters(user_input_ast))
+}
+
+fn term_filter(field: &str, value: &str) -> BloomFilterAst {
+    BloomFilterAst::Term {
+        field: field.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// Returns the predicate implied by a query, or `None` if the query can match any split, for
+/// instance if it has no term clause, or only negated ones.
+fn collect_bloom_filters(user_input_ast: UserInputAst) -> Option<BloomFilterAst> {
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries) => {
+            let has_must_clause = sub_queries
+                .iter()
+                .any(|(occur_opt, _)| *occur_opt == Some(Occur::Must));
+            if has_must_clause {
+                let mut filters: Vec<BloomFilterAst> = sub_queries
+                    .into_iter()
+                    .filter(|(occur_opt, _)| *occur_opt == Some(Occur::Must))
+                    .filter_map(|(_, ast)| collect_bloom_filters(ast))
+                    .collect();
+                return match filters.len() {
+                    0 => None,
+                    1 => filters.pop(),
+                    _ => Some(BloomFilterAst::And(filters)),
+                };
+            }
+            let mut filters = Vec::with_capacity(sub_queries.len());
+            for (occur_opt, ast) in sub_queries {
+                // A document matching no positive clause can match a negated one.
+                if occur_opt == Some(Occur::MustNot) {
+                    return None;
+                }
+                filters.push(collect_bloom_filters(ast)?);
+            }
+            match filters.len() {
+                0 => None,
+                1 => filters.pop(),
+                _ => Some(BloomFilterAst::Or(filters)),
+            }
+        }
+        UserInputAst::Boost(ast, _) => collect_bloom_filters(*ast),
+        UserInputAst::Leaf(leaf) => match *leaf {
+            UserInputLeaf::Literal(UserInputLiteral {
+                field_name: Some(field_name),
+                phrase,
+                ..
+            }) => Some(term_filter(&field_name, &phrase)),
+            UserInputLeaf::Set {
+                field: Some(field),
+                elements,
+            } => {
+                let filters = elements
+                    .iter()
+                    .map(|element| term_filter(&field, element))
+                    .collect();
+                Some(BloomFilterAst::Or(filters))
+            }
+            UserInputLeaf::Literal(_)
+            | UserInputLeaf::All
+            | UserInputLeaf::Range { .. }
+            | UserInputLeaf::Set { .. } => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bloom_filters(field: &str, terms: &[&str]) -> BloomFilters {
+        let mut bloom_filter = BloomFilter::with_capacity(terms.len()).unwrap();
+        for term in terms {
+            bloom_filter.insert(term.as_bytes());
+        }
+        BloomFilters::from([(field.to_string(), bloom_filter)])
+    }
+
+    fn may_match(query: &str, bloom_filters: &BloomFilters) -> bool {
+        extract_bloom_filter_from_query(query)
+            .unwrap()
+            .map_or(true, |bloom_filter| bloom_filter.evaluate(bloom_filters))
+    }
+
+    #[test]
+    fn test_bloom_filter_false_positive_rate() {
+        let num_terms = 5_000;
+        let mut bloom_filter = BloomFilter::with_capacity(num_terms).unwrap();
+        for term_ord in 0..num_terms {
+            bloom_filter.insert(format!("trace-{term_ord}").as_bytes());
+        }
+        for term_ord in 0..num_terms {
+            assert!(bloom_filter.may_contain(format!("trace-{term_ord}").as_bytes()));
+        }
+        let num_false_positives = (num_terms..2 * num_terms)
+            .filter(|term_ord| bloom_filter.may_contain(format!("trace-{term_ord}").as_bytes()))
+            .count();
+        assert!(num_false_positives < num_terms * 2 / 100);
+    }
+
+    #[test]
+    fn test_bloom_filter_capacity_is_bounded() {
+        assert!(BloomFilter::with_capacity(0).is_some());
+        assert!(BloomFilter::with_capacity(6_500).is_some());
+        assert!(BloomFilter::with_capacity(7_000).is_none());
+    }
+
+    #[test]
+    fn test_bloom_filter_serialization() {
+        let bloom_filters = bloom_filters("trace_id", &["abc", "def"]);
+        let bloom_filters_json = serde_json::to_string(&bloom_filters).unwrap();
+        let deserialized_bloom_filters: BloomFilters =
+            serde_json::from_str(&bloom_filters_json).unwrap();
+        assert_eq!(deserialized_bloom_filters, bloom_filters);
+
+        let error = serde_json::from_str::<BloomFilter>(r#"{"num_hashes": 3, "bits": "AAA="}"#)
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid bloom filter"));
+    }
+
+    #[test]
+    fn test_extract_bloom_filter_from_query() {
+        assert_eq!(extract_bloom_filter_from_query("*").unwrap(), None);
+        assert_eq!(extract_bloom_filter_from_query("error").unwrap(), None);
+        assert_eq!(
+            extract_bloom_filter_from_query("-trace_id:abc").unwrap(),
+            None
+        );
+        assert_eq!(
+            extract_bloom_filter_from_query("trace_id:abc OR error").unwrap(),
+            None
+        );
+        assert_eq!(
+            extract_bloom_filter_from_query("trace_id:abc AND status:[400 TO 500]").unwrap(),
+            Some(term_filter("trace_id", "abc"))
+        );
+        assert!(extract_bloom_filter_from_query(":>").is_err());
+    }
+
+    #[test]
+    fn test_bloom_filter_evaluate() {
+        let bloom_filters = bloom_filters("trace_id", &["abc", "def"]);
+        assert!(may_match("trace_id:abc", &bloom_filters));
+        assert!(may_match("trace_id:\"def\"", &bloom_filters));
+        assert!(!may_match("trace_id:ghi", &bloom_filters));
+        assert!(!may_match("trace_id:ghi OR trace_id:jkl", &bloom_filters));
+        assert!(may_match("trace_id:ghi OR trace_id:abc", &bloom_filters));
+        assert!(!may_match("trace_id:ghi AND service:api", &bloom_filters));
+        assert!(may_match("trace_id:ghi OR service:api", &bloom_filters));
+        assert!(!may_match("trace_id: IN [ghi jkl]", &bloom_filters));
+        assert!(may_match("trace_id: IN [ghi abc]", &bloom_filters));
+        assert!(may_match("span_id:ghi", &bloom_filters));
+        assert!(may_match("trace_id:[a TO z]", &bloom_filters));
+    }
+}
@@ -0,0 +1,316 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use csv::{ReaderBuilder, StringRecord};
+use serde_json::{Map as JsonMap, Number, Value as JsonValue};
+use tantivy::schema::{FieldType, Schema};
+
+/// Error affecting a single row of a CSV payload.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvRowError {
+    /// Row number, starting at 1 with the header row if any.
+    pub row: u64,
+    /// Description of the error.
+    pub message: String,
+}
+
+impl std::fmt::Display for CsvRowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+/// Converts CSV records into JSON documents.
+///
+/// Columns are mapped to the fields of the doc mapping by name. Nested fields are targeted with
+/// dotted column names, e.g. `user.name`. Values are converted to the type of the mapped field,
+/// while values of unmapped columns are kept as strings. Empty values are skipped.
+#[derive(Clone)]
+pub struct CsvDocParser {
+    schema: Schema,
+    delimiter: u8,
+}
+
+impl CsvDocParser {
+    /// Creates a parser for documents of the given schema.
+    pub fn new(schema: Schema, delimiter: u8) -> Self {
+        Self { schema, delimiter }
+    }
+
+    fn reader_builder(&self) -> ReaderBuilder {
+        let mut reader_builder = ReaderBuilder::new();
+        reader_builder
+            .delimiter(self.delimiter)
+            .has_headers(false)
+            .flexible(true);
+        reader_builder
+    }
+
+    /// Parses a whole CSV payload. If `columns_opt` is `None`, the first row of the payload is
+    /// used as header. Returns the valid documents along with the errors of the invalid rows.
+    pub fn parse_payload(
+        &self,
+        payload: &[u8],
+        columns_opt: Option<&[String]>,
+    ) -> (Vec<JsonMap<String, JsonValue>>, Vec<CsvRowError>) {
+        let mut reader = self.reader_builder().from_reader(payload);
+        let mut records = reader.records();
+        let mut row: u64 = 0;
+        let columns: Vec<String> = if let Some(columns) = columns_opt {
+            columns.to_vec()
+        } else {
+            row += 1;
+            match records.next() {
+                Some(Ok(header)) => parse_header(&header),
+                Some(Err(error)) => {
+                    let row_error = CsvRowError {
+                        row,
+                        message: format!("Failed to parse header: {error}"),
+                    };
+                    return (Vec::new(), vec![row_error]);
+                }
+                None => return (Vec::new(), Vec::new()),
+            }
+        };
+        let mut docs = Vec::new();
+        let mut row_errors = Vec::new();
+        for record_res in records {
+            row += 1;
+            match record_res
+                .map_err(|error| error.to_string())
+                .and_then(|record| self.record_to_json(&columns, &record))
+            {
+                Ok(doc) => docs.push(doc),
+                Err(message) => row_errors.push(CsvRowError { row, message }),
+            }
+        }
+        (docs, row_errors)
+    }
+
+    /// Parses a header line into a list of column names.
+    pub fn parse_header_line(&self, line: &str) -> Result<Vec<String>, String> {
+        let header = self.parse_record(line)?;
+        Ok(parse_header(&header))
+    }
+
+    /// Parses a single CSV line into a JSON document.
+    pub fn parse_line(
+        &self,
+        columns: &[String],
+        line: &str,
+    ) -> Result<JsonMap<String, JsonValue>, String> {
+        let record = self.parse_record(line)?;
+        self.record_to_json(columns, &record)
+    }
+
+    fn parse_record(&self, line: &str) -> Result<StringRecord, String> {
+        let mut reader = self.reader_builder().from_reader(line.as_bytes());
+        match reader.records().next() {
+            Some(record_res) => record_res.map_err(|error| error.to_string()),
+            None => Ok(StringRecord::new()),
+        }
+    }
+
+    fn record_to_json(
+        &self,
+        columns: &[String],
+        record: &StringRecord,
+    ) -> Result<JsonMap<String, JsonValue>, String> {
+        if record.len() != columns.len() {
+            return Err(format!(
+                "Expected {} values, got {}.",
+                columns.len(),
+                record.len()
+            ));
+        }
+        let mut doc = JsonMap::new();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            if value.is_empty() {
+                continue;
+            }
+            let json_value = self.convert_value(column, value)?;
+            insert_at_path(&mut doc, column, json_value)?;
+        }
+        Ok(doc)
+    }
+
+    fn convert_value(&self, column: &str, value: &str) -> Result<JsonValue, String> {
+        let Ok(field) = self.schema.get_field(column) else {
+            return Ok(JsonValue::String(value.to_string()));
+        };
+        let parse_error =
+            |type_name: &str| format!("Failed to parse `{value}` as {type_name} for `{column}`.");
+        let json_value = match self.schema.get_field_entry(field).field_type() {
+            FieldType::U64(_) => value
+                .parse::<u64>()
+                .map(JsonValue::from)
+                .map_err(|_| parse_error("u64"))?,
+            FieldType::I64(_) => value
+                .parse::<i64>()
+                .map(JsonValue::from)
+                .map_err(|_| parse_error("i64"))?,
+            FieldType::F64(_) => value
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(JsonValue::Number)
+                .ok_or_else(|| parse_error("f64"))?,
+            FieldType::Bool(_) => value
+                .parse::<bool>()
+                .map(JsonValue::Bool)
+                .map_err(|_| parse_error("bool"))?,
+            // Datetimes may be formatted as strings or Unix timestamps.
+            FieldType::Date(_) => value
+                .parse::<i64>()
+                .map(JsonValue::from)
+                .unwrap_or_else(|_| JsonValue::String(value.to_string())),
+            FieldType::JsonObject(_) => {
+                serde_json::from_str(value).map_err(|_| parse_error("JSON object"))?
+            }
+            _ => JsonValue::String(value.to_string()),
+        };
+        Ok(json_value)
+    }
+}
+
+fn parse_header(header: &StringRecord) -> Vec<String> {
+    header
+        .iter()
+        .map(|column| column.trim().to_string())
+        .collect()
+}
+
+fn insert_at_path(
+    doc: &mut JsonMap<String, JsonValue>,
+    column: &str,
+    json_value: JsonValue,
+) -> Result<(), String> {
+    let mut path = column.split('.').peekable();
+    let mut json_obj = doc;
+    while let Some(field_name) = path.next() {
+        if path.peek().is_none() {
+            json_obj.insert(field_name.to_string(), json_value);
+            return Ok(());
+        }
+        let child = json_obj
+            .entry(field_name.to_string())
+            .or_insert_with(|| JsonValue::Object(JsonMap::new()));
+        json_obj = child
+            .as_object_mut()
+            .ok_or_else(|| format!("Column `{column}` conflicts with another column."))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{DefaultDocMapper, DocMapper};
+
+    fn csv_doc_parser() -> CsvDocParser {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+                "field_mappings": [
+                    {"name": "body", "type": "text"},
+                    {"name": "count", "type": "u64"},
+                    {"name": "ratio", "type": "f64"},
+                    {"name": "ts", "type": "datetime", "fast": true},
+                    {
+                        "name": "user",
+                        "type": "object",
+                        "field_mappings": [{"name": "active", "type": "bool"}]
+                    }
+                ],
+                "mode": "dynamic"
+            }"#,
+        )
+        .unwrap();
+        CsvDocParser::new(doc_mapper.schema(), b',')
+    }
+
+    #[test]
+    fn test_csv_doc_parser_parse_payload_with_header() {
+        let payload = b"body,count,ratio,ts,user.active,other\n\
+                        \"hello, world\",12,0.5,1672531200,true,007\n\
+                        foo,bar,0.1,2023-01-01T00:00:00Z,false,\n\
+                        bar,3,,2023-01-01T00:00:00Z,false,x\n";
+        let (docs, row_errors) = csv_doc_parser().parse_payload(payload, None);
+        assert_eq!(docs.len(), 2);
+        assert_eq!(
+            JsonValue::Object(docs[0].clone()),
+            json!({
+                "body": "hello, world",
+                "count": 12,
+                "ratio": 0.5,
+                "ts": 1672531200,
+                "user": {"active": true},
+                "other": "007"
+            })
+        );
+        assert_eq!(
+            JsonValue::Object(docs[1].clone()),
+            json!({
+                "body": "bar",
+                "count": 3,
+                "ts": "2023-01-01T00:00:00Z",
+                "user": {"active": false},
+                "other": "x"
+            })
+        );
+        assert_eq!(row_errors.len(), 1);
+        assert_eq!(row_errors[0].row, 3);
+        assert!(row_errors[0].message.contains("`bar` as u64 for `count`"));
+    }
+
+    #[test]
+    fn test_csv_doc_parser_parse_payload_with_columns() {
+        let columns = vec!["body".to_string(), "count".to_string()];
+        let payload = b"hello,1\nworld\n";
+        let (docs, row_errors) = csv_doc_parser().parse_payload(payload, Some(&columns));
+        assert_eq!(docs.len(), 1);
+        assert_eq!(
+            JsonValue::Object(docs[0].clone()),
+            json!({"body": "hello", "count": 1})
+        );
+        assert_eq!(
+            row_errors,
+            vec![CsvRowError {
+                row: 2,
+                message: "Expected 2 values, got 1.".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_csv_doc_parser_parse_line() {
+        let csv_doc_parser = csv_doc_parser();
+        let columns = csv_doc_parser.parse_header_line("body, count\n").unwrap();
+        assert_eq!(columns, ["body", "count"]);
+        let doc = csv_doc_parser.parse_line(&columns, "hello,42\n").unwrap();
+        assert_eq!(
+            JsonValue::Object(doc),
+            json!({"body": "hello", "count": 42})
+        );
+        csv_doc_parser
+            .parse_line(&columns, "hello,-1\n")
+            .unwrap_err();
+    }
+}
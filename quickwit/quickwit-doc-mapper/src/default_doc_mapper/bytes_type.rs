@@ -0,0 +1,222 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::schema::Value as TantivyValue;
+
+use super::default_as_true;
+
+/// Textual encoding of the values of a `bytes` field in the ingested documents and in the
+/// search results.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryFormat {
+    #[default]
+    Base64,
+    /// Hexadecimal, case-insensitive in input and lowercase in output.
+    Hex,
+}
+
+impl BinaryFormat {
+    /// Decodes `encoded_str` into raw bytes.
+    pub fn parse_str(&self, encoded_str: &str) -> Result<Vec<u8>, String> {
+        match self {
+            BinaryFormat::Base64 => {
+                BASE64_STANDARD
+                    .decode(encoded_str)
+                    .map_err(|base64_decode_err| {
+                        format!("Expected Base64 string, got `{encoded_str}`: {base64_decode_err}")
+                    })
+            }
+            BinaryFormat::Hex => decode_hex(encoded_str).map_err(|hex_decode_err| {
+                format!("Expected hex string, got `{encoded_str}`: {hex_decode_err}")
+            }),
+        }
+    }
+
+    /// Encodes `bytes` into a string.
+    pub fn format_to_string(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryFormat::Base64 => BASE64_STANDARD.encode(bytes),
+            BinaryFormat::Hex => encode_hex(bytes),
+        }
+    }
+}
+
+impl fmt::Display for BinaryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryFormat::Base64 => write!(f, "base64"),
+            BinaryFormat::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex_str = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex_str.push(HEX_DIGITS[(byte >> 4) as usize] as char);
+        hex_str.push(HEX_DIGITS[(byte & 0x0f) as usize] as char);
+    }
+    hex_str
+}
+
+fn decode_hex(hex_str: &str) -> Result<Vec<u8>, String> {
+    if hex_str.len() % 2 != 0 {
+        return Err("odd number of digits.".to_string());
+    }
+    let hex_digit = |byte: u8| -> Result<u8, String> {
+        (byte as char)
+            .to_digit(16)
+            .map(|digit| digit as u8)
+            .ok_or_else(|| format!("invalid character `{}`.", byte as char))
+    };
+    hex_str
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| Ok(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?))
+        .collect()
+}
+
+/// A struct holding bytes field options.
+///
+/// The values are stored as raw bytes. They are indexed as a single term, so that they can be
+/// searched by exact match or by prefix, and are never tokenized.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitBytesOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+
+    #[serde(default = "default_as_true")]
+    pub indexed: bool,
+
+    #[serde(default)]
+    pub fast: bool,
+
+    /// Encoding of the values in the ingested documents and in the queries.
+    #[serde(default)]
+    pub input_format: BinaryFormat,
+
+    /// Encoding of the values in the search results.
+    #[serde(default)]
+    pub output_format: BinaryFormat,
+}
+
+impl Default for QuickwitBytesOptions {
+    fn default() -> Self {
+        Self {
+            description: None,
+            stored: true,
+            indexed: true,
+            fast: false,
+            input_format: BinaryFormat::default(),
+            output_format: BinaryFormat::default(),
+        }
+    }
+}
+
+impl QuickwitBytesOptions {
+    pub(crate) fn parse_json(&self, json_value: JsonValue) -> Result<TantivyValue, String> {
+        let JsonValue::String(encoded_str) = json_value else {
+            return Err(format!(
+                "Expected {} string, got `{json_value}`.",
+                self.input_format
+            ));
+        };
+        let bytes = self.input_format.parse_str(&encoded_str)?;
+        Ok(TantivyValue::Bytes(bytes))
+    }
+
+    pub(crate) fn format_to_json(&self, bytes: &[u8]) -> JsonValue {
+        JsonValue::String(self.output_format.format_to_string(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_bytes_options_deser_default() {
+        let bytes_options: QuickwitBytesOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(bytes_options, QuickwitBytesOptions::default());
+
+        let bytes_options: QuickwitBytesOptions = serde_json::from_value(json!({
+            "input_format": "hex",
+            "output_format": "base64",
+        }))
+        .unwrap();
+        assert_eq!(bytes_options.input_format, BinaryFormat::Hex);
+        assert_eq!(bytes_options.output_format, BinaryFormat::Base64);
+
+        let error = serde_json::from_value::<QuickwitBytesOptions>(json!({
+            "input_format": "base32",
+        }))
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown variant `base32`"));
+    }
+
+    #[test]
+    fn test_binary_format_hex() {
+        assert_eq!(
+            BinaryFormat::Hex.parse_str("00ff7A").unwrap(),
+            vec![0x00, 0xff, 0x7a]
+        );
+        assert_eq!(BinaryFormat::Hex.parse_str("").unwrap(), Vec::<u8>::new());
+        assert_eq!(
+            BinaryFormat::Hex.parse_str("abc").unwrap_err(),
+            "Expected hex string, got `abc`: odd number of digits."
+        );
+        assert_eq!(
+            BinaryFormat::Hex.parse_str("0g").unwrap_err(),
+            "Expected hex string, got `0g`: invalid character `g`."
+        );
+        assert_eq!(
+            BinaryFormat::Hex.format_to_string(&[0x00, 0xff, 0x7a]),
+            "00ff7a"
+        );
+    }
+
+    #[test]
+    fn test_bytes_options_parse_and_format_json() {
+        let bytes_options = QuickwitBytesOptions {
+            input_format: BinaryFormat::Hex,
+            output_format: BinaryFormat::Base64,
+            ..Default::default()
+        };
+        let value = bytes_options.parse_json(json!("68656c6c6f")).unwrap();
+        assert_eq!(value.as_bytes().unwrap(), b"hello");
+        assert_eq!(bytes_options.format_to_json(b"hello"), json!("aGVsbG8="));
+
+        let error = bytes_options.parse_json(json!(2)).unwrap_err();
+        assert_eq!(error, "Expected hex string, got `2`.");
+    }
+}
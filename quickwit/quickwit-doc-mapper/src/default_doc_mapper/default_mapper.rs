@@ -17,27 +17,34 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::num::NonZeroU32;
 
 use anyhow::{bail, Context};
-use quickwit_proto::SearchRequest;
+use quickwit_proto::{SearchRequest, SortOrder};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value as JsonValue};
-use tantivy::query::Query;
+use tantivy::query::{EmptyQuery, Query};
 use tantivy::schema::{Cardinality, Field, FieldType, Schema, Value as TantivyValue, STORED};
+use tantivy::tokenizer::TokenizerManager;
 use tantivy::Document;
 
 use super::field_mapping_entry::QuickwitTextTokenizer;
-use super::DefaultDocMapperBuilder;
-use crate::default_doc_mapper::mapping_tree::{build_mapping_tree, MappingNode, MappingTree};
+use super::{validate_field_mapping_name, BinaryFormat, DefaultDocMapperBuilder};
+use crate::default_doc_mapper::dynamic_type_hints::DynamicTypeHints;
+use crate::default_doc_mapper::mapping_tree::{
+    build_mapping_tree, JsonFastFieldMapping, LeafType, MappingNode, MappingTree,
+    MissingFieldPolicy,
+};
 pub use crate::default_doc_mapper::QuickwitJsonOptions;
 use crate::doc_mapper::{JsonObject, Partition};
-use crate::query_builder::build_query;
+use crate::query_builder::{build_query, validate_sort_by_field, QuerySettings};
 use crate::routing_expression::RoutingExpr;
+use crate::schema_evolution::{adapt_request_to_split_schema, split_lacks_fields};
+use crate::tokenizers::create_tokenizer_manager;
 use crate::{
-    DocMapper, DocParsingError, ModeType, QueryParserError, WarmupInfo, DYNAMIC_FIELD_NAME,
-    SOURCE_FIELD_NAME,
+    DocMapper, DocParsingError, ModeType, QueryParserError, TermAutomaton, TokenizerEntry,
+    VectorField, WarmupInfo, DYNAMIC_FIELD_NAME, NESTED_FIELD_NAME, SOURCE_FIELD_NAME,
 };
 
 /// Defines how an unmapped field should be handled.
@@ -75,10 +82,28 @@ pub struct DefaultDocMapper {
     /// This field is only valid when using the schema associated with the default
     /// doc mapper, and therefore cannot be used in the `query` method.
     dynamic_field: Option<Field>,
+    /// Field in which the elements of the nested fields should be stored.
+    /// This field is only valid when using the schema associated with the default
+    /// doc mapper, and therefore cannot be used in the `query` method.
+    nested_field: Option<Field>,
     /// Default list of field names used for search.
     default_search_field_names: Vec<String>,
+    /// Whether the queries may contain regex clauses.
+    enable_regex_queries: bool,
+    /// Search settings applied when parsing the queries.
+    query_settings: QuerySettings,
+    /// Maximum number of terms a regex or fuzzy clause may match in a split segment.
+    max_term_expansions: Option<usize>,
+    /// Field and order the hits are sorted by when the request does not specify any sort.
+    default_sort_by: Option<(String, SortOrder)>,
     /// Timestamp field name.
     timestamp_field_name: Option<String>,
+    /// Name of the field uniquely identifying a document.
+    doc_unique_id_field_name: Option<String>,
+    /// Whether documents can be upserted.
+    enable_upserts: bool,
+    /// Alternate names of the fields, mapping each alias to the path of the field it refers to.
+    field_aliases: BTreeMap<String, String>,
     /// Root node of the field mapping tree.
     /// See [`MappingNode`] and [`MappingTree`].
     field_mappings: MappingNode,
@@ -86,18 +111,81 @@ pub struct DefaultDocMapper {
     schema: Schema,
     /// List of field names used for tagging.
     tag_field_names: BTreeSet<String>,
+    /// List of numeric fast field names whose range of values is recorded in the split metadata.
+    range_pruning_field_names: BTreeSet<String>,
+    /// List of text field names whose terms are recorded in a bloom filter in the split metadata.
+    bloom_filter_field_names: BTreeSet<String>,
+    /// List of `geo_point` field names.
+    geo_point_field_names: BTreeSet<String>,
+    /// `vector` fields, keyed by field name.
+    vector_fields: BTreeMap<String, VectorField>,
+    /// List of `nested` field paths.
+    nested_field_names: BTreeSet<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     partition_key: RoutingExpr,
     /// Maximum number of partitions
     max_num_partitions: NonZeroU32,
-    /// List of required fields. Right now this is the list of fast fields.
+    /// Version of the doc mapping.
+    doc_mapping_version: u64,
+    /// List of required fields. Right now this is the list of single-valued fast fields without
+    /// missing-field policy.
     required_fields: Vec<Field>,
+    /// JSON sub-fields copied into dedicated fast fields.
+    json_fast_field_mappings: Vec<JsonFastFieldMapping>,
+    /// Fields with a missing-field policy, applied once the document is parsed.
+    missing_field_policies: Vec<(Field, MissingFieldPolicy)>,
     /// Defines how unmapped fields should be handle.
     mode: Mode,
+    /// Types enforced on the dynamically mapped fields.
+    dynamic_type_hints: DynamicTypeHints,
+    /// Custom tokenizers defined in the doc mapping.
+    tokenizer_entries: Vec<TokenizerEntry>,
+    /// Tokenizer manager holding Quickwit's tokenizers and the custom tokenizers.
+    tokenizer_manager: TokenizerManager,
 }
 
 impl DefaultDocMapper {
+    /// Rejects the regex clauses unless the index allows them.
+    fn validate_term_automatons(&self, warmup_info: &WarmupInfo) -> anyhow::Result<()> {
+        if self.enable_regex_queries {
+            return Ok(());
+        }
+        let regex_clause_opt = warmup_info
+            .term_automatons
+            .iter()
+            .find(|(_, term_automaton)| matches!(term_automaton, TermAutomaton::Regex { .. }));
+        if let Some((field, term_automaton)) = regex_clause_opt {
+            bail!(
+                "Regex clause `{}:{term_automaton}` is not allowed: regex queries are disabled on \
+                 this index. They can be enabled with the `enable_regex_queries` search setting.",
+                self.schema.get_field_name(*field)
+            );
+        }
+        Ok(())
+    }
+
+    fn apply_missing_field_policies(&self, doc: &mut Document) -> Result<(), DocParsingError> {
+        for (field, missing_field_policy) in &self.missing_field_policies {
+            if doc.get_first(*field).is_some() {
+                continue;
+            }
+            match missing_field_policy {
+                MissingFieldPolicy::Fail => {
+                    let missing_field_name = self.schema.get_field_name(*field);
+                    return Err(DocParsingError::MissingField(
+                        missing_field_name.to_string(),
+                    ));
+                }
+                MissingFieldPolicy::Skip => {}
+                MissingFieldPolicy::Default(default_value) => {
+                    doc.add_field_value(*field, default_value.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn check_missing_required_fields(&self, doc: &Document) -> Result<(), DocParsingError> {
         for &required_field in &self.required_fields {
             if doc.get_first(required_field).is_none() {
@@ -141,6 +229,82 @@ fn validate_tag_fields(tag_fields: &[String], schema: &Schema) -> anyhow::Result
     Ok(())
 }
 
+/// Parses and validates the default sort field, prefixed with `-` for a descending order, or `+`
+/// for an ascending one, the default.
+fn resolve_default_sort_by(
+    default_sort_by_field_opt: Option<&str>,
+    schema: &Schema,
+) -> anyhow::Result<Option<(String, SortOrder)>> {
+    let Some(default_sort_by_field) = default_sort_by_field_opt else {
+        return Ok(None);
+    };
+    let (field_name, sort_order) = if let Some(field_name) = default_sort_by_field.strip_prefix('-')
+    {
+        (field_name.trim(), SortOrder::Desc)
+    } else if let Some(field_name) = default_sort_by_field.strip_prefix('+') {
+        (field_name.trim(), SortOrder::Asc)
+    } else {
+        (default_sort_by_field.trim(), SortOrder::Asc)
+    };
+    validate_sort_by_field(field_name, schema, None)
+        .context("Invalid `default_sort_by_field` search setting.")?;
+    Ok(Some((field_name.to_string(), sort_order)))
+}
+
+fn validate_range_pruning_fields(
+    range_pruning_fields: &[String],
+    schema: &Schema,
+) -> anyhow::Result<BTreeSet<String>> {
+    let mut range_pruning_field_names = BTreeSet::new();
+    for field_name in range_pruning_fields {
+        if !range_pruning_field_names.insert(field_name.clone()) {
+            bail!("Duplicated range pruning field: `{field_name}`");
+        }
+        let field = schema
+            .get_field(field_name)
+            .with_context(|| format!("Unknown range pruning field: `{field_name}`"))?;
+        let is_numeric_fast_field = match schema.get_field_entry(field).field_type() {
+            FieldType::U64(options) | FieldType::I64(options) | FieldType::F64(options) => {
+                options.get_fastfield_cardinality().is_some()
+            }
+            _ => false,
+        };
+        if !is_numeric_fast_field {
+            bail!(
+                "Range pruning field `{field_name}` must be a `u64`, `i64`, or `f64` fast field."
+            );
+        }
+    }
+    Ok(range_pruning_field_names)
+}
+
+fn validate_bloom_filter_fields(
+    bloom_filter_fields: &[String],
+    schema: &Schema,
+) -> anyhow::Result<BTreeSet<String>> {
+    let mut bloom_filter_field_names = BTreeSet::new();
+    for field_name in bloom_filter_fields {
+        if !bloom_filter_field_names.insert(field_name.clone()) {
+            bail!("Duplicated bloom filter field: `{field_name}`");
+        }
+        let field = schema
+            .get_field(field_name)
+            .with_context(|| format!("Unknown bloom filter field: `{field_name}`"))?;
+        let tokenizer_opt = match schema.get_field_entry(field).field_type() {
+            FieldType::Str(options) => options
+                .get_indexing_options()
+                .map(|text_options| text_options.tokenizer()),
+            _ => None,
+        };
+        if tokenizer_opt != Some(QuickwitTextTokenizer::Raw.get_name()) {
+            bail!(
+                "Bloom filter field `{field_name}` must be a text field with the `raw` tokenizer."
+            );
+        }
+    }
+    Ok(bloom_filter_field_names)
+}
+
 fn list_required_fields_for_node(node: &MappingNode) -> Vec<Field> {
     node.children().flat_map(list_required_fields).collect()
 }
@@ -157,6 +321,107 @@ fn list_required_fields(field_mappings: &MappingTree) -> Vec<Field> {
             }
         }
         MappingTree::Node(node) => list_required_fields_for_node(node),
+        // A document may have no nested element at all.
+        MappingTree::Nested(_) => Vec::new(),
+    }
+}
+
+fn list_geo_point_field_names(node: &MappingNode, schema: &Schema) -> BTreeSet<String> {
+    let mut geo_point_field_names = BTreeSet::new();
+    for child in node.children() {
+        match child {
+            MappingTree::Leaf(leaf) => {
+                if let LeafType::GeoPoint(_) = leaf.get_type() {
+                    geo_point_field_names.insert(schema.get_field_name(leaf.field()).to_string());
+                }
+            }
+            MappingTree::Node(child_node) | MappingTree::Nested(child_node) => {
+                geo_point_field_names.extend(list_geo_point_field_names(child_node, schema));
+            }
+        }
+    }
+    geo_point_field_names
+}
+
+fn list_bytes_input_formats(node: &MappingNode, schema: &Schema) -> HashMap<String, BinaryFormat> {
+    let mut bytes_input_formats = HashMap::new();
+    for child in node.children() {
+        match child {
+            MappingTree::Leaf(leaf) => {
+                if let LeafType::Bytes(options) = leaf.get_type() {
+                    bytes_input_formats.insert(
+                        schema.get_field_name(leaf.field()).to_string(),
+                        options.input_format,
+                    );
+                }
+            }
+            MappingTree::Node(child_node) | MappingTree::Nested(child_node) => {
+                bytes_input_formats.extend(list_bytes_input_formats(child_node, schema));
+            }
+        }
+    }
+    bytes_input_formats
+}
+
+fn list_vector_fields(node: &MappingNode, schema: &Schema) -> BTreeMap<String, VectorField> {
+    let mut vector_fields = BTreeMap::new();
+    for child in node.children() {
+        match child {
+            MappingTree::Leaf(leaf) => {
+                if let LeafType::Vector(options, dims) = leaf.get_type() {
+                    let vector_field = VectorField {
+                        dims: *dims,
+                        similarity: options.similarity,
+                    };
+                    vector_fields.insert(
+                        schema.get_field_name(leaf.field()).to_string(),
+                        vector_field,
+                    );
+                }
+            }
+            MappingTree::Node(child_node) | MappingTree::Nested(child_node) => {
+                vector_fields.extend(list_vector_fields(child_node, schema));
+            }
+        }
+    }
+    vector_fields
+}
+
+fn list_nested_field_names<'a>(
+    node: &'a MappingNode,
+    field_path: &mut Vec<&'a str>,
+) -> BTreeSet<String> {
+    let mut nested_field_names = BTreeSet::new();
+    for (field_name, child) in &node.branches {
+        field_path.push(field_name);
+        match child {
+            MappingTree::Leaf(_) => {}
+            MappingTree::Node(child_node) => {
+                nested_field_names.extend(list_nested_field_names(child_node, field_path));
+            }
+            MappingTree::Nested(_) => {
+                nested_field_names.insert(field_path.join("."));
+            }
+        }
+        field_path.pop();
+    }
+    nested_field_names
+}
+
+/// Merges `json_obj` into `doc_json`, recursing into the objects present in both.
+fn merge_json_obj(
+    doc_json: &mut serde_json::Map<String, JsonValue>,
+    json_obj: serde_json::Map<String, JsonValue>,
+) {
+    for (key, json_val) in json_obj {
+        match (doc_json.get_mut(&key), json_val) {
+            (Some(JsonValue::Object(doc_json_child)), JsonValue::Object(json_obj_child)) => {
+                merge_json_obj(doc_json_child, json_obj_child);
+            }
+            (_, json_val) => {
+                doc_json.insert(key, json_val);
+            }
+        }
     }
 }
 
@@ -199,11 +464,98 @@ fn resolve_timestamp_field(
     Ok(())
 }
 
+fn resolve_doc_unique_id_field(
+    doc_unique_id_field_name_opt: Option<&String>,
+    schema: &Schema,
+) -> anyhow::Result<()> {
+    if let Some(unique_id_field_name) = doc_unique_id_field_name_opt {
+        let unique_id_field = schema
+            .get_field(unique_id_field_name)
+            .with_context(|| format!("Unknown doc unique ID field: `{unique_id_field_name}`"))?;
+        let unique_id_field_entry = schema.get_field_entry(unique_id_field);
+        match unique_id_field_entry.field_type() {
+            FieldType::Str(_) | FieldType::U64(_) | FieldType::I64(_) | FieldType::Bytes(_) => {}
+            _ => {
+                bail!(
+                    "Doc unique ID field must be of type text, u64, i64, or bytes, please change \
+                     your field type `{}`.",
+                    unique_id_field_name
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_upserts(
+    doc_unique_id_field_name_opt: Option<&str>,
+    timestamp_field_name_opt: Option<&str>,
+    schema: &Schema,
+) -> anyhow::Result<()> {
+    let Some(unique_id_field_name) = doc_unique_id_field_name_opt else {
+        bail!("Upserts require a `doc_unique_id_field`.");
+    };
+    if timestamp_field_name_opt.is_none() {
+        bail!("Upserts require a `timestamp_field`.");
+    }
+    let unique_id_field = schema.get_field(unique_id_field_name)?;
+    if let FieldType::Bytes(_) = schema.get_field_entry(unique_id_field).field_type() {
+        bail!("Upserts are not supported on bytes doc unique ID fields.");
+    }
+    Ok(())
+}
+
+/// Checks that the tokenizers of the text and JSON fields are registered in the tokenizer
+/// manager.
+fn validate_field_tokenizers(
+    schema: &Schema,
+    tokenizer_manager: &TokenizerManager,
+) -> anyhow::Result<()> {
+    for (_field, field_entry) in schema.fields() {
+        let text_indexing_options_opt = match field_entry.field_type() {
+            FieldType::Str(text_options) => text_options.get_indexing_options(),
+            FieldType::JsonObject(json_options) => json_options.get_text_indexing_options(),
+            _ => None,
+        };
+        if let Some(text_indexing_options) = text_indexing_options_opt {
+            let tokenizer_name = text_indexing_options.tokenizer();
+            if tokenizer_manager.get(tokenizer_name).is_none() {
+                bail!(
+                    "Unknown tokenizer `{tokenizer_name}` for field `{}`.",
+                    field_entry.name()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_field_aliases(
+    field_aliases: &BTreeMap<String, String>,
+    schema: &Schema,
+) -> anyhow::Result<()> {
+    for (alias, field_path) in field_aliases {
+        validate_field_mapping_name(alias)
+            .with_context(|| format!("Invalid field alias `{alias}`."))?;
+        if schema.get_field(alias).is_ok() {
+            bail!("Field alias `{alias}` conflicts with an existing field.");
+        }
+        if field_aliases.contains_key(field_path) {
+            bail!("Field alias `{alias}` must not refer to another alias `{field_path}`.");
+        }
+        schema
+            .get_field(field_path)
+            .with_context(|| format!("Unknown field `{field_path}` for field alias `{alias}`."))?;
+    }
+    Ok(())
+}
+
 impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
     type Error = anyhow::Error;
 
     fn try_from(builder: DefaultDocMapperBuilder) -> anyhow::Result<DefaultDocMapper> {
         let mode = builder.mode()?;
+        let dynamic_type_hints = DynamicTypeHints::new(builder.dynamic_type_hints)?;
         let mut schema_builder = Schema::builder();
         let field_mappings = build_mapping_tree(&builder.field_mappings, &mut schema_builder)?;
         let source_field = if builder.store_source {
@@ -218,8 +570,18 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             None
         };
 
+        let nested_field_names = list_nested_field_names(&field_mappings, &mut Vec::new());
+        let nested_field = if nested_field_names.is_empty() {
+            None
+        } else {
+            Some(schema_builder.add_json_field(NESTED_FIELD_NAME, STORED))
+        };
+
         let schema = schema_builder.build();
 
+        let tokenizer_manager = create_tokenizer_manager(&builder.tokenizers)?;
+        validate_field_tokenizers(&schema, &tokenizer_manager)?;
+
         // validate fast fields
         validate_tag_fields(&builder.tag_fields, &schema)?;
 
@@ -236,6 +598,28 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
         }
 
         resolve_timestamp_field(builder.timestamp_field.as_ref(), &schema)?;
+        let default_sort_by =
+            resolve_default_sort_by(builder.default_sort_by_field.as_deref(), &schema)?;
+        if builder.max_query_clauses == Some(0) {
+            bail!("The `max_query_clauses` search setting must be strictly positive.");
+        }
+        if builder.max_term_expansions == Some(0) {
+            bail!("The `max_term_expansions` search setting must be strictly positive.");
+        }
+        let query_settings = QuerySettings {
+            default_operator: builder.default_operator,
+            max_query_clauses: builder.max_query_clauses,
+            bytes_input_formats: list_bytes_input_formats(&field_mappings, &schema),
+        };
+        resolve_doc_unique_id_field(builder.doc_unique_id_field.as_ref(), &schema)?;
+        if builder.enable_upserts {
+            validate_upserts(
+                builder.doc_unique_id_field.as_deref(),
+                builder.timestamp_field.as_deref(),
+                &schema,
+            )?;
+        }
+        validate_field_aliases(&builder.field_aliases, &schema)?;
 
         // Resolve tag fields
         let mut tag_field_names: BTreeSet<String> = Default::default();
@@ -249,21 +633,64 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
             tag_field_names.insert(tag_field_name.clone());
         }
 
-        let required_fields = list_required_fields_for_node(&field_mappings);
+        let range_pruning_field_names =
+            validate_range_pruning_fields(&builder.range_pruning_fields, &schema)?;
+        let bloom_filter_field_names =
+            validate_bloom_filter_fields(&builder.bloom_filter_fields, &schema)?;
+
+        let json_fast_field_mappings = field_mappings.json_fast_field_mappings(&schema);
+        let missing_field_policies = field_mappings.missing_field_policies()?;
+        let mut required_fields = list_required_fields_for_node(&field_mappings);
+        for (field, missing_field_policy) in &missing_field_policies {
+            if matches!(missing_field_policy, MissingFieldPolicy::Skip)
+                && required_fields.contains(field)
+            {
+                bail!(
+                    "Single-valued fast field `{}` cannot skip missing values.",
+                    schema.get_field_name(*field)
+                );
+            }
+        }
+        required_fields.retain(|required_field| {
+            missing_field_policies
+                .iter()
+                .all(|(field, _)| field != required_field)
+        });
+        let geo_point_field_names = list_geo_point_field_names(&field_mappings, &schema);
+        let vector_fields = list_vector_fields(&field_mappings, &schema);
         let partition_key = RoutingExpr::new(builder.partition_key.as_deref().unwrap_or(""))
             .context("Failed to interpret the partition key.")?;
         Ok(DefaultDocMapper {
             schema,
             source_field,
             dynamic_field,
+            nested_field,
             default_search_field_names,
+            enable_regex_queries: builder.enable_regex_queries,
+            query_settings,
+            max_term_expansions: builder.max_term_expansions,
+            default_sort_by,
             timestamp_field_name: builder.timestamp_field,
+            doc_unique_id_field_name: builder.doc_unique_id_field,
+            enable_upserts: builder.enable_upserts,
+            field_aliases: builder.field_aliases,
             field_mappings,
             tag_field_names,
+            range_pruning_field_names,
+            bloom_filter_field_names,
+            geo_point_field_names,
+            vector_fields,
+            nested_field_names,
             required_fields,
+            json_fast_field_mappings,
+            missing_field_policies,
             partition_key,
             max_num_partitions: builder.max_num_partitions,
+            doc_mapping_version: builder.doc_mapping_version,
             mode,
+            dynamic_type_hints,
+            tokenizer_entries: builder.tokenizers,
+            tokenizer_manager,
         })
     }
 }
@@ -275,6 +702,13 @@ impl From<DefaultDocMapper> for DefaultDocMapperBuilder {
             Mode::Dynamic(mapping_options) => Some(mapping_options.clone()),
             _ => None,
         };
+        let default_sort_by_field =
+            default_doc_mapper
+                .default_sort_by
+                .map(|(field_name, sort_order)| match sort_order {
+                    SortOrder::Asc => field_name,
+                    SortOrder::Desc => format!("-{field_name}"),
+                });
         let partition_key_str = default_doc_mapper.partition_key.to_string();
         let partition_key_opt: Option<String> = if partition_key_str.is_empty() {
             None
@@ -286,13 +720,32 @@ impl From<DefaultDocMapper> for DefaultDocMapperBuilder {
             timestamp_field: default_doc_mapper
                 .timestamp_field_name()
                 .map(ToString::to_string),
+            doc_unique_id_field: default_doc_mapper.doc_unique_id_field_name,
+            enable_upserts: default_doc_mapper.enable_upserts,
             field_mappings: default_doc_mapper.field_mappings.into(),
+            field_aliases: default_doc_mapper.field_aliases,
+            tokenizers: default_doc_mapper.tokenizer_entries,
             tag_fields: default_doc_mapper.tag_field_names.into_iter().collect(),
+            range_pruning_fields: default_doc_mapper
+                .range_pruning_field_names
+                .into_iter()
+                .collect(),
+            bloom_filter_fields: default_doc_mapper
+                .bloom_filter_field_names
+                .into_iter()
+                .collect(),
             default_search_fields: default_doc_mapper.default_search_field_names,
+            enable_regex_queries: default_doc_mapper.enable_regex_queries,
+            default_operator: default_doc_mapper.query_settings.default_operator,
+            max_query_clauses: default_doc_mapper.query_settings.max_query_clauses,
+            max_term_expansions: default_doc_mapper.max_term_expansions,
+            default_sort_by_field,
             mode,
             dynamic_mapping,
+            dynamic_type_hints: default_doc_mapper.dynamic_type_hints.type_hints().to_vec(),
             partition_key: partition_key_opt,
             max_num_partitions: default_doc_mapper.max_num_partitions,
+            doc_mapping_version: default_doc_mapper.doc_mapping_version,
         }
     }
 }
@@ -344,6 +797,7 @@ impl DocMapper for DefaultDocMapper {
         let partition: Partition = self.partition_key.eval_hash(&json_obj);
 
         let mut dynamic_json_obj = serde_json::Map::default();
+        let mut nested_json_obj = serde_json::Map::default();
         let mut field_path = Vec::new();
         let mut document = Document::default();
 
@@ -358,14 +812,26 @@ impl DocMapper for DefaultDocMapper {
             &mut document,
             &mut field_path,
             &mut dynamic_json_obj,
+            &mut nested_json_obj,
         )?;
 
         if let Some(dynamic_field) = self.dynamic_field {
             if !dynamic_json_obj.is_empty() {
+                self.dynamic_type_hints.apply(&mut dynamic_json_obj)?;
                 document.add_json_object(dynamic_field, dynamic_json_obj);
             }
         }
 
+        if let Some(nested_field) = self.nested_field {
+            if !nested_json_obj.is_empty() {
+                document.add_json_object(nested_field, nested_json_obj);
+            }
+        }
+
+        for json_fast_field_mapping in &self.json_fast_field_mappings {
+            json_fast_field_mapping.populate_doc(&mut document)?;
+        }
+        self.apply_missing_field_policies(&mut document)?;
         self.check_missing_required_fields(&document)?;
         Ok((partition, document))
     }
@@ -380,6 +846,10 @@ impl DocMapper for DefaultDocMapper {
         self.field_mappings
             .populate_json(&mut named_doc, &mut field_path, &mut doc_json);
 
+        if let Some(nested_json_obj) = extract_single_obj(&mut named_doc, NESTED_FIELD_NAME)? {
+            merge_json_obj(&mut doc_json, nested_json_obj);
+        }
+
         if let Some(source_json) = extract_single_obj(&mut named_doc, SOURCE_FIELD_NAME)? {
             doc_json.insert(
                 SOURCE_FIELD_NAME.to_string(),
@@ -401,7 +871,37 @@ impl DocMapper for DefaultDocMapper {
                 tantivy_default_search_field_names.push(DYNAMIC_FIELD_NAME.to_string());
             }
         }
-        build_query(split_schema, request, &tantivy_default_search_field_names)
+        if split_lacks_fields(&self.schema, &split_schema) {
+            let Some((split_request, split_default_search_field_names)) =
+                adapt_request_to_split_schema(
+                    &self.schema,
+                    &split_schema,
+                    request,
+                    &tantivy_default_search_field_names,
+                    self.query_settings.default_operator.default_occur(),
+                )
+            else {
+                return Ok((Box::new(EmptyQuery), WarmupInfo::default()));
+            };
+            let (query, warmup_info) = build_query(
+                split_schema,
+                &split_request,
+                &split_default_search_field_names,
+                &self.tokenizer_manager,
+                &self.query_settings,
+            )?;
+            self.validate_term_automatons(&warmup_info)?;
+            return Ok((query, warmup_info));
+        }
+        let (query, warmup_info) = build_query(
+            split_schema,
+            request,
+            &tantivy_default_search_field_names,
+            &self.tokenizer_manager,
+            &self.query_settings,
+        )?;
+        self.validate_term_automatons(&warmup_info)?;
+        Ok((query, warmup_info))
     }
 
     fn schema(&self) -> Schema {
@@ -412,18 +912,94 @@ impl DocMapper for DefaultDocMapper {
         self.timestamp_field_name.as_deref()
     }
 
+    fn doc_unique_id_field_name(&self) -> Option<&str> {
+        self.doc_unique_id_field_name.as_deref()
+    }
+
+    fn upserts_enabled(&self) -> bool {
+        self.enable_upserts
+    }
+
     fn tag_field_names(&self) -> BTreeSet<String> {
         self.tag_field_names.clone()
     }
 
+    fn range_pruning_field_names(&self) -> BTreeSet<String> {
+        self.range_pruning_field_names.clone()
+    }
+
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        self.bloom_filter_field_names.clone()
+    }
+
+    fn geo_point_field_names(&self) -> BTreeSet<String> {
+        self.geo_point_field_names.clone()
+    }
+
+    fn vector_fields(&self) -> BTreeMap<String, VectorField> {
+        self.vector_fields.clone()
+    }
+
+    fn tokenizer_manager(&self) -> &TokenizerManager {
+        &self.tokenizer_manager
+    }
+
+    fn field_aliases(&self) -> BTreeMap<String, String> {
+        self.field_aliases.clone()
+    }
+
+    fn nested_field_names(&self) -> BTreeSet<String> {
+        self.nested_field_names.clone()
+    }
+
+    fn nested_docs_from_json(
+        &self,
+        nested_path: &str,
+        mut doc_json: JsonObject,
+    ) -> anyhow::Result<Vec<Document>> {
+        let field_path: Vec<&str> = nested_path.split('.').collect();
+        let nested_node = self
+            .field_mappings
+            .find_nested_node(&field_path)
+            .with_context(|| format!("Unknown nested field `{nested_path}`."))?;
+        let (last_field_name, parent_field_path) = field_path
+            .split_last()
+            .expect("Split should return at least one element.");
+        let mut parent_json_obj = &mut doc_json;
+        for field_name in parent_field_path {
+            match parent_json_obj.get_mut(*field_name) {
+                Some(JsonValue::Object(child_json_obj)) => parent_json_obj = child_json_obj,
+                _ => return Ok(Vec::new()),
+            }
+        }
+        let Some(nested_json_val) = parent_json_obj.remove(*last_field_name) else {
+            return Ok(Vec::new());
+        };
+        let mut path: Vec<String> = field_path.iter().map(ToString::to_string).collect();
+        let nested_docs = nested_node.nested_docs_from_json(nested_json_val, &mut path)?;
+        Ok(nested_docs)
+    }
+
+    fn max_term_expansions(&self) -> Option<usize> {
+        self.max_term_expansions
+    }
+
+    fn default_sort_by(&self) -> Option<(String, SortOrder)> {
+        self.default_sort_by.clone()
+    }
+
     fn max_num_partitions(&self) -> NonZeroU32 {
         self.max_num_partitions
     }
+
+    fn doc_mapping_version(&self) -> u64 {
+        self.doc_mapping_version
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::{BTreeSet, HashMap};
 
     use quickwit_proto::SearchRequest;
     use serde_json::{self, json, Value as JsonValue};
@@ -696,71 +1272,248 @@ mod tests {
     }
 
     #[test]
-    fn test_fail_with_field_name_equal_to_source() {
-        let doc_mapper = r#"{
-            "default_search_fields": [],
-            "tag_fields": [],
-            "field_mappings": [
-                {
-                    "name": "_source",
-                    "type": "i64"
-                }
-            ]
-        }"#;
-        let deser_err = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)
-            .err()
-            .unwrap();
-        assert!(deser_err
-            .to_string()
-            .contains("The following fields are reserved for Quickwit internal usage"));
-    }
-
-    #[test]
-    fn test_fail_to_parse_document_with_wrong_base64_value() -> anyhow::Result<()> {
-        let doc_mapper = r#"{
-            "default_search_fields": [],
-            "timestamp_field": null,
-            "tag_fields": [],
-            "field_mappings": [
-                {
-                    "name": "image",
-                    "type": "bytes",
-                    "stored": true
-                }
-            ]
-        }"#;
-        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
-        let doc_mapper = builder.try_build()?;
-        let result = doc_mapper.doc_from_json_str(
-            r#"{
-            "image": "invalid base64 data"
-        }"#,
-        );
-        let expected_msg = "The field `image` could not be parsed: Expected Base64 string, got \
-                            `invalid base64 data`: Invalid byte 32, offset 7.";
-        assert_eq!(result.unwrap_err().to_string(), expected_msg);
+    fn test_build_doc_mapper_with_doc_unique_id_field() -> anyhow::Result<()> {
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "request_id",
+                "field_mappings": [
+                    {"name": "request_id", "type": "text", "tokenizer": "raw"}
+                ]
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            let doc_mapper = builder.try_build()?;
+            assert_eq!(doc_mapper.doc_unique_id_field_name(), Some("request_id"));
+        }
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "request_id",
+                "field_mappings": []
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            assert_eq!(
+                builder.try_build().unwrap_err().to_string(),
+                "Unknown doc unique ID field: `request_id`"
+            );
+        }
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "request_id",
+                "field_mappings": [
+                    {"name": "request_id", "type": "f64"}
+                ]
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            let expected_msg = "Doc unique ID field must be of type text, u64, i64, or bytes, \
+                                please change your field type `request_id`.";
+            assert_eq!(builder.try_build().unwrap_err().to_string(), expected_msg);
+        }
         Ok(())
     }
 
     #[test]
-    fn test_parse_document_with_tag_fields() {
-        let doc_mapper = r#"{
-            "default_search_fields": [],
-            "timestamp_field": null,
-            "tag_fields": ["city"],
-            "store_source": true,
-            "field_mappings": [
-                {
-                    "name": "city",
-                    "type": "text",
-                    "stored": true,
-                    "tokenizer": "raw"
-                },
-                {
-                    "name": "image",
-                    "type": "bytes",
-                    "stored": true
-                }
+    fn test_build_doc_mapper_with_upserts() -> anyhow::Result<()> {
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "user_id",
+                "timestamp_field": "ts",
+                "enable_upserts": true,
+                "field_mappings": [
+                    {"name": "user_id", "type": "text", "tokenizer": "raw"},
+                    {"name": "ts", "type": "datetime", "fast": true}
+                ]
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            let doc_mapper = builder.try_build()?;
+            assert!(doc_mapper.upserts_enabled());
+        }
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "user_id",
+                "enable_upserts": true,
+                "field_mappings": [
+                    {"name": "user_id", "type": "text", "tokenizer": "raw"}
+                ]
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            assert_eq!(
+                builder.try_build().unwrap_err().to_string(),
+                "Upserts require a `timestamp_field`."
+            );
+        }
+        {
+            let doc_mapper = r#"{
+                "doc_unique_id_field": "user_id",
+                "timestamp_field": "ts",
+                "enable_upserts": true,
+                "field_mappings": [
+                    {"name": "user_id", "type": "bytes"},
+                    {"name": "ts", "type": "datetime", "fast": true}
+                ]
+            }"#;
+            let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+            assert_eq!(
+                builder.try_build().unwrap_err().to_string(),
+                "Upserts are not supported on bytes doc unique ID fields."
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_field_aliases() -> anyhow::Result<()> {
+        let build_doc_mapper = |field_aliases: JsonValue| {
+            let doc_mapper_json = json!({
+                "field_aliases": field_aliases,
+                "field_mappings": [
+                    {
+                        "name": "body",
+                        "type": "object",
+                        "field_mappings": [{"name": "text", "type": "text"}]
+                    },
+                    {"name": "severity", "type": "text"}
+                ]
+            });
+            serde_json::from_value::<DefaultDocMapperBuilder>(doc_mapper_json)
+                .unwrap()
+                .try_build()
+        };
+        let doc_mapper = build_doc_mapper(json!({"message": "body.text"}))?;
+        assert_eq!(
+            doc_mapper
+                .field_aliases()
+                .get("message")
+                .map(String::as_str),
+            Some("body.text")
+        );
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper)?;
+        assert_eq!(
+            serialized_doc_mapper["field_aliases"],
+            json!({"message": "body.text"})
+        );
+
+        let error = build_doc_mapper(json!({"message": "body.unknown"})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown field `body.unknown` for field alias `message`."
+        );
+        let error = build_doc_mapper(json!({"severity": "body.text"})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Field alias `severity` conflicts with an existing field."
+        );
+        let error =
+            build_doc_mapper(json!({"message": "body.text", "msg": "message"})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Field alias `msg` must not refer to another alias `message`."
+        );
+        let error = build_doc_mapper(json!({"_source": "body.text"})).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid field alias `_source`.");
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_custom_tokenizers() -> anyhow::Result<()> {
+        let doc_mapper_json = json!({
+            "tokenizers": [
+                {"name": "prefix", "type": "ngram", "min_gram": 1, "max_gram": 10, "prefix_only": true}
+            ],
+            "field_mappings": [
+                {"name": "service", "type": "text", "tokenizer": "prefix"},
+                {"name": "attributes", "type": "json", "tokenizer": "prefix"}
+            ]
+        });
+        let doc_mapper =
+            serde_json::from_value::<DefaultDocMapperBuilder>(doc_mapper_json.clone())?
+                .try_build()?;
+        assert!(doc_mapper.tokenizer_manager().get("prefix").is_some());
+        let serialized_doc_mapper = serde_json::to_value(&doc_mapper)?;
+        assert_eq!(
+            serialized_doc_mapper["tokenizers"],
+            doc_mapper_json["tokenizers"]
+        );
+
+        let doc_mapper_json = json!({
+            "field_mappings": [
+                {"name": "service", "type": "text", "tokenizer": "prefix"}
+            ]
+        });
+        let error = serde_json::from_value::<DefaultDocMapperBuilder>(doc_mapper_json)?
+            .try_build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown tokenizer `prefix` for field `service`."
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fail_with_field_name_equal_to_source() {
+        let doc_mapper = r#"{
+            "default_search_fields": [],
+            "tag_fields": [],
+            "field_mappings": [
+                {
+                    "name": "_source",
+                    "type": "i64"
+                }
+            ]
+        }"#;
+        let deser_err = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)
+            .err()
+            .unwrap();
+        assert!(deser_err
+            .to_string()
+            .contains("The following fields are reserved for Quickwit internal usage"));
+    }
+
+    #[test]
+    fn test_fail_to_parse_document_with_wrong_base64_value() -> anyhow::Result<()> {
+        let doc_mapper = r#"{
+            "default_search_fields": [],
+            "timestamp_field": null,
+            "tag_fields": [],
+            "field_mappings": [
+                {
+                    "name": "image",
+                    "type": "bytes",
+                    "stored": true
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper)?;
+        let doc_mapper = builder.try_build()?;
+        let result = doc_mapper.doc_from_json_str(
+            r#"{
+            "image": "invalid base64 data"
+        }"#,
+        );
+        let expected_msg = "The field `image` could not be parsed: Expected Base64 string, got \
+                            `invalid base64 data`: Invalid byte 32, offset 7.";
+        assert_eq!(result.unwrap_err().to_string(), expected_msg);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_document_with_tag_fields() {
+        let doc_mapper = r#"{
+            "default_search_fields": [],
+            "timestamp_field": null,
+            "tag_fields": ["city"],
+            "store_source": true,
+            "field_mappings": [
+                {
+                    "name": "city",
+                    "type": "text",
+                    "stored": true,
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "image",
+                    "type": "bytes",
+                    "stored": true
+                }
             ]
         }"#;
 
@@ -843,6 +1596,71 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_doc_mapper_with_range_pruning_fields() {
+        let doc_mapper = r#"{
+            "range_pruning_fields": ["status_code", "latency"],
+            "field_mappings": [
+                {
+                    "name": "status_code",
+                    "type": "u64",
+                    "fast": true
+                },
+                {
+                    "name": "latency",
+                    "type": "f64",
+                    "fast": true
+                },
+                {
+                    "name": "port",
+                    "type": "u64"
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        let doc_mapper = builder.try_build().unwrap();
+        assert_eq!(
+            doc_mapper.range_pruning_field_names(),
+            BTreeSet::from(["latency".to_string(), "status_code".to_string()])
+        );
+        let mut builder = DefaultDocMapperBuilder::from(doc_mapper);
+        builder.range_pruning_fields = vec!["port".to_string()];
+        assert_eq!(
+            builder.try_build().unwrap_err().to_string(),
+            "Range pruning field `port` must be a `u64`, `i64`, or `f64` fast field."
+        );
+    }
+
+    #[test]
+    fn test_build_doc_mapper_with_bloom_filter_fields() {
+        let doc_mapper = r#"{
+            "bloom_filter_fields": ["trace_id"],
+            "field_mappings": [
+                {
+                    "name": "trace_id",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "body",
+                    "type": "text"
+                }
+            ]
+        }"#;
+        let builder = serde_json::from_str::<DefaultDocMapperBuilder>(doc_mapper).unwrap();
+        let doc_mapper = builder.try_build().unwrap();
+        assert_eq!(
+            doc_mapper.bloom_filter_field_names(),
+            BTreeSet::from(["trace_id".to_string()])
+        );
+        let mut builder = DefaultDocMapperBuilder::from(doc_mapper);
+        builder.bloom_filter_fields = vec!["body".to_string()];
+        assert_eq!(
+            builder.try_build().unwrap_err().to_string(),
+            "Bloom filter field `body` must be a text field with the `raw` tokenizer."
+        );
+    }
+
     // See #1132
     #[test]
     fn test_by_default_store_source_is_false_and_fields_are_stored_individually() {
@@ -1040,6 +1858,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_dymamic_mode_type_hints() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "mode": "dynamic",
+            "dynamic_type_hints": [
+                {"pattern": "*_at", "type": "datetime"},
+                {"pattern": "*_count", "type": "u64"}
+            ]
+        }"#,
+        )
+        .unwrap();
+        let dynamic_field = default_doc_mapper
+            .schema()
+            .get_field(DYNAMIC_FIELD_NAME)
+            .unwrap();
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(
+                r#"{ "created_at": 1672531200, "user": { "login_count": "12" }, "name": "7" }"#,
+            )
+            .unwrap();
+        let vals: Vec<&TantivyValue> = doc.get_all(dynamic_field).collect();
+        assert_eq!(vals.len(), 1);
+        if let TantivyValue::JsonObject(json_val) = &vals[0] {
+            assert_eq!(
+                serde_json::to_value(json_val).unwrap(),
+                json!({
+                    "created_at": "2023-01-01T00:00:00Z",
+                    "user": {
+                        "login_count": 12
+                    },
+                    "name": "7"
+                })
+            );
+        } else {
+            panic!("Expected json");
+        }
+        let error = default_doc_mapper
+            .doc_from_json_str(r#"{ "login_count": "many" }"#)
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(_, _)));
+
+        // The type hints survive a serialization round trip.
+        let serialized_doc_mapper = serde_json::to_string(&default_doc_mapper).unwrap();
+        let deserialized_doc_mapper: DefaultDocMapper =
+            serde_json::from_str(&serialized_doc_mapper).unwrap();
+        assert_eq!(
+            deserialized_doc_mapper.dynamic_type_hints.type_hints(),
+            default_doc_mapper.dynamic_type_hints.type_hints()
+        );
+    }
+
+    #[test]
+    fn test_fail_to_build_doc_mapper_with_type_hints_outside_dynamic_mode() {
+        let error = serde_json::from_str::<DefaultDocMapper>(
+            r#"{
+            "mode": "lenient",
+            "dynamic_type_hints": [{"pattern": "*_at", "type": "datetime"}]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`dynamic_type_hints` is only allowed with mode=dynamic"));
+    }
+
     #[test]
     fn test_json_object_in_mapping() {
         let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
@@ -1081,6 +1965,431 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_geo_point_in_mapping() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "location",
+                    "type": "geo_point"
+                },
+                {
+                    "name": "trip",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "stops",
+                            "type": "array<geo_point>"
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            default_doc_mapper
+                .geo_point_field_names()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["location".to_string(), "trip.stops".to_string()]
+        );
+        // Geo point fields are not required.
+        default_doc_mapper.doc_from_json_str("{}").unwrap();
+
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(
+                r#"{"location": [2.35, 48.85], "trip": {"stops": ["48.85,2.35", {"lat": 51.5, "lon": -0.12}]}}"#,
+            )
+            .unwrap();
+        let named_doc = default_doc_mapper.schema().to_named_doc(&doc).0;
+        let doc_json = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        let location = crate::GeoPoint::from_json(&doc_json["location"]).unwrap();
+        assert!((location.lat - 48.85).abs() < 1e-6);
+        assert!((location.lon - 2.35).abs() < 1e-6);
+        assert_eq!(doc_json["trip"]["stops"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_vector_in_mapping() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "embedding",
+                    "type": "vector<f32, 3>",
+                    "similarity": "l2_norm"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            default_doc_mapper.vector_fields(),
+            std::collections::BTreeMap::from_iter([(
+                "embedding".to_string(),
+                crate::VectorField {
+                    dims: 3,
+                    similarity: crate::VectorSimilarity::L2Norm,
+                }
+            )])
+        );
+        // Vector fields are not required.
+        default_doc_mapper.doc_from_json_str("{}").unwrap();
+
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"embedding": [0.5, -1.0, 2.0]}"#)
+            .unwrap();
+        let named_doc = default_doc_mapper.schema().to_named_doc(&doc).0;
+        let doc_json = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(doc_json["embedding"], json!([0.5, -1.0, 2.0]));
+
+        let error = default_doc_mapper
+            .doc_from_json_str(r#"{"embedding": [0.5, -1.0]}"#)
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(_, _)));
+    }
+
+    #[test]
+    fn test_hex_bytes_in_mapping() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "sha1",
+                    "type": "bytes",
+                    "input_format": "hex",
+                    "output_format": "hex"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"sha1": "DA39A3EE5E6B4B0D3255BFEF95601890AFD80709"}"#)
+            .unwrap();
+        let named_doc = default_doc_mapper.schema().to_named_doc(&doc).0;
+        let doc_json = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(
+            doc_json["sha1"],
+            json!("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+        );
+
+        let query = default_doc_mapper_query_aux(&default_doc_mapper, "sha1:da39a3ee*").unwrap();
+        assert!(query.contains("RangeQuery { field: \"sha1\", value_type: Bytes"));
+        let error = default_doc_mapper_query_aux(&default_doc_mapper, "sha1:da39a3e").unwrap_err();
+        assert!(error.contains("odd number of digits"));
+    }
+
+    #[test]
+    fn test_json_fast_fields() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "attributes",
+                    "type": "json",
+                    "fast_fields": [
+                        {"name": "http.status", "type": "u64"},
+                        {"name": "cached", "type": "bool"}
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let schema = default_doc_mapper.schema();
+        let status_field = schema.get_field("attributes.http.status").unwrap();
+        let status_field_entry = schema.get_field_entry(status_field);
+        assert!(status_field_entry.is_fast());
+        assert!(!status_field_entry.is_stored());
+        let cached_field = schema.get_field("attributes.cached").unwrap();
+
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(
+                r#"{"attributes": {"http": {"status": 200}, "cached": [true, false], "host": "a"}}"#,
+            )
+            .unwrap();
+        assert_eq!(
+            doc.get_all(status_field).collect::<Vec<_>>(),
+            [&TantivyValue::U64(200)]
+        );
+        assert_eq!(doc.get_all(cached_field).count(), 2);
+
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": {"http.status": 404}}"#)
+            .unwrap();
+        assert_eq!(
+            doc.get_all(status_field).collect::<Vec<_>>(),
+            [&TantivyValue::U64(404)]
+        );
+        // JSON fast fields are not required.
+        default_doc_mapper.doc_from_json_str("{}").unwrap();
+
+        let error = default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": {"http": {"status": "OK"}}}"#)
+            .unwrap_err();
+        assert!(matches!(error, DocParsingError::ValueError(path, _) if path == "http.status"));
+    }
+
+    #[test]
+    fn test_doc_value_only_numeric_field() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "latency",
+                    "type": "u64",
+                    "indexed": false,
+                    "stored": false,
+                    "fast": true
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let schema = default_doc_mapper.schema();
+        let field_entry = schema.get_field_entry(schema.get_field("latency").unwrap());
+        assert!(field_entry.is_fast());
+        assert!(!field_entry.is_indexed());
+        assert!(!field_entry.is_stored());
+    }
+
+    #[test]
+    fn test_missing_field_policies() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "severity",
+                    "type": "text",
+                    "tokenizer": "raw",
+                    "default_value": "INFO"
+                },
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "service",
+                            "type": "text",
+                            "on_missing": "fail"
+                        }
+                    ]
+                },
+                {
+                    "name": "latency",
+                    "type": "u64",
+                    "fast": true,
+                    "default_value": 0
+                },
+                {
+                    "name": "tags",
+                    "type": "array<text>",
+                    "on_missing": "skip"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        let error = default_doc_mapper.doc_from_json_str("{}").unwrap_err();
+        assert_eq!(
+            error,
+            DocParsingError::MissingField("resource.service".to_string())
+        );
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"resource": {"service": "api"}, "severity": null}"#)
+            .unwrap();
+        let named_doc = default_doc_mapper.schema().to_named_doc(&doc).0;
+        let doc_json = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(doc_json["severity"], json!("INFO"));
+        assert_eq!(doc_json["latency"], json!(0));
+
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"resource": {"service": "api"}, "severity": "WARN"}"#)
+            .unwrap();
+        let named_doc = default_doc_mapper.schema().to_named_doc(&doc).0;
+        let doc_json = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(doc_json["severity"], json!("WARN"));
+
+        let serialized_doc_mapper = serde_json::to_value(&default_doc_mapper).unwrap();
+        assert_eq!(
+            serialized_doc_mapper["field_mappings"][0]["default_value"],
+            json!("INFO")
+        );
+        assert_eq!(
+            serialized_doc_mapper["field_mappings"][3]["on_missing"],
+            json!("skip")
+        );
+    }
+
+    #[test]
+    fn test_invalid_missing_field_policies() {
+        for (field_mapping, expected_error) in [
+            (
+                r#"{"name": "latency", "type": "u64", "default_value": "fast"}"#,
+                "Invalid default value for field `latency`",
+            ),
+            (
+                r#"{"name": "latency", "type": "u64", "fast": true, "on_missing": "skip"}"#,
+                "cannot skip missing values",
+            ),
+            (
+                r#"{"name": "latency", "type": "u64", "on_missing": "default"}"#,
+                "`on_missing: default` requires a `default_value`",
+            ),
+            (
+                r#"{"name": "latency", "type": "u64", "on_missing": "fail", "default_value": 0}"#,
+                "`default_value` is only allowed with `on_missing: default`",
+            ),
+            (
+                r#"{"name": "spans", "type": "nested", "field_mappings": [{"name": "latency", "type": "u64", "default_value": 0}]}"#,
+                "not allowed within nested field",
+            ),
+        ] {
+            let doc_mapper_json = format!(r#"{{"field_mappings": [{field_mapping}]}}"#);
+            let error = serde_json::from_str::<DefaultDocMapper>(&doc_mapper_json).unwrap_err();
+            assert!(
+                error.to_string().contains(expected_error),
+                "`{error}` does not contain `{expected_error}`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_vector_in_nested_mapping_is_rejected() {
+        let error = serde_json::from_str::<DefaultDocMapper>(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "chunks",
+                    "type": "nested",
+                    "field_mappings": [
+                        {
+                            "name": "embedding",
+                            "type": "vector<f32, 3>"
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("cannot be declared within a nested field"));
+    }
+
+    #[test]
+    fn test_nested_in_mapping() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "mode": "strict",
+            "field_mappings": [
+                {
+                    "name": "trace_id",
+                    "type": "text",
+                    "tokenizer": "raw"
+                },
+                {
+                    "name": "spans",
+                    "type": "nested",
+                    "field_mappings": [
+                        {
+                            "name": "name",
+                            "type": "text"
+                        },
+                        {
+                            "name": "duration",
+                            "type": "u64",
+                            "fast": true
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            default_doc_mapper
+                .nested_field_names()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["spans".to_string()]
+        );
+        let schema = default_doc_mapper.schema();
+        let duration_field = schema.get_field("spans.duration").unwrap();
+        assert!(schema.get_field_entry(duration_field).is_fast());
+        // Nested fields are not required.
+        default_doc_mapper.doc_from_json_str("{}").unwrap();
+
+        let doc_json = json!({
+            "trace_id": "abc",
+            "spans": [
+                {"name": "foo", "duration": 50},
+                {"name": "bar", "duration": 150}
+            ]
+        });
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(&doc_json.to_string())
+            .unwrap();
+        assert_eq!(doc.get_all(duration_field).count(), 2);
+        let named_doc = schema.to_named_doc(&doc).0;
+        let doc_json_roundtrip = default_doc_mapper.doc_to_json(named_doc).unwrap();
+        assert_eq!(JsonValue::Object(doc_json_roundtrip), doc_json);
+
+        let nested_docs = default_doc_mapper
+            .nested_docs_from_json("spans", doc_json.as_object().unwrap().clone())
+            .unwrap();
+        assert_eq!(nested_docs.len(), 2);
+        assert_eq!(
+            nested_docs[1].get_first(duration_field),
+            Some(&TantivyValue::U64(150))
+        );
+        assert!(default_doc_mapper
+            .nested_docs_from_json("trace_id", doc_json.as_object().unwrap().clone())
+            .is_err());
+
+        // Strict mode applies to the nested elements.
+        let parsing_error = default_doc_mapper
+            .doc_from_json_str(r#"{"spans": [{"name": "foo", "status": "ok"}]}"#)
+            .unwrap_err();
+        assert!(matches!(
+            parsing_error,
+            DocParsingError::NoSuchFieldInSchema(_)
+        ));
+        let parsing_error = default_doc_mapper
+            .doc_from_json_str(r#"{"spans": ["foo"]}"#)
+            .unwrap_err();
+        assert!(matches!(parsing_error, DocParsingError::ValueError(_, _)));
+    }
+
+    #[test]
+    fn test_nested_within_nested_is_rejected() {
+        let error = serde_json::from_str::<DefaultDocMapper>(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "spans",
+                    "type": "nested",
+                    "field_mappings": [
+                        {
+                            "name": "events",
+                            "type": "nested",
+                            "field_mappings": [{"name": "name", "type": "text"}]
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("cannot be declared within another nested field"));
+    }
+
     fn default_doc_mapper_query_aux(
         doc_mapper: &dyn DocMapper,
         query: &str,
@@ -17,15 +17,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::num::NonZeroU32;
 
 use anyhow::bail;
 use serde::{Deserialize, Serialize};
 
-use super::FieldMappingEntry;
+use super::{DynamicTypeHint, FieldMappingEntry};
 use crate::default_doc_mapper::default_mapper::Mode;
 use crate::default_doc_mapper::QuickwitJsonOptions;
-use crate::DefaultDocMapper;
+use crate::{DefaultDocMapper, QueryOperator, TokenizerEntry};
 
 /// DefaultDocMapperBuilder is here
 /// to create a valid DocMapper.
@@ -42,16 +43,64 @@ pub struct DefaultDocMapperBuilder {
     /// Name of the fields that are searched by default, unless overridden.
     #[serde(default)]
     pub default_search_fields: Vec<String>,
+    /// Allows the queries to contain regex clauses.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub enable_regex_queries: bool,
+    /// Operator combining the clauses of the queries that are not joined by an explicit `AND` or
+    /// `OR`.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "QueryOperator::is_and")]
+    pub default_operator: QueryOperator,
+    /// Maximum number of clauses of a query.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_query_clauses: Option<usize>,
+    /// Maximum number of terms a regex or fuzzy clause may match in a split segment.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_term_expansions: Option<usize>,
+    /// Field the hits are sorted by when the request does not specify any sort, prefixed with
+    /// `-` for a descending order.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_sort_by_field: Option<String>,
     /// Name of the field storing the timestamp of the event for time series data.
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamp_field: Option<String>,
+    /// Name of the field uniquely identifying a document. When set, the indexer can drop
+    /// documents whose ID was already seen recently.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_unique_id_field: Option<String>,
+    /// Accepts upserts, and only returns the latest version of a document among the hits of a
+    /// page. Requires the doc unique ID and timestamp fields.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub enable_upserts: bool,
     /// Describes which fields are indexed and how.
     #[serde(default)]
     pub field_mappings: Vec<FieldMappingEntry>,
+    /// Alternate names of the fields, mapping each alias to the path of the field it refers to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_aliases: BTreeMap<String, String>,
+    /// Custom tokenizers which the text and JSON fields can refer to.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokenizers: Vec<TokenizerEntry>,
     /// Name of the fields that are tagged.
     #[serde(default)]
     pub tag_fields: Vec<String>,
+    /// Name of the numeric fast fields whose range of values is recorded in the split metadata.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub range_pruning_fields: Vec<String>,
+    /// Name of the text fields whose terms are recorded in a bloom filter in the split metadata.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bloom_filter_fields: Vec<String>,
     /// The partition key is a DSL used to route documents
     /// into specific splits.
     #[serde(default)]
@@ -59,6 +108,11 @@ pub struct DefaultDocMapperBuilder {
     /// Maximum number of partitions.
     #[serde(default = "DefaultDocMapper::default_max_num_partitions")]
     pub max_num_partitions: NonZeroU32,
+    /// Version of the doc mapping, incremented every time the doc mapping of the index is
+    /// updated.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub doc_mapping_version: u64,
     /// Defines the indexing mode.
     #[serde(default)]
     pub mode: ModeType,
@@ -66,6 +120,11 @@ pub struct DefaultDocMapperBuilder {
     /// how the unmapped fields should be handled.
     #[serde(default)]
     pub dynamic_mapping: Option<QuickwitJsonOptions>,
+    /// If mode is set to dynamic, `dynamic_type_hints` enforces the type of the unmapped fields
+    /// whose path matches a pattern.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_type_hints: Vec<DynamicTypeHint>,
 }
 
 /// `Mode` describing how the unmapped field should be handled.
@@ -83,6 +142,14 @@ pub enum ModeType {
     Dynamic,
 }
 
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 #[cfg(test)]
 impl Default for DefaultDocMapperBuilder {
     fn default() -> Self {
@@ -98,6 +165,21 @@ impl DefaultDocMapperBuilder {
                 self.mode
             );
         }
+        if self.mode != ModeType::Dynamic && !self.dynamic_type_hints.is_empty() {
+            bail!(
+                "`dynamic_type_hints` is only allowed with mode=dynamic. (Here mode=`{:?}`)",
+                self.mode
+            );
+        }
+        if self
+            .dynamic_mapping
+            .as_ref()
+            .map_or(false, |dynamic_mapping| {
+                !dynamic_mapping.fast_fields.is_empty()
+            })
+        {
+            bail!("`fast_fields` is not allowed in `dynamic_mapping`.");
+        }
         Ok(match self.mode {
             ModeType::Lenient => Mode::Lenient,
             ModeType::Strict => Mode::Strict,
@@ -127,6 +209,13 @@ mod tests {
         assert!(default_mapper_builder.dynamic_mapping.is_none());
         assert_eq!(default_mapper_builder.store_source, false);
         assert!(default_mapper_builder.timestamp_field.is_none());
+        assert!(default_mapper_builder.doc_unique_id_field.is_none());
+        assert!(default_mapper_builder.field_aliases.is_empty());
+        assert!(default_mapper_builder.tokenizers.is_empty());
+        assert_eq!(default_mapper_builder.default_operator, QueryOperator::And);
+        assert!(default_mapper_builder.max_query_clauses.is_none());
+        assert!(default_mapper_builder.max_term_expansions.is_none());
+        assert!(default_mapper_builder.default_sort_by_field.is_none());
     }
 
     #[test]
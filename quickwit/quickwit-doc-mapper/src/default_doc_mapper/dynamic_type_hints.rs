@@ -0,0 +1,318 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{bail, Context};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value as JsonValue};
+use tantivy::schema::Value as TantivyValue;
+
+use crate::default_doc_mapper::date_time_type::QuickwitDateTimeOptions;
+use crate::DocParsingError;
+
+/// Type enforced on the dynamic fields matching a type hint.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DynamicFieldType {
+    Text,
+    I64,
+    U64,
+    F64,
+    Bool,
+    Datetime,
+}
+
+impl DynamicFieldType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DynamicFieldType::Text => "text",
+            DynamicFieldType::I64 => "i64",
+            DynamicFieldType::U64 => "u64",
+            DynamicFieldType::F64 => "f64",
+            DynamicFieldType::Bool => "bool",
+            DynamicFieldType::Datetime => "datetime",
+        }
+    }
+}
+
+/// Enforces a type on the dynamic fields whose path matches `pattern`. The pattern is matched
+/// against the full path of the field, e.g. `user.created_at`, and may contain `*` wildcards.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct DynamicTypeHint {
+    pub pattern: String,
+    #[serde(rename = "type")]
+    pub field_type: DynamicFieldType,
+}
+
+/// Compiled list of type hints. When several hints match a field, the first one wins.
+#[derive(Clone, Default)]
+pub(crate) struct DynamicTypeHints {
+    type_hints: Vec<DynamicTypeHint>,
+    patterns: Vec<Regex>,
+}
+
+impl DynamicTypeHints {
+    pub fn new(type_hints: Vec<DynamicTypeHint>) -> anyhow::Result<Self> {
+        let mut patterns = Vec::with_capacity(type_hints.len());
+        for type_hint in &type_hints {
+            if type_hint.pattern.is_empty() {
+                bail!("Dynamic type hint pattern is empty.");
+            }
+            let regex_str = format!(
+                "^{}$",
+                type_hint
+                    .pattern
+                    .split('*')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join(".*")
+            );
+            let pattern = Regex::new(&regex_str).with_context(|| {
+                format!("Invalid dynamic type hint pattern `{}`.", type_hint.pattern)
+            })?;
+            patterns.push(pattern);
+        }
+        Ok(Self {
+            type_hints,
+            patterns,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.type_hints.is_empty()
+    }
+
+    pub fn type_hints(&self) -> &[DynamicTypeHint] {
+        &self.type_hints
+    }
+
+    fn field_type(&self, field_path: &str) -> Option<DynamicFieldType> {
+        self.patterns
+            .iter()
+            .position(|pattern| pattern.is_match(field_path))
+            .map(|hint_ord| self.type_hints[hint_ord].field_type)
+    }
+
+    /// Converts the values of the dynamic fields matching a type hint to the hinted type.
+    pub fn apply(
+        &self,
+        dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
+    ) -> Result<(), DocParsingError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut field_path = String::new();
+        self.apply_to_obj(dynamic_json_obj, &mut field_path)
+    }
+
+    fn apply_to_obj(
+        &self,
+        json_obj: &mut serde_json::Map<String, JsonValue>,
+        field_path: &mut String,
+    ) -> Result<(), DocParsingError> {
+        for (field_name, json_value) in json_obj.iter_mut() {
+            let parent_path_len = field_path.len();
+            if !field_path.is_empty() {
+                field_path.push('.');
+            }
+            field_path.push_str(field_name);
+            self.apply_to_value(json_value, field_path)?;
+            field_path.truncate(parent_path_len);
+        }
+        Ok(())
+    }
+
+    fn apply_to_value(
+        &self,
+        json_value: &mut JsonValue,
+        field_path: &mut String,
+    ) -> Result<(), DocParsingError> {
+        match json_value {
+            JsonValue::Object(json_obj) => self.apply_to_obj(json_obj, field_path),
+            JsonValue::Array(json_values) => {
+                for json_value in json_values {
+                    self.apply_to_value(json_value, field_path)?;
+                }
+                Ok(())
+            }
+            JsonValue::Null => Ok(()),
+            _ => {
+                if let Some(field_type) = self.field_type(field_path) {
+                    let converted_value = convert_json_value(json_value.take(), field_type)
+                        .map_err(|error| {
+                            DocParsingError::ValueError(field_path.to_string(), error)
+                        })?;
+                    *json_value = converted_value;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn convert_json_value(
+    json_value: JsonValue,
+    field_type: DynamicFieldType,
+) -> Result<JsonValue, String> {
+    if field_type == DynamicFieldType::Datetime {
+        // Datetimes are normalized to RFC 3339 strings, which tantivy indexes as dates in JSON
+        // fields.
+        let date_time_options = QuickwitDateTimeOptions::default();
+        let TantivyValue::Date(date_time) = date_time_options.parse_json(json_value)? else {
+            unreachable!("Parsing a datetime should yield a date value.");
+        };
+        return date_time_options.format_to_json(date_time);
+    }
+    let converted_value_opt = match (field_type, &json_value) {
+        (DynamicFieldType::Text, JsonValue::String(_)) => Some(json_value.clone()),
+        (DynamicFieldType::Text, JsonValue::Number(number)) => {
+            Some(JsonValue::String(number.to_string()))
+        }
+        (DynamicFieldType::Text, JsonValue::Bool(value)) => {
+            Some(JsonValue::String(value.to_string()))
+        }
+        (DynamicFieldType::I64, JsonValue::Number(number)) => number.as_i64().map(JsonValue::from),
+        (DynamicFieldType::I64, JsonValue::String(text)) => {
+            text.trim().parse::<i64>().ok().map(JsonValue::from)
+        }
+        (DynamicFieldType::U64, JsonValue::Number(number)) => number.as_u64().map(JsonValue::from),
+        (DynamicFieldType::U64, JsonValue::String(text)) => {
+            text.trim().parse::<u64>().ok().map(JsonValue::from)
+        }
+        (DynamicFieldType::F64, JsonValue::Number(number)) => number
+            .as_f64()
+            .and_then(Number::from_f64)
+            .map(JsonValue::Number),
+        (DynamicFieldType::F64, JsonValue::String(text)) => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(JsonValue::Number),
+        (DynamicFieldType::Bool, JsonValue::Bool(_)) => Some(json_value.clone()),
+        (DynamicFieldType::Bool, JsonValue::String(text)) => {
+            text.trim().parse::<bool>().ok().map(JsonValue::Bool)
+        }
+        _ => None,
+    };
+    converted_value_opt.ok_or_else(|| {
+        format!(
+            "Failed to convert `{json_value}` to type `{}`.",
+            field_type.as_str()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn type_hints(hints: &[(&str, DynamicFieldType)]) -> DynamicTypeHints {
+        let type_hints = hints
+            .iter()
+            .map(|(pattern, field_type)| DynamicTypeHint {
+                pattern: pattern.to_string(),
+                field_type: *field_type,
+            })
+            .collect();
+        DynamicTypeHints::new(type_hints).unwrap()
+    }
+
+    #[test]
+    fn test_dynamic_type_hints_field_type() {
+        let type_hints = type_hints(&[
+            ("*_at", DynamicFieldType::Datetime),
+            ("user.id", DynamicFieldType::U64),
+            ("*", DynamicFieldType::Text),
+        ]);
+        assert_eq!(
+            type_hints.field_type("created_at"),
+            Some(DynamicFieldType::Datetime)
+        );
+        assert_eq!(
+            type_hints.field_type("user.updated_at"),
+            Some(DynamicFieldType::Datetime)
+        );
+        assert_eq!(
+            type_hints.field_type("user.id"),
+            Some(DynamicFieldType::U64)
+        );
+        assert_eq!(
+            type_hints.field_type("userXid"),
+            Some(DynamicFieldType::Text)
+        );
+
+        assert!(DynamicTypeHints::new(vec![DynamicTypeHint {
+            pattern: "".to_string(),
+            field_type: DynamicFieldType::Text,
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn test_dynamic_type_hints_apply() {
+        let type_hints = type_hints(&[
+            ("*_at", DynamicFieldType::Datetime),
+            ("*_count", DynamicFieldType::I64),
+            ("*.ratio", DynamicFieldType::F64),
+            ("flag", DynamicFieldType::Bool),
+            ("code", DynamicFieldType::Text),
+        ]);
+        let mut dynamic_json_obj = json!({
+            "created_at": 1_672_531_200,
+            "retry_count": "3",
+            "stats": {"ratio": "0.5", "error_count": [1, "2"]},
+            "flag": "true",
+            "code": 404,
+            "other": "12",
+        })
+        .as_object()
+        .unwrap()
+        .clone();
+        type_hints.apply(&mut dynamic_json_obj).unwrap();
+        assert_eq!(
+            JsonValue::Object(dynamic_json_obj),
+            json!({
+                "created_at": "2023-01-01T00:00:00Z",
+                "retry_count": 3,
+                "stats": {"ratio": 0.5, "error_count": [1, 2]},
+                "flag": true,
+                "code": "404",
+                "other": "12",
+            })
+        );
+    }
+
+    #[test]
+    fn test_dynamic_type_hints_apply_invalid_value() {
+        let type_hints = type_hints(&[("*_count", DynamicFieldType::U64)]);
+        let mut dynamic_json_obj = json!({"user": {"login_count": "many"}})
+            .as_object()
+            .unwrap()
+            .clone();
+        let error = type_hints.apply(&mut dynamic_json_obj).unwrap_err();
+        assert!(matches!(
+            error,
+            DocParsingError::ValueError(field_path, _) if field_path == "user.login_count"
+        ));
+    }
+}
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::convert::TryFrom;
 
 use anyhow::bail;
@@ -26,10 +27,12 @@ use tantivy::schema::{
     Cardinality, IndexRecordOption, JsonObjectOptions, TextFieldIndexing, TextOptions, Type,
 };
 
+use super::bytes_type::QuickwitBytesOptions;
 use super::date_time_type::QuickwitDateTimeOptions;
 use super::{default_as_true, FieldMappingType};
 use crate::default_doc_mapper::field_mapping_type::QuickwitFieldType;
 use crate::default_doc_mapper::validate_field_mapping_name;
+use crate::{VectorSimilarity, MAX_VECTOR_DIMS};
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct QuickwitObjectOptions {
@@ -48,6 +51,76 @@ pub struct FieldMappingEntry {
     pub name: String,
     /// Property parameters which defines the type and the way the value must be indexed.
     pub mapping_type: FieldMappingType,
+    /// Defines how documents that do not hold the field are handled.
+    pub missing_field_options: MissingFieldOptions,
+}
+
+/// Policy applied to the documents that do not hold a field, or hold `null`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnMissing {
+    /// The document is rejected.
+    Fail,
+    /// The document is indexed without the field.
+    Skip,
+    /// The document is indexed with the default value of the field.
+    Default,
+}
+
+/// Missing-field handling of a field mapping entry. When no policy is set, documents missing a
+/// single-valued fast field are rejected and other missing fields are skipped.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MissingFieldOptions {
+    /// Value indexed in place of the missing field.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<JsonValue>,
+    /// Policy applied to the documents missing the field. Defaults to `default` when a default
+    /// value is set.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_missing: Option<OnMissing>,
+}
+
+const MISSING_FIELD_OPTION_KEYS: [&str; 2] = ["default_value", "on_missing"];
+
+impl MissingFieldOptions {
+    /// Returns the policy applied to the documents missing the field, if any.
+    pub fn policy(&self) -> Option<OnMissing> {
+        self.on_missing
+            .or_else(|| self.default_value.as_ref().map(|_| OnMissing::Default))
+    }
+
+    /// Returns true if no missing-field option is set.
+    pub fn is_empty(&self) -> bool {
+        self.default_value.is_none() && self.on_missing.is_none()
+    }
+
+    /// Extracts the missing-field options from the parameters of a field mapping entry.
+    fn extract(
+        field_mapping_json: &mut serde_json::Map<String, JsonValue>,
+    ) -> anyhow::Result<MissingFieldOptions> {
+        let options_json: serde_json::Map<String, JsonValue> = MISSING_FIELD_OPTION_KEYS
+            .iter()
+            .flat_map(|key| field_mapping_json.remove_entry(*key))
+            .collect();
+        let missing_field_options: MissingFieldOptions =
+            serde_json::from_value(JsonValue::Object(options_json))?;
+        match (
+            missing_field_options.on_missing,
+            &missing_field_options.default_value,
+        ) {
+            (Some(OnMissing::Default), None) => {
+                bail!("`on_missing: default` requires a `default_value`.")
+            }
+            (Some(OnMissing::Fail | OnMissing::Skip), Some(_)) => {
+                bail!("`default_value` is only allowed with `on_missing: default`.")
+            }
+            _ => {}
+        }
+        Ok(missing_field_options)
+    }
 }
 
 // Struct used for serialization and deserialization
@@ -129,16 +202,60 @@ impl Default for QuickwitIpAddrOptions {
     }
 }
 
+/// Geo points are always stored in a fast field, so that they can be filtered
+/// at search time. They are not indexed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitGeoPointOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+}
+
+impl Default for QuickwitGeoPointOptions {
+    fn default() -> Self {
+        Self {
+            description: None,
+            stored: true,
+        }
+    }
+}
+
+/// Vectors are always stored in a fast field, so that they can be scored at search time. They
+/// are not indexed.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitVectorOptions {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "default_as_true")]
+    pub stored: bool,
+    #[serde(default)]
+    pub similarity: VectorSimilarity,
+}
+
+impl Default for QuickwitVectorOptions {
+    fn default() -> Self {
+        Self {
+            description: None,
+            stored: true,
+            similarity: VectorSimilarity::default(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(from = "String", into = "String")]
 pub enum QuickwitTextTokenizer {
-    #[serde(rename = "raw")]
     Raw,
-    #[serde(rename = "default")]
     Default,
-    #[serde(rename = "en_stem")]
     StemEn,
-    #[serde(rename = "chinese_compatible")]
     Chinese,
+    /// Language analyzer or tokenizer defined in the `tokenizers` section of the doc mapping.
+    Custom(String),
 }
 
 impl QuickwitTextTokenizer {
@@ -148,6 +265,28 @@ impl QuickwitTextTokenizer {
             QuickwitTextTokenizer::Default => "default",
             QuickwitTextTokenizer::StemEn => "en_stem",
             QuickwitTextTokenizer::Chinese => "chinese_compatible",
+            QuickwitTextTokenizer::Custom(tokenizer_name) => tokenizer_name,
+        }
+    }
+}
+
+impl From<String> for QuickwitTextTokenizer {
+    fn from(tokenizer_name: String) -> Self {
+        match tokenizer_name.as_str() {
+            "raw" => QuickwitTextTokenizer::Raw,
+            "default" => QuickwitTextTokenizer::Default,
+            "en_stem" => QuickwitTextTokenizer::StemEn,
+            "chinese_compatible" => QuickwitTextTokenizer::Chinese,
+            _ => QuickwitTextTokenizer::Custom(tokenizer_name),
+        }
+    }
+}
+
+impl From<QuickwitTextTokenizer> for String {
+    fn from(tokenizer: QuickwitTextTokenizer) -> Self {
+        match tokenizer {
+            QuickwitTextTokenizer::Custom(tokenizer_name) => tokenizer_name,
+            tokenizer => tokenizer.get_name().to_string(),
         }
     }
 }
@@ -233,6 +372,28 @@ pub enum IndexRecordOptionSchema {
     WithFreqsAndPositions,
 }
 
+/// Type of a JSON sub-field stored in a dedicated fast field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonFastFieldType {
+    I64,
+    U64,
+    F64,
+    Bool,
+}
+
+/// Sub-field of a JSON field copied into a dedicated fast field, so that it can be used for
+/// sorting and aggregations.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct QuickwitJsonFastField {
+    /// Path of the sub-field within the JSON object, e.g. `http.status`.
+    pub name: String,
+    /// Type of the sub-field values.
+    #[serde(rename = "type")]
+    pub typ: JsonFastFieldType,
+}
+
 /// Options associated to a json field.
 ///
 /// `QuickwitJsonOptions` is also used to configure
@@ -266,6 +427,10 @@ pub struct QuickwitJsonOptions {
     /// If true, the '.' in json keys will be expanded.
     #[serde(default = "default_as_true")]
     pub expand_dots: bool,
+    /// Sub-fields copied into dedicated fast fields.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fast_fields: Vec<QuickwitJsonFastField>,
 }
 
 impl Default for QuickwitJsonOptions {
@@ -277,6 +442,7 @@ impl Default for QuickwitJsonOptions {
             record: None,
             stored: true,
             expand_dots: true,
+            fast_fields: Vec::new(),
         }
     }
 }
@@ -320,6 +486,24 @@ fn deserialize_mapping_type(
             }
             return Ok(FieldMappingType::Object(object_options));
         }
+        QuickwitFieldType::Nested => {
+            let nested_options: QuickwitObjectOptions = serde_json::from_value(json)?;
+            if nested_options.field_mappings.is_empty() {
+                anyhow::bail!("nested type must have at least one field mapping.");
+            }
+            return Ok(FieldMappingType::Nested(nested_options));
+        }
+        QuickwitFieldType::GeoPoint(cardinality) => {
+            let geo_point_options: QuickwitGeoPointOptions = serde_json::from_value(json)?;
+            return Ok(FieldMappingType::GeoPoint(geo_point_options, cardinality));
+        }
+        QuickwitFieldType::Vector(dims) => {
+            if dims == 0 || dims > MAX_VECTOR_DIMS {
+                bail!("vector type must have between 1 and {MAX_VECTOR_DIMS} dimensions.");
+            }
+            let vector_options: QuickwitVectorOptions = serde_json::from_value(json)?;
+            return Ok(FieldMappingType::Vector(vector_options, dims));
+        }
     };
     match typ {
         Type::Str => {
@@ -364,11 +548,11 @@ fn deserialize_mapping_type(
         }
         Type::Facet => unimplemented!("Facet are not supported in quickwit yet."),
         Type::Bytes => {
-            let numeric_options: QuickwitNumericOptions = serde_json::from_value(json)?;
-            if numeric_options.fast && cardinality == Cardinality::MultiValues {
+            let bytes_options: QuickwitBytesOptions = serde_json::from_value(json)?;
+            if bytes_options.fast && cardinality == Cardinality::MultiValues {
                 bail!("fast field is not allowed for array<bytes>.");
             }
-            Ok(FieldMappingType::Bytes(numeric_options, cardinality))
+            Ok(FieldMappingType::Bytes(bytes_options, cardinality))
         }
         Type::Json => {
             let json_options: QuickwitJsonOptions = serde_json::from_value(json)?;
@@ -380,16 +564,34 @@ fn deserialize_mapping_type(
                     );
                 }
             }
+            validate_json_fast_fields(&json_options.fast_fields)?;
             Ok(FieldMappingType::Json(json_options, cardinality))
         }
     }
 }
 
+fn validate_json_fast_fields(json_fast_fields: &[QuickwitJsonFastField]) -> anyhow::Result<()> {
+    let mut json_fast_field_names = HashSet::new();
+    for json_fast_field in json_fast_fields {
+        let name = &json_fast_field.name;
+        if name.is_empty() || name.split('.').any(str::is_empty) {
+            bail!("Invalid JSON fast field name `{name}`.");
+        }
+        if !json_fast_field_names.insert(name) {
+            bail!("Duplicated JSON fast field `{name}`.");
+        }
+    }
+    Ok(())
+}
+
 impl TryFrom<FieldMappingEntryForSerialization> for FieldMappingEntry {
     type Error = String;
 
     fn try_from(value: FieldMappingEntryForSerialization) -> Result<Self, String> {
         validate_field_mapping_name(&value.name).map_err(|err| err.to_string())?;
+        let mut field_mapping_json = value.field_mapping_json;
+        let missing_field_options = MissingFieldOptions::extract(&mut field_mapping_json)
+            .map_err(|err| format!("Error while parsing field `{}`: {}", value.name, err))?;
         let quickwit_field_type =
             QuickwitFieldType::parse_type_id(&value.type_id).ok_or_else(|| {
                 format!(
@@ -397,14 +599,25 @@ impl TryFrom<FieldMappingEntryForSerialization> for FieldMappingEntry {
                     &value.name, &value.type_id
                 )
             })?;
-        let mapping_type = deserialize_mapping_type(
-            quickwit_field_type,
-            JsonValue::Object(value.field_mapping_json),
-        )
-        .map_err(|err| format!("Error while parsing field `{}`: {}", value.name, err))?;
+        let mapping_type =
+            deserialize_mapping_type(quickwit_field_type, JsonValue::Object(field_mapping_json))
+                .map_err(|err| format!("Error while parsing field `{}`: {}", value.name, err))?;
+        if !missing_field_options.is_empty()
+            && matches!(
+                mapping_type,
+                FieldMappingType::Object(_) | FieldMappingType::Nested(_)
+            )
+        {
+            return Err(format!(
+                "Error while parsing field `{}`: `default_value` and `on_missing` are not allowed \
+                 on object and nested fields.",
+                value.name
+            ));
+        }
         Ok(FieldMappingEntry {
             name: value.name,
             mapping_type,
+            missing_field_options,
         })
     }
 }
@@ -426,13 +639,17 @@ fn typed_mapping_to_json_params(
         FieldMappingType::Text(text_options, _) => serialize_to_map(&text_options),
         FieldMappingType::U64(options, _)
         | FieldMappingType::I64(options, _)
-        | FieldMappingType::Bytes(options, _)
         | FieldMappingType::F64(options, _)
         | FieldMappingType::Bool(options, _) => serialize_to_map(&options),
         FieldMappingType::IpAddr(options, _) => serialize_to_map(&options),
+        FieldMappingType::Bytes(options, _) => serialize_to_map(&options),
+        FieldMappingType::GeoPoint(options, _) => serialize_to_map(&options),
+        FieldMappingType::Vector(options, _) => serialize_to_map(&options),
         FieldMappingType::DateTime(date_time_options, _) => serialize_to_map(&date_time_options),
         FieldMappingType::Json(json_options, _) => serialize_to_map(&json_options),
-        FieldMappingType::Object(object_options) => serialize_to_map(&object_options),
+        FieldMappingType::Object(object_options) | FieldMappingType::Nested(object_options) => {
+            serialize_to_map(&object_options)
+        }
     }
     .unwrap()
 }
@@ -443,7 +660,11 @@ impl From<FieldMappingEntry> for FieldMappingEntryForSerialization {
             .mapping_type
             .quickwit_field_type()
             .to_type_id();
-        let field_mapping_json = typed_mapping_to_json_params(field_mapping_entry.mapping_type);
+        let mut field_mapping_json = typed_mapping_to_json_params(field_mapping_entry.mapping_type);
+        field_mapping_json.extend(
+            serialize_to_map(&field_mapping_entry.missing_field_options)
+                .expect("Missing field options should serialize to a JSON object."),
+        );
         FieldMappingEntryForSerialization {
             name: field_mapping_entry.name,
             type_id,
@@ -461,9 +682,11 @@ mod tests {
 
     use super::FieldMappingEntry;
     use crate::default_doc_mapper::field_mapping_entry::{
-        QuickwitJsonOptions, QuickwitTextOptions, QuickwitTextTokenizer,
+        JsonFastFieldType, QuickwitGeoPointOptions, QuickwitJsonFastField, QuickwitJsonOptions,
+        QuickwitTextOptions, QuickwitTextTokenizer, QuickwitVectorOptions,
     };
     use crate::default_doc_mapper::FieldMappingType;
+    use crate::VectorSimilarity;
 
     #[test]
     fn test_tantivy_text_options_from_quickwit_text_options() {
@@ -586,7 +809,7 @@ mod tests {
     }
 
     #[test]
-    fn test_deserialize_invalid_text_mapping_entry() -> anyhow::Result<()> {
+    fn test_deserialize_text_mapping_entry_with_custom_tokenizer() -> anyhow::Result<()> {
         let mapping_entry = serde_json::from_str::<FieldMappingEntry>(
             r#"
             {
@@ -594,17 +817,21 @@ mod tests {
                 "type": "text",
                 "stored": true,
                 "record": "basic",
-                "tokenizer": "notexist"
+                "tokenizer": "my_tokenizer"
             }
             "#,
-        );
-        assert!(mapping_entry.is_err());
-        assert_eq!(
-            mapping_entry.unwrap_err().to_string(),
-            "Error while parsing field `my_field_name`: unknown variant `notexist`, expected one \
-             of `raw`, `default`, `en_stem`, `chinese_compatible`"
-                .to_string()
-        );
+        )?;
+        match &mapping_entry.mapping_type {
+            FieldMappingType::Text(options, _) => {
+                assert_eq!(
+                    options.tokenizer,
+                    Some(QuickwitTextTokenizer::Custom("my_tokenizer".to_string()))
+                );
+            }
+            _ => panic!("wrong property type"),
+        }
+        let mapping_entry_json = serde_json::to_value(&mapping_entry)?;
+        assert_eq!(mapping_entry_json["tokenizer"], "my_tokenizer");
         Ok(())
     }
 
@@ -766,6 +993,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_deserialize_nested_mapping_entry() {
+        let mapping_entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "spans",
+                "type": "nested",
+                "field_mappings": [
+                    {
+                        "name": "name",
+                        "type": "text"
+                    },
+                    {
+                        "name": "duration",
+                        "type": "u64",
+                        "fast": true
+                    }
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(mapping_entry.name, "spans");
+        match &mapping_entry.mapping_type {
+            FieldMappingType::Nested(options) => {
+                assert_eq!(options.field_mappings.len(), 2);
+            }
+            _ => panic!("wrong property type"),
+        }
+        let mapping_entry_json = serde_json::to_value(&mapping_entry).unwrap();
+        assert_eq!(mapping_entry_json["type"], "nested");
+        assert_eq!(mapping_entry_json["field_mappings"][1]["name"], "duration");
+    }
+
     #[test]
     fn test_deserialize_mapping_with_unknown_type() {
         let result = serde_json::from_str::<FieldMappingEntry>(
@@ -1049,6 +1310,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_geo_point_mapping() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "location",
+                "type": "array<geo_point>",
+                "stored": false
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.mapping_type,
+            FieldMappingType::GeoPoint(
+                QuickwitGeoPointOptions {
+                    description: None,
+                    stored: false,
+                },
+                Cardinality::MultiValues
+            )
+        );
+        let entry_json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            entry_json,
+            serde_json::json!({
+                "name": "location",
+                "type": "array<geo_point>",
+                "stored": false,
+            })
+        );
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "location",
+                "type": "geo_point",
+                "indexed": true
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown field `indexed`"));
+    }
+
+    #[test]
+    fn test_parse_vector_mapping() {
+        let entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "embedding",
+                "type": "vector<f32, 384>",
+                "similarity": "dot_product"
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            entry.mapping_type,
+            FieldMappingType::Vector(
+                QuickwitVectorOptions {
+                    description: None,
+                    stored: true,
+                    similarity: VectorSimilarity::DotProduct,
+                },
+                384
+            )
+        );
+        let entry_json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            entry_json,
+            serde_json::json!({
+                "name": "embedding",
+                "type": "vector<f32, 384>",
+                "stored": true,
+                "similarity": "dot_product",
+            })
+        );
+
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "name": "embedding",
+                "type": "vector<f32, 0>"
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("vector type must have between 1 and 4096 dimensions"));
+    }
+
     #[test]
     fn test_parse_text_mapping() {
         let entry = serde_json::from_str::<FieldMappingEntry>(
@@ -1174,6 +1528,8 @@ mod tests {
                 "stored": true,
                 "indexed": true,
                 "fast": false,
+                "input_format": "base64",
+                "output_format": "base64",
             })
         );
     }
@@ -1198,6 +1554,8 @@ mod tests {
                 "stored": true,
                 "indexed": true,
                 "fast": false,
+                "input_format": "base64",
+                "output_format": "base64",
             })
         );
     }
@@ -1241,6 +1599,7 @@ mod tests {
             record: None,
             stored: true,
             expand_dots: true,
+            fast_fields: Vec::new(),
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field");
         assert!(
@@ -1249,6 +1608,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_json_mapping_with_fast_fields() {
+        let field_mapping_entry = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "type": "json",
+                "name": "attributes",
+                "fast_fields": [
+                    {"name": "http.status", "type": "u64"},
+                    {"name": "latency", "type": "f64"}
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        let FieldMappingType::Json(json_options, _) = &field_mapping_entry.mapping_type else {
+            panic!("Expected JSON mapping type.");
+        };
+        assert_eq!(
+            json_options.fast_fields,
+            [
+                QuickwitJsonFastField {
+                    name: "http.status".to_string(),
+                    typ: JsonFastFieldType::U64,
+                },
+                QuickwitJsonFastField {
+                    name: "latency".to_string(),
+                    typ: JsonFastFieldType::F64,
+                },
+            ]
+        );
+        let error = serde_json::from_str::<FieldMappingEntry>(
+            r#"
+            {
+                "type": "json",
+                "name": "attributes",
+                "fast_fields": [{"name": "http..status", "type": "u64"}]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid JSON fast field name `http..status`"));
+    }
+
     #[test]
     fn test_quickwit_json_options_default_tokenizer_is_default() {
         let quickwit_json_options = QuickwitJsonOptions::default();
@@ -1281,6 +1686,7 @@ mod tests {
             record: None,
             stored: false,
             expand_dots: true,
+            fast_fields: Vec::new(),
         };
         assert_eq!(&field_mapping_entry.name, "my_json_field_multi");
         assert!(
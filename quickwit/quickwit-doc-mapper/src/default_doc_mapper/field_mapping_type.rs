@@ -19,10 +19,11 @@
 
 use tantivy::schema::{Cardinality, Type};
 
+use super::bytes_type::QuickwitBytesOptions;
 use super::date_time_type::QuickwitDateTimeOptions;
 use crate::default_doc_mapper::field_mapping_entry::{
-    QuickwitIpAddrOptions, QuickwitJsonOptions, QuickwitNumericOptions, QuickwitObjectOptions,
-    QuickwitTextOptions,
+    QuickwitGeoPointOptions, QuickwitIpAddrOptions, QuickwitJsonOptions, QuickwitNumericOptions,
+    QuickwitObjectOptions, QuickwitTextOptions, QuickwitVectorOptions,
 };
 
 /// A `FieldMappingType` defines the type and indexing options
@@ -44,11 +45,20 @@ pub enum FieldMappingType {
     /// IP Address mapping type configuration.
     IpAddr(QuickwitIpAddrOptions, Cardinality),
     /// Bytes mapping type configuration.
-    Bytes(QuickwitNumericOptions, Cardinality),
+    Bytes(QuickwitBytesOptions, Cardinality),
+    /// Geo point mapping type configuration.
+    GeoPoint(QuickwitGeoPointOptions, Cardinality),
+    /// Dense vector mapping type configuration, with its number of dimensions.
+    Vector(QuickwitVectorOptions, usize),
     /// Json mapping type configuration.
     Json(QuickwitJsonOptions, Cardinality),
     /// Object mapping type configuration.
     Object(QuickwitObjectOptions),
+    /// Nested mapping type configuration.
+    ///
+    /// Like an object, but the elements of an array of nested objects are kept apart, so that
+    /// a nested query can match several sub-fields within the same element.
+    Nested(QuickwitObjectOptions),
 }
 
 impl FieldMappingType {
@@ -63,9 +73,18 @@ impl FieldMappingType {
             FieldMappingType::DateTime(_, cardinality) => (Type::Date, *cardinality),
             FieldMappingType::Bytes(_, cardinality) => (Type::Bytes, *cardinality),
             FieldMappingType::Json(_, cardinality) => (Type::Json, *cardinality),
+            FieldMappingType::GeoPoint(_, cardinality) => {
+                return QuickwitFieldType::GeoPoint(*cardinality);
+            }
+            FieldMappingType::Vector(_, dims) => {
+                return QuickwitFieldType::Vector(*dims);
+            }
             FieldMappingType::Object(_) => {
                 return QuickwitFieldType::Object;
             }
+            FieldMappingType::Nested(_) => {
+                return QuickwitFieldType::Nested;
+            }
         };
         match cardinality {
             Cardinality::SingleValue => QuickwitFieldType::Simple(primitive_type),
@@ -78,15 +97,27 @@ impl FieldMappingType {
 pub enum QuickwitFieldType {
     Simple(Type),
     Object,
+    Nested,
     Array(Type),
+    GeoPoint(Cardinality),
+    Vector(usize),
 }
 
+const GEO_POINT_TYPE_ID: &str = "geo_point";
+const GEO_POINT_ARRAY_TYPE_ID: &str = "array<geo_point>";
+
 impl QuickwitFieldType {
     pub fn to_type_id(&self) -> String {
         match self {
             QuickwitFieldType::Simple(typ) => primitive_type_to_str(typ).to_string(),
             QuickwitFieldType::Object => "object".to_string(),
+            QuickwitFieldType::Nested => "nested".to_string(),
             QuickwitFieldType::Array(typ) => format!("array<{}>", primitive_type_to_str(typ)),
+            QuickwitFieldType::GeoPoint(Cardinality::SingleValue) => GEO_POINT_TYPE_ID.to_string(),
+            QuickwitFieldType::GeoPoint(Cardinality::MultiValues) => {
+                GEO_POINT_ARRAY_TYPE_ID.to_string()
+            }
+            QuickwitFieldType::Vector(dims) => format!("vector<f32, {dims}>"),
         }
     }
 
@@ -94,6 +125,26 @@ impl QuickwitFieldType {
         if type_str == "object" {
             return Some(QuickwitFieldType::Object);
         }
+        if type_str == "nested" {
+            return Some(QuickwitFieldType::Nested);
+        }
+        if type_str == GEO_POINT_TYPE_ID {
+            return Some(QuickwitFieldType::GeoPoint(Cardinality::SingleValue));
+        }
+        if type_str == GEO_POINT_ARRAY_TYPE_ID {
+            return Some(QuickwitFieldType::GeoPoint(Cardinality::MultiValues));
+        }
+        if let Some(dims_str) = type_str
+            .strip_prefix("vector<")
+            .and_then(|type_str| type_str.strip_suffix('>'))
+        {
+            let (element_type_str, dims_str) = dims_str.split_once(',')?;
+            if element_type_str.trim() != "f32" {
+                return None;
+            }
+            let dims = dims_str.trim().parse::<usize>().ok()?;
+            return Some(QuickwitFieldType::Vector(dims));
+        }
         if type_str.starts_with("array<") && type_str.ends_with('>') {
             let parsed_type_str = parse_primitive_type(&type_str[6..type_str.len() - 1])?;
             return Some(QuickwitFieldType::Array(parsed_type_str));
@@ -137,7 +188,7 @@ fn primitive_type_to_str(primitive_type: &Type) -> &'static str {
 
 #[cfg(test)]
 mod tests {
-    use tantivy::schema::Type;
+    use tantivy::schema::{Cardinality, Type};
 
     use super::QuickwitFieldType;
 
@@ -155,7 +206,31 @@ mod tests {
         test_parse_type_aux("text", Some(QuickwitFieldType::Simple(Type::Str)));
         test_parse_type_aux("object", Some(QuickwitFieldType::Object));
         test_parse_type_aux("object2", None);
+        test_parse_type_aux("nested", Some(QuickwitFieldType::Nested));
         test_parse_type_aux("bool", Some(QuickwitFieldType::Simple(Type::Bool)));
         test_parse_type_aux("ip", Some(QuickwitFieldType::Simple(Type::IpAddr)));
+        test_parse_type_aux(
+            "geo_point",
+            Some(QuickwitFieldType::GeoPoint(Cardinality::SingleValue)),
+        );
+        test_parse_type_aux(
+            "array<geo_point>",
+            Some(QuickwitFieldType::GeoPoint(Cardinality::MultiValues)),
+        );
+        test_parse_type_aux("vector<f32, 384>", Some(QuickwitFieldType::Vector(384)));
+        test_parse_type_aux("vector<f32,3>", Some(QuickwitFieldType::Vector(3)));
+        test_parse_type_aux("vector<f64, 3>", None);
+        test_parse_type_aux("vector<f32>", None);
+        test_parse_type_aux("vector<f32, -3>", None);
+    }
+
+    #[test]
+    fn test_vector_type_id_round_trip() {
+        let type_id = QuickwitFieldType::Vector(384).to_type_id();
+        assert_eq!(type_id, "vector<f32, 384>");
+        assert_eq!(
+            QuickwitFieldType::parse_type_id(&type_id),
+            Some(QuickwitFieldType::Vector(384))
+        );
     }
 }
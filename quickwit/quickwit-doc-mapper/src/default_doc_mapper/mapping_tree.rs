@@ -22,23 +22,29 @@ use std::collections::BTreeMap;
 use std::net::IpAddr;
 use std::str::FromStr;
 
-use anyhow::bail;
-use base64::prelude::{Engine, BASE64_STANDARD};
+use anyhow::{bail, Context};
 use itertools::Itertools;
 use serde_json::Value as JsonValue;
 use tantivy::schema::{
     BytesOptions, Cardinality, Field, IntoIpv6Addr, IpAddrOptions, JsonObjectOptions,
-    NumericOptions, SchemaBuilder, TextOptions, Value as TantivyValue,
+    NumericOptions, Schema, SchemaBuilder, TextOptions, Value as TantivyValue,
 };
 use tantivy::{DateOptions, Document};
 use tracing::warn;
 
+use super::bytes_type::QuickwitBytesOptions;
 use super::date_time_type::QuickwitDateTimeOptions;
 use crate::default_doc_mapper::field_mapping_entry::{
+    JsonFastFieldType, MissingFieldOptions, OnMissing, QuickwitGeoPointOptions,
     QuickwitIpAddrOptions, QuickwitNumericOptions, QuickwitObjectOptions, QuickwitTextOptions,
+    QuickwitVectorOptions,
 };
 use crate::default_doc_mapper::{FieldMappingType, QuickwitJsonOptions};
-use crate::{DocParsingError, FieldMappingEntry, ModeType};
+use crate::geo_point::is_coordinates_pair;
+use crate::{
+    decode_vector, encode_vector, vector_from_json, DocParsingError, FieldMappingEntry, GeoPoint,
+    ModeType,
+};
 
 #[derive(Clone, Debug)]
 pub enum LeafType {
@@ -49,22 +55,28 @@ pub enum LeafType {
     Bool(QuickwitNumericOptions),
     IpAddr(QuickwitIpAddrOptions),
     DateTime(QuickwitDateTimeOptions),
-    Bytes(QuickwitNumericOptions),
+    Bytes(QuickwitBytesOptions),
     Json(QuickwitJsonOptions),
+    GeoPoint(QuickwitGeoPointOptions),
+    /// Dense vector with its number of dimensions.
+    Vector(QuickwitVectorOptions, usize),
 }
 
 impl LeafType {
     pub fn is_single_value_fast_field(&self) -> bool {
         match self {
             LeafType::Text(_) => false, // Text is always multivalue
-            LeafType::I64(opt)
-            | LeafType::U64(opt)
-            | LeafType::F64(opt)
-            | LeafType::Bool(opt)
-            | LeafType::Bytes(opt) => opt.fast,
+            LeafType::I64(opt) | LeafType::U64(opt) | LeafType::F64(opt) | LeafType::Bool(opt) => {
+                opt.fast
+            }
+            LeafType::Bytes(opt) => opt.fast,
             LeafType::IpAddr(opt) => opt.fast,
             LeafType::DateTime(opt) => opt.fast,
             LeafType::Json(_) => false,
+            // Geo points are stored in a multivalued fast field, so they are never required.
+            LeafType::GeoPoint(_) => false,
+            // Documents without embedding are accepted.
+            LeafType::Vector(..) => false,
         }
     }
 
@@ -98,19 +110,7 @@ impl LeafType {
                 }
             }
             LeafType::DateTime(date_time_options) => date_time_options.parse_json(json_val),
-            LeafType::Bytes(_) => {
-                let base64_str = if let JsonValue::String(base64_str) = json_val {
-                    base64_str
-                } else {
-                    return Err(format!("Expected base64 string, got `{json_val}`."));
-                };
-                let payload = BASE64_STANDARD
-                    .decode(&base64_str)
-                    .map_err(|base64_decode_err| {
-                        format!("Expected Base64 string, got `{base64_str}`: {base64_decode_err}")
-                    })?;
-                Ok(TantivyValue::Bytes(payload))
-            }
+            LeafType::Bytes(bytes_options) => bytes_options.parse_json(json_val),
             LeafType::Json(_) => {
                 if let JsonValue::Object(json_obj) = json_val {
                     Ok(TantivyValue::JsonObject(json_obj))
@@ -118,6 +118,14 @@ impl LeafType {
                     Err(format!("Expected JSON object  got `{json_val}`."))
                 }
             }
+            LeafType::GeoPoint(_) => {
+                let geo_point = GeoPoint::from_json(&json_val)?;
+                Ok(TantivyValue::U64(geo_point.to_u64()))
+            }
+            LeafType::Vector(_, dims) => {
+                let vector = vector_from_json(&json_val, *dims)?;
+                Ok(TantivyValue::Bytes(encode_vector(&vector)))
+            }
         }
     }
 }
@@ -140,22 +148,33 @@ impl MappingLeaf {
             // We just ignore `null`.
             return Ok(());
         }
-        if let JsonValue::Array(els) = json_val {
-            if self.cardinality == Cardinality::SingleValue {
-                return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
+        // A `[lon, lat]` array is a single geo point and an array of numbers is a single vector,
+        // not arrays of values.
+        let is_single_array_value = match &self.typ {
+            LeafType::GeoPoint(_) => {
+                matches!(&json_val, JsonValue::Array(els) if is_coordinates_pair(els))
             }
-            for el_json_val in els {
-                if el_json_val.is_null() {
-                    // We just ignore `null`.
-                    continue;
+            LeafType::Vector(..) => true,
+            _ => false,
+        };
+        if !is_single_array_value {
+            if let JsonValue::Array(els) = json_val {
+                if self.cardinality == Cardinality::SingleValue {
+                    return Err(DocParsingError::MultiValuesNotSupported(path.join(".")));
+                }
+                for el_json_val in els {
+                    if el_json_val.is_null() {
+                        // We just ignore `null`.
+                        continue;
+                    }
+                    let value = self
+                        .typ
+                        .value_from_json(el_json_val)
+                        .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
+                    document.add_field_value(self.field, value);
                 }
-                let value = self
-                    .typ
-                    .value_from_json(el_json_val)
-                    .map_err(|err_msg| DocParsingError::ValueError(path.join("."), err_msg))?;
-                document.add_field_value(self.field, value);
+                return Ok(());
             }
-            return Ok(());
         }
         let value = self
             .typ
@@ -216,12 +235,23 @@ fn value_to_json(value: TantivyValue, leaf_type: &LeafType) -> Option<JsonValue>
         | (TantivyValue::F64(_), LeafType::F64(_))
         | (TantivyValue::Bool(_), LeafType::Bool(_))
         | (TantivyValue::IpAddr(_), LeafType::IpAddr(_))
-        | (TantivyValue::Bytes(_), LeafType::Bytes(_))
         | (TantivyValue::JsonObject(_), LeafType::Json(_)) => {
             let json_value =
                 serde_json::to_value(&value).expect("Json serialization should never fail.");
             Some(json_value)
         }
+        (TantivyValue::Bytes(bytes), LeafType::Bytes(bytes_options)) => {
+            Some(bytes_options.format_to_json(bytes))
+        }
+        (TantivyValue::U64(encoded_geo_point), LeafType::GeoPoint(_)) => {
+            Some(GeoPoint::from_u64(*encoded_geo_point).to_json())
+        }
+        (TantivyValue::Bytes(encoded_vector), LeafType::Vector(..)) => {
+            let vector = decode_vector(encoded_vector)?;
+            let json_value =
+                serde_json::to_value(vector).expect("Json serialization should never fail.");
+            Some(json_value)
+        }
         (TantivyValue::Date(date_time), LeafType::DateTime(date_time_options)) => {
             let json_value = date_time_options
                 .format_to_json(*date_time)
@@ -298,6 +328,86 @@ impl NumVal for f64 {
 pub(crate) struct MappingNode {
     pub branches: fnv::FnvHashMap<String, MappingTree>,
     branches_order: Vec<String>,
+    /// Missing-field options of the leaves, keyed by field name.
+    missing_field_options: fnv::FnvHashMap<String, MissingFieldOptions>,
+}
+
+/// Sub-field of a JSON field copied into a dedicated fast field.
+#[derive(Clone, Debug)]
+pub(crate) struct JsonFastFieldMapping {
+    json_field: Field,
+    path: Vec<String>,
+    field: Field,
+    typ: JsonFastFieldType,
+}
+
+impl JsonFastFieldMapping {
+    /// Copies the values of the sub-field held by the JSON field of the document into the
+    /// dedicated fast field.
+    pub fn populate_doc(&self, document: &mut Document) -> Result<(), DocParsingError> {
+        let path: Vec<&str> = self.path.iter().map(String::as_str).collect();
+        let mut json_vals = Vec::new();
+        for value in document.get_all(self.json_field) {
+            if let TantivyValue::JsonObject(json_obj) = value {
+                collect_json_vals_at_path(json_obj, &path, &mut json_vals);
+            }
+        }
+        let mut fast_values = Vec::with_capacity(json_vals.len());
+        for json_val in json_vals {
+            let el_json_vals: Vec<&JsonValue> = if let JsonValue::Array(els) = json_val {
+                els.iter().collect()
+            } else {
+                vec![json_val]
+            };
+            for el_json_val in el_json_vals {
+                if el_json_val.is_null() {
+                    continue;
+                }
+                let fast_value = match self.typ {
+                    JsonFastFieldType::I64 => i64::from_json(el_json_val.clone()),
+                    JsonFastFieldType::U64 => u64::from_json(el_json_val.clone()),
+                    JsonFastFieldType::F64 => f64::from_json(el_json_val.clone()),
+                    JsonFastFieldType::Bool => el_json_val
+                        .as_bool()
+                        .map(TantivyValue::Bool)
+                        .ok_or_else(|| format!("Expected bool value, got `{el_json_val}`.")),
+                }
+                .map_err(|err_msg| DocParsingError::ValueError(self.path.join("."), err_msg))?;
+                fast_values.push(fast_value);
+            }
+        }
+        for fast_value in fast_values {
+            document.add_field_value(self.field, fast_value);
+        }
+        Ok(())
+    }
+}
+
+/// Collects the values located at `path` in a JSON object. Keys containing dots are matched as
+/// well, e.g. `{"http.status": 200}` holds a value at path `["http", "status"]`.
+fn collect_json_vals_at_path<'a>(
+    json_obj: &'a serde_json::Map<String, JsonValue>,
+    path: &[&str],
+    json_vals: &mut Vec<&'a JsonValue>,
+) {
+    for split_pos in 1..=path.len() {
+        let key = path[..split_pos].join(".");
+        match json_obj.get(&key) {
+            Some(json_val) if split_pos == path.len() => json_vals.push(json_val),
+            Some(JsonValue::Object(child_json_obj)) => {
+                collect_json_vals_at_path(child_json_obj, &path[split_pos..], json_vals)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Handling of the documents that do not hold a field.
+#[derive(Clone, Debug)]
+pub(crate) enum MissingFieldPolicy {
+    Fail,
+    Skip,
+    Default(TantivyValue),
 }
 
 fn get_or_insert_path<'a>(
@@ -343,12 +453,96 @@ impl MappingNode {
             let field_mapping_entry = FieldMappingEntry {
                 name: field_name.clone(),
                 mapping_type: child_tree.clone().into(),
+                missing_field_options: self
+                    .missing_field_options
+                    .get(field_name)
+                    .cloned()
+                    .unwrap_or_default(),
             };
             field_mapping_entries.push(field_mapping_entry);
         }
         field_mapping_entries
     }
 
+    /// Lists the JSON sub-fields copied into dedicated fast fields.
+    pub fn json_fast_field_mappings(&self, schema: &Schema) -> Vec<JsonFastFieldMapping> {
+        let mut json_fast_field_mappings = Vec::new();
+        for child_tree in self.children() {
+            match child_tree {
+                MappingTree::Leaf(leaf) => {
+                    let LeafType::Json(json_options) = &leaf.typ else {
+                        continue;
+                    };
+                    let json_field_name = schema.get_field_name(leaf.field);
+                    for json_fast_field in &json_options.fast_fields {
+                        let field_name = format!("{json_field_name}.{}", json_fast_field.name);
+                        let field = schema
+                            .get_field(&field_name)
+                            .expect("JSON fast field should be declared in the schema.");
+                        json_fast_field_mappings.push(JsonFastFieldMapping {
+                            json_field: leaf.field,
+                            path: json_fast_field
+                                .name
+                                .split('.')
+                                .map(str::to_string)
+                                .collect(),
+                            field,
+                            typ: json_fast_field.typ,
+                        });
+                    }
+                }
+                MappingTree::Node(child_node) => {
+                    json_fast_field_mappings.extend(child_node.json_fast_field_mappings(schema));
+                }
+                // JSON fast fields are not allowed within nested fields.
+                MappingTree::Nested(_) => {}
+            }
+        }
+        json_fast_field_mappings
+    }
+
+    /// Lists the fields with a missing-field policy, parsing their default values.
+    pub fn missing_field_policies(&self) -> anyhow::Result<Vec<(Field, MissingFieldPolicy)>> {
+        let mut missing_field_policies = Vec::new();
+        for field_name in &self.branches_order {
+            match self.branches.get(field_name).expect("Missing field") {
+                MappingTree::Leaf(leaf) => {
+                    let missing_field_options = self
+                        .missing_field_options
+                        .get(field_name)
+                        .cloned()
+                        .unwrap_or_default();
+                    let missing_field_policy = match missing_field_options.policy() {
+                        Some(OnMissing::Fail) => MissingFieldPolicy::Fail,
+                        Some(OnMissing::Skip) => MissingFieldPolicy::Skip,
+                        Some(OnMissing::Default) => {
+                            let default_json_val = missing_field_options
+                                .default_value
+                                .context("`on_missing: default` requires a `default_value`.")?;
+                            let default_value = leaf
+                                .typ
+                                .value_from_json(default_json_val)
+                                .map_err(|err_msg| {
+                                    anyhow::anyhow!(
+                                        "Invalid default value for field `{field_name}`: {err_msg}"
+                                    )
+                                })?;
+                            MissingFieldPolicy::Default(default_value)
+                        }
+                        None => continue,
+                    };
+                    missing_field_policies.push((leaf.field(), missing_field_policy));
+                }
+                MappingTree::Node(child_node) => {
+                    missing_field_policies.extend(child_node.missing_field_policies()?);
+                }
+                // Missing-field options are not allowed within nested fields.
+                MappingTree::Nested(_) => {}
+            }
+        }
+        Ok(missing_field_policies)
+    }
+
     pub fn doc_from_json(
         &self,
         json_obj: serde_json::Map<String, JsonValue>,
@@ -356,11 +550,19 @@ impl MappingNode {
         document: &mut Document,
         path: &mut Vec<String>,
         dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
+        nested_json_obj: &mut serde_json::Map<String, JsonValue>,
     ) -> Result<(), DocParsingError> {
         for (field_name, val) in json_obj {
             if let Some(child_tree) = self.branches.get(&field_name) {
                 path.push(field_name);
-                child_tree.doc_from_json(val, mode, document, path, dynamic_json_obj)?;
+                child_tree.doc_from_json(
+                    val,
+                    mode,
+                    document,
+                    path,
+                    dynamic_json_obj,
+                    nested_json_obj,
+                )?;
                 path.pop();
             } else {
                 match mode {
@@ -395,6 +597,70 @@ impl MappingNode {
             field_path.pop();
         }
     }
+
+    /// Returns the nested node reached by following `field_path` from this node, if any.
+    pub fn find_nested_node(&self, field_path: &[&str]) -> Option<&MappingNode> {
+        let (first_field_name, rest) = field_path.split_first()?;
+        match (self.branches.get(*first_field_name)?, rest.is_empty()) {
+            (MappingTree::Nested(nested_node), true) => Some(nested_node),
+            (MappingTree::Node(child_node), false) => child_node.find_nested_node(rest),
+            _ => None,
+        }
+    }
+
+    /// Builds one document per element of a nested array, each document holding only the
+    /// values of this nested node's fields for that element.
+    pub fn nested_docs_from_json(
+        &self,
+        json_value: JsonValue,
+        path: &mut Vec<String>,
+    ) -> Result<Vec<Document>, DocParsingError> {
+        let mut ignored_dynamic_json_obj = serde_json::Map::default();
+        let mut ignored_nested_json_obj = serde_json::Map::default();
+        let mut nested_docs = Vec::new();
+        for element_obj in nested_elements_from_json(json_value, path)? {
+            let mut nested_doc = Document::default();
+            self.doc_from_json(
+                element_obj,
+                ModeType::Lenient,
+                &mut nested_doc,
+                path,
+                &mut ignored_dynamic_json_obj,
+                &mut ignored_nested_json_obj,
+            )?;
+            nested_docs.push(nested_doc);
+        }
+        Ok(nested_docs)
+    }
+}
+
+/// Lists the JSON objects held by a nested field. A single object is accepted as an array of
+/// one element.
+fn nested_elements_from_json(
+    json_value: JsonValue,
+    path: &[String],
+) -> Result<Vec<serde_json::Map<String, JsonValue>>, DocParsingError> {
+    let json_values = match json_value {
+        JsonValue::Array(json_values) => json_values,
+        JsonValue::Null => Vec::new(),
+        json_value => vec![json_value],
+    };
+    let mut elements = Vec::with_capacity(json_values.len());
+    for json_value in json_values {
+        match json_value {
+            JsonValue::Object(element_obj) => elements.push(element_obj),
+            JsonValue::Null => {
+                // We just ignore `null`.
+            }
+            json_value => {
+                return Err(DocParsingError::ValueError(
+                    path.join("."),
+                    format!("Expected a JSON object or an array of JSON objects, got {json_value}"),
+                ));
+            }
+        }
+    }
+    Ok(elements)
 }
 
 impl From<MappingTree> for FieldMappingType {
@@ -404,6 +670,9 @@ impl From<MappingTree> for FieldMappingType {
             MappingTree::Node(node) => FieldMappingType::Object(QuickwitObjectOptions {
                 field_mappings: node.into(),
             }),
+            MappingTree::Nested(node) => FieldMappingType::Nested(QuickwitObjectOptions {
+                field_mappings: node.into(),
+            }),
         }
     }
 }
@@ -420,6 +689,8 @@ impl From<MappingLeaf> for FieldMappingType {
             LeafType::DateTime(opt) => FieldMappingType::DateTime(opt, leaf.cardinality),
             LeafType::Bytes(opt) => FieldMappingType::Bytes(opt, leaf.cardinality),
             LeafType::Json(opt) => FieldMappingType::Json(opt, leaf.cardinality),
+            LeafType::GeoPoint(opt) => FieldMappingType::GeoPoint(opt, leaf.cardinality),
+            LeafType::Vector(opt, dims) => FieldMappingType::Vector(opt, dims),
         }
     }
 }
@@ -434,6 +705,9 @@ impl From<MappingNode> for Vec<FieldMappingEntry> {
 pub(crate) enum MappingTree {
     Leaf(MappingLeaf),
     Node(MappingNode),
+    /// A nested node: its leaves hold the values of all the elements of the nested array, and
+    /// the elements themselves are kept as is in the nested JSON object.
+    Nested(MappingNode),
 }
 
 impl MappingTree {
@@ -444,14 +718,50 @@ impl MappingTree {
         document: &mut Document,
         path: &mut Vec<String>,
         dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
+        nested_json_obj: &mut serde_json::Map<String, JsonValue>,
     ) -> Result<(), DocParsingError> {
         match self {
             MappingTree::Leaf(mapping_leaf) => {
                 mapping_leaf.doc_from_json(json_value, document, path)
             }
+            MappingTree::Nested(mapping_node) => {
+                let elements = nested_elements_from_json(json_value, path)?;
+                // Unmapped fields of the elements are not sent to the dynamic field: the elements
+                // are kept whole in the nested JSON object anyway.
+                let element_mode = if mode == ModeType::Dynamic {
+                    ModeType::Lenient
+                } else {
+                    mode
+                };
+                let mut ignored_dynamic_json_obj = serde_json::Map::default();
+                let mut ignored_nested_json_obj = serde_json::Map::default();
+                for element_obj in &elements {
+                    mapping_node.doc_from_json(
+                        element_obj.clone(),
+                        element_mode,
+                        document,
+                        path,
+                        &mut ignored_dynamic_json_obj,
+                        &mut ignored_nested_json_obj,
+                    )?;
+                }
+                if let Some((field_name, parent_path)) = path.split_last() {
+                    let elements_json = elements.into_iter().map(JsonValue::Object).collect();
+                    get_or_insert_path(parent_path, nested_json_obj)
+                        .insert(field_name.clone(), JsonValue::Array(elements_json));
+                }
+                Ok(())
+            }
             MappingTree::Node(mapping_node) => {
                 if let JsonValue::Object(json_obj) = json_value {
-                    mapping_node.doc_from_json(json_obj, mode, document, path, dynamic_json_obj)
+                    mapping_node.doc_from_json(
+                        json_obj,
+                        mode,
+                        document,
+                        path,
+                        dynamic_json_obj,
+                        nested_json_obj,
+                    )
                 } else {
                     Err(DocParsingError::ValueError(
                         path.join("."),
@@ -475,6 +785,10 @@ impl MappingTree {
             MappingTree::Node(mapping_node) => {
                 mapping_node.populate_json(named_doc, field_path, doc_json);
             }
+            MappingTree::Nested(_) => {
+                // Nested elements are restored from the nested JSON object, as the values of
+                // their fields can't be regrouped by element.
+            }
         }
     }
 }
@@ -484,13 +798,14 @@ pub(crate) fn build_mapping_tree(
     schema: &mut SchemaBuilder,
 ) -> anyhow::Result<MappingNode> {
     let mut field_path = Vec::new();
-    build_mapping_tree_from_entries(entries, &mut field_path, schema)
+    build_mapping_tree_from_entries(entries, &mut field_path, schema, false)
 }
 
 fn build_mapping_tree_from_entries<'a>(
     entries: &'a [FieldMappingEntry],
     field_path: &mut Vec<&'a str>,
     schema: &mut SchemaBuilder,
+    in_nested: bool,
 ) -> anyhow::Result<MappingNode> {
     let mut mapping_node = MappingNode::default();
     for entry in entries {
@@ -498,7 +813,19 @@ fn build_mapping_tree_from_entries<'a>(
         if mapping_node.branches.contains_key(&entry.name) {
             bail!("Duplicated field definition `{}`.", entry.name);
         }
-        let child_tree = build_mapping_from_field_type(&entry.mapping_type, field_path, schema)?;
+        let child_tree =
+            build_mapping_from_field_type(&entry.mapping_type, field_path, schema, in_nested)?;
+        if !entry.missing_field_options.is_empty() {
+            if in_nested {
+                bail!(
+                    "`default_value` and `on_missing` are not allowed within nested field `{}`.",
+                    field_name_for_field_path(field_path)
+                );
+            }
+            mapping_node
+                .missing_field_options
+                .insert(entry.name.clone(), entry.missing_field_options.clone());
+        }
         field_path.pop();
         mapping_node.insert(&entry.name, child_tree);
     }
@@ -539,15 +866,15 @@ fn get_date_time_options(
     date_time_options.set_precision(quickwit_date_time_options.precision)
 }
 
-fn get_bytes_options(quickwit_numeric_options: &QuickwitNumericOptions) -> BytesOptions {
+fn get_bytes_options(quickwit_bytes_options: &QuickwitBytesOptions) -> BytesOptions {
     let mut bytes_options = BytesOptions::default();
-    if quickwit_numeric_options.indexed {
+    if quickwit_bytes_options.indexed {
         bytes_options = bytes_options.set_indexed();
     }
-    if quickwit_numeric_options.fast {
+    if quickwit_bytes_options.fast {
         bytes_options = bytes_options.set_fast();
     }
-    if quickwit_numeric_options.stored {
+    if quickwit_bytes_options.stored {
         bytes_options = bytes_options.set_stored();
     }
     bytes_options
@@ -570,6 +897,25 @@ fn get_ip_address_options(
     ip_address_options
 }
 
+/// Geo points are encoded into a `u64` (see [`GeoPoint::to_u64`]) and always stored in a
+/// multivalued fast field, whatever the cardinality of the mapping.
+fn get_geo_point_options(quickwit_geo_point_options: &QuickwitGeoPointOptions) -> NumericOptions {
+    let mut numeric_options = NumericOptions::default().set_fast(Cardinality::MultiValues);
+    if quickwit_geo_point_options.stored {
+        numeric_options = numeric_options.set_stored();
+    }
+    numeric_options
+}
+
+/// Vectors are encoded into bytes (see [`encode_vector`]) and always stored in a fast field.
+fn get_vector_options(quickwit_vector_options: &QuickwitVectorOptions) -> BytesOptions {
+    let mut bytes_options = BytesOptions::default().set_fast();
+    if quickwit_vector_options.stored {
+        bytes_options = bytes_options.set_stored();
+    }
+    bytes_options
+}
+
 /// Creates a tantivy field name for a given field path.
 ///
 /// By field path, we mean the list of `field_name` that are crossed
@@ -594,10 +940,21 @@ fn escape_dots(field_name: &str) -> String {
     escaped_field_name
 }
 
+/// Within a nested node, a document holds the values of all the elements of the nested array,
+/// so fast fields are always multivalued, whatever the cardinality of the mapping.
+fn fast_field_cardinality(cardinality: Cardinality, in_nested: bool) -> Cardinality {
+    if in_nested {
+        Cardinality::MultiValues
+    } else {
+        cardinality
+    }
+}
+
 fn build_mapping_from_field_type<'a>(
     field_mapping_type: &'a FieldMappingType,
     field_path: &mut Vec<&'a str>,
     schema_builder: &mut SchemaBuilder,
+    in_nested: bool,
 ) -> anyhow::Result<MappingTree> {
     let field_name = field_name_for_field_path(field_path);
     match field_mapping_type {
@@ -612,7 +969,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::I64(options, cardinality) => {
-            let numeric_options = get_numeric_options(options, *cardinality);
+            let numeric_options =
+                get_numeric_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_i64_field(&field_name, numeric_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -622,7 +980,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::U64(options, cardinality) => {
-            let numeric_options = get_numeric_options(options, *cardinality);
+            let numeric_options =
+                get_numeric_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_u64_field(&field_name, numeric_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -632,7 +991,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::F64(options, cardinality) => {
-            let numeric_options = get_numeric_options(options, *cardinality);
+            let numeric_options =
+                get_numeric_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_f64_field(&field_name, numeric_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -642,7 +1002,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::Bool(options, cardinality) => {
-            let numeric_options = get_numeric_options(options, *cardinality);
+            let numeric_options =
+                get_numeric_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_bool_field(&field_name, numeric_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -652,7 +1013,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::IpAddr(options, cardinality) => {
-            let ip_addr_options = get_ip_address_options(options, *cardinality);
+            let ip_addr_options =
+                get_ip_address_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_ip_addr_field(&field_name, ip_addr_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -662,7 +1024,8 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::DateTime(options, cardinality) => {
-            let date_time_options = get_date_time_options(options, *cardinality);
+            let date_time_options =
+                get_date_time_options(options, fast_field_cardinality(*cardinality, in_nested));
             let field = schema_builder.add_date_field(&field_name, date_time_options);
             let mapping_leaf = MappingLeaf {
                 field,
@@ -672,6 +1035,9 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::Bytes(options, cardinality) => {
+            if in_nested && options.fast {
+                bail!("Fast field is not allowed for bytes in nested field `{field_name}`.");
+            }
             let bytes_options = get_bytes_options(options);
             let field = schema_builder.add_bytes_field(&field_name, bytes_options);
             let mapping_leaf = MappingLeaf {
@@ -682,22 +1048,85 @@ fn build_mapping_from_field_type<'a>(
             Ok(MappingTree::Leaf(mapping_leaf))
         }
         FieldMappingType::Json(options, cardinality) => {
+            if in_nested && !options.fast_fields.is_empty() {
+                bail!("JSON fast fields are not allowed within nested field `{field_name}`.");
+            }
             let json_options = JsonObjectOptions::from(options.clone());
             let field = schema_builder.add_json_field(&field_name, json_options);
+            for json_fast_field in &options.fast_fields {
+                // The dedicated fast field is indexed as well, so that the queries targeting the
+                // sub-field, which resolve to it, keep matching.
+                let numeric_options = NumericOptions::default()
+                    .set_indexed()
+                    .set_fast(Cardinality::MultiValues);
+                let json_fast_field_name = format!("{field_name}.{}", json_fast_field.name);
+                match json_fast_field.typ {
+                    JsonFastFieldType::I64 => {
+                        schema_builder.add_i64_field(&json_fast_field_name, numeric_options)
+                    }
+                    JsonFastFieldType::U64 => {
+                        schema_builder.add_u64_field(&json_fast_field_name, numeric_options)
+                    }
+                    JsonFastFieldType::F64 => {
+                        schema_builder.add_f64_field(&json_fast_field_name, numeric_options)
+                    }
+                    JsonFastFieldType::Bool => {
+                        schema_builder.add_bool_field(&json_fast_field_name, numeric_options)
+                    }
+                };
+            }
             Ok(MappingTree::Leaf(MappingLeaf {
                 field,
                 typ: LeafType::Json(options.clone()),
                 cardinality: *cardinality,
             }))
         }
+        FieldMappingType::GeoPoint(options, cardinality) => {
+            let geo_point_options = get_geo_point_options(options);
+            let field = schema_builder.add_u64_field(&field_name, geo_point_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::GeoPoint(options.clone()),
+                cardinality: *cardinality,
+            };
+            Ok(MappingTree::Leaf(mapping_leaf))
+        }
+        FieldMappingType::Vector(options, dims) => {
+            if in_nested {
+                bail!("Vector field `{field_name}` cannot be declared within a nested field.");
+            }
+            let vector_options = get_vector_options(options);
+            let field = schema_builder.add_bytes_field(&field_name, vector_options);
+            let mapping_leaf = MappingLeaf {
+                field,
+                typ: LeafType::Vector(options.clone(), *dims),
+                cardinality: Cardinality::SingleValue,
+            };
+            Ok(MappingTree::Leaf(mapping_leaf))
+        }
         FieldMappingType::Object(entries) => {
             let mapping_node = build_mapping_tree_from_entries(
                 &entries.field_mappings,
                 field_path,
                 schema_builder,
+                in_nested,
             )?;
             Ok(MappingTree::Node(mapping_node))
         }
+        FieldMappingType::Nested(entries) => {
+            if in_nested {
+                bail!(
+                    "Nested field `{field_name}` cannot be declared within another nested field."
+                );
+            }
+            let mapping_node = build_mapping_tree_from_entries(
+                &entries.field_mappings,
+                field_path,
+                schema_builder,
+                true,
+            )?;
+            Ok(MappingTree::Nested(mapping_node))
+        }
     }
 }
 
@@ -710,11 +1139,14 @@ mod tests {
     use tantivy::{DateTime, Document};
     use time::macros::datetime;
 
-    use super::{LeafType, MappingLeaf};
+    use super::{value_to_json, LeafType, MappingLeaf};
+    use crate::default_doc_mapper::bytes_type::{BinaryFormat, QuickwitBytesOptions};
     use crate::default_doc_mapper::date_time_type::QuickwitDateTimeOptions;
     use crate::default_doc_mapper::field_mapping_entry::{
-        QuickwitIpAddrOptions, QuickwitNumericOptions, QuickwitTextOptions,
+        QuickwitGeoPointOptions, QuickwitIpAddrOptions, QuickwitNumericOptions,
+        QuickwitTextOptions, QuickwitVectorOptions,
     };
+    use crate::{encode_vector, GeoPoint};
 
     #[test]
     fn test_field_name_from_field_path() {
@@ -912,6 +1344,94 @@ mod tests {
         assert!(err.contains("Expected string value, got `1200`"));
     }
 
+    #[test]
+    fn test_parse_geo_point() {
+        let typ = LeafType::GeoPoint(QuickwitGeoPointOptions::default());
+        let field = Field::from_field_id(10);
+        let single_leaf_entry = MappingLeaf {
+            field,
+            typ: typ.clone(),
+            cardinality: Cardinality::SingleValue,
+        };
+        let expected_geo_point = GeoPoint::new(48.85, 2.35).unwrap();
+        let mut document = Document::default();
+        let mut path = Vec::new();
+        single_leaf_entry
+            .doc_from_json(json!([2.35, 48.85]), &mut document, &mut path)
+            .unwrap();
+        single_leaf_entry
+            .doc_from_json(json!({"lat": 48.85, "lon": 2.35}), &mut document, &mut path)
+            .unwrap();
+        assert_eq!(document.len(), 2);
+        for value in document.get_all(field) {
+            assert_eq!(value, &TantivyValue::U64(expected_geo_point.to_u64()));
+        }
+        let error = single_leaf_entry
+            .doc_from_json(
+                json!([[2.35, 48.85], [2.36, 48.86]]),
+                &mut document,
+                &mut path,
+            )
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            crate::DocParsingError::MultiValuesNotSupported(_)
+        ));
+
+        let multi_leaf_entry = MappingLeaf {
+            field,
+            typ: typ.clone(),
+            cardinality: Cardinality::MultiValues,
+        };
+        let mut document = Document::default();
+        multi_leaf_entry
+            .doc_from_json(
+                json!(["48.85,2.35", [2.35, 48.85]]),
+                &mut document,
+                &mut path,
+            )
+            .unwrap();
+        assert_eq!(document.len(), 2);
+
+        let error = typ
+            .value_from_json(json!({"lat": 100, "lon": 2.35}))
+            .unwrap_err();
+        assert!(error.contains("Latitude must be within [-90, 90]"));
+
+        let geo_point_json =
+            value_to_json(TantivyValue::U64(expected_geo_point.to_u64()), &typ).unwrap();
+        let decoded_geo_point = GeoPoint::from_json(&geo_point_json).unwrap();
+        assert!((decoded_geo_point.lat - 48.85).abs() < 1e-6);
+        assert!((decoded_geo_point.lon - 2.35).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_parse_vector() {
+        let typ = LeafType::Vector(QuickwitVectorOptions::default(), 3);
+        let field = Field::from_field_id(10);
+        let leaf_entry = MappingLeaf {
+            field,
+            typ: typ.clone(),
+            cardinality: Cardinality::SingleValue,
+        };
+        let mut document = Document::default();
+        let mut path = Vec::new();
+        leaf_entry
+            .doc_from_json(json!([1.0, 2.0, -3.5]), &mut document, &mut path)
+            .unwrap();
+        assert_eq!(document.len(), 1);
+        let expected_value = TantivyValue::Bytes(encode_vector(&[1.0, 2.0, -3.5]));
+        assert_eq!(document.field_values()[0].value(), &expected_value);
+
+        let error = leaf_entry
+            .doc_from_json(json!([1.0, 2.0]), &mut document, &mut path)
+            .unwrap_err();
+        assert!(matches!(error, crate::DocParsingError::ValueError(_, _)));
+
+        let vector_json = value_to_json(expected_value, &typ).unwrap();
+        assert_eq!(vector_json, json!([1.0, 2.0, -3.5]));
+    }
+
     #[test]
     fn test_parse_i64_mutivalued() {
         let typ = LeafType::I64(QuickwitNumericOptions::default());
@@ -1043,7 +1563,7 @@ mod tests {
 
     #[test]
     fn test_parse_bytes() {
-        let typ = LeafType::Bytes(QuickwitNumericOptions::default());
+        let typ = LeafType::Bytes(QuickwitBytesOptions::default());
         let value = typ
             .value_from_json(json!("dGhpcyBpcyBhIGJhc2U2NCBlbmNvZGVkIHN0cmluZw=="))
             .unwrap();
@@ -1055,14 +1575,14 @@ mod tests {
 
     #[test]
     fn test_parse_bytes_number_should_err() {
-        let typ = LeafType::Bytes(QuickwitNumericOptions::default());
+        let typ = LeafType::Bytes(QuickwitBytesOptions::default());
         let error = typ.value_from_json(json!(2u64)).err().unwrap();
         assert_eq!(error, "Expected base64 string, got `2`.");
     }
 
     #[test]
     fn test_parse_bytes_invalid_base64() {
-        let typ = LeafType::Bytes(QuickwitNumericOptions::default());
+        let typ = LeafType::Bytes(QuickwitBytesOptions::default());
         let error = typ.value_from_json(json!("dEwerwer#!%")).err().unwrap();
         assert_eq!(
             error,
@@ -1072,7 +1592,7 @@ mod tests {
 
     #[test]
     fn test_parse_array_of_bytes() {
-        let typ = LeafType::Bytes(QuickwitNumericOptions::default());
+        let typ = LeafType::Bytes(QuickwitBytesOptions::default());
         let field = Field::from_field_id(10);
         let leaf_entry = MappingLeaf {
             field,
@@ -1104,4 +1624,22 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_parse_and_format_hex_bytes() {
+        let typ = LeafType::Bytes(QuickwitBytesOptions {
+            input_format: BinaryFormat::Hex,
+            output_format: BinaryFormat::Hex,
+            ..Default::default()
+        });
+        let value = typ.value_from_json(json!("DEADbeef")).unwrap();
+        assert_eq!(value.as_bytes().unwrap(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(value_to_json(value, &typ), Some(json!("deadbeef")));
+
+        let error = typ.value_from_json(json!("dGhpcw==")).unwrap_err();
+        assert_eq!(
+            error,
+            "Expected hex string, got `dGhpcw==`: invalid character `G`."
+        );
+    }
 }
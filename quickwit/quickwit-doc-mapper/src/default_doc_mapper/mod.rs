@@ -17,11 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod bytes_type;
 mod date_time_format;
 mod date_time_parsing;
 mod date_time_type;
 mod default_mapper;
 mod default_mapper_builder;
+mod dynamic_type_hints;
 mod field_mapping_entry;
 mod field_mapping_type;
 mod mapping_tree;
@@ -30,10 +32,13 @@ use anyhow::bail;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+pub use self::bytes_type::{BinaryFormat, QuickwitBytesOptions};
 pub use self::default_mapper::DefaultDocMapper;
 pub use self::default_mapper_builder::{DefaultDocMapperBuilder, ModeType};
+pub use self::dynamic_type_hints::{DynamicFieldType, DynamicTypeHint};
 pub use self::field_mapping_entry::{
-    FieldMappingEntry, QuickwitJsonOptions, QuickwitNumericOptions, QuickwitTextOptions,
+    FieldMappingEntry, MissingFieldOptions, OnMissing, QuickwitJsonOptions, QuickwitNumericOptions,
+    QuickwitTextOptions,
 };
 pub(crate) use self::field_mapping_entry::{
     FieldMappingEntryForSerialization, IndexRecordOptionSchema, QuickwitTextTokenizer,
@@ -23,17 +23,20 @@ use std::num::NonZeroU32;
 
 use anyhow::Context;
 use dyn_clone::{clone_trait_object, DynClone};
-use quickwit_proto::SearchRequest;
+use quickwit_proto::{SearchRequest, SortOrder};
 use serde_json::Value as JsonValue;
 use tantivy::query::Query;
 use tantivy::schema::{Field, FieldType, Schema, Value};
+use tantivy::tokenizer::TokenizerManager;
 use tantivy::{Document, Term};
 
 pub type Partition = u64;
 
 pub type JsonObject = serde_json::Map<String, JsonValue>;
 
-use crate::{DocParsingError, QueryParserError};
+use crate::{
+    DocParsingError, QueryParserError, TermAutomaton, VectorField, QUICKWIT_TOKENIZER_MANAGER,
+};
 
 /// The `DocMapper` trait defines the way of defining how a (json) document,
 /// and the fields it contains, are stored and indexed.
@@ -97,32 +100,109 @@ pub trait DocMapper: Send + Sync + Debug + DynClone + 'static {
         None
     }
 
+    /// Returns the name of the field uniquely identifying a document, if any.
+    fn doc_unique_id_field_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns whether documents can be upserted, in which case the searches only return the
+    /// latest version of a document among the hits of a page.
+    fn upserts_enabled(&self) -> bool {
+        false
+    }
+
     /// Returns the tag field names
     fn tag_field_names(&self) -> BTreeSet<String> {
         Default::default()
     }
 
+    /// Returns the names of the numeric fast fields whose range of values is recorded in the
+    /// split metadata.
+    fn range_pruning_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the names of the text fields whose terms are recorded in a bloom filter in the
+    /// split metadata.
+    fn bloom_filter_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the names of the `geo_point` fields.
+    fn geo_point_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Returns the `vector` fields, keyed by field name.
+    fn vector_fields(&self) -> BTreeMap<String, VectorField> {
+        Default::default()
+    }
+
+    /// Returns the tokenizer manager holding the tokenizers used by the text and JSON fields.
+    fn tokenizer_manager(&self) -> &TokenizerManager {
+        &QUICKWIT_TOKENIZER_MANAGER
+    }
+
+    /// Returns the field aliases, mapping each alias to the path of the field it refers to.
+    fn field_aliases(&self) -> BTreeMap<String, String> {
+        Default::default()
+    }
+
+    /// Returns the paths of the `nested` fields.
+    fn nested_field_names(&self) -> BTreeSet<String> {
+        Default::default()
+    }
+
+    /// Builds one document per element of the nested field `nested_path` of a JSON document.
+    ///
+    /// Each document only holds the values of the nested field's sub-fields for its element,
+    /// using the fields of [`DocMapper::schema`].
+    fn nested_docs_from_json(
+        &self,
+        nested_path: &str,
+        _doc_json: JsonObject,
+    ) -> anyhow::Result<Vec<Document>> {
+        anyhow::bail!("Unknown nested field `{nested_path}`.")
+    }
+
     /// Returns the tag `NameField`s on the current schema.
     /// Returns an error if a tag field is not found in this schema.
     fn tag_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
-        let index_schema = self.schema();
-        self.tag_field_names()
-            .iter()
-            .map(|field_name| {
-                index_schema
-                    .get_field(field_name)
-                    .context(format!("Field `{field_name}` must exist in the schema."))
-                    .map(|field| NamedField {
-                        name: field_name.clone(),
-                        field,
-                        field_type: index_schema.get_field_entry(field).field_type().clone(),
-                    })
-            })
-            .collect::<Result<Vec<_>, _>>()
+        named_fields(&self.schema(), &self.tag_field_names())
+    }
+
+    /// Returns the range pruning `NamedField`s on the current schema.
+    /// Returns an error if a range pruning field is not found in this schema.
+    fn range_pruning_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        named_fields(&self.schema(), &self.range_pruning_field_names())
+    }
+
+    /// Returns the bloom filter `NamedField`s on the current schema.
+    /// Returns an error if a bloom filter field is not found in this schema.
+    fn bloom_filter_named_fields(&self) -> anyhow::Result<Vec<NamedField>> {
+        named_fields(&self.schema(), &self.bloom_filter_field_names())
+    }
+
+    /// Returns the maximum number of terms a regex or fuzzy clause may match in a split segment,
+    /// if the index restricts it.
+    fn max_term_expansions(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the field and order the hits are sorted by when the request does not specify any
+    /// sort.
+    fn default_sort_by(&self) -> Option<(String, SortOrder)> {
+        None
     }
 
     /// Returns the maximum number of partitions.
     fn max_num_partitions(&self) -> NonZeroU32;
+
+    /// Returns the version of the doc mapping. Splits record the version of the doc mapping they
+    /// were indexed with.
+    fn doc_mapping_version(&self) -> u64 {
+        0
+    }
 }
 
 /// A struct to wrap a tantivy field with its name.
@@ -138,6 +218,25 @@ pub struct NamedField {
 
 clone_trait_object!(DocMapper);
 
+fn named_fields(
+    schema: &Schema,
+    field_names: &BTreeSet<String>,
+) -> anyhow::Result<Vec<NamedField>> {
+    field_names
+        .iter()
+        .map(|field_name| {
+            schema
+                .get_field(field_name)
+                .context(format!("Field `{field_name}` must exist in the schema."))
+                .map(|field| NamedField {
+                    name: field_name.clone(),
+                    field,
+                    field_type: schema.get_field_entry(field).field_type().clone(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()
+}
+
 /// Information about what a DocMapper think should be warmed up before
 /// running the query.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -154,6 +253,8 @@ pub struct WarmupInfo {
     pub field_norms: bool,
     /// Terms to warmup, and, whether their position is needed too.
     pub terms_grouped_by_field: HashMap<Field, HashMap<Term, bool>>,
+    /// Automatons of the fuzzy clauses, matched against the term dictionary of their field.
+    pub term_automatons: Vec<(Field, TermAutomaton)>,
 }
 
 impl WarmupInfo {
@@ -174,6 +275,11 @@ impl WarmupInfo {
                 *sub_map.entry(term).or_default() |= include_position;
             }
         }
+        for term_automaton in other.term_automatons {
+            if !self.term_automatons.contains(&term_automaton) {
+                self.term_automatons.push(term_automaton);
+            }
+        }
     }
 }
 
@@ -181,12 +287,15 @@ impl WarmupInfo {
 mod tests {
     use std::collections::{HashMap, HashSet};
 
-    use quickwit_proto::SearchRequest;
+    use quickwit_proto::{SearchRequest, SortOrder};
     use tantivy::schema::{Cardinality, Field, FieldType, Term};
 
-    use crate::default_doc_mapper::{FieldMappingType, QuickwitJsonOptions, QuickwitTextOptions};
+    use crate::default_doc_mapper::{
+        FieldMappingType, MissingFieldOptions, QuickwitJsonOptions, QuickwitTextOptions,
+    };
     use crate::{
-        DefaultDocMapperBuilder, DocMapper, FieldMappingEntry, WarmupInfo, DYNAMIC_FIELD_NAME,
+        DefaultDocMapperBuilder, DocMapper, FieldMappingEntry, TermAutomaton, WarmupInfo,
+        DYNAMIC_FIELD_NAME,
     };
 
     const JSON_DEFAULT_DOC_MAPPER: &str = r#"
@@ -267,6 +376,7 @@ mod tests {
                 QuickwitJsonOptions::default(),
                 Cardinality::SingleValue,
             ),
+            missing_field_options: MissingFieldOptions::default(),
         });
         let doc_mapper = doc_mapper_builder.try_build().unwrap();
         let schema = doc_mapper.schema();
@@ -282,6 +392,25 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let (query, _) = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -301,6 +430,7 @@ mod tests {
         doc_mapper_builder.field_mappings.push(FieldMappingEntry {
             name: "text_field".to_string(),
             mapping_type: FieldMappingType::Text(text_opt, Cardinality::SingleValue),
+            missing_field_options: MissingFieldOptions::default(),
         });
         doc_mapper_builder
             .default_search_fields
@@ -319,6 +449,25 @@ mod tests {
             sort_order: None,
             sort_by_field: Some("text_field".to_string()),
             aggregation_request: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let query = doc_mapper.query(schema, &search_request).unwrap_err();
         assert_eq!(
@@ -336,6 +485,7 @@ mod tests {
                 QuickwitJsonOptions::default(),
                 Cardinality::SingleValue,
             ),
+            missing_field_options: MissingFieldOptions::default(),
         });
         doc_mapper_builder
             .default_search_fields
@@ -354,6 +504,25 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let (query, _) = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -371,6 +540,7 @@ mod tests {
                     QuickwitJsonOptions::default(),
                     Cardinality::SingleValue,
                 ),
+                missing_field_options: MissingFieldOptions::default(),
             }],
             default_search_fields: vec!["json_field".to_string()],
             ..Default::default()
@@ -389,6 +559,25 @@ mod tests {
             sort_order: None,
             sort_by_field: None,
             aggregation_request: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let (query, _) = doc_mapper.query(schema, &search_request).unwrap();
         assert_eq!(
@@ -397,10 +586,112 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_doc_mapper_query_with_regex_clause() {
+        let mut doc_mapper_builder = DefaultDocMapperBuilder {
+            field_mappings: vec![FieldMappingEntry {
+                name: "body".to_string(),
+                mapping_type: FieldMappingType::Text(
+                    QuickwitTextOptions::default(),
+                    Cardinality::SingleValue,
+                ),
+                missing_field_options: MissingFieldOptions::default(),
+            }],
+            ..Default::default()
+        };
+        let search_request = SearchRequest {
+            query: "body:/.*base64_decode.*/".to_string(),
+            ..Default::default()
+        };
+        let doc_mapper = doc_mapper_builder.clone().try_build().unwrap();
+        let error = doc_mapper
+            .query(doc_mapper.schema(), &search_request)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("regex queries are disabled on this index"));
+
+        doc_mapper_builder.enable_regex_queries = true;
+        let doc_mapper = doc_mapper_builder.try_build().unwrap();
+        let (query, warmup_info) = doc_mapper
+            .query(doc_mapper.schema(), &search_request)
+            .unwrap();
+        assert!(format!("{query:?}").contains("RegexQuery"));
+        assert_eq!(warmup_info.term_automatons.len(), 1);
+    }
+
+    #[test]
+    fn test_doc_mapper_query_with_search_settings() {
+        let doc_mapper_builder: DefaultDocMapperBuilder = serde_json::from_str(
+            r#"{
+                "field_mappings": [
+                    {"name": "body", "type": "text"},
+                    {"name": "timestamp", "type": "datetime", "fast": true}
+                ],
+                "default_search_fields": ["body"],
+                "default_operator": "or",
+                "max_query_clauses": 3,
+                "max_term_expansions": 100,
+                "default_sort_by_field": "-timestamp"
+            }"#,
+        )
+        .unwrap();
+        let doc_mapper = doc_mapper_builder.try_build().unwrap();
+        assert_eq!(doc_mapper.max_term_expansions(), Some(100));
+        assert_eq!(
+            doc_mapper.default_sort_by(),
+            Some(("timestamp".to_string(), SortOrder::Desc))
+        );
+        let search_request = SearchRequest {
+            query: "hello world".to_string(),
+            ..Default::default()
+        };
+        let (query, _) = doc_mapper
+            .query(doc_mapper.schema(), &search_request)
+            .unwrap();
+        assert!(format!("{query:?}").contains("Should"));
+        assert!(!format!("{query:?}").contains("Must"));
+
+        let search_request = SearchRequest {
+            query: "hello world foo bar".to_string(),
+            ..Default::default()
+        };
+        let error = doc_mapper
+            .query(doc_mapper.schema(), &search_request)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("exceeds the `max_query_clauses` limit of 3"));
+
+        let doc_mapper_json = serde_json::to_value(&doc_mapper).unwrap();
+        assert_eq!(doc_mapper_json["default_operator"], "or");
+        assert_eq!(doc_mapper_json["default_sort_by_field"], "-timestamp");
+    }
+
+    #[test]
+    fn test_doc_mapper_with_invalid_default_sort_by_field() {
+        let doc_mapper_builder: DefaultDocMapperBuilder = serde_json::from_str(
+            r#"{
+                "field_mappings": [{"name": "body", "type": "text"}],
+                "default_sort_by_field": "-body"
+            }"#,
+        )
+        .unwrap();
+        let error = doc_mapper_builder.try_build().unwrap_err();
+        assert!(format!("{error:#}").contains("Invalid `default_sort_by_field` search setting"));
+    }
+
     fn hashset(elements: &[&str]) -> HashSet<String> {
         elements.iter().map(|elem| elem.to_string()).collect()
     }
 
+    fn fuzzy(text: &str) -> TermAutomaton {
+        TermAutomaton::Fuzzy {
+            text: text.to_string(),
+            distance: 1,
+        }
+    }
+
     fn hashmap(elements: &[(u32, &str, bool)]) -> HashMap<Field, HashMap<Term, bool>> {
         let mut result: HashMap<Field, HashMap<Term, bool>> = HashMap::new();
         for (field, term, pos) in elements {
@@ -423,6 +714,7 @@ mod tests {
             fast_field_names: hashset(&["fast1", "fast2"]),
             field_norms: false,
             terms_grouped_by_field: hashmap(&[(1, "term1", false), (1, "term2", false)]),
+            term_automatons: vec![(Field::from_field_id(1), fuzzy("term1"))],
         };
 
         // merging with default has no impact
@@ -437,6 +729,10 @@ mod tests {
             fast_field_names: hashset(&["fast2", "fast3"]),
             field_norms: true,
             terms_grouped_by_field: hashmap(&[(2, "term1", false), (1, "term2", true)]),
+            term_automatons: vec![
+                (Field::from_field_id(1), fuzzy("term1")),
+                (Field::from_field_id(2), fuzzy("term2")),
+            ],
         };
         wi_base.merge(wi_2.clone());
 
@@ -469,6 +765,13 @@ mod tests {
                 pos
             );
         }
+        assert_eq!(
+            wi_base.term_automatons,
+            [
+                (Field::from_field_id(1), fuzzy("term1")),
+                (Field::from_field_id(2), fuzzy("term2")),
+            ]
+        );
 
         // merge is idempotent
         let mut wi_cloned = wi_base.clone();
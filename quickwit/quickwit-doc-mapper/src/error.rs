@@ -50,6 +50,9 @@ pub enum DocParsingError {
     /// The document does not contains a field that is required.
     #[error("The document must contain field {0:?}. As a fast field, it is implicitly required.")]
     RequiredFastField(String),
+    /// The document does not contain a field with the `on_missing: fail` policy.
+    #[error("The document must contain field {0:?}.")]
+    MissingField(String),
 }
 
 impl From<TantivyDocParsingError> for DocParsingError {
@@ -0,0 +1,266 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use quickwit_proto::SearchRequest;
+use regex::{Captures, Regex};
+use serde_json::Value as JsonValue;
+
+/// Matches the `field:` prefix of the query clauses.
+static FIELD_CLAUSE_PTN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?P<prefix>^|[\s(+\-])(?P<field>(?:[^\s:()"\\+\-]|\\.)(?:[^\s:()"\\]|\\.)*):"#)
+        .unwrap()
+});
+
+/// Returns the path of the field targeted by `field_name`, which is `field_name` itself if it is
+/// not an alias.
+pub fn resolve_field_alias<'a>(
+    field_name: &'a str,
+    field_aliases: &'a BTreeMap<String, String>,
+) -> &'a str {
+    field_aliases
+        .get(field_name)
+        .map(String::as_str)
+        .unwrap_or(field_name)
+}
+
+/// Rewrites the field names of a query string, replacing the aliases with the path of the field
+/// they refer to. Phrases between double quotes are left untouched.
+pub fn resolve_query_field_aliases(
+    query: &str,
+    field_aliases: &BTreeMap<String, String>,
+) -> String {
+    if field_aliases.is_empty() || !query.contains(':') {
+        return query.to_string();
+    }
+    let mut resolved_query = String::with_capacity(query.len());
+    let mut segment_start = 0;
+    let mut in_phrase = false;
+    let mut escaped = false;
+
+    for (pos, ch) in query.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' => escaped = true,
+            '"' if in_phrase => {
+                resolved_query.push_str(&query[segment_start..=pos]);
+                segment_start = pos + 1;
+                in_phrase = false;
+            }
+            '"' => {
+                resolve_segment(
+                    &query[segment_start..pos],
+                    field_aliases,
+                    &mut resolved_query,
+                );
+                segment_start = pos;
+                in_phrase = true;
+            }
+            _ => {}
+        }
+    }
+    if in_phrase {
+        resolved_query.push_str(&query[segment_start..]);
+    } else {
+        resolve_segment(&query[segment_start..], field_aliases, &mut resolved_query);
+    }
+    resolved_query
+}
+
+fn resolve_segment(
+    segment: &str,
+    field_aliases: &BTreeMap<String, String>,
+    resolved_query: &mut String,
+) {
+    let resolved_segment = FIELD_CLAUSE_PTN.replace_all(segment, |captures: &Captures| {
+        let field_name = &captures["field"];
+        format!(
+            "{}{}:",
+            &captures["prefix"],
+            resolve_field_alias(field_name, field_aliases)
+        )
+    });
+    resolved_query.push_str(&resolved_segment);
+}
+
+/// Replaces the `field` values of an aggregation request targeting an alias.
+fn resolve_aggregation_field_aliases(
+    json_value: &mut JsonValue,
+    field_aliases: &BTreeMap<String, String>,
+) {
+    match json_value {
+        JsonValue::Object(json_obj) => {
+            for (key, child_json_value) in json_obj.iter_mut() {
+                if key == "field" {
+                    if let JsonValue::String(field_name) = child_json_value {
+                        if let Some(field_path) = field_aliases.get(field_name) {
+                            *field_name = field_path.clone();
+                        }
+                        continue;
+                    }
+                }
+                resolve_aggregation_field_aliases(child_json_value, field_aliases);
+            }
+        }
+        JsonValue::Array(json_values) => {
+            for child_json_value in json_values {
+                resolve_aggregation_field_aliases(child_json_value, field_aliases);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rewrites the query, the search, snippet, and sort fields, and the aggregations of a search
+/// request, replacing the aliases with the path of the field they refer to.
+pub fn resolve_field_aliases(
+    search_request: &mut SearchRequest,
+    field_aliases: &BTreeMap<String, String>,
+) {
+    if field_aliases.is_empty() {
+        return;
+    }
+    search_request.query = resolve_query_field_aliases(&search_request.query, field_aliases);
+
+    for field_name in search_request
+        .search_fields
+        .iter_mut()
+        .chain(search_request.snippet_fields.iter_mut())
+        .chain(search_request.sort_by_field.iter_mut())
+    {
+        if let Some(field_path) = field_aliases.get(field_name.as_str()) {
+            *field_name = field_path.clone();
+        }
+    }
+    if let Some(aggregation_request) = search_request.aggregation_request.as_mut() {
+        // Invalid aggregation requests are left untouched and rejected later on.
+        if let Ok(mut aggregation_json) = serde_json::from_str::<JsonValue>(aggregation_request) {
+            resolve_aggregation_field_aliases(&mut aggregation_json, field_aliases);
+            *aggregation_request = aggregation_json.to_string();
+        }
+    }
+    if let Some(sort_fields) = search_request.sort_fields.as_mut() {
+        // Invalid sort fields are left untouched and rejected later on.
+        if let Ok(mut sort_fields_json) = serde_json::from_str::<Vec<JsonValue>>(sort_fields) {
+            for sort_field_json in &mut sort_fields_json {
+                if let Some(JsonValue::String(field_name)) = sort_field_json.get_mut("field") {
+                    if let Some(field_path) = field_aliases.get(field_name.as_str()) {
+                        *field_name = field_path.clone();
+                    }
+                }
+            }
+            *sort_fields = JsonValue::Array(sort_fields_json).to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn field_aliases() -> BTreeMap<String, String> {
+        BTreeMap::from_iter([
+            ("message".to_string(), "body.text".to_string()),
+            ("service.name".to_string(), "resource.service".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_query_field_aliases() {
+        let field_aliases = field_aliases();
+        for (query, expected_query) in [
+            ("message:foo", "body.text:foo"),
+            ("body.text:foo", "body.text:foo"),
+            (
+                "message:foo AND -service.name:bar",
+                "body.text:foo AND -resource.service:bar",
+            ),
+            (
+                "(message:foo OR +message:\"bar\")",
+                "(body.text:foo OR +body.text:\"bar\")",
+            ),
+            (
+                "title:\"message:foo\" message:bar",
+                "title:\"message:foo\" body.text:bar",
+            ),
+            ("title:message", "title:message"),
+            ("message:[1 TO 2]", "body.text:[1 TO 2]"),
+            ("messages:foo", "messages:foo"),
+            ("foo", "foo"),
+        ] {
+            assert_eq!(
+                resolve_query_field_aliases(query, &field_aliases),
+                expected_query
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_field_aliases() {
+        let mut search_request = SearchRequest {
+            query: "message:foo".to_string(),
+            search_fields: vec!["message".to_string(), "title".to_string()],
+            snippet_fields: vec!["message".to_string()],
+            sort_by_field: Some("service.name".to_string()),
+            sort_fields: Some(json!([{"field": "message", "order": "asc"}]).to_string()),
+            aggregation_request: Some(
+                json!({
+                    "services": {
+                        "terms": {"field": "service.name"},
+                        "aggs": {"max_len": {"max": {"field": "len"}}}
+                    }
+                })
+                .to_string(),
+            ),
+            ..Default::default()
+        };
+        resolve_field_aliases(&mut search_request, &field_aliases());
+        assert_eq!(search_request.query, "body.text:foo");
+        assert_eq!(search_request.search_fields, ["body.text", "title"]);
+        assert_eq!(search_request.snippet_fields, ["body.text"]);
+        assert_eq!(
+            search_request.sort_by_field.as_deref(),
+            Some("resource.service")
+        );
+        let sort_fields_json: JsonValue =
+            serde_json::from_str(search_request.sort_fields.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            sort_fields_json,
+            json!([{"field": "body.text", "order": "asc"}])
+        );
+        let aggregation_json: JsonValue =
+            serde_json::from_str(search_request.aggregation_request.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            aggregation_json,
+            json!({
+                "services": {
+                    "terms": {"field": "resource.service"},
+                    "aggs": {"max_len": {"max": {"field": "len"}}}
+                }
+            })
+        );
+    }
+}
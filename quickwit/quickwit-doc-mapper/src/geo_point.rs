@@ -0,0 +1,437 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+use std::fmt;
+
+use anyhow::bail;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value as JsonValue};
+
+/// Mean radius of the Earth in meters, as used by the haversine formula.
+const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// A geographical point expressed in decimal degrees.
+///
+/// Geo points are accepted in the following JSON formats:
+/// - an object with `lat` and `lon` keys: `{"lat": 48.85, "lon": 2.35}`;
+/// - an array of coordinates in the GeoJSON order: `[2.35, 48.85]`;
+/// - a string: `"48.85,2.35"`;
+/// - a GeoJSON point: `{"type": "Point", "coordinates": [2.35, 48.85]}`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    /// Latitude in degrees, within `[-90, 90]`.
+    pub lat: f64,
+    /// Longitude in degrees, within `[-180, 180]`.
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// Creates a new geo point, validating that the latitude and longitude are within bounds.
+    pub fn new(lat: f64, lon: f64) -> Result<GeoPoint, String> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(format!("Latitude must be within [-90, 90], got `{lat}`."));
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            return Err(format!(
+                "Longitude must be within [-180, 180], got `{lon}`."
+            ));
+        }
+        Ok(GeoPoint { lat, lon })
+    }
+
+    /// Parses a geo point from any of the supported JSON formats.
+    pub fn from_json(json_val: &JsonValue) -> Result<GeoPoint, String> {
+        match json_val {
+            JsonValue::Object(json_obj) => {
+                if let Some(coordinates) = json_obj.get("coordinates") {
+                    if json_obj.get("type").and_then(JsonValue::as_str) != Some("Point") {
+                        return Err(format!(
+                            "Expected GeoJSON object of type `Point`, got `{json_val}`."
+                        ));
+                    }
+                    return GeoPoint::from_json(coordinates);
+                }
+                let lat_opt = json_obj.get("lat").and_then(JsonValue::as_f64);
+                let lon_opt = json_obj.get("lon").and_then(JsonValue::as_f64);
+                match (lat_opt, lon_opt) {
+                    (Some(lat), Some(lon)) if json_obj.len() == 2 => GeoPoint::new(lat, lon),
+                    _ => Err(format!(
+                        "Expected geo point object with numeric `lat` and `lon` keys, got \
+                         `{json_val}`."
+                    )),
+                }
+            }
+            JsonValue::Array(coordinates) if is_coordinates_pair(coordinates) => {
+                // GeoJSON order: longitude first.
+                GeoPoint::new(
+                    coordinates[1].as_f64().unwrap_or_default(),
+                    coordinates[0].as_f64().unwrap_or_default(),
+                )
+            }
+            JsonValue::String(lat_lon) => {
+                let parse_coordinate = |coordinate: &str| {
+                    coordinate.trim().parse::<f64>().map_err(|_| {
+                        format!(
+                            "Expected geo point string formatted as `lat,lon`, got `{lat_lon}`."
+                        )
+                    })
+                };
+                let Some((lat_str, lon_str)) = lat_lon.split_once(',') else {
+                    return Err(format!(
+                        "Expected geo point string formatted as `lat,lon`, got `{lat_lon}`."
+                    ));
+                };
+                GeoPoint::new(parse_coordinate(lat_str)?, parse_coordinate(lon_str)?)
+            }
+            _ => Err(format!("Expected geo point, got `{json_val}`.")),
+        }
+    }
+
+    /// Returns the JSON representation of the geo point, `{"lat": .., "lon": ..}`.
+    pub fn to_json(&self) -> JsonValue {
+        json!({"lat": self.lat, "lon": self.lon})
+    }
+
+    /// Encodes the geo point into a `u64`: the latitude is quantized into the 32 most
+    /// significant bits and the longitude into the 32 least significant bits. The quantization
+    /// error is below one centimeter.
+    pub fn to_u64(&self) -> u64 {
+        let lat_bits = quantize(self.lat, 90.0) as u64;
+        let lon_bits = quantize(self.lon, 180.0) as u64;
+        (lat_bits << 32) | lon_bits
+    }
+
+    /// Decodes a geo point previously encoded with [`GeoPoint::to_u64`].
+    pub fn from_u64(val: u64) -> GeoPoint {
+        GeoPoint {
+            lat: dequantize((val >> 32) as u32, 90.0),
+            lon: dequantize(val as u32, 180.0),
+        }
+    }
+
+    /// Returns the great-circle distance in meters between two points, using the haversine
+    /// formula.
+    pub fn distance_meters(&self, other: &GeoPoint) -> f64 {
+        let lat_1 = self.lat.to_radians();
+        let lat_2 = other.lat.to_radians();
+        let delta_lat = (other.lat - self.lat).to_radians();
+        let delta_lon = (other.lon - self.lon).to_radians();
+        let a = (delta_lat / 2.0).sin().powi(2)
+            + lat_1.cos() * lat_2.cos() * (delta_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_MEAN_RADIUS_METERS * a.sqrt().min(1.0).asin()
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{},{}", self.lat, self.lon)
+    }
+}
+
+impl Serialize for GeoPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for GeoPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json_val = JsonValue::deserialize(deserializer)?;
+        GeoPoint::from_json(&json_val).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns true if the JSON array is a `[lon, lat]` coordinates pair.
+pub(crate) fn is_coordinates_pair(json_vals: &[JsonValue]) -> bool {
+    json_vals.len() == 2 && json_vals.iter().all(JsonValue::is_number)
+}
+
+fn quantize(coordinate: f64, max: f64) -> u32 {
+    ((coordinate + max) / (2.0 * max) * u32::MAX as f64).round() as u32
+}
+
+fn dequantize(bits: u32, max: f64) -> f64 {
+    bits as f64 / u32::MAX as f64 * (2.0 * max) - max
+}
+
+/// A filter restricting search results to the documents with at least one geo point
+/// located within a shape.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoFilter {
+    /// Name of the `geo_point` field to filter on.
+    pub field: String,
+    /// Shape the geo points must be located in.
+    #[serde(flatten)]
+    pub shape: GeoShape,
+}
+
+impl GeoFilter {
+    /// Parses and validates a geo filter from its JSON representation.
+    pub fn parse(geo_filter_json: &str) -> anyhow::Result<GeoFilter> {
+        let geo_filter: GeoFilter = serde_json::from_str(geo_filter_json)?;
+        geo_filter.shape.validate()?;
+        Ok(geo_filter)
+    }
+}
+
+/// A shape used to filter geo points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GeoShape {
+    /// Rectangle defined by its top left and bottom right corners. The box may cross the
+    /// antimeridian, in which case the top left longitude is greater than the bottom right one.
+    BoundingBox {
+        /// Top left corner.
+        top_left: GeoPoint,
+        /// Bottom right corner.
+        bottom_right: GeoPoint,
+    },
+    /// Circle defined by its center and its radius in meters.
+    Distance {
+        /// Center of the circle.
+        center: GeoPoint,
+        /// Radius of the circle in meters.
+        radius_meters: f64,
+    },
+    /// Polygon defined by its vertices. The polygon is closed implicitly.
+    Polygon {
+        /// Vertices of the polygon.
+        points: Vec<GeoPoint>,
+    },
+}
+
+impl GeoShape {
+    /// Checks that the shape is well-formed.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            GeoShape::BoundingBox {
+                top_left,
+                bottom_right,
+            } => {
+                if top_left.lat < bottom_right.lat {
+                    bail!(
+                        "The top left corner latitude `{}` of a bounding box must be greater than \
+                         or equal to its bottom right corner latitude `{}`.",
+                        top_left.lat,
+                        bottom_right.lat
+                    );
+                }
+            }
+            GeoShape::Distance { radius_meters, .. } => {
+                if !radius_meters.is_finite() || *radius_meters < 0.0 {
+                    bail!("The radius must be a positive number of meters, got `{radius_meters}`.");
+                }
+            }
+            GeoShape::Polygon { points } => {
+                if points.len() < 3 {
+                    bail!(
+                        "A polygon must have at least 3 points, got {}.",
+                        points.len()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the point is located within the shape, boundary included.
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        match self {
+            GeoShape::BoundingBox {
+                top_left,
+                bottom_right,
+            } => {
+                if point.lat > top_left.lat || point.lat < bottom_right.lat {
+                    return false;
+                }
+                if top_left.lon <= bottom_right.lon {
+                    top_left.lon <= point.lon && point.lon <= bottom_right.lon
+                } else {
+                    // The bounding box crosses the antimeridian.
+                    point.lon >= top_left.lon || point.lon <= bottom_right.lon
+                }
+            }
+            GeoShape::Distance {
+                center,
+                radius_meters,
+            } => center.distance_meters(point) <= *radius_meters,
+            GeoShape::Polygon { points } => polygon_contains(points, point),
+        }
+    }
+}
+
+/// Ray casting point-in-polygon test, treating coordinates as planar.
+fn polygon_contains(vertices: &[GeoPoint], point: &GeoPoint) -> bool {
+    let mut inside = false;
+    let mut previous = vertices[vertices.len() - 1];
+    for &current in vertices {
+        if (current.lat > point.lat) != (previous.lat > point.lat) {
+            let lon_intersection = current.lon
+                + (point.lat - current.lat) * (previous.lon - current.lon)
+                    / (previous.lat - current.lat);
+            if point.lon < lon_intersection {
+                inside = !inside;
+            }
+        }
+        previous = current;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{GeoFilter, GeoPoint, GeoShape};
+
+    #[test]
+    fn test_geo_point_from_json() {
+        let expected = GeoPoint {
+            lat: 48.85,
+            lon: 2.35,
+        };
+        for json_val in [
+            json!({"lat": 48.85, "lon": 2.35}),
+            json!([2.35, 48.85]),
+            json!("48.85, 2.35"),
+            json!({"type": "Point", "coordinates": [2.35, 48.85]}),
+        ] {
+            assert_eq!(GeoPoint::from_json(&json_val).unwrap(), expected);
+        }
+        for invalid_json_val in [
+            json!({"lat": 91.0, "lon": 2.35}),
+            json!({"lat": 48.85, "lon": -180.5}),
+            json!({"lat": 48.85}),
+            json!({"lat": 48.85, "lon": 2.35, "alt": 10}),
+            json!({"type": "LineString", "coordinates": [2.35, 48.85]}),
+            json!([2.35]),
+            json!("48.85"),
+            json!("foo,bar"),
+            json!(48.85),
+        ] {
+            assert!(
+                GeoPoint::from_json(&invalid_json_val).is_err(),
+                "{invalid_json_val}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_geo_point_u64_encoding() {
+        for (lat, lon) in [
+            (0.0, 0.0),
+            (90.0, 180.0),
+            (-90.0, -180.0),
+            (48.8566, 2.3522),
+            (-33.8688, 151.2093),
+        ] {
+            let geo_point = GeoPoint::new(lat, lon).unwrap();
+            let decoded = GeoPoint::from_u64(geo_point.to_u64());
+            assert!((decoded.lat - lat).abs() < 1e-7);
+            assert!((decoded.lon - lon).abs() < 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_geo_point_distance() {
+        let paris = GeoPoint::new(48.8566, 2.3522).unwrap();
+        let london = GeoPoint::new(51.5072, -0.1276).unwrap();
+        let distance = paris.distance_meters(&london);
+        assert!((343_000.0..345_000.0).contains(&distance), "{distance}");
+        assert_eq!(paris.distance_meters(&paris), 0.0);
+    }
+
+    #[test]
+    fn test_geo_shape_bounding_box() {
+        let bounding_box = GeoShape::BoundingBox {
+            top_left: GeoPoint::new(50.0, 0.0).unwrap(),
+            bottom_right: GeoPoint::new(40.0, 10.0).unwrap(),
+        };
+        assert!(bounding_box.contains(&GeoPoint::new(48.85, 2.35).unwrap()));
+        assert!(bounding_box.contains(&GeoPoint::new(50.0, 10.0).unwrap()));
+        assert!(!bounding_box.contains(&GeoPoint::new(51.5, -0.12).unwrap()));
+
+        let antimeridian_box = GeoShape::BoundingBox {
+            top_left: GeoPoint::new(10.0, 170.0).unwrap(),
+            bottom_right: GeoPoint::new(-10.0, -170.0).unwrap(),
+        };
+        assert!(antimeridian_box.contains(&GeoPoint::new(0.0, 175.0).unwrap()));
+        assert!(antimeridian_box.contains(&GeoPoint::new(0.0, -175.0).unwrap()));
+        assert!(!antimeridian_box.contains(&GeoPoint::new(0.0, 0.0).unwrap()));
+    }
+
+    #[test]
+    fn test_geo_shape_distance() {
+        let distance = GeoShape::Distance {
+            center: GeoPoint::new(48.8566, 2.3522).unwrap(),
+            radius_meters: 20_000.0,
+        };
+        assert!(distance.contains(&GeoPoint::new(48.8049, 2.1204).unwrap()));
+        assert!(!distance.contains(&GeoPoint::new(51.5072, -0.1276).unwrap()));
+    }
+
+    #[test]
+    fn test_geo_shape_polygon() {
+        let triangle = GeoShape::Polygon {
+            points: vec![
+                GeoPoint::new(0.0, 0.0).unwrap(),
+                GeoPoint::new(10.0, 5.0).unwrap(),
+                GeoPoint::new(0.0, 10.0).unwrap(),
+            ],
+        };
+        assert!(triangle.contains(&GeoPoint::new(2.0, 5.0).unwrap()));
+        assert!(!triangle.contains(&GeoPoint::new(8.0, 1.0).unwrap()));
+        assert!(!triangle.contains(&GeoPoint::new(-1.0, 5.0).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_geo_filter() {
+        let geo_filter = GeoFilter::parse(
+            r#"{"field": "location", "distance": {"center": "48.85,2.35", "radius_meters": 500}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            geo_filter,
+            GeoFilter {
+                field: "location".to_string(),
+                shape: GeoShape::Distance {
+                    center: GeoPoint::new(48.85, 2.35).unwrap(),
+                    radius_meters: 500.0,
+                },
+            }
+        );
+        let geo_filter = GeoFilter::parse(
+            r#"{"field": "location", "bounding_box": {"top_left": [0, 50], "bottom_right": [10, 40]}}"#,
+        )
+        .unwrap();
+        assert!(matches!(geo_filter.shape, GeoShape::BoundingBox { .. }));
+
+        let error = GeoFilter::parse(
+            r#"{"field": "location", "bounding_box": {"top_left": [0, 40], "bottom_right": [10, 50]}}"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("must be greater than"));
+
+        let error =
+            GeoFilter::parse(r#"{"field": "location", "polygon": {"points": [[0, 0], [1, 1]]}}"#)
+                .unwrap_err();
+        assert!(error.to_string().contains("at least 3 points"));
+
+        GeoFilter::parse(r#"{"field": "location"}"#).unwrap_err();
+    }
+}
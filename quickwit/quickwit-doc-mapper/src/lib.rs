@@ -25,25 +25,55 @@
 //! to convert a json like documents to a document indexable by tantivy
 //! engine, aka tantivy::Document.
 
+mod csv_doc_parser;
 mod default_doc_mapper;
 mod doc_mapper;
 mod error;
+mod field_aliases;
+mod geo_point;
 mod query_builder;
 mod routing_expression;
+mod runtime_fields;
+mod schema_evolution;
+mod term_automaton;
 mod tokenizers;
+mod vector;
+mod vector_index;
 
+/// Pruning splits by the bloom filters of their high-cardinality fields.
+pub mod bloom_filter;
+/// Pruning splits by the ranges of their numeric field values.
+pub mod range_pruning;
 /// Pruning tags manipulation.
 pub mod tag_pruning;
 
+pub use csv_doc_parser::{CsvDocParser, CsvRowError};
 pub use default_doc_mapper::{
-    DefaultDocMapper, DefaultDocMapperBuilder, FieldMappingEntry, ModeType, QuickwitJsonOptions,
+    DefaultDocMapper, DefaultDocMapperBuilder, DynamicFieldType, DynamicTypeHint,
+    FieldMappingEntry, FieldMappingType, MissingFieldOptions, ModeType, OnMissing,
+    QuickwitJsonOptions,
 };
 use default_doc_mapper::{
     FieldMappingEntryForSerialization, IndexRecordOptionSchema, QuickwitTextTokenizer,
 };
 pub use doc_mapper::{DocMapper, NamedField, WarmupInfo};
 pub use error::{DocParsingError, QueryParserError};
-pub use tokenizers::QUICKWIT_TOKENIZER_MANAGER;
+pub use field_aliases::{resolve_field_alias, resolve_field_aliases, resolve_query_field_aliases};
+pub use geo_point::{GeoFilter, GeoPoint, GeoShape};
+pub use query_builder::QueryOperator;
+pub use runtime_fields::{is_truthy, validate_runtime_expr, BinaryOp, RuntimeExpr, RuntimeFields};
+pub use term_automaton::TermAutomaton;
+pub use tokenizers::{
+    CharFilter, NgramTokenizerOptions, PatternReplaceOptions, RegexTokenizerOptions,
+    TokenFilterType, TokenizerEntry, TokenizerType, QUICKWIT_TOKENIZER_MANAGER,
+};
+pub use vector::{
+    decode_vector, encode_vector, vector_from_json, VectorField, VectorSimilarity, MAX_VECTOR_DIMS,
+};
+pub use vector_index::{
+    FieldVectorIndex, ScoredVector, VectorIndexBuilder, VectorIndexFooter, DEFAULT_NUM_PROBES,
+    VECTOR_INDEX_FILE_NAME, VECTOR_INDEX_FOOTER_LEN_NUM_BYTES,
+};
 
 /// Field name reserved for storing the source document.
 pub const SOURCE_FIELD_NAME: &str = "_source";
@@ -51,13 +81,20 @@ pub const SOURCE_FIELD_NAME: &str = "_source";
 /// Field name reserved for storing the dynamically indexed fields.
 pub const DYNAMIC_FIELD_NAME: &str = "_dynamic";
 
+/// Field name reserved for storing the elements of the nested fields.
+pub const NESTED_FIELD_NAME: &str = "_nested";
+
 /// Quickwit reserved field names.
-const QW_RESERVED_FIELD_NAMES: &[&str] = &[SOURCE_FIELD_NAME, DYNAMIC_FIELD_NAME];
+const QW_RESERVED_FIELD_NAMES: &[&str] =
+    &[SOURCE_FIELD_NAME, DYNAMIC_FIELD_NAME, NESTED_FIELD_NAME];
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(
     QuickwitJsonOptions,
     ModeType,
+    QueryOperator,
+    DynamicTypeHint,
+    DynamicFieldType,
     QuickwitTextTokenizer,
     IndexRecordOptionSchema,
     FieldMappingEntryForSerialization,
@@ -17,25 +17,96 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
+use std::str::FromStr;
 
 use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
 use quickwit_proto::SearchRequest;
-use tantivy::query::{Query, QueryParser, QueryParserError as TantivyQueryParserError};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tantivy::query::{
+    BooleanQuery, BoostQuery, Occur as TantivyOccur, Query, QueryParser,
+    QueryParserError as TantivyQueryParserError,
+};
 use tantivy::schema::{Field, FieldEntry, FieldType, Schema};
-use tantivy_query_grammar::{UserInputAst, UserInputLeaf, UserInputLiteral};
+use tantivy::tokenizer::TokenizerManager;
+use tantivy::Score;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
 
-use crate::{QueryParserError, WarmupInfo, DYNAMIC_FIELD_NAME, QUICKWIT_TOKENIZER_MANAGER};
+use crate::default_doc_mapper::BinaryFormat;
+use crate::schema_evolution::user_input_ast_to_query;
+use crate::term_automaton::{automaton_clause_ord, extract_automaton_clauses, AutomatonClause};
+use crate::{
+    validate_runtime_expr, QueryParserError, RuntimeFields, TermAutomaton, WarmupInfo,
+    DYNAMIC_FIELD_NAME,
+};
+
+/// Boolean operator combining the clauses of a query that are not joined by an explicit `AND` or
+/// `OR`.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOperator {
+    /// The documents must match all the clauses.
+    #[default]
+    And,
+    /// The documents must match at least one of the clauses.
+    Or,
+}
+
+impl QueryOperator {
+    /// Returns whether the operator is `AND`, the default one.
+    pub fn is_and(&self) -> bool {
+        *self == QueryOperator::And
+    }
+
+    /// Returns the occurrence of the clauses that are not joined by an explicit operator.
+    pub(crate) fn default_occur(&self) -> Occur {
+        match self {
+            QueryOperator::And => Occur::Must,
+            QueryOperator::Or => Occur::Should,
+        }
+    }
+}
+
+/// Search settings of the index applied when parsing the queries.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct QuerySettings {
+    pub default_operator: QueryOperator,
+    pub max_query_clauses: Option<usize>,
+    /// Input format of the bytes fields, keyed by field name.
+    pub bytes_input_formats: HashMap<String, BinaryFormat>,
+}
 
 /// Build a `Query` with field resolution & forbidding range clauses.
 pub(crate) fn build_query(
     schema: Schema,
     request: &SearchRequest,
     default_field_names: &[String],
+    tokenizer_manager: &TokenizerManager,
+    query_settings: &QuerySettings,
 ) -> Result<(Box<dyn Query>, WarmupInfo), QueryParserError> {
-    let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
+    let (automaton_query_str, automaton_clauses) = extract_automaton_clauses(&request.query)?;
+    let query_str = rewrite_cidr_clauses(&schema, &automaton_query_str)?;
+    let query_str = rewrite_hex_bytes_clauses(&query_settings.bytes_input_formats, &query_str)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&query_str)
         .map_err(|_| TantivyQueryParserError::SyntaxError(request.query.to_string()))?;
 
+    if let Some(max_query_clauses) = query_settings.max_query_clauses {
+        let num_query_clauses = count_query_clauses(&user_input_ast);
+        if num_query_clauses > max_query_clauses {
+            return Err(anyhow::anyhow!(
+                "Query has {num_query_clauses} clauses, which exceeds the `max_query_clauses` \
+                 limit of {max_query_clauses} of the index."
+            )
+            .into());
+        }
+    }
+
     let fast_field_names: HashSet<String> = extract_field_with_ranges(&schema, &user_input_ast)?;
 
     if needs_default_search_field(&user_input_ast)
@@ -55,17 +126,50 @@ pub(crate) fn build_query(
         resolve_fields(&schema, &request.search_fields)?
     };
 
+    let runtime_fields = RuntimeFields::parse(request.runtime_fields.as_deref())?;
+    runtime_fields.validate(&schema)?;
+    if let Some(runtime_filter) = &request.runtime_filter {
+        let runtime_filter_expr = runtime_fields.parse_filter(runtime_filter)?;
+        validate_runtime_expr(&runtime_filter_expr, &schema).context("Invalid runtime filter.")?;
+    }
+
     if let Some(sort_by_field) = &request.sort_by_field {
-        validate_sort_by_field(sort_by_field, &schema, Some(&search_fields))?;
+        if runtime_fields.get(sort_by_field).is_none() {
+            validate_sort_by_field(sort_by_field, &schema, Some(&search_fields))?;
+        }
     }
 
-    let mut query_parser =
-        QueryParser::new(schema, search_fields, QUICKWIT_TOKENIZER_MANAGER.clone());
-    query_parser.set_conjunction_by_default();
-    let query = query_parser.parse_query(&request.query)?;
+    let term_automatons = resolve_automaton_clauses(
+        &schema,
+        &user_input_ast,
+        &automaton_clauses,
+        tokenizer_manager,
+        &query_settings.bytes_input_formats,
+    )?;
+    let automaton_field_names: HashSet<String> = term_automatons
+        .values()
+        .map(|(field, _)| schema.get_field_name(*field).to_string())
+        .collect();
+
+    let mut query_parser = QueryParser::new(schema, search_fields, tokenizer_manager.clone());
+    if query_settings.default_operator.is_and() {
+        query_parser.set_conjunction_by_default();
+    }
+    let query = if term_automatons.is_empty() {
+        query_parser.parse_query(&query_str)?
+    } else {
+        build_query_with_automaton_clauses(
+            &user_input_ast,
+            &query_parser,
+            &term_automatons,
+            query_settings.default_operator.default_occur(),
+        )?
+    };
 
     let mut term_set_query_fields = HashSet::new();
     extract_term_set_query_fields(&user_input_ast, &mut term_set_query_fields);
+    // Automaton queries match the terms of the whole term dictionary of their field.
+    term_set_query_fields.extend(automaton_field_names);
 
     let mut terms_grouped_by_field: HashMap<Field, HashMap<_, bool>> = Default::default();
 
@@ -83,12 +187,245 @@ pub(crate) fn build_query(
         posting_field_names: term_set_query_fields,
         terms_grouped_by_field,
         fast_field_names,
+        term_automatons: term_automatons.into_values().collect(),
         ..WarmupInfo::default()
     };
 
     Ok((query, warmup_info))
 }
 
+fn automaton_clause_leaf_ord(leaf: &UserInputLeaf) -> Option<usize> {
+    match leaf {
+        UserInputLeaf::Literal(UserInputLiteral { phrase, .. }) => automaton_clause_ord(phrase),
+        _ => None,
+    }
+}
+
+/// Resolves the automaton clauses of the query against the fields they target, keyed by the
+/// ordinal of their placeholder.
+fn resolve_automaton_clauses(
+    schema: &Schema,
+    user_input_ast: &UserInputAst,
+    automaton_clauses: &[AutomatonClause],
+    tokenizer_manager: &TokenizerManager,
+    bytes_input_formats: &HashMap<String, BinaryFormat>,
+) -> anyhow::Result<BTreeMap<usize, (Field, TermAutomaton)>> {
+    let mut term_automatons = BTreeMap::new();
+    for leaf in collect_leaves(user_input_ast) {
+        let Some(ord) = automaton_clause_leaf_ord(leaf) else {
+            continue;
+        };
+        // Words looking like placeholders but typed by the user are regular terms.
+        let (Some(automaton_clause), Some(field_name)) =
+            (automaton_clauses.get(ord), extract_field_name(leaf))
+        else {
+            continue;
+        };
+        let bytes_input_format = bytes_input_formats
+            .get(field_name)
+            .copied()
+            .unwrap_or_default();
+        let term_automaton =
+            automaton_clause.resolve(schema, field_name, tokenizer_manager, bytes_input_format)?;
+        term_automatons.insert(ord, term_automaton);
+    }
+    Ok(term_automatons)
+}
+
+/// Builds the query from its AST. The tantivy query parser does not support automaton clauses,
+/// so the subqueries containing some are assembled here, and the other ones are delegated to the
+/// query parser.
+fn build_query_with_automaton_clauses(
+    user_input_ast: &UserInputAst,
+    query_parser: &QueryParser,
+    term_automatons: &BTreeMap<usize, (Field, TermAutomaton)>,
+    default_occur: Occur,
+) -> Result<Box<dyn Query>, QueryParserError> {
+    let contains_automaton_clause = collect_leaves(user_input_ast).into_iter().any(|leaf| {
+        automaton_clause_leaf_ord(leaf).map_or(false, |ord| term_automatons.contains_key(&ord))
+    });
+    if !contains_automaton_clause {
+        let query = query_parser.parse_query(&user_input_ast_to_query(user_input_ast))?;
+        return Ok(query);
+    }
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries) => {
+            let mut clauses = Vec::with_capacity(sub_queries.len());
+            for (occur_opt, sub_ast) in sub_queries {
+                let occur = match occur_opt.unwrap_or(default_occur) {
+                    Occur::Must => TantivyOccur::Must,
+                    Occur::MustNot => TantivyOccur::MustNot,
+                    Occur::Should => TantivyOccur::Should,
+                };
+                let sub_query = build_query_with_automaton_clauses(
+                    sub_ast,
+                    query_parser,
+                    term_automatons,
+                    default_occur,
+                )?;
+                clauses.push((occur, sub_query));
+            }
+            Ok(Box::new(BooleanQuery::new(clauses)))
+        }
+        UserInputAst::Boost(ast, boost) => {
+            let query = build_query_with_automaton_clauses(
+                ast,
+                query_parser,
+                term_automatons,
+                default_occur,
+            )?;
+            Ok(Box::new(BoostQuery::new(query, *boost as Score)))
+        }
+        UserInputAst::Leaf(leaf) => {
+            let (field, term_automaton) = automaton_clause_leaf_ord(leaf)
+                .and_then(|ord| term_automatons.get(&ord))
+                .expect("Automaton clauses should have been resolved.");
+            let field_name =
+                extract_field_name(leaf).expect("Automaton clauses should always target a field.");
+            Ok(term_automaton.query(*field, field_name)?)
+        }
+    }
+}
+
+/// Matches `field:ip/prefix_length` clauses. The CIDR block may be quoted, which is required for
+/// IPv6 blocks.
+static CIDR_CLAUSE_PTN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?P<prefix>^|[\s(+\-])(?P<field>(?:[^\s:()"\\]|\\.)+):(?P<cidr>"[0-9a-fA-F:.]+/\d{1,3}"|[0-9.]+/\d{1,3})"#,
+    )
+    .unwrap()
+});
+
+/// Rewrites the CIDR clauses targeting IP fields, e.g. `src_ip:10.0.0.0/8`, into the equivalent
+/// range clauses, e.g. `src_ip:[10.0.0.0 TO 10.255.255.255]`.
+fn rewrite_cidr_clauses<'a>(schema: &Schema, query: &'a str) -> anyhow::Result<Cow<'a, str>> {
+    if !query.contains('/') {
+        return Ok(Cow::Borrowed(query));
+    }
+    let mut rewritten_query = String::with_capacity(query.len());
+    let mut last_match_end = 0;
+    for captures in CIDR_CLAUSE_PTN.captures_iter(query) {
+        let field_name = &captures["field"];
+        let Ok(field) = schema.get_field(field_name) else {
+            continue;
+        };
+        if !matches!(
+            schema.get_field_entry(field).field_type(),
+            FieldType::IpAddr(_)
+        ) {
+            continue;
+        }
+        let (start_ip, end_ip) = parse_cidr_block(captures["cidr"].trim_matches('"'))?;
+        let clause_match = captures
+            .get(0)
+            .expect("The whole match should always be captured.");
+        rewritten_query.push_str(&query[last_match_end..clause_match.start()]);
+        rewritten_query.push_str(&format!(
+            "{}{field_name}:[{start_ip} TO {end_ip}]",
+            &captures["prefix"]
+        ));
+        last_match_end = clause_match.end();
+    }
+    if last_match_end == 0 {
+        return Ok(Cow::Borrowed(query));
+    }
+    rewritten_query.push_str(&query[last_match_end..]);
+    Ok(Cow::Owned(rewritten_query))
+}
+
+/// Matches `field:value` and `field:"value"` clauses.
+static TERM_CLAUSE_PTN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?P<prefix>^|[\s(+\-])(?P<field>(?:[^\s:()"\\+\-]|\\.)(?:[^\s:()"\\]|\\.)*):(?P<value>"[^"]*"|[^\s()"^\[\]{}]+)"#,
+    )
+    .unwrap()
+});
+
+/// Rewrites the term clauses targeting bytes fields with the `hex` input format, e.g.
+/// `sha1:da39a3ee`, into clauses carrying the base64 encoded value the query parser expects, e.g.
+/// `sha1:"2jmj7g=="`.
+fn rewrite_hex_bytes_clauses<'a>(
+    bytes_input_formats: &HashMap<String, BinaryFormat>,
+    query: &'a str,
+) -> anyhow::Result<Cow<'a, str>> {
+    if !bytes_input_formats
+        .values()
+        .any(|format| *format == BinaryFormat::Hex)
+    {
+        return Ok(Cow::Borrowed(query));
+    }
+    let mut rewritten_query = String::with_capacity(query.len());
+    let mut last_match_end = 0;
+    for captures in TERM_CLAUSE_PTN.captures_iter(query) {
+        let field_name = &captures["field"];
+        if bytes_input_formats.get(field_name) != Some(&BinaryFormat::Hex) {
+            continue;
+        }
+        let value = captures["value"].trim_matches('"');
+        // `field:*` and the automaton clauses are resolved separately.
+        if value == "*" || automaton_clause_ord(value).is_some() {
+            continue;
+        }
+        let bytes = BinaryFormat::Hex
+            .parse_str(value)
+            .map_err(|error| anyhow::anyhow!("Invalid clause `{field_name}:{value}`: {error}"))?;
+        let clause_match = captures
+            .get(0)
+            .expect("The whole match should always be captured.");
+        rewritten_query.push_str(&query[last_match_end..clause_match.start()]);
+        rewritten_query.push_str(&format!(
+            "{}{field_name}:\"{}\"",
+            &captures["prefix"],
+            BinaryFormat::Base64.format_to_string(&bytes)
+        ));
+        last_match_end = clause_match.end();
+    }
+    if last_match_end == 0 {
+        return Ok(Cow::Borrowed(query));
+    }
+    rewritten_query.push_str(&query[last_match_end..]);
+    Ok(Cow::Owned(rewritten_query))
+}
+
+/// Parses a CIDR block such as `10.0.0.0/8` or `2001:db8::/32` and returns the first and last
+/// IP addresses of the block.
+fn parse_cidr_block(cidr_block: &str) -> anyhow::Result<(IpAddr, IpAddr)> {
+    let (ip_str, prefix_length_str) = cidr_block
+        .split_once('/')
+        .with_context(|| format!("Invalid CIDR block `{cidr_block}`."))?;
+    let ip_addr = IpAddr::from_str(ip_str)
+        .with_context(|| format!("Invalid IP address in CIDR block `{cidr_block}`."))?;
+    let prefix_length: u32 = prefix_length_str
+        .parse()
+        .with_context(|| format!("Invalid prefix length in CIDR block `{cidr_block}`."))?;
+    match ip_addr {
+        IpAddr::V4(ipv4_addr) => {
+            if prefix_length > 32 {
+                bail!(
+                    "Invalid CIDR block `{cidr_block}`: the prefix length of an IPv4 block must \
+                     be lower than or equal to 32."
+                );
+            }
+            let mask = u32::MAX.checked_shl(32 - prefix_length).unwrap_or(0);
+            let start = u32::from(ipv4_addr) & mask;
+            let end = start | !mask;
+            Ok((IpAddr::V4(start.into()), IpAddr::V4(end.into())))
+        }
+        IpAddr::V6(ipv6_addr) => {
+            if prefix_length > 128 {
+                bail!(
+                    "Invalid CIDR block `{cidr_block}`: the prefix length of an IPv6 block must \
+                     be lower than or equal to 128."
+                );
+            }
+            let mask = u128::MAX.checked_shl(128 - prefix_length).unwrap_or(0);
+            let start = u128::from(ipv6_addr) & mask;
+            let end = start | !mask;
+            Ok((IpAddr::V6(start.into()), IpAddr::V6(end.into())))
+        }
+    }
+}
+
 fn resolve_fields(schema: &Schema, field_names: &[String]) -> anyhow::Result<Vec<Field>> {
     let mut fields = vec![];
     for field_name in field_names {
@@ -113,6 +450,17 @@ fn collect_leaves(user_input_ast: &UserInputAst) -> Vec<&UserInputLeaf> {
     }
 }
 
+/// Counts the clauses of a query. Each term of a term set counts as a clause.
+fn count_query_clauses(user_input_ast: &UserInputAst) -> usize {
+    collect_leaves(user_input_ast)
+        .into_iter()
+        .map(|leaf| match leaf {
+            UserInputLeaf::Set { elements, .. } => elements.len(),
+            _ => 1,
+        })
+        .sum()
+}
+
 fn extract_field_name(leaf: &UserInputLeaf) -> Option<&str> {
     match leaf {
         UserInputLeaf::Literal(UserInputLiteral { field_name, .. }) => field_name.as_deref(),
@@ -191,14 +539,10 @@ fn extract_term_set_query_fields(user_input_ast: &UserInputAst, set: &mut HashSe
 /// Tells if the query has a Term or Range node which does not
 /// specify a search field.
 fn needs_default_search_field(user_input_ast: &UserInputAst) -> bool {
-    // `All` query apply to all fields, therefore doesn't need default fields.
-    if matches!(user_input_ast, UserInputAst::Leaf(leaf) if **leaf == UserInputLeaf::All) {
-        return false;
-    }
-
+    // `All` queries apply to all fields, therefore don't need default fields.
     collect_leaves(user_input_ast)
         .into_iter()
-        .any(|leaf| extract_field_name(leaf).is_none())
+        .any(|leaf| *leaf != UserInputLeaf::All && extract_field_name(leaf).is_none())
 }
 
 /// Collects all the fields names on the query ast nodes.
@@ -301,14 +645,21 @@ fn validate_sort_by_score(
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use quickwit_proto::SearchRequest;
     use tantivy::query::QueryParserError;
     use tantivy::schema::{
         Cardinality, DateOptions, IpAddrOptions, Schema, FAST, INDEXED, STORED, TEXT,
     };
+    use tantivy::Term;
 
-    use super::{build_query, validate_requested_snippet_fields};
-    use crate::{DYNAMIC_FIELD_NAME, SOURCE_FIELD_NAME};
+    use super::{
+        build_query, parse_cidr_block, validate_requested_snippet_fields, QueryOperator,
+        QuerySettings,
+    };
+    use crate::default_doc_mapper::BinaryFormat;
+    use crate::{TermAutomaton, DYNAMIC_FIELD_NAME, QUICKWIT_TOKENIZER_MANAGER, SOURCE_FIELD_NAME};
 
     enum TestExpectation {
         Err(&'static str),
@@ -337,6 +688,7 @@ mod test {
         schema_builder.add_u64_field("u64_fast", FAST | STORED);
         schema_builder.add_i64_field("i64_fast", FAST | STORED);
         schema_builder.add_f64_field("f64_fast", FAST | STORED);
+        schema_builder.add_bytes_field("payload", INDEXED | STORED);
         schema_builder.build()
     }
 
@@ -359,12 +711,37 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
 
         let default_field_names =
             default_search_fields.unwrap_or_else(|| vec!["title".to_string(), "desc".to_string()]);
 
-        let query_result = build_query(make_schema(), &request, &default_field_names);
+        let query_result = build_query(
+            make_schema(),
+            &request,
+            &default_field_names,
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        );
         match expected {
             TestExpectation::Err(sub_str) => {
                 assert!(
@@ -397,6 +774,13 @@ mod test {
     #[test]
     fn test_build_query() {
         check_build_query("*", vec![], None, TestExpectation::Ok("All")).unwrap();
+        check_build_query(
+            "(* AND NOT title:foo)",
+            vec![],
+            None,
+            TestExpectation::Ok("AllQuery"),
+        )
+        .unwrap();
         check_build_query(
             "foo:bar",
             vec![],
@@ -513,6 +897,268 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_fuzzy_query() {
+        check_build_query(
+            "title:jonh~1",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("FuzzyTermQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "desc:foo AND (title:jonh~ OR -title:\"bar~1\")",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("TermQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:jonh~3",
+            Vec::new(),
+            None,
+            TestExpectation::Err("the edit distance must be 0, 1, or 2"),
+        )
+        .unwrap();
+        check_build_query(
+            "server.running:tru~1",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Fuzzy queries are only supported on text fields"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:foo-bar~1",
+            Vec::new(),
+            None,
+            TestExpectation::Err("must target a single term"),
+        )
+        .unwrap();
+
+        let request = SearchRequest {
+            query: "desc:foo title:Jonh~1".to_string(),
+            ..Default::default()
+        };
+        let schema = make_schema();
+        let title_field = schema.get_field("title").unwrap();
+        let (_, warmup_info) = build_query(
+            schema,
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            warmup_info.term_automatons,
+            [(
+                title_field,
+                TermAutomaton::Fuzzy {
+                    text: "jonh".to_string(),
+                    distance: 1,
+                }
+            )]
+        );
+        assert!(warmup_info.term_dict_field_names.contains("title"));
+        assert!(warmup_info.posting_field_names.contains("title"));
+    }
+
+    #[test]
+    fn test_regex_query() {
+        check_build_query(
+            "title:/.*base64_decode.*/",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("RegexQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "desc:foo AND (title:/jo.*/ OR -title:\"/bar/\")",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("RegexQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:/foo(/",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Invalid regex clause `title:/foo(/`"),
+        )
+        .unwrap();
+        check_build_query(
+            "server.running:/tr.*/",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Regex queries are only supported on text fields"),
+        )
+        .unwrap();
+
+        let request = SearchRequest {
+            query: r#"title:/a\/b.*/"#.to_string(),
+            ..Default::default()
+        };
+        let schema = make_schema();
+        let title_field = schema.get_field("title").unwrap();
+        let (_, warmup_info) = build_query(
+            schema,
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            warmup_info.term_automatons,
+            [(
+                title_field,
+                TermAutomaton::Regex {
+                    pattern: "a/b.*".to_string(),
+                }
+            )]
+        );
+        assert!(warmup_info.term_dict_field_names.contains("title"));
+    }
+
+    #[test]
+    fn test_wildcard_query() {
+        check_build_query(
+            "title:*timeout*",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("RegexQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "server.running:tr*",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Wildcard queries are only supported on text fields"),
+        )
+        .unwrap();
+        check_build_query(
+            "title:foo-bar*",
+            Vec::new(),
+            None,
+            TestExpectation::Err("must target a single term"),
+        )
+        .unwrap();
+
+        let request = SearchRequest {
+            query: "title:*TimeOut* desc:conn?".to_string(),
+            ..Default::default()
+        };
+        let schema = make_schema();
+        let title_field = schema.get_field("title").unwrap();
+        let desc_field = schema.get_field("desc").unwrap();
+        let (_, warmup_info) = build_query(
+            schema,
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            warmup_info.term_automatons,
+            [
+                (
+                    title_field,
+                    TermAutomaton::Wildcard {
+                        pattern: "*timeout*".to_string(),
+                    }
+                ),
+                (
+                    desc_field,
+                    TermAutomaton::Wildcard {
+                        pattern: "conn?".to_string(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bytes_query() {
+        check_build_query(
+            "payload:\"aGVsbG8=\"",
+            Vec::new(),
+            None,
+            TestExpectation::Ok("TermQuery"),
+        )
+        .unwrap();
+        check_build_query(
+            "payload:aGVs*",
+            Vec::new(),
+            None,
+            TestExpectation::Ok(
+                "RangeQuery { field: \"payload\", value_type: Bytes, left_bound: Included([104, \
+                 101, 108]), right_bound: Excluded([104, 101, 109]) }",
+            ),
+        )
+        .unwrap();
+        check_build_query(
+            "payload:a?Vs",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Wildcard queries are not supported on bytes fields"),
+        )
+        .unwrap();
+        check_build_query(
+            "payload:aGV*",
+            Vec::new(),
+            None,
+            TestExpectation::Err("Invalid prefix clause `payload:aGV*`"),
+        )
+        .unwrap();
+
+        let schema = make_schema();
+        let payload_field = schema.get_field("payload").unwrap();
+        let query_settings = QuerySettings {
+            bytes_input_formats: HashMap::from([("payload".to_string(), BinaryFormat::Hex)]),
+            ..Default::default()
+        };
+        let request = SearchRequest {
+            query: "payload:68656C6C6F OR payload:6865*".to_string(),
+            ..Default::default()
+        };
+        let (_, warmup_info) = build_query(
+            schema.clone(),
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &query_settings,
+        )
+        .unwrap();
+        assert!(warmup_info.terms_grouped_by_field[&payload_field]
+            .contains_key(&Term::from_field_bytes(payload_field, b"hello")));
+        assert_eq!(
+            warmup_info.term_automatons,
+            [(
+                payload_field,
+                TermAutomaton::BytesPrefix {
+                    prefix: b"he".to_vec(),
+                    pattern: "6865*".to_string(),
+                }
+            )]
+        );
+        let request = SearchRequest {
+            query: "payload:aGVsbG8=".to_string(),
+            ..Default::default()
+        };
+        let error = build_query(
+            schema,
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &query_settings,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid clause `payload:aGVsbG8=`: Expected hex string"));
+    }
+
     #[test]
     fn test_datetime_range_query() {
         check_build_query(
@@ -556,6 +1202,115 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_ip_cidr_query() {
+        check_build_query(
+            "ip:127.0.0.0/8",
+            Vec::new(),
+            None,
+            TestExpectation::Ok(
+                "RangeQuery { field: \"ip\", value_type: IpAddr, left_bound: Included([0, 0, 0, \
+                 0, 0, 0, 0, 0, 0, 0, 255, 255, 127, 0, 0, 0]), right_bound: Included([0, 0, 0, \
+                 0, 0, 0, 0, 0, 0, 0, 255, 255, 127, 255, 255, 255]) }",
+            ),
+        )
+        .unwrap();
+        check_build_query(
+            "title:foo AND (ips:10.0.0.0/16 OR ips:\"192.168.1.0/24\")",
+            Vec::new(),
+            None,
+            TestExpectation::Ok(
+                "RangeQuery { field: \"ips\", value_type: IpAddr, left_bound: Included([0, 0, 0, \
+                 0, 0, 0, 0, 0, 0, 0, 255, 255, 192, 168, 1, 0]), right_bound: Included([0, 0, 0, \
+                 0, 0, 0, 0, 0, 0, 0, 255, 255, 192, 168, 1, 255]) }",
+            ),
+        )
+        .unwrap();
+        check_build_query(
+            "ip:127.0.0.0/33",
+            Vec::new(),
+            None,
+            TestExpectation::Err("the prefix length of an IPv4 block must be lower than"),
+        )
+        .unwrap();
+        check_build_query(
+            "ip_notff:127.0.0.0/8",
+            Vec::new(),
+            None,
+            TestExpectation::Err("field `ip_notff` is not declared as a fast field"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_build_query_with_runtime_fields() {
+        let build_query_with_runtime_fields =
+            |runtime_fields: &str, runtime_filter: Option<&str>, sort_by_field: Option<&str>| {
+                let request = SearchRequest {
+                    index_id: "test_index".to_string(),
+                    query: "*".to_string(),
+                    max_hits: 20,
+                    sort_by_field: sort_by_field.map(ToString::to_string),
+                    runtime_fields: Some(runtime_fields.to_string()),
+                    runtime_filter: runtime_filter.map(ToString::to_string),
+                    ..Default::default()
+                };
+                build_query(
+                    make_schema(),
+                    &request,
+                    &["title".to_string()],
+                    &QUICKWIT_TOKENIZER_MANAGER,
+                    &QuerySettings::default(),
+                )
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+            };
+        build_query_with_runtime_fields(
+            r#"{"total": "u64_fast + i64_fast * f64_fast"}"#,
+            Some("total > 10 AND server.running"),
+            Some("total"),
+        )
+        .unwrap();
+        let error =
+            build_query_with_runtime_fields(r#"{"total": "title + 1"}"#, None, None).unwrap_err();
+        assert!(error.contains("Invalid runtime field `total`"));
+        let error =
+            build_query_with_runtime_fields(r#"{"title": "u64_fast"}"#, None, None).unwrap_err();
+        assert!(error.contains("already a field of the schema"));
+        build_query_with_runtime_fields("{}", Some("ip_notff > 1"), None).unwrap_err();
+        build_query_with_runtime_fields("{}", None, Some("total")).unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_cidr_block() {
+        let cidr_range = |cidr_block: &str| {
+            let (start_ip, end_ip) = parse_cidr_block(cidr_block).unwrap();
+            (start_ip.to_string(), end_ip.to_string())
+        };
+        assert_eq!(
+            cidr_range("10.1.2.3/8"),
+            ("10.0.0.0".to_string(), "10.255.255.255".to_string())
+        );
+        assert_eq!(
+            cidr_range("192.168.1.17/32"),
+            ("192.168.1.17".to_string(), "192.168.1.17".to_string())
+        );
+        assert_eq!(
+            cidr_range("1.2.3.4/0"),
+            ("0.0.0.0".to_string(), "255.255.255.255".to_string())
+        );
+        assert_eq!(
+            cidr_range("2001:db8::1/32"),
+            (
+                "2001:db8::".to_string(),
+                "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff".to_string()
+            )
+        );
+        parse_cidr_block("10.0.0.0").unwrap_err();
+        parse_cidr_block("10.0.0/8").unwrap_err();
+        parse_cidr_block("2001:db8::/129").unwrap_err();
+    }
+
     #[test]
     fn test_f64_range_query() {
         check_build_query(
@@ -656,6 +1411,25 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let user_input_ast = tantivy_query_grammar::parse_query(&request.query)
             .map_err(|_| QueryParserError::SyntaxError(request.query.clone()))
@@ -666,6 +1440,44 @@ mod test {
         validate_requested_snippet_fields(&schema, &request, &user_input_ast, &default_field_names)
     }
 
+    #[test]
+    fn test_build_query_with_query_settings() {
+        let request = SearchRequest {
+            query: "title:foo (desc:bar OR title: IN [baz qux])".to_string(),
+            ..Default::default()
+        };
+        let query_settings = QuerySettings {
+            default_operator: QueryOperator::Or,
+            max_query_clauses: Some(4),
+            ..Default::default()
+        };
+        let (query, _) = build_query(
+            make_schema(),
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &query_settings,
+        )
+        .unwrap();
+        assert!(!format!("{query:?}").contains("Must"));
+
+        let query_settings = QuerySettings {
+            max_query_clauses: Some(3),
+            ..query_settings
+        };
+        let error = build_query(
+            make_schema(),
+            &request,
+            &[],
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &query_settings,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Query has 4 clauses, which exceeds the `max_query_clauses` limit of 3"));
+    }
+
     #[test]
     #[should_panic(expected = "provided string was not `true` or `false`")]
     fn test_build_query_not_bool_should_fail() {
@@ -766,6 +1578,25 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let request_without_set = SearchRequest {
             aggregation_request: None,
@@ -779,18 +1610,48 @@ mod test {
             start_offset: 0,
             sort_order: None,
             sort_by_field: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
 
         let default_field_names = vec!["title".to_string(), "desc".to_string()];
 
-        let (_, warmup_info) = build_query(make_schema(), &request_with_set, &default_field_names)?;
+        let (_, warmup_info) = build_query(
+            make_schema(),
+            &request_with_set,
+            &default_field_names,
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        )?;
         assert_eq!(warmup_info.term_dict_field_names.len(), 1);
         assert_eq!(warmup_info.posting_field_names.len(), 1);
         assert!(warmup_info.term_dict_field_names.contains("title"));
         assert!(warmup_info.posting_field_names.contains("title"));
 
-        let (_, warmup_info) =
-            build_query(make_schema(), &request_without_set, &default_field_names)?;
+        let (_, warmup_info) = build_query(
+            make_schema(),
+            &request_without_set,
+            &default_field_names,
+            &QUICKWIT_TOKENIZER_MANAGER,
+            &QuerySettings::default(),
+        )?;
         assert!(warmup_info.term_dict_field_names.is_empty());
         assert!(warmup_info.posting_field_names.is_empty());
 
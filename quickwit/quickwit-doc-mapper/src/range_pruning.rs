@@ -0,0 +1,279 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use serde_json::Number as JsonNumber;
+use tantivy::query::QueryParserError as TantivyQueryParserError;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
+
+use crate::term_automaton::extract_automaton_clauses;
+use crate::QueryParserError;
+
+/// Ranges of the values of the range pruning fields of a split, keyed by field name.
+pub type FieldRanges = BTreeMap<String, RangeInclusive<JsonNumber>>;
+
+/// Represents a predicate over the ranges of the values of the numeric fields of a split.
+///
+/// If the predicate evaluates to false for the field ranges of a split, we are guaranteed that
+/// no document of the split matches the query.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RangeFilterAst {
+    And(Vec<RangeFilterAst>),
+    Or(Vec<RangeFilterAst>),
+    /// The field has a value within the bounds, which are inclusive, or unbounded if `None`.
+    Range {
+        field: String,
+        lower_opt: Option<JsonNumber>,
+        upper_opt: Option<JsonNumber>,
+    },
+}
+
+impl RangeFilterAst {
+    /// Evaluates the predicate over the field ranges of a split. The fields without a range are
+    /// assumed to have any value.
+    pub fn evaluate(&self, field_ranges: &FieldRanges) -> bool {
+        match self {
+            RangeFilterAst::And(children) => {
+                children.iter().all(|child| child.evaluate(field_ranges))
+            }
+            RangeFilterAst::Or(children) => {
+                children.iter().any(|child| child.evaluate(field_ranges))
+            }
+            RangeFilterAst::Range {
+                field,
+                lower_opt,
+                upper_opt,
+            } => {
+                let Some(field_range) = field_ranges.get(field) else {
+                    return true;
+                };
+                let is_lower_bound_satisfied = lower_opt.as_ref().map_or(true, |lower| {
+                    compare_json_numbers(lower, field_range.end()) != Ordering::Greater
+                });
+                let is_upper_bound_satisfied = upper_opt.as_ref().map_or(true, |upper| {
+                    compare_json_numbers(upper, field_range.start()) != Ordering::Less
+                });
+                is_lower_bound_satisfied && is_upper_bound_satisfied
+            }
+        }
+    }
+}
+
+/// Extracts from a user query a predicate over the field ranges of a split, or returns `None` if
+/// the query does not restrict the values of any field.
+pub fn extract_range_filter_from_query(
+    user_query: &str,
+) -> Result<Option<RangeFilterAst>, QueryParserError> {
+    let (query, _) = extract_automaton_clauses(user_query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&query)
+        .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
+    Ok(collect_range_filters(user_input_ast))
+}
+
+/// Compares two JSON numbers, exactly if they are both integers.
+pub fn compare_json_numbers(left: &JsonNumber, right: &JsonNumber) -> Ordering {
+    let as_i128 = |number: &JsonNumber| {
+        number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from))
+    };
+    if let (Some(left_int), Some(right_int)) = (as_i128(left), as_i128(right)) {
+        return left_int.cmp(&right_int);
+    }
+    // JSON numbers are always finite.
+    let left_float = left.as_f64().unwrap_or_default();
+    let right_float = right.as_f64().unwrap_or_default();
+    left_float.total_cmp(&right_float)
+}
+
+fn parse_json_number(value: &str) -> Option<JsonNumber> {
+    serde_json::from_str(value.trim()).ok()
+}
+
+/// Bounds of a range clause, made inclusive. Returns `None` if a bound is not a number.
+fn parse_bound(bound: &UserInputBound) -> Option<Option<JsonNumber>> {
+    match bound {
+        // The exclusive bounds are relaxed, which is harmless for pruning.
+        UserInputBound::Inclusive(value) | UserInputBound::Exclusive(value) => {
+            parse_json_number(value).map(Some)
+        }
+        UserInputBound::Unbounded => Some(None),
+    }
+}
+
+fn point_filter(field: &str, value: &str) -> Option<RangeFilterAst> {
+    let number = parse_json_number(value)?;
+    Some(RangeFilterAst::Range {
+        field: field.to_string(),
+        lower_opt: Some(number.clone()),
+        upper_opt: Some(number),
+    })
+}
+
+/// Returns the predicate implied by a query, or `None` if the query can match any split, for
+/// instance if it has no clause on a numeric value, or only negated ones.
+fn collect_range_filters(user_input_ast: UserInputAst) -> Option<RangeFilterAst> {
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries) => {
+            let has_must_clause = sub_queries
+                .iter()
+                .any(|(occur_opt, _)| *occur_opt == Some(Occur::Must));
+            if has_must_clause {
+                let mut filters: Vec<RangeFilterAst> = sub_queries
+                    .into_iter()
+                    .filter(|(occur_opt, _)| *occur_opt == Some(Occur::Must))
+                    .filter_map(|(_, ast)| collect_range_filters(ast))
+                    .collect();
+                return match filters.len() {
+                    0 => None,
+                    1 => filters.pop(),
+                    _ => Some(RangeFilterAst::And(filters)),
+                };
+            }
+            let mut filters = Vec::with_capacity(sub_queries.len());
+            for (occur_opt, ast) in sub_queries {
+                // A document matching no positive clause can match a negated one.
+                if occur_opt == Some(Occur::MustNot) {
+                    return None;
+                }
+                filters.push(collect_range_filters(ast)?);
+            }
+            match filters.len() {
+                0 => None,
+                1 => filters.pop(),
+                _ => Some(RangeFilterAst::Or(filters)),
+            }
+        }
+        UserInputAst::Boost(ast, _) => collect_range_filters(*ast),
+        UserInputAst::Leaf(leaf) => match *leaf {
+            UserInputLeaf::Literal(UserInputLiteral {
+                field_name: Some(field_name),
+                phrase,
+                ..
+            }) => point_filter(&field_name, &phrase),
+            UserInputLeaf::Range {
+                field: Some(field),
+                lower,
+                upper,
+            } => Some(RangeFilterAst::Range {
+                field,
+                lower_opt: parse_bound(&lower)?,
+                upper_opt: parse_bound(&upper)?,
+            }),
+            UserInputLeaf::Set {
+                field: Some(field),
+                elements,
+            } => {
+                let filters = elements
+                    .iter()
+                    .map(|element| point_filter(&field, element))
+                    .collect::<Option<Vec<RangeFilterAst>>>()?;
+                Some(RangeFilterAst::Or(filters))
+            }
+            UserInputLeaf::Literal(_)
+            | UserInputLeaf::All
+            | UserInputLeaf::Range { .. }
+            | UserInputLeaf::Set { .. } => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field_ranges(ranges: &[(&str, i64, i64)]) -> FieldRanges {
+        ranges
+            .iter()
+            .map(|(field, start, end)| (field.to_string(), (*start).into()..=(*end).into()))
+            .collect()
+    }
+
+    fn may_match(query: &str, field_ranges: &FieldRanges) -> bool {
+        extract_range_filter_from_query(query)
+            .unwrap()
+            .map_or(true, |range_filter| range_filter.evaluate(field_ranges))
+    }
+
+    #[test]
+    fn test_extract_range_filter_from_query() {
+        assert_eq!(extract_range_filter_from_query("*").unwrap(), None);
+        assert_eq!(extract_range_filter_from_query("body:error").unwrap(), None);
+        assert_eq!(
+            extract_range_filter_from_query("-status:500").unwrap(),
+            None
+        );
+        assert_eq!(
+            extract_range_filter_from_query("status:500 OR body:error").unwrap(),
+            None
+        );
+        assert_eq!(
+            extract_range_filter_from_query("status:[400 TO 500} AND body:error").unwrap(),
+            Some(RangeFilterAst::Range {
+                field: "status".to_string(),
+                lower_opt: Some(400.into()),
+                upper_opt: Some(500.into()),
+            })
+        );
+        assert!(extract_range_filter_from_query(":>").is_err());
+    }
+
+    #[test]
+    fn test_range_filter_evaluate() {
+        let ranges = field_ranges(&[("status", 200, 404), ("latency", -5, 10)]);
+        assert!(may_match("status:200", &ranges));
+        assert!(!may_match("status:500", &ranges));
+        assert!(!may_match("status:>=405", &ranges));
+        assert!(may_match("status:>404", &ranges));
+        // Exclusive bounds are relaxed.
+        assert!(may_match("status:<200", &ranges));
+        assert!(!may_match("status:[0 TO 199]", &ranges));
+        assert!(may_match("status:[0 TO *]", &ranges));
+        assert!(!may_match("status:500 OR status:503", &ranges));
+        assert!(may_match("status:500 OR status:404", &ranges));
+        assert!(!may_match("status:404 AND latency:>10.5", &ranges));
+        assert!(may_match("status:404 AND latency:9.5", &ranges));
+        assert!(!may_match("status: IN [500 503]", &ranges));
+        assert!(may_match("status:500 OR body:error", &ranges));
+        assert!(may_match("other:500", &ranges));
+        assert!(may_match("status:abc", &ranges));
+    }
+
+    #[test]
+    fn test_compare_json_numbers() {
+        let big_u64 = JsonNumber::from(u64::MAX);
+        assert_eq!(
+            compare_json_numbers(&big_u64, &JsonNumber::from(-1)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_json_numbers(&JsonNumber::from(3), &JsonNumber::from_f64(2.5).unwrap()),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_json_numbers(&JsonNumber::from(2), &JsonNumber::from(2)),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,591 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::{bail, Context};
+use tantivy::schema::{Cardinality, FieldType, Schema};
+
+/// Maximum depth of runtime fields referring to other runtime fields.
+const MAX_RUNTIME_FIELD_DEPTH: usize = 16;
+
+/// Binary operators of the runtime expression language, by increasing precedence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    /// `OR`, `||`
+    Or,
+    /// `AND`, `&&`
+    And,
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
+}
+
+impl BinaryOp {
+    fn apply(self, left: f64, right: f64) -> f64 {
+        let from_bool = |value: bool| if value { 1.0 } else { 0.0 };
+        match self {
+            BinaryOp::Or => from_bool(is_truthy(left) || is_truthy(right)),
+            BinaryOp::And => from_bool(is_truthy(left) && is_truthy(right)),
+            BinaryOp::Eq => from_bool(left == right),
+            BinaryOp::Ne => from_bool(left != right),
+            BinaryOp::Lt => from_bool(left < right),
+            BinaryOp::Le => from_bool(left <= right),
+            BinaryOp::Gt => from_bool(left > right),
+            BinaryOp::Ge => from_bool(left >= right),
+            BinaryOp::Add => left + right,
+            BinaryOp::Sub => left - right,
+            BinaryOp::Mul => left * right,
+            BinaryOp::Div => left / right,
+            BinaryOp::Rem => left % right,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+}
+
+/// Returns whether a value evaluated by a runtime expression is considered true: any value
+/// other than `0` and `NaN`.
+pub fn is_truthy(value: f64) -> bool {
+    value != 0.0 && !value.is_nan()
+}
+
+/// An expression computed at query time over the fast fields of a document.
+///
+/// All values are `f64`: booleans are `0` or `1`, and datetimes are milliseconds since the Unix
+/// epoch. Comparisons and logical operators return `0` or `1`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeExpr {
+    /// A numeric literal.
+    Number(f64),
+    /// The value of a fast field.
+    Field(String),
+    /// Arithmetic negation.
+    Neg(Box<RuntimeExpr>),
+    /// Logical negation.
+    Not(Box<RuntimeExpr>),
+    /// A binary operation.
+    Binary(BinaryOp, Box<RuntimeExpr>, Box<RuntimeExpr>),
+}
+
+impl RuntimeExpr {
+    /// Parses an expression, e.g. `end - start` or `duration_ms > 100 AND status != 200`.
+    pub fn parse(expr_str: &str) -> anyhow::Result<RuntimeExpr> {
+        let tokens = tokenize(expr_str)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected token `{token}` in expression `{expr_str}`.");
+        }
+        Ok(expr)
+    }
+
+    /// Returns the names of the fields the expression reads.
+    pub fn field_names(&self) -> BTreeSet<String> {
+        let mut field_names = BTreeSet::new();
+        self.collect_field_names(&mut field_names);
+        field_names
+    }
+
+    fn collect_field_names(&self, field_names: &mut BTreeSet<String>) {
+        match self {
+            RuntimeExpr::Number(_) => {}
+            RuntimeExpr::Field(field_name) => {
+                field_names.insert(field_name.clone());
+            }
+            RuntimeExpr::Neg(expr) | RuntimeExpr::Not(expr) => {
+                expr.collect_field_names(field_names)
+            }
+            RuntimeExpr::Binary(_, left, right) => {
+                left.collect_field_names(field_names);
+                right.collect_field_names(field_names);
+            }
+        }
+    }
+
+    /// Evaluates the expression, reading the field values with `field_value`.
+    pub fn eval(&self, field_value: &mut impl FnMut(&str) -> f64) -> f64 {
+        match self {
+            RuntimeExpr::Number(value) => *value,
+            RuntimeExpr::Field(field_name) => field_value(field_name),
+            RuntimeExpr::Neg(expr) => -expr.eval(field_value),
+            RuntimeExpr::Not(expr) => {
+                if is_truthy(expr.eval(field_value)) {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            RuntimeExpr::Binary(op, left, right) => {
+                let left_value = left.eval(field_value);
+                let right_value = right.eval(field_value);
+                op.apply(left_value, right_value)
+            }
+        }
+    }
+
+    /// Replaces the references to runtime fields by their expressions.
+    fn inline_runtime_fields(
+        self,
+        runtime_fields: &BTreeMap<String, RuntimeExpr>,
+        depth: usize,
+    ) -> anyhow::Result<RuntimeExpr> {
+        if depth > MAX_RUNTIME_FIELD_DEPTH {
+            bail!("Runtime fields refer to each other too deeply, or form a cycle.");
+        }
+        let inlined_expr = match self {
+            RuntimeExpr::Field(field_name) => match runtime_fields.get(&field_name) {
+                Some(runtime_expr) => runtime_expr
+                    .clone()
+                    .inline_runtime_fields(runtime_fields, depth + 1)?,
+                None => RuntimeExpr::Field(field_name),
+            },
+            RuntimeExpr::Number(value) => RuntimeExpr::Number(value),
+            RuntimeExpr::Neg(expr) => {
+                RuntimeExpr::Neg(Box::new(expr.inline_runtime_fields(runtime_fields, depth)?))
+            }
+            RuntimeExpr::Not(expr) => {
+                RuntimeExpr::Not(Box::new(expr.inline_runtime_fields(runtime_fields, depth)?))
+            }
+            RuntimeExpr::Binary(op, left, right) => RuntimeExpr::Binary(
+                op,
+                Box::new(left.inline_runtime_fields(runtime_fields, depth)?),
+                Box::new(right.inline_runtime_fields(runtime_fields, depth)?),
+            ),
+        };
+        Ok(inlined_expr)
+    }
+}
+
+impl fmt::Display for RuntimeExpr {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeExpr::Number(value) => write!(formatter, "{value}"),
+            RuntimeExpr::Field(field_name) => write!(formatter, "{field_name}"),
+            RuntimeExpr::Neg(expr) => write!(formatter, "-({expr})"),
+            RuntimeExpr::Not(expr) => write!(formatter, "NOT ({expr})"),
+            RuntimeExpr::Binary(op, left, right) => {
+                write!(formatter, "({left} {} {right})", op.as_str())
+            }
+        }
+    }
+}
+
+/// The runtime fields of a search request, defined as a JSON object mapping each runtime field
+/// name to its expression, e.g. `{"duration_ms": "end - start"}`.
+///
+/// Runtime fields can refer to other runtime fields: their expressions are inlined so that the
+/// expressions returned by [`RuntimeFields::get`] only read actual fast fields.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuntimeFields {
+    runtime_fields: BTreeMap<String, RuntimeExpr>,
+}
+
+impl RuntimeFields {
+    /// Parses the JSON serialized runtime fields of a search request.
+    pub fn parse(runtime_fields_json_opt: Option<&str>) -> anyhow::Result<RuntimeFields> {
+        let Some(runtime_fields_json) = runtime_fields_json_opt else {
+            return Ok(RuntimeFields::default());
+        };
+        let expr_strs: BTreeMap<String, String> = serde_json::from_str(runtime_fields_json)
+            .context("Runtime fields must be a JSON object mapping names to expressions.")?;
+        let mut raw_runtime_fields = BTreeMap::new();
+        for (field_name, expr_str) in expr_strs {
+            let expr = RuntimeExpr::parse(&expr_str)
+                .with_context(|| format!("Invalid runtime field `{field_name}`."))?;
+            raw_runtime_fields.insert(field_name, expr);
+        }
+        let mut runtime_fields = BTreeMap::new();
+        for (field_name, expr) in &raw_runtime_fields {
+            let inlined_expr = expr
+                .clone()
+                .inline_runtime_fields(&raw_runtime_fields, 0)
+                .with_context(|| format!("Invalid runtime field `{field_name}`."))?;
+            runtime_fields.insert(field_name.clone(), inlined_expr);
+        }
+        Ok(RuntimeFields { runtime_fields })
+    }
+
+    /// Returns the expression of a runtime field.
+    pub fn get(&self, field_name: &str) -> Option<&RuntimeExpr> {
+        self.runtime_fields.get(field_name)
+    }
+
+    /// Returns true if there is no runtime field.
+    pub fn is_empty(&self) -> bool {
+        self.runtime_fields.is_empty()
+    }
+
+    /// Returns the names of the runtime fields.
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.runtime_fields.keys().map(String::as_str)
+    }
+
+    /// Parses a filter expression, which may refer to the runtime fields.
+    pub fn parse_filter(&self, filter_str: &str) -> anyhow::Result<RuntimeExpr> {
+        RuntimeExpr::parse(filter_str)?.inline_runtime_fields(&self.runtime_fields, 0)
+    }
+
+    /// Checks that the runtime fields do not shadow schema fields and only read single-valued
+    /// numeric, datetime, or bool fast fields.
+    pub fn validate(&self, schema: &Schema) -> anyhow::Result<()> {
+        for (field_name, expr) in &self.runtime_fields {
+            if schema.get_field(field_name).is_ok() {
+                bail!("Runtime field `{field_name}` is already a field of the schema.");
+            }
+            validate_runtime_expr(expr, schema)
+                .with_context(|| format!("Invalid runtime field `{field_name}`."))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that an expression only reads single-valued numeric, datetime, or bool fast fields.
+pub fn validate_runtime_expr(expr: &RuntimeExpr, schema: &Schema) -> anyhow::Result<()> {
+    for field_name in expr.field_names() {
+        let field = schema
+            .get_field(&field_name)
+            .with_context(|| format!("Unknown field `{field_name}`."))?;
+        let fast_field_cardinality = match schema.get_field_entry(field).field_type() {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => options.get_fastfield_cardinality(),
+            FieldType::Date(options) => options.get_fastfield_cardinality(),
+            _ => bail!(
+                "Field `{field_name}` must be a numeric, datetime, or bool field to be used in an \
+                 expression."
+            ),
+        };
+        if fast_field_cardinality != Some(Cardinality::SingleValue) {
+            bail!("Field `{field_name}` must be a single-valued fast field to be used in an expression.");
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(&'static str),
+    LeftParen,
+    RightParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(formatter, "{value}"),
+            Token::Ident(ident) => write!(formatter, "{ident}"),
+            Token::Op(op) => write!(formatter, "{op}"),
+            Token::LeftParen => write!(formatter, "("),
+            Token::RightParen => write!(formatter, ")"),
+        }
+    }
+}
+
+/// Operators, longest first so that `<=` is not read as `<`.
+const OPERATORS: [&str; 15] = [
+    "&&", "||", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "=",
+];
+
+fn tokenize(expr_str: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr_str.trim_start();
+    while !rest.is_empty() {
+        let first_char = rest
+            .chars()
+            .next()
+            .expect("The string should not be empty.");
+        if first_char == '(' {
+            tokens.push(Token::LeftParen);
+            rest = &rest[1..];
+        } else if first_char == ')' {
+            tokens.push(Token::RightParen);
+            rest = &rest[1..];
+        } else if first_char.is_ascii_digit() || first_char == '.' {
+            let len = rest
+                .find(|chr: char| !(chr.is_ascii_digit() || chr == '.'))
+                .unwrap_or(rest.len());
+            let value: f64 = rest[..len]
+                .parse()
+                .with_context(|| format!("Invalid number `{}`.", &rest[..len]))?;
+            tokens.push(Token::Number(value));
+            rest = &rest[len..];
+        } else if first_char.is_ascii_alphabetic() || first_char == '_' {
+            let len = rest
+                .find(|chr: char| !(chr.is_ascii_alphanumeric() || chr == '_' || chr == '.'))
+                .unwrap_or(rest.len());
+            let ident = &rest[..len];
+            let token = match ident {
+                "AND" => Token::Op("&&"),
+                "OR" => Token::Op("||"),
+                "NOT" => Token::Op("!"),
+                _ => Token::Ident(ident.to_string()),
+            };
+            tokens.push(token);
+            rest = &rest[len..];
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            if *op == "=" {
+                bail!("Unexpected `=` in expression `{expr_str}`, use `==` for equality.");
+            }
+            tokens.push(Token::Op(*op));
+            rest = &rest[op.len()..];
+        } else {
+            bail!("Unexpected character `{first_char}` in expression `{expr_str}`.");
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_op_in(&mut self, ops: &[(&str, BinaryOp)]) -> Option<BinaryOp> {
+        let Some(Token::Op(token_op)) = self.tokens.get(self.pos) else {
+            return None;
+        };
+        let (_, op) = ops.iter().find(|(op_str, _)| op_str == token_op)?;
+        self.pos += 1;
+        Some(*op)
+    }
+
+    fn parse_binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        parse_operand: fn(&mut Parser) -> anyhow::Result<RuntimeExpr>,
+    ) -> anyhow::Result<RuntimeExpr> {
+        let mut expr = parse_operand(self)?;
+        while let Some(op) = self.next_op_in(ops) {
+            let right = parse_operand(self)?;
+            expr = RuntimeExpr::Binary(op, Box::new(expr), Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<RuntimeExpr> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Parser::parse_and)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<RuntimeExpr> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Parser::parse_not)
+    }
+
+    fn parse_not(&mut self) -> anyhow::Result<RuntimeExpr> {
+        if self.tokens.get(self.pos) == Some(&Token::Op("!")) {
+            self.pos += 1;
+            let expr = self.parse_not()?;
+            return Ok(RuntimeExpr::Not(Box::new(expr)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> anyhow::Result<RuntimeExpr> {
+        let left = self.parse_additive()?;
+        let comparison_ops = [
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<", BinaryOp::Lt),
+            ("<=", BinaryOp::Le),
+            (">", BinaryOp::Gt),
+            (">=", BinaryOp::Ge),
+        ];
+        if let Some(op) = self.next_op_in(&comparison_ops) {
+            let right = self.parse_additive()?;
+            return Ok(RuntimeExpr::Binary(op, Box::new(left), Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> anyhow::Result<RuntimeExpr> {
+        self.parse_binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Parser::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> anyhow::Result<RuntimeExpr> {
+        self.parse_binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Parser::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<RuntimeExpr> {
+        if self.tokens.get(self.pos) == Some(&Token::Op("-")) {
+            self.pos += 1;
+            let expr = self.parse_unary()?;
+            return Ok(RuntimeExpr::Neg(Box::new(expr)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<RuntimeExpr> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            bail!("Unexpected end of expression.");
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(RuntimeExpr::Number(value)),
+            Token::Ident(field_name) => Ok(RuntimeExpr::Field(field_name)),
+            Token::LeftParen => {
+                let expr = self.parse_or()?;
+                if self.tokens.get(self.pos) != Some(&Token::RightParen) {
+                    bail!("Missing closing parenthesis.");
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            token => bail!("Unexpected token `{token}`."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tantivy::schema::{Cardinality, NumericOptions, Schema, FAST, TEXT};
+
+    use super::{RuntimeExpr, RuntimeFields};
+
+    fn eval(expr_str: &str, field_values: &[(&str, f64)]) -> f64 {
+        let field_values: HashMap<&str, f64> = field_values.iter().copied().collect();
+        RuntimeExpr::parse(expr_str)
+            .unwrap()
+            .eval(&mut |field_name| field_values.get(field_name).copied().unwrap_or(f64::NAN))
+    }
+
+    #[test]
+    fn test_runtime_expr_eval() {
+        assert_eq!(eval("end - start", &[("start", 10.0), ("end", 25.0)]), 15.0);
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.0);
+        assert_eq!(eval("-2 * -3", &[]), 6.0);
+        assert_eq!(eval("7 % 4 / 2", &[]), 1.5);
+        assert_eq!(eval("resp.size > 100", &[("resp.size", 150.0)]), 1.0);
+        assert_eq!(eval("a >= 1 AND a <= 2", &[("a", 3.0)]), 0.0);
+        assert_eq!(eval("a < 1 || a == 3", &[("a", 3.0)]), 1.0);
+        assert_eq!(eval("NOT a != 3", &[("a", 3.0)]), 1.0);
+        assert!(eval("missing + 1", &[]).is_nan());
+    }
+
+    #[test]
+    fn test_runtime_expr_parse_errors() {
+        assert!(RuntimeExpr::parse("").is_err());
+        assert!(RuntimeExpr::parse("1 +").is_err());
+        assert!(RuntimeExpr::parse("(1 + 2").is_err());
+        assert!(RuntimeExpr::parse("1 2").is_err());
+        assert!(RuntimeExpr::parse("a = 2").is_err());
+        assert!(RuntimeExpr::parse("a ^ 2").is_err());
+        assert!(RuntimeExpr::parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_runtime_fields_parse() {
+        let runtime_fields = RuntimeFields::parse(Some(
+            r#"{"duration_ms": "end - start", "slow": "duration_ms > 100"}"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            runtime_fields.get("slow").unwrap().to_string(),
+            "((end - start) > 100)"
+        );
+        assert_eq!(
+            runtime_fields
+                .parse_filter("slow AND duration_ms < 500")
+                .unwrap()
+                .to_string(),
+            "(((end - start) > 100) AND ((end - start) < 500))"
+        );
+        assert!(RuntimeFields::parse(None).unwrap().is_empty());
+        assert!(RuntimeFields::parse(Some(r#"{"a": "b + 1", "b": "a + 1"}"#)).is_err());
+        assert!(RuntimeFields::parse(Some(r#"["a"]"#)).is_err());
+    }
+
+    #[test]
+    fn test_runtime_fields_validate() {
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_u64_field("start", FAST);
+        schema_builder.add_u64_field("end", FAST);
+        schema_builder.add_u64_field(
+            "retries",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        schema_builder.add_text_field("body", TEXT);
+        let schema = schema_builder.build();
+
+        let validate = |runtime_fields_json: &str| {
+            RuntimeFields::parse(Some(runtime_fields_json))
+                .unwrap()
+                .validate(&schema)
+        };
+        validate(r#"{"duration": "end - start"}"#).unwrap();
+        assert!(validate(r#"{"start": "end - 1"}"#).is_err());
+        assert!(validate(r#"{"duration": "stop - start"}"#).is_err());
+        assert!(validate(r#"{"total": "retries + 1"}"#).is_err());
+        assert!(validate(r#"{"length": "body + 1"}"#).is_err());
+    }
+}
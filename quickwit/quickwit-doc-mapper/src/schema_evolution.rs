@@ -0,0 +1,409 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Write;
+
+use quickwit_proto::SearchRequest;
+use tantivy::schema::Schema;
+use tantivy_query_grammar::{Occur, UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
+
+use crate::term_automaton::{extract_automaton_clauses, restore_automaton_clauses};
+
+/// Characters that must be escaped in the field names of a query.
+const FIELD_NAME_SPECIAL_CHARS: &[char] = &[
+    '+', '^', '`', ':', '{', '}', '"', '[', ']', '(', ')', '~', '!', '\\', '*', ' ',
+];
+
+/// Returns true if the split lacks some of the fields of the index schema, which happens when
+/// the split was indexed with an older version of the doc mapping.
+pub(crate) fn split_lacks_fields(index_schema: &Schema, split_schema: &Schema) -> bool {
+    index_schema
+        .fields()
+        .any(|(_, field_entry)| split_schema.get_field(field_entry.name()).is_err())
+}
+
+/// Adapts a search request targeting the current doc mapping to the schema of a split indexed
+/// with an older version of the doc mapping: the clauses on the fields the split does not have
+/// match no documents, and these fields are removed from the search, snippet, and sort fields.
+///
+/// Returns the adapted request and default search fields, or `None` if the request cannot match
+/// any document of the split. `default_occur` is the occurrence of the clauses that are not
+/// joined by an explicit operator.
+pub(crate) fn adapt_request_to_split_schema(
+    index_schema: &Schema,
+    split_schema: &Schema,
+    request: &SearchRequest,
+    default_field_names: &[String],
+    default_occur: Occur,
+) -> Option<(SearchRequest, Vec<String>)> {
+    let is_missing_field = |field_name: &str| {
+        split_schema.find_field(field_name).is_none()
+            && index_schema.find_field(field_name).is_some()
+    };
+    let mut split_request = request.clone();
+    split_request
+        .search_fields
+        .retain(|field_name| !is_missing_field(field_name));
+    split_request
+        .snippet_fields
+        .retain(|field_name| !is_missing_field(field_name));
+    if split_request
+        .sort_by_field
+        .as_deref()
+        .map_or(false, is_missing_field)
+    {
+        split_request.sort_by_field = None;
+    }
+    let split_default_field_names: Vec<String> = default_field_names
+        .iter()
+        .filter(|field_name| !is_missing_field(field_name))
+        .cloned()
+        .collect();
+    let unfielded_clauses_match_nothing = if request.search_fields.is_empty() {
+        !default_field_names.is_empty() && split_default_field_names.is_empty()
+    } else {
+        split_request.search_fields.is_empty()
+    };
+    // Syntax errors are reported when the query is built.
+    let Ok((query, automaton_clauses)) = extract_automaton_clauses(&request.query) else {
+        return Some((split_request, split_default_field_names));
+    };
+    let Ok(user_input_ast) = tantivy_query_grammar::parse_query(&query) else {
+        return Some((split_request, split_default_field_names));
+    };
+    let pruned_ast = prune_missing_field_clauses(
+        user_input_ast,
+        &is_missing_field,
+        unfielded_clauses_match_nothing,
+        default_occur,
+    )?;
+    split_request.query =
+        restore_automaton_clauses(&user_input_ast_to_query(&pruned_ast), &automaton_clauses);
+    Some((split_request, split_default_field_names))
+}
+
+/// Removes the clauses matching no documents from the query. Returns `None` if the whole query
+/// matches no documents.
+fn prune_missing_field_clauses(
+    user_input_ast: UserInputAst,
+    is_missing_field: &impl Fn(&str) -> bool,
+    unfielded_clauses_match_nothing: bool,
+    default_occur: Occur,
+) -> Option<UserInputAst> {
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries) => {
+            let mut pruned_sub_queries = Vec::with_capacity(sub_queries.len());
+            let mut pruned_should_clause = false;
+
+            for (occur_opt, sub_ast) in sub_queries {
+                match prune_missing_field_clauses(
+                    sub_ast,
+                    is_missing_field,
+                    unfielded_clauses_match_nothing,
+                    default_occur,
+                ) {
+                    Some(pruned_sub_ast) => pruned_sub_queries.push((occur_opt, pruned_sub_ast)),
+                    None => match occur_opt.unwrap_or(default_occur) {
+                        // Excluding the documents matching nothing is a no-op.
+                        Occur::MustNot => {}
+                        Occur::Should => pruned_should_clause = true,
+                        Occur::Must => return None,
+                    },
+                }
+            }
+            if pruned_sub_queries.is_empty() {
+                if pruned_should_clause {
+                    return None;
+                }
+                return Some(UserInputAst::Leaf(Box::new(UserInputLeaf::All)));
+            }
+            let has_positive_clause = pruned_sub_queries
+                .iter()
+                .any(|(occur_opt, _)| *occur_opt != Some(Occur::MustNot));
+            if pruned_should_clause && !has_positive_clause {
+                return None;
+            }
+            Some(UserInputAst::Clause(pruned_sub_queries))
+        }
+        UserInputAst::Boost(ast, boost) => prune_missing_field_clauses(
+            *ast,
+            is_missing_field,
+            unfielded_clauses_match_nothing,
+            default_occur,
+        )
+        .map(|pruned_ast| UserInputAst::Boost(Box::new(pruned_ast), boost)),
+        UserInputAst::Leaf(leaf) => {
+            let field_name_opt = match leaf.as_ref() {
+                UserInputLeaf::Literal(UserInputLiteral { field_name, .. }) => {
+                    field_name.as_deref()
+                }
+                UserInputLeaf::Range { field, .. } | UserInputLeaf::Set { field, .. } => {
+                    field.as_deref()
+                }
+                UserInputLeaf::All => return Some(UserInputAst::Leaf(leaf)),
+            };
+            let matches_nothing = match field_name_opt {
+                Some(field_name) => is_missing_field(field_name),
+                None => unfielded_clauses_match_nothing,
+            };
+            if matches_nothing {
+                return None;
+            }
+            Some(UserInputAst::Leaf(leaf))
+        }
+    }
+}
+
+/// Serializes a query AST back into a query string.
+pub(crate) fn user_input_ast_to_query(user_input_ast: &UserInputAst) -> String {
+    let mut query = String::new();
+    write_user_input_ast(user_input_ast, &mut query);
+    query
+}
+
+fn write_user_input_ast(user_input_ast: &UserInputAst, query: &mut String) {
+    match user_input_ast {
+        UserInputAst::Clause(sub_queries)
+            if sub_queries.len() == 1 && sub_queries[0].0 != Some(Occur::MustNot) =>
+        {
+            write_user_input_ast(&sub_queries[0].1, query);
+        }
+        UserInputAst::Clause(sub_queries) => {
+            let is_disjunction = sub_queries
+                .iter()
+                .all(|(occur_opt, _)| *occur_opt == Some(Occur::Should));
+            query.push('(');
+            for (ord, (occur_opt, sub_ast)) in sub_queries.iter().enumerate() {
+                if ord > 0 {
+                    query.push_str(if is_disjunction { " OR " } else { " " });
+                }
+                match occur_opt {
+                    Some(Occur::Must) => query.push('+'),
+                    Some(Occur::MustNot) => query.push('-'),
+                    Some(Occur::Should) | None => {}
+                }
+                write_user_input_ast(sub_ast, query);
+            }
+            query.push(')');
+        }
+        UserInputAst::Boost(ast, boost) => {
+            query.push('(');
+            write_user_input_ast(ast, query);
+            let _ = write!(query, ")^{boost}");
+        }
+        UserInputAst::Leaf(leaf) => write_user_input_leaf(leaf, query),
+    }
+}
+
+fn write_user_input_leaf(leaf: &UserInputLeaf, query: &mut String) {
+    match leaf {
+        UserInputLeaf::Literal(UserInputLiteral {
+            field_name,
+            phrase,
+            slop,
+        }) => {
+            write_field_name(field_name.as_deref(), query);
+            let _ = write!(query, "\"{phrase}\"");
+            if *slop > 0 {
+                let _ = write!(query, "~{slop}");
+            }
+        }
+        UserInputLeaf::All => query.push('*'),
+        UserInputLeaf::Range {
+            field,
+            lower,
+            upper,
+        } => {
+            write_field_name(field.as_deref(), query);
+            match lower {
+                UserInputBound::Inclusive(value) => {
+                    let _ = write!(query, "[{value}");
+                }
+                UserInputBound::Exclusive(value) => {
+                    let _ = write!(query, "{{{value}");
+                }
+                UserInputBound::Unbounded => query.push_str("[*"),
+            }
+            query.push_str(" TO ");
+            match upper {
+                UserInputBound::Inclusive(value) => {
+                    let _ = write!(query, "{value}]");
+                }
+                UserInputBound::Exclusive(value) => {
+                    let _ = write!(query, "{value}}}");
+                }
+                UserInputBound::Unbounded => query.push_str("*]"),
+            }
+        }
+        UserInputLeaf::Set { field, elements } => {
+            write_field_name(field.as_deref(), query);
+            let _ = write!(query, " IN [{}]", elements.join(" "));
+        }
+    }
+}
+
+fn write_field_name(field_name_opt: Option<&str>, query: &mut String) {
+    let Some(field_name) = field_name_opt else {
+        return;
+    };
+    for ch in field_name.chars() {
+        if FIELD_NAME_SPECIAL_CHARS.contains(&ch) {
+            query.push('\\');
+        }
+        query.push(ch);
+    }
+    query.push(':');
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{Schema, FAST, STRING, TEXT};
+
+    use super::*;
+
+    fn index_and_split_schemas() -> (Schema, Schema) {
+        let mut split_schema_builder = Schema::builder();
+        split_schema_builder.add_text_field("body", TEXT);
+        split_schema_builder.add_text_field("severity", STRING);
+        split_schema_builder.add_i64_field("status", FAST);
+        let split_schema = split_schema_builder.build();
+
+        let mut index_schema_builder = Schema::builder();
+        index_schema_builder.add_text_field("body", TEXT);
+        index_schema_builder.add_text_field("severity", STRING);
+        index_schema_builder.add_i64_field("status", FAST);
+        index_schema_builder.add_text_field("service", STRING);
+        index_schema_builder.add_i64_field("latency", FAST);
+        let index_schema = index_schema_builder.build();
+
+        (index_schema, split_schema)
+    }
+
+    /// Compares the queries once parsed, since the adapted query is not formatted like the
+    /// original one.
+    #[track_caller]
+    fn test_adapt_query_aux(query: &str, expected_query_opt: Option<&str>) {
+        test_adapt_query_with_occur_aux(query, Occur::Must, expected_query_opt);
+    }
+
+    #[track_caller]
+    fn test_adapt_query_with_occur_aux(
+        query: &str,
+        default_occur: Occur,
+        expected_query_opt: Option<&str>,
+    ) {
+        let (index_schema, split_schema) = index_and_split_schemas();
+        let request = SearchRequest {
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let default_field_names = vec!["body".to_string()];
+        let split_query_opt = adapt_request_to_split_schema(
+            &index_schema,
+            &split_schema,
+            &request,
+            &default_field_names,
+            default_occur,
+        )
+        .map(|(split_request, _)| split_request.query);
+        let parse_query = |query: &str| {
+            let user_input_ast = tantivy_query_grammar::parse_query(query).unwrap();
+            format!("{user_input_ast:?}")
+        };
+        assert_eq!(
+            split_query_opt.as_deref().map(parse_query),
+            expected_query_opt.map(parse_query)
+        );
+    }
+
+    #[test]
+    fn test_split_lacks_fields() {
+        let (index_schema, split_schema) = index_and_split_schemas();
+        assert!(split_lacks_fields(&index_schema, &split_schema));
+        assert!(!split_lacks_fields(&split_schema, &index_schema));
+        assert!(!split_lacks_fields(&index_schema, &index_schema));
+    }
+
+    #[test]
+    fn test_adapt_query_to_split_schema() {
+        test_adapt_query_aux("service:foo", None);
+        test_adapt_query_aux("severity:ERROR AND service:foo", None);
+        test_adapt_query_aux("latency:[100 TO *]", None);
+        test_adapt_query_aux("severity:ERROR", Some("severity:ERROR"));
+        test_adapt_query_aux("severity:ERROR OR service:foo", Some("severity:ERROR"));
+        test_adapt_query_aux("severity:ERROR -service:foo", Some("severity:ERROR"));
+        test_adapt_query_aux(
+            "+hello +status:[200 TO 300] -service:foo",
+            Some("+hello +status:[200 TO 300]"),
+        );
+        test_adapt_query_aux("-service:foo", Some("*"));
+        test_adapt_query_aux("service:foo OR latency:10", None);
+        test_adapt_query_aux("unknown:foo", Some("unknown:foo"));
+    }
+
+    #[test]
+    fn test_adapt_query_to_split_schema_with_or_operator() {
+        test_adapt_query_with_occur_aux("service:foo", Occur::Should, None);
+        test_adapt_query_with_occur_aux(
+            "severity:ERROR service:foo",
+            Occur::Should,
+            Some("severity:ERROR"),
+        );
+        test_adapt_query_with_occur_aux("severity:ERROR service:foo", Occur::Must, None);
+        test_adapt_query_with_occur_aux("service:foo latency:10", Occur::Should, None);
+    }
+
+    #[test]
+    fn test_adapt_request_to_split_schema() {
+        let (index_schema, split_schema) = index_and_split_schemas();
+        let request = SearchRequest {
+            query: "hello".to_string(),
+            search_fields: vec!["body".to_string(), "service".to_string()],
+            snippet_fields: vec!["body".to_string()],
+            sort_by_field: Some("latency".to_string()),
+            ..Default::default()
+        };
+        let (split_request, split_default_field_names) = adapt_request_to_split_schema(
+            &index_schema,
+            &split_schema,
+            &request,
+            &["body".to_string(), "service".to_string()],
+            Occur::Must,
+        )
+        .unwrap();
+        assert_eq!(split_request.search_fields, ["body"]);
+        assert_eq!(split_request.snippet_fields, ["body"]);
+        assert!(split_request.sort_by_field.is_none());
+        assert_eq!(split_default_field_names, ["body"]);
+
+        let request = SearchRequest {
+            query: "hello".to_string(),
+            search_fields: vec!["service".to_string()],
+            ..Default::default()
+        };
+        assert!(adapt_request_to_split_schema(
+            &index_schema,
+            &split_schema,
+            &request,
+            &["body".to_string()],
+            Occur::Must,
+        )
+        .is_none());
+    }
+}
@@ -24,6 +24,7 @@ use serde::{Deserialize, Serialize};
 use tantivy::query::QueryParserError as TantivyQueryParserError;
 use tantivy_query_grammar::{Occur, UserInputAst, UserInputLeaf, UserInputLiteral};
 
+use crate::term_automaton::{automaton_clause_ord, extract_automaton_clauses};
 use crate::QueryParserError;
 
 fn user_input_ast_to_tags_filter_ast(user_input_ast: UserInputAst) -> Option<TagFilterAst> {
@@ -46,7 +47,8 @@ pub fn match_tag_field_name(field_name: &str, tag: &str) -> bool {
 /// associated with a split, we are guaranteed that no documents
 /// in the split matches the query.
 pub fn extract_tags_from_query(user_query: &str) -> Result<Option<TagFilterAst>, QueryParserError> {
-    let user_input_ast = tantivy_query_grammar::parse_query(user_query)
+    let (query, _) = extract_automaton_clauses(user_query)?;
+    let user_input_ast = tantivy_query_grammar::parse_query(&query)
         .map_err(|_| TantivyQueryParserError::SyntaxError(user_query.to_string()))?;
     Ok(user_input_ast_to_tags_filter_ast(user_input_ast))
 }
@@ -323,6 +325,12 @@ fn collect_tag_filters(user_input_ast: UserInputAst) -> UnsimplifiedTagFilterAst
         }
         UserInputAst::Boost(ast, _) => collect_tag_filters(*ast),
         UserInputAst::Leaf(leaf) => match *leaf {
+            // Automaton clauses can match several values of the tag field.
+            UserInputLeaf::Literal(UserInputLiteral { phrase, .. })
+                if automaton_clause_ord(&phrase).is_some() =>
+            {
+                UnsimplifiedTagFilterAst::Uninformative
+            }
             UserInputLeaf::Literal(UserInputLiteral {
                 field_name: Some(field_name),
                 phrase,
@@ -390,6 +398,18 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_extract_tags_from_query_fuzzy_query() -> anyhow::Result<()> {
+        assert_eq!(extract_tags_from_query("lang:fr~1")?, None);
+        assert_eq!(
+            &extract_tags_from_query("lang:fr~1 AND user:bart")?
+                .unwrap()
+                .to_string(),
+            "(¬user! ∨ user:bart)"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_extract_tags_from_query_mixed_disjunction() -> anyhow::Result<()> {
         assert_eq!(
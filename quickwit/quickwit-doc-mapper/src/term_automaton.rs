@@ -0,0 +1,712 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::borrow::Cow;
+use std::ops::Bound;
+use std::{fmt, iter};
+
+use anyhow::{anyhow, bail, Context};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex, RegexBuilder};
+use tantivy::query::{FuzzyTermQuery, Query, RangeQuery, RegexQuery};
+use tantivy::schema::{Field, FieldType, Schema, Type};
+use tantivy::tokenizer::{TextAnalyzer, TokenizerManager};
+use tantivy::Term;
+
+use crate::default_doc_mapper::BinaryFormat;
+
+/// Maximum edit distance of the fuzzy clauses.
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// Maximum length of the patterns of the regex clauses.
+const MAX_REGEX_PATTERN_LEN: usize = 1_000;
+
+/// Maximum size of the compiled regexes, which bounds the complexity of the patterns.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Prefix of the words substituted for the automaton clauses, which the query grammar does not
+/// support.
+const AUTOMATON_CLAUSE_PLACEHOLDER_PREFIX: &str = "__automaton_clause_";
+
+/// Matches `field:/pattern/`, `field:term~distance`, and `field:wild*card` clauses.
+static AUTOMATON_CLAUSE_PTN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?P<prefix>^|[\s(+\-])(?P<field>(?:[^\s:()"\\+\-]|\\.)(?:[^\s:()"\\]|\\.)*):(?:/(?P<pattern>(?:[^/\\]|\\.)+)/|(?P<text>[^\s:()"~^\\\[\]{}]+)~(?P<distance>[0-9]*)|(?P<wildcard>[^\s:()"~^\\\[\]{}/]*[*?][^\s:()"~^\\\[\]{}/]*))"#,
+    )
+    .unwrap()
+});
+
+/// Automaton matching the terms of a field.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TermAutomaton {
+    /// Matches the terms within `distance` edits (insertions, deletions, substitutions, and
+    /// transpositions of adjacent characters) of `text`.
+    Fuzzy {
+        /// Text of the term.
+        text: String,
+        /// Maximum edit distance.
+        distance: u8,
+    },
+    /// Matches the terms matching the regular expression `pattern` as a whole.
+    Regex {
+        /// Regular expression.
+        pattern: String,
+    },
+    /// Matches the terms matching `pattern`, in which `*` stands for any sequence of characters
+    /// and `?` for any single character.
+    Wildcard {
+        /// Wildcard pattern.
+        pattern: String,
+    },
+    /// Matches the terms of a bytes field starting with `prefix`.
+    BytesPrefix {
+        /// Prefix of the terms.
+        prefix: Vec<u8>,
+        /// Prefix pattern as written in the query, e.g. `deadbe*`.
+        pattern: String,
+    },
+}
+
+impl TermAutomaton {
+    /// Returns a function telling whether the automaton accepts a term.
+    /// The terms of text fields that are not valid UTF-8 are never accepted.
+    pub fn matcher(&self) -> anyhow::Result<Box<dyn Fn(&[u8]) -> bool + '_>> {
+        match self {
+            TermAutomaton::Fuzzy { text, distance } => Ok(text_matcher(move |term| {
+                is_within_edit_distance(text, term, *distance as usize)
+            })),
+            TermAutomaton::Regex { pattern } => {
+                let regex = build_term_regex(pattern)?;
+                Ok(text_matcher(move |term| regex.is_match(term)))
+            }
+            TermAutomaton::Wildcard { pattern } => {
+                let regex = build_term_regex(&wildcard_to_regex(pattern))?;
+                Ok(text_matcher(move |term| regex.is_match(term)))
+            }
+            TermAutomaton::BytesPrefix { prefix, .. } => {
+                Ok(Box::new(move |term: &[u8]| term.starts_with(prefix)))
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            TermAutomaton::Fuzzy { .. } => "Fuzzy",
+            TermAutomaton::Regex { .. } => "Regex",
+            TermAutomaton::Wildcard { .. } => "Wildcard",
+            TermAutomaton::BytesPrefix { .. } => "Prefix",
+        }
+    }
+
+    pub(crate) fn query(&self, field: Field, field_name: &str) -> anyhow::Result<Box<dyn Query>> {
+        match self {
+            TermAutomaton::Fuzzy { text, distance } => {
+                let term = Term::from_field_text(field, text);
+                Ok(Box::new(FuzzyTermQuery::new(term, *distance, true)))
+            }
+            TermAutomaton::Regex { pattern } => {
+                Ok(Box::new(RegexQuery::from_pattern(pattern, field)?))
+            }
+            TermAutomaton::Wildcard { pattern } => Ok(Box::new(RegexQuery::from_pattern(
+                &wildcard_to_regex(pattern),
+                field,
+            )?)),
+            TermAutomaton::BytesPrefix { prefix, .. } => {
+                let lower_bound = Bound::Included(Term::from_field_bytes(field, prefix));
+                let upper_bound = match prefix_successor(prefix) {
+                    Some(successor) => Bound::Excluded(Term::from_field_bytes(field, &successor)),
+                    None => Bound::Unbounded,
+                };
+                Ok(Box::new(RangeQuery::new_term_bounds(
+                    field_name.to_string(),
+                    Type::Bytes,
+                    &lower_bound,
+                    &upper_bound,
+                )))
+            }
+        }
+    }
+}
+
+impl fmt::Display for TermAutomaton {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TermAutomaton::Fuzzy { text, distance } => write!(formatter, "{text}~{distance}"),
+            TermAutomaton::Regex { pattern } => write!(formatter, "/{pattern}/"),
+            TermAutomaton::Wildcard { pattern } | TermAutomaton::BytesPrefix { pattern, .. } => {
+                write!(formatter, "{pattern}")
+            }
+        }
+    }
+}
+
+/// A fuzzy, regex, or wildcard clause of a query, e.g. `name:jonh~1`, `body:/.*base64_decode.*/`,
+/// or `body:*timeout*`, which the query grammar does not support. These clauses are substituted
+/// with placeholder words before parsing the query.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct AutomatonClause {
+    /// Text of the clause following the field name, e.g. `jonh~1`, `/.*base64_decode.*/`, or
+    /// `*timeout*`.
+    pub source: String,
+    pub automaton: TermAutomaton,
+}
+
+impl AutomatonClause {
+    /// Resolves the automaton of the clause against the targeted field, normalizing the fuzzy
+    /// text and the literal parts of the wildcard pattern with the tokenizer of the field.
+    /// Wildcard clauses targeting a bytes field must be prefix clauses, whose prefix is decoded
+    /// with `bytes_input_format`.
+    pub fn resolve(
+        &self,
+        schema: &Schema,
+        field_name: &str,
+        tokenizer_manager: &TokenizerManager,
+        bytes_input_format: BinaryFormat,
+    ) -> anyhow::Result<(Field, TermAutomaton)> {
+        let field = schema
+            .get_field(field_name)
+            .with_context(|| format!("Unknown field `{field_name}`."))?;
+        let field_entry = schema.get_field_entry(field);
+
+        if let FieldType::Bytes(_) = field_entry.field_type() {
+            if !field_entry.is_indexed() {
+                bail!("Field `{field_name}` is not indexed.");
+            }
+            let automaton = self.resolve_bytes_prefix(field_name, bytes_input_format)?;
+            return Ok((field, automaton));
+        }
+        let FieldType::Str(text_options) = field_entry.field_type() else {
+            bail!(
+                "Field `{field_name}` is not a text field. {} queries are only supported on text \
+                 fields.",
+                self.automaton.kind()
+            );
+        };
+        let indexing_options = text_options
+            .get_indexing_options()
+            .with_context(|| format!("Field `{field_name}` is not indexed."))?;
+        let tokenizer_name = indexing_options.tokenizer();
+        let text_analyzer = tokenizer_manager
+            .get(tokenizer_name)
+            .with_context(|| format!("Unknown tokenizer `{tokenizer_name}`."))?;
+        let automaton = match &self.automaton {
+            TermAutomaton::Fuzzy { text, distance } => TermAutomaton::Fuzzy {
+                text: self.normalize_term(&text_analyzer, field_name, text)?,
+                distance: *distance,
+            },
+            // Regexes are matched against the terms as indexed.
+            TermAutomaton::Regex { .. } => self.automaton.clone(),
+            TermAutomaton::Wildcard { pattern } => {
+                let mut normalized_pattern = String::with_capacity(pattern.len());
+                let mut literal_start = 0;
+
+                for (wildcard_pos, wildcard) in pattern
+                    .match_indices(['*', '?'])
+                    .chain(iter::once((pattern.len(), "")))
+                {
+                    let literal = &pattern[literal_start..wildcard_pos];
+                    if !literal.is_empty() {
+                        normalized_pattern.push_str(&self.normalize_term(
+                            &text_analyzer,
+                            field_name,
+                            literal,
+                        )?);
+                    }
+                    normalized_pattern.push_str(wildcard);
+                    literal_start = wildcard_pos + wildcard.len();
+                }
+                TermAutomaton::Wildcard {
+                    pattern: normalized_pattern,
+                }
+            }
+        };
+        Ok((field, automaton))
+    }
+
+    fn resolve_bytes_prefix(
+        &self,
+        field_name: &str,
+        bytes_input_format: BinaryFormat,
+    ) -> anyhow::Result<TermAutomaton> {
+        let prefix_str_opt = match &self.automaton {
+            TermAutomaton::Wildcard { pattern } => pattern
+                .strip_suffix('*')
+                .filter(|prefix_str| !prefix_str.contains(['*', '?'])),
+            _ => None,
+        };
+        let Some(prefix_str) = prefix_str_opt else {
+            bail!(
+                "Field `{field_name}` is a bytes field. {} queries are not supported on bytes \
+                 fields, only exact and prefix queries, e.g. `{field_name}:cafe*`.",
+                self.automaton.kind()
+            );
+        };
+        let prefix = bytes_input_format.parse_str(prefix_str).map_err(|error| {
+            anyhow!(
+                "Invalid prefix clause `{field_name}:{}`: {error}",
+                self.source
+            )
+        })?;
+        Ok(TermAutomaton::BytesPrefix {
+            prefix,
+            pattern: self.source.clone(),
+        })
+    }
+
+    /// Normalizes `text` with the tokenizer of the targeted field, which must not split it into
+    /// several terms.
+    fn normalize_term(
+        &self,
+        text_analyzer: &TextAnalyzer,
+        field_name: &str,
+        text: &str,
+    ) -> anyhow::Result<String> {
+        let mut tokens = Vec::new();
+        text_analyzer
+            .token_stream(text)
+            .process(&mut |token| tokens.push(token.text.clone()));
+        let [normalized_text] = &tokens[..] else {
+            bail!(
+                "{} clause `{field_name}:{}` must target a single term, but `{text}` is tokenized \
+                 into {} terms.",
+                self.automaton.kind(),
+                self.source,
+                tokens.len()
+            );
+        };
+        Ok(normalized_text.clone())
+    }
+}
+
+/// Returns the ordinal of the automaton clause a word of the query stands for.
+pub(crate) fn automaton_clause_ord(word: &str) -> Option<usize> {
+    word.strip_prefix(AUTOMATON_CLAUSE_PLACEHOLDER_PREFIX)?
+        .parse()
+        .ok()
+}
+
+fn automaton_clause_placeholder(ord: usize) -> String {
+    format!("{AUTOMATON_CLAUSE_PLACEHOLDER_PREFIX}{ord}")
+}
+
+/// Substitutes the automaton clauses of a query with placeholder words the query grammar can
+/// parse, and returns them ordered by placeholder ordinal. Phrases between double quotes are left
+/// untouched.
+pub(crate) fn extract_automaton_clauses(
+    query: &str,
+) -> anyhow::Result<(Cow<str>, Vec<AutomatonClause>)> {
+    if !query.contains(['~', '/', '*', '?']) {
+        return Ok((Cow::Borrowed(query), Vec::new()));
+    }
+    let mut rewritten_query = String::with_capacity(query.len());
+    let mut automaton_clauses = Vec::new();
+    let mut segment_start = 0;
+    let mut in_phrase = false;
+    let mut escaped = false;
+
+    for (pos, ch) in query.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' => escaped = true,
+            '"' if in_phrase => {
+                rewritten_query.push_str(&query[segment_start..=pos]);
+                segment_start = pos + 1;
+                in_phrase = false;
+            }
+            '"' => {
+                extract_segment_automaton_clauses(
+                    &query[segment_start..pos],
+                    &mut rewritten_query,
+                    &mut automaton_clauses,
+                )?;
+                segment_start = pos;
+                in_phrase = true;
+            }
+            _ => {}
+        }
+    }
+    if in_phrase {
+        rewritten_query.push_str(&query[segment_start..]);
+    } else {
+        extract_segment_automaton_clauses(
+            &query[segment_start..],
+            &mut rewritten_query,
+            &mut automaton_clauses,
+        )?;
+    }
+    if automaton_clauses.is_empty() {
+        return Ok((Cow::Borrowed(query), Vec::new()));
+    }
+    Ok((Cow::Owned(rewritten_query), automaton_clauses))
+}
+
+fn extract_segment_automaton_clauses(
+    segment: &str,
+    rewritten_query: &mut String,
+    automaton_clauses: &mut Vec<AutomatonClause>,
+) -> anyhow::Result<()> {
+    let mut last_match_end = 0;
+    for captures in AUTOMATON_CLAUSE_PTN.captures_iter(segment) {
+        let clause_match = captures
+            .get(0)
+            .expect("The whole match should always be captured.");
+        // The clause must be followed by a separator, otherwise the `~` or `/` is part of the
+        // term.
+        if !segment[clause_match.end()..]
+            .chars()
+            .next()
+            .map_or(true, |ch| ch.is_whitespace() || ch == ')' || ch == '^')
+        {
+            continue;
+        }
+        let automaton_clause = if captures.name("pattern").is_some() {
+            parse_regex_clause(&captures)?
+        } else if let Some(wildcard_match) = captures.name("wildcard") {
+            // `field:*` matches all the documents, and is supported by the query grammar.
+            if wildcard_match.as_str().chars().all(|ch| ch == '*') {
+                continue;
+            }
+            AutomatonClause {
+                source: wildcard_match.as_str().to_string(),
+                automaton: TermAutomaton::Wildcard {
+                    pattern: wildcard_match.as_str().to_string(),
+                },
+            }
+        } else {
+            parse_fuzzy_clause(&captures)?
+        };
+        rewritten_query.push_str(&segment[last_match_end..clause_match.start()]);
+        rewritten_query.push_str(&format!(
+            "{}{}:{}",
+            &captures["prefix"],
+            &captures["field"],
+            automaton_clause_placeholder(automaton_clauses.len())
+        ));
+        automaton_clauses.push(automaton_clause);
+        last_match_end = clause_match.end();
+    }
+    rewritten_query.push_str(&segment[last_match_end..]);
+    Ok(())
+}
+
+fn parse_fuzzy_clause(captures: &Captures) -> anyhow::Result<AutomatonClause> {
+    let text = &captures["text"];
+    let distance_str = &captures["distance"];
+    let distance = if distance_str.is_empty() {
+        MAX_FUZZY_DISTANCE
+    } else {
+        distance_str
+            .parse::<u8>()
+            .ok()
+            .filter(|distance| *distance <= MAX_FUZZY_DISTANCE)
+            .with_context(|| {
+                format!(
+                    "Invalid fuzzy clause `{}:{text}~{distance_str}`: the edit distance must be \
+                     0, 1, or 2.",
+                    &captures["field"]
+                )
+            })?
+    };
+    Ok(AutomatonClause {
+        source: format!("{text}~{distance_str}"),
+        automaton: TermAutomaton::Fuzzy {
+            text: text.to_string(),
+            distance,
+        },
+    })
+}
+
+fn parse_regex_clause(captures: &Captures) -> anyhow::Result<AutomatonClause> {
+    let escaped_pattern = &captures["pattern"];
+    // Slashes and double quotes are escaped in the query, but they are not special characters of
+    // regexes.
+    let pattern = escaped_pattern.replace("\\/", "/").replace("\\\"", "\"");
+    build_term_regex(&pattern).map_err(|error| {
+        anyhow!(
+            "Invalid regex clause `{}:/{escaped_pattern}/`: {error}",
+            &captures["field"]
+        )
+    })?;
+    Ok(AutomatonClause {
+        source: format!("/{escaped_pattern}/"),
+        automaton: TermAutomaton::Regex { pattern },
+    })
+}
+
+/// Builds a regex matching the terms matching `pattern` as a whole, rejecting the patterns that
+/// are too complex.
+fn build_term_regex(pattern: &str) -> anyhow::Result<Regex> {
+    if pattern.len() > MAX_REGEX_PATTERN_LEN {
+        bail!("the pattern must not be longer than {MAX_REGEX_PATTERN_LEN} characters");
+    }
+    let regex = RegexBuilder::new(&format!("^(?:{pattern})$"))
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()?;
+    Ok(regex)
+}
+
+/// Wraps a matcher of text terms into a matcher of raw terms rejecting the terms that are not
+/// valid UTF-8.
+fn text_matcher<'a>(matches: impl Fn(&str) -> bool + 'a) -> Box<dyn Fn(&[u8]) -> bool + 'a> {
+    Box::new(move |term: &[u8]| std::str::from_utf8(term).map_or(false, &matches))
+}
+
+/// Returns the smallest byte string greater than all the byte strings starting with `prefix`, or
+/// `None` if there is no such byte string, i.e. if `prefix` only contains `0xff` bytes.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last_incrementable_pos = prefix.iter().rposition(|byte| *byte != u8::MAX)?;
+    let mut successor = prefix[..=last_incrementable_pos].to_vec();
+    successor[last_incrementable_pos] += 1;
+    Some(successor)
+}
+
+/// Converts a wildcard pattern into the equivalent regex.
+fn wildcard_to_regex(pattern: &str) -> String {
+    let mut regex = String::with_capacity(pattern.len() * 2);
+    let mut literal_start = 0;
+
+    for (wildcard_pos, wildcard) in pattern.match_indices(['*', '?']) {
+        regex.push_str(&regex::escape(&pattern[literal_start..wildcard_pos]));
+        regex.push_str(if wildcard == "*" { ".*" } else { "." });
+        literal_start = wildcard_pos + wildcard.len();
+    }
+    regex.push_str(&regex::escape(&pattern[literal_start..]));
+    regex
+}
+
+/// Substitutes back the automaton clauses for their placeholders in a query serialized from an
+/// AST, in which the placeholders are quoted.
+pub(crate) fn restore_automaton_clauses(
+    query: &str,
+    automaton_clauses: &[AutomatonClause],
+) -> String {
+    let mut restored_query = query.to_string();
+    for (ord, automaton_clause) in automaton_clauses.iter().enumerate() {
+        let quoted_placeholder = format!("\"{}\"", automaton_clause_placeholder(ord));
+        restored_query = restored_query.replace(&quoted_placeholder, &automaton_clause.source);
+    }
+    restored_query
+}
+
+/// Returns true if the optimal string alignment distance between `left` and `right`, i.e. the
+/// Levenshtein distance counting the transposition of two adjacent characters as a single edit,
+/// is lower than or equal to `max_distance`.
+fn is_within_edit_distance(left: &str, right: &str, max_distance: usize) -> bool {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+
+    if left.len().abs_diff(right.len()) > max_distance {
+        return false;
+    }
+    let mut prev_prev_row: Vec<usize> = Vec::new();
+    let mut prev_row: Vec<usize> = (0..=right.len()).collect();
+
+    for i in 1..=left.len() {
+        let mut row = vec![i; right.len() + 1];
+        for j in 1..=right.len() {
+            let substitution_cost = usize::from(left[i - 1] != right[j - 1]);
+            row[j] = (prev_row[j] + 1)
+                .min(row[j - 1] + 1)
+                .min(prev_row[j - 1] + substitution_cost);
+            if i > 1 && j > 1 && left[i - 1] == right[j - 2] && left[i - 2] == right[j - 1] {
+                row[j] = row[j].min(prev_prev_row[j - 2] + 1);
+            }
+        }
+        // The distance can only grow from the minimum of the row.
+        if row.iter().all(|distance| *distance > max_distance) {
+            return false;
+        }
+        prev_prev_row = std::mem::replace(&mut prev_row, row);
+    }
+    prev_row[right.len()] <= max_distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_automaton_clauses() {
+        let (query, automaton_clauses) = extract_automaton_clauses("body:foo").unwrap();
+        assert_eq!(query, "body:foo");
+        assert!(automaton_clauses.is_empty());
+
+        let (query, automaton_clauses) = extract_automaton_clauses(
+            "name:jonh~1 AND (-title:\"foo~1\" OR last\\.name:smiht~) body:bar~3x",
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "name:__automaton_clause_0 AND (-title:\"foo~1\" OR last\\.name:__automaton_clause_1) \
+             body:bar~3x"
+        );
+        assert_eq!(
+            automaton_clauses,
+            [
+                AutomatonClause {
+                    source: "jonh~1".to_string(),
+                    automaton: TermAutomaton::Fuzzy {
+                        text: "jonh".to_string(),
+                        distance: 1,
+                    },
+                },
+                AutomatonClause {
+                    source: "smiht~".to_string(),
+                    automaton: TermAutomaton::Fuzzy {
+                        text: "smiht".to_string(),
+                        distance: 2,
+                    },
+                },
+            ]
+        );
+        assert_eq!(automaton_clause_ord("__automaton_clause_1"), Some(1));
+        assert_eq!(automaton_clause_ord("smiht"), None);
+        assert_eq!(
+            restore_automaton_clauses(
+                "(+name:\"__automaton_clause_0\" +last\\.name:\"__automaton_clause_1\")",
+                &automaton_clauses
+            ),
+            "(+name:jonh~1 +last\\.name:smiht~)"
+        );
+        assert!(extract_automaton_clauses("name:jonh~3").is_err());
+    }
+
+    #[test]
+    fn test_extract_regex_clauses() {
+        let (query, automaton_clauses) = extract_automaton_clauses(
+            r#"body:/.*base64_decode.*/ AND url:http://foo/bar title:"/foo/" path:/a\/b/"#,
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            r#"body:__automaton_clause_0 AND url:http://foo/bar title:"/foo/" path:__automaton_clause_1"#
+        );
+        assert_eq!(
+            automaton_clauses,
+            [
+                AutomatonClause {
+                    source: "/.*base64_decode.*/".to_string(),
+                    automaton: TermAutomaton::Regex {
+                        pattern: ".*base64_decode.*".to_string(),
+                    },
+                },
+                AutomatonClause {
+                    source: r#"/a\/b/"#.to_string(),
+                    automaton: TermAutomaton::Regex {
+                        pattern: "a/b".to_string(),
+                    },
+                },
+            ]
+        );
+        assert_eq!(
+            restore_automaton_clauses(
+                r#"(+body:"__automaton_clause_0" +path:"__automaton_clause_1")"#,
+                &automaton_clauses
+            ),
+            r#"(+body:/.*base64_decode.*/ +path:/a\/b/)"#
+        );
+        assert!(extract_automaton_clauses("body:/foo(/").is_err());
+        let long_pattern = "a".repeat(MAX_REGEX_PATTERN_LEN + 1);
+        assert!(extract_automaton_clauses(&format!("body:/{long_pattern}/")).is_err());
+
+        let term_automaton = TermAutomaton::Regex {
+            pattern: ".*base64_decode.*".to_string(),
+        };
+        let matches = term_automaton.matcher().unwrap();
+        assert!(matches(b"eval_base64_decode_string"));
+        assert!(!matches(b"base64_encode"));
+        assert!(!matches(b"base64_decode\xff"));
+    }
+
+    #[test]
+    fn test_extract_wildcard_clauses() {
+        let (query, automaton_clauses) = extract_automaton_clauses(
+            "body:*timeout* AND (title:fo?bar OR -title:\"foo*\") body:*",
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            "body:__automaton_clause_0 AND (title:__automaton_clause_1 OR -title:\"foo*\") body:*"
+        );
+        assert_eq!(
+            automaton_clauses,
+            [
+                AutomatonClause {
+                    source: "*timeout*".to_string(),
+                    automaton: TermAutomaton::Wildcard {
+                        pattern: "*timeout*".to_string(),
+                    },
+                },
+                AutomatonClause {
+                    source: "fo?bar".to_string(),
+                    automaton: TermAutomaton::Wildcard {
+                        pattern: "fo?bar".to_string(),
+                    },
+                },
+            ]
+        );
+        let term_automaton = TermAutomaton::Wildcard {
+            pattern: "*time.out*".to_string(),
+        };
+        let matches = term_automaton.matcher().unwrap();
+        assert!(matches(b"time.out"));
+        assert!(matches(b"connection_time.out_error"));
+        assert!(!matches(b"timeout"));
+    }
+
+    #[test]
+    fn test_bytes_prefix_automaton() {
+        let term_automaton = TermAutomaton::BytesPrefix {
+            prefix: vec![0xca, 0xfe],
+            pattern: "cafe*".to_string(),
+        };
+        let matches = term_automaton.matcher().unwrap();
+        assert!(matches(&[0xca, 0xfe]));
+        assert!(matches(&[0xca, 0xfe, 0xff, 0x00]));
+        assert!(!matches(&[0xca, 0xff]));
+        assert_eq!(term_automaton.to_string(), "cafe*");
+
+        assert_eq!(prefix_successor(&[0xca, 0xfe]), Some(vec![0xca, 0xff]));
+        assert_eq!(prefix_successor(&[0xca, 0xff, 0xff]), Some(vec![0xcb]));
+        assert_eq!(prefix_successor(&[0xff, 0xff]), None);
+    }
+
+    #[test]
+    fn test_wildcard_to_regex() {
+        assert_eq!(wildcard_to_regex("timeout"), "timeout");
+        assert_eq!(wildcard_to_regex("*timeout*"), ".*timeout.*");
+        assert_eq!(wildcard_to_regex("fo?.bar*"), "fo.\\.bar.*");
+    }
+
+    #[test]
+    fn test_is_within_edit_distance() {
+        assert!(is_within_edit_distance("jonh", "jonh", 0));
+        assert!(!is_within_edit_distance("jonh", "john", 0));
+        assert!(is_within_edit_distance("jonh", "john", 1));
+        assert!(is_within_edit_distance("jonh", "jon", 1));
+        assert!(is_within_edit_distance("jonh", "jonhy", 1));
+        assert!(!is_within_edit_distance("jonh", "jane", 1));
+        assert!(is_within_edit_distance("jonh", "jane", 3));
+        assert!(!is_within_edit_distance("jo", "jonhy", 2));
+        assert!(is_within_edit_distance("", "jo", 2));
+    }
+}
@@ -18,11 +18,17 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::str::CharIndices;
+use std::sync::Arc;
 
+use anyhow::{bail, Context};
 use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tantivy::tokenizer::{
-    BoxTokenStream, LowerCaser, RawTokenizer, RemoveLongFilter, TextAnalyzer, Token, TokenStream,
-    Tokenizer, TokenizerManager,
+    AlphaNumOnlyFilter, AsciiFoldingFilter, BoxTokenStream, Language, LowerCaser, NgramTokenizer,
+    PreTokenizedStream, PreTokenizedString, RawTokenizer, RegexTokenizer, RemoveLongFilter,
+    SimpleTokenizer, Stemmer, StopWordFilter, TextAnalyzer, Token, TokenStream, Tokenizer,
+    TokenizerManager, WhitespaceTokenizer,
 };
 
 fn get_quickwit_tokenizer_manager() -> TokenizerManager {
@@ -37,9 +43,92 @@ fn get_quickwit_tokenizer_manager() -> TokenizerManager {
     tokenizer_manager.register("raw", raw_tokenizer);
     tokenizer_manager.register("chinese_compatible", chinese_tokenizer);
 
+    for (analyzer_name, language) in LANGUAGE_ANALYZERS {
+        tokenizer_manager.register(analyzer_name, language_analyzer(language));
+    }
+    #[cfg(feature = "lindera")]
+    tokenizer_manager.register(
+        "japanese",
+        TextAnalyzer::from(JapaneseTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser),
+    );
     tokenizer_manager
 }
 
+/// Language analyzers, registered under the name of their language.
+const LANGUAGE_ANALYZERS: [(&str, Language); 8] = [
+    ("dutch", Language::Dutch),
+    ("english", Language::English),
+    ("french", Language::French),
+    ("german", Language::German),
+    ("italian", Language::Italian),
+    ("portuguese", Language::Portuguese),
+    ("russian", Language::Russian),
+    ("spanish", Language::Spanish),
+];
+
+/// Builds an analyzer splitting the text like the `default` tokenizer, then removing the stop
+/// words and stemming the tokens of `language`.
+fn language_analyzer(language: Language) -> TextAnalyzer {
+    let stop_word_filter = StopWordFilter::new(language)
+        .expect("The stop words of the language analyzers should be available.");
+    TextAnalyzer::from(SimpleTokenizer)
+        .filter(RemoveLongFilter::limit(40))
+        .filter(LowerCaser)
+        .filter(stop_word_filter)
+        .filter(Stemmer::new(language))
+}
+
+#[cfg(feature = "lindera")]
+static LINDERA_TOKENIZER: Lazy<lindera_tokenizer::tokenizer::Tokenizer> = Lazy::new(|| {
+    use lindera_core::mode::Mode;
+    use lindera_dictionary::{load_dictionary_from_config, DictionaryConfig, DictionaryKind};
+
+    let dictionary_config = DictionaryConfig {
+        kind: Some(DictionaryKind::IPADIC),
+        path: None,
+    };
+    let dictionary = load_dictionary_from_config(dictionary_config)
+        .expect("The Lindera IPADIC dictionary should be embedded in the binary.");
+    lindera_tokenizer::tokenizer::Tokenizer::new(dictionary, None, Mode::Normal)
+});
+
+/// Splits Japanese text into words with the Lindera morphological analyzer.
+#[cfg(feature = "lindera")]
+#[derive(Clone)]
+struct JapaneseTokenizer;
+
+#[cfg(feature = "lindera")]
+impl Tokenizer for JapaneseTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let lindera_tokens = match LINDERA_TOKENIZER.tokenize(text) {
+            Ok(lindera_tokens) => lindera_tokens,
+            Err(error) => {
+                tracing::warn!(error=?error, "Failed to tokenize Japanese text.");
+                Vec::new()
+            }
+        };
+        let tokens: Vec<Token> = lindera_tokens
+            .into_iter()
+            .filter(|lindera_token| !lindera_token.text.trim().is_empty())
+            .enumerate()
+            .map(|(position, lindera_token)| Token {
+                offset_from: lindera_token.byte_start,
+                offset_to: lindera_token.byte_end,
+                position,
+                text: lindera_token.text.to_string(),
+                position_length: 1,
+            })
+            .collect();
+        let pre_tokenized_string = PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        };
+        BoxTokenStream::from(PreTokenizedStream::from(pre_tokenized_string))
+    }
+}
+
 #[derive(Clone)]
 struct ChineseTokenizer;
 
@@ -148,11 +237,357 @@ impl<'a> TokenStream for ChineseTokenStream<'a> {
 pub static QUICKWIT_TOKENIZER_MANAGER: Lazy<TokenizerManager> =
     Lazy::new(get_quickwit_tokenizer_manager);
 
+/// A custom tokenizer defined in the doc mapping. Text and JSON fields refer to it by its name.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TokenizerEntry {
+    /// Name of the tokenizer.
+    pub name: String,
+    /// Splits the text into tokens.
+    #[serde(flatten)]
+    pub tokenizer_type: TokenizerType,
+    /// Rewrite the text before it is split into tokens.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub char_filters: Vec<CharFilter>,
+    /// Transform the tokens, in order.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<TokenFilterType>,
+}
+
+/// Splits the text into tokens.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TokenizerType {
+    /// Splits on the non-alphanumeric characters.
+    Simple,
+    /// Splits on the whitespaces.
+    Whitespace,
+    /// Emits the whole text as a single token.
+    Raw,
+    /// Emits the matches of a regular expression.
+    Regex(RegexTokenizerOptions),
+    /// Emits the n-grams of the text.
+    Ngram(NgramTokenizerOptions),
+}
+
+/// Options of the `regex` tokenizer.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegexTokenizerOptions {
+    /// Regular expression matching the tokens.
+    pub pattern: String,
+}
+
+/// Options of the `ngram` tokenizer.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NgramTokenizerOptions {
+    /// Minimum length of the n-grams, in characters.
+    pub min_gram: usize,
+    /// Maximum length of the n-grams, in characters.
+    pub max_gram: usize,
+    /// Only emits the n-grams starting at the beginning of the text (edge n-grams), which is
+    /// what prefix search needs.
+    #[serde(default)]
+    pub prefix_only: bool,
+}
+
+/// Rewrites the text before it is split into tokens.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CharFilter {
+    /// Replaces the HTML tags with a whitespace.
+    HtmlStrip,
+    /// Replaces the matches of a regular expression.
+    PatternReplace(PatternReplaceOptions),
+}
+
+/// Options of the `pattern_replace` character filter.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternReplaceOptions {
+    /// Regular expression matching the text to replace.
+    pub pattern: String,
+    /// Replacement text, which may refer to the capture groups with `$1`, `$name`, etc.
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// Transforms the tokens.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenFilterType {
+    /// Lowercases the tokens.
+    LowerCase,
+    /// Converts the non-ASCII characters to their ASCII equivalent, if any.
+    AsciiFolding,
+    /// Removes the tokens containing non-alphanumeric characters.
+    AlphaNumOnly,
+    /// Removes the tokens longer than 255 bytes.
+    RemoveLong,
+}
+
+/// Maximum length in bytes of the tokens kept by the `remove_long` filter.
+const REMOVE_LONG_FILTER_LIMIT: usize = 255;
+
+/// Tokenizer applying character filters to the text before handing it to a text analyzer.
+///
+/// The tokens are collected eagerly because the filtered text does not outlive the call. Their
+/// offsets refer to the filtered text.
+#[derive(Clone)]
+struct CharFilteringTokenizer {
+    char_filters: Arc<Vec<(Regex, String)>>,
+    text_analyzer: TextAnalyzer,
+}
+
+impl Tokenizer for CharFilteringTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let mut filtered_text = text.to_string();
+        for (pattern, replacement) in self.char_filters.iter() {
+            filtered_text = pattern
+                .replace_all(&filtered_text, replacement.as_str())
+                .into_owned();
+        }
+        let mut tokens = Vec::new();
+        let mut token_stream = self.text_analyzer.token_stream(&filtered_text);
+        while token_stream.advance() {
+            tokens.push(token_stream.token().clone());
+        }
+        drop(token_stream);
+        let pre_tokenized_string = PreTokenizedString {
+            text: filtered_text,
+            tokens,
+        };
+        BoxTokenStream::from(PreTokenizedStream::from(pre_tokenized_string))
+    }
+}
+
+fn build_char_filter(char_filter: &CharFilter) -> anyhow::Result<(Regex, String)> {
+    match char_filter {
+        CharFilter::HtmlStrip => {
+            let pattern = Regex::new(r"<[^>]*>").expect("The HTML tag pattern should be valid.");
+            Ok((pattern, " ".to_string()))
+        }
+        CharFilter::PatternReplace(options) => {
+            let pattern = Regex::new(&options.pattern)
+                .with_context(|| format!("Invalid pattern `{}`.", options.pattern))?;
+            Ok((pattern, options.replacement.clone()))
+        }
+    }
+}
+
+fn build_text_analyzer(tokenizer_entry: &TokenizerEntry) -> anyhow::Result<TextAnalyzer> {
+    let mut text_analyzer = match &tokenizer_entry.tokenizer_type {
+        TokenizerType::Simple => TextAnalyzer::from(SimpleTokenizer),
+        TokenizerType::Whitespace => TextAnalyzer::from(WhitespaceTokenizer),
+        TokenizerType::Raw => TextAnalyzer::from(RawTokenizer),
+        TokenizerType::Regex(options) => {
+            let regex_tokenizer = RegexTokenizer::new(&options.pattern)
+                .map_err(|error| anyhow::anyhow!("{error}"))
+                .with_context(|| format!("Invalid pattern `{}`.", options.pattern))?;
+            TextAnalyzer::from(regex_tokenizer)
+        }
+        TokenizerType::Ngram(options) => {
+            if options.min_gram == 0 || options.min_gram > options.max_gram {
+                bail!(
+                    "The n-gram lengths must satisfy `0 < min_gram <= max_gram`, got `{}` and \
+                     `{}`.",
+                    options.min_gram,
+                    options.max_gram
+                );
+            }
+            TextAnalyzer::from(NgramTokenizer::new(
+                options.min_gram,
+                options.max_gram,
+                options.prefix_only,
+            ))
+        }
+    };
+    for token_filter in &tokenizer_entry.filters {
+        text_analyzer = match token_filter {
+            TokenFilterType::LowerCase => text_analyzer.filter(LowerCaser),
+            TokenFilterType::AsciiFolding => text_analyzer.filter(AsciiFoldingFilter),
+            TokenFilterType::AlphaNumOnly => text_analyzer.filter(AlphaNumOnlyFilter),
+            TokenFilterType::RemoveLong => {
+                text_analyzer.filter(RemoveLongFilter::limit(REMOVE_LONG_FILTER_LIMIT))
+            }
+        };
+    }
+    if tokenizer_entry.char_filters.is_empty() {
+        return Ok(text_analyzer);
+    }
+    let char_filters = tokenizer_entry
+        .char_filters
+        .iter()
+        .map(build_char_filter)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let char_filtering_tokenizer = CharFilteringTokenizer {
+        char_filters: Arc::new(char_filters),
+        text_analyzer,
+    };
+    Ok(TextAnalyzer::from(char_filtering_tokenizer))
+}
+
+/// Creates a tokenizer manager holding Quickwit's tokenizers and the custom tokenizers.
+pub(crate) fn create_tokenizer_manager(
+    tokenizer_entries: &[TokenizerEntry],
+) -> anyhow::Result<TokenizerManager> {
+    let tokenizer_manager = get_quickwit_tokenizer_manager();
+    for tokenizer_entry in tokenizer_entries {
+        let tokenizer_name = &tokenizer_entry.name;
+        if tokenizer_name.is_empty()
+            || !tokenizer_name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        {
+            bail!(
+                "Tokenizer name `{tokenizer_name}` is invalid. It may only contain ASCII letters, \
+                 digits, and underscores."
+            );
+        }
+        if tokenizer_manager.get(tokenizer_name).is_some() {
+            bail!("Tokenizer `{tokenizer_name}` is already defined.");
+        }
+        let text_analyzer = build_text_analyzer(tokenizer_entry)
+            .with_context(|| format!("Failed to build tokenizer `{tokenizer_name}`."))?;
+        tokenizer_manager.register(tokenizer_name, text_analyzer);
+    }
+    Ok(tokenizer_manager)
+}
+
 #[cfg(test)]
 mod tests {
     use tantivy::tokenizer::Token;
 
-    use super::get_quickwit_tokenizer_manager;
+    use super::{create_tokenizer_manager, get_quickwit_tokenizer_manager, TokenizerEntry};
+
+    fn token_texts(tokenizer_entries_json: serde_json::Value, text: &str) -> Vec<String> {
+        let tokenizer_entries: Vec<TokenizerEntry> =
+            serde_json::from_value(tokenizer_entries_json).unwrap();
+        let tokenizer_manager = create_tokenizer_manager(&tokenizer_entries).unwrap();
+        let tokenizer = tokenizer_manager.get(&tokenizer_entries[0].name).unwrap();
+        let mut token_stream = tokenizer.token_stream(text);
+        let mut token_texts = Vec::new();
+        while let Some(token) = token_stream.next() {
+            token_texts.push(token.text.clone());
+        }
+        token_texts
+    }
+
+    fn analyze(tokenizer_name: &str, text: &str) -> Vec<String> {
+        let tokenizer = get_quickwit_tokenizer_manager()
+            .get(tokenizer_name)
+            .unwrap();
+        let mut token_stream = tokenizer.token_stream(text);
+        let mut token_texts = Vec::new();
+        while let Some(token) = token_stream.next() {
+            token_texts.push(token.text.clone());
+        }
+        token_texts
+    }
+
+    #[test]
+    fn test_language_analyzers() {
+        assert_eq!(
+            analyze("english", "The runners were running in the parks"),
+            ["runner", "run", "park"]
+        );
+        assert_eq!(
+            analyze("french", "Les chevaux mangeaient dans le pré"),
+            ["cheval", "mang", "pré"]
+        );
+        assert_eq!(
+            analyze("german", "Die Kinder spielten in den Gärten"),
+            ["kind", "spielt", "gart"]
+        );
+    }
+
+    #[cfg(feature = "lindera")]
+    #[test]
+    fn test_japanese_analyzer() {
+        assert_eq!(
+            analyze("japanese", "東京都に住む"),
+            ["東京", "都", "に", "住む"]
+        );
+    }
+
+    #[test]
+    fn test_custom_tokenizers() {
+        assert_eq!(
+            token_texts(
+                serde_json::json!([{
+                    "name": "path",
+                    "type": "regex",
+                    "pattern": "[^/]+",
+                    "filters": ["lower_case"]
+                }]),
+                "/API/v1/Users"
+            ),
+            ["api", "v1", "users"]
+        );
+        assert_eq!(
+            token_texts(
+                serde_json::json!([{
+                    "name": "prefix",
+                    "type": "ngram",
+                    "min_gram": 2,
+                    "max_gram": 4,
+                    "prefix_only": true
+                }]),
+                "hello"
+            ),
+            ["he", "hel", "hell"]
+        );
+        assert_eq!(
+            token_texts(
+                serde_json::json!([{
+                    "name": "html",
+                    "type": "simple",
+                    "char_filters": [
+                        {"type": "html_strip"},
+                        {"type": "pattern_replace", "pattern": "\\d+", "replacement": "N"}
+                    ],
+                    "filters": ["lower_case", "ascii_folding"]
+                }]),
+                "<p>Café 42</p>"
+            ),
+            ["cafe", "n"]
+        );
+    }
+
+    #[test]
+    fn test_create_tokenizer_manager_errors() {
+        let create_tokenizer_manager_from_json = |tokenizer_entries_json: serde_json::Value| {
+            let tokenizer_entries: Vec<TokenizerEntry> =
+                serde_json::from_value(tokenizer_entries_json).unwrap();
+            create_tokenizer_manager(&tokenizer_entries)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            create_tokenizer_manager_from_json(
+                serde_json::json!([{"name": "raw", "type": "simple"}])
+            ),
+            "Tokenizer `raw` is already defined."
+        );
+        assert_eq!(
+            create_tokenizer_manager_from_json(serde_json::json!([
+                {"name": "bad", "type": "ngram", "min_gram": 3, "max_gram": 2}
+            ])),
+            "Failed to build tokenizer `bad`."
+        );
+        assert_eq!(
+            create_tokenizer_manager_from_json(serde_json::json!([
+                {"name": "bad", "type": "regex", "pattern": "("}
+            ])),
+            "Failed to build tokenizer `bad`."
+        );
+        assert!(create_tokenizer_manager_from_json(
+            serde_json::json!([{"name": "a-b", "type": "raw"}])
+        )
+        .contains("is invalid"));
+    }
 
     #[test]
     fn test_raw_tokenizer() {
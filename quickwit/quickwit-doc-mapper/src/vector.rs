@@ -0,0 +1,189 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+/// Maximum number of dimensions of a `vector` field.
+pub const MAX_VECTOR_DIMS: usize = 4_096;
+
+/// Similarity function used to score a vector against a query vector. The higher the score, the
+/// closer the vectors.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorSimilarity {
+    /// Cosine of the angle between the two vectors.
+    #[default]
+    Cosine,
+    /// Dot product of the two vectors, for vectors normalized at indexing time.
+    DotProduct,
+    /// Opposite of the euclidean distance between the two vectors.
+    L2Norm,
+}
+
+impl VectorSimilarity {
+    /// Scores `vector` against `query_vector`. Both vectors must have the same number of
+    /// dimensions.
+    pub fn score(&self, query_vector: &[f32], vector: &[f32]) -> f32 {
+        debug_assert_eq!(query_vector.len(), vector.len());
+        match self {
+            VectorSimilarity::Cosine => {
+                let norms_product = norm(query_vector) * norm(vector);
+                if norms_product == 0.0 {
+                    return 0.0;
+                }
+                dot_product(query_vector, vector) / norms_product
+            }
+            VectorSimilarity::DotProduct => dot_product(query_vector, vector),
+            VectorSimilarity::L2Norm => {
+                let squared_distance: f32 = query_vector
+                    .iter()
+                    .zip(vector)
+                    .map(|(left, right)| (left - right) * (left - right))
+                    .sum();
+                -squared_distance.sqrt()
+            }
+        }
+    }
+}
+
+/// Dimensions and similarity function of a `vector` field.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VectorField {
+    /// Number of dimensions of the vectors.
+    pub dims: usize,
+    /// Similarity function used to score the vectors.
+    pub similarity: VectorSimilarity,
+}
+
+fn dot_product(left: &[f32], right: &[f32]) -> f32 {
+    left.iter()
+        .zip(right)
+        .map(|(left, right)| left * right)
+        .sum()
+}
+
+fn norm(vector: &[f32]) -> f32 {
+    dot_product(vector, vector).sqrt()
+}
+
+/// Parses a vector of `dims` dimensions from a JSON array of numbers.
+pub fn vector_from_json(json_val: &JsonValue, dims: usize) -> Result<Vec<f32>, String> {
+    let JsonValue::Array(json_vals) = json_val else {
+        return Err(format!("Expected array of numbers, got `{json_val}`."));
+    };
+    if json_vals.len() != dims {
+        return Err(format!(
+            "Expected vector with {dims} dimensions, got {} dimensions.",
+            json_vals.len()
+        ));
+    }
+    json_vals
+        .iter()
+        .map(|json_val| {
+            json_val
+                .as_f64()
+                .map(|val| val as f32)
+                .filter(|val| val.is_finite())
+                .ok_or_else(|| format!("Expected finite number, got `{json_val}`."))
+        })
+        .collect()
+}
+
+/// Encodes a vector into bytes, as a sequence of little-endian `f32`.
+pub fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|component| component.to_le_bytes())
+        .collect()
+}
+
+/// Decodes a vector previously encoded with [`encode_vector`]. Returns `None` if the number of
+/// bytes is not a multiple of 4.
+pub fn decode_vector(bytes: &[u8]) -> Option<Vec<f32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    let vector = bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect();
+    Some(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_vector_from_json() {
+        assert_eq!(
+            vector_from_json(&json!([1.0, -2.5, 3]), 3).unwrap(),
+            vec![1.0, -2.5, 3.0]
+        );
+        assert_eq!(
+            vector_from_json(&json!([1.0, 2.0]), 3).unwrap_err(),
+            "Expected vector with 3 dimensions, got 2 dimensions."
+        );
+        assert_eq!(
+            vector_from_json(&json!([1.0, "2.0"]), 2).unwrap_err(),
+            "Expected finite number, got `\"2.0\"`."
+        );
+        assert_eq!(
+            vector_from_json(&json!("1.0,2.0"), 2).unwrap_err(),
+            "Expected array of numbers, got `\"1.0,2.0\"`."
+        );
+    }
+
+    #[test]
+    fn test_encode_decode_vector() {
+        let vector = vec![0.5, -1.25, f32::MAX, 0.0];
+        let encoded_vector = encode_vector(&vector);
+        assert_eq!(encoded_vector.len(), 16);
+        assert_eq!(decode_vector(&encoded_vector).unwrap(), vector);
+        assert!(decode_vector(&encoded_vector[..3]).is_none());
+    }
+
+    #[test]
+    fn test_vector_similarity_score() {
+        let query_vector = [1.0, 0.0];
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[2.0, 0.0]),
+            1.0
+        );
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[0.0, 3.0]),
+            0.0
+        );
+        assert_eq!(
+            VectorSimilarity::Cosine.score(&query_vector, &[0.0, 0.0]),
+            0.0
+        );
+        assert_eq!(
+            VectorSimilarity::DotProduct.score(&query_vector, &[2.0, 5.0]),
+            2.0
+        );
+        assert_eq!(
+            VectorSimilarity::L2Norm.score(&query_vector, &[4.0, 4.0]),
+            -5.0
+        );
+    }
+}
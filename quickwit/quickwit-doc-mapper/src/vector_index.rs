@@ -0,0 +1,501 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-split approximate nearest neighbors index of the `vector` fields.
+//!
+//! The vectors of each field are partitioned into lists by a k-means clustering (IVF, for
+//! inverted file index). At search time, only the lists whose centroids are the most similar to
+//! the query vector are scored. The index is stored in the split bundle as a single file:
+//! - for each field, the centroids followed by the lists of vectors;
+//! - a JSON footer holding the byte ranges of the centroids and of the lists;
+//! - the length of this footer (8 bytes little endian).
+//!
+//! Each vector of a list is stored along with its document address, as the index of its segment
+//! in the footer, its doc ID, and its components, all little endian.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{VectorField, VectorSimilarity};
+
+/// Name of the vector index file in the split bundle.
+pub const VECTOR_INDEX_FILE_NAME: &str = "vectors.ivf";
+
+/// Number of bytes encoding the length of the footer of the vector index file.
+pub const VECTOR_INDEX_FOOTER_LEN_NUM_BYTES: usize = std::mem::size_of::<u64>();
+
+/// Default number of lists scored for each search.
+pub const DEFAULT_NUM_PROBES: usize = 8;
+
+const VECTOR_INDEX_FORMAT_VERSION: u32 = 1;
+
+/// The number of lists is the square root of the number of vectors, up to this limit, which
+/// bounds the cost of the clustering when the split is packaged.
+const MAX_NUM_LISTS: usize = 256;
+
+/// The centroids are computed from a sample of the vectors.
+const MAX_NUM_TRAINING_VECTORS: usize = 25_000;
+
+const NUM_KMEANS_ITERATIONS: usize = 8;
+
+/// A vector of a list, scored against a query vector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoredVector {
+    /// Index of the segment in [`VectorIndexFooter::segment_ids`].
+    pub segment_idx: u32,
+    /// Doc ID of the document within its segment.
+    pub doc_id: u32,
+    /// Similarity of the vector with the query vector.
+    pub score: f32,
+}
+
+#[derive(Default)]
+struct FieldVectors {
+    dims: usize,
+    similarity: VectorSimilarity,
+    doc_addrs: Vec<(u32, u32)>,
+    // Components of the vectors, one vector after the other.
+    components: Vec<f32>,
+}
+
+impl FieldVectors {
+    fn num_vectors(&self) -> usize {
+        self.doc_addrs.len()
+    }
+
+    fn vector(&self, ord: usize) -> &[f32] {
+        &self.components[ord * self.dims..(ord + 1) * self.dims]
+    }
+}
+
+/// Collects the vectors of a split and writes its vector index.
+pub struct VectorIndexBuilder {
+    segment_ids: Vec<String>,
+    fields: BTreeMap<String, FieldVectors>,
+}
+
+impl VectorIndexBuilder {
+    /// Creates a builder for the split made of the given segments. The vectors are then added
+    /// with the index of their segment in `segment_ids`.
+    pub fn new(segment_ids: Vec<String>) -> Self {
+        Self {
+            segment_ids,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Returns true if no vector has been added.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Adds the vector of a document. Vectors with the wrong number of dimensions are ignored.
+    pub fn add_vector(
+        &mut self,
+        field_name: &str,
+        vector_field: VectorField,
+        segment_idx: u32,
+        doc_id: u32,
+        vector: &[f32],
+    ) {
+        if vector.len() != vector_field.dims {
+            return;
+        }
+        let field_vectors = self
+            .fields
+            .entry(field_name.to_string())
+            .or_insert_with(|| FieldVectors {
+                dims: vector_field.dims,
+                similarity: vector_field.similarity,
+                ..Default::default()
+            });
+        field_vectors.doc_addrs.push((segment_idx, doc_id));
+        field_vectors.components.extend_from_slice(vector);
+    }
+
+    /// Clusters the vectors of each field and writes the index.
+    pub fn write<W: io::Write>(self, wrt: &mut W) -> io::Result<()> {
+        let mut footer = VectorIndexFooter {
+            version: VECTOR_INDEX_FORMAT_VERSION,
+            segment_ids: self.segment_ids,
+            fields: BTreeMap::new(),
+        };
+        let mut offset = 0u64;
+        for (field_name, field_vectors) in self.fields {
+            let centroids = compute_centroids(&field_vectors);
+            let mut lists: Vec<Vec<usize>> = vec![Vec::new(); centroids.len()];
+            for ord in 0..field_vectors.num_vectors() {
+                let list_ord = closest_centroid(
+                    &centroids,
+                    field_vectors.similarity,
+                    field_vectors.vector(ord),
+                );
+                lists[list_ord].push(ord);
+            }
+            let mut centroid_bytes = Vec::new();
+            for centroid in &centroids {
+                write_components(centroid, &mut centroid_bytes);
+            }
+            wrt.write_all(&centroid_bytes)?;
+            let centroids_range = offset..offset + centroid_bytes.len() as u64;
+            offset = centroids_range.end;
+
+            let mut list_ranges = Vec::with_capacity(lists.len());
+            for list in lists {
+                let mut list_bytes =
+                    Vec::with_capacity(list.len() * entry_num_bytes(field_vectors.dims));
+                for ord in list {
+                    let (segment_idx, doc_id) = field_vectors.doc_addrs[ord];
+                    list_bytes.extend_from_slice(&segment_idx.to_le_bytes());
+                    list_bytes.extend_from_slice(&doc_id.to_le_bytes());
+                    write_components(field_vectors.vector(ord), &mut list_bytes);
+                }
+                wrt.write_all(&list_bytes)?;
+                list_ranges.push(offset..offset + list_bytes.len() as u64);
+                offset += list_bytes.len() as u64;
+            }
+            let field_index = FieldVectorIndex {
+                dims: field_vectors.dims,
+                similarity: field_vectors.similarity,
+                num_vectors: field_vectors.num_vectors() as u64,
+                centroids: centroids_range,
+                lists: list_ranges,
+            };
+            footer.fields.insert(field_name, field_index);
+        }
+        let footer_bytes = serde_json::to_vec(&footer)?;
+        wrt.write_all(&footer_bytes)?;
+        wrt.write_all(&(footer_bytes.len() as u64).to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// Footer of the vector index file, locating the centroids and the lists of each field.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VectorIndexFooter {
+    version: u32,
+    /// IDs of the segments of the split. Vectors refer to their segment by its index in this
+    /// list.
+    pub segment_ids: Vec<String>,
+    fields: BTreeMap<String, FieldVectorIndex>,
+}
+
+impl VectorIndexFooter {
+    /// Returns the length of the footer, given the last
+    /// [`VECTOR_INDEX_FOOTER_LEN_NUM_BYTES`] bytes of the file.
+    pub fn footer_num_bytes(footer_len_bytes: &[u8]) -> io::Result<usize> {
+        let footer_len_bytes: [u8; VECTOR_INDEX_FOOTER_LEN_NUM_BYTES] =
+            footer_len_bytes.try_into().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid vector index footer length.",
+                )
+            })?;
+        Ok(u64::from_le_bytes(footer_len_bytes) as usize)
+    }
+
+    /// Deserializes the footer.
+    pub fn deserialize(footer_bytes: &[u8]) -> io::Result<Self> {
+        let footer: VectorIndexFooter = serde_json::from_slice(footer_bytes)?;
+        if footer.version != VECTOR_INDEX_FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported vector index version `{}`.", footer.version),
+            ));
+        }
+        Ok(footer)
+    }
+
+    /// Returns the index of the field, or `None` if the split has no vector for this field.
+    pub fn field(&self, field_name: &str) -> Option<&FieldVectorIndex> {
+        self.fields.get(field_name)
+    }
+}
+
+/// Locations of the centroids and of the lists of a field.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldVectorIndex {
+    dims: usize,
+    similarity: VectorSimilarity,
+    num_vectors: u64,
+    centroids: Range<u64>,
+    lists: Vec<Range<u64>>,
+}
+
+impl FieldVectorIndex {
+    /// Byte range of the centroids in the vector index file.
+    pub fn centroids_byte_range(&self) -> Range<usize> {
+        self.centroids.start as usize..self.centroids.end as usize
+    }
+
+    /// Returns the byte ranges of the `num_probes` lists whose centroids are the most similar to
+    /// the query vector.
+    pub fn probe(
+        &self,
+        centroid_bytes: &[u8],
+        query_vector: &[f32],
+        num_probes: usize,
+    ) -> io::Result<Vec<Range<usize>>> {
+        if query_vector.len() != self.dims {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Expected query vector with {} dimensions, got {} dimensions.",
+                    self.dims,
+                    query_vector.len()
+                ),
+            ));
+        }
+        let centroids = read_vectors(centroid_bytes, self.dims)?;
+        if centroids.len() != self.lists.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Vector index centroids do not match the lists.",
+            ));
+        }
+        let mut scored_lists: Vec<(f32, usize)> = centroids
+            .iter()
+            .enumerate()
+            .map(|(list_ord, centroid)| (self.similarity.score(query_vector, centroid), list_ord))
+            .collect();
+        scored_lists.sort_by(|left, right| right.0.total_cmp(&left.0));
+        let list_ranges = scored_lists
+            .into_iter()
+            .take(num_probes)
+            .map(|(_, list_ord)| &self.lists[list_ord])
+            .filter(|list_range| list_range.end > list_range.start)
+            .map(|list_range| list_range.start as usize..list_range.end as usize)
+            .collect();
+        Ok(list_ranges)
+    }
+
+    /// Scores the vectors of a list against the query vector.
+    pub fn score_list(
+        &self,
+        list_bytes: &[u8],
+        query_vector: &[f32],
+    ) -> io::Result<Vec<ScoredVector>> {
+        let entry_num_bytes = entry_num_bytes(self.dims);
+        if list_bytes.len() % entry_num_bytes != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Vector index list is truncated.",
+            ));
+        }
+        let mut scored_vectors = Vec::with_capacity(list_bytes.len() / entry_num_bytes);
+        let mut vector = Vec::with_capacity(self.dims);
+        for entry_bytes in list_bytes.chunks_exact(entry_num_bytes) {
+            let segment_idx = read_u32(&entry_bytes[0..4]);
+            let doc_id = read_u32(&entry_bytes[4..8]);
+            vector.clear();
+            vector.extend(entry_bytes[8..].chunks_exact(4).map(read_f32));
+            scored_vectors.push(ScoredVector {
+                segment_idx,
+                doc_id,
+                score: self.similarity.score(query_vector, &vector),
+            });
+        }
+        Ok(scored_vectors)
+    }
+}
+
+fn entry_num_bytes(dims: usize) -> usize {
+    8 + 4 * dims
+}
+
+fn write_components(vector: &[f32], out: &mut Vec<u8>) {
+    for component in vector {
+        out.extend_from_slice(&component.to_le_bytes());
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_f32(bytes: &[u8]) -> f32 {
+    f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn read_vectors(bytes: &[u8], dims: usize) -> io::Result<Vec<Vec<f32>>> {
+    if dims == 0 || bytes.len() % (4 * dims) != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Vector index centroids are truncated.",
+        ));
+    }
+    let vectors = bytes
+        .chunks_exact(4 * dims)
+        .map(|vector_bytes| vector_bytes.chunks_exact(4).map(read_f32).collect())
+        .collect();
+    Ok(vectors)
+}
+
+fn closest_centroid(centroids: &[Vec<f32>], similarity: VectorSimilarity, vector: &[f32]) -> usize {
+    let mut best_ord = 0;
+    let mut best_score = f32::NEG_INFINITY;
+    for (ord, centroid) in centroids.iter().enumerate() {
+        let score = similarity.score(vector, centroid);
+        if score > best_score {
+            best_ord = ord;
+            best_score = score;
+        }
+    }
+    best_ord
+}
+
+/// Runs a k-means clustering over a sample of the vectors, seeded with evenly spaced vectors.
+fn compute_centroids(field_vectors: &FieldVectors) -> Vec<Vec<f32>> {
+    let num_vectors = field_vectors.num_vectors();
+    let num_lists = ((num_vectors as f64).sqrt().ceil() as usize).clamp(1, MAX_NUM_LISTS);
+    let sample_step = (num_vectors / MAX_NUM_TRAINING_VECTORS).max(1);
+    let training_ords: Vec<usize> = (0..num_vectors).step_by(sample_step).collect();
+
+    let seed_step = (training_ords.len() / num_lists).max(1);
+    let mut centroids: Vec<Vec<f32>> = training_ords
+        .iter()
+        .step_by(seed_step)
+        .take(num_lists)
+        .map(|&ord| field_vectors.vector(ord).to_vec())
+        .collect();
+
+    for _ in 0..NUM_KMEANS_ITERATIONS {
+        let mut sums = vec![vec![0f32; field_vectors.dims]; centroids.len()];
+        let mut counts = vec![0usize; centroids.len()];
+        for &ord in &training_ords {
+            let vector = field_vectors.vector(ord);
+            let list_ord = closest_centroid(&centroids, field_vectors.similarity, vector);
+            for (sum, component) in sums[list_ord].iter_mut().zip(vector) {
+                *sum += component;
+            }
+            counts[list_ord] += 1;
+        }
+        // Empty clusters keep their previous centroid.
+        for ((centroid, sum), count) in centroids.iter_mut().zip(sums).zip(counts) {
+            if count > 0 {
+                *centroid = sum
+                    .into_iter()
+                    .map(|component| component / count as f32)
+                    .collect();
+            }
+        }
+    }
+    centroids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_vector_index(vectors: &[[f32; 2]]) -> Vec<u8> {
+        let vector_field = VectorField {
+            dims: 2,
+            similarity: VectorSimilarity::L2Norm,
+        };
+        let mut builder = VectorIndexBuilder::new(vec!["segment-a".to_string()]);
+        for (doc_id, vector) in vectors.iter().enumerate() {
+            builder.add_vector("embedding", vector_field, 0, doc_id as u32, vector);
+        }
+        // Vectors with the wrong number of dimensions are ignored.
+        builder.add_vector("embedding", vector_field, 0, 100, &[1.0]);
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn open_footer(bytes: &[u8]) -> VectorIndexFooter {
+        let footer_len_start = bytes.len() - VECTOR_INDEX_FOOTER_LEN_NUM_BYTES;
+        let footer_num_bytes =
+            VectorIndexFooter::footer_num_bytes(&bytes[footer_len_start..]).unwrap();
+        VectorIndexFooter::deserialize(
+            &bytes[footer_len_start - footer_num_bytes..footer_len_start],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_vector_index_search() {
+        let mut vectors = Vec::new();
+        for i in 0..50 {
+            vectors.push([i as f32 / 100.0, 0.0]);
+        }
+        for i in 0..50 {
+            vectors.push([100.0 + i as f32 / 100.0, 100.0]);
+        }
+        let bytes = build_vector_index(&vectors);
+        let footer = open_footer(&bytes);
+        assert_eq!(footer.segment_ids, vec!["segment-a".to_string()]);
+        assert!(footer.field("title").is_none());
+
+        let field_index = footer.field("embedding").unwrap();
+        assert_eq!(field_index.num_vectors, 100);
+        assert_eq!(field_index.lists.len(), 10);
+
+        let query_vector = [100.0, 100.0];
+        let centroid_bytes = &bytes[field_index.centroids_byte_range()];
+        let list_ranges = field_index.probe(centroid_bytes, &query_vector, 1).unwrap();
+        assert_eq!(list_ranges.len(), 1);
+        let scored_vectors = field_index
+            .score_list(&bytes[list_ranges[0].clone()], &query_vector)
+            .unwrap();
+        assert!(!scored_vectors.is_empty());
+        for scored_vector in &scored_vectors {
+            assert_eq!(scored_vector.segment_idx, 0);
+            // The probed list only holds vectors of the cluster around the query vector.
+            assert!(scored_vector.doc_id >= 50);
+            assert!(scored_vector.score > -1.0);
+        }
+
+        // Probing all the lists scores all the vectors.
+        let all_list_ranges = field_index
+            .probe(centroid_bytes, &query_vector, 100)
+            .unwrap();
+        let num_scored_vectors: usize = all_list_ranges
+            .into_iter()
+            .map(|list_range| {
+                field_index
+                    .score_list(&bytes[list_range], &query_vector)
+                    .unwrap()
+                    .len()
+            })
+            .sum();
+        assert_eq!(num_scored_vectors, 100);
+
+        let error = field_index
+            .probe(centroid_bytes, &[1.0, 2.0, 3.0], 1)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected query vector with 2 dimensions, got 3 dimensions."
+        );
+    }
+
+    #[test]
+    fn test_vector_index_empty() {
+        let builder = VectorIndexBuilder::new(Vec::new());
+        assert!(builder.is_empty());
+        let mut bytes = Vec::new();
+        builder.write(&mut bytes).unwrap();
+        let footer = open_footer(&bytes);
+        assert!(footer.field("embedding").is_none());
+    }
+}
@@ -29,11 +29,13 @@ use tokio::sync::watch;
 use tokio::sync::watch::Receiver;
 use tokio_stream::wrappers::WatchStream;
 use tokio_stream::StreamExt;
-use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::transport::{Channel, Endpoint};
 use tower::discover::Change;
 use tower::timeout::Timeout;
 use tracing::{error, info};
 
+use crate::tls::{grpc_endpoint, tls_reload_watcher};
+
 const CLIENT_TIMEOUT_DURATION: Duration = if cfg!(any(test, feature = "testsuite")) {
     Duration::from_millis(100)
 } else {
@@ -61,25 +63,44 @@ pub async fn create_balance_channel_from_watched_members(
     // Watch for cluster members changes and dynamically update channel endpoint.
     tokio::spawn({
         let mut current_grpc_address_pool = HashSet::new();
+        let mut tls_reload_rx = tls_reload_watcher();
         async move {
-            while let Some(new_members) = members_watch_channel.next().await {
-                let new_grpc_address_pool = filter_grpc_addresses(&new_members, &service);
-                if let Err(error) = update_channel_endpoints(
-                    &new_grpc_address_pool,
-                    &current_grpc_address_pool,
-                    &channel_tx,
-                    &service,
-                )
-                .await
-                {
-                    // If it fails, just log an error and stop the loop.
-                    error!("Failed to update balance channel endpoints: {error:?}");
-                    break;
-                };
-                current_grpc_address_pool = new_grpc_address_pool;
-                // TODO: Expose number of metastore servers in the pool as a Prometheus metric.
-                // The pool size channel can be closed if it's not used. Just ignore the send error.
-                let _ = pool_size_tx.send(current_grpc_address_pool.len());
+            loop {
+                tokio::select! {
+                    new_members_opt = members_watch_channel.next() => {
+                        let Some(new_members) = new_members_opt else {
+                            break;
+                        };
+                        let new_grpc_address_pool = filter_grpc_addresses(&new_members, &service);
+                        if let Err(error) = update_channel_endpoints(
+                            &new_grpc_address_pool,
+                            &current_grpc_address_pool,
+                            &channel_tx,
+                            &service,
+                        )
+                        .await
+                        {
+                            // If it fails, just log an error and stop the loop.
+                            error!("Failed to update balance channel endpoints: {error:?}");
+                            break;
+                        };
+                        current_grpc_address_pool = new_grpc_address_pool;
+                        // TODO: Expose number of metastore servers in the pool as a Prometheus
+                        // metric. The pool size channel can be closed if it's not used. Just
+                        // ignore the send error.
+                        let _ = pool_size_tx.send(current_grpc_address_pool.len());
+                    }
+                    Ok(()) = tls_reload_rx.changed() => {
+                        // Replace the endpoints so that new connections present the reloaded
+                        // certificates.
+                        for &grpc_address in &current_grpc_address_pool {
+                            let endpoint = make_endpoint(grpc_address);
+                            if channel_tx.send(Change::Insert(grpc_address, endpoint)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
             }
             Result::<_, anyhow::Error>::Ok(())
         }
@@ -99,6 +120,10 @@ fn filter_grpc_addresses(
         .collect()
 }
 
+fn make_endpoint(grpc_address: SocketAddr) -> Endpoint {
+    grpc_endpoint(grpc_address).connect_timeout(Duration::from_secs(5))
+}
+
 /// Updates channel endpoints by:
 /// - Sending `Change::Insert` grpc addresses not already present in `grpc_addresses_in_use`.
 /// - Sending `Change::Remove` event on grpc addresses present in `grpc_addresses_in_use` but not in
@@ -133,14 +158,7 @@ async fn update_channel_endpoints(
             "Adding `{quickwit_service}` servers to client pool.",
         );
         for new_grpc_address in new_grpc_addresses {
-            let new_grpc_uri = Uri::builder()
-                .scheme("http")
-                .authority(new_grpc_address.to_string())
-                .path_and_query("/")
-                .build()
-                .expect("Failed to build URI. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
-            let new_grpc_endpoint =
-                Endpoint::from(new_grpc_uri).connect_timeout(Duration::from_secs(5));
+            let new_grpc_endpoint = make_endpoint(new_grpc_address);
             channel_endpoint_tx
                 .send(Change::Insert(new_grpc_address, new_grpc_endpoint))
                 .await?;
@@ -19,6 +19,7 @@
 
 pub mod balance_channel;
 pub mod service_client_pool;
+pub mod tls;
 
 pub use balance_channel::create_balance_channel_from_watched_members;
 use tonic::transport::{Channel, Endpoint, Uri};
@@ -30,6 +30,8 @@ use tokio_stream::wrappers::WatchStream;
 use tokio_stream::StreamExt;
 use tracing::{debug, error};
 
+use crate::tls::tls_reload_watcher;
+
 /// A service client is a gRPC client that sends requests
 /// to a [`QuickwitService`] running on a node .
 /// It is used by the [`ServiceClientPool`] that manages
@@ -38,6 +40,10 @@ use tracing::{debug, error};
 pub trait ServiceClient: Clone + Send + Sync + 'static {
     /// Returns the [`QuickwitService`] of the client.
     fn service() -> QuickwitService;
+    /// Returns whether the cluster member runs the service of the client.
+    fn is_served_by(member: &ClusterMember) -> bool {
+        member.enabled_services.contains(&Self::service())
+    }
     /// Builds a client from a [`SocketAddr`].
     async fn build_client(addr: SocketAddr) -> anyhow::Result<Self>;
     /// Returns the gRPC address of the client.
@@ -94,16 +100,28 @@ impl<T: ServiceClient> ServiceClientPool<T> {
 
     /// Creates a [`ServiceClientPool`] from watched cluster members.
     /// When the pool is created, the thread that monitors cluster members
-    /// is started at the same time.
+    /// is started at the same time. The clients are rebuilt when the gRPC TLS certificates are
+    /// reloaded.
     pub async fn create_and_update_members(
         mut members_watch_channel: WatchStream<Vec<ClusterMember>>,
     ) -> anyhow::Result<Self> {
         let pool = ServiceClientPool::default();
         let pool_clone = pool.clone();
+        let mut tls_reload_rx = tls_reload_watcher();
         tokio::spawn(async move {
-            while let Some(new_members) = members_watch_channel.next().await {
-                let mut new_clients = pool_clone.all();
-                update_client_map::<T>(&new_members, &mut new_clients).await;
+            let mut members = Vec::new();
+            loop {
+                let mut new_clients = tokio::select! {
+                    new_members_opt = members_watch_channel.next() => {
+                        let Some(new_members) = new_members_opt else {
+                            break;
+                        };
+                        members = new_members;
+                        pool_clone.all()
+                    }
+                    Ok(()) = tls_reload_rx.changed() => HashMap::new(),
+                };
+                update_client_map::<T>(&members, &mut new_clients).await;
                 pool_clone.set(new_clients).await;
             }
             Result::<(), anyhow::Error>::Ok(())
@@ -137,7 +155,7 @@ async fn update_client_map<T: ServiceClient>(
 ) {
     let filtered_cluster_members = cluster_members
         .iter()
-        .filter(|member| member.enabled_services.contains(&T::service()))
+        .filter(|member| T::is_served_by(member))
         .collect_vec();
     let members_grpc_addrs: HashSet<SocketAddr> = filtered_cluster_members
         .iter()
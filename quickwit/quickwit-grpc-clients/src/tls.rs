@@ -0,0 +1,333 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Mutual TLS of the cluster-internal gRPC and gossip traffic.
+//!
+//! The TLS settings are process-wide: once [`init_grpc_tls`] is called, the endpoints returned by
+//! [`grpc_endpoint`] connect over TLS and present the node certificate, and the gRPC server
+//! accepts connections with [`tls_acceptor`]. The gossip transport connects with [`connect_tls`]
+//! and accepts connections with [`tls_acceptor`] as well. The certificate files are reloaded when
+//! they change, and [`tls_reload_watcher`] notifies the clients, which then reconnect with the
+//! new certificates.
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use once_cell::sync::Lazy;
+use quickwit_config::GrpcTlsConfig;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tonic::transport::{self, ClientTlsConfig, Endpoint, Identity, Uri};
+use tracing::{error, info};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+
+static GRPC_TLS_STATE: Lazy<RwLock<Option<Arc<GrpcTlsState>>>> = Lazy::new(RwLock::default);
+
+static TLS_RELOAD_TX: Lazy<watch::Sender<usize>> = Lazy::new(|| watch::channel(0).0);
+
+#[derive(Eq, PartialEq)]
+struct TlsFiles {
+    cert_pem: Vec<u8>,
+    key_pem: Vec<u8>,
+    ca_cert_pem: Vec<u8>,
+}
+
+impl TlsFiles {
+    async fn load(grpc_tls_config: &GrpcTlsConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            cert_pem: read_tls_file(&grpc_tls_config.cert_path).await?,
+            key_pem: read_tls_file(&grpc_tls_config.key_path).await?,
+            ca_cert_pem: read_tls_file(&grpc_tls_config.ca_cert_path).await?,
+        })
+    }
+}
+
+async fn read_tls_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read TLS file `{}`.", path.display()))
+}
+
+struct GrpcTlsState {
+    grpc_tls_config: GrpcTlsConfig,
+    tls_files: TlsFiles,
+    client_tls_config: ClientTlsConfig,
+    tls_acceptor: TlsAcceptor,
+    tls_connector: TlsConnector,
+    server_name: ServerName,
+}
+
+impl GrpcTlsState {
+    fn new(grpc_tls_config: GrpcTlsConfig, tls_files: TlsFiles) -> anyhow::Result<Self> {
+        let cert_chain = parse_certificates(&tls_files.cert_pem)?;
+        let private_key = parse_private_key(&tls_files.key_pem)?;
+        let mut root_cert_store = RootCertStore::empty();
+        for ca_cert in parse_certificates(&tls_files.ca_cert_pem)? {
+            root_cert_store
+                .add(&ca_cert)
+                .map_err(|error| anyhow!("Invalid CA certificate: {error:?}."))?;
+        }
+        let mut server_config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(root_cert_store.clone()))
+            .with_single_cert(cert_chain.clone(), private_key.clone())?;
+        server_config.alpn_protocols = vec![b"h2".to_vec()];
+        let tls_acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+        let client_config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(root_cert_store)
+            .with_single_cert(cert_chain, private_key)?;
+        let tls_connector = TlsConnector::from(Arc::new(client_config));
+        let server_name = ServerName::try_from(grpc_tls_config.server_name.as_str())
+            .map_err(|_| anyhow!("Invalid TLS server name `{}`.", grpc_tls_config.server_name))?;
+
+        let client_tls_config = ClientTlsConfig::new()
+            .ca_certificate(transport::Certificate::from_pem(&tls_files.ca_cert_pem))
+            .identity(Identity::from_pem(&tls_files.cert_pem, &tls_files.key_pem))
+            .domain_name(&grpc_tls_config.server_name);
+        Ok(Self {
+            grpc_tls_config,
+            tls_files,
+            client_tls_config,
+            tls_acceptor,
+            tls_connector,
+            server_name,
+        })
+    }
+}
+
+fn parse_certificates(pem: &[u8]) -> anyhow::Result<Vec<Certificate>> {
+    let certificates: Vec<Certificate> = rustls_pemfile::certs(&mut &pem[..])?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certificates.is_empty() {
+        bail!("No certificate found in PEM file.");
+    }
+    Ok(certificates)
+}
+
+fn parse_private_key(pem: &[u8]) -> anyhow::Result<PrivateKey> {
+    for item in rustls_pemfile::read_all(&mut &pem[..])? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+    bail!("No private key found in PEM file.")
+}
+
+/// Enables mutual TLS for the gRPC clients and server of the node, and starts watching the
+/// certificate files for changes. Must be called before any gRPC client is created.
+pub async fn init_grpc_tls(grpc_tls_config: GrpcTlsConfig) -> anyhow::Result<()> {
+    let tls_files = TlsFiles::load(&grpc_tls_config).await?;
+    let reload_interval = grpc_tls_config.reload_interval();
+    let grpc_tls_state = GrpcTlsState::new(grpc_tls_config, tls_files)?;
+    *GRPC_TLS_STATE.write().unwrap() = Some(Arc::new(grpc_tls_state));
+    tokio::spawn(reload_tls_files_loop(reload_interval));
+    Ok(())
+}
+
+async fn reload_tls_files_loop(reload_interval: Duration) {
+    let mut interval = tokio::time::interval(reload_interval);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        if let Err(error) = reload_tls_files().await {
+            error!(error=?error, "Failed to reload gRPC TLS certificates.");
+        }
+    }
+}
+
+/// Reloads the certificate files and, if they changed, notifies the gRPC clients.
+async fn reload_tls_files() -> anyhow::Result<()> {
+    let Some(current_state) = current_state() else {
+        return Ok(());
+    };
+    let tls_files = TlsFiles::load(&current_state.grpc_tls_config).await?;
+    if tls_files == current_state.tls_files {
+        return Ok(());
+    }
+    let new_state = GrpcTlsState::new(current_state.grpc_tls_config.clone(), tls_files)?;
+    *GRPC_TLS_STATE.write().unwrap() = Some(Arc::new(new_state));
+    TLS_RELOAD_TX.send_modify(|generation| *generation += 1);
+    info!("Reloaded gRPC TLS certificates.");
+    Ok(())
+}
+
+fn current_state() -> Option<Arc<GrpcTlsState>> {
+    GRPC_TLS_STATE.read().unwrap().clone()
+}
+
+/// Returns whether mutual TLS is enabled.
+pub fn is_grpc_tls_enabled() -> bool {
+    current_state().is_some()
+}
+
+/// Returns a receiver notified every time the certificates are reloaded.
+pub fn tls_reload_watcher() -> watch::Receiver<usize> {
+    TLS_RELOAD_TX.subscribe()
+}
+
+/// Returns the endpoint of the gRPC server listening on `grpc_addr`, which connects over TLS when
+/// mutual TLS is enabled.
+pub fn grpc_endpoint(grpc_addr: SocketAddr) -> Endpoint {
+    let grpc_tls_state_opt = current_state();
+    let scheme = if grpc_tls_state_opt.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    let uri = Uri::builder()
+        .scheme(scheme)
+        .authority(grpc_addr.to_string())
+        .path_and_query("/")
+        .build()
+        .expect("Failed to build URI. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
+    let endpoint = Endpoint::from(uri);
+    if let Some(grpc_tls_state) = grpc_tls_state_opt {
+        endpoint
+            .tls_config(grpc_tls_state.client_tls_config.clone())
+            .expect("The TLS configuration should have been validated when loaded.")
+    } else {
+        endpoint
+    }
+}
+
+/// Returns the acceptor of the TLS connections of the gRPC server, if mutual TLS is enabled.
+pub fn tls_acceptor() -> Option<TlsAcceptor> {
+    current_state().map(|grpc_tls_state| grpc_tls_state.tls_acceptor.clone())
+}
+
+/// Opens a TCP connection to `addr` and completes its TLS handshake, presenting the node
+/// certificate. Fails if mutual TLS is not enabled.
+pub async fn connect_tls(addr: SocketAddr) -> anyhow::Result<TlsStream<TcpStream>> {
+    let Some(grpc_tls_state) = current_state() else {
+        bail!("Mutual TLS is not enabled.");
+    };
+    let tcp_stream = TcpStream::connect(addr).await?;
+    let tls_stream = grpc_tls_state
+        .tls_connector
+        .connect(grpc_tls_state.server_name.clone(), tcp_stream)
+        .await?;
+    Ok(tls_stream)
+}
+
+/// Checks the SPIFFE ID of the certificate presented by a client against the allowed SPIFFE IDs.
+pub fn is_peer_allowed(peer_certificates_opt: Option<&[Certificate]>) -> bool {
+    let Some(grpc_tls_state) = current_state() else {
+        return true;
+    };
+    let allowed_spiffe_ids = &grpc_tls_state.grpc_tls_config.allowed_spiffe_ids;
+    if allowed_spiffe_ids.is_empty() {
+        return true;
+    }
+    let peer_certificate_opt = peer_certificates_opt.and_then(|certificates| certificates.first());
+    let Some(peer_certificate) = peer_certificate_opt else {
+        return false;
+    };
+    extract_spiffe_ids(&peer_certificate.0)
+        .iter()
+        .any(|spiffe_id| {
+            allowed_spiffe_ids
+                .iter()
+                .any(|allowed_spiffe_id| spiffe_id_matches(spiffe_id, allowed_spiffe_id))
+        })
+}
+
+/// Returns the SPIFFE IDs found in the URI subject alternative names of a DER certificate.
+fn extract_spiffe_ids(certificate_der: &[u8]) -> Vec<String> {
+    let Ok((_, certificate)) = X509Certificate::from_der(certificate_der) else {
+        return Vec::new();
+    };
+    let Ok(Some(subject_alternative_name)) = certificate.subject_alternative_name() else {
+        return Vec::new();
+    };
+    subject_alternative_name
+        .value
+        .general_names
+        .iter()
+        .filter_map(|general_name| match general_name {
+            GeneralName::URI(uri) if uri.starts_with("spiffe://") => Some(uri.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn spiffe_id_matches(spiffe_id: &str, allowed_spiffe_id: &str) -> bool {
+    if let Some(prefix) = allowed_spiffe_id.strip_suffix('*') {
+        spiffe_id.starts_with(prefix)
+    } else {
+        spiffe_id == allowed_spiffe_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spiffe_id_matches() {
+        assert!(spiffe_id_matches(
+            "spiffe://example.org/quickwit/searcher",
+            "spiffe://example.org/quickwit/*"
+        ));
+        assert!(spiffe_id_matches(
+            "spiffe://example.org/quickwit",
+            "spiffe://example.org/quickwit"
+        ));
+        assert!(!spiffe_id_matches(
+            "spiffe://example.org/other",
+            "spiffe://example.org/quickwit/*"
+        ));
+        assert!(!spiffe_id_matches(
+            "spiffe://example.org/quickwit/searcher",
+            "spiffe://example.org/quickwit"
+        ));
+    }
+
+    #[test]
+    fn test_parse_pem_files() {
+        let error = parse_certificates(b"").unwrap_err();
+        assert_eq!(error.to_string(), "No certificate found in PEM file.");
+        let error = parse_private_key(b"").unwrap_err();
+        assert_eq!(error.to_string(), "No private key found in PEM file.");
+    }
+
+    #[test]
+    fn test_grpc_endpoint_without_tls() {
+        let grpc_addr: SocketAddr = ([127, 0, 0, 1], 7281).into();
+        let endpoint = grpc_endpoint(grpc_addr);
+        assert_eq!(endpoint.uri().to_string(), "http://127.0.0.1:7281/");
+    }
+}
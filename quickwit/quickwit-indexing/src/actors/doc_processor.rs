@@ -19,6 +19,7 @@
 
 use std::collections::BTreeMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -35,6 +36,7 @@ use tracing::warn;
 use vrl::{Program, Runtime, TargetValueRef, Terminate, TimeZone};
 
 use crate::actors::Indexer;
+use crate::dead_letter_queue::{DeadLetterQueue, RejectedDoc};
 use crate::models::{NewPublishLock, PreparedDoc, PreparedDocBatch, PublishLock, RawDocBatch};
 
 type VrlValue = ::value::Value;
@@ -42,9 +44,11 @@ type VrlSecrets = ::value::Secrets;
 
 #[derive(Debug)]
 pub enum PrepareDocumentError {
-    ParsingError,
-    MissingField,
+    ParsingError(String),
+    MissingField(String),
     TransformError(Terminate),
+    /// The document was deliberately dropped by the transform (VRL `abort`).
+    Dropped,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
@@ -52,14 +56,16 @@ pub struct DocProcessorCounters {
     index_id: String,
     source_id: String,
     /// Overall number of documents received, partitioned
-    /// into 4 categories:
+    /// into 5 categories:
     /// - number of docs that could not be parsed.
     /// - number of docs that could not be transformed.
+    /// - number of docs dropped on purpose by the transform.
     /// - number of docs without a timestamp (if the index has no timestamp field,
     /// then this counter is equal to zero)
     /// - number of valid docs.
     pub num_parse_errors: u64,
     pub num_transform_errors: u64,
+    pub num_dropped_docs: u64,
     pub num_docs_with_missing_fields: u64,
     pub num_valid_docs: u64,
 
@@ -77,6 +83,7 @@ impl DocProcessorCounters {
             source_id,
             num_parse_errors: 0,
             num_transform_errors: 0,
+            num_dropped_docs: 0,
             num_docs_with_missing_fields: 0,
             num_valid_docs: 0,
             overall_num_bytes: 0,
@@ -89,6 +96,7 @@ impl DocProcessorCounters {
             + self.num_parse_errors
             + self.num_docs_with_missing_fields
             + self.num_transform_errors
+            + self.num_dropped_docs
     }
 
     /// Returns the overall number of docs that were sent to the indexer but were invalid.
@@ -98,6 +106,13 @@ impl DocProcessorCounters {
         self.num_parse_errors + self.num_docs_with_missing_fields + self.num_transform_errors
     }
 
+    pub fn record_processing_time(&self, processing_time: Duration) {
+        crate::metrics::INDEXER_METRICS
+            .processing_micros
+            .with_label_values([self.index_id.as_str(), "doc_processor"])
+            .inc_by(processing_time.as_micros() as u64);
+    }
+
     pub fn record_parsing_error(&mut self, num_bytes: u64) {
         self.num_parse_errors += 1;
         self.overall_num_bytes += num_bytes;
@@ -140,6 +155,19 @@ impl DocProcessorCounters {
             .inc_by(num_bytes);
     }
 
+    pub fn record_dropped(&mut self, num_bytes: u64) {
+        self.num_dropped_docs += 1;
+        self.overall_num_bytes += num_bytes;
+        crate::metrics::INDEXER_METRICS
+            .processed_docs_total
+            .with_label_values([self.index_id.as_str(), self.source_id.as_str(), "dropped"])
+            .inc();
+        crate::metrics::INDEXER_METRICS
+            .processed_bytes
+            .with_label_values([self.index_id.as_str(), self.source_id.as_str(), "dropped"])
+            .inc_by(num_bytes);
+    }
+
     pub fn record_missing_field(&mut self, num_bytes: u64) {
         self.num_docs_with_missing_fields += 1;
         self.overall_num_bytes += num_bytes;
@@ -182,6 +210,7 @@ pub struct DocProcessor {
     counters: DocProcessorCounters,
     publish_lock: PublishLock,
     transform_opt: Option<VrlProgram>,
+    dead_letter_queue_opt: Option<DeadLetterQueue>,
 }
 
 impl DocProcessor {
@@ -204,10 +233,17 @@ impl DocProcessor {
             counters: DocProcessorCounters::new(index_id, source_id),
             publish_lock: PublishLock::default(),
             transform_opt,
+            dead_letter_queue_opt: None,
         };
         Ok(doc_processor)
     }
 
+    /// Routes the rejected documents to a dead letter queue instead of only counting them.
+    pub(crate) fn with_dead_letter_queue(mut self, dead_letter_queue: DeadLetterQueue) -> Self {
+        self.dead_letter_queue_opt = Some(dead_letter_queue);
+        self
+    }
+
     // Extract a timestamp from a tantivy document.
     //
     // If the timestamp is set up in the docmapper and the timestamp is missing,
@@ -219,7 +255,9 @@ impl DocProcessor {
         let timestamp = doc
             .get_first(timestamp_field)
             .and_then(Value::as_date)
-            .ok_or(PrepareDocumentError::MissingField)?;
+            .ok_or_else(|| {
+                PrepareDocumentError::MissingField("The timestamp field is missing.".to_string())
+            })?;
         Ok(Some(timestamp))
     }
 
@@ -235,7 +273,11 @@ impl DocProcessor {
             let vrl_value = vrl_program.transform_doc(json_doc)?;
             let json_obj = match serde_json::to_value(vrl_value) {
                 Ok(JsonValue::Object(json_obj)) => json_obj,
-                _ => return Err(PrepareDocumentError::ParsingError),
+                _ => {
+                    return Err(PrepareDocumentError::ParsingError(
+                        "The transformed document is not a JSON object.".to_string(),
+                    ))
+                }
             };
             self.doc_mapper.doc_from_json_obj(json_obj)
         } else {
@@ -244,8 +286,10 @@ impl DocProcessor {
         let (partition, doc) = doc_parsing_result.map_err(|doc_parsing_error| {
             warn!(err=?doc_parsing_error);
             match doc_parsing_error {
-                DocParsingError::RequiredFastField(_) => PrepareDocumentError::MissingField,
-                _ => PrepareDocumentError::ParsingError,
+                DocParsingError::RequiredFastField(_) | DocParsingError::MissingField(_) => {
+                    PrepareDocumentError::MissingField(doc_parsing_error.to_string())
+                }
+                _ => PrepareDocumentError::ParsingError(doc_parsing_error.to_string()),
             }
         })?;
         let timestamp_opt = self.extract_timestamp(&doc)?;
@@ -320,26 +364,54 @@ impl Handler<RawDocBatch> for DocProcessor {
         if self.publish_lock.is_dead() {
             return Ok(());
         }
+        let start = Instant::now();
         let mut prepared_docs: Vec<PreparedDoc> = Vec::with_capacity(raw_doc_batch.docs.len());
+        let mut rejected_docs: Vec<RejectedDoc> = Vec::new();
         for json_doc in raw_doc_batch.docs {
             let json_doc_num_bytes = json_doc.len() as u64;
-            match self.prepare_document(&json_doc, ctx) {
+            let rejection_opt = match self.prepare_document(&json_doc, ctx) {
                 Ok(document) => {
                     self.counters.record_valid(json_doc_num_bytes);
                     prepared_docs.push(document);
+                    None
                 }
-                Err(PrepareDocumentError::ParsingError) => {
+                Err(PrepareDocumentError::ParsingError(error)) => {
                     self.counters.record_parsing_error(json_doc_num_bytes);
+                    Some(("parsing_error", error))
                 }
-                Err(PrepareDocumentError::TransformError(_)) => {
+                Err(PrepareDocumentError::TransformError(transform_error)) => {
                     self.counters.record_transform_error(json_doc_num_bytes);
+                    Some(("transform_error", transform_error.to_string()))
+                }
+                Err(PrepareDocumentError::Dropped) => {
+                    self.counters.record_dropped(json_doc_num_bytes);
+                    None
                 }
-                Err(PrepareDocumentError::MissingField) => {
+                Err(PrepareDocumentError::MissingField(error)) => {
                     self.counters.record_missing_field(json_doc_num_bytes);
+                    Some(("missing_field", error))
+                }
+            };
+            if let Some((reason, error)) = rejection_opt {
+                if self.dead_letter_queue_opt.is_some() {
+                    rejected_docs.push(RejectedDoc {
+                        doc: json_doc,
+                        reason,
+                        error,
+                    });
                 }
             }
             ctx.record_progress();
         }
+        self.counters.record_processing_time(start.elapsed());
+
+        if let Some(dead_letter_queue) = &self.dead_letter_queue_opt {
+            // The batch is not forwarded to the indexer if the rejected documents cannot be
+            // routed: the pipeline fails and the source replays them after restarting.
+            ctx.protect_future(dead_letter_queue.send(&rejected_docs))
+                .await
+                .map_err(ActorExitStatus::from)?;
+        }
         let prepared_doc_batch = PreparedDocBatch {
             docs: prepared_docs,
             checkpoint_delta: raw_doc_batch.checkpoint_delta,
@@ -376,7 +448,12 @@ impl VrlProgram {
     fn transform_doc(&mut self, json_doc: &str) -> Result<VrlValue, PrepareDocumentError> {
         let mut value = match serde_json::from_str::<VrlValue>(json_doc) {
             Ok(value) if value.is_object() => value,
-            _ => return Err(PrepareDocumentError::ParsingError),
+            Ok(_) => {
+                return Err(PrepareDocumentError::ParsingError(
+                    "The document is not a JSON object.".to_string(),
+                ))
+            }
+            Err(error) => return Err(PrepareDocumentError::ParsingError(error.to_string())),
         };
         let mut metadata = VrlValue::Object(BTreeMap::new());
         let mut secrets = VrlSecrets::new();
@@ -388,9 +465,13 @@ impl VrlProgram {
         let runtime_res = self
             .runtime
             .resolve(&mut target, &self.program, &self.timezone)
-            .map_err(|transform_error| {
-                warn!(transform_error=?transform_error);
-                PrepareDocumentError::TransformError(transform_error)
+            .map_err(|transform_error| match transform_error {
+                // `abort` is the VRL idiom for discarding an event: it is not an error.
+                Terminate::Abort(_) => PrepareDocumentError::Dropped,
+                Terminate::Error(_) => {
+                    warn!(transform_error=?transform_error);
+                    PrepareDocumentError::TransformError(transform_error)
+                }
             });
 
         self.runtime.clear();
@@ -418,6 +499,7 @@ mod tests {
     use quickwit_actors::Universe;
     use quickwit_doc_mapper::{default_doc_mapper_for_test, DefaultDocMapper};
     use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+    use quickwit_storage::{RamStorage, Storage};
     use serde_json::Value as JsonValue;
     use tantivy::schema::NamedFieldDocument;
 
@@ -464,6 +546,7 @@ mod tests {
                 source_id: source_id.to_string(),
                 num_parse_errors: 1,
                 num_transform_errors: 0,
+                num_dropped_docs: 0,
                 num_docs_with_missing_fields: 1,
                 num_valid_docs: 2,
                 overall_num_bytes: 387,
@@ -504,6 +587,61 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_doc_processor_dead_letter_queue() {
+        let universe = Universe::with_accelerated_time();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let storage = Arc::new(RamStorage::default());
+        let dead_letter_queue = DeadLetterQueue::for_test("my-index", "my-source", storage.clone());
+        let doc_processor = DocProcessor::try_new(
+            "my-index".to_string(),
+            "my-source".to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            None,
+        )
+        .unwrap()
+        .with_dead_letter_queue(dead_letter_queue);
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                    r#"{"body": "happy"}"#.to_string(), // missing timestamp
+                    r#"{"body": "happy", "timestamp": 1628837062}"#.to_string(), // ok
+                    "{".to_string(),                    // invalid json
+                ],
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..3),
+            })
+            .await
+            .unwrap();
+        let doc_processor_counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(doc_processor_counters.num_invalid_docs(), 2);
+
+        let batch = indexer_inbox.drain_for_test_typed::<PreparedDocBatch>();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].docs.len(), 1);
+
+        let files = storage.list_files().await;
+        assert_eq!(files.len(), 1);
+        let payload = storage.get_all(&files[0]).await.unwrap();
+        let records: Vec<JsonValue> = std::str::from_utf8(&payload)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["reason"], "missing_field");
+        assert_eq!(records[0]["doc"], r#"{"body": "happy"}"#);
+        assert_eq!(records[1]["reason"], "parsing_error");
+        assert_eq!(records[1]["doc"], "{");
+        universe.assert_quit().await;
+    }
+
     const DOCMAPPER_WITH_PARTITION_JSON: &str = r#"
         {
             "tag_fields": ["tenant"],
@@ -675,6 +813,7 @@ mod tests {
                 source_id: source_id.to_string(),
                 num_parse_errors: 1,
                 num_transform_errors: 0,
+                num_dropped_docs: 0,
                 num_docs_with_missing_fields: 1,
                 num_valid_docs: 2,
                 overall_num_bytes: 397,
@@ -714,4 +853,52 @@ mod tests {
         universe.assert_quit().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_doc_processor_vrl_abort_drops_doc() {
+        let index_id = "my-index";
+        let source_id = "my-source";
+        let universe = Universe::with_accelerated_time();
+        let (indexer_mailbox, indexer_inbox) = universe.create_test_mailbox();
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let transform_config = TransformConfig::for_test(
+            r#"if .body == "drop me" { abort }
+            .body = string!(.body)"#,
+        );
+        let doc_processor = DocProcessor::try_new(
+            index_id.to_string(),
+            source_id.to_string(),
+            doc_mapper,
+            indexer_mailbox,
+            Some(transform_config),
+        )
+        .unwrap();
+        let (doc_processor_mailbox, doc_processor_handle) =
+            universe.spawn_builder().spawn(doc_processor);
+        doc_processor_mailbox
+            .send_message(RawDocBatch {
+                docs: vec![
+                        r#"{"body": "drop me", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(), // dropped
+                        r#"{"body": "keep me", "timestamp": 1628837062, "response_date": "2021-12-19T16:39:59+00:00", "response_time": 2, "response_payload": "YWJj"}"#.to_string(), // ok
+                        r#"{"timestamp": 1628837062}"#.to_string(), // transform error
+                    ],
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..3),
+            })
+            .await
+            .unwrap();
+        let doc_processor_counters = doc_processor_handle
+            .process_pending_and_observe()
+            .await
+            .state;
+        assert_eq!(doc_processor_counters.num_dropped_docs, 1);
+        assert_eq!(doc_processor_counters.num_transform_errors, 1);
+        assert_eq!(doc_processor_counters.num_valid_docs, 1);
+        assert_eq!(doc_processor_counters.num_processed_docs(), 3);
+        assert_eq!(doc_processor_counters.num_invalid_docs(), 1);
+
+        let prepared_doc_batches: Vec<PreparedDocBatch> = indexer_inbox.drain_for_test_typed();
+        assert_eq!(prepared_doc_batches.len(), 1);
+        assert_eq!(prepared_doc_batches[0].docs.len(), 1);
+        universe.assert_quit().await;
+    }
 }
@@ -17,46 +17,74 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::hash_map::Entry;
+use std::hash::{Hash, Hasher};
 use std::num::NonZeroU32;
 use std::ops::RangeInclusive;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Context;
 use async_trait::async_trait;
 use byte_unit::Byte;
 use fail::fail_point;
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHasher};
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::IndexingSettings;
-use quickwit_doc_mapper::{DocMapper, QUICKWIT_TOKENIZER_MANAGER};
+use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
 use quickwit_metastore::Metastore;
 use serde::Serialize;
-use tantivy::schema::Schema;
+use tantivy::schema::{Field, Schema, Value};
 use tantivy::store::{Compressor, ZstdCompressor};
-use tantivy::{DateTime, IndexBuilder, IndexSettings};
+use tantivy::tokenizer::TokenizerManager;
+use tantivy::{DateTime, Document, IndexBuilder, IndexSettings};
 use tokio::runtime::Handle;
 use tracing::{info, info_span, warn, Span};
 use ulid::Ulid;
 
 use crate::actors::IndexSerializer;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::{
-    CommitTrigger, IndexedSplitBatchBuilder, IndexedSplitBuilder, IndexingPipelineId,
-    NewPublishLock, PreparedDoc, PreparedDocBatch, PublishLock, ScratchDirectory,
+    CommitTrigger, DocDeduplicator, IndexedSplitBatchBuilder, IndexedSplitBuilder,
+    IndexingMemoryBudget, IndexingPipelineId, MemoryUsageTracker, NewPublishLock, PreparedDoc,
+    PreparedDocBatch, PublishLock, ScratchDirectory,
 };
 
 // Random partition id used to gather partitions exceeding the maximum number of partitions.
 const OTHER_PARTITION_ID: u64 = 3264326757911759461u64;
 
+/// Key of the split custom metadata entry listing the source partitions the documents of the
+/// split were read from, e.g. the path of the file for the file source.
+const SOURCE_PARTITIONS_METADATA_KEY: &str = "source_partitions";
+
 #[derive(Debug)]
 struct CommitTimeout {
     workbench_id: Ulid,
 }
 
+/// Commits the current workbench right away.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ForceCommit;
+
+/// Scheduled every `min_commit_timeout` when the adaptive commit policy is enabled. The workbench
+/// is committed if it has not received any batch since the previous check.
+#[derive(Debug)]
+struct IdleCheck {
+    workbench_id: Ulid,
+    num_batches: u64,
+}
+
+/// Identifies a split of the workbench: each partition is split across up to
+/// `num_split_builders` split builders.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct SplitKey {
+    partition_id: u64,
+    split_builder_ord: usize,
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
 pub struct IndexerCounters {
     /// Number of splits that were emitted by the indexer.
@@ -68,6 +96,10 @@ pub struct IndexerCounters {
     /// Number of (valid) documents in the current workbench.
     /// This value is used to trigger commit and for observation.
     pub num_docs_in_workbench: u64,
+
+    /// Number of documents dropped because their unique ID was already seen within the
+    /// deduplication window.
+    pub num_duplicate_docs: u64,
 }
 
 struct IndexerState {
@@ -77,8 +109,15 @@ struct IndexerState {
     indexing_settings: IndexingSettings,
     publish_lock: PublishLock,
     schema: Schema,
+    tokenizer_manager: TokenizerManager,
     max_num_partitions: NonZeroU32,
+    doc_mapping_version: u64,
     index_settings: IndexSettings,
+    num_split_builders: usize,
+    // Field used to route documents to split builders. Documents are dispatched in a round-robin
+    // fashion when it is not set.
+    routing_field_opt: Option<Field>,
+    memory_budget: IndexingMemoryBudget,
 }
 
 impl IndexerState {
@@ -91,21 +130,23 @@ impl IndexerState {
         let index_builder = IndexBuilder::new()
             .settings(self.index_settings.clone())
             .schema(self.schema.clone())
-            .tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
+            .tokenizers(self.tokenizer_manager.clone());
 
         let io_controls = IoControls::default()
             .set_progress(ctx.progress().clone())
             .set_kill_switch(ctx.kill_switch().clone())
             .set_index_and_component(&self.pipeline_id.index_id, "indexer");
 
-        let indexed_split = IndexedSplitBuilder::new_in_dir(
+        let mut indexed_split = IndexedSplitBuilder::new_in_dir(
             self.pipeline_id.clone(),
             partition_id,
+            self.doc_mapping_version,
             last_delete_opstamp,
             self.indexing_directory.clone(),
             index_builder,
             io_controls,
         )?;
+        indexed_split.split_attrs.custom_metadata = self.indexing_settings.split_metadata.clone();
         info!(
             split_id = indexed_split.split_id(),
             partition_id = partition_id,
@@ -114,43 +155,64 @@ impl IndexerState {
         Ok(indexed_split)
     }
 
-    fn get_or_create_indexed_split<'a>(
+    /// Returns the key of the split the document should be added to, creating the split if
+    /// necessary, or `None` if the document should be added to the `OTHER` split.
+    fn get_or_create_indexed_split(
         &self,
-        partition_id: u64,
+        split_key: SplitKey,
         last_delete_opstamp: u64,
-        splits: &'a mut FnvHashMap<u64, IndexedSplitBuilder>,
-        other_split_opt: &'a mut Option<IndexedSplitBuilder>,
-        counter: &'a mut IndexerCounters,
+        splits: &mut FnvHashMap<SplitKey, IndexedSplitBuilder>,
+        other_split_opt: &mut Option<IndexedSplitBuilder>,
+        counter: &IndexerCounters,
         ctx: &ActorContext<Indexer>,
-    ) -> anyhow::Result<&'a mut IndexedSplitBuilder> {
-        let num_splits = splits.len();
-        match splits.entry(partition_id) {
-            Entry::Occupied(indexed_split) => Ok(indexed_split.into_mut()),
-            Entry::Vacant(vacant_entry) => {
-                if num_splits as u32 >= self.max_num_partitions.get() {
-                    // In order to avoid exceeding max_num_partitions, we map the document to the
-                    // `OTHER` special partition.
-                    if other_split_opt.is_none() {
-                        warn!(
-                            num_docs_in_workbench = counter.num_docs_in_workbench,
-                            max_num_partition = self.max_num_partitions.get(),
-                            "Exceeding max_num_partition"
-                        );
-                        let new_other_split = self.create_indexed_split_builder(
-                            OTHER_PARTITION_ID,
-                            last_delete_opstamp,
-                            ctx,
-                        )?;
-                        *other_split_opt = Some(new_other_split);
-                    }
-                    Ok(other_split_opt.as_mut().unwrap())
-                } else {
-                    let indexed_split =
-                        self.create_indexed_split_builder(partition_id, last_delete_opstamp, ctx)?;
-                    Ok(vacant_entry.insert(indexed_split))
+    ) -> anyhow::Result<Option<SplitKey>> {
+        if splits.contains_key(&split_key) {
+            return Ok(Some(split_key));
+        }
+        let is_new_partition = splits
+            .keys()
+            .all(|key| key.partition_id != split_key.partition_id);
+        if is_new_partition {
+            let num_partitions = splits.keys().map(|key| key.partition_id).unique().count();
+            if num_partitions as u32 >= self.max_num_partitions.get() {
+                // In order to avoid exceeding max_num_partitions, we map the document to the
+                // `OTHER` special partition.
+                if other_split_opt.is_none() {
+                    warn!(
+                        num_docs_in_workbench = counter.num_docs_in_workbench,
+                        max_num_partition = self.max_num_partitions.get(),
+                        "Exceeding max_num_partition"
+                    );
+                    let new_other_split = self.create_indexed_split_builder(
+                        OTHER_PARTITION_ID,
+                        last_delete_opstamp,
+                        ctx,
+                    )?;
+                    *other_split_opt = Some(new_other_split);
                 }
+                return Ok(None);
             }
         }
+        let indexed_split =
+            self.create_indexed_split_builder(split_key.partition_id, last_delete_opstamp, ctx)?;
+        splits.insert(split_key, indexed_split);
+        Ok(Some(split_key))
+    }
+
+    /// Returns the ordinal of the split builder the document should be routed to.
+    fn split_builder_ord(&self, doc: &Document, doc_ord: u64) -> usize {
+        if self.num_split_builders <= 1 {
+            return 0;
+        }
+        let routing_value_opt = self
+            .routing_field_opt
+            .and_then(|routing_field| doc.get_first(routing_field));
+        let hash = if let Some(routing_value) = routing_value_opt {
+            hash_routing_value(routing_value)
+        } else {
+            doc_ord
+        };
+        (hash % self.num_split_builders as u64) as usize
     }
 
     async fn create_workbench(
@@ -169,6 +231,9 @@ impl IndexerState {
             pipeline_ord=%self.pipeline_id.pipeline_ord
         );
         let indexing_span = info_span!(parent: batch_parent_span.id(), "indexer");
+        let memory_usage_gauge = INDEXER_METRICS
+            .in_flight_splits_memory_usage_bytes
+            .with_label_values([&self.pipeline_id.index_id, &self.pipeline_id.source_id]);
         let workbench = IndexingWorkbench {
             batch_parent_span,
             _indexing_span: indexing_span,
@@ -178,10 +243,12 @@ impl IndexerState {
             checkpoint_delta: IndexCheckpointDelta {
                 source_id: self.pipeline_id.source_id.clone(),
                 source_delta: SourceCheckpointDelta::default(),
+                publish_token: None,
             },
             publish_lock: self.publish_lock.clone(),
             last_delete_opstamp,
-            memory_usage: Byte::from_bytes(0),
+            memory_usage: self.memory_budget.track(memory_usage_gauge),
+            num_batches: 0,
         };
         Ok(workbench)
     }
@@ -205,6 +272,15 @@ impl IndexerState {
                 commit_timeout_message,
             )
             .await;
+            if let Some(min_commit_timeout) = self.indexing_settings.min_commit_timeout() {
+                // The workbench is created upon receiving its first batch.
+                let idle_check_message = IdleCheck {
+                    workbench_id: indexing_workbench.workbench_id,
+                    num_batches: 1,
+                };
+                ctx.schedule_self_msg(min_commit_timeout, idle_check_message)
+                    .await;
+            }
             *indexing_workbench_opt = Some(indexing_workbench);
         }
         let current_indexing_workbench = indexing_workbench_opt.as_mut().context(
@@ -217,6 +293,7 @@ impl IndexerState {
         &self,
         batch: PreparedDocBatch,
         indexing_workbench_opt: &mut Option<IndexingWorkbench>,
+        doc_deduplicator_opt: &mut Option<DocDeduplicator>,
         counters: &mut IndexerCounters,
         ctx: &ActorContext<Indexer>,
     ) -> Result<(), ActorExitStatus> {
@@ -227,6 +304,7 @@ impl IndexerState {
             publish_lock,
             last_delete_opstamp,
             memory_usage,
+            num_batches,
             ..
         } = self
             .get_or_create_workbench(indexing_workbench_opt, ctx)
@@ -234,43 +312,64 @@ impl IndexerState {
         if publish_lock.is_dead() {
             return Ok(());
         }
+        *num_batches += 1;
         checkpoint_delta
             .source_delta
             .extend(batch.checkpoint_delta)
             .context("Batch delta does not follow indexer checkpoint")?;
-        let mut memory_usage_delta: u64 = 0;
-        for doc in batch.docs {
-            let PreparedDoc {
-                doc,
-                timestamp_opt,
-                partition,
-                num_bytes,
-            } = doc;
+        let now = Instant::now();
+        // Documents are first grouped by split (`None` stands for the `OTHER` split), then each
+        // split builder indexes its share of the batch on a dedicated thread.
+        let mut docs_per_split: FnvHashMap<Option<SplitKey>, Vec<PreparedDoc>> =
+            FnvHashMap::default();
+        for prepared_doc in batch.docs {
+            if let Some(doc_deduplicator) = doc_deduplicator_opt.as_mut() {
+                if doc_deduplicator.is_duplicate(&prepared_doc.doc, now) {
+                    counters.num_duplicate_docs += 1;
+                    continue;
+                }
+            }
+            let split_key = SplitKey {
+                partition_id: prepared_doc.partition,
+                split_builder_ord: self
+                    .split_builder_ord(&prepared_doc.doc, counters.num_docs_in_workbench),
+            };
             counters.num_docs_in_workbench += 1;
-            let indexed_split: &mut IndexedSplitBuilder = self.get_or_create_indexed_split(
-                partition,
+            let split_key_opt = self.get_or_create_indexed_split(
+                split_key,
                 *last_delete_opstamp,
                 indexed_splits,
                 other_indexed_split_opt,
                 counters,
                 ctx,
             )?;
-            let mem_usage_before = indexed_split.index_writer.mem_usage() as u64;
-            indexed_split.split_attrs.uncompressed_docs_size_in_bytes += num_bytes as u64;
-            indexed_split.split_attrs.num_docs += 1;
-            if let Some(timestamp) = timestamp_opt {
-                record_timestamp(timestamp, &mut indexed_split.split_attrs.time_range);
+            docs_per_split
+                .entry(split_key_opt)
+                .or_default()
+                .push(prepared_doc);
+        }
+        let mut jobs_per_split_builder: Vec<SplitBuilderJobs> =
+            (0..self.num_split_builders).map(|_| Vec::new()).collect();
+        for (split_key, indexed_split) in indexed_splits.iter_mut() {
+            if let Some(docs) = docs_per_split.remove(&Some(*split_key)) {
+                jobs_per_split_builder[split_key.split_builder_ord].push((indexed_split, docs));
             }
-            let _protect_guard = ctx.protect_zone();
-            indexed_split
-                .index_writer
-                .add_document(doc)
-                .context("Failed to add document.")?;
-            let mem_usage_after = indexed_split.index_writer.mem_usage() as u64;
-            memory_usage_delta += mem_usage_after - mem_usage_before;
-            ctx.record_progress();
         }
-        *memory_usage = Byte::from_bytes(memory_usage.get_bytes() + memory_usage_delta);
+        if let Some(docs) = docs_per_split.remove(&None) {
+            if let Some(other_indexed_split) = other_indexed_split_opt.as_mut() {
+                jobs_per_split_builder[0].push((other_indexed_split, docs));
+            }
+        }
+        let memory_usage_delta = {
+            let _protect_guard = ctx.protect_zone();
+            add_docs_to_split_builders(jobs_per_split_builder)?
+        };
+        ctx.record_progress();
+        memory_usage.add(memory_usage_delta);
+        INDEXER_METRICS
+            .processing_micros
+            .with_label_values([&self.pipeline_id.index_id, "indexer"])
+            .inc_by(now.elapsed().as_micros() as u64);
         Ok(())
     }
 }
@@ -284,7 +383,7 @@ struct IndexingWorkbench {
     // Span for the in-memory indexing (done in the Indexer actor).
     _indexing_span: Span,
 
-    indexed_splits: FnvHashMap<u64, IndexedSplitBuilder>,
+    indexed_splits: FnvHashMap<SplitKey, IndexedSplitBuilder>,
     other_indexed_split_opt: Option<IndexedSplitBuilder>,
 
     checkpoint_delta: IndexCheckpointDelta,
@@ -292,14 +391,18 @@ struct IndexingWorkbench {
     // On workbench creation, we fetch from the metastore the last delete task opstamp.
     // We use this value to set the `delete_opstamp` of the workbench splits.
     last_delete_opstamp: u64,
-    // Number of bytes declared as used by tantivy.
-    memory_usage: Byte,
+    // Number of bytes declared as used by tantivy, accounted for in the node memory budget.
+    memory_usage: MemoryUsageTracker,
+    // Number of batches received by the workbench, used to detect idleness.
+    num_batches: u64,
 }
 
 pub struct Indexer {
     indexer_state: IndexerState,
     index_serializer_mailbox: Mailbox<IndexSerializer>,
     indexing_workbench_opt: Option<IndexingWorkbench>,
+    // Carried across workbenches so that duplicates are detected across splits.
+    doc_deduplicator_opt: Option<DocDeduplicator>,
     metastore: Arc<dyn Metastore>,
     counters: IndexerCounters,
 }
@@ -348,6 +451,80 @@ impl Actor for Indexer {
     }
 }
 
+/// Documents to be added by a split builder, grouped by split.
+type SplitBuilderJobs<'a> = Vec<(&'a mut IndexedSplitBuilder, Vec<PreparedDoc>)>;
+
+/// Adds the documents to their splits, running one thread per split builder, and returns the
+/// increase of the memory usage.
+fn add_docs_to_split_builders(
+    jobs_per_split_builder: Vec<SplitBuilderJobs>,
+) -> anyhow::Result<u64> {
+    let mut non_empty_jobs: Vec<SplitBuilderJobs> = jobs_per_split_builder
+        .into_iter()
+        .filter(|jobs| !jobs.is_empty())
+        .collect();
+    if non_empty_jobs.len() <= 1 {
+        return non_empty_jobs
+            .pop()
+            .map(add_docs_to_splits)
+            .unwrap_or(Ok(0));
+    }
+    std::thread::scope(|scope| {
+        let join_handles: Vec<_> = non_empty_jobs
+            .into_iter()
+            .map(|jobs| scope.spawn(move || add_docs_to_splits(jobs)))
+            .collect();
+        let mut memory_usage_delta: u64 = 0;
+        for join_handle in join_handles {
+            memory_usage_delta += join_handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("Split builder thread panicked."))??;
+        }
+        Ok(memory_usage_delta)
+    })
+}
+
+fn add_docs_to_splits(jobs: SplitBuilderJobs) -> anyhow::Result<u64> {
+    let mut memory_usage_delta: u64 = 0;
+    for (indexed_split, docs) in jobs {
+        let mem_usage_before = indexed_split.index_writer.mem_usage() as u64;
+        for prepared_doc in docs {
+            let PreparedDoc {
+                doc,
+                timestamp_opt,
+                num_bytes,
+                ..
+            } = prepared_doc;
+            indexed_split.split_attrs.uncompressed_docs_size_in_bytes += num_bytes as u64;
+            indexed_split.split_attrs.num_docs += 1;
+            if let Some(timestamp) = timestamp_opt {
+                record_timestamp(timestamp, &mut indexed_split.split_attrs.time_range);
+            }
+            indexed_split
+                .index_writer
+                .add_document(doc)
+                .context("Failed to add document.")?;
+        }
+        let mem_usage_after = indexed_split.index_writer.mem_usage() as u64;
+        memory_usage_delta += mem_usage_after.saturating_sub(mem_usage_before);
+    }
+    Ok(memory_usage_delta)
+}
+
+fn hash_routing_value(value: &Value) -> u64 {
+    let mut hasher = FnvHasher::default();
+    match value {
+        Value::Str(text) => text.hash(&mut hasher),
+        Value::U64(val) => val.hash(&mut hasher),
+        Value::I64(val) => val.hash(&mut hasher),
+        Value::F64(val) => val.to_bits().hash(&mut hasher),
+        Value::Bool(val) => val.hash(&mut hasher),
+        Value::Bytes(bytes) => bytes.hash(&mut hasher),
+        other => format!("{other:?}").hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
 fn record_timestamp(timestamp: DateTime, time_range: &mut Option<RangeInclusive<DateTime>>) {
     let new_timestamp_range = match time_range {
         Some(range) => timestamp.min(*range.start())..=timestamp.max(*range.end()),
@@ -376,6 +553,53 @@ impl Handler<CommitTimeout> for Indexer {
     }
 }
 
+#[async_trait]
+impl Handler<ForceCommit> for Indexer {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _force_commit: ForceCommit,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.send_to_serializer(CommitTrigger::Forced, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<IdleCheck> for Indexer {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        idle_check: IdleCheck,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let Some(indexing_workbench) = &self.indexing_workbench_opt else {
+            return Ok(());
+        };
+        // If this is a check for a different workbench, we must ignore it.
+        if indexing_workbench.workbench_id != idle_check.workbench_id {
+            return Ok(());
+        }
+        if indexing_workbench.num_batches == idle_check.num_batches {
+            self.send_to_serializer(CommitTrigger::Idle, ctx).await?;
+            return Ok(());
+        }
+        if let Some(min_commit_timeout) = self.indexer_state.indexing_settings.min_commit_timeout()
+        {
+            let idle_check_message = IdleCheck {
+                workbench_id: indexing_workbench.workbench_id,
+                num_batches: indexing_workbench.num_batches,
+            };
+            ctx.schedule_self_msg(min_commit_timeout, idle_check_message)
+                .await;
+        }
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<PreparedDocBatch> for Indexer {
     type Reply = ();
@@ -400,6 +624,10 @@ impl Handler<NewPublishLock> for Indexer {
     ) -> Result<(), ActorExitStatus> {
         let NewPublishLock(publish_lock) = message;
         self.indexing_workbench_opt = None;
+        // The documents of the discarded workbench will be sent again: we must forget their IDs.
+        if let Some(doc_deduplicator) = self.doc_deduplicator_opt.as_mut() {
+            doc_deduplicator.clear();
+        }
         self.indexer_state.publish_lock = publish_lock;
         Ok(())
     }
@@ -412,6 +640,7 @@ impl Indexer {
         metastore: Arc<dyn Metastore>,
         indexing_directory: ScratchDirectory,
         indexing_settings: IndexingSettings,
+        memory_budget: IndexingMemoryBudget,
         index_serializer_mailbox: Mailbox<IndexSerializer>,
     ) -> Self {
         let schema = doc_mapper.schema();
@@ -424,6 +653,21 @@ impl Indexer {
             docstore_compress_dedicated_thread: true,
             sort_by_field: None,
         };
+        let doc_deduplicator_opt = doc_mapper
+            .doc_unique_id_field_name()
+            .zip(indexing_settings.deduplication_window())
+            .and_then(|(doc_unique_id_field_name, deduplication_window)| {
+                let doc_unique_id_field = schema.get_field(doc_unique_id_field_name).ok()?;
+                Some(DocDeduplicator::new(
+                    doc_unique_id_field,
+                    deduplication_window,
+                ))
+            });
+        let num_split_builders = indexing_settings.num_split_builders().max(1);
+        let routing_field_opt = indexing_settings
+            .split_builder_routing_field
+            .as_ref()
+            .and_then(|field_name| schema.get_field(field_name).ok());
         let publish_lock = PublishLock::default();
         Self {
             indexer_state: IndexerState {
@@ -433,11 +677,17 @@ impl Indexer {
                 indexing_settings,
                 publish_lock,
                 schema,
+                tokenizer_manager: doc_mapper.tokenizer_manager().clone(),
                 index_settings,
                 max_num_partitions: doc_mapper.max_num_partitions(),
+                doc_mapping_version: doc_mapper.doc_mapping_version(),
+                num_split_builders,
+                routing_field_opt,
+                memory_budget,
             },
             index_serializer_mailbox,
             indexing_workbench_opt: None,
+            doc_deduplicator_opt,
             metastore,
             counters: IndexerCounters::default(),
         }
@@ -445,7 +695,7 @@ impl Indexer {
 
     fn memory_usage(&self) -> Byte {
         if let Some(workbench) = &self.indexing_workbench_opt {
-            workbench.memory_usage
+            workbench.memory_usage.memory_usage()
         } else {
             Byte::from_bytes(0)
         }
@@ -461,6 +711,7 @@ impl Indexer {
             .index_batch(
                 batch,
                 &mut self.indexing_workbench_opt,
+                &mut self.doc_deduplicator_opt,
                 &mut self.counters,
                 ctx,
             )
@@ -469,6 +720,19 @@ impl Indexer {
             self.send_to_serializer(CommitTrigger::MemoryLimit, ctx)
                 .await?;
         }
+        if self.indexing_workbench_opt.is_some()
+            && self.indexer_state.memory_budget.is_nearly_exhausted()
+        {
+            INDEXER_METRICS
+                .memory_budget_commits_total
+                .with_label_values([
+                    &self.indexer_state.pipeline_id.index_id,
+                    &self.indexer_state.pipeline_id.source_id,
+                ])
+                .inc();
+            self.send_to_serializer(CommitTrigger::MemoryLimit, ctx)
+                .await?;
+        }
         if self.counters.num_docs_in_workbench
             >= self.indexer_state.indexing_settings.split_num_docs_target as u64
         {
@@ -502,6 +766,19 @@ impl Indexer {
         if let Some(other_split) = other_indexed_split_opt {
             splits.push(other_split)
         }
+        let source_partitions = checkpoint_delta
+            .source_delta
+            .partitions()
+            .map(|partition_id| partition_id.0.as_str())
+            .join(",");
+        if !source_partitions.is_empty() {
+            for split in &mut splits {
+                split
+                    .split_attrs
+                    .custom_metadata
+                    .insert(SOURCE_PARTITIONS_METADATA_KEY.to_string(), source_partitions.clone());
+            }
+        }
 
         // Avoid producing empty split, but still update the checkpoint to avoid
         // reprocessing the same faulty documents.
@@ -554,6 +831,7 @@ impl Indexer {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::fmt::Write;
     use std::sync::Arc;
     use std::time::Duration;
@@ -613,6 +891,9 @@ mod tests {
         let indexing_directory = ScratchDirectory::for_test();
         let mut indexing_settings = IndexingSettings::for_test();
         indexing_settings.split_num_docs_target = 3;
+        indexing_settings
+            .split_metadata
+            .insert("pipeline_version".to_string(), "v2".to_string());
         let universe = Universe::with_accelerated_time();
         let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
         let mut metastore = MockMetastore::default();
@@ -630,6 +911,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -704,6 +986,7 @@ mod tests {
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
                 num_docs_in_workbench: 1, //< the num docs in split counter has been reset.
+                num_duplicate_docs: 0,
             }
         );
         let messages: Vec<IndexedSplitBatchBuilder> = index_serializer_inbox.drain_for_test_typed();
@@ -715,6 +998,10 @@ mod tests {
             batch.splits[0].split_attrs.delete_opstamp,
             last_delete_opstamp
         );
+        assert_eq!(
+            batch.splits[0].split_attrs.custom_metadata,
+            BTreeMap::from_iter([("pipeline_version".to_string(), "v2".to_string())])
+        );
         let index_checkpoint = batch.checkpoint_delta.unwrap();
         assert_eq!(index_checkpoint.source_id, "test-source");
         assert_eq!(
@@ -759,6 +1046,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, _indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -801,6 +1089,64 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_trigger_on_memory_budget() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let indexing_directory = ScratchDirectory::for_test();
+        let indexing_settings = IndexingSettings::for_test();
+        let memory_budget = IndexingMemoryBudget::new(Some(Byte::from_bytes(1)));
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(|_| Ok(10));
+        metastore.expect_publish_splits().never();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            memory_budget.clone(),
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        indexer_mailbox
+            .send_message(PreparedDocBatch {
+                docs: vec![PreparedDoc {
+                    doc: doc!(body_field=>"this is a test document"),
+                    timestamp_opt: None,
+                    partition: 0,
+                    num_bytes: 23,
+                }],
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..1),
+            })
+            .await?;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_split_batches_emitted, 1);
+        assert_eq!(memory_budget.num_bytes_used(), 0);
+
+        let output_messages: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(output_messages.len(), 1);
+        assert_eq!(
+            output_messages[0].commit_trigger,
+            CommitTrigger::MemoryLimit
+        );
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_on_timeout() -> anyhow::Result<()> {
         let universe = Universe::with_accelerated_time();
@@ -833,6 +1179,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -858,6 +1205,7 @@ mod tests {
                 num_splits_emitted: 0,
                 num_split_batches_emitted: 0,
                 num_docs_in_workbench: 1,
+                num_duplicate_docs: 0,
             }
         );
         universe.sleep(Duration::from_secs(61)).await;
@@ -868,6 +1216,7 @@ mod tests {
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
                 num_docs_in_workbench: 0,
+                num_duplicate_docs: 0,
             }
         );
         let indexed_split_batches: Vec<IndexedSplitBatchBuilder> =
@@ -888,6 +1237,76 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_adaptive_commit_on_idle() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let indexing_directory = ScratchDirectory::for_test();
+        let indexing_settings = IndexingSettings {
+            commit_timeout_secs: 60,
+            min_commit_timeout_secs: Some(5),
+            ..IndexingSettings::for_test()
+        };
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .once()
+            .returning(|_| Ok(10));
+        metastore.expect_publish_splits().never();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            IndexingMemoryBudget::unlimited(),
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        for (batch_ord, sleep_secs) in [(0, 4), (1, 3)] {
+            indexer_mailbox
+                .send_message(PreparedDocBatch {
+                    docs: vec![PreparedDoc {
+                        doc: doc!(body_field=>format!("this is a test document {batch_ord}")),
+                        timestamp_opt: None,
+                        partition: 1,
+                        num_bytes: 30,
+                    }],
+                    checkpoint_delta: SourceCheckpointDelta::from_range(batch_ord..batch_ord + 1),
+                })
+                .await
+                .unwrap();
+            indexer_handle.process_pending_and_observe().await;
+            universe.sleep(Duration::from_secs(sleep_secs)).await;
+        }
+        // The source is still active: the first idle check is postponed.
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_split_batches_emitted, 0);
+        assert_eq!(indexer_counters.num_docs_in_workbench, 2);
+
+        universe.sleep(Duration::from_secs(4)).await;
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(indexer_counters.num_split_batches_emitted, 1);
+        assert_eq!(indexer_counters.num_docs_in_workbench, 0);
+
+        let indexed_split_batches: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(indexed_split_batches.len(), 1);
+        assert_eq!(indexed_split_batches[0].commit_trigger, CommitTrigger::Idle);
+        assert_eq!(indexed_split_batches[0].splits[0].split_attrs.num_docs, 2);
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_eof() -> anyhow::Result<()> {
         let universe = Universe::with_accelerated_time();
@@ -919,6 +1338,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -946,6 +1366,7 @@ mod tests {
                 num_splits_emitted: 1,
                 num_split_batches_emitted: 1,
                 num_docs_in_workbench: 0,
+                num_duplicate_docs: 0,
             }
         );
         let output_messages: Vec<IndexedSplitBatchBuilder> =
@@ -1000,6 +1421,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -1036,6 +1458,7 @@ mod tests {
                 num_docs_in_workbench: 2,
                 num_splits_emitted: 0,
                 num_split_batches_emitted: 0,
+                num_duplicate_docs: 0,
             }
         );
         universe.send_exit_with_success(&indexer_mailbox).await?;
@@ -1047,6 +1470,7 @@ mod tests {
                 num_docs_in_workbench: 0,
                 num_splits_emitted: 2,
                 num_split_batches_emitted: 1,
+                num_duplicate_docs: 0,
             }
         );
         let split_batches: Vec<IndexedSplitBatchBuilder> =
@@ -1092,6 +1516,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -1133,6 +1558,165 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_indexer_concurrent_split_builders() {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> =
+            Arc::new(serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_SIMPLE_JSON).unwrap());
+        let body_field = doc_mapper.schema().get_field("body").unwrap();
+        let indexing_directory = ScratchDirectory::for_test();
+        let indexing_settings = IndexingSettings {
+            num_split_builders: Some(2),
+            ..IndexingSettings::for_test()
+        };
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(move |index_id| {
+                assert_eq!("test-index", index_id);
+                Ok(10)
+            });
+        metastore.expect_publish_splits().never();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            IndexingMemoryBudget::unlimited(),
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        let docs = (0..5)
+            .map(|i| PreparedDoc {
+                doc: doc!(body_field=>format!("doc {i}")),
+                timestamp_opt: None,
+                partition: 0,
+                num_bytes: 30,
+            })
+            .collect();
+        indexer_mailbox
+            .send_message(PreparedDocBatch {
+                docs,
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..5),
+            })
+            .await
+            .unwrap();
+        universe
+            .send_exit_with_success(&indexer_mailbox)
+            .await
+            .unwrap();
+
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_splits_emitted, 2);
+        assert_eq!(indexer_counters.num_split_batches_emitted, 1);
+
+        let index_serializer_msgs: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(index_serializer_msgs.len(), 1);
+        let msg = index_serializer_msgs.into_iter().next().unwrap();
+        let mut num_docs_per_split: Vec<u64> = msg
+            .splits
+            .iter()
+            .map(|split| split.split_attrs.num_docs)
+            .collect();
+        num_docs_per_split.sort();
+        assert_eq!(num_docs_per_split, vec![2, 3]);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_indexer_drops_duplicate_docs() {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> = Arc::new(
+            serde_json::from_str::<DefaultDocMapper>(
+                r#"{
+                    "doc_unique_id_field": "request_id",
+                    "field_mappings": [
+                        {"name": "request_id", "type": "text", "tokenizer": "raw"},
+                        {"name": "body", "type": "text"}
+                    ]
+                }"#,
+            )
+            .unwrap(),
+        );
+        let schema = doc_mapper.schema();
+        let request_id_field = schema.get_field("request_id").unwrap();
+        let body_field = schema.get_field("body").unwrap();
+        let indexing_directory = ScratchDirectory::for_test();
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.split_num_docs_target = 2;
+        indexing_settings.deduplication_window_secs = Some(3_600);
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .returning(move |_index_id| Ok(10));
+        metastore.expect_publish_splits().never();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            IndexingMemoryBudget::unlimited(),
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+
+        let request_ids = ["req-1", "req-2", "req-1", "req-3", "req-2"];
+        for (position, request_id) in request_ids.iter().enumerate() {
+            indexer_mailbox
+                .send_message(PreparedDocBatch {
+                    docs: vec![PreparedDoc {
+                        doc: doc!(request_id_field=>*request_id, body_field=>"body"),
+                        timestamp_opt: None,
+                        partition: 0,
+                        num_bytes: 30,
+                    }],
+                    checkpoint_delta: SourceCheckpointDelta::from_range(
+                        position as u64..position as u64 + 1,
+                    ),
+                })
+                .await
+                .unwrap();
+        }
+        universe
+            .send_exit_with_success(&indexer_mailbox)
+            .await
+            .unwrap();
+
+        let (exit_status, indexer_counters) = indexer_handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Success));
+        assert_eq!(indexer_counters.num_duplicate_docs, 2);
+        assert_eq!(indexer_counters.num_splits_emitted, 2);
+
+        let index_serializer_msgs: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        let num_docs: u64 = index_serializer_msgs
+            .iter()
+            .flat_map(|batch| batch.splits.iter())
+            .map(|split| split.split_attrs.num_docs)
+            .sum();
+        assert_eq!(num_docs, 3);
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_indexer_propagates_publish_lock() {
         let universe = Universe::with_accelerated_time();
@@ -1164,6 +1748,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -1237,6 +1822,7 @@ mod tests {
             Arc::new(metastore),
             indexing_directory,
             indexing_settings,
+            IndexingMemoryBudget::unlimited(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
@@ -30,7 +30,7 @@ use quickwit_common::KillSwitch;
 use quickwit_config::{IndexingSettings, SourceConfig};
 use quickwit_doc_mapper::DocMapper;
 use quickwit_metastore::{Metastore, MetastoreError};
-use quickwit_storage::Storage;
+use quickwit_storage::{Storage, StorageUriResolver};
 use tokio::join;
 use tokio::sync::Semaphore;
 use tracing::{debug, error, info, instrument};
@@ -38,11 +38,15 @@ use tracing::{debug, error, info, instrument};
 use super::MergePlanner;
 use crate::actors::doc_processor::DocProcessor;
 use crate::actors::index_serializer::IndexSerializer;
+use crate::actors::indexer::ForceCommit;
 use crate::actors::publisher::PublisherType;
 use crate::actors::sequencer::Sequencer;
 use crate::actors::uploader::UploaderType;
 use crate::actors::{Indexer, Packager, Publisher, Uploader};
-use crate::models::{IndexingPipelineId, IndexingStatistics, Observe, ScratchDirectory};
+use crate::dead_letter_queue::DeadLetterQueue;
+use crate::models::{
+    IndexingMemoryBudget, IndexingPipelineId, IndexingStatistics, Observe, ScratchDirectory,
+};
 use crate::source::{quickwit_supported_sources, SourceActor, SourceExecutionContext};
 use crate::split_store::IndexingSplitStore;
 use crate::SplitsUpdateMailbox;
@@ -90,6 +94,11 @@ pub struct Spawn {
     retry_count: usize,
 }
 
+/// Pauses or resumes the source of the pipeline. The source remains paused across the respawns
+/// of the pipeline.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SetSourcePaused(pub bool);
+
 pub struct IndexingPipeline {
     params: IndexingPipelineParams,
     previous_generations_statistics: IndexingStatistics,
@@ -97,6 +106,7 @@ pub struct IndexingPipeline {
     handles: Option<IndexingPipelineHandles>,
     // Killswitch used for the actors in the pipeline. This is not the supervisor killswitch.
     kill_switch: KillSwitch,
+    is_source_paused: bool,
 }
 
 #[async_trait]
@@ -156,6 +166,7 @@ impl IndexingPipeline {
             handles: None,
             kill_switch: KillSwitch::default(),
             statistics: IndexingStatistics::default(),
+            is_source_paused: false,
         }
     }
 
@@ -267,7 +278,7 @@ impl IndexingPipeline {
         let publisher = Publisher::new(
             PublisherType::MainPublisher,
             self.params.metastore.clone(),
-            Some(self.params.merge_planner_mailbox.clone()),
+            self.params.merge_planner_mailbox_opt.clone(),
             Some(source_mailbox.clone()),
         );
         let (publisher_mailbox, publisher_handle) = ctx
@@ -311,7 +322,17 @@ impl IndexingPipeline {
 
         // Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("Packager", tag_fields, uploader_mailbox);
+        let range_pruning_fields = self.params.doc_mapper.range_pruning_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let vector_fields = self.params.doc_mapper.vector_fields();
+        let packager = Packager::new(
+            "Packager",
+            tag_fields,
+            range_pruning_fields,
+            bloom_filter_fields,
+            vector_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_handle) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -331,6 +352,7 @@ impl IndexingPipeline {
             self.params.metastore.clone(),
             self.params.indexing_directory.clone(),
             self.params.indexing_settings.clone(),
+            self.params.memory_budget.clone(),
             index_serializer_mailbox,
         );
         let (indexer_mailbox, indexer_handle) = ctx
@@ -343,13 +365,26 @@ impl IndexingPipeline {
             .set_kill_switch(self.kill_switch.clone())
             .spawn(indexer);
 
-        let doc_processor = DocProcessor::try_new(
+        let mut doc_processor = DocProcessor::try_new(
             index_id.to_string(),
             source_id.to_string(),
             self.params.doc_mapper.clone(),
             indexer_mailbox,
             self.params.source_config.transform_config.clone(),
         )?;
+        if let Some(dead_letter_config) = &self.params.indexing_settings.dead_letter {
+            let dead_letter_queue = ctx
+                .protect_future(DeadLetterQueue::try_new(
+                    index_id.to_string(),
+                    source_id.to_string(),
+                    dead_letter_config,
+                    &*self.params.metastore,
+                    &self.params.queues_dir_path,
+                    &self.params.storage_resolver,
+                ))
+                .await?;
+            doc_processor = doc_processor.with_dead_letter_queue(dead_letter_queue);
+        }
         let (doc_processor_mailbox, doc_processor_handle) = ctx
             .spawn_actor()
             .set_backpressure_micros_counter(
@@ -374,8 +409,11 @@ impl IndexingPipeline {
                 Arc::new(SourceExecutionContext {
                     metastore: self.params.metastore.clone(),
                     index_id: self.params.pipeline_id.index_id.clone(),
+                    node_id: self.params.pipeline_id.node_id.clone(),
+                    pipeline_ord: self.params.pipeline_id.pipeline_ord,
                     queues_dir_path: self.params.queues_dir_path.clone(),
                     source_config: self.params.source_config.clone(),
+                    storage_resolver: self.params.storage_resolver.clone(),
                 }),
                 source_checkpoint,
             ))
@@ -389,6 +427,9 @@ impl IndexingPipeline {
             .set_mailboxes(source_mailbox, source_inbox)
             .set_kill_switch(self.kill_switch.clone())
             .spawn(actor_source);
+        if self.is_source_paused {
+            source_handle.pause();
+        }
 
         // Increment generation once we are sure there will be no spawning error.
         self.previous_generations_statistics = self.statistics.clone();
@@ -452,6 +493,53 @@ impl Handler<Observe> for IndexingPipeline {
     }
 }
 
+#[async_trait]
+impl Handler<SetSourcePaused> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: SetSourcePaused,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let SetSourcePaused(is_source_paused) = message;
+        self.is_source_paused = is_source_paused;
+        if let Some(handles) = &self.handles {
+            if is_source_paused {
+                handles.source.pause();
+            } else {
+                handles.source.resume();
+            }
+        }
+        info!(
+            pipeline_id=?self.params.pipeline_id,
+            is_source_paused=is_source_paused,
+            "Updated indexing pipeline source state."
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<ForceCommit> for IndexingPipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        force_commit: ForceCommit,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(handles) = &self.handles {
+            // The indexer may have exited in the meantime, in which case there is nothing left
+            // to commit.
+            let _ = ctx
+                .send_message(handles.indexer.mailbox(), force_commit)
+                .await;
+        }
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<Supervise> for IndexingPipeline {
     type Reply = ();
@@ -523,10 +611,14 @@ pub struct IndexingPipelineParams {
     pub source_config: SourceConfig,
     pub metastore: Arc<dyn Metastore>,
     pub storage: Arc<dyn Storage>,
+    pub storage_resolver: StorageUriResolver,
     pub split_store: IndexingSplitStore,
     pub max_concurrent_split_uploads_index: usize,
     pub max_concurrent_split_uploads_merge: usize,
-    pub merge_planner_mailbox: Mailbox<MergePlanner>,
+    pub memory_budget: IndexingMemoryBudget,
+    /// Merge planner notified of the published splits. `None` when the splits are merged by
+    /// merger nodes.
+    pub merge_planner_mailbox_opt: Option<Mailbox<MergePlanner>>,
 }
 
 #[cfg(test)]
@@ -544,7 +636,7 @@ mod tests {
     use super::{IndexingPipeline, *};
     use crate::actors::merge_pipeline::{MergePipeline, MergePipelineParams};
     use crate::merge_policy::default_merge_policy;
-    use crate::models::ScratchDirectory;
+    use crate::models::{IndexingMemoryBudget, ScratchDirectory};
 
     #[test]
     fn test_wait_duration() {
@@ -629,11 +721,13 @@ mod tests {
             indexing_settings: IndexingSettings::for_test(),
             metastore: metastore.clone(),
             storage,
+            storage_resolver: StorageUriResolver::for_test(),
             split_store,
             queues_dir_path: PathBuf::from("./queues"),
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
-            merge_planner_mailbox,
+            memory_budget: IndexingMemoryBudget::unlimited(),
+            merge_planner_mailbox_opt: Some(merge_planner_mailbox),
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handle) = universe.spawn_builder().spawn(pipeline);
@@ -720,10 +814,12 @@ mod tests {
             metastore: metastore.clone(),
             queues_dir_path: PathBuf::from("./queues"),
             storage,
+            storage_resolver: StorageUriResolver::for_test(),
             split_store,
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
-            merge_planner_mailbox,
+            memory_budget: IndexingMemoryBudget::unlimited(),
+            merge_planner_mailbox_opt: Some(merge_planner_mailbox),
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
@@ -778,6 +874,7 @@ mod tests {
             merge_policy: default_merge_policy(),
             max_concurrent_split_uploads: 2,
             merge_max_io_num_bytes_per_sec: None,
+            split_refresh_interval_opt: None,
         };
         let merge_pipeline = MergePipeline::new(merge_pipeline_params, universe.spawn_ctx());
         let merge_planner_mailbox = merge_pipeline.merge_planner_mailbox().clone();
@@ -792,10 +889,12 @@ mod tests {
             metastore: metastore.clone(),
             queues_dir_path: PathBuf::from("./queues"),
             storage,
+            storage_resolver: StorageUriResolver::for_test(),
             split_store,
             max_concurrent_split_uploads_index: 4,
             max_concurrent_split_uploads_merge: 5,
-            merge_planner_mailbox: merge_planner_mailbox.clone(),
+            memory_budget: IndexingMemoryBudget::unlimited(),
+            merge_planner_mailbox_opt: Some(merge_planner_mailbox.clone()),
         };
         let indexing_pipeline = IndexingPipeline::new(indexing_pipeline_params);
         let (_indexing_pipeline_mailbox, indexing_pipeline_handler) =
@@ -17,9 +17,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -34,27 +35,43 @@ use quickwit_common::fs::get_cache_directory_path;
 use quickwit_config::{
     build_doc_mapper, IndexConfig, IndexerConfig, SourceConfig, INGEST_API_SOURCE_ID,
 };
-use quickwit_ingest_api::{DropQueueRequest, IngestApiService, ListQueuesRequest, QUEUES_DIR_NAME};
+use quickwit_ingest_api::{
+    parse_replica_queue_id, parse_shard_queue_id, DropQueueRequest, GetIngestLags,
+    IngestApiService, ListQueuesRequest, SetNumShards, QUEUES_DIR_NAME,
+};
+use quickwit_metastore::checkpoint::SourceCheckpoint;
 use quickwit_metastore::{IndexMetadata, Metastore, MetastoreError};
 use quickwit_proto::indexing_api::{ApplyIndexingPlanRequest, IndexingTask};
 use quickwit_proto::{ServiceError, ServiceErrorCode};
-use quickwit_storage::{StorageError, StorageResolverError, StorageUriResolver};
+use quickwit_storage::{Storage, StorageError, StorageResolverError, StorageUriResolver};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
-use super::merge_pipeline::{MergePipeline, MergePipelineParams};
+use super::indexer::ForceCommit;
+use super::indexing_pipeline::SetSourcePaused;
+use super::merge_pipeline::{MergePipeline, MergePipelineParams, ThrottleMerges};
+use super::merge_planner::PlanMerge;
 use super::MergePlanner;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::{
-    DetachIndexingPipeline, DetachMergePipeline, IndexingPipelineId, Observe, ObservePipeline,
-    ScratchDirectory, SpawnPipeline, WeakScratchDirectory,
+    ControlPipelines, DetachIndexingPipeline, DetachMergePipeline, DrainPipelines,
+    IndexingMemoryBudget, IndexingPipelineId, ListPipelines, MergeStatistics, Observe,
+    ObservePipeline, PipelineCommand, RefreshIndexConfigs, ScratchDirectory, SpawnPipeline,
+    WeakScratchDirectory,
+};
+use crate::split_store::{
+    LocalSplitStore, SplitStoreQuota, UploadJournal, UPLOAD_JOURNAL_DIR_NAME,
 };
-use crate::split_store::{LocalSplitStore, SplitStoreQuota};
 use crate::{IndexingPipeline, IndexingPipelineParams, IndexingSplitStore, IndexingStatistics};
 
 /// Name of the indexing directory, usually located at `<data_dir_path>/indexing`.
 pub const INDEXING_DIR_NAME: &str = "indexing";
 
+/// Interval at which the merge pipelines of the merge tasks fetch the splits published by the
+/// indexers.
+const MERGE_TASK_SPLIT_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Error, Debug)]
 pub enum IndexingServiceError {
     #[error("Indexing pipeline `{index_id}` for source `{source_id}` does not exist.")]
@@ -104,6 +121,42 @@ pub struct IndexingServiceCounters {
     pub num_delete_queue_failures: usize,
 }
 
+/// Actors of the indexing pipeline whose backpressure is reported in the pipeline status.
+const BACKPRESSURED_ACTOR_NAMES: [&str; 5] = [
+    "doc_processor",
+    "indexer",
+    "uploader",
+    "sequencer",
+    "publisher",
+];
+
+/// Actors of the indexing pipeline whose processing time is reported in the pipeline status.
+const PROCESSING_ACTOR_NAMES: [&str; 2] = ["doc_processor", "indexer"];
+
+/// Status of an indexing pipeline running on the node.
+#[derive(Clone, Debug, Serialize, utoipa::ToSchema)]
+pub struct IndexingPipelineStatus {
+    pub index_id: String,
+    pub source_id: String,
+    pub pipeline_ord: usize,
+    /// Whether the source of the pipeline was paused by an operator.
+    pub is_source_paused: bool,
+    pub statistics: IndexingStatistics,
+    /// Statistics of the merge pipeline of the index and source.
+    #[schema(value_type = Object)]
+    pub merge_statistics: Option<MergeStatistics>,
+    /// Checkpoint of the source published in the metastore.
+    #[schema(value_type = Object)]
+    pub checkpoint: SourceCheckpoint,
+    /// Time spent by the actors waiting for room in the queue of their downstream actor, in
+    /// microseconds. The time is accumulated over all the pipelines of the index running on the
+    /// node.
+    pub backpressure_micros: BTreeMap<String, u64>,
+    /// Time spent by the actors processing batches of documents, in microseconds. The time is
+    /// accumulated over all the pipelines of the index running on the node.
+    pub processing_micros: BTreeMap<String, u64>,
+}
+
 type IndexId = String;
 type SourceId = String;
 
@@ -137,9 +190,22 @@ pub struct IndexingService {
     indexing_pipeline_handles: HashMap<IndexingPipelineId, ActorHandle<IndexingPipeline>>,
     counters: IndexingServiceCounters,
     indexing_directories: HashMap<(IndexId, SourceId), WeakScratchDirectory>,
+    /// Upload journals of the index sources, opened and recovered when the first pipeline of the
+    /// index source is spawned.
+    upload_journals: HashMap<(IndexId, SourceId), UploadJournal>,
     local_split_store: Arc<LocalSplitStore>,
     max_concurrent_split_uploads: usize,
+    memory_budget: IndexingMemoryBudget,
     merge_pipeline_handles: HashMap<MergePipelineId, MergePipelineHandle>,
+    paused_pipeline_ids: HashSet<IndexingPipelineId>,
+    /// Config of the indexes the running pipelines were spawned with.
+    index_configs: HashMap<IndexId, IndexConfig>,
+    /// Whether the splits of the indexing pipelines are merged by merger nodes, in which case the
+    /// indexing pipelines run without merge pipeline.
+    offload_merges: bool,
+    /// Merge pipelines assigned to the node as a merger, which merge the splits of their index
+    /// and source published by any node.
+    merge_task_ids: HashSet<MergePipelineId>,
 }
 
 impl IndexingService {
@@ -170,8 +236,14 @@ impl IndexingService {
             indexing_pipeline_handles: Default::default(),
             counters: Default::default(),
             indexing_directories: HashMap::new(),
+            upload_journals: HashMap::new(),
             max_concurrent_split_uploads: indexer_config.max_concurrent_split_uploads,
+            memory_budget: IndexingMemoryBudget::new(indexer_config.max_indexing_memory_usage),
             merge_pipeline_handles: HashMap::new(),
+            paused_pipeline_ids: HashSet::new(),
+            index_configs: HashMap::new(),
+            offload_merges: false,
+            merge_task_ids: HashSet::new(),
         })
     }
 
@@ -186,6 +258,7 @@ impl IndexingService {
                 index_id: pipeline_id.index_id.clone(),
                 source_id: pipeline_id.source_id.clone(),
             })?;
+        self.paused_pipeline_ids.remove(pipeline_id);
         self.counters.num_running_pipelines -= 1;
         Ok(pipeline_handle)
     }
@@ -264,33 +337,43 @@ impl IndexingService {
         let queues_dir_path = self.data_dir_path.join(QUEUES_DIR_NAME);
         let merge_policy =
             crate::merge_policy::merge_policy_from_settings(&index_config.indexing_settings);
+        let upload_journal = self
+            .get_or_open_upload_journal(&pipeline_id, &*storage)
+            .await?;
         let split_store = IndexingSplitStore::new(
             storage.clone(),
             merge_policy.clone(),
             self.local_split_store.clone(),
-        );
+        )
+        .with_upload_journal(upload_journal)
+        .with_split_storage_layout(index_config.indexing_settings.split_storage_layout);
 
         let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
             .map_err(IndexingServiceError::InvalidParams)?;
 
-        let merge_pipeline_params = MergePipelineParams {
-            pipeline_id: pipeline_id.clone(),
-            doc_mapper: doc_mapper.clone(),
-            indexing_directory: indexing_directory.clone(),
-            metastore: self.metastore.clone(),
-            split_store: split_store.clone(),
-            merge_policy,
-            merge_max_io_num_bytes_per_sec: index_config
-                .indexing_settings
-                .resources
-                .max_merge_write_throughput,
-            max_concurrent_split_uploads: self.max_concurrent_split_uploads,
+        let merge_planner_mailbox_opt = if self.offload_merges {
+            None
+        } else {
+            let merge_pipeline_params = MergePipelineParams {
+                pipeline_id: pipeline_id.clone(),
+                doc_mapper: doc_mapper.clone(),
+                indexing_directory: indexing_directory.clone(),
+                metastore: self.metastore.clone(),
+                split_store: split_store.clone(),
+                merge_policy,
+                merge_max_io_num_bytes_per_sec: index_config
+                    .indexing_settings
+                    .resources
+                    .max_merge_write_throughput,
+                max_concurrent_split_uploads: self.max_concurrent_split_uploads,
+                split_refresh_interval_opt: None,
+            };
+            let merge_planner_mailbox = self
+                .get_or_create_merge_pipeline(merge_pipeline_params, ctx)
+                .await?;
+            Some(merge_planner_mailbox)
         };
 
-        let merge_planner_mailbox = self
-            .get_or_create_merge_pipeline(merge_pipeline_params, ctx)
-            .await?;
-
         // The concurrent uploads budget is split in 2: 1/2 for the indexing pipeline, 1/2 for the
         // merge pipeline.
         let max_concurrent_split_uploads_index = (self.max_concurrent_split_uploads / 2).max(1);
@@ -304,20 +387,78 @@ impl IndexingService {
             indexing_directory,
             metastore: self.metastore.clone(),
             storage,
+            storage_resolver: self.storage_resolver.clone(),
             split_store,
             max_concurrent_split_uploads_index,
             max_concurrent_split_uploads_merge,
+            memory_budget: self.memory_budget.clone(),
             queues_dir_path,
-            merge_planner_mailbox,
+            merge_planner_mailbox_opt,
         };
         let pipeline = IndexingPipeline::new(pipeline_params);
         let (_pipeline_mailbox, pipeline_handle) = ctx.spawn_actor().spawn(pipeline);
+        self.index_configs
+            .insert(pipeline_id.index_id.clone(), index_config);
         self.indexing_pipeline_handles
             .insert(pipeline_id, pipeline_handle);
         self.counters.num_running_pipelines += 1;
         Ok(())
     }
 
+    /// Spawns the merge pipeline of a merge task, which merges the splits of the index and source
+    /// published by any node.
+    async fn spawn_merge_task(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        merge_task_id: MergePipelineId,
+        index_config: &IndexConfig,
+    ) -> Result<(), IndexingServiceError> {
+        let pipeline_id = IndexingPipelineId {
+            node_id: self.node_id.clone(),
+            index_id: merge_task_id.index_id.clone(),
+            source_id: merge_task_id.source_id.clone(),
+            pipeline_ord: 0,
+        };
+        let indexing_dir_path = self.data_dir_path.join(INDEXING_DIR_NAME);
+        let indexing_directory = self
+            .get_or_create_indexing_directory(&pipeline_id, indexing_dir_path)
+            .await?;
+        let storage = self.storage_resolver.resolve(&index_config.index_uri)?;
+        let merge_policy =
+            crate::merge_policy::merge_policy_from_settings(&index_config.indexing_settings);
+        let upload_journal = self
+            .get_or_open_upload_journal(&pipeline_id, &*storage)
+            .await?;
+        let split_store = IndexingSplitStore::new(
+            storage,
+            merge_policy.clone(),
+            self.local_split_store.clone(),
+        )
+        .with_upload_journal(upload_journal)
+        .with_split_storage_layout(index_config.indexing_settings.split_storage_layout);
+        let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+            .map_err(IndexingServiceError::InvalidParams)?;
+
+        let merge_pipeline_params = MergePipelineParams {
+            pipeline_id,
+            doc_mapper,
+            indexing_directory,
+            metastore: self.metastore.clone(),
+            split_store,
+            merge_policy,
+            merge_max_io_num_bytes_per_sec: index_config
+                .indexing_settings
+                .resources
+                .max_merge_write_throughput,
+            max_concurrent_split_uploads: self.max_concurrent_split_uploads,
+            split_refresh_interval_opt: Some(MERGE_TASK_SPLIT_REFRESH_INTERVAL),
+        };
+        self.get_or_create_merge_pipeline(merge_pipeline_params, ctx)
+            .await?;
+        self.merge_task_ids.insert(merge_task_id);
+        Ok(())
+    }
+
     async fn index_metadata(
         &self,
         ctx: &ActorContext<Self>,
@@ -359,12 +500,24 @@ impl IndexingService {
                     }
                 },
             );
+        let indexing_pipeline_handles = &self.indexing_pipeline_handles;
+        self.paused_pipeline_ids
+            .retain(|pipeline_id| indexing_pipeline_handles.contains_key(pipeline_id));
+        self.index_configs.retain(|index_id, _| {
+            indexing_pipeline_handles
+                .keys()
+                .any(|pipeline_id| &pipeline_id.index_id == index_id)
+        });
         // Evict and kill merge pipelines that are not needed.
-        let needed_merge_pipeline_ids: HashSet<MergePipelineId> = self
-            .indexing_pipeline_handles
-            .keys()
-            .map(MergePipelineId::from)
-            .collect();
+        let mut needed_merge_pipeline_ids: HashSet<MergePipelineId> = if self.offload_merges {
+            HashSet::new()
+        } else {
+            self.indexing_pipeline_handles
+                .keys()
+                .map(MergePipelineId::from)
+                .collect()
+        };
+        needed_merge_pipeline_ids.extend(self.merge_task_ids.iter().cloned());
         let current_merge_pipeline_ids: HashSet<MergePipelineId> =
             self.merge_pipeline_handles.keys().cloned().collect();
         for merge_pipeline_id_to_shut_down in
@@ -390,7 +543,12 @@ impl IndexingService {
                 merge_pipeline_mailbox_handle.handle.state().is_running()
             });
         self.counters.num_running_merge_pipelines = self.merge_pipeline_handles.len();
+        // The merge tasks whose pipeline exited are spawned again by the next indexing plan.
+        let merge_pipeline_handles = &self.merge_pipeline_handles;
+        self.merge_task_ids
+            .retain(|merge_task_id| merge_pipeline_handles.contains_key(merge_task_id));
         self.update_cluster_running_indexing_tasks().await;
+        self.update_cluster_ingest_lags().await;
         Ok(())
     }
 
@@ -419,6 +577,34 @@ impl IndexingService {
         Ok(indexing_directory)
     }
 
+    /// Returns the upload journal of the index source, opening it and recovering the uploads
+    /// interrupted by the last shutdown of the node the first time.
+    async fn get_or_open_upload_journal(
+        &mut self,
+        pipeline_id: &IndexingPipelineId,
+        storage: &dyn Storage,
+    ) -> Result<UploadJournal, IndexingServiceError> {
+        let key = (pipeline_id.index_id.clone(), pipeline_id.source_id.clone());
+        if let Some(upload_journal) = self.upload_journals.get(&key) {
+            return Ok(upload_journal.clone());
+        }
+        let upload_journal_dir_path = self
+            .data_dir_path
+            .join(INDEXING_DIR_NAME)
+            .join(&pipeline_id.index_id)
+            .join(&pipeline_id.source_id)
+            .join(UPLOAD_JOURNAL_DIR_NAME);
+        let upload_journal = UploadJournal::open(upload_journal_dir_path)
+            .await
+            .map_err(IndexingServiceError::InvalidParams)?;
+        upload_journal
+            .recover(&*self.metastore, storage)
+            .await
+            .map_err(IndexingServiceError::InvalidParams)?;
+        self.upload_journals.insert(key, upload_journal.clone());
+        Ok(upload_journal)
+    }
+
     async fn get_or_create_merge_pipeline(
         &mut self,
         merge_pipeline_params: MergePipelineParams,
@@ -454,6 +640,58 @@ impl IndexingService {
         ctx: &ActorContext<Self>,
         physical_indexing_plan_request: ApplyIndexingPlanRequest,
     ) -> Result<(), IndexingServiceError> {
+        if physical_indexing_plan_request.offload_merges != self.offload_merges {
+            info!(
+                offload_merges = physical_indexing_plan_request.offload_merges,
+                "Restarting indexing pipelines to move merges to or from merger nodes."
+            );
+            self.offload_merges = physical_indexing_plan_request.offload_merges;
+            // The indexing pipelines are respawned below with or without merge pipeline.
+            let pipeline_ids: Vec<IndexingPipelineId> =
+                self.indexing_pipeline_handles.keys().cloned().collect();
+            for pipeline_id in pipeline_ids {
+                if let Ok(pipeline_handle) = self.detach_pipeline(&pipeline_id).await {
+                    pipeline_handle.kill().await;
+                }
+            }
+            let merge_pipeline_ids: Vec<MergePipelineId> = self
+                .merge_pipeline_handles
+                .keys()
+                .filter(|merge_pipeline_id| !self.merge_task_ids.contains(*merge_pipeline_id))
+                .cloned()
+                .collect();
+            for merge_pipeline_id in merge_pipeline_ids {
+                if let Ok(merge_pipeline_handle) =
+                    self.detach_merge_pipeline(&merge_pipeline_id).await
+                {
+                    merge_pipeline_handle.kill().await;
+                }
+            }
+        }
+        let updated_merge_task_ids: HashSet<MergePipelineId> = physical_indexing_plan_request
+            .merge_tasks
+            .iter()
+            .map(|merge_task| MergePipelineId {
+                index_id: merge_task.index_id.clone(),
+                source_id: merge_task.source_id.clone(),
+            })
+            .collect();
+        let merge_task_ids_to_remove: Vec<MergePipelineId> = self
+            .merge_task_ids
+            .difference(&updated_merge_task_ids)
+            .cloned()
+            .collect();
+        for merge_task_id in merge_task_ids_to_remove {
+            self.merge_task_ids.remove(&merge_task_id);
+            if let Ok(merge_pipeline_handle) = self.detach_merge_pipeline(&merge_task_id).await {
+                merge_pipeline_handle.kill().await;
+            }
+        }
+        let new_merge_task_ids: Vec<MergePipelineId> = updated_merge_task_ids
+            .difference(&self.merge_task_ids)
+            .cloned()
+            .collect();
+
         let mut updated_pipeline_ids: HashSet<IndexingPipelineId> = HashSet::new();
         let mut pipeline_ordinals: HashMap<(&IndexId, &SourceId), usize> = HashMap::new();
         for indexing_task in physical_indexing_plan_request.indexing_tasks.iter() {
@@ -478,8 +716,14 @@ impl IndexingService {
 
         let indexes_metadata_futures = new_pipeline_ids
             .iter()
-            .unique_by(|pipeline_id| pipeline_id.index_id.clone())
-            .map(|pipeline_id| self.index_metadata(ctx, &pipeline_id.index_id));
+            .map(|pipeline_id| &pipeline_id.index_id)
+            .chain(
+                new_merge_task_ids
+                    .iter()
+                    .map(|merge_task_id| &merge_task_id.index_id),
+            )
+            .unique()
+            .map(|index_id| self.index_metadata(ctx, index_id));
         let indexes_metadata = try_join_all(indexes_metadata_futures).await?;
         let indexes_metadata_by_index_id: HashMap<String, IndexMetadata> = indexes_metadata
             .into_iter()
@@ -512,6 +756,29 @@ impl IndexingService {
             }
         }
 
+        // Add new merge tasks.
+        for new_merge_task_id in new_merge_task_ids {
+            info!(merge_task_id=?new_merge_task_id, "Spawning merge pipeline of merge task.");
+            let index_config = indexes_metadata_by_index_id
+                .get(&new_merge_task_id.index_id)
+                .expect("`indexes_metadata_by_index_id` must contain the index ID.")
+                .index_config
+                .clone();
+            let pipeline_id = IndexingPipelineId {
+                node_id: self.node_id.clone(),
+                index_id: new_merge_task_id.index_id.clone(),
+                source_id: new_merge_task_id.source_id.clone(),
+                pipeline_ord: 0,
+            };
+            if let Err(error) = self
+                .spawn_merge_task(ctx, new_merge_task_id, &index_config)
+                .await
+            {
+                error!(pipeline_id=?pipeline_id, err=?error, "Failed to spawn merge pipeline.");
+                failed_spawning_pipeline_ids.push(pipeline_id);
+            }
+        }
+
         // Remove missing pipeline ids.
         for pipeline_id_to_remove in running_pipeline_ids.difference(&updated_pipeline_ids) {
             match self.detach_pipeline(pipeline_id_to_remove).await {
@@ -531,6 +798,36 @@ impl IndexingService {
             }
         }
 
+        // The documents ingested into an index are spread over as many shards as there are ingest
+        // API pipelines for the index, the pipelines of the retired shards being shut down by now.
+        let running_num_ingest_shards = count_ingest_api_pipelines(running_pipeline_ids.iter());
+        let updated_num_ingest_shards =
+            count_ingest_api_pipelines(self.indexing_pipeline_handles.keys());
+        let resized_index_ids: Vec<&String> = running_num_ingest_shards
+            .keys()
+            .chain(updated_num_ingest_shards.keys())
+            .unique()
+            .filter(|index_id| {
+                running_num_ingest_shards.get(*index_id) != updated_num_ingest_shards.get(*index_id)
+            })
+            .collect();
+        for index_id in resized_index_ids {
+            let num_shards = updated_num_ingest_shards
+                .get(index_id)
+                .copied()
+                .unwrap_or_default();
+            if let Err(error) = self
+                .set_ingest_api_num_shards(ctx, index_id, num_shards)
+                .await
+            {
+                warn!(
+                    index_id=%index_id,
+                    err=?error,
+                    "Failed to set the number of ingest API shards.",
+                );
+            }
+        }
+
         // If at least one ingest source has been removed, the related index has possibly been
         // deleted. Thus we run a garbage collect to remove queues of potentially deleted
         // indexes.
@@ -557,6 +854,321 @@ impl IndexingService {
         Ok(())
     }
 
+    /// Applies the command to the pipelines of the index and source, and returns the number of
+    /// pipelines the command was sent to. The command is applied asynchronously.
+    async fn control_pipelines(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        control_pipelines: ControlPipelines,
+    ) -> Result<usize, IndexingServiceError> {
+        let pipeline_ids: Vec<IndexingPipelineId> = self
+            .indexing_pipeline_handles
+            .keys()
+            .filter(|pipeline_id| {
+                pipeline_id.index_id == control_pipelines.index_id
+                    && pipeline_id.source_id == control_pipelines.source_id
+            })
+            .cloned()
+            .collect();
+        if pipeline_ids.is_empty() {
+            return Err(IndexingServiceError::MissingPipeline {
+                index_id: control_pipelines.index_id,
+                source_id: control_pipelines.source_id,
+            });
+        }
+        let merge_pipeline_id = MergePipelineId {
+            index_id: control_pipelines.index_id,
+            source_id: control_pipelines.source_id,
+        };
+        match control_pipelines.command {
+            PipelineCommand::PauseSource | PipelineCommand::ResumeSource => {
+                let is_source_paused = control_pipelines.command == PipelineCommand::PauseSource;
+                for pipeline_id in &pipeline_ids {
+                    let pipeline_mailbox = self.indexing_pipeline_handles[pipeline_id].mailbox();
+                    let _ = ctx
+                        .send_message(pipeline_mailbox, SetSourcePaused(is_source_paused))
+                        .await;
+                    if is_source_paused {
+                        self.paused_pipeline_ids.insert(pipeline_id.clone());
+                    } else {
+                        self.paused_pipeline_ids.remove(pipeline_id);
+                    }
+                }
+            }
+            PipelineCommand::Commit => {
+                for pipeline_id in &pipeline_ids {
+                    let pipeline_mailbox = self.indexing_pipeline_handles[pipeline_id].mailbox();
+                    let _ = ctx.send_message(pipeline_mailbox, ForceCommit).await;
+                }
+            }
+            PipelineCommand::PlanMerges => {
+                if let Some(merge_pipeline_handle) =
+                    self.merge_pipeline_handles.get(&merge_pipeline_id)
+                {
+                    // If the queue of the merge planner is full, the merge planner is about to
+                    // plan merges anyway.
+                    let _ = merge_pipeline_handle.mailbox.try_send_message(PlanMerge);
+                }
+            }
+            PipelineCommand::ThrottleMerges {
+                max_merge_write_throughput,
+            } => {
+                if let Some(merge_pipeline_handle) =
+                    self.merge_pipeline_handles.get(&merge_pipeline_id)
+                {
+                    let throttle_merges = ThrottleMerges {
+                        max_merge_write_throughput,
+                    };
+                    let _ = ctx
+                        .send_message(merge_pipeline_handle.handle.mailbox(), throttle_merges)
+                        .await;
+                }
+            }
+        }
+        Ok(pipeline_ids.len())
+    }
+
+    async fn drain_pipelines(&mut self, ctx: &ActorContext<Self>) -> usize {
+        let pipeline_ids: Vec<IndexingPipelineId> =
+            self.indexing_pipeline_handles.keys().cloned().collect();
+        let mut num_pipelines_with_in_flight_data = 0;
+
+        for pipeline_id in pipeline_ids {
+            let pipeline_handle = &self.indexing_pipeline_handles[&pipeline_id];
+            let pipeline_mailbox = pipeline_handle.mailbox().clone();
+            let statistics = pipeline_handle.last_observation();
+
+            if statistics.num_docs_in_workbench > 0 || statistics.num_in_flight_split_batches > 0 {
+                num_pipelines_with_in_flight_data += 1;
+            }
+            if pipeline_id.source_id != INGEST_API_SOURCE_ID
+                && !self.paused_pipeline_ids.contains(&pipeline_id)
+            {
+                let _ = ctx
+                    .send_message(&pipeline_mailbox, SetSourcePaused(true))
+                    .await;
+                self.paused_pipeline_ids.insert(pipeline_id);
+            }
+            let _ = ctx.send_message(&pipeline_mailbox, ForceCommit).await;
+        }
+        num_pipelines_with_in_flight_data
+    }
+
+    async fn refresh_index_configs(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<usize, IndexingServiceError> {
+        let index_ids: Vec<IndexId> = self.index_configs.keys().cloned().collect();
+        let mut num_respawned_pipelines = 0;
+
+        for index_id in index_ids {
+            let index_metadata = match self.index_metadata(ctx, &index_id).await {
+                Ok(index_metadata) => index_metadata,
+                Err(error) => {
+                    warn!(index_id=%index_id, error=?error, "Failed to refresh index config.");
+                    continue;
+                }
+            };
+            let new_index_config = &index_metadata.index_config;
+            let Some(index_config) = self.index_configs.get(&index_id) else {
+                continue;
+            };
+            if index_config.doc_mapping == new_index_config.doc_mapping
+                && index_config.indexing_settings == new_index_config.indexing_settings
+                && index_config.search_settings == new_index_config.search_settings
+            {
+                continue;
+            }
+            info!(index_id=%index_id, "Index config changed, respawning indexing pipelines.");
+            // The pipelines of the sources missing from the metastore cannot be respawned.
+            let pipeline_ids: Vec<IndexingPipelineId> = self
+                .indexing_pipeline_handles
+                .keys()
+                .filter(|pipeline_id| {
+                    pipeline_id.index_id == index_id
+                        && index_metadata.sources.contains_key(&pipeline_id.source_id)
+                })
+                .cloned()
+                .collect();
+            let merge_pipeline_ids: HashSet<MergePipelineId> =
+                pipeline_ids.iter().map(MergePipelineId::from).collect();
+            let mut paused_pipeline_ids: HashSet<IndexingPipelineId> = HashSet::new();
+
+            // The documents not published yet are indexed again by the new pipelines from the
+            // checkpoint of their source.
+            for pipeline_id in &pipeline_ids {
+                if self.paused_pipeline_ids.contains(pipeline_id) {
+                    paused_pipeline_ids.insert(pipeline_id.clone());
+                }
+                let pipeline_handle = self.detach_pipeline(pipeline_id).await?;
+                pipeline_handle.kill().await;
+            }
+            // The merge pipelines are respawned along with the indexing pipelines to apply the
+            // new merge policy and doc mapping.
+            for merge_pipeline_id in &merge_pipeline_ids {
+                if let Ok(merge_pipeline_handle) =
+                    self.detach_merge_pipeline(merge_pipeline_id).await
+                {
+                    merge_pipeline_handle.kill().await;
+                }
+            }
+            for pipeline_id in pipeline_ids {
+                let source_config = index_metadata.sources[&pipeline_id.source_id].clone();
+                self.spawn_pipeline_inner(
+                    ctx,
+                    pipeline_id.clone(),
+                    new_index_config.clone(),
+                    source_config,
+                )
+                .await?;
+                if paused_pipeline_ids.contains(&pipeline_id) {
+                    let pipeline_mailbox = self.indexing_pipeline_handles[&pipeline_id].mailbox();
+                    let _ = ctx
+                        .send_message(pipeline_mailbox, SetSourcePaused(true))
+                        .await;
+                    self.paused_pipeline_ids.insert(pipeline_id);
+                }
+                num_respawned_pipelines += 1;
+            }
+        }
+        Ok(num_respawned_pipelines)
+    }
+
+    async fn list_pipelines(
+        &self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Vec<IndexingPipelineStatus>, IndexingServiceError> {
+        let mut index_metadata_per_index_id: HashMap<&str, IndexMetadata> = HashMap::new();
+        for pipeline_id in self.indexing_pipeline_handles.keys() {
+            if !index_metadata_per_index_id.contains_key(pipeline_id.index_id.as_str()) {
+                let index_metadata = self.index_metadata(ctx, &pipeline_id.index_id).await?;
+                index_metadata_per_index_id.insert(&pipeline_id.index_id, index_metadata);
+            }
+        }
+        let pipeline_statuses = self
+            .indexing_pipeline_handles
+            .iter()
+            .sorted_by_key(|(pipeline_id, _)| {
+                (
+                    &pipeline_id.index_id,
+                    &pipeline_id.source_id,
+                    pipeline_id.pipeline_ord,
+                )
+            })
+            .map(|(pipeline_id, pipeline_handle)| {
+                let checkpoint = index_metadata_per_index_id[pipeline_id.index_id.as_str()]
+                    .checkpoint
+                    .source_checkpoint(&pipeline_id.source_id)
+                    .cloned()
+                    .unwrap_or_default();
+                let merge_statistics = self
+                    .merge_pipeline_handles
+                    .get(&MergePipelineId::from(pipeline_id))
+                    .map(|merge_pipeline_handle| merge_pipeline_handle.handle.last_observation());
+                let backpressure_micros = BACKPRESSURED_ACTOR_NAMES
+                    .iter()
+                    .map(|actor_name| {
+                        let backpressure_micros = INDEXER_METRICS
+                            .backpressure_micros
+                            .with_label_values([&pipeline_id.index_id, actor_name])
+                            .get();
+                        (actor_name.to_string(), backpressure_micros)
+                    })
+                    .collect();
+                let processing_micros = PROCESSING_ACTOR_NAMES
+                    .iter()
+                    .map(|actor_name| {
+                        let processing_micros = INDEXER_METRICS
+                            .processing_micros
+                            .with_label_values([&pipeline_id.index_id, actor_name])
+                            .get();
+                        (actor_name.to_string(), processing_micros)
+                    })
+                    .collect();
+                IndexingPipelineStatus {
+                    index_id: pipeline_id.index_id.clone(),
+                    source_id: pipeline_id.source_id.clone(),
+                    pipeline_ord: pipeline_id.pipeline_ord,
+                    is_source_paused: self.paused_pipeline_ids.contains(pipeline_id),
+                    statistics: pipeline_handle.last_observation(),
+                    merge_statistics,
+                    checkpoint,
+                    backpressure_micros,
+                    processing_micros,
+                }
+            })
+            .collect();
+        Ok(pipeline_statuses)
+    }
+
+    /// Sets the number of shards of the ingest API queues of the index. The records of the retired
+    /// shards that were not published yet are moved to the first shard.
+    async fn set_ingest_api_num_shards(
+        &self,
+        ctx: &ActorContext<Self>,
+        index_id: &str,
+        num_shards: usize,
+    ) -> anyhow::Result<()> {
+        let Some(ingest_api_service) = &self.ingest_api_service_opt else {
+            return Ok(());
+        };
+        // The queues of deleted indexes are garbage collected.
+        let index_metadata = match self.index_metadata(ctx, index_id).await {
+            Ok(index_metadata) => index_metadata,
+            Err(IndexingServiceError::MetastoreError(MetastoreError::IndexDoesNotExist {
+                ..
+            })) => return Ok(()),
+            Err(error) => return Err(error.into()),
+        };
+        let published_positions: HashMap<String, u64> = index_metadata
+            .checkpoint
+            .source_checkpoint(INGEST_API_SOURCE_ID)
+            .map(|source_checkpoint| {
+                source_checkpoint
+                    .iter()
+                    .filter_map(|(partition_id, position)| {
+                        let position = position.as_str().parse::<u64>().ok()?;
+                        Some((partition_id.0.to_string(), position))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        ingest_api_service
+            .ask_for_res(SetNumShards {
+                index_id: index_id.to_string(),
+                num_shards,
+                published_positions,
+            })
+            .await
+            .context("Failed to set the number of shards.")?;
+        Ok(())
+    }
+
+    /// Updates the ingest lags of the indexes in chitchat cluster state, so that the control plane
+    /// adjusts their number of ingest API pipelines.
+    async fn update_cluster_ingest_lags(&self) {
+        let Some(ingest_api_service) = &self.ingest_api_service_opt else {
+            return;
+        };
+        let ingest_lags_per_queue = match ingest_api_service.ask_for_res(GetIngestLags).await {
+            Ok(ingest_lags_per_queue) => ingest_lags_per_queue,
+            Err(error) => {
+                warn!(error=?error, "Failed to get the ingest lags.");
+                return;
+            }
+        };
+        let mut ingest_lags: HashMap<String, u64> = HashMap::new();
+
+        for (queue_id, ingest_lag) in ingest_lags_per_queue {
+            if let Some((index_id, _shard_ord)) = parse_shard_queue_id(&queue_id) {
+                *ingest_lags.entry(index_id.to_string()).or_default() += ingest_lag as u64;
+            }
+        }
+        self.cluster
+            .update_self_node_ingest_lags(&ingest_lags)
+            .await;
+    }
+
     /// Updates running indexing tasks in chitchat cluster state.
     async fn update_cluster_running_indexing_tasks(&self) {
         let indexing_tasks = self
@@ -584,7 +1196,7 @@ impl IndexingService {
         }
     }
 
-    /// Garbage collects ingest API queues of deleted indexes.
+    /// Garbage collects ingest API queues, including shard and replica queues, of deleted indexes.
     async fn run_ingest_api_queues_gc(&mut self) -> anyhow::Result<()> {
         let Some(ingest_api_service) = &self.ingest_api_service_opt else {
             return Ok(());
@@ -608,7 +1220,13 @@ impl IndexingService {
             .collect();
         debug!(index_ids=?index_ids, "List indexes.");
 
-        let queue_ids_to_delete = queues.difference(&index_ids);
+        let queue_ids_to_delete = queues.iter().filter(|queue_id| {
+            let index_id = parse_replica_queue_id(queue_id)
+                .map(|(_leader_node_id, index_id)| index_id)
+                .or_else(|| parse_shard_queue_id(queue_id).map(|(index_id, _shard_ord)| index_id))
+                .unwrap_or(queue_id.as_str());
+            !index_ids.contains(index_id)
+        });
 
         for queue_id in queue_ids_to_delete {
             let delete_queue_res = ingest_api_service
@@ -745,6 +1363,58 @@ impl Handler<ApplyIndexingPlanRequest> for IndexingService {
     }
 }
 
+#[async_trait]
+impl Handler<ControlPipelines> for IndexingService {
+    type Reply = Result<usize, IndexingServiceError>;
+
+    async fn handle(
+        &mut self,
+        message: ControlPipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.control_pipelines(ctx, message).await)
+    }
+}
+
+#[async_trait]
+impl Handler<DrainPipelines> for IndexingService {
+    type Reply = usize;
+
+    async fn handle(
+        &mut self,
+        _message: DrainPipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.drain_pipelines(ctx).await)
+    }
+}
+
+#[async_trait]
+impl Handler<RefreshIndexConfigs> for IndexingService {
+    type Reply = Result<usize, IndexingServiceError>;
+
+    async fn handle(
+        &mut self,
+        _message: RefreshIndexConfigs,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.refresh_index_configs(ctx).await)
+    }
+}
+
+#[async_trait]
+impl Handler<ListPipelines> for IndexingService {
+    type Reply = Result<Vec<IndexingPipelineStatus>, IndexingServiceError>;
+
+    async fn handle(
+        &mut self,
+        _message: ListPipelines,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.list_pipelines(ctx).await)
+    }
+}
+
 #[async_trait]
 impl Handler<Healthz> for IndexingService {
     type Reply = bool;
@@ -759,13 +1429,25 @@ impl Handler<Healthz> for IndexingService {
     }
 }
 
+/// Counts the ingest API pipelines of each index, which is the number of shards of its ingest API
+/// queues.
+fn count_ingest_api_pipelines<'a>(
+    pipeline_ids: impl Iterator<Item = &'a IndexingPipelineId>,
+) -> HashMap<String, usize> {
+    pipeline_ids
+        .filter(|pipeline_id| pipeline_id.source_id == INGEST_API_SOURCE_ID)
+        .map(|pipeline_id| pipeline_id.index_id.clone())
+        .counts()
+}
+
 #[cfg(test)]
 mod tests {
     use std::num::NonZeroUsize;
     use std::time::Duration;
 
+    use byte_unit::Byte;
     use chitchat::transport::ChannelTransport;
-    use quickwit_actors::{Health, ObservationType, Supervisable, Universe, HEARTBEAT};
+    use quickwit_actors::{AskError, Health, ObservationType, Supervisable, Universe, HEARTBEAT};
     use quickwit_cluster::create_cluster_for_test;
     use quickwit_common::rand::append_random_suffix;
     use quickwit_common::uri::Uri;
@@ -902,6 +1584,179 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_indexing_service_control_pipelines() {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let metastore_uri = Uri::from_well_formed("ram:///metastore");
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-control");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let universe = Universe::with_accelerated_time();
+        let (indexing_service, _indexing_service_handle) =
+            spawn_indexing_service(&universe, metastore, cluster).await;
+        let source_config = SourceConfig {
+            source_id: "test-indexing-service--source".to_string(),
+            max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+            desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+            enabled: true,
+            source_params: SourceParams::void(),
+            transform_config: None,
+        };
+        indexing_service
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                pipeline_ord: 0,
+                source_config: source_config.clone(),
+            })
+            .await
+            .unwrap();
+
+        let control_pipelines = |command: PipelineCommand| ControlPipelines {
+            index_id: index_id.clone(),
+            source_id: source_config.source_id.clone(),
+            command,
+        };
+        let num_pipelines = indexing_service
+            .ask_for_res(control_pipelines(PipelineCommand::PauseSource))
+            .await
+            .unwrap();
+        assert_eq!(num_pipelines, 1);
+
+        let pipeline_statuses = indexing_service.ask_for_res(ListPipelines).await.unwrap();
+        assert_eq!(pipeline_statuses.len(), 1);
+        assert_eq!(pipeline_statuses[0].index_id, index_id);
+        assert_eq!(pipeline_statuses[0].source_id, source_config.source_id);
+        assert!(pipeline_statuses[0].is_source_paused);
+        assert!(pipeline_statuses[0].merge_statistics.is_some());
+        assert_eq!(pipeline_statuses[0].backpressure_micros.len(), 5);
+        assert_eq!(pipeline_statuses[0].processing_micros.len(), 2);
+
+        for command in [
+            PipelineCommand::Commit,
+            PipelineCommand::PlanMerges,
+            PipelineCommand::ThrottleMerges {
+                max_merge_write_throughput: Some(Byte::from_bytes(1_000_000)),
+            },
+            PipelineCommand::ResumeSource,
+        ] {
+            indexing_service
+                .ask_for_res(control_pipelines(command))
+                .await
+                .unwrap();
+        }
+        let pipeline_statuses = indexing_service.ask_for_res(ListPipelines).await.unwrap();
+        assert!(!pipeline_statuses[0].is_source_paused);
+
+        let error = indexing_service
+            .ask_for_res(ControlPipelines {
+                index_id: index_id.clone(),
+                source_id: "test-indexing-service--missing-source".to_string(),
+                command: PipelineCommand::Commit,
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            AskError::ErrorReply(IndexingServiceError::MissingPipeline { .. })
+        ));
+
+        let num_pipelines_with_in_flight_data = indexing_service.ask(DrainPipelines).await.unwrap();
+        assert_eq!(num_pipelines_with_in_flight_data, 0);
+
+        let pipeline_statuses = indexing_service.ask_for_res(ListPipelines).await.unwrap();
+        assert!(pipeline_statuses[0].is_source_paused);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_indexing_service_refresh_index_configs() {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let metastore_uri = Uri::from_well_formed("ram:///metastore");
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service-refresh");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let mut index_config = IndexConfig::for_test(&index_id, &index_uri);
+        metastore.create_index(index_config.clone()).await.unwrap();
+        let source_config = SourceConfig {
+            source_id: "test-indexing-service--source".to_string(),
+            max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+            desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+            enabled: true,
+            source_params: SourceParams::void(),
+            transform_config: None,
+        };
+        metastore
+            .add_source(&index_id, source_config.clone())
+            .await
+            .unwrap();
+
+        let universe = Universe::with_accelerated_time();
+        let (indexing_service, _indexing_service_handle) =
+            spawn_indexing_service(&universe, metastore.clone(), cluster).await;
+        indexing_service
+            .ask_for_res(SpawnPipeline {
+                index_id: index_id.clone(),
+                pipeline_ord: 0,
+                source_config: source_config.clone(),
+            })
+            .await
+            .unwrap();
+        indexing_service
+            .ask_for_res(ControlPipelines {
+                index_id: index_id.clone(),
+                source_id: source_config.source_id.clone(),
+                command: PipelineCommand::PauseSource,
+            })
+            .await
+            .unwrap();
+        let num_respawned_pipelines = indexing_service
+            .ask_for_res(RefreshIndexConfigs)
+            .await
+            .unwrap();
+        assert_eq!(num_respawned_pipelines, 0);
+
+        index_config.indexing_settings.commit_timeout_secs = 5;
+        metastore.update_index_config(index_config).await.unwrap();
+        let num_respawned_pipelines = indexing_service
+            .ask_for_res(RefreshIndexConfigs)
+            .await
+            .unwrap();
+        assert_eq!(num_respawned_pipelines, 1);
+
+        let pipeline_statuses = indexing_service.ask_for_res(ListPipelines).await.unwrap();
+        assert_eq!(pipeline_statuses.len(), 1);
+        assert!(pipeline_statuses[0].is_source_paused);
+        let num_respawned_pipelines = indexing_service
+            .ask_for_res(RefreshIndexConfigs)
+            .await
+            .unwrap();
+        assert_eq!(num_respawned_pipelines, 0);
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_indexing_service_supervise_pipelines() {
         quickwit_common::setup_logging_for_tests();
@@ -1012,7 +1867,10 @@ mod tests {
             },
         ];
         indexing_service
-            .ask_for_res(ApplyIndexingPlanRequest { indexing_tasks })
+            .ask_for_res(ApplyIndexingPlanRequest {
+                indexing_tasks,
+                ..Default::default()
+            })
             .await
             .unwrap();
         assert_eq!(
@@ -1057,6 +1915,7 @@ mod tests {
         indexing_service
             .ask_for_res(ApplyIndexingPlanRequest {
                 indexing_tasks: indexing_tasks.clone(),
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1090,6 +1949,7 @@ mod tests {
         indexing_service
             .ask_for_res(ApplyIndexingPlanRequest {
                 indexing_tasks: indexing_tasks.clone(),
+                ..Default::default()
             })
             .await
             .unwrap();
@@ -1107,9 +1967,7 @@ mod tests {
         // Delete index and apply empty plan
         metastore.delete_index(&index_id).await.unwrap();
         indexing_service
-            .ask_for_res(ApplyIndexingPlanRequest {
-                indexing_tasks: Vec::new(),
-            })
+            .ask_for_res(ApplyIndexingPlanRequest::default())
             .await
             .unwrap();
         let indexing_service_obs = indexing_service_handle.observe().await;
@@ -1120,6 +1978,87 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_indexing_service_apply_plan_with_merge_tasks() {
+        quickwit_common::setup_logging_for_tests();
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["indexer", "merger"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let metastore_uri = Uri::from_well_formed("ram:///metastore");
+        let metastore = quickwit_metastore_uri_resolver()
+            .resolve(&metastore_uri)
+            .await
+            .unwrap();
+
+        let index_id = append_random_suffix("test-indexing-service");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+        let source_config = SourceConfig {
+            source_id: "test-indexing-service--source".to_string(),
+            max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+            desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+            enabled: true,
+            source_params: SourceParams::void(),
+            transform_config: None,
+        };
+        metastore
+            .add_source(&index_id, source_config.clone())
+            .await
+            .unwrap();
+        let universe = Universe::new();
+        let (indexing_service, indexing_service_handle) =
+            spawn_indexing_service(&universe, metastore.clone(), cluster.clone()).await;
+
+        let indexing_tasks = vec![IndexingTask {
+            index_id: index_id.to_string(),
+            source_id: source_config.source_id.clone(),
+        }];
+        indexing_service
+            .ask_for_res(ApplyIndexingPlanRequest {
+                indexing_tasks: indexing_tasks.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let indexing_service_obs = indexing_service_handle.observe().await;
+        assert_eq!(indexing_service_obs.num_running_pipelines, 1);
+        assert_eq!(indexing_service_obs.num_running_merge_pipelines, 1);
+
+        // Offloading merges restarts the indexing pipeline without merge pipeline. The merge
+        // pipeline of the merge task replaces the one of the indexing pipeline.
+        indexing_service
+            .ask_for_res(ApplyIndexingPlanRequest {
+                indexing_tasks: indexing_tasks.clone(),
+                merge_tasks: indexing_tasks.clone(),
+                offload_merges: true,
+            })
+            .await
+            .unwrap();
+        let indexing_service_obs = indexing_service_handle.process_pending_and_observe().await;
+        assert_eq!(indexing_service_obs.num_running_pipelines, 1);
+        assert_eq!(indexing_service_obs.num_running_merge_pipelines, 1);
+
+        // Removing the merge task kills its merge pipeline.
+        indexing_service
+            .ask_for_res(ApplyIndexingPlanRequest {
+                indexing_tasks: indexing_tasks.clone(),
+                merge_tasks: Vec::new(),
+                offload_merges: true,
+            })
+            .await
+            .unwrap();
+        let indexing_service_obs = indexing_service_handle.process_pending_and_observe().await;
+        assert_eq!(indexing_service_obs.num_running_pipelines, 1);
+        assert_eq!(indexing_service_obs.num_running_merge_pipelines, 0);
+
+        indexing_service_handle.quit().await;
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_indexing_service_shut_down_merge_pipeline_when_no_indexing_pipeline() {
         quickwit_common::setup_logging_for_tests();
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::RangeInclusive;
 use std::path::Path;
 use std::sync::Arc;
@@ -242,6 +242,13 @@ pub fn merge_split_attrs(
         .map(|split| split.delete_opstamp)
         .min()
         .unwrap_or(0);
+    // The merge planner only merges splits indexed with the same version of the doc mapping.
+    let doc_mapping_version = splits
+        .iter()
+        .map(|split| split.doc_mapping_version)
+        .max()
+        .unwrap_or(0);
+    let custom_metadata = merge_custom_metadata(splits);
     SplitAttrs {
         split_id: merge_split_id,
         partition_id,
@@ -252,9 +259,28 @@ pub fn merge_split_attrs(
         uncompressed_docs_size_in_bytes,
         delete_opstamp,
         num_merge_ops: max_merge_ops(splits) + 1,
+        doc_mapping_version,
+        custom_metadata,
     }
 }
 
+/// Returns the custom metadata entries shared by all the splits.
+fn merge_custom_metadata(splits: &[SplitMetadata]) -> BTreeMap<String, String> {
+    let Some((first_split, other_splits)) = splits.split_first() else {
+        return BTreeMap::new();
+    };
+    first_split
+        .custom_metadata
+        .iter()
+        .filter(|(key, value)| {
+            other_splits
+                .iter()
+                .all(|split| split.custom_metadata.get(*key) == Some(*value))
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
 fn max_merge_ops(splits: &[SplitMetadata]) -> usize {
     splits
         .iter()
@@ -416,6 +442,8 @@ impl MergeExecutor {
                 uncompressed_docs_size_in_bytes,
                 delete_opstamp: last_delete_opstamp,
                 num_merge_ops: split.num_merge_ops,
+                doc_mapping_version: split.doc_mapping_version,
+                custom_metadata: split.custom_metadata.clone(),
             },
             index: merged_index,
             split_scratch_directory: merge_scratch_directory,
@@ -644,6 +672,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_merge_custom_metadata() {
+        assert!(merge_custom_metadata(&[]).is_empty());
+
+        let split_metadata = |split_id: &str, custom_metadata: &[(&str, &str)]| SplitMetadata {
+            split_id: split_id.to_string(),
+            custom_metadata: custom_metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let splits = [
+            split_metadata(
+                "split-1",
+                &[("pipeline_version", "v2"), ("source_partitions", "a.json")],
+            ),
+            split_metadata(
+                "split-2",
+                &[("pipeline_version", "v2"), ("source_partitions", "b.json")],
+            ),
+        ];
+        assert_eq!(
+            merge_custom_metadata(&splits),
+            BTreeMap::from_iter([("pipeline_version".to_string(), "v2".to_string())])
+        );
+    }
+
     async fn aux_test_delete_and_merge_executor(
         index_id: &str,
         docs: Vec<JsonValue>,
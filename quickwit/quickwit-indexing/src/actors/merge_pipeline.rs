@@ -48,6 +48,8 @@ pub struct MergePipelineHandles {
     pub merge_packager: ActorHandle<Packager>,
     pub merge_uploader: ActorHandle<Uploader>,
     pub merge_publisher: ActorHandle<Publisher>,
+    /// Controls shared by the split downloader and the merge executor.
+    pub merge_io_controls: IoControls,
 }
 
 // Messages
@@ -59,6 +61,19 @@ struct Spawn {
     retry_count: usize,
 }
 
+/// Updates the write throughput limit of the merges, including the ongoing ones. The limit
+/// survives the respawns of the pipeline.
+#[derive(Clone, Debug)]
+pub(crate) struct ThrottleMerges {
+    pub max_merge_write_throughput: Option<Byte>,
+}
+
+fn max_merge_write_throughput(merge_max_io_num_bytes_per_sec: Option<&Byte>) -> f64 {
+    merge_max_io_num_bytes_per_sec
+        .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
+        .unwrap_or(f64::INFINITY)
+}
+
 pub struct MergePipeline {
     params: MergePipelineParams,
     merge_planner_mailbox: Mailbox<MergePlanner>,
@@ -197,14 +212,18 @@ impl MergePipeline {
             merge_policy=?self.params.merge_policy,
             "Spawning merge pipeline.",
         );
-        let query = ListSplitsQuery::for_index(&self.params.pipeline_id.index_id)
-            .with_split_state(SplitState::Published);
-        let published_splits = ctx
-            .protect_future(self.params.metastore.list_splits(query))
-            .await?
-            .into_iter()
-            .map(|split| split.split_metadata)
-            .collect::<Vec<_>>();
+        let published_splits = if self.params.split_refresh_interval_opt.is_some() {
+            // The merge planner fetches the splits published by any node itself.
+            Vec::new()
+        } else {
+            let query = ListSplitsQuery::for_index(&self.params.pipeline_id.index_id)
+                .with_split_state(SplitState::Published);
+            ctx.protect_future(self.params.metastore.list_splits(query))
+                .await?
+                .into_iter()
+                .map(|split| split.split_metadata)
+                .collect::<Vec<_>>()
+        };
 
         // Merge publisher
         let merge_publisher = Publisher::new(
@@ -233,18 +252,24 @@ impl MergePipeline {
 
         // Merge Packager
         let tag_fields = self.params.doc_mapper.tag_named_fields()?;
-        let merge_packager = Packager::new("MergePackager", tag_fields, merge_uploader_mailbox);
+        let range_pruning_fields = self.params.doc_mapper.range_pruning_named_fields()?;
+        let bloom_filter_fields = self.params.doc_mapper.bloom_filter_named_fields()?;
+        let vector_fields = self.params.doc_mapper.vector_fields();
+        let merge_packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            range_pruning_fields,
+            bloom_filter_fields,
+            vector_fields,
+            merge_uploader_mailbox,
+        );
         let (merge_packager_mailbox, merge_packager_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
             .spawn(merge_packager);
 
-        let max_merge_write_throughput: f64 = self
-            .params
-            .merge_max_io_num_bytes_per_sec
-            .as_ref()
-            .map(|bytes_per_sec| bytes_per_sec.get_bytes() as f64)
-            .unwrap_or(f64::INFINITY);
+        let max_merge_write_throughput: f64 =
+            max_merge_write_throughput(self.params.merge_max_io_num_bytes_per_sec.as_ref());
 
         let split_downloader_io_controls = IoControls::default()
             .set_throughput_limit(max_merge_write_throughput)
@@ -275,7 +300,7 @@ impl MergePipeline {
             scratch_directory: self.params.indexing_directory.clone(),
             split_store: self.params.split_store.clone(),
             executor_mailbox: merge_executor_mailbox,
-            io_controls: split_downloader_io_controls,
+            io_controls: split_downloader_io_controls.clone(),
         };
         let (merge_split_downloader_mailbox, merge_split_downloader_handler) = ctx
             .spawn_actor()
@@ -291,12 +316,16 @@ impl MergePipeline {
             .spawn(merge_split_downloader);
 
         // Merge planner
-        let merge_planner = MergePlanner::new(
+        let mut merge_planner = MergePlanner::new(
             self.params.pipeline_id.clone(),
             published_splits,
             self.params.merge_policy.clone(),
             merge_split_downloader_mailbox,
         );
+        if let Some(split_refresh_interval) = self.params.split_refresh_interval_opt {
+            merge_planner = merge_planner
+                .with_split_refresh(self.params.metastore.clone(), split_refresh_interval);
+        }
         let (_, merge_planner_handler) = ctx
             .spawn_actor()
             .set_kill_switch(self.kill_switch.clone())
@@ -315,6 +344,7 @@ impl MergePipeline {
             merge_packager: merge_packager_handler,
             merge_uploader: merge_uploader_handler,
             merge_publisher: merge_publisher_handler,
+            merge_io_controls: split_downloader_io_controls,
         });
         Ok(())
     }
@@ -361,6 +391,33 @@ impl Handler<Observe> for MergePipeline {
     }
 }
 
+#[async_trait]
+impl Handler<ThrottleMerges> for MergePipeline {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: ThrottleMerges,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let max_merge_write_throughput =
+            max_merge_write_throughput(message.max_merge_write_throughput.as_ref());
+        if let Some(handles) = &self.handles {
+            handles
+                .merge_io_controls
+                .update_throughput_limit(max_merge_write_throughput);
+        }
+        info!(
+            index_id=%self.params.pipeline_id.index_id,
+            source_id=%self.params.pipeline_id.source_id,
+            max_merge_write_throughput=?message.max_merge_write_throughput,
+            "Updated merge throughput limit."
+        );
+        self.params.merge_max_io_num_bytes_per_sec = message.max_merge_write_throughput;
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<Supervise> for MergePipeline {
     type Reply = ();
@@ -433,6 +490,10 @@ pub struct MergePipelineParams {
     pub merge_policy: Arc<dyn MergePolicy>,
     pub max_concurrent_split_uploads: usize, //< TODO share with the indexing pipeline.
     pub merge_max_io_num_bytes_per_sec: Option<Byte>,
+    /// When set, the pipeline merges the splits of the index and source published by any node,
+    /// and fetches them from the metastore at this interval. This is how the merger nodes, which
+    /// are not notified of the splits published by the indexers, run their merges.
+    pub split_refresh_interval_opt: Option<Duration>,
 }
 
 #[cfg(test)]
@@ -474,6 +535,7 @@ mod tests {
             merge_policy: default_merge_policy(),
             max_concurrent_split_uploads: 2,
             merge_max_io_num_bytes_per_sec: None,
+            split_refresh_interval_opt: None,
         };
         let pipeline = MergePipeline::new(pipeline_params, universe.spawn_ctx());
         let (_pipeline_mailbox, pipeline_handler) = universe.spawn_builder().spawn(pipeline);
@@ -18,17 +18,18 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp::Reverse;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_actors::channel_with_priority::TrySendError;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
-use quickwit_metastore::SplitMetadata;
+use quickwit_metastore::{ListSplitsQuery, Metastore, SplitMetadata, SplitState};
 use serde::Serialize;
 use tantivy::Inventory;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::actors::MergeSplitDownloader;
 use crate::merge_policy::MergeOperation;
@@ -41,13 +42,20 @@ pub struct MergePlanner {
     pipeline_id: IndexingPipelineId,
     /// A young split is a split that has not reached maturity
     /// yet and can be candidate to merge operations.
-    partitioned_young_splits: HashMap<u64, Vec<SplitMetadata>>,
+    ///
+    /// Splits are grouped by partition and by version of the doc mapping: splits indexed with
+    /// different versions of the doc mapping have different schemas and cannot be merged
+    /// together.
+    partitioned_young_splits: HashMap<(u64, u64), Vec<SplitMetadata>>,
     merge_policy: Arc<dyn MergePolicy>,
     merge_split_downloader_mailbox: Mailbox<MergeSplitDownloader>,
     /// Inventory of ongoing merge operations. If everything goes well,
     /// a merge operation is dropped after the publish of the merged split.
     /// Used for observability.
     ongoing_merge_operations_inventory: Inventory<MergeOperation>,
+    /// Metastore and interval at which the splits published by any node are fetched, for the
+    /// merge planners of merger nodes.
+    split_refresh_opt: Option<(Arc<dyn Metastore>, Duration)>,
 }
 
 #[async_trait]
@@ -76,7 +84,11 @@ impl Actor for MergePlanner {
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
         self.handle(RefreshMetric, ctx).await?;
-        self.send_merge_ops(ctx).await?;
+        if self.split_refresh_opt.is_some() {
+            self.handle(RefreshSplits, ctx).await?;
+        } else {
+            self.send_merge_ops(ctx).await?;
+        }
         Ok(())
     }
 }
@@ -142,18 +154,30 @@ impl MergePlanner {
             merge_policy,
             merge_split_downloader_mailbox,
             ongoing_merge_operations_inventory: Inventory::default(),
+            split_refresh_opt: None,
         };
         merge_planner.record_splits(published_splits);
         merge_planner
     }
 
+    /// Makes the merge planner merge the splits of the index and source published by any node,
+    /// which it fetches from the metastore every `refresh_interval`.
+    pub fn with_split_refresh(
+        mut self,
+        metastore: Arc<dyn Metastore>,
+        refresh_interval: Duration,
+    ) -> Self {
+        self.split_refresh_opt = Some((metastore, refresh_interval));
+        self
+    }
+
     fn record_split(&mut self, new_split: SplitMetadata) {
         if self.merge_policy.is_mature(&new_split) {
             return;
         }
         let splits_for_partition: &mut Vec<SplitMetadata> = self
             .partitioned_young_splits
-            .entry(new_split.partition_id)
+            .entry((new_split.partition_id, new_split.doc_mapping_version))
             .or_default();
         // Due to the recycling of the mailbox of the merge planner, it is possible for
         // a split already in store to be received.
@@ -254,7 +278,8 @@ impl MergePlanner {
     }
 }
 
-/// We can merge splits from the same (index_id, source_id, node_id).
+/// We can merge splits from the same (index_id, source_id, node_id), except on the merger nodes,
+/// which merge the splits of all the nodes (see [`MergePlanner::with_split_refresh`]).
 fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata) -> bool {
     pipeline_id.index_id == split.index_id
         && pipeline_id.source_id == split.source_id
@@ -264,8 +289,9 @@ fn belongs_to_pipeline(pipeline_id: &IndexingPipelineId, split: &SplitMetadata)
 #[derive(Debug)]
 struct RefreshMetric;
 
+/// Plans merges among the young splits right away.
 #[derive(Debug)]
-struct PlanMerge;
+pub(crate) struct PlanMerge;
 
 #[async_trait]
 impl Handler<RefreshMetric> for MergePlanner {
@@ -289,6 +315,68 @@ impl Handler<RefreshMetric> for MergePlanner {
     }
 }
 
+/// Fetches the splits of the index and source published by any node from the metastore.
+#[derive(Debug)]
+struct RefreshSplits;
+
+#[async_trait]
+impl Handler<RefreshSplits> for MergePlanner {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: RefreshSplits,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let Some((metastore, refresh_interval)) = self.split_refresh_opt.clone() else {
+            return Ok(());
+        };
+        // The splits of the ongoing merge operations remain published until the merged split
+        // replaces them, so they must not be recorded again. The ongoing operations are listed
+        // before the splits: an operation completing in between has its splits marked for
+        // deletion by the time they are listed.
+        let ongoing_split_ids: HashSet<String> = self
+            .ongoing_merge_operations_inventory
+            .list()
+            .iter()
+            .flat_map(|merge_operation| {
+                merge_operation
+                    .as_ref()
+                    .splits_as_slice()
+                    .iter()
+                    .map(|split_metadata| split_metadata.split_id().to_string())
+                    .collect_vec()
+            })
+            .collect();
+        let query = ListSplitsQuery::for_index(&self.pipeline_id.index_id)
+            .with_split_state(SplitState::Published);
+
+        match ctx.protect_future(metastore.list_splits(query)).await {
+            Ok(splits) => {
+                let published_splits = splits
+                    .into_iter()
+                    .map(|split| split.split_metadata)
+                    .filter(|split_metadata| {
+                        split_metadata.source_id == self.pipeline_id.source_id
+                            && !ongoing_split_ids.contains(split_metadata.split_id())
+                    })
+                    .collect();
+                self.record_splits(published_splits);
+                self.send_merge_ops(ctx).await?;
+            }
+            Err(error) => {
+                warn!(
+                    index_id=%self.pipeline_id.index_id,
+                    error=?error,
+                    "Failed to fetch the published splits to merge."
+                );
+            }
+        }
+        ctx.schedule_self_msg(refresh_interval, RefreshSplits).await;
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct MergePlannerState {
     pub(crate) ongoing_merge_operations: Vec<MergeOperation>,
@@ -305,7 +393,7 @@ mod tests {
         ConstWriteAmplificationMergePolicyConfig, MergePolicyConfig, StableLogMergePolicyConfig,
     };
     use quickwit_config::IndexingSettings;
-    use quickwit_metastore::SplitMetadata;
+    use quickwit_metastore::{MockMetastore, Split, SplitMetadata, SplitState};
     use tantivy::TrackedObject;
     use time::OffsetDateTime;
 
@@ -419,6 +507,72 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_merge_planner_does_not_mix_doc_mapping_versions() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 3,
+                max_merge_factor: 5,
+                maturation_period: Duration::from_secs(3600),
+            },
+            50_000,
+        ));
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![],
+            merge_policy,
+            merge_split_downloader_mailbox,
+        );
+        let (merge_planner_mailbox, merge_planner_handle) =
+            universe.spawn_builder().spawn(merge_planner);
+
+        let split_metadata_for_version = |split_id: &str, doc_mapping_version: u64| SplitMetadata {
+            doc_mapping_version,
+            ..split_metadata_for_test(split_id, 1, 1000, 0)
+        };
+        {
+            let message = NewSplits {
+                new_splits: vec![
+                    split_metadata_for_version("1_v0", 0),
+                    split_metadata_for_version("2_v0", 0),
+                    split_metadata_for_version("1_v1", 1),
+                    split_metadata_for_version("2_v1", 1),
+                ],
+            };
+            merge_planner_mailbox.send_message(message).await?;
+            merge_planner_handle.process_pending_and_observe().await;
+            let merge_ops = merge_split_downloader_inbox.drain_for_test();
+            assert_eq!(merge_ops.len(), 0);
+        }
+        {
+            let message = NewSplits {
+                new_splits: vec![split_metadata_for_version("3_v1", 1)],
+            };
+            merge_planner_mailbox.send_message(message).await?;
+            merge_planner_handle.process_pending_and_observe().await;
+            let merge_ops = merge_split_downloader_inbox
+                .drain_for_test_typed::<TrackedObject<MergeOperation>>();
+            assert_eq!(merge_ops.len(), 1);
+            assert_eq!(merge_ops[0].splits.len(), 3);
+            assert!(merge_ops[0]
+                .splits
+                .iter()
+                .all(|split| split.doc_mapping_version == 1));
+        }
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_merge_planner_priority() -> anyhow::Result<()> {
         let universe = Universe::with_accelerated_time();
@@ -561,4 +715,70 @@ mod tests {
         .unwrap();
         universe.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_merge_planner_with_split_refresh() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let pipeline_id = IndexingPipelineId {
+            index_id: "test-index".to_string(),
+            source_id: "test-source".to_string(),
+            node_id: "merger-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy_config = ConstWriteAmplificationMergePolicyConfig {
+            merge_factor: 3,
+            max_merge_factor: 3,
+            ..Default::default()
+        };
+        let indexing_settings = IndexingSettings {
+            merge_policy: MergePolicyConfig::ConstWriteAmplification(merge_policy_config),
+            ..Default::default()
+        };
+        let merge_policy: Arc<dyn MergePolicy> = merge_policy_from_settings(&indexing_settings);
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_splits().returning(|_| {
+            let splits = ["indexer-1", "indexer-2", "indexer-3"]
+                .iter()
+                .enumerate()
+                .map(|(split_ord, node_id)| {
+                    let mut split_metadata =
+                        split_metadata_for_test(&format!("split-{split_ord}"), 1, 1_000, 0);
+                    split_metadata.node_id = node_id.to_string();
+                    Split {
+                        split_state: SplitState::Published,
+                        update_timestamp: 0,
+                        publish_timestamp: None,
+                        split_metadata,
+                    }
+                })
+                .collect();
+            Ok(splits)
+        });
+        let merge_planner = MergePlanner::new(
+            pipeline_id,
+            Vec::new(),
+            merge_policy,
+            merge_split_downloader_mailbox,
+        )
+        .with_split_refresh(Arc::new(metastore), Duration::from_secs(10));
+        let (_merge_planner_mailbox, merge_planner_handle) =
+            universe.spawn_builder().spawn(merge_planner);
+        merge_planner_handle.process_pending_and_observe().await;
+
+        // The splits indexed by the different nodes are merged together.
+        let merge_ops: Vec<TrackedObject<MergeOperation>> =
+            merge_split_downloader_inbox.drain_for_test_typed();
+        assert_eq!(merge_ops.len(), 1);
+        assert_eq!(merge_ops[0].splits_as_slice().len(), 3);
+
+        // The splits of the ongoing merge operation are not planned again.
+        universe.sleep(Duration::from_secs(15)).await;
+        merge_planner_handle.process_pending_and_observe().await;
+        assert!(merge_split_downloader_inbox.drain_for_test().is_empty());
+        drop(merge_ops);
+        universe.assert_quit().await;
+        Ok(())
+    }
 }
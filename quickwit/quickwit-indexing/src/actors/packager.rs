@@ -17,8 +17,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
-use std::io;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -29,10 +30,15 @@ use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_directories::write_hotcache;
+use quickwit_doc_mapper::bloom_filter::{BloomFilter, BloomFilters};
+use quickwit_doc_mapper::range_pruning::{compare_json_numbers, FieldRanges};
 use quickwit_doc_mapper::tag_pruning::append_to_tag_set;
-use quickwit_doc_mapper::NamedField;
-use tantivy::schema::FieldType;
-use tantivy::{InvertedIndexReader, ReloadPolicy, SegmentMeta};
+use quickwit_doc_mapper::{
+    decode_vector, NamedField, VectorField, VectorIndexBuilder, VECTOR_INDEX_FILE_NAME,
+};
+use serde_json::Number as JsonNumber;
+use tantivy::schema::{Cardinality, FieldType};
+use tantivy::{InvertedIndexReader, ReloadPolicy, Searcher, SegmentMeta, SegmentReader};
 use tokio::runtime::Handle;
 use tracing::{debug, info, instrument, warn};
 
@@ -54,6 +60,9 @@ use crate::models::{
 /// This includes the following steps:
 /// - commit: this step is CPU heavy
 /// - identifying the list of tags for the splits, and labelling it accordingly
+/// - computing the min / max values of the range pruning fields
+/// - building the bloom filters of the bloom filter fields
+/// - building the approximate nearest neighbors index of the vector fields
 /// - creating a bundle file
 /// - computing the hotcache
 /// - appending it to the split file.
@@ -65,18 +74,30 @@ pub struct Packager {
     uploader_mailbox: Mailbox<Uploader>,
     /// List of tag fields ([`Vec<NamedField>`]) defined in the index config.
     tag_fields: Vec<NamedField>,
+    /// List of range pruning fields ([`Vec<NamedField>`]) defined in the index config.
+    range_pruning_fields: Vec<NamedField>,
+    /// List of bloom filter fields ([`Vec<NamedField>`]) defined in the index config.
+    bloom_filter_fields: Vec<NamedField>,
+    /// Vector fields defined in the index config, keyed by field name.
+    vector_fields: BTreeMap<String, VectorField>,
 }
 
 impl Packager {
     pub fn new(
         actor_name: &'static str,
         tag_fields: Vec<NamedField>,
+        range_pruning_fields: Vec<NamedField>,
+        bloom_filter_fields: Vec<NamedField>,
+        vector_fields: BTreeMap<String, VectorField>,
         uploader_mailbox: Mailbox<Uploader>,
     ) -> Packager {
         Packager {
             actor_name,
             uploader_mailbox,
             tag_fields,
+            range_pruning_fields,
+            bloom_filter_fields,
+            vector_fields,
         }
     }
 
@@ -87,8 +108,15 @@ impl Packager {
     ) -> anyhow::Result<PackagedSplit> {
         let segment_metas = split.index.searchable_segment_metas()?;
         assert_eq!(segment_metas.len(), 1);
-        let packaged_split =
-            create_packaged_split(&segment_metas[..], split, &self.tag_fields, ctx)?;
+        let packaged_split = create_packaged_split(
+            &segment_metas[..],
+            split,
+            &self.tag_fields,
+            &self.range_pruning_fields,
+            &self.bloom_filter_fields,
+            &self.vector_fields,
+            ctx,
+        )?;
         Ok(packaged_split)
     }
 }
@@ -187,6 +215,50 @@ fn list_split_files(
     Ok(index_files)
 }
 
+/// Builds the approximate nearest neighbors index of the vector fields and writes it in the split
+/// directory. Returns `None` if the split does not hold any vector.
+fn build_vector_index(
+    searcher: &Searcher,
+    vector_fields: &BTreeMap<String, VectorField>,
+    split_path: &Path,
+) -> anyhow::Result<Option<PathBuf>> {
+    let segment_ids = searcher
+        .segment_readers()
+        .iter()
+        .map(|segment_reader| segment_reader.segment_id().uuid_string())
+        .collect();
+    let mut vector_index_builder = VectorIndexBuilder::new(segment_ids);
+    for (field_name, vector_field) in vector_fields {
+        if searcher.schema().get_field(field_name).is_err() {
+            continue;
+        }
+        for (segment_idx, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            let vector_reader = segment_reader.fast_fields().bytes(field_name)?;
+            for doc_id in segment_reader.doc_ids_alive() {
+                // Documents without a vector have an empty value.
+                let Some(vector) = decode_vector(vector_reader.get_bytes(doc_id)) else {
+                    continue;
+                };
+                vector_index_builder.add_vector(
+                    field_name,
+                    *vector_field,
+                    segment_idx as u32,
+                    doc_id,
+                    &vector,
+                );
+            }
+        }
+    }
+    if vector_index_builder.is_empty() {
+        return Ok(None);
+    }
+    let vector_index_path = split_path.join(VECTOR_INDEX_FILE_NAME);
+    let mut vector_index_file = io::BufWriter::new(std::fs::File::create(&vector_index_path)?);
+    vector_index_builder.write(&mut vector_index_file)?;
+    vector_index_file.flush()?;
+    Ok(Some(vector_index_path))
+}
+
 fn build_hotcache<W: io::Write>(split_path: &Path, out: &mut W) -> anyhow::Result<()> {
     let mmap_directory = tantivy::directory::MmapDirectory::open(split_path)?;
     write_hotcache(mmap_directory, out)?;
@@ -248,14 +320,122 @@ fn try_extract_terms(
     Ok(terms)
 }
 
+/// Returns the min / max values of a numeric fast field over the segments of a split, or `None`
+/// if the split does not hold any value for this field.
+///
+/// Single-valued fast fields assign a default value to the documents missing the field, which may
+/// widen the range but never hurts Quickwit's result validity.
+fn extract_field_range(
+    named_field: &NamedField,
+    segment_readers: &[SegmentReader],
+) -> anyhow::Result<Option<RangeInclusive<JsonNumber>>> {
+    let field_name = named_field.name.as_str();
+    let mut field_range_opt: Option<RangeInclusive<JsonNumber>> = None;
+    for segment_reader in segment_readers {
+        if segment_reader.num_docs() == 0 {
+            continue;
+        }
+        let fast_fields = segment_reader.fast_fields();
+        let cardinality = match &named_field.field_type {
+            FieldType::U64(options) | FieldType::I64(options) | FieldType::F64(options) => {
+                options.get_fastfield_cardinality()
+            }
+            _ => None,
+        };
+        let segment_range: RangeInclusive<JsonNumber> = match (&named_field.field_type, cardinality)
+        {
+            (FieldType::U64(_), Some(Cardinality::SingleValue)) => {
+                let reader = fast_fields.u64(field_name)?;
+                reader.min_value().into()..=reader.max_value().into()
+            }
+            (FieldType::U64(_), Some(Cardinality::MultiValues)) => {
+                let reader = fast_fields.u64s(field_name)?;
+                if reader.total_num_vals() == 0 {
+                    continue;
+                }
+                reader.min_value().into()..=reader.max_value().into()
+            }
+            (FieldType::I64(_), Some(Cardinality::SingleValue)) => {
+                let reader = fast_fields.i64(field_name)?;
+                reader.min_value().into()..=reader.max_value().into()
+            }
+            (FieldType::I64(_), Some(Cardinality::MultiValues)) => {
+                let reader = fast_fields.i64s(field_name)?;
+                if reader.total_num_vals() == 0 {
+                    continue;
+                }
+                reader.min_value().into()..=reader.max_value().into()
+            }
+            (FieldType::F64(_), Some(Cardinality::SingleValue)) => {
+                let reader = fast_fields.f64(field_name)?;
+                json_number_range_from_f64(reader.min_value(), reader.max_value())?
+            }
+            (FieldType::F64(_), Some(Cardinality::MultiValues)) => {
+                let reader = fast_fields.f64s(field_name)?;
+                if reader.total_num_vals() == 0 {
+                    continue;
+                }
+                json_number_range_from_f64(reader.min_value(), reader.max_value())?
+            }
+            _ => bail!(
+                "Range pruning field `{field_name}` must be a `u64`, `i64`, or `f64` fast field."
+            ),
+        };
+        field_range_opt = Some(match field_range_opt {
+            Some(field_range) => {
+                let (start, end) = field_range.into_inner();
+                let (segment_start, segment_end) = segment_range.into_inner();
+                let start = std::cmp::min_by(start, segment_start, compare_json_numbers);
+                let end = std::cmp::max_by(end, segment_end, compare_json_numbers);
+                start..=end
+            }
+            None => segment_range,
+        });
+    }
+    Ok(field_range_opt)
+}
+
+/// Builds a bloom filter over the terms of a field, or returns `None` if the split holds too many
+/// distinct terms for this field.
+fn build_bloom_filter(
+    inv_indexes: &[Arc<InvertedIndexReader>],
+) -> anyhow::Result<Option<BloomFilter>> {
+    let num_terms = inv_indexes
+        .iter()
+        .map(|inv_index| inv_index.terms().num_terms())
+        .sum::<usize>();
+    let Some(mut bloom_filter) = BloomFilter::with_capacity(num_terms) else {
+        return Ok(None);
+    };
+    for inv_index in inv_indexes {
+        let mut terms_streamer = inv_index.terms().stream()?;
+        while let Some((term_data, _)) = terms_streamer.next() {
+            bloom_filter.insert(term_data);
+        }
+    }
+    Ok(Some(bloom_filter))
+}
+
+fn json_number_range_from_f64(
+    min_value: f64,
+    max_value: f64,
+) -> anyhow::Result<RangeInclusive<JsonNumber>> {
+    let start = JsonNumber::from_f64(min_value).context("Field value must be a finite number.")?;
+    let end = JsonNumber::from_f64(max_value).context("Field value must be a finite number.")?;
+    Ok(start..=end)
+}
+
 fn create_packaged_split(
     segment_metas: &[SegmentMeta],
     split: IndexedSplit,
     tag_fields: &[NamedField],
+    range_pruning_fields: &[NamedField],
+    bloom_filter_fields: &[NamedField],
+    vector_fields: &BTreeMap<String, VectorField>,
     ctx: &ActorContext<Packager>,
 ) -> anyhow::Result<PackagedSplit> {
     info!(split_id = split.split_id(), "create-packaged-split");
-    let split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
+    let mut split_files = list_split_files(segment_metas, &split.split_scratch_directory)?;
 
     // Extracts tag values from inverted indexes only when a field cardinality is less
     // than `MAX_VALUES_PER_TAG_FIELD`.
@@ -286,6 +466,61 @@ fn create_packaged_split(
 
     ctx.record_progress();
 
+    debug!(
+        split_id = split.split_id(),
+        range_pruning_fields =? range_pruning_fields,
+        "extract-field-ranges"
+    );
+    let mut field_ranges = FieldRanges::new();
+    for named_field in range_pruning_fields {
+        match extract_field_range(named_field, index_reader.searcher().segment_readers()) {
+            Ok(Some(field_range)) => {
+                field_ranges.insert(named_field.name.clone(), field_range);
+            }
+            Ok(None) => {}
+            Err(range_extraction_error) => {
+                warn!(err=?range_extraction_error, "No field range will be registered in the split metadata.");
+            }
+        }
+    }
+
+    debug!(
+        split_id = split.split_id(),
+        bloom_filter_fields =? bloom_filter_fields,
+        "build-bloom-filters"
+    );
+    let mut bloom_filters = BloomFilters::new();
+    for named_field in bloom_filter_fields {
+        let inverted_indexes = index_reader
+            .searcher()
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(named_field.field))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match build_bloom_filter(&inverted_indexes)? {
+            Some(bloom_filter) => {
+                bloom_filters.insert(named_field.name.clone(), bloom_filter);
+            }
+            None => {
+                warn!(field=%named_field.name, "Too many distinct terms, no bloom filter will be registered in the split metadata.");
+            }
+        }
+        ctx.record_progress();
+    }
+
+    if !vector_fields.is_empty() {
+        debug!(split_id = split.split_id(), "build-vector-index");
+        if let Some(vector_index_path) = build_vector_index(
+            &index_reader.searcher(),
+            vector_fields,
+            split.split_scratch_directory.path(),
+        )? {
+            split_files.push(vector_index_path);
+        }
+        ctx.record_progress();
+    }
+
     debug!(split_id = split.split_id(), "build-hotcache");
     let mut hotcache_bytes = Vec::new();
     build_hotcache(split.split_scratch_directory.path(), &mut hotcache_bytes)?;
@@ -295,6 +530,8 @@ fn create_packaged_split(
         split_attrs: split.split_attrs,
         split_scratch_directory: split.split_scratch_directory,
         tags,
+        field_ranges,
+        bloom_filters,
         split_files,
         hotcache_bytes,
     };
@@ -314,7 +551,10 @@ mod tests {
     use std::ops::RangeInclusive;
 
     use quickwit_actors::{ObservationType, Universe};
-    use quickwit_doc_mapper::QUICKWIT_TOKENIZER_MANAGER;
+    use quickwit_doc_mapper::{
+        encode_vector, ScoredVector, VectorIndexFooter, VectorSimilarity, DEFAULT_NUM_PROBES,
+        QUICKWIT_TOKENIZER_MANAGER, VECTOR_INDEX_FOOTER_LEN_NUM_BYTES,
+    };
     use quickwit_metastore::checkpoint::IndexCheckpointDelta;
     use tantivy::schema::{NumericOptions, Schema, FAST, STRING, TEXT};
     use tantivy::{doc, DateTime, Index};
@@ -392,6 +632,8 @@ mod tests {
                 replaced_split_ids: Vec::new(),
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                doc_mapping_version: 0,
+                custom_metadata: BTreeMap::new(),
             },
             index,
             split_scratch_directory,
@@ -430,7 +672,14 @@ mod tests {
                 "tag_str", "tag_many", "tag_u64", "tag_i64", "tag_f64", "tag_bool",
             ],
         );
-        let packager = Packager::new("TestPackager", tag_fields, mailbox);
+        let packager = Packager::new(
+            "TestPackager",
+            tag_fields,
+            Vec::new(),
+            Vec::new(),
+            BTreeMap::new(),
+            mailbox,
+        );
         let (packager_mailbox, packager_handle) = universe.spawn_builder().spawn(packager);
         packager_mailbox
             .send_message(IndexedSplitBatch {
@@ -476,4 +725,136 @@ mod tests {
         universe.assert_quit().await;
         Ok(())
     }
+
+    #[test]
+    fn test_extract_field_range() -> anyhow::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let status_field = schema_builder.add_u64_field("status", FAST);
+        let latency_field = schema_builder.add_f64_field("latency", FAST);
+        let offsets_field = schema_builder.add_i64_field(
+            "offsets",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema.clone());
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        index_writer.add_document(doc!(
+            status_field => 200u64,
+            latency_field => 0.5f64,
+            offsets_field => -3i64,
+            offsets_field => 7i64,
+        ))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(
+            status_field => 503u64,
+            latency_field => 12.25f64,
+            offsets_field => 1i64,
+        ))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let range_pruning_fields = get_tag_fields(schema, &["status", "latency", "offsets"]);
+        let field_ranges: Vec<RangeInclusive<JsonNumber>> = range_pruning_fields
+            .iter()
+            .map(|named_field| {
+                extract_field_range(named_field, searcher.segment_readers())
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            field_ranges,
+            vec![
+                JsonNumber::from(200u64)..=JsonNumber::from(503u64),
+                JsonNumber::from_f64(0.5).unwrap()..=JsonNumber::from_f64(12.25).unwrap(),
+                JsonNumber::from(-3i64)..=JsonNumber::from(7i64),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_bloom_filter() -> anyhow::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let trace_id_field = schema_builder.add_text_field("trace_id", STRING);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        for trace_id in ["trace-1", "trace-2"] {
+            index_writer.add_document(doc!(trace_id_field => trace_id))?;
+            index_writer.commit()?;
+        }
+        let searcher = index.reader()?.searcher();
+        let inverted_indexes = searcher
+            .segment_readers()
+            .iter()
+            .map(|segment| segment.inverted_index(trace_id_field))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bloom_filter = build_bloom_filter(&inverted_indexes)?.unwrap();
+        assert!(bloom_filter.may_contain(b"trace-1"));
+        assert!(bloom_filter.may_contain(b"trace-2"));
+        assert!(!bloom_filter.may_contain(b"trace-3"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_vector_index() -> anyhow::Result<()> {
+        let split_scratch_directory = ScratchDirectory::for_test();
+        let mut schema_builder = Schema::builder();
+        let embedding_field = schema_builder.add_bytes_field("embedding", FAST);
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_dir(split_scratch_directory.path(), schema_builder.build())?;
+        let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+        index_writer.add_document(doc!(embedding_field => encode_vector(&[1.0, 0.0])))?;
+        index_writer.add_document(doc!(text_field => "no vector"))?;
+        index_writer.add_document(doc!(embedding_field => encode_vector(&[0.0, 1.0])))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let vector_field = VectorField {
+            dims: 2,
+            similarity: VectorSimilarity::Cosine,
+        };
+        let vector_fields = BTreeMap::from([("embedding".to_string(), vector_field)]);
+        let vector_index_path =
+            build_vector_index(&searcher, &vector_fields, split_scratch_directory.path())?.unwrap();
+        assert_eq!(
+            vector_index_path,
+            split_scratch_directory.path().join(VECTOR_INDEX_FILE_NAME)
+        );
+        let vector_index_bytes = std::fs::read(&vector_index_path)?;
+        let footer_len_start = vector_index_bytes.len() - VECTOR_INDEX_FOOTER_LEN_NUM_BYTES;
+        let footer_num_bytes =
+            VectorIndexFooter::footer_num_bytes(&vector_index_bytes[footer_len_start..])?;
+        let footer = VectorIndexFooter::deserialize(
+            &vector_index_bytes[footer_len_start - footer_num_bytes..footer_len_start],
+        )?;
+        assert_eq!(
+            footer.segment_ids,
+            vec![searcher.segment_reader(0).segment_id().uuid_string()]
+        );
+        let field_index = footer.field("embedding").unwrap();
+        let query_vector = [1.0, 0.0];
+        let list_ranges = field_index.probe(
+            &vector_index_bytes[field_index.centroids_byte_range()],
+            &query_vector,
+            DEFAULT_NUM_PROBES,
+        )?;
+        let mut scored_vectors: Vec<ScoredVector> = Vec::new();
+        for list_range in list_ranges {
+            scored_vectors
+                .extend(field_index.score_list(&vector_index_bytes[list_range], &query_vector)?);
+        }
+        scored_vectors.sort_by_key(|scored_vector| scored_vector.doc_id);
+        assert_eq!(scored_vectors.len(), 2);
+        assert_eq!(scored_vectors[0].doc_id, 0);
+        assert_eq!(scored_vectors[0].score, 1.0);
+        assert_eq!(scored_vectors[1].doc_id, 2);
+        assert_eq!(scored_vectors[1].score, 0.0);
+
+        assert!(
+            build_vector_index(&searcher, &BTreeMap::new(), split_scratch_directory.path())?
+                .is_none()
+        );
+        Ok(())
+    }
 }
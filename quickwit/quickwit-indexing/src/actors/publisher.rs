@@ -25,7 +25,7 @@ use fail::fail_point;
 use quickwit_actors::{Actor, ActorContext, Handler, Mailbox, QueueCapacity};
 use quickwit_metastore::Metastore;
 use serde::Serialize;
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use crate::actors::MergePlanner;
 use crate::models::{NewSplits, SplitsUpdate};
@@ -117,6 +117,7 @@ impl Handler<SplitsUpdate> for Publisher {
             checkpoint_delta_opt,
             publish_lock,
             merge_operation: _,
+            upload_journal_ticket_opt,
             parent_span: _,
         } = split_update;
 
@@ -125,6 +126,13 @@ impl Handler<SplitsUpdate> for Publisher {
         let replaced_split_ids_ref_vec: Vec<&str> =
             replaced_split_ids.iter().map(String::as_str).collect();
 
+        // Stamping the delta with the token of the lock lets the metastore fence off this
+        // pipeline if its partitions have been reassigned to another pipeline in the meantime.
+        let checkpoint_delta_opt =
+            checkpoint_delta_opt.map(|checkpoint_delta| match publish_lock.publish_token() {
+                Some(publish_token) => checkpoint_delta.with_publish_token(publish_token),
+                None => checkpoint_delta,
+            });
         if let Some(_guard) = publish_lock.acquire().await {
             ctx.protect_future(self.metastore.publish_splits(
                 &index_id,
@@ -143,6 +151,13 @@ impl Handler<SplitsUpdate> for Publisher {
             return Ok(());
         }
         info!(new_splits=?split_ids, checkpoint_delta=?checkpoint_delta_opt, "publish-new-splits");
+        if let Some(upload_journal_ticket) = upload_journal_ticket_opt {
+            // A leftover entry is harmless: its splits are found already published by the
+            // recovery.
+            if let Err(error) = upload_journal_ticket.remove().await {
+                warn!(error=?error, "Failed to remove upload journal entry.");
+            }
+        }
         if let Some(source_mailbox) = self.source_mailbox_opt.as_ref() {
             if let Some(checkpoint) = checkpoint_delta_opt {
                 // We voluntarily do not log anything here.
@@ -184,7 +199,8 @@ impl Handler<SplitsUpdate> for Publisher {
 mod tests {
     use quickwit_actors::Universe;
     use quickwit_metastore::checkpoint::{
-        IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
+        IndexCheckpointDelta, PartitionId, Position, PublishToken, SourceCheckpoint,
+        SourceCheckpointDelta,
     };
     use quickwit_metastore::{MockMetastore, SplitMetadata};
     use tracing::Span;
@@ -195,17 +211,20 @@ mod tests {
     #[tokio::test]
     async fn test_publisher_publish_operation() {
         let universe = Universe::with_accelerated_time();
+        let publish_token = PublishToken::from(1u64);
+        let publish_lock = PublishLock::with_publish_token(publish_token.clone());
         let mut mock_metastore = MockMetastore::default();
         mock_metastore
             .expect_publish_splits()
             .withf(
-                |index_id, split_ids, replaced_split_ids, checkpoint_delta_opt| {
+                move |index_id, split_ids, replaced_split_ids, checkpoint_delta_opt| {
                     let checkpoint_delta = checkpoint_delta_opt.as_ref().unwrap();
                     index_id == "index"
                         && checkpoint_delta.source_id == "source"
                         && split_ids[..] == ["split"]
                         && replaced_split_ids.is_empty()
                         && checkpoint_delta.source_delta == SourceCheckpointDelta::from_range(1..3)
+                        && checkpoint_delta.publish_token.as_ref() == Some(&publish_token)
                 },
             )
             .times(1)
@@ -233,9 +252,11 @@ mod tests {
                 checkpoint_delta_opt: Some(IndexCheckpointDelta {
                     source_id: "source".to_string(),
                     source_delta: SourceCheckpointDelta::from_range(1..3),
+                    publish_token: None,
                 }),
-                publish_lock: PublishLock::default(),
+                publish_lock,
                 merge_operation: None,
+                upload_journal_ticket_opt: None,
                 parent_span: tracing::Span::none(),
             })
             .await
@@ -298,6 +319,7 @@ mod tests {
             checkpoint_delta_opt: None,
             publish_lock: PublishLock::default(),
             merge_operation: None,
+            upload_journal_ticket_opt: None,
             parent_span: Span::none(),
         };
         assert!(publisher_mailbox
@@ -339,6 +361,7 @@ mod tests {
                 checkpoint_delta_opt: None,
                 publish_lock,
                 merge_operation: None,
+                upload_journal_ticket_opt: None,
                 parent_span: Span::none(),
             })
             .await
@@ -45,7 +45,7 @@ use crate::metrics::INDEXER_METRICS;
 use crate::models::{
     create_split_metadata, PackagedSplit, PackagedSplitBatch, PublishLock, SplitsUpdate,
 };
-use crate::split_store::IndexingSplitStore;
+use crate::split_store::{IndexingSplitStore, UploadJournalTicket};
 
 /// The following two semaphores ensures that, we have at most `max_concurrent_split_uploads` split
 /// uploads can happen at the same time, as configured in the `IndexerConfig`.
@@ -309,15 +309,35 @@ impl Handler<PackagedSplitBatch> for Uploader {
                         &packaged_split.split_files,
                         &packaged_split.hotcache_bytes,
                     )?;
-                    let split_metadata = create_split_metadata(
+                    let mut split_metadata = create_split_metadata(
                         &packaged_split.split_attrs,
                         packaged_split.tags.clone(),
+                        packaged_split.field_ranges.clone(),
+                        packaged_split.bloom_filters.clone(),
                         split_streamer.footer_range.start..split_streamer.footer_range.end,
                     );
+                    split_metadata.content_hash = split_store.content_hash(&split_streamer).await?;
 
                     split_metadata_list.push(split_metadata);
                 }
 
+                let mut upload_journal_ticket_opt = None;
+                if let Some(upload_journal) = split_store.upload_journal_opt() {
+                    let replaced_split_ids = batch
+                        .splits
+                        .iter()
+                        .flat_map(|split| split.split_attrs.replaced_split_ids.clone())
+                        .collect::<HashSet<_>>();
+                    let upload_journal_ticket = upload_journal
+                        .record_staged_splits(
+                            &index_id,
+                            &split_metadata_list,
+                            Vec::from_iter(replaced_split_ids),
+                            batch.checkpoint_delta_opt.clone(),
+                        )
+                        .await?;
+                    upload_journal_ticket_opt = Some(upload_journal_ticket);
+                }
                 metastore
                     .stage_splits(&index_id, split_metadata_list.clone())
                     .await?;
@@ -341,6 +361,9 @@ impl Handler<PackagedSplitBatch> for Uploader {
 
                     packaged_splits_and_metadata.push((packaged_split, metadata));
                 }
+                if let Some(upload_journal_ticket) = upload_journal_ticket_opt.as_mut() {
+                    upload_journal_ticket.record_uploaded().await?;
+                }
 
                 let splits_update = make_publish_operation(
                     index_id,
@@ -348,6 +371,7 @@ impl Handler<PackagedSplitBatch> for Uploader {
                     packaged_splits_and_metadata,
                     batch.checkpoint_delta_opt,
                     batch.merge_operation,
+                    upload_journal_ticket_opt,
                     batch.parent_span,
                 );
 
@@ -370,6 +394,7 @@ fn make_publish_operation(
     packaged_splits_and_metadatas: Vec<(PackagedSplit, SplitMetadata)>,
     checkpoint_delta_opt: Option<IndexCheckpointDelta>,
     merge_operation: Option<TrackedObject<MergeOperation>>,
+    upload_journal_ticket_opt: Option<UploadJournalTicket>,
     parent_span: Span,
 ) -> SplitsUpdate {
     assert!(!packaged_splits_and_metadatas.is_empty());
@@ -387,6 +412,7 @@ fn make_publish_operation(
         replaced_split_ids: Vec::from_iter(replaced_split_ids),
         checkpoint_delta_opt,
         merge_operation,
+        upload_journal_ticket_opt,
         parent_span,
     }
 }
@@ -421,6 +447,7 @@ async fn upload_split(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::path::PathBuf;
 
     use quickwit_actors::{ObservationType, Universe};
@@ -470,6 +497,7 @@ mod tests {
         let checkpoint_delta_opt: Option<IndexCheckpointDelta> = Some(IndexCheckpointDelta {
             source_id: "test-source".to_string(),
             source_delta: SourceCheckpointDelta::from_range(3..15),
+            publish_token: None,
         });
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -487,9 +515,13 @@ mod tests {
                         split_id: "test-split".to_string(),
                         delete_opstamp: 10,
                         num_merge_ops: 0,
+                        doc_mapping_version: 0,
+                        custom_metadata: BTreeMap::new(),
                     },
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_ranges: Default::default(),
+                    bloom_filters: Default::default(),
                     hotcache_bytes: vec![],
                     split_files: vec![],
                 }],
@@ -593,9 +625,13 @@ mod tests {
                 ],
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                doc_mapping_version: 0,
+                custom_metadata: BTreeMap::new(),
             },
             split_scratch_directory: split_scratch_directory_1,
             tags: Default::default(),
+            field_ranges: Default::default(),
+            bloom_filters: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
         };
@@ -616,9 +652,13 @@ mod tests {
                 ],
                 delete_opstamp: 0,
                 num_merge_ops: 0,
+                doc_mapping_version: 0,
+                custom_metadata: BTreeMap::new(),
             },
             split_scratch_directory: split_scratch_directory_2,
             tags: Default::default(),
+            field_ranges: Default::default(),
+            bloom_filters: Default::default(),
             split_files: vec![],
             hotcache_bytes: vec![],
         };
@@ -712,6 +752,7 @@ mod tests {
         let checkpoint_delta_opt: Option<IndexCheckpointDelta> = Some(IndexCheckpointDelta {
             source_id: "test-source".to_string(),
             source_delta: SourceCheckpointDelta::from_range(3..15),
+            publish_token: None,
         });
         uploader_mailbox
             .send_message(PackagedSplitBatch::new(
@@ -726,9 +767,13 @@ mod tests {
                         split_id: "test-split".to_string(),
                         delete_opstamp: 10,
                         num_merge_ops: 0,
+                        doc_mapping_version: 0,
+                        custom_metadata: BTreeMap::new(),
                     },
                     split_scratch_directory,
                     tags: Default::default(),
+                    field_ranges: Default::default(),
+                    bloom_filters: Default::default(),
                     hotcache_bytes: vec![],
                     split_files: vec![],
                 }],
@@ -0,0 +1,270 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "kafka")]
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use quickwit_actors::Mailbox;
+use quickwit_config::DeadLetterConfig;
+use quickwit_ingest_api::{
+    get_ingest_api_service, CreateQueueIfNotExistsRequest, DocBatchBuilder, IngestApiService,
+    IngestRequest,
+};
+use quickwit_metastore::Metastore;
+use quickwit_storage::{Storage, StorageUriResolver};
+#[cfg(feature = "kafka")]
+use rdkafka::producer::{FutureProducer, FutureRecord};
+#[cfg(feature = "kafka")]
+use rdkafka::util::Timeout;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+/// Maximum amount of time spent producing a rejected document to Kafka.
+#[cfg(feature = "kafka")]
+const KAFKA_SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A document rejected by the doc processor.
+#[derive(Debug)]
+pub(crate) struct RejectedDoc {
+    /// Raw document, as received from the source.
+    pub doc: String,
+    /// Reason of the rejection, in [parsing_error, missing_field, transform_error].
+    pub reason: &'static str,
+    pub error: String,
+}
+
+/// Record written to the dead letter destination for each rejected document.
+#[derive(Serialize)]
+struct DeadLetterRecord<'a> {
+    index_id: &'a str,
+    source_id: &'a str,
+    rejected_at: i64,
+    reason: &'a str,
+    error: &'a str,
+    doc: &'a str,
+}
+
+enum DeadLetterDestination {
+    Index {
+        ingest_api_service: Mailbox<IngestApiService>,
+        index_id: String,
+    },
+    Storage(Arc<dyn Storage>),
+    #[cfg(feature = "kafka")]
+    Kafka {
+        producer: FutureProducer,
+        topic: String,
+    },
+}
+
+/// Routes the documents rejected by the doc processor of an indexing pipeline to the dead letter
+/// destination of the index.
+pub(crate) struct DeadLetterQueue {
+    index_id: String,
+    source_id: String,
+    destination: DeadLetterDestination,
+}
+
+impl DeadLetterQueue {
+    pub async fn try_new(
+        index_id: String,
+        source_id: String,
+        dead_letter_config: &DeadLetterConfig,
+        metastore: &dyn Metastore,
+        queues_dir_path: &Path,
+        storage_resolver: &StorageUriResolver,
+    ) -> anyhow::Result<Self> {
+        let destination = match dead_letter_config {
+            DeadLetterConfig::Index {
+                index_id: dead_letter_index_id,
+            } => {
+                if !metastore.index_exists(dead_letter_index_id).await? {
+                    bail!("Dead letter index `{dead_letter_index_id}` does not exist.");
+                }
+                let ingest_api_service = get_ingest_api_service(queues_dir_path).await?;
+                let create_queue_req = CreateQueueIfNotExistsRequest {
+                    queue_id: dead_letter_index_id.clone(),
+                };
+                ingest_api_service.ask_for_res(create_queue_req).await?;
+                DeadLetterDestination::Index {
+                    ingest_api_service,
+                    index_id: dead_letter_index_id.clone(),
+                }
+            }
+            DeadLetterConfig::Storage { uri } => {
+                let storage = storage_resolver.resolve(uri)?;
+                DeadLetterDestination::Storage(storage)
+            }
+            #[cfg(feature = "kafka")]
+            DeadLetterConfig::Kafka {
+                topic,
+                client_params,
+            } => {
+                let producer = crate::source::parse_kafka_client_params(client_params.clone())?
+                    .create()
+                    .context("Failed to create dead letter Kafka producer.")?;
+                DeadLetterDestination::Kafka {
+                    producer,
+                    topic: topic.clone(),
+                }
+            }
+            #[cfg(not(feature = "kafka"))]
+            DeadLetterConfig::Kafka { .. } => {
+                bail!("Quickwit binary was not compiled with the `kafka` feature.")
+            }
+        };
+        Ok(Self {
+            index_id,
+            source_id,
+            destination,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn for_test(index_id: &str, source_id: &str, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+            destination: DeadLetterDestination::Storage(storage),
+        }
+    }
+
+    /// Sends a batch of rejected documents to the dead letter destination.
+    pub async fn send(&self, rejected_docs: &[RejectedDoc]) -> anyhow::Result<()> {
+        if rejected_docs.is_empty() {
+            return Ok(());
+        }
+        let rejected_at = OffsetDateTime::now_utc().unix_timestamp();
+        let records = rejected_docs.iter().map(|rejected_doc| DeadLetterRecord {
+            index_id: &self.index_id,
+            source_id: &self.source_id,
+            rejected_at,
+            reason: rejected_doc.reason,
+            error: &rejected_doc.error,
+            doc: &rejected_doc.doc,
+        });
+        match &self.destination {
+            DeadLetterDestination::Index {
+                ingest_api_service,
+                index_id,
+            } => {
+                let mut doc_batch_builder = DocBatchBuilder::new(index_id.clone()).json_writer();
+                for record in records {
+                    doc_batch_builder.ingest_doc(record)?;
+                }
+                let ingest_req = IngestRequest {
+                    doc_batches: vec![doc_batch_builder.build()],
+                };
+                ingest_api_service
+                    .ask_for_res(ingest_req)
+                    .await
+                    .context("Failed to ingest rejected documents into the dead letter index.")?;
+            }
+            DeadLetterDestination::Storage(storage) => {
+                let mut payload = Vec::new();
+                for record in records {
+                    serde_json::to_writer(&mut payload, &record)?;
+                    payload.push(b'\n');
+                }
+                storage
+                    .put(&self.storage_path(), Box::new(payload))
+                    .await
+                    .context("Failed to write rejected documents to the dead letter storage.")?;
+            }
+            #[cfg(feature = "kafka")]
+            DeadLetterDestination::Kafka { producer, topic } => {
+                let payloads = records
+                    .map(|record| serde_json::to_string(&record))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let send_futures = payloads.iter().map(|payload| {
+                    let record = FutureRecord::to(topic)
+                        .key(self.source_id.as_str())
+                        .payload(payload.as_str());
+                    producer.send(record, Timeout::After(KAFKA_SEND_TIMEOUT))
+                });
+                futures::future::try_join_all(send_futures)
+                    .await
+                    .map_err(|(error, _)| error)
+                    .context("Failed to produce rejected documents to the dead letter topic.")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the path of a new NDJSON file of rejected documents, relative to the dead letter
+    /// storage: `<index_id>/<source_id>/<ulid>.ndjson`.
+    fn storage_path(&self) -> PathBuf {
+        Path::new(&self.index_id)
+            .join(&self.source_id)
+            .join(format!("{}.ndjson", ulid::Ulid::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_storage::RamStorage;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dead_letter_queue_storage() {
+        let storage = Arc::new(RamStorage::default());
+        let dead_letter_queue =
+            DeadLetterQueue::for_test("test-index", "test-source", storage.clone());
+        dead_letter_queue.send(&[]).await.unwrap();
+        assert!(storage.list_files().await.is_empty());
+
+        let rejected_docs = vec![
+            RejectedDoc {
+                doc: "{".to_string(),
+                reason: "parsing_error",
+                error: "EOF while parsing an object".to_string(),
+            },
+            RejectedDoc {
+                doc: r#"{"body": "happy"}"#.to_string(),
+                reason: "missing_field",
+                error: "The timestamp field is missing.".to_string(),
+            },
+        ];
+        dead_letter_queue.send(&rejected_docs).await.unwrap();
+
+        let files = storage.list_files().await;
+        assert_eq!(files.len(), 1);
+        assert!(files[0].starts_with("test-index/test-source"));
+
+        let payload = storage.get_all(&files[0]).await.unwrap();
+        let records: Vec<JsonValue> = std::str::from_utf8(&payload)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["index_id"], "test-index");
+        assert_eq!(records[0]["source_id"], "test-source");
+        assert_eq!(records[0]["reason"], "parsing_error");
+        assert_eq!(records[0]["doc"], "{");
+        assert_eq!(records[1]["reason"], "missing_field");
+        assert_eq!(records[1]["error"], "The timestamp field is missing.");
+        assert!(records[1]["rejected_at"].is_i64());
+    }
+}
@@ -23,16 +23,24 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 use quickwit_actors::Mailbox;
+use quickwit_cluster::ClusterMember;
 use quickwit_config::service::QuickwitService;
 use quickwit_grpc_clients::service_client_pool::ServiceClient;
+use quickwit_grpc_clients::tls::grpc_endpoint;
 use quickwit_proto::indexing_api::ApplyIndexingPlanRequest;
-use quickwit_proto::tonic::transport::{Channel, Endpoint, Uri};
+use quickwit_proto::tonic::codegen::InterceptedService;
+use quickwit_proto::tonic::transport::Channel;
+use quickwit_proto::ClusterInterceptor;
 
 use crate::IndexingService;
 
 #[derive(Clone)]
 enum IndexingServiceClientImpl {
-    Grpc(quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient<Channel>),
+    Grpc(
+        quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient<
+            InterceptedService<Channel, ClusterInterceptor>,
+        >,
+    ),
     Local(Mailbox<IndexingService>),
 }
 
@@ -61,6 +69,12 @@ impl ServiceClient for IndexingServiceClient {
         QuickwitService::Indexer
     }
 
+    /// The merger nodes run the indexing service too, to host the merge pipelines.
+    fn is_served_by(member: &ClusterMember) -> bool {
+        member.enabled_services.contains(&QuickwitService::Indexer)
+            || member.enabled_services.contains(&QuickwitService::Merger)
+    }
+
     async fn build_client(grpc_addr: SocketAddr) -> anyhow::Result<Self> {
         create_indexing_service_client(grpc_addr).await
     }
@@ -73,7 +87,7 @@ impl ServiceClient for IndexingServiceClient {
 impl IndexingServiceClient {
     pub fn from_grpc_client(
         client: quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient<
-            Channel,
+            InterceptedService<Channel, ClusterInterceptor>,
         >,
         grpc_addr: SocketAddr,
     ) -> Self {
@@ -114,16 +128,14 @@ impl IndexingServiceClient {
 pub async fn create_indexing_service_client(
     grpc_addr: SocketAddr,
 ) -> anyhow::Result<IndexingServiceClient> {
-    let uri = Uri::builder()
-        .scheme("http")
-        .authority(grpc_addr.to_string().as_str())
-        .path_and_query("/")
-        .build()?;
-    let channel = Endpoint::from(uri)
+    let channel = grpc_endpoint(grpc_addr)
         .connect_timeout(Duration::from_secs(5))
         .connect_lazy();
     let client = IndexingServiceClient::from_grpc_client(
-        quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient::new(channel),
+        quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient::with_interceptor(
+            channel,
+            ClusterInterceptor,
+        ),
         grpc_addr,
     );
     Ok(client)
@@ -148,9 +160,7 @@ mod tests {
         let (mailbox, inbox) = universe.create_test_mailbox::<IndexingService>();
         let mut client = IndexingServiceClient::from_service(mailbox, grpc_addr);
         client
-            .apply_indexing_plan(ApplyIndexingPlanRequest {
-                indexing_tasks: Vec::new(),
-            })
+            .apply_indexing_plan(ApplyIndexingPlanRequest::default())
             .await
             .unwrap();
         assert_eq!(inbox.drain_for_test().len(), 1);
@@ -172,14 +182,13 @@ mod tests {
         let channel = create_channel_from_duplex_stream(client).await.unwrap();
         let grpc_addr = ([127, 0, 0, 1], 1).into();
         let grpc_client =
-            quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient::new(
+            quickwit_proto::indexing_api::indexing_service_client::IndexingServiceClient::with_interceptor(
                 channel,
+                ClusterInterceptor,
             );
         let mut client = IndexingServiceClient::from_grpc_client(grpc_client, grpc_addr);
         client
-            .apply_indexing_plan(ApplyIndexingPlanRequest {
-                indexing_tasks: Vec::new(),
-            })
+            .apply_indexing_plan(ApplyIndexingPlanRequest::default())
             .await
             .unwrap();
         assert_eq!(inbox.drain_for_test().len(), 1);
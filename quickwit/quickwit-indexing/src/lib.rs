@@ -39,6 +39,7 @@ pub use crate::split_store::{get_tantivy_directory_from_split_bundle, IndexingSp
 
 pub mod actors;
 mod controlled_directory;
+mod dead_letter_queue;
 pub mod grpc_adapter;
 pub mod indexing_client;
 pub mod merge_policy;
@@ -90,3 +91,28 @@ pub async fn start_indexing_service(
 
     Ok(indexing_service)
 }
+
+/// Starts the indexing service of a node running the merger service without the indexer service.
+/// Such a node only runs the merge pipelines of the merge tasks assigned by the control plane.
+pub async fn start_merger_service(
+    universe: &Universe,
+    config: &QuickwitConfig,
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
+) -> anyhow::Result<Mailbox<IndexingService>> {
+    info!("Starting merger service.");
+    let indexing_service = IndexingService::new(
+        config.node_id.clone(),
+        config.data_dir_path.to_path_buf(),
+        config.indexer_config.clone(),
+        cluster,
+        metastore,
+        None,
+        storage_resolver,
+    )
+    .await?;
+    let (indexing_service, _) = universe.spawn_builder().spawn(indexing_service);
+
+    Ok(indexing_service)
+}
@@ -20,6 +20,7 @@
 mod const_write_amplification;
 mod nop_merge_policy;
 mod stable_log_merge_policy;
+mod time_tiered_merge_policy;
 
 use std::fmt;
 use std::sync::Arc;
@@ -32,6 +33,7 @@ use quickwit_config::IndexingSettings;
 use quickwit_metastore::SplitMetadata;
 use serde::Serialize;
 pub(crate) use stable_log_merge_policy::StableLogMergePolicy;
+pub(crate) use time_tiered_merge_policy::TimeTieredMergePolicy;
 use tracing::{info_span, Span};
 
 use crate::new_split_id;
@@ -132,6 +134,10 @@ pub fn merge_policy_from_settings(settings: &IndexingSettings) -> Arc<dyn MergeP
             let merge_policy = StableLogMergePolicy::new(config, settings.split_num_docs_target);
             Arc::new(merge_policy)
         }
+        MergePolicyConfig::TimeTiered(config) => {
+            let merge_policy = TimeTieredMergePolicy::new(config, settings.split_num_docs_target);
+            Arc::new(merge_policy)
+        }
     }
 }
 
@@ -322,7 +328,13 @@ pub mod tests {
             pipeline_ord: 0,
         };
         let split_attrs = merge_split_attrs(merged_split_id, &pipeline_id, splits);
-        create_split_metadata(&split_attrs, tags, 0..0)
+        create_split_metadata(
+            &split_attrs,
+            tags,
+            Default::default(),
+            Default::default(),
+            0..0,
+        )
     }
 
     fn apply_merge(
@@ -0,0 +1,188 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use quickwit_config::merge_policy_config::{
+    ConstWriteAmplificationMergePolicyConfig, TimeTieredMergePolicyConfig,
+};
+use quickwit_metastore::SplitMetadata;
+
+use super::{ConstWriteAmplificationMergePolicy, MergeOperation};
+use crate::merge_policy::MergePolicy;
+
+/// The `TimeTieredMergePolicy` has been designed for append-only time series, for which
+/// searches are usually restricted to a time range.
+///
+/// The time axis is divided into fixed-width buckets (an hour, a day, ...), and a split belongs
+/// to the bucket containing the start of its time range. Only splits belonging to the same bucket
+/// are merged together, so that merged splits keep a narrow time range and time pruning remains
+/// effective at search time. Splits lacking a time range are grouped together.
+///
+/// Within a bucket, splits are merged following the `ConstWriteAmplificationMergePolicy` logic.
+#[derive(Debug, Clone)]
+pub struct TimeTieredMergePolicy {
+    time_bucket_secs: i64,
+    bucket_merge_policy: ConstWriteAmplificationMergePolicy,
+}
+
+impl TimeTieredMergePolicy {
+    pub fn new(config: TimeTieredMergePolicyConfig, split_num_docs_target: usize) -> Self {
+        let bucket_merge_policy_config = ConstWriteAmplificationMergePolicyConfig {
+            merge_factor: config.merge_factor,
+            max_merge_factor: config.max_merge_factor,
+            max_merge_ops: config.max_merge_ops,
+            maturation_period: config.maturation_period,
+        };
+        TimeTieredMergePolicy {
+            time_bucket_secs: config.time_bucket.as_secs().max(1) as i64,
+            bucket_merge_policy: ConstWriteAmplificationMergePolicy::new(
+                bucket_merge_policy_config,
+                split_num_docs_target,
+            ),
+        }
+    }
+
+    #[cfg(test)]
+    fn for_test() -> TimeTieredMergePolicy {
+        use std::time::Duration;
+
+        let config = TimeTieredMergePolicyConfig {
+            time_bucket: Duration::from_secs(3600),
+            merge_factor: 3,
+            max_merge_factor: 5,
+            max_merge_ops: 3,
+            maturation_period: Duration::from_secs(3600),
+        };
+        Self::new(config, 10_000_000)
+    }
+
+    fn time_bucket(&self, split: &SplitMetadata) -> Option<i64> {
+        split
+            .time_range
+            .as_ref()
+            .map(|time_range| time_range.start().div_euclid(self.time_bucket_secs))
+    }
+}
+
+impl MergePolicy for TimeTieredMergePolicy {
+    fn operations(&self, splits: &mut Vec<SplitMetadata>) -> Vec<MergeOperation> {
+        let mut splits_per_time_bucket: HashMap<Option<i64>, Vec<SplitMetadata>> =
+            HashMap::default();
+        for split in splits.drain(..) {
+            splits_per_time_bucket
+                .entry(self.time_bucket(&split))
+                .or_default()
+                .push(split);
+        }
+        let mut merge_operations = Vec::new();
+        for splits_in_time_bucket in splits_per_time_bucket.values_mut() {
+            let merge_ops = self.bucket_merge_policy.operations(splits_in_time_bucket);
+            merge_operations.extend(merge_ops);
+            splits.append(splits_in_time_bucket);
+        }
+        merge_operations
+    }
+
+    fn is_mature(&self, split: &SplitMetadata) -> bool {
+        self.bucket_merge_policy.is_mature(split)
+    }
+
+    #[cfg(test)]
+    fn check_is_valid(&self, merge_op: &MergeOperation, remaining_splits: &[SplitMetadata]) {
+        use std::collections::HashSet;
+        self.bucket_merge_policy
+            .check_is_valid(merge_op, remaining_splits);
+        let time_buckets: HashSet<Option<i64>> = merge_op
+            .splits_as_slice()
+            .iter()
+            .map(|split| self.time_bucket(split))
+            .collect();
+        assert_eq!(time_buckets.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::SplitMetadata;
+    use time::OffsetDateTime;
+
+    use super::TimeTieredMergePolicy;
+    use crate::merge_policy::MergeOperation;
+    use crate::MergePolicy;
+
+    fn make_split(split_id: &str, start_timestamp: i64) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            num_docs: 1_000,
+            time_range: Some(start_timestamp..=start_timestamp + 60),
+            create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_tiered_merge_policy_empty() {
+        let mut splits = Vec::new();
+        let merge_policy = TimeTieredMergePolicy::for_test();
+        assert!(merge_policy.operations(&mut splits).is_empty());
+    }
+
+    #[test]
+    fn test_time_tiered_merge_policy_merges_within_time_bucket() {
+        let merge_policy = TimeTieredMergePolicy::for_test();
+        let mut splits = vec![
+            make_split("split-hour-0-a", 0),
+            make_split("split-hour-0-b", 1_000),
+            make_split("split-hour-1-a", 3_600),
+            make_split("split-hour-0-c", 3_000),
+            make_split("split-hour-1-b", 4_000),
+        ];
+        let operations: Vec<MergeOperation> = merge_policy.operations(&mut splits);
+        assert_eq!(operations.len(), 1);
+        let mut merged_split_ids: Vec<&str> = operations[0]
+            .splits_as_slice()
+            .iter()
+            .map(|split| split.split_id())
+            .collect();
+        merged_split_ids.sort();
+        assert_eq!(
+            merged_split_ids,
+            ["split-hour-0-a", "split-hour-0-b", "split-hour-0-c"]
+        );
+        assert_eq!(splits.len(), 2);
+    }
+
+    #[test]
+    fn test_time_tiered_merge_policy_does_not_merge_across_time_buckets() {
+        let merge_policy = TimeTieredMergePolicy::for_test();
+        let mut splits: Vec<SplitMetadata> = (0..5)
+            .map(|hour| make_split(&format!("split-{hour}"), hour * 3_600))
+            .collect();
+        let operations: Vec<MergeOperation> = merge_policy.operations(&mut splits);
+        assert!(operations.is_empty());
+        assert_eq!(splits.len(), 5);
+    }
+
+    #[test]
+    fn test_time_tiered_merge_policy_proptest() {
+        let merge_policy = TimeTieredMergePolicy::for_test();
+        crate::merge_policy::tests::proptest_merge_policy(&merge_policy);
+    }
+}
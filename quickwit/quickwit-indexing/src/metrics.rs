@@ -24,6 +24,11 @@ pub struct IndexerMetrics {
     pub processed_docs_total: IntCounterVec<3>,
     pub processed_bytes: IntCounterVec<3>,
     pub backpressure_micros: IntCounterVec<2>,
+    pub processing_micros: IntCounterVec<2>,
+    pub in_flight_splits_memory_usage_bytes: IntGaugeVec<2>,
+    pub memory_budget_commits_total: IntCounterVec<2>,
+    pub source_decode_errors_total: IntCounterVec<2>,
+    pub kinesis_shard_lag_millis: IntGaugeVec<3>,
     pub available_concurrent_upload_permits: IntGaugeVec<1>,
     pub ongoing_merge_operations: IntGaugeVec<2>,
 }
@@ -52,6 +57,40 @@ impl Default for IndexerMetrics {
                 "quickwit_indexing",
                 ["index", "actor_name"],
             ),
+            processing_micros: new_counter_vec(
+                "processing_micros",
+                "Amount of time spent by the doc processors and the indexers processing batches of \
+                 documents (in micros). It approximates the CPU time used for indexing.",
+                "quickwit_indexing",
+                ["index", "actor_name"],
+            ),
+            in_flight_splits_memory_usage_bytes: new_gauge_vec(
+                "in_flight_splits_memory_usage_bytes",
+                "Amount of memory used by the splits being built by the indexers (in bytes).",
+                "quickwit_indexing",
+                ["index", "source"],
+            ),
+            memory_budget_commits_total: new_counter_vec(
+                "memory_budget_commits_total",
+                "Number of commits triggered early because the indexing memory budget of the node \
+                 was nearly exhausted.",
+                "quickwit_indexing",
+                ["index", "source"],
+            ),
+            source_decode_errors_total: new_counter_vec(
+                "source_decode_errors_total",
+                "Number of messages skipped by the sources because they could not be decoded, \
+                 e.g. Avro messages not matching their writer schema.",
+                "quickwit_indexing",
+                ["index", "source"],
+            ),
+            kinesis_shard_lag_millis: new_gauge_vec(
+                "kinesis_shard_lag_millis",
+                "Number of milliseconds the records read by the Kinesis sources are behind the tip \
+                 of their shard, by index, source and shard.",
+                "quickwit_indexing",
+                ["index", "source", "shard"],
+            ),
             available_concurrent_upload_permits: new_gauge_vec(
                 "concurrent_upload_available_permits_num",
                 "Number of available concurrent upload permits by component in [merger, indexer]",
@@ -0,0 +1,212 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hasher;
+use std::time::{Duration, Instant};
+
+use siphasher::sip128::{Hasher128, SipHasher};
+use tantivy::schema::{Field, Value};
+use tantivy::Document;
+
+/// Maximum number of doc IDs tracked by a deduplicator. Each doc ID costs less than 100 bytes, so
+/// a deduplicator uses at most about 100MB.
+pub const MAX_NUM_TRACKED_DOC_IDS: usize = 1_000_000;
+
+/// Keeps track of the doc unique IDs seen by an indexing pipeline over a rolling time window.
+///
+/// The deduplicator outlives workbenches, so duplicates are detected across splits. Doc IDs are
+/// stored as 128-bit SipHash hashes computed with fixed keys over a fixed byte encoding, so they do
+/// not depend on the Rust version. Two distinct IDs hashing to the same value would make the
+/// second document be dropped, but with 128-bit hashes and at most `max_num_doc_ids` tracked IDs,
+/// the odds are negligible (below 10^-26). When a stream holds more distinct IDs than that within
+/// the window, the oldest IDs are forgotten before the end of the window and a duplicate may then
+/// be indexed.
+pub struct DocDeduplicator {
+    doc_unique_id_field: Field,
+    window: Duration,
+    max_num_doc_ids: usize,
+    seen_doc_id_hashes: HashSet<u128>,
+    // Doc ID hashes in insertion order, used to evict the hashes older than `window` or in excess
+    // of `max_num_doc_ids`.
+    expiration_queue: VecDeque<(Instant, u128)>,
+}
+
+impl DocDeduplicator {
+    pub fn new(doc_unique_id_field: Field, window: Duration) -> Self {
+        Self {
+            doc_unique_id_field,
+            window,
+            max_num_doc_ids: MAX_NUM_TRACKED_DOC_IDS,
+            seen_doc_id_hashes: HashSet::new(),
+            expiration_queue: VecDeque::new(),
+        }
+    }
+
+    /// Sets the maximum number of doc IDs tracked, which defaults to [`MAX_NUM_TRACKED_DOC_IDS`].
+    pub fn with_max_num_doc_ids(mut self, max_num_doc_ids: usize) -> Self {
+        self.max_num_doc_ids = max_num_doc_ids.max(1);
+        self
+    }
+
+    /// Returns `true` if a document with the same unique ID was already seen within the window.
+    /// Otherwise, records the document ID and returns `false`.
+    ///
+    /// Documents without a unique ID are never considered as duplicates.
+    pub fn is_duplicate(&mut self, doc: &Document, now: Instant) -> bool {
+        self.evict_expired(now);
+
+        let Some(doc_id_hash) = doc
+            .get_first(self.doc_unique_id_field)
+            .and_then(hash_doc_id) else {
+            return false;
+        };
+        if !self.seen_doc_id_hashes.insert(doc_id_hash) {
+            return true;
+        }
+        self.expiration_queue.push_back((now, doc_id_hash));
+
+        if self.expiration_queue.len() > self.max_num_doc_ids {
+            if let Some((_, oldest_doc_id_hash)) = self.expiration_queue.pop_front() {
+                self.seen_doc_id_hashes.remove(&oldest_doc_id_hash);
+            }
+        }
+        false
+    }
+
+    /// Returns the number of doc IDs currently tracked.
+    pub fn num_tracked_doc_ids(&self) -> usize {
+        self.seen_doc_id_hashes.len()
+    }
+
+    /// Forgets all the doc IDs seen so far.
+    pub fn clear(&mut self) {
+        self.seen_doc_id_hashes.clear();
+        self.expiration_queue.clear();
+    }
+
+    fn evict_expired(&mut self, now: Instant) {
+        while let Some((seen_at, doc_id_hash)) = self.expiration_queue.front().copied() {
+            if now.saturating_duration_since(seen_at) < self.window {
+                break;
+            }
+            self.seen_doc_id_hashes.remove(&doc_id_hash);
+            self.expiration_queue.pop_front();
+        }
+    }
+}
+
+/// Hashes the doc ID into a 128-bit value. The bytes fed to the hasher are spelled out rather than
+/// relying on the `Hash` implementations of the standard library, which may change.
+fn hash_doc_id(value: &Value) -> Option<u128> {
+    let mut hasher = SipHasher::new();
+    // We prefix the value with its type so that, for instance, `1u64` and `1i64` do not collide.
+    match value {
+        Value::Str(text) => {
+            hasher.write_u8(0);
+            hasher.write(text.as_bytes());
+        }
+        Value::U64(val) => {
+            hasher.write_u8(1);
+            hasher.write(&val.to_le_bytes());
+        }
+        Value::I64(val) => {
+            hasher.write_u8(2);
+            hasher.write(&val.to_le_bytes());
+        }
+        Value::Bytes(bytes) => {
+            hasher.write_u8(3);
+            hasher.write(bytes);
+        }
+        _ => return None,
+    }
+    Some(hasher.finish128().as_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::doc;
+    use tantivy::schema::{Schema, FAST, STRING};
+
+    use super::*;
+
+    #[test]
+    fn test_doc_deduplicator() {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING);
+        let body_field = schema_builder.add_text_field("body", STRING);
+        let _schema = schema_builder.build();
+
+        let mut deduplicator = DocDeduplicator::new(id_field, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert!(!deduplicator.is_duplicate(&doc!(id_field => "id-1"), start));
+        assert!(!deduplicator.is_duplicate(&doc!(id_field => "id-2"), start));
+        assert!(deduplicator.is_duplicate(&doc!(id_field => "id-1"), start));
+        // Documents without ID are never duplicates.
+        assert!(!deduplicator.is_duplicate(&doc!(body_field => "no id"), start));
+        assert!(!deduplicator.is_duplicate(&doc!(body_field => "no id"), start));
+        assert_eq!(deduplicator.num_tracked_doc_ids(), 2);
+
+        let within_window = start + Duration::from_secs(30);
+        assert!(deduplicator.is_duplicate(&doc!(id_field => "id-2"), within_window));
+        assert!(!deduplicator.is_duplicate(&doc!(id_field => "id-3"), within_window));
+
+        let after_window = start + Duration::from_secs(60);
+        assert!(!deduplicator.is_duplicate(&doc!(id_field => "id-1"), after_window));
+        assert!(deduplicator.is_duplicate(&doc!(id_field => "id-3"), after_window));
+        assert_eq!(deduplicator.num_tracked_doc_ids(), 2);
+    }
+
+    #[test]
+    fn test_doc_deduplicator_memory_is_bounded() {
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_u64_field("id", FAST);
+        let _schema = schema_builder.build();
+
+        let max_num_doc_ids = 1_000;
+        let mut deduplicator = DocDeduplicator::new(id_field, Duration::from_secs(3_600))
+            .with_max_num_doc_ids(max_num_doc_ids);
+        let now = Instant::now();
+
+        for doc_id in 0..100 * max_num_doc_ids as u64 {
+            assert!(!deduplicator.is_duplicate(&doc!(id_field => doc_id), now));
+            assert!(deduplicator.num_tracked_doc_ids() <= max_num_doc_ids);
+        }
+        assert_eq!(deduplicator.num_tracked_doc_ids(), max_num_doc_ids);
+        assert_eq!(deduplicator.expiration_queue.len(), max_num_doc_ids);
+        assert!(deduplicator.seen_doc_id_hashes.capacity() <= 2 * max_num_doc_ids);
+        assert!(deduplicator.expiration_queue.capacity() <= 2 * max_num_doc_ids);
+
+        // The most recent IDs are still detected as duplicates, the oldest ones were forgotten.
+        let last_doc_id = 100 * max_num_doc_ids as u64 - 1;
+        assert!(deduplicator.is_duplicate(&doc!(id_field => last_doc_id), now));
+        assert!(!deduplicator.is_duplicate(&doc!(id_field => 0u64), now));
+    }
+
+    #[test]
+    fn test_hash_doc_id() {
+        assert_eq!(
+            hash_doc_id(&Value::Str("id-1".to_string())),
+            hash_doc_id(&Value::Str("id-1".to_string()))
+        );
+        assert_ne!(hash_doc_id(&Value::U64(1)), hash_doc_id(&Value::I64(1)));
+        assert_eq!(hash_doc_id(&Value::Bool(true)), None);
+    }
+}
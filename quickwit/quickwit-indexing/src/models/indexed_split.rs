@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::path::Path;
 
@@ -77,6 +78,7 @@ impl IndexedSplitBuilder {
     pub fn new_in_dir(
         pipeline_id: IndexingPipelineId,
         partition_id: u64,
+        doc_mapping_version: u64,
         last_delete_opstamp: u64,
         scratch_directory: ScratchDirectory,
         index_builder: IndexBuilder,
@@ -107,6 +109,8 @@ impl IndexedSplitBuilder {
                 time_range: None,
                 delete_opstamp: last_delete_opstamp,
                 num_merge_ops: 0,
+                doc_mapping_version,
+                custom_metadata: BTreeMap::new(),
             },
             index_writer,
             split_scratch_directory,
@@ -164,9 +168,12 @@ pub struct IndexedSplitBatch {
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum CommitTrigger {
     Timeout,
+    Idle,
     NoMoreDocs,
     NumDocsLimit,
     MemoryLimit,
+    /// The commit was requested by an operator.
+    Forced,
 }
 
 #[derive(Debug)]
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use byte_unit::Byte;
 use quickwit_config::SourceConfig;
 
 use super::IndexingPipelineId;
@@ -62,3 +63,46 @@ pub struct DetachMergePipeline {
 pub struct ObservePipeline {
     pub pipeline_id: IndexingPipelineId,
 }
+
+/// Command applied by an operator to the indexing pipelines of an index and a source running on
+/// the node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PipelineCommand {
+    /// Stops fetching documents from the source. The documents already fetched are still indexed
+    /// and committed.
+    PauseSource,
+    /// Resumes fetching documents from a paused source.
+    ResumeSource,
+    /// Commits the documents indexed so far without waiting for the commit timeout.
+    Commit,
+    /// Plans the merges of the splits produced by the pipelines right away.
+    PlanMerges,
+    /// Limits the write throughput of the merges. `None` lifts the limit.
+    ThrottleMerges {
+        max_merge_write_throughput: Option<Byte>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct ControlPipelines {
+    pub index_id: String,
+    pub source_id: String,
+    pub command: PipelineCommand,
+}
+
+/// Lists the status of the indexing pipelines running on the node.
+#[derive(Clone, Copy, Debug)]
+pub struct ListPipelines;
+
+/// Pauses the sources of the indexing pipelines running on the node, except the ingest API
+/// sources which must empty their queue, and commits the documents indexed so far. Replies with
+/// the number of pipelines still holding documents or splits that have not been published yet.
+/// The message is sent repeatedly while the node is drained.
+#[derive(Clone, Copy, Debug)]
+pub struct DrainPipelines;
+
+/// Respawns the pipelines of the indexes whose doc mapping, indexing settings, or search
+/// settings changed since their pipelines were spawned, so that the pipelines apply the new
+/// config. Replies with the number of respawned indexing pipelines.
+#[derive(Clone, Copy, Debug)]
+pub struct RefreshIndexConfigs;
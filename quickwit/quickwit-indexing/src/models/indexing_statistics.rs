@@ -42,6 +42,10 @@ pub struct IndexingStatistics {
     pub total_bytes_processed: u64,
     /// Size in bytes of resulting split
     pub total_size_splits: u64,
+    /// Number of valid documents indexed but not committed yet
+    pub num_docs_in_workbench: u64,
+    /// Number of split batches committed but not published yet
+    pub num_in_flight_split_batches: u64,
     /// Pipeline generation.
     pub generation: usize,
     /// Number of successive pipeline spawn attempts.
@@ -63,6 +67,12 @@ impl IndexingStatistics {
         self.num_staged_splits += uploader_counters.num_staged_splits.load(Ordering::SeqCst);
         self.num_uploaded_splits += uploader_counters.num_uploaded_splits.load(Ordering::SeqCst);
         self.num_published_splits += publisher_counters.num_published_splits;
+        // The in-flight counts only depend on the current generation of the pipeline: the data
+        // in flight in the previous generations was lost and will be fetched again.
+        self.num_docs_in_workbench = indexer_counters.num_docs_in_workbench;
+        self.num_in_flight_split_batches = indexer_counters
+            .num_split_batches_emitted
+            .saturating_sub(publisher_counters.num_published_splits);
         self
     }
 
@@ -0,0 +1,145 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use byte_unit::Byte;
+use quickwit_common::metrics::IntGauge;
+
+/// Ratio of the budget above which the budget is considered nearly exhausted.
+const NEAR_EXHAUSTION_RATIO: f64 = 0.9;
+
+/// Memory budget shared by the indexers of a node. Each indexer accounts for the memory used by
+/// the splits it is building, and commits early when the budget is nearly exhausted.
+#[derive(Clone, Debug, Default)]
+pub struct IndexingMemoryBudget {
+    inner: Arc<IndexingMemoryBudgetInner>,
+}
+
+#[derive(Debug, Default)]
+struct IndexingMemoryBudgetInner {
+    capacity_opt: Option<u64>,
+    num_bytes_used: AtomicU64,
+}
+
+impl IndexingMemoryBudget {
+    pub fn new(capacity_opt: Option<Byte>) -> Self {
+        let inner = IndexingMemoryBudgetInner {
+            capacity_opt: capacity_opt.map(|capacity| capacity.get_bytes() as u64),
+            num_bytes_used: AtomicU64::new(0),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Returns a budget that is never exhausted.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn num_bytes_used(&self) -> u64 {
+        self.inner.num_bytes_used.load(Ordering::Relaxed)
+    }
+
+    pub fn is_nearly_exhausted(&self) -> bool {
+        if let Some(capacity) = self.inner.capacity_opt {
+            self.num_bytes_used() as f64 >= capacity as f64 * NEAR_EXHAUSTION_RATIO
+        } else {
+            false
+        }
+    }
+
+    /// Creates a tracker accounting for the memory used by a set of splits. The memory is released
+    /// when the tracker is dropped.
+    pub fn track(&self, memory_usage_gauge: IntGauge) -> MemoryUsageTracker {
+        MemoryUsageTracker {
+            budget: self.clone(),
+            memory_usage_gauge,
+            num_bytes: 0,
+        }
+    }
+}
+
+/// Tracks the memory used by the splits of an indexing workbench.
+pub struct MemoryUsageTracker {
+    budget: IndexingMemoryBudget,
+    memory_usage_gauge: IntGauge,
+    num_bytes: u64,
+}
+
+impl MemoryUsageTracker {
+    pub fn add(&mut self, num_bytes: u64) {
+        self.num_bytes += num_bytes;
+        self.budget
+            .inner
+            .num_bytes_used
+            .fetch_add(num_bytes, Ordering::Relaxed);
+        self.memory_usage_gauge.add(num_bytes as i64);
+    }
+
+    pub fn memory_usage(&self) -> Byte {
+        Byte::from_bytes(self.num_bytes)
+    }
+}
+
+impl Drop for MemoryUsageTracker {
+    fn drop(&mut self) {
+        self.budget
+            .inner
+            .num_bytes_used
+            .fetch_sub(self.num_bytes, Ordering::Relaxed);
+        self.memory_usage_gauge.sub(self.num_bytes as i64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexing_memory_budget() {
+        let budget = IndexingMemoryBudget::new(Some(Byte::from_bytes(100)));
+        assert!(!budget.is_nearly_exhausted());
+
+        let mut tracker_1 = budget.track(IntGauge::new("test_gauge_1", "help").unwrap());
+        tracker_1.add(50);
+        assert_eq!(tracker_1.memory_usage(), Byte::from_bytes(50));
+        let mut tracker_2 = budget.track(IntGauge::new("test_gauge_2", "help").unwrap());
+        tracker_2.add(30);
+        assert_eq!(budget.num_bytes_used(), 80);
+        assert!(!budget.is_nearly_exhausted());
+
+        tracker_2.add(10);
+        assert!(budget.is_nearly_exhausted());
+
+        drop(tracker_1);
+        assert_eq!(budget.num_bytes_used(), 40);
+        assert!(!budget.is_nearly_exhausted());
+    }
+
+    #[test]
+    fn test_indexing_memory_budget_unlimited() {
+        let budget = IndexingMemoryBudget::unlimited();
+        let mut tracker = budget.track(IntGauge::new("test_gauge", "help").unwrap());
+        tracker.add(u32::MAX as u64);
+        assert!(!budget.is_nearly_exhausted());
+    }
+}
@@ -19,10 +19,12 @@
 
 #![allow(rustdoc::invalid_html_tags)]
 
+mod doc_deduplicator;
 mod indexed_split;
 mod indexing_pipeline_id;
 mod indexing_service_message;
 mod indexing_statistics;
+mod memory_budget;
 mod merge_planner_message;
 mod merge_scratch;
 mod merge_statistics;
@@ -34,14 +36,17 @@ mod raw_doc_batch;
 mod scratch_directory;
 mod split_attrs;
 
+pub use doc_deduplicator::DocDeduplicator;
 pub use indexed_split::{
     CommitTrigger, IndexedSplit, IndexedSplitBatch, IndexedSplitBatchBuilder, IndexedSplitBuilder,
 };
 pub use indexing_pipeline_id::IndexingPipelineId;
 pub use indexing_service_message::{
-    DetachIndexingPipeline, DetachMergePipeline, ObservePipeline, SpawnPipeline,
+    ControlPipelines, DetachIndexingPipeline, DetachMergePipeline, DrainPipelines, ListPipelines,
+    ObservePipeline, PipelineCommand, RefreshIndexConfigs, SpawnPipeline,
 };
 pub use indexing_statistics::IndexingStatistics;
+pub use memory_budget::{IndexingMemoryBudget, MemoryUsageTracker};
 pub use merge_planner_message::NewSplits;
 pub use merge_scratch::MergeScratch;
 pub use merge_statistics::MergeStatistics;
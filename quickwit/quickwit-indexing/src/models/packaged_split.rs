@@ -20,6 +20,8 @@
 use std::collections::{BTreeSet, HashSet};
 use std::fmt;
 
+use quickwit_doc_mapper::bloom_filter::BloomFilters;
+use quickwit_doc_mapper::range_pruning::FieldRanges;
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use tantivy::TrackedObject;
 use tracing::Span;
@@ -31,6 +33,8 @@ pub struct PackagedSplit {
     pub split_attrs: SplitAttrs,
     pub split_scratch_directory: ScratchDirectory,
     pub tags: BTreeSet<String>,
+    pub field_ranges: FieldRanges,
+    pub bloom_filters: BloomFilters,
     pub split_files: Vec<std::path::PathBuf>,
     pub hotcache_bytes: Vec<u8>,
 }
@@ -47,6 +51,8 @@ impl fmt::Debug for PackagedSplit {
             .field("split_attrs", &self.split_attrs)
             .field("split_scratch_directory", &self.split_scratch_directory)
             .field("tags", &self.tags)
+            .field("field_ranges", &self.field_ranges)
+            .field("bloom_filters", &self.bloom_filters)
             .field("split_files", &self.split_files)
             .finish()
     }
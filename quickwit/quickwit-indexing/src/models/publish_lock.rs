@@ -21,6 +21,7 @@ use std::fmt::Debug;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use quickwit_metastore::checkpoint::PublishToken;
 use tokio::sync::{Mutex, MutexGuard};
 
 // Publisher locks have two clients: publishers and sources.
@@ -29,6 +30,13 @@ use tokio::sync::{Mutex, MutexGuard};
 //
 // When a partition reassignment occurs, sources must (i) acquire, then (ii) kill, and finally (iii)
 // release the lock before propagating a new lock via message passing to the downstream consumers.
+//
+// The lock only protects the pipeline against itself. Sources whose partitions can be reassigned
+// across pipelines also attach a publish token, issued by the metastore, to each lock they create.
+// Publishers stamp the checkpoint deltas they publish with it, and the metastore rejects deltas
+// carrying no token or a token older than the token of the last publisher of the same partitions,
+// fencing off zombie pipelines running on other nodes that have not noticed yet that their
+// partitions were reassigned.
 #[derive(Clone, Default)]
 pub struct PublishLock {
     inner: Arc<PublishLockInner>,
@@ -44,6 +52,10 @@ impl Debug for PublishLock {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
         fmt.debug_struct("PublishLock")
             .field("is_alive", &self.is_alive())
+            .field(
+                "publish_token",
+                &self.inner.publish_token.as_ref().map(PublishToken::as_str),
+            )
             .finish()
     }
 }
@@ -51,6 +63,7 @@ impl Debug for PublishLock {
 struct PublishLockInner {
     alive: AtomicBool,
     mutex: Mutex<()>,
+    publish_token: Option<PublishToken>,
 }
 
 impl Default for PublishLockInner {
@@ -58,11 +71,23 @@ impl Default for PublishLockInner {
         Self {
             alive: AtomicBool::new(true),
             mutex: Mutex::default(),
+            publish_token: None,
         }
     }
 }
 
 impl PublishLock {
+    /// Creates a lock whose publishers stamp their checkpoint deltas with `publish_token`.
+    pub fn with_publish_token(publish_token: PublishToken) -> Self {
+        let inner = PublishLockInner {
+            publish_token: Some(publish_token),
+            ..Default::default()
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
     pub async fn acquire(&self) -> Option<MutexGuard<'_, ()>> {
         let guard = self.inner.mutex.lock().await;
         if self.is_dead() {
@@ -79,6 +104,10 @@ impl PublishLock {
         !self.is_alive()
     }
 
+    pub fn publish_token(&self) -> Option<PublishToken> {
+        self.inner.publish_token.clone()
+    }
+
     pub async fn kill(&self) {
         let _guard = self.inner.mutex.lock().await;
         self.inner.alive.store(false, Ordering::Relaxed);
@@ -112,4 +141,13 @@ mod tests {
         assert!(lock.is_dead());
         assert!(lock.acquire().await.is_none());
     }
+
+    #[test]
+    fn test_publish_lock_publish_token() {
+        assert!(PublishLock::default().publish_token().is_none());
+
+        let lock = PublishLock::with_publish_token(PublishToken::from(1u64));
+        assert_eq!(lock.publish_token(), Some(PublishToken::from(1u64)));
+        assert_eq!(lock.clone().publish_token(), lock.publish_token());
+    }
 }
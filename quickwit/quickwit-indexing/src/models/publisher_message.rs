@@ -27,6 +27,7 @@ use tracing::Span;
 
 use crate::merge_policy::MergeOperation;
 use crate::models::PublishLock;
+use crate::split_store::UploadJournalTicket;
 
 pub struct SplitsUpdate {
     pub index_id: String,
@@ -39,6 +40,9 @@ pub struct SplitsUpdate {
     /// See planners docs to understand the usage.
     /// If `None`, the split batch was built in the `IndexingPipeline`.
     pub merge_operation: Option<TrackedObject<MergeOperation>>,
+    /// Journal entry of the splits, removed once the splits are published. `None` if the
+    /// uploads are not journaled.
+    pub upload_journal_ticket_opt: Option<UploadJournalTicket>,
     pub parent_span: Span,
 }
 
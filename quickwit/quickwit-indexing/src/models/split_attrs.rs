@@ -17,10 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 
+use quickwit_doc_mapper::bloom_filter::BloomFilters;
+use quickwit_doc_mapper::range_pruning::FieldRanges;
 use quickwit_metastore::SplitMetadata;
 use tantivy::DateTime;
 use time::OffsetDateTime;
@@ -61,6 +63,12 @@ pub struct SplitAttrs {
 
     // Number of merge operation the split has been through so far.
     pub num_merge_ops: usize,
+
+    /// Version of the doc mapping the split was indexed with.
+    pub doc_mapping_version: u64,
+
+    /// Custom key-value metadata attached to the split.
+    pub custom_metadata: BTreeMap<String, String>,
 }
 
 impl fmt::Debug for SplitAttrs {
@@ -76,6 +84,8 @@ impl fmt::Debug for SplitAttrs {
             )
             .field("num_docs", &self.num_docs)
             .field("num_merge_ops", &self.num_merge_ops)
+            .field("doc_mapping_version", &self.doc_mapping_version)
+            .field("custom_metadata", &self.custom_metadata)
             .finish()
     }
 }
@@ -83,6 +93,8 @@ impl fmt::Debug for SplitAttrs {
 pub fn create_split_metadata(
     split_attrs: &SplitAttrs,
     tags: BTreeSet<String>,
+    field_ranges: FieldRanges,
+    bloom_filters: BloomFilters,
     footer_offsets: Range<u64>,
 ) -> SplitMetadata {
     SplitMetadata {
@@ -99,8 +111,13 @@ pub fn create_split_metadata(
         uncompressed_docs_size_in_bytes: split_attrs.uncompressed_docs_size_in_bytes,
         create_timestamp: OffsetDateTime::now_utc().unix_timestamp(),
         tags,
+        field_ranges,
+        bloom_filters,
         footer_offsets,
         delete_opstamp: split_attrs.delete_opstamp,
         num_merge_ops: split_attrs.num_merge_ops,
+        doc_mapping_version: split_attrs.doc_mapping_version,
+        custom_metadata: split_attrs.custom_metadata.clone(),
+        content_hash: None,
     }
 }
@@ -0,0 +1,221 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use apache_avro::{from_avro_datum, Schema};
+use quickwit_config::AvroOptions;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+/// Magic byte prefixing the messages encoded with the Confluent wire format.
+const CONFLUENT_MAGIC_BYTE: u8 = 0;
+
+/// Length of the header of the Confluent wire format: the magic byte followed by the schema ID.
+const CONFLUENT_HEADER_LEN: usize = 5;
+
+#[derive(Debug, Error)]
+pub(crate) enum AvroDecodeError {
+    /// The message cannot be decoded and should be skipped.
+    #[error("Invalid Avro message: {0}")]
+    InvalidMessage(String),
+    /// The schema registry could not be reached. The message should be retried later.
+    #[error("Failed to fetch schema `{schema_id}` from the schema registry: {message}")]
+    SchemaRegistryUnavailable { schema_id: u32, message: String },
+}
+
+#[derive(Deserialize)]
+struct SchemaResponse {
+    schema: String,
+}
+
+/// Decodes Avro messages encoded with the Confluent wire format into JSON documents.
+///
+/// The writer schemas are fetched from a Confluent-compatible schema registry the first time their
+/// ID is encountered and cached for the lifetime of the decoder.
+pub(crate) struct AvroDecoder {
+    http_client: reqwest::Client,
+    options: AvroOptions,
+    schemas: HashMap<u32, Schema>,
+}
+
+impl AvroDecoder {
+    pub fn new(options: AvroOptions) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            options,
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Decodes a message into a JSON document.
+    pub async fn decode(&mut self, payload: &[u8]) -> Result<String, AvroDecodeError> {
+        let (schema_id, datum) = split_confluent_header(payload)?;
+
+        if !self.schemas.contains_key(&schema_id) {
+            let schema = self.fetch_schema(schema_id).await?;
+            self.schemas.insert(schema_id, schema);
+        }
+        let schema = &self.schemas[&schema_id];
+        decode_datum(schema, datum)
+    }
+
+    async fn fetch_schema(&self, schema_id: u32) -> Result<Schema, AvroDecodeError> {
+        let schema_registry_unavailable =
+            |message: String| AvroDecodeError::SchemaRegistryUnavailable { schema_id, message };
+        let url = format!(
+            "{}/schemas/ids/{schema_id}",
+            self.options.schema_registry_url.trim_end_matches('/')
+        );
+        let mut request = self.http_client.get(url);
+
+        if let Some(username) = &self.options.schema_registry_username {
+            request = request.basic_auth(username, self.options.schema_registry_password.as_ref());
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(AvroDecodeError::InvalidMessage(format!(
+                "Schema `{schema_id}` does not exist."
+            )));
+        }
+        let schema_response: SchemaResponse = response
+            .error_for_status()
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?
+            .json()
+            .await
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?;
+        Schema::parse_str(&schema_response.schema).map_err(|error| {
+            AvroDecodeError::InvalidMessage(format!("Schema `{schema_id}` is invalid: {error}"))
+        })
+    }
+}
+
+/// Splits a message encoded with the Confluent wire format into its schema ID and its Avro datum.
+fn split_confluent_header(payload: &[u8]) -> Result<(u32, &[u8]), AvroDecodeError> {
+    if payload.len() < CONFLUENT_HEADER_LEN || payload[0] != CONFLUENT_MAGIC_BYTE {
+        return Err(AvroDecodeError::InvalidMessage(
+            "Message is not encoded with the Confluent wire format.".to_string(),
+        ));
+    }
+    let schema_id = u32::from_be_bytes(payload[1..CONFLUENT_HEADER_LEN].try_into().unwrap());
+    Ok((schema_id, &payload[CONFLUENT_HEADER_LEN..]))
+}
+
+/// Decodes an Avro datum written with `schema` into a JSON document.
+pub(crate) fn decode_datum(schema: &Schema, mut datum: &[u8]) -> Result<String, AvroDecodeError> {
+    let value = from_avro_datum(schema, &mut datum, None)
+        .map_err(|error| AvroDecodeError::InvalidMessage(error.to_string()))?;
+    let doc_json = JsonValue::try_from(value)
+        .map_err(|error| AvroDecodeError::InvalidMessage(error.to_string()))?;
+    if !doc_json.is_object() {
+        return Err(AvroDecodeError::InvalidMessage(
+            "Avro datum is not a record.".to_string(),
+        ));
+    }
+    Ok(doc_json.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::to_avro_datum;
+    use apache_avro::types::{Record, Value as AvroValue};
+
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "event",
+        "fields": [
+            {"name": "body", "type": "string"},
+            {"name": "count", "type": "long"},
+            {"name": "user", "type": ["null", "string"], "default": null}
+        ]
+    }"#;
+
+    fn encode_message(schema_id: u32, schema: &Schema, body: &str, count: i64) -> Vec<u8> {
+        let mut record = Record::new(schema).unwrap();
+        record.put("body", body);
+        record.put("count", count);
+        record.put("user", AvroValue::Union(0, Box::new(AvroValue::Null)));
+        let mut payload = vec![CONFLUENT_MAGIC_BYTE];
+        payload.extend_from_slice(&schema_id.to_be_bytes());
+        payload.extend(to_avro_datum(schema, record).unwrap());
+        payload
+    }
+
+    fn avro_decoder_for_test() -> AvroDecoder {
+        AvroDecoder::new(AvroOptions {
+            // Nothing listens on this port: the test schemas are registered beforehand.
+            schema_registry_url: "http://localhost:1".to_string(),
+            schema_registry_username: None,
+            schema_registry_password: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_avro_decoder() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut avro_decoder = avro_decoder_for_test();
+        avro_decoder.schemas.insert(42, schema.clone());
+
+        let payload = encode_message(42, &schema, "hello", 3);
+        let doc = avro_decoder.decode(&payload).await.unwrap();
+        let doc_json: JsonValue = serde_json::from_str(&doc).unwrap();
+        assert_eq!(
+            doc_json,
+            serde_json::json!({"body": "hello", "count": 3, "user": null})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_avro_decoder_invalid_messages() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut avro_decoder = avro_decoder_for_test();
+        avro_decoder.schemas.insert(42, schema.clone());
+
+        let error = avro_decoder
+            .decode(b"{\"body\": \"hello\"}")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AvroDecodeError::InvalidMessage(_)));
+
+        let error = avro_decoder
+            .decode(&[0, 0, 0, 0, 42, 255])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AvroDecodeError::InvalidMessage(_)));
+    }
+
+    #[tokio::test]
+    async fn test_avro_decoder_schema_registry_unavailable() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut avro_decoder = avro_decoder_for_test();
+        let payload = encode_message(7, &schema, "hello", 3);
+        let error = avro_decoder.decode(&payload).await.unwrap_err();
+        assert!(matches!(
+            error,
+            AvroDecodeError::SchemaRegistryUnavailable { schema_id: 7, .. }
+        ));
+    }
+}
@@ -22,18 +22,20 @@ use std::sync::Arc;
 use std::time::Duration;
 use std::{fmt, io};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use quickwit_actors::{ActorExitStatus, Mailbox};
-use quickwit_config::FileSourceParams;
+use quickwit_config::{build_doc_mapper, FileSourceParams};
+use quickwit_doc_mapper::CsvDocParser;
 use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
 use serde::Serialize;
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeekExt, BufReader};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::actors::DocProcessor;
 use crate::models::RawDocBatch;
+use crate::source::parquet_doc_reader::{is_parquet_file, ParquetDocReader};
 use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
 
 /// Number of bytes after which a new batch is cut.
@@ -44,6 +46,13 @@ pub struct FileSourceCounters {
     pub previous_offset: u64,
     pub current_offset: u64,
     pub num_lines_processed: u64,
+    pub num_invalid_rows: u64,
+}
+
+/// Converts the CSV rows of the file into JSON documents.
+struct CsvRowConverter {
+    parser: CsvDocParser,
+    columns: Vec<String>,
 }
 
 pub struct FileSource {
@@ -51,25 +60,19 @@ pub struct FileSource {
     params: FileSourceParams,
     counters: FileSourceCounters,
     reader: BufReader<Box<dyn AsyncRead + Send + Sync + Unpin>>,
+    csv_row_converter_opt: Option<CsvRowConverter>,
+    // Parquet files are read by row instead of by line. Positions are then row numbers.
+    parquet_doc_reader_opt: Option<ParquetDocReader>,
 }
 
-impl fmt::Debug for FileSource {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "FileSource {{ source_id: {} }}", self.source_id)
-    }
-}
-
-#[async_trait]
-impl Source for FileSource {
-    async fn emit_batches(
+impl FileSource {
+    /// Reads lines into the batch. Returns true if the end of the file was reached.
+    async fn read_lines(
         &mut self,
-        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        doc_batch: &mut RawDocBatch,
         ctx: &SourceContext,
-    ) -> Result<Duration, ActorExitStatus> {
-        // We collect batches of documents before sending them to the indexer.
+    ) -> anyhow::Result<bool> {
         let limit_num_bytes = self.counters.previous_offset + BATCH_NUM_BYTES_LIMIT;
-        let mut reached_eof = false;
-        let mut doc_batch = RawDocBatch::default();
         while self.counters.current_offset < limit_num_bytes {
             let mut doc_line = String::new();
             // guard the zone in case of slow read, such as reading from someone
@@ -79,14 +82,71 @@ impl Source for FileSource {
                 .await
                 .map_err(|io_err: io::Error| anyhow::anyhow!(io_err))?;
             if num_bytes == 0 {
-                reached_eof = true;
-                break;
+                return Ok(true);
             }
-            doc_batch.docs.push(doc_line);
             self.counters.current_offset += num_bytes as u64;
             self.counters.num_lines_processed += 1;
+
+            if let Some(csv_row_converter) = &self.csv_row_converter_opt {
+                if doc_line.trim().is_empty() {
+                    continue;
+                }
+                match csv_row_converter
+                    .parser
+                    .parse_line(&csv_row_converter.columns, &doc_line)
+                {
+                    Ok(doc_json) => {
+                        doc_batch
+                            .docs
+                            .push(serde_json::Value::Object(doc_json).to_string());
+                    }
+                    Err(error) => {
+                        self.counters.num_invalid_rows += 1;
+                        warn!(
+                            line = self.counters.num_lines_processed,
+                            error = %error,
+                            "Failed to parse CSV row."
+                        );
+                    }
+                }
+                continue;
+            }
+            doc_batch.docs.push(doc_line);
         }
-        if !doc_batch.docs.is_empty() {
+        Ok(false)
+    }
+}
+
+impl fmt::Debug for FileSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileSource {{ source_id: {} }}", self.source_id)
+    }
+}
+
+#[async_trait]
+impl Source for FileSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        // We collect batches of documents before sending them to the indexer.
+        let mut doc_batch = RawDocBatch::default();
+        let reached_eof = if let Some(parquet_doc_reader) = self.parquet_doc_reader_opt.as_mut() {
+            let (docs, reached_eof) = ctx
+                .protect_future(parquet_doc_reader.read_docs(BATCH_NUM_BYTES_LIMIT))
+                .await?;
+            let num_rows = docs.len() as u64;
+            self.counters.current_offset += num_rows;
+            self.counters.num_lines_processed += num_rows;
+            doc_batch.docs = docs;
+            reached_eof
+        } else {
+            self.read_lines(&mut doc_batch, ctx).await?
+        };
+        if !doc_batch.docs.is_empty()
+            || self.counters.current_offset > self.counters.previous_offset
+        {
             if let Some(filepath) = &self.params.filepath {
                 let filepath_str = filepath
                     .to_str()
@@ -135,6 +195,40 @@ impl TypedSourceFactory for FileSourceFactory {
         params: FileSourceParams,
         checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<FileSource> {
+        if let Some(filepath) = params
+            .filepath
+            .as_ref()
+            .filter(|filepath| is_parquet_file(filepath))
+        {
+            if params.csv.is_some() {
+                bail!(
+                    "CSV options cannot be applied to Parquet file `{}`.",
+                    filepath.display()
+                );
+            }
+            let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+            let mut num_rows_to_skip = 0;
+            if let Some(Position::Offset(offset_str)) =
+                checkpoint.position_for_partition(&partition_id).cloned()
+            {
+                num_rows_to_skip = offset_str.parse::<u64>()?;
+            }
+            let parquet_doc_reader = ParquetDocReader::open(filepath, num_rows_to_skip)?;
+            let file_source = FileSource {
+                source_id: ctx.source_config.source_id.clone(),
+                counters: FileSourceCounters {
+                    previous_offset: num_rows_to_skip,
+                    current_offset: num_rows_to_skip,
+                    num_lines_processed: 0,
+                    num_invalid_rows: 0,
+                },
+                reader: BufReader::new(Box::new(tokio::io::empty())),
+                params,
+                csv_row_converter_opt: None,
+                parquet_doc_reader_opt: Some(parquet_doc_reader),
+            };
+            return Ok(file_source);
+        }
         let mut offset = 0;
         let reader: Box<dyn AsyncRead + Send + Sync + Unpin> =
             if let Some(filepath) = &params.filepath {
@@ -153,20 +247,67 @@ impl TypedSourceFactory for FileSourceFactory {
                 // We cannot use the checkpoint.
                 Box::new(tokio::io::stdin())
             };
+        let mut reader = BufReader::new(reader);
+        let mut csv_row_converter_opt = None;
+
+        if let Some(csv_options) = &params.csv {
+            let index_metadata = ctx.metastore.index_metadata(&ctx.index_id).await?;
+            let index_config = index_metadata.into_index_config();
+            let doc_mapper =
+                build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
+            let parser = CsvDocParser::new(doc_mapper.schema(), csv_options.delimiter as u8);
+            let columns = if let Some(columns) = &csv_options.columns {
+                columns.clone()
+            } else if offset == 0 {
+                // The header line is consumed here and accounted for in the offset so that it
+                // is never emitted as a document.
+                let mut header_line = String::new();
+                let num_bytes = reader.read_line(&mut header_line).await?;
+                offset += num_bytes as u64;
+                parser
+                    .parse_header_line(&header_line)
+                    .map_err(|error| anyhow::anyhow!("Failed to parse CSV header: {error}"))?
+            } else {
+                read_csv_header(&params, &parser).await?
+            };
+            csv_row_converter_opt = Some(CsvRowConverter { parser, columns });
+        }
         let file_source = FileSource {
             source_id: ctx.source_config.source_id.clone(),
             counters: FileSourceCounters {
                 previous_offset: offset,
                 current_offset: offset,
                 num_lines_processed: 0,
+                num_invalid_rows: 0,
             },
-            reader: BufReader::new(reader),
+            reader,
             params,
+            csv_row_converter_opt,
+            parquet_doc_reader_opt: None,
         };
         Ok(file_source)
     }
 }
 
+/// Reads the header line of a CSV file when resuming from a checkpoint.
+async fn read_csv_header(
+    params: &FileSourceParams,
+    parser: &CsvDocParser,
+) -> anyhow::Result<Vec<String>> {
+    let filepath = params
+        .filepath
+        .as_ref()
+        .context("Cannot resume reading a CSV stream from stdin.")?;
+    let file = File::open(&filepath)
+        .await
+        .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))?;
+    let mut header_line = String::new();
+    BufReader::new(file).read_line(&mut header_line).await?;
+    parser
+        .parse_header_line(&header_line)
+        .map_err(|error| anyhow::anyhow!("Failed to parse CSV header: {error}"))
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Write;
@@ -174,12 +315,14 @@ mod tests {
     use std::path::PathBuf;
 
     use quickwit_actors::{Command, Universe};
-    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_config::{CsvOptions, SourceConfig, SourceParams};
     use quickwit_metastore::checkpoint::{SourceCheckpoint, SourceCheckpointDelta};
     use quickwit_metastore::metastore_for_test;
 
     use super::*;
+    use crate::source::parquet_doc_reader::tests::write_parquet_file_for_test;
     use crate::source::SourceActor;
+    use crate::TestSandbox;
 
     #[tokio::test]
     async fn test_file_source() -> anyhow::Result<()> {
@@ -219,7 +362,8 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 1030u64,
                 "current_offset": 1030u64,
-                "num_lines_processed": 4u32
+                "num_lines_processed": 4u32,
+                "num_invalid_rows": 0u64
             })
         );
         let batch = indexer_inbox.drain_for_test();
@@ -284,7 +428,8 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 700_000u64,
                 "current_offset": 700_000u64,
-                "num_lines_processed": 20_000u64
+                "num_lines_processed": 20_000u64,
+                "num_invalid_rows": 0u64
             })
         );
         let indexer_msgs = doc_processor_inbox.drain_for_test();
@@ -374,10 +519,166 @@ mod tests {
             serde_json::json!({
                 "previous_offset": 290u64,
                 "current_offset": 290u64,
-                "num_lines_processed": 98u64
+                "num_lines_processed": 98u64,
+                "num_invalid_rows": 0u64
             })
         );
         let indexer_messages: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
         assert!(indexer_messages[0].docs[0].starts_with("2\n"));
     }
+
+    #[tokio::test]
+    async fn test_file_source_csv() {
+        quickwit_common::setup_logging_for_tests();
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: count
+                type: u64
+        "#;
+        let test_sandbox = TestSandbox::create("test-csv-index", doc_mapping_yaml, "{}", &["body"])
+            .await
+            .unwrap();
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        use tempfile::NamedTempFile;
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file
+            .write_all(b"body,count\nhello,1\n\"happy, tax payer\",2\ninvalid,-3\n\nbye,4\n")
+            .unwrap();
+        temp_file.flush().unwrap();
+        let mut params = FileSourceParams::file(temp_file.path());
+        params.csv = Some(CsvOptions::default());
+
+        let source = FileSourceFactory::typed_create_source(
+            Arc::new(SourceExecutionContext {
+                metastore: test_sandbox.metastore(),
+                index_id: test_sandbox.index_id().to_string(),
+                node_id: "test-node".to_string(),
+                pipeline_ord: 0,
+                queues_dir_path: PathBuf::from("./queues"),
+                source_config: SourceConfig {
+                    source_id: "test-file-source".to_string(),
+                    desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+                    max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+                    enabled: true,
+                    source_params: SourceParams::File(params.clone()),
+                    transform_config: None,
+                },
+                storage_resolver: test_sandbox.storage_uri_resolver(),
+            }),
+            params,
+            SourceCheckpoint::default(),
+        )
+        .await
+        .unwrap();
+        let file_source_actor = SourceActor {
+            source: Box::new(source),
+            doc_processor_mailbox,
+        };
+        let (_file_source_mailbox, file_source_handle) =
+            universe.spawn_builder().spawn(file_source_actor);
+        let (actor_termination, counters) = file_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(
+            counters,
+            serde_json::json!({
+                "previous_offset": 58u64,
+                "current_offset": 58u64,
+                "num_lines_processed": 5u64,
+                "num_invalid_rows": 1u64
+            })
+        );
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        let docs: Vec<serde_json::Value> = batches[0]
+            .docs
+            .iter()
+            .map(|doc| serde_json::from_str(doc).unwrap())
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                serde_json::json!({"body": "hello", "count": 1}),
+                serde_json::json!({"body": "happy, tax payer", "count": 2}),
+                serde_json::json!({"body": "bye", "count": 4}),
+            ]
+        );
+        assert_eq!(
+            &extract_position_delta(&batches[0].checkpoint_delta).unwrap(),
+            "00000000000000000011..00000000000000000058"
+        );
+        test_sandbox.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_file_source_parquet_resume_from_checkpoint() {
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let filepath = temp_dir.path().join("docs.parquet");
+        write_parquet_file_for_test(&filepath, &[&[("doc 1", 1), ("doc 2", 2)], &[("doc 3", 3)]]);
+        let params = FileSourceParams::file(&filepath);
+        let mut checkpoint = SourceCheckpoint::default();
+        let partition_id = PartitionId::from(filepath.to_string_lossy().to_string());
+        let checkpoint_delta = SourceCheckpointDelta::from_partition_delta(
+            partition_id,
+            Position::from(0u64),
+            Position::from(1u64),
+        )
+        .unwrap();
+        checkpoint.try_apply_delta(checkpoint_delta).unwrap();
+
+        let metastore = metastore_for_test();
+        let source = FileSourceFactory::typed_create_source(
+            SourceExecutionContext::for_test(
+                metastore,
+                "test-index",
+                PathBuf::from("./queues"),
+                SourceConfig {
+                    source_id: "test-file-source".to_string(),
+                    desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+                    max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+                    enabled: true,
+                    source_params: SourceParams::File(params.clone()),
+                    transform_config: None,
+                },
+            ),
+            params,
+            checkpoint,
+        )
+        .await
+        .unwrap();
+        let file_source_actor = SourceActor {
+            source: Box::new(source),
+            doc_processor_mailbox,
+        };
+        let (_file_source_mailbox, file_source_handle) =
+            universe.spawn_builder().spawn(file_source_actor);
+        let (actor_termination, counters) = file_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        assert_eq!(
+            counters,
+            serde_json::json!({
+                "previous_offset": 3u64,
+                "current_offset": 3u64,
+                "num_lines_processed": 2u64,
+                "num_invalid_rows": 0u64
+            })
+        );
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(
+            batches[0].docs,
+            vec![
+                r#"{"body":"doc 2","count":2}"#.to_string(),
+                r#"{"body":"doc 3","count":3}"#.to_string(),
+            ]
+        );
+        assert_eq!(
+            &extract_position_delta(&batches[0].checkpoint_delta).unwrap(),
+            "00000000000000000001..00000000000000000003"
+        );
+    }
 }
@@ -0,0 +1,472 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_common::split_file;
+use quickwit_config::{build_doc_mapper, IndexSourceParams};
+use quickwit_doc_mapper::{DocMapper, SOURCE_FIELD_NAME};
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use quickwit_metastore::{ListSplitsQuery, SplitMetadata, SplitState};
+use quickwit_storage::Storage;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tantivy::schema::NamedFieldDocument;
+use tantivy::{DocAddress, Index, ReloadPolicy, Searcher};
+use tempfile::TempDir;
+use tracing::info;
+
+use crate::actors::DocProcessor;
+use crate::get_tantivy_directory_from_split_bundle;
+use crate::models::RawDocBatch;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+/// Number of bytes after which a new batch is cut.
+const BATCH_NUM_BYTES_LIMIT: usize = 500_000;
+
+#[derive(Default, Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct IndexSourceCounters {
+    pub num_splits_total: usize,
+    pub num_splits_processed: usize,
+    pub num_docs_total: u64,
+    pub num_docs_processed: u64,
+}
+
+/// Split currently being read by the source.
+struct SplitReader {
+    partition_id: PartitionId,
+    searcher: Searcher,
+    doc_addresses: Vec<DocAddress>,
+    next_doc_idx: usize,
+    split_path: PathBuf,
+}
+
+/// Source reading the documents stored in the published splits of another index.
+///
+/// The partitions of the source are the split IDs of the source index and the positions are the
+/// ordinals of the documents within a split. The list of splits to read is snapshotted when the
+/// source is created: the source index should not be merged or receive new documents while it is
+/// being reindexed. Once all the splits have been read, the source exits successfully.
+pub struct IndexSource {
+    source_id: String,
+    source_index_id: String,
+    storage: Arc<dyn Storage>,
+    doc_mapper: Arc<dyn DocMapper>,
+    scratch_directory: TempDir,
+    pending_splits: VecDeque<(SplitMetadata, usize)>,
+    current_split_opt: Option<SplitReader>,
+    counters: IndexSourceCounters,
+}
+
+impl fmt::Debug for IndexSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "IndexSource {{ source_id: {}, source_index_id: {} }}",
+            self.source_id, self.source_index_id
+        )
+    }
+}
+
+pub struct IndexSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for IndexSourceFactory {
+    type Source = IndexSource;
+    type Params = IndexSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: IndexSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<IndexSource> {
+        if params.index_id == ctx.index_id {
+            bail!(
+                "Index `{}` cannot be used as a source for itself.",
+                params.index_id
+            );
+        }
+        let source_index_metadata = ctx.metastore.index_metadata(&params.index_id).await?;
+        let source_index_config = source_index_metadata.into_index_config();
+        let doc_mapper = build_doc_mapper(
+            &source_index_config.doc_mapping,
+            &source_index_config.search_settings,
+        )?;
+        let storage = ctx
+            .storage_resolver
+            .resolve(&source_index_config.index_uri)?;
+
+        let query =
+            ListSplitsQuery::for_index(&params.index_id).with_split_state(SplitState::Published);
+        let mut splits: Vec<SplitMetadata> = ctx
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+
+        let mut counters = IndexSourceCounters {
+            num_splits_total: splits.len(),
+            ..Default::default()
+        };
+        let mut pending_splits = VecDeque::with_capacity(splits.len());
+        for split in splits {
+            counters.num_docs_total += split.num_docs as u64;
+            let partition_id = PartitionId::from(split.split_id.as_str());
+            let next_doc_idx = match checkpoint.position_for_partition(&partition_id) {
+                Some(Position::Offset(offset_str)) => offset_str.parse::<usize>()? + 1,
+                Some(Position::Beginning) | None => 0,
+            };
+            counters.num_docs_processed += next_doc_idx.min(split.num_docs) as u64;
+            if next_doc_idx >= split.num_docs {
+                counters.num_splits_processed += 1;
+                continue;
+            }
+            pending_splits.push_back((split, next_doc_idx));
+        }
+        let scratch_directory =
+            tempfile::tempdir().context("Failed to create scratch directory.")?;
+        info!(
+            source_index_id = %params.index_id,
+            num_pending_splits = pending_splits.len(),
+            "Starting index source."
+        );
+        Ok(IndexSource {
+            source_id: ctx.source_config.source_id.clone(),
+            source_index_id: params.index_id,
+            storage,
+            doc_mapper,
+            scratch_directory,
+            pending_splits,
+            current_split_opt: None,
+            counters,
+        })
+    }
+}
+
+fn position_from_offset(offset: usize) -> Position {
+    if offset == 0 {
+        return Position::Beginning;
+    }
+    Position::from(offset as u64 - 1)
+}
+
+impl IndexSource {
+    /// Downloads the split and lists the addresses of its documents.
+    async fn open_split(
+        &self,
+        split: &SplitMetadata,
+        next_doc_idx: usize,
+        ctx: &SourceContext,
+    ) -> anyhow::Result<SplitReader> {
+        let split_path = self
+            .scratch_directory
+            .path()
+            .join(split_file(split.split_id()));
+        ctx.protect_future(
+            self.storage
+                .copy_to_file(Path::new(&split.split_file()), &split_path),
+        )
+        .await
+        .with_context(|| format!("Failed to download split `{}`.", split.split_id()))?;
+        let directory = get_tantivy_directory_from_split_bundle(&split_path)?;
+        let index = Index::open(directory)?;
+        let index_reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = index_reader.searcher();
+        let mut doc_addresses = Vec::with_capacity(split.num_docs);
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                doc_addresses.push(DocAddress::new(segment_ord as u32, doc_id));
+            }
+        }
+        Ok(SplitReader {
+            partition_id: PartitionId::from(split.split_id()),
+            searcher,
+            doc_addresses,
+            next_doc_idx,
+            split_path,
+        })
+    }
+
+    /// Converts a stored document back into its JSON representation. The original document is
+    /// used whenever the source index stores it.
+    fn doc_to_json_string(
+        &self,
+        searcher: &Searcher,
+        doc_address: DocAddress,
+    ) -> anyhow::Result<String> {
+        let doc = searcher.doc(doc_address)?;
+        let NamedFieldDocument(named_doc) = searcher.schema().to_named_doc(&doc);
+        let mut doc_json = self.doc_mapper.doc_to_json(named_doc)?;
+        let doc_json = match doc_json.remove(SOURCE_FIELD_NAME) {
+            Some(source_json @ JsonValue::Object(_)) => source_json,
+            _ => JsonValue::Object(doc_json),
+        };
+        Ok(serde_json::to_string(&doc_json)?)
+    }
+}
+
+#[async_trait]
+impl Source for IndexSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let mut split_reader = match self.current_split_opt.take() {
+            Some(split_reader) => split_reader,
+            None => {
+                let Some((split, next_doc_idx)) = self.pending_splits.pop_front() else {
+                    info!(source_index_id = %self.source_index_id, "Reached end of source index.");
+                    ctx.send_exit_with_success(doc_processor_mailbox).await?;
+                    return Err(ActorExitStatus::Success);
+                };
+                self.open_split(&split, next_doc_idx, ctx).await?
+            }
+        };
+        let from_doc_idx = split_reader.next_doc_idx;
+        let mut num_bytes = 0;
+        let mut doc_batch = RawDocBatch::default();
+
+        while num_bytes < BATCH_NUM_BYTES_LIMIT
+            && split_reader.next_doc_idx < split_reader.doc_addresses.len()
+        {
+            let doc_address = split_reader.doc_addresses[split_reader.next_doc_idx];
+            let doc_json_str = self.doc_to_json_string(&split_reader.searcher, doc_address)?;
+            num_bytes += doc_json_str.len();
+            doc_batch.docs.push(doc_json_str);
+            split_reader.next_doc_idx += 1;
+        }
+        let to_doc_idx = split_reader.next_doc_idx;
+        self.counters.num_docs_processed += (to_doc_idx - from_doc_idx) as u64;
+
+        if to_doc_idx > from_doc_idx {
+            doc_batch
+                .checkpoint_delta
+                .record_partition_delta(
+                    split_reader.partition_id.clone(),
+                    position_from_offset(from_doc_idx),
+                    position_from_offset(to_doc_idx),
+                )
+                .context("Failed to record partition delta.")?;
+            ctx.send_message(doc_processor_mailbox, doc_batch).await?;
+        }
+        if split_reader.next_doc_idx < split_reader.doc_addresses.len() {
+            self.current_split_opt = Some(split_reader);
+        } else {
+            self.counters.num_splits_processed += 1;
+            tokio::fs::remove_file(&split_reader.split_path)
+                .await
+                .context("Failed to remove split file from scratch directory.")?;
+        }
+        Ok(Duration::default())
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "IndexSource {{ source_id={}, source_index_id={} }}",
+            self.source_id, self.source_index_id
+        )
+    }
+
+    fn observable_state(&self) -> JsonValue {
+        serde_json::to_value(&self.counters).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use quickwit_actors::{Command, Universe};
+    use quickwit_config::{SourceConfig, SourceParams};
+    use quickwit_metastore::checkpoint::SourceCheckpointDelta;
+    use serde_json::json;
+
+    use super::*;
+    use crate::source::SourceActor;
+    use crate::TestSandbox;
+
+    fn index_source_config(index_id: &str) -> SourceConfig {
+        SourceConfig {
+            source_id: "test-index-source".to_string(),
+            desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+            max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+            enabled: true,
+            source_params: SourceParams::Index(IndexSourceParams {
+                index_id: index_id.to_string(),
+            }),
+            transform_config: None,
+        }
+    }
+
+    async fn read_all_docs(
+        test_sandbox: &TestSandbox,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<(Vec<RawDocBatch>, JsonValue)> {
+        let universe = Universe::with_accelerated_time();
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+        let params = IndexSourceParams {
+            index_id: test_sandbox.index_id().to_string(),
+        };
+        let index_source = IndexSourceFactory::typed_create_source(
+            Arc::new(SourceExecutionContext {
+                metastore: test_sandbox.metastore(),
+                index_id: "target-index".to_string(),
+                node_id: "test-node".to_string(),
+                pipeline_ord: 0,
+                queues_dir_path: PathBuf::from("./queues"),
+                source_config: index_source_config(test_sandbox.index_id()),
+                storage_resolver: test_sandbox.storage_uri_resolver(),
+            }),
+            params,
+            checkpoint,
+        )
+        .await?;
+        let index_source_actor = SourceActor {
+            source: Box::new(index_source),
+            doc_processor_mailbox,
+        };
+        let (_index_source_mailbox, index_source_handle) =
+            universe.spawn_builder().spawn(index_source_actor);
+        let (actor_termination, last_observation) = index_source_handle.join().await;
+        assert!(actor_termination.is_success());
+        let messages = doc_processor_inbox.drain_for_test();
+        assert!(matches!(
+            messages.last().unwrap().downcast_ref::<Command>().unwrap(),
+            &Command::ExitWithSuccess
+        ));
+        let batches = messages
+            .into_iter()
+            .filter_map(|message| message.downcast::<RawDocBatch>().ok())
+            .map(|batch| *batch)
+            .collect();
+        Ok((batches, last_observation))
+    }
+
+    #[tokio::test]
+    async fn test_index_source() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: count
+                type: u64
+        "#;
+        let test_sandbox =
+            TestSandbox::create("source-index", doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![
+                json!({"body": "doc 1", "count": 1}),
+                json!({"body": "doc 2", "count": 2}),
+            ])
+            .await?;
+        test_sandbox
+            .add_documents(vec![json!({"body": "doc 3", "count": 3})])
+            .await?;
+        let (batches, last_observation) =
+            read_all_docs(&test_sandbox, SourceCheckpoint::default()).await?;
+        assert_eq!(batches.len(), 2);
+        let mut docs: Vec<JsonValue> = batches
+            .iter()
+            .flat_map(|batch| batch.docs.iter())
+            .map(|doc| serde_json::from_str(doc).unwrap())
+            .collect();
+        docs.sort_by_key(|doc| doc["count"].as_u64());
+        assert_eq!(
+            docs,
+            vec![
+                json!({"body": "doc 1", "count": 1}),
+                json!({"body": "doc 2", "count": 2}),
+                json!({"body": "doc 3", "count": 3}),
+            ]
+        );
+        assert_eq!(
+            last_observation,
+            json!({
+                "num_splits_total": 2,
+                "num_splits_processed": 2,
+                "num_docs_total": 3,
+                "num_docs_processed": 3,
+            })
+        );
+        // Resume from the checkpoint of the first batch: only the second split is read.
+        let mut checkpoint = SourceCheckpoint::default();
+        checkpoint.try_apply_delta(batches[0].checkpoint_delta.clone())?;
+        let (batches_after_restart, last_observation) =
+            read_all_docs(&test_sandbox, checkpoint).await?;
+        assert_eq!(batches_after_restart.len(), 1);
+        assert_eq!(
+            batches_after_restart[0].checkpoint_delta,
+            batches[1].checkpoint_delta
+        );
+        assert_eq!(last_observation["num_splits_processed"], json!(2));
+        assert_eq!(last_observation["num_docs_processed"], json!(3));
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_index_source_partial_split_checkpoint() -> anyhow::Result<()> {
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox =
+            TestSandbox::create("source-index-partial", doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![
+                json!({"body": "doc 1"}),
+                json!({"body": "doc 2"}),
+                json!({"body": "doc 3"}),
+            ])
+            .await?;
+        let split_id = test_sandbox
+            .metastore()
+            .list_all_splits("source-index-partial")
+            .await?[0]
+            .split_id()
+            .to_string();
+        let mut checkpoint = SourceCheckpoint::default();
+        checkpoint.try_apply_delta(SourceCheckpointDelta::from_partition_delta(
+            PartitionId::from(split_id.as_str()),
+            Position::Beginning,
+            Position::from(0u64),
+        )?)?;
+        let (batches, last_observation) = read_all_docs(&test_sandbox, checkpoint).await?;
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].docs.len(), 2);
+        assert_eq!(last_observation["num_docs_processed"], json!(3));
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+}
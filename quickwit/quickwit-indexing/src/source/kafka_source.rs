@@ -47,6 +47,8 @@ use tracing::{debug, info, warn};
 
 use crate::actors::DocProcessor;
 use crate::models::{NewPublishLock, PublishLock, RawDocBatch};
+use crate::source::avro_decoder::{AvroDecodeError, AvroDecoder};
+use crate::source::protobuf_decoder::ProtobufDecoder;
 use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
 
 /// Number of bytes after which we cut a new batch.
@@ -93,18 +95,30 @@ enum KafkaEvent {
     Error(anyhow::Error),
 }
 
+#[derive(Debug)]
+enum MessagePayload {
+    Doc(String),
+    // Avro and Protobuf payloads are decoded by the source, which owns the decoders.
+    Binary(Vec<u8>),
+}
+
 #[derive(Debug)]
 struct KafkaMessage {
-    doc_opt: Option<String>,
+    payload_opt: Option<MessagePayload>,
     payload_len: u64,
     partition: i32,
     offset: i64,
 }
 
-impl From<BorrowedMessage<'_>> for KafkaMessage {
-    fn from(message: BorrowedMessage<'_>) -> Self {
+impl KafkaMessage {
+    fn new(message: BorrowedMessage<'_>, binary_payload: bool) -> Self {
+        let payload_opt = if binary_payload {
+            parse_binary_message_payload(&message).map(MessagePayload::Binary)
+        } else {
+            parse_message_payload(&message).map(MessagePayload::Doc)
+        };
         Self {
-            doc_opt: parse_message_payload(&message),
+            payload_opt,
             payload_len: message.payload_len() as u64,
             partition: message.partition(),
             offset: message.offset(),
@@ -206,7 +220,7 @@ pub struct KafkaSourceState {
     pub num_bytes_processed: u64,
     /// Number of messages processed by the source (including invalid messages).
     pub num_messages_processed: u64,
-    // Number of invalid messages, i.e., that were empty or could not be parsed.
+    // Number of invalid messages, i.e., that were empty or could not be parsed or decoded.
     pub num_invalid_messages: u64,
     /// Number of rebalances the consumer went through.
     pub num_rebalances: usize,
@@ -221,6 +235,8 @@ pub struct KafkaSource {
     events_rx: mpsc::Receiver<KafkaEvent>,
     poll_loop_jh: JoinHandle<()>,
     publish_lock: PublishLock,
+    avro_decoder_opt: Option<AvroDecoder>,
+    protobuf_decoder_opt: Option<ProtobufDecoder>,
 }
 
 impl fmt::Debug for KafkaSource {
@@ -243,6 +259,13 @@ impl KafkaSource {
     ) -> anyhow::Result<Self> {
         let topic = params.topic.clone();
         let backfill_mode_enabled = params.enable_backfill_mode;
+        let avro_decoder_opt = params.avro.clone().map(AvroDecoder::new);
+        let protobuf_decoder_opt = if let Some(protobuf_options) = &params.protobuf {
+            Some(ProtobufDecoder::load(protobuf_options).await?)
+        } else {
+            None
+        };
+        let binary_payload = avro_decoder_opt.is_some() || protobuf_decoder_opt.is_some();
 
         let (events_tx, events_rx) = mpsc::channel(100);
         let (client_config, consumer) = create_consumer(
@@ -260,8 +283,13 @@ impl KafkaSource {
             .get("max.poll.interval.ms")?
             .parse::<u64>()?;
 
-        let poll_loop_jh = spawn_consumer_poll_loop(consumer, topic.clone(), events_tx);
-        let publish_lock = PublishLock::default();
+        let poll_loop_jh =
+            spawn_consumer_poll_loop(consumer, topic.clone(), binary_payload, events_tx);
+        let publish_token = ctx
+            .metastore
+            .issue_publish_token(&ctx.index_id, &ctx.source_config.source_id)
+            .await?;
+        let publish_lock = PublishLock::with_publish_token(publish_token);
 
         info!(
             index_id=%ctx.index_id,
@@ -287,6 +315,8 @@ impl KafkaSource {
             events_rx,
             poll_loop_jh,
             publish_lock,
+            avro_decoder_opt,
+            protobuf_decoder_opt,
         })
     }
 
@@ -296,17 +326,51 @@ impl KafkaSource {
         batch: &mut BatchBuilder,
     ) -> anyhow::Result<()> {
         let KafkaMessage {
-            doc_opt,
+            payload_opt,
             payload_len,
             partition,
             offset,
             ..
         } = message;
 
-        if let Some(doc) = doc_opt {
-            batch.push(doc, payload_len);
-        } else {
-            self.state.num_invalid_messages += 1;
+        match payload_opt {
+            Some(MessagePayload::Doc(doc)) => batch.push(doc, payload_len),
+            Some(MessagePayload::Binary(payload)) => {
+                let decode_res = if let Some(avro_decoder) = self.avro_decoder_opt.as_mut() {
+                    match avro_decoder.decode(&payload).await {
+                        Ok(doc) => Ok(doc),
+                        Err(AvroDecodeError::InvalidMessage(error)) => Err(error),
+                        // The message is not skipped: the source fails and will consume it again
+                        // after restarting.
+                        Err(error) => return Err(error.into()),
+                    }
+                } else if let Some(protobuf_decoder) = &self.protobuf_decoder_opt {
+                    protobuf_decoder.decode(&payload)
+                } else {
+                    unreachable!("Binary payloads are only emitted when a decoder is configured.");
+                };
+                match decode_res {
+                    Ok(doc) => batch.push(doc, payload_len),
+                    Err(error) => {
+                        warn!(
+                            topic = %self.topic,
+                            partition = %partition,
+                            offset = %offset,
+                            error = %error,
+                            "Failed to decode message."
+                        );
+                        crate::metrics::INDEXER_METRICS
+                            .source_decode_errors_total
+                            .with_label_values([
+                                self.ctx.index_id.as_str(),
+                                self.ctx.source_config.source_id.as_str(),
+                            ])
+                            .inc();
+                        self.state.num_invalid_messages += 1;
+                    }
+                }
+            }
+            None => self.state.num_invalid_messages += 1,
         }
         self.state.num_bytes_processed += payload_len;
         self.state.num_messages_processed += 1;
@@ -411,7 +475,16 @@ impl KafkaSource {
             .map_err(|_| anyhow!("Consumer context was dropped."))?;
 
         batch.clear();
-        self.publish_lock = PublishLock::default();
+        // The token is issued after the partitions are revoked so that it supersedes the tokens
+        // of the pipelines which owned the partitions that are about to be assigned to us.
+        let publish_token = ctx
+            .protect_future(
+                self.ctx
+                    .metastore
+                    .issue_publish_token(&self.ctx.index_id, &self.ctx.source_config.source_id),
+            )
+            .await?;
+        self.publish_lock = PublishLock::with_publish_token(publish_token);
         self.state.num_rebalances += 1;
         ctx.send_message(
             doc_processor_mailbox,
@@ -577,6 +650,7 @@ impl Source for KafkaSource {
 fn spawn_consumer_poll_loop(
     consumer: RdKafkaConsumer,
     topic: String,
+    binary_payload: bool,
     events_tx: mpsc::Sender<KafkaEvent>,
 ) -> JoinHandle<()> {
     spawn_blocking(move || {
@@ -594,7 +668,7 @@ fn spawn_consumer_poll_loop(
         while !events_tx.is_closed() {
             if let Some(message_res) = consumer.poll(Some(Duration::from_secs(1))) {
                 let event = match message_res {
-                    Ok(message) => KafkaEvent::Message(message.into()),
+                    Ok(message) => KafkaEvent::Message(KafkaMessage::new(message, binary_payload)),
                     Err(KafkaError::PartitionEOF(partition)) => KafkaEvent::PartitionEOF(partition),
                     Err(error) => KafkaEvent::Error(anyhow!(error)),
                 };
@@ -701,7 +775,7 @@ fn parse_client_log_level(client_log_level: Option<String>) -> anyhow::Result<RD
     Ok(log_level)
 }
 
-fn parse_client_params(client_params: JsonValue) -> anyhow::Result<ClientConfig> {
+pub(crate) fn parse_client_params(client_params: JsonValue) -> anyhow::Result<ClientConfig> {
     let params = if let JsonValue::Object(params) = client_params {
         params
     } else {
@@ -759,6 +833,23 @@ fn parse_message_payload(message: &BorrowedMessage) -> Option<String> {
     None
 }
 
+/// Copies the raw bytes of a binary message payload, skipping empty messages.
+fn parse_binary_message_payload(message: &BorrowedMessage) -> Option<Vec<u8>> {
+    match message.payload() {
+        Some(payload) if !payload.is_empty() => Some(payload.to_vec()),
+        _ => {
+            debug!(
+                topic = ?message.topic(),
+                partition = ?message.partition(),
+                offset = ?message.offset(),
+                timestamp = ?message.timestamp(),
+                "Message payload is empty."
+            );
+            None
+        }
+    }
+}
+
 #[cfg(all(test, feature = "kafka-broker-tests"))]
 mod kafka_broker_tests {
     use std::num::NonZeroUsize;
@@ -879,6 +970,8 @@ mod kafka_broker_tests {
                     "bootstrap.servers": "localhost:9092",
                 }),
                 enable_backfill_mode: true,
+                avro: None,
+                protobuf: None,
             }),
             transform_config: None,
         };
@@ -936,6 +1029,7 @@ mod kafka_broker_tests {
         let index_delta = IndexCheckpointDelta {
             source_id: source_id.to_string(),
             source_delta,
+            publish_token: None,
         };
         metastore
             .publish_splits(index_id, &[&split_id], &[], Some(index_delta))
@@ -951,7 +1045,9 @@ mod kafka_broker_tests {
 
         let metastore = metastore_for_test();
         let index_id = append_random_suffix("test-kafka-source--process-message--index");
-        let (_source_id, source_config) = get_source_config(&topic);
+        let (source_id, source_config) = get_source_config(&topic);
+        setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
         let params = if let SourceParams::Kafka(params) = source_config.clone().source_params {
             params
         } else {
@@ -980,7 +1076,7 @@ mod kafka_broker_tests {
         let mut batch = BatchBuilder::default();
 
         let message = KafkaMessage {
-            doc_opt: None,
+            payload_opt: None,
             payload_len: 7,
             partition: 1,
             offset: 0,
@@ -1001,7 +1097,7 @@ mod kafka_broker_tests {
         assert_eq!(kafka_source.state.num_invalid_messages, 1);
 
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            payload_opt: Some(MessagePayload::Doc("test-doc".to_string())),
             payload_len: 8,
             partition: 1,
             offset: 1,
@@ -1023,7 +1119,7 @@ mod kafka_broker_tests {
         assert_eq!(kafka_source.state.num_invalid_messages, 1);
 
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            payload_opt: Some(MessagePayload::Doc("test-doc".to_string())),
             payload_len: 8,
             partition: 2,
             offset: 42,
@@ -1055,7 +1151,7 @@ mod kafka_broker_tests {
 
         // Message from unassigned partition
         let message = KafkaMessage {
-            doc_opt: Some("test-doc".to_string()),
+            payload_opt: Some(MessagePayload::Doc("test-doc".to_string())),
             payload_len: 8,
             partition: 3,
             offset: 42,
@@ -1137,7 +1233,9 @@ mod kafka_broker_tests {
 
         let metastore = metastore_for_test();
         let index_id = append_random_suffix("test-kafka-source--process-revoke--partitions--index");
-        let (_source_id, source_config) = get_source_config(&topic);
+        let (source_id, source_config) = get_source_config(&topic);
+        setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
         let params = if let SourceParams::Kafka(params) = source_config.clone().source_params {
             params
         } else {
@@ -1183,6 +1281,7 @@ mod kafka_broker_tests {
         let indexer_messages: Vec<NewPublishLock> = indexer_inbox.drain_for_test_typed();
         assert_eq!(indexer_messages.len(), 1);
         assert!(indexer_messages[0].0.is_alive());
+        assert!(indexer_messages[0].0.publish_token() > publish_lock.publish_token());
     }
 
     #[tokio::test]
@@ -1193,7 +1292,9 @@ mod kafka_broker_tests {
 
         let metastore = metastore_for_test();
         let index_id = append_random_suffix("test-kafka-source--process-partition-eof--index");
-        let (_source_id, source_config) = get_source_config(&topic);
+        let (source_id, source_config) = get_source_config(&topic);
+        setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
         let params = if let SourceParams::Kafka(params) = source_config.clone().source_params {
             params
         } else {
@@ -1235,6 +1336,8 @@ mod kafka_broker_tests {
             let index_id = append_random_suffix("test-kafka-source--index");
 
             let (source_id, source_config) = get_source_config(&topic);
+            setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
             let source = source_loader
                 .load_source(
                     SourceExecutionContext::for_test(
@@ -1247,8 +1350,6 @@ mod kafka_broker_tests {
                 )
                 .await?;
 
-            setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
-
             let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
             let source_actor = SourceActor {
                 source,
@@ -1297,6 +1398,8 @@ mod kafka_broker_tests {
             let index_id = append_random_suffix("test-kafka-source--index");
 
             let (source_id, source_config) = get_source_config(&topic);
+            setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
+
             let source = source_loader
                 .load_source(
                     SourceExecutionContext::for_test(
@@ -1309,8 +1412,6 @@ mod kafka_broker_tests {
                 )
                 .await?;
 
-            setup_index(metastore.clone(), &index_id, &source_id, &[]).await;
-
             let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
             let source_actor = SourceActor {
                 source,
@@ -1363,6 +1464,14 @@ mod kafka_broker_tests {
             let index_id = append_random_suffix("test-kafka-source--index");
 
             let (source_id, source_config) = get_source_config(&topic);
+            setup_index(
+                metastore.clone(),
+                &index_id,
+                &source_id,
+                &[(0, -1, 0), (1, -1, 2)],
+            )
+            .await;
+
             let source = source_loader
                 .load_source(
                     SourceExecutionContext::for_test(
@@ -1375,14 +1484,6 @@ mod kafka_broker_tests {
                 )
                 .await?;
 
-            setup_index(
-                metastore.clone(),
-                &index_id,
-                &source_id,
-                &[(0, -1, 0), (1, -1, 2)],
-            )
-            .await;
-
             let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
             let source_actor = SourceActor {
                 source,
@@ -1443,6 +1544,8 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            avro: None,
+            protobuf: None,
         })
         .await
         .unwrap();
@@ -1454,6 +1557,8 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            avro: None,
+            protobuf: None,
         })
         .await
         .unwrap_err();
@@ -1466,6 +1571,8 @@ mod kafka_broker_tests {
                 "bootstrap.servers": "192.0.2.10:9092"
             }),
             enable_backfill_mode: true,
+            avro: None,
+            protobuf: None,
         })
         .await
         .unwrap_err();
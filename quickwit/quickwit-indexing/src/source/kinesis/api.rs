@@ -17,13 +17,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
+use anyhow::{bail, Context};
 use quickwit_aws::error::RusotoErrorWrapper;
 use quickwit_aws::retry::{retry, RetryParams};
+use rusoto_core::RusotoError;
 use rusoto_kinesis::{
-    GetRecordsInput, GetRecordsOutput, GetShardIteratorInput, Kinesis, KinesisClient,
-    ListShardsInput, Shard,
+    DescribeStreamConsumerInput, DescribeStreamSummaryInput, GetRecordsInput, GetRecordsOutput,
+    GetShardIteratorInput, Kinesis, KinesisClient, ListShardsInput, RegisterStreamConsumerError,
+    RegisterStreamConsumerInput, Shard, StartingPosition, SubscribeToShardInput,
+    SubscribeToShardOutput,
 };
 
+/// Maximum number of `DescribeStreamConsumer` calls made while waiting for a newly registered
+/// consumer to become active. The API has a limit of 20 transactions per second per stream.
+const MAX_CONSUMER_ACTIVATION_CHECKS: usize = 60;
+
 /// Gets records from a Kinesis data stream's shard.
 /// <https://docs.aws.amazon.com/kinesis/latest/APIReference/API_GetRecords.html>
 pub(crate) async fn get_records(
@@ -128,6 +138,124 @@ pub(crate) async fn list_shards(
     }
 }
 
+/// Returns the ARN of a Kinesis data stream.
+/// <https://docs.aws.amazon.com/kinesis/latest/APIReference/API_DescribeStreamSummary.html>
+pub(crate) async fn get_stream_arn(
+    kinesis_client: &KinesisClient,
+    retry_params: &RetryParams,
+    stream_name: &str,
+) -> anyhow::Result<String> {
+    let request = DescribeStreamSummaryInput {
+        stream_name: stream_name.to_string(),
+    };
+    let response = retry(retry_params, || async {
+        kinesis_client
+            .describe_stream_summary(request.clone())
+            .await
+            .map_err(RusotoErrorWrapper::from)
+    })
+    .await?;
+    Ok(response.stream_description_summary.stream_arn)
+}
+
+/// Registers an enhanced fan-out consumer named `consumer_name` on a stream, or reuses the
+/// consumer with the same name if it already exists, and returns the consumer ARN once the
+/// consumer is active.
+/// <https://docs.aws.amazon.com/kinesis/latest/APIReference/API_RegisterStreamConsumer.html>
+pub(crate) async fn register_stream_consumer(
+    kinesis_client: &KinesisClient,
+    retry_params: &RetryParams,
+    stream_arn: &str,
+    consumer_name: &str,
+) -> anyhow::Result<String> {
+    let request = RegisterStreamConsumerInput {
+        stream_arn: stream_arn.to_string(),
+        consumer_name: consumer_name.to_string(),
+    };
+    let register_result = retry(retry_params, || async {
+        kinesis_client
+            .register_stream_consumer(request.clone())
+            .await
+            .map_err(RusotoErrorWrapper::from)
+    })
+    .await;
+    match register_result {
+        Ok(_) => {}
+        // The consumer was registered by another pipeline or a previous run of the source.
+        Err(RusotoErrorWrapper(RusotoError::Service(
+            RegisterStreamConsumerError::ResourceInUse(_),
+        ))) => {}
+        Err(error) => {
+            return Err(error).with_context(|| {
+                format!("Failed to register Kinesis stream consumer `{consumer_name}`.")
+            })
+        }
+    }
+    let request = DescribeStreamConsumerInput {
+        stream_arn: Some(stream_arn.to_string()),
+        consumer_name: Some(consumer_name.to_string()),
+        consumer_arn: None,
+    };
+    for _ in 0..MAX_CONSUMER_ACTIVATION_CHECKS {
+        let response = retry(retry_params, || async {
+            kinesis_client
+                .describe_stream_consumer(request.clone())
+                .await
+                .map_err(RusotoErrorWrapper::from)
+        })
+        .await?;
+        let consumer_description = response.consumer_description;
+        match consumer_description.consumer_status.as_str() {
+            "ACTIVE" => return Ok(consumer_description.consumer_arn),
+            "CREATING" => tokio::time::sleep(Duration::from_secs(1)).await,
+            consumer_status => bail!(
+                "Kinesis stream consumer `{consumer_name}` is in unexpected state \
+                 `{consumer_status}`."
+            ),
+        }
+    }
+    bail!("Kinesis stream consumer `{consumer_name}` did not become active in time.")
+}
+
+/// Subscribes an enhanced fan-out consumer to a shard. The subscription pushes the records of the
+/// shard through an event stream for up to 5 minutes, after which the consumer must subscribe
+/// again from the continuation sequence number of the last event.
+/// <https://docs.aws.amazon.com/kinesis/latest/APIReference/API_SubscribeToShard.html>
+///
+/// The event stream starts right after `from_sequence_number_exclusive` if a value is provided.
+/// Otherwise, it starts at the first (oldest) record in the shard.
+pub(crate) async fn subscribe_to_shard(
+    kinesis_client: &KinesisClient,
+    retry_params: &RetryParams,
+    consumer_arn: &str,
+    shard_id: &str,
+    from_sequence_number_exclusive: Option<String>,
+) -> anyhow::Result<SubscribeToShardOutput> {
+    let starting_position_type = if from_sequence_number_exclusive.is_some() {
+        "AFTER_SEQUENCE_NUMBER"
+    } else {
+        "TRIM_HORIZON"
+    }
+    .to_string();
+    let request = SubscribeToShardInput {
+        consumer_arn: consumer_arn.to_string(),
+        shard_id: shard_id.to_string(),
+        starting_position: StartingPosition {
+            type_: starting_position_type,
+            sequence_number: from_sequence_number_exclusive,
+            timestamp: None,
+        },
+    };
+    let response = retry(retry_params, || async {
+        kinesis_client
+            .subscribe_to_shard(request.clone())
+            .await
+            .map_err(RusotoErrorWrapper::from)
+    })
+    .await?;
+    Ok(response)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use std::collections::BTreeSet;
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
@@ -33,15 +33,16 @@ use quickwit_metastore::checkpoint::{
     PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
 };
 use rusoto_core::Region;
-use rusoto_kinesis::KinesisClient;
+use rusoto_kinesis::{KinesisClient, Shard};
 use serde_json::{json, Value as JsonValue};
 use tokio::sync::mpsc;
 use tokio::time;
 use tracing::{info, warn};
 
-use super::api::list_shards;
+use super::api::{get_stream_arn, list_shards, register_stream_consumer};
 use super::shard_consumer::{ShardConsumer, ShardConsumerHandle, ShardConsumerMessage};
 use crate::actors::DocProcessor;
+use crate::metrics::INDEXER_METRICS;
 use crate::models::RawDocBatch;
 use crate::source::kinesis::helpers::get_kinesis_client;
 use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
@@ -63,7 +64,13 @@ impl TypedSourceFactory for KinesisSourceFactory {
         params: KinesisSourceParams,
         checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<Self::Source> {
-        KinesisSource::try_new(ctx.source_config.source_id.clone(), params, checkpoint).await
+        KinesisSource::try_new(
+            ctx.index_id.clone(),
+            ctx.source_config.source_id.clone(),
+            params,
+            checkpoint,
+        )
+        .await
     }
 }
 
@@ -78,6 +85,12 @@ struct ShardConsumerState {
 pub struct KinesisSourceState {
     /// Pool of [`ShardConsumer`] managed by the source.
     shard_consumers: HashMap<ShardId, ShardConsumerState>,
+    /// Shards of the stream, including the closed shards that have not expired yet.
+    shards: HashMap<ShardId, Shard>,
+    /// Closed shards whose records have all been processed. The children of a shard are only
+    /// consumed once all their parents are finished, so that the records of a key are processed
+    /// in order across merges and splits.
+    finished_shards: HashSet<ShardId>,
     /// Number of bytes processed by the source.
     pub num_bytes_processed: u64,
     /// Number of records processed by the source (including invalid messages).
@@ -87,6 +100,8 @@ pub struct KinesisSourceState {
 }
 
 pub struct KinesisSource {
+    // Index ID
+    index_id: String,
     // Source ID
     source_id: String,
     // Target stream to consume.
@@ -102,6 +117,10 @@ pub struct KinesisSource {
     shard_consumers_rx: mpsc::Receiver<ShardConsumerMessage>,
     state: KinesisSourceState,
     backfill_mode_enabled: bool,
+    // Name of the enhanced fan-out consumer used to subscribe to the shards, if any.
+    enhanced_fan_out_consumer_name_opt: Option<String>,
+    // ARN of the enhanced fan-out consumer, resolved when the source is initialized.
+    consumer_arn_opt: Option<String>,
 }
 
 impl fmt::Debug for KinesisSource {
@@ -117,18 +136,21 @@ impl fmt::Debug for KinesisSource {
 impl KinesisSource {
     /// Instantiates a new `KinesisSource`.
     pub async fn try_new(
+        index_id: String,
         source_id: String,
         params: KinesisSourceParams,
         checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<Self> {
         let stream_name = params.stream_name;
         let backfill_mode_enabled = params.enable_backfill_mode;
+        let enhanced_fan_out_consumer_name_opt = params.enhanced_fan_out_consumer_name;
         let region = get_region(params.region_or_endpoint)?;
         let kinesis_client = get_kinesis_client(region)?;
         let (shard_consumers_tx, shard_consumers_rx) = mpsc::channel(1_000);
         let state = KinesisSourceState::default();
         let retry_params = RetryParams::default();
         Ok(KinesisSource {
+            index_id,
             source_id,
             stream_name,
             checkpoint,
@@ -138,9 +160,47 @@ impl KinesisSource {
             state,
             backfill_mode_enabled,
             retry_params,
+            enhanced_fan_out_consumer_name_opt,
+            consumer_arn_opt: None,
         })
     }
 
+    /// Fetches the list of shards of the stream. The closed shards seen for the first time whose
+    /// records were all processed according to the checkpoint are marked as finished.
+    async fn refresh_shards(&mut self, ctx: &SourceContext) -> anyhow::Result<()> {
+        let shards = ctx
+            .protect_future(list_shards(
+                &self.kinesis_client,
+                &self.retry_params,
+                &self.stream_name,
+                None,
+            ))
+            .await?;
+        for shard in shards {
+            if !self.state.shards.contains_key(&shard.shard_id) {
+                let partition_id = PartitionId::from(shard.shard_id.as_ref());
+                let position_opt = self.checkpoint.position_for_partition(&partition_id);
+                if position_opt.is_some() && position_opt == ending_position(&shard).as_ref() {
+                    self.state.finished_shards.insert(shard.shard_id.clone());
+                }
+            }
+            self.state.shards.insert(shard.shard_id.clone(), shard);
+        }
+        Ok(())
+    }
+
+    /// Spawns a consumer for each unfinished shard whose parents are finished.
+    fn spawn_ready_shard_consumers(&mut self, ctx: &SourceContext) {
+        let ready_shard_ids: Vec<ShardId> = ready_shard_ids(
+            &self.state.shards,
+            &self.state.finished_shards,
+            |shard_id| self.state.shard_consumers.contains_key(shard_id),
+        );
+        for shard_id in ready_shard_ids {
+            self.spawn_shard_consumer(ctx, shard_id);
+        }
+    }
+
     fn spawn_shard_consumer(&mut self, ctx: &SourceContext, shard_id: ShardId) {
         assert!(!self.state.shard_consumers.contains_key(&shard_id));
 
@@ -154,7 +214,7 @@ impl KinesisSource {
             Position::Offset(offset) => Some(offset.to_string()),
             Position::Beginning => None,
         };
-        let shard_consumer = ShardConsumer::new(
+        let mut shard_consumer = ShardConsumer::new(
             self.stream_name.clone(),
             shard_id.clone(),
             from_sequence_number_exclusive,
@@ -163,6 +223,9 @@ impl KinesisSource {
             self.shard_consumers_tx.clone(),
             self.retry_params.clone(),
         );
+        if let Some(consumer_arn) = &self.consumer_arn_opt {
+            shard_consumer = shard_consumer.with_consumer_arn(consumer_arn.clone());
+        }
         let _shard_consumer_handle = shard_consumer.spawn(ctx);
         let shard_consumer_state = ShardConsumerState {
             partition_id,
@@ -183,17 +246,31 @@ impl Source for KinesisSource {
         _doc_processor_mailbox: &Mailbox<DocProcessor>,
         ctx: &SourceContext,
     ) -> Result<(), ActorExitStatus> {
-        let shards = ctx
-            .protect_future(list_shards(
-                &self.kinesis_client,
-                &self.retry_params,
-                &self.stream_name,
-                None,
-            ))
-            .await?;
-        for shard in shards {
-            self.spawn_shard_consumer(ctx, shard.shard_id);
+        if let Some(consumer_name) = &self.enhanced_fan_out_consumer_name_opt {
+            let stream_arn = ctx
+                .protect_future(get_stream_arn(
+                    &self.kinesis_client,
+                    &self.retry_params,
+                    &self.stream_name,
+                ))
+                .await?;
+            let consumer_arn = ctx
+                .protect_future(register_stream_consumer(
+                    &self.kinesis_client,
+                    &self.retry_params,
+                    &stream_arn,
+                    consumer_name,
+                ))
+                .await?;
+            info!(
+                stream_name = %self.stream_name,
+                consumer_arn = %consumer_arn,
+                "Consuming Kinesis stream with enhanced fan-out."
+            );
+            self.consumer_arn_opt = Some(consumer_arn);
         }
+        self.refresh_shards(ctx).await?;
+        self.spawn_ready_shard_consumers(ctx);
         info!(
             stream_name = %self.stream_name,
             assigned_shards = %self.state.shard_consumers.keys().sorted().join(", "),
@@ -220,8 +297,9 @@ impl Source for KinesisSource {
                     // The source always carries a sender for this channel.
                     match message_opt.expect("Channel unexpectedly closed.") {
                         ShardConsumerMessage::ChildShards(shard_ids) => {
-                            for shard_id in shard_ids {
-                                self.spawn_shard_consumer(ctx, shard_id);
+                            // The children are consumed once their parents are finished.
+                            if shard_ids.iter().any(|shard_id| !self.state.shards.contains_key(shard_id)) {
+                                self.refresh_shards(ctx).await?;
                             }
                         }
                         ShardConsumerMessage::Records { shard_id, records, lag_millis } => {
@@ -265,6 +343,13 @@ impl Source for KinesisSource {
                                         })?;
                                     shard_consumer_state.lag_millis = lag_millis;
 
+                                    if let Some(lag_millis) = lag_millis {
+                                        INDEXER_METRICS
+                                            .kinesis_shard_lag_millis
+                                            .with_label_values([&self.index_id, &self.source_id, &shard_id])
+                                            .set(lag_millis);
+                                    }
+
                                     let partition_id = shard_consumer_state.partition_id.clone();
                                     let current_position = Position::from(record.sequence_number);
                                     let previous_position = std::mem::replace(&mut shard_consumer_state.position, current_position.clone());
@@ -287,8 +372,31 @@ impl Source for KinesisSource {
                                 num_active_shards = %self.state.shard_consumers.len(),
                                 "Shard is closed."
                             );
-                            self.state.shard_consumers.remove(&shard_id);
-
+                            if let Some(shard_consumer_state) = self.state.shard_consumers.remove(&shard_id) {
+                                // The ending sequence number of the shard is unknown if the shard
+                                // was still open when the shards were listed.
+                                if self.state.shards.get(&shard_id).and_then(ending_position).is_none() {
+                                    self.refresh_shards(ctx).await?;
+                                }
+                                // Moving the position of the shard to its ending sequence number
+                                // marks it as finished in the checkpoint, so that its children are
+                                // consumed right away when the source restarts.
+                                if let Some(ending_position) = self.state.shards.get(&shard_id).and_then(ending_position) {
+                                    if shard_consumer_state.position < ending_position {
+                                        checkpoint_delta.record_partition_delta(
+                                            shard_consumer_state.partition_id,
+                                            shard_consumer_state.position,
+                                            ending_position,
+                                        ).context("Failed to record partition delta.")?;
+                                    }
+                                }
+                            }
+                            INDEXER_METRICS
+                                .kinesis_shard_lag_millis
+                                .with_label_values([&self.index_id, &self.source_id, &shard_id])
+                                .set(0);
+                            self.state.finished_shards.insert(shard_id);
+                            self.spawn_ready_shard_consumers(ctx);
                         }
                         ShardConsumerMessage::ShardEOF(shard_id) => {
                             info!(
@@ -346,6 +454,39 @@ impl Source for KinesisSource {
     }
 }
 
+/// Returns the position of the last record of a closed shard.
+fn ending_position(shard: &Shard) -> Option<Position> {
+    shard
+        .sequence_number_range
+        .ending_sequence_number
+        .clone()
+        .map(Position::from)
+}
+
+/// Returns the IDs of the shards that are neither finished nor consumed, and whose parents are
+/// finished. A parent that is not listed anymore has expired and is considered finished.
+fn ready_shard_ids(
+    shards: &HashMap<ShardId, Shard>,
+    finished_shards: &HashSet<ShardId>,
+    is_consumed: impl Fn(&str) -> bool,
+) -> Vec<ShardId> {
+    shards
+        .values()
+        .filter(|shard| !finished_shards.contains(&shard.shard_id) && !is_consumed(&shard.shard_id))
+        .filter(|shard| {
+            [&shard.parent_shard_id, &shard.adjacent_parent_shard_id]
+                .into_iter()
+                .flatten()
+                .all(|parent_shard_id| {
+                    !shards.contains_key(parent_shard_id)
+                        || finished_shards.contains(parent_shard_id)
+                })
+        })
+        .map(|shard| shard.shard_id.clone())
+        .sorted()
+        .collect()
+}
+
 pub(super) fn get_region(region_or_endpoint: Option<RegionOrEndpoint>) -> anyhow::Result<Region> {
     if let Some(RegionOrEndpoint::Endpoint(endpoint)) = region_or_endpoint {
         return Ok(Region::Custom {
@@ -416,6 +557,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ready_shard_ids() {
+        let shard = |shard_id: &str, parent_shard_ids: &[&str]| Shard {
+            shard_id: shard_id.to_string(),
+            parent_shard_id: parent_shard_ids
+                .first()
+                .map(|shard_id| shard_id.to_string()),
+            adjacent_parent_shard_id: parent_shard_ids.get(1).map(|shard_id| shard_id.to_string()),
+            ..Default::default()
+        };
+        // Shard 0 was split into shards 1 and 2, which were then merged into shard 3. The parent
+        // of shard 0 has expired.
+        let shards: HashMap<ShardId, Shard> = [
+            shard("shard-0", &["expired-shard"]),
+            shard("shard-1", &["shard-0"]),
+            shard("shard-2", &["shard-0"]),
+            shard("shard-3", &["shard-1", "shard-2"]),
+        ]
+        .into_iter()
+        .map(|shard| (shard.shard_id.clone(), shard))
+        .collect();
+        let mut finished_shards = HashSet::new();
+        assert_eq!(
+            ready_shard_ids(&shards, &finished_shards, |_| false),
+            ["shard-0"]
+        );
+        finished_shards.insert("shard-0".to_string());
+        assert_eq!(
+            ready_shard_ids(&shards, &finished_shards, |_| false),
+            ["shard-1", "shard-2"]
+        );
+        assert_eq!(
+            ready_shard_ids(&shards, &finished_shards, |shard_id| shard_id == "shard-1"),
+            ["shard-2"]
+        );
+        finished_shards.insert("shard-1".to_string());
+        assert_eq!(
+            ready_shard_ids(&shards, &finished_shards, |_| false),
+            ["shard-2"]
+        );
+        finished_shards.insert("shard-2".to_string());
+        assert_eq!(
+            ready_shard_ids(&shards, &finished_shards, |_| false),
+            ["shard-3"]
+        );
+    }
+
     #[tokio::test]
     async fn test_kinesis_source() {
         let universe = Universe::with_accelerated_time();
@@ -427,13 +615,18 @@ mod tests {
                 "http://localhost:4566".to_string(),
             )),
             enable_backfill_mode: true,
+            enhanced_fan_out_consumer_name: None,
         };
         {
             let checkpoint = SourceCheckpoint::default();
-            let kinesis_source =
-                KinesisSource::try_new("my-kinesis-source".to_string(), params.clone(), checkpoint)
-                    .await
-                    .unwrap();
+            let kinesis_source = KinesisSource::try_new(
+                "my-index".to_string(),
+                "my-kinesis-source".to_string(),
+                params.clone(),
+                checkpoint,
+            )
+            .await
+            .unwrap();
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
@@ -484,10 +677,14 @@ mod tests {
             .collect();
         {
             let checkpoint = SourceCheckpoint::default();
-            let kinesis_source =
-                KinesisSource::try_new("my-kinesis-source".to_string(), params.clone(), checkpoint)
-                    .await
-                    .unwrap();
+            let kinesis_source = KinesisSource::try_new(
+                "my-index".to_string(),
+                "my-kinesis-source".to_string(),
+                params.clone(),
+                checkpoint,
+            )
+            .await
+            .unwrap();
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
@@ -555,10 +752,14 @@ mod tests {
             .into_iter()
             .map(|(partition_id, offset)| (PartitionId::from(partition_id), Position::from(offset)))
             .collect();
-            let kinesis_source =
-                KinesisSource::try_new("my-kinesis-source".to_string(), params.clone(), checkpoint)
-                    .await
-                    .unwrap();
+            let kinesis_source = KinesisSource::try_new(
+                "my-index".to_string(),
+                "my-kinesis-source".to_string(),
+                params.clone(),
+                checkpoint,
+            )
+            .await
+            .unwrap();
             let actor = SourceActor {
                 source: Box::new(kinesis_source),
                 doc_processor_mailbox: doc_processor_mailbox.clone(),
@@ -18,18 +18,27 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::fmt;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::StreamExt;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, ActorHandle, Handler, Mailbox};
 use quickwit_aws::retry::RetryParams;
-use rusoto_kinesis::{KinesisClient, Record};
+use rusoto_core::event_stream::EventStream;
+use rusoto_kinesis::{ChildShard, KinesisClient, Record, SubscribeToShardEventStreamItem};
 use serde_json::{json, Value as JsonValue};
 use tokio::sync::mpsc;
+use tokio::time;
+use tracing::warn;
 
-use crate::source::kinesis::api::{get_records, get_shard_iterator};
+use crate::source::kinesis::api::{get_records, get_shard_iterator, subscribe_to_shard};
 use crate::source::SourceContext;
 
+/// Maximum amount of time spent waiting for the next event of an enhanced fan-out subscription
+/// before yielding, so that the actor keeps recording progress on an idle shard.
+const SUBSCRIPTION_EVENT_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub(super) enum ShardConsumerMessage {
     /// The shard was the subject of a merge or a split and points to one (merge) or two (split)
@@ -59,6 +68,11 @@ pub(super) struct ShardConsumerState {
     num_records_processed: u64,
     /// The shard iterator value that will be used for the next call to `GetRecords`.
     next_shard_iterator: Option<String>,
+    /// The event stream of the current enhanced fan-out subscription to the shard. The event
+    /// stream is not `Sync`, so it is wrapped in a mutex, which is never contended.
+    event_stream_opt: Option<Mutex<EventStream<SubscribeToShardEventStreamItem>>>,
+    /// The sequence number the next enhanced fan-out subscription resumes after.
+    continuation_sequence_number: Option<String>,
 }
 
 pub(super) struct ShardConsumer {
@@ -70,6 +84,9 @@ pub(super) struct ShardConsumer {
     /// When this value is set to true, the consumer shuts down after reaching the last (most
     /// recent) record in the shard.
     shutdown_at_shard_eof: bool,
+    /// ARN of the enhanced fan-out consumer. When set, the records are pushed by the Kinesis
+    /// service through `SubscribeToShard` instead of being polled with `GetRecords`.
+    consumer_arn_opt: Option<String>,
     state: ShardConsumerState,
     kinesis_client: KinesisClient,
     sink: mpsc::Sender<ShardConsumerMessage>,
@@ -102,12 +119,19 @@ impl ShardConsumer {
             from_sequence_number_exclusive,
             state: Default::default(),
             shutdown_at_shard_eof,
+            consumer_arn_opt: None,
             kinesis_client,
             sink,
             retry_params,
         }
     }
 
+    /// Consumes the shard with the enhanced fan-out consumer identified by `consumer_arn`.
+    pub fn with_consumer_arn(mut self, consumer_arn: String) -> Self {
+        self.consumer_arn_opt = Some(consumer_arn);
+        self
+    }
+
     pub fn spawn(self, ctx: &SourceContext) -> ShardConsumerHandle {
         let (_mailbox, _actor_handle) = ctx.spawn_actor().spawn(self);
         ShardConsumerHandle {
@@ -125,6 +149,175 @@ impl ShardConsumer {
         self.sink.send(message).await?;
         Ok(())
     }
+
+    /// Forwards a batch of records to the source.
+    async fn process_records(
+        &mut self,
+        records: Vec<Record>,
+        lag_millis: Option<i64>,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        self.state.lag_millis = lag_millis;
+
+        if records.is_empty() {
+            return Ok(());
+        }
+        self.state.current_sequence_number =
+            records.last().map(|record| record.sequence_number.clone());
+        self.state.num_bytes_processed += records
+            .iter()
+            .map(|record| record.data.len() as u64)
+            .sum::<u64>();
+        self.state.num_records_processed += records.len() as u64;
+
+        let message = ShardConsumerMessage::Records {
+            shard_id: self.shard_id.clone(),
+            records,
+            lag_millis,
+        };
+        self.send_message(ctx, message).await
+    }
+
+    /// Notifies the source of the children of the shard after a merge or a split.
+    async fn process_child_shards(
+        &self,
+        children: Vec<ChildShard>,
+        ctx: &ActorContext<Self>,
+    ) -> anyhow::Result<()> {
+        let shard_ids: Vec<String> = children
+            .into_iter()
+            // Filter out duplicate message when two shards are merged.
+            .filter(|child| child.parent_shards.first() == Some(&self.shard_id))
+            .map(|child| child.shard_id)
+            .collect();
+        if !shard_ids.is_empty() {
+            let message = ShardConsumerMessage::ChildShards(shard_ids);
+            self.send_message(ctx, message).await?;
+        }
+        Ok(())
+    }
+
+    /// Polls the shard with `GetRecords`.
+    async fn poll_records(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        if let Some(shard_iterator) = self.state.next_shard_iterator.take() {
+            let response = ctx
+                .protect_future(get_records(
+                    &self.kinesis_client,
+                    &self.retry_params,
+                    shard_iterator,
+                ))
+                .await?;
+            self.state.next_shard_iterator = response.next_shard_iterator;
+            self.process_records(response.records, response.millis_behind_latest, ctx)
+                .await?;
+
+            if let Some(children) = response.child_shards {
+                self.process_child_shards(children, ctx).await?;
+            }
+            if self.shutdown_at_shard_eof && response.millis_behind_latest == Some(0) {
+                let message = ShardConsumerMessage::ShardEOF(self.shard_id.clone());
+                self.send_message(ctx, message).await?;
+                return Err(ActorExitStatus::Success);
+            };
+            // The `GetRecords` API has a limit of 5 transactions per second. 1s / 5 + ε = 210ms.
+            let interval = Duration::from_millis(210);
+            ctx.schedule_self_msg(interval, Loop).await;
+            return Ok(());
+        }
+        let message = ShardConsumerMessage::ShardClosed(self.shard_id.clone());
+        self.send_message(ctx, message).await?;
+        Err(ActorExitStatus::Success)
+    }
+
+    /// Receives the records pushed through an enhanced fan-out subscription to the shard,
+    /// subscribing again whenever the current subscription expires.
+    async fn receive_subscription_event(
+        &mut self,
+        consumer_arn: &str,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if self.state.event_stream_opt.is_none() {
+            let from_sequence_number_exclusive = self
+                .state
+                .continuation_sequence_number
+                .clone()
+                .or_else(|| self.from_sequence_number_exclusive.clone());
+            let response = ctx
+                .protect_future(subscribe_to_shard(
+                    &self.kinesis_client,
+                    &self.retry_params,
+                    consumer_arn,
+                    &self.shard_id,
+                    from_sequence_number_exclusive,
+                ))
+                .await?;
+            self.state.event_stream_opt = Some(Mutex::new(response.event_stream));
+        }
+        let event_stream = self
+            .state
+            .event_stream_opt
+            .as_mut()
+            .expect("The shard consumer should be subscribed to the shard.")
+            .get_mut()
+            .expect("The lock should never be poisoned.");
+        let event_res_opt = match ctx
+            .protect_future(time::timeout(
+                SUBSCRIPTION_EVENT_TIMEOUT,
+                event_stream.next(),
+            ))
+            .await
+        {
+            Ok(event_res_opt) => event_res_opt,
+            Err(_elapsed) => {
+                ctx.send_self_message(Loop).await?;
+                return Ok(());
+            }
+        };
+        let event = match event_res_opt {
+            Some(Ok(SubscribeToShardEventStreamItem::SubscribeToShardEvent(event))) => event,
+            Some(Ok(event_stream_item)) => {
+                return Err(anyhow::anyhow!(
+                    "Subscription to Kinesis shard `{}` failed: `{event_stream_item:?}`.",
+                    self.shard_id
+                )
+                .into());
+            }
+            Some(Err(error)) => {
+                warn!(
+                    stream_name = %self.stream_name,
+                    shard_id = %self.shard_id,
+                    error = ?error,
+                    "Subscription to shard failed. Subscribing again."
+                );
+                self.state.event_stream_opt = None;
+                ctx.schedule_self_msg(Duration::from_secs(1), Loop).await;
+                return Ok(());
+            }
+            // Subscriptions expire after 5 minutes.
+            None => {
+                self.state.event_stream_opt = None;
+                ctx.send_self_message(Loop).await?;
+                return Ok(());
+            }
+        };
+        self.state.continuation_sequence_number = Some(event.continuation_sequence_number);
+        self.process_records(event.records, Some(event.millis_behind_latest), ctx)
+            .await?;
+
+        if let Some(children) = event.child_shards.filter(|children| !children.is_empty()) {
+            self.process_child_shards(children, ctx).await?;
+            let message = ShardConsumerMessage::ShardClosed(self.shard_id.clone());
+            self.send_message(ctx, message).await?;
+            return Err(ActorExitStatus::Success);
+        }
+        if self.shutdown_at_shard_eof && event.millis_behind_latest == 0 {
+            let message = ShardConsumerMessage::ShardEOF(self.shard_id.clone());
+            self.send_message(ctx, message).await?;
+            return Err(ActorExitStatus::Success);
+        }
+        ctx.send_self_message(Loop).await?;
+        Ok(())
+    }
 }
 
 pub(super) struct ShardConsumerHandle {
@@ -144,6 +337,10 @@ impl Actor for ShardConsumer {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        if self.consumer_arn_opt.is_some() {
+            ctx.send_self_message(Loop).await?;
+            return Ok(());
+        }
         self.state.next_shard_iterator = ctx
             .protect_future(get_shard_iterator(
                 &self.kinesis_client,
@@ -182,61 +379,11 @@ impl Handler<Loop> for ShardConsumer {
         _message: Loop,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
-        if let Some(shard_iterator) = self.state.next_shard_iterator.take() {
-            let response = ctx
-                .protect_future(get_records(
-                    &self.kinesis_client,
-                    &self.retry_params,
-                    shard_iterator,
-                ))
-                .await?;
-            self.state.lag_millis = response.millis_behind_latest;
-            self.state.next_shard_iterator = response.next_shard_iterator;
-
-            if !response.records.is_empty() {
-                self.state.current_sequence_number = response
-                    .records
-                    .last()
-                    .map(|record| record.sequence_number.clone());
-                self.state.num_bytes_processed += response
-                    .records
-                    .iter()
-                    .map(|record| record.data.len() as u64)
-                    .sum::<u64>();
-                self.state.num_records_processed += response.records.len() as u64;
-
-                let message = ShardConsumerMessage::Records {
-                    shard_id: self.shard_id.clone(),
-                    records: response.records,
-                    lag_millis: response.millis_behind_latest,
-                };
-                self.send_message(ctx, message).await?;
-            }
-            if let Some(children) = response.child_shards {
-                let shard_ids: Vec<String> = children
-                    .into_iter()
-                    // Filter out duplicate message when two shards are merged.
-                    .filter(|child| child.parent_shards.first() == Some(&self.shard_id))
-                    .map(|child| child.shard_id)
-                    .collect();
-                if !shard_ids.is_empty() {
-                    let message = ShardConsumerMessage::ChildShards(shard_ids);
-                    self.send_message(ctx, message).await?;
-                }
-            }
-            if self.shutdown_at_shard_eof && response.millis_behind_latest == Some(0) {
-                let message = ShardConsumerMessage::ShardEOF(self.shard_id.clone());
-                self.send_message(ctx, message).await?;
-                return Err(ActorExitStatus::Success);
-            };
-            // The `GetRecords` API has a limit of 5 transactions per second. 1s / 5 + ε = 210ms.
-            let interval = Duration::from_millis(210);
-            ctx.schedule_self_msg(interval, Loop).await;
-            return Ok(());
+        if let Some(consumer_arn) = self.consumer_arn_opt.clone() {
+            self.receive_subscription_event(&consumer_arn, ctx).await
+        } else {
+            self.poll_records(ctx).await
         }
-        let message = ShardConsumerMessage::ShardClosed(self.shard_id.clone());
-        self.send_message(ctx, message).await?;
-        Err(ActorExitStatus::Success)
     }
 }
 
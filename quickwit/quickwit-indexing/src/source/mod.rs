@@ -54,15 +54,26 @@
 //!
 //! Right now two sources are implemented in quickwit.
 //! - the file source: there partition here is a filepath, and the position is a byte-offset within
-//!   that file.
+//!   that file, or a row number for Parquet files.
 //! - the kafka source: the partition id is a kafka topic partition id, and the position is a kafka
 //!   offset.
+//! - the index source: the partition id is a split ID of another index, and the position is the
+//!   ordinal of a document within that split.
+#[cfg(any(feature = "kafka", feature = "pulsar"))]
+#[cfg_attr(not(feature = "kafka"), allow(dead_code))]
+mod avro_decoder;
 mod file_source;
+mod index_source;
 mod ingest_api_source;
 #[cfg(feature = "kafka")]
 mod kafka_source;
 #[cfg(feature = "kinesis")]
 mod kinesis;
+mod parquet_doc_reader;
+#[cfg(any(feature = "kafka", feature = "pulsar"))]
+mod protobuf_decoder;
+#[cfg(feature = "pulsar")]
+mod pulsar_schema_decoder;
 #[cfg(feature = "pulsar")]
 mod pulsar_source;
 mod source_factory;
@@ -76,6 +87,9 @@ use std::time::Duration;
 use anyhow::bail;
 use async_trait::async_trait;
 pub use file_source::{FileSource, FileSourceFactory};
+pub use index_source::{IndexSource, IndexSourceFactory};
+#[cfg(feature = "kafka")]
+pub(crate) use kafka_source::parse_client_params as parse_kafka_client_params;
 #[cfg(feature = "kafka")]
 pub use kafka_source::{KafkaSource, KafkaSourceFactory};
 #[cfg(feature = "kinesis")]
@@ -88,6 +102,7 @@ use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::{SourceConfig, SourceParams};
 use quickwit_metastore::checkpoint::SourceCheckpoint;
 use quickwit_metastore::Metastore;
+use quickwit_storage::StorageUriResolver;
 use serde_json::Value as JsonValue;
 pub use source_factory::{SourceFactory, SourceLoader, TypedSourceFactory};
 use tokio::runtime::Handle;
@@ -102,9 +117,14 @@ use crate::source::ingest_api_source::IngestApiSourceFactory;
 pub struct SourceExecutionContext {
     pub metastore: Arc<dyn Metastore>,
     pub index_id: String,
+    // ID of the node running the pipeline.
+    pub node_id: String,
+    // Ordinal of the pipeline among the pipelines of the source running on the node.
+    pub pipeline_ord: usize,
     // Ingest API queues directory path.
     pub queues_dir_path: PathBuf,
     pub source_config: SourceConfig,
+    pub storage_resolver: StorageUriResolver,
 }
 
 impl SourceExecutionContext {
@@ -118,8 +138,11 @@ impl SourceExecutionContext {
         Arc::new(Self {
             metastore,
             index_id: index_id.to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
             queues_dir_path,
             source_config,
+            storage_resolver: StorageUriResolver::for_test(),
         })
     }
 }
@@ -286,6 +309,7 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
     SOURCE_LOADER.get_or_init(|| {
         let mut source_factory = SourceLoader::default();
         source_factory.add_source("file", FileSourceFactory);
+        source_factory.add_source("index", IndexSourceFactory);
         #[cfg(feature = "kafka")]
         source_factory.add_source("kafka", KafkaSourceFactory);
         #[cfg(feature = "kinesis")]
@@ -0,0 +1,266 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::Context;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::serialized_reader::ReadOptionsBuilder;
+use parquet::record::reader::RowIter;
+use parquet::record::{Field, Row};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Returns true if the file should be read as a Parquet file, based on its extension.
+pub(crate) fn is_parquet_file(filepath: &Path) -> bool {
+    filepath
+        .extension()
+        .map(|extension| extension.eq_ignore_ascii_case("parquet"))
+        .unwrap_or(false)
+}
+
+/// Reads the rows of a Parquet file as JSON documents.
+///
+/// Row groups are decoded one at a time, so the memory usage of the reader is bounded by the size
+/// of the largest row group rather than by the size of the file. Columns are mapped by name to
+/// the fields of the doc mapping, and groups are converted into JSON objects.
+pub(crate) struct ParquetDocReader {
+    // Taken out while a batch of rows is being read on a blocking thread.
+    row_iter_opt: Option<RowIter<'static>>,
+}
+
+impl ParquetDocReader {
+    /// Opens a Parquet file and skips its first `num_rows_to_skip` rows.
+    pub fn open(filepath: &Path, num_rows_to_skip: u64) -> anyhow::Result<Self> {
+        let open_reader = || -> anyhow::Result<File> {
+            File::open(filepath)
+                .with_context(|| format!("Failed to open source file `{}`.", filepath.display()))
+        };
+        let file_reader = SerializedFileReader::new(open_reader()?)
+            .with_context(|| format!("Failed to read Parquet file `{}`.", filepath.display()))?;
+
+        // Row groups entirely covered by the rows to skip are not even decoded.
+        let mut first_row_group_ord = 0;
+        let mut num_rows_to_skip_in_row_group = num_rows_to_skip;
+        for row_group_metadata in file_reader.metadata().row_groups() {
+            let num_rows = row_group_metadata.num_rows() as u64;
+            if num_rows_to_skip_in_row_group < num_rows {
+                break;
+            }
+            num_rows_to_skip_in_row_group -= num_rows;
+            first_row_group_ord += 1;
+        }
+        let read_options = ReadOptionsBuilder::new()
+            .with_predicate(Box::new(move |_, row_group_ord| {
+                row_group_ord >= first_row_group_ord
+            }))
+            .build();
+        let file_reader = SerializedFileReader::new_with_options(open_reader()?, read_options)
+            .with_context(|| format!("Failed to read Parquet file `{}`.", filepath.display()))?;
+        let mut row_iter = RowIter::from_file_into(Box::new(file_reader));
+
+        for _ in 0..num_rows_to_skip_in_row_group {
+            if row_iter.next().is_none() {
+                break;
+            }
+        }
+        Ok(Self {
+            row_iter_opt: Some(row_iter),
+        })
+    }
+
+    /// Reads rows until the accumulated size of the documents reaches `num_bytes_limit` or the end
+    /// of the file is reached. Returns the documents and whether the end of the file was reached.
+    pub async fn read_docs(&mut self, num_bytes_limit: u64) -> anyhow::Result<(Vec<String>, bool)> {
+        let mut row_iter = self
+            .row_iter_opt
+            .take()
+            .context("A previous read of the Parquet file failed.")?;
+        // Decoding Parquet pages is CPU-intensive and the underlying file reads are blocking.
+        let (row_iter, docs, reached_eof) = tokio::task::spawn_blocking(move || {
+            let mut docs = Vec::new();
+            let mut num_bytes = 0;
+            let mut reached_eof = false;
+            while num_bytes < num_bytes_limit {
+                let Some(row) = row_iter.next() else {
+                    reached_eof = true;
+                    break;
+                };
+                let doc = JsonValue::Object(row_to_json(&row)).to_string();
+                num_bytes += doc.len() as u64;
+                docs.push(doc);
+            }
+            (row_iter, docs, reached_eof)
+        })
+        .await?;
+        self.row_iter_opt = Some(row_iter);
+        Ok((docs, reached_eof))
+    }
+}
+
+fn row_to_json(row: &Row) -> JsonMap<String, JsonValue> {
+    row.get_column_iter()
+        .filter(|(_, field)| !matches!(field, Field::Null))
+        .map(|(column_name, field)| (column_name.clone(), field_to_json(field)))
+        .collect()
+}
+
+/// Converts a Parquet field into a JSON value accepted by the doc mapper. In particular,
+/// timestamps and dates are converted into Unix timestamps, whose precision is inferred by the
+/// datetime parser.
+fn field_to_json(field: &Field) -> JsonValue {
+    match field {
+        Field::Date(num_days) => JsonValue::from(*num_days as i64 * SECONDS_PER_DAY),
+        Field::TimestampMillis(timestamp_millis) => JsonValue::from(*timestamp_millis),
+        Field::TimestampMicros(timestamp_micros) => JsonValue::from(*timestamp_micros),
+        Field::Group(row) => JsonValue::Object(row_to_json(row)),
+        Field::ListInternal(list) => {
+            JsonValue::Array(list.elements().iter().map(field_to_json).collect())
+        }
+        Field::MapInternal(map) => {
+            let json_obj = map
+                .entries()
+                .iter()
+                .map(|(key, value)| {
+                    let key_str = match key {
+                        Field::Str(key_str) => key_str.clone(),
+                        _ => key.to_string(),
+                    };
+                    (key_str, field_to_json(value))
+                })
+                .collect();
+            JsonValue::Object(json_obj)
+        }
+        _ => field.to_json_value(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    use super::*;
+
+    /// Writes a Parquet file with a `body` and a `count` column, and one row group per slice of
+    /// rows.
+    pub(crate) fn write_parquet_file_for_test(filepath: &Path, row_groups: &[&[(&str, i64)]]) {
+        let schema = parse_message_type(
+            "message schema {
+                REQUIRED BINARY body (UTF8);
+                REQUIRED INT64 count;
+            }",
+        )
+        .unwrap();
+        let file = File::create(filepath).unwrap();
+        let mut writer = SerializedFileWriter::new(
+            file,
+            Arc::new(schema),
+            Arc::new(WriterProperties::builder().build()),
+        )
+        .unwrap();
+        for rows in row_groups {
+            let mut row_group_writer = writer.next_row_group().unwrap();
+
+            let mut column_writer = row_group_writer.next_column().unwrap().unwrap();
+            let bodies: Vec<ByteArray> = rows.iter().map(|(body, _)| (*body).into()).collect();
+            column_writer
+                .typed::<ByteArrayType>()
+                .write_batch(&bodies, None, None)
+                .unwrap();
+            column_writer.close().unwrap();
+
+            let mut column_writer = row_group_writer.next_column().unwrap().unwrap();
+            let counts: Vec<i64> = rows.iter().map(|(_, count)| *count).collect();
+            column_writer
+                .typed::<Int64Type>()
+                .write_batch(&counts, None, None)
+                .unwrap();
+            column_writer.close().unwrap();
+
+            row_group_writer.close().unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_is_parquet_file() {
+        assert!(is_parquet_file(Path::new("data/hits.parquet")));
+        assert!(is_parquet_file(Path::new("data/hits.PARQUET")));
+        assert!(!is_parquet_file(Path::new("data/hits.json")));
+        assert!(!is_parquet_file(Path::new("data/parquet")));
+    }
+
+    #[tokio::test]
+    async fn test_parquet_doc_reader() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let filepath = temp_dir.path().join("docs.parquet");
+        write_parquet_file_for_test(
+            &filepath,
+            &[
+                &[("doc 1", 1), ("doc 2", 2)],
+                &[("doc 3", 3)],
+                &[("doc 4", 4)],
+            ],
+        );
+        {
+            let mut parquet_doc_reader = ParquetDocReader::open(&filepath, 0).unwrap();
+            let (docs, reached_eof) = parquet_doc_reader.read_docs(20).await.unwrap();
+            assert!(!reached_eof);
+            assert_eq!(docs, vec![r#"{"body":"doc 1","count":1}"#.to_string()]);
+            let (docs, reached_eof) = parquet_doc_reader.read_docs(1_000).await.unwrap();
+            assert!(reached_eof);
+            assert_eq!(docs.len(), 3);
+        }
+        {
+            // Skipping rows across row groups.
+            let mut parquet_doc_reader = ParquetDocReader::open(&filepath, 3).unwrap();
+            let (docs, reached_eof) = parquet_doc_reader.read_docs(1_000).await.unwrap();
+            assert!(reached_eof);
+            assert_eq!(docs, vec![r#"{"body":"doc 4","count":4}"#.to_string()]);
+        }
+        {
+            let mut parquet_doc_reader = ParquetDocReader::open(&filepath, 10).unwrap();
+            let (docs, reached_eof) = parquet_doc_reader.read_docs(1_000).await.unwrap();
+            assert!(reached_eof);
+            assert!(docs.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_field_to_json() {
+        assert_eq!(field_to_json(&Field::Date(1)), JsonValue::from(86_400));
+        assert_eq!(
+            field_to_json(&Field::TimestampMillis(1_672_531_200_000)),
+            JsonValue::from(1_672_531_200_000i64)
+        );
+        assert_eq!(
+            field_to_json(&Field::Str("hello".to_string())),
+            JsonValue::from("hello")
+        );
+        assert_eq!(field_to_json(&Field::Long(-3)), JsonValue::from(-3));
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::Context;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, SerializeOptions};
+use quickwit_config::ProtobufOptions;
+
+/// Decodes binary Protobuf messages into JSON documents.
+///
+/// Fields are named after their name in the `.proto` file rather than their JSON name, 64-bit
+/// integers are kept as numbers, and well-known types such as `google.protobuf.Timestamp` follow
+/// the Protobuf JSON mapping.
+pub(crate) struct ProtobufDecoder {
+    message_descriptor: MessageDescriptor,
+    serialize_options: SerializeOptions,
+}
+
+impl ProtobufDecoder {
+    /// Loads the file descriptor set and looks up the message type of the options.
+    pub async fn load(options: &ProtobufOptions) -> anyhow::Result<Self> {
+        let descriptor_set_bytes = quickwit_storage::load_file(&options.descriptor_set_uri)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load Protobuf descriptor set `{}`.",
+                    options.descriptor_set_uri
+                )
+            })?;
+        Self::from_descriptor_set(descriptor_set_bytes.as_slice(), &options.message_type)
+    }
+
+    fn from_descriptor_set(
+        descriptor_set_bytes: &[u8],
+        message_type: &str,
+    ) -> anyhow::Result<Self> {
+        let descriptor_pool = DescriptorPool::decode(descriptor_set_bytes)
+            .context("Failed to parse Protobuf descriptor set.")?;
+        let message_descriptor = descriptor_pool
+            .get_message_by_name(message_type)
+            .with_context(|| {
+                format!("Message type `{message_type}` does not exist in the descriptor set.")
+            })?;
+        let serialize_options = SerializeOptions::new()
+            .stringify_64_bit_integers(false)
+            .use_proto_field_name(true);
+        Ok(Self {
+            message_descriptor,
+            serialize_options,
+        })
+    }
+
+    /// Decodes a message into a JSON document.
+    pub fn decode(&self, payload: &[u8]) -> Result<String, String> {
+        let message = DynamicMessage::decode(self.message_descriptor.clone(), payload)
+            .map_err(|error| format!("Invalid Protobuf message: {error}"))?;
+        let mut serializer = serde_json::Serializer::new(Vec::new());
+        message
+            .serialize_with_options(&mut serializer, &self.serialize_options)
+            .map_err(|error| format!("Failed to serialize Protobuf message: {error}"))?;
+        let doc = String::from_utf8(serializer.into_inner())
+            .expect("The JSON serializer should produce valid UTF-8.");
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use prost_reflect::Value;
+    use prost_types::field_descriptor_proto::{Label, Type};
+    use prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    };
+
+    use super::*;
+
+    fn field(name: &str, number: i32, field_type: Type) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(field_type as i32),
+            ..Default::default()
+        }
+    }
+
+    fn descriptor_set_for_test() -> Vec<u8> {
+        let file_descriptor = FileDescriptorProto {
+            name: Some("event.proto".to_string()),
+            package: Some("test".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("Event".to_string()),
+                field: vec![
+                    field("body", 1, Type::String),
+                    field("user_id", 2, Type::Int64),
+                    field("is_error", 3, Type::Bool),
+                ],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+        FileDescriptorSet {
+            file: vec![file_descriptor],
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_protobuf_decoder() {
+        let descriptor_set = descriptor_set_for_test();
+        let protobuf_decoder =
+            ProtobufDecoder::from_descriptor_set(&descriptor_set, "test.Event").unwrap();
+
+        let mut message = DynamicMessage::new(protobuf_decoder.message_descriptor.clone());
+        message.set_field_by_name("body", Value::String("hello".to_string()));
+        message.set_field_by_name("user_id", Value::I64(1 << 40));
+        let payload = message.encode_to_vec();
+
+        let doc = protobuf_decoder.decode(&payload).unwrap();
+        let doc_json: serde_json::Value = serde_json::from_str(&doc).unwrap();
+        assert_eq!(
+            doc_json,
+            serde_json::json!({"body": "hello", "user_id": 1u64 << 40})
+        );
+        protobuf_decoder.decode(&[0xff, 0xff]).unwrap_err();
+    }
+
+    #[test]
+    fn test_protobuf_decoder_unknown_message_type() {
+        let descriptor_set = descriptor_set_for_test();
+        let error = ProtobufDecoder::from_descriptor_set(&descriptor_set, "test.Unknown")
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("test.Unknown"));
+        assert!(
+            ProtobufDecoder::from_descriptor_set(b"not a descriptor set", "test.Event").is_err()
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use apache_avro::Schema;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::source::avro_decoder::decode_datum;
+
+const DEFAULT_TENANT: &str = "public";
+
+const DEFAULT_NAMESPACE: &str = "default";
+
+#[derive(Debug, Error)]
+pub(crate) enum PulsarSchemaDecodeError {
+    /// The message cannot be decoded and should be skipped.
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+    /// The schema registry could not be reached. The message should be retried later.
+    #[error(
+        "Failed to fetch version `{schema_version}` of the schema of topic `{topic}` from the \
+         schema registry: {message}"
+    )]
+    SchemaRegistryUnavailable {
+        topic: String,
+        schema_version: i64,
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct GetSchemaResponse {
+    #[serde(rename = "type")]
+    schema_type: String,
+    data: String,
+}
+
+/// Schema of a topic, as registered in the Pulsar schema registry.
+enum TopicSchema {
+    Avro(Schema),
+    Json,
+    /// Schemas of primitive types such as `STRING` or `BYTES`: the payload is decoded as a UTF-8
+    /// string.
+    Primitive,
+    Unsupported(String),
+}
+
+/// Decodes the messages published with an Avro or JSON schema into JSON documents.
+///
+/// The schemas are fetched from the schema registry exposed by the Pulsar admin REST API the first
+/// time a schema version is encountered for a topic and cached for the lifetime of the decoder.
+pub(crate) struct PulsarSchemaDecoder {
+    http_client: reqwest::Client,
+    admin_url: String,
+    auth_token_opt: Option<String>,
+    schemas: HashMap<(String, i64), TopicSchema>,
+}
+
+impl PulsarSchemaDecoder {
+    pub fn new(admin_url: String, auth_token_opt: Option<String>) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            admin_url,
+            auth_token_opt,
+            schemas: HashMap::new(),
+        }
+    }
+
+    /// Decodes the payload of a message published on `topic` with the schema version
+    /// `schema_version` into a JSON document.
+    pub async fn decode(
+        &mut self,
+        topic: &str,
+        schema_version: &[u8],
+        payload: &[u8],
+    ) -> Result<String, PulsarSchemaDecodeError> {
+        let schema_version = parse_schema_version(schema_version)?;
+        let topic_path = topic_path(topic)?;
+        let schema_key = (topic_path, schema_version);
+
+        if !self.schemas.contains_key(&schema_key) {
+            let schema = self.fetch_schema(&schema_key.0, schema_version).await?;
+            self.schemas.insert(schema_key.clone(), schema);
+        }
+        match &self.schemas[&schema_key] {
+            TopicSchema::Avro(schema) => decode_datum(schema, payload)
+                .map_err(|error| PulsarSchemaDecodeError::InvalidMessage(error.to_string())),
+            TopicSchema::Json | TopicSchema::Primitive => String::from_utf8(payload.to_vec())
+                .map_err(|error| PulsarSchemaDecodeError::InvalidMessage(error.to_string())),
+            TopicSchema::Unsupported(schema_type) => Err(PulsarSchemaDecodeError::InvalidMessage(
+                format!("Schema type `{schema_type}` is not supported."),
+            )),
+        }
+    }
+
+    async fn fetch_schema(
+        &self,
+        topic_path: &str,
+        schema_version: i64,
+    ) -> Result<TopicSchema, PulsarSchemaDecodeError> {
+        let schema_registry_unavailable =
+            |message: String| PulsarSchemaDecodeError::SchemaRegistryUnavailable {
+                topic: topic_path.to_string(),
+                schema_version,
+                message,
+            };
+        let url = format!(
+            "{}/admin/v2/schemas/{topic_path}/schema/{schema_version}",
+            self.admin_url.trim_end_matches('/')
+        );
+        let mut request = self.http_client.get(url);
+
+        if let Some(auth_token) = &self.auth_token_opt {
+            request = request.bearer_auth(auth_token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(PulsarSchemaDecodeError::InvalidMessage(format!(
+                "Version `{schema_version}` of the schema of topic `{topic_path}` does not exist."
+            )));
+        }
+        let schema_response: GetSchemaResponse = response
+            .error_for_status()
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?
+            .json()
+            .await
+            .map_err(|error| schema_registry_unavailable(error.to_string()))?;
+        parse_topic_schema(schema_response)
+    }
+}
+
+fn parse_topic_schema(
+    schema_response: GetSchemaResponse,
+) -> Result<TopicSchema, PulsarSchemaDecodeError> {
+    let topic_schema = match schema_response.schema_type.as_str() {
+        "AVRO" => {
+            let schema = Schema::parse_str(&schema_response.data).map_err(|error| {
+                PulsarSchemaDecodeError::InvalidMessage(format!("Avro schema is invalid: {error}"))
+            })?;
+            TopicSchema::Avro(schema)
+        }
+        "JSON" => TopicSchema::Json,
+        "NONE" | "STRING" | "BYTES" => TopicSchema::Primitive,
+        schema_type => TopicSchema::Unsupported(schema_type.to_string()),
+    };
+    Ok(topic_schema)
+}
+
+/// Parses the schema version attached to a message, a big-endian encoded 64-bit integer.
+fn parse_schema_version(schema_version: &[u8]) -> Result<i64, PulsarSchemaDecodeError> {
+    let schema_version_bytes: [u8; 8] = schema_version.try_into().map_err(|_| {
+        PulsarSchemaDecodeError::InvalidMessage(format!(
+            "Schema version `{schema_version:?}` is invalid."
+        ))
+    })?;
+    Ok(i64::from_be_bytes(schema_version_bytes))
+}
+
+/// Returns the `{tenant}/{namespace}/{topic}` path of a topic in the admin REST API. The partition
+/// suffix of the topics of partitioned topics is stripped since their partitions share the same
+/// schema.
+fn topic_path(topic: &str) -> Result<String, PulsarSchemaDecodeError> {
+    let topic_name = topic
+        .strip_prefix("persistent://")
+        .or_else(|| topic.strip_prefix("non-persistent://"))
+        .unwrap_or(topic);
+    let topic_name = match topic_name.rsplit_once("-partition-") {
+        Some((partitioned_topic_name, partition))
+            if partition.chars().all(|char| char.is_ascii_digit()) =>
+        {
+            partitioned_topic_name
+        }
+        _ => topic_name,
+    };
+    match topic_name.split('/').count() {
+        1 => Ok(format!("{DEFAULT_TENANT}/{DEFAULT_NAMESPACE}/{topic_name}")),
+        3 => Ok(topic_name.to_string()),
+        _ => Err(PulsarSchemaDecodeError::InvalidMessage(format!(
+            "Topic name `{topic}` is invalid."
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apache_avro::to_avro_datum;
+    use apache_avro::types::Record;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "event",
+        "fields": [
+            {"name": "body", "type": "string"},
+            {"name": "count", "type": "long"}
+        ]
+    }"#;
+
+    fn pulsar_schema_decoder_for_test() -> PulsarSchemaDecoder {
+        // Nothing listens on this port: the test schemas are registered beforehand.
+        PulsarSchemaDecoder::new("http://localhost:1".to_string(), None)
+    }
+
+    #[test]
+    fn test_topic_path() {
+        assert_eq!(topic_path("my-topic").unwrap(), "public/default/my-topic");
+        assert_eq!(
+            topic_path("persistent://my-tenant/my-namespace/my-topic").unwrap(),
+            "my-tenant/my-namespace/my-topic"
+        );
+        assert_eq!(
+            topic_path("persistent://my-tenant/my-namespace/my-topic-partition-3").unwrap(),
+            "my-tenant/my-namespace/my-topic"
+        );
+        assert_eq!(
+            topic_path("non-persistent://public/default/my-topic-partition-x").unwrap(),
+            "public/default/my-topic-partition-x"
+        );
+        topic_path("persistent://my-namespace/my-topic").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse_schema_version() {
+        assert_eq!(parse_schema_version(&[0, 0, 0, 0, 0, 0, 0, 0]).unwrap(), 0);
+        assert_eq!(
+            parse_schema_version(&[0, 0, 0, 0, 0, 0, 1, 2]).unwrap(),
+            258
+        );
+        parse_schema_version(&[1, 2]).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_pulsar_schema_decoder() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut decoder = pulsar_schema_decoder_for_test();
+        decoder.schemas.insert(
+            ("public/default/avro-topic".to_string(), 1),
+            TopicSchema::Avro(schema.clone()),
+        );
+        decoder.schemas.insert(
+            ("public/default/json-topic".to_string(), 0),
+            TopicSchema::Json,
+        );
+        decoder.schemas.insert(
+            ("public/default/protobuf-topic".to_string(), 0),
+            TopicSchema::Unsupported("PROTOBUF".to_string()),
+        );
+        let mut record = Record::new(&schema).unwrap();
+        record.put("body", "hello");
+        record.put("count", 3i64);
+        let payload = to_avro_datum(&schema, record).unwrap();
+
+        let doc = decoder
+            .decode(
+                "persistent://public/default/avro-topic-partition-0",
+                &1i64.to_be_bytes(),
+                &payload,
+            )
+            .await
+            .unwrap();
+        let doc_json: JsonValue = serde_json::from_str(&doc).unwrap();
+        assert_eq!(doc_json, serde_json::json!({"body": "hello", "count": 3}));
+
+        let doc = decoder
+            .decode("json-topic", &0i64.to_be_bytes(), br#"{"body": "hello"}"#)
+            .await
+            .unwrap();
+        assert_eq!(doc, r#"{"body": "hello"}"#);
+
+        let error = decoder
+            .decode("protobuf-topic", &0i64.to_be_bytes(), b"")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PulsarSchemaDecodeError::InvalidMessage(_)));
+
+        let error = decoder
+            .decode("json-topic", &1i64.to_be_bytes(), b"")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            PulsarSchemaDecodeError::SchemaRegistryUnavailable {
+                schema_version: 1,
+                ..
+            }
+        ));
+    }
+}
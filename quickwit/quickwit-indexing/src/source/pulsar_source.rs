@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -31,7 +31,7 @@ use pulsar::{
     Authentication, Consumer, DeserializeMessage, Payload, Pulsar, SubType, TokioExecutor,
 };
 use quickwit_actors::{ActorContext, ActorExitStatus, Mailbox};
-use quickwit_config::{PulsarSourceAuth, PulsarSourceParams};
+use quickwit_config::{PulsarSourceAuth, PulsarSourceParams, PulsarSubscriptionType};
 use quickwit_metastore::checkpoint::{
     PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
 };
@@ -42,6 +42,8 @@ use tracing::{debug, info, warn};
 
 use crate::actors::DocProcessor;
 use crate::models::RawDocBatch;
+use crate::source::protobuf_decoder::ProtobufDecoder;
+use crate::source::pulsar_schema_decoder::{PulsarSchemaDecodeError, PulsarSchemaDecoder};
 use crate::source::{
     Source, SourceActor, SourceContext, SourceExecutionContext, TypedSourceFactory,
 };
@@ -90,13 +92,33 @@ pub struct PulsarSourceState {
     pub num_skipped_messages: u64,
 }
 
+/// Consumption state of a `Key_Shared` subscription.
+///
+/// The consumers of a `Key_Shared` subscription receive interleaved subsets of the messages of
+/// each topic partition, so their message IDs cannot be used as checkpoint positions. Instead,
+/// each consumer checkpoints the number of messages it consumed under its own partition, and
+/// acknowledges the messages individually once they are covered by a published checkpoint. The
+/// messages that were not acknowledged before a restart are redelivered by Pulsar, which makes the
+/// delivery at-least-once.
+struct KeySharedState {
+    partition_id: PartitionId,
+    num_messages: u64,
+    /// Messages awaiting acknowledgement, along with the number of messages consumed when they
+    /// were received. The mutex is only locked by `suggest_truncate`, which borrows the source
+    /// immutably.
+    pending_acks: Mutex<VecDeque<(u64, String, MessageIdData)>>,
+}
+
 pub struct PulsarSource {
     ctx: Arc<SourceExecutionContext>,
     pulsar_consumer: PulsarConsumer,
     params: PulsarSourceParams,
     subscription_name: String,
     current_positions: BTreeMap<PartitionId, Position>,
+    key_shared_state_opt: Option<KeySharedState>,
     state: PulsarSourceState,
+    protobuf_decoder_opt: Option<ProtobufDecoder>,
+    schema_decoder_opt: Option<PulsarSchemaDecoder>,
 }
 
 impl PulsarSource {
@@ -114,13 +136,51 @@ impl PulsarSource {
             "Create Pulsar source."
         );
 
+        let protobuf_decoder_opt = if let Some(protobuf_options) = &params.protobuf {
+            Some(ProtobufDecoder::load(protobuf_options).await?)
+        } else {
+            None
+        };
+        let schema_decoder_opt = params.admin_url.clone().map(|admin_url| {
+            let auth_token_opt = match &params.authentication {
+                Some(PulsarSourceAuth::Token(token)) => Some(token.clone()),
+                _ => None,
+            };
+            PulsarSchemaDecoder::new(admin_url, auth_token_opt)
+        });
+        let key_shared_state_opt = if params.subscription_type == PulsarSubscriptionType::KeyShared
+        {
+            let consumer_name =
+                key_shared_consumer_name(&params.consumer_name, &ctx.node_id, ctx.pipeline_ord);
+            let partition_id = PartitionId::from(consumer_name);
+            let num_messages = checkpoint
+                .position_for_partition(&partition_id)
+                .map(|position| position.as_str().parse::<u64>())
+                .transpose()
+                .context("Failed to parse `Key_Shared` checkpoint position.")?
+                .unwrap_or_default();
+            Some(KeySharedState {
+                partition_id,
+                num_messages,
+                pending_acks: Mutex::new(VecDeque::new()),
+            })
+        } else {
+            None
+        };
         let pulsar = connect_pulsar(&params).await?;
 
         // Current positions are built mapping the topic ID to the last-saved
         // message ID, pulsar ensures these topics (and topic partitions) are
         // unique so that we don't inadvertently clash.
+        // `Key_Shared` consumers do not seek: Pulsar redelivers the messages
+        // they did not acknowledge.
         let mut current_positions = BTreeMap::new();
-        for topic in params.topics.iter() {
+        let topics = if key_shared_state_opt.is_none() {
+            params.topics.as_slice()
+        } else {
+            &[]
+        };
+        for topic in topics {
             let partitions = pulsar.lookup_partitioned_topic(topic).await?;
 
             for (partition, _) in partitions {
@@ -133,8 +193,13 @@ impl PulsarSource {
             }
         }
 
+        let consumer_name = match &key_shared_state_opt {
+            Some(key_shared_state) => key_shared_state.partition_id.0.to_string(),
+            None => params.consumer_name.clone(),
+        };
         let pulsar_consumer = create_pulsar_consumer(
             subscription_name.clone(),
+            consumer_name,
             params.clone(),
             pulsar,
             current_positions.clone(),
@@ -147,29 +212,101 @@ impl PulsarSource {
             pulsar_consumer,
             subscription_name,
             current_positions,
+            key_shared_state_opt,
             state: PulsarSourceState::default(),
+            protobuf_decoder_opt,
+            schema_decoder_opt,
         })
     }
 
-    fn process_message(
+    async fn process_message(
         &mut self,
         message: Message<PulsarMessage>,
         batch: &mut BatchBuilder,
     ) -> anyhow::Result<()> {
-        let current_position = msg_id_to_position(message.message_id());
-
-        let doc = match message.deserialize() {
-            Err(e) => {
-                warn!(error = ?e, "Failed to parse message from queue.");
+        let schema_version_opt = message.payload.metadata.schema_version.clone();
+        let payload = message.deserialize();
+        let doc_res = if let Some(protobuf_decoder) = &self.protobuf_decoder_opt {
+            protobuf_decoder.decode(&payload)
+        } else if let (Some(schema_decoder), Some(schema_version)) =
+            (self.schema_decoder_opt.as_mut(), schema_version_opt)
+        {
+            match schema_decoder
+                .decode(&message.topic, &schema_version, &payload)
+                .await
+            {
+                Ok(doc) => Ok(doc),
+                Err(PulsarSchemaDecodeError::InvalidMessage(error)) => Err(error),
+                // The message is not acknowledged: the source fails and will consume it again
+                // after restarting.
+                Err(error) => return Err(error.into()),
+            }
+        } else {
+            String::from_utf8(payload).map_err(|error| error.to_string())
+        };
+        let doc = match doc_res {
+            Err(error) => {
+                warn!(error = %error, "Failed to parse message from queue.");
+                crate::metrics::INDEXER_METRICS
+                    .source_decode_errors_total
+                    .with_label_values([
+                        self.ctx.index_id.as_str(),
+                        self.ctx.source_config.source_id.as_str(),
+                    ])
+                    .inc();
                 self.state.num_invalid_messages += 1;
+                self.defer_ack(&message);
                 return Ok(());
             }
             Ok(doc) => doc,
         };
 
+        if let Some(key_shared_state) = self.key_shared_state_opt.as_mut() {
+            if doc.is_empty() {
+                warn!("Message received from queue was empty.");
+                self.state.num_invalid_messages += 1;
+                self.defer_ack(&message);
+                return Ok(());
+            }
+            let num_bytes = doc.as_bytes().len();
+            let previous_position = if key_shared_state.num_messages == 0 {
+                Position::Beginning
+            } else {
+                Position::from(key_shared_state.num_messages)
+            };
+            key_shared_state.num_messages += 1;
+            let current_position = Position::from(key_shared_state.num_messages);
+            batch
+                .checkpoint_delta
+                .record_partition_delta(
+                    key_shared_state.partition_id.clone(),
+                    previous_position,
+                    current_position,
+                )
+                .context("Failed to record partition delta.")?;
+            batch.push(doc, num_bytes as u64);
+            self.defer_ack(&message);
+
+            self.state.num_bytes_processed += num_bytes as u64;
+            self.state.num_messages_processed += 1;
+            return Ok(());
+        }
+        let current_position = msg_id_to_position(message.message_id());
         self.add_doc_to_batch(&message.topic, current_position, doc, batch)
     }
 
+    /// Registers a message of a `Key_Shared` subscription for acknowledgement once the messages
+    /// consumed so far are covered by a published checkpoint.
+    fn defer_ack(&mut self, message: &Message<PulsarMessage>) {
+        if let Some(key_shared_state) = self.key_shared_state_opt.as_mut() {
+            key_shared_state.pending_acks.get_mut().push_back((
+                key_shared_state.num_messages,
+                message.topic.clone(),
+                message.message_id().clone(),
+            ));
+        }
+    }
+
     fn add_doc_to_batch(
         &mut self,
         topic: &str,
@@ -217,6 +354,26 @@ impl PulsarSource {
     async fn try_ack_messages(&self, checkpoint: SourceCheckpoint) -> anyhow::Result<()> {
         debug!(ckpt = ?checkpoint, "Truncating message queue.");
         let mut consumer = self.pulsar_consumer.lock().await;
+
+        if let Some(key_shared_state) = &self.key_shared_state_opt {
+            // `Key_Shared` subscriptions do not support cumulative acknowledgements.
+            let num_checkpointed_messages = checkpoint
+                .position_for_partition(&key_shared_state.partition_id)
+                .and_then(|position| position.as_str().parse::<u64>().ok())
+                .unwrap_or_default();
+            let mut pending_acks = key_shared_state.pending_acks.lock().await;
+
+            while let Some((num_messages, _, _)) = pending_acks.front() {
+                if *num_messages > num_checkpointed_messages {
+                    break;
+                }
+                let (_, topic, msg_id) = pending_acks
+                    .pop_front()
+                    .expect("The queue should not be empty.");
+                consumer.ack_with_id(&topic, msg_id).await?;
+            }
+            return Ok(());
+        }
         for (partition, position) in checkpoint.iter() {
             if let Some(msg_id) = msg_id_from_position(&position) {
                 consumer
@@ -250,7 +407,7 @@ impl Source for PulsarSource {
                         .ok_or_else(|| ActorExitStatus::from(anyhow!("Consumer was dropped.")))?
                         .map_err(|e| ActorExitStatus::from(anyhow!("Failed to get message from consumer: {:?}", e)))?;
 
-                    self.process_message(message, &mut batch).map_err(ActorExitStatus::from)?;
+                    self.process_message(message, &mut batch).await.map_err(ActorExitStatus::from)?;
 
                     if batch.num_bytes >= BATCH_NUM_BYTES_LIMIT {
                         break;
@@ -298,6 +455,7 @@ impl Source for PulsarSource {
             "topics": self.params.topics,
             "subscription_name": self.subscription_name,
             "consumer_name": self.params.consumer_name,
+            "subscription_type": self.params.subscription_type,
             "num_bytes_processed": self.state.num_bytes_processed,
             "num_messages_processed": self.state.num_messages_processed,
             "num_invalid_messages": self.state.num_invalid_messages,
@@ -309,10 +467,11 @@ impl Source for PulsarSource {
 struct PulsarMessage;
 
 impl DeserializeMessage for PulsarMessage {
-    type Output = anyhow::Result<String>;
+    // Payloads are decoded by the source, which may hold a Protobuf decoder.
+    type Output = Vec<u8>;
 
     fn deserialize_message(payload: &Payload) -> Self::Output {
-        String::from_utf8(payload.data.clone()).map_err(anyhow::Error::from)
+        payload.data.clone()
     }
 }
 
@@ -341,16 +500,21 @@ impl BatchBuilder {
 /// Creates a new pulsar consumer
 async fn create_pulsar_consumer(
     subscription_name: String,
+    consumer_name: String,
     params: PulsarSourceParams,
     pulsar: Pulsar<TokioExecutor>,
     current_positions: BTreeMap<PartitionId, Position>,
 ) -> anyhow::Result<PulsarConsumer> {
+    let subscription_type = match params.subscription_type {
+        PulsarSubscriptionType::Failover => SubType::Failover,
+        PulsarSubscriptionType::KeyShared => SubType::KeyShared,
+    };
     let mut consumer: Consumer<PulsarMessage, _> = pulsar
         .consumer()
         .with_topics(&params.topics)
-        .with_consumer_name(&params.consumer_name)
+        .with_consumer_name(consumer_name)
         .with_subscription(subscription_name)
-        .with_subscription_type(SubType::Failover)
+        .with_subscription_type(subscription_type)
         .build()
         .await?;
 
@@ -464,6 +628,12 @@ fn subscription_name(index_id: &str, source_id: &str) -> String {
     format!("quickwit-{index_id}-{source_id}")
 }
 
+/// Returns the name of the consumer of a `Key_Shared` subscription, which is unique for each
+/// pipeline of the source.
+fn key_shared_consumer_name(consumer_name: &str, node_id: &str, pipeline_ord: usize) -> String {
+    format!("{consumer_name}-{node_id}-{pipeline_ord}")
+}
+
 #[cfg(all(test, feature = "pulsar-broker-tests"))]
 mod pulsar_broker_tests {
     use std::collections::HashSet;
@@ -548,6 +718,7 @@ mod pulsar_broker_tests {
         let index_delta = IndexCheckpointDelta {
             source_id: source_id.to_string(),
             source_delta,
+            publish_token: None,
         };
         metastore
             .publish_splits(index_id, &[&split_id], &[], Some(index_delta))
@@ -569,6 +740,9 @@ mod pulsar_broker_tests {
                 address: PULSAR_URI.to_string(),
                 consumer_name: CLIENT_NAME.to_string(),
                 authentication: None,
+                protobuf: None,
+                subscription_type: PulsarSubscriptionType::Failover,
+                admin_url: None,
             }),
             transform_config: None,
         };
@@ -27,13 +27,14 @@ use anyhow::Context;
 #[cfg(any(test, feature = "testsuite"))]
 use byte_unit::Byte;
 use quickwit_common::io::{IoControls, IoControlsAccess};
+use quickwit_config::SplitStorageLayout;
 use quickwit_metastore::SplitMetadata;
 use quickwit_storage::{PutPayload, Storage, StorageResult};
 use tantivy::directory::MmapDirectory;
 use tantivy::Directory;
 use tracing::{info, info_span, instrument, Instrument};
 
-use super::LocalSplitStore;
+use super::{LocalSplitStore, UploadJournal};
 use crate::merge_policy::NopMergePolicy;
 use crate::{get_tantivy_directory_from_split_bundle, MergePolicy};
 
@@ -71,6 +72,12 @@ struct InnerIndexingSplitStore {
     /// should be stored in the local storage or not.
     /// (mature splits do not need to be stored).
     merge_policy: Arc<dyn MergePolicy>,
+
+    /// Journal of the splits staged but not published yet, see [`UploadJournal`].
+    upload_journal_opt: Option<UploadJournal>,
+
+    /// Naming scheme of the split files in the remote storage.
+    split_storage_layout: SplitStorageLayout,
 }
 
 pub struct WeakIndexingSplitStore {
@@ -98,12 +105,47 @@ impl IndexingSplitStore {
             remote_storage,
             local_split_store,
             merge_policy,
+            upload_journal_opt: None,
+            split_storage_layout: SplitStorageLayout::default(),
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Makes the uploaders of the split store journal the splits they stage, so that their
+    /// uploads can be recovered after a crash of the node.
+    pub fn with_upload_journal(self, upload_journal: UploadJournal) -> Self {
+        let inner = InnerIndexingSplitStore {
+            remote_storage: self.inner.remote_storage.clone(),
+            local_split_store: self.inner.local_split_store.clone(),
+            merge_policy: self.inner.merge_policy.clone(),
+            upload_journal_opt: Some(upload_journal),
+            split_storage_layout: self.inner.split_storage_layout,
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Makes the split store name the split files it uploads according to the given layout.
+    pub fn with_split_storage_layout(self, split_storage_layout: SplitStorageLayout) -> Self {
+        let inner = InnerIndexingSplitStore {
+            remote_storage: self.inner.remote_storage.clone(),
+            local_split_store: self.inner.local_split_store.clone(),
+            merge_policy: self.inner.merge_policy.clone(),
+            upload_journal_opt: self.inner.upload_journal_opt.clone(),
+            split_storage_layout,
         };
         Self {
             inner: Arc::new(inner),
         }
     }
 
+    pub(crate) fn upload_journal_opt(&self) -> Option<&UploadJournal> {
+        self.inner.upload_journal_opt.as_ref()
+    }
+
     /// Helper function to create a indexing split store for tests.
     /// The resulting store does not have any local cache.
     pub fn create_without_local_store(remote_storage: Arc<dyn Storage>) -> Self {
@@ -111,12 +153,33 @@ impl IndexingSplitStore {
             remote_storage,
             local_split_store: Arc::new(LocalSplitStore::no_caching()),
             merge_policy: Arc::new(NopMergePolicy),
+            upload_journal_opt: None,
+            split_storage_layout: SplitStorageLayout::default(),
         };
         IndexingSplitStore {
             inner: Arc::new(inner),
         }
     }
 
+    /// Returns the hash of the split payload when the split files are named after their content,
+    /// or `None` when they are named after their split ID. The hash must be recorded in the
+    /// split metadata before the split is staged.
+    pub async fn content_hash(
+        &self,
+        put_payload: &dyn PutPayload,
+    ) -> anyhow::Result<Option<String>> {
+        match self.inner.split_storage_layout {
+            SplitStorageLayout::SplitId => Ok(None),
+            SplitStorageLayout::ContentAddressed => {
+                let content_hash = put_payload
+                    .content_hash()
+                    .await
+                    .context("Failed to hash split payload.")?;
+                Ok(Some(content_hash))
+            }
+        }
+    }
+
     /// Stores a split.
     ///
     /// If a split is identified as mature by the merge policy,
@@ -136,7 +199,7 @@ impl IndexingSplitStore {
         let start = Instant::now();
         let split_num_bytes = put_payload.len();
 
-        let key = PathBuf::from(quickwit_common::split_file(split.split_id()));
+        let key = PathBuf::from(split.split_file());
         let is_mature = self.inner.merge_policy.is_mature(split);
         self.inner
             .remote_storage
@@ -196,14 +259,15 @@ impl IndexingSplitStore {
     ///
     /// As we fetch the split, we optimistically assume that this is for a merge
     /// operation that will be successful and we remove the split from the cache.
-    #[instrument(skip(self, output_dir_path, io_controls), fields(cache_hit))]
+    #[instrument(skip_all, fields(split_id = %split.split_id(), cache_hit))]
     pub async fn fetch_and_open_split(
         &self,
-        split_id: &str,
+        split: &SplitMetadata,
         output_dir_path: &Path,
         io_controls: &IoControls,
     ) -> StorageResult<Box<dyn Directory>> {
-        let path = PathBuf::from(quickwit_common::split_file(split_id));
+        let split_id = split.split_id();
+        let path = PathBuf::from(split.split_file());
         if let Some(split_path) = self
             .inner
             .local_split_store
@@ -216,7 +280,7 @@ impl IndexingSplitStore {
         } else {
             tracing::Span::current().record("cache_hit", false);
         }
-        let dest_filepath = output_dir_path.join(&path);
+        let dest_filepath = output_dir_path.join(quickwit_common::split_file(split_id));
         let dest_file = tokio::fs::File::create(&dest_filepath).await?;
         let mut dest_file_with_write_limit = io_controls.clone().wrap_write(dest_file);
         self.inner
@@ -242,12 +306,14 @@ impl IndexingSplitStore {
 
 #[cfg(test)]
 mod tests {
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
 
     use byte_unit::Byte;
     use quickwit_common::io::IoControls;
+    use quickwit_config::SplitStorageLayout;
     use quickwit_metastore::SplitMetadata;
-    use quickwit_storage::{RamStorage, SplitPayloadBuilder};
+    use quickwit_storage::{PutPayload, RamStorage, SplitPayloadBuilder, Storage};
     use tempfile::tempdir;
     use time::OffsetDateTime;
     use tokio::fs;
@@ -409,13 +475,69 @@ mod tests {
             let io_controls = IoControls::default();
             // get from cache
             let _split1 = split_store
-                .fetch_and_open_split(&split_id1, output.path(), &io_controls)
+                .fetch_and_open_split(
+                    &create_test_split_metadata(&split_id1),
+                    output.path(),
+                    &io_controls,
+                )
                 .await?;
             // get from remote storage
             let _split2 = split_store
-                .fetch_and_open_split(&split_id2, output.path(), &io_controls)
+                .fetch_and_open_split(
+                    &create_test_split_metadata(&split_id2),
+                    output.path(),
+                    &io_controls,
+                )
                 .await?;
         }
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_store_split_content_addressed() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let remote_storage = Arc::new(RamStorage::default());
+        let split_store = IndexingSplitStore::create_without_local_store(remote_storage.clone())
+            .with_split_storage_layout(SplitStorageLayout::ContentAddressed);
+
+        let split_id = Ulid::new().to_string();
+        let split_path = temp_dir.path().join(&split_id);
+        fs::create_dir_all(&split_path).await?;
+        let split_payload = SplitPayloadBuilder::get_split_payload(&[], &[5, 5, 5])?;
+
+        let mut split_metadata = create_test_split_metadata(&split_id);
+        split_metadata.content_hash = split_store.content_hash(&split_payload).await?;
+        let content_hash = split_metadata.content_hash.clone().unwrap();
+        assert_eq!(content_hash, split_payload.content_hash().await?);
+
+        split_store
+            .store_split(&split_metadata, &split_path, Box::new(split_payload))
+            .await?;
+        let split_file_path = PathBuf::from(format!("splits/{content_hash}.split"));
+        assert!(remote_storage.exists(&split_file_path).await?);
+        assert!(
+            !remote_storage
+                .exists(Path::new(&format!("{split_id}.split")))
+                .await?
+        );
+
+        let output = tempfile::tempdir()?;
+        split_store
+            .fetch_and_open_split(&split_metadata, output.path(), &IoControls::default())
+            .await?;
+        assert!(output
+            .path()
+            .join(format!("{split_id}.split"))
+            .try_exists()?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_content_hash_split_id_layout() -> anyhow::Result<()> {
+        let split_store =
+            IndexingSplitStore::create_without_local_store(Arc::new(RamStorage::default()));
+        let split_payload = SplitPayloadBuilder::get_split_payload(&[], &[5, 5, 5])?;
+        assert!(split_store.content_hash(&split_payload).await?.is_none());
+        Ok(())
+    }
 }
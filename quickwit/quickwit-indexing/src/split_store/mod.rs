@@ -20,7 +20,11 @@
 mod indexing_split_store;
 mod local_split_store;
 mod split_store_quota;
+mod upload_journal;
 
 pub use indexing_split_store::{IndexingSplitStore, WeakIndexingSplitStore};
 pub use local_split_store::{get_tantivy_directory_from_split_bundle, LocalSplitStore};
 pub use split_store_quota::SplitStoreQuota;
+pub use upload_journal::{
+    UploadJournal, UploadJournalTicket, UploadRecoveryStats, UPLOAD_JOURNAL_DIR_NAME,
+};
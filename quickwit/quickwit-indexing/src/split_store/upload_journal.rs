@@ -0,0 +1,484 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+// The upload journal keeps track of the splits that were staged in the metastore but not
+// published yet, so that the uploads interrupted by a crash of the node can be resumed or aborted
+// when the node restarts.
+//
+// An entry is written for each batch of splits before the splits are staged, updated once all
+// the splits of the batch are uploaded, and removed once the splits are published. Upon restart:
+// - the batches fully uploaded are published, which saves the node from indexing them again. The
+// checkpoint delta of the batch guarantees that they are published only if nothing has been
+// published for the same partitions in the meantime.
+// - the batches partially uploaded are aborted: the incomplete multipart uploads are aborted to
+// release the storage used by their parts and the splits are marked for deletion.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::Context;
+use quickwit_common::ignore_error_kind;
+use quickwit_metastore::checkpoint::IndexCheckpointDelta;
+use quickwit_metastore::{Metastore, MetastoreError, SplitMetadata};
+use quickwit_storage::Storage;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{info, warn};
+
+/// Name of the directory holding the upload journal of an index source, usually located at
+/// `<data_dir_path>/indexing/<index_id>/<source_id>/uploads`.
+pub const UPLOAD_JOURNAL_DIR_NAME: &str = "uploads";
+
+const ENTRY_FILE_EXTENSION: &str = "json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UploadJournalEntry {
+    index_id: String,
+    splits: Vec<SplitMetadata>,
+    replaced_split_ids: Vec<String>,
+    /// The delta is journaled without publish token: the token of the pipeline that produced the
+    /// splits is gone after a restart.
+    checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    is_uploaded: bool,
+}
+
+impl UploadJournalEntry {
+    fn split_ids(&self) -> Vec<&str> {
+        self.splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect()
+    }
+}
+
+/// Outcome of the recovery of the uploads interrupted by a restart of the node.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct UploadRecoveryStats {
+    /// Number of batches of uploaded splits published during the recovery.
+    pub num_published_batches: usize,
+    /// Number of batches of splits aborted during the recovery.
+    pub num_aborted_batches: usize,
+}
+
+/// Journal of the uploads of an index source, see module docs.
+#[derive(Clone)]
+pub struct UploadJournal {
+    inner: Arc<InnerUploadJournal>,
+}
+
+struct InnerUploadJournal {
+    dir_path: PathBuf,
+    /// The entries are named after an increasing ordinal so that the recovery publishes the
+    /// batches in the order they were produced.
+    next_entry_ord: AtomicU64,
+}
+
+impl UploadJournal {
+    /// Opens the journal located in `dir_path`, creating the directory if it does not exist.
+    pub async fn open(dir_path: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir_path).await.with_context(|| {
+            format!(
+                "Failed to create upload journal directory `{}`.",
+                dir_path.display()
+            )
+        })?;
+        let next_entry_ord = list_entry_ords(&dir_path)
+            .await?
+            .last()
+            .map(|entry_ord| entry_ord + 1)
+            .unwrap_or(0);
+        let inner = InnerUploadJournal {
+            dir_path,
+            next_entry_ord: AtomicU64::new(next_entry_ord),
+        };
+        Ok(UploadJournal {
+            inner: Arc::new(inner),
+        })
+    }
+
+    fn entry_path(&self, entry_ord: u64) -> PathBuf {
+        self.inner
+            .dir_path
+            .join(format!("{entry_ord:020}.{ENTRY_FILE_EXTENSION}"))
+    }
+
+    /// Records a batch of splits about to be staged and uploaded.
+    pub(crate) async fn record_staged_splits(
+        &self,
+        index_id: &str,
+        splits: &[SplitMetadata],
+        replaced_split_ids: Vec<String>,
+        checkpoint_delta_opt: Option<IndexCheckpointDelta>,
+    ) -> anyhow::Result<UploadJournalTicket> {
+        let entry_ord = self.inner.next_entry_ord.fetch_add(1, Ordering::SeqCst);
+        let entry = UploadJournalEntry {
+            index_id: index_id.to_string(),
+            splits: splits.to_vec(),
+            replaced_split_ids,
+            checkpoint_delta_opt: checkpoint_delta_opt.map(|checkpoint_delta| {
+                IndexCheckpointDelta {
+                    publish_token: None,
+                    ..checkpoint_delta
+                }
+            }),
+            is_uploaded: false,
+        };
+        let ticket = UploadJournalTicket {
+            entry_path: self.entry_path(entry_ord),
+            entry,
+        };
+        ticket.write_entry().await?;
+        Ok(ticket)
+    }
+
+    /// Resumes or aborts the uploads recorded in the journal, see module docs. It must be called
+    /// before any pipeline of the index source starts uploading splits.
+    ///
+    /// The entries that cannot be recovered because of a transient metastore or storage error
+    /// are kept for the next recovery.
+    pub async fn recover(
+        &self,
+        metastore: &dyn Metastore,
+        storage: &dyn Storage,
+    ) -> anyhow::Result<UploadRecoveryStats> {
+        let mut recovery_stats = UploadRecoveryStats::default();
+
+        for entry_ord in list_entry_ords(&self.inner.dir_path).await? {
+            let entry_path = self.entry_path(entry_ord);
+            let entry_json = fs::read(&entry_path).await?;
+            let entry: UploadJournalEntry = match serde_json::from_slice(&entry_json) {
+                Ok(entry) => entry,
+                Err(error) => {
+                    // The node crashed while writing the entry, before staging the splits.
+                    warn!(
+                        entry_path=%entry_path.display(),
+                        error=?error,
+                        "Failed to deserialize upload journal entry.",
+                    );
+                    remove_entry(&entry_path).await?;
+                    continue;
+                }
+            };
+            let is_recovered = if entry.is_uploaded {
+                resume_upload(&entry, metastore, &mut recovery_stats).await
+            } else {
+                abort_upload(&entry, metastore, Some(storage), &mut recovery_stats).await
+            };
+            if is_recovered {
+                remove_entry(&entry_path).await?;
+            }
+        }
+        if recovery_stats != UploadRecoveryStats::default() {
+            info!(
+                upload_journal=%self.inner.dir_path.display(),
+                num_published_batches=recovery_stats.num_published_batches,
+                num_aborted_batches=recovery_stats.num_aborted_batches,
+                "Recovered interrupted uploads."
+            );
+        }
+        Ok(recovery_stats)
+    }
+}
+
+/// Handle over a journal entry, passed along the splits from the uploader to the publisher.
+pub struct UploadJournalTicket {
+    entry_path: PathBuf,
+    entry: UploadJournalEntry,
+}
+
+impl UploadJournalTicket {
+    async fn write_entry(&self) -> anyhow::Result<()> {
+        let entry_json = serde_json::to_vec(&self.entry)?;
+        // The entry is written to a temporary file first so that a crash never leaves a
+        // truncated entry behind.
+        let temp_entry_path = self.entry_path.with_extension("tmp");
+        fs::write(&temp_entry_path, entry_json)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to write upload journal entry `{}`.",
+                    temp_entry_path.display()
+                )
+            })?;
+        fs::rename(&temp_entry_path, &self.entry_path).await?;
+        Ok(())
+    }
+
+    /// Records that all the splits of the batch are uploaded.
+    pub(crate) async fn record_uploaded(&mut self) -> anyhow::Result<()> {
+        self.entry.is_uploaded = true;
+        self.write_entry().await
+    }
+
+    /// Removes the entry from the journal once the splits are published.
+    pub(crate) async fn remove(self) -> anyhow::Result<()> {
+        remove_entry(&self.entry_path).await
+    }
+}
+
+async fn resume_upload(
+    entry: &UploadJournalEntry,
+    metastore: &dyn Metastore,
+    recovery_stats: &mut UploadRecoveryStats,
+) -> bool {
+    let split_ids = entry.split_ids();
+    let replaced_split_ids: Vec<&str> = entry
+        .replaced_split_ids
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let publish_res = metastore
+        .publish_splits(
+            &entry.index_id,
+            &split_ids,
+            &replaced_split_ids,
+            entry.checkpoint_delta_opt.clone(),
+        )
+        .await;
+    match publish_res {
+        Ok(()) => {
+            info!(index_id=%entry.index_id, split_ids=?split_ids, "Published uploaded splits.");
+            recovery_stats.num_published_batches += 1;
+            true
+        }
+        // The splits were published before the node crashed, or garbage collected.
+        Err(MetastoreError::SplitsNotStaged { .. })
+        | Err(MetastoreError::IndexDoesNotExist { .. }) => true,
+        Err(error) if is_transient_error(&error) => {
+            warn!(
+                index_id=%entry.index_id,
+                split_ids=?split_ids,
+                error=?error,
+                "Failed to publish uploaded splits.",
+            );
+            false
+        }
+        Err(error) => {
+            // The checkpoint delta conflicts with splits published in the meantime, or the
+            // merged splits are gone.
+            info!(
+                index_id=%entry.index_id,
+                split_ids=?split_ids,
+                error=?error,
+                "Aborting uploaded splits that cannot be published.",
+            );
+            abort_upload(entry, metastore, None, recovery_stats).await
+        }
+    }
+}
+
+/// Aborts the splits of a journal entry. The incomplete uploads of the splits are aborted if a
+/// storage is given, which is not needed for the splits fully uploaded.
+async fn abort_upload(
+    entry: &UploadJournalEntry,
+    metastore: &dyn Metastore,
+    storage_opt: Option<&dyn Storage>,
+    recovery_stats: &mut UploadRecoveryStats,
+) -> bool {
+    let split_ids = entry.split_ids();
+    if let Some(storage) = storage_opt {
+        for split in &entry.splits {
+            let split_file_path = PathBuf::from(split.split_file());
+            if let Err(error) = storage.abort_incomplete_uploads(&split_file_path).await {
+                warn!(split_id=%split.split_id(), error=?error, "Failed to abort incomplete split upload.");
+                return false;
+            }
+        }
+    }
+    // The janitor deletes the files of the splits marked for deletion.
+    match metastore
+        .mark_splits_for_deletion(&entry.index_id, &split_ids)
+        .await
+    {
+        Err(error) if is_transient_error(&error) => {
+            warn!(
+                index_id=%entry.index_id,
+                split_ids=?split_ids,
+                error=?error,
+                "Failed to mark aborted splits for deletion.",
+            );
+            false
+        }
+        // The splits may have never been staged.
+        _ => {
+            info!(
+                index_id=%entry.index_id,
+                split_ids=?split_ids,
+                "Aborted interrupted split upload.",
+            );
+            recovery_stats.num_aborted_batches += 1;
+            true
+        }
+    }
+}
+
+fn is_transient_error(error: &MetastoreError) -> bool {
+    matches!(
+        error,
+        MetastoreError::ConnectionError { .. }
+            | MetastoreError::DbError { .. }
+            | MetastoreError::InternalError { .. }
+            | MetastoreError::Io { .. }
+    )
+}
+
+async fn remove_entry(entry_path: &Path) -> anyhow::Result<()> {
+    ignore_error_kind!(io::ErrorKind::NotFound, fs::remove_file(entry_path).await).with_context(
+        || {
+            format!(
+                "Failed to remove upload journal entry `{}`.",
+                entry_path.display()
+            )
+        },
+    )?;
+    Ok(())
+}
+
+/// Returns the ordinals of the entries of the journal in increasing order.
+async fn list_entry_ords(dir_path: &Path) -> anyhow::Result<Vec<u64>> {
+    let mut entry_ords = Vec::new();
+    let mut read_dir = fs::read_dir(dir_path).await?;
+    while let Some(dir_entry) = read_dir.next_entry().await? {
+        let path = dir_entry.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some(ENTRY_FILE_EXTENSION) {
+            continue;
+        }
+        if let Some(entry_ord) = path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str())
+            .and_then(|file_stem| file_stem.parse::<u64>().ok())
+        {
+            entry_ords.push(entry_ord);
+        }
+    }
+    entry_ords.sort_unstable();
+    Ok(entry_ords)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::checkpoint::{IncompatibleCheckpointDelta, PartitionId, Position};
+    use quickwit_metastore::MockMetastore;
+    use quickwit_storage::MockStorage;
+
+    use super::*;
+
+    fn split_for_test(split_id: &str) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: "test-index".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_journal_recover() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let journal_dir_path = temp_dir.path().join(UPLOAD_JOURNAL_DIR_NAME);
+        let upload_journal = UploadJournal::open(journal_dir_path.clone()).await.unwrap();
+
+        let checkpoint_delta = IndexCheckpointDelta::for_test("test-source", 0..10)
+            .with_publish_token("test-token".into());
+        for split_id in ["split-1", "split-2", "split-3", "split-4"] {
+            let mut ticket = upload_journal
+                .record_staged_splits(
+                    "test-index",
+                    &[split_for_test(split_id)],
+                    Vec::new(),
+                    Some(checkpoint_delta.clone()),
+                )
+                .await
+                .unwrap();
+            if split_id != "split-2" {
+                ticket.record_uploaded().await.unwrap();
+            }
+        }
+        let published_ticket = upload_journal
+            .record_staged_splits("test-index", &[split_for_test("split-5")], Vec::new(), None)
+            .await
+            .unwrap();
+        published_ticket.remove().await.unwrap();
+        assert_eq!(
+            list_entry_ords(&journal_dir_path).await.unwrap(),
+            [0, 1, 2, 3]
+        );
+
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_publish_splits()
+            .withf(|_, _, _, checkpoint_delta_opt| {
+                // The publish token is not journaled.
+                checkpoint_delta_opt
+                    .as_ref()
+                    .unwrap()
+                    .publish_token
+                    .is_none()
+            })
+            .times(3)
+            .returning(|_, split_ids, _, _| match split_ids[0] {
+                "split-1" => Ok(()),
+                "split-3" => Err(MetastoreError::IncompatibleCheckpointDelta(
+                    IncompatibleCheckpointDelta {
+                        partition_id: PartitionId::default(),
+                        current_position: Position::from(20u64),
+                        delta_position_from: Position::from(0u64),
+                    },
+                )),
+                _ => Err(MetastoreError::ConnectionError {
+                    message: "Connection refused.".to_string(),
+                }),
+            });
+        mock_metastore
+            .expect_mark_splits_for_deletion()
+            .withf(|index_id, split_ids| {
+                index_id == "test-index"
+                    && (split_ids[..] == ["split-2"] || split_ids[..] == ["split-3"])
+            })
+            .times(2)
+            .returning(|_, _| Ok(()));
+        let mut mock_storage = MockStorage::default();
+        mock_storage
+            .expect_abort_incomplete_uploads()
+            .withf(|path| path == Path::new("split-2.split"))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let recovery_stats = upload_journal
+            .recover(&mock_metastore, &mock_storage)
+            .await
+            .unwrap();
+        assert_eq!(
+            recovery_stats,
+            UploadRecoveryStats {
+                num_published_batches: 1,
+                num_aborted_batches: 2,
+            }
+        );
+        // The entry of `split-4` is kept for the next recovery.
+        assert_eq!(list_entry_ords(&journal_dir_path).await.unwrap(), [3]);
+
+        let upload_journal = UploadJournal::open(journal_dir_path).await.unwrap();
+        assert_eq!(
+            upload_journal.inner.next_entry_ord.load(Ordering::SeqCst),
+            4
+        );
+    }
+}
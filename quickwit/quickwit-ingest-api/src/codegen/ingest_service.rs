@@ -39,6 +39,20 @@ pub struct IngestRequest {
 pub struct IngestResponse {
     #[prost(uint64, tag = "1")]
     pub num_docs_for_processing: u64,
+    /// / Number of documents dropped because their batch was already committed.
+    #[prost(uint64, tag = "2")]
+    pub num_duplicate_docs: u64,
+    /// / Partition ID of the queues of the indexer, in which the documents were appended.
+    #[prost(string, tag = "3")]
+    pub partition_id: ::prost::alloc::string::String,
+    /// / Position in its queue of the last document of each batch, keyed by the index of the batch
+    /// / in the request. The batches dropped as duplicates are included if their position is known.
+    #[prost(map = "uint32, uint64", tag = "4")]
+    pub batch_last_positions: ::std::collections::HashMap<u32, u64>,
+    /// / Shard of the index in which each batch was appended, keyed by the index of the batch in
+    /// / the request. The batches appended to the first shard are omitted.
+    #[prost(map = "uint32, uint32", tag = "5")]
+    pub batch_shard_ords: ::std::collections::HashMap<u32, u32>,
 }
 #[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -70,6 +84,25 @@ pub struct DocBatch {
     pub concat_docs: ::prost::bytes::Bytes,
     #[prost(uint64, repeated, tag = "3")]
     pub doc_lens: ::prost::alloc::vec::Vec<u64>,
+    /// / Key supplied by the client to deduplicate the retries of the batch. A batch whose key was
+    /// / already committed to the same queue within the retention period is dropped.
+    #[prost(string, optional, tag = "4")]
+    pub idempotency_key: ::core::option::Option<::prost::alloc::string::String>,
+    /// / Position of the documents of a batch replicated to a peer indexer in the queue of their
+    /// / leader indexer.
+    #[prost(message, optional, tag = "5")]
+    pub leader_position: ::core::option::Option<LeaderPosition>,
+}
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LeaderPosition {
+    /// / Partition ID of the queues of the leader indexer.
+    #[prost(string, tag = "1")]
+    pub partition_id: ::prost::alloc::string::String,
+    /// / Position of the last document of the batch in the queue of the leader indexer.
+    #[prost(uint64, tag = "2")]
+    pub last_position: u64,
 }
 /// / Suggest to truncate the queue.
 /// /
@@ -145,6 +145,8 @@ impl DocBatchBuilder {
             index_id: self.index_id,
             concat_docs: self.concat_docs.freeze(),
             doc_lens: self.doc_lens,
+            idempotency_key: None,
+            leader_position: None,
         }
     }
 }
@@ -100,7 +100,7 @@ impl ServiceError for IngestServiceError {
             IngestServiceError::InvalidPosition(_) => ServiceErrorCode::BadRequest,
             IngestServiceError::IoError { .. } => ServiceErrorCode::Internal,
             IngestServiceError::RateLimited => ServiceErrorCode::RateLimited,
-            IngestServiceError::Unavailable => ServiceErrorCode::Internal,
+            IngestServiceError::Unavailable => ServiceErrorCode::Unavailable,
         }
     }
 }
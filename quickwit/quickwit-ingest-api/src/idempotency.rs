@@ -0,0 +1,246 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Clients may attach an idempotency key to their ingest batches, so that retrying a batch after
+//! a timeout does not ingest its documents twice. The keys of the committed batches are logged in
+//! an internal queue of the record log, right after the batch is appended to its queue, and are
+//! forgotten once their retention period expires. The position of the last record of the batch is
+//! logged along with its key, so that a retried batch is replicated at its original position.
+//!
+//! The keys are shared by the shards of an index: a retried batch is dropped even if it would be
+//! routed to another shard than the original batch.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use quickwit_actors::ActorContext;
+use tracing::warn;
+
+use crate::shard::parse_shard_queue_id;
+use crate::{IngestApiService, Queues};
+
+/// ID of the queue of the record log holding the committed idempotency keys. It lacks the prefix
+/// of the ingest queues, so it is never listed, fetched, or indexed.
+pub(crate) const IDEMPOTENCY_KEYS_QUEUE_ID: &str = ".idempotency_keys";
+
+struct CommittedKey {
+    commit_timestamp: u64,
+    position: u64,
+    queue_id: String,
+    idempotency_key: String,
+    batch_last_position: Option<u64>,
+}
+
+/// The idempotency keys committed within the retention period, per index.
+pub(crate) struct IdempotencyKeys {
+    retention: Duration,
+    /// The queue and the position of the last record of the committed batches, per index and key.
+    keys: HashMap<(String, String), (String, Option<u64>)>,
+    /// The committed keys in the order of their commit.
+    commits: VecDeque<CommittedKey>,
+}
+
+/// Returns the ID of the index of the queue, under which the keys of its batches are committed.
+/// Replica queues are not sharded and keep their own keys.
+fn index_id(queue_id: &str) -> &str {
+    parse_shard_queue_id(queue_id)
+        .map(|(index_id, _shard_ord)| index_id)
+        .unwrap_or(queue_id)
+}
+
+fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+// The queue IDs cannot contain tabs, so the key may. The position is empty for empty batches.
+fn serialize_record(
+    commit_timestamp: u64,
+    queue_id: &str,
+    batch_last_position: Option<u64>,
+    idempotency_key: &str,
+) -> String {
+    let batch_last_position_str = batch_last_position
+        .map(|position| position.to_string())
+        .unwrap_or_default();
+    format!("{commit_timestamp}\t{queue_id}\t{batch_last_position_str}\t{idempotency_key}")
+}
+
+fn parse_record(record: &[u8]) -> Option<(u64, &str, Option<u64>, &str)> {
+    let record_str = std::str::from_utf8(record).ok()?;
+    let mut parts = record_str.splitn(4, '\t');
+    let commit_timestamp = parts.next()?.parse().ok()?;
+    let queue_id = parts.next()?;
+    let batch_last_position = match parts.next()? {
+        "" => None,
+        position_str => Some(position_str.parse().ok()?),
+    };
+    let idempotency_key = parts.next()?;
+    Some((
+        commit_timestamp,
+        queue_id,
+        batch_last_position,
+        idempotency_key,
+    ))
+}
+
+impl IdempotencyKeys {
+    /// Loads the keys committed within the retention period from the record log.
+    pub fn load(queues: &Queues, retention: Duration) -> Self {
+        let mut idempotency_keys = IdempotencyKeys {
+            retention,
+            keys: HashMap::new(),
+            commits: VecDeque::new(),
+        };
+        for (position, record) in queues.idempotency_key_records() {
+            let Some((commit_timestamp, queue_id, batch_last_position, idempotency_key)) =
+                parse_record(&record)
+            else {
+                warn!(position=%position, "Failed to parse idempotency key record.");
+                continue;
+            };
+            // The expired keys are truncated by the next call to `truncate_expired`.
+            idempotency_keys.insert(CommittedKey {
+                commit_timestamp,
+                position,
+                queue_id: queue_id.to_string(),
+                idempotency_key: idempotency_key.to_string(),
+                batch_last_position,
+            });
+        }
+        idempotency_keys
+    }
+
+    fn insert(&mut self, committed_key: CommittedKey) {
+        self.keys.insert(
+            (
+                index_id(&committed_key.queue_id).to_string(),
+                committed_key.idempotency_key.clone(),
+            ),
+            (
+                committed_key.queue_id.clone(),
+                committed_key.batch_last_position,
+            ),
+        );
+        self.commits.push_back(committed_key);
+    }
+
+    /// Returns whether a batch with the key was already committed to the queue or to another
+    /// shard of its index.
+    pub fn contains(&self, queue_id: &str, idempotency_key: &str) -> bool {
+        self.keys
+            .contains_key(&(index_id(queue_id).to_string(), idempotency_key.to_string()))
+    }
+
+    /// Returns the queue and the position of the last record of the batch committed with the key
+    /// to the queue or to another shard of its index, if any.
+    pub fn batch_last_position(
+        &self,
+        queue_id: &str,
+        idempotency_key: &str,
+    ) -> Option<(&str, u64)> {
+        let (committed_queue_id, batch_last_position_opt) = self
+            .keys
+            .get(&(index_id(queue_id).to_string(), idempotency_key.to_string()))?;
+        batch_last_position_opt.map(|position| (committed_queue_id.as_str(), position))
+    }
+
+    /// Logs the key of a batch committed to the queue, along with the position of its last record.
+    pub async fn commit(
+        &mut self,
+        queues: &mut Queues,
+        queue_id: &str,
+        idempotency_key: &str,
+        batch_last_position: Option<u64>,
+        ctx: &ActorContext<IngestApiService>,
+    ) -> crate::Result<()> {
+        let commit_timestamp = now_timestamp();
+        let record = serialize_record(
+            commit_timestamp,
+            queue_id,
+            batch_last_position,
+            idempotency_key,
+        );
+        let Some(position) = queues
+            .append_idempotency_key_record(record.as_bytes(), ctx)
+            .await?
+        else {
+            return Ok(());
+        };
+        self.insert(CommittedKey {
+            commit_timestamp,
+            position,
+            queue_id: queue_id.to_string(),
+            idempotency_key: idempotency_key.to_string(),
+            batch_last_position,
+        });
+        Ok(())
+    }
+
+    /// Forgets the keys committed before the retention period and truncates them from the
+    /// record log.
+    pub async fn truncate_expired(
+        &mut self,
+        queues: &mut Queues,
+        ctx: &ActorContext<IngestApiService>,
+    ) -> crate::Result<()> {
+        let expiration_timestamp = now_timestamp().saturating_sub(self.retention.as_secs());
+        let mut truncate_position_opt = None;
+
+        while let Some(committed_key) = self.commits.front() {
+            if committed_key.commit_timestamp > expiration_timestamp {
+                break;
+            }
+            let committed_key = self.commits.pop_front().expect("The commit should exist.");
+            truncate_position_opt = Some(committed_key.position);
+            self.keys.remove(&(
+                index_id(&committed_key.queue_id).to_string(),
+                committed_key.idempotency_key,
+            ));
+        }
+        if let Some(truncate_position) = truncate_position_opt {
+            queues
+                .truncate_idempotency_key_records(truncate_position, ctx)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_key_record_serialization() {
+        let record = serialize_record(1_700_000_000, "my-index", Some(42), "batch\t1");
+        assert_eq!(
+            parse_record(record.as_bytes()),
+            Some((1_700_000_000, "my-index", Some(42), "batch\t1"))
+        );
+        let record = serialize_record(1_700_000_000, "my-index", None, "batch-1");
+        assert_eq!(
+            parse_record(record.as_bytes()),
+            Some((1_700_000_000, "my-index", None, "batch-1"))
+        );
+        assert_eq!(parse_record(b"my-index\tbatch-1"), None);
+    }
+}
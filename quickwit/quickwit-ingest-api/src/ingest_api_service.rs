@@ -17,17 +17,25 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_common::tower::Cost;
-use tracing::info;
+use tracing::{info, warn};
 use ulid::Ulid;
 
+use crate::idempotency::IdempotencyKeys;
 use crate::metrics::INGEST_METRICS;
+use crate::replication::{
+    decode_replica_record, encode_replica_record, is_replica_queue_id, parse_replica_queue_id,
+    MAX_PARTITION_ID_LEN,
+};
+use crate::shard::{parse_shard_queue_id, shard_partition_id, shard_queue_id};
 use crate::{
     CreateQueueIfNotExistsRequest, CreateQueueRequest, DropQueueRequest, FetchRequest,
     FetchResponse, IngestRequest, IngestResponse, IngestServiceError, ListQueuesRequest,
@@ -49,8 +57,29 @@ pub struct IngestApiService {
     memory_limit: usize,
     disk_limit: usize,
     memory_capacity: MemoryCapacity,
+    /// When the node is drained, new documents are rejected so that the queues can be emptied.
+    is_draining: bool,
+    /// How long the records of the replica queues are retained before being truncated.
+    replica_retention: Duration,
+    /// Time and position of the last record of the batches appended to each replica queue, in
+    /// the order they were appended.
+    replica_appends: HashMap<String, VecDeque<(Instant, u64)>>,
+    /// Idempotency keys of the batches committed within their retention period.
+    idempotency_keys: IdempotencyKeys,
+    /// Number of shards over which the documents ingested into each index are spread. The indexes
+    /// missing from the map have a single shard.
+    num_shards_per_index: HashMap<String, usize>,
+    /// Sequence number of the next batch, from which the shard of the batch is picked in a
+    /// round-robin fashion.
+    next_batch_seq: usize,
+    /// Position of the last record fetched from each queue by its ingest API source.
+    fetched_positions: HashMap<String, u64>,
 }
 
+/// Period at which the expired records of the replica queues and the expired idempotency keys are
+/// truncated.
+const TRUNCATE_EXPIRED_REPLICAS_INTERVAL: Duration = Duration::from_secs(30);
+
 impl fmt::Debug for IngestApiService {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("IngestApiService")
@@ -91,8 +120,11 @@ impl IngestApiService {
         queues_dir_path: &Path,
         memory_limit: usize,
         disk_limit: usize,
+        replica_retention: Duration,
+        idempotency_key_retention: Duration,
     ) -> crate::Result<Self> {
         let queues = Queues::open(queues_dir_path).await?;
+        let idempotency_keys = IdempotencyKeys::load(&queues, idempotency_key_retention);
         let partition_id = get_or_initialize_partition_id(queues_dir_path).await?;
         let memory_capacity = MemoryCapacity::new(memory_limit);
         info!(ingest_partition_id=%partition_id, "Ingest API partition id");
@@ -102,26 +134,69 @@ impl IngestApiService {
             memory_limit,
             disk_limit,
             memory_capacity,
+            is_draining: false,
+            replica_retention,
+            replica_appends: HashMap::new(),
+            idempotency_keys,
+            num_shards_per_index: HashMap::new(),
+            next_batch_seq: 0,
+            fetched_positions: HashMap::new(),
         })
     }
 
+    /// Picks the shard of the index the next batch is appended to.
+    fn next_shard_ord(&mut self, index_id: &str) -> usize {
+        let num_shards = self
+            .num_shards_per_index
+            .get(index_id)
+            .copied()
+            .unwrap_or(1);
+        let shard_ord = self.next_batch_seq % num_shards;
+        self.next_batch_seq = self.next_batch_seq.wrapping_add(1);
+        shard_ord
+    }
+
     async fn ingest(
         &mut self,
         request: IngestRequest,
         ctx: &ActorContext<Self>,
     ) -> crate::Result<IngestResponse> {
+        if self.is_draining {
+            info!("Ingest request rejected because the node is draining.");
+            return Err(IngestServiceError::Unavailable);
+        }
         // Check all indexes exist assuming existing queues always have a corresponding index.
+        // Replica queues are created on the fly.
         let first_non_existing_queue_opt = request
             .doc_batches
             .iter()
             .map(|batch| batch.index_id.as_str())
-            .find(|index_id| !self.queues.queue_exists(index_id));
+            .find(|index_id| !is_replica_queue_id(index_id) && !self.queues.queue_exists(index_id));
 
         if let Some(index_id) = first_non_existing_queue_opt {
             return Err(IngestServiceError::IndexNotFound {
                 index_id: index_id.to_string(),
             });
         }
+        for doc_batch in &request.doc_batches {
+            if !is_replica_queue_id(&doc_batch.index_id) {
+                continue;
+            }
+            let Some(leader_position) = &doc_batch.leader_position else {
+                return Err(IngestServiceError::InvalidPosition(format!(
+                    "Replicated batch for queue `{}` lacks the position of its leader",
+                    doc_batch.index_id
+                )));
+            };
+            if leader_position.partition_id.len() > MAX_PARTITION_ID_LEN
+                || leader_position.last_position < (doc_batch.num_docs() as u64).saturating_sub(1)
+            {
+                return Err(IngestServiceError::InvalidPosition(format!(
+                    "Replicated batch for queue `{}` has an invalid leader position",
+                    doc_batch.index_id
+                )));
+            }
+        }
         let disk_usage = self.queues.disk_usage();
 
         if disk_usage > self.disk_limit {
@@ -138,13 +213,113 @@ impl IngestApiService {
             return Err(IngestServiceError::RateLimited);
         }
         let mut num_docs = 0usize;
-        for doc_batch in &request.doc_batches {
+        let mut num_duplicate_docs = 0usize;
+        let mut batch_last_positions = HashMap::new();
+        let mut batch_shard_ords = HashMap::new();
+
+        for (batch_idx, doc_batch) in request.doc_batches.iter().enumerate() {
+            let is_replica = is_replica_queue_id(&doc_batch.index_id);
+
+            if let Some(idempotency_key) = &doc_batch.idempotency_key {
+                if self
+                    .idempotency_keys
+                    .contains(&doc_batch.index_id, idempotency_key)
+                {
+                    // The position of a retried batch is returned so that the batch is replicated
+                    // again in case its replication failed.
+                    if let (false, Some((queue_id, position))) = (
+                        is_replica,
+                        self.idempotency_keys
+                            .batch_last_position(&doc_batch.index_id, idempotency_key),
+                    ) {
+                        batch_last_positions.insert(batch_idx as u32, position);
+
+                        if let Some((_, shard_ord)) = parse_shard_queue_id(queue_id) {
+                            if shard_ord > 0 {
+                                batch_shard_ords.insert(batch_idx as u32, shard_ord as u32);
+                            }
+                        }
+                    }
+                    let batch_num_docs = doc_batch.num_docs();
+                    num_duplicate_docs += batch_num_docs;
+                    INGEST_METRICS
+                        .duplicate_num_docs
+                        .inc_by(batch_num_docs as u64);
+                    continue;
+                }
+            }
+            let shard_ord = if is_replica {
+                0
+            } else {
+                self.next_shard_ord(&doc_batch.index_id)
+            };
+            let queue_id = if is_replica {
+                doc_batch.index_id.clone()
+            } else {
+                shard_queue_id(&doc_batch.index_id, shard_ord)
+            };
             // TODO better error handling.
             // If there is an error, we probably want a transactional behavior.
-            let records_it = doc_batch.iter_raw();
-            self.queues
-                .append_batch(&doc_batch.index_id, records_it, ctx)
-                .await?;
+            let position_opt = if is_replica {
+                let leader_position = doc_batch
+                    .leader_position
+                    .as_ref()
+                    .expect("The leader position of the replicated batch should be validated.");
+                if !self.queues.queue_exists(&doc_batch.index_id) {
+                    self.queues.create_queue(&doc_batch.index_id, ctx).await?;
+                }
+                // The records of the batch occupy consecutive positions in the queue of the
+                // leader.
+                let first_position =
+                    leader_position.last_position - (doc_batch.num_docs() as u64).saturating_sub(1);
+                let replica_records: Vec<Vec<u8>> = doc_batch
+                    .iter_raw()
+                    .zip(first_position..)
+                    .map(|(record, position)| {
+                        encode_replica_record(&leader_position.partition_id, position, &record)
+                    })
+                    .collect();
+                self.queues
+                    .append_batch(
+                        &doc_batch.index_id,
+                        replica_records.iter().map(|record| record.as_slice()),
+                        ctx,
+                    )
+                    .await?
+            } else {
+                self.queues
+                    .append_batch(&queue_id, doc_batch.iter_raw(), ctx)
+                    .await?
+            };
+            match (is_replica, position_opt) {
+                (true, Some(position)) => {
+                    self.replica_appends
+                        .entry(doc_batch.index_id.clone())
+                        .or_default()
+                        .push_back((Instant::now(), position));
+                }
+                (false, Some(position)) => {
+                    batch_last_positions.insert(batch_idx as u32, position);
+
+                    if shard_ord > 0 {
+                        batch_shard_ords.insert(batch_idx as u32, shard_ord as u32);
+                    }
+                }
+                (_, None) => {}
+            }
+            // The key is committed after the batch: a crash in between lets a retry of the batch
+            // through rather than losing it.
+            if let Some(idempotency_key) = &doc_batch.idempotency_key {
+                self.idempotency_keys
+                    .commit(
+                        &mut self.queues,
+                        &queue_id,
+                        idempotency_key,
+                        position_opt,
+                        ctx,
+                    )
+                    .await?;
+            }
             let batch_num_docs = doc_batch.num_docs();
             let batch_num_bytes = doc_batch.num_bytes();
             num_docs += batch_num_docs;
@@ -158,6 +333,10 @@ impl IngestApiService {
         // TODO we could fsync here and disable autosync to have better i/o perfs.
         Ok(IngestResponse {
             num_docs_for_processing: num_docs as u64,
+            num_duplicate_docs: num_duplicate_docs as u64,
+            partition_id: self.partition_id.clone(),
+            batch_last_positions,
+            batch_shard_ords,
         })
     }
 
@@ -165,11 +344,116 @@ impl IngestApiService {
         let num_bytes_limit_opt: Option<usize> = fetch_req
             .num_bytes_limit
             .map(|num_bytes_limit| num_bytes_limit as usize);
-        self.queues.fetch(
+        let fetch_response = self.queues.fetch(
             &fetch_req.index_id,
             fetch_req.start_after,
             num_bytes_limit_opt,
-        )
+        )?;
+        if let (Some(first_position), Some(doc_batch)) =
+            (fetch_response.first_position, &fetch_response.doc_batch)
+        {
+            let last_position = first_position + (doc_batch.num_docs() as u64).saturating_sub(1);
+            self.fetched_positions
+                .insert(fetch_req.index_id, last_position);
+        }
+        Ok(fetch_response)
+    }
+
+    /// Returns the number of records of each queue that were not fetched by its ingest API source
+    /// yet, keyed by queue ID. Replica queues are not indexed, so they are ignored.
+    fn ingest_lags(&self) -> crate::Result<HashMap<String, usize>> {
+        let ingest_lags = self
+            .queues
+            .list_queues()?
+            .queues
+            .into_iter()
+            .filter(|queue_id| !is_replica_queue_id(queue_id))
+            .map(|queue_id| {
+                let fetched_position_opt = self.fetched_positions.get(&queue_id).copied();
+                let num_records = self
+                    .queues
+                    .num_records_after(&queue_id, fetched_position_opt);
+                (queue_id, num_records)
+            })
+            .collect();
+        Ok(ingest_lags)
+    }
+
+    /// Sets the number of shards the documents ingested into the index are spread over, creating
+    /// the missing queues. The records of the shards beyond that number that were not published
+    /// yet are moved to the first shard, and these shards are emptied. They are kept rather than
+    /// dropped so that their positions keep increasing past their published position if they are
+    /// used again. Returns the number of records moved.
+    async fn set_num_shards(
+        &mut self,
+        index_id: &str,
+        num_shards: usize,
+        published_positions: &HashMap<String, u64>,
+        ctx: &ActorContext<Self>,
+    ) -> crate::Result<usize> {
+        if num_shards == 0 && !self.queues.queue_exists(index_id) {
+            return Ok(0);
+        }
+        let num_shards = num_shards.max(1);
+
+        for shard_ord in 0..num_shards {
+            let queue_id = shard_queue_id(index_id, shard_ord);
+
+            if !self.queues.queue_exists(&queue_id) {
+                self.queues.create_queue(&queue_id, ctx).await?;
+            }
+        }
+        let retired_shard_ords: Vec<usize> = self
+            .queues
+            .list_queues()?
+            .queues
+            .iter()
+            .filter_map(|queue_id| parse_shard_queue_id(queue_id))
+            .filter(|(queue_index_id, shard_ord)| {
+                *queue_index_id == index_id && *shard_ord >= num_shards
+            })
+            .map(|(_, shard_ord)| shard_ord)
+            .collect();
+        let mut num_moved_records = 0;
+
+        for shard_ord in retired_shard_ords {
+            let queue_id = shard_queue_id(index_id, shard_ord);
+            let Some(last_position) = self.queues.last_position(&queue_id) else {
+                continue;
+            };
+            let partition_id = shard_partition_id(&self.partition_id, shard_ord);
+            let published_position_opt = published_positions.get(&partition_id).copied();
+            let records = self.queues.records(&queue_id, published_position_opt)?;
+
+            if !records.is_empty() {
+                self.queues
+                    .append_batch(
+                        index_id,
+                        records.iter().map(|record| record.as_slice()),
+                        ctx,
+                    )
+                    .await?;
+            }
+            self.queues
+                .suggest_truncate(&queue_id, last_position, ctx)
+                .await?;
+            self.fetched_positions.remove(&queue_id);
+            num_moved_records += records.len();
+        }
+        if num_shards > 1 {
+            self.num_shards_per_index
+                .insert(index_id.to_string(), num_shards);
+        } else {
+            self.num_shards_per_index.remove(index_id);
+        }
+        info!(
+            index_id=%index_id,
+            num_shards=%num_shards,
+            num_moved_records=%num_moved_records,
+            "Set number of ingest shards."
+        );
+        self.reset_memory_capacity();
+        Ok(num_moved_records)
     }
 
     async fn suggest_truncate(
@@ -181,12 +465,122 @@ impl IngestApiService {
             .suggest_truncate(&request.index_id, request.up_to_position_included, ctx)
             .await?;
 
+        self.reset_memory_capacity();
+        Ok(())
+    }
+
+    fn reset_memory_capacity(&mut self) {
         let memory_usage = self.queues.memory_usage();
-        let new_capacity = self.memory_limit - memory_usage;
+        let new_capacity = self.memory_limit.saturating_sub(memory_usage);
         self.memory_capacity.reset_capacity(new_capacity);
+    }
 
+    /// Truncates the records of the replica queues older than the retention period.
+    async fn truncate_expired_replicas(&mut self, ctx: &ActorContext<Self>) -> crate::Result<()> {
+        let Some(expiration) = Instant::now().checked_sub(self.replica_retention) else {
+            return Ok(());
+        };
+        let mut truncated_any = false;
+
+        for (queue_id, appends) in self.replica_appends.iter_mut() {
+            let mut truncate_position_opt = None;
+
+            while let Some((append_instant, position)) = appends.front().copied() {
+                if append_instant > expiration {
+                    break;
+                }
+                truncate_position_opt = Some(position);
+                appends.pop_front();
+            }
+            if let Some(truncate_position) = truncate_position_opt {
+                self.queues
+                    .suggest_truncate(queue_id, truncate_position, ctx)
+                    .await?;
+                truncated_any = true;
+            }
+        }
+        self.replica_appends
+            .retain(|_, appends| !appends.is_empty());
+
+        if truncated_any {
+            self.reset_memory_capacity();
+        }
         Ok(())
     }
+
+    /// Appends the records replicated from the leader `leader_node_id` to the queues of their
+    /// indexes and drops the corresponding replica queues. The records at or before the positions
+    /// published by the leader, and the records replicated more than once, are skipped. Returns
+    /// the number of records recovered.
+    async fn recover_replicas(
+        &mut self,
+        leader_node_id: &str,
+        published_positions: &HashMap<String, HashMap<String, u64>>,
+        ctx: &ActorContext<Self>,
+    ) -> crate::Result<usize> {
+        let replica_queue_ids: Vec<String> = self
+            .queues
+            .list_queues()?
+            .queues
+            .into_iter()
+            .filter(|queue_id| {
+                parse_replica_queue_id(queue_id)
+                    .map(|(node_id, _)| node_id == leader_node_id)
+                    .unwrap_or(false)
+            })
+            .collect();
+        let mut num_recovered_records = 0;
+
+        for replica_queue_id in replica_queue_ids {
+            let (_, index_id) = parse_replica_queue_id(&replica_queue_id)
+                .expect("The queue ID should be a replica queue ID.");
+            // The replicas of deleted indexes are simply dropped.
+            if self.queues.queue_exists(index_id) {
+                let index_published_positions = published_positions.get(index_id);
+                let mut recovered_positions = HashSet::new();
+                let mut records = Vec::new();
+
+                for replica_record in self.queues.records(&replica_queue_id, None)? {
+                    let Some((partition_id, position, record)) =
+                        decode_replica_record(&replica_record)
+                    else {
+                        warn!(queue_id=%replica_queue_id, "Failed to parse replica record.");
+                        continue;
+                    };
+                    let published_position_opt = index_published_positions
+                        .and_then(|partition_positions| partition_positions.get(partition_id));
+                    if published_position_opt
+                        .map(|published_position| position <= *published_position)
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    if recovered_positions.insert((partition_id.to_string(), position)) {
+                        records.push(record.to_vec());
+                    }
+                }
+                if !records.is_empty() {
+                    self.queues
+                        .append_batch(
+                            index_id,
+                            records.iter().map(|record| record.as_slice()),
+                            ctx,
+                        )
+                        .await?;
+                }
+                num_recovered_records += records.len();
+            }
+            self.queues.drop_queue(&replica_queue_id, ctx).await?;
+            self.replica_appends.remove(&replica_queue_id);
+        }
+        info!(
+            leader_node_id=%leader_node_id,
+            num_recovered_records=%num_recovered_records,
+            "Recovered replicated records."
+        );
+        self.reset_memory_capacity();
+        Ok(num_recovered_records)
+    }
 }
 
 #[async_trait]
@@ -195,6 +589,33 @@ impl Actor for IngestApiService {
 
     fn observable_state(&self) -> Self::ObservableState {}
 
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        // The replicas that survived a restart are retained for a full retention period.
+        let now = Instant::now();
+        let queue_ids = self
+            .queues
+            .list_queues()
+            .map(|list_queues_response| list_queues_response.queues)
+            .unwrap_or_default();
+        for queue_id in queue_ids {
+            if !is_replica_queue_id(&queue_id) {
+                continue;
+            }
+            if let Some(last_position) = self.queues.last_position(&queue_id) {
+                self.replica_appends
+                    .entry(queue_id)
+                    .or_default()
+                    .push_back((now, last_position));
+            }
+        }
+        ctx.schedule_self_msg(
+            TRUNCATE_EXPIRED_REPLICAS_INTERVAL,
+            TruncateExpiredReplicasLoop,
+        )
+        .await;
+        Ok(())
+    }
+
     fn runtime_handle(&self) -> tokio::runtime::Handle {
         RuntimeType::NonBlocking.get_runtime_handle()
     }
@@ -237,6 +658,153 @@ impl Handler<GetMemoryCapacity> for IngestApiService {
     }
 }
 
+/// Returns the number of records that have not been indexed and truncated yet in each queue,
+/// keyed by queue ID.
+#[derive(Debug)]
+pub struct GetPendingRecords;
+
+#[async_trait]
+impl Handler<GetPendingRecords> for IngestApiService {
+    type Reply = HashMap<String, usize>;
+
+    async fn handle(
+        &mut self,
+        _request: GetPendingRecords,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.queues.num_pending_records_per_queue())
+    }
+}
+
+/// Returns the number of records of each queue that were not fetched by its ingest API source
+/// yet, keyed by queue ID. It measures how far the ingest API pipelines lag behind ingestion.
+#[derive(Debug)]
+pub struct GetIngestLags;
+
+#[async_trait]
+impl Handler<GetIngestLags> for IngestApiService {
+    type Reply = crate::Result<HashMap<String, usize>>;
+
+    async fn handle(
+        &mut self,
+        _request: GetIngestLags,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self.ingest_lags())
+    }
+}
+
+/// Stops accepting new documents so that the queues can be emptied by the indexing pipelines
+/// before the node is decommissioned. Replies with the number of records that have not been
+/// indexed and truncated yet.
+#[derive(Debug)]
+pub struct DrainQueues;
+
+#[async_trait]
+impl Handler<DrainQueues> for IngestApiService {
+    type Reply = usize;
+
+    async fn handle(
+        &mut self,
+        _request: DrainQueues,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        self.is_draining = true;
+        Ok(self.queues.num_pending_records())
+    }
+}
+
+#[derive(Debug)]
+struct TruncateExpiredReplicasLoop;
+
+#[async_trait]
+impl Handler<TruncateExpiredReplicasLoop> for IngestApiService {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: TruncateExpiredReplicasLoop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        if let Err(error) = self.truncate_expired_replicas(ctx).await {
+            warn!(error=?error, "Failed to truncate expired replicas.");
+        }
+        if let Err(error) = self
+            .idempotency_keys
+            .truncate_expired(&mut self.queues, ctx)
+            .await
+        {
+            warn!(error=?error, "Failed to truncate expired idempotency keys.");
+        }
+        ctx.schedule_self_msg(
+            TRUNCATE_EXPIRED_REPLICAS_INTERVAL,
+            TruncateExpiredReplicasLoop,
+        )
+        .await;
+        Ok(())
+    }
+}
+
+/// Re-appends the records replicated from a lost leader indexer to the queues of their indexes
+/// so that they get indexed by this node, then drops the corresponding replica queues. Replies
+/// with the number of records recovered.
+///
+/// The records that the leader had already published are skipped. The records that the leader
+/// had indexed but not published yet are indexed again.
+#[derive(Debug)]
+pub struct RecoverReplicas {
+    pub leader_node_id: String,
+    /// The last position published by the ingest API source, per index ID and partition ID.
+    pub published_positions: HashMap<String, HashMap<String, u64>>,
+}
+
+#[async_trait]
+impl Handler<RecoverReplicas> for IngestApiService {
+    type Reply = crate::Result<usize>;
+
+    async fn handle(
+        &mut self,
+        request: RecoverReplicas,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .recover_replicas(&request.leader_node_id, &request.published_positions, ctx)
+            .await)
+    }
+}
+
+/// Sets the number of shards the documents ingested into an index are spread over, once the
+/// ingest API pipelines of the shards beyond that number have been shut down. Their records that
+/// were not published yet are moved to the first shard so that they get indexed. Replies with the
+/// number of records moved.
+#[derive(Debug)]
+pub struct SetNumShards {
+    pub index_id: String,
+    pub num_shards: usize,
+    /// The last position published by the ingest API source of the index, per partition ID.
+    pub published_positions: HashMap<String, u64>,
+}
+
+#[async_trait]
+impl Handler<SetNumShards> for IngestApiService {
+    type Reply = crate::Result<usize>;
+
+    async fn handle(
+        &mut self,
+        request: SetNumShards,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        Ok(self
+            .set_num_shards(
+                &request.index_id,
+                request.num_shards,
+                &request.published_positions,
+                ctx,
+            )
+            .await)
+    }
+}
+
 #[async_trait]
 impl Handler<CreateQueueRequest> for IngestApiService {
     type Reply = crate::Result<()>;
@@ -278,6 +846,9 @@ impl Handler<DropQueueRequest> for IngestApiService {
         drop_queue_req: DropQueueRequest,
         ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
+        self.replica_appends.remove(&drop_queue_req.queue_id);
+        self.num_shards_per_index.remove(&drop_queue_req.queue_id);
+        self.fetched_positions.remove(&drop_queue_req.queue_id);
         Ok(self.queues.drop_queue(&drop_queue_req.queue_id, ctx).await)
     }
 }
@@ -357,11 +928,15 @@ mod tests {
                     index_id: "index-1".to_string(),
                     concat_docs: Bytes::from_static(&[0, 1, 2]),
                     doc_lens: vec![1, 2],
+                    idempotency_key: None,
+                    leader_position: None,
                 },
                 DocBatch {
                     index_id: "index-2".to_string(),
                     concat_docs: Bytes::from_static(&[3, 4, 5, 6, 7, 8]),
                     doc_lens: vec![1, 3, 2],
+                    idempotency_key: None,
+                    leader_position: None,
                 },
             ],
         };
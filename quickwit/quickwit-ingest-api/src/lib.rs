@@ -20,6 +20,7 @@
 #![deny(clippy::disallowed_methods)]
 
 mod errors;
+mod idempotency;
 mod ingest_api_service;
 #[path = "codegen/ingest_service.rs"]
 mod ingest_service;
@@ -27,13 +28,18 @@ mod memory_capacity;
 mod metrics;
 mod position;
 mod queue;
+mod replication;
+mod shard;
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context};
 pub use errors::IngestServiceError;
-pub use ingest_api_service::{GetMemoryCapacity, GetPartitionId, IngestApiService};
+pub use ingest_api_service::{
+    DrainQueues, GetIngestLags, GetMemoryCapacity, GetPartitionId, GetPendingRecords,
+    IngestApiService, RecoverReplicas, SetNumShards,
+};
 pub use ingest_service::*;
 pub use memory_capacity::MemoryCapacity;
 use once_cell::sync::OnceCell;
@@ -41,6 +47,8 @@ pub use position::Position;
 pub use queue::Queues;
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_config::IngestApiConfig;
+pub use replication::{parse_replica_queue_id, replica_queue_id, REPLICA_QUEUE_PREFIX};
+pub use shard::{parse_shard_queue_id, shard_partition_id, shard_queue_id};
 use tokio::sync::Mutex;
 
 mod doc_batch;
@@ -72,6 +80,8 @@ pub async fn init_ingest_api(
         queues_dir_path,
         config.max_queue_memory_usage.get_bytes() as usize,
         config.max_queue_disk_usage.get_bytes() as usize,
+        config.replica_retention(),
+        config.idempotency_key_retention(),
     )
     .await
     .with_context(|| {
@@ -115,6 +125,8 @@ pub async fn start_ingest_api_service(
 #[cfg(test)]
 mod tests {
 
+    use std::time::Duration;
+
     use byte_unit::Byte;
     use quickwit_actors::AskError;
 
@@ -177,11 +189,15 @@ mod tests {
                     index_id: "index-1".to_string(),
                     concat_docs: vec![10, 11, 12].into(),
                     doc_lens: vec![2],
+                    idempotency_key: None,
+                    leader_position: None,
                 },
                 DocBatch {
                     index_id: "index-2".to_string(),
                     concat_docs: vec![10, 11, 12].into(),
                     doc_lens: vec![2],
+                    idempotency_key: None,
+                    leader_position: None,
                 },
             ],
         };
@@ -209,6 +225,7 @@ mod tests {
             &IngestApiConfig {
                 max_queue_memory_usage: Byte::from_bytes(1200),
                 max_queue_disk_usage: Byte::from_bytes(1024 * 1024 * 256),
+                ..Default::default()
             },
         )
         .await
@@ -227,6 +244,8 @@ mod tests {
                 index_id: "test-queue".to_string(),
                 concat_docs: vec![1; 600].into(),
                 doc_lens: vec![30; 20],
+                idempotency_key: None,
+                leader_position: None,
             }],
         };
 
@@ -265,4 +284,363 @@ mod tests {
             .unwrap();
         universe.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_drain_queues() {
+        let universe = Universe::with_accelerated_time();
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let queues_dir_path = tempdir.path().join("queues-0");
+        let ingest_api_service =
+            init_ingest_api(&universe, &queues_dir_path, &IngestApiConfig::default())
+                .await
+                .unwrap();
+        ingest_api_service
+            .ask_for_res(CreateQueueRequest {
+                queue_id: "test-queue".to_string(),
+            })
+            .await
+            .unwrap();
+        let ingest_request = IngestRequest {
+            doc_batches: vec![DocBatch {
+                index_id: "test-queue".to_string(),
+                concat_docs: vec![1; 60].into(),
+                doc_lens: vec![30; 2],
+                idempotency_key: None,
+                leader_position: None,
+            }],
+        };
+        ingest_api_service
+            .ask_for_res(ingest_request.clone())
+            .await
+            .unwrap();
+
+        let num_pending_records_per_queue =
+            ingest_api_service.ask(GetPendingRecords).await.unwrap();
+        assert_eq!(num_pending_records_per_queue.len(), 1);
+        assert_eq!(num_pending_records_per_queue["test-queue"], 2);
+
+        let num_pending_records = ingest_api_service.ask(DrainQueues).await.unwrap();
+        assert_eq!(num_pending_records, 2);
+
+        assert!(matches!(
+            ingest_api_service
+                .ask_for_res(ingest_request)
+                .await
+                .unwrap_err(),
+            AskError::ErrorReply(IngestServiceError::Unavailable)
+        ));
+        ingest_api_service
+            .ask_for_res(SuggestTruncateRequest {
+                index_id: "test-queue".to_string(),
+                up_to_position_included: 1,
+            })
+            .await
+            .unwrap();
+
+        let num_pending_records = ingest_api_service.ask(DrainQueues).await.unwrap();
+        assert_eq!(num_pending_records, 0);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_replica_queues() {
+        let universe = Universe::with_accelerated_time();
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let queues_dir_path = tempdir.path().join("queues-0");
+        let config = IngestApiConfig {
+            replica_retention_secs: 60,
+            ..Default::default()
+        };
+        let ingest_api_service = init_ingest_api(&universe, &queues_dir_path, &config)
+            .await
+            .unwrap();
+        ingest_api_service
+            .ask_for_res(CreateQueueRequest {
+                queue_id: "test-index".to_string(),
+            })
+            .await
+            .unwrap();
+        let replica_queue_id_0 = replica_queue_id("node-0", "test-index");
+        let replica_queue_id_1 = replica_queue_id("node-1", "test-index");
+        let replica_batch = |queue_id: &str, num_docs: usize, last_position: u64| DocBatch {
+            index_id: queue_id.to_string(),
+            concat_docs: vec![1; 30 * num_docs].into(),
+            doc_lens: vec![30; num_docs],
+            idempotency_key: None,
+            leader_position: Some(LeaderPosition {
+                partition_id: "partition-0".to_string(),
+                last_position,
+            }),
+        };
+        // The second batch is a retry of the first one.
+        let ingest_request = IngestRequest {
+            doc_batches: vec![
+                replica_batch(&replica_queue_id_0, 2, 5),
+                replica_batch(&replica_queue_id_0, 2, 5),
+                replica_batch(&replica_queue_id_0, 2, 7),
+                replica_batch(&replica_queue_id_1, 1, 0),
+            ],
+        };
+        ingest_api_service
+            .ask_for_res(ingest_request)
+            .await
+            .unwrap();
+
+        // Replicated batches must carry the position of their leader.
+        let ingest_request = IngestRequest {
+            doc_batches: vec![DocBatch {
+                leader_position: None,
+                ..replica_batch(&replica_queue_id_0, 1, 8)
+            }],
+        };
+        let ingest_error = ingest_api_service
+            .ask_for_res(ingest_request)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            ingest_error,
+            AskError::ErrorReply(IngestServiceError::InvalidPosition(_))
+        ));
+
+        // Replica queues are not indexed.
+        let num_pending_records_per_queue =
+            ingest_api_service.ask(GetPendingRecords).await.unwrap();
+        assert_eq!(num_pending_records_per_queue.len(), 1);
+        assert_eq!(num_pending_records_per_queue["test-index"], 0);
+
+        // The leader published the documents up to position 4, so the documents at positions 5,
+        // 6, and 7 are recovered, once each.
+        let published_positions = HashMap::from([(
+            "test-index".to_string(),
+            HashMap::from([("partition-0".to_string(), 4)]),
+        )]);
+        let num_recovered_records = ingest_api_service
+            .ask_for_res(RecoverReplicas {
+                leader_node_id: "node-0".to_string(),
+                published_positions,
+            })
+            .await
+            .unwrap();
+        assert_eq!(num_recovered_records, 3);
+
+        let num_pending_records_per_queue =
+            ingest_api_service.ask(GetPendingRecords).await.unwrap();
+        assert_eq!(num_pending_records_per_queue["test-index"], 3);
+
+        let queues = ingest_api_service
+            .ask_for_res(ListQueuesRequest {})
+            .await
+            .unwrap()
+            .queues;
+        assert_eq!(queues.len(), 2);
+        assert!(queues.contains(&replica_queue_id_1));
+
+        // The replicas of `node-1` expire.
+        universe.sleep(Duration::from_secs(120)).await;
+
+        let fetch_response = ingest_api_service
+            .ask_for_res(TailRequest {
+                index_id: replica_queue_id_1,
+            })
+            .await
+            .unwrap();
+        assert_eq!(fetch_response.doc_batch.unwrap().num_docs(), 0);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_idempotency_keys() {
+        let universe = Universe::with_accelerated_time();
+        let tempdir = tempfile::tempdir().unwrap();
+        let queues_dir_path = tempdir.path().join("queues-0");
+
+        let ingest_request = |idempotency_key: &str| IngestRequest {
+            doc_batches: vec![DocBatch {
+                index_id: "test-index".to_string(),
+                concat_docs: vec![1; 60].into(),
+                doc_lens: vec![30; 2],
+                idempotency_key: Some(idempotency_key.to_string()),
+                leader_position: None,
+            }],
+        };
+        let ingest_api_service = IngestApiService::with_queues_dir(
+            &queues_dir_path,
+            1_000_000,
+            1_000_000,
+            Duration::from_secs(60),
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+        let (ingest_api_mailbox, ingest_api_handle) =
+            universe.spawn_builder().spawn(ingest_api_service);
+        ingest_api_mailbox
+            .ask_for_res(CreateQueueRequest {
+                queue_id: "test-index".to_string(),
+            })
+            .await
+            .unwrap();
+        let ingest_response = ingest_api_mailbox
+            .ask_for_res(ingest_request("batch-1"))
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+        assert_eq!(ingest_response.num_duplicate_docs, 0);
+        let batch_last_positions = ingest_response.batch_last_positions;
+        assert_eq!(batch_last_positions.len(), 1);
+
+        // The retried batch keeps its position, so that it can be replicated again.
+        let ingest_response = ingest_api_mailbox
+            .ask_for_res(ingest_request("batch-1"))
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 0);
+        assert_eq!(ingest_response.num_duplicate_docs, 2);
+        assert_eq!(ingest_response.batch_last_positions, batch_last_positions);
+        ingest_api_handle.quit().await;
+
+        // The committed keys survive a restart.
+        let ingest_api_service = IngestApiService::with_queues_dir(
+            &queues_dir_path,
+            1_000_000,
+            1_000_000,
+            Duration::from_secs(60),
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+        let (ingest_api_mailbox, _ingest_api_handle) =
+            universe.spawn_builder().spawn(ingest_api_service);
+        let ingest_response = ingest_api_mailbox
+            .ask_for_res(ingest_request("batch-1"))
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_duplicate_docs, 2);
+        assert_eq!(ingest_response.batch_last_positions, batch_last_positions);
+
+        let ingest_response = ingest_api_mailbox
+            .ask_for_res(ingest_request("batch-2"))
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+        assert_eq!(
+            ingest_response.batch_last_positions[&0],
+            batch_last_positions[&0] + 2
+        );
+
+        // The idempotency keys are not listed as a queue.
+        let num_pending_records_per_queue =
+            ingest_api_mailbox.ask(GetPendingRecords).await.unwrap();
+        assert_eq!(num_pending_records_per_queue.len(), 1);
+        assert_eq!(num_pending_records_per_queue["test-index"], 4);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_shards() {
+        let universe = Universe::with_accelerated_time();
+        let tempdir = tempfile::tempdir().unwrap();
+
+        let queues_dir_path = tempdir.path().join("queues-0");
+        let ingest_api_service =
+            init_ingest_api(&universe, &queues_dir_path, &IngestApiConfig::default())
+                .await
+                .unwrap();
+        ingest_api_service
+            .ask_for_res(CreateQueueRequest {
+                queue_id: "test-index".to_string(),
+            })
+            .await
+            .unwrap();
+        let partition_id = ingest_api_service.ask(GetPartitionId).await.unwrap();
+        let doc_batch = |idempotency_key: &str| DocBatch {
+            index_id: "test-index".to_string(),
+            concat_docs: vec![1; 60].into(),
+            doc_lens: vec![30; 2],
+            idempotency_key: Some(idempotency_key.to_string()),
+            leader_position: None,
+        };
+        let num_moved_records = ingest_api_service
+            .ask_for_res(SetNumShards {
+                index_id: "test-index".to_string(),
+                num_shards: 2,
+                published_positions: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(num_moved_records, 0);
+
+        // The batches are spread over the shards.
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch("batch-1"), doc_batch("batch-2")],
+        };
+        let ingest_response = ingest_api_service
+            .ask_for_res(ingest_request)
+            .await
+            .unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 4);
+        assert_eq!(ingest_response.batch_last_positions.len(), 2);
+        assert_eq!(ingest_response.batch_shard_ords.len(), 1);
+
+        // The retry of a batch is dropped whichever shard it is routed to.
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch("batch-1"), doc_batch("batch-2")],
+        };
+        let retry_ingest_response = ingest_api_service
+            .ask_for_res(ingest_request)
+            .await
+            .unwrap();
+        assert_eq!(retry_ingest_response.num_duplicate_docs, 4);
+        assert_eq!(
+            retry_ingest_response.batch_shard_ords,
+            ingest_response.batch_shard_ords
+        );
+
+        let ingest_lags = ingest_api_service.ask_for_res(GetIngestLags).await.unwrap();
+        assert_eq!(ingest_lags["test-index"], 2);
+        assert_eq!(ingest_lags[&shard_queue_id("test-index", 1)], 2);
+
+        ingest_api_service
+            .ask_for_res(FetchRequest {
+                index_id: shard_queue_id("test-index", 1),
+                start_after: None,
+                num_bytes_limit: None,
+            })
+            .await
+            .unwrap();
+        let ingest_lags = ingest_api_service.ask_for_res(GetIngestLags).await.unwrap();
+        assert_eq!(ingest_lags[&shard_queue_id("test-index", 1)], 0);
+
+        // The second shard published its first record, so its second record is moved to the first
+        // shard.
+        let published_positions = HashMap::from([(shard_partition_id(&partition_id, 1), 0)]);
+        let num_moved_records = ingest_api_service
+            .ask_for_res(SetNumShards {
+                index_id: "test-index".to_string(),
+                num_shards: 1,
+                published_positions,
+            })
+            .await
+            .unwrap();
+        assert_eq!(num_moved_records, 1);
+
+        let num_pending_records_per_queue =
+            ingest_api_service.ask(GetPendingRecords).await.unwrap();
+        assert_eq!(num_pending_records_per_queue["test-index"], 3);
+        assert_eq!(
+            num_pending_records_per_queue[&shard_queue_id("test-index", 1)],
+            0
+        );
+
+        let ingest_response = ingest_api_service
+            .ask_for_res(IngestRequest {
+                doc_batches: vec![doc_batch("batch-3"), doc_batch("batch-4")],
+            })
+            .await
+            .unwrap();
+        assert!(ingest_response.batch_shard_ords.is_empty());
+        universe.assert_quit().await;
+    }
 }
@@ -23,6 +23,7 @@ use quickwit_common::metrics::{new_counter, new_gauge, IntCounter, IntGauge};
 pub struct IngestMetrics {
     pub ingested_num_bytes: IntCounter,
     pub ingested_num_docs: IntCounter,
+    pub duplicate_num_docs: IntCounter,
     pub queue_count: IntGauge,
 }
 
@@ -39,6 +40,11 @@ impl Default for IngestMetrics {
                 "Number of docs received to be ingested",
                 "quickwit_ingest",
             ),
+            duplicate_num_docs: new_counter(
+                "duplicate_num_docs",
+                "Number of docs dropped because their batch was already committed",
+                "quickwit_ingest",
+            ),
             queue_count: new_gauge(
                 "queue_count",
                 "Number of queues currently active",
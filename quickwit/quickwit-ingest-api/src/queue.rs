@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::ops::Bound;
 use std::path::Path;
 
@@ -25,6 +26,8 @@ use mrecordlog::error::CreateQueueError;
 use mrecordlog::MultiRecordLog;
 use quickwit_actors::ActorContext;
 
+use crate::idempotency::IDEMPOTENCY_KEYS_QUEUE_ID;
+use crate::replication::is_replica_queue_id;
 use crate::{
     DocBatchBuilder, FetchResponse, IngestApiService, IngestServiceError, ListQueuesResponse,
 };
@@ -122,10 +125,12 @@ impl Queues {
         ctx: &ActorContext<IngestApiService>,
     ) -> crate::Result<()> {
         self.append_batch(queue_id, std::iter::once(record), ctx)
-            .await
+            .await?;
+        Ok(())
     }
 
-    // Append a batch of records to a target queue.
+    // Append a batch of records to a target queue and returns the position of the last record
+    // appended, if any.
     //
     // This operation is atomic: the batch of records is either entirely added or not.
     pub async fn append_batch<'a>(
@@ -133,17 +138,40 @@ impl Queues {
         queue_id: &str,
         records_it: impl Iterator<Item = impl Buf>,
         ctx: &ActorContext<IngestApiService>,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<Option<u64>> {
         let real_queue_id = format!("{QUICKWIT_CF_PREFIX}{queue_id}");
 
         // TODO None means we don't have itempotent inserts
-        ctx.protect_future(
-            self.record_log
-                .append_records(&real_queue_id, None, records_it),
-        )
-        .await?;
+        let position_opt = ctx
+            .protect_future(
+                self.record_log
+                    .append_records(&real_queue_id, None, records_it),
+            )
+            .await?;
 
-        Ok(())
+        Ok(position_opt)
+    }
+
+    // Returns the records of the queue in `]start_after, +∞[` that have not been truncated yet.
+    pub(crate) fn records(
+        &self,
+        queue_id: &str,
+        start_after: Option<u64>,
+    ) -> crate::Result<Vec<Vec<u8>>> {
+        let real_queue_id = format!("{QUICKWIT_CF_PREFIX}{queue_id}");
+        let starting_bound = match start_after {
+            Some(pos) => Bound::Excluded(pos),
+            None => Bound::Unbounded,
+        };
+        let records = self
+            .record_log
+            .range(&real_queue_id, (starting_bound, Bound::Unbounded))
+            .ok_or_else(|| crate::IngestServiceError::IndexNotFound {
+                index_id: queue_id.to_string(),
+            })?
+            .map(|(_position, record)| record.to_vec())
+            .collect();
+        Ok(records)
     }
 
     // Streams messages from in `]after_position, +∞[`.
@@ -194,6 +222,84 @@ impl Queues {
         self.fetch(queue_id, None, None)
     }
 
+    // Returns the position of the last record of the queue that has not been truncated yet.
+    pub(crate) fn last_position(&self, queue_id: &str) -> Option<u64> {
+        let real_queue_id = format!("{QUICKWIT_CF_PREFIX}{queue_id}");
+        self.record_log
+            .range(&real_queue_id, (Bound::Unbounded, Bound::Unbounded))?
+            .last()
+            .map(|(position, _record)| position)
+    }
+
+    // Returns the number of records of the queue in `]start_after, +∞[` that have not been
+    // truncated yet.
+    pub(crate) fn num_records_after(&self, queue_id: &str, start_after: Option<u64>) -> usize {
+        let real_queue_id = format!("{QUICKWIT_CF_PREFIX}{queue_id}");
+        let starting_bound = match start_after {
+            Some(pos) => Bound::Excluded(pos),
+            None => Bound::Unbounded,
+        };
+        self.record_log
+            .range(&real_queue_id, (starting_bound, Bound::Unbounded))
+            .map(|records| records.count())
+            .unwrap_or(0)
+    }
+
+    // Appends a record to the log of the committed idempotency keys, creating it if necessary,
+    // and returns its position.
+    pub(crate) async fn append_idempotency_key_record(
+        &mut self,
+        record: &[u8],
+        ctx: &ActorContext<IngestApiService>,
+    ) -> crate::Result<Option<u64>> {
+        if !self.record_log.queue_exists(IDEMPOTENCY_KEYS_QUEUE_ID) {
+            ctx.protect_future(self.record_log.create_queue(IDEMPOTENCY_KEYS_QUEUE_ID))
+                .await
+                .map_err(|error| match error {
+                    CreateQueueError::AlreadyExists => IngestServiceError::Corruption(
+                        "The idempotency keys queue already exists.".to_string(),
+                    ),
+                    CreateQueueError::IoError(io_error) => io_error.into(),
+                })?;
+        }
+        let position_opt = ctx
+            .protect_future(self.record_log.append_records(
+                IDEMPOTENCY_KEYS_QUEUE_ID,
+                None,
+                std::iter::once(record),
+            ))
+            .await?;
+        Ok(position_opt)
+    }
+
+    // Returns the records of the log of the committed idempotency keys that have not been
+    // truncated yet, along with their position.
+    pub(crate) fn idempotency_key_records(&self) -> Vec<(u64, Vec<u8>)> {
+        let Some(records) = self
+            .record_log
+            .range(IDEMPOTENCY_KEYS_QUEUE_ID, (Bound::Unbounded, Bound::Unbounded))
+        else {
+            return Vec::new();
+        };
+        records
+            .map(|(position, record)| (position, record.to_vec()))
+            .collect()
+    }
+
+    // Truncates the log of the committed idempotency keys up to `up_to_position_included`.
+    pub(crate) async fn truncate_idempotency_key_records(
+        &mut self,
+        up_to_position_included: u64,
+        ctx: &ActorContext<IngestApiService>,
+    ) -> crate::Result<()> {
+        ctx.protect_future(
+            self.record_log
+                .truncate(IDEMPOTENCY_KEYS_QUEUE_ID, up_to_position_included),
+        )
+        .await?;
+        Ok(())
+    }
+
     pub fn list_queues(&self) -> crate::Result<ListQueuesResponse> {
         Ok(ListQueuesResponse {
             queues: self
@@ -212,6 +318,30 @@ impl Queues {
     pub(crate) fn memory_usage(&self) -> usize {
         self.record_log.in_memory_size()
     }
+
+    /// Returns the number of records that have not been truncated yet across all the queues.
+    pub(crate) fn num_pending_records(&self) -> usize {
+        self.num_pending_records_per_queue().values().sum()
+    }
+
+    /// Returns the number of records that have not been truncated yet in each queue. Replica
+    /// queues are not indexed, so their records are not pending.
+    pub(crate) fn num_pending_records_per_queue(&self) -> HashMap<String, usize> {
+        self.record_log
+            .list_queues()
+            .filter_map(|real_queue_id| {
+                let queue_id = real_queue_id.strip_prefix(QUICKWIT_CF_PREFIX)?;
+                if is_replica_queue_id(queue_id) {
+                    return None;
+                }
+                let num_records = self
+                    .record_log
+                    .range(real_queue_id, (Bound::Unbounded, Bound::Unbounded))?
+                    .count();
+                Some((queue_id.to_string(), num_records))
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
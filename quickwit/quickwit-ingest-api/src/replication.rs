@@ -0,0 +1,117 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Documents ingested with `ack=replicas` are appended to the queue of the index on the leader
+//! indexer and to a replica queue on each of the peer indexers they are replicated to. Replica
+//! queues are never indexed: they are truncated once their retention period expires, or
+//! re-appended to the index queues of the peer when the leader is lost.
+//!
+//! The documents are replicated once the leader has appended them, and each replica record is
+//! tagged with the partition and the position of the document in the queue of the leader. Upon
+//! recovery, the records already published by the leader and the records replicated twice by
+//! retries are skipped.
+
+/// Prefix of the IDs of the replica queues. Index IDs cannot contain slashes, so replica queues
+/// never collide with index queues.
+pub const REPLICA_QUEUE_PREFIX: &str = "_replica/";
+
+/// Returns the ID of the queue holding the replicas of the documents ingested into the index
+/// `index_id` on the leader indexer `leader_node_id`.
+pub fn replica_queue_id(leader_node_id: &str, index_id: &str) -> String {
+    format!("{REPLICA_QUEUE_PREFIX}{leader_node_id}/{index_id}")
+}
+
+/// Parses a replica queue ID into the leader node ID and the index ID. Returns `None` if the
+/// queue is not a replica queue.
+pub fn parse_replica_queue_id(queue_id: &str) -> Option<(&str, &str)> {
+    queue_id
+        .strip_prefix(REPLICA_QUEUE_PREFIX)?
+        .rsplit_once('/')
+}
+
+/// Maximum length of the partition IDs tagging the replica records. Partition IDs are ULIDs, far
+/// shorter than that.
+pub(crate) const MAX_PARTITION_ID_LEN: usize = u8::MAX as usize;
+
+pub(crate) fn is_replica_queue_id(queue_id: &str) -> bool {
+    queue_id.starts_with(REPLICA_QUEUE_PREFIX)
+}
+
+/// Prepends the partition ID of the leader and the position of the document in the queue of the
+/// leader to a replicated record. The partition ID must be at most `MAX_PARTITION_ID_LEN` bytes
+/// long.
+pub(crate) fn encode_replica_record(partition_id: &str, position: u64, record: &[u8]) -> Vec<u8> {
+    debug_assert!(partition_id.len() <= MAX_PARTITION_ID_LEN);
+    let mut replica_record = Vec::with_capacity(1 + partition_id.len() + 8 + record.len());
+    replica_record.push(partition_id.len() as u8);
+    replica_record.extend_from_slice(partition_id.as_bytes());
+    replica_record.extend_from_slice(&position.to_be_bytes());
+    replica_record.extend_from_slice(record);
+    replica_record
+}
+
+/// Parses a replica record into the partition ID of the leader, the position of the document in
+/// the queue of the leader, and the original record.
+pub(crate) fn decode_replica_record(replica_record: &[u8]) -> Option<(&str, u64, &[u8])> {
+    let (partition_id_len, rest) = replica_record.split_first()?;
+    let partition_id_len = *partition_id_len as usize;
+
+    if rest.len() < partition_id_len + 8 {
+        return None;
+    }
+    let (partition_id_bytes, rest) = rest.split_at(partition_id_len);
+    let partition_id = std::str::from_utf8(partition_id_bytes).ok()?;
+    let (position_bytes, record) = rest.split_at(8);
+    let position = u64::from_be_bytes(position_bytes.try_into().ok()?);
+    Some((partition_id, position, record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_queue_id() {
+        let queue_id = replica_queue_id("node/1", "my-index");
+        assert_eq!(queue_id, "_replica/node/1/my-index");
+        assert!(is_replica_queue_id(&queue_id));
+        assert_eq!(
+            parse_replica_queue_id(&queue_id),
+            Some(("node/1", "my-index"))
+        );
+        assert!(!is_replica_queue_id("my-index"));
+        assert_eq!(parse_replica_queue_id("my-index"), None);
+    }
+
+    #[test]
+    fn test_replica_record_serialization() {
+        let replica_record = encode_replica_record("partition-0", 42, b"\x00{}");
+        assert_eq!(
+            decode_replica_record(&replica_record),
+            Some(("partition-0", 42, &b"\x00{}"[..]))
+        );
+        let replica_record = encode_replica_record("partition-0", 42, b"");
+        assert_eq!(
+            decode_replica_record(&replica_record),
+            Some(("partition-0", 42, &b""[..]))
+        );
+        assert_eq!(decode_replica_record(b""), None);
+        assert_eq!(decode_replica_record(b"\x0bpartition-0"), None);
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+//! The documents ingested into an index on an indexer are spread over one or several shards, each
+//! consumed by its own ingest API pipeline so that an index can be indexed faster than a single
+//! pipeline allows. The first shard is the historical queue of the index, so that an index with a
+//! single shard is laid out as before. The control plane adjusts the number of shards of each
+//! index to its ingestion lag.
+//!
+//! Each shard is tracked in the source checkpoint under its own partition, derived from the
+//! partition of the queues of the indexer.
+
+use crate::replication::is_replica_queue_id;
+
+/// Separator between the index ID and the ordinal of the shard in the ID of the shard queues.
+/// Index IDs cannot contain slashes, so shard queues never collide with index queues.
+const SHARD_QUEUE_SEPARATOR: &str = "/shard-";
+
+/// Returns the ID of the queue of the shard `shard_ord` of the index `index_id`. The first shard
+/// is the queue of the index itself.
+pub fn shard_queue_id(index_id: &str, shard_ord: usize) -> String {
+    if shard_ord == 0 {
+        return index_id.to_string();
+    }
+    format!("{index_id}{SHARD_QUEUE_SEPARATOR}{shard_ord}")
+}
+
+/// Parses a queue ID into the index ID and the ordinal of the shard. Returns `None` if the queue
+/// is a replica queue.
+pub fn parse_shard_queue_id(queue_id: &str) -> Option<(&str, usize)> {
+    if is_replica_queue_id(queue_id) {
+        return None;
+    }
+    if let Some((index_id, shard_ord_str)) = queue_id.rsplit_once(SHARD_QUEUE_SEPARATOR) {
+        if let Ok(shard_ord) = shard_ord_str.parse::<usize>() {
+            return Some((index_id, shard_ord));
+        }
+    }
+    Some((queue_id, 0))
+}
+
+/// Returns the partition ID under which the shard `shard_ord` is tracked in the source
+/// checkpoint, given the partition ID of the queues of the indexer.
+pub fn shard_partition_id(partition_id: &str, shard_ord: usize) -> String {
+    if shard_ord == 0 {
+        return partition_id.to_string();
+    }
+    format!("{partition_id}{SHARD_QUEUE_SEPARATOR}{shard_ord}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replica_queue_id;
+
+    #[test]
+    fn test_shard_queue_id() {
+        assert_eq!(shard_queue_id("my-index", 0), "my-index");
+        assert_eq!(shard_queue_id("my-index", 2), "my-index/shard-2");
+
+        assert_eq!(parse_shard_queue_id("my-index"), Some(("my-index", 0)));
+        assert_eq!(
+            parse_shard_queue_id("my-index/shard-2"),
+            Some(("my-index", 2))
+        );
+        assert_eq!(
+            parse_shard_queue_id(&replica_queue_id("node-1", "my-index")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_shard_partition_id() {
+        assert_eq!(shard_partition_id("partition-0", 0), "partition-0");
+        assert_eq!(shard_partition_id("partition-0", 2), "partition-0/shard-2");
+    }
+}
@@ -0,0 +1,504 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_opentelemetry::otlp::OTEL_TRACE_INDEX_ID;
+use quickwit_proto::jaeger::api_v2::DependencyLink;
+use quickwit_proto::jaeger::storage::v1::{GetDependenciesRequest, GetDependenciesResponse};
+use quickwit_proto::{ScrollRequest, SearchRequest};
+use quickwit_search::SearchService;
+use serde::Deserialize;
+use time::OffsetDateTime;
+use tonic::Status;
+use tracing::{debug, instrument, warn};
+
+use crate::{json_deserialize, JaegerResult, JaegerService};
+
+/// Width of the time buckets for which the dependency links are materialized.
+const BUCKET_SECS: i64 = 3600;
+
+/// Interval at which the materialization job computes the dependency links of the buckets that
+/// have been completed since its last run.
+const MATERIALIZATION_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+const SCROLL_PAGE_SIZE: u64 = 1_000;
+
+const SCROLL_TTL_SECS: u32 = 60;
+
+/// Number of calls between two services, keyed by `(parent service, child service)`.
+type DependencyEdges = HashMap<(String, String), u64>;
+
+/// The subset of the span fields required to compute the service dependency graph.
+#[derive(Debug, Deserialize)]
+struct DependencySpan {
+    trace_id: String,
+    span_id: String,
+    #[serde(default)]
+    parent_span_id: Option<String>,
+    service_name: String,
+    span_start_timestamp_nanos: u64,
+}
+
+/// Computes and caches the service dependency links of the traces index, one time bucket at a
+/// time. A bucket is cached once it is complete, i.e. once the spans of the traces that started
+/// in it are no longer expected to be ingested.
+pub(crate) struct DependenciesMaterializer {
+    search_service: Arc<dyn SearchService>,
+    lookback_period_secs: i64,
+    max_trace_duration_secs: i64,
+    max_dependency_spans: u64,
+    buckets: Mutex<BTreeMap<i64, Arc<DependencyEdges>>>,
+}
+
+impl DependenciesMaterializer {
+    pub(crate) fn new(
+        search_service: Arc<dyn SearchService>,
+        lookback_period_secs: i64,
+        max_trace_duration_secs: i64,
+        max_dependency_spans: u64,
+    ) -> Self {
+        Self {
+            search_service,
+            lookback_period_secs,
+            max_trace_duration_secs,
+            max_dependency_spans,
+            buckets: Mutex::default(),
+        }
+    }
+
+    fn is_complete(&self, bucket_start: i64, now: i64) -> bool {
+        bucket_start + BUCKET_SECS + self.max_trace_duration_secs <= now
+    }
+
+    fn cached_bucket(&self, bucket_start: i64) -> Option<Arc<DependencyEdges>> {
+        self.buckets
+            .lock()
+            .expect("The lock should not be poisoned.")
+            .get(&bucket_start)
+            .cloned()
+    }
+
+    fn cache_bucket(&self, bucket_start: i64, edges: Arc<DependencyEdges>) {
+        self.buckets
+            .lock()
+            .expect("The lock should not be poisoned.")
+            .insert(bucket_start, edges);
+    }
+
+    async fn bucket_edges(
+        &self,
+        bucket_start: i64,
+        now: i64,
+    ) -> JaegerResult<Arc<DependencyEdges>> {
+        if let Some(edges) = self.cached_bucket(bucket_start) {
+            return Ok(edges);
+        }
+        let edges = Arc::new(self.compute_bucket_edges(bucket_start).await?);
+
+        if self.is_complete(bucket_start, now) {
+            self.cache_bucket(bucket_start, edges.clone());
+        }
+        Ok(edges)
+    }
+
+    #[instrument("compute_dependencies", skip(self))]
+    async fn compute_bucket_edges(&self, bucket_start: i64) -> JaegerResult<DependencyEdges> {
+        let bucket_end = bucket_start + BUCKET_SECS;
+        // The parent of a span starts at most `max_trace_duration` before it.
+        let spans = self
+            .fetch_spans(bucket_start - self.max_trace_duration_secs, bucket_end)
+            .await?;
+        let edges = compute_dependency_edges(&spans, to_nanos(bucket_start), to_nanos(bucket_end));
+        debug!(num_spans=%spans.len(), num_edges=%edges.len(), "Computed dependency links.");
+        Ok(edges)
+    }
+
+    async fn fetch_spans(
+        &self,
+        start_timestamp: i64,
+        end_timestamp: i64,
+    ) -> JaegerResult<Vec<DependencySpan>> {
+        let search_request = SearchRequest {
+            index_id: OTEL_TRACE_INDEX_ID.to_string(),
+            query: "*".to_string(),
+            aggregation_request: None,
+            max_hits: SCROLL_PAGE_SIZE.min(self.max_dependency_spans),
+            start_timestamp: Some(start_timestamp),
+            end_timestamp: Some(end_timestamp),
+            search_fields: Vec::new(),
+            start_offset: 0,
+            sort_order: None,
+            sort_by_field: None,
+            snippet_fields: Vec::new(),
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: Some(SCROLL_TTL_SECS),
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
+        };
+        let mut search_response = self.search_service.root_search(search_request).await?;
+        let mut spans = Vec::new();
+
+        while !search_response.hits.is_empty() {
+            for hit in search_response.hits {
+                spans.push(json_deserialize::<DependencySpan>(&hit.json, "span")?);
+            }
+            if spans.len() as u64 >= self.max_dependency_spans {
+                warn!(
+                    max_dependency_spans=%self.max_dependency_spans,
+                    "Reached the maximum number of spans scanned to compute the dependency links."
+                );
+                spans.truncate(self.max_dependency_spans as usize);
+                break;
+            }
+            let Some(scroll_id) = search_response.scroll_id else {
+                break;
+            };
+            let scroll_request = ScrollRequest {
+                scroll_id,
+                scroll_ttl_secs: Some(SCROLL_TTL_SECS),
+            };
+            search_response = self.search_service.scroll(scroll_request).await?;
+        }
+        Ok(spans)
+    }
+
+    /// Computes the dependency links of the complete buckets of the lookback period that are not
+    /// cached yet and evicts the buckets that fell out of it. Returns the number of computed
+    /// buckets.
+    async fn materialize(&self, now: i64) -> JaegerResult<usize> {
+        let lookback_start = now - self.lookback_period_secs;
+        self.buckets
+            .lock()
+            .expect("The lock should not be poisoned.")
+            .retain(|bucket_start, _| *bucket_start + BUCKET_SECS > lookback_start);
+
+        let mut num_computed_buckets = 0;
+
+        for bucket_start in bucket_starts(lookback_start, now).rev() {
+            if !self.is_complete(bucket_start, now) || self.cached_bucket(bucket_start).is_some() {
+                continue;
+            }
+            let edges = self.compute_bucket_edges(bucket_start).await?;
+            self.cache_bucket(bucket_start, Arc::new(edges));
+            num_computed_buckets += 1;
+        }
+        Ok(num_computed_buckets)
+    }
+}
+
+impl JaegerService {
+    #[instrument("get_dependencies", skip_all)]
+    pub(crate) async fn get_dependencies_inner(
+        &self,
+        request: GetDependenciesRequest,
+    ) -> JaegerResult<GetDependenciesResponse> {
+        debug!(request=?request, "`get_dependencies` request");
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let end = request
+            .end_time
+            .map(|ts| ts.seconds)
+            .unwrap_or(now)
+            .min(now);
+        let start = request
+            .start_time
+            .map(|ts| ts.seconds)
+            .unwrap_or(i64::MIN)
+            .max(end - self.lookback_period_secs);
+
+        if start > end {
+            return Err(Status::invalid_argument(
+                "Start time must be before end time.",
+            ));
+        }
+        let mut edges = DependencyEdges::new();
+
+        for bucket_start in bucket_starts(start, end) {
+            let bucket_edges = self.dependencies.bucket_edges(bucket_start, now).await?;
+
+            for ((parent, child), call_count) in bucket_edges.iter() {
+                *edges.entry((parent.clone(), child.clone())).or_default() += call_count;
+            }
+        }
+        let mut dependencies: Vec<DependencyLink> = edges
+            .into_iter()
+            .map(|((parent, child), call_count)| DependencyLink {
+                parent,
+                child,
+                call_count,
+                source: String::new(),
+            })
+            .collect();
+        dependencies.sort_unstable_by(|left, right| {
+            (&left.parent, &left.child).cmp(&(&right.parent, &right.child))
+        });
+        debug!(dependencies=?dependencies, "`get_dependencies` response");
+        let response = GetDependenciesResponse { dependencies };
+        Ok(response)
+    }
+
+    /// Spawns the job that periodically materializes the service dependency links of the
+    /// lookback period so that `get_dependencies` requests are served from the cache. The job
+    /// stops once the service and all its clones are dropped.
+    pub fn spawn_dependencies_materialization(&self) {
+        let materializer = Arc::downgrade(&self.dependencies);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MATERIALIZATION_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let Some(materializer) = materializer.upgrade() else {
+                    break;
+                };
+                let now = OffsetDateTime::now_utc().unix_timestamp();
+
+                match materializer.materialize(now).await {
+                    Ok(num_computed_buckets) => {
+                        debug!(
+                            num_computed_buckets=%num_computed_buckets,
+                            "Materialized dependency links."
+                        );
+                    }
+                    Err(status) => {
+                        warn!(status=?status, "Failed to materialize dependency links.");
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Returns the start of the buckets overlapping the time range `[start_secs, end_secs)`, or the
+/// bucket containing `start_secs` if the range is empty.
+fn bucket_starts(start_secs: i64, end_secs: i64) -> impl DoubleEndedIterator<Item = i64> {
+    let first_bucket_start = start_secs - start_secs.rem_euclid(BUCKET_SECS);
+    (first_bucket_start..end_secs.max(start_secs + 1)).step_by(BUCKET_SECS as usize)
+}
+
+fn to_nanos(timestamp_secs: i64) -> u64 {
+    timestamp_secs.max(0) as u64 * 1_000_000_000
+}
+
+/// Counts the calls between services for the spans that started in
+/// `[bucket_start_nanos, bucket_end_nanos)`. A call is a span whose parent span belongs to a
+/// different service. Spans whose parent is missing from `spans` are ignored.
+fn compute_dependency_edges(
+    spans: &[DependencySpan],
+    bucket_start_nanos: u64,
+    bucket_end_nanos: u64,
+) -> DependencyEdges {
+    let services: HashMap<(&str, &str), &str> = spans
+        .iter()
+        .map(|span| {
+            (
+                (span.trace_id.as_str(), span.span_id.as_str()),
+                span.service_name.as_str(),
+            )
+        })
+        .collect();
+    let mut edges = DependencyEdges::new();
+
+    for span in spans {
+        if span.span_start_timestamp_nanos < bucket_start_nanos
+            || span.span_start_timestamp_nanos >= bucket_end_nanos
+        {
+            continue;
+        }
+        let Some(parent_span_id) = &span.parent_span_id else {
+            continue;
+        };
+        let Some(parent_service_name) =
+            services.get(&(span.trace_id.as_str(), parent_span_id.as_str()))
+        else {
+            continue;
+        };
+        if *parent_service_name == span.service_name {
+            continue;
+        }
+        *edges
+            .entry((parent_service_name.to_string(), span.service_name.clone()))
+            .or_default() += 1;
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::JaegerConfig;
+    use quickwit_proto::jaeger::storage::v1::dependencies_reader_plugin_server::DependenciesReaderPlugin;
+    use quickwit_search::MockSearchService;
+    use serde_json::json;
+
+    use super::*;
+
+    fn span(
+        trace_id: &str,
+        span_id: &str,
+        parent_span_id: Option<&str>,
+        service_name: &str,
+        span_start_timestamp_secs: u64,
+    ) -> DependencySpan {
+        DependencySpan {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            parent_span_id: parent_span_id.map(|parent_span_id| parent_span_id.to_string()),
+            service_name: service_name.to_string(),
+            span_start_timestamp_nanos: span_start_timestamp_secs * 1_000_000_000,
+        }
+    }
+
+    #[test]
+    fn test_bucket_starts() {
+        assert_eq!(bucket_starts(0, 0).collect::<Vec<_>>(), [0]);
+        assert_eq!(bucket_starts(3600, 7200).collect::<Vec<_>>(), [3600]);
+        assert_eq!(bucket_starts(3601, 7201).collect::<Vec<_>>(), [3600, 7200]);
+        assert_eq!(
+            bucket_starts(1000, 10_000).rev().collect::<Vec<_>>(),
+            [7200, 3600, 0]
+        );
+    }
+
+    #[test]
+    fn test_compute_dependency_edges() {
+        let spans = vec![
+            span("trace-1", "span-1", None, "frontend", 3590),
+            span("trace-1", "span-2", Some("span-1"), "backend", 3600),
+            span("trace-1", "span-3", Some("span-2"), "backend", 3601),
+            span("trace-1", "span-4", Some("span-2"), "database", 3602),
+            span("trace-1", "span-5", Some("span-1"), "backend", 7200),
+            span("trace-2", "span-1", None, "frontend", 3700),
+            span("trace-2", "span-2", Some("span-1"), "backend", 3701),
+            span("trace-2", "span-3", Some("span-missing"), "cache", 3702),
+        ];
+        let edges = compute_dependency_edges(&spans, to_nanos(3600), to_nanos(7200));
+        let expected_edges = DependencyEdges::from_iter([
+            (("frontend".to_string(), "backend".to_string()), 2),
+            (("backend".to_string(), "database".to_string()), 1),
+        ]);
+        assert_eq!(edges, expected_edges);
+    }
+
+    #[tokio::test]
+    async fn test_get_dependencies() {
+        let mut service = MockSearchService::new();
+        service
+            .expect_root_search()
+            .withf(|req| {
+                req.index_id == "otel-trace-v0"
+                    && req.scroll_ttl_secs.is_some()
+                    && req.start_timestamp.is_some()
+            })
+            .returning(|_| {
+                let hits = [
+                    json!({
+                        "trace_id": "trace-1",
+                        "span_id": "span-1",
+                        "service_name": "frontend",
+                        "span_start_timestamp_nanos": 0
+                    }),
+                    json!({
+                        "trace_id": "trace-1",
+                        "span_id": "span-2",
+                        "parent_span_id": "span-1",
+                        "service_name": "backend",
+                        "span_start_timestamp_nanos": 0
+                    }),
+                ]
+                .into_iter()
+                .map(|hit| quickwit_proto::Hit {
+                    json: hit.to_string(),
+                    partial_hit: None,
+                    snippet: None,
+                })
+                .collect();
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 2,
+                    hits,
+                    elapsed_time_micros: 0,
+                    errors: Vec::new(),
+                    aggregation: None,
+                    scroll_id: Some("scroll-1".to_string()),
+                    timed_out: false,
+                    failed_splits: Vec::new(),
+                    split_metadata: HashMap::new(),
+                })
+            });
+        service
+            .expect_scroll()
+            .withf(|req| req.scroll_id == "scroll-1")
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 2,
+                    hits: Vec::new(),
+                    elapsed_time_micros: 0,
+                    errors: Vec::new(),
+                    aggregation: None,
+                    scroll_id: Some("scroll-1".to_string()),
+                    timed_out: false,
+                    failed_splits: Vec::new(),
+                    split_metadata: HashMap::new(),
+                })
+            });
+        let service = Arc::new(service);
+        let jaeger = JaegerService::new(JaegerConfig::default(), service);
+
+        let request = tonic::Request::new(GetDependenciesRequest {
+            start_time: Some(prost_types::Timestamp {
+                seconds: 0,
+                nanos: 0,
+            }),
+            end_time: Some(prost_types::Timestamp {
+                seconds: 1,
+                nanos: 0,
+            }),
+        });
+        let response = jaeger.get_dependencies(request).await.unwrap().into_inner();
+        assert_eq!(
+            response.dependencies,
+            [DependencyLink {
+                parent: "frontend".to_string(),
+                child: "backend".to_string(),
+                call_count: 1,
+                source: String::new(),
+            }]
+        );
+        // The bucket is complete, so its dependency links are cached.
+        assert!(jaeger.dependencies.cached_bucket(0).is_some());
+    }
+}
@@ -38,11 +38,12 @@ use quickwit_proto::jaeger::api_v2::{
     KeyValue as JaegerKeyValue, Log as JaegerLog, Process as JaegerProcess, Span as JaegerSpan,
     SpanRef as JaegerSpanRef, SpanRefType as JaegerSpanRefType, ValueType,
 };
+use quickwit_proto::jaeger::storage::v1::dependencies_reader_plugin_server::DependenciesReaderPlugin;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPlugin;
 use quickwit_proto::jaeger::storage::v1::{
-    FindTraceIDsRequest, FindTraceIDsResponse, FindTracesRequest, GetOperationsRequest,
-    GetOperationsResponse, GetServicesRequest, GetServicesResponse, GetTraceRequest, Operation,
-    SpansResponseChunk, TraceQueryParameters,
+    FindTraceIDsRequest, FindTraceIDsResponse, FindTracesRequest, GetDependenciesRequest,
+    GetDependenciesResponse, GetOperationsRequest, GetOperationsResponse, GetServicesRequest,
+    GetServicesResponse, GetTraceRequest, Operation, SpansResponseChunk, TraceQueryParameters,
 };
 use quickwit_proto::{ListTermsRequest, SearchRequest};
 use quickwit_search::{FindTraceIdsCollector, SearchService};
@@ -57,8 +58,10 @@ use tonic::{Request, Response, Status};
 use tracing::field::Empty;
 use tracing::{debug, error, instrument, warn, Span as RuntimeSpan};
 
+use crate::dependencies::DependenciesMaterializer;
 use crate::metrics::JAEGER_SERVICE_METRICS;
 
+mod dependencies;
 mod metrics;
 
 // OpenTelemetry to Jaeger Transformation
@@ -70,20 +73,31 @@ type JaegerResult<T> = Result<T, Status>;
 
 type SpanStream = ReceiverStream<Result<SpansResponseChunk, Status>>;
 
+#[derive(Clone)]
 pub struct JaegerService {
     search_service: Arc<dyn SearchService>,
     lookback_period_secs: i64,
     max_trace_duration_secs: i64,
     max_fetch_spans: u64,
+    dependencies: Arc<DependenciesMaterializer>,
 }
 
 impl JaegerService {
     pub fn new(config: JaegerConfig, search_service: Arc<dyn SearchService>) -> Self {
+        let lookback_period_secs = config.lookback_period().as_secs() as i64;
+        let max_trace_duration_secs = config.max_trace_duration().as_secs() as i64;
+        let dependencies = DependenciesMaterializer::new(
+            search_service.clone(),
+            lookback_period_secs,
+            max_trace_duration_secs,
+            config.max_dependency_spans.get(),
+        );
         Self {
             search_service,
-            lookback_period_secs: config.lookback_period().as_secs() as i64,
-            max_trace_duration_secs: config.max_trace_duration().as_secs() as i64,
+            lookback_period_secs,
+            max_trace_duration_secs,
             max_fetch_spans: config.max_fetch_spans.get(),
+            dependencies: Arc::new(dependencies),
         }
     }
 
@@ -107,6 +121,8 @@ impl JaegerService {
             end_timestamp: None,
             start_key: None,
             end_key: None,
+            query: None,
+            sort_by_doc_count: false,
         };
         let search_response = self.search_service.root_list_terms(search_request).await?;
         let services: Vec<String> = search_response
@@ -143,6 +159,8 @@ impl JaegerService {
             end_timestamp: None,
             start_key,
             end_key,
+            query: None,
+            sort_by_doc_count: false,
         };
         let search_response = self.search_service.root_list_terms(search_request).await?;
         let operations: Vec<Operation> = search_response
@@ -261,6 +279,25 @@ impl JaegerService {
             sort_order: None,
             sort_by_field: None,
             snippet_fields: Vec::new(),
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let search_response = self.search_service.root_search(search_request).await?;
 
@@ -309,6 +346,25 @@ impl JaegerService {
             sort_by_field: None,
             aggregation_request: None,
             snippet_fields: Vec::new(),
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         };
         let search_response = match self.search_service.root_search(search_request).await {
             Ok(search_response) => search_response,
@@ -502,6 +558,19 @@ impl SpanReaderPlugin for JaegerService {
     }
 }
 
+#[async_trait]
+impl DependenciesReaderPlugin for JaegerService {
+    async fn get_dependencies(
+        &self,
+        request: Request<GetDependenciesRequest>,
+    ) -> Result<Response<GetDependenciesResponse>, Status> {
+        metrics!(
+            self.get_dependencies_inner(request.into_inner()).await,
+            [get_dependencies, OTEL_TRACE_INDEX_ID]
+        );
+    }
+}
+
 fn extract_term(term_bytes: &[u8]) -> String {
     tantivy::Term::wrap(term_bytes)
         .as_str()
@@ -1744,6 +1813,7 @@ mod tests {
                     ],
                     elapsed_time_micros: 0,
                     errors: Vec::new(),
+                    doc_counts: Vec::new(),
                 })
             });
 
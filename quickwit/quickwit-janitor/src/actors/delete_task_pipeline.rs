@@ -168,7 +168,17 @@ impl DeleteTaskPipeline {
         let doc_mapper =
             build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)?;
         let tag_fields = doc_mapper.tag_named_fields()?;
-        let packager = Packager::new("MergePackager", tag_fields, uploader_mailbox);
+        let range_pruning_fields = doc_mapper.range_pruning_named_fields()?;
+        let bloom_filter_fields = doc_mapper.bloom_filter_named_fields()?;
+        let vector_fields = doc_mapper.vector_fields();
+        let packager = Packager::new(
+            "MergePackager",
+            tag_fields,
+            range_pruning_fields,
+            bloom_filter_fields,
+            vector_fields,
+            uploader_mailbox,
+        );
         let (packager_mailbox, packager_supervisor_handler) = ctx.spawn_actor().supervise(packager);
         let index_pipeline_id = IndexingPipelineId {
             index_id: self.index_id.to_string(),
@@ -97,6 +97,8 @@ impl DeleteTaskService {
             .list_indexes_metadatas()
             .await?
             .into_iter()
+            // Mounted indexes are read-only: their splits are never rewritten.
+            .filter(|index_metadata| !index_metadata.index_config().is_mounted())
             .map(|index_metadata| {
                 (
                     index_metadata.index_id().to_string(),
@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -25,6 +25,7 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_config::GarbageCollectionSettings;
 use quickwit_metastore::Metastore;
 use quickwit_storage::StorageUriResolver;
 use serde::Serialize;
@@ -32,7 +33,9 @@ use tracing::{error, info};
 
 use crate::garbage_collection::run_garbage_collect;
 
-const RUN_INTERVAL: Duration = Duration::from_secs(60); // 1 minutes
+/// Default interval between two garbage collection runs on an index. Indexes may configure a
+/// longer interval with their `garbage_collection.run_interval` setting.
+const RUN_INTERVAL: Duration = GarbageCollectionSettings::MIN_RUN_INTERVAL; // 1 minutes
 /// Staged files needs to be deleted if there was a failure.
 /// TODO ideally we want clean up all staged splits every time we restart the indexing pipeline, but
 /// the grace period strategy should do the job for the moment.
@@ -42,9 +45,13 @@ const STAGED_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60 * 24); // 24 h
 /// We deal this probably by introducing a grace period. A split is first marked as delete,
 /// and hence won't be selected for search. After a few minutes, once it reasonably safe to assume
 /// that all queries involving this split have terminated, we effectively delete the split.
-/// This duration is controlled by `DELETION_GRACE_PERIOD`.
+/// This duration is controlled by `DELETION_GRACE_PERIOD`, unless the index configures its own
+/// `garbage_collection.deletion_grace_period`.
 const DELETION_GRACE_PERIOD: Duration = Duration::from_secs(120); // 2 min
 
+/// Default number of batches of splits deleted concurrently for an index.
+const DEFAULT_MAX_CONCURRENT_DELETIONS: usize = 1;
+
 const MAX_CONCURRENT_STORAGE_REQUESTS: usize = if cfg!(test) { 2 } else { 10 };
 
 #[derive(Clone, Debug, Default, Serialize)]
@@ -63,6 +70,9 @@ pub struct GarbageCollectorCounters {
     pub num_failed_storage_resolution: usize,
     /// The number of splits that were unable to be removed.
     pub num_failed_splits: usize,
+    /// The number of garbage collection runs skipped because the index garbage collection is
+    /// paused.
+    pub num_paused_gc_run_on_index: usize,
 }
 
 #[derive(Debug)]
@@ -72,6 +82,8 @@ struct Loop;
 pub struct GarbageCollector {
     metastore: Arc<dyn Metastore>,
     storage_resolver: StorageUriResolver,
+    /// Pass during which each index was last garbage collected.
+    last_gc_passes: HashMap<String, usize>,
     counters: GarbageCollectorCounters,
 }
 
@@ -80,6 +92,7 @@ impl GarbageCollector {
         Self {
             metastore,
             storage_resolver,
+            last_gc_passes: HashMap::new(),
             counters: GarbageCollectorCounters::default(),
         }
     }
@@ -97,40 +110,86 @@ impl GarbageCollector {
                 return;
             }
         };
-        info!(index_ids=%index_metadatas.iter().map(|im| im.index_id()).join(", "), "Garbage collecting indexes.");
-
-        let index_ids_to_storage_iter = index_metadatas
-            .into_iter()
-            .filter_map(|index_metadata| {
-                let index_uri = index_metadata.index_uri();
-                match self.storage_resolver.resolve(index_uri) {
-                    Ok(storage) => Some((index_metadata.index_id().to_string(), storage)),
-                    Err(error) => {
-                        self.counters.num_failed_storage_resolution += 1;
-                        error!(index=%index_metadata.index_id(), error=?error, "Failed to resolve the index storage Uri.");
-                        None
-                    },
+        let index_ids: HashSet<&str> = index_metadatas
+            .iter()
+            .map(|index_metadata| index_metadata.index_id())
+            .collect();
+        self.last_gc_passes
+            .retain(|index_id, _| index_ids.contains(index_id.as_str()));
+
+        let mut indexes_to_gc = Vec::new();
+        for index_metadata in &index_metadatas {
+            // The split files of mounted indexes are owned by another cluster.
+            if index_metadata.index_config().is_mounted() {
+                continue;
+            }
+            let gc_settings = &index_metadata.index_config().garbage_collection;
+            if gc_settings.paused {
+                self.counters.num_paused_gc_run_on_index += 1;
+                continue;
+            }
+            let run_interval = gc_settings
+                .run_interval()
+                .ok()
+                .flatten()
+                .unwrap_or(RUN_INTERVAL);
+            if let Some(last_gc_pass) = self.last_gc_passes.get(index_metadata.index_id()) {
+                let elapsed = RUN_INTERVAL * (self.counters.num_passes - last_gc_pass) as u32;
+                if elapsed < run_interval {
+                    continue;
                 }
-            });
-
-        let run_gc_tasks: Vec<_> = index_ids_to_storage_iter
-            .map(|(index_id, storage)| {
-                let moved_metastore = self.metastore.clone();
-                async move {
-                    let run_gc_result = run_garbage_collect(
-                        &index_id,
-                        storage,
-                        moved_metastore,
-                        STAGED_GRACE_PERIOD,
-                        DELETION_GRACE_PERIOD,
-                        false,
-                        Some(ctx),
-                    )
-                    .await;
-
-                    (index_id, run_gc_result)
+            }
+            let storage = match self.storage_resolver.resolve(index_metadata.index_uri()) {
+                Ok(storage) => storage,
+                Err(error) => {
+                    self.counters.num_failed_storage_resolution += 1;
+                    error!(index=%index_metadata.index_id(), error=?error, "Failed to resolve the index storage Uri.");
+                    continue;
                 }
-            })
+            };
+            let deletion_grace_period = gc_settings
+                .deletion_grace_period()
+                .ok()
+                .flatten()
+                .unwrap_or(DELETION_GRACE_PERIOD);
+            let max_concurrent_deletions = gc_settings
+                .max_concurrent_deletions
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_DELETIONS);
+            self.last_gc_passes.insert(
+                index_metadata.index_id().to_string(),
+                self.counters.num_passes,
+            );
+            indexes_to_gc.push((
+                index_metadata.index_id().to_string(),
+                storage,
+                deletion_grace_period,
+                max_concurrent_deletions,
+            ));
+        }
+        info!(index_ids=%indexes_to_gc.iter().map(|(index_id, ..)| index_id).join(", "), "Garbage collecting indexes.");
+
+        let run_gc_tasks: Vec<_> = indexes_to_gc
+            .into_iter()
+            .map(
+                |(index_id, storage, deletion_grace_period, max_concurrent_deletions)| {
+                    let moved_metastore = self.metastore.clone();
+                    async move {
+                        let run_gc_result = run_garbage_collect(
+                            &index_id,
+                            storage,
+                            moved_metastore,
+                            STAGED_GRACE_PERIOD,
+                            deletion_grace_period,
+                            max_concurrent_deletions,
+                            false,
+                            Some(ctx),
+                        )
+                        .await;
+
+                        (index_id, run_gc_result)
+                    }
+                },
+            )
             .collect();
 
         let mut stream =
@@ -318,6 +377,7 @@ mod tests {
             Arc::new(mock_metastore),
             STAGED_GRACE_PERIOD,
             DELETION_GRACE_PERIOD,
+            1,
             false,
             None,
         )
@@ -673,4 +733,76 @@ mod tests {
         assert_eq!(counters.num_failed_splits, 2);
         universe.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_garbage_collect_honors_index_gc_settings() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(3)
+            .returning(|| {
+                let mut paused_index_metadata =
+                    IndexMetadata::for_test("test-index-1", "ram://indexes/test-index-1");
+                paused_index_metadata.index_config.garbage_collection.paused = true;
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index-2", "ram://indexes/test-index-2");
+                index_metadata.index_config.garbage_collection.run_interval =
+                    Some("2m".to_string());
+                Ok(vec![paused_index_metadata, index_metadata])
+            });
+        mock_metastore
+            .expect_list_splits()
+            .times(4)
+            .returning(|query| {
+                assert_eq!(query.index_id, "test-index-2");
+                let splits = match query.split_states[0] {
+                    SplitState::Staged => make_splits(&["a"], SplitState::Staged),
+                    SplitState::MarkedForDeletion => {
+                        make_splits(&["a", "b"], SplitState::MarkedForDeletion)
+                    }
+                    _ => panic!("only Staged and MarkedForDeletion expected."),
+                };
+                Ok(splits)
+            });
+        mock_metastore
+            .expect_mark_splits_for_deletion()
+            .times(2)
+            .returning(|index_id, _split_ids| {
+                assert_eq!(index_id, "test-index-2");
+                Ok(())
+            });
+        mock_metastore
+            .expect_delete_splits()
+            .times(2)
+            .returning(|index_id, _split_ids| {
+                assert_eq!(index_id, "test-index-2");
+                Ok(())
+            });
+
+        let garbage_collect_actor =
+            GarbageCollector::new(Arc::new(mock_metastore), storage_resolver);
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(garbage_collect_actor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_successful_gc_run_on_index, 1);
+        assert_eq!(counters.num_paused_gc_run_on_index, 1);
+
+        // The run interval of `test-index-2` has not elapsed yet.
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 2);
+        assert_eq!(counters.num_successful_gc_run_on_index, 1);
+        assert_eq!(counters.num_paused_gc_run_on_index, 2);
+
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 3);
+        assert_eq!(counters.num_successful_gc_run_on_index, 2);
+        assert_eq!(counters.num_paused_gc_run_on_index, 3);
+        assert_eq!(counters.num_deleted_files, 4);
+        universe.assert_quit().await;
+    }
 }
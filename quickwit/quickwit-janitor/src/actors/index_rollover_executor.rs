@@ -0,0 +1,204 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_common::uri::Uri;
+use quickwit_metastore::Metastore;
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::index_rollover::rollover_index_alias;
+
+const RUN_INTERVAL: Duration = Duration::from_secs(5 * 60); // 5 minutes
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct IndexRolloverExecutorCounters {
+    /// The number of evaluation passes.
+    pub num_passes: usize,
+    /// The number of write aliases rolled over to a new index, including the aliases pointed at
+    /// the first index of their series.
+    pub num_rollovers: usize,
+    /// The number of rollover policies that could not be evaluated or applied.
+    pub num_failed_rollovers: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor that periodically evaluates the rollover policies of the index templates and rolls
+/// their write aliases over to a new index when the current one meets the rollover conditions.
+pub struct IndexRolloverExecutor {
+    metastore: Arc<dyn Metastore>,
+    default_index_root_uri: Uri,
+    counters: IndexRolloverExecutorCounters,
+}
+
+impl IndexRolloverExecutor {
+    pub fn new(metastore: Arc<dyn Metastore>, default_index_root_uri: Uri) -> Self {
+        Self {
+            metastore,
+            default_index_root_uri,
+            counters: IndexRolloverExecutorCounters::default(),
+        }
+    }
+
+    /// Rollover loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        debug!("index-rollover-operation");
+        self.counters.num_passes += 1;
+
+        let index_templates = match ctx
+            .protect_future(self.metastore.list_index_templates())
+            .await
+        {
+            Ok(index_templates) => index_templates,
+            Err(error) => {
+                error!(error=?error, "Failed to list index templates from the metastore.");
+                return;
+            }
+        };
+        for index_template in index_templates {
+            let Some(rollover_policy) = &index_template.rollover_policy else {
+                continue;
+            };
+            let rollover_result = ctx
+                .protect_future(rollover_index_alias(
+                    &*self.metastore,
+                    &rollover_policy.write_alias,
+                    &rollover_policy.conditions,
+                    &self.default_index_root_uri,
+                    false,
+                ))
+                .await;
+            match rollover_result {
+                Ok(rollover_report) => {
+                    if rollover_report.rolled_over {
+                        self.counters.num_rollovers += 1;
+                    }
+                }
+                Err(error) => {
+                    self.counters.num_failed_rollovers += 1;
+                    error!(
+                        template_id=%index_template.template_id,
+                        alias_id=%rollover_policy.write_alias,
+                        error=?error,
+                        "Failed to roll alias over."
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for IndexRolloverExecutor {
+    type ObservableState = IndexRolloverExecutorCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "IndexRolloverExecutor".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle(Loop, ctx).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for IndexRolloverExecutor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_config::{load_index_template_from_user_config, ConfigFormat};
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_index_rollover_executor() {
+        let metastore = metastore_for_test();
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+            rollover:
+              write_alias: logs
+              conditions:
+                max_docs: 1000
+        "#;
+        let index_template = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap();
+        metastore
+            .create_index_template(index_template, false)
+            .await
+            .unwrap();
+
+        let index_rollover_executor =
+            IndexRolloverExecutor::new(metastore.clone(), Uri::from_well_formed("ram:///indexes"));
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(index_rollover_executor);
+
+        // The first index of the series is created right away.
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_rollovers, 1);
+        assert_eq!(counters.num_failed_rollovers, 0);
+
+        let index_aliases = metastore.list_index_aliases().await.unwrap();
+        assert_eq!(index_aliases.len(), 1);
+        assert_eq!(index_aliases[0].index_ids, ["logs-000001"]);
+
+        // The index is empty, so the alias is not rolled over.
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 2);
+        assert_eq!(counters.num_rollovers, 1);
+        assert!(!metastore.index_exists("logs-000002").await.unwrap());
+        universe.assert_quit().await;
+    }
+}
@@ -21,8 +21,14 @@ mod delete_task_pipeline;
 mod delete_task_planner;
 mod delete_task_service;
 mod garbage_collector;
+mod index_rollover_executor;
 mod retention_policy_executor;
+mod split_verifier;
+mod storage_reconciler;
 
 pub use delete_task_service::DeleteTaskService;
 pub use garbage_collector::GarbageCollector;
+pub use index_rollover_executor::IndexRolloverExecutor;
 pub use retention_policy_executor::RetentionPolicyExecutor;
+pub use split_verifier::SplitVerifier;
+pub use storage_reconciler::StorageReconciler;
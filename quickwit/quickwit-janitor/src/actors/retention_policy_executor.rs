@@ -26,6 +26,7 @@ use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, Handler};
 use quickwit_config::IndexConfig;
 use quickwit_metastore::Metastore;
+use quickwit_storage::StorageUriResolver;
 use serde::Serialize;
 use tracing::{debug, error, info};
 
@@ -58,6 +59,7 @@ struct Execute {
 /// in a cache and periodically update this list.
 pub struct RetentionPolicyExecutor {
     metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
     /// A map of index_id to index metadata that are managed by this executor.
     /// This act as local cache that is periodically updated while taking into
     /// account deleted indexes, updated or removed retention policy on indexes.
@@ -66,9 +68,10 @@ pub struct RetentionPolicyExecutor {
 }
 
 impl RetentionPolicyExecutor {
-    pub fn new(metastore: Arc<dyn Metastore>) -> Self {
+    pub fn new(metastore: Arc<dyn Metastore>, storage_resolver: StorageUriResolver) -> Self {
         Self {
             metastore,
+            storage_resolver,
             index_configs: HashMap::new(),
             counters: RetentionPolicyExecutorCounters::default(),
         }
@@ -104,10 +107,11 @@ impl RetentionPolicyExecutor {
 
         for index_metadata in index_metadatas {
             let index_config = index_metadata.into_index_config();
-            // We only care about indexes with a retention policy configured.
+            // We only care about indexes with a retention policy configured. The retention of
+            // mounted indexes is enforced by the cluster that owns them.
             let retention_policy = match &index_config.retention_policy {
-                Some(policy) => policy,
-                None => {
+                Some(policy) if !index_config.is_mounted() => policy,
+                _ => {
                     // Remove the index from the cache if it exist.
                     // In case where the retention policy was removed this index might have
                     // been inserted in the cache from a previous iteration.
@@ -202,7 +206,9 @@ impl Handler<Execute> for RetentionPolicyExecutor {
 
         let execution_result = run_execute_retention_policy(
             &message.index_id,
+            &index_config.index_uri,
             self.metastore.clone(),
+            &self.storage_resolver,
             retention_policy,
             ctx,
         )
@@ -373,7 +379,8 @@ mod tests {
                 ]))
             });
 
-        let retention_policy_executor = RetentionPolicyExecutor::new(Arc::new(mock_metastore));
+        let retention_policy_executor =
+            RetentionPolicyExecutor::new(Arc::new(mock_metastore), StorageUriResolver::for_test());
         let universe = Universe::with_accelerated_time();
         let (mailbox, handle) = universe.spawn_builder().spawn(retention_policy_executor);
 
@@ -455,7 +462,8 @@ mod tests {
                 Ok(())
             });
 
-        let retention_policy_executor = RetentionPolicyExecutor::new(Arc::new(mock_metastore));
+        let retention_policy_executor =
+            RetentionPolicyExecutor::new(Arc::new(mock_metastore), StorageUriResolver::for_test());
         let universe = Universe::with_accelerated_time();
         let (_mailbox, handle) = universe.spawn_builder().spawn(retention_policy_executor);
 
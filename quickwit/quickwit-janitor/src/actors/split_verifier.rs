@@ -0,0 +1,308 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_metastore::{ListSplitsQuery, Metastore, SplitMetadata, SplitState};
+use quickwit_storage::StorageUriResolver;
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::metrics::JANITOR_METRICS;
+use crate::split_verification::verify_index_splits;
+
+/// Verifying a split requires downloading it entirely, so only a few splits per index are
+/// verified on each pass.
+const RUN_INTERVAL: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+const NUM_SPLITS_TO_VERIFY_PER_INDEX: usize = if cfg!(test) { 2 } else { 5 };
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SplitVerifierCounters {
+    /// The number of verification passes.
+    pub num_passes: usize,
+    /// The number of splits verified.
+    pub num_verified_splits: usize,
+    /// The number of splits that could not be verified.
+    pub num_failed_verifications: usize,
+    /// The number of corrupt splits found and marked for deletion.
+    pub num_corrupt_splits: usize,
+    /// The number of failed verification runs on an index.
+    pub num_failed_runs_on_index: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor that periodically samples the published splits of each index, verifies their
+/// integrity, and marks the corrupt ones for deletion so that searches stop failing on them.
+///
+/// Splits are sampled in the order of their IDs, resuming after the last split verified on the
+/// previous pass, so that all the splits of an index end up being verified.
+pub struct SplitVerifier {
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
+    /// ID of the last split verified for each index.
+    last_verified_split_ids: HashMap<String, String>,
+    counters: SplitVerifierCounters,
+}
+
+impl SplitVerifier {
+    pub fn new(metastore: Arc<dyn Metastore>, storage_resolver: StorageUriResolver) -> Self {
+        Self {
+            metastore,
+            storage_resolver,
+            last_verified_split_ids: HashMap::new(),
+            counters: SplitVerifierCounters::default(),
+        }
+    }
+
+    /// Picks the next splits to verify for an index.
+    fn sample_splits(&self, index_id: &str, mut splits: Vec<SplitMetadata>) -> Vec<SplitMetadata> {
+        splits.sort_by(|left, right| left.split_id.cmp(&right.split_id));
+        let start = match self.last_verified_split_ids.get(index_id) {
+            Some(last_verified_split_id) => splits
+                .iter()
+                .position(|split| split.split_id() > last_verified_split_id.as_str())
+                .unwrap_or(0),
+            None => 0,
+        };
+        let num_splits = splits.len().min(NUM_SPLITS_TO_VERIFY_PER_INDEX);
+        splits.rotate_left(start);
+        splits.truncate(num_splits);
+        splits
+    }
+
+    /// Verification loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        info!("split-verification-operation");
+        self.counters.num_passes += 1;
+
+        let index_metadatas = match ctx
+            .protect_future(self.metastore.list_indexes_metadatas())
+            .await
+        {
+            Ok(metadatas) => metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        let index_ids: HashSet<&str> = index_metadatas
+            .iter()
+            .map(|index_metadata| index_metadata.index_id())
+            .collect();
+        self.last_verified_split_ids
+            .retain(|index_id, _| index_ids.contains(index_id.as_str()));
+
+        for index_metadata in &index_metadatas {
+            let index_id = index_metadata.index_id();
+            let storage = match self.storage_resolver.resolve(index_metadata.index_uri()) {
+                Ok(storage) => storage,
+                Err(error) => {
+                    self.counters.num_failed_runs_on_index += 1;
+                    error!(index_id=%index_id, error=?error, "Failed to resolve the index storage Uri.");
+                    continue;
+                }
+            };
+            let query =
+                ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+            let splits = match ctx.protect_future(self.metastore.list_splits(query)).await {
+                Ok(splits) => splits
+                    .into_iter()
+                    .map(|split| split.split_metadata)
+                    .collect(),
+                Err(error) => {
+                    self.counters.num_failed_runs_on_index += 1;
+                    error!(index_id=%index_id, error=?error, "Failed to list splits from the metastore.");
+                    continue;
+                }
+            };
+            let sampled_splits = self.sample_splits(index_id, splits);
+            let Some(last_sampled_split) = sampled_splits.last() else {
+                continue;
+            };
+            self.last_verified_split_ids
+                .insert(index_id.to_string(), last_sampled_split.split_id.clone());
+
+            let verification_result = ctx
+                .protect_future(verify_index_splits(
+                    index_id,
+                    storage,
+                    self.metastore.clone(),
+                    &sampled_splits,
+                    true,
+                ))
+                .await;
+            let report = match verification_result {
+                Ok(report) => report,
+                Err(error) => {
+                    self.counters.num_failed_runs_on_index += 1;
+                    error!(index_id=%index_id, error=?error, "Failed to verify index splits.");
+                    continue;
+                }
+            };
+            self.counters.num_verified_splits += report.num_verified_splits;
+            self.counters.num_failed_verifications += report.num_failed_verifications;
+            self.counters.num_corrupt_splits += report.corrupt_splits.len();
+            JANITOR_METRICS
+                .corrupt_splits_total
+                .with_label_values([index_id])
+                .inc_by(report.corrupt_splits.len() as u64);
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for SplitVerifier {
+    type ObservableState = SplitVerifierCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "SplitVerifier".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        // The first pass is delayed to avoid downloading splits on each restart.
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for SplitVerifier {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_metastore::{IndexMetadata, MockMetastore, Split};
+
+    use super::*;
+
+    fn make_splits(split_ids: &[&str]) -> Vec<Split> {
+        split_ids
+            .iter()
+            .map(|split_id| Split {
+                split_metadata: SplitMetadata {
+                    split_id: split_id.to_string(),
+                    ..Default::default()
+                },
+                split_state: SplitState::Published,
+                update_timestamp: 0,
+                publish_timestamp: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_split_verifier_samples_splits_in_turn() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(2)
+            .returning(|| {
+                Ok(vec![IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                )])
+            });
+        mock_metastore
+            .expect_list_splits()
+            .times(2)
+            .returning(|_| Ok(make_splits(&["c", "a", "b"])));
+        let split_verifier = SplitVerifier::new(Arc::new(mock_metastore), storage_resolver);
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(split_verifier);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 0);
+
+        // The split files do not exist, so the splits cannot be verified but are not corrupt.
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_verified_splits, 0);
+        assert_eq!(counters.num_failed_verifications, 2);
+        assert_eq!(counters.num_corrupt_splits, 0);
+
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 2);
+        assert_eq!(counters.num_failed_verifications, 4);
+        universe.assert_quit().await;
+    }
+
+    #[test]
+    fn test_split_verifier_sample_splits() {
+        let mut split_verifier = SplitVerifier::new(
+            Arc::new(MockMetastore::default()),
+            StorageUriResolver::for_test(),
+        );
+        let splits = |split_ids: &[&str]| -> Vec<SplitMetadata> {
+            make_splits(split_ids)
+                .into_iter()
+                .map(|split| split.split_metadata)
+                .collect()
+        };
+        let sampled_split_ids = |sampled_splits: Vec<SplitMetadata>| -> Vec<String> {
+            sampled_splits
+                .into_iter()
+                .map(|split| split.split_id)
+                .collect()
+        };
+        let sampled_splits = split_verifier.sample_splits("test-index", splits(&["c", "a", "b"]));
+        assert_eq!(sampled_split_ids(sampled_splits), ["a", "b"]);
+
+        split_verifier
+            .last_verified_split_ids
+            .insert("test-index".to_string(), "b".to_string());
+        let sampled_splits = split_verifier.sample_splits("test-index", splits(&["c", "a", "b"]));
+        assert_eq!(sampled_split_ids(sampled_splits), ["c", "a"]);
+
+        split_verifier
+            .last_verified_split_ids
+            .insert("test-index".to_string(), "c".to_string());
+        let sampled_splits = split_verifier.sample_splits("test-index", splits(&["c", "a", "b"]));
+        assert_eq!(sampled_split_ids(sampled_splits), ["a", "b"]);
+    }
+}
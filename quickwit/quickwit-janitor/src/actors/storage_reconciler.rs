@@ -0,0 +1,224 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use quickwit_actors::{Actor, ActorContext, Handler};
+use quickwit_metastore::Metastore;
+use quickwit_storage::StorageUriResolver;
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::metrics::JANITOR_METRICS;
+use crate::storage_reconciliation::reconcile_index_storage;
+
+/// Listing an index storage is expensive, so reconciliation runs much less often than the GC.
+const RUN_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60); // 6 hours
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StorageReconcilerCounters {
+    /// The number of reconciliation passes.
+    pub num_passes: usize,
+    /// The number of successful reconciliation runs on an index.
+    pub num_successful_runs_on_index: usize,
+    /// The number of failed reconciliation runs on an index.
+    pub num_failed_runs_on_index: usize,
+    /// The number of orphan split files found during the last pass.
+    pub num_orphan_files: usize,
+    /// The number of published splits without file found during the last pass.
+    pub num_missing_splits: usize,
+}
+
+#[derive(Debug)]
+struct Loop;
+
+/// An actor that periodically diffs the split files of each index storage against the metastore
+/// and reports orphan files and missing split files. It never deletes anything: orphan files are
+/// deleted on demand with `quickwit index reconcile --delete-orphans`.
+pub struct StorageReconciler {
+    metastore: Arc<dyn Metastore>,
+    storage_resolver: StorageUriResolver,
+    counters: StorageReconcilerCounters,
+}
+
+impl StorageReconciler {
+    pub fn new(metastore: Arc<dyn Metastore>, storage_resolver: StorageUriResolver) -> Self {
+        Self {
+            metastore,
+            storage_resolver,
+            counters: StorageReconcilerCounters::default(),
+        }
+    }
+
+    /// Reconciliation loop handler logic.
+    /// Should not return an error to prevent the actor from crashing.
+    async fn handle_inner(&mut self, ctx: &ActorContext<Self>) {
+        info!("storage-reconciliation-operation");
+        self.counters.num_passes += 1;
+        self.counters.num_orphan_files = 0;
+        self.counters.num_missing_splits = 0;
+
+        let index_metadatas = match ctx
+            .protect_future(self.metastore.list_indexes_metadatas())
+            .await
+        {
+            Ok(metadatas) => metadatas,
+            Err(error) => {
+                error!(error=?error, "Failed to list indexes from the metastore.");
+                return;
+            }
+        };
+        for index_metadata in index_metadatas {
+            // The storage of mounted indexes is owned by another cluster.
+            if index_metadata.index_config().is_mounted() {
+                continue;
+            }
+            let index_id = index_metadata.index_id();
+            let storage = match self.storage_resolver.resolve(index_metadata.index_uri()) {
+                Ok(storage) => storage,
+                Err(error) => {
+                    self.counters.num_failed_runs_on_index += 1;
+                    error!(index_id=%index_id, error=?error, "Failed to resolve the index storage Uri.");
+                    continue;
+                }
+            };
+            let reconciliation_result = ctx
+                .protect_future(reconcile_index_storage(
+                    index_id,
+                    storage,
+                    self.metastore.clone(),
+                    false,
+                ))
+                .await;
+            let report = match reconciliation_result {
+                Ok(report) => report,
+                Err(error) => {
+                    self.counters.num_failed_runs_on_index += 1;
+                    error!(index_id=%index_id, error=?error, "Failed to reconcile index storage.");
+                    continue;
+                }
+            };
+            self.counters.num_successful_runs_on_index += 1;
+            self.counters.num_orphan_files += report.orphan_files.len();
+            self.counters.num_missing_splits += report.missing_split_ids.len();
+            JANITOR_METRICS
+                .orphan_split_files
+                .with_label_values([index_id])
+                .set(report.orphan_files.len() as i64);
+            JANITOR_METRICS
+                .missing_split_files
+                .with_label_values([index_id])
+                .set(report.missing_split_ids.len() as i64);
+        }
+    }
+}
+
+#[async_trait]
+impl Actor for StorageReconciler {
+    type ObservableState = StorageReconcilerCounters;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.counters.clone()
+    }
+
+    fn name(&self) -> String {
+        "StorageReconciler".to_string()
+    }
+
+    async fn initialize(
+        &mut self,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        // The first pass is delayed to avoid listing every index storage on each restart.
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Loop> for StorageReconciler {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _: Loop,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), quickwit_actors::ActorExitStatus> {
+        self.handle_inner(ctx).await;
+        ctx.schedule_self_msg(RUN_INTERVAL, Loop).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_actors::Universe;
+    use quickwit_common::uri::Uri;
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_storage::PutPayload;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_storage_reconciler_reports_orphan_files() {
+        let storage_resolver = StorageUriResolver::for_test();
+        let storage = storage_resolver
+            .resolve(&Uri::from_well_formed("ram:///indexes/test-index"))
+            .unwrap();
+        let payload: Box<dyn PutPayload> = Box::new(b"split".to_vec());
+        storage
+            .put(Path::new("orphan.split"), payload)
+            .await
+            .unwrap();
+
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(|| {
+                Ok(vec![IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                )])
+            });
+        mock_metastore
+            .expect_list_all_splits()
+            .times(1)
+            .returning(|_| Ok(Vec::new()));
+        let storage_reconciler = StorageReconciler::new(Arc::new(mock_metastore), storage_resolver);
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(storage_reconciler);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 0);
+
+        universe.sleep(RUN_INTERVAL).await;
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_successful_runs_on_index, 1);
+        assert_eq!(counters.num_orphan_files, 1);
+        assert_eq!(counters.num_missing_splits, 0);
+        assert!(storage.exists(Path::new("orphan.split")).await.unwrap());
+        universe.assert_quit().await;
+    }
+}
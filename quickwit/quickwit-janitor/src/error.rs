@@ -28,6 +28,10 @@ use thiserror::Error;
 pub enum JanitorError {
     #[error("Invalid delete query: `{0}`.")]
     InvalidDeleteQuery(String),
+    #[error("Delete task `{opstamp}` does not exist for index `{index_id}`.")]
+    DeleteTaskDoesNotExist { index_id: String, opstamp: u64 },
+    #[error("Invalid rollover: {0}")]
+    InvalidRollover(String),
     #[error("Internal error: {0}")]
     InternalError(String),
     #[error("Metastore error `{0}`.")]
@@ -38,6 +42,8 @@ impl ServiceError for JanitorError {
     fn status_code(&self) -> ServiceErrorCode {
         match self {
             JanitorError::InvalidDeleteQuery(_) => ServiceErrorCode::BadRequest,
+            JanitorError::DeleteTaskDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            JanitorError::InvalidRollover(_) => ServiceErrorCode::BadRequest,
             JanitorError::InternalError(_) => ServiceErrorCode::Internal,
             JanitorError::MetastoreError(error) => error.status_code(),
         }
@@ -17,12 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
-use futures::Future;
+use futures::{Future, StreamExt};
+use itertools::Itertools;
 use quickwit_actors::ActorContext;
 use quickwit_common::{FileEntry, PrettySample};
 use quickwit_metastore::{ListSplitsQuery, Metastore, MetastoreError, SplitMetadata, SplitState};
@@ -47,10 +48,7 @@ pub enum SplitDeletionError {
     },
 }
 
-async fn protect_future<Fut, T>(
-    ctx_opt: Option<&ActorContext<GarbageCollector>>,
-    future: Fut,
-) -> T
+async fn protect_future<Fut, T>(ctx_opt: Option<&ActorContext<GarbageCollector>>, future: Fut) -> T
 where
     Fut: Future<Output = T>,
 {
@@ -78,6 +76,7 @@ pub struct SplitRemovalInfo {
 ///   collected.
 /// * `deletion_grace_period` -  Threshold period after which a marked as deleted split can be
 ///   safely deleted.
+/// * `max_concurrent_deletions` - Maximum number of batches of splits deleted concurrently.
 /// * `dry_run` - Should this only return a list of affected files without performing deletion.
 /// * `ctx_opt` - A context for reporting progress (only useful within quickwit actor).
 pub async fn run_garbage_collect(
@@ -86,6 +85,7 @@ pub async fn run_garbage_collect(
     metastore: Arc<dyn Metastore>,
     staged_grace_period: Duration,
     deletion_grace_period: Duration,
+    max_concurrent_deletions: usize,
     dry_run: bool,
     ctx_opt: Option<&ActorContext<GarbageCollector>>,
 ) -> anyhow::Result<SplitRemovalInfo> {
@@ -146,6 +146,7 @@ pub async fn run_garbage_collect(
     let deleted_files = delete_splits_marked_for_deletion(
         index_id,
         updated_before_timestamp,
+        max_concurrent_deletions,
         storage,
         metastore,
         ctx_opt,
@@ -157,24 +158,28 @@ pub async fn run_garbage_collect(
 
 #[instrument(skip(storage, metastore, ctx_opt))]
 /// Removes any splits marked for deletion which haven't been
-/// updated after `updated_before_timestamp` in batches of 1000 splits.
+/// updated after `updated_before_timestamp` in batches of 1000 splits, with at most
+/// `max_concurrent_deletions` batches deleted concurrently.
 ///
 /// The aim of this is to spread the load out across a longer period
 /// rather than short, heavy bursts on the metastore and storage system itself.
 async fn delete_splits_marked_for_deletion(
     index_id: &str,
     updated_before_timestamp: i64,
+    max_concurrent_deletions: usize,
     storage: Arc<dyn Storage>,
     metastore: Arc<dyn Metastore>,
     ctx_opt: Option<&ActorContext<GarbageCollector>>,
 ) -> SplitRemovalInfo {
     let mut failed_split_ids = Vec::new();
     let mut removed_split_files = Vec::new();
+    let max_concurrent_deletions = max_concurrent_deletions.max(1);
+    let num_splits_per_round = DELETE_SPLITS_BATCH_SIZE * max_concurrent_deletions;
     loop {
         let query = ListSplitsQuery::for_index(index_id)
             .with_split_state(SplitState::MarkedForDeletion)
             .with_update_timestamp_lte(updated_before_timestamp)
-            .with_limit(DELETE_SPLITS_BATCH_SIZE);
+            .with_limit(num_splits_per_round);
 
         let list_splits_result = protect_future(ctx_opt, metastore.list_splits(query)).await;
 
@@ -186,44 +191,51 @@ async fn delete_splits_marked_for_deletion(
             }
         };
 
-        let splits_to_delete = splits_to_delete
-            .into_iter()
-            .map(|split| split.split_metadata)
-            .collect::<Vec<_>>();
-
         let num_splits_to_delete = splits_to_delete.len();
         if num_splits_to_delete == 0 {
             break;
         }
+        let split_batches: Vec<Vec<SplitMetadata>> = splits_to_delete
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .chunks(DELETE_SPLITS_BATCH_SIZE)
+            .into_iter()
+            .map(|split_batch| split_batch.collect())
+            .collect();
 
-        let delete_splits_result = delete_splits_with_files(
-            index_id,
-            storage.clone(),
-            metastore.clone(),
-            splits_to_delete,
-            ctx_opt,
-        )
-        .await;
-
-        match delete_splits_result {
-            Ok(entries) => removed_split_files.extend(entries),
-            Err(SplitDeletionError::MetastoreFailure {
-                error,
-                failed_split_ids: failed_split_ids_inner,
-            }) => {
-                error!(
-                    error=?error,
-                    index_id=%index_id,
-                    split_ids=?PrettySample::new(&failed_split_ids_inner, 5),
-                    "Failed to delete {} splits.",
-                    failed_split_ids_inner.len()
-                );
-                failed_split_ids.extend(failed_split_ids_inner);
-                break;
+        let mut delete_splits_stream = futures::stream::iter(split_batches)
+            .map(|split_batch| {
+                delete_splits_with_files(
+                    index_id,
+                    storage.clone(),
+                    metastore.clone(),
+                    split_batch,
+                    ctx_opt,
+                )
+            })
+            .buffer_unordered(max_concurrent_deletions);
+
+        let mut metastore_failed = false;
+        while let Some(delete_splits_result) = delete_splits_stream.next().await {
+            match delete_splits_result {
+                Ok(entries) => removed_split_files.extend(entries),
+                Err(SplitDeletionError::MetastoreFailure {
+                    error,
+                    failed_split_ids: failed_split_ids_inner,
+                }) => {
+                    error!(
+                        error=?error,
+                        index_id=%index_id,
+                        split_ids=?PrettySample::new(&failed_split_ids_inner, 5),
+                        "Failed to delete {} splits.",
+                        failed_split_ids_inner.len()
+                    );
+                    failed_split_ids.extend(failed_split_ids_inner);
+                    metastore_failed = true;
+                }
             }
         }
-
-        if num_splits_to_delete < DELETE_SPLITS_BATCH_SIZE {
+        if metastore_failed || num_splits_to_delete < num_splits_per_round {
             break;
         }
     }
@@ -234,9 +246,49 @@ async fn delete_splits_marked_for_deletion(
     }
 }
 
+/// Returns the files of the given splits that are named after their content and also referenced
+/// by splits of the index that are not being deleted. These files must be kept.
+///
+/// Since a split with the same content can be staged while the splits of the index are being
+/// listed, the references are checked again against the splits staged or published since the
+/// listing started.
+async fn list_shared_split_files(
+    index_id: &str,
+    metastore: &dyn Metastore,
+    splits: &[SplitMetadata],
+    ctx_opt: Option<&ActorContext<GarbageCollector>>,
+) -> Result<HashSet<String>, MetastoreError> {
+    if splits.iter().all(|split| split.content_hash.is_none()) {
+        return Ok(HashSet::new());
+    }
+    let deleted_split_ids: HashSet<&str> = splits.iter().map(SplitMetadata::split_id).collect();
+    let listed_at = OffsetDateTime::now_utc().unix_timestamp();
+    let mut index_splits = protect_future(ctx_opt, metastore.list_all_splits(index_id)).await?;
+
+    let query = ListSplitsQuery::for_index(index_id)
+        .with_split_states([SplitState::Staged, SplitState::Published])
+        .with_update_timestamp_gte(listed_at);
+    let recently_updated_splits = protect_future(ctx_opt, metastore.list_splits(query)).await?;
+    index_splits.extend(recently_updated_splits);
+
+    let shared_split_files = index_splits
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .filter(|split| {
+            split.content_hash.is_some() && !deleted_split_ids.contains(split.split_id())
+        })
+        .map(|split| split.split_file())
+        .collect();
+    Ok(shared_split_files)
+}
+
 /// Delete a list of splits from the storage and the metastore.
 /// It should leave the index and the metastore in good state.
 ///
+/// The files named after their content are shared by all the splits with the same content and
+/// are only deleted along with the last of these splits. The uploaders store the split file after
+/// staging the split, so a split staged after the references are checked uploads the file again.
+///
 /// * `index_id` - The target index id.
 /// * `storage - The storage managing the target index.
 /// * `metastore` - The metastore managing the target index.
@@ -249,36 +301,52 @@ pub async fn delete_splits_with_files(
     splits: Vec<SplitMetadata>,
     ctx_opt: Option<&ActorContext<GarbageCollector>>,
 ) -> anyhow::Result<Vec<FileEntry>, SplitDeletionError> {
-    let mut paths_to_splits = HashMap::with_capacity(splits.len());
+    let shared_split_files = list_shared_split_files(index_id, &*metastore, &splits, ctx_opt)
+        .await
+        .map_err(|error| SplitDeletionError::MetastoreFailure {
+            error,
+            failed_split_ids: splits
+                .iter()
+                .map(|split| split.split_id().to_string())
+                .collect(),
+        })?;
+    let mut paths_to_splits: HashMap<PathBuf, (Vec<String>, FileEntry)> =
+        HashMap::with_capacity(splits.len());
+    // The splits whose file is still referenced by other splits are only deleted from the
+    // metastore.
+    let mut deleted_split_ids = Vec::new();
 
     for split in splits {
-        let file_entry = FileEntry::from(&split);
-        let split_filename = quickwit_common::split_file(split.split_id());
-        let split_path = Path::new(&split_filename);
+        let split_file = split.split_file();
 
-        paths_to_splits.insert(
-            split_path.to_path_buf(),
-            (split.split_id().to_string(), file_entry),
-        );
+        if shared_split_files.contains(&split_file) {
+            deleted_split_ids.push(split.split_id);
+            continue;
+        }
+        let file_entry = FileEntry::from(&split);
+        paths_to_splits
+            .entry(PathBuf::from(split_file))
+            .or_insert_with(|| (Vec::new(), file_entry))
+            .0
+            .push(split.split_id);
     }
 
     let paths = paths_to_splits
         .keys()
         .map(|key| key.as_path())
-        .collect::<Vec<&Path>>();
+        .collect::<Vec<_>>();
     let delete_result = storage.bulk_delete(&paths).await;
 
     if let Some(ctx) = ctx_opt {
         ctx.record_progress();
     }
 
-    let mut deleted_split_ids = Vec::new();
     let mut deleted_file_entries = Vec::new();
 
     match delete_result {
         Ok(()) => {
-            for (split_id, entry) in paths_to_splits.into_values() {
-                deleted_split_ids.push(split_id);
+            for (split_ids, entry) in paths_to_splits.into_values() {
+                deleted_split_ids.extend(split_ids);
                 deleted_file_entries.push(entry);
             }
         }
@@ -301,11 +369,11 @@ pub async fn delete_splits_with_files(
             );
 
             for split_path in bulk_delete_error.successes {
-                let (split_id, entry) = paths_to_splits
+                let (split_ids, entry) = paths_to_splits
                     .remove(&split_path)
                     .expect("The successful split path should be present within the lookup table.");
 
-                deleted_split_ids.push(split_id);
+                deleted_split_ids.extend(split_ids);
                 deleted_file_entries.push(entry);
             }
         }
@@ -326,15 +394,18 @@ pub async fn delete_splits_with_files(
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Bound;
+    use std::path::Path;
     use std::sync::Arc;
     use std::time::Duration;
 
     use quickwit_config::IndexConfig;
     use quickwit_metastore::{
-        metastore_for_test, ListSplitsQuery, MockMetastore, SplitMetadata, SplitState,
+        metastore_for_test, ListSplitsQuery, MockMetastore, Split, SplitMetadata, SplitState,
     };
-    use quickwit_storage::storage_for_test;
+    use quickwit_storage::{storage_for_test, RamStorage, Storage};
 
+    use super::delete_splits_with_files;
     use crate::run_garbage_collect;
 
     #[tokio::test]
@@ -368,6 +439,7 @@ mod tests {
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(30),
+            1,
             false,
             None,
         )
@@ -384,6 +456,7 @@ mod tests {
             metastore.clone(),
             Duration::from_secs(0),
             Duration::from_secs(30),
+            1,
             false,
             None,
         )
@@ -431,6 +504,7 @@ mod tests {
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(30),
+            1,
             false,
             None,
         )
@@ -448,6 +522,7 @@ mod tests {
             metastore.clone(),
             Duration::from_secs(30),
             Duration::from_secs(0),
+            1,
             false,
             None,
         )
@@ -473,10 +548,141 @@ mod tests {
             Arc::new(metastore),
             Duration::from_secs(30),
             Duration::from_secs(30),
+            1,
             false,
             None,
         )
         .await
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn test_delete_splits_with_shared_content_addressed_file() {
+        let metastore = metastore_for_test();
+        let index_id = "test-delete-splits-with-shared-file";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let split_metadata = |split_id: &str| SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: index_id.to_string(),
+            content_hash: Some("abcd".to_string()),
+            ..Default::default()
+        };
+        let split_metadata_1 = split_metadata("split-1");
+        let split_metadata_2 = split_metadata("split-2");
+        metastore
+            .stage_splits(
+                index_id,
+                vec![split_metadata_1.clone(), split_metadata_2.clone()],
+            )
+            .await
+            .unwrap();
+        let storage: Arc<dyn Storage> = Arc::new(
+            RamStorage::builder()
+                .put("splits/abcd.split", b"split-payload")
+                .build(),
+        );
+        let split_path = Path::new("splits/abcd.split");
+
+        // The file is still referenced by the second split.
+        let deleted_file_entries = delete_splits_with_files(
+            index_id,
+            storage.clone(),
+            metastore.clone(),
+            vec![split_metadata_1],
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(deleted_file_entries.is_empty());
+        assert!(storage.exists(split_path).await.unwrap());
+        assert_eq!(metastore.list_all_splits(index_id).await.unwrap().len(), 1);
+
+        let deleted_file_entries = delete_splits_with_files(
+            index_id,
+            storage.clone(),
+            metastore.clone(),
+            vec![split_metadata_2],
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(deleted_file_entries.len(), 1);
+        assert_eq!(deleted_file_entries[0].file_name, "splits/abcd.split");
+        assert!(!storage.exists(split_path).await.unwrap());
+        assert!(metastore
+            .list_all_splits(index_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_splits_keeps_content_addressed_file_of_split_staged_meanwhile() {
+        let index_id = "test-delete-splits-staged-meanwhile";
+        let split_metadata = |split_id: &str| SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: index_id.to_string(),
+            content_hash: Some("abcd".to_string()),
+            ..Default::default()
+        };
+        let split_metadata_1 = split_metadata("split-1");
+        let split_metadata_1_clone = split_metadata_1.clone();
+        let split_metadata_2 = split_metadata("split-2");
+
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_all_splits()
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![Split {
+                    split_state: SplitState::MarkedForDeletion,
+                    update_timestamp: 0,
+                    publish_timestamp: None,
+                    split_metadata: split_metadata_1_clone.clone(),
+                }])
+            });
+        // The second split is staged while the splits of the index are being listed.
+        metastore
+            .expect_list_splits()
+            .times(1)
+            .returning(move |query| {
+                assert!(matches!(query.update_timestamp.start, Bound::Included(_)));
+                Ok(vec![Split {
+                    split_state: SplitState::Staged,
+                    update_timestamp: 0,
+                    publish_timestamp: None,
+                    split_metadata: split_metadata_2.clone(),
+                }])
+            });
+        metastore
+            .expect_delete_splits()
+            .times(1)
+            .returning(|index_id, split_ids| {
+                assert_eq!(index_id, "test-delete-splits-staged-meanwhile");
+                assert_eq!(split_ids, ["split-1"]);
+                Ok(())
+            });
+        let storage: Arc<dyn Storage> = Arc::new(
+            RamStorage::builder()
+                .put("splits/abcd.split", b"split-payload")
+                .build(),
+        );
+        let deleted_file_entries = delete_splits_with_files(
+            index_id,
+            storage.clone(),
+            Arc::new(metastore),
+            vec![split_metadata_1],
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(deleted_file_entries.is_empty());
+        assert!(storage
+            .exists(Path::new("splits/abcd.split"))
+            .await
+            .unwrap());
+    }
 }
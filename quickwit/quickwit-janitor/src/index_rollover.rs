@@ -0,0 +1,406 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_common::uri::Uri;
+use quickwit_config::{
+    find_matching_index_template, first_rollover_index_id, IndexConfig, RolloverConditions,
+    SourceConfig,
+};
+use quickwit_metastore::{IndexAliasAction, IndexMetadata, ListSplitsQuery, Metastore, SplitState};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::info;
+
+use crate::error::JanitorError;
+
+/// Outcome of the evaluation of the rollover conditions of a write alias.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct RolloverReport {
+    pub alias_id: String,
+    /// Index the alias pointed at before the rollover, if the alias existed.
+    pub old_index_id: Option<String>,
+    /// Index the alias points at after the rollover.
+    pub new_index_id: String,
+    /// Whether the alias was rolled over, or would have been in a dry run.
+    pub rolled_over: bool,
+    pub dry_run: bool,
+    /// Rollover conditions met by the old index.
+    pub met_conditions: Vec<String>,
+}
+
+/// Rolls the write alias `alias_id` over to a new index if the index it points at meets any of
+/// the rollover conditions, or unconditionally if no condition is defined. The new index is
+/// named after the index it succeeds with its sequence number incremented (`logs-000002` succeeds
+/// `logs-000001`) and created from the matching index template with the highest priority, or from
+/// the config of the index it succeeds if no template matches.
+///
+/// If the alias does not exist yet, the first index of the series, `<alias_id>-000001`, is
+/// created from the matching index template and the alias is pointed at it.
+///
+/// The alias is swapped from the old index to the new one atomically, so that documents ingested
+/// into the alias are never rejected. The old index remains searchable.
+pub async fn rollover_index_alias(
+    metastore: &dyn Metastore,
+    alias_id: &str,
+    conditions: &RolloverConditions,
+    default_index_root_uri: &Uri,
+    dry_run: bool,
+) -> Result<RolloverReport, JanitorError> {
+    conditions
+        .validate()
+        .map_err(|error| JanitorError::InvalidRollover(error.to_string()))?;
+    let index_aliases = metastore.list_index_aliases().await?;
+    let write_index_id_opt = match index_aliases
+        .iter()
+        .find(|index_alias| index_alias.alias_id == alias_id)
+    {
+        Some(index_alias) if index_alias.index_ids.len() > 1 => {
+            return Err(JanitorError::InvalidRollover(format!(
+                "Alias `{alias_id}` points to several indexes ({}), but only aliases pointing to a \
+                 single write index can be rolled over.",
+                index_alias.index_ids.join(", ")
+            )));
+        }
+        Some(index_alias) => index_alias.index_ids.first().cloned(),
+        None => None,
+    };
+    let (new_index_id, met_conditions, write_index_metadata_opt) = match &write_index_id_opt {
+        Some(write_index_id) => {
+            let write_index_metadata = metastore.index_metadata(write_index_id).await?;
+            let met_conditions =
+                evaluate_rollover_conditions(metastore, &write_index_metadata, conditions).await?;
+            let new_index_id = next_rollover_index_id(write_index_id).ok_or_else(|| {
+                JanitorError::InvalidRollover(format!(
+                    "Alias `{alias_id}` points to index `{write_index_id}`, whose ID does not end \
+                     with a sequence number such as `-000001`."
+                ))
+            })?;
+            (new_index_id, met_conditions, Some(write_index_metadata))
+        }
+        None => (first_rollover_index_id(alias_id), Vec::new(), None),
+    };
+    let rolled_over =
+        write_index_id_opt.is_none() || conditions.is_empty() || !met_conditions.is_empty();
+    let rollover_report = RolloverReport {
+        alias_id: alias_id.to_string(),
+        old_index_id: write_index_id_opt.clone(),
+        new_index_id: new_index_id.clone(),
+        rolled_over,
+        dry_run,
+        met_conditions: met_conditions
+            .into_iter()
+            .map(|condition| condition.to_string())
+            .collect(),
+    };
+    if !rolled_over || dry_run {
+        return Ok(rollover_report);
+    }
+    // Indexes and aliases share the same namespace.
+    if index_aliases
+        .iter()
+        .any(|index_alias| index_alias.alias_id == new_index_id)
+    {
+        return Err(JanitorError::InvalidRollover(format!(
+            "Index ID `{new_index_id}` is already used by an index alias."
+        )));
+    }
+    let index_templates = metastore.list_index_templates().await?;
+    let new_index_config = match (
+        find_matching_index_template(&index_templates, &new_index_id),
+        write_index_metadata_opt,
+    ) {
+        (Some(index_template), _) => index_template
+            .apply_template(new_index_id.clone(), default_index_root_uri)
+            .map_err(|error| JanitorError::InvalidRollover(error.to_string()))?,
+        (None, Some(write_index_metadata)) => {
+            let mut index_config = write_index_metadata.into_index_config();
+            index_config.index_id = new_index_id.clone();
+            index_config.mounted_from = None;
+            index_config.index_uri = default_index_root_uri
+                .join(&new_index_id)
+                .map_err(|error| JanitorError::InvalidRollover(error.to_string()))?;
+            index_config
+        }
+        (None, None) => {
+            return Err(JanitorError::InvalidRollover(format!(
+                "Alias `{alias_id}` does not exist and no index template matches the first index \
+                 of the series `{new_index_id}`."
+            )));
+        }
+    };
+    create_rollover_index(metastore, new_index_config).await?;
+
+    let mut index_alias_actions = Vec::with_capacity(2);
+    if let Some(write_index_id) = write_index_id_opt {
+        index_alias_actions.push(IndexAliasAction::Remove {
+            alias_id: alias_id.to_string(),
+            index_id: write_index_id,
+        });
+    }
+    index_alias_actions.push(IndexAliasAction::Add {
+        alias_id: alias_id.to_string(),
+        index_id: new_index_id.clone(),
+    });
+    metastore.update_index_aliases(index_alias_actions).await?;
+
+    info!(
+        alias_id=%alias_id,
+        old_index_id=?rollover_report.old_index_id,
+        new_index_id=%new_index_id,
+        met_conditions=?rollover_report.met_conditions,
+        "Rolled alias over to a new index."
+    );
+    Ok(rollover_report)
+}
+
+/// Creates the index along with the default sources of the indexes created through the index
+/// service.
+async fn create_rollover_index(
+    metastore: &dyn Metastore,
+    index_config: IndexConfig,
+) -> Result<(), JanitorError> {
+    let index_id = index_config.index_id.clone();
+    metastore.create_index(index_config).await?;
+    metastore
+        .add_source(&index_id, SourceConfig::ingest_api_default())
+        .await?;
+    metastore
+        .add_source(&index_id, SourceConfig::cli_ingest_source())
+        .await?;
+    Ok(())
+}
+
+/// Returns the rollover conditions met by the index. The published splits are only listed if a
+/// condition depends on them.
+async fn evaluate_rollover_conditions(
+    metastore: &dyn Metastore,
+    index_metadata: &IndexMetadata,
+    conditions: &RolloverConditions,
+) -> Result<Vec<&'static str>, JanitorError> {
+    let index_age_secs =
+        OffsetDateTime::now_utc().unix_timestamp() - index_metadata.create_timestamp;
+    let mut num_docs = 0;
+    let mut size_in_bytes = 0;
+
+    if conditions.max_docs.is_some() || conditions.max_size.is_some() {
+        let query = ListSplitsQuery::for_index(index_metadata.index_id())
+            .with_split_state(SplitState::Published);
+        for split in metastore.list_splits(query).await? {
+            num_docs += split.split_metadata.num_docs as u64;
+            size_in_bytes += split.split_metadata.uncompressed_docs_size_in_bytes;
+        }
+    }
+    met_rollover_conditions(conditions, index_age_secs, num_docs, size_in_bytes)
+        .map_err(|error| JanitorError::InvalidRollover(error.to_string()))
+}
+
+fn met_rollover_conditions(
+    conditions: &RolloverConditions,
+    index_age_secs: i64,
+    num_docs: u64,
+    size_in_bytes: u64,
+) -> anyhow::Result<Vec<&'static str>> {
+    let mut met_conditions = Vec::new();
+
+    if let Some(max_age) = conditions.max_age()? {
+        if index_age_secs >= max_age.as_secs() as i64 {
+            met_conditions.push("max_age");
+        }
+    }
+    if let Some(max_docs) = conditions.max_docs {
+        if num_docs >= max_docs {
+            met_conditions.push("max_docs");
+        }
+    }
+    if let Some(max_size_in_bytes) = conditions.max_size_in_bytes() {
+        if size_in_bytes >= max_size_in_bytes {
+            met_conditions.push("max_size");
+        }
+    }
+    Ok(met_conditions)
+}
+
+/// Increments the sequence number ending the index ID, preserving its width: `logs-000001`
+/// becomes `logs-000002`.
+fn next_rollover_index_id(index_id: &str) -> Option<String> {
+    let (prefix, sequence_number_str) = index_id.rsplit_once('-')?;
+    if sequence_number_str.is_empty()
+        || !sequence_number_str
+            .chars()
+            .all(|character| character.is_ascii_digit())
+    {
+        return None;
+    }
+    let sequence_number: u64 = sequence_number_str.parse().ok()?;
+    let width = sequence_number_str.len();
+    Some(format!("{prefix}-{:0width$}", sequence_number + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::{load_index_template_from_user_config, ConfigFormat};
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+
+    #[test]
+    fn test_next_rollover_index_id() {
+        assert_eq!(
+            next_rollover_index_id("logs-000001").unwrap(),
+            "logs-000002"
+        );
+        assert_eq!(
+            next_rollover_index_id("logs-eu-000099").unwrap(),
+            "logs-eu-000100"
+        );
+        assert_eq!(
+            next_rollover_index_id("logs-999999").unwrap(),
+            "logs-1000000"
+        );
+        assert!(next_rollover_index_id("logs").is_none());
+        assert!(next_rollover_index_id("logs-").is_none());
+        assert!(next_rollover_index_id("logs-eu").is_none());
+    }
+
+    #[test]
+    fn test_met_rollover_conditions() {
+        let conditions: RolloverConditions =
+            serde_json::from_str(r#"{"max_age": "1 hour", "max_docs": 1000, "max_size": "1 MB"}"#)
+                .unwrap();
+        assert!(met_rollover_conditions(&conditions, 60, 10, 1_000)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            met_rollover_conditions(&conditions, 3_600, 10, 1_000).unwrap(),
+            ["max_age"]
+        );
+        assert_eq!(
+            met_rollover_conditions(&conditions, 60, 1_000, 1_000_000).unwrap(),
+            ["max_docs", "max_size"]
+        );
+        assert!(
+            met_rollover_conditions(&RolloverConditions::default(), 3_600, 1_000, 0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollover_index_alias() {
+        let metastore = metastore_for_test();
+        let default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let no_conditions = RolloverConditions::default();
+
+        let error = rollover_index_alias(
+            &*metastore,
+            "logs",
+            &no_conditions,
+            &default_index_root_uri,
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, JanitorError::InvalidRollover(_)));
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+        "#;
+        let index_template = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap();
+        metastore
+            .create_index_template(index_template, false)
+            .await
+            .unwrap();
+
+        let rollover_report = rollover_index_alias(
+            &*metastore,
+            "logs",
+            &no_conditions,
+            &default_index_root_uri,
+            true,
+        )
+        .await
+        .unwrap();
+        assert!(rollover_report.rolled_over);
+        assert_eq!(rollover_report.new_index_id, "logs-000001");
+        assert!(!metastore.index_exists("logs-000001").await.unwrap());
+
+        let rollover_report = rollover_index_alias(
+            &*metastore,
+            "logs",
+            &no_conditions,
+            &default_index_root_uri,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(rollover_report.rolled_over);
+        assert!(rollover_report.old_index_id.is_none());
+        assert_eq!(rollover_report.new_index_id, "logs-000001");
+
+        let index_metadata = metastore.index_metadata("logs-000001").await.unwrap();
+        assert_eq!(
+            index_metadata.index_uri().as_str(),
+            "ram:///indexes/logs-000001"
+        );
+        assert_eq!(index_metadata.sources.len(), 2);
+
+        let max_docs_conditions = RolloverConditions {
+            max_docs: Some(1_000),
+            ..Default::default()
+        };
+        let rollover_report = rollover_index_alias(
+            &*metastore,
+            "logs",
+            &max_docs_conditions,
+            &default_index_root_uri,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(!rollover_report.rolled_over);
+        assert_eq!(rollover_report.old_index_id.unwrap(), "logs-000001");
+        assert_eq!(rollover_report.new_index_id, "logs-000002");
+        assert!(!metastore.index_exists("logs-000002").await.unwrap());
+
+        let rollover_report = rollover_index_alias(
+            &*metastore,
+            "logs",
+            &no_conditions,
+            &default_index_root_uri,
+            false,
+        )
+        .await
+        .unwrap();
+        assert!(rollover_report.rolled_over);
+        assert_eq!(rollover_report.new_index_id, "logs-000002");
+
+        let index_aliases = metastore.list_index_aliases().await.unwrap();
+        assert_eq!(index_aliases.len(), 1);
+        assert_eq!(index_aliases[0].alias_id, "logs");
+        assert_eq!(index_aliases[0].index_ids, ["logs-000002"]);
+        assert!(metastore.index_exists("logs-000001").await.unwrap());
+    }
+}
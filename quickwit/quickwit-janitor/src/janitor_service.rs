@@ -23,12 +23,18 @@ use quickwit_actors::{
 };
 use serde_json::{json, Value as JsonValue};
 
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+use crate::actors::{
+    DeleteTaskService, GarbageCollector, IndexRolloverExecutor, RetentionPolicyExecutor,
+    SplitVerifier, StorageReconciler,
+};
 
 pub struct JanitorService {
     delete_task_service_handle: ActorHandle<DeleteTaskService>,
     garbage_collector_handle: ActorHandle<GarbageCollector>,
     retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+    storage_reconciler_handle: ActorHandle<StorageReconciler>,
+    split_verifier_handle: ActorHandle<SplitVerifier>,
+    index_rollover_executor_handle: ActorHandle<IndexRolloverExecutor>,
 }
 
 impl JanitorService {
@@ -36,11 +42,17 @@ impl JanitorService {
         delete_task_service_handle: ActorHandle<DeleteTaskService>,
         garbage_collector_handle: ActorHandle<GarbageCollector>,
         retention_policy_executor_handle: ActorHandle<RetentionPolicyExecutor>,
+        storage_reconciler_handle: ActorHandle<StorageReconciler>,
+        split_verifier_handle: ActorHandle<SplitVerifier>,
+        index_rollover_executor_handle: ActorHandle<IndexRolloverExecutor>,
     ) -> Self {
         Self {
             delete_task_service_handle,
             garbage_collector_handle,
             retention_policy_executor_handle,
+            storage_reconciler_handle,
+            split_verifier_handle,
+            index_rollover_executor_handle,
         }
     }
 
@@ -49,6 +61,9 @@ impl JanitorService {
             &self.delete_task_service_handle,
             &self.garbage_collector_handle,
             &self.retention_policy_executor_handle,
+            &self.storage_reconciler_handle,
+            &self.split_verifier_handle,
+            &self.index_rollover_executor_handle,
         ]
     }
 
@@ -32,19 +32,39 @@ use tracing::info;
 pub mod actors;
 pub mod error;
 mod garbage_collection;
+mod index_rollover;
 mod janitor_service;
 mod metrics;
 mod retention_policy_execution;
+mod split_archival;
+mod split_verification;
+mod storage_reconciliation;
 
 pub use janitor_service::JanitorService;
 
 pub use self::garbage_collection::{
     delete_splits_with_files, run_garbage_collect, SplitDeletionError, SplitRemovalInfo,
 };
-use crate::actors::{DeleteTaskService, GarbageCollector, RetentionPolicyExecutor};
+pub use self::index_rollover::{rollover_index_alias, RolloverReport};
+pub use self::split_archival::{archive_splits, copy_split_files, rehydrate_splits};
+pub use self::split_verification::{
+    verify_index_splits, verify_split, CorruptSplit, SplitVerificationError,
+    SplitVerificationReport,
+};
+pub use self::storage_reconciliation::{reconcile_index_storage, StorageReconciliationReport};
+use crate::actors::{
+    DeleteTaskService, GarbageCollector, IndexRolloverExecutor, RetentionPolicyExecutor,
+    SplitVerifier, StorageReconciler,
+};
 
 #[derive(utoipa::OpenApi)]
-#[openapi(components(schemas(FileEntry)))]
+#[openapi(components(schemas(
+    FileEntry,
+    StorageReconciliationReport,
+    CorruptSplit,
+    SplitVerificationReport,
+    RolloverReport
+)))]
 /// Schema used for the OpenAPI generation which are apart of this crate.
 pub struct JanitorApiSchemas;
 
@@ -59,10 +79,23 @@ pub async fn start_janitor_service(
     let garbage_collector = GarbageCollector::new(metastore.clone(), storage_uri_resolver.clone());
     let (_, garbage_collector_handle) = universe.spawn_builder().spawn(garbage_collector);
 
-    let retention_policy_executor = RetentionPolicyExecutor::new(metastore.clone());
+    let storage_reconciler =
+        StorageReconciler::new(metastore.clone(), storage_uri_resolver.clone());
+    let (_, storage_reconciler_handle) = universe.spawn_builder().spawn(storage_reconciler);
+
+    let split_verifier = SplitVerifier::new(metastore.clone(), storage_uri_resolver.clone());
+    let (_, split_verifier_handle) = universe.spawn_builder().spawn(split_verifier);
+
+    let retention_policy_executor =
+        RetentionPolicyExecutor::new(metastore.clone(), storage_uri_resolver.clone());
     let (_, retention_policy_executor_handle) =
         universe.spawn_builder().spawn(retention_policy_executor);
 
+    let index_rollover_executor =
+        IndexRolloverExecutor::new(metastore.clone(), config.default_index_root_uri.clone());
+    let (_, index_rollover_executor_handle) =
+        universe.spawn_builder().spawn(index_rollover_executor);
+
     let delete_task_service = DeleteTaskService::new(
         metastore,
         search_job_placer,
@@ -76,6 +109,9 @@ pub async fn start_janitor_service(
         delete_task_service_handle,
         garbage_collector_handle,
         retention_policy_executor_handle,
+        storage_reconciler_handle,
+        split_verifier_handle,
+        index_rollover_executor_handle,
     );
     let (janitor_service_mailbox, _janitor_service_handle) =
         universe.spawn_builder().spawn(janitor_service);
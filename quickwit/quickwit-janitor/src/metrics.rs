@@ -18,10 +18,13 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use once_cell::sync::Lazy;
-use quickwit_common::metrics::{new_gauge_vec, IntGaugeVec};
+use quickwit_common::metrics::{new_counter_vec, new_gauge_vec, IntCounterVec, IntGaugeVec};
 
 pub struct JanitorMetrics {
     pub ongoing_num_delete_operations_total: IntGaugeVec<1>,
+    pub orphan_split_files: IntGaugeVec<1>,
+    pub missing_split_files: IntGaugeVec<1>,
+    pub corrupt_splits_total: IntCounterVec<1>,
 }
 
 impl Default for JanitorMetrics {
@@ -33,6 +36,24 @@ impl Default for JanitorMetrics {
                 "quickwit_janitor",
                 ["index"],
             ),
+            orphan_split_files: new_gauge_vec(
+                "orphan_split_files",
+                "Num of split files in the index storage unknown to the metastore (per index).",
+                "quickwit_janitor",
+                ["index"],
+            ),
+            missing_split_files: new_gauge_vec(
+                "missing_split_files",
+                "Num of published splits whose file is missing from the index storage (per index).",
+                "quickwit_janitor",
+                ["index"],
+            ),
+            corrupt_splits_total: new_counter_vec(
+                "corrupt_splits_total",
+                "Num of corrupt splits found by the split verifier (per index).",
+                "quickwit_janitor",
+                ["index"],
+            ),
         }
     }
 }
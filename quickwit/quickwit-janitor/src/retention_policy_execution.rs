@@ -17,33 +17,120 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::Context;
 use quickwit_actors::ActorContext;
+use quickwit_common::uri::Uri;
 use quickwit_common::PrettySample;
-use quickwit_config::RetentionPolicy;
+use quickwit_config::{RetentionAction, RetentionPolicy};
 use quickwit_metastore::{ListSplitsQuery, Metastore, SplitMetadata, SplitState};
+use quickwit_storage::StorageUriResolver;
 use time::OffsetDateTime;
 use tracing::{info, warn};
 
 use crate::actors::RetentionPolicyExecutor;
+use crate::split_archival::archive_splits;
 
 /// Detect all expired splits based a retention policy and
 /// only mark them as `MarkedForDeletion`. Actual split deletion
-/// is taken care of by the garbage collector.
+/// is taken care of by the garbage collector. If the action of the
+/// retention policy is `archive`, the expired splits are moved to the
+/// archive storage and marked as `Archived` instead.
+///
+/// Splits are first expired based on the retention period. Then, if the remaining splits exceed
+/// the maximum size or number of splits of the retention policy, the oldest ones are expired
+/// until the index fits within these limits.
 ///
 /// * `index_id` - The target index id.
+/// * `index_uri` - The URI of the target index.
 /// * `metastore` - The metastore managing the target index.
+/// * `storage_resolver` - The storage resolver used to resolve the index and archive storages.
 /// * `retention_policy` - The retention policy to used to evaluate the splits.
 /// * `ctx_opt` - A context for reporting progress (only useful within quickwit actor).
 pub async fn run_execute_retention_policy(
     index_id: &str,
+    index_uri: &Uri,
     metastore: Arc<dyn Metastore>,
+    storage_resolver: &StorageUriResolver,
     retention_policy: &RetentionPolicy,
     ctx: &ActorContext<RetentionPolicyExecutor>,
 ) -> anyhow::Result<Vec<SplitMetadata>> {
-    // Select splits that are published and older than the retention period.
-    let retention_period = retention_policy.retention_period()?;
+    let mut expired_splits = Vec::new();
+
+    if let Some(retention_period) = retention_policy.retention_period()? {
+        expired_splits =
+            list_splits_older_than_retention_period(index_id, &metastore, retention_period, ctx)
+                .await?;
+    }
+    let max_size_in_bytes_opt = retention_policy.max_size_in_bytes();
+    let max_num_splits_opt = retention_policy.max_num_splits();
+
+    if max_size_in_bytes_opt.is_some() || max_num_splits_opt.is_some() {
+        let expired_split_ids: HashSet<&str> = expired_splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect();
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+        let remaining_splits: Vec<SplitMetadata> = ctx
+            .protect_future(metastore.list_splits(query))
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .filter(|split_metadata| !expired_split_ids.contains(split_metadata.split_id()))
+            .collect();
+        let evicted_splits = select_splits_exceeding_limits(
+            remaining_splits,
+            max_size_in_bytes_opt,
+            max_num_splits_opt,
+        );
+        expired_splits.extend(evicted_splits);
+    }
+    if expired_splits.is_empty() {
+        return Ok(expired_splits);
+    }
+    if retention_policy.action() == RetentionAction::Archive {
+        let archive_uri = retention_policy
+            .archive_uri()
+            .context("The retention policy action `archive` requires an `archive_uri`.")?;
+        let index_storage = storage_resolver.resolve(index_uri)?;
+        let archive_storage = storage_resolver.resolve(archive_uri)?;
+        ctx.protect_future(archive_splits(
+            index_id,
+            metastore,
+            index_storage,
+            archive_storage,
+            &expired_splits,
+        ))
+        .await?;
+        return Ok(expired_splits);
+    }
+    // Mark the expired splits for deletion.
+    let expired_split_ids: Vec<&str> = expired_splits
+        .iter()
+        .map(|split_metadata| split_metadata.split_id())
+        .collect();
+    info!(
+        index_id=%index_id,
+        split_ids=?PrettySample::new(&expired_split_ids, 5),
+        "Marking {} splits for deletion based on retention policy.",
+        expired_split_ids.len()
+    );
+    ctx.protect_future(metastore.mark_splits_for_deletion(index_id, &expired_split_ids))
+        .await?;
+    Ok(expired_splits)
+}
+
+/// Selects the published splits that are older than the retention period.
+async fn list_splits_older_than_retention_period(
+    index_id: &str,
+    metastore: &Arc<dyn Metastore>,
+    retention_period: Duration,
+    ctx: &ActorContext<RetentionPolicyExecutor>,
+) -> anyhow::Result<Vec<SplitMetadata>> {
     let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
     let max_retention_timestamp = current_timestamp - retention_period.as_secs() as i64;
     let query = ListSplitsQuery::for_index(index_id)
@@ -69,21 +156,88 @@ pub async fn run_execute_retention_policy(
             ignored_split_ids.len()
         );
     }
-    if expired_splits.is_empty() {
-        return Ok(expired_splits);
-    }
-    // Mark the expired splits for deletion.
-    let expired_split_ids: Vec<&str> = expired_splits
-        .iter()
-        .map(|split_metadata| split_metadata.split_id())
-        .collect();
-    info!(
-        index_id=%index_id,
-        split_ids=?PrettySample::new(&expired_split_ids, 5),
-        "Marking {} splits for deletion based on retention policy.",
-        expired_split_ids.len()
-    );
-    ctx.protect_future(metastore.mark_splits_for_deletion(index_id, &expired_split_ids))
-        .await?;
     Ok(expired_splits)
 }
+
+/// Keeps the most recent splits within the maximum total size and number of splits, and returns
+/// the other ones. Splits are ordered by the end of their time range, or by their creation
+/// timestamp if they lack a time range.
+fn select_splits_exceeding_limits(
+    mut splits: Vec<SplitMetadata>,
+    max_size_in_bytes_opt: Option<u64>,
+    max_num_splits_opt: Option<usize>,
+) -> Vec<SplitMetadata> {
+    splits.sort_by_key(|split_metadata| {
+        let split_timestamp = split_metadata
+            .time_range
+            .as_ref()
+            .map(|time_range| *time_range.end())
+            .unwrap_or(split_metadata.create_timestamp);
+        Reverse(split_timestamp)
+    });
+    let max_size_in_bytes = max_size_in_bytes_opt.unwrap_or(u64::MAX);
+    let max_num_splits = max_num_splits_opt.unwrap_or(usize::MAX);
+    let mut total_size_in_bytes = 0u64;
+    let num_retained_splits = splits
+        .iter()
+        .enumerate()
+        .take_while(|(split_ord, split_metadata)| {
+            total_size_in_bytes += split_metadata.footer_offsets.end;
+            *split_ord < max_num_splits && total_size_in_bytes <= max_size_in_bytes
+        })
+        .count();
+    splits.split_off(num_retained_splits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_split(split_id: &str, end_timestamp: i64, size_in_bytes: u64) -> SplitMetadata {
+        SplitMetadata {
+            split_id: split_id.to_string(),
+            time_range: Some(0..=end_timestamp),
+            footer_offsets: 0..size_in_bytes,
+            ..Default::default()
+        }
+    }
+
+    fn split_ids(splits: &[SplitMetadata]) -> Vec<&str> {
+        splits
+            .iter()
+            .map(|split_metadata| split_metadata.split_id())
+            .collect()
+    }
+
+    #[test]
+    fn test_select_splits_exceeding_limits() {
+        let splits = vec![
+            make_split("split-2", 2000, 100),
+            make_split("split-4", 4000, 100),
+            make_split("split-1", 1000, 100),
+            make_split("split-3", 3000, 100),
+        ];
+        assert!(select_splits_exceeding_limits(splits.clone(), None, None).is_empty());
+        assert!(select_splits_exceeding_limits(splits.clone(), Some(400), Some(4)).is_empty());
+        assert_eq!(
+            split_ids(&select_splits_exceeding_limits(
+                splits.clone(),
+                Some(250),
+                None
+            )),
+            ["split-2", "split-1"]
+        );
+        assert_eq!(
+            split_ids(&select_splits_exceeding_limits(
+                splits.clone(),
+                None,
+                Some(1)
+            )),
+            ["split-3", "split-2", "split-1"]
+        );
+        assert_eq!(
+            split_ids(&select_splits_exceeding_limits(splits, Some(50), Some(3))),
+            ["split-4", "split-3", "split-2", "split-1"]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use quickwit_common::PrettySample;
+use quickwit_metastore::{Metastore, SplitMetadata};
+use quickwit_storage::{FilePayload, Storage};
+use tracing::{info, warn};
+
+/// Copies the files of the given splits from the source storage to the target storage, going
+/// through a scratch directory on the local disk.
+pub async fn copy_split_files(
+    source_storage: &dyn Storage,
+    target_storage: &dyn Storage,
+    splits: &[SplitMetadata],
+) -> anyhow::Result<()> {
+    let scratch_dir =
+        tempfile::tempdir().context("Failed to create scratch directory for the split copy.")?;
+
+    for split in splits {
+        let split_id = split.split_id();
+        let split_file = split.split_file();
+        let scratch_path = scratch_dir
+            .path()
+            .join(quickwit_common::split_file(split_id));
+        source_storage
+            .copy_to_file(Path::new(&split_file), &scratch_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to download split `{split_id}` from `{}`.",
+                    source_storage.uri()
+                )
+            })?;
+        let payload = FilePayload::open(scratch_path.clone()).await?;
+        target_storage
+            .put(Path::new(&split_file), Box::new(payload))
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to upload split `{split_id}` to `{}`.",
+                    target_storage.uri()
+                )
+            })?;
+        tokio::fs::remove_file(&scratch_path).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the files of the given splits. Failures are only logged: the files left behind are
+/// reported as orphans by the storage reconciler.
+async fn delete_split_files(index_id: &str, storage: &dyn Storage, splits: &[SplitMetadata]) {
+    let split_files: Vec<String> = splits.iter().map(SplitMetadata::split_file).collect();
+    let split_paths: Vec<&Path> = split_files.iter().map(Path::new).collect();
+
+    if let Err(bulk_delete_error) = storage.bulk_delete(&split_paths).await {
+        warn!(
+            index_id=%index_id,
+            storage_uri=%storage.uri(),
+            error=?bulk_delete_error,
+            "Failed to delete split files."
+        );
+    }
+}
+
+/// Moves the splits from the storage of the index to its archive storage and marks them as
+/// `Archived` in the metastore. The splits must be published.
+///
+/// The split files are copied to the archive storage before the splits are marked as archived,
+/// so that the splits always have a file in the storage matching their state.
+pub async fn archive_splits(
+    index_id: &str,
+    metastore: Arc<dyn Metastore>,
+    index_storage: Arc<dyn Storage>,
+    archive_storage: Arc<dyn Storage>,
+    splits: &[SplitMetadata],
+) -> anyhow::Result<()> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    let split_ids: Vec<&str> = splits
+        .iter()
+        .map(|split_metadata| split_metadata.split_id())
+        .collect();
+    info!(
+        index_id=%index_id,
+        split_ids=?PrettySample::new(&split_ids, 5),
+        archive_uri=%archive_storage.uri(),
+        "Archiving {} splits.",
+        split_ids.len()
+    );
+    copy_split_files(&*index_storage, &*archive_storage, splits).await?;
+    metastore.archive_splits(index_id, &split_ids).await?;
+    delete_split_files(index_id, &*index_storage, splits).await;
+    Ok(())
+}
+
+/// Moves the splits from the archive storage of the index back to its storage and marks them as
+/// `Published` in the metastore, making them searchable again. The splits must be archived.
+pub async fn rehydrate_splits(
+    index_id: &str,
+    metastore: Arc<dyn Metastore>,
+    index_storage: Arc<dyn Storage>,
+    archive_storage: Arc<dyn Storage>,
+    splits: &[SplitMetadata],
+) -> anyhow::Result<()> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+    let split_ids: Vec<&str> = splits
+        .iter()
+        .map(|split_metadata| split_metadata.split_id())
+        .collect();
+    info!(
+        index_id=%index_id,
+        split_ids=?PrettySample::new(&split_ids, 5),
+        archive_uri=%archive_storage.uri(),
+        "Rehydrating {} splits.",
+        split_ids.len()
+    );
+    copy_split_files(&*archive_storage, &*index_storage, splits).await?;
+    metastore.rehydrate_splits(index_id, &split_ids).await?;
+    delete_split_files(index_id, &*archive_storage, splits).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::IndexConfig;
+    use quickwit_metastore::{metastore_for_test, ListSplitsQuery, SplitState};
+    use quickwit_storage::RamStorage;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_archive_and_rehydrate_splits() {
+        let index_id = "test-archive-splits";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let metastore = metastore_for_test();
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let split_metadata = SplitMetadata {
+            split_id: "split-1".to_string(),
+            index_id: index_id.to_string(),
+            ..Default::default()
+        };
+        metastore
+            .stage_splits(index_id, vec![split_metadata.clone()])
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(index_id, &["split-1"], &[], None)
+            .await
+            .unwrap();
+
+        let index_storage: Arc<dyn Storage> = Arc::new(
+            RamStorage::builder()
+                .put("split-1.split", b"split-payload")
+                .build(),
+        );
+        let archive_storage: Arc<dyn Storage> = Arc::new(RamStorage::default());
+
+        archive_splits(
+            index_id,
+            metastore.clone(),
+            index_storage.clone(),
+            archive_storage.clone(),
+            &[split_metadata.clone()],
+        )
+        .await
+        .unwrap();
+
+        let split_path = Path::new("split-1.split");
+        assert!(!index_storage.exists(split_path).await.unwrap());
+        assert!(archive_storage.exists(split_path).await.unwrap());
+
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Archived);
+        assert_eq!(metastore.list_splits(query).await.unwrap().len(), 1);
+
+        rehydrate_splits(
+            index_id,
+            metastore.clone(),
+            index_storage.clone(),
+            archive_storage.clone(),
+            &[split_metadata],
+        )
+        .await
+        .unwrap();
+
+        let split_bytes = index_storage.get_all(split_path).await.unwrap();
+        assert_eq!(split_bytes.as_slice(), b"split-payload");
+        assert!(!archive_storage.exists(split_path).await.unwrap());
+
+        let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+        assert_eq!(metastore.list_splits(query).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use quickwit_common::PrettySample;
+use quickwit_directories::{BundleDirectory, HotDirectory};
+use quickwit_metastore::{Metastore, SplitMetadata};
+use quickwit_storage::{OwnedBytes, Storage, StorageError};
+use serde::{Deserialize, Serialize};
+use tantivy::directory::FileSlice;
+use tantivy::{Index, IndexReader, ReloadPolicy};
+use thiserror::Error;
+use tracing::{info, warn};
+
+#[derive(Error, Debug)]
+pub enum SplitVerificationError {
+    /// The split file could not be fetched, so its integrity is unknown.
+    #[error("Failed to fetch split file: `{0}`.")]
+    Storage(#[from] StorageError),
+    /// The split file is corrupt.
+    #[error("{0}")]
+    Corrupt(String),
+}
+
+/// A split that failed verification.
+#[derive(Clone, Debug, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct CorruptSplit {
+    pub split_id: String,
+    /// ID of the source the documents of the split were ingested from.
+    pub source_id: String,
+    /// Time range of the documents of the split, if the index has a timestamp field.
+    #[schema(value_type = Option<Vec<i64>>)]
+    pub time_range: Option<std::ops::RangeInclusive<i64>>,
+    pub reason: String,
+}
+
+/// Outcome of the verification of a set of splits.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct SplitVerificationReport {
+    /// Number of splits successfully verified, whether they are corrupt or not.
+    pub num_verified_splits: usize,
+    /// Number of splits that could not be verified because their file could not be fetched.
+    pub num_failed_verifications: usize,
+    /// Splits that failed verification.
+    pub corrupt_splits: Vec<CorruptSplit>,
+    /// IDs of the corrupt splits marked for deletion. Only populated when marking corrupt
+    /// splits is requested.
+    pub marked_split_ids: Vec<String>,
+}
+
+/// Downloads the file of a split and checks that:
+/// - the footer offsets recorded in the metastore match the file,
+/// - the bundle metadata and the hotcache can be parsed,
+/// - the checksums of all the files of the underlying tantivy index are valid,
+/// - the split can be opened through its hotcache and holds the expected number of documents.
+pub async fn verify_split(
+    storage: Arc<dyn Storage>,
+    split_metadata: &SplitMetadata,
+) -> Result<(), SplitVerificationError> {
+    let split_file_path = PathBuf::from(split_metadata.split_file());
+    let split_data = storage.get_all(&split_file_path).await?;
+    let split_metadata = split_metadata.clone();
+    tokio::task::spawn_blocking(move || verify_split_data(split_data, &split_metadata))
+        .await
+        .map_err(|join_error| {
+            SplitVerificationError::Corrupt(format!("Split verification panicked: {join_error}."))
+        })?
+}
+
+fn verify_split_data(
+    split_data: OwnedBytes,
+    split_metadata: &SplitMetadata,
+) -> Result<(), SplitVerificationError> {
+    let corrupt = SplitVerificationError::Corrupt;
+    let split_num_bytes = split_data.len() as u64;
+    let footer_offsets = &split_metadata.footer_offsets;
+    if footer_offsets.end != split_num_bytes || footer_offsets.start > footer_offsets.end {
+        return Err(corrupt(format!(
+            "Footer offsets `{footer_offsets:?}` do not match the split file of {split_num_bytes} \
+             bytes."
+        )));
+    }
+    let hotcache_bytes = quickwit_directories::get_hotcache_from_split(split_data.clone())
+        .map_err(|error| corrupt(format!("Failed to read hotcache: {error}.")))?;
+    let bundle_directory = BundleDirectory::open_split(FileSlice::new(Arc::new(split_data)))
+        .map_err(|error| corrupt(format!("Failed to read bundle metadata: {error}.")))?;
+
+    let index = Index::open(bundle_directory.clone())
+        .map_err(|error| corrupt(format!("Failed to open index: {error}.")))?;
+    let corrupt_file_paths = index
+        .validate_checksum()
+        .map_err(|error| corrupt(format!("Failed to validate checksums: {error}.")))?;
+    if !corrupt_file_paths.is_empty() {
+        let mut corrupt_file_names: Vec<String> = corrupt_file_paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        corrupt_file_names.sort();
+        return Err(corrupt(format!(
+            "Invalid checksum for file(s) {}.",
+            corrupt_file_names.join(", ")
+        )));
+    }
+    let hot_directory = HotDirectory::open(bundle_directory, hotcache_bytes)
+        .map_err(|error| corrupt(format!("Failed to open hotcache: {error}.")))?;
+    let index = Index::open(hot_directory)
+        .map_err(|error| corrupt(format!("Failed to open index with hotcache: {error}.")))?;
+    let reader: IndexReader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()
+        .map_err(|error| corrupt(format!("Failed to open index reader: {error}.")))?;
+    let num_docs = reader.searcher().num_docs();
+    if num_docs != split_metadata.num_docs as u64 {
+        return Err(corrupt(format!(
+            "Split holds {num_docs} document(s), expected {}.",
+            split_metadata.num_docs
+        )));
+    }
+    Ok(())
+}
+
+/// Verifies the integrity of the given splits of an index.
+///
+/// Splits whose file cannot be fetched are counted as failed verifications and are never
+/// considered corrupt. Splits do not record the checkpoint ranges they were indexed from, so
+/// documents of corrupt splits must be re-ingested from their source by the operator.
+///
+/// * `index_id` - The target index ID.
+/// * `storage` - The storage managing the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `splits` - The splits to verify.
+/// * `mark_corrupt` - Whether the corrupt splits should be marked for deletion so that searches
+///   stop failing on them.
+pub async fn verify_index_splits(
+    index_id: &str,
+    storage: Arc<dyn Storage>,
+    metastore: Arc<dyn Metastore>,
+    splits: &[SplitMetadata],
+    mark_corrupt: bool,
+) -> anyhow::Result<SplitVerificationReport> {
+    let mut report = SplitVerificationReport::default();
+
+    for split_metadata in splits {
+        match verify_split(storage.clone(), split_metadata).await {
+            Ok(()) => {}
+            Err(SplitVerificationError::Storage(error)) => {
+                report.num_failed_verifications += 1;
+                warn!(index_id=%index_id, split_id=%split_metadata.split_id(), error=?error, "Failed to verify split.");
+                continue;
+            }
+            Err(SplitVerificationError::Corrupt(reason)) => {
+                report.corrupt_splits.push(CorruptSplit {
+                    split_id: split_metadata.split_id().to_string(),
+                    source_id: split_metadata.source_id.clone(),
+                    time_range: split_metadata.time_range.clone(),
+                    reason,
+                });
+            }
+        }
+        report.num_verified_splits += 1;
+    }
+    if report.corrupt_splits.is_empty() {
+        return Ok(report);
+    }
+    for corrupt_split in &report.corrupt_splits {
+        warn!(
+            index_id=%index_id,
+            split_id=%corrupt_split.split_id,
+            source_id=%corrupt_split.source_id,
+            time_range=?corrupt_split.time_range,
+            reason=%corrupt_split.reason,
+            "Found corrupt split."
+        );
+    }
+    if !mark_corrupt {
+        return Ok(report);
+    }
+    let corrupt_split_ids: Vec<&str> = report
+        .corrupt_splits
+        .iter()
+        .map(|corrupt_split| corrupt_split.split_id.as_str())
+        .collect();
+    metastore
+        .mark_splits_for_deletion(index_id, &corrupt_split_ids)
+        .await?;
+    info!(
+        index_id=%index_id,
+        split_ids=?PrettySample::new(&corrupt_split_ids, 5),
+        "Marked {} corrupt split(s) for deletion.",
+        corrupt_split_ids.len()
+    );
+    report.marked_split_ids = corrupt_split_ids
+        .into_iter()
+        .map(|split_id| split_id.to_string())
+        .collect();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_common::split_file;
+    use quickwit_indexing::TestSandbox;
+    use quickwit_metastore::SplitState;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_index_splits() -> anyhow::Result<()> {
+        let index_id = "test-verify-index-splits";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "foo"})])
+            .await?;
+        test_sandbox
+            .add_documents(vec![serde_json::json!({"body": "bar"})])
+            .await?;
+        let metastore = test_sandbox.metastore();
+        let storage = test_sandbox.storage();
+        let splits: Vec<SplitMetadata> = metastore
+            .list_all_splits(index_id)
+            .await?
+            .into_iter()
+            .map(|split| split.split_metadata)
+            .collect();
+        assert_eq!(splits.len(), 2);
+
+        let report =
+            verify_index_splits(index_id, storage.clone(), metastore.clone(), &splits, true)
+                .await?;
+        assert_eq!(report.num_verified_splits, 2);
+        assert_eq!(report.num_failed_verifications, 0);
+        assert!(report.corrupt_splits.is_empty());
+
+        let corrupt_split_id = splits[0].split_id();
+        let corrupt_split_path = PathBuf::from(split_file(corrupt_split_id));
+        let mut split_data = storage.get_all(&corrupt_split_path).await?.to_vec();
+        split_data[0] ^= 0xFF;
+        storage
+            .put(&corrupt_split_path, Box::new(split_data))
+            .await?;
+        storage
+            .delete(Path::new(&split_file(splits[1].split_id())))
+            .await?;
+
+        let report =
+            verify_index_splits(index_id, storage.clone(), metastore.clone(), &splits, true)
+                .await?;
+        assert_eq!(report.num_verified_splits, 1);
+        assert_eq!(report.num_failed_verifications, 1);
+        assert_eq!(report.corrupt_splits.len(), 1);
+        assert_eq!(report.corrupt_splits[0].split_id, corrupt_split_id);
+        assert_eq!(report.marked_split_ids, vec![corrupt_split_id.to_string()]);
+
+        let split_states: Vec<(String, SplitState)> = metastore
+            .list_all_splits(index_id)
+            .await?
+            .into_iter()
+            .map(|split| (split.split_id().to_string(), split.split_state))
+            .collect();
+        for (split_id, split_state) in split_states {
+            if split_id == corrupt_split_id {
+                assert_eq!(split_state, SplitState::MarkedForDeletion);
+            } else {
+                assert_eq!(split_state, SplitState::Published);
+            }
+        }
+        test_sandbox.assert_quit().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use quickwit_common::{FileEntry, PrettySample, CONTENT_ADDRESSED_SPLIT_DIR};
+use quickwit_metastore::{Metastore, SplitState};
+use quickwit_storage::{Storage, StorageErrorKind};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+/// Differences found between the split files present in the storage of an index and the splits
+/// recorded in the metastore.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, utoipa::ToSchema)]
+pub struct StorageReconciliationReport {
+    /// Split files present in the index storage but unknown to the metastore.
+    pub orphan_files: Vec<FileEntry>,
+    /// IDs of the published splits whose file is missing from the index storage.
+    pub missing_split_ids: Vec<String>,
+    /// Orphan files successfully deleted. Only populated when the deletion of orphan files is
+    /// requested.
+    pub deleted_orphan_files: Vec<String>,
+}
+
+impl StorageReconciliationReport {
+    /// Returns whether the storage and the metastore agree.
+    pub fn is_consistent(&self) -> bool {
+        self.orphan_files.is_empty() && self.missing_split_ids.is_empty()
+    }
+
+    /// Returns the total size of the orphan files.
+    pub fn orphan_num_bytes(&self) -> u64 {
+        self.orphan_files
+            .iter()
+            .map(|file_entry| file_entry.file_size_in_bytes)
+            .sum()
+    }
+}
+
+/// Lists the split files stored at the root of the index storage and in its `splits`
+/// subdirectory, where the split files named after their content are stored, and diffs them
+/// against the splits recorded in the metastore.
+///
+/// A split file is an orphan when the metastore does not know about any split stored in it,
+/// whatever the split state. A split is missing when it is published but its file does not exist
+/// in the storage. Files that are not split files or that are located in other subdirectories are
+/// ignored.
+///
+/// * `index_id` - The target index ID.
+/// * `storage` - The storage managing the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `delete_orphans` - Whether the orphan files should be deleted from the storage.
+pub async fn reconcile_index_storage(
+    index_id: &str,
+    storage: Arc<dyn Storage>,
+    metastore: Arc<dyn Metastore>,
+    delete_orphans: bool,
+) -> anyhow::Result<StorageReconciliationReport> {
+    // The storage must be listed before the metastore: a split is always staged in the metastore
+    // before its file is uploaded, so a file listed first cannot belong to a split created
+    // in-between.
+    let mut file_paths = storage.list_dir(Path::new("")).await?;
+    file_paths.extend(
+        storage
+            .list_dir(Path::new(CONTENT_ADDRESSED_SPLIT_DIR))
+            .await?,
+    );
+    let split_file_paths: HashMap<String, PathBuf> = file_paths
+        .into_iter()
+        .filter_map(|file_path| {
+            if file_path.extension()? != "split" {
+                return None;
+            }
+            let split_file = file_path.to_str()?.to_string();
+            Some((split_file, file_path))
+        })
+        .collect();
+    let splits = metastore.list_all_splits(index_id).await?;
+    let known_split_files: HashSet<String> = splits
+        .iter()
+        .map(|split| split.split_metadata.split_file())
+        .collect();
+
+    let mut report = StorageReconciliationReport::default();
+
+    for (split_file, file_path) in &split_file_paths {
+        if known_split_files.contains(split_file) {
+            continue;
+        }
+        let file_size_in_bytes = match storage.file_num_bytes(file_path).await {
+            Ok(num_bytes) => num_bytes,
+            // The file was deleted since it was listed, most likely by the garbage collector.
+            Err(error) if error.kind() == StorageErrorKind::DoesNotExist => continue,
+            Err(error) => return Err(error.into()),
+        };
+        report.orphan_files.push(FileEntry {
+            file_name: file_path.to_string_lossy().to_string(),
+            file_size_in_bytes,
+        });
+    }
+    for split in &splits {
+        let split_file = split.split_metadata.split_file();
+
+        if split.split_state != SplitState::Published || split_file_paths.contains_key(&split_file)
+        {
+            continue;
+        }
+        // The split may have been uploaded and published after the storage was listed.
+        let split_file_path = PathBuf::from(split_file);
+        if !storage.exists(&split_file_path).await? {
+            report.missing_split_ids.push(split.split_id().to_string());
+        }
+    }
+    report
+        .orphan_files
+        .sort_by(|left, right| left.file_name.cmp(&right.file_name));
+    report.missing_split_ids.sort();
+
+    if !report.missing_split_ids.is_empty() {
+        warn!(
+            index_id=%index_id,
+            split_ids=?PrettySample::new(&report.missing_split_ids, 5),
+            "Found {} published split(s) without file.",
+            report.missing_split_ids.len()
+        );
+    }
+    if report.orphan_files.is_empty() {
+        return Ok(report);
+    }
+    let orphan_file_names: Vec<&str> = report
+        .orphan_files
+        .iter()
+        .map(|file_entry| file_entry.file_name.as_str())
+        .collect();
+    warn!(
+        index_id=%index_id,
+        file_names=?PrettySample::new(&orphan_file_names, 5),
+        "Found {} orphan split file(s).",
+        orphan_file_names.len()
+    );
+    if !delete_orphans {
+        return Ok(report);
+    }
+    let orphan_file_paths: Vec<&Path> = orphan_file_names.iter().map(Path::new).collect();
+    let deleted_orphan_files: Vec<String> = match storage.bulk_delete(&orphan_file_paths).await {
+        Ok(()) => orphan_file_names
+            .iter()
+            .map(|file_name| file_name.to_string())
+            .collect(),
+        Err(bulk_delete_error) => {
+            error!(index_id=%index_id, error=?bulk_delete_error, "Failed to delete orphan split files.");
+            bulk_delete_error
+                .successes
+                .iter()
+                .map(|file_path| file_path.to_string_lossy().to_string())
+                .collect()
+        }
+    };
+    info!(
+        index_id=%index_id,
+        "Deleted {} orphan split file(s).",
+        deleted_orphan_files.len()
+    );
+    report.deleted_orphan_files = deleted_orphan_files;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quickwit_config::IndexConfig;
+    use quickwit_metastore::{metastore_for_test, SplitMetadata};
+    use quickwit_storage::{storage_for_test, PutPayload};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reconcile_index_storage() {
+        let storage = storage_for_test();
+        let metastore = metastore_for_test();
+
+        let index_id = "test-reconcile-index-storage--index";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let split_metadatas = ["published", "missing", "staged"]
+            .into_iter()
+            .map(|split_id| SplitMetadata {
+                split_id: split_id.to_string(),
+                index_id: index_id.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        metastore
+            .stage_splits(index_id, split_metadatas)
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(index_id, &["published", "missing"], &[], None)
+            .await
+            .unwrap();
+
+        for file_name in [
+            "published.split",
+            "staged.split",
+            "orphan.split",
+            "not-a-split.json",
+            "nested/nested-orphan.split",
+        ] {
+            let payload: Box<dyn PutPayload> = Box::new(b"split".to_vec());
+            storage.put(Path::new(file_name), payload).await.unwrap();
+        }
+        let report = reconcile_index_storage(index_id, storage.clone(), metastore.clone(), false)
+            .await
+            .unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.orphan_files.len(), 1);
+        assert_eq!(report.orphan_files[0].file_name, "orphan.split");
+        assert_eq!(report.orphan_num_bytes(), 5);
+        assert_eq!(report.missing_split_ids, ["missing"]);
+        assert!(report.deleted_orphan_files.is_empty());
+        assert!(storage.exists(Path::new("orphan.split")).await.unwrap());
+
+        let report = reconcile_index_storage(index_id, storage.clone(), metastore.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(report.deleted_orphan_files, ["orphan.split"]);
+        assert!(!storage.exists(Path::new("orphan.split")).await.unwrap());
+        assert!(storage.exists(Path::new("staged.split")).await.unwrap());
+        assert!(storage
+            .exists(Path::new("nested/nested-orphan.split"))
+            .await
+            .unwrap());
+
+        let report = reconcile_index_storage(index_id, storage, metastore, false)
+            .await
+            .unwrap();
+        assert!(report.orphan_files.is_empty());
+        assert_eq!(report.missing_split_ids, ["missing"]);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_index_storage_content_addressed() {
+        let storage = storage_for_test();
+        let metastore = metastore_for_test();
+
+        let index_id = "test-reconcile-index-storage-content-addressed--index";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let split_metadatas = [
+            ("split-1", "abcd"),
+            ("split-2", "abcd"),
+            ("split-3", "ef01"),
+        ]
+        .into_iter()
+        .map(|(split_id, content_hash)| SplitMetadata {
+            split_id: split_id.to_string(),
+            index_id: index_id.to_string(),
+            content_hash: Some(content_hash.to_string()),
+            ..Default::default()
+        })
+        .collect();
+        metastore
+            .stage_splits(index_id, split_metadatas)
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(index_id, &["split-1", "split-2", "split-3"], &[], None)
+            .await
+            .unwrap();
+
+        // The file of `split-1` in the split ID layout is left over from a migration.
+        for file_name in ["splits/abcd.split", "splits/2345.split", "split-1.split"] {
+            let payload: Box<dyn PutPayload> = Box::new(b"split".to_vec());
+            storage.put(Path::new(file_name), payload).await.unwrap();
+        }
+        let report = reconcile_index_storage(index_id, storage, metastore, false)
+            .await
+            .unwrap();
+        let orphan_file_names: Vec<&str> = report
+            .orphan_files
+            .iter()
+            .map(|file_entry| file_entry.file_name.as_str())
+            .collect();
+        assert_eq!(orphan_file_names, ["split-1.split", "splits/2345.split"]);
+        assert_eq!(report.missing_split_ids, ["split-3"]);
+    }
+}
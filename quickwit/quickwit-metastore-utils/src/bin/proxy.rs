@@ -98,6 +98,16 @@ impl MetastoreApiService for MetastoreProxyService {
         let resp = lock.client.index_metadata(request).await?;
         Ok(resp)
     }
+    /// Updates an index config.
+    async fn update_index_config(
+        &self,
+        request: tonic::Request<UpdateIndexConfigRequest>,
+    ) -> Result<tonic::Response<UpdateIndexConfigResponse>, tonic::Status> {
+        let mut lock = self.inner.lock().await;
+        lock.record(request.get_ref().clone()).await.unwrap();
+        let resp = lock.client.update_index_config(request).await?;
+        Ok(resp)
+    }
     /// Gets an indexes metadatas.
     async fn list_indexes_metadatas(
         &self,
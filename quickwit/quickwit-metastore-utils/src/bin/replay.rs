@@ -37,6 +37,9 @@ async fn replay_grpc_request(
         GrpcRequest::IndexMetadataRequest(req) => {
             client.index_metadata(req).await?;
         }
+        GrpcRequest::UpdateIndexConfigRequest(req) => {
+            client.update_index_config(req).await?;
+        }
         GrpcRequest::ListIndexesMetadatasRequest(req) => {
             client.list_indexes_metadatas(req).await?;
         }
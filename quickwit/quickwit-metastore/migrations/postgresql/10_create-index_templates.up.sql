@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS index_templates (
+    template_id VARCHAR(50) PRIMARY KEY,
+    index_template_json TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS leases (
+    lease_id VARCHAR(50) PRIMARY KEY,
+    holder_id VARCHAR(255) NOT NULL,
+    expire_timestamp BIGINT NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS index_aliases (
+    alias_id VARCHAR(50) NOT NULL,
+    index_id VARCHAR(50) NOT NULL REFERENCES indexes(index_id) ON DELETE CASCADE,
+    PRIMARY KEY (alias_id, index_id)
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS alert_rules (
+    rule_id VARCHAR(50) PRIMARY KEY,
+    alert_rule_json TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS api_keys (
+    key_id VARCHAR(50) PRIMARY KEY,
+    api_key_json TEXT NOT NULL
+);
@@ -0,0 +1,119 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+
+use quickwit_common::index_id_matches_pattern;
+use quickwit_config::ApiKeyScope;
+use serde::{Deserialize, Serialize};
+
+/// An API key, as stored in the metastore. Only the hash of the key secret is stored, the secret
+/// itself is returned once, upon creation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ApiKey {
+    /// Public identifier of the key, embedded in the key itself.
+    pub key_id: String,
+    /// Hex-encoded SHA-256 hash of the key secret.
+    pub key_hash: String,
+    /// Free-form description of the key.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Operations granted to the key.
+    pub scopes: BTreeSet<ApiKeyScope>,
+    /// Patterns of the index IDs the key grants access to, in which `*` matches any sequence of
+    /// characters.
+    pub index_patterns: Vec<String>,
+    /// Names of the roles assigned to the key, whose permissions add up to the scopes above.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    /// Timestamp of the key creation.
+    pub create_timestamp: i64,
+}
+
+impl ApiKey {
+    /// Returns whether the key grants `scope`.
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&ApiKeyScope::Admin)
+    }
+
+    /// Returns whether the key grants access to the index `index_id`.
+    pub fn can_access_index(&self, index_id: &str) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|index_pattern| index_id_matches_pattern(index_id, index_pattern))
+    }
+
+    /// Returns whether the key grants access to all the indexes.
+    pub fn can_access_all_indexes(&self) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|index_pattern| index_pattern == "*")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_permissions() {
+        let api_key = ApiKey {
+            key_id: "test-key".to_string(),
+            key_hash: "".to_string(),
+            description: None,
+            scopes: BTreeSet::from([ApiKeyScope::Search]),
+            index_patterns: vec!["logs-*".to_string()],
+            roles: Vec::new(),
+            create_timestamp: 0,
+        };
+        assert!(api_key.has_scope(ApiKeyScope::Search));
+        assert!(!api_key.has_scope(ApiKeyScope::Ingest));
+        assert!(!api_key.has_scope(ApiKeyScope::Admin));
+        assert!(api_key.can_access_index("logs-eu"));
+        assert!(!api_key.can_access_index("traces"));
+        assert!(!api_key.can_access_all_indexes());
+
+        let admin_api_key = ApiKey {
+            scopes: BTreeSet::from([ApiKeyScope::Admin]),
+            ..api_key
+        };
+        assert!(admin_api_key.has_scope(ApiKeyScope::Ingest));
+        assert!(admin_api_key.has_scope(ApiKeyScope::Search));
+    }
+
+    #[test]
+    fn test_api_key_serde() {
+        let api_key_json = r#"{
+            "key_id": "test-key",
+            "key_hash": "abc",
+            "scopes": ["ingest", "search"],
+            "index_patterns": ["*"],
+            "create_timestamp": 1
+        }"#;
+        let api_key: ApiKey = serde_json::from_str(api_key_json).unwrap();
+        assert_eq!(api_key.description, None);
+        assert!(api_key.roles.is_empty());
+        assert_eq!(
+            api_key.scopes,
+            BTreeSet::from([ApiKeyScope::Ingest, ApiKeyScope::Search])
+        );
+    }
+}
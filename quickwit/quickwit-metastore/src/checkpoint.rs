@@ -122,10 +122,72 @@ impl<'a> From<&'a str> for Position {
     }
 }
 
+/// Token identifying the indexing pipeline, or more precisely the partition assignment of an
+/// indexing pipeline, that emitted a checkpoint delta.
+///
+/// Tokens are issued by the metastore from a per-source counter, see
+/// [`Metastore::issue_publish_token`](crate::Metastore::issue_publish_token), so a pipeline whose
+/// partitions get reassigned obtains a token greater than the tokens of all the previous owners of
+/// those partitions, regardless of the clocks of the nodes. Tokens are compared lexicographically,
+/// hence the zero-padding of the counter. Once a delta carrying a given token has been applied to
+/// a partition, deltas carrying a smaller token, or no token at all, for the same partition are
+/// rejected, which fences off zombie pipelines.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PublishToken(Arc<String>);
+
+impl PublishToken {
+    /// String representation of the publish token.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for PublishToken {
+    fn from(publish_token_str: String) -> Self {
+        PublishToken(Arc::new(publish_token_str))
+    }
+}
+
+impl From<&str> for PublishToken {
+    fn from(publish_token_str: &str) -> Self {
+        PublishToken(Arc::new(publish_token_str.to_string()))
+    }
+}
+
+impl From<u64> for PublishToken {
+    fn from(counter: u64) -> Self {
+        PublishToken(Arc::new(format!("{counter:0>20}")))
+    }
+}
+
+impl fmt::Display for PublishToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 #[derive(Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct IndexCheckpoint {
     #[serde(flatten)]
     per_source: BTreeMap<String, SourceCheckpoint>,
+    /// Per-source, per-partition token of the last publisher. The key cannot collide with a
+    /// source ID since `$` is not a valid identifier character.
+    #[serde(
+        rename = "$publish_tokens",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    publish_tokens: BTreeMap<String, BTreeMap<PartitionId, PublishToken>>,
+    /// Per-source counter of the last publish token issued by the metastore. It survives the
+    /// resets of the checkpoint of the source, so that the pipelines still holding older tokens
+    /// remain fenced.
+    #[serde(
+        rename = "$issued_publish_tokens",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    issued_publish_tokens: BTreeMap<String, u64>,
 }
 
 impl fmt::Debug for IndexCheckpoint {
@@ -138,7 +200,11 @@ impl fmt::Debug for IndexCheckpoint {
 
 impl From<BTreeMap<String, SourceCheckpoint>> for IndexCheckpoint {
     fn from(per_source: BTreeMap<String, SourceCheckpoint>) -> Self {
-        IndexCheckpoint { per_source }
+        IndexCheckpoint {
+            per_source,
+            publish_tokens: BTreeMap::default(),
+            issued_publish_tokens: BTreeMap::default(),
+        }
     }
 }
 
@@ -150,23 +216,96 @@ impl IndexCheckpoint {
     /// checkpoint remains unchanged.
     ///
     /// See [`SourceCheckpoint::try_apply_delta`] for more details.
+    ///
+    /// The publish token of the delta must not be older than the token of the last publisher of
+    /// any of the partitions covered by the delta, and deltas without a token are rejected on the
+    /// partitions that have a publisher token. Either all the partitions of the delta are updated,
+    /// or none of them.
     pub fn try_apply_delta(
         &mut self,
         delta: IndexCheckpointDelta,
-    ) -> Result<bool, IncompatibleCheckpointDelta> {
+    ) -> Result<bool, IndexCheckpointDeltaError> {
         if delta.is_empty() {
             return Ok(false);
         }
+        let IndexCheckpointDelta {
+            source_id,
+            source_delta,
+            publish_token,
+        } = delta;
+        self.check_publish_token(&source_id, &source_delta, publish_token.as_ref())?;
+
+        let partition_ids: Vec<PartitionId> = if publish_token.is_some() {
+            source_delta.partitions().cloned().collect()
+        } else {
+            Vec::new()
+        };
         self.per_source
-            .entry(delta.source_id)
+            .entry(source_id.clone())
             .or_default()
-            .try_apply_delta(delta.source_delta)?;
+            .try_apply_delta(source_delta)?;
+
+        if let Some(publish_token) = publish_token {
+            let source_publish_tokens = self.publish_tokens.entry(source_id).or_default();
+
+            for partition_id in partition_ids {
+                source_publish_tokens.insert(partition_id, publish_token.clone());
+            }
+        }
         Ok(true)
     }
 
+    fn check_publish_token(
+        &self,
+        source_id: &str,
+        delta: &SourceCheckpointDelta,
+        publish_token_opt: Option<&PublishToken>,
+    ) -> Result<(), StalePublishToken> {
+        let Some(source_publish_tokens) = self.publish_tokens.get(source_id) else {
+            return Ok(());
+        };
+        for partition_id in delta.partitions() {
+            let Some(current_publish_token) = source_publish_tokens.get(partition_id) else {
+                continue;
+            };
+            if publish_token_opt
+                .map(|publish_token| current_publish_token > publish_token)
+                .unwrap_or(true)
+            {
+                return Err(StalePublishToken {
+                    partition_id: partition_id.clone(),
+                    current_publish_token: current_publish_token.clone(),
+                    delta_publish_token: publish_token_opt.cloned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Issues a publish token for the source, greater than all the tokens previously issued for
+    /// it.
+    pub fn issue_publish_token(&mut self, source_id: &str) -> PublishToken {
+        let last_issued_publish_token = self
+            .issued_publish_tokens
+            .entry(source_id.to_string())
+            .or_default();
+        *last_issued_publish_token += 1;
+        PublishToken::from(*last_issued_publish_token)
+    }
+
+    /// Returns the token of the last publisher of a given partition, if any.
+    pub fn publish_token_for_partition(
+        &self,
+        source_id: &str,
+        partition_id: &PartitionId,
+    ) -> Option<&PublishToken> {
+        self.publish_tokens.get(source_id)?.get(partition_id)
+    }
+
     /// Resets the checkpoint of the source identified by `source_id`. Returns whether a mutation
     /// occurred.
     pub(crate) fn reset_source(&mut self, source_id: &str) -> bool {
+        self.publish_tokens.remove(source_id);
         self.per_source.remove(source_id).is_some()
     }
 
@@ -189,6 +328,8 @@ impl IndexCheckpoint {
     /// Removes a source.
     /// Returns successfully regardless of whether the source was present or not.
     pub fn remove_source(&mut self, source_id: &str) {
+        self.publish_tokens.remove(source_id);
+        self.issued_publish_tokens.remove(source_id);
         self.per_source.remove(source_id);
     }
 
@@ -280,6 +421,31 @@ pub struct IncompatibleCheckpointDelta {
     pub delta_position_from: Position,
 }
 
+/// Error returned when trying to apply a checkpoint delta emitted by a publisher that has since
+/// been superseded, ie. another publisher with a more recent token has already published on one
+/// of the partitions of the delta.
+#[derive(Clone, Debug, Error, Eq, PartialEq, Serialize, Deserialize)]
+#[error(
+    "StalePublishToken at partition: {partition_id:?} cur_token:{current_publish_token} \
+     delta_token:{delta_publish_token:?}"
+)]
+pub struct StalePublishToken {
+    /// One PartitionId for which the stale token has been detected.
+    pub partition_id: PartitionId,
+    /// The token of the last publisher of this partition.
+    pub current_publish_token: PublishToken,
+    /// The token carried by the delta, if any.
+    pub delta_publish_token: Option<PublishToken>,
+}
+
+#[derive(Clone, Debug, Error, Serialize, Deserialize, PartialEq, Eq)]
+pub enum IndexCheckpointDeltaError {
+    #[error(transparent)]
+    IncompatibleCheckpointDelta(#[from] IncompatibleCheckpointDelta),
+    #[error(transparent)]
+    StalePublishToken(#[from] StalePublishToken),
+}
+
 #[derive(Clone, Debug, Error, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PartitionDeltaError {
     #[error(transparent)]
@@ -408,6 +574,10 @@ pub struct SourceCheckpointDelta {
 pub struct IndexCheckpointDelta {
     pub source_id: String,
     pub source_delta: SourceCheckpointDelta,
+    /// Token of the publisher of the delta. Deltas without a token are only fenced on the
+    /// partitions that have a publisher token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_token: Option<PublishToken>,
 }
 
 impl IndexCheckpointDelta {
@@ -420,13 +590,23 @@ impl IndexCheckpointDelta {
         IndexCheckpointDelta {
             source_id: source_id.to_string(),
             source_delta: SourceCheckpointDelta::from_range(pos_range),
+            publish_token: None,
         }
     }
+
+    /// Stamps the delta with the token of its publisher.
+    pub fn with_publish_token(mut self, publish_token: PublishToken) -> Self {
+        self.publish_token = Some(publish_token);
+        self
+    }
 }
 
 impl fmt::Debug for IndexCheckpointDelta {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}:{:?}", &self.source_id, self.source_delta)?;
+        if let Some(publish_token) = &self.publish_token {
+            write!(f, "@{publish_token}")?;
+        }
         Ok(())
     }
 }
@@ -832,6 +1012,169 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_index_checkpoint_fences_stale_publish_tokens() {
+        let mut index_checkpoint = IndexCheckpoint::default();
+        let mut source_delta = SourceCheckpointDelta::default();
+        for partition in ["a", "b"] {
+            source_delta
+                .record_partition_delta(
+                    PartitionId::from(partition),
+                    Position::Beginning,
+                    Position::from(10u64),
+                )
+                .unwrap();
+        }
+        let delta = IndexCheckpointDelta {
+            source_id: "source".to_string(),
+            source_delta,
+            publish_token: Some(PublishToken::from("token-2")),
+        };
+        assert!(index_checkpoint.try_apply_delta(delta).unwrap());
+        assert_eq!(
+            index_checkpoint
+                .publish_token_for_partition("source", &PartitionId::from("a"))
+                .unwrap(),
+            &PublishToken::from("token-2")
+        );
+        // A zombie pipeline publishing with an older token is fenced, even though its delta does
+        // not overlap with the current checkpoint. None of its partitions are updated.
+        let mut zombie_source_delta = SourceCheckpointDelta::default();
+        zombie_source_delta
+            .record_partition_delta(
+                PartitionId::from("b"),
+                Position::from(20u64),
+                Position::from(30u64),
+            )
+            .unwrap();
+        zombie_source_delta
+            .record_partition_delta(
+                PartitionId::from("c"),
+                Position::Beginning,
+                Position::from(30u64),
+            )
+            .unwrap();
+        let zombie_delta = IndexCheckpointDelta {
+            source_id: "source".to_string(),
+            source_delta: zombie_source_delta,
+            publish_token: Some(PublishToken::from("token-1")),
+        };
+        let error = index_checkpoint
+            .try_apply_delta(zombie_delta.clone())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            IndexCheckpointDeltaError::StalePublishToken(StalePublishToken {
+                partition_id: PartitionId::from("b"),
+                current_publish_token: PublishToken::from("token-2"),
+                delta_publish_token: Some(PublishToken::from("token-1")),
+            })
+        );
+        let source_checkpoint = index_checkpoint.source_checkpoint("source").unwrap();
+        assert_eq!(source_checkpoint.num_partitions(), 2);
+
+        // The new owner of the partitions can publish.
+        let new_owner_delta = zombie_delta.with_publish_token(PublishToken::from("token-3"));
+        assert!(index_checkpoint.try_apply_delta(new_owner_delta).unwrap());
+        assert_eq!(
+            index_checkpoint
+                .publish_token_for_partition("source", &PartitionId::from("c"))
+                .unwrap(),
+            &PublishToken::from("token-3")
+        );
+        // Deltas without token are fenced on the partitions that have a publisher token.
+        let delta = IndexCheckpointDelta {
+            source_id: "source".to_string(),
+            source_delta: SourceCheckpointDelta::from_partition_delta(
+                PartitionId::from("a"),
+                Position::from(10u64),
+                Position::from(20u64),
+            )
+            .unwrap(),
+            publish_token: None,
+        };
+        let error = index_checkpoint.try_apply_delta(delta).unwrap_err();
+        assert_eq!(
+            error,
+            IndexCheckpointDeltaError::StalePublishToken(StalePublishToken {
+                partition_id: PartitionId::from("a"),
+                current_publish_token: PublishToken::from("token-2"),
+                delta_publish_token: None,
+            })
+        );
+        // Deltas without token are not fenced on the other partitions.
+        let delta = IndexCheckpointDelta {
+            source_id: "source".to_string(),
+            source_delta: SourceCheckpointDelta::from_partition_delta(
+                PartitionId::from("d"),
+                Position::Beginning,
+                Position::from(20u64),
+            )
+            .unwrap(),
+            publish_token: None,
+        };
+        assert!(index_checkpoint.try_apply_delta(delta).unwrap());
+        assert!(index_checkpoint
+            .publish_token_for_partition("source", &PartitionId::from("d"))
+            .is_none());
+
+        let index_checkpoint_json = serde_json::to_string(&index_checkpoint).unwrap();
+        let deserialized_index_checkpoint: IndexCheckpoint =
+            serde_json::from_str(&index_checkpoint_json).unwrap();
+        assert_eq!(deserialized_index_checkpoint, index_checkpoint);
+
+        index_checkpoint.remove_source("source");
+        assert!(index_checkpoint
+            .publish_token_for_partition("source", &PartitionId::from("a"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_index_checkpoint_issue_publish_token() {
+        let mut index_checkpoint = IndexCheckpoint::default();
+        let first_publish_token = index_checkpoint.issue_publish_token("source");
+        assert_eq!(first_publish_token, PublishToken::from(1u64));
+
+        // The counter survives the resets of the source checkpoint.
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta::for_test("source", 0..10))
+            .unwrap();
+        index_checkpoint.reset_source("source");
+        let second_publish_token = index_checkpoint.issue_publish_token("source");
+        assert!(first_publish_token < second_publish_token);
+        assert_eq!(
+            index_checkpoint.issue_publish_token("other-source"),
+            PublishToken::from(1u64)
+        );
+        // Zero-padding keeps the lexicographic order of the tokens numeric.
+        assert!(PublishToken::from(9u64) < PublishToken::from(10u64));
+
+        let index_checkpoint_json = serde_json::to_string(&index_checkpoint).unwrap();
+        let mut deserialized_index_checkpoint: IndexCheckpoint =
+            serde_json::from_str(&index_checkpoint_json).unwrap();
+        assert_eq!(deserialized_index_checkpoint, index_checkpoint);
+        assert_eq!(
+            deserialized_index_checkpoint.issue_publish_token("source"),
+            PublishToken::from(3u64)
+        );
+    }
+
+    #[test]
+    fn test_index_checkpoint_serialization_without_publish_tokens() {
+        let mut index_checkpoint = IndexCheckpoint::default();
+        index_checkpoint
+            .try_apply_delta(IndexCheckpointDelta::for_test("source", 0..10))
+            .unwrap();
+        let index_checkpoint_json = serde_json::to_value(&index_checkpoint).unwrap();
+        assert_eq!(
+            index_checkpoint_json,
+            serde_json::json!({"source": {"": "00000000000000000009"}})
+        );
+        let deserialized_index_checkpoint: IndexCheckpoint =
+            serde_json::from_value(index_checkpoint_json).unwrap();
+        assert_eq!(deserialized_index_checkpoint, index_checkpoint);
+    }
+
     #[test]
     fn test_get_source_checkpoint() {
         let partition = PartitionId::from("a");
@@ -21,12 +21,26 @@ use quickwit_proto::{ServiceError, ServiceErrorCode};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::checkpoint::IncompatibleCheckpointDelta;
+use crate::checkpoint::{
+    IncompatibleCheckpointDelta, IndexCheckpointDeltaError, StalePublishToken,
+};
 
 /// Metastore error kinds.
 #[allow(missing_docs)]
 #[derive(Clone, Debug, Error, Serialize, Deserialize, PartialEq, Eq)]
 pub enum MetastoreError {
+    #[error("Alert rule `{rule_id}` already exists.")]
+    AlertRuleAlreadyExists { rule_id: String },
+
+    #[error("Alert rule `{rule_id}` does not exist.")]
+    AlertRuleDoesNotExist { rule_id: String },
+
+    #[error("API key `{key_id}` already exists.")]
+    ApiKeyAlreadyExists { key_id: String },
+
+    #[error("API key `{key_id}` does not exist.")]
+    ApiKeyDoesNotExist { key_id: String },
+
     #[error("Connection error: `{message}`.")]
     ConnectionError { message: String },
 
@@ -39,12 +53,27 @@ pub enum MetastoreError {
     #[error("Index `{index_id}` does not exist.")]
     IndexDoesNotExist { index_id: String },
 
+    #[error("Index alias `{alias_id}` does not exist.")]
+    IndexAliasDoesNotExist { alias_id: String },
+
+    #[error("Index template `{template_id}` already exists.")]
+    IndexTemplateAlreadyExists { template_id: String },
+
+    #[error("Index template `{template_id}` does not exist.")]
+    IndexTemplateDoesNotExist { template_id: String },
+
     /// Any generic internal error.
     /// The message can be helpful to users, but the detail of the error
     /// are judged uncoverable and not useful for error handling.
     #[error("Internal error: `{message}` Cause: `{cause}`.")]
     InternalError { message: String, cause: String },
 
+    #[error("Invalid index alias update: `{message}`")]
+    InvalidIndexAlias { message: String },
+
+    #[error("Invalid index config update: `{message}`")]
+    InvalidIndexConfigUpdate { message: String },
+
     #[error("Failed to deserialize index metadata: `{message}`")]
     InvalidManifest { message: String },
 
@@ -60,9 +89,18 @@ pub enum MetastoreError {
     #[error("Splits `{split_ids:?}` are not staged.")]
     SplitsNotStaged { split_ids: Vec<String> },
 
+    #[error("Splits `{split_ids:?}` are not published.")]
+    SplitsNotPublished { split_ids: Vec<String> },
+
+    #[error("Splits `{split_ids:?}` are not archived.")]
+    SplitsNotArchived { split_ids: Vec<String> },
+
     #[error("Publish checkpoint delta overlaps with the current checkpoint: {0:?}.")]
     IncompatibleCheckpointDelta(#[from] IncompatibleCheckpointDelta),
 
+    #[error("Publish token is stale, the partitions were reassigned to another pipeline: {0:?}.")]
+    StalePublishToken(#[from] StalePublishToken),
+
     #[error("Source `{source_id}` of type `{source_type}` already exists.")]
     SourceAlreadyExists {
         source_id: String,
@@ -88,6 +126,15 @@ pub enum MetastoreError {
     },
 }
 
+impl From<IndexCheckpointDeltaError> for MetastoreError {
+    fn from(error: IndexCheckpointDeltaError) -> Self {
+        match error {
+            IndexCheckpointDeltaError::IncompatibleCheckpointDelta(error) => error.into(),
+            IndexCheckpointDeltaError::StalePublishToken(error) => error.into(),
+        }
+    }
+}
+
 #[cfg(feature = "postgres")]
 impl From<sqlx::Error> for MetastoreError {
     fn from(error: sqlx::Error) -> Self {
@@ -109,12 +156,22 @@ impl From<MetastoreError> for quickwit_proto::tonic::Status {
 impl ServiceError for MetastoreError {
     fn status_code(&self) -> ServiceErrorCode {
         match self {
+            Self::AlertRuleAlreadyExists { .. } => ServiceErrorCode::BadRequest,
+            Self::AlertRuleDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            Self::ApiKeyAlreadyExists { .. } => ServiceErrorCode::BadRequest,
+            Self::ApiKeyDoesNotExist { .. } => ServiceErrorCode::NotFound,
             Self::ConnectionError { .. } => ServiceErrorCode::Internal,
             Self::Forbidden { .. } => ServiceErrorCode::MethodNotAllowed,
             Self::IncompatibleCheckpointDelta(_) => ServiceErrorCode::BadRequest,
+            Self::StalePublishToken(_) => ServiceErrorCode::BadRequest,
             Self::IndexAlreadyExists { .. } => ServiceErrorCode::BadRequest,
             Self::IndexDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            Self::IndexAliasDoesNotExist { .. } => ServiceErrorCode::NotFound,
+            Self::IndexTemplateAlreadyExists { .. } => ServiceErrorCode::BadRequest,
+            Self::IndexTemplateDoesNotExist { .. } => ServiceErrorCode::NotFound,
             Self::InternalError { .. } => ServiceErrorCode::Internal,
+            Self::InvalidIndexAlias { .. } => ServiceErrorCode::BadRequest,
+            Self::InvalidIndexConfigUpdate { .. } => ServiceErrorCode::BadRequest,
             Self::InvalidManifest { .. } => ServiceErrorCode::Internal,
             Self::Io { .. } => ServiceErrorCode::Internal,
             Self::SourceAlreadyExists { .. } => ServiceErrorCode::BadRequest,
@@ -122,6 +179,8 @@ impl ServiceError for MetastoreError {
             Self::SplitsDoNotExist { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotDeletable { .. } => ServiceErrorCode::BadRequest,
             Self::SplitsNotStaged { .. } => ServiceErrorCode::BadRequest,
+            Self::SplitsNotPublished { .. } => ServiceErrorCode::BadRequest,
+            Self::SplitsNotArchived { .. } => ServiceErrorCode::BadRequest,
             Self::DbError { .. } => ServiceErrorCode::Internal,
             Self::JsonDeserializeError { .. } => ServiceErrorCode::Internal,
             Self::JsonSerializeError { .. } => ServiceErrorCode::Internal,
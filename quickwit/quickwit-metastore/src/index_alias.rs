@@ -0,0 +1,244 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{MetastoreError, MetastoreResult};
+
+/// An index alias is a name resolving to one or several indexes. Searching an alias searches all
+/// the indexes it points to.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct IndexAlias {
+    /// Name of the alias. It lives in the same namespace as the index IDs.
+    pub alias_id: String,
+    /// IDs of the indexes the alias points to, sorted.
+    pub index_ids: Vec<String>,
+}
+
+/// An action on the index aliases. The actions of a single update are applied atomically, which
+/// allows swapping the index an alias points to without any window during which the alias
+/// resolves to zero or two indexes.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexAliasAction {
+    /// Makes the alias `alias_id` point to the index `index_id`, creating the alias if needed.
+    Add {
+        /// Name of the alias.
+        alias_id: String,
+        /// ID of the index to add to the alias.
+        index_id: String,
+    },
+    /// Removes the index `index_id` from the alias `alias_id`. The alias is deleted once it does
+    /// not point to any index anymore.
+    Remove {
+        /// Name of the alias.
+        alias_id: String,
+        /// ID of the index to remove from the alias.
+        index_id: String,
+    },
+}
+
+impl IndexAliasAction {
+    /// Returns the IDs of the alias and the index the action refers to.
+    pub fn ids(&self) -> (&str, &str) {
+        match self {
+            IndexAliasAction::Add { alias_id, index_id }
+            | IndexAliasAction::Remove { alias_id, index_id } => {
+                (alias_id.as_str(), index_id.as_str())
+            }
+        }
+    }
+}
+
+/// Applies `actions` in order to `aliases` and returns the resulting aliases. The update is all or
+/// nothing: if any action is invalid, an error is returned and no action is applied.
+///
+/// `existing_index_ids` must contain, among the alias and index IDs referred to by the actions,
+/// the ones that are IDs of existing indexes.
+pub(crate) fn apply_index_alias_actions(
+    aliases: Vec<IndexAlias>,
+    actions: &[IndexAliasAction],
+    existing_index_ids: &HashSet<String>,
+) -> MetastoreResult<Vec<IndexAlias>> {
+    let mut alias_map: BTreeMap<String, BTreeSet<String>> = aliases
+        .into_iter()
+        .map(|alias| (alias.alias_id, alias.index_ids.into_iter().collect()))
+        .collect();
+    for action in actions {
+        match action {
+            IndexAliasAction::Add { alias_id, index_id } => {
+                if existing_index_ids.contains(alias_id) {
+                    return Err(MetastoreError::InvalidIndexAlias {
+                        message: format!(
+                            "alias `{alias_id}` conflicts with the index of the same name"
+                        ),
+                    });
+                }
+                if !existing_index_ids.contains(index_id) {
+                    return Err(MetastoreError::IndexDoesNotExist {
+                        index_id: index_id.clone(),
+                    });
+                }
+                alias_map
+                    .entry(alias_id.clone())
+                    .or_default()
+                    .insert(index_id.clone());
+            }
+            IndexAliasAction::Remove { alias_id, index_id } => {
+                let Some(index_ids) = alias_map.get_mut(alias_id) else {
+                    return Err(MetastoreError::IndexAliasDoesNotExist {
+                        alias_id: alias_id.clone(),
+                    });
+                };
+                if !index_ids.remove(index_id) {
+                    return Err(MetastoreError::InvalidIndexAlias {
+                        message: format!("alias `{alias_id}` does not point to index `{index_id}`"),
+                    });
+                }
+                if index_ids.is_empty() {
+                    alias_map.remove(alias_id);
+                }
+            }
+        }
+    }
+    let aliases = alias_map
+        .into_iter()
+        .map(|(alias_id, index_ids)| IndexAlias {
+            alias_id,
+            index_ids: index_ids.into_iter().collect(),
+        })
+        .collect();
+    Ok(aliases)
+}
+
+/// Removes the index `index_id` from all the aliases, deleting the aliases left empty. Returns
+/// whether any alias was modified.
+pub(crate) fn remove_index_from_aliases(aliases: &mut Vec<IndexAlias>, index_id: &str) -> bool {
+    let mut modified = false;
+    for alias in aliases.iter_mut() {
+        let num_index_ids = alias.index_ids.len();
+        alias
+            .index_ids
+            .retain(|alias_index_id| alias_index_id != index_id);
+        modified |= alias.index_ids.len() != num_index_ids;
+    }
+    aliases.retain(|alias| !alias.index_ids.is_empty());
+    modified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(alias_id: &str, index_id: &str) -> IndexAliasAction {
+        IndexAliasAction::Add {
+            alias_id: alias_id.to_string(),
+            index_id: index_id.to_string(),
+        }
+    }
+
+    fn remove(alias_id: &str, index_id: &str) -> IndexAliasAction {
+        IndexAliasAction::Remove {
+            alias_id: alias_id.to_string(),
+            index_id: index_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_index_alias_actions() {
+        let existing_index_ids: HashSet<String> = ["logs-v1", "logs-v2"]
+            .into_iter()
+            .map(|index_id| index_id.to_string())
+            .collect();
+        let aliases =
+            apply_index_alias_actions(Vec::new(), &[add("logs", "logs-v1")], &existing_index_ids)
+                .unwrap();
+        assert_eq!(
+            aliases,
+            [IndexAlias {
+                alias_id: "logs".to_string(),
+                index_ids: vec!["logs-v1".to_string()],
+            }]
+        );
+        let swapped_aliases = apply_index_alias_actions(
+            aliases.clone(),
+            &[remove("logs", "logs-v1"), add("logs", "logs-v2")],
+            &existing_index_ids,
+        )
+        .unwrap();
+        assert_eq!(swapped_aliases[0].index_ids, ["logs-v2"]);
+
+        let error = apply_index_alias_actions(
+            aliases.clone(),
+            &[add("logs", "logs-v2"), add("logs", "logs-v3")],
+            &existing_index_ids,
+        )
+        .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        let error = apply_index_alias_actions(
+            aliases.clone(),
+            &[add("logs-v1", "logs-v2")],
+            &existing_index_ids,
+        )
+        .unwrap_err();
+        assert!(matches!(error, MetastoreError::InvalidIndexAlias { .. }));
+
+        let error = apply_index_alias_actions(
+            aliases.clone(),
+            &[remove("logs", "logs-v2")],
+            &existing_index_ids,
+        )
+        .unwrap_err();
+        assert!(matches!(error, MetastoreError::InvalidIndexAlias { .. }));
+
+        let error =
+            apply_index_alias_actions(aliases, &[remove("traces", "logs-v1")], &existing_index_ids)
+                .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::IndexAliasDoesNotExist { .. }
+        ));
+    }
+
+    #[test]
+    fn test_remove_index_from_aliases() {
+        let mut aliases = vec![
+            IndexAlias {
+                alias_id: "logs".to_string(),
+                index_ids: vec!["logs-v1".to_string()],
+            },
+            IndexAlias {
+                alias_id: "all".to_string(),
+                index_ids: vec!["logs-v1".to_string(), "traces".to_string()],
+            },
+        ];
+        assert!(!remove_index_from_aliases(&mut aliases, "logs-v2"));
+        assert!(remove_index_from_aliases(&mut aliases, "logs-v1"));
+        assert_eq!(
+            aliases,
+            [IndexAlias {
+                alias_id: "all".to_string(),
+                index_ids: vec!["traces".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,40 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+
+/// A lease grants its holder the exclusive ownership of a resource, for instance the leadership of
+/// the control plane, until it expires. The holder must renew the lease before it expires to keep
+/// it.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    /// Identifier of the leased resource.
+    pub lease_id: String,
+    /// Identifier of the holder of the lease, usually a node ID.
+    pub holder_id: String,
+    /// Timestamp at which the lease expires, measured with the clock of the metastore.
+    pub expire_timestamp: i64,
+}
+
+impl Lease {
+    /// Returns whether the lease is held by `holder_id`.
+    pub fn is_held_by(&self, holder_id: &str) -> bool {
+        self.holder_id == holder_id
+    }
+}
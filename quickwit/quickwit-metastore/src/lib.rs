@@ -29,9 +29,12 @@
 
 #[macro_use]
 mod tests;
+mod api_key;
 #[allow(missing_docs)]
 pub mod checkpoint;
 mod error;
+mod index_alias;
+mod lease;
 mod metastore;
 mod metastore_resolver;
 mod metrics;
@@ -40,11 +43,17 @@ mod split_metadata_version;
 
 use std::ops::Range;
 
+pub use api_key::ApiKey;
 pub use error::{MetastoreError, MetastoreResolverError, MetastoreResult};
+pub(crate) use index_alias::{apply_index_alias_actions, remove_index_from_aliases};
+pub use index_alias::{IndexAlias, IndexAliasAction};
+pub use lease::Lease;
 pub use metastore::file_backed_metastore::FileBackedMetastore;
 pub use metastore::grpc_metastore::{GrpcMetastoreAdapter, MetastoreGrpcClient};
 pub(crate) use metastore::index_metadata::serialize::{IndexMetadataV0_4, VersionedIndexMetadata};
-pub use metastore::metastore_event_publisher::{MetastoreEvent, MetastoreEventPublisher};
+pub use metastore::metastore_event_publisher::{
+    MetastoreEvent, MetastoreEventPublisher, PublishSplitsEvent,
+};
 #[cfg(feature = "postgres")]
 pub use metastore::postgresql_metastore::PostgresqlMetastore;
 pub use metastore::retrying_metastore::RetryingMetastore;
@@ -55,12 +64,17 @@ pub use metastore_resolver::{
     quickwit_metastore_uri_resolver, MetastoreFactory, MetastoreUriResolver,
 };
 use quickwit_common::is_disjoint;
+pub use quickwit_config::ApiKeyScope;
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 pub use split_metadata::{Split, SplitMetadata, SplitState};
 pub(crate) use split_metadata_version::{SplitMetadataV0_4, VersionedSplitMetadata};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(
+    ApiKey,
+    ApiKeyScope,
+    IndexAlias,
+    IndexAliasAction,
     Split,
     SplitState,
     VersionedIndexMetadata,
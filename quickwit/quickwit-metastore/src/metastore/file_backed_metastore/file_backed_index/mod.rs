@@ -28,14 +28,14 @@ use std::fmt::Debug;
 
 use itertools::Itertools;
 use quickwit_common::PrettySample;
-use quickwit_config::{SourceConfig, TestableForRegression};
+use quickwit_config::{IndexConfig, SourceConfig, TestableForRegression};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use serde::{Deserialize, Serialize};
 use serialize::VersionedFileBackedIndex;
 use time::OffsetDateTime;
 use tracing::{info, warn};
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
 use crate::{
     split_tag_filter, IndexMetadata, ListSplitsQuery, MetastoreError, MetastoreResult, Split,
     SplitMetadata, SplitState,
@@ -257,6 +257,51 @@ impl FileBackedIndex {
         Ok(is_modified)
     }
 
+    /// Moves the splits from `from_state` to `to_state`. Splits already in `to_state` are
+    /// skipped. Returns whether a mutation occurred.
+    pub(crate) fn transition_splits_state(
+        &mut self,
+        split_ids: &[&str],
+        from_state: SplitState,
+        to_state: SplitState,
+    ) -> MetastoreResult<bool> {
+        let mut is_modified = false;
+        let mut split_not_found_ids = Vec::new();
+        let mut split_invalid_state_ids = Vec::new();
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+        for &split_id in split_ids {
+            let Some(metadata) = self.splits.get_mut(split_id) else {
+                split_not_found_ids.push(split_id.to_string());
+                continue;
+            };
+            if metadata.split_state == to_state {
+                continue;
+            }
+            if metadata.split_state != from_state {
+                split_invalid_state_ids.push(split_id.to_string());
+                continue;
+            }
+            metadata.split_state = to_state;
+            metadata.update_timestamp = now_timestamp;
+            is_modified = true;
+        }
+        if !split_not_found_ids.is_empty() {
+            return Err(MetastoreError::SplitsDoNotExist {
+                split_ids: split_not_found_ids,
+            });
+        }
+        if !split_invalid_state_ids.is_empty() {
+            let split_ids = split_invalid_state_ids;
+            return Err(match from_state {
+                SplitState::Archived => MetastoreError::SplitsNotArchived { split_ids },
+                SplitState::Staged => MetastoreError::SplitsNotStaged { split_ids },
+                _ => MetastoreError::SplitsNotPublished { split_ids },
+            });
+        }
+        Ok(is_modified)
+    }
+
     /// Helper to mark a list of splits as published.
     /// This function however does not update the checkpoint.
     fn mark_splits_as_published_helper(&mut self, split_ids: &[&str]) -> MetastoreResult<()> {
@@ -332,7 +377,9 @@ impl FileBackedIndex {
                 self.splits.remove(split_id);
                 DeleteSplitOutcome::Success
             }
-            Some(SplitState::Staged | SplitState::Published) => DeleteSplitOutcome::Forbidden,
+            Some(SplitState::Staged | SplitState::Published | SplitState::Archived) => {
+                DeleteSplitOutcome::Forbidden
+            }
             None => DeleteSplitOutcome::SplitNotFound,
         }
     }
@@ -371,6 +418,14 @@ impl FileBackedIndex {
         Ok(())
     }
 
+    /// Updates the index config. Returns whether a mutation occurred.
+    pub(crate) fn update_index_config(
+        &mut self,
+        index_config: IndexConfig,
+    ) -> MetastoreResult<bool> {
+        self.metadata.update_index_config(index_config)
+    }
+
     /// Adds a source.
     pub(crate) fn add_source(&mut self, source: SourceConfig) -> MetastoreResult<()> {
         self.metadata.add_source(source)
@@ -390,6 +445,11 @@ impl FileBackedIndex {
         Ok(self.metadata.checkpoint.reset_source(source_id))
     }
 
+    /// Issues a new publish token for the source identified by `source_id`.
+    pub(crate) fn issue_publish_token(&mut self, source_id: &str) -> PublishToken {
+        self.metadata.checkpoint.issue_publish_token(source_id)
+    }
+
     /// Creates [`DeleteTask`] from a [`DeleteQuery`].
     pub(crate) fn create_delete_task(
         &mut self,
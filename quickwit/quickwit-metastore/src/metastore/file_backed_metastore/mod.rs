@@ -26,28 +26,31 @@ mod file_backed_metastore_factory;
 mod lazy_file_backed_index;
 mod store_operations;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::future::try_join_all;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use quickwit_storage::Storage;
+use time::OffsetDateTime;
 use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 
 use self::file_backed_index::FileBackedIndex;
 pub use self::file_backed_metastore_factory::FileBackedMetastoreFactory;
 use self::lazy_file_backed_index::LazyFileBackedIndex;
 use self::store_operations::{
-    delete_index, fetch_and_build_indexes_states, fetch_index, index_exists, put_index,
-    put_indexes_states,
+    delete_index, fetch_alert_rules, fetch_and_build_indexes_states, fetch_api_keys, fetch_index,
+    fetch_index_aliases, fetch_index_templates, index_exists, put_alert_rules, put_api_keys,
+    put_index, put_index_aliases, put_index_templates, put_indexes_states,
 };
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
 use crate::{
-    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, MetastoreResult, Split,
+    apply_index_alias_actions, remove_index_from_aliases, ApiKey, IndexAlias, IndexAliasAction,
+    IndexMetadata, Lease, ListSplitsQuery, Metastore, MetastoreError, MetastoreResult, Split,
     SplitMetadata, SplitState,
 };
 
@@ -98,6 +101,17 @@ pub struct FileBackedMetastore {
     storage: Arc<dyn Storage>,
     per_index_metastores: Arc<RwLock<HashMap<String, IndexState>>>,
     polling_interval_opt: Option<Duration>,
+    /// Serializes the read-modify-write cycles of the API keys file.
+    api_keys_mutex: Mutex<()>,
+    /// Serializes the read-modify-write cycles of the index templates file.
+    index_templates_mutex: Mutex<()>,
+    /// Serializes the read-modify-write cycles of the index aliases file.
+    index_aliases_mutex: Mutex<()>,
+    /// Serializes the read-modify-write cycles of the alert rules file.
+    alert_rules_mutex: Mutex<()>,
+    /// Leases are short-lived, so they are kept in memory rather than written to the storage. They
+    /// are lost when the metastore restarts, after which their holders acquire them again.
+    leases: Mutex<HashMap<String, Lease>>,
 }
 
 impl FileBackedMetastore {
@@ -108,6 +122,11 @@ impl FileBackedMetastore {
             storage,
             per_index_metastores: Default::default(),
             polling_interval_opt: None,
+            api_keys_mutex: Mutex::default(),
+            index_templates_mutex: Mutex::default(),
+            index_aliases_mutex: Mutex::default(),
+            alert_rules_mutex: Mutex::default(),
+            leases: Mutex::default(),
         }
     }
 
@@ -136,6 +155,11 @@ impl FileBackedMetastore {
             storage,
             per_index_metastores,
             polling_interval_opt,
+            api_keys_mutex: Mutex::default(),
+            index_templates_mutex: Mutex::default(),
+            index_aliases_mutex: Mutex::default(),
+            alert_rules_mutex: Mutex::default(),
+            leases: Mutex::default(),
         })
     }
 
@@ -367,6 +391,11 @@ impl Metastore for FileBackedMetastore {
                     per_index_metastores_wlock.insert(index_id.to_string(), IndexState::Deleting);
                     return Err(error);
                 }
+                let _index_aliases_guard = self.index_aliases_mutex.lock().await;
+                let mut index_aliases = fetch_index_aliases(&*self.storage).await?;
+                if remove_index_from_aliases(&mut index_aliases, index_id) {
+                    put_index_aliases(&*self.storage, &index_aliases).await?;
+                }
             },
             _ => {}
         }
@@ -433,6 +462,7 @@ impl Metastore for FileBackedMetastore {
                     SplitState::Staged,
                     SplitState::Published,
                     SplitState::MarkedForDeletion,
+                    SplitState::Archived,
                 ],
                 false,
             )
@@ -441,6 +471,30 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.transition_splits_state(split_ids, SplitState::Published, SplitState::Archived)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.mutate(index_id, |index| {
+            index.transition_splits_state(split_ids, SplitState::Archived, SplitState::Published)
+        })
+        .await?;
+        Ok(())
+    }
+
     async fn delete_splits<'a>(
         &self,
         index_id: &str,
@@ -454,6 +508,13 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        let index_id = index_config.index_id.clone();
+        self.mutate(&index_id, |index| index.update_index_config(index_config))
+            .await?;
+        Ok(())
+    }
+
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
         self.mutate(index_id, |index| {
             index.add_source(source)?;
@@ -490,6 +551,21 @@ impl Metastore for FileBackedMetastore {
         Ok(())
     }
 
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        // The `mutate` callback only returns a boolean, so the token is captured on the side.
+        let mut publish_token_opt: Option<PublishToken> = None;
+        self.mutate(index_id, |index| {
+            publish_token_opt = Some(index.issue_publish_token(source_id));
+            Ok(true)
+        })
+        .await?;
+        Ok(publish_token_opt.expect("The publish token should have been issued."))
+    }
+
     /// -------------------------------------------------------------------------------
     /// Read-only accessors
 
@@ -603,6 +679,157 @@ impl Metastore for FileBackedMetastore {
             .await??;
         Ok(delete_tasks)
     }
+
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        let _api_keys_guard = self.api_keys_mutex.lock().await;
+        let mut api_keys = fetch_api_keys(&*self.storage).await?;
+        if api_keys
+            .iter()
+            .any(|existing_api_key| existing_api_key.key_id == api_key.key_id)
+        {
+            return Err(MetastoreError::ApiKeyAlreadyExists {
+                key_id: api_key.key_id,
+            });
+        }
+        api_keys.push(api_key);
+        put_api_keys(&*self.storage, &api_keys).await
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        fetch_api_keys(&*self.storage).await
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        let _api_keys_guard = self.api_keys_mutex.lock().await;
+        let mut api_keys = fetch_api_keys(&*self.storage).await?;
+        let num_api_keys = api_keys.len();
+        api_keys.retain(|api_key| api_key.key_id != key_id);
+        if api_keys.len() == num_api_keys {
+            return Err(MetastoreError::ApiKeyDoesNotExist {
+                key_id: key_id.to_string(),
+            });
+        }
+        put_api_keys(&*self.storage, &api_keys).await
+    }
+
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let _index_templates_guard = self.index_templates_mutex.lock().await;
+        let mut index_templates = fetch_index_templates(&*self.storage).await?;
+        let existing_template_pos = index_templates.iter().position(|existing_index_template| {
+            existing_index_template.template_id == index_template.template_id
+        });
+        match existing_template_pos {
+            Some(_) if !overwrite => {
+                return Err(MetastoreError::IndexTemplateAlreadyExists {
+                    template_id: index_template.template_id,
+                });
+            }
+            Some(pos) => index_templates[pos] = index_template,
+            None => index_templates.push(index_template),
+        }
+        put_index_templates(&*self.storage, &index_templates).await
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        fetch_index_templates(&*self.storage).await
+    }
+
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        let _index_templates_guard = self.index_templates_mutex.lock().await;
+        let mut index_templates = fetch_index_templates(&*self.storage).await?;
+        let num_index_templates = index_templates.len();
+        index_templates.retain(|index_template| index_template.template_id != template_id);
+        if index_templates.len() == num_index_templates {
+            return Err(MetastoreError::IndexTemplateDoesNotExist {
+                template_id: template_id.to_string(),
+            });
+        }
+        put_index_templates(&*self.storage, &index_templates).await
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        let now_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let mut leases = self.leases.lock().await;
+        let lease = leases.entry(lease_id.to_string()).or_insert_with(|| Lease {
+            lease_id: lease_id.to_string(),
+            holder_id: holder_id.to_string(),
+            expire_timestamp: now_timestamp,
+        });
+        if lease.is_held_by(holder_id) || lease.expire_timestamp <= now_timestamp {
+            lease.holder_id = holder_id.to_string();
+            lease.expire_timestamp = now_timestamp + lease_duration_secs as i64;
+        }
+        Ok(lease.clone())
+    }
+
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        let _index_aliases_guard = self.index_aliases_mutex.lock().await;
+        let mut existing_index_ids = HashSet::new();
+        for action in &actions {
+            let (alias_id, index_id) = action.ids();
+            for id in [alias_id, index_id] {
+                if !existing_index_ids.contains(id) && index_exists(&*self.storage, id).await? {
+                    existing_index_ids.insert(id.to_string());
+                }
+            }
+        }
+        let index_aliases = fetch_index_aliases(&*self.storage).await?;
+        let index_aliases =
+            apply_index_alias_actions(index_aliases, &actions, &existing_index_ids)?;
+        put_index_aliases(&*self.storage, &index_aliases).await
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        fetch_index_aliases(&*self.storage).await
+    }
+
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let _alert_rules_guard = self.alert_rules_mutex.lock().await;
+        let mut alert_rules = fetch_alert_rules(&*self.storage).await?;
+        let existing_rule_pos = alert_rules
+            .iter()
+            .position(|existing_alert_rule| existing_alert_rule.rule_id == alert_rule.rule_id);
+        match existing_rule_pos {
+            Some(_) if !overwrite => {
+                return Err(MetastoreError::AlertRuleAlreadyExists {
+                    rule_id: alert_rule.rule_id,
+                });
+            }
+            Some(pos) => alert_rules[pos] = alert_rule,
+            None => alert_rules.push(alert_rule),
+        }
+        put_alert_rules(&*self.storage, &alert_rules).await
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        fetch_alert_rules(&*self.storage).await
+    }
+
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        let _alert_rules_guard = self.alert_rules_mutex.lock().await;
+        let mut alert_rules = fetch_alert_rules(&*self.storage).await?;
+        let num_alert_rules = alert_rules.len();
+        alert_rules.retain(|alert_rule| alert_rule.rule_id != rule_id);
+        if alert_rules.len() == num_alert_rules {
+            return Err(MetastoreError::AlertRuleDoesNotExist {
+                rule_id: rule_id.to_string(),
+            });
+        }
+        put_alert_rules(&*self.storage, &alert_rules).await
+    }
 }
 
 async fn get_index_mutex(
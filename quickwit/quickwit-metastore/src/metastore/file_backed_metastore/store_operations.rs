@@ -22,16 +22,29 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
+use quickwit_config::{AlertRule, IndexTemplate};
 use quickwit_storage::{Storage, StorageError, StorageErrorKind};
 use serde::{Deserialize, Serialize};
 
 use super::{IndexState, LazyFileBackedIndex};
 use crate::metastore::file_backed_metastore::file_backed_index::FileBackedIndex;
-use crate::{MetastoreError, MetastoreResult};
+use crate::{ApiKey, IndexAlias, MetastoreError, MetastoreResult};
 
 /// Indexes states file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
 const INDEXES_STATES_FILENAME: &str = "indexes_states.json";
 
+/// API keys file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
+const API_KEYS_FILENAME: &str = "api_keys.json";
+
+/// Index templates file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
+const INDEX_TEMPLATES_FILENAME: &str = "index_templates.json";
+
+/// Index aliases file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
+const INDEX_ALIASES_FILENAME: &str = "index_aliases.json";
+
+/// Alert rules file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
+const ALERT_RULES_FILENAME: &str = "alert_rules.json";
+
 /// Index metadata file managed by [`FileBackedMetastore`](crate::FileBackedMetastore).
 const META_FILENAME: &str = "metastore.json";
 
@@ -144,6 +157,197 @@ pub(crate) async fn put_indexes_states(
     Ok(())
 }
 
+/// Fetches the `API_KEYS_FILENAME` file. If the file does not exist, returns an empty list.
+pub(crate) async fn fetch_api_keys(storage: &dyn Storage) -> MetastoreResult<Vec<ApiKey>> {
+    let api_keys_path = Path::new(API_KEYS_FILENAME);
+    let exists = storage
+        .exists(api_keys_path)
+        .await
+        .map_err(|storage_err| convert_error("api_keys", storage_err))?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let content = storage
+        .get_all(api_keys_path)
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to get `{API_KEYS_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    let api_keys: Vec<ApiKey> = serde_json::from_slice(&content[..]).map_err(|serde_err| {
+        MetastoreError::InvalidManifest {
+            message: serde_err.to_string(),
+        }
+    })?;
+    Ok(api_keys)
+}
+
+pub(crate) async fn put_api_keys(
+    storage: &dyn Storage,
+    api_keys: &[ApiKey],
+) -> MetastoreResult<()> {
+    let api_keys_path = Path::new(API_KEYS_FILENAME);
+    let content: Vec<u8> =
+        serde_json::to_vec_pretty(api_keys).map_err(|serde_err| MetastoreError::InternalError {
+            message: "Failed to serialize API keys".to_string(),
+            cause: serde_err.to_string(),
+        })?;
+    storage
+        .put(api_keys_path, Box::new(content))
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to put `{API_KEYS_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    Ok(())
+}
+
+/// Fetches the `INDEX_TEMPLATES_FILENAME` file. If the file does not exist, returns an empty
+/// list.
+pub(crate) async fn fetch_index_templates(
+    storage: &dyn Storage,
+) -> MetastoreResult<Vec<IndexTemplate>> {
+    let index_templates_path = Path::new(INDEX_TEMPLATES_FILENAME);
+    let exists = storage
+        .exists(index_templates_path)
+        .await
+        .map_err(|storage_err| convert_error("index_templates", storage_err))?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let content = storage
+        .get_all(index_templates_path)
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to get `{INDEX_TEMPLATES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    let index_templates: Vec<IndexTemplate> =
+        serde_json::from_slice(&content[..]).map_err(|serde_err| {
+            MetastoreError::InvalidManifest {
+                message: serde_err.to_string(),
+            }
+        })?;
+    Ok(index_templates)
+}
+
+pub(crate) async fn put_index_templates(
+    storage: &dyn Storage,
+    index_templates: &[IndexTemplate],
+) -> MetastoreResult<()> {
+    let index_templates_path = Path::new(INDEX_TEMPLATES_FILENAME);
+    let content: Vec<u8> = serde_json::to_vec_pretty(index_templates).map_err(|serde_err| {
+        MetastoreError::InternalError {
+            message: "Failed to serialize index templates".to_string(),
+            cause: serde_err.to_string(),
+        }
+    })?;
+    storage
+        .put(index_templates_path, Box::new(content))
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to put `{INDEX_TEMPLATES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    Ok(())
+}
+
+/// Fetches the `INDEX_ALIASES_FILENAME` file. If the file does not exist, returns an empty list
+/// of aliases.
+pub(crate) async fn fetch_index_aliases(storage: &dyn Storage) -> MetastoreResult<Vec<IndexAlias>> {
+    let index_aliases_path = Path::new(INDEX_ALIASES_FILENAME);
+    let exists = storage
+        .exists(index_aliases_path)
+        .await
+        .map_err(|storage_err| convert_error("index_aliases", storage_err))?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let content = storage
+        .get_all(index_aliases_path)
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to get `{INDEX_ALIASES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    let index_aliases: Vec<IndexAlias> =
+        serde_json::from_slice(&content[..]).map_err(|serde_err| {
+            MetastoreError::InvalidManifest {
+                message: serde_err.to_string(),
+            }
+        })?;
+    Ok(index_aliases)
+}
+
+pub(crate) async fn put_index_aliases(
+    storage: &dyn Storage,
+    index_aliases: &[IndexAlias],
+) -> MetastoreResult<()> {
+    let index_aliases_path = Path::new(INDEX_ALIASES_FILENAME);
+    let content: Vec<u8> = serde_json::to_vec_pretty(index_aliases).map_err(|serde_err| {
+        MetastoreError::InternalError {
+            message: "Failed to serialize index aliases".to_string(),
+            cause: serde_err.to_string(),
+        }
+    })?;
+    storage
+        .put(index_aliases_path, Box::new(content))
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to put `{INDEX_ALIASES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    Ok(())
+}
+
+/// Fetches the `ALERT_RULES_FILENAME` file. If the file does not exist, returns an empty list of
+/// rules.
+pub(crate) async fn fetch_alert_rules(storage: &dyn Storage) -> MetastoreResult<Vec<AlertRule>> {
+    let alert_rules_path = Path::new(ALERT_RULES_FILENAME);
+    let exists = storage
+        .exists(alert_rules_path)
+        .await
+        .map_err(|storage_err| convert_error("alert_rules", storage_err))?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+    let content = storage
+        .get_all(alert_rules_path)
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to get `{ALERT_RULES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    let alert_rules: Vec<AlertRule> =
+        serde_json::from_slice(&content[..]).map_err(|serde_err| {
+            MetastoreError::InvalidManifest {
+                message: serde_err.to_string(),
+            }
+        })?;
+    Ok(alert_rules)
+}
+
+pub(crate) async fn put_alert_rules(
+    storage: &dyn Storage,
+    alert_rules: &[AlertRule],
+) -> MetastoreResult<()> {
+    let alert_rules_path = Path::new(ALERT_RULES_FILENAME);
+    let content: Vec<u8> = serde_json::to_vec_pretty(alert_rules).map_err(|serde_err| {
+        MetastoreError::InternalError {
+            message: "Failed to serialize alert rules".to_string(),
+            cause: serde_err.to_string(),
+        }
+    })?;
+    storage
+        .put(alert_rules_path, Box::new(content))
+        .await
+        .map_err(|storage_err| MetastoreError::InternalError {
+            message: format!("Failed to put `{ALERT_RULES_FILENAME}` file."),
+            cause: storage_err.to_string(),
+        })?;
+    Ok(())
+}
+
 pub(crate) async fn fetch_index(
     storage: &dyn Storage,
     index_id: &str,
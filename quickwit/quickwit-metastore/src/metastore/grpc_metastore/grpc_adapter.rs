@@ -21,24 +21,31 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use itertools::Itertools;
-use quickwit_config::IndexConfig;
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate};
 use quickwit_proto::metastore_api::metastore_api_service_server::{self as grpc};
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, CreateIndexResponse, DeleteIndexRequest,
-    DeleteIndexResponse, DeleteQuery, DeleteSourceRequest, DeleteSplitsRequest, DeleteTask,
-    IndexMetadataRequest, IndexMetadataResponse, LastDeleteOpstampRequest,
-    LastDeleteOpstampResponse, ListAllSplitsRequest, ListDeleteTasksRequest,
-    ListDeleteTasksResponse, ListIndexesMetadatasRequest, ListIndexesMetadatasResponse,
-    ListSplitsRequest, ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest,
-    PublishSplitsRequest, ResetSourceCheckpointRequest, SourceResponse, SplitResponse,
-    StageSplitsRequest, ToggleSourceRequest, UpdateSplitsDeleteOpstampRequest,
+    AcquireLeaseRequest, AcquireLeaseResponse, AddSourceRequest, AlertRuleResponse, ApiKeyResponse,
+    ArchiveSplitsRequest, CreateAlertRuleRequest, CreateApiKeyRequest, CreateIndexRequest,
+    CreateIndexResponse, CreateIndexTemplateRequest, DeleteAlertRuleRequest, DeleteApiKeyRequest,
+    DeleteIndexRequest, DeleteIndexResponse, DeleteIndexTemplateRequest, DeleteQuery,
+    DeleteSourceRequest, DeleteSplitsRequest, DeleteTask, IndexMetadataRequest,
+    IndexMetadataResponse, IndexTemplateResponse, IssuePublishTokenRequest,
+    IssuePublishTokenResponse, LastDeleteOpstampRequest, LastDeleteOpstampResponse,
+    ListAlertRulesRequest, ListAlertRulesResponse, ListAllSplitsRequest, ListApiKeysRequest,
+    ListApiKeysResponse, ListDeleteTasksRequest, ListDeleteTasksResponse, ListIndexAliasesRequest,
+    ListIndexAliasesResponse, ListIndexTemplatesRequest, ListIndexTemplatesResponse,
+    ListIndexesMetadatasRequest, ListIndexesMetadatasResponse, ListSplitsRequest,
+    ListSplitsResponse, ListStaleSplitsRequest, MarkSplitsForDeletionRequest, PublishSplitsRequest,
+    RehydrateSplitsRequest, ResetSourceCheckpointRequest, SourceResponse, SplitResponse,
+    StageSplitsRequest, ToggleSourceRequest, UpdateIndexAliasesRequest, UpdateIndexAliasesResponse,
+    UpdateIndexConfigRequest, UpdateIndexConfigResponse, UpdateSplitsDeleteOpstampRequest,
     UpdateSplitsDeleteOpstampResponse,
 };
 use quickwit_proto::tonic::{Request, Response, Status};
 use quickwit_proto::{set_parent_span_from_request_metadata, tonic};
 use tracing::instrument;
 
-use crate::{ListSplitsQuery, Metastore, MetastoreError};
+use crate::{ApiKey, IndexAliasAction, ListSplitsQuery, Metastore, MetastoreError};
 
 #[allow(missing_docs)]
 #[derive(Clone)]
@@ -95,6 +102,28 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(index_metadata_reply))
     }
 
+    #[instrument(skip(self, request))]
+    async fn update_index_config(
+        &self,
+        request: tonic::Request<UpdateIndexConfigRequest>,
+    ) -> Result<tonic::Response<UpdateIndexConfigResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let update_index_config_request = request.into_inner();
+        let index_config = serde_json::from_str::<IndexConfig>(
+            &update_index_config_request.index_config_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            struct_name: "IndexConfig".to_string(),
+            message: error.to_string(),
+        })?;
+        let update_index_config_reply = self
+            .0
+            .update_index_config(index_config)
+            .await
+            .map(|_| UpdateIndexConfigResponse {})?;
+        Ok(tonic::Response::new(update_index_config_reply))
+    }
+
     #[instrument(skip(self, request))]
     async fn list_indexes_metadatas(
         &self,
@@ -254,6 +283,46 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(mark_splits_for_deletion_reply))
     }
 
+    #[instrument(skip(self, request))]
+    async fn archive_splits(
+        &self,
+        request: tonic::Request<ArchiveSplitsRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let archive_splits_request = request.into_inner();
+        let split_ids = archive_splits_request
+            .split_ids
+            .iter()
+            .map(|split_id| split_id.as_str())
+            .collect_vec();
+        let archive_splits_reply = self
+            .0
+            .archive_splits(&archive_splits_request.index_id, &split_ids)
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(archive_splits_reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn rehydrate_splits(
+        &self,
+        request: tonic::Request<RehydrateSplitsRequest>,
+    ) -> Result<tonic::Response<SplitResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let rehydrate_splits_request = request.into_inner();
+        let split_ids = rehydrate_splits_request
+            .split_ids
+            .iter()
+            .map(|split_id| split_id.as_str())
+            .collect_vec();
+        let rehydrate_splits_reply = self
+            .0
+            .rehydrate_splits(&rehydrate_splits_request.index_id, &split_ids)
+            .await
+            .map(|_| SplitResponse {})?;
+        Ok(tonic::Response::new(rehydrate_splits_reply))
+    }
+
     #[instrument(skip(self, request))]
     async fn delete_splits(
         &self,
@@ -346,6 +415,23 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
         Ok(tonic::Response::new(reply))
     }
 
+    #[instrument(skip(self, request))]
+    async fn issue_publish_token(
+        &self,
+        request: tonic::Request<IssuePublishTokenRequest>,
+    ) -> Result<tonic::Response<IssuePublishTokenResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let publish_token = self
+            .0
+            .issue_publish_token(&request.index_id, &request.source_id)
+            .await?;
+        let reply = IssuePublishTokenResponse {
+            publish_token: publish_token.as_str().to_string(),
+        };
+        Ok(tonic::Response::new(reply))
+    }
+
     #[instrument(skip(self, request))]
     async fn last_delete_opstamp(
         &self,
@@ -434,4 +520,234 @@ impl grpc::MetastoreApiService for GrpcMetastoreAdapter {
             })?;
         Ok(tonic::Response::new(reply))
     }
+
+    #[instrument(skip(self, request))]
+    async fn create_api_key(
+        &self,
+        request: tonic::Request<CreateApiKeyRequest>,
+    ) -> Result<tonic::Response<ApiKeyResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let api_key: ApiKey =
+            serde_json::from_str(&request.api_key_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "ApiKey".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let reply = self
+            .0
+            .create_api_key(api_key)
+            .await
+            .map(|_| ApiKeyResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_api_keys(
+        &self,
+        request: tonic::Request<ListApiKeysRequest>,
+    ) -> Result<tonic::Response<ListApiKeysResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let api_keys = self.0.list_api_keys().await?;
+        let reply = serde_json::to_string(&api_keys)
+            .map(|api_keys_serialized_json| ListApiKeysResponse {
+                api_keys_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                struct_name: "Vec<ApiKey>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_api_key(
+        &self,
+        request: tonic::Request<DeleteApiKeyRequest>,
+    ) -> Result<tonic::Response<ApiKeyResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let reply = self
+            .0
+            .delete_api_key(&request.key_id)
+            .await
+            .map(|_| ApiKeyResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn create_index_template(
+        &self,
+        request: tonic::Request<CreateIndexTemplateRequest>,
+    ) -> Result<tonic::Response<IndexTemplateResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let index_template: IndexTemplate =
+            serde_json::from_str(&request.index_template_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "IndexTemplate".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let reply = self
+            .0
+            .create_index_template(index_template, request.overwrite)
+            .await
+            .map(|_| IndexTemplateResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_index_templates(
+        &self,
+        request: tonic::Request<ListIndexTemplatesRequest>,
+    ) -> Result<tonic::Response<ListIndexTemplatesResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let index_templates = self.0.list_index_templates().await?;
+        let reply = serde_json::to_string(&index_templates)
+            .map(
+                |index_templates_serialized_json| ListIndexTemplatesResponse {
+                    index_templates_serialized_json,
+                },
+            )
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                struct_name: "Vec<IndexTemplate>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_index_template(
+        &self,
+        request: tonic::Request<DeleteIndexTemplateRequest>,
+    ) -> Result<tonic::Response<IndexTemplateResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let reply = self
+            .0
+            .delete_index_template(&request.template_id)
+            .await
+            .map(|_| IndexTemplateResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn acquire_lease(
+        &self,
+        request: tonic::Request<AcquireLeaseRequest>,
+    ) -> Result<tonic::Response<AcquireLeaseResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let lease = self
+            .0
+            .acquire_lease(
+                &request.lease_id,
+                &request.holder_id,
+                request.lease_duration_secs,
+            )
+            .await?;
+        let reply = serde_json::to_string(&lease)
+            .map(|lease_serialized_json| AcquireLeaseResponse {
+                lease_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                struct_name: "Lease".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn update_index_aliases(
+        &self,
+        request: tonic::Request<UpdateIndexAliasesRequest>,
+    ) -> Result<tonic::Response<UpdateIndexAliasesResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let actions: Vec<IndexAliasAction> = serde_json::from_str(
+            &request.into_inner().actions_serialized_json,
+        )
+        .map_err(|error| MetastoreError::JsonDeserializeError {
+            struct_name: "Vec<IndexAliasAction>".to_string(),
+            message: error.to_string(),
+        })?;
+        let reply = self
+            .0
+            .update_index_aliases(actions)
+            .await
+            .map(|_| UpdateIndexAliasesResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_index_aliases(
+        &self,
+        request: tonic::Request<ListIndexAliasesRequest>,
+    ) -> Result<tonic::Response<ListIndexAliasesResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let index_aliases = self.0.list_index_aliases().await?;
+        let reply = serde_json::to_string(&index_aliases)
+            .map(|index_aliases_serialized_json| ListIndexAliasesResponse {
+                index_aliases_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                struct_name: "Vec<IndexAlias>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn create_alert_rule(
+        &self,
+        request: tonic::Request<CreateAlertRuleRequest>,
+    ) -> Result<tonic::Response<AlertRuleResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let alert_rule: AlertRule = serde_json::from_str(&request.alert_rule_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                struct_name: "AlertRule".to_string(),
+                message: error.to_string(),
+            })?;
+        let reply = self
+            .0
+            .create_alert_rule(alert_rule, request.overwrite)
+            .await
+            .map(|_| AlertRuleResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn list_alert_rules(
+        &self,
+        request: tonic::Request<ListAlertRulesRequest>,
+    ) -> Result<tonic::Response<ListAlertRulesResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let alert_rules = self.0.list_alert_rules().await?;
+        let reply = serde_json::to_string(&alert_rules)
+            .map(|alert_rules_serialized_json| ListAlertRulesResponse {
+                alert_rules_serialized_json,
+            })
+            .map_err(|error| MetastoreError::JsonSerializeError {
+                struct_name: "Vec<AlertRule>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(tonic::Response::new(reply))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn delete_alert_rule(
+        &self,
+        request: tonic::Request<DeleteAlertRuleRequest>,
+    ) -> Result<tonic::Response<AlertRuleResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        let reply = self
+            .0
+            .delete_alert_rule(&request.rule_id)
+            .await
+            .map(|_| AlertRuleResponse {})?;
+        Ok(tonic::Response::new(reply))
+    }
 }
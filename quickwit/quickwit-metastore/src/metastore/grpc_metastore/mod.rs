@@ -27,30 +27,34 @@ use itertools::Itertools;
 use quickwit_cluster::ClusterMember;
 use quickwit_common::uri::Uri as QuickwitUri;
 use quickwit_config::service::QuickwitService;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_grpc_clients::create_balance_channel_from_watched_members;
 use quickwit_proto::metastore_api::metastore_api_service_client::MetastoreApiServiceClient;
 use quickwit_proto::metastore_api::{
-    AddSourceRequest, CreateIndexRequest, DeleteIndexRequest, DeleteQuery, DeleteSourceRequest,
-    DeleteSplitsRequest, DeleteTask, IndexMetadataRequest, LastDeleteOpstampRequest,
-    ListAllSplitsRequest, ListDeleteTasksRequest, ListIndexesMetadatasRequest, ListSplitsRequest,
+    AcquireLeaseRequest, AddSourceRequest, ArchiveSplitsRequest, CreateAlertRuleRequest,
+    CreateApiKeyRequest, CreateIndexRequest, CreateIndexTemplateRequest, DeleteAlertRuleRequest,
+    DeleteApiKeyRequest, DeleteIndexRequest, DeleteIndexTemplateRequest, DeleteQuery,
+    DeleteSourceRequest, DeleteSplitsRequest, DeleteTask, IndexMetadataRequest,
+    IssuePublishTokenRequest, LastDeleteOpstampRequest, ListAlertRulesRequest,
+    ListAllSplitsRequest, ListApiKeysRequest, ListDeleteTasksRequest, ListIndexAliasesRequest,
+    ListIndexTemplatesRequest, ListIndexesMetadatasRequest, ListSplitsRequest,
     ListStaleSplitsRequest, MarkSplitsForDeletionRequest, PublishSplitsRequest,
-    ResetSourceCheckpointRequest, StageSplitsRequest, ToggleSourceRequest,
-    UpdateSplitsDeleteOpstampRequest,
+    RehydrateSplitsRequest, ResetSourceCheckpointRequest, StageSplitsRequest, ToggleSourceRequest,
+    UpdateIndexAliasesRequest, UpdateIndexConfigRequest, UpdateSplitsDeleteOpstampRequest,
 };
 use quickwit_proto::tonic::codegen::InterceptedService;
 use quickwit_proto::tonic::transport::Channel;
 use quickwit_proto::tonic::Status;
-use quickwit_proto::SpanContextInterceptor;
+use quickwit_proto::ClusterInterceptor;
 use tokio::sync::watch;
 use tokio_stream::wrappers::WatchStream;
 use tower::timeout::error::Elapsed;
 use tower::timeout::Timeout;
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
 use crate::{
-    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, MetastoreResult, Split,
-    SplitMetadata,
+    ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease, ListSplitsQuery, Metastore,
+    MetastoreError, MetastoreResult, Split, SplitMetadata,
 };
 
 // URI describing in a generic way the metastore services resource present in the cluster (=
@@ -64,8 +68,7 @@ const GRPC_METASTORE_BASE_URI: &str = "grpc://metastore.service.cluster";
 /// listen to cluster live nodes changes to keep updated the list of available nodes.
 #[derive(Clone)]
 pub struct MetastoreGrpcClient {
-    underlying:
-        MetastoreApiServiceClient<InterceptedService<Timeout<Channel>, SpanContextInterceptor>>,
+    underlying: MetastoreApiServiceClient<InterceptedService<Timeout<Channel>, ClusterInterceptor>>,
     pool_size_rx: watch::Receiver<usize>,
     // URI used to describe the metastore resource of form
     // `GRPC_METASTORE_BASE_URI:{grpc_advertise_port}`. This value is only useful for
@@ -87,8 +90,7 @@ impl MetastoreGrpcClient {
             QuickwitService::Metastore,
         )
         .await?;
-        let underlying =
-            MetastoreApiServiceClient::with_interceptor(channel, SpanContextInterceptor);
+        let underlying = MetastoreApiServiceClient::with_interceptor(channel, ClusterInterceptor);
         let uri = QuickwitUri::from_well_formed(format!(
             "{GRPC_METASTORE_BASE_URI}:{grpc_advertise_port}"
         ));
@@ -120,7 +122,7 @@ impl MetastoreGrpcClient {
             .await?;
         let timeout_channel = Timeout::new(channel, Duration::from_secs(1));
         let underlying =
-            MetastoreApiServiceClient::with_interceptor(timeout_channel, SpanContextInterceptor);
+            MetastoreApiServiceClient::with_interceptor(timeout_channel, ClusterInterceptor);
         let (_pool_size_tx, pool_size_rx) = watch::channel(1);
         Ok(Self {
             underlying,
@@ -201,6 +203,26 @@ impl Metastore for MetastoreGrpcClient {
         Ok(index_metadata)
     }
 
+    /// Updates the config of an index.
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        let index_config_serialized_json =
+            serde_json::to_string(&index_config).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    struct_name: "IndexConfig".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = UpdateIndexConfigRequest {
+            index_config_serialized_json,
+        };
+        self.underlying
+            .clone()
+            .update_index_config(request)
+            .await
+            .map(|_| ())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))
+    }
+
     /// Deletes an index.
     async fn delete_index(&self, index_id: &str) -> MetastoreResult<()> {
         let request = DeleteIndexRequest {
@@ -344,6 +366,50 @@ impl Metastore for MetastoreGrpcClient {
         Ok(())
     }
 
+    /// Archives a list of published splits.
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        let request = ArchiveSplitsRequest {
+            index_id: index_id.to_string(),
+            split_ids: split_ids
+                .iter()
+                .map(|split_id| split_id.to_string())
+                .collect(),
+        };
+        self.underlying
+            .clone()
+            .archive_splits(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    /// Rehydrates a list of archived splits.
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        let request = RehydrateSplitsRequest {
+            index_id: index_id.to_string(),
+            split_ids: split_ids
+                .iter()
+                .map(|split_id| split_id.to_string())
+                .collect(),
+        };
+        self.underlying
+            .clone()
+            .rehydrate_splits(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
     /// Deletes a list of splits.
     async fn delete_splits<'a>(
         &self,
@@ -442,6 +508,25 @@ impl Metastore for MetastoreGrpcClient {
         Ok(())
     }
 
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        let request = IssuePublishTokenRequest {
+            index_id: index_id.to_string(),
+            source_id: source_id.to_string(),
+        };
+        let response = self
+            .underlying
+            .clone()
+            .issue_publish_token(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(PublishToken::from(response.publish_token))
+    }
+
     async fn last_delete_opstamp(&self, index_id: &str) -> MetastoreResult<u64> {
         let request = LastDeleteOpstampRequest {
             index_id: index_id.to_string(),
@@ -542,6 +627,223 @@ impl Metastore for MetastoreGrpcClient {
             })?;
         Ok(splits)
     }
+
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        let api_key_serialized_json = serde_json::to_string(&api_key).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "ApiKey".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let request = CreateApiKeyRequest {
+            api_key_serialized_json,
+        };
+        self.underlying
+            .clone()
+            .create_api_key(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        let response = self
+            .underlying
+            .clone()
+            .list_api_keys(ListApiKeysRequest {})
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let api_keys: Vec<ApiKey> = serde_json::from_str(&response.api_keys_serialized_json)
+            .map_err(|error| MetastoreError::JsonDeserializeError {
+                struct_name: "Vec<ApiKey>".to_string(),
+                message: error.to_string(),
+            })?;
+        Ok(api_keys)
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        let request = DeleteApiKeyRequest {
+            key_id: key_id.to_string(),
+        };
+        self.underlying
+            .clone()
+            .delete_api_key(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let index_template_serialized_json =
+            serde_json::to_string(&index_template).map_err(|error| {
+                MetastoreError::JsonSerializeError {
+                    struct_name: "IndexTemplate".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        let request = CreateIndexTemplateRequest {
+            index_template_serialized_json,
+            overwrite,
+        };
+        self.underlying
+            .clone()
+            .create_index_template(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        let response = self
+            .underlying
+            .clone()
+            .list_index_templates(ListIndexTemplatesRequest {})
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let index_templates: Vec<IndexTemplate> =
+            serde_json::from_str(&response.index_templates_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "Vec<IndexTemplate>".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        Ok(index_templates)
+    }
+
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        let request = DeleteIndexTemplateRequest {
+            template_id: template_id.to_string(),
+        };
+        self.underlying
+            .clone()
+            .delete_index_template(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        let request = AcquireLeaseRequest {
+            lease_id: lease_id.to_string(),
+            holder_id: holder_id.to_string(),
+            lease_duration_secs,
+        };
+        let response = self
+            .underlying
+            .clone()
+            .acquire_lease(request)
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let lease: Lease =
+            serde_json::from_str(&response.lease_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "Lease".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        Ok(lease)
+    }
+
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        let actions_serialized_json = serde_json::to_string(&actions).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "Vec<IndexAliasAction>".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let request = UpdateIndexAliasesRequest {
+            actions_serialized_json,
+        };
+        self.underlying
+            .clone()
+            .update_index_aliases(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        let response = self
+            .underlying
+            .clone()
+            .list_index_aliases(ListIndexAliasesRequest {})
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let index_aliases: Vec<IndexAlias> =
+            serde_json::from_str(&response.index_aliases_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "Vec<IndexAlias>".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        Ok(index_aliases)
+    }
+
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let alert_rule_serialized_json = serde_json::to_string(&alert_rule).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "AlertRule".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let request = CreateAlertRuleRequest {
+            alert_rule_serialized_json,
+            overwrite,
+        };
+        self.underlying
+            .clone()
+            .create_alert_rule(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        let response = self
+            .underlying
+            .clone()
+            .list_alert_rules(ListAlertRulesRequest {})
+            .await
+            .map(|tonic_response| tonic_response.into_inner())
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        let alert_rules: Vec<AlertRule> =
+            serde_json::from_str(&response.alert_rules_serialized_json).map_err(|error| {
+                MetastoreError::JsonDeserializeError {
+                    struct_name: "Vec<AlertRule>".to_string(),
+                    message: error.to_string(),
+                }
+            })?;
+        Ok(alert_rules)
+    }
+
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        let request = DeleteAlertRuleRequest {
+            rule_id: rule_id.to_string(),
+        };
+        self.underlying
+            .clone()
+            .delete_alert_rule(request)
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(())
+    }
 }
 
 /// Parse tonic error and returns [`MetastoreError`].
@@ -23,7 +23,9 @@ use std::collections::hash_map::Entry;
 use std::collections::{BTreeMap, HashMap};
 
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig, TestableForRegression};
+use quickwit_config::{
+    validate_doc_mapping_update, IndexConfig, SourceConfig, TestableForRegression,
+};
 use serde::{Deserialize, Serialize};
 use serialize::VersionedIndexMetadata;
 use time::OffsetDateTime;
@@ -85,6 +87,35 @@ impl IndexMetadata {
         &self.index_config().index_uri
     }
 
+    /// Replaces the index config, incrementing the doc mapping version if the doc mapping changed.
+    /// Returns whether a mutation occurred.
+    pub(crate) fn update_index_config(
+        &mut self,
+        mut new_index_config: IndexConfig,
+    ) -> MetastoreResult<bool> {
+        if new_index_config.index_uri != self.index_config.index_uri {
+            return Err(MetastoreError::InvalidIndexConfigUpdate {
+                message: "the index URI cannot be changed".to_string(),
+            });
+        }
+        validate_doc_mapping_update(
+            &self.index_config.doc_mapping,
+            &new_index_config.doc_mapping,
+        )
+        .map_err(|error| MetastoreError::InvalidIndexConfigUpdate {
+            message: error.to_string(),
+        })?;
+        let doc_mapping_version = self.index_config.doc_mapping.doc_mapping_version;
+        new_index_config.doc_mapping.doc_mapping_version = doc_mapping_version;
+
+        if new_index_config.doc_mapping != self.index_config.doc_mapping {
+            new_index_config.doc_mapping.doc_mapping_version = doc_mapping_version + 1;
+        }
+        let mutation_occurred = new_index_config != self.index_config;
+        self.index_config = new_index_config;
+        Ok(mutation_occurred)
+    }
+
     /// Adds a source to the index. Returns an error if the source_id already exists.
     pub fn add_source(&mut self, source: SourceConfig) -> MetastoreResult<()> {
         let entry = self.sources.entry(source.source_id.clone());
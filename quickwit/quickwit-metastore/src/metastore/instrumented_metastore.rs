@@ -19,11 +19,14 @@
 
 use async_trait::async_trait;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{IndexMetadata, ListSplitsQuery, Metastore, MetastoreResult, Split, SplitMetadata};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
+use crate::{
+    ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease, ListSplitsQuery, Metastore,
+    MetastoreResult, Split, SplitMetadata,
+};
 
 macro_rules! instrument {
     ($expr:expr, [$operation:ident, $($label:expr),*]) => {
@@ -182,6 +185,28 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.archive_splits(index_id, split_ids).await,
+            [archive_splits, index_id]
+        );
+    }
+
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.rehydrate_splits(index_id, split_ids).await,
+            [rehydrate_splits, index_id]
+        );
+    }
+
     async fn delete_splits<'a>(
         &self,
         index_id: &str,
@@ -193,6 +218,14 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        let index_id = index_config.index_id.clone();
+        instrument!(
+            self.underlying.update_index_config(index_config).await,
+            [update_index_config, index_id.as_str()]
+        );
+    }
+
     // Source API
 
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
@@ -229,6 +262,19 @@ impl Metastore for InstrumentedMetastore {
         );
     }
 
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        instrument!(
+            self.underlying
+                .issue_publish_token(index_id, source_id)
+                .await,
+            [issue_publish_token, index_id]
+        );
+    }
+
     async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
         instrument!(
             self.underlying.delete_source(index_id, source_id).await,
@@ -292,6 +338,112 @@ impl Metastore for InstrumentedMetastore {
             [list_stale_splits, index_id]
         );
     }
+
+    // API keys API
+
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.create_api_key(api_key).await,
+            [create_api_key, ""]
+        );
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        instrument!(self.underlying.list_api_keys().await, [list_api_keys, ""]);
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.delete_api_key(key_id).await,
+            [delete_api_key, ""]
+        );
+    }
+
+    // Index templates API
+
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying
+                .create_index_template(index_template, overwrite)
+                .await,
+            [create_index_template, ""]
+        );
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        instrument!(
+            self.underlying.list_index_templates().await,
+            [list_index_templates, ""]
+        );
+    }
+
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.delete_index_template(template_id).await,
+            [delete_index_template, ""]
+        );
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        instrument!(
+            self.underlying
+                .acquire_lease(lease_id, holder_id, lease_duration_secs)
+                .await,
+            [acquire_lease, ""]
+        );
+    }
+
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.update_index_aliases(actions).await,
+            [update_index_aliases, ""]
+        );
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        instrument!(
+            self.underlying.list_index_aliases().await,
+            [list_index_aliases, ""]
+        );
+    }
+
+    // Alert rules API
+
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying
+                .create_alert_rule(alert_rule, overwrite)
+                .await,
+            [create_alert_rule, ""]
+        );
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        instrument!(
+            self.underlying.list_alert_rules().await,
+            [list_alert_rules, ""]
+        );
+    }
+
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        instrument!(
+            self.underlying.delete_alert_rule(rule_id).await,
+            [delete_alert_rule, ""]
+        );
+    }
 }
 
 #[cfg(test)]
@@ -23,11 +23,14 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use quickwit_common::pubsub::{Event, EventBroker};
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{IndexMetadata, ListSplitsQuery, Metastore, MetastoreResult, Split, SplitMetadata};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
+use crate::{
+    ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease, ListSplitsQuery, Metastore,
+    MetastoreResult, Split, SplitMetadata,
+};
 
 /// Metastore events dispatched to subscribers.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -64,6 +67,20 @@ pub enum MetastoreEvent {
 
 impl Event for MetastoreEvent {}
 
+/// Event dispatched to subscribers when splits get published.
+///
+/// It is kept apart from [`MetastoreEvent`] because it is emitted on every commit of the
+/// indexing pipelines, whereas the subscribers of [`MetastoreEvent`] track index changes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PublishSplitsEvent {
+    /// Index ID of the published splits.
+    pub index_id: String,
+    /// IDs of the published splits.
+    pub split_ids: Vec<String>,
+}
+
+impl Event for PublishSplitsEvent {}
+
 /// Wraps a metastore and dispatches events to subscribers.
 pub struct MetastoreEventPublisher {
     underlying: Arc<dyn Metastore>,
@@ -150,7 +167,18 @@ impl Metastore for MetastoreEventPublisher {
                 replaced_split_ids,
                 checkpoint_delta_opt,
             )
-            .await
+            .await?;
+        if !split_ids.is_empty() {
+            let event = PublishSplitsEvent {
+                index_id: index_id.to_string(),
+                split_ids: split_ids
+                    .iter()
+                    .map(|split_id| split_id.to_string())
+                    .collect(),
+            };
+            self.event_broker.publish(event);
+        }
+        Ok(())
     }
 
     async fn list_splits<'a>(&self, query: ListSplitsQuery<'a>) -> MetastoreResult<Vec<Split>> {
@@ -171,6 +199,22 @@ impl Metastore for MetastoreEventPublisher {
             .await
     }
 
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.underlying.archive_splits(index_id, split_ids).await
+    }
+
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.underlying.rehydrate_splits(index_id, split_ids).await
+    }
+
     async fn delete_splits<'a>(
         &self,
         index_id: &str,
@@ -179,6 +223,10 @@ impl Metastore for MetastoreEventPublisher {
         self.underlying.delete_splits(index_id, split_ids).await
     }
 
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        self.underlying.update_index_config(index_config).await
+    }
+
     // Source API
 
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
@@ -219,6 +267,16 @@ impl Metastore for MetastoreEventPublisher {
             .await
     }
 
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        self.underlying
+            .issue_publish_token(index_id, source_id)
+            .await
+    }
+
     async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
         let event = MetastoreEvent::DeleteSource {
             index_id: index_id.to_string(),
@@ -269,6 +327,79 @@ impl Metastore for MetastoreEventPublisher {
             .list_stale_splits(index_id, delete_opstamp, num_splits)
             .await
     }
+
+    // API keys API
+
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        self.underlying.create_api_key(api_key).await
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        self.underlying.list_api_keys().await
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        self.underlying.delete_api_key(key_id).await
+    }
+
+    // Index templates API
+
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .create_index_template(index_template, overwrite)
+            .await
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        self.underlying.list_index_templates().await
+    }
+
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        self.underlying.delete_index_template(template_id).await
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        self.underlying
+            .acquire_lease(lease_id, holder_id, lease_duration_secs)
+            .await
+    }
+
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        self.underlying.update_index_aliases(actions).await
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        self.underlying.list_index_aliases().await
+    }
+
+    // Alert rules API
+
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        self.underlying
+            .create_alert_rule(alert_rule, overwrite)
+            .await
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        self.underlying.list_alert_rules().await
+    }
+
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        self.underlying.delete_alert_rule(rule_id).await
+    }
 }
 
 #[cfg(test)]
@@ -364,4 +495,49 @@ mod tests {
         );
         subscription.cancel();
     }
+
+    #[derive(Debug, Clone)]
+    struct PublishSplitsTxSubscriber(tokio::sync::mpsc::Sender<PublishSplitsEvent>);
+
+    #[async_trait]
+    impl EventSubscriber<PublishSplitsEvent> for PublishSplitsTxSubscriber {
+        async fn handle_event(&mut self, event: PublishSplitsEvent) {
+            let _ = self.0.send(event).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metastore_event_publisher_publish_splits() {
+        let metastore = MetastoreEventPublisher::default_for_test().await;
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+        let subscription = metastore
+            .event_broker
+            .subscribe(PublishSplitsTxSubscriber(tx));
+
+        let index_id = "test-index";
+        let index_uri = "ram:///indexes/test-index";
+        metastore
+            .create_index(IndexConfig::for_test(index_id, index_uri))
+            .await
+            .unwrap();
+        let split_metadata = SplitMetadata::for_test("test-split".to_string());
+        metastore
+            .stage_splits(index_id, vec![split_metadata])
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(index_id, &["test-split"], &[], None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            PublishSplitsEvent {
+                index_id: index_id.to_string(),
+                split_ids: vec!["test-split".to_string()],
+            }
+        );
+        subscription.cancel();
+    }
 }
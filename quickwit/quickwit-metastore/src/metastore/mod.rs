@@ -33,12 +33,15 @@ use std::ops::{Bound, RangeInclusive};
 use async_trait::async_trait;
 pub use index_metadata::IndexMetadata;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
+use crate::{
+    ApiKey, IndexAlias, IndexAliasAction, Lease, MetastoreError, MetastoreResult, Split,
+    SplitMetadata, SplitState,
+};
 
 /// Metastore meant to manage Quickwit's indexes, their splits and delete tasks.
 ///
@@ -86,6 +89,21 @@ use crate::{MetastoreError, MetastoreResult, Split, SplitMetadata, SplitState};
 /// For splits created after a given delete task, Quickwit's indexing ensures that these splits
 /// are created with a `delete_optstamp` equal the latest opstamp of the tasks of the
 /// corresponding index.
+///
+/// III. API keys management.
+///
+/// The metastore stores the API keys used to authenticate requests, along with their scopes and
+/// the patterns of the indexes they grant access to. Only the hash of the key secrets is stored.
+///
+/// IV. Index templates management.
+///
+/// The metastore stores the index templates from which the indexes are created automatically
+/// when documents are ingested into an index that does not exist yet.
+///
+/// V. Alert rules management.
+///
+/// The metastore stores the alert rules, i.e. the standing queries evaluated periodically by the
+/// searchers, along with the conditions under which they fire and the webhooks they notify.
 #[cfg_attr(any(test, feature = "testsuite"), mockall::automock)]
 #[async_trait]
 pub trait Metastore: Send + Sync + 'static {
@@ -116,6 +134,15 @@ pub trait Metastore: Send + Sync + 'static {
     /// TODO consider merging with list_splits to remove one round-trip
     async fn index_metadata(&self, index_id: &str) -> MetastoreResult<IndexMetadata>;
 
+    /// Updates the config of the index `index_config.index_id`.
+    ///
+    /// The doc mapping can only evolve additively (see
+    /// [`validate_doc_mapping_update`](quickwit_config::validate_doc_mapping_update)) and its
+    /// version is incremented whenever it changes. Fails with
+    /// [`InvalidIndexConfigUpdate`](crate::MetastoreError::InvalidIndexConfigUpdate) if the update
+    /// is not compatible with the splits of the index.
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()>;
+
     /// Lists the indexes.
     ///
     /// This API lists the indexes stored in the metastore and returns a collection of
@@ -219,6 +246,30 @@ pub trait Metastore: Send + Sync + 'static {
         split_ids: &[&'a str],
     ) -> MetastoreResult<()>;
 
+    /// Archives a list of published splits.
+    ///
+    /// This API changes the state of the splits to [`SplitState::Archived`] so that they are not
+    /// searchable anymore. It does not move the split files to the archive storage. Splits that
+    /// are already archived are skipped, and an error is returned if some splits do not exist or
+    /// are neither published nor archived.
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()>;
+
+    /// Rehydrates a list of archived splits.
+    ///
+    /// This API changes the state of the splits back to [`SplitState::Published`]. The split
+    /// files must be moved back to the index storage beforehand. Splits that are already
+    /// published are skipped, and an error is returned if some splits do not exist or are
+    /// neither archived nor published.
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()>;
+
     /// Deletes a list of splits.
     ///
     /// This API only accepts splits that are in [`SplitState::Staged`] or
@@ -250,6 +301,15 @@ pub trait Metastore: Send + Sync + 'static {
     async fn reset_source_checkpoint(&self, index_id: &str, source_id: &str)
         -> MetastoreResult<()>;
 
+    /// Issues a publish token for the source identified by `index_id` and `source_id`, greater
+    /// than all the tokens previously issued for it. Indexing pipelines stamp their checkpoint
+    /// deltas with the token issued upon their last partition assignment, see [`PublishToken`].
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken>;
+
     /// Deletes a source. Fails with
     /// [`SourceDoesNotExist`](crate::MetastoreError::SourceDoesNotExist) if the specified source
     /// does not exist.
@@ -280,6 +340,102 @@ pub trait Metastore: Send + Sync + 'static {
         index_id: &str,
         opstamp_start: u64,
     ) -> MetastoreResult<Vec<DeleteTask>>;
+
+    // API keys API
+
+    /// Creates an API key.
+    ///
+    /// This API returns an error of the type
+    /// [`ApiKeyAlreadyExists`](crate::MetastoreError::ApiKeyAlreadyExists) if a key with the same
+    /// ID already exists.
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()>;
+
+    /// Lists all the API keys.
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>>;
+
+    /// Deletes an API key.
+    ///
+    /// This API returns an error of the type
+    /// [`ApiKeyDoesNotExist`](crate::MetastoreError::ApiKeyDoesNotExist) if the specified key
+    /// does not exist.
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()>;
+
+    // Index templates API
+
+    /// Creates an index template. If `overwrite` is true, an existing template with the same ID
+    /// is replaced.
+    ///
+    /// This API returns an error of the type
+    /// [`IndexTemplateAlreadyExists`](crate::MetastoreError::IndexTemplateAlreadyExists) if a
+    /// template with the same ID already exists and `overwrite` is false.
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()>;
+
+    /// Lists all the index templates.
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>>;
+
+    /// Deletes an index template.
+    ///
+    /// This API returns an error of the type
+    /// [`IndexTemplateDoesNotExist`](crate::MetastoreError::IndexTemplateDoesNotExist) if the
+    /// specified template does not exist.
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()>;
+
+    // Leases API
+
+    /// Acquires the lease `lease_id` for `holder_id`, or renews it if `holder_id` already holds
+    /// it, for `lease_duration_secs` seconds. The lease is left unchanged if it is held by another
+    /// holder and has not expired yet.
+    ///
+    /// Returns the lease as it stands after the request, whose holder is not necessarily
+    /// `holder_id`.
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease>;
+
+    // Index aliases API
+
+    /// Applies the actions to the index aliases atomically: either all the actions succeed, or
+    /// none is applied. An alias pointing to no index after the update is deleted.
+    ///
+    /// Returns [`IndexDoesNotExist`](crate::MetastoreError::IndexDoesNotExist) if an action adds an
+    /// index that does not exist and
+    /// [`IndexAliasDoesNotExist`](crate::MetastoreError::IndexAliasDoesNotExist) if an action
+    /// removes an index from an alias that does not exist.
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()>;
+
+    /// Lists the index aliases, sorted by alias ID.
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>>;
+
+    // Alert rules API
+
+    /// Creates an alert rule. If `overwrite` is true, an existing rule with the same ID is
+    /// replaced.
+    ///
+    /// This API returns an error of the type
+    /// [`AlertRuleAlreadyExists`](crate::MetastoreError::AlertRuleAlreadyExists) if a rule with
+    /// the same ID already exists and `overwrite` is false.
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()>;
+
+    /// Lists all the alert rules.
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>>;
+
+    /// Deletes an alert rule.
+    ///
+    /// This API returns an error of the type
+    /// [`AlertRuleDoesNotExist`](crate::MetastoreError::AlertRuleDoesNotExist) if the specified
+    /// rule does not exist.
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()>;
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
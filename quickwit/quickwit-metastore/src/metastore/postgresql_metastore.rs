@@ -17,7 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Write};
 use std::ops::Bound;
 use std::sync::Arc;
@@ -27,7 +27,7 @@ use async_trait::async_trait;
 use itertools::Itertools;
 use quickwit_common::uri::Uri;
 use quickwit_common::PrettySample;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_doc_mapper::tag_pruning::TagFilterAst;
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use sqlx::migrate::Migrator;
@@ -37,15 +37,16 @@ use tokio::sync::Mutex;
 use tracing::log::LevelFilter;
 use tracing::{debug, error, info, instrument, warn};
 
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
 use crate::metastore::instrumented_metastore::InstrumentedMetastore;
 use crate::metastore::postgresql_model::{
     DeleteTask as PgDeleteTask, Index as PgIndex, Split as PgSplit,
 };
 use crate::metastore::FilterRange;
 use crate::{
-    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, MetastoreFactory,
-    MetastoreResolverError, MetastoreResult, Split, SplitMetadata, SplitState,
+    apply_index_alias_actions, ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease,
+    ListSplitsQuery, Metastore, MetastoreError, MetastoreFactory, MetastoreResolverError,
+    MetastoreResult, Split, SplitMetadata, SplitState,
 };
 
 static MIGRATOR: Migrator = sqlx::migrate!("migrations/postgresql");
@@ -350,6 +351,97 @@ where
     Ok(mutation_occurred)
 }
 
+/// Moves the splits from `from_state` to `to_state`. The update only happens if all the splits
+/// exist and are either in `from_state` or `to_state`.
+async fn transition_splits_state(
+    connection_pool: &Pool<Postgres>,
+    index_id: &str,
+    split_ids: &[&str],
+    from_state: SplitState,
+    to_state: SplitState,
+) -> MetastoreResult<()> {
+    const TRANSITION_SPLITS_STATE_QUERY: &str = r#"
+        -- Select the splits to update, regardless of their state.
+        -- The left join make it possible to identify the splits that do not exist.
+        WITH input_splits AS (
+            SELECT input_splits.split_id, splits.split_state
+            FROM UNNEST($2) AS input_splits(split_id)
+            LEFT JOIN (
+                SELECT split_id, split_state
+                FROM splits
+                WHERE
+                    index_id = $1
+                    AND split_id = ANY($2)
+                FOR UPDATE
+                ) AS splits
+            USING (split_id)
+        ),
+        -- Update the splits if and only if all the splits exist and are in the source or the
+        -- target state.
+        updated_splits AS (
+            UPDATE splits
+            SET
+                split_state = $4,
+                update_timestamp = (CURRENT_TIMESTAMP AT TIME ZONE 'UTC')
+            FROM input_splits
+            WHERE
+                splits.index_id = $1
+                AND splits.split_id = input_splits.split_id
+                AND splits.split_state = $3
+                AND NOT EXISTS (
+                    SELECT 1
+                    FROM input_splits
+                    WHERE
+                        split_state IS NULL
+                        OR split_state NOT IN ($3, $4)
+                )
+        )
+        -- Report the outcome of the update query.
+        SELECT
+            COUNT(split_state),
+            COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state NOT IN ($3, $4)), ARRAY[]::TEXT[]),
+            COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state IS NULL), ARRAY[]::TEXT[])
+            FROM input_splits
+    "#;
+    let (num_found_splits, invalid_state_split_ids, not_found_split_ids): (
+        i64,
+        Vec<String>,
+        Vec<String>,
+    ) = sqlx::query_as(TRANSITION_SPLITS_STATE_QUERY)
+        .bind(index_id)
+        .bind(split_ids)
+        .bind(from_state.as_str())
+        .bind(to_state.as_str())
+        .fetch_one(connection_pool)
+        .await
+        .map_err(|error| convert_sqlx_err(index_id, error))?;
+
+    if num_found_splits == 0 && index_opt(connection_pool, index_id).await?.is_none() {
+        return Err(MetastoreError::IndexDoesNotExist {
+            index_id: index_id.to_string(),
+        });
+    }
+    if !not_found_split_ids.is_empty() {
+        return Err(MetastoreError::SplitsDoNotExist {
+            split_ids: not_found_split_ids,
+        });
+    }
+    if !invalid_state_split_ids.is_empty() {
+        let split_ids = invalid_state_split_ids;
+        return Err(match from_state {
+            SplitState::Archived => MetastoreError::SplitsNotArchived { split_ids },
+            SplitState::Staged => MetastoreError::SplitsNotStaged { split_ids },
+            _ => MetastoreError::SplitsNotPublished { split_ids },
+        });
+    }
+    info!(
+        index_id=%index_id,
+        "Moved {} splits from state `{from_state}` to state `{to_state}`.",
+        split_ids.len()
+    );
+    Ok(())
+}
+
 #[async_trait]
 impl Metastore for PostgresqlMetastore {
     async fn check_connectivity(&self) -> anyhow::Result<()> {
@@ -673,7 +765,7 @@ impl Metastore for PostgresqlMetastore {
                     ) AS splits
                 USING (split_id)
             ),
-            -- Mark the staged and published splits for deletion.
+            -- Mark the staged, published, and archived splits for deletion.
             marked_splits AS (
                 UPDATE splits
                 SET
@@ -683,12 +775,12 @@ impl Metastore for PostgresqlMetastore {
                 WHERE
                     splits.index_id = $1
                     AND splits.split_id = input_splits.split_id
-                    AND splits.split_state IN ('Staged', 'Published')
+                    AND splits.split_state IN ('Staged', 'Published', 'Archived')
             )
             -- Report the outcome of the update query.
             SELECT
                 COUNT(split_state),
-                COUNT(1) FILTER (WHERE split_state IN ('Staged', 'Published')),
+                COUNT(1) FILTER (WHERE split_state IN ('Staged', 'Published', 'Archived')),
                 COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state IS NULL), ARRAY[]::TEXT[])
                 FROM input_splits
         "#;
@@ -722,6 +814,38 @@ impl Metastore for PostgresqlMetastore {
         Ok(())
     }
 
+    #[instrument(skip(self), fields(index_id=index_id))]
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        transition_splits_state(
+            &self.connection_pool,
+            index_id,
+            split_ids,
+            SplitState::Published,
+            SplitState::Archived,
+        )
+        .await
+    }
+
+    #[instrument(skip(self), fields(index_id=index_id))]
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        transition_splits_state(
+            &self.connection_pool,
+            index_id,
+            split_ids,
+            SplitState::Archived,
+            SplitState::Published,
+        )
+        .await
+    }
+
     #[instrument(skip(self), fields(index_id=index_id))]
     async fn delete_splits<'a>(
         &self,
@@ -755,14 +879,14 @@ impl Metastore for PostgresqlMetastore {
                         SELECT 1
                         FROM input_splits
                         WHERE
-                            split_state IN ('Staged', 'Published')
+                            split_state IN ('Staged', 'Published', 'Archived')
                     )
             )
             -- Report the outcome of the delete query.
             SELECT
                 COUNT(split_state),
                 COUNT(1) FILTER (WHERE split_state = 'MarkedForDeletion'),
-                COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state IN ('Staged', 'Published')), ARRAY[]::TEXT[]),
+                COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state IN ('Staged', 'Published', 'Archived')), ARRAY[]::TEXT[]),
                 COALESCE(ARRAY_AGG(split_id) FILTER (WHERE split_state IS NULL), ARRAY[]::TEXT[])
                 FROM input_splits
         "#;
@@ -811,6 +935,18 @@ impl Metastore for PostgresqlMetastore {
             .index_metadata()
     }
 
+    #[instrument(skip(self, index_config), fields(index_id=index_config.index_id))]
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        let index_id = index_config.index_id.clone();
+        run_with_tx!(self.connection_pool, tx, {
+            mutate_index_metadata(tx, &index_id, |index_metadata| {
+                index_metadata.update_index_config(index_config)
+            })
+            .await?;
+            Ok(())
+        })
+    }
+
     #[instrument(skip(self, source), fields(index_id=index_id, source_id=source.source_id))]
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
         run_with_tx!(self.connection_pool, tx, {
@@ -869,6 +1005,25 @@ impl Metastore for PostgresqlMetastore {
         })
     }
 
+    #[instrument(skip(self), fields(index_id=index_id, source_id=source_id))]
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        // The index row is locked for the duration of the transaction, so concurrent issuers are
+        // serialized and each of them gets a distinct token.
+        run_with_tx!(self.connection_pool, tx, {
+            let mut publish_token_opt: Option<PublishToken> = None;
+            mutate_index_metadata(tx, index_id, |index_metadata| {
+                publish_token_opt = Some(index_metadata.checkpoint.issue_publish_token(source_id));
+                Ok::<_, MetastoreError>(true)
+            })
+            .await?;
+            Ok(publish_token_opt.expect("The publish token should have been issued."))
+        })
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -1032,6 +1187,299 @@ impl Metastore for PostgresqlMetastore {
             .map(|pg_split| pg_split.try_into())
             .collect()
     }
+
+    #[instrument(skip(self, api_key), fields(key_id=%api_key.key_id))]
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        let api_key_json = serde_json::to_string(&api_key).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "ApiKey".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let insert_res = sqlx::query(
+            r#"
+            INSERT INTO api_keys (key_id, api_key_json) VALUES ($1, $2)
+            ON CONFLICT (key_id) DO NOTHING
+            "#,
+        )
+        .bind(&api_key.key_id)
+        .bind(&api_key_json)
+        .execute(&self.connection_pool)
+        .await?;
+        if insert_res.rows_affected() == 0 {
+            return Err(MetastoreError::ApiKeyAlreadyExists {
+                key_id: api_key.key_id,
+            });
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        let api_key_jsons: Vec<(String,)> =
+            sqlx::query_as("SELECT api_key_json FROM api_keys ORDER BY key_id")
+                .fetch_all(&self.connection_pool)
+                .await?;
+        api_key_jsons
+            .into_iter()
+            .map(|(api_key_json,)| {
+                serde_json::from_str(&api_key_json).map_err(|error| {
+                    MetastoreError::JsonDeserializeError {
+                        struct_name: "ApiKey".to_string(),
+                        message: error.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        let delete_res = sqlx::query("DELETE FROM api_keys WHERE key_id = $1")
+            .bind(key_id)
+            .execute(&self.connection_pool)
+            .await?;
+        if delete_res.rows_affected() == 0 {
+            return Err(MetastoreError::ApiKeyDoesNotExist {
+                key_id: key_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self, index_template), fields(template_id=%index_template.template_id))]
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let index_template_json = serde_json::to_string(&index_template).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "IndexTemplate".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let on_conflict_action = if overwrite {
+            "DO UPDATE SET index_template_json = EXCLUDED.index_template_json"
+        } else {
+            "DO NOTHING"
+        };
+        let insert_res = sqlx::query(&format!(
+            "INSERT INTO index_templates (template_id, index_template_json) VALUES ($1, $2) ON \
+             CONFLICT (template_id) {on_conflict_action}"
+        ))
+        .bind(&index_template.template_id)
+        .bind(&index_template_json)
+        .execute(&self.connection_pool)
+        .await?;
+        if insert_res.rows_affected() == 0 {
+            return Err(MetastoreError::IndexTemplateAlreadyExists {
+                template_id: index_template.template_id,
+            });
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        let index_template_jsons: Vec<(String,)> =
+            sqlx::query_as("SELECT index_template_json FROM index_templates ORDER BY template_id")
+                .fetch_all(&self.connection_pool)
+                .await?;
+        index_template_jsons
+            .into_iter()
+            .map(|(index_template_json,)| {
+                serde_json::from_str(&index_template_json).map_err(|error| {
+                    MetastoreError::JsonDeserializeError {
+                        struct_name: "IndexTemplate".to_string(),
+                        message: error.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        let delete_res = sqlx::query("DELETE FROM index_templates WHERE template_id = $1")
+            .bind(template_id)
+            .execute(&self.connection_pool)
+            .await?;
+        if delete_res.rows_affected() == 0 {
+            return Err(MetastoreError::IndexTemplateDoesNotExist {
+                template_id: template_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        // The expiration timestamps are computed with the clock of the database so that the
+        // holders do not need synchronized clocks.
+        sqlx::query(
+            r#"
+            INSERT INTO leases (lease_id, holder_id, expire_timestamp)
+            VALUES ($1, $2, EXTRACT(EPOCH FROM NOW())::BIGINT + $3)
+            ON CONFLICT (lease_id) DO UPDATE
+            SET holder_id = EXCLUDED.holder_id, expire_timestamp = EXCLUDED.expire_timestamp
+            WHERE leases.holder_id = EXCLUDED.holder_id
+                OR leases.expire_timestamp <= EXTRACT(EPOCH FROM NOW())::BIGINT
+            "#,
+        )
+        .bind(lease_id)
+        .bind(holder_id)
+        .bind(lease_duration_secs as i64)
+        .execute(&self.connection_pool)
+        .await?;
+        let (holder_id, expire_timestamp): (String, i64) =
+            sqlx::query_as("SELECT holder_id, expire_timestamp FROM leases WHERE lease_id = $1")
+                .bind(lease_id)
+                .fetch_one(&self.connection_pool)
+                .await?;
+        Ok(Lease {
+            lease_id: lease_id.to_string(),
+            holder_id,
+            expire_timestamp,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        run_with_tx!(self.connection_pool, tx, {
+            // The aliases are rewritten as a whole, so concurrent updates must not interleave.
+            sqlx::query("LOCK TABLE index_aliases IN EXCLUSIVE MODE")
+                .execute(&mut *tx)
+                .await?;
+            let index_aliases = list_index_aliases(&mut *tx).await?;
+            let ids: Vec<String> = actions
+                .iter()
+                .flat_map(|action| {
+                    let (alias_id, index_id) = action.ids();
+                    [alias_id.to_string(), index_id.to_string()]
+                })
+                .collect();
+            let existing_index_ids: HashSet<String> = sqlx::query_as::<_, (String,)>(
+                "SELECT index_id FROM indexes WHERE index_id = ANY($1)",
+            )
+            .bind(&ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|(index_id,)| index_id)
+            .collect();
+            let index_aliases =
+                apply_index_alias_actions(index_aliases, &actions, &existing_index_ids)?;
+            sqlx::query("DELETE FROM index_aliases")
+                .execute(&mut *tx)
+                .await?;
+            for index_alias in &index_aliases {
+                for index_id in &index_alias.index_ids {
+                    sqlx::query("INSERT INTO index_aliases (alias_id, index_id) VALUES ($1, $2)")
+                        .bind(&index_alias.alias_id)
+                        .bind(index_id)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        list_index_aliases(&self.connection_pool).await
+    }
+
+    #[instrument(skip(self, alert_rule), fields(rule_id=%alert_rule.rule_id))]
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        let alert_rule_json = serde_json::to_string(&alert_rule).map_err(|error| {
+            MetastoreError::JsonSerializeError {
+                struct_name: "AlertRule".to_string(),
+                message: error.to_string(),
+            }
+        })?;
+        let on_conflict_action = if overwrite {
+            "DO UPDATE SET alert_rule_json = EXCLUDED.alert_rule_json"
+        } else {
+            "DO NOTHING"
+        };
+        let insert_res = sqlx::query(&format!(
+            "INSERT INTO alert_rules (rule_id, alert_rule_json) VALUES ($1, $2) ON CONFLICT \
+             (rule_id) {on_conflict_action}"
+        ))
+        .bind(&alert_rule.rule_id)
+        .bind(&alert_rule_json)
+        .execute(&self.connection_pool)
+        .await?;
+        if insert_res.rows_affected() == 0 {
+            return Err(MetastoreError::AlertRuleAlreadyExists {
+                rule_id: alert_rule.rule_id,
+            });
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        let alert_rule_jsons: Vec<(String,)> =
+            sqlx::query_as("SELECT alert_rule_json FROM alert_rules ORDER BY rule_id")
+                .fetch_all(&self.connection_pool)
+                .await?;
+        alert_rule_jsons
+            .into_iter()
+            .map(|(alert_rule_json,)| {
+                serde_json::from_str(&alert_rule_json).map_err(|error| {
+                    MetastoreError::JsonDeserializeError {
+                        struct_name: "AlertRule".to_string(),
+                        message: error.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        let delete_res = sqlx::query("DELETE FROM alert_rules WHERE rule_id = $1")
+            .bind(rule_id)
+            .execute(&self.connection_pool)
+            .await?;
+        if delete_res.rows_affected() == 0 {
+            return Err(MetastoreError::AlertRuleDoesNotExist {
+                rule_id: rule_id.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the index aliases, sorted by alias ID.
+async fn list_index_aliases<'a, E>(executor: E) -> MetastoreResult<Vec<IndexAlias>>
+where E: sqlx::Executor<'a, Database = Postgres> {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT alias_id, index_id FROM index_aliases ORDER BY alias_id, index_id")
+            .fetch_all(executor)
+            .await?;
+    let index_aliases = rows
+        .into_iter()
+        .group_by(|(alias_id, _)| alias_id.clone())
+        .into_iter()
+        .map(|(alias_id, group)| IndexAlias {
+            alias_id,
+            index_ids: group.map(|(_, index_id)| index_id).collect(),
+        })
+        .collect();
+    Ok(index_aliases)
 }
 
 // We use dollar-quoted strings in Postgresql.
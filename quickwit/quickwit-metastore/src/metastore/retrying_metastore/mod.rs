@@ -24,12 +24,15 @@ mod test;
 
 use async_trait::async_trait;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
 use self::retry::{retry, RetryParams};
-use crate::checkpoint::IndexCheckpointDelta;
-use crate::{IndexMetadata, ListSplitsQuery, Metastore, MetastoreResult, Split, SplitMetadata};
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
+use crate::{
+    ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease, ListSplitsQuery, Metastore,
+    MetastoreResult, Split, SplitMetadata,
+};
 
 /// Retry layer for a [`Metastore`].
 /// This is a band-aid solution for now. This will be removed after retry can be usable on
@@ -172,6 +175,28 @@ impl Metastore for RetryingMetastore {
         .await
     }
 
+    async fn archive_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.archive_splits(index_id, split_ids).await
+        })
+        .await
+    }
+
+    async fn rehydrate_splits<'a>(
+        &self,
+        index_id: &str,
+        split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.rehydrate_splits(index_id, split_ids).await
+        })
+        .await
+    }
+
     async fn delete_splits<'a>(
         &self,
         index_id: &str,
@@ -183,6 +208,13 @@ impl Metastore for RetryingMetastore {
         .await
     }
 
+    async fn update_index_config(&self, index_config: IndexConfig) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.update_index_config(index_config.clone()).await
+        })
+        .await
+    }
+
     async fn add_source(&self, index_id: &str, source: SourceConfig) -> MetastoreResult<()> {
         retry(&self.retry_params, || async {
             self.inner.add_source(index_id, source.clone()).await
@@ -215,6 +247,17 @@ impl Metastore for RetryingMetastore {
         .await
     }
 
+    async fn issue_publish_token(
+        &self,
+        index_id: &str,
+        source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        retry(&self.retry_params, || async {
+            self.inner.issue_publish_token(index_id, source_id).await
+        })
+        .await
+    }
+
     async fn delete_source(&self, index_id: &str, source_id: &str) -> MetastoreResult<()> {
         retry(&self.retry_params, || async {
             self.inner.delete_source(index_id, source_id).await
@@ -260,4 +303,107 @@ impl Metastore for RetryingMetastore {
         })
         .await
     }
+
+    async fn create_api_key(&self, api_key: ApiKey) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.create_api_key(api_key.clone()).await
+        })
+        .await
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        retry(&self.retry_params, || async {
+            self.inner.list_api_keys().await
+        })
+        .await
+    }
+
+    async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.delete_api_key(key_id).await
+        })
+        .await
+    }
+
+    async fn create_index_template(
+        &self,
+        index_template: IndexTemplate,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner
+                .create_index_template(index_template.clone(), overwrite)
+                .await
+        })
+        .await
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        retry(&self.retry_params, || async {
+            self.inner.list_index_templates().await
+        })
+        .await
+    }
+
+    async fn delete_index_template(&self, template_id: &str) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.delete_index_template(template_id).await
+        })
+        .await
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        retry(&self.retry_params, || async {
+            self.inner
+                .acquire_lease(lease_id, holder_id, lease_duration_secs)
+                .await
+        })
+        .await
+    }
+
+    async fn update_index_aliases(&self, actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.update_index_aliases(actions.clone()).await
+        })
+        .await
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        retry(&self.retry_params, || async {
+            self.inner.list_index_aliases().await
+        })
+        .await
+    }
+
+    async fn create_alert_rule(
+        &self,
+        alert_rule: AlertRule,
+        overwrite: bool,
+    ) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner
+                .create_alert_rule(alert_rule.clone(), overwrite)
+                .await
+        })
+        .await
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        retry(&self.retry_params, || async {
+            self.inner.list_alert_rules().await
+        })
+        .await
+    }
+
+    async fn delete_alert_rule(&self, rule_id: &str) -> MetastoreResult<()> {
+        retry(&self.retry_params, || async {
+            self.inner.delete_alert_rule(rule_id).await
+        })
+        .await
+    }
 }
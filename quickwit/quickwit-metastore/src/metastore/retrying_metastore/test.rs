@@ -21,14 +21,14 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use async_trait::async_trait;
 use quickwit_common::uri::Uri;
-use quickwit_config::{IndexConfig, SourceConfig};
+use quickwit_config::{AlertRule, IndexConfig, IndexTemplate, SourceConfig};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 
 use super::retry::RetryParams;
-use crate::checkpoint::IndexCheckpointDelta;
+use crate::checkpoint::{IndexCheckpointDelta, PublishToken};
 use crate::{
-    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, MetastoreResult, RetryingMetastore,
-    Split, SplitMetadata,
+    ApiKey, IndexAlias, IndexAliasAction, IndexMetadata, Lease, ListSplitsQuery, Metastore,
+    MetastoreError, MetastoreResult, RetryingMetastore, Split, SplitMetadata,
 };
 
 struct RetryTestMetastore {
@@ -138,6 +138,22 @@ impl Metastore for RetryTestMetastore {
         self.try_success()
     }
 
+    async fn archive_splits<'a>(
+        &self,
+        _index_id: &str,
+        _split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn rehydrate_splits<'a>(
+        &self,
+        _index_id: &str,
+        _split_ids: &[&'a str],
+    ) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
     async fn delete_splits<'a>(
         &self,
         _index_id: &str,
@@ -146,6 +162,10 @@ impl Metastore for RetryTestMetastore {
         self.try_success()
     }
 
+    async fn update_index_config(&self, _index_config: IndexConfig) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
     async fn add_source(&self, _index_id: &str, _source: SourceConfig) -> MetastoreResult<()> {
         self.try_success()
     }
@@ -167,6 +187,14 @@ impl Metastore for RetryTestMetastore {
         self.try_success()
     }
 
+    async fn issue_publish_token(
+        &self,
+        _index_id: &str,
+        _source_id: &str,
+    ) -> MetastoreResult<PublishToken> {
+        self.try_success().map(|_| PublishToken::from(1u64))
+    }
+
     async fn delete_source(&self, _index_id: &str, _source_id: &str) -> MetastoreResult<()> {
         self.try_success()
     }
@@ -211,6 +239,79 @@ impl Metastore for RetryTestMetastore {
             Err(err) => Err(err),
         }
     }
+
+    async fn create_api_key(&self, _api_key: ApiKey) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        let result = self.try_success();
+        match result {
+            Ok(_) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn delete_api_key(&self, _key_id: &str) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn create_index_template(
+        &self,
+        _index_template: IndexTemplate,
+        _overwrite: bool,
+    ) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn list_index_templates(&self) -> MetastoreResult<Vec<IndexTemplate>> {
+        let result = self.try_success();
+        match result {
+            Ok(_) => Ok(vec![]),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn delete_index_template(&self, _template_id: &str) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn acquire_lease(
+        &self,
+        lease_id: &str,
+        holder_id: &str,
+        _lease_duration_secs: u64,
+    ) -> MetastoreResult<Lease> {
+        self.try_success().map(|_| Lease {
+            lease_id: lease_id.to_string(),
+            holder_id: holder_id.to_string(),
+            expire_timestamp: 0,
+        })
+    }
+
+    async fn update_index_aliases(&self, _actions: Vec<IndexAliasAction>) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn list_index_aliases(&self) -> MetastoreResult<Vec<IndexAlias>> {
+        self.try_success().map(|_| Vec::new())
+    }
+
+    async fn create_alert_rule(
+        &self,
+        _alert_rule: AlertRule,
+        _overwrite: bool,
+    ) -> MetastoreResult<()> {
+        self.try_success()
+    }
+
+    async fn list_alert_rules(&self) -> MetastoreResult<Vec<AlertRule>> {
+        self.try_success().map(|_| Vec::new())
+    }
+
+    async fn delete_alert_rule(&self, _rule_id: &str) -> MetastoreResult<()> {
+        self.try_success()
+    }
 }
 
 #[tokio::test]
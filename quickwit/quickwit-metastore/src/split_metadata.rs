@@ -17,13 +17,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
 
 use quickwit_common::FileEntry;
 use quickwit_config::TestableForRegression;
+use quickwit_doc_mapper::bloom_filter::BloomFilters;
+use quickwit_doc_mapper::range_pruning::FieldRanges;
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
@@ -113,6 +115,16 @@ pub struct SplitMetadata {
     /// [`MAX_VALUES_PER_TAG_FIELD`]: https://github.com/quickwit-oss/quickwit/blob/main/quickwit-indexing/src/actors/packager.rs#L36
     pub tags: BTreeSet<String>,
 
+    /// Min / max values of the fields registered in the
+    /// [`DocMapping`](quickwit_config::DocMapping) `range_pruning_fields` attribute.
+    /// A field is missing from the map if the split does not contain any value for it.
+    pub field_ranges: FieldRanges,
+
+    /// Bloom filters over the terms of the fields registered in the
+    /// [`DocMapping`](quickwit_config::DocMapping) `bloom_filter_fields` attribute.
+    /// A field is missing from the map if the split holds too many distinct terms for it.
+    pub bloom_filters: BloomFilters,
+
     /// Contains the range of bytes of the footer that needs to be downloaded
     /// in order to open a split.
     ///
@@ -126,6 +138,19 @@ pub struct SplitMetadata {
     /// Number of merge operations that was involved to create
     /// this split.
     pub num_merge_ops: usize,
+
+    /// Version of the doc mapping the split was indexed with. Splits indexed with different
+    /// versions of the doc mapping have different schemas and cannot be merged together.
+    pub doc_mapping_version: u64,
+
+    /// Custom key-value metadata attached to the split at indexing time, e.g. the version of the
+    /// pipeline that produced it or the file it was ingested from. When splits are merged, only
+    /// the entries common to all the merged splits are kept.
+    pub custom_metadata: BTreeMap<String, String>,
+
+    /// SHA-256 hash of the split file, set when the split is stored in the content-addressed
+    /// layout. The split file is then named after the hash rather than after the split ID.
+    pub content_hash: Option<String>,
 }
 
 impl SplitMetadata {
@@ -153,6 +178,15 @@ impl SplitMetadata {
         &self.split_id
     }
 
+    /// Returns the path of the split file relative to the index URI.
+    pub fn split_file(&self) -> String {
+        if let Some(content_hash) = &self.content_hash {
+            quickwit_common::content_addressed_split_file(content_hash)
+        } else {
+            quickwit_common::split_file(&self.split_id)
+        }
+    }
+
     #[cfg(any(test, feature = "testsuite"))]
     /// Returns an instance of `SplitMetadata` for testing.
     pub fn for_test(split_id: String) -> Self {
@@ -166,7 +200,7 @@ impl SplitMetadata {
 impl From<&SplitMetadata> for FileEntry {
     fn from(split: &SplitMetadata) -> Self {
         FileEntry {
-            file_name: quickwit_common::split_file(split.split_id()),
+            file_name: split.split_file(),
             file_size_in_bytes: split.footer_offsets.end,
         }
     }
@@ -186,8 +220,13 @@ impl TestableForRegression for SplitMetadata {
             time_range: Some(121000..=130198),
             create_timestamp: 3,
             tags: ["234".to_string(), "aaa".to_string()].into_iter().collect(),
+            field_ranges: FieldRanges::new(),
+            bloom_filters: BloomFilters::new(),
             footer_offsets: 1000..2000,
             num_merge_ops: 3,
+            doc_mapping_version: 0,
+            custom_metadata: BTreeMap::new(),
+            content_hash: None,
         }
     }
 
@@ -207,6 +246,10 @@ pub enum SplitState {
 
     /// The split is marked for deletion.
     MarkedForDeletion,
+
+    /// The split file was moved to the archive storage of the index. The split is not searchable
+    /// until it is rehydrated.
+    Archived,
 }
 
 impl fmt::Display for SplitState {
@@ -222,6 +265,7 @@ impl SplitState {
             SplitState::Staged => "Staged",
             SplitState::Published => "Published",
             SplitState::MarkedForDeletion => "MarkedForDeletion",
+            SplitState::Archived => "Archived",
         }
     }
 }
@@ -234,6 +278,7 @@ impl FromStr for SplitState {
             "Staged" => SplitState::Staged,
             "Published" => SplitState::Published,
             "MarkedForDeletion" => SplitState::MarkedForDeletion,
+            "Archived" => SplitState::Archived,
             "ScheduledForDeletion" => SplitState::MarkedForDeletion, // Deprecated
             "New" => SplitState::Staged,                             // Deprecated
             _ => return Err(format!("Unknown split state `{input}`.")),
@@ -17,9 +17,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::{Range, RangeInclusive};
 
+use quickwit_doc_mapper::bloom_filter::BloomFilters;
+use quickwit_doc_mapper::range_pruning::FieldRanges;
 use serde::{Deserialize, Serialize};
 
 use crate::split_metadata::utc_now_timestamp;
@@ -70,6 +72,18 @@ pub(crate) struct SplitMetadataV0_4 {
     /// A set of tags for categorizing and searching group of splits.
     pub tags: BTreeSet<String>,
 
+    #[serde(default)]
+    #[serde(skip_serializing_if = "FieldRanges::is_empty")]
+    #[schema(value_type = Object)]
+    /// Min / max values of the range pruning fields.
+    pub field_ranges: FieldRanges,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BloomFilters::is_empty")]
+    #[schema(value_type = Object)]
+    /// Bloom filters over the terms of the bloom filter fields.
+    pub bloom_filters: BloomFilters,
+
     #[schema(value_type = Object)]
     /// Contains the range of bytes of the footer that needs to be downloaded
     /// in order to open a split.
@@ -84,6 +98,22 @@ pub(crate) struct SplitMetadataV0_4 {
 
     #[serde(default)]
     num_merge_ops: usize,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    doc_mapping_version: u64,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    custom_metadata: BTreeMap<String, String>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 impl From<SplitMetadataV0_4> for SplitMetadata {
@@ -116,8 +146,13 @@ impl From<SplitMetadataV0_4> for SplitMetadata {
             time_range: v3.time_range,
             create_timestamp: v3.create_timestamp,
             tags: v3.tags,
+            field_ranges: v3.field_ranges,
+            bloom_filters: v3.bloom_filters,
             footer_offsets: v3.footer_offsets,
             num_merge_ops: v3.num_merge_ops,
+            doc_mapping_version: v3.doc_mapping_version,
+            custom_metadata: v3.custom_metadata,
+            content_hash: v3.content_hash,
         }
     }
 }
@@ -136,8 +171,13 @@ impl From<SplitMetadata> for SplitMetadataV0_4 {
             time_range: split.time_range,
             create_timestamp: split.create_timestamp,
             tags: split.tags,
+            field_ranges: split.field_ranges,
+            bloom_filters: split.bloom_filters,
             footer_offsets: split.footer_offsets,
             num_merge_ops: split.num_merge_ops,
+            doc_mapping_version: split.doc_mapping_version,
+            custom_metadata: split.custom_metadata,
+            content_hash: split.content_hash,
         }
     }
 }
@@ -28,7 +28,11 @@ pub mod test_suite {
     use futures::future::try_join_all;
     use itertools::Itertools;
     use quickwit_common::rand::append_random_suffix;
-    use quickwit_config::{IndexConfig, SourceConfig, SourceParams};
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{
+        AlertComparison, AlertCondition, AlertRule, AlertWebhookConfig, IndexConfig, IndexTemplate,
+        SourceConfig, SourceParams,
+    };
     use quickwit_doc_mapper::tag_pruning::{no_tag, tag, TagFilterAst};
     use quickwit_proto::metastore_api::DeleteQuery;
     use time::OffsetDateTime;
@@ -38,7 +42,10 @@ pub mod test_suite {
     use crate::checkpoint::{
         IndexCheckpointDelta, PartitionId, Position, SourceCheckpoint, SourceCheckpointDelta,
     };
-    use crate::{ListSplitsQuery, Metastore, MetastoreError, Split, SplitMetadata, SplitState};
+    use crate::{
+        ApiKey, ApiKeyScope, IndexAlias, IndexAliasAction, ListSplitsQuery, Metastore,
+        MetastoreError, Split, SplitMetadata, SplitState,
+    };
 
     #[async_trait]
     pub trait DefaultForTest {
@@ -227,6 +234,94 @@ pub mod test_suite {
         cleanup_index(&metastore, &index_id).await;
     }
 
+    pub async fn test_metastore_update_index_config<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-update-index-config");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+
+        let error = metastore
+            .update_index_config(index_config.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        metastore.create_index(index_config.clone()).await.unwrap();
+
+        // Add a field.
+        let mut new_index_config = index_config.clone();
+        new_index_config.doc_mapping.field_mappings.push(
+            serde_json::from_str(r#"{"name": "severity", "type": "text", "tokenizer": "raw"}"#)
+                .unwrap(),
+        );
+        metastore
+            .update_index_config(new_index_config.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.index_config.doc_mapping.doc_mapping_version,
+            1
+        );
+        assert!(index_metadata
+            .index_config
+            .doc_mapping
+            .field_mappings
+            .iter()
+            .any(|field_mapping| field_mapping.name == "severity"));
+
+        // Updating the other settings does not bump the doc mapping version.
+        new_index_config.indexing_settings.commit_timeout_secs += 1;
+        metastore
+            .update_index_config(new_index_config.clone())
+            .await
+            .unwrap();
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        assert_eq!(
+            index_metadata.index_config.doc_mapping.doc_mapping_version,
+            1
+        );
+        assert_eq!(
+            index_metadata
+                .index_config
+                .indexing_settings
+                .commit_timeout_secs,
+            new_index_config.indexing_settings.commit_timeout_secs
+        );
+
+        // Change the type of a field.
+        let mut invalid_index_config = new_index_config.clone();
+        invalid_index_config.doc_mapping.field_mappings.pop();
+        invalid_index_config
+            .doc_mapping
+            .field_mappings
+            .push(serde_json::from_str(r#"{"name": "severity", "type": "u64"}"#).unwrap());
+        let error = metastore
+            .update_index_config(invalid_index_config)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::InvalidIndexConfigUpdate { .. }
+        ));
+
+        // Change the index URI.
+        let mut invalid_index_config = new_index_config.clone();
+        invalid_index_config.index_uri =
+            Uri::from_well_formed(format!("ram:///indexes/{index_id}-moved"));
+        let error = metastore
+            .update_index_config(invalid_index_config)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::InvalidIndexConfigUpdate { .. }
+        ));
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_add_source<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -970,6 +1065,7 @@ pub mod test_suite {
                 let checkpoint_delta = IndexCheckpointDelta {
                     source_id,
                     source_delta,
+                    publish_token: None,
                 };
                 metastore
                     .publish_splits(&index_id, &[&split_id], &[], Some(checkpoint_delta))
@@ -991,6 +1087,96 @@ pub mod test_suite {
         cleanup_index(metastore.as_ref(), &index_id).await
     }
 
+    pub async fn test_metastore_publish_splits_fences_stale_publish_token<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let index_id = append_random_suffix("test-publish-splits-fencing");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+        metastore.create_index(index_config).await.unwrap();
+
+        let source_id = format!("{index_id}--source");
+
+        let split_ids: Vec<String> = (0..4)
+            .map(|split_ord| format!("{index_id}--split-{split_ord}"))
+            .collect();
+        let split_metadatas: Vec<SplitMetadata> = split_ids
+            .iter()
+            .map(|split_id| SplitMetadata {
+                split_id: split_id.clone(),
+                index_id: index_id.clone(),
+                ..Default::default()
+            })
+            .collect();
+        metastore
+            .stage_splits(&index_id, split_metadatas)
+            .await
+            .unwrap();
+
+        let zombie_publish_token = metastore
+            .issue_publish_token(&index_id, &source_id)
+            .await
+            .unwrap();
+        let owner_publish_token = metastore
+            .issue_publish_token(&index_id, &source_id)
+            .await
+            .unwrap();
+        assert!(zombie_publish_token < owner_publish_token);
+
+        // The new owner of the partition publishes first.
+        let checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 0..10)
+            .with_publish_token(owner_publish_token.clone());
+        metastore
+            .publish_splits(&index_id, &[&split_ids[0]], &[], Some(checkpoint_delta))
+            .await
+            .unwrap();
+
+        // The zombie pipeline is fenced even though its delta follows the checkpoint.
+        let checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 10..20)
+            .with_publish_token(zombie_publish_token);
+        let error = metastore
+            .publish_splits(&index_id, &[&split_ids[1]], &[], Some(checkpoint_delta))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::StalePublishToken(_)));
+
+        // So is a pipeline publishing without a token.
+        let checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 10..20);
+        let error = metastore
+            .publish_splits(&index_id, &[&split_ids[3]], &[], Some(checkpoint_delta))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::StalePublishToken(_)));
+
+        let splits = metastore
+            .list_splits(
+                ListSplitsQuery::for_index(&index_id).with_split_state(SplitState::Published),
+            )
+            .await
+            .unwrap();
+        assert_eq!(splits.len(), 1);
+
+        let checkpoint_delta = IndexCheckpointDelta::for_test(&source_id, 10..20)
+            .with_publish_token(owner_publish_token);
+        metastore
+            .publish_splits(&index_id, &[&split_ids[2]], &[], Some(checkpoint_delta))
+            .await
+            .unwrap();
+
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        let source_checkpoint = index_metadata
+            .checkpoint
+            .source_checkpoint(&source_id)
+            .unwrap();
+        assert_eq!(
+            source_checkpoint.position_for_partition(&PartitionId::default()),
+            Some(&Position::from(19u64))
+        );
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_replace_splits<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -1336,6 +1522,127 @@ pub mod test_suite {
         cleanup_index(&metastore, &index_id).await;
     }
 
+    pub async fn test_metastore_archive_splits<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+        let index_id = append_random_suffix("test-archive-splits");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+
+        metastore.create_index(index_config).await.unwrap();
+
+        let error = metastore
+            .archive_splits("index-not-found", &["split-not-found"])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+
+        let error = metastore
+            .archive_splits(&index_id, &["split-not-found"])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsDoNotExist { .. }));
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_metadata_1 = SplitMetadata {
+            split_id: split_id_1.clone(),
+            index_id: index_id.clone(),
+            create_timestamp: current_timestamp,
+            ..Default::default()
+        };
+        let split_id_2 = format!("{index_id}--split-2");
+        let split_metadata_2 = SplitMetadata {
+            split_id: split_id_2.clone(),
+            index_id: index_id.clone(),
+            create_timestamp: current_timestamp,
+            ..Default::default()
+        };
+        metastore
+            .stage_splits(&index_id, vec![split_metadata_1, split_metadata_2])
+            .await
+            .unwrap();
+        metastore
+            .publish_splits(&index_id, &[&split_id_1], &[], None)
+            .await
+            .unwrap();
+
+        // Staged splits cannot be archived.
+        let error = metastore
+            .archive_splits(&index_id, &[&split_id_1, &split_id_2])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsNotPublished { .. }));
+
+        metastore
+            .archive_splits(&index_id, &[&split_id_1])
+            .await
+            .unwrap();
+        // Archiving an archived split is a no-op.
+        metastore
+            .archive_splits(&index_id, &[&split_id_1])
+            .await
+            .unwrap();
+
+        let archived_splits = metastore
+            .list_splits(
+                ListSplitsQuery::for_index(&index_id).with_split_state(SplitState::Archived),
+            )
+            .await
+            .unwrap();
+        assert_eq!(archived_splits.len(), 1);
+        assert_eq!(archived_splits[0].split_id(), split_id_1);
+
+        let published_splits = metastore
+            .list_splits(
+                ListSplitsQuery::for_index(&index_id).with_split_state(SplitState::Published),
+            )
+            .await
+            .unwrap();
+        assert!(published_splits.is_empty());
+
+        let error = metastore
+            .rehydrate_splits(&index_id, &[&split_id_1, &split_id_2])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsNotArchived { .. }));
+
+        metastore
+            .rehydrate_splits(&index_id, &[&split_id_1])
+            .await
+            .unwrap();
+
+        let published_splits = metastore
+            .list_splits(
+                ListSplitsQuery::for_index(&index_id).with_split_state(SplitState::Published),
+            )
+            .await
+            .unwrap();
+        assert_eq!(published_splits.len(), 1);
+        assert_eq!(published_splits[0].split_id(), split_id_1);
+
+        // Archived splits can be marked for deletion.
+        metastore
+            .archive_splits(&index_id, &[&split_id_1])
+            .await
+            .unwrap();
+        metastore
+            .mark_splits_for_deletion(&index_id, &[&split_id_1])
+            .await
+            .unwrap();
+        let marked_splits = metastore
+            .list_splits(
+                ListSplitsQuery::for_index(&index_id)
+                    .with_split_state(SplitState::MarkedForDeletion),
+            )
+            .await
+            .unwrap();
+        assert_eq!(marked_splits.len(), 1);
+
+        cleanup_index(&metastore, &index_id).await;
+    }
+
     pub async fn test_metastore_delete_splits<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -2477,6 +2784,298 @@ pub mod test_suite {
 
         cleanup_index(&metastore, &index_id).await;
     }
+
+    pub async fn test_metastore_api_keys<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+        let key_id = append_random_suffix("test-api-keys");
+        let api_key = ApiKey {
+            key_id: key_id.clone(),
+            key_hash: "test-hash".to_string(),
+            description: Some("Test key".to_string()),
+            scopes: BTreeSet::from([ApiKeyScope::Search]),
+            index_patterns: vec!["logs-*".to_string()],
+            roles: vec!["team-a".to_string()],
+            create_timestamp: 1,
+        };
+        metastore.create_api_key(api_key.clone()).await.unwrap();
+
+        let error = metastore.create_api_key(api_key.clone()).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::ApiKeyAlreadyExists { .. }));
+
+        let api_keys = metastore.list_api_keys().await.unwrap();
+        assert!(api_keys.contains(&api_key));
+
+        metastore.delete_api_key(&key_id).await.unwrap();
+
+        let api_keys = metastore.list_api_keys().await.unwrap();
+        assert!(!api_keys.contains(&api_key));
+
+        let error = metastore.delete_api_key(&key_id).await.unwrap_err();
+        assert!(matches!(error, MetastoreError::ApiKeyDoesNotExist { .. }));
+    }
+
+    pub async fn test_metastore_index_templates<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+        let template_id = append_random_suffix("test-index-templates");
+        let index_config = IndexConfig::for_test("index-template", "ram:///indexes/index-template");
+        let index_template = IndexTemplate {
+            template_id: template_id.clone(),
+            index_id_patterns: vec!["logs-*".to_string()],
+            priority: 0,
+            description: Some("Test template".to_string()),
+            index_root_uri: None,
+            doc_mapping: index_config.doc_mapping,
+            indexing_settings: index_config.indexing_settings,
+            search_settings: index_config.search_settings,
+            retention_policy: index_config.retention_policy,
+            rollover_policy: None,
+        };
+        metastore
+            .create_index_template(index_template.clone(), false)
+            .await
+            .unwrap();
+
+        let error = metastore
+            .create_index_template(index_template.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::IndexTemplateAlreadyExists { .. }
+        ));
+
+        let index_templates = metastore.list_index_templates().await.unwrap();
+        assert!(index_templates.contains(&index_template));
+
+        let mut updated_index_template = index_template.clone();
+        updated_index_template.priority = 10;
+        metastore
+            .create_index_template(updated_index_template.clone(), true)
+            .await
+            .unwrap();
+
+        let index_templates = metastore.list_index_templates().await.unwrap();
+        assert!(index_templates.contains(&updated_index_template));
+        assert!(!index_templates.contains(&index_template));
+
+        metastore.delete_index_template(&template_id).await.unwrap();
+
+        let index_templates = metastore.list_index_templates().await.unwrap();
+        assert!(!index_templates.contains(&updated_index_template));
+
+        let error = metastore
+            .delete_index_template(&template_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::IndexTemplateDoesNotExist { .. }
+        ));
+    }
+
+    pub async fn test_metastore_leases<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+        let lease_id = append_random_suffix("test-leases");
+
+        let lease = metastore
+            .acquire_lease(&lease_id, "node-1", 60)
+            .await
+            .unwrap();
+        assert_eq!(lease.lease_id, lease_id);
+        assert!(lease.is_held_by("node-1"));
+
+        // The lease is held by another node and has not expired.
+        let lease = metastore
+            .acquire_lease(&lease_id, "node-2", 60)
+            .await
+            .unwrap();
+        assert!(lease.is_held_by("node-1"));
+
+        // The holder renews the lease, this time for a duration of zero so that it expires
+        // immediately.
+        let renewed_lease = metastore
+            .acquire_lease(&lease_id, "node-1", 0)
+            .await
+            .unwrap();
+        assert!(renewed_lease.is_held_by("node-1"));
+        assert!(renewed_lease.expire_timestamp < lease.expire_timestamp);
+
+        let lease = metastore
+            .acquire_lease(&lease_id, "node-2", 60)
+            .await
+            .unwrap();
+        assert!(lease.is_held_by("node-2"));
+    }
+
+    async fn find_index_alias(metastore: &dyn Metastore, alias_id: &str) -> Option<IndexAlias> {
+        metastore
+            .list_index_aliases()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|index_alias| index_alias.alias_id == alias_id)
+    }
+
+    pub async fn test_metastore_index_aliases<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let alias_id = append_random_suffix("test-index-aliases");
+        let index_id_v1 = format!("{alias_id}-v1");
+        let index_id_v2 = format!("{alias_id}-v2");
+        for index_id in [&index_id_v1, &index_id_v2] {
+            let index_uri = format!("ram:///indexes/{index_id}");
+            let index_config = IndexConfig::for_test(index_id, &index_uri);
+            metastore.create_index(index_config).await.unwrap();
+        }
+        metastore
+            .update_index_aliases(vec![IndexAliasAction::Add {
+                alias_id: alias_id.clone(),
+                index_id: index_id_v1.clone(),
+            }])
+            .await
+            .unwrap();
+        assert_eq!(
+            find_index_alias(&metastore, &alias_id).await.unwrap(),
+            IndexAlias {
+                alias_id: alias_id.clone(),
+                index_ids: vec![index_id_v1.clone()],
+            }
+        );
+
+        // A failing action cancels the whole update.
+        let error = metastore
+            .update_index_aliases(vec![
+                IndexAliasAction::Remove {
+                    alias_id: alias_id.clone(),
+                    index_id: index_id_v1.clone(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: alias_id.clone(),
+                    index_id: format!("{alias_id}-v3"),
+                },
+            ])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::IndexDoesNotExist { .. }));
+        assert_eq!(
+            find_index_alias(&metastore, &alias_id)
+                .await
+                .unwrap()
+                .index_ids,
+            [index_id_v1.clone()]
+        );
+
+        // Swap the index the alias points to.
+        metastore
+            .update_index_aliases(vec![
+                IndexAliasAction::Remove {
+                    alias_id: alias_id.clone(),
+                    index_id: index_id_v1.clone(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: alias_id.clone(),
+                    index_id: index_id_v2.clone(),
+                },
+            ])
+            .await
+            .unwrap();
+        assert_eq!(
+            find_index_alias(&metastore, &alias_id)
+                .await
+                .unwrap()
+                .index_ids,
+            [index_id_v2.clone()]
+        );
+
+        let error = metastore
+            .update_index_aliases(vec![IndexAliasAction::Add {
+                alias_id: index_id_v1.clone(),
+                index_id: index_id_v2.clone(),
+            }])
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::InvalidIndexAlias { .. }));
+
+        // Deleting the last index of an alias deletes the alias.
+        metastore.delete_index(&index_id_v2).await.unwrap();
+        assert!(find_index_alias(&metastore, &alias_id).await.is_none());
+
+        let error = metastore
+            .update_index_aliases(vec![IndexAliasAction::Remove {
+                alias_id: alias_id.clone(),
+                index_id: index_id_v2.clone(),
+            }])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::IndexAliasDoesNotExist { .. }
+        ));
+
+        metastore.delete_index(&index_id_v1).await.unwrap();
+    }
+
+    pub async fn test_metastore_alert_rules<MetastoreToTest: Metastore + DefaultForTest>() {
+        let metastore = MetastoreToTest::default_for_test().await;
+        let rule_id = append_random_suffix("test-alert-rules");
+        let alert_rule = AlertRule {
+            rule_id: rule_id.clone(),
+            description: Some("Test alert rule".to_string()),
+            index_id: "app-logs".to_string(),
+            query: "severity_text:ERROR".to_string(),
+            search_fields: Vec::new(),
+            window: Duration::from_secs(300),
+            evaluation_interval: Duration::from_secs(60),
+            condition: AlertCondition {
+                comparison: AlertComparison::GreaterThan,
+                threshold: 100,
+            },
+            num_sample_hits: 5,
+            webhook: AlertWebhookConfig {
+                url: "https://hooks.example.com/alerts".to_string(),
+                headers: Default::default(),
+            },
+            enabled: true,
+        };
+        metastore
+            .create_alert_rule(alert_rule.clone(), false)
+            .await
+            .unwrap();
+
+        let error = metastore
+            .create_alert_rule(alert_rule.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::AlertRuleAlreadyExists { .. }
+        ));
+
+        let alert_rules = metastore.list_alert_rules().await.unwrap();
+        assert!(alert_rules.contains(&alert_rule));
+
+        let mut updated_alert_rule = alert_rule.clone();
+        updated_alert_rule.condition.threshold = 10;
+        metastore
+            .create_alert_rule(updated_alert_rule.clone(), true)
+            .await
+            .unwrap();
+
+        let alert_rules = metastore.list_alert_rules().await.unwrap();
+        assert!(alert_rules.contains(&updated_alert_rule));
+        assert!(!alert_rules.contains(&alert_rule));
+
+        metastore.delete_alert_rule(&rule_id).await.unwrap();
+
+        let alert_rules = metastore.list_alert_rules().await.unwrap();
+        assert!(!alert_rules.contains(&updated_alert_rule));
+
+        let error = metastore.delete_alert_rule(&rule_id).await.unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::AlertRuleDoesNotExist { .. }
+        ));
+    }
 }
 
 macro_rules! metastore_test_suite {
@@ -2541,6 +3140,12 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_publish_splits_concurrency::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_publish_splits_fences_stale_publish_token() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_publish_splits_fences_stale_publish_token::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_publish_splits_empty_splits_array_is_allowed() {
                 crate::tests::test_suite::test_metastore_publish_splits_empty_splits_array_is_allowed::<$metastore_type>().await;
@@ -2559,6 +3164,12 @@ macro_rules! metastore_test_suite {
                 .await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_archive_splits() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_archive_splits::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_delete_splits() {
                 let _ = tracing_subscriber::fmt::try_init();
@@ -2591,6 +3202,12 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_add_source::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_update_index_config() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_update_index_config::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_toggle_source() {
                 let _ = tracing_subscriber::fmt::try_init();
@@ -2650,6 +3267,46 @@ macro_rules! metastore_test_suite {
                 let _ = tracing_subscriber::fmt::try_init();
                 crate::tests::test_suite::test_metastore_stage_splits::<$metastore_type>().await;
             }
+
+            // API keys API tests
+
+            #[tokio::test]
+            async fn test_metastore_api_keys() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_api_keys::<$metastore_type>().await;
+            }
+
+            // Index templates API tests
+
+            #[tokio::test]
+            async fn test_metastore_index_templates() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_index_templates::<$metastore_type>().await;
+            }
+
+            // Leases API tests
+
+            #[tokio::test]
+            async fn test_metastore_leases() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_leases::<$metastore_type>().await;
+            }
+
+            // Index aliases API tests
+
+            #[tokio::test]
+            async fn test_metastore_index_aliases() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_index_aliases::<$metastore_type>().await;
+            }
+
+            // Alert rules API tests
+
+            #[tokio::test]
+            async fn test_metastore_alert_rules() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_alert_rules::<$metastore_type>().await;
+            }
         }
     }
 }
@@ -413,6 +413,7 @@ impl LogsService for OtlpGrpcLogsService {
         &self,
         request: Request<ExportLogsServiceRequest>,
     ) -> Result<Response<ExportLogsServiceResponse>, Status> {
+        quickwit_proto::set_parent_span_from_request_metadata(request.metadata());
         let request = request.into_inner();
         self.clone()
             .export_instrumented(request)
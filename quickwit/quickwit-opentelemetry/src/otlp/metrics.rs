@@ -26,6 +26,7 @@ pub struct OtlpServiceMetrics {
     pub request_duration_seconds: HistogramVec<5>,
     pub ingested_log_records_total: IntCounterVec<4>,
     pub ingested_spans_total: IntCounterVec<4>,
+    pub ingested_data_points_total: IntCounterVec<4>,
     pub ingested_bytes_total: IntCounterVec<4>,
 }
 
@@ -62,6 +63,12 @@ impl Default for OtlpServiceMetrics {
                 "quickwit_otlp",
                 ["service", "index", "transport", "format"],
             ),
+            ingested_data_points_total: new_counter_vec(
+                "ingested_data_points_total",
+                "Number of metric data points ingested",
+                "quickwit_otlp",
+                ["service", "index", "transport", "format"],
+            ),
             ingested_bytes_total: new_counter_vec(
                 "ingested_bytes_total",
                 "Number of bytes ingested",
@@ -33,9 +33,16 @@ use tracing::warn;
 
 mod logs;
 mod metrics;
+mod otel_metrics;
+mod sampling;
 mod trace;
 
 pub use logs::{OtlpGrpcLogsService, OTEL_LOGS_INDEX_CONFIG, OTEL_LOGS_INDEX_ID};
+pub use otel_metrics::{
+    MetricDataPoint, MetricType, OtlpGrpcMetricsService, OTEL_METRICS_INDEX_CONFIG,
+    OTEL_METRICS_INDEX_ID,
+};
+pub use sampling::{SamplingDecision, TraceSampler};
 pub use trace::{
     Event, Link, OtlpGrpcTraceService, Span, SpanFingerprint, SpanKind, SpanStatus,
     OTEL_TRACE_INDEX_CONFIG, OTEL_TRACE_INDEX_ID,
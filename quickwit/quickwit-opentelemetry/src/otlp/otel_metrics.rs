@@ -0,0 +1,828 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use quickwit_ingest_api::{
+    DocBatch, DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient,
+};
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::metrics_service_server::MetricsService;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::{
+    ExportMetricsPartialSuccess, ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+};
+use quickwit_proto::opentelemetry::proto::common::v1::KeyValue as OtlpKeyValue;
+use quickwit_proto::opentelemetry::proto::metrics::v1::metric::Data as OtlpMetricData;
+use quickwit_proto::opentelemetry::proto::metrics::v1::{
+    exemplar, number_data_point, Exemplar as OtlpExemplar, NumberDataPoint as OtlpNumberDataPoint,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tonic::{Request, Response, Status};
+use tracing::field::Empty;
+use tracing::{error, instrument, Span as RuntimeSpan};
+
+use super::trace::B64SpanId;
+use super::{B64TraceId, TraceId};
+use crate::otlp::extract_attributes;
+use crate::otlp::metrics::OTLP_SERVICE_METRICS;
+
+pub const OTEL_METRICS_INDEX_ID: &str = "otel-metrics-v0";
+
+pub const OTEL_METRICS_INDEX_CONFIG: &str = r#"
+version: 0.4
+
+index_id: otel-metrics-v0
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp_secs
+      type: datetime
+      input_formats: [unix_timestamp]
+      indexed: false
+      fast: true
+      precision: seconds
+      stored: false
+    - name: timestamp_nanos
+      type: u64
+      indexed: false
+    - name: start_timestamp_nanos
+      type: u64
+      indexed: false
+    - name: service_name
+      type: text
+      tokenizer: raw
+    - name: metric_name
+      type: text
+      tokenizer: raw
+    - name: metric_description
+      type: text
+      indexed: false
+    - name: metric_unit
+      type: text
+      tokenizer: raw
+    - name: metric_type
+      type: text
+      tokenizer: raw
+    - name: aggregation_temporality
+      type: text
+      tokenizer: raw
+    - name: is_monotonic
+      type: bool
+      indexed: false
+    - name: value
+      type: f64
+      fast: true
+    - name: count
+      type: u64
+      indexed: false
+      fast: true
+    - name: sum
+      type: f64
+      indexed: false
+      fast: true
+    - name: min
+      type: f64
+      indexed: false
+    - name: max
+      type: f64
+      indexed: false
+    - name: bucket_counts
+      type: array<u64>
+      indexed: false
+    - name: explicit_bounds
+      type: array<f64>
+      indexed: false
+    - name: exponential_histogram
+      type: json
+      indexed: false
+    - name: quantiles
+      type: array<json>
+      indexed: false
+    - name: attributes
+      type: json
+      tokenizer: raw
+    - name: flags
+      type: u64
+      indexed: false
+    - name: exemplars
+      type: array<json>
+      tokenizer: raw
+    - name: exemplar_trace_ids
+      type: array<text>
+      tokenizer: raw
+    - name: resource_attributes
+      type: json
+      tokenizer: raw
+    - name: resource_dropped_attributes_count
+      type: u64
+      indexed: false
+    - name: scope_name
+      type: text
+      indexed: false
+    - name: scope_version
+      type: text
+      indexed: false
+    - name: scope_attributes
+      type: json
+      indexed: false
+    - name: scope_dropped_attributes_count
+      type: u64
+      indexed: false
+
+  timestamp_field: timestamp_secs
+
+  partition_key: hash_mod(service_name, 100)
+  tag_fields: [service_name]
+
+indexing_settings:
+  commit_timeout_secs: 30
+
+search_settings:
+  default_search_fields: []
+"#;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricType {
+    Gauge,
+    Sum,
+    Histogram,
+    ExponentialHistogram,
+    Summary,
+}
+
+/// A single data point of an OTLP metric. Data points of every metric type share the same
+/// document shape: the fields that do not apply to a metric type are left empty.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricDataPoint {
+    /// Data point timestamp in seconds used for aggregations and range queries.
+    pub timestamp_secs: u64,
+    /// Data point timestamp in nanoseconds. Stored as a `u64` instead of a `datetime` to avoid
+    /// the truncation to microseconds.
+    pub timestamp_nanos: u64,
+    pub start_timestamp_nanos: Option<u64>,
+    pub service_name: String,
+    pub metric_name: String,
+    pub metric_description: Option<String>,
+    pub metric_unit: Option<String>,
+    pub metric_type: MetricType,
+    pub aggregation_temporality: Option<String>,
+    pub is_monotonic: Option<bool>,
+    /// Value of a gauge or sum data point.
+    pub value: Option<f64>,
+    pub count: Option<u64>,
+    pub sum: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub bucket_counts: Vec<u64>,
+    #[serde(default)]
+    pub explicit_bounds: Vec<f64>,
+    pub exponential_histogram: Option<ExponentialHistogramBuckets>,
+    #[serde(default)]
+    pub quantiles: Vec<QuantileValue>,
+    pub attributes: HashMap<String, JsonValue>,
+    pub flags: u32,
+    #[serde(default)]
+    pub exemplars: Vec<MetricExemplar>,
+    /// Trace IDs of the exemplars, encoded like the trace IDs of the trace index so that a metric
+    /// data point can be joined with the spans it was sampled from.
+    #[serde(default)]
+    pub exemplar_trace_ids: Vec<B64TraceId>,
+    pub resource_attributes: HashMap<String, JsonValue>,
+    pub resource_dropped_attributes_count: u32,
+    pub scope_name: Option<String>,
+    pub scope_version: Option<String>,
+    pub scope_attributes: HashMap<String, JsonValue>,
+    pub scope_dropped_attributes_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExponentialHistogramBuckets {
+    pub scale: i32,
+    pub zero_count: u64,
+    pub positive_offset: i32,
+    pub positive_bucket_counts: Vec<u64>,
+    pub negative_offset: i32,
+    pub negative_bucket_counts: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuantileValue {
+    pub quantile: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricExemplar {
+    pub exemplar_timestamp_nanos: u64,
+    pub exemplar_value: Option<f64>,
+    pub exemplar_trace_id: Option<B64TraceId>,
+    pub exemplar_span_id: Option<B64SpanId>,
+    pub exemplar_filtered_attributes: HashMap<String, JsonValue>,
+}
+
+/// Resource, scope, and metric level fields shared by the data points of a metric.
+struct MetricContext<'a> {
+    service_name: &'a str,
+    resource_attributes: &'a HashMap<String, JsonValue>,
+    resource_dropped_attributes_count: u32,
+    scope_name: Option<&'a String>,
+    scope_version: Option<&'a String>,
+    scope_attributes: &'a HashMap<String, JsonValue>,
+    scope_dropped_attributes_count: u32,
+    metric_name: &'a str,
+    metric_description: Option<&'a String>,
+    metric_unit: Option<&'a String>,
+}
+
+impl<'a> MetricContext<'a> {
+    fn new_data_point(
+        &self,
+        metric_type: MetricType,
+        attributes: Vec<OtlpKeyValue>,
+        start_time_unix_nano: u64,
+        time_unix_nano: u64,
+        flags: u32,
+        exemplars: Vec<OtlpExemplar>,
+    ) -> Result<MetricDataPoint, Status> {
+        let start_timestamp_nanos = if start_time_unix_nano != 0 {
+            Some(start_time_unix_nano)
+        } else {
+            None
+        };
+        let exemplars = exemplars
+            .into_iter()
+            .map(parse_exemplar)
+            .collect::<Result<Vec<_>, _>>()?;
+        let exemplar_trace_ids = exemplars
+            .iter()
+            .flat_map(|exemplar| exemplar.exemplar_trace_id.clone())
+            .collect();
+        let data_point = MetricDataPoint {
+            timestamp_secs: time_unix_nano / 1_000_000_000,
+            timestamp_nanos: time_unix_nano,
+            start_timestamp_nanos,
+            service_name: self.service_name.to_string(),
+            metric_name: self.metric_name.to_string(),
+            metric_description: self.metric_description.cloned(),
+            metric_unit: self.metric_unit.cloned(),
+            metric_type,
+            aggregation_temporality: None,
+            is_monotonic: None,
+            value: None,
+            count: None,
+            sum: None,
+            min: None,
+            max: None,
+            bucket_counts: Vec::new(),
+            explicit_bounds: Vec::new(),
+            exponential_histogram: None,
+            quantiles: Vec::new(),
+            attributes: extract_attributes(attributes),
+            flags,
+            exemplars,
+            exemplar_trace_ids,
+            resource_attributes: self.resource_attributes.clone(),
+            resource_dropped_attributes_count: self.resource_dropped_attributes_count,
+            scope_name: self.scope_name.cloned(),
+            scope_version: self.scope_version.cloned(),
+            scope_attributes: self.scope_attributes.clone(),
+            scope_dropped_attributes_count: self.scope_dropped_attributes_count,
+        };
+        Ok(data_point)
+    }
+
+    fn new_number_data_point(
+        &self,
+        metric_type: MetricType,
+        number_data_point: OtlpNumberDataPoint,
+    ) -> Result<MetricDataPoint, Status> {
+        let value = number_data_point.value.map(|value| match value {
+            number_data_point::Value::AsDouble(value) => value,
+            number_data_point::Value::AsInt(value) => value as f64,
+        });
+        let mut data_point = self.new_data_point(
+            metric_type,
+            number_data_point.attributes,
+            number_data_point.start_time_unix_nano,
+            number_data_point.time_unix_nano,
+            number_data_point.flags,
+            number_data_point.exemplars,
+        )?;
+        data_point.value = value;
+        Ok(data_point)
+    }
+}
+
+fn parse_exemplar(exemplar: OtlpExemplar) -> Result<MetricExemplar, Status> {
+    let exemplar_trace_id = if exemplar.trace_id.iter().any(|&byte| byte != 0) {
+        let b64trace_id = TraceId::try_from(exemplar.trace_id)
+            .map_err(|error| Status::invalid_argument(error.to_string()))?
+            .b64_encode();
+        Some(b64trace_id)
+    } else {
+        None
+    };
+    let exemplar_span_id = if exemplar.span_id.iter().any(|&byte| byte != 0) {
+        Some(BASE64_STANDARD.encode(exemplar.span_id))
+    } else {
+        None
+    };
+    let exemplar_value = exemplar.value.map(|value| match value {
+        exemplar::Value::AsDouble(value) => value,
+        exemplar::Value::AsInt(value) => value as f64,
+    });
+    let exemplar = MetricExemplar {
+        exemplar_timestamp_nanos: exemplar.time_unix_nano,
+        exemplar_value,
+        exemplar_trace_id,
+        exemplar_span_id,
+        exemplar_filtered_attributes: extract_attributes(exemplar.filtered_attributes),
+    };
+    Ok(exemplar)
+}
+
+fn aggregation_temporality_name(aggregation_temporality: i32) -> Option<String> {
+    match aggregation_temporality {
+        1 => Some("delta".to_string()),
+        2 => Some("cumulative".to_string()),
+        _ => None,
+    }
+}
+
+/// Flattens the metrics of an export request into data points.
+fn parse_data_points(request: ExportMetricsServiceRequest) -> Result<Vec<MetricDataPoint>, Status> {
+    let mut data_points = Vec::new();
+
+    for resource_metric in request.resource_metrics {
+        let mut resource_attributes = extract_attributes(
+            resource_metric
+                .resource
+                .clone()
+                .map(|rsrc| rsrc.attributes)
+                .unwrap_or_else(Vec::new),
+        );
+        let resource_dropped_attributes_count = resource_metric
+            .resource
+            .map(|rsrc| rsrc.dropped_attributes_count)
+            .unwrap_or(0);
+
+        let service_name = match resource_attributes.remove("service.name") {
+            Some(JsonValue::String(value)) => value.to_string(),
+            _ => "unknown_service".to_string(),
+        };
+        for scope_metric in resource_metric.scope_metrics {
+            let scope_name = scope_metric
+                .scope
+                .as_ref()
+                .map(|scope| &scope.name)
+                .filter(|name| !name.is_empty());
+            let scope_version = scope_metric
+                .scope
+                .as_ref()
+                .map(|scope| &scope.version)
+                .filter(|version| !version.is_empty());
+            let scope_attributes = extract_attributes(
+                scope_metric
+                    .scope
+                    .clone()
+                    .map(|scope| scope.attributes)
+                    .unwrap_or_else(Vec::new),
+            );
+            let scope_dropped_attributes_count = scope_metric
+                .scope
+                .as_ref()
+                .map(|scope| scope.dropped_attributes_count)
+                .unwrap_or(0);
+
+            for metric in scope_metric.metrics {
+                let context = MetricContext {
+                    service_name: &service_name,
+                    resource_attributes: &resource_attributes,
+                    resource_dropped_attributes_count,
+                    scope_name,
+                    scope_version,
+                    scope_attributes: &scope_attributes,
+                    scope_dropped_attributes_count,
+                    metric_name: &metric.name,
+                    metric_description: Some(&metric.description)
+                        .filter(|description| !description.is_empty()),
+                    metric_unit: Some(&metric.unit).filter(|unit| !unit.is_empty()),
+                };
+                let Some(metric_data) = metric.data else {
+                    continue;
+                };
+                match metric_data {
+                    OtlpMetricData::Gauge(gauge) => {
+                        for number_data_point in gauge.data_points {
+                            let data_point = context
+                                .new_number_data_point(MetricType::Gauge, number_data_point)?;
+                            data_points.push(data_point);
+                        }
+                    }
+                    OtlpMetricData::Sum(sum) => {
+                        for number_data_point in sum.data_points {
+                            let mut data_point = context
+                                .new_number_data_point(MetricType::Sum, number_data_point)?;
+                            data_point.aggregation_temporality =
+                                aggregation_temporality_name(sum.aggregation_temporality);
+                            data_point.is_monotonic = Some(sum.is_monotonic);
+                            data_points.push(data_point);
+                        }
+                    }
+                    OtlpMetricData::Histogram(histogram) => {
+                        for histogram_data_point in histogram.data_points {
+                            let mut data_point = context.new_data_point(
+                                MetricType::Histogram,
+                                histogram_data_point.attributes,
+                                histogram_data_point.start_time_unix_nano,
+                                histogram_data_point.time_unix_nano,
+                                histogram_data_point.flags,
+                                histogram_data_point.exemplars,
+                            )?;
+                            data_point.aggregation_temporality =
+                                aggregation_temporality_name(histogram.aggregation_temporality);
+                            data_point.count = Some(histogram_data_point.count);
+                            data_point.sum = histogram_data_point.sum;
+                            data_point.min = histogram_data_point.min;
+                            data_point.max = histogram_data_point.max;
+                            data_point.bucket_counts = histogram_data_point.bucket_counts;
+                            data_point.explicit_bounds = histogram_data_point.explicit_bounds;
+                            data_points.push(data_point);
+                        }
+                    }
+                    OtlpMetricData::ExponentialHistogram(exponential_histogram) => {
+                        for histogram_data_point in exponential_histogram.data_points {
+                            let mut data_point = context.new_data_point(
+                                MetricType::ExponentialHistogram,
+                                histogram_data_point.attributes,
+                                histogram_data_point.start_time_unix_nano,
+                                histogram_data_point.time_unix_nano,
+                                histogram_data_point.flags,
+                                histogram_data_point.exemplars,
+                            )?;
+                            data_point.aggregation_temporality = aggregation_temporality_name(
+                                exponential_histogram.aggregation_temporality,
+                            );
+                            data_point.count = Some(histogram_data_point.count);
+                            data_point.sum = histogram_data_point.sum;
+                            data_point.min = histogram_data_point.min;
+                            data_point.max = histogram_data_point.max;
+                            let positive = histogram_data_point.positive.unwrap_or_default();
+                            let negative = histogram_data_point.negative.unwrap_or_default();
+                            data_point.exponential_histogram = Some(ExponentialHistogramBuckets {
+                                scale: histogram_data_point.scale,
+                                zero_count: histogram_data_point.zero_count,
+                                positive_offset: positive.offset,
+                                positive_bucket_counts: positive.bucket_counts,
+                                negative_offset: negative.offset,
+                                negative_bucket_counts: negative.bucket_counts,
+                            });
+                            data_points.push(data_point);
+                        }
+                    }
+                    OtlpMetricData::Summary(summary) => {
+                        for summary_data_point in summary.data_points {
+                            let mut data_point = context.new_data_point(
+                                MetricType::Summary,
+                                summary_data_point.attributes,
+                                summary_data_point.start_time_unix_nano,
+                                summary_data_point.time_unix_nano,
+                                summary_data_point.flags,
+                                Vec::new(),
+                            )?;
+                            data_point.count = Some(summary_data_point.count);
+                            data_point.sum = Some(summary_data_point.sum);
+                            data_point.quantiles = summary_data_point
+                                .quantile_values
+                                .into_iter()
+                                .map(|quantile_value| QuantileValue {
+                                    quantile: quantile_value.quantile,
+                                    value: quantile_value.value,
+                                })
+                                .collect();
+                            data_points.push(data_point);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(data_points)
+}
+
+struct ParsedDataPoints {
+    doc_batch: DocBatch,
+    num_data_points: u64,
+    num_parse_errors: u64,
+    error_message: String,
+}
+
+#[derive(Clone)]
+pub struct OtlpGrpcMetricsService {
+    ingest_service: IngestServiceClient,
+}
+
+impl OtlpGrpcMetricsService {
+    // TODO: remove and use registry
+    pub fn new(ingest_service: IngestServiceClient) -> Self {
+        Self { ingest_service }
+    }
+
+    async fn export_inner(
+        &mut self,
+        request: ExportMetricsServiceRequest,
+        labels: [&'static str; 4],
+    ) -> Result<ExportMetricsServiceResponse, Status> {
+        let ParsedDataPoints {
+            doc_batch,
+            num_data_points,
+            num_parse_errors,
+            error_message,
+        } = tokio::task::spawn_blocking({
+            let parent_span = RuntimeSpan::current();
+            || Self::parse_metrics(request, parent_span)
+        })
+        .await
+        .map_err(|join_error| {
+            error!("Failed to parse metric data points: {join_error:?}");
+            Status::internal("Failed to parse metric data points.")
+        })??;
+        if num_data_points == num_parse_errors {
+            return Err(tonic::Status::internal(error_message));
+        }
+        let num_bytes = doc_batch.concat_docs.len() as u64;
+        self.store_data_points(doc_batch).await?;
+
+        OTLP_SERVICE_METRICS
+            .ingested_data_points_total
+            .with_label_values(labels)
+            .inc_by(num_data_points);
+        OTLP_SERVICE_METRICS
+            .ingested_bytes_total
+            .with_label_values(labels)
+            .inc_by(num_bytes);
+
+        let response = ExportMetricsServiceResponse {
+            // `rejected_data_points=0` and `error_message=""` is considered a "full" success.
+            partial_success: Some(ExportMetricsPartialSuccess {
+                rejected_data_points: num_parse_errors as i64,
+                error_message,
+            }),
+        };
+        Ok(response)
+    }
+
+    #[instrument(skip_all, parent = parent_span, fields(num_data_points = Empty, num_bytes = Empty, num_parse_errors = Empty))]
+    fn parse_metrics(
+        request: ExportMetricsServiceRequest,
+        parent_span: RuntimeSpan,
+    ) -> Result<ParsedDataPoints, Status> {
+        let data_points = parse_data_points(request)?;
+        let num_data_points = data_points.len() as u64;
+        let mut num_parse_errors = 0;
+        let mut error_message = String::new();
+
+        let mut doc_batch = DocBatchBuilder::new(OTEL_METRICS_INDEX_ID.to_string()).json_writer();
+        for data_point in data_points {
+            if let Err(error) = doc_batch.ingest_doc(&data_point) {
+                error!(error=?error, "Failed to JSON serialize metric data point.");
+                error_message = format!("Failed to JSON serialize metric data point: {error:?}");
+                num_parse_errors += 1;
+            }
+        }
+        let doc_batch = doc_batch.build();
+        let current_span = RuntimeSpan::current();
+        current_span.record("num_data_points", num_data_points);
+        current_span.record("num_bytes", doc_batch.num_bytes());
+        current_span.record("num_parse_errors", num_parse_errors);
+
+        let parsed_data_points = ParsedDataPoints {
+            doc_batch,
+            num_data_points,
+            num_parse_errors,
+            error_message,
+        };
+        Ok(parsed_data_points)
+    }
+
+    #[instrument(skip_all, fields(num_bytes = doc_batch.concat_docs.len()))]
+    async fn store_data_points(&mut self, doc_batch: DocBatch) -> Result<(), tonic::Status> {
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch],
+        };
+        self.ingest_service.ingest(ingest_request).await?;
+        Ok(())
+    }
+
+    async fn export_instrumented(
+        &mut self,
+        request: ExportMetricsServiceRequest,
+    ) -> Result<ExportMetricsServiceResponse, Status> {
+        let start = std::time::Instant::now();
+
+        let labels = ["metrics", OTEL_METRICS_INDEX_ID, "grpc", "protobuf"];
+
+        OTLP_SERVICE_METRICS
+            .requests_total
+            .with_label_values(labels)
+            .inc();
+        let (export_res, is_error) = match self.export_inner(request, labels).await {
+            ok @ Ok(_) => (ok, "false"),
+            err @ Err(_) => {
+                OTLP_SERVICE_METRICS
+                    .request_errors_total
+                    .with_label_values(labels)
+                    .inc();
+                (err, "true")
+            }
+        };
+        let elapsed = start.elapsed().as_secs_f64();
+        let labels = [
+            "metrics",
+            OTEL_METRICS_INDEX_ID,
+            "grpc",
+            "protobuf",
+            is_error,
+        ];
+        OTLP_SERVICE_METRICS
+            .request_duration_seconds
+            .with_label_values(labels)
+            .observe(elapsed);
+
+        export_res
+    }
+}
+
+#[async_trait]
+impl MetricsService for OtlpGrpcMetricsService {
+    #[instrument(name = "ingest_metrics", skip_all)]
+    async fn export(
+        &self,
+        request: Request<ExportMetricsServiceRequest>,
+    ) -> Result<Response<ExportMetricsServiceResponse>, Status> {
+        quickwit_proto::set_parent_span_from_request_metadata(request.metadata());
+        let request = request.into_inner();
+        self.clone()
+            .export_instrumented(request)
+            .await
+            .map(Response::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::opentelemetry::proto::common::v1::any_value::Value as OtlpValue;
+    use quickwit_proto::opentelemetry::proto::common::v1::AnyValue as OtlpAnyValue;
+    use quickwit_proto::opentelemetry::proto::metrics::v1::{
+        Gauge, Histogram, HistogramDataPoint, Metric, ResourceMetrics, ScopeMetrics, Sum,
+    };
+    use quickwit_proto::opentelemetry::proto::resource::v1::Resource;
+
+    use super::*;
+
+    fn service_name_attribute(service_name: &str) -> OtlpKeyValue {
+        OtlpKeyValue {
+            key: "service.name".to_string(),
+            value: Some(OtlpAnyValue {
+                value: Some(OtlpValue::StringValue(service_name.to_string())),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_parse_data_points() {
+        let sum_metric = Metric {
+            name: "http.server.requests".to_string(),
+            description: String::new(),
+            unit: "1".to_string(),
+            data: Some(OtlpMetricData::Sum(Sum {
+                data_points: vec![OtlpNumberDataPoint {
+                    time_unix_nano: 1_678_974_011_000_000_001,
+                    value: Some(number_data_point::Value::AsInt(42)),
+                    exemplars: vec![OtlpExemplar {
+                        trace_id: vec![1; 16],
+                        span_id: vec![2; 8],
+                        value: Some(exemplar::Value::AsInt(1)),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                aggregation_temporality: 2,
+                is_monotonic: true,
+            })),
+        };
+        let histogram_metric = Metric {
+            name: "http.server.duration".to_string(),
+            description: "Request duration".to_string(),
+            unit: "ms".to_string(),
+            data: Some(OtlpMetricData::Histogram(Histogram {
+                data_points: vec![HistogramDataPoint {
+                    time_unix_nano: 1_678_974_012_000_000_000,
+                    count: 3,
+                    sum: Some(30.0),
+                    bucket_counts: vec![1, 2, 0],
+                    explicit_bounds: vec![5.0, 15.0],
+                    ..Default::default()
+                }],
+                aggregation_temporality: 1,
+            })),
+        };
+        let request = ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: vec![service_name_attribute("quickwit")],
+                    dropped_attributes_count: 0,
+                }),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![sum_metric, histogram_metric],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+        let data_points = parse_data_points(request).unwrap();
+        assert_eq!(data_points.len(), 2);
+
+        let sum_data_point = &data_points[0];
+        assert_eq!(sum_data_point.service_name, "quickwit");
+        assert_eq!(sum_data_point.metric_name, "http.server.requests");
+        assert_eq!(sum_data_point.metric_type, MetricType::Sum);
+        assert_eq!(sum_data_point.timestamp_secs, 1_678_974_011);
+        assert_eq!(sum_data_point.value, Some(42.0));
+        assert_eq!(
+            sum_data_point.aggregation_temporality.as_deref(),
+            Some("cumulative")
+        );
+        assert_eq!(sum_data_point.is_monotonic, Some(true));
+        assert!(sum_data_point.metric_description.is_none());
+        assert_eq!(
+            sum_data_point.exemplar_trace_ids,
+            [TraceId::new([1; 16]).b64_encode()]
+        );
+        assert_eq!(sum_data_point.exemplars[0].exemplar_value, Some(1.0));
+
+        let histogram_data_point = &data_points[1];
+        assert_eq!(histogram_data_point.metric_type, MetricType::Histogram);
+        assert_eq!(histogram_data_point.count, Some(3));
+        assert_eq!(histogram_data_point.sum, Some(30.0));
+        assert_eq!(histogram_data_point.bucket_counts, [1, 2, 0]);
+        assert_eq!(histogram_data_point.explicit_bounds, [5.0, 15.0]);
+        assert!(histogram_data_point.exemplar_trace_ids.is_empty());
+
+        let data_point_json = serde_json::to_value(histogram_data_point).unwrap();
+        assert_eq!(data_point_json["metric_type"], "histogram");
+        assert_eq!(data_point_json["aggregation_temporality"], "delta");
+    }
+
+    #[test]
+    fn test_parse_data_points_rejects_invalid_exemplar_trace_id() {
+        let gauge_metric = Metric {
+            name: "memory.usage".to_string(),
+            data: Some(OtlpMetricData::Gauge(Gauge {
+                data_points: vec![OtlpNumberDataPoint {
+                    exemplars: vec![OtlpExemplar {
+                        trace_id: vec![1; 4],
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+            })),
+            ..Default::default()
+        };
+        let request = ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: None,
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![gauge_metric],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+        let status = parse_data_points(request).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
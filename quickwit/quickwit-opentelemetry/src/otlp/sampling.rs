@@ -0,0 +1,461 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use quickwit_common::metrics::{new_counter_vec, new_gauge, IntCounterVec, IntGauge};
+use quickwit_config::{TraceSamplingConfig, TraceSamplingRule};
+use quickwit_ingest_api::{DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient};
+use serde_json::Value as JsonValue;
+use tracing::{debug, error};
+
+use super::trace::{Span, OTEL_TRACE_INDEX_ID};
+use super::B64TraceId;
+
+/// Status code of the spans that ended with an error.
+const STATUS_CODE_ERROR: i32 = 2;
+
+/// Decisions are remembered for this many decision windows so that the spans of a trace received
+/// after its decision follow the same fate.
+const DECISION_RETENTION_FACTOR: u32 = 6;
+
+/// Interval at which the sampling decisions of the expired traces are taken.
+const DECISION_INTERVAL: Duration = Duration::from_secs(1);
+
+struct TraceSamplingMetrics {
+    sampled_traces_total: IntCounterVec<2>,
+    sampled_spans_total: IntCounterVec<1>,
+    buffered_spans: IntGauge,
+}
+
+impl Default for TraceSamplingMetrics {
+    fn default() -> Self {
+        Self {
+            sampled_traces_total: new_counter_vec(
+                "sampled_traces_total",
+                "Number of traces for which a sampling decision was taken",
+                "quickwit_otlp",
+                ["decision", "reason"],
+            ),
+            sampled_spans_total: new_counter_vec(
+                "sampled_spans_total",
+                "Number of spans kept or dropped by the trace sampler",
+                "quickwit_otlp",
+                ["decision"],
+            ),
+            buffered_spans: new_gauge(
+                "sampling_buffered_spans",
+                "Number of spans waiting for the sampling decision of their trace",
+                "quickwit_otlp",
+            ),
+        }
+    }
+}
+
+static TRACE_SAMPLING_METRICS: Lazy<TraceSamplingMetrics> =
+    Lazy::new(TraceSamplingMetrics::default);
+
+/// Outcome of the sampling of a trace.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SamplingDecision {
+    /// The trace contains a span with an error status.
+    KeepError,
+    /// The trace contains a span lasting longer than the latency threshold.
+    KeepLatency,
+    /// The trace contains a span matching the rule with the given name.
+    KeepRule(String),
+    Drop,
+}
+
+impl SamplingDecision {
+    pub fn is_keep(&self) -> bool {
+        !matches!(self, SamplingDecision::Drop)
+    }
+
+    fn labels(&self) -> [&str; 2] {
+        match self {
+            SamplingDecision::KeepError => ["keep", "error"],
+            SamplingDecision::KeepLatency => ["keep", "latency"],
+            SamplingDecision::KeepRule(rule_name) => ["keep", rule_name],
+            SamplingDecision::Drop => ["drop", "none"],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BufferedTrace {
+    spans: Vec<Span>,
+}
+
+#[derive(Debug, Default)]
+struct SamplerState {
+    buffered_traces: HashMap<B64TraceId, BufferedTrace>,
+    /// Buffered traces ordered by arrival time of their first span.
+    arrival_queue: VecDeque<(Instant, B64TraceId)>,
+    num_buffered_spans: usize,
+    decisions: HashMap<B64TraceId, bool>,
+    /// Decided traces ordered by decision time.
+    decision_queue: VecDeque<(Instant, B64TraceId)>,
+}
+
+/// Tail-based trace sampler: buffers the spans of each trace for the decision window, then keeps
+/// or drops the whole trace.
+#[derive(Debug, Clone)]
+pub struct TraceSampler {
+    config: Arc<TraceSamplingConfig>,
+    state: Arc<Mutex<SamplerState>>,
+}
+
+impl TraceSampler {
+    pub fn new(config: TraceSamplingConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::default(),
+        }
+    }
+
+    /// Buffers the spans of undecided traces and returns the spans of the traces already kept.
+    pub fn add_spans(&self, spans: impl IntoIterator<Item = Span>, now: Instant) -> Vec<Span> {
+        let mut state_guard = self.state.lock().expect("Lock should not be poisoned.");
+        let state = &mut *state_guard;
+        let mut kept_spans = Vec::new();
+        let mut num_dropped_spans = 0;
+
+        for span in spans {
+            match state.decisions.get(&span.trace_id).copied() {
+                Some(true) => kept_spans.push(span),
+                Some(false) => num_dropped_spans += 1,
+                None => {
+                    let trace_id = span.trace_id.clone();
+                    state.num_buffered_spans += 1;
+
+                    if let Some(buffered_trace) = state.buffered_traces.get_mut(&trace_id) {
+                        buffered_trace.spans.push(span);
+                    } else {
+                        state.arrival_queue.push_back((now, trace_id.clone()));
+                        let buffered_trace = BufferedTrace { spans: vec![span] };
+                        state.buffered_traces.insert(trace_id, buffered_trace);
+                    }
+                }
+            }
+        }
+        TRACE_SAMPLING_METRICS
+            .sampled_spans_total
+            .with_label_values(["keep"])
+            .inc_by(kept_spans.len() as u64);
+        TRACE_SAMPLING_METRICS
+            .sampled_spans_total
+            .with_label_values(["drop"])
+            .inc_by(num_dropped_spans);
+        TRACE_SAMPLING_METRICS
+            .buffered_spans
+            .set(state.num_buffered_spans as i64);
+        kept_spans
+    }
+
+    /// Takes the sampling decision of the traces buffered for longer than the decision window, or
+    /// of the oldest traces when too many spans are buffered, and returns the spans to index.
+    pub fn decide_expired_traces(&self, now: Instant) -> Vec<Span> {
+        let mut state_guard = self.state.lock().expect("Lock should not be poisoned.");
+        let state = &mut *state_guard;
+        let decision_wait = self.config.decision_wait();
+        let mut kept_spans = Vec::new();
+        let mut num_dropped_spans = 0;
+
+        while let Some((arrived_at, _)) = state.arrival_queue.front() {
+            if now.saturating_duration_since(*arrived_at) < decision_wait
+                && state.num_buffered_spans <= self.config.max_buffered_spans
+            {
+                break;
+            }
+            let (_, trace_id) = state
+                .arrival_queue
+                .pop_front()
+                .expect("The arrival queue should not be empty.");
+            let Some(buffered_trace) = state.buffered_traces.remove(&trace_id) else {
+                continue;
+            };
+            state.num_buffered_spans -= buffered_trace.spans.len();
+
+            let decision = self.decide(&buffered_trace.spans);
+            debug!(trace_id=%trace_id.as_str(), decision=?decision, num_spans=buffered_trace.spans.len(), "Sampled trace.");
+            TRACE_SAMPLING_METRICS
+                .sampled_traces_total
+                .with_label_values(decision.labels())
+                .inc();
+
+            if decision.is_keep() {
+                kept_spans.extend(buffered_trace.spans);
+            } else {
+                num_dropped_spans += buffered_trace.spans.len() as u64;
+            }
+            state.decisions.insert(trace_id.clone(), decision.is_keep());
+            state.decision_queue.push_back((now, trace_id));
+        }
+        let decision_retention = decision_wait * DECISION_RETENTION_FACTOR;
+
+        while let Some((decided_at, _)) = state.decision_queue.front() {
+            if now.saturating_duration_since(*decided_at) < decision_retention {
+                break;
+            }
+            let (_, trace_id) = state
+                .decision_queue
+                .pop_front()
+                .expect("The decision queue should not be empty.");
+            state.decisions.remove(&trace_id);
+        }
+        TRACE_SAMPLING_METRICS
+            .sampled_spans_total
+            .with_label_values(["keep"])
+            .inc_by(kept_spans.len() as u64);
+        TRACE_SAMPLING_METRICS
+            .sampled_spans_total
+            .with_label_values(["drop"])
+            .inc_by(num_dropped_spans);
+        TRACE_SAMPLING_METRICS
+            .buffered_spans
+            .set(state.num_buffered_spans as i64);
+        kept_spans
+    }
+
+    fn decide(&self, spans: &[Span]) -> SamplingDecision {
+        let is_error = |span: &Span| {
+            span.span_status
+                .as_ref()
+                .map(|span_status| span_status.code == STATUS_CODE_ERROR)
+                .unwrap_or(false)
+        };
+        if self.config.keep_errors && spans.iter().any(is_error) {
+            return SamplingDecision::KeepError;
+        }
+        if let Some(latency_threshold_millis) = self.config.latency_threshold_millis {
+            let is_slow = |span: &Span| {
+                span.span_duration_millis
+                    .map(|span_duration_millis| span_duration_millis >= latency_threshold_millis)
+                    .unwrap_or(false)
+            };
+            if spans.iter().any(is_slow) {
+                return SamplingDecision::KeepLatency;
+            }
+        }
+        for rule in &self.config.rules {
+            if spans.iter().any(|span| rule_matches(rule, span)) {
+                return SamplingDecision::KeepRule(rule.name.clone());
+            }
+        }
+        SamplingDecision::Drop
+    }
+
+    /// Periodically takes the sampling decisions of the expired traces and ingests the spans of
+    /// the kept traces. The loop stops once the sampler is dropped.
+    pub fn spawn_decision_loop(&self, mut ingest_service: IngestServiceClient) {
+        let config = self.config.clone();
+        let weak_state: Weak<Mutex<SamplerState>> = Arc::downgrade(&self.state);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(DECISION_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(state) = weak_state.upgrade() else {
+                    break;
+                };
+                let sampler = TraceSampler {
+                    config: config.clone(),
+                    state,
+                };
+                let kept_spans = sampler.decide_expired_traces(Instant::now());
+                drop(sampler);
+
+                if kept_spans.is_empty() {
+                    continue;
+                }
+                let mut doc_batch =
+                    DocBatchBuilder::new(OTEL_TRACE_INDEX_ID.to_string()).json_writer();
+                for span in &kept_spans {
+                    if let Err(error) = doc_batch.ingest_doc(span) {
+                        error!(error=?error, "Failed to JSON serialize span.");
+                    }
+                }
+                let ingest_request = IngestRequest {
+                    doc_batches: vec![doc_batch.build()],
+                };
+                if let Err(error) = ingest_service.ingest(ingest_request).await {
+                    error!(error=?error, num_spans=kept_spans.len(), "Failed to ingest sampled spans.");
+                }
+            }
+        });
+    }
+}
+
+fn rule_matches(rule: &TraceSamplingRule, span: &Span) -> bool {
+    if let Some(service_name) = &rule.service_name {
+        if span.service_name != *service_name {
+            return false;
+        }
+    }
+    if let Some(span_name) = &rule.span_name {
+        if span.span_name != *span_name {
+            return false;
+        }
+    }
+    rule.attributes.iter().all(
+        |(key, expected_value)| match span.span_attributes.get(key) {
+            Some(JsonValue::String(value)) => value == expected_value,
+            Some(value) => value.to_string() == *expected_value,
+            None => false,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::num::NonZeroU64;
+
+    use super::*;
+    use crate::otlp::{SpanStatus, TraceId};
+
+    fn make_span(trace_byte: u8, span_name: &str) -> Span {
+        Span {
+            trace_id: TraceId::new([trace_byte; 16]).b64_encode(),
+            trace_state: None,
+            service_name: "quickwit".to_string(),
+            resource_attributes: HashMap::new(),
+            resource_dropped_attributes_count: 0,
+            scope_name: None,
+            scope_version: None,
+            scope_attributes: HashMap::new(),
+            scope_dropped_attributes_count: 0,
+            span_id: "AQEBAQEBAQE=".to_string(),
+            span_kind: 1,
+            span_name: span_name.to_string(),
+            span_fingerprint: None,
+            span_start_timestamp_nanos: 0,
+            span_end_timestamp_nanos: 0,
+            span_start_timestamp_secs: None,
+            span_duration_millis: Some(10),
+            span_attributes: HashMap::new(),
+            span_dropped_attributes_count: 0,
+            span_dropped_events_count: 0,
+            span_dropped_links_count: 0,
+            span_status: None,
+            parent_span_id: None,
+            events: Vec::new(),
+            event_names: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    fn make_sampler() -> TraceSampler {
+        TraceSampler::new(TraceSamplingConfig {
+            decision_wait_secs: NonZeroU64::new(10).unwrap(),
+            max_buffered_spans: 100,
+            keep_errors: true,
+            latency_threshold_millis: Some(1_000),
+            rules: vec![TraceSamplingRule {
+                name: "checkout".to_string(),
+                service_name: None,
+                span_name: None,
+                attributes: BTreeMap::from([("http.route".to_string(), "/cart".to_string())]),
+            }],
+        })
+    }
+
+    #[test]
+    fn test_trace_sampler_decide() {
+        let sampler = make_sampler();
+        assert_eq!(
+            sampler.decide(&[make_span(1, "root"), make_span(1, "child")]),
+            SamplingDecision::Drop
+        );
+        let mut error_span = make_span(1, "child");
+        error_span.span_status = Some(SpanStatus {
+            code: STATUS_CODE_ERROR,
+            message: None,
+        });
+        assert_eq!(
+            sampler.decide(&[make_span(1, "root"), error_span]),
+            SamplingDecision::KeepError
+        );
+        let mut slow_span = make_span(1, "root");
+        slow_span.span_duration_millis = Some(1_500);
+        assert_eq!(sampler.decide(&[slow_span]), SamplingDecision::KeepLatency);
+
+        let mut matching_span = make_span(1, "root");
+        matching_span.span_attributes.insert(
+            "http.route".to_string(),
+            JsonValue::String("/cart".to_string()),
+        );
+        assert_eq!(
+            sampler.decide(&[matching_span]),
+            SamplingDecision::KeepRule("checkout".to_string())
+        );
+    }
+
+    #[test]
+    fn test_trace_sampler_buffers_spans_until_decision() {
+        let sampler = make_sampler();
+        let start = Instant::now();
+
+        let mut slow_span = make_span(1, "root");
+        slow_span.span_duration_millis = Some(2_000);
+        let kept_spans = sampler.add_spans([slow_span, make_span(2, "root")], start);
+        assert!(kept_spans.is_empty());
+
+        let kept_spans = sampler.decide_expired_traces(start + Duration::from_secs(5));
+        assert!(kept_spans.is_empty());
+
+        let kept_spans = sampler.add_spans([make_span(1, "child")], start + Duration::from_secs(5));
+        assert!(kept_spans.is_empty());
+
+        let kept_spans = sampler.decide_expired_traces(start + Duration::from_secs(10));
+        assert_eq!(kept_spans.len(), 2);
+        assert!(kept_spans
+            .iter()
+            .all(|span| span.trace_id == TraceId::new([1; 16]).b64_encode()));
+
+        // Late spans follow the decision of their trace.
+        let late_spans = [make_span(1, "late"), make_span(2, "late")];
+        let kept_spans = sampler.add_spans(late_spans, start + Duration::from_secs(11));
+        assert_eq!(kept_spans.len(), 1);
+        assert_eq!(kept_spans[0].span_name, "late");
+        assert_eq!(kept_spans[0].trace_id, TraceId::new([1; 16]).b64_encode());
+        let state = sampler.state.lock().unwrap();
+        assert_eq!(state.num_buffered_spans, 0);
+        assert!(state.buffered_traces.is_empty());
+    }
+
+    #[test]
+    fn test_trace_sampler_decides_early_when_buffer_is_full() {
+        let sampler = make_sampler();
+        let start = Instant::now();
+
+        for trace_byte in 0..=100 {
+            let mut span = make_span(trace_byte, "root");
+            span.span_duration_millis = Some(5_000);
+            sampler.add_spans([span], start);
+        }
+        let kept_spans = sampler.decide_expired_traces(start);
+        assert_eq!(kept_spans.len(), 1);
+        assert_eq!(kept_spans[0].trace_id, TraceId::new([0; 16]).b64_encode());
+    }
+}
@@ -20,9 +20,11 @@
 use std::cmp::{Ord, Ordering, PartialEq, PartialOrd};
 use std::collections::{BTreeSet, HashMap};
 use std::str::FromStr;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use base64::prelude::{Engine, BASE64_STANDARD};
+use quickwit_config::TraceSamplingConfig;
 use quickwit_ingest_api::{
     DocBatch, DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient,
 };
@@ -38,6 +40,7 @@ use tracing::field::Empty;
 use tracing::{error, instrument, warn, Span as RuntimeSpan};
 
 use crate::otlp::metrics::OTLP_SERVICE_METRICS;
+use crate::otlp::sampling::TraceSampler;
 use crate::otlp::{extract_attributes, B64TraceId, TraceId};
 
 pub const OTEL_TRACE_INDEX_ID: &str = "otel-trace-v0";
@@ -409,12 +412,30 @@ struct ParsedSpans {
 #[derive(Debug, Clone)]
 pub struct OtlpGrpcTraceService {
     ingest_service: IngestServiceClient,
+    sampler_opt: Option<TraceSampler>,
 }
 
 impl OtlpGrpcTraceService {
     // TODO: remove and use registry
     pub fn new(ingest_service: IngestServiceClient) -> Self {
-        Self { ingest_service }
+        Self {
+            ingest_service,
+            sampler_opt: None,
+        }
+    }
+
+    /// Creates a trace service that indexes only the traces kept by a tail-based sampler. Must
+    /// be called from within a Tokio runtime.
+    pub fn with_sampling(
+        ingest_service: IngestServiceClient,
+        sampling_config: TraceSamplingConfig,
+    ) -> Self {
+        let sampler = TraceSampler::new(sampling_config);
+        sampler.spawn_decision_loop(ingest_service.clone());
+        Self {
+            ingest_service,
+            sampler_opt: Some(sampler),
+        }
     }
 
     async fn export_inner(
@@ -428,8 +449,9 @@ impl OtlpGrpcTraceService {
             num_parse_errors,
             error_message,
         } = tokio::task::spawn_blocking({
+            let sampler_opt = self.sampler_opt.clone();
             let parent_span = RuntimeSpan::current();
-            || Self::parse_spans(request, parent_span)
+            || Self::parse_spans(request, sampler_opt, parent_span)
         })
         .await
         .map_err(|join_error| {
@@ -440,7 +462,10 @@ impl OtlpGrpcTraceService {
             return Err(tonic::Status::internal(error_message));
         }
         let num_bytes = doc_batch.concat_docs.len() as u64;
-        self.store_spans(doc_batch).await?;
+
+        if doc_batch.num_docs() > 0 {
+            self.store_spans(doc_batch).await?;
+        }
 
         OTLP_SERVICE_METRICS
             .ingested_spans_total
@@ -464,6 +489,7 @@ impl OtlpGrpcTraceService {
     #[instrument(skip_all, parent = parent_span, fields(num_spans = Empty, num_bytes = Empty, num_parse_errors = Empty))]
     fn parse_spans(
         request: ExportTraceServiceRequest,
+        sampler_opt: Option<TraceSampler>,
         parent_span: RuntimeSpan,
     ) -> Result<ParsedSpans, Status> {
         let mut spans = BTreeSet::new();
@@ -601,9 +627,17 @@ impl OtlpGrpcTraceService {
                 }
             }
         }
+        let spans: Vec<Span> = spans.into_iter().map(|span| span.0).collect();
+        // The spans of the traces still waiting for their sampling decision are ingested later by
+        // the sampler.
+        let spans = if let Some(sampler) = sampler_opt {
+            sampler.add_spans(spans, Instant::now())
+        } else {
+            spans
+        };
         let mut doc_batch = DocBatchBuilder::new(OTEL_TRACE_INDEX_ID.to_string()).json_writer();
         for span in spans {
-            if let Err(error) = doc_batch.ingest_doc(&span.0) {
+            if let Err(error) = doc_batch.ingest_doc(&span) {
                 error!(error=?error, "Failed to JSON serialize span.");
                 error_message = format!("Failed to JSON serialize span: {error:?}");
                 num_parse_errors += 1;
@@ -673,6 +707,7 @@ impl TraceService for OtlpGrpcTraceService {
         &self,
         request: Request<ExportTraceServiceRequest>,
     ) -> Result<Response<ExportTraceServiceResponse>, Status> {
+        quickwit_proto::set_parent_span_from_request_metadata(request.metadata());
         let request = request.into_inner();
         self.clone()
             .export_instrumented(request)
@@ -41,6 +41,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "#[serde(skip_serializing_if = \"Option::is_none\")]",
         )
         .type_attribute("OutputFormat", "#[serde(rename_all = \"snake_case\")]")
+        .type_attribute("FieldMaskAction", "#[serde(rename_all = \"snake_case\")]")
         .out_dir("src/")
         .compile_with_config(prost_config, &protos, &["protos/quickwit"])?;
 
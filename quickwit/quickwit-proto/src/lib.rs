@@ -55,11 +55,11 @@ pub mod opentelemetry {
                     include!("opentelemetry.proto.collector.logs.v1.rs");
                 }
             }
-            // pub mod metrics {
-            //     pub mod v1 {
-            //         include!("opentelemetry.proto.collector.metrics.v1.rs");
-            //     }
-            // }
+            pub mod metrics {
+                pub mod v1 {
+                    include!("opentelemetry.proto.collector.metrics.v1.rs");
+                }
+            }
             pub mod trace {
                 pub mod v1 {
                     include!("opentelemetry.proto.collector.trace.v1.rs");
@@ -76,14 +76,11 @@ pub mod opentelemetry {
                 include!("opentelemetry.proto.logs.v1.rs");
             }
         }
-        // pub mod metrics {
-        //     pub mod experimental {
-        //         include!("opentelemetry.proto.metrics.experimental.rs");
-        //     }
-        //     pub mod v1 {
-        //         tonic::include_proto!("opentelemetry.proto.metrics.v1");
-        //     }
-        // }
+        pub mod metrics {
+            pub mod v1 {
+                include!("opentelemetry.proto.metrics.v1.rs");
+            }
+        }
         pub mod resource {
             pub mod v1 {
                 include!("opentelemetry.proto.resource.v1.rs");
@@ -102,6 +99,7 @@ extern crate serde;
 
 use std::convert::Infallible;
 use std::fmt;
+use std::sync::RwLock;
 
 use ::opentelemetry::global;
 pub use quickwit::*;
@@ -109,6 +107,8 @@ use quickwit_metastore_api::DeleteQuery;
 pub use tonic;
 use tonic::Status;
 use tonic::codegen::http;
+use tonic::metadata::errors::InvalidMetadataValue;
+use tonic::metadata::{Ascii, MetadataValue};
 use tonic::service::Interceptor;
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -122,10 +122,12 @@ use ::opentelemetry::propagation::Extractor;
 #[derive(Clone, Copy)]
 pub enum ServiceErrorCode {
     BadRequest,
+    Forbidden,
     Internal,
     MethodNotAllowed,
     NotFound,
     RateLimited,
+    Unauthenticated,
     Unavailable,
     UnsupportedMediaType,
 }
@@ -134,10 +136,12 @@ impl ServiceErrorCode {
     pub fn to_grpc_status_code(self) -> tonic::Code {
         match self {
             ServiceErrorCode::BadRequest => tonic::Code::InvalidArgument,
+            ServiceErrorCode::Forbidden => tonic::Code::PermissionDenied,
             ServiceErrorCode::Internal => tonic::Code::Internal,
             ServiceErrorCode::MethodNotAllowed => tonic::Code::InvalidArgument,
             ServiceErrorCode::NotFound => tonic::Code::NotFound,
             ServiceErrorCode::RateLimited => tonic::Code::ResourceExhausted,
+            ServiceErrorCode::Unauthenticated => tonic::Code::Unauthenticated,
             ServiceErrorCode::Unavailable => tonic::Code::Unavailable,
             ServiceErrorCode::UnsupportedMediaType => tonic::Code::InvalidArgument,
         }
@@ -145,10 +149,12 @@ impl ServiceErrorCode {
     pub fn to_http_status_code(self) -> http::StatusCode {
         match self {
             ServiceErrorCode::BadRequest => http::StatusCode::BAD_REQUEST,
+            ServiceErrorCode::Forbidden => http::StatusCode::FORBIDDEN,
             ServiceErrorCode::Internal => http::StatusCode::INTERNAL_SERVER_ERROR,
             ServiceErrorCode::MethodNotAllowed => http::StatusCode::METHOD_NOT_ALLOWED,
             ServiceErrorCode::NotFound => http::StatusCode::NOT_FOUND,
             ServiceErrorCode::RateLimited => http::StatusCode::TOO_MANY_REQUESTS,
+            ServiceErrorCode::Unauthenticated => http::StatusCode::UNAUTHORIZED,
             ServiceErrorCode::Unavailable => http::StatusCode::SERVICE_UNAVAILABLE,
             ServiceErrorCode::UnsupportedMediaType => http::StatusCode::UNSUPPORTED_MEDIA_TYPE,
         }
@@ -191,6 +197,25 @@ impl From<SearchStreamRequest> for SearchRequest {
             sort_by_field: None,
             sort_order: None,
             aggregation_request: None,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         }
     }
 }
@@ -208,6 +233,17 @@ impl From<DeleteQuery> for SearchRequest {
     }
 }
 
+impl SplitIdAndFooterOffsets {
+    /// Returns the path of the split file relative to the index URI.
+    pub fn split_file(&self) -> String {
+        if self.split_file.is_empty() {
+            format!("{}.split", self.split_id)
+        } else {
+            self.split_file.clone()
+        }
+    }
+}
+
 impl fmt::Display for SplitSearchError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "({}, split_id: {})", self.error, self.split_id)
@@ -247,6 +283,19 @@ impl<'a> Extractor for MutMetadataMap<'a> {
     }
 }
 
+/// Key of the gRPC metadata carrying the secret the nodes of a cluster present to each other.
+pub const CLUSTER_SECRET_METADATA_KEY: &str = "x-quickwit-cluster-secret";
+
+static CLUSTER_SECRET: RwLock<Option<MetadataValue<Ascii>>> = RwLock::new(None);
+
+/// Sets the cluster secret presented by the gRPC clients of the node to the other nodes of the
+/// cluster. Must be called before any gRPC client is created.
+pub fn set_cluster_secret(cluster_secret: &str) -> Result<(), InvalidMetadataValue> {
+    let cluster_secret = MetadataValue::try_from(cluster_secret)?;
+    *CLUSTER_SECRET.write().unwrap() = Some(cluster_secret);
+    Ok(())
+}
+
 /// [`tonic::service::interceptor::Interceptor`] which injects the span context into [`tonic::metadata::MetadataMap`].
 #[derive(Clone, Debug)]
 pub struct SpanContextInterceptor;
@@ -263,6 +312,24 @@ impl Interceptor for SpanContextInterceptor {
     }
 }
 
+/// [`tonic::service::interceptor::Interceptor`] of the clients of the cluster-internal gRPC
+/// services, which injects the span context and the cluster secret of the node, if any, into
+/// [`tonic::metadata::MetadataMap`].
+#[derive(Clone, Debug)]
+pub struct ClusterInterceptor;
+
+impl Interceptor for ClusterInterceptor {
+    fn call(&mut self, request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        let mut request = SpanContextInterceptor.call(request)?;
+        if let Some(cluster_secret) = CLUSTER_SECRET.read().unwrap().clone() {
+            request
+                .metadata_mut()
+                .insert(CLUSTER_SECRET_METADATA_KEY, cluster_secret);
+        }
+        Ok(request)
+    }
+}
+
 
 /// `MetadataMap` extracts OpenTelemetry
 /// tracing keys from request's headers.
@@ -40,6 +40,85 @@ pub struct SearchRequest {
     /// Fields to extract snippet on
     #[prost(string, repeated, tag = "12")]
     pub snippet_fields: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// json serialized geo filter
+    #[prost(string, optional, tag = "13")]
+    pub geo_filter: ::core::option::Option<::prost::alloc::string::String>,
+    /// json serialized nested query
+    #[prost(string, optional, tag = "14")]
+    pub nested_query: ::core::option::Option<::prost::alloc::string::String>,
+    /// json serialized runtime fields, mapping names to expressions
+    #[prost(string, optional, tag = "15")]
+    pub runtime_fields: ::core::option::Option<::prost::alloc::string::String>,
+    /// Filter expression, which may refer to the runtime fields
+    #[prost(string, optional, tag = "16")]
+    pub runtime_filter: ::core::option::Option<::prost::alloc::string::String>,
+    /// Only hits ranked strictly after this partial hit are returned. Passing the
+    /// partial hit of the last hit of a page (with the same query and sort) returns
+    /// the next page, without the cost of a large start_offset.
+    #[prost(message, optional, tag = "17")]
+    pub search_after: ::core::option::Option<PartialHit>,
+    /// If set, a scroll context pinning the searched splits is kept on the node for
+    /// this duration, and its ID is returned in the response.
+    #[prost(uint32, optional, tag = "18")]
+    pub scroll_ttl_secs: ::core::option::Option<u32>,
+    /// Maximum number of characters of the snippets. Defaults to 150.
+    #[prost(uint32, optional, tag = "19")]
+    pub snippet_max_num_chars: ::core::option::Option<u32>,
+    /// Tag inserted before the highlighted terms of the snippets. Defaults to `<b>`.
+    #[prost(string, optional, tag = "20")]
+    pub snippet_pre_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// Tag inserted after the highlighted terms of the snippets. Defaults to `</b>`.
+    #[prost(string, optional, tag = "21")]
+    pub snippet_post_tag: ::core::option::Option<::prost::alloc::string::String>,
+    /// json serialized kNN query, retrieving the nearest neighbors of a query vector
+    #[prost(string, optional, tag = "22")]
+    pub knn_query: ::core::option::Option<::prost::alloc::string::String>,
+    /// json serialized hybrid ranking, fusing the lexical and the kNN rankings
+    #[prost(string, optional, tag = "23")]
+    pub hybrid_ranking: ::core::option::Option<::prost::alloc::string::String>,
+    /// If set, the search also runs on the remote clusters configured on the searcher
+    #[prost(bool, tag = "24")]
+    pub cross_cluster: bool,
+    /// If set, the leaf searches still running after this many milliseconds are
+    /// cancelled, and the hits of the splits searched so far are returned.
+    #[prost(uint64, optional, tag = "25")]
+    pub timeout_ms: ::core::option::Option<u64>,
+    /// If set, only the number of hits is computed: no hit is fetched and no
+    /// aggregation is computed.
+    #[prost(bool, tag = "26")]
+    pub count_only: bool,
+    /// json serialized list of sort fields, sorting the hits by several fields or
+    /// by geo distance. Cannot be set along with `sort_by_field`.
+    #[prost(string, optional, tag = "27")]
+    pub sort_fields: ::core::option::Option<::prost::alloc::string::String>,
+    /// json serialized collapse options, only returning the top hit of each distinct
+    /// value of a fast field.
+    #[prost(string, optional, tag = "28")]
+    pub collapse: ::core::option::Option<::prost::alloc::string::String>,
+    /// If set, the custom metadata of the splits of the hits are returned in the
+    /// response.
+    #[prost(bool, tag = "29")]
+    pub include_split_metadata: bool,
+    /// Fields masked in the returned documents, set from the roles of the caller
+    /// of the search.
+    #[prost(message, repeated, tag = "30")]
+    pub field_masks: ::prost::alloc::vec::Vec<FieldMask>,
+    /// If set, the search runs on the splits pinned by this search view instead of
+    /// the splits currently published.
+    #[prost(string, optional, tag = "31")]
+    pub view_id: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Masks a field of the documents returned by a search.
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FieldMask {
+    /// Path of the field. The fields of objects are separated by dots, e.g.
+    /// `user.email`.
+    #[prost(string, tag = "1")]
+    pub field: ::prost::alloc::string::String,
+    #[prost(enumeration = "FieldMaskAction", tag = "2")]
+    pub action: i32,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -61,6 +140,80 @@ pub struct SearchResponse {
     /// Serialized aggregation response
     #[prost(string, optional, tag = "5")]
     pub aggregation: ::core::option::Option<::prost::alloc::string::String>,
+    /// Scroll ID, to pass to the scroll API to fetch the next page.
+    #[prost(string, optional, tag = "6")]
+    pub scroll_id: ::core::option::Option<::prost::alloc::string::String>,
+    /// Whether some leaf searches were cancelled because the search timed out.
+    #[prost(bool, tag = "7")]
+    pub timed_out: bool,
+    /// The splits that could not be searched, e.g. because the search timed out.
+    #[prost(message, repeated, tag = "8")]
+    pub failed_splits: ::prost::alloc::vec::Vec<SplitSearchError>,
+    /// Custom metadata of the splits of the hits, keyed by split ID and json
+    /// serialized. Only set if `include_split_metadata` is set in the request.
+    #[prost(map = "string, string", tag = "9")]
+    pub split_metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchHitsChunk {
+    /// Number of hits matching the query on the splits of the leaf that emitted the chunk.
+    #[prost(uint64, tag = "1")]
+    pub num_hits: u64,
+    /// Hits of the leaf, sorted.
+    #[prost(message, repeated, tag = "2")]
+    pub hits: ::prost::alloc::vec::Vec<Hit>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LiveTailRequest {
+    /// Index ID or single-index alias to tail.
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    /// Query filtering the streamed documents. Defaults to all the documents.
+    #[prost(string, optional, tag = "2")]
+    pub query: ::core::option::Option<::prost::alloc::string::String>,
+    /// Streams the documents whose timestamp is greater than or equal to this value,
+    /// in seconds since the Unix epoch. Defaults to the time of the call.
+    #[prost(int64, optional, tag = "3")]
+    pub start_timestamp: ::core::option::Option<i64>,
+    /// Maximum interval between two searches of the index, in milliseconds.
+    /// The index is also searched as soon as splits get published on nodes
+    /// running the metastore service.
+    #[prost(uint64, optional, tag = "4")]
+    pub poll_interval_ms: ::core::option::Option<u64>,
+    /// Maximum number of hits per response message.
+    #[prost(uint32, optional, tag = "5")]
+    pub max_hits_per_message: ::core::option::Option<u32>,
+    /// Maximum number of response messages buffered on the server while the
+    /// client is not reading them. The index is not searched while the buffer is full.
+    #[prost(uint32, optional, tag = "6")]
+    pub max_buffered_messages: ::core::option::Option<u32>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LiveTailResponse {
+    /// Hits matching the query, in timestamp order.
+    #[prost(message, repeated, tag = "1")]
+    pub hits: ::prost::alloc::vec::Vec<Hit>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ScrollRequest {
+    /// Scroll ID returned by the previous search or scroll call.
+    #[prost(string, tag = "1")]
+    pub scroll_id: ::prost::alloc::string::String,
+    /// If set, the scroll context is kept for this duration instead of the
+    /// duration of the previous call.
+    #[prost(uint32, optional, tag = "2")]
+    pub scroll_ttl_secs: ::core::option::Option<u32>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -110,6 +263,9 @@ pub struct SplitIdAndFooterOffsets {
     /// The offset of the end of the footer in split bundle. The footer contains the file bundle metadata and the hotcache.
     #[prost(uint64, tag = "3")]
     pub split_footer_end: u64,
+    /// Path of the split file relative to the index_uri. Defaults to `{split_id}.split` when empty.
+    #[prost(string, tag = "4")]
+    pub split_file: ::prost::alloc::string::String,
 }
 /// / Hits returned by a FetchDocRequest.
 /// /
@@ -181,6 +337,18 @@ pub struct PartialHit {
     /// The DocId identifies a unique document at the scale of a tantivy segment.
     #[prost(uint32, tag = "4")]
     pub doc_id: u32,
+    /// Values of the sort fields following the first one, if the hits are sorted
+    /// by several fields. They are mapped like `sorting_field_value` and break its
+    /// ties before the split_id, the segment_ord, and the doc id.
+    #[prost(uint64, repeated, tag = "5")]
+    pub secondary_sorting_field_values: ::prost::alloc::vec::Vec<u64>,
+    /// JSON serialized value of the collapse field of the hit, if the hits are collapsed.
+    #[prost(string, optional, tag = "6")]
+    pub collapse_value: ::core::option::Option<::prost::alloc::string::String>,
+    /// Number of hits sharing the collapse value of the hit, including itself. Only set
+    /// if the count of the collapsed hits is requested.
+    #[prost(uint64, optional, tag = "7")]
+    pub num_collapsed_hits: ::core::option::Option<u64>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -263,6 +431,13 @@ pub struct ListTermsRequest {
     pub start_key: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
     #[prost(bytes = "vec", optional, tag = "8")]
     pub end_key: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// If set, only the terms of the documents matching this query are returned.
+    #[prost(string, optional, tag = "9")]
+    pub query: ::core::option::Option<::prost::alloc::string::String>,
+    /// If set, the terms are sorted by decreasing number of documents instead of
+    /// by term.
+    #[prost(bool, tag = "10")]
+    pub sort_by_doc_count: bool,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -281,6 +456,9 @@ pub struct ListTermsResponse {
     /// The searcherrors that occurred formatted as string.
     #[prost(string, repeated, tag = "4")]
     pub errors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Number of documents of each term, in the same order as `terms`.
+    #[prost(uint64, repeated, tag = "5")]
+    pub doc_counts: ::prost::alloc::vec::Vec<u64>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -297,6 +475,9 @@ pub struct LeafListTermsRequest {
     /// split files.
     #[prost(string, tag = "3")]
     pub index_uri: ::prost::alloc::string::String,
+    /// json serialized doc mapper, set if the list terms request has a query.
+    #[prost(string, optional, tag = "4")]
+    pub doc_mapper: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -315,6 +496,78 @@ pub struct LeafListTermsResponse {
     /// num_attempted_splits = num_successful_splits + num_failed_splits.
     #[prost(uint64, tag = "4")]
     pub num_attempted_splits: u64,
+    /// Number of documents of each term, in the same order as `terms`.
+    #[prost(uint64, repeated, tag = "5")]
+    pub doc_counts: ::prost::alloc::vec::Vec<u64>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WarmupRequest {
+    /// Index ID
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    /// IDs of the splits to warm up. If empty, the splits overlapping the time range are warmed up.
+    #[prost(string, repeated, tag = "2")]
+    pub split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// The time range is expressed in seconds and is half-open, \[start, end).
+    #[prost(int64, optional, tag = "3")]
+    pub start_timestamp: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag = "4")]
+    pub end_timestamp: ::core::option::Option<i64>,
+    /// Fast fields to warm up, in addition to the timestamp field.
+    #[prost(string, repeated, tag = "5")]
+    pub fast_field_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Fields whose term dictionary is warmed up.
+    #[prost(string, repeated, tag = "6")]
+    pub term_dict_field_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Maximum number of bytes each searcher downloads to warm up its splits.
+    #[prost(uint64, optional, tag = "7")]
+    pub max_num_bytes: ::core::option::Option<u64>,
+    /// Maximum download rate of each searcher, in bytes per second.
+    #[prost(uint64, optional, tag = "8")]
+    pub max_num_bytes_per_sec: ::core::option::Option<u64>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LeafWarmupRequest {
+    /// Index split ids to warm up.
+    #[prost(message, repeated, tag = "1")]
+    pub split_offsets: ::prost::alloc::vec::Vec<SplitIdAndFooterOffsets>,
+    /// Index URI. The index URI defines the location of the storage that contains the
+    /// split files.
+    #[prost(string, tag = "2")]
+    pub index_uri: ::prost::alloc::string::String,
+    /// Fast fields to warm up.
+    #[prost(string, repeated, tag = "3")]
+    pub fast_field_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Fields whose term dictionary is warmed up.
+    #[prost(string, repeated, tag = "4")]
+    pub term_dict_field_names: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Maximum number of bytes the searcher downloads to warm up the splits.
+    #[prost(uint64, optional, tag = "5")]
+    pub max_num_bytes: ::core::option::Option<u64>,
+    /// Maximum download rate of the searcher, in bytes per second.
+    #[prost(uint64, optional, tag = "6")]
+    pub max_num_bytes_per_sec: ::core::option::Option<u64>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WarmupResponse {
+    /// Number of splits warmed up.
+    #[prost(uint64, tag = "1")]
+    pub num_warmed_splits: u64,
+    /// Number of splits left cold because the download budget was exhausted.
+    #[prost(uint64, tag = "2")]
+    pub num_skipped_splits: u64,
+    /// Number of bytes downloaded to warm up the splits.
+    #[prost(uint64, tag = "3")]
+    pub num_downloaded_bytes: u64,
+    /// The list of splits that failed to warm up.
+    #[prost(message, repeated, tag = "4")]
+    pub failed_splits: ::prost::alloc::vec::Vec<SplitSearchError>,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -379,6 +632,36 @@ pub struct LeafSearchStreamResponse {
     pub split_id: ::prost::alloc::string::String,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum FieldMaskAction {
+    /// The field is removed from the documents.
+    Exclude = 0,
+    /// The value of the field is replaced with its hex-encoded SHA-256 hash.
+    Hash = 1,
+}
+impl FieldMaskAction {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            FieldMaskAction::Exclude => "EXCLUDE",
+            FieldMaskAction::Hash => "HASH",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "EXCLUDE" => Some(Self::Exclude),
+            "HASH" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum SortOrder {
@@ -655,6 +938,103 @@ pub mod search_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Scroll API.
+        /// Returns the next page of hits of a search started with `scroll_ttl_secs`,
+        /// searching the splits pinned when the search started.
+        pub async fn scroll(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ScrollRequest>,
+        ) -> Result<tonic::Response<super::SearchResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.SearchService/Scroll",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Root search API streaming the hits.
+        /// The hits of each leaf are fetched and emitted as soon as the leaf responds,
+        /// instead of being merged with the hits of the other leaves. The hits are
+        /// sorted within a chunk, but not across chunks.
+        pub async fn root_search_hits_stream(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchRequest>,
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::SearchHitsChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.SearchService/RootSearchHitsStream",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// Live tail API.
+        /// Keeps searching the index for the documents matching the query as new splits
+        /// get published, and streams them in timestamp order until the client cancels
+        /// the call.
+        pub async fn live_tail(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LiveTailRequest>,
+        ) -> Result<
+            tonic::Response<tonic::codec::Streaming<super::LiveTailResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.SearchService/LiveTail",
+            );
+            self.inner.server_streaming(request.into_request(), path, codec).await
+        }
+        /// Perform a leaf warm-up on a given set of splits.
+        ///
+        /// The node downloads the data of the splits needed by the searches into its
+        /// caches, until it has downloaded `max_num_bytes`.
+        pub async fn leaf_warmup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LeafWarmupRequest>,
+        ) -> Result<tonic::Response<super::WarmupResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit.SearchService/LeafWarmup",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -720,6 +1100,49 @@ pub mod search_service_server {
             &self,
             request: tonic::Request<super::LeafListTermsRequest>,
         ) -> Result<tonic::Response<super::LeafListTermsResponse>, tonic::Status>;
+        /// Scroll API.
+        /// Returns the next page of hits of a search started with `scroll_ttl_secs`,
+        /// searching the splits pinned when the search started.
+        async fn scroll(
+            &self,
+            request: tonic::Request<super::ScrollRequest>,
+        ) -> Result<tonic::Response<super::SearchResponse>, tonic::Status>;
+        /// Server streaming response type for the RootSearchHitsStream method.
+        type RootSearchHitsStreamStream: futures_core::Stream<
+                Item = Result<super::SearchHitsChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Root search API streaming the hits.
+        /// The hits of each leaf are fetched and emitted as soon as the leaf responds,
+        /// instead of being merged with the hits of the other leaves. The hits are
+        /// sorted within a chunk, but not across chunks.
+        async fn root_search_hits_stream(
+            &self,
+            request: tonic::Request<super::SearchRequest>,
+        ) -> Result<tonic::Response<Self::RootSearchHitsStreamStream>, tonic::Status>;
+        /// Server streaming response type for the LiveTail method.
+        type LiveTailStream: futures_core::Stream<
+                Item = Result<super::LiveTailResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Live tail API.
+        /// Keeps searching the index for the documents matching the query as new splits
+        /// get published, and streams them in timestamp order until the client cancels
+        /// the call.
+        async fn live_tail(
+            &self,
+            request: tonic::Request<super::LiveTailRequest>,
+        ) -> Result<tonic::Response<Self::LiveTailStream>, tonic::Status>;
+        /// Perform a leaf warm-up on a given set of splits.
+        ///
+        /// The node downloads the data of the splits needed by the searches into its
+        /// caches, until it has downloaded `max_num_bytes`.
+        async fn leaf_warmup(
+            &self,
+            request: tonic::Request<super::LeafWarmupRequest>,
+        ) -> Result<tonic::Response<super::WarmupResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct SearchServiceServer<T: SearchService> {
@@ -1016,6 +1439,164 @@ pub mod search_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/quickwit.SearchService/Scroll" => {
+                    #[allow(non_camel_case_types)]
+                    struct ScrollSvc<T: SearchService>(pub Arc<T>);
+                    impl<
+                        T: SearchService,
+                    > tonic::server::UnaryService<super::ScrollRequest>
+                    for ScrollSvc<T> {
+                        type Response = super::SearchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ScrollRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).scroll(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ScrollSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.SearchService/RootSearchHitsStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct RootSearchHitsStreamSvc<T: SearchService>(pub Arc<T>);
+                    impl<
+                        T: SearchService,
+                    > tonic::server::ServerStreamingService<super::SearchRequest>
+                    for RootSearchHitsStreamSvc<T> {
+                        type Response = super::SearchHitsChunk;
+                        type ResponseStream = T::RootSearchHitsStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).root_search_hits_stream(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RootSearchHitsStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.SearchService/LiveTail" => {
+                    #[allow(non_camel_case_types)]
+                    struct LiveTailSvc<T: SearchService>(pub Arc<T>);
+                    impl<
+                        T: SearchService,
+                    > tonic::server::ServerStreamingService<super::LiveTailRequest>
+                    for LiveTailSvc<T> {
+                        type Response = super::LiveTailResponse;
+                        type ResponseStream = T::LiveTailStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LiveTailRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).live_tail(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = LiveTailSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit.SearchService/LeafWarmup" => {
+                    #[allow(non_camel_case_types)]
+                    struct LeafWarmupSvc<T: SearchService>(pub Arc<T>);
+                    impl<
+                        T: SearchService,
+                    > tonic::server::UnaryService<super::LeafWarmupRequest>
+                    for LeafWarmupSvc<T> {
+                        type Response = super::WarmupResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LeafWarmupRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).leaf_warmup(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = LeafWarmupSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -4,6 +4,14 @@
 pub struct ApplyIndexingPlanRequest {
     #[prost(message, repeated, tag = "1")]
     pub indexing_tasks: ::prost::alloc::vec::Vec<IndexingTask>,
+    /// / Indexes and sources whose splits the node merges, whichever node indexed them.
+    /// / Only assigned to the nodes running the merger service.
+    #[prost(message, repeated, tag = "2")]
+    pub merge_tasks: ::prost::alloc::vec::Vec<IndexingTask>,
+    /// / Whether the splits of the indexing tasks are merged by merger nodes instead of
+    /// / the node running the indexing tasks.
+    #[prost(bool, tag = "3")]
+    pub offload_merges: bool,
 }
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
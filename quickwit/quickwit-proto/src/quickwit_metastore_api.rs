@@ -52,6 +52,17 @@ pub struct IndexMetadataResponse {
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexConfigRequest {
+    #[prost(string, tag = "1")]
+    pub index_config_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexConfigResponse {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ListAllSplitsRequest {
     #[prost(string, tag = "1")]
     pub index_id: ::prost::alloc::string::String,
@@ -106,6 +117,24 @@ pub struct MarkSplitsForDeletionRequest {
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ArchiveSplitsRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RehydrateSplitsRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "2")]
+    pub split_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteSplitsRequest {
     #[prost(string, tag = "2")]
     pub index_id: ::prost::alloc::string::String,
@@ -161,6 +190,22 @@ pub struct SourceResponse {}
 #[derive(Serialize, Deserialize, utoipa::ToSchema)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IssuePublishTokenRequest {
+    #[prost(string, tag = "1")]
+    pub index_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub source_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IssuePublishTokenResponse {
+    #[prost(string, tag = "1")]
+    pub publish_token: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct DeleteTask {
     #[prost(int64, tag = "1")]
     pub create_timestamp: i64,
@@ -248,6 +293,137 @@ pub struct ListDeleteTasksResponse {
     #[prost(message, repeated, tag = "1")]
     pub delete_tasks: ::prost::alloc::vec::Vec<DeleteTask>,
 }
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateApiKeyRequest {
+    #[prost(string, tag = "1")]
+    pub api_key_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListApiKeysRequest {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListApiKeysResponse {
+    #[prost(string, tag = "1")]
+    pub api_keys_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteApiKeyRequest {
+    #[prost(string, tag = "1")]
+    pub key_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ApiKeyResponse {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateIndexTemplateRequest {
+    #[prost(string, tag = "1")]
+    pub index_template_serialized_json: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub overwrite: bool,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexTemplatesRequest {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexTemplatesResponse {
+    #[prost(string, tag = "1")]
+    pub index_templates_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteIndexTemplateRequest {
+    #[prost(string, tag = "1")]
+    pub template_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IndexTemplateResponse {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquireLeaseRequest {
+    #[prost(string, tag = "1")]
+    pub lease_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub holder_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub lease_duration_secs: u64,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AcquireLeaseResponse {
+    #[prost(string, tag = "1")]
+    pub lease_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexAliasesRequest {
+    #[prost(string, tag = "1")]
+    pub actions_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexAliasesResponse {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexAliasesRequest {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListIndexAliasesResponse {
+    #[prost(string, tag = "1")]
+    pub index_aliases_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateAlertRuleRequest {
+    #[prost(string, tag = "1")]
+    pub alert_rule_serialized_json: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub overwrite: bool,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListAlertRulesRequest {}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListAlertRulesResponse {
+    #[prost(string, tag = "1")]
+    pub alert_rules_serialized_json: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteAlertRuleRequest {
+    #[prost(string, tag = "1")]
+    pub rule_id: ::prost::alloc::string::String,
+}
+#[derive(Serialize, Deserialize, utoipa::ToSchema)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AlertRuleResponse {}
 /// Generated client implementations.
 pub mod metastore_api_service_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -357,6 +533,26 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Updates an index config.
+        pub async fn update_index_config(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateIndexConfigRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexConfigResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_index_config",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Gets an indexes metadatas.
         pub async fn list_indexes_metadatas(
             &mut self,
@@ -600,6 +796,26 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Issues a publish token for a source, greater than all the tokens previously issued for it.
+        pub async fn issue_publish_token(
+            &mut self,
+            request: impl tonic::IntoRequest<super::IssuePublishTokenRequest>,
+        ) -> Result<tonic::Response<super::IssuePublishTokenResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/issue_publish_token",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
         /// Gets last opstamp for a given `index_id`.
         pub async fn last_delete_opstamp(
             &mut self,
@@ -703,192 +919,1152 @@ pub mod metastore_api_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod metastore_api_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with MetastoreApiServiceServer.
-    #[async_trait]
-    pub trait MetastoreApiService: Send + Sync + 'static {
-        /// Creates an index.
-        async fn create_index(
-            &self,
-            request: tonic::Request<super::CreateIndexRequest>,
-        ) -> Result<tonic::Response<super::CreateIndexResponse>, tonic::Status>;
-        /// Gets an index metadata.
-        async fn index_metadata(
-            &self,
-            request: tonic::Request<super::IndexMetadataRequest>,
-        ) -> Result<tonic::Response<super::IndexMetadataResponse>, tonic::Status>;
-        /// Gets an indexes metadatas.
-        async fn list_indexes_metadatas(
-            &self,
-            request: tonic::Request<super::ListIndexesMetadatasRequest>,
-        ) -> Result<tonic::Response<super::ListIndexesMetadatasResponse>, tonic::Status>;
-        /// Deletes an index
-        async fn delete_index(
-            &self,
-            request: tonic::Request<super::DeleteIndexRequest>,
-        ) -> Result<tonic::Response<super::DeleteIndexResponse>, tonic::Status>;
-        /// Gets all splits from index.
-        async fn list_all_splits(
-            &self,
-            request: tonic::Request<super::ListAllSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
-        /// Gets splits from index.
-        async fn list_splits(
-            &self,
-            request: tonic::Request<super::ListSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
-        /// Stages several splits.
-        async fn stage_splits(
-            &self,
-            request: tonic::Request<super::StageSplitsRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Publishes split.
-        async fn publish_splits(
-            &self,
-            request: tonic::Request<super::PublishSplitsRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Marks splits for deletion.
-        async fn mark_splits_for_deletion(
-            &self,
-            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Deletes splits.
-        async fn delete_splits(
-            &self,
-            request: tonic::Request<super::DeleteSplitsRequest>,
-        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
-        /// Adds source.
-        async fn add_source(
-            &self,
-            request: tonic::Request<super::AddSourceRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Toggles source.
-        async fn toggle_source(
-            &self,
-            request: tonic::Request<super::ToggleSourceRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Removes source.
-        async fn delete_source(
-            &self,
-            request: tonic::Request<super::DeleteSourceRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Resets source checkpoint.
-        async fn reset_source_checkpoint(
-            &self,
-            request: tonic::Request<super::ResetSourceCheckpointRequest>,
-        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
-        /// Gets last opstamp for a given `index_id`.
-        async fn last_delete_opstamp(
-            &self,
-            request: tonic::Request<super::LastDeleteOpstampRequest>,
-        ) -> Result<tonic::Response<super::LastDeleteOpstampResponse>, tonic::Status>;
-        /// Creates a delete task.
-        async fn create_delete_task(
-            &self,
-            request: tonic::Request<super::DeleteQuery>,
-        ) -> Result<tonic::Response<super::DeleteTask>, tonic::Status>;
-        /// Updates splits `delete_opstamp`.
-        async fn update_splits_delete_opstamp(
-            &self,
-            request: tonic::Request<super::UpdateSplitsDeleteOpstampRequest>,
-        ) -> Result<
-            tonic::Response<super::UpdateSplitsDeleteOpstampResponse>,
-            tonic::Status,
-        >;
-        /// Lists delete tasks with `delete_task.opstamp` > `opstamp_start` for a given `index_id`.
-        async fn list_delete_tasks(
-            &self,
-            request: tonic::Request<super::ListDeleteTasksRequest>,
-        ) -> Result<tonic::Response<super::ListDeleteTasksResponse>, tonic::Status>;
-        /// / Lists splits with `split.delete_opstamp` < `delete_opstamp` for a given `index_id`.
-        async fn list_stale_splits(
-            &self,
-            request: tonic::Request<super::ListStaleSplitsRequest>,
-        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
-    }
-    #[derive(Debug)]
-    pub struct MetastoreApiServiceServer<T: MetastoreApiService> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: MetastoreApiService> MetastoreApiServiceServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
+        /// Creates an API key.
+        pub async fn create_api_key(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateApiKeyRequest>,
+        ) -> Result<tonic::Response<super::ApiKeyResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/create_api_key",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-            }
+        /// Lists API keys.
+        pub async fn list_api_keys(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListApiKeysRequest>,
+        ) -> Result<tonic::Response<super::ListApiKeysResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_api_keys",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> InterceptedService<Self, F>
-        where
-            F: tonic::service::Interceptor,
-        {
-            InterceptedService::new(Self::new(inner), interceptor)
+        /// Deletes an API key.
+        pub async fn delete_api_key(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteApiKeyRequest>,
+        ) -> Result<tonic::Response<super::ApiKeyResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/delete_api_key",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        /// Enable decompressing requests with the given encoding.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
-            self
+        /// Creates an index template.
+        pub async fn create_index_template(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateIndexTemplateRequest>,
+        ) -> Result<tonic::Response<super::IndexTemplateResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/create_index_template",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        /// Compress responses with the given encoding, if the client supports it.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
-            self
+        /// Lists index templates.
+        pub async fn list_index_templates(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListIndexTemplatesRequest>,
+        ) -> Result<tonic::Response<super::ListIndexTemplatesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_index_templates",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for MetastoreApiServiceServer<T>
-    where
-        T: MetastoreApiService,
-        B: Body + Send + 'static,
-        B::Error: Into<StdError> + Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
+        /// Deletes an index template.
+        pub async fn delete_index_template(
             &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
+            request: impl tonic::IntoRequest<super::DeleteIndexTemplateRequest>,
+        ) -> Result<tonic::Response<super::IndexTemplateResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/delete_index_template",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
         }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            let inner = self.inner.clone();
-            match req.uri().path() {
-                "/quickwit_metastore_api.MetastoreApiService/create_index" => {
+        /// Acquires or renews a lease.
+        pub async fn acquire_lease(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AcquireLeaseRequest>,
+        ) -> Result<tonic::Response<super::AcquireLeaseResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/acquire_lease",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Applies a set of actions to the index aliases atomically.
+        pub async fn update_index_aliases(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateIndexAliasesRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexAliasesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/update_index_aliases",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Lists the index aliases.
+        pub async fn list_index_aliases(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListIndexAliasesRequest>,
+        ) -> Result<tonic::Response<super::ListIndexAliasesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_index_aliases",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Creates an alert rule.
+        pub async fn create_alert_rule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateAlertRuleRequest>,
+        ) -> Result<tonic::Response<super::AlertRuleResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/create_alert_rule",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Lists alert rules.
+        pub async fn list_alert_rules(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListAlertRulesRequest>,
+        ) -> Result<tonic::Response<super::ListAlertRulesResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/list_alert_rules",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Deletes an alert rule.
+        pub async fn delete_alert_rule(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteAlertRuleRequest>,
+        ) -> Result<tonic::Response<super::AlertRuleResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/delete_alert_rule",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Archives a list of published splits.
+        pub async fn archive_splits(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ArchiveSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/archive_splits",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Rehydrates a list of archived splits.
+        pub async fn rehydrate_splits(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RehydrateSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/quickwit_metastore_api.MetastoreApiService/rehydrate_splits",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod metastore_api_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with MetastoreApiServiceServer.
+    #[async_trait]
+    pub trait MetastoreApiService: Send + Sync + 'static {
+        /// Creates an index.
+        async fn create_index(
+            &self,
+            request: tonic::Request<super::CreateIndexRequest>,
+        ) -> Result<tonic::Response<super::CreateIndexResponse>, tonic::Status>;
+        /// Gets an index metadata.
+        async fn index_metadata(
+            &self,
+            request: tonic::Request<super::IndexMetadataRequest>,
+        ) -> Result<tonic::Response<super::IndexMetadataResponse>, tonic::Status>;
+        /// Updates an index config.
+        async fn update_index_config(
+            &self,
+            request: tonic::Request<super::UpdateIndexConfigRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexConfigResponse>, tonic::Status>;
+        /// Gets an indexes metadatas.
+        async fn list_indexes_metadatas(
+            &self,
+            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+        ) -> Result<tonic::Response<super::ListIndexesMetadatasResponse>, tonic::Status>;
+        /// Deletes an index
+        async fn delete_index(
+            &self,
+            request: tonic::Request<super::DeleteIndexRequest>,
+        ) -> Result<tonic::Response<super::DeleteIndexResponse>, tonic::Status>;
+        /// Gets all splits from index.
+        async fn list_all_splits(
+            &self,
+            request: tonic::Request<super::ListAllSplitsRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
+        /// Gets splits from index.
+        async fn list_splits(
+            &self,
+            request: tonic::Request<super::ListSplitsRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
+        /// Stages several splits.
+        async fn stage_splits(
+            &self,
+            request: tonic::Request<super::StageSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Publishes split.
+        async fn publish_splits(
+            &self,
+            request: tonic::Request<super::PublishSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Marks splits for deletion.
+        async fn mark_splits_for_deletion(
+            &self,
+            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Deletes splits.
+        async fn delete_splits(
+            &self,
+            request: tonic::Request<super::DeleteSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Adds source.
+        async fn add_source(
+            &self,
+            request: tonic::Request<super::AddSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Toggles source.
+        async fn toggle_source(
+            &self,
+            request: tonic::Request<super::ToggleSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Removes source.
+        async fn delete_source(
+            &self,
+            request: tonic::Request<super::DeleteSourceRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Resets source checkpoint.
+        async fn reset_source_checkpoint(
+            &self,
+            request: tonic::Request<super::ResetSourceCheckpointRequest>,
+        ) -> Result<tonic::Response<super::SourceResponse>, tonic::Status>;
+        /// Issues a publish token for a source, greater than all the tokens previously issued for it.
+        async fn issue_publish_token(
+            &self,
+            request: tonic::Request<super::IssuePublishTokenRequest>,
+        ) -> Result<tonic::Response<super::IssuePublishTokenResponse>, tonic::Status>;
+        /// Gets last opstamp for a given `index_id`.
+        async fn last_delete_opstamp(
+            &self,
+            request: tonic::Request<super::LastDeleteOpstampRequest>,
+        ) -> Result<tonic::Response<super::LastDeleteOpstampResponse>, tonic::Status>;
+        /// Creates a delete task.
+        async fn create_delete_task(
+            &self,
+            request: tonic::Request<super::DeleteQuery>,
+        ) -> Result<tonic::Response<super::DeleteTask>, tonic::Status>;
+        /// Updates splits `delete_opstamp`.
+        async fn update_splits_delete_opstamp(
+            &self,
+            request: tonic::Request<super::UpdateSplitsDeleteOpstampRequest>,
+        ) -> Result<
+            tonic::Response<super::UpdateSplitsDeleteOpstampResponse>,
+            tonic::Status,
+        >;
+        /// Lists delete tasks with `delete_task.opstamp` > `opstamp_start` for a given `index_id`.
+        async fn list_delete_tasks(
+            &self,
+            request: tonic::Request<super::ListDeleteTasksRequest>,
+        ) -> Result<tonic::Response<super::ListDeleteTasksResponse>, tonic::Status>;
+        /// / Lists splits with `split.delete_opstamp` < `delete_opstamp` for a given `index_id`.
+        async fn list_stale_splits(
+            &self,
+            request: tonic::Request<super::ListStaleSplitsRequest>,
+        ) -> Result<tonic::Response<super::ListSplitsResponse>, tonic::Status>;
+        /// Creates an API key.
+        async fn create_api_key(
+            &self,
+            request: tonic::Request<super::CreateApiKeyRequest>,
+        ) -> Result<tonic::Response<super::ApiKeyResponse>, tonic::Status>;
+        /// Lists API keys.
+        async fn list_api_keys(
+            &self,
+            request: tonic::Request<super::ListApiKeysRequest>,
+        ) -> Result<tonic::Response<super::ListApiKeysResponse>, tonic::Status>;
+        /// Deletes an API key.
+        async fn delete_api_key(
+            &self,
+            request: tonic::Request<super::DeleteApiKeyRequest>,
+        ) -> Result<tonic::Response<super::ApiKeyResponse>, tonic::Status>;
+        /// Creates an index template.
+        async fn create_index_template(
+            &self,
+            request: tonic::Request<super::CreateIndexTemplateRequest>,
+        ) -> Result<tonic::Response<super::IndexTemplateResponse>, tonic::Status>;
+        /// Lists index templates.
+        async fn list_index_templates(
+            &self,
+            request: tonic::Request<super::ListIndexTemplatesRequest>,
+        ) -> Result<tonic::Response<super::ListIndexTemplatesResponse>, tonic::Status>;
+        /// Deletes an index template.
+        async fn delete_index_template(
+            &self,
+            request: tonic::Request<super::DeleteIndexTemplateRequest>,
+        ) -> Result<tonic::Response<super::IndexTemplateResponse>, tonic::Status>;
+        /// Acquires or renews a lease.
+        async fn acquire_lease(
+            &self,
+            request: tonic::Request<super::AcquireLeaseRequest>,
+        ) -> Result<tonic::Response<super::AcquireLeaseResponse>, tonic::Status>;
+        /// Applies a set of actions to the index aliases atomically.
+        async fn update_index_aliases(
+            &self,
+            request: tonic::Request<super::UpdateIndexAliasesRequest>,
+        ) -> Result<tonic::Response<super::UpdateIndexAliasesResponse>, tonic::Status>;
+        /// Lists the index aliases.
+        async fn list_index_aliases(
+            &self,
+            request: tonic::Request<super::ListIndexAliasesRequest>,
+        ) -> Result<tonic::Response<super::ListIndexAliasesResponse>, tonic::Status>;
+        /// Creates an alert rule.
+        async fn create_alert_rule(
+            &self,
+            request: tonic::Request<super::CreateAlertRuleRequest>,
+        ) -> Result<tonic::Response<super::AlertRuleResponse>, tonic::Status>;
+        /// Lists alert rules.
+        async fn list_alert_rules(
+            &self,
+            request: tonic::Request<super::ListAlertRulesRequest>,
+        ) -> Result<tonic::Response<super::ListAlertRulesResponse>, tonic::Status>;
+        /// Deletes an alert rule.
+        async fn delete_alert_rule(
+            &self,
+            request: tonic::Request<super::DeleteAlertRuleRequest>,
+        ) -> Result<tonic::Response<super::AlertRuleResponse>, tonic::Status>;
+        /// Archives a list of published splits.
+        async fn archive_splits(
+            &self,
+            request: tonic::Request<super::ArchiveSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+        /// Rehydrates a list of archived splits.
+        async fn rehydrate_splits(
+            &self,
+            request: tonic::Request<super::RehydrateSplitsRequest>,
+        ) -> Result<tonic::Response<super::SplitResponse>, tonic::Status>;
+    }
+    #[derive(Debug)]
+    pub struct MetastoreApiServiceServer<T: MetastoreApiService> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: MetastoreApiService> MetastoreApiServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for MetastoreApiServiceServer<T>
+    where
+        T: MetastoreApiService,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/quickwit_metastore_api.MetastoreApiService/create_index" => {
+                    #[allow(non_camel_case_types)]
+                    struct create_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::CreateIndexRequest>
+                    for create_indexSvc<T> {
+                        type Response = super::CreateIndexResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateIndexRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).create_index(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = create_indexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/index_metadata" => {
+                    #[allow(non_camel_case_types)]
+                    struct index_metadataSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::IndexMetadataRequest>
+                    for index_metadataSvc<T> {
+                        type Response = super::IndexMetadataResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::IndexMetadataRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).index_metadata(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = index_metadataSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/update_index_config" => {
+                    #[allow(non_camel_case_types)]
+                    struct update_index_configSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::UpdateIndexConfigRequest>
+                    for update_index_configSvc<T> {
+                        type Response = super::UpdateIndexConfigResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateIndexConfigRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).update_index_config(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = update_index_configSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_indexes_metadatasSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListIndexesMetadatasRequest>
+                    for list_indexes_metadatasSvc<T> {
+                        type Response = super::ListIndexesMetadatasResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_indexes_metadatas(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_indexes_metadatasSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_index" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteIndexRequest>
+                    for delete_indexSvc<T> {
+                        type Response = super::DeleteIndexResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteIndexRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_index(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_indexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_all_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_all_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListAllSplitsRequest>
+                    for list_all_splitsSvc<T> {
+                        type Response = super::ListSplitsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAllSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).list_all_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_all_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/list_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct list_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ListSplitsRequest>
+                    for list_splitsSvc<T> {
+                        type Response = super::ListSplitsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).list_splits(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = list_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/stage_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct stage_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::StageSplitsRequest>
+                    for stage_splitsSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StageSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).stage_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = stage_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/publish_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct publish_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::PublishSplitsRequest>
+                    for publish_splitsSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PublishSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).publish_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = publish_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/mark_splits_for_deletion" => {
+                    #[allow(non_camel_case_types)]
+                    struct mark_splits_for_deletionSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::MarkSplitsForDeletionRequest>
+                    for mark_splits_for_deletionSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).mark_splits_for_deletion(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = mark_splits_for_deletionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteSplitsRequest>
+                    for delete_splitsSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/add_source" => {
+                    #[allow(non_camel_case_types)]
+                    struct add_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::AddSourceRequest>
+                    for add_sourceSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddSourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).add_source(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = add_sourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/toggle_source" => {
+                    #[allow(non_camel_case_types)]
+                    struct toggle_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ToggleSourceRequest>
+                    for toggle_sourceSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ToggleSourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).toggle_source(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = toggle_sourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/delete_source" => {
+                    #[allow(non_camel_case_types)]
+                    struct delete_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::DeleteSourceRequest>
+                    for delete_sourceSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteSourceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).delete_source(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = delete_sourceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/reset_source_checkpoint" => {
+                    #[allow(non_camel_case_types)]
+                    struct reset_source_checkpointSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::ResetSourceCheckpointRequest>
+                    for reset_source_checkpointSvc<T> {
+                        type Response = super::SourceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ResetSourceCheckpointRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).reset_source_checkpoint(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = reset_source_checkpointSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/issue_publish_token" => {
                     #[allow(non_camel_case_types)]
-                    struct create_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct issue_publish_tokenSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::CreateIndexRequest>
-                    for create_indexSvc<T> {
-                        type Response = super::CreateIndexResponse;
+                    > tonic::server::UnaryService<super::IssuePublishTokenRequest>
+                    for issue_publish_tokenSvc<T> {
+                        type Response = super::IssuePublishTokenResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::CreateIndexRequest>,
+                            request: tonic::Request<super::IssuePublishTokenRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).create_index(request).await
+                                (*inner).issue_publish_token(request).await
                             };
                             Box::pin(fut)
                         }
@@ -898,7 +2074,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = create_indexSvc(inner);
+                        let method = issue_publish_tokenSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -910,25 +2086,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/index_metadata" => {
+                "/quickwit_metastore_api.MetastoreApiService/last_delete_opstamp" => {
                     #[allow(non_camel_case_types)]
-                    struct index_metadataSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct last_delete_opstampSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::IndexMetadataRequest>
-                    for index_metadataSvc<T> {
-                        type Response = super::IndexMetadataResponse;
+                    > tonic::server::UnaryService<super::LastDeleteOpstampRequest>
+                    for last_delete_opstampSvc<T> {
+                        type Response = super::LastDeleteOpstampResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::IndexMetadataRequest>,
+                            request: tonic::Request<super::LastDeleteOpstampRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).index_metadata(request).await
+                                (*inner).last_delete_opstamp(request).await
                             };
                             Box::pin(fut)
                         }
@@ -938,7 +2114,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = index_metadataSvc(inner);
+                        let method = last_delete_opstampSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -950,25 +2126,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_indexes_metadatas" => {
+                "/quickwit_metastore_api.MetastoreApiService/create_delete_task" => {
                     #[allow(non_camel_case_types)]
-                    struct list_indexes_metadatasSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct create_delete_taskSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListIndexesMetadatasRequest>
-                    for list_indexes_metadatasSvc<T> {
-                        type Response = super::ListIndexesMetadatasResponse;
+                    > tonic::server::UnaryService<super::DeleteQuery>
+                    for create_delete_taskSvc<T> {
+                        type Response = super::DeleteTask;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListIndexesMetadatasRequest>,
+                            request: tonic::Request<super::DeleteQuery>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_indexes_metadatas(request).await
+                                (*inner).create_delete_task(request).await
                             };
                             Box::pin(fut)
                         }
@@ -978,7 +2154,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_indexes_metadatasSvc(inner);
+                        let method = create_delete_taskSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -990,25 +2166,30 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_index" => {
+                "/quickwit_metastore_api.MetastoreApiService/update_splits_delete_opstamp" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_indexSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct update_splits_delete_opstampSvc<T: MetastoreApiService>(
+                        pub Arc<T>,
+                    );
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteIndexRequest>
-                    for delete_indexSvc<T> {
-                        type Response = super::DeleteIndexResponse;
+                    > tonic::server::UnaryService<
+                        super::UpdateSplitsDeleteOpstampRequest,
+                    > for update_splits_delete_opstampSvc<T> {
+                        type Response = super::UpdateSplitsDeleteOpstampResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteIndexRequest>,
+                            request: tonic::Request<
+                                super::UpdateSplitsDeleteOpstampRequest,
+                            >,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_index(request).await
+                                (*inner).update_splits_delete_opstamp(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1018,7 +2199,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_indexSvc(inner);
+                        let method = update_splits_delete_opstampSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1030,25 +2211,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_all_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_delete_tasks" => {
                     #[allow(non_camel_case_types)]
-                    struct list_all_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_delete_tasksSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListAllSplitsRequest>
-                    for list_all_splitsSvc<T> {
-                        type Response = super::ListSplitsResponse;
+                    > tonic::server::UnaryService<super::ListDeleteTasksRequest>
+                    for list_delete_tasksSvc<T> {
+                        type Response = super::ListDeleteTasksResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListAllSplitsRequest>,
+                            request: tonic::Request<super::ListDeleteTasksRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_all_splits(request).await
+                                (*inner).list_delete_tasks(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1058,7 +2239,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_all_splitsSvc(inner);
+                        let method = list_delete_tasksSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1070,13 +2251,13 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_stale_splits" => {
                     #[allow(non_camel_case_types)]
-                    struct list_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_stale_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListSplitsRequest>
-                    for list_splitsSvc<T> {
+                    > tonic::server::UnaryService<super::ListStaleSplitsRequest>
+                    for list_stale_splitsSvc<T> {
                         type Response = super::ListSplitsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
@@ -1084,10 +2265,12 @@ pub mod metastore_api_service_server {
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListSplitsRequest>,
+                            request: tonic::Request<super::ListStaleSplitsRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).list_splits(request).await };
+                            let fut = async move {
+                                (*inner).list_stale_splits(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -1096,7 +2279,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_splitsSvc(inner);
+                        let method = list_stale_splitsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1108,25 +2291,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/stage_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/create_api_key" => {
                     #[allow(non_camel_case_types)]
-                    struct stage_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct create_api_keySvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::StageSplitsRequest>
-                    for stage_splitsSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::CreateApiKeyRequest>
+                    for create_api_keySvc<T> {
+                        type Response = super::ApiKeyResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::StageSplitsRequest>,
+                            request: tonic::Request<super::CreateApiKeyRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).stage_splits(request).await
+                                (*inner).create_api_key(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1136,7 +2319,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = stage_splitsSvc(inner);
+                        let method = create_api_keySvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1148,25 +2331,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/publish_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_api_keys" => {
                     #[allow(non_camel_case_types)]
-                    struct publish_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_api_keysSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::PublishSplitsRequest>
-                    for publish_splitsSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::ListApiKeysRequest>
+                    for list_api_keysSvc<T> {
+                        type Response = super::ListApiKeysResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PublishSplitsRequest>,
+                            request: tonic::Request<super::ListApiKeysRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).publish_splits(request).await
+                                (*inner).list_api_keys(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1176,7 +2359,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = publish_splitsSvc(inner);
+                        let method = list_api_keysSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1188,27 +2371,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/mark_splits_for_deletion" => {
+                "/quickwit_metastore_api.MetastoreApiService/delete_api_key" => {
                     #[allow(non_camel_case_types)]
-                    struct mark_splits_for_deletionSvc<T: MetastoreApiService>(
-                        pub Arc<T>,
-                    );
+                    struct delete_api_keySvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::MarkSplitsForDeletionRequest>
-                    for mark_splits_for_deletionSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::DeleteApiKeyRequest>
+                    for delete_api_keySvc<T> {
+                        type Response = super::ApiKeyResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MarkSplitsForDeletionRequest>,
+                            request: tonic::Request<super::DeleteApiKeyRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).mark_splits_for_deletion(request).await
+                                (*inner).delete_api_key(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1218,7 +2399,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = mark_splits_for_deletionSvc(inner);
+                        let method = delete_api_keySvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1230,25 +2411,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/create_index_template" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct create_index_templateSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteSplitsRequest>
-                    for delete_splitsSvc<T> {
-                        type Response = super::SplitResponse;
+                    > tonic::server::UnaryService<super::CreateIndexTemplateRequest>
+                    for create_index_templateSvc<T> {
+                        type Response = super::IndexTemplateResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteSplitsRequest>,
+                            request: tonic::Request<super::CreateIndexTemplateRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_splits(request).await
+                                (*inner).create_index_template(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1258,7 +2439,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_splitsSvc(inner);
+                        let method = create_index_templateSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1270,24 +2451,26 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/add_source" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_index_templates" => {
                     #[allow(non_camel_case_types)]
-                    struct add_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_index_templatesSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::AddSourceRequest>
-                    for add_sourceSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::ListIndexTemplatesRequest>
+                    for list_index_templatesSvc<T> {
+                        type Response = super::ListIndexTemplatesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AddSourceRequest>,
+                            request: tonic::Request<super::ListIndexTemplatesRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
-                            let fut = async move { (*inner).add_source(request).await };
+                            let fut = async move {
+                                (*inner).list_index_templates(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -1296,7 +2479,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = add_sourceSvc(inner);
+                        let method = list_index_templatesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1308,25 +2491,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/toggle_source" => {
+                "/quickwit_metastore_api.MetastoreApiService/delete_index_template" => {
                     #[allow(non_camel_case_types)]
-                    struct toggle_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct delete_index_templateSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ToggleSourceRequest>
-                    for toggle_sourceSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::DeleteIndexTemplateRequest>
+                    for delete_index_templateSvc<T> {
+                        type Response = super::IndexTemplateResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ToggleSourceRequest>,
+                            request: tonic::Request<super::DeleteIndexTemplateRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).toggle_source(request).await
+                                (*inner).delete_index_template(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1336,7 +2519,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = toggle_sourceSvc(inner);
+                        let method = delete_index_templateSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1348,25 +2531,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/delete_source" => {
+                "/quickwit_metastore_api.MetastoreApiService/acquire_lease" => {
                     #[allow(non_camel_case_types)]
-                    struct delete_sourceSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct acquire_leaseSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteSourceRequest>
-                    for delete_sourceSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::AcquireLeaseRequest>
+                    for acquire_leaseSvc<T> {
+                        type Response = super::AcquireLeaseResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteSourceRequest>,
+                            request: tonic::Request<super::AcquireLeaseRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).delete_source(request).await
+                                (*inner).acquire_lease(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1376,7 +2559,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = delete_sourceSvc(inner);
+                        let method = acquire_leaseSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1388,27 +2571,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/reset_source_checkpoint" => {
+                "/quickwit_metastore_api.MetastoreApiService/update_index_aliases" => {
                     #[allow(non_camel_case_types)]
-                    struct reset_source_checkpointSvc<T: MetastoreApiService>(
-                        pub Arc<T>,
-                    );
+                    struct update_index_aliasesSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ResetSourceCheckpointRequest>
-                    for reset_source_checkpointSvc<T> {
-                        type Response = super::SourceResponse;
+                    > tonic::server::UnaryService<super::UpdateIndexAliasesRequest>
+                    for update_index_aliasesSvc<T> {
+                        type Response = super::UpdateIndexAliasesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ResetSourceCheckpointRequest>,
+                            request: tonic::Request<super::UpdateIndexAliasesRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).reset_source_checkpoint(request).await
+                                (*inner).update_index_aliases(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1418,7 +2599,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = reset_source_checkpointSvc(inner);
+                        let method = update_index_aliasesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1430,25 +2611,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/last_delete_opstamp" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_index_aliases" => {
                     #[allow(non_camel_case_types)]
-                    struct last_delete_opstampSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct list_index_aliasesSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::LastDeleteOpstampRequest>
-                    for last_delete_opstampSvc<T> {
-                        type Response = super::LastDeleteOpstampResponse;
+                    > tonic::server::UnaryService<super::ListIndexAliasesRequest>
+                    for list_index_aliasesSvc<T> {
+                        type Response = super::ListIndexAliasesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::LastDeleteOpstampRequest>,
+                            request: tonic::Request<super::ListIndexAliasesRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).last_delete_opstamp(request).await
+                                (*inner).list_index_aliases(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1458,7 +2639,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = last_delete_opstampSvc(inner);
+                        let method = list_index_aliasesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1470,25 +2651,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/create_delete_task" => {
+                "/quickwit_metastore_api.MetastoreApiService/create_alert_rule" => {
                     #[allow(non_camel_case_types)]
-                    struct create_delete_taskSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct create_alert_ruleSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::DeleteQuery>
-                    for create_delete_taskSvc<T> {
-                        type Response = super::DeleteTask;
+                    > tonic::server::UnaryService<super::CreateAlertRuleRequest>
+                    for create_alert_ruleSvc<T> {
+                        type Response = super::AlertRuleResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteQuery>,
+                            request: tonic::Request<super::CreateAlertRuleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).create_delete_task(request).await
+                                (*inner).create_alert_rule(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1498,7 +2679,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = create_delete_taskSvc(inner);
+                        let method = create_alert_ruleSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1510,30 +2691,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/update_splits_delete_opstamp" => {
+                "/quickwit_metastore_api.MetastoreApiService/list_alert_rules" => {
                     #[allow(non_camel_case_types)]
-                    struct update_splits_delete_opstampSvc<T: MetastoreApiService>(
-                        pub Arc<T>,
-                    );
+                    struct list_alert_rulesSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<
-                        super::UpdateSplitsDeleteOpstampRequest,
-                    > for update_splits_delete_opstampSvc<T> {
-                        type Response = super::UpdateSplitsDeleteOpstampResponse;
+                    > tonic::server::UnaryService<super::ListAlertRulesRequest>
+                    for list_alert_rulesSvc<T> {
+                        type Response = super::ListAlertRulesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<
-                                super::UpdateSplitsDeleteOpstampRequest,
-                            >,
+                            request: tonic::Request<super::ListAlertRulesRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).update_splits_delete_opstamp(request).await
+                                (*inner).list_alert_rules(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1543,7 +2719,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = update_splits_delete_opstampSvc(inner);
+                        let method = list_alert_rulesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1555,25 +2731,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_delete_tasks" => {
+                "/quickwit_metastore_api.MetastoreApiService/delete_alert_rule" => {
                     #[allow(non_camel_case_types)]
-                    struct list_delete_tasksSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct delete_alert_ruleSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListDeleteTasksRequest>
-                    for list_delete_tasksSvc<T> {
-                        type Response = super::ListDeleteTasksResponse;
+                    > tonic::server::UnaryService<super::DeleteAlertRuleRequest>
+                    for delete_alert_ruleSvc<T> {
+                        type Response = super::AlertRuleResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListDeleteTasksRequest>,
+                            request: tonic::Request<super::DeleteAlertRuleRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_delete_tasks(request).await
+                                (*inner).delete_alert_rule(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1583,7 +2759,7 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_delete_tasksSvc(inner);
+                        let method = delete_alert_ruleSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1595,25 +2771,25 @@ pub mod metastore_api_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/quickwit_metastore_api.MetastoreApiService/list_stale_splits" => {
+                "/quickwit_metastore_api.MetastoreApiService/archive_splits" => {
                     #[allow(non_camel_case_types)]
-                    struct list_stale_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    struct archive_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
                     impl<
                         T: MetastoreApiService,
-                    > tonic::server::UnaryService<super::ListStaleSplitsRequest>
-                    for list_stale_splitsSvc<T> {
-                        type Response = super::ListSplitsResponse;
+                    > tonic::server::UnaryService<super::ArchiveSplitsRequest>
+                    for archive_splitsSvc<T> {
+                        type Response = super::SplitResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListStaleSplitsRequest>,
+                            request: tonic::Request<super::ArchiveSplitsRequest>,
                         ) -> Self::Future {
                             let inner = self.0.clone();
                             let fut = async move {
-                                (*inner).list_stale_splits(request).await
+                                (*inner).archive_splits(request).await
                             };
                             Box::pin(fut)
                         }
@@ -1623,7 +2799,47 @@ pub mod metastore_api_service_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = list_stale_splitsSvc(inner);
+                        let method = archive_splitsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/quickwit_metastore_api.MetastoreApiService/rehydrate_splits" => {
+                    #[allow(non_camel_case_types)]
+                    struct rehydrate_splitsSvc<T: MetastoreApiService>(pub Arc<T>);
+                    impl<
+                        T: MetastoreApiService,
+                    > tonic::server::UnaryService<super::RehydrateSplitsRequest>
+                    for rehydrate_splitsSvc<T> {
+                        type Response = super::SplitResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RehydrateSplitsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).rehydrate_splits(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = rehydrate_splitsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -20,11 +20,17 @@
 use std::time::Duration;
 
 use bytes::Bytes;
+use quickwit_cluster::ClusterSnapshot;
+use quickwit_common::uri::Uri;
 use quickwit_common::FileEntry;
-use quickwit_config::{ConfigFormat, SourceConfig};
-use quickwit_metastore::{IndexMetadata, Split};
-use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString};
+use quickwit_config::{ConfigFormat, RolloverConditions, SourceConfig};
+use quickwit_core::{
+    IndexBackupSummary, MountedIndexRefreshSummary, SplitStorageLayoutMigrationSummary,
+};
+use quickwit_janitor::{RolloverReport, StorageReconciliationReport};
+use quickwit_metastore::{IndexAlias, IndexAliasAction, IndexMetadata, Split, SplitMetadata};
+use quickwit_search::{SearchResponseRest, SqlResponse};
+use quickwit_serve::{ListSplitsQueryParams, NodeDrainState, SearchRequestQueryString, SqlRequest};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::{Client, Method, StatusCode, Url};
 use serde::Serialize;
@@ -127,10 +133,80 @@ impl QuickwitClient {
         Ok(search_response)
     }
 
+    /// Fetches the next page of hits of a search started with the `scroll` parameter.
+    pub async fn scroll(
+        &self,
+        scroll_id: &str,
+        scroll_opt: Option<&str>,
+    ) -> Result<SearchResponseRest, Error> {
+        let body = Bytes::from(
+            json!({
+                "scroll_id": scroll_id,
+                "scroll": scroll_opt,
+            })
+            .to_string(),
+        );
+        let response = self
+            .transport
+            .send::<()>(Method::POST, "_search/scroll", None, None, Some(body))
+            .await?;
+        let search_response = response.deserialize().await?;
+        Ok(search_response)
+    }
+
+    pub async fn sql(&self, sql_request: SqlRequest) -> Result<SqlResponse, Error> {
+        let bytes = serde_json::to_string(&sql_request)
+            .unwrap()
+            .as_bytes()
+            .to_vec();
+        let body = Bytes::from(bytes);
+        let response = self
+            .transport
+            .send::<()>(Method::POST, "_sql", None, None, Some(body))
+            .await?;
+        let sql_response = response.deserialize().await?;
+        Ok(sql_response)
+    }
+
+    /// Returns the cluster state as seen by the targeted node.
+    pub async fn cluster(&self) -> Result<ClusterSnapshot, Error> {
+        let response = self
+            .transport
+            .send::<()>(Method::GET, "cluster", None, None, None)
+            .await?;
+        let cluster_snapshot = response.deserialize().await?;
+        Ok(cluster_snapshot)
+    }
+
+    /// Starts draining the node `node_id`. The client must target that node.
+    pub async fn drain_node(&self, node_id: &str) -> Result<NodeDrainState, Error> {
+        let path = format!("nodes/{node_id}/drain");
+        let response = self
+            .transport
+            .send::<()>(Method::POST, &path, None, None, None)
+            .await?;
+        let node_drain_state = response.deserialize().await?;
+        Ok(node_drain_state)
+    }
+
+    pub async fn node_drain_state(&self, node_id: &str) -> Result<NodeDrainState, Error> {
+        let path = format!("nodes/{node_id}/drain");
+        let response = self
+            .transport
+            .send::<()>(Method::GET, &path, None, None, None)
+            .await?;
+        let node_drain_state = response.deserialize().await?;
+        Ok(node_drain_state)
+    }
+
     pub fn indexes(&self) -> IndexClient {
         IndexClient::new(&self.transport)
     }
 
+    pub fn aliases(&self) -> AliasClient {
+        AliasClient::new(&self.transport)
+    }
+
     pub fn splits<'a, 'b: 'a>(&'a self, index_id: &'b str) -> SplitClient {
         SplitClient::new(&self.transport, index_id)
     }
@@ -145,7 +221,6 @@ impl QuickwitClient {
         ingest_source: IngestSource,
         on_ingest_event: Option<&dyn Fn(IngestEvent)>,
     ) -> Result<(), Error> {
-        let ingest_path = format!("{index_id}/ingest");
         let mut batch_reader = match ingest_source {
             IngestSource::File(filepath) => {
                 BatchLineReader::from_file(&filepath, INGEST_CONTENT_LENGTH_LIMIT).await?
@@ -153,26 +228,39 @@ impl QuickwitClient {
             IngestSource::Stdin => BatchLineReader::from_stdin(INGEST_CONTENT_LENGTH_LIMIT),
         };
         while let Some(batch) = batch_reader.next_batch().await? {
-            loop {
-                let response = self
-                    .transport
-                    .send::<()>(Method::POST, &ingest_path, None, None, Some(batch.clone()))
-                    .await?;
-
-                if response.status_code() == StatusCode::TOO_MANY_REQUESTS {
-                    if let Some(event_fn) = &on_ingest_event {
-                        event_fn(IngestEvent::Sleep)
-                    }
-                    tokio::time::sleep(Duration::from_secs(1)).await;
-                } else {
-                    response.check().await?;
-                    break;
+            self.ingest_batch(index_id, batch, on_ingest_event).await?;
+        }
+        Ok(())
+    }
+
+    /// Ingests a batch of NDJSON documents, waiting and retrying for as long as the ingest API
+    /// rejects the batch because of rate limiting.
+    pub async fn ingest_batch(
+        &self,
+        index_id: &str,
+        batch: Bytes,
+        on_ingest_event: Option<&dyn Fn(IngestEvent)>,
+    ) -> Result<(), Error> {
+        let ingest_path = format!("{index_id}/ingest");
+        loop {
+            let response = self
+                .transport
+                .send::<()>(Method::POST, &ingest_path, None, None, Some(batch.clone()))
+                .await?;
+
+            if response.status_code() == StatusCode::TOO_MANY_REQUESTS {
+                if let Some(event_fn) = &on_ingest_event {
+                    event_fn(IngestEvent::Sleep)
                 }
-            }
-            if let Some(event_fn) = on_ingest_event.as_ref() {
-                event_fn(IngestEvent::IngestedDocBatch(batch.len()))
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            } else {
+                response.check().await?;
+                break;
             }
         }
+        if let Some(event_fn) = on_ingest_event.as_ref() {
+            event_fn(IngestEvent::IngestedDocBatch(batch.len()))
+        }
         Ok(())
     }
 }
@@ -242,6 +330,177 @@ impl<'a> IndexClient<'a> {
         Ok(())
     }
 
+    pub async fn clone(
+        &self,
+        index_id: &str,
+        target_index_id: &str,
+        target_index_uri_opt: Option<&str>,
+    ) -> Result<IndexMetadata, Error> {
+        let path = format!("indexes/{index_id}/clone");
+        let json_value = json!({
+            "target_index_id": target_index_id,
+            "target_index_uri": target_index_uri_opt,
+        });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                &path,
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let index_metadata = response.deserialize().await?;
+        Ok(index_metadata)
+    }
+
+    pub async fn backup(
+        &self,
+        index_id: &str,
+        backup_uri: &Uri,
+        include_split_files: bool,
+    ) -> Result<IndexBackupSummary, Error> {
+        let path = format!("indexes/{index_id}/backup");
+        let json_value = json!({
+            "backup_uri": backup_uri,
+            "include_split_files": include_split_files,
+        });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                &path,
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let backup_summary = response.deserialize().await?;
+        Ok(backup_summary)
+    }
+
+    pub async fn restore(
+        &self,
+        backup_uri: &Uri,
+        index_id_opt: Option<&str>,
+        index_uri_opt: Option<&Uri>,
+    ) -> Result<IndexMetadata, Error> {
+        let json_value = json!({
+            "backup_uri": backup_uri,
+            "index_id": index_id_opt,
+            "index_uri": index_uri_opt,
+        });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                "indexes/restore",
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let index_metadata = response.deserialize().await?;
+        Ok(index_metadata)
+    }
+
+    pub async fn mount(
+        &self,
+        snapshot_uri: &Uri,
+        index_id_opt: Option<&str>,
+    ) -> Result<IndexMetadata, Error> {
+        let json_value = json!({
+            "snapshot_uri": snapshot_uri,
+            "index_id": index_id_opt,
+        });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                "indexes/mount",
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let index_metadata = response.deserialize().await?;
+        Ok(index_metadata)
+    }
+
+    pub async fn refresh_mount(&self, index_id: &str) -> Result<MountedIndexRefreshSummary, Error> {
+        let path = format!("indexes/{index_id}/refresh-mount");
+        let response = self
+            .transport
+            .send::<()>(Method::POST, &path, None, None, None)
+            .await?;
+        let refresh_summary = response.deserialize().await?;
+        Ok(refresh_summary)
+    }
+
+    pub async fn reconcile(
+        &self,
+        index_id: &str,
+        delete_orphans: bool,
+    ) -> Result<StorageReconciliationReport, Error> {
+        let path = format!("indexes/{index_id}/reconcile");
+        let response = self
+            .transport
+            .send(
+                Method::POST,
+                &path,
+                None,
+                Some(&[("delete_orphans", delete_orphans)]),
+                None,
+            )
+            .await?;
+        let report = response.deserialize().await?;
+        Ok(report)
+    }
+
+    pub async fn migrate_split_storage_layout(
+        &self,
+        index_id: &str,
+    ) -> Result<SplitStorageLayoutMigrationSummary, Error> {
+        let path = format!("indexes/{index_id}/migrate-split-storage-layout");
+        let response = self
+            .transport
+            .send::<()>(Method::POST, &path, None, None, None)
+            .await?;
+        let migration_summary = response.deserialize().await?;
+        Ok(migration_summary)
+    }
+
+    pub async fn rehydrate(
+        &self,
+        index_id: &str,
+        start_timestamp_opt: Option<i64>,
+        end_timestamp_opt: Option<i64>,
+    ) -> Result<Vec<SplitMetadata>, Error> {
+        let path = format!("indexes/{index_id}/rehydrate");
+        let json_value = json!({
+            "start_timestamp": start_timestamp_opt,
+            "end_timestamp": end_timestamp_opt,
+        });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                &path,
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let splits = response.deserialize().await?;
+        Ok(splits)
+    }
+
     pub async fn delete(&self, index_id: &str, dry_run: bool) -> Result<Vec<FileEntry>, Error> {
         let path = format!("indexes/{index_id}");
         let response = self
@@ -404,6 +663,89 @@ impl<'a, 'b> SourceClient<'a, 'b> {
     }
 }
 
+/// Client for index aliases APIs.
+pub struct AliasClient<'a> {
+    transport: &'a Transport,
+}
+
+impl<'a> AliasClient<'a> {
+    pub fn new(transport: &'a Transport) -> Self {
+        Self { transport }
+    }
+
+    /// Applies the actions to the index aliases atomically and returns the resulting aliases.
+    pub async fn update(&self, actions: Vec<IndexAliasAction>) -> Result<Vec<IndexAlias>, Error> {
+        let json_value = json!({ "actions": actions });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                "aliases",
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let index_aliases = response.deserialize().await?;
+        Ok(index_aliases)
+    }
+
+    pub async fn list(&self) -> Result<Vec<IndexAlias>, Error> {
+        let response = self
+            .transport
+            .send::<()>(Method::GET, "aliases", None, None, None)
+            .await?;
+        let index_aliases = response.deserialize().await?;
+        Ok(index_aliases)
+    }
+
+    pub async fn get(&self, alias_id: &str) -> Result<IndexAlias, Error> {
+        let path = format!("aliases/{alias_id}");
+        let response = self
+            .transport
+            .send::<()>(Method::GET, &path, None, None, None)
+            .await?;
+        let index_alias = response.deserialize().await?;
+        Ok(index_alias)
+    }
+
+    pub async fn delete(&self, alias_id: &str) -> Result<(), Error> {
+        let path = format!("aliases/{alias_id}");
+        let response = self
+            .transport
+            .send::<()>(Method::DELETE, &path, None, None, None)
+            .await?;
+        response.check().await?;
+        Ok(())
+    }
+
+    /// Rolls the write alias over to a new index if the index it points to meets any of the
+    /// conditions, or unconditionally if no condition is defined.
+    pub async fn rollover(
+        &self,
+        alias_id: &str,
+        conditions: &RolloverConditions,
+        dry_run: bool,
+    ) -> Result<RolloverReport, Error> {
+        let path = format!("aliases/{alias_id}/rollover");
+        let json_value = json!({ "conditions": conditions, "dry_run": dry_run });
+        let json_bytes = serde_json::to_vec(&json_value).expect("Serialization should never fail.");
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                &path,
+                None,
+                None,
+                Some(Bytes::from(json_bytes)),
+            )
+            .await?;
+        let rollover_report = response.deserialize().await?;
+        Ok(rollover_report)
+    }
+}
+
 fn header_from_config_format(config_format: ConfigFormat) -> HeaderMap {
     let mut header_map = HeaderMap::new();
     let content_type_value = format!("application/{}", config_format.as_str());
@@ -420,9 +762,10 @@ mod test {
     use std::str::FromStr;
 
     use bytes::Bytes;
-    use quickwit_config::{ConfigFormat, SourceConfig};
+    use quickwit_config::{ConfigFormat, RolloverConditions, SourceConfig};
     use quickwit_indexing::mock_split;
-    use quickwit_metastore::IndexMetadata;
+    use quickwit_janitor::RolloverReport;
+    use quickwit_metastore::{IndexAlias, IndexAliasAction, IndexMetadata};
     use quickwit_search::SearchResponseRest;
     use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString};
     use reqwest::header::CONTENT_TYPE;
@@ -470,9 +813,15 @@ mod test {
             num_hits: 0,
             hits: Vec::new(),
             snippets: None,
+            num_collapsed_hits: None,
+            split_metadata: None,
             aggregations: None,
             elapsed_time_micros: 100,
             errors: Vec::new(),
+            search_after: None,
+            scroll_id: None,
+            timed_out: false,
+            failed_splits: Vec::new(),
         };
         Mock::given(method("POST"))
             .and(path("/api/v1/my-index/search"))
@@ -489,6 +838,158 @@ mod test {
                 .unwrap(),
             expected_search_response
         );
+        // Scroll
+        Mock::given(method("POST"))
+            .and(path("/api/v1/_search/scroll"))
+            .and(body_json(json!({"scroll_id": "scroll-1", "scroll": "1m"})))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(
+                json!({"num_hits": 0, "hits": [], "elapsed_time_micros": 100, "errors": []}),
+            ))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            qw_client.scroll("scroll-1", Some("1m")).await.unwrap(),
+            expected_search_response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_node_drain_endpoints() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClient::new(Transport::new(server_url));
+        let node_drain_state_json = json!({
+            "node_id": "node-1",
+            "status": "draining",
+            "num_pending_ingest_records": 10,
+            "num_pipelines_with_in_flight_data": 1,
+        });
+        Mock::given(method("POST"))
+            .and(path("/api/v1/nodes/node-1/drain"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(node_drain_state_json.clone()),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/nodes/node-1/drain"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(node_drain_state_json),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let node_drain_state = qw_client.drain_node("node-1").await.unwrap();
+        assert_eq!(node_drain_state.node_id, "node-1");
+        assert_eq!(node_drain_state.status.to_string(), "draining");
+        assert_eq!(node_drain_state.num_pending_ingest_records, 10);
+
+        let node_drain_state = qw_client.node_drain_state("node-1").await.unwrap();
+        assert_eq!(node_drain_state.num_pipelines_with_in_flight_data, 1);
+    }
+
+    #[tokio::test]
+    async fn test_aliases_endpoints() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClient::new(Transport::new(server_url));
+        let index_alias = IndexAlias {
+            alias_id: "logs-current".to_string(),
+            index_ids: vec!["logs-v2".to_string()],
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/v1/aliases"))
+            .and(body_json(json!({
+                "actions": [
+                    {"remove": {"alias_id": "logs-current", "index_id": "logs-v1"}},
+                    {"add": {"alias_id": "logs-current", "index_id": "logs-v2"}},
+                ]
+            })))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(json!([index_alias.clone()])),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/aliases"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(json!([index_alias.clone()])),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/aliases/logs-current"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(index_alias.clone()))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/api/v1/aliases/logs-current"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let index_aliases = qw_client
+            .aliases()
+            .update(vec![
+                IndexAliasAction::Remove {
+                    alias_id: "logs-current".to_string(),
+                    index_id: "logs-v1".to_string(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: "logs-current".to_string(),
+                    index_id: "logs-v2".to_string(),
+                },
+            ])
+            .await
+            .unwrap();
+        assert_eq!(index_aliases, [index_alias.clone()]);
+        assert_eq!(
+            qw_client.aliases().list().await.unwrap(),
+            [index_alias.clone()]
+        );
+        assert_eq!(
+            qw_client.aliases().get("logs-current").await.unwrap(),
+            index_alias
+        );
+        qw_client.aliases().delete("logs-current").await.unwrap();
+
+        let rollover_report = RolloverReport {
+            alias_id: "logs".to_string(),
+            old_index_id: Some("logs-000001".to_string()),
+            new_index_id: "logs-000002".to_string(),
+            rolled_over: true,
+            dry_run: false,
+            met_conditions: vec!["max_docs".to_string()],
+        };
+        Mock::given(method("POST"))
+            .and(path("/api/v1/aliases/logs/rollover"))
+            .and(body_json(json!({
+                "conditions": {"max_docs": 1000},
+                "dry_run": false,
+            })))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(rollover_report.clone()),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let conditions = RolloverConditions {
+            max_docs: Some(1_000),
+            ..Default::default()
+        };
+        assert_eq!(
+            qw_client
+                .aliases()
+                .rollover("logs", &conditions, false)
+                .await
+                .unwrap(),
+            rollover_report
+        );
     }
 
     fn get_ndjson_filepath(ndjson_dataset_filename: &str) -> String {
@@ -643,6 +1144,70 @@ mod test {
             .await;
         qw_client.indexes().clear("my-index").await.unwrap_err();
 
+        // POST clone index
+        Mock::given(method("POST"))
+            .and(path("/api/v1/indexes/my-index/clone"))
+            .and(body_json(
+                json!({"target_index_id": "my-clone", "target_index_uri": null}),
+            ))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(index_metadata.clone()),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            qw_client
+                .indexes()
+                .clone("my-index", "my-clone", None)
+                .await
+                .unwrap(),
+            index_metadata
+        );
+
+        // POST reconcile index storage
+        Mock::given(method("POST"))
+            .and(path("/api/v1/indexes/my-index/reconcile"))
+            .and(query_param("delete_orphans", "true"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(json!({
+                "orphan_files": [{"file_name": "orphan.split", "file_size_in_bytes": 100}],
+                "missing_split_ids": ["missing"],
+                "deleted_orphan_files": ["orphan.split"],
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let report = qw_client
+            .indexes()
+            .reconcile("my-index", true)
+            .await
+            .unwrap();
+        assert_eq!(report.orphan_num_bytes(), 100);
+        assert_eq!(report.missing_split_ids, ["missing"]);
+        assert_eq!(report.deleted_orphan_files, ["orphan.split"]);
+
+        // POST migrate split storage layout
+        Mock::given(method("POST"))
+            .and(path(
+                "/api/v1/indexes/my-index/migrate-split-storage-layout",
+            ))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(json!({
+                "num_migrated_splits": 3,
+                "num_deduplicated_splits": 1,
+                "num_uploaded_bytes": 2048,
+            })))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let migration_summary = qw_client
+            .indexes()
+            .migrate_split_storage_layout("my-index")
+            .await
+            .unwrap();
+        assert_eq!(migration_summary.num_migrated_splits, 3);
+        assert_eq!(migration_summary.num_deduplicated_splits, 1);
+        assert_eq!(migration_summary.num_uploaded_bytes, 2048);
+
         // DELETE index
         Mock::given(method("DELETE"))
             .and(path("/api/v1/indexes/my-index"))
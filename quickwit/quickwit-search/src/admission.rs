@@ -0,0 +1,349 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Admission control of the searches coordinated by a searcher.
+//!
+//! Searches beyond the concurrency limits wait in a queue ordered by priority class, then by
+//! arrival, and are rejected when the queue is full or after waiting for too long.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_config::SearchAdmissionConfig;
+use tokio::sync::oneshot;
+
+use crate::metrics::SEARCH_METRICS;
+use crate::SearchError;
+
+/// Priority class of a search. Queued interactive searches are admitted before queued batch
+/// searches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum SearchPriority {
+    Interactive,
+    Batch,
+}
+
+struct QueuedSearch {
+    index_id: String,
+    permit_tx: oneshot::Sender<AdmissionPermit>,
+}
+
+#[derive(Default)]
+struct AdmissionState {
+    num_running_searches: usize,
+    num_running_batch_searches: usize,
+    num_running_searches_per_index: HashMap<String, usize>,
+    queue: BTreeMap<(SearchPriority, u64), QueuedSearch>,
+    next_queue_ord: u64,
+}
+
+struct AdmissionControllerInner {
+    config: SearchAdmissionConfig,
+    state: Mutex<AdmissionState>,
+}
+
+impl AdmissionControllerInner {
+    fn can_admit(&self, state: &AdmissionState, index_id: &str, priority: SearchPriority) -> bool {
+        if state.num_running_searches >= self.config.max_concurrent_searches {
+            return false;
+        }
+        if priority == SearchPriority::Batch
+            && state.num_running_batch_searches >= self.config.max_concurrent_batch_searches
+        {
+            return false;
+        }
+        if let Some(max_concurrent_searches_per_index) =
+            self.config.max_concurrent_searches_per_index
+        {
+            let num_running_index_searches = state
+                .num_running_searches_per_index
+                .get(index_id)
+                .copied()
+                .unwrap_or(0);
+            if num_running_index_searches >= max_concurrent_searches_per_index {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn admit(
+        self: &Arc<Self>,
+        state: &mut AdmissionState,
+        index_id: &str,
+        priority: SearchPriority,
+    ) -> AdmissionPermit {
+        state.num_running_searches += 1;
+        SEARCH_METRICS
+            .running_searches
+            .set(state.num_running_searches as i64);
+        if priority == SearchPriority::Batch {
+            state.num_running_batch_searches += 1;
+        }
+        *state
+            .num_running_searches_per_index
+            .entry(index_id.to_string())
+            .or_default() += 1;
+        AdmissionPermit {
+            controller_opt: Some(self.clone()),
+            index_id: index_id.to_string(),
+            priority,
+        }
+    }
+
+    fn release(state: &mut AdmissionState, index_id: &str, priority: SearchPriority) {
+        state.num_running_searches -= 1;
+        SEARCH_METRICS
+            .running_searches
+            .set(state.num_running_searches as i64);
+        if priority == SearchPriority::Batch {
+            state.num_running_batch_searches -= 1;
+        }
+        if let Some(num_running_index_searches) =
+            state.num_running_searches_per_index.get_mut(index_id)
+        {
+            *num_running_index_searches -= 1;
+            if *num_running_index_searches == 0 {
+                state.num_running_searches_per_index.remove(index_id);
+            }
+        }
+    }
+
+    /// Admits the queued searches that fit within the limits, in the order of the queue.
+    fn admit_queued_searches(self: &Arc<Self>, state: &mut AdmissionState) {
+        let queue_keys: Vec<(SearchPriority, u64)> = state.queue.keys().copied().collect();
+
+        for queue_key in queue_keys {
+            let priority = queue_key.0;
+            if state.num_running_searches >= self.config.max_concurrent_searches {
+                break;
+            }
+            let index_id = &state.queue[&queue_key].index_id;
+            if !self.can_admit(state, index_id, priority) {
+                continue;
+            }
+            let queued_search = state
+                .queue
+                .remove(&queue_key)
+                .expect("The queued search should be present.");
+            let permit = self.admit(state, &queued_search.index_id, priority);
+
+            if let Err(mut permit) = queued_search.permit_tx.send(permit) {
+                // The search was cancelled while waiting: the permit must be released without
+                // locking the state again.
+                permit.controller_opt.take();
+                Self::release(state, &queued_search.index_id, priority);
+            }
+        }
+        SEARCH_METRICS.queued_searches.set(state.queue.len() as i64);
+    }
+}
+
+/// Admits the searches coordinated by a searcher within the limits of the admission config.
+#[derive(Clone)]
+pub(crate) struct SearchAdmissionController {
+    inner: Arc<AdmissionControllerInner>,
+}
+
+impl SearchAdmissionController {
+    pub fn new(config: SearchAdmissionConfig) -> Self {
+        let inner = AdmissionControllerInner {
+            config,
+            state: Mutex::default(),
+        };
+        SearchAdmissionController {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Waits until a search on the index can run, and returns a permit releasing its slot when
+    /// dropped. Returns a `TooManyRequests` error if the queue is full or the search waited for
+    /// too long.
+    pub async fn admit(
+        &self,
+        index_id: &str,
+        priority: SearchPriority,
+    ) -> crate::Result<AdmissionPermit> {
+        let (queue_key, mut permit_rx) = {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if self.inner.can_admit(&state, index_id, priority) {
+                return Ok(self.inner.admit(&mut state, index_id, priority));
+            }
+            if state.queue.len() >= self.inner.config.max_queued_searches {
+                SEARCH_METRICS.rejected_searches_total.inc();
+                return Err(SearchError::TooManyRequests(
+                    "The search queue is full.".to_string(),
+                ));
+            }
+            let (permit_tx, permit_rx) = oneshot::channel();
+            let queue_key = (priority, state.next_queue_ord);
+            state.next_queue_ord += 1;
+            let queued_search = QueuedSearch {
+                index_id: index_id.to_string(),
+                permit_tx,
+            };
+            state.queue.insert(queue_key, queued_search);
+            SEARCH_METRICS.queued_searches.set(state.queue.len() as i64);
+            (queue_key, permit_rx)
+        };
+        let max_queue_wait = Duration::from_secs(self.inner.config.max_queue_wait_secs);
+
+        if let Ok(permit_res) = tokio::time::timeout(max_queue_wait, &mut permit_rx).await {
+            return permit_res.map_err(|_| {
+                SearchError::InternalError("The search admission queue was dropped.".to_string())
+            });
+        }
+        let is_still_queued = {
+            let mut state = self.inner.state.lock().unwrap();
+            let is_still_queued = state.queue.remove(&queue_key).is_some();
+            SEARCH_METRICS.queued_searches.set(state.queue.len() as i64);
+            is_still_queued
+        };
+        if is_still_queued {
+            SEARCH_METRICS.rejected_searches_total.inc();
+            return Err(SearchError::TooManyRequests(format!(
+                "The search waited more than {} seconds to be admitted.",
+                max_queue_wait.as_secs()
+            )));
+        }
+        // The search was admitted right after the timeout elapsed.
+        permit_rx.await.map_err(|_| {
+            SearchError::InternalError("The search admission queue was dropped.".to_string())
+        })
+    }
+}
+
+/// Slot of an admitted search, released when the permit is dropped.
+pub(crate) struct AdmissionPermit {
+    controller_opt: Option<Arc<AdmissionControllerInner>>,
+    index_id: String,
+    priority: SearchPriority,
+}
+
+impl Drop for AdmissionPermit {
+    fn drop(&mut self) {
+        let Some(controller) = self.controller_opt.take() else {
+            return;
+        };
+        let mut state = controller.state.lock().unwrap();
+        AdmissionControllerInner::release(&mut state, &self.index_id, self.priority);
+        controller.admit_queued_searches(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admission_config(
+        max_concurrent_searches: usize,
+        max_concurrent_searches_per_index: Option<usize>,
+    ) -> SearchAdmissionConfig {
+        SearchAdmissionConfig {
+            max_concurrent_searches,
+            max_concurrent_batch_searches: 1,
+            max_concurrent_searches_per_index,
+            max_queued_searches: 2,
+            max_queue_wait_secs: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admission_controller_queues_by_priority() {
+        let controller = SearchAdmissionController::new(admission_config(1, None));
+        let permit = controller
+            .admit("index", SearchPriority::Interactive)
+            .await
+            .unwrap();
+        let (admitted_tx, mut admitted_rx) = tokio::sync::mpsc::unbounded_channel();
+
+        for priority in [SearchPriority::Batch, SearchPriority::Interactive] {
+            let controller = controller.clone();
+            let admitted_tx = admitted_tx.clone();
+            tokio::spawn(async move {
+                let permit = controller.admit("index", priority).await.unwrap();
+                admitted_tx.send(priority).unwrap();
+                drop(permit);
+            });
+            // Lets the search enter the queue.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let error = controller
+            .admit("index", SearchPriority::Interactive)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+
+        drop(permit);
+        assert_eq!(admitted_rx.recv().await, Some(SearchPriority::Interactive));
+        assert_eq!(admitted_rx.recv().await, Some(SearchPriority::Batch));
+    }
+
+    #[tokio::test]
+    async fn test_admission_controller_limits_searches_per_index() {
+        let controller = SearchAdmissionController::new(admission_config(3, Some(1)));
+        let _permit_1 = controller
+            .admit("index-1", SearchPriority::Interactive)
+            .await
+            .unwrap();
+        let _permit_2 = controller
+            .admit("index-2", SearchPriority::Interactive)
+            .await
+            .unwrap();
+        let controller_clone = controller.clone();
+        let queued_search_handle = tokio::spawn(async move {
+            controller_clone
+                .admit("index-1", SearchPriority::Interactive)
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(controller.inner.state.lock().unwrap().queue.len(), 1);
+
+        let error = queued_search_handle.await.unwrap().err().unwrap();
+        assert!(matches!(error, SearchError::TooManyRequests(_)));
+        assert!(controller.inner.state.lock().unwrap().queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_admission_controller_releases_permits_of_cancelled_searches() {
+        let controller = SearchAdmissionController::new(admission_config(1, None));
+        let permit = controller
+            .admit("index", SearchPriority::Interactive)
+            .await
+            .unwrap();
+        let controller_clone = controller.clone();
+        let cancelled_search_handle = tokio::spawn(async move {
+            controller_clone
+                .admit("index", SearchPriority::Interactive)
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancelled_search_handle.abort();
+        let _ = cancelled_search_handle.await;
+        drop(permit);
+
+        let _permit = controller
+            .admit("index", SearchPriority::Interactive)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,911 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::time::format_description::well_known::Rfc3339;
+use tantivy::time::OffsetDateTime;
+use tantivy::{DocId, SegmentReader, TantivyError};
+
+use super::column::{format_numeric_value, AggregationColumn, NumericColumn};
+use super::date_histogram::{DateHistogramAggregation, DateRounding};
+use super::{
+    is_multi_valued_fast_field, merge_metric_results, validate_datetime_fast_field,
+    validate_fast_field, validate_numeric_fast_field, IntermediateMetricResult,
+    MetricSegmentCollector, MetricSegmentState, QuickwitMetricAggregation,
+};
+use crate::collector::AGGREGATION_BUCKET_LIMIT;
+use crate::SearchError;
+
+fn default_terms_size() -> usize {
+    10
+}
+
+fn default_terms_min_doc_count() -> u64 {
+    1
+}
+
+/// Creates a bucket per interval of the values of a numeric or datetime fast field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HistogramAggregation {
+    /// Name of the field to aggregate on.
+    pub field: String,
+    /// Width of the buckets. Datetime intervals are expressed in microseconds.
+    pub interval: f64,
+    /// Shift of the bucket grid, between 0 and `interval`.
+    #[serde(default)]
+    pub offset: f64,
+    /// Minimum number of documents of the returned buckets.
+    #[serde(default)]
+    pub min_doc_count: u64,
+    /// Whether the buckets are returned as a map keyed by bucket key or as a list.
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+/// Sort order of the buckets of a terms aggregation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// Ascending order.
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+/// Sort key of the buckets of a terms aggregation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermsOrder {
+    /// Sorts the buckets by document count. Ties are broken by ascending key.
+    #[serde(rename = "_count")]
+    Count(Order),
+    /// Sorts the buckets by key.
+    #[serde(rename = "_key")]
+    Key(Order),
+}
+
+impl Default for TermsOrder {
+    fn default() -> Self {
+        TermsOrder::Count(Order::Desc)
+    }
+}
+
+/// Creates a bucket per distinct value of a fast field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TermsAggregation {
+    /// Name of the field to aggregate on.
+    pub field: String,
+    /// Number of buckets returned.
+    #[serde(default = "default_terms_size")]
+    pub size: usize,
+    /// Minimum number of documents of the returned buckets.
+    #[serde(default = "default_terms_min_doc_count")]
+    pub min_doc_count: u64,
+    /// Sort order of the buckets.
+    #[serde(default)]
+    pub order: TermsOrder,
+}
+
+/// Bucketing strategy of a [`QuickwitBucketAggregation`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BucketAggregation {
+    /// Fixed-width buckets over a numeric field.
+    Histogram(HistogramAggregation),
+    /// One bucket per distinct value of a field.
+    Terms(TermsAggregation),
+    /// Time zone- and calendar-aware buckets over a datetime field.
+    DateHistogram(DateHistogramAggregation),
+}
+
+/// Bucket aggregation computed by Quickwit, used for date histograms and when a histogram or terms
+/// aggregation has Quickwit metric sub-aggregations, which tantivy cannot compute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickwitBucketAggregation {
+    /// Bucketing strategy.
+    pub bucket_aggregation: BucketAggregation,
+    /// Metric aggregations computed for each bucket, by name.
+    pub sub_aggregations: BTreeMap<String, QuickwitMetricAggregation>,
+}
+
+pub(super) const SUB_AGGREGATIONS_KEYS: [&str; 2] = ["aggs", "aggregations"];
+
+impl QuickwitBucketAggregation {
+    const NAMES: [&'static str; 2] = ["histogram", "terms"];
+
+    /// Returns whether `aggregation_json` is a date histogram aggregation, or a histogram or terms
+    /// aggregation with at least one Quickwit metric sub-aggregation. If the `schema` is known,
+    /// the histograms over a multi-valued field are computed by Quickwit as well.
+    pub(crate) fn is_quickwit_bucket_aggregation(
+        aggregation_json: &JsonValue,
+        schema_opt: Option<&Schema>,
+    ) -> bool {
+        let Some(aggregation_obj) = aggregation_json.as_object() else {
+            return false;
+        };
+        if aggregation_obj.contains_key("date_histogram") {
+            return true;
+        }
+        if !aggregation_obj
+            .keys()
+            .any(|key| Self::NAMES.contains(&key.as_str()))
+        {
+            return false;
+        }
+        if let (Some(schema), Some(field_name)) = (
+            schema_opt,
+            aggregation_obj
+                .get("histogram")
+                .and_then(|histogram_json| histogram_json.get("field"))
+                .and_then(JsonValue::as_str),
+        ) {
+            if is_multi_valued_fast_field(schema, field_name) {
+                return true;
+            }
+        }
+        SUB_AGGREGATIONS_KEYS
+            .iter()
+            .filter_map(|key| aggregation_obj.get(*key))
+            .filter_map(JsonValue::as_object)
+            .flat_map(|sub_aggregations_obj| sub_aggregations_obj.values())
+            .any(|sub_aggregation_json| {
+                QuickwitMetricAggregation::is_metric_aggregation(sub_aggregation_json, schema_opt)
+            })
+    }
+
+    pub(crate) fn field_names(&self) -> impl Iterator<Item = &str> {
+        let field_name = match &self.bucket_aggregation {
+            BucketAggregation::Histogram(aggregation) => aggregation.field.as_str(),
+            BucketAggregation::Terms(aggregation) => aggregation.field.as_str(),
+            BucketAggregation::DateHistogram(aggregation) => aggregation.field.as_str(),
+        };
+        std::iter::once(field_name).chain(
+            self.sub_aggregations
+                .values()
+                .map(QuickwitMetricAggregation::field_name),
+        )
+    }
+
+    pub(crate) fn term_dict_field_names(&self) -> impl Iterator<Item = &str> {
+        let terms_field_name_opt = match &self.bucket_aggregation {
+            BucketAggregation::Histogram(_) | BucketAggregation::DateHistogram(_) => None,
+            BucketAggregation::Terms(aggregation) => Some(aggregation.field.as_str()),
+        };
+        terms_field_name_opt.into_iter().chain(
+            self.sub_aggregations
+                .values()
+                .filter_map(QuickwitMetricAggregation::term_dict_field_name),
+        )
+    }
+
+    pub(crate) fn validate(&self, schema: &Schema) -> Result<(), String> {
+        match &self.bucket_aggregation {
+            BucketAggregation::Histogram(aggregation) => {
+                if aggregation.interval <= 0.0 {
+                    return Err("`interval` must be strictly positive.".to_string());
+                }
+                if aggregation.offset < 0.0 || aggregation.offset >= aggregation.interval {
+                    return Err("`offset` must be in the range `[0, interval)`.".to_string());
+                }
+                validate_numeric_fast_field(schema, &aggregation.field)?;
+            }
+            BucketAggregation::Terms(aggregation) => {
+                if aggregation.size == 0 {
+                    return Err("`size` must be strictly positive.".to_string());
+                }
+                validate_fast_field(schema, &aggregation.field)?;
+            }
+            BucketAggregation::DateHistogram(aggregation) => {
+                aggregation.rounding()?;
+                validate_datetime_fast_field(schema, &aggregation.field)?;
+            }
+        }
+        for (name, sub_aggregation) in &self.sub_aggregations {
+            sub_aggregation
+                .validate(schema)
+                .map_err(|error| format!("Sub-aggregation `{name}` is invalid: {error}"))?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_final_result(
+        self,
+        intermediate_result_opt: Option<IntermediateBucketResult>,
+        schema: &Schema,
+    ) -> crate::Result<JsonValue> {
+        match self.bucket_aggregation {
+            BucketAggregation::Histogram(aggregation) => {
+                let buckets = match intermediate_result_opt {
+                    Some(IntermediateBucketResult::Histogram(buckets)) => buckets,
+                    _ => BTreeMap::new(),
+                };
+                histogram_into_final_result(&aggregation, &self.sub_aggregations, buckets, schema)
+            }
+            BucketAggregation::Terms(aggregation) => {
+                let buckets = match intermediate_result_opt {
+                    Some(IntermediateBucketResult::Terms(buckets)) => buckets,
+                    _ => BTreeMap::new(),
+                };
+                Ok(terms_into_final_result(
+                    &aggregation,
+                    &self.sub_aggregations,
+                    buckets,
+                    schema,
+                ))
+            }
+            BucketAggregation::DateHistogram(aggregation) => {
+                let buckets = match intermediate_result_opt {
+                    Some(IntermediateBucketResult::DateHistogram(buckets)) => buckets,
+                    _ => BTreeMap::new(),
+                };
+                aggregation.into_final_result(&self.sub_aggregations, buckets)
+            }
+        }
+    }
+}
+
+/// Removes the sub-aggregations of `aggregation_json`, which must all be Quickwit metric
+/// aggregations.
+pub(super) fn remove_metric_sub_aggregations(
+    aggregation_json: &mut JsonMap<String, JsonValue>,
+) -> Result<BTreeMap<String, QuickwitMetricAggregation>, String> {
+    let mut sub_aggregations = BTreeMap::new();
+
+    for key in SUB_AGGREGATIONS_KEYS {
+        let Some(sub_aggregations_json) = aggregation_json.remove(key) else {
+            continue;
+        };
+        let sub_aggregations_json: JsonMap<String, JsonValue> =
+            serde_json::from_value(sub_aggregations_json).map_err(|error| error.to_string())?;
+
+        for (name, sub_aggregation_json) in sub_aggregations_json {
+            if !QuickwitMetricAggregation::is_sub_aggregation(&sub_aggregation_json) {
+                return Err(format!(
+                    "sub-aggregation `{name}` is not supported: the buckets of date histograms, \
+                     composite aggregations, and the other bucket aggregations computed by \
+                     Quickwit only support metric sub-aggregations"
+                ));
+            }
+            let sub_aggregation =
+                serde_json::from_value(sub_aggregation_json).map_err(|error| error.to_string())?;
+            sub_aggregations.insert(name, sub_aggregation);
+        }
+    }
+    Ok(sub_aggregations)
+}
+
+impl<'de> Deserialize<'de> for QuickwitBucketAggregation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let mut aggregation_json = JsonMap::<String, JsonValue>::deserialize(deserializer)?;
+        let sub_aggregations =
+            remove_metric_sub_aggregations(&mut aggregation_json).map_err(D::Error::custom)?;
+        let bucket_aggregation = serde_json::from_value(JsonValue::Object(aggregation_json))
+            .map_err(D::Error::custom)?;
+        Ok(QuickwitBucketAggregation {
+            bucket_aggregation,
+            sub_aggregations,
+        })
+    }
+}
+
+/// A bucket of a [`QuickwitBucketAggregation`], mergeable across segments, splits, and leaves.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateBucket {
+    pub(super) doc_count: u64,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(super) sub_results: BTreeMap<String, IntermediateMetricResult>,
+}
+
+impl IntermediateBucket {
+    pub(super) fn merge(&mut self, other: IntermediateBucket) {
+        self.doc_count += other.doc_count;
+        merge_metric_results(&mut self.sub_results, other.sub_results);
+    }
+
+    pub(super) fn into_final_result(
+        mut self,
+        sub_aggregations: &BTreeMap<String, QuickwitMetricAggregation>,
+        mut bucket_json: JsonMap<String, JsonValue>,
+    ) -> JsonValue {
+        bucket_json.insert("doc_count".to_string(), json!(self.doc_count));
+
+        for (name, sub_aggregation) in sub_aggregations {
+            let sub_result_opt = self.sub_results.remove(name);
+            bucket_json.insert(
+                name.clone(),
+                sub_aggregation.clone().into_final_result(sub_result_opt),
+            );
+        }
+        JsonValue::Object(bucket_json)
+    }
+}
+
+fn merge_buckets<K: Ord>(
+    buckets: &mut BTreeMap<K, IntermediateBucket>,
+    other_buckets: BTreeMap<K, IntermediateBucket>,
+) {
+    for (key, other_bucket) in other_buckets {
+        buckets.entry(key).or_default().merge(other_bucket);
+    }
+}
+
+/// Intermediate result of a [`QuickwitBucketAggregation`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateBucketResult {
+    /// Buckets of a histogram aggregation by index: the bucket `i` holds the values in
+    /// `[offset + i * interval, offset + (i + 1) * interval)`.
+    Histogram(BTreeMap<i64, IntermediateBucket>),
+    /// Buckets of a terms aggregation by key.
+    Terms(BTreeMap<String, IntermediateBucket>),
+    /// Buckets of a date histogram aggregation by start, in milliseconds since the Unix epoch.
+    DateHistogram(BTreeMap<i64, IntermediateBucket>),
+}
+
+impl IntermediateBucketResult {
+    pub(crate) fn merge(&mut self, other: IntermediateBucketResult) {
+        match (self, other) {
+            (
+                IntermediateBucketResult::Histogram(buckets),
+                IntermediateBucketResult::Histogram(other_buckets),
+            ) => merge_buckets(buckets, other_buckets),
+            (
+                IntermediateBucketResult::Terms(buckets),
+                IntermediateBucketResult::Terms(other_buckets),
+            ) => merge_buckets(buckets, other_buckets),
+            (
+                IntermediateBucketResult::DateHistogram(buckets),
+                IntermediateBucketResult::DateHistogram(other_buckets),
+            ) => merge_buckets(buckets, other_buckets),
+            _ => {}
+        }
+    }
+}
+
+fn is_field_of_type(schema: &Schema, field_name: &str, predicate: fn(&FieldType) -> bool) -> bool {
+    schema
+        .get_field(field_name)
+        .map(|field| predicate(schema.get_field_entry(field).field_type()))
+        .unwrap_or(false)
+}
+
+fn format_rfc3339(timestamp_micros: f64) -> Option<String> {
+    OffsetDateTime::from_unix_timestamp_nanos(timestamp_micros as i128 * 1_000)
+        .ok()?
+        .format(&Rfc3339)
+        .ok()
+}
+
+fn histogram_into_final_result(
+    aggregation: &HistogramAggregation,
+    sub_aggregations: &BTreeMap<String, QuickwitMetricAggregation>,
+    mut buckets: BTreeMap<i64, IntermediateBucket>,
+    schema: &Schema,
+) -> crate::Result<JsonValue> {
+    // Like the tantivy histogram, we return the empty buckets between the first and the last
+    // non-empty buckets when `min_doc_count` is 0.
+    if aggregation.min_doc_count == 0 {
+        if let (Some(first_index), Some(last_index)) = (
+            buckets.keys().next().copied(),
+            buckets.keys().next_back().copied(),
+        ) {
+            if (last_index - first_index) as u64 >= AGGREGATION_BUCKET_LIMIT as u64 {
+                return Err(SearchError::InvalidAggregationRequest(format!(
+                    "Histogram `{}` has too many buckets (limit: {AGGREGATION_BUCKET_LIMIT}).",
+                    aggregation.field
+                )));
+            }
+            for index in first_index..=last_index {
+                buckets.entry(index).or_default();
+            }
+        }
+    }
+    let is_datetime = is_field_of_type(schema, &aggregation.field, |field_type| {
+        matches!(field_type, FieldType::Date(_))
+    });
+    let buckets_json = buckets
+        .into_iter()
+        .filter(|(_, bucket)| bucket.doc_count >= aggregation.min_doc_count)
+        .map(|(index, bucket)| {
+            let key = index as f64 * aggregation.interval + aggregation.offset;
+            let mut bucket_json = JsonMap::new();
+            bucket_json.insert("key".to_string(), json!(key));
+
+            if is_datetime {
+                if let Some(key_as_string) = format_rfc3339(key) {
+                    bucket_json.insert("key_as_string".to_string(), json!(key_as_string));
+                }
+            }
+            (key, bucket.into_final_result(sub_aggregations, bucket_json))
+        });
+    let buckets_json = if aggregation.keyed {
+        JsonValue::Object(
+            buckets_json
+                .map(|(key, bucket_json)| (key.to_string(), bucket_json))
+                .collect(),
+        )
+    } else {
+        JsonValue::Array(buckets_json.map(|(_, bucket_json)| bucket_json).collect())
+    };
+    Ok(json!({ "buckets": buckets_json }))
+}
+
+fn compare_keys(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(left_value), Some(right_value)) => left_value.total_cmp(&right_value),
+        _ => left.to_string().cmp(&right.to_string()),
+    }
+}
+
+fn terms_into_final_result(
+    aggregation: &TermsAggregation,
+    sub_aggregations: &BTreeMap<String, QuickwitMetricAggregation>,
+    buckets: BTreeMap<String, IntermediateBucket>,
+    schema: &Schema,
+) -> JsonValue {
+    let is_text = is_field_of_type(schema, &aggregation.field, |field_type| {
+        matches!(field_type, FieldType::Str(_))
+    });
+    let mut buckets: Vec<(JsonValue, IntermediateBucket)> = buckets
+        .into_iter()
+        .filter(|(_, bucket)| bucket.doc_count >= aggregation.min_doc_count)
+        .map(|(key, bucket)| {
+            let key_json = if is_text {
+                JsonValue::String(key)
+            } else {
+                serde_json::from_str(&key).unwrap_or(JsonValue::String(key))
+            };
+            (key_json, bucket)
+        })
+        .collect();
+    buckets.sort_by(
+        |(left_key, left_bucket), (right_key, right_bucket)| match aggregation.order {
+            TermsOrder::Count(order) => {
+                let count_ordering = left_bucket.doc_count.cmp(&right_bucket.doc_count);
+                let count_ordering = match order {
+                    Order::Asc => count_ordering,
+                    Order::Desc => count_ordering.reverse(),
+                };
+                count_ordering.then_with(|| compare_keys(left_key, right_key))
+            }
+            TermsOrder::Key(Order::Asc) => compare_keys(left_key, right_key),
+            TermsOrder::Key(Order::Desc) => compare_keys(right_key, left_key),
+        },
+    );
+    let sum_other_doc_count: u64 = buckets
+        .iter()
+        .skip(aggregation.size)
+        .map(|(_, bucket)| bucket.doc_count)
+        .sum();
+    buckets.truncate(aggregation.size);
+
+    let buckets_json: Vec<JsonValue> = buckets
+        .into_iter()
+        .map(|(key, bucket)| {
+            let mut bucket_json = JsonMap::new();
+            bucket_json.insert("key".to_string(), key);
+            bucket.into_final_result(sub_aggregations, bucket_json)
+        })
+        .collect();
+    // The leaves return all their buckets, so the document counts are exact.
+    json!({
+        "doc_count_error_upper_bound": 0,
+        "sum_other_doc_count": sum_other_doc_count,
+        "buckets": buckets_json,
+    })
+}
+
+/// Column of the values the documents are bucketed by.
+enum BucketKeyColumn {
+    Histogram {
+        column: NumericColumn,
+        interval: f64,
+        offset: f64,
+    },
+    Terms(AggregationColumn),
+    DateHistogram {
+        column: NumericColumn,
+        rounding: DateRounding,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum BucketKind {
+    Histogram,
+    Terms,
+    DateHistogram,
+}
+
+pub(super) struct SegmentBucket {
+    pub doc_count: u64,
+    pub sub_states: Vec<MetricSegmentState>,
+}
+
+impl SegmentBucket {
+    pub fn new(sub_collectors: &[(String, MetricSegmentCollector)]) -> Self {
+        SegmentBucket {
+            doc_count: 0,
+            sub_states: sub_collectors
+                .iter()
+                .map(|(_, sub_collector)| sub_collector.new_state())
+                .collect(),
+        }
+    }
+
+    /// Counts the document `doc_id` in the bucket and collects it with the sub-aggregations.
+    pub fn collect(
+        &mut self,
+        doc_id: DocId,
+        sub_collectors: &mut [(String, MetricSegmentCollector)],
+    ) {
+        self.doc_count += 1;
+
+        for ((_, sub_collector), sub_state) in
+            sub_collectors.iter_mut().zip(self.sub_states.iter_mut())
+        {
+            sub_collector.collect(doc_id, sub_state);
+        }
+    }
+
+    pub fn harvest(
+        self,
+        sub_collectors: &[(String, MetricSegmentCollector)],
+    ) -> tantivy::Result<IntermediateBucket> {
+        let sub_results = sub_collectors
+            .iter()
+            .zip(self.sub_states)
+            .map(|((name, sub_collector), sub_state)| {
+                Ok((name.clone(), sub_collector.harvest(sub_state)?))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(IntermediateBucket {
+            doc_count: self.doc_count,
+            sub_results,
+        })
+    }
+}
+
+/// Collects a [`QuickwitBucketAggregation`] at the scale of the segment.
+pub(crate) struct BucketSegmentCollector {
+    bucket_kind: BucketKind,
+    // `None` if the segment does not have the field.
+    key_column_opt: Option<BucketKeyColumn>,
+    sub_collectors: Vec<(String, MetricSegmentCollector)>,
+    // Buckets by histogram bucket index, term ordinal, bits of the numeric term, or date histogram
+    // bucket start.
+    buckets: HashMap<u64, SegmentBucket>,
+    bucket_limit: u32,
+    bucket_keys_buffer: Vec<u64>,
+    values_buffer: Vec<f64>,
+}
+
+impl BucketSegmentCollector {
+    pub fn new(
+        aggregation: &QuickwitBucketAggregation,
+        segment_reader: &SegmentReader,
+        bucket_limit: u32,
+    ) -> tantivy::Result<Self> {
+        let key_column_opt = match &aggregation.bucket_aggregation {
+            BucketAggregation::Histogram(histogram) => {
+                AggregationColumn::open_numeric(segment_reader, &histogram.field)?.map(|column| {
+                    BucketKeyColumn::Histogram {
+                        column,
+                        interval: histogram.interval,
+                        offset: histogram.offset,
+                    }
+                })
+            }
+            BucketAggregation::Terms(terms) => {
+                AggregationColumn::open(segment_reader, &terms.field)?.map(BucketKeyColumn::Terms)
+            }
+            BucketAggregation::DateHistogram(date_histogram) => {
+                let rounding = date_histogram
+                    .rounding()
+                    .map_err(TantivyError::InvalidArgument)?;
+                AggregationColumn::open_numeric(segment_reader, &date_histogram.field)?
+                    .map(|column| BucketKeyColumn::DateHistogram { column, rounding })
+            }
+        };
+        let bucket_kind = match &aggregation.bucket_aggregation {
+            BucketAggregation::Histogram(_) => BucketKind::Histogram,
+            BucketAggregation::Terms(_) => BucketKind::Terms,
+            BucketAggregation::DateHistogram(_) => BucketKind::DateHistogram,
+        };
+        let sub_collectors = aggregation
+            .sub_aggregations
+            .iter()
+            .map(|(name, sub_aggregation)| {
+                let sub_collector = MetricSegmentCollector::new(sub_aggregation, segment_reader)?;
+                Ok((name.clone(), sub_collector))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(BucketSegmentCollector {
+            bucket_kind,
+            key_column_opt,
+            sub_collectors,
+            buckets: HashMap::new(),
+            bucket_limit,
+            bucket_keys_buffer: Vec::new(),
+            values_buffer: Vec::new(),
+        })
+    }
+
+    fn collect_in_bucket(&mut self, bucket_key: u64, doc_id: DocId) {
+        let sub_collectors = &mut self.sub_collectors;
+        self.buckets
+            .entry(bucket_key)
+            .or_insert_with(|| SegmentBucket::new(sub_collectors))
+            .collect(doc_id, sub_collectors);
+    }
+
+    pub fn collect(&mut self, doc_id: DocId) {
+        let mut bucket_keys = std::mem::take(&mut self.bucket_keys_buffer);
+        let values = &mut self.values_buffer;
+        bucket_keys.clear();
+
+        match &self.key_column_opt {
+            Some(BucketKeyColumn::Histogram {
+                column,
+                interval,
+                offset,
+            }) => {
+                column.get_vals(doc_id, values);
+                bucket_keys.extend(
+                    values
+                        .iter()
+                        .filter(|value| !value.is_nan())
+                        .map(|value| ((value - offset) / interval).floor() as i64 as u64),
+                );
+            }
+            Some(BucketKeyColumn::Terms(AggregationColumn::Numeric(column))) => {
+                column.get_vals(doc_id, values);
+                bucket_keys.extend(values.iter().map(|value| value.to_bits()));
+            }
+            Some(BucketKeyColumn::Terms(AggregationColumn::Text(column))) => {
+                column.term_ords(doc_id, &mut bucket_keys);
+            }
+            Some(BucketKeyColumn::DateHistogram { column, rounding }) => {
+                column.get_vals(doc_id, values);
+                // Datetimes are read as microseconds.
+                bucket_keys.extend(values.iter().map(|value| {
+                    let timestamp_millis = (*value as i64).div_euclid(1_000);
+                    rounding.round_down(timestamp_millis) as u64
+                }));
+            }
+            None => {}
+        }
+        // A document with several values in the same bucket is counted once in that bucket.
+        bucket_keys.sort_unstable();
+        bucket_keys.dedup();
+
+        for bucket_key in &bucket_keys {
+            self.collect_in_bucket(*bucket_key, doc_id);
+        }
+        self.bucket_keys_buffer = bucket_keys;
+    }
+
+    fn harvest_bucket(&self, bucket: SegmentBucket) -> tantivy::Result<IntermediateBucket> {
+        bucket.harvest(&self.sub_collectors)
+    }
+
+    fn harvest_i64_keyed_buckets(
+        &self,
+        segment_buckets: HashMap<u64, SegmentBucket>,
+    ) -> tantivy::Result<BTreeMap<i64, IntermediateBucket>> {
+        segment_buckets
+            .into_iter()
+            .map(|(bucket_key, bucket)| Ok((bucket_key as i64, self.harvest_bucket(bucket)?)))
+            .collect()
+    }
+
+    pub fn harvest(mut self) -> tantivy::Result<IntermediateBucketResult> {
+        if self.buckets.len() > self.bucket_limit as usize {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Aborting aggregation because too many buckets were created (limit: {}).",
+                self.bucket_limit
+            )));
+        }
+        let segment_buckets = std::mem::take(&mut self.buckets);
+
+        match self.bucket_kind {
+            BucketKind::Histogram => {
+                let buckets = self.harvest_i64_keyed_buckets(segment_buckets)?;
+                return Ok(IntermediateBucketResult::Histogram(buckets));
+            }
+            BucketKind::DateHistogram => {
+                let buckets = self.harvest_i64_keyed_buckets(segment_buckets)?;
+                return Ok(IntermediateBucketResult::DateHistogram(buckets));
+            }
+            BucketKind::Terms => {}
+        }
+        let mut buffer = Vec::new();
+        let buckets = segment_buckets
+            .into_iter()
+            .map(|(bucket_key, bucket)| {
+                let term = match &self.key_column_opt {
+                    Some(BucketKeyColumn::Terms(AggregationColumn::Text(column))) => {
+                        column.term(bucket_key, &mut buffer)?
+                    }
+                    _ => format_numeric_value(f64::from_bits(bucket_key)),
+                };
+                Ok((term, self.harvest_bucket(bucket)?))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(IntermediateBucketResult::Terms(buckets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, FAST, STRING};
+
+    use super::*;
+    use crate::aggregations::CardinalitySketch;
+
+    fn cardinality_bucket(doc_count: u64, terms: &[&str]) -> IntermediateBucket {
+        let mut sketch = CardinalitySketch::default();
+        for term in terms {
+            sketch.add_term(term.as_bytes());
+        }
+        IntermediateBucket {
+            doc_count,
+            sub_results: BTreeMap::from_iter([(
+                "unique_users".to_string(),
+                IntermediateMetricResult::Cardinality(sketch),
+            )]),
+        }
+    }
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = SchemaBuilder::new();
+        schema_builder.add_date_field("timestamp", FAST);
+        schema_builder.add_text_field("service", STRING | FAST);
+        schema_builder.add_u64_field("status", FAST);
+        schema_builder.add_text_field("user_id", STRING | FAST);
+        schema_builder.build()
+    }
+
+    #[test]
+    fn test_histogram_final_result() {
+        let aggregation: QuickwitBucketAggregation = serde_json::from_value(json!({
+            "histogram": {"field": "timestamp", "interval": 3_600_000_000u64},
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        aggregation.validate(&test_schema()).unwrap();
+
+        let mut buckets = IntermediateBucketResult::Histogram(BTreeMap::from_iter([(
+            0,
+            cardinality_bucket(3, &["alice", "bob"]),
+        )]));
+        let other_buckets = IntermediateBucketResult::Histogram(BTreeMap::from_iter([
+            (0, cardinality_bucket(1, &["alice"])),
+            (2, cardinality_bucket(1, &["carol"])),
+        ]));
+        buckets.merge(other_buckets);
+
+        let final_result_json = aggregation
+            .into_final_result(Some(buckets), &test_schema())
+            .unwrap();
+        assert_eq!(
+            final_result_json,
+            json!({
+                "buckets": [
+                    {
+                        "key": 0.0,
+                        "key_as_string": "1970-01-01T00:00:00Z",
+                        "doc_count": 4,
+                        "unique_users": {"value": 2}
+                    },
+                    {
+                        "key": 3_600_000_000.0,
+                        "key_as_string": "1970-01-01T01:00:00Z",
+                        "doc_count": 0,
+                        "unique_users": {"value": 0}
+                    },
+                    {
+                        "key": 7_200_000_000.0,
+                        "key_as_string": "1970-01-01T02:00:00Z",
+                        "doc_count": 1,
+                        "unique_users": {"value": 1}
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_terms_final_result() {
+        let aggregation: QuickwitBucketAggregation = serde_json::from_value(json!({
+            "terms": {"field": "service", "size": 2},
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        aggregation.validate(&test_schema()).unwrap();
+
+        let buckets = IntermediateBucketResult::Terms(BTreeMap::from_iter([
+            ("backend".to_string(), cardinality_bucket(2, &["alice"])),
+            (
+                "frontend".to_string(),
+                cardinality_bucket(5, &["alice", "bob"]),
+            ),
+            ("database".to_string(), cardinality_bucket(2, &["carol"])),
+        ]));
+        let final_result_json = aggregation
+            .into_final_result(Some(buckets), &test_schema())
+            .unwrap();
+        assert_eq!(
+            final_result_json,
+            json!({
+                "doc_count_error_upper_bound": 0,
+                "sum_other_doc_count": 2,
+                "buckets": [
+                    {"key": "frontend", "doc_count": 5, "unique_users": {"value": 2}},
+                    {"key": "backend", "doc_count": 2, "unique_users": {"value": 1}}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_terms_final_result_numeric_keys() {
+        let aggregation: QuickwitBucketAggregation = serde_json::from_value(json!({
+            "terms": {"field": "status", "order": {"_key": "asc"}},
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        let buckets = IntermediateBucketResult::Terms(BTreeMap::from_iter([
+            ("500".to_string(), cardinality_bucket(1, &["alice"])),
+            ("404".to_string(), cardinality_bucket(3, &["bob"])),
+        ]));
+        let final_result_json = aggregation
+            .into_final_result(Some(buckets), &test_schema())
+            .unwrap();
+        assert_eq!(final_result_json["buckets"][0]["key"], 404);
+        assert_eq!(final_result_json["buckets"][1]["key"], 500);
+    }
+
+    #[test]
+    fn test_bucket_aggregation_validate() {
+        let aggregation: QuickwitBucketAggregation = serde_json::from_value(json!({
+            "histogram": {"field": "service", "interval": 10},
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        assert!(aggregation.validate(&test_schema()).is_err());
+
+        let aggregation: QuickwitBucketAggregation = serde_json::from_value(json!({
+            "histogram": {"field": "status", "interval": 0},
+            "aggs": {"unique_users": {"cardinality": {"field": "service"}}}
+        }))
+        .unwrap();
+        assert_eq!(
+            aggregation.validate(&test_schema()).unwrap_err(),
+            "`interval` must be strictly positive."
+        );
+    }
+}
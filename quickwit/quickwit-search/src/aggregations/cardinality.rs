@@ -0,0 +1,326 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hasher;
+
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::AggregationColumn;
+
+/// Number of bits of the hash used to pick a register.
+const PRECISION: u32 = 14;
+
+const NUM_REGISTERS: usize = 1 << PRECISION;
+
+/// Computes the approximate number of distinct values of a fast field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CardinalityAggregation {
+    /// Name of the field to count the distinct values of.
+    pub field: String,
+}
+
+impl CardinalityAggregation {
+    pub(crate) fn into_final_result(self, sketch: &CardinalitySketch) -> JsonValue {
+        json!({ "value": sketch.estimate() })
+    }
+}
+
+/// Finalizer of MurmurHash3, which spreads the bits of the FNV hashes evenly.
+fn fmix64(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    hash
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(bytes);
+    fmix64(hasher.finish())
+}
+
+fn hash_numeric_value(value: f64) -> u64 {
+    // `-0.0` and `0.0` are the same value.
+    let value = if value == 0.0 { 0.0 } else { value };
+    hash_bytes(&value.to_bits().to_le_bytes())
+}
+
+/// HyperLogLog sketch estimating the number of distinct values of a set, with a standard error of
+/// about 0.8%.
+///
+/// Sketches are merged by taking the maximum of their registers, so merging the sketches of the
+/// segments, splits, and leaves is lossless.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SparseRegisters", into = "SparseRegisters")]
+pub struct CardinalitySketch {
+    // Empty until the first value is added.
+    registers: Vec<u8>,
+}
+
+/// Serialized form of a [`CardinalitySketch`], which only records the non-zero registers.
+#[derive(Serialize, Deserialize)]
+struct SparseRegisters {
+    #[serde(default)]
+    registers: BTreeMap<u16, u8>,
+}
+
+impl From<SparseRegisters> for CardinalitySketch {
+    fn from(sparse_registers: SparseRegisters) -> Self {
+        let mut sketch = CardinalitySketch::default();
+        for (index, value) in sparse_registers.registers {
+            sketch.update_register(index as usize % NUM_REGISTERS, value);
+        }
+        sketch
+    }
+}
+
+impl From<CardinalitySketch> for SparseRegisters {
+    fn from(sketch: CardinalitySketch) -> Self {
+        let registers = sketch
+            .registers
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| **value > 0)
+            .map(|(index, value)| (index as u16, *value))
+            .collect();
+        SparseRegisters { registers }
+    }
+}
+
+impl CardinalitySketch {
+    fn update_register(&mut self, index: usize, value: u8) {
+        if self.registers.is_empty() {
+            self.registers = vec![0; NUM_REGISTERS];
+        }
+        if self.registers[index] < value {
+            self.registers[index] = value;
+        }
+    }
+
+    fn add_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // The sentinel bit bounds the rank when the remaining bits are all zeros.
+        let remaining_bits = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = remaining_bits.leading_zeros() as u8 + 1;
+        self.update_register(index, rank);
+    }
+
+    /// Records a numeric value. `NaN` values are ignored.
+    pub fn add_numeric_value(&mut self, value: f64) {
+        if !value.is_nan() {
+            self.add_hash(hash_numeric_value(value));
+        }
+    }
+
+    /// Records a term.
+    pub fn add_term(&mut self, term: &[u8]) {
+        self.add_hash(hash_bytes(term));
+    }
+
+    /// Merges `other` into this sketch.
+    pub fn merge(&mut self, other: CardinalitySketch) {
+        if self.registers.is_empty() {
+            *self = other;
+            return;
+        }
+        for (index, value) in other.registers.into_iter().enumerate() {
+            self.update_register(index, value);
+        }
+    }
+
+    /// Returns the estimated number of distinct values.
+    pub fn estimate(&self) -> u64 {
+        if self.registers.is_empty() {
+            return 0;
+        }
+        let num_registers = NUM_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / num_registers);
+        let mut inverse_sum = 0.0;
+        let mut num_zero_registers = 0;
+
+        for value in &self.registers {
+            inverse_sum += 2f64.powi(-(*value as i32));
+            if *value == 0 {
+                num_zero_registers += 1;
+            }
+        }
+        let raw_estimate = alpha * num_registers * num_registers / inverse_sum;
+
+        // Small cardinalities are better estimated by linear counting.
+        if raw_estimate <= 2.5 * num_registers && num_zero_registers > 0 {
+            let linear_estimate = num_registers * (num_registers / num_zero_registers as f64).ln();
+            return linear_estimate.round() as u64;
+        }
+        raw_estimate.round() as u64
+    }
+}
+
+/// Records the distinct values of a field for the documents of a segment.
+pub(crate) struct CardinalitySegmentCollector {
+    column_opt: Option<AggregationColumn>,
+    term_ords_buffer: Vec<u64>,
+    values_buffer: Vec<f64>,
+}
+
+/// Values recorded by a [`CardinalitySegmentCollector`]. The terms of text fields are recorded
+/// by ordinal, and only hashed when the collector is harvested.
+#[derive(Default)]
+pub(crate) struct CardinalitySegmentState {
+    sketch: CardinalitySketch,
+    term_ords: HashSet<u64>,
+}
+
+impl CardinalitySegmentCollector {
+    pub fn new(
+        aggregation: &CardinalityAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let column_opt = AggregationColumn::open(segment_reader, &aggregation.field)?;
+        Ok(CardinalitySegmentCollector {
+            column_opt,
+            term_ords_buffer: Vec::new(),
+            values_buffer: Vec::new(),
+        })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId, state: &mut CardinalitySegmentState) {
+        match &self.column_opt {
+            Some(AggregationColumn::Numeric(column)) => {
+                column.get_vals(doc_id, &mut self.values_buffer);
+
+                for value in &self.values_buffer {
+                    state.sketch.add_numeric_value(*value);
+                }
+            }
+            Some(AggregationColumn::Text(column)) => {
+                column.term_ords(doc_id, &mut self.term_ords_buffer);
+                state
+                    .term_ords
+                    .extend(self.term_ords_buffer.iter().copied());
+            }
+            None => {}
+        }
+    }
+
+    pub fn harvest(&self, state: CardinalitySegmentState) -> tantivy::Result<CardinalitySketch> {
+        let mut sketch = state.sketch;
+
+        if let Some(AggregationColumn::Text(column)) = &self.column_opt {
+            let mut buffer = Vec::new();
+            for term_ord in state.term_ords {
+                let term = column.term(term_ord, &mut buffer)?;
+                sketch.add_term(term.as_bytes());
+            }
+        }
+        Ok(sketch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_within_error(actual: u64, expected: u64) {
+        let error = (actual as f64 - expected as f64).abs() / expected as f64;
+        assert!(error < 0.03, "{actual} is not within 3% of {expected}");
+    }
+
+    #[test]
+    fn test_cardinality_sketch() {
+        let mut sketch = CardinalitySketch::default();
+        assert_eq!(sketch.estimate(), 0);
+
+        for value in 0..10 {
+            sketch.add_numeric_value(value as f64);
+            sketch.add_numeric_value(value as f64);
+        }
+        assert_eq!(sketch.estimate(), 10);
+
+        for value in 0..100_000 {
+            sketch.add_numeric_value(value as f64);
+        }
+        sketch.add_numeric_value(f64::NAN);
+        assert_within_error(sketch.estimate(), 100_000);
+    }
+
+    #[test]
+    fn test_cardinality_sketch_terms() {
+        let mut sketch = CardinalitySketch::default();
+        for user_id in 0..5_000 {
+            sketch.add_term(format!("user-{user_id}").as_bytes());
+        }
+        assert_within_error(sketch.estimate(), 5_000);
+    }
+
+    #[test]
+    fn test_cardinality_sketch_merge() {
+        let mut left_sketch = CardinalitySketch::default();
+        let mut right_sketch = CardinalitySketch::default();
+        let mut sketch = CardinalitySketch::default();
+
+        for value in 0..20_000 {
+            if value % 2 == 0 {
+                left_sketch.add_numeric_value(value as f64);
+            } else {
+                right_sketch.add_numeric_value(value as f64);
+            }
+            // The values shared by both sketches are only counted once.
+            if value % 5 == 0 {
+                left_sketch.add_numeric_value(value as f64);
+                right_sketch.add_numeric_value(value as f64);
+            }
+            sketch.add_numeric_value(value as f64);
+        }
+        let right_sketch_json = serde_json::to_string(&right_sketch).unwrap();
+        let right_sketch: CardinalitySketch = serde_json::from_str(&right_sketch_json).unwrap();
+
+        left_sketch.merge(right_sketch);
+        left_sketch.merge(CardinalitySketch::default());
+        assert_eq!(left_sketch, sketch);
+        assert_within_error(left_sketch.estimate(), 20_000);
+    }
+
+    #[test]
+    fn test_cardinality_sketch_serialization() {
+        let sketch_json = serde_json::to_value(CardinalitySketch::default()).unwrap();
+        assert_eq!(sketch_json, json!({"registers": {}}));
+
+        let mut sketch = CardinalitySketch::default();
+        sketch.add_term(b"foo");
+        let sketch_json = serde_json::to_value(&sketch).unwrap();
+        assert_eq!(sketch_json["registers"].as_object().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_cardinality_aggregation_final_result() {
+        let mut sketch = CardinalitySketch::default();
+        sketch.add_term(b"foo");
+        sketch.add_term(b"bar");
+        let aggregation: CardinalityAggregation =
+            serde_json::from_value(json!({"field": "user_id"})).unwrap();
+        assert_eq!(aggregation.into_final_result(&sketch), json!({"value": 2}));
+    }
+}
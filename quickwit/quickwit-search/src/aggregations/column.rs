@@ -0,0 +1,224 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use tantivy::fastfield::{Column, FastValue, MultiValuedFastFieldReader};
+use tantivy::schema::{Cardinality, FieldType};
+use tantivy::{DateTime, DocId, InvertedIndexReader, SegmentReader, TantivyError};
+
+/// A fast field column read by the Quickwit aggregations.
+pub(crate) enum AggregationColumn {
+    Numeric(NumericColumn),
+    Text(TextColumn),
+}
+
+impl AggregationColumn {
+    /// Opens the fast field column of `field_name`, or returns `None` if the segment does not have
+    /// the field, e.g. if it was added to the doc mapping after the split was indexed.
+    pub fn open(
+        segment_reader: &SegmentReader,
+        field_name: &str,
+    ) -> tantivy::Result<Option<AggregationColumn>> {
+        let schema = segment_reader.schema();
+        let Ok(field) = schema.get_field(field_name) else {
+            return Ok(None);
+        };
+        let fast_fields = segment_reader.fast_fields();
+        let field_type = schema.get_field_entry(field).field_type();
+
+        if fast_field_cardinality(field_type) == Some(Cardinality::MultiValues) {
+            let column = match field_type {
+                FieldType::U64(_) => NumericColumn::U64s(fast_fields.u64s(field_name)?),
+                FieldType::I64(_) => NumericColumn::I64s(fast_fields.i64s(field_name)?),
+                FieldType::F64(_) => NumericColumn::F64s(fast_fields.f64s(field_name)?),
+                FieldType::Bool(_) => NumericColumn::Bools(fast_fields.bools(field_name)?),
+                _ => NumericColumn::DateTimes(fast_fields.dates(field_name)?),
+            };
+            return Ok(Some(AggregationColumn::Numeric(column)));
+        }
+        let column = match field_type {
+            FieldType::U64(_) => {
+                AggregationColumn::Numeric(NumericColumn::U64(fast_fields.u64(field_name)?))
+            }
+            FieldType::I64(_) => {
+                AggregationColumn::Numeric(NumericColumn::I64(fast_fields.i64(field_name)?))
+            }
+            FieldType::F64(_) => {
+                AggregationColumn::Numeric(NumericColumn::F64(fast_fields.f64(field_name)?))
+            }
+            FieldType::Bool(_) => {
+                AggregationColumn::Numeric(NumericColumn::Bool(fast_fields.bool(field_name)?))
+            }
+            FieldType::Date(_) => {
+                AggregationColumn::Numeric(NumericColumn::DateTime(fast_fields.date(field_name)?))
+            }
+            FieldType::Str(_) => AggregationColumn::Text(TextColumn {
+                term_ords_reader: fast_fields.u64s(field_name)?,
+                inverted_index_reader: segment_reader.inverted_index(field)?,
+            }),
+            _ => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field `{field_name}` cannot be aggregated."
+                )))
+            }
+        };
+        Ok(Some(column))
+    }
+
+    /// Opens the column of `field_name`, which must be numeric, or returns `None` if the segment
+    /// does not have the field.
+    pub fn open_numeric(
+        segment_reader: &SegmentReader,
+        field_name: &str,
+    ) -> tantivy::Result<Option<NumericColumn>> {
+        match AggregationColumn::open(segment_reader, field_name)? {
+            Some(AggregationColumn::Numeric(numeric_column)) => Ok(Some(numeric_column)),
+            Some(AggregationColumn::Text(_)) => Err(TantivyError::SchemaError(format!(
+                "Field `{field_name}` is not a numeric field."
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns the cardinality of the fast field of type `field_type`, or `None` if the field is not
+/// a numeric, bool, or datetime fast field.
+pub(crate) fn fast_field_cardinality(field_type: &FieldType) -> Option<Cardinality> {
+    match field_type {
+        FieldType::U64(options)
+        | FieldType::I64(options)
+        | FieldType::F64(options)
+        | FieldType::Bool(options) => options.get_fastfield_cardinality(),
+        FieldType::Date(options) => options.get_fastfield_cardinality(),
+        _ => None,
+    }
+}
+
+/// A single-valued or multi-valued numeric fast field column read as `f64`. Datetimes are read as
+/// microseconds since the Unix epoch, like in the tantivy aggregations.
+pub(crate) enum NumericColumn {
+    U64(Arc<dyn Column<u64>>),
+    I64(Arc<dyn Column<i64>>),
+    F64(Arc<dyn Column<f64>>),
+    Bool(Arc<dyn Column<bool>>),
+    DateTime(Arc<dyn Column<DateTime>>),
+    U64s(MultiValuedFastFieldReader<u64>),
+    I64s(MultiValuedFastFieldReader<i64>),
+    F64s(MultiValuedFastFieldReader<f64>),
+    Bools(MultiValuedFastFieldReader<bool>),
+    DateTimes(MultiValuedFastFieldReader<DateTime>),
+}
+
+fn bool_to_f64(value: bool) -> f64 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Appends the values of the document `doc_id` of the multi-valued `reader` to `values`.
+fn extend_with_vals<T: FastValue>(
+    reader: &MultiValuedFastFieldReader<T>,
+    doc_id: DocId,
+    to_f64: impl Fn(T) -> f64,
+    values: &mut Vec<f64>,
+) {
+    let mut typed_values = Vec::new();
+    reader.get_vals(doc_id, &mut typed_values);
+    values.extend(typed_values.into_iter().map(to_f64));
+}
+
+impl NumericColumn {
+    /// Fills `values` with the values of the document `doc_id`. The documents of a single-valued
+    /// column always have exactly one value, the documents of a multi-valued column may have
+    /// none.
+    pub fn get_vals(&self, doc_id: DocId, values: &mut Vec<f64>) {
+        values.clear();
+
+        match self {
+            NumericColumn::U64(column) => values.push(column.get_val(doc_id) as f64),
+            NumericColumn::I64(column) => values.push(column.get_val(doc_id) as f64),
+            NumericColumn::F64(column) => values.push(column.get_val(doc_id)),
+            NumericColumn::Bool(column) => values.push(bool_to_f64(column.get_val(doc_id))),
+            NumericColumn::DateTime(column) => {
+                values.push(column.get_val(doc_id).into_timestamp_micros() as f64)
+            }
+            NumericColumn::U64s(reader) => {
+                extend_with_vals(reader, doc_id, |value| value as f64, values)
+            }
+            NumericColumn::I64s(reader) => {
+                extend_with_vals(reader, doc_id, |value| value as f64, values)
+            }
+            NumericColumn::F64s(reader) => extend_with_vals(reader, doc_id, |value| value, values),
+            NumericColumn::Bools(reader) => extend_with_vals(reader, doc_id, bool_to_f64, values),
+            NumericColumn::DateTimes(reader) => extend_with_vals(
+                reader,
+                doc_id,
+                |value| value.into_timestamp_micros() as f64,
+                values,
+            ),
+        }
+    }
+}
+
+/// A text fast field column, which stores the ordinals of the terms of each document.
+pub(crate) struct TextColumn {
+    term_ords_reader: MultiValuedFastFieldReader<u64>,
+    inverted_index_reader: Arc<InvertedIndexReader>,
+}
+
+impl TextColumn {
+    /// Fills `term_ords` with the ordinals of the terms of the document `doc_id`.
+    pub fn term_ords(&self, doc_id: DocId, term_ords: &mut Vec<u64>) {
+        self.term_ords_reader.get_vals(doc_id, term_ords);
+    }
+
+    /// Returns the term of ordinal `term_ord`.
+    pub fn term(&self, term_ord: u64, buffer: &mut Vec<u8>) -> tantivy::Result<String> {
+        self.inverted_index_reader
+            .terms()
+            .ord_to_term(term_ord, buffer)?;
+        Ok(String::from_utf8_lossy(buffer).into_owned())
+    }
+
+    /// Returns the ordinal of the first term greater than or equal to `term`, or the number of
+    /// terms if there is none, and whether that term is equal to `term`.
+    pub fn seek_term(&self, term: &str) -> tantivy::Result<(u64, bool)> {
+        let term_dict = self.inverted_index_reader.terms();
+        let mut term_stream = term_dict.range().ge(term.as_bytes()).into_stream()?;
+
+        if term_stream.advance() {
+            Ok((term_stream.term_ord(), term_stream.key() == term.as_bytes()))
+        } else {
+            Ok((term_dict.num_terms() as u64, false))
+        }
+    }
+}
+
+/// Formats a numeric value as a terms bucket key, so that integers are formatted identically
+/// whatever the type of the field.
+pub(crate) fn format_numeric_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 {
+        format!("{}", value as i64)
+    } else {
+        format!("{value}")
+    }
+}
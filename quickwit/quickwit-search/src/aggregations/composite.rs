@@ -0,0 +1,931 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::schema::{FieldType, Schema};
+use tantivy::{DocId, SegmentReader, TantivyError};
+
+use super::bucket::{remove_metric_sub_aggregations, IntermediateBucket, Order, SegmentBucket};
+use super::column::{AggregationColumn, NumericColumn};
+use super::date_histogram::DateRounding;
+use super::{
+    validate_datetime_fast_field, validate_fast_field, validate_numeric_fast_field,
+    MetricSegmentCollector, QuickwitMetricAggregation,
+};
+
+fn default_composite_size() -> usize {
+    10
+}
+
+fn default_source_order() -> Order {
+    Order::Asc
+}
+
+/// Source of a composite aggregation bucketing the documents by term.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TermsCompositeSource {
+    /// Name of the fast field to read the terms from.
+    pub field: String,
+    /// Sort order of the terms.
+    #[serde(default = "default_source_order")]
+    pub order: Order,
+}
+
+/// Source of a composite aggregation bucketing the documents by fixed-width interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HistogramCompositeSource {
+    /// Name of the numeric fast field to read the values from.
+    pub field: String,
+    /// Width of the intervals.
+    pub interval: f64,
+    /// Sort order of the intervals.
+    #[serde(default = "default_source_order")]
+    pub order: Order,
+}
+
+/// Source of a composite aggregation bucketing the documents by fixed or calendar interval. See
+/// [`DateHistogramAggregation`](super::DateHistogramAggregation) for the parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateHistogramCompositeSource {
+    /// Name of the datetime fast field to read the values from.
+    pub field: String,
+    /// Fixed width of the intervals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed_interval: Option<String>,
+    /// Calendar unit of the intervals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_interval: Option<String>,
+    /// Time zone of the intervals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Shift of the interval boundaries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+    /// Sort order of the intervals.
+    #[serde(default = "default_source_order")]
+    pub order: Order,
+}
+
+impl DateHistogramCompositeSource {
+    fn rounding(&self) -> Result<DateRounding, String> {
+        DateRounding::new(
+            self.fixed_interval.as_deref(),
+            self.calendar_interval.as_deref(),
+            self.time_zone.as_deref(),
+            self.offset.as_deref(),
+        )
+    }
+}
+
+/// Source of the values of a composite aggregation key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositeSource {
+    /// Terms of a field.
+    Terms(TermsCompositeSource),
+    /// Fixed-width intervals of a numeric field.
+    Histogram(HistogramCompositeSource),
+    /// Time zone- and calendar-aware intervals of a datetime field.
+    DateHistogram(DateHistogramCompositeSource),
+}
+
+impl CompositeSource {
+    fn field_name(&self) -> &str {
+        match self {
+            CompositeSource::Terms(source) => &source.field,
+            CompositeSource::Histogram(source) => &source.field,
+            CompositeSource::DateHistogram(source) => &source.field,
+        }
+    }
+
+    fn order(&self) -> Order {
+        match self {
+            CompositeSource::Terms(source) => source.order,
+            CompositeSource::Histogram(source) => source.order,
+            CompositeSource::DateHistogram(source) => source.order,
+        }
+    }
+
+    fn validate(&self, schema: &Schema) -> Result<(), String> {
+        match self {
+            CompositeSource::Terms(source) => validate_fast_field(schema, &source.field),
+            CompositeSource::Histogram(source) => {
+                if source.interval <= 0.0 {
+                    return Err("`interval` must be strictly positive.".to_string());
+                }
+                validate_numeric_fast_field(schema, &source.field)
+            }
+            CompositeSource::DateHistogram(source) => {
+                source.rounding()?;
+                validate_datetime_fast_field(schema, &source.field)
+            }
+        }
+    }
+
+    /// Returns whether the values of the source are terms rather than numbers.
+    fn has_term_values(&self, schema: &Schema) -> bool {
+        let CompositeSource::Terms(source) = self else {
+            return false;
+        };
+        schema
+            .get_field(&source.field)
+            .map(|field| {
+                matches!(
+                    schema.get_field_entry(field).field_type(),
+                    FieldType::Str(_)
+                )
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Value of a source in the key of a composite bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CompositeKeyValue {
+    /// Numeric value, or start of a date histogram interval in milliseconds since the Unix
+    /// epoch.
+    Numeric(f64),
+    /// Term of a text field.
+    Term(String),
+}
+
+impl CompositeKeyValue {
+    fn compare(&self, other: &CompositeKeyValue) -> Ordering {
+        match (self, other) {
+            (CompositeKeyValue::Numeric(left), CompositeKeyValue::Numeric(right)) => {
+                left.total_cmp(right)
+            }
+            (CompositeKeyValue::Term(left), CompositeKeyValue::Term(right)) => left.cmp(right),
+            (CompositeKeyValue::Numeric(_), CompositeKeyValue::Term(_)) => Ordering::Less,
+            (CompositeKeyValue::Term(_), CompositeKeyValue::Numeric(_)) => Ordering::Greater,
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        match self {
+            CompositeKeyValue::Numeric(value)
+                if value.fract() == 0.0 && value.abs() < (1u64 << 53) as f64 =>
+            {
+                json!(*value as i64)
+            }
+            CompositeKeyValue::Numeric(value) => json!(value),
+            CompositeKeyValue::Term(term) => json!(term),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositeParams {
+    sources: Vec<BTreeMap<String, CompositeSource>>,
+    #[serde(default = "default_composite_size")]
+    size: usize,
+    #[serde(default)]
+    after: Option<BTreeMap<String, CompositeKeyValue>>,
+}
+
+/// Creates a bucket per combination of the values of several sources, e.g. service and status,
+/// sorted by combination. The buckets are paginated: the `after_key` of a page is passed as the
+/// `after` parameter of the request of the next page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositeAggregation {
+    /// Sources of the bucket keys, by name.
+    pub sources: Vec<(String, CompositeSource)>,
+    /// Number of buckets per page.
+    pub size: usize,
+    /// Values of the key after which the page starts, in source order.
+    pub after_opt: Option<Vec<CompositeKeyValue>>,
+    /// Metric aggregations computed for each bucket, by name.
+    pub sub_aggregations: BTreeMap<String, QuickwitMetricAggregation>,
+}
+
+impl CompositeAggregation {
+    pub(crate) fn is_composite_aggregation(aggregation_json: &JsonValue) -> bool {
+        aggregation_json
+            .as_object()
+            .map(|aggregation_obj| aggregation_obj.contains_key("composite"))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.sources
+            .iter()
+            .map(|(_, source)| source.field_name())
+            .chain(
+                self.sub_aggregations
+                    .values()
+                    .map(QuickwitMetricAggregation::field_name),
+            )
+    }
+
+    pub(crate) fn term_dict_field_names(&self) -> impl Iterator<Item = &str> {
+        self.sources
+            .iter()
+            .filter_map(|(_, source)| match source {
+                CompositeSource::Terms(source) => Some(source.field.as_str()),
+                _ => None,
+            })
+            .chain(
+                self.sub_aggregations
+                    .values()
+                    .filter_map(QuickwitMetricAggregation::term_dict_field_name),
+            )
+    }
+
+    pub(crate) fn validate(&self, schema: &Schema) -> Result<(), String> {
+        if self.size == 0 {
+            return Err("`size` must be strictly positive.".to_string());
+        }
+        for (name, source) in &self.sources {
+            source
+                .validate(schema)
+                .map_err(|error| format!("Source `{name}` is invalid: {error}"))?;
+        }
+        if let Some(after) = &self.after_opt {
+            for ((name, source), value) in self.sources.iter().zip(after) {
+                let is_term = matches!(value, CompositeKeyValue::Term(_));
+
+                if is_term != source.has_term_values(schema) {
+                    let expected_type = if source.has_term_values(schema) {
+                        "a string"
+                    } else {
+                        "a number"
+                    };
+                    return Err(format!(
+                        "The `after` value of source `{name}` must be {expected_type}."
+                    ));
+                }
+            }
+        }
+        for (name, sub_aggregation) in &self.sub_aggregations {
+            sub_aggregation
+                .validate(schema)
+                .map_err(|error| format!("Sub-aggregation `{name}` is invalid: {error}"))?;
+        }
+        Ok(())
+    }
+
+    fn compare_keys(&self, left: &[CompositeKeyValue], right: &[CompositeKeyValue]) -> Ordering {
+        for ((left_value, right_value), (_, source)) in left.iter().zip(right).zip(&self.sources) {
+            let ordering = match source.order() {
+                Order::Asc => left_value.compare(right_value),
+                Order::Desc => right_value.compare(left_value),
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    }
+
+    fn key_to_json(&self, key: &[CompositeKeyValue]) -> JsonValue {
+        let key_json: JsonMap<String, JsonValue> = self
+            .sources
+            .iter()
+            .zip(key)
+            .map(|((name, _), value)| (name.clone(), value.to_json()))
+            .collect();
+        JsonValue::Object(key_json)
+    }
+
+    pub(crate) fn into_final_result(
+        self,
+        intermediate_result_opt: Option<IntermediateCompositeResult>,
+    ) -> JsonValue {
+        let mut buckets: Vec<IntermediateCompositeBucket> = intermediate_result_opt
+            .map(|intermediate_result| intermediate_result.buckets.into_values().collect())
+            .unwrap_or_default();
+        buckets.sort_by(|left, right| self.compare_keys(&left.key, &right.key));
+        // Each leaf returns its first `size` buckets, which include all the leaf buckets of the
+        // first `size` buckets overall.
+        buckets.truncate(self.size);
+
+        let mut result_json = JsonMap::new();
+
+        if let Some(last_bucket) = buckets.last() {
+            result_json.insert("after_key".to_string(), self.key_to_json(&last_bucket.key));
+        }
+        let buckets_json: Vec<JsonValue> = buckets
+            .into_iter()
+            .map(|composite_bucket| {
+                let mut bucket_json = JsonMap::new();
+                bucket_json.insert("key".to_string(), self.key_to_json(&composite_bucket.key));
+                composite_bucket
+                    .bucket
+                    .into_final_result(&self.sub_aggregations, bucket_json)
+            })
+            .collect();
+        result_json.insert("buckets".to_string(), JsonValue::Array(buckets_json));
+        JsonValue::Object(result_json)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompositeAggregation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let mut aggregation_json = JsonMap::<String, JsonValue>::deserialize(deserializer)?;
+        let sub_aggregations =
+            remove_metric_sub_aggregations(&mut aggregation_json).map_err(D::Error::custom)?;
+        let params_json = aggregation_json
+            .remove("composite")
+            .ok_or_else(|| D::Error::missing_field("composite"))?;
+        if let Some(key) = aggregation_json.keys().next() {
+            return Err(D::Error::custom(format!(
+                "unknown key `{key}` in composite aggregation"
+            )));
+        }
+        let params: CompositeParams =
+            serde_json::from_value(params_json).map_err(D::Error::custom)?;
+
+        if params.sources.is_empty() {
+            return Err(D::Error::custom("`sources` must not be empty"));
+        }
+        let mut source_names = HashSet::new();
+        let mut sources = Vec::with_capacity(params.sources.len());
+
+        for source_json in params.sources {
+            if source_json.len() != 1 {
+                return Err(D::Error::custom(
+                    "each source must be an object with a single key, the name of the source",
+                ));
+            }
+            let (name, source) = source_json
+                .into_iter()
+                .next()
+                .expect("The source should have a single key.");
+            if !source_names.insert(name.clone()) {
+                return Err(D::Error::custom(format!(
+                    "source `{name}` is defined twice"
+                )));
+            }
+            sources.push((name, source));
+        }
+        let after_opt = match params.after {
+            Some(mut after) => {
+                let after_values = sources
+                    .iter()
+                    .map(|(name, _)| {
+                        after.remove(name).ok_or_else(|| {
+                            D::Error::custom(format!("`after` is missing source `{name}`"))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if let Some(name) = after.keys().next() {
+                    return Err(D::Error::custom(format!(
+                        "`after` contains the unknown source `{name}`"
+                    )));
+                }
+                Some(after_values)
+            }
+            None => None,
+        };
+        Ok(CompositeAggregation {
+            sources,
+            size: params.size,
+            after_opt,
+            sub_aggregations,
+        })
+    }
+}
+
+/// A bucket of a [`CompositeAggregation`] and its key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateCompositeBucket {
+    key: Vec<CompositeKeyValue>,
+    bucket: IntermediateBucket,
+}
+
+/// Intermediate result of a [`CompositeAggregation`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateCompositeResult {
+    // Buckets by key serialized as JSON.
+    buckets: BTreeMap<String, IntermediateCompositeBucket>,
+}
+
+impl IntermediateCompositeResult {
+    pub(crate) fn merge(&mut self, other: IntermediateCompositeResult) {
+        for (key, other_bucket) in other.buckets {
+            match self.buckets.get_mut(&key) {
+                Some(composite_bucket) => composite_bucket.bucket.merge(other_bucket.bucket),
+                None => {
+                    self.buckets.insert(key, other_bucket);
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, key: Vec<CompositeKeyValue>, bucket: IntermediateBucket) {
+        let key_json =
+            serde_json::to_string(&key).expect("Composite keys should be serializable to JSON.");
+        self.buckets
+            .insert(key_json, IntermediateCompositeBucket { key, bucket });
+    }
+}
+
+/// Maps a float to an integer with the same ordering.
+fn f64_to_sortable_u64(value: f64) -> u64 {
+    let bits = value.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    }
+}
+
+fn sortable_u64_to_f64(sortable_value: u64) -> f64 {
+    let bits = if sortable_value >> 63 == 1 {
+        sortable_value & !(1 << 63)
+    } else {
+        !sortable_value
+    };
+    f64::from_bits(bits)
+}
+
+enum SourceColumn {
+    Terms(AggregationColumn),
+    Histogram {
+        column: NumericColumn,
+        interval: f64,
+    },
+    DateHistogram {
+        column: NumericColumn,
+        rounding: DateRounding,
+    },
+}
+
+/// Source of a composite aggregation at the scale of the segment.
+///
+/// The values of the source are encoded as ranks, ordered like the values in the order of the
+/// source: term ordinals, sortable bits of numeric values, or date histogram interval starts,
+/// doubled, and negated for the descending sources. The odd ranks encode the `after` terms that
+/// are missing from the term dictionary of the segment.
+struct SegmentSource {
+    column: SourceColumn,
+    order: Order,
+}
+
+impl SegmentSource {
+    fn apply_order(&self, rank: i128) -> i128 {
+        match self.order {
+            Order::Asc => rank,
+            Order::Desc => -rank,
+        }
+    }
+
+    fn numeric_rank(value: f64) -> i128 {
+        f64_to_sortable_u64(value) as i128 * 2
+    }
+
+    /// Fills `ranks` with the distinct ranks of the values of the document `doc_id`.
+    fn ranks(
+        &self,
+        doc_id: DocId,
+        ranks: &mut Vec<i128>,
+        term_ords_buffer: &mut Vec<u64>,
+        values_buffer: &mut Vec<f64>,
+    ) {
+        ranks.clear();
+
+        match &self.column {
+            SourceColumn::Terms(AggregationColumn::Text(column)) => {
+                column.term_ords(doc_id, term_ords_buffer);
+                ranks.extend(
+                    term_ords_buffer
+                        .iter()
+                        .map(|term_ord| *term_ord as i128 * 2),
+                );
+            }
+            SourceColumn::Terms(AggregationColumn::Numeric(column)) => {
+                column.get_vals(doc_id, values_buffer);
+                ranks.extend(values_buffer.iter().map(|value| Self::numeric_rank(*value)));
+            }
+            SourceColumn::Histogram { column, interval } => {
+                column.get_vals(doc_id, values_buffer);
+                ranks.extend(
+                    values_buffer
+                        .iter()
+                        .filter(|value| !value.is_nan())
+                        .map(|value| Self::numeric_rank((value / interval).floor() * interval)),
+                );
+            }
+            SourceColumn::DateHistogram { column, rounding } => {
+                column.get_vals(doc_id, values_buffer);
+                // Datetimes are read as microseconds.
+                ranks.extend(values_buffer.iter().map(|value| {
+                    let timestamp_millis = (*value as i64).div_euclid(1_000);
+                    rounding.round_down(timestamp_millis) as i128 * 2
+                }));
+            }
+        }
+        for rank in ranks.iter_mut() {
+            *rank = self.apply_order(*rank);
+        }
+        ranks.sort_unstable();
+        ranks.dedup();
+    }
+
+    fn after_rank(&self, value: &CompositeKeyValue) -> tantivy::Result<i128> {
+        let rank = match (&self.column, value) {
+            (
+                SourceColumn::Terms(AggregationColumn::Text(column)),
+                CompositeKeyValue::Term(term),
+            ) => {
+                let (term_ord, is_exact_match) = column.seek_term(term)?;
+                if is_exact_match {
+                    term_ord as i128 * 2
+                } else {
+                    term_ord as i128 * 2 - 1
+                }
+            }
+            (
+                SourceColumn::Terms(AggregationColumn::Numeric(_)) | SourceColumn::Histogram { .. },
+                CompositeKeyValue::Numeric(value),
+            ) => Self::numeric_rank(*value),
+            (SourceColumn::DateHistogram { .. }, CompositeKeyValue::Numeric(timestamp_millis)) => {
+                *timestamp_millis as i64 as i128 * 2
+            }
+            _ => {
+                return Err(TantivyError::InvalidArgument(
+                    "The `after` key does not match the types of the composite sources."
+                        .to_string(),
+                ))
+            }
+        };
+        Ok(self.apply_order(rank))
+    }
+
+    fn key_value(&self, rank: i128, buffer: &mut Vec<u8>) -> tantivy::Result<CompositeKeyValue> {
+        let rank = self.apply_order(rank) / 2;
+        let key_value = match &self.column {
+            SourceColumn::Terms(AggregationColumn::Text(column)) => {
+                CompositeKeyValue::Term(column.term(rank as u64, buffer)?)
+            }
+            SourceColumn::Terms(AggregationColumn::Numeric(_)) | SourceColumn::Histogram { .. } => {
+                CompositeKeyValue::Numeric(sortable_u64_to_f64(rank as u64))
+            }
+            SourceColumn::DateHistogram { .. } => CompositeKeyValue::Numeric(rank as f64),
+        };
+        Ok(key_value)
+    }
+}
+
+/// Collects a [`CompositeAggregation`] at the scale of the segment. Only the first `size`
+/// buckets after the `after` key are kept, so the number of buckets is bounded by `size`.
+pub(crate) struct CompositeSegmentCollector {
+    // `None` if the segment does not have one of the source fields.
+    sources_opt: Option<Vec<SegmentSource>>,
+    after_ranks_opt: Option<Vec<i128>>,
+    size: usize,
+    sub_collectors: Vec<(String, MetricSegmentCollector)>,
+    // Buckets by source ranks, which are ordered like the bucket keys.
+    buckets: BTreeMap<Vec<i128>, SegmentBucket>,
+    source_ranks: Vec<Vec<i128>>,
+    value_idxs: Vec<usize>,
+    term_ords_buffer: Vec<u64>,
+    values_buffer: Vec<f64>,
+}
+
+impl CompositeSegmentCollector {
+    pub fn new(
+        aggregation: &CompositeAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let mut sources = Vec::with_capacity(aggregation.sources.len());
+
+        for (_, source) in &aggregation.sources {
+            let column_opt = match source {
+                CompositeSource::Terms(terms) => {
+                    AggregationColumn::open(segment_reader, &terms.field)?.map(SourceColumn::Terms)
+                }
+                CompositeSource::Histogram(histogram) => {
+                    AggregationColumn::open_numeric(segment_reader, &histogram.field)?.map(
+                        |column| SourceColumn::Histogram {
+                            column,
+                            interval: histogram.interval,
+                        },
+                    )
+                }
+                CompositeSource::DateHistogram(date_histogram) => {
+                    let rounding = date_histogram
+                        .rounding()
+                        .map_err(TantivyError::InvalidArgument)?;
+                    AggregationColumn::open_numeric(segment_reader, &date_histogram.field)?
+                        .map(|column| SourceColumn::DateHistogram { column, rounding })
+                }
+            };
+            let Some(column) = column_opt else {
+                break;
+            };
+            sources.push(SegmentSource {
+                column,
+                order: source.order(),
+            });
+        }
+        let sources_opt = if sources.len() == aggregation.sources.len() {
+            Some(sources)
+        } else {
+            None
+        };
+        let after_ranks_opt = match (&sources_opt, &aggregation.after_opt) {
+            (Some(sources), Some(after)) => Some(
+                sources
+                    .iter()
+                    .zip(after)
+                    .map(|(source, value)| source.after_rank(value))
+                    .collect::<tantivy::Result<_>>()?,
+            ),
+            _ => None,
+        };
+        let sub_collectors = aggregation
+            .sub_aggregations
+            .iter()
+            .map(|(name, sub_aggregation)| {
+                let sub_collector = MetricSegmentCollector::new(sub_aggregation, segment_reader)?;
+                Ok((name.clone(), sub_collector))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        let num_sources = aggregation.sources.len();
+
+        Ok(CompositeSegmentCollector {
+            sources_opt,
+            after_ranks_opt,
+            size: aggregation.size,
+            sub_collectors,
+            buckets: BTreeMap::new(),
+            source_ranks: vec![Vec::new(); num_sources],
+            value_idxs: vec![0; num_sources],
+            term_ords_buffer: Vec::new(),
+            values_buffer: Vec::new(),
+        })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId) {
+        let Some(sources) = &self.sources_opt else {
+            return;
+        };
+        for (source, ranks) in sources.iter().zip(self.source_ranks.iter_mut()) {
+            source.ranks(
+                doc_id,
+                ranks,
+                &mut self.term_ords_buffer,
+                &mut self.values_buffer,
+            );
+
+            if ranks.is_empty() {
+                return;
+            }
+        }
+        // Collects the document in the bucket of each combination of its source values.
+        self.value_idxs.fill(0);
+
+        loop {
+            let key: Vec<i128> = self
+                .source_ranks
+                .iter()
+                .zip(&self.value_idxs)
+                .map(|(ranks, value_idx)| ranks[*value_idx])
+                .collect();
+            self.collect_in_bucket(key, doc_id);
+
+            let mut source_idx = self.value_idxs.len();
+            loop {
+                if source_idx == 0 {
+                    return;
+                }
+                source_idx -= 1;
+                self.value_idxs[source_idx] += 1;
+
+                if self.value_idxs[source_idx] < self.source_ranks[source_idx].len() {
+                    break;
+                }
+                self.value_idxs[source_idx] = 0;
+            }
+        }
+    }
+
+    fn collect_in_bucket(&mut self, key: Vec<i128>, doc_id: DocId) {
+        if let Some(after_ranks) = &self.after_ranks_opt {
+            if key <= *after_ranks {
+                return;
+            }
+        }
+        if let Some(bucket) = self.buckets.get_mut(&key) {
+            bucket.collect(doc_id, &mut self.sub_collectors);
+            return;
+        }
+        if self.buckets.len() >= self.size {
+            // Only the first `size` buckets can be returned.
+            if matches!(self.buckets.last_key_value(), Some((last_key, _)) if key > *last_key) {
+                return;
+            }
+            self.buckets.pop_last();
+        }
+        let mut bucket = SegmentBucket::new(&self.sub_collectors);
+        bucket.collect(doc_id, &mut self.sub_collectors);
+        self.buckets.insert(key, bucket);
+    }
+
+    pub fn harvest(self) -> tantivy::Result<IntermediateCompositeResult> {
+        let mut result = IntermediateCompositeResult::default();
+
+        let Some(sources) = &self.sources_opt else {
+            return Ok(result);
+        };
+        let mut buffer = Vec::new();
+
+        for (ranks, bucket) in self.buckets {
+            let key = sources
+                .iter()
+                .zip(ranks)
+                .map(|(source, rank)| source.key_value(rank, &mut buffer))
+                .collect::<tantivy::Result<_>>()?;
+            result.insert(key, bucket.harvest(&self.sub_collectors)?);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{SchemaBuilder, FAST, STRING};
+
+    use super::*;
+    use crate::aggregations::{CardinalitySketch, IntermediateMetricResult};
+
+    fn test_schema() -> Schema {
+        let mut schema_builder = SchemaBuilder::new();
+        schema_builder.add_date_field("timestamp", FAST);
+        schema_builder.add_text_field("service", STRING | FAST);
+        schema_builder.add_u64_field("status", FAST);
+        schema_builder.add_text_field("user_id", STRING | FAST);
+        schema_builder.build()
+    }
+
+    fn composite_result(buckets: &[(&str, u64, u64)]) -> IntermediateCompositeResult {
+        let mut result = IntermediateCompositeResult::default();
+        for (service, status, doc_count) in buckets {
+            let mut sketch = CardinalitySketch::default();
+            sketch.add_term(service.as_bytes());
+            let bucket = IntermediateBucket {
+                doc_count: *doc_count,
+                sub_results: BTreeMap::from_iter([(
+                    "unique_users".to_string(),
+                    IntermediateMetricResult::Cardinality(sketch),
+                )]),
+            };
+            let key = vec![
+                CompositeKeyValue::Term(service.to_string()),
+                CompositeKeyValue::Numeric(*status as f64),
+            ];
+            result.insert(key, bucket);
+        }
+        result
+    }
+
+    #[test]
+    fn test_sortable_u64() {
+        let values = [f64::MIN, -10.5, -0.0, 0.0, 1e-9, 42.0, f64::MAX];
+        for window in values.windows(2) {
+            assert!(f64_to_sortable_u64(window[0]) < f64_to_sortable_u64(window[1]));
+        }
+        for value in values {
+            assert_eq!(sortable_u64_to_f64(f64_to_sortable_u64(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_composite_aggregation_deserialize() {
+        let aggregation: CompositeAggregation = serde_json::from_value(json!({
+            "composite": {
+                "size": 2,
+                "sources": [
+                    {"service": {"terms": {"field": "service"}}},
+                    {"status": {"terms": {"field": "status", "order": "desc"}}},
+                    {"hour": {"date_histogram": {"field": "timestamp", "fixed_interval": "1h"}}}
+                ],
+                "after": {"service": "api", "status": 500, "hour": 1_672_531_200_000i64}
+            },
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        assert_eq!(aggregation.size, 2);
+        assert_eq!(
+            aggregation
+                .sources
+                .iter()
+                .map(|(name, source)| (name.as_str(), source.order()))
+                .collect::<Vec<_>>(),
+            [
+                ("service", Order::Asc),
+                ("status", Order::Desc),
+                ("hour", Order::Asc)
+            ]
+        );
+        assert_eq!(
+            aggregation.after_opt.as_deref().unwrap(),
+            [
+                CompositeKeyValue::Term("api".to_string()),
+                CompositeKeyValue::Numeric(500.0),
+                CompositeKeyValue::Numeric(1_672_531_200_000.0)
+            ]
+        );
+        aggregation.validate(&test_schema()).unwrap();
+
+        let aggregation: CompositeAggregation = serde_json::from_value(json!({
+            "composite": {
+                "sources": [{"service": {"terms": {"field": "service"}}}],
+                "after": {"service": 42}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            aggregation.validate(&test_schema()).unwrap_err(),
+            "The `after` value of source `service` must be a string."
+        );
+        for aggregation_json in [
+            json!({"composite": {"sources": []}}),
+            json!({
+                "composite": {
+                    "sources": [
+                        {"service": {"terms": {"field": "service"}}},
+                        {"service": {"terms": {"field": "status"}}}
+                    ]
+                }
+            }),
+            json!({
+                "composite": {
+                    "sources": [{"service": {"terms": {"field": "service"}}}],
+                    "after": {"status": 200}
+                }
+            }),
+        ] {
+            serde_json::from_value::<CompositeAggregation>(aggregation_json).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_composite_aggregation_final_result() {
+        let aggregation: CompositeAggregation = serde_json::from_value(json!({
+            "composite": {
+                "size": 3,
+                "sources": [
+                    {"service": {"terms": {"field": "service"}}},
+                    {"status": {"terms": {"field": "status", "order": "desc"}}}
+                ]
+            },
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }))
+        .unwrap();
+        let mut result = composite_result(&[("api", 200, 2), ("web", 200, 1)]);
+        result.merge(composite_result(&[("api", 200, 3), ("api", 500, 1)]));
+
+        assert_eq!(
+            aggregation.into_final_result(Some(result)),
+            json!({
+                "after_key": {"service": "web", "status": 200},
+                "buckets": [
+                    {
+                        "key": {"service": "api", "status": 500},
+                        "doc_count": 1,
+                        "unique_users": {"value": 1}
+                    },
+                    {
+                        "key": {"service": "api", "status": 200},
+                        "doc_count": 5,
+                        "unique_users": {"value": 1}
+                    },
+                    {
+                        "key": {"service": "web", "status": 200},
+                        "doc_count": 1,
+                        "unique_users": {"value": 1}
+                    }
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,696 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use chrono::{
+    Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, SecondsFormat, TimeZone,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::bucket::IntermediateBucket;
+use super::QuickwitMetricAggregation;
+use crate::collector::AGGREGATION_BUCKET_LIMIT;
+use crate::SearchError;
+
+const MINUTE_MILLIS: i64 = 60_000;
+const HOUR_MILLIS: i64 = 60 * MINUTE_MILLIS;
+const DAY_MILLIS: i64 = 24 * HOUR_MILLIS;
+
+/// Creates a bucket per fixed or calendar interval of the values of a datetime fast field. The
+/// buckets are aligned on the local time of `time_zone`, so calendar buckets follow the DST
+/// transitions: a daily bucket lasts 23 or 25 hours on the days the clocks change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DateHistogramAggregation {
+    /// Name of the datetime field to aggregate on.
+    pub field: String,
+    /// Fixed width of the buckets, e.g. `30m`, `1h`, or `1d`. Supported units are `ms`, `s`, `m`,
+    /// `h`, and `d`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixed_interval: Option<String>,
+    /// Calendar unit of the buckets: `minute`, `hour`, `day`, `week`, `month`, `quarter`, or
+    /// `year`, or their single-unit forms `1m`, `1h`, `1d`, `1w`, `1M`, `1q`, and `1y`. Weeks
+    /// start on Monday.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_interval: Option<String>,
+    /// Time zone of the buckets, either an IANA time zone name such as `Europe/Paris` or a UTC
+    /// offset such as `-05:00`. Defaults to UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+    /// Shift of the bucket boundaries, e.g. `+6h` for days starting at 6 AM.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<String>,
+    /// Minimum number of documents of the returned buckets.
+    #[serde(default)]
+    pub min_doc_count: u64,
+    /// Whether the buckets are returned as a map keyed by formatted bucket key or as a list.
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+impl DateHistogramAggregation {
+    /// Returns the rounding of the timestamps into the buckets of the aggregation.
+    pub(crate) fn rounding(&self) -> Result<DateRounding, String> {
+        DateRounding::new(
+            self.fixed_interval.as_deref(),
+            self.calendar_interval.as_deref(),
+            self.time_zone.as_deref(),
+            self.offset.as_deref(),
+        )
+    }
+
+    pub(crate) fn into_final_result(
+        &self,
+        sub_aggregations: &BTreeMap<String, QuickwitMetricAggregation>,
+        mut buckets: BTreeMap<i64, IntermediateBucket>,
+    ) -> crate::Result<JsonValue> {
+        let rounding = self
+            .rounding()
+            .map_err(SearchError::InvalidAggregationRequest)?;
+
+        // Like the histogram, we return the empty buckets between the first and the last
+        // non-empty buckets when `min_doc_count` is 0.
+        if self.min_doc_count == 0 {
+            if let (Some(first_bucket_start), Some(last_bucket_start)) = (
+                buckets.keys().next().copied(),
+                buckets.keys().next_back().copied(),
+            ) {
+                let mut bucket_start = first_bucket_start;
+                let mut num_buckets = 1;
+
+                while bucket_start < last_bucket_start {
+                    bucket_start = rounding.next_bucket_start(bucket_start);
+                    num_buckets += 1;
+
+                    if num_buckets > AGGREGATION_BUCKET_LIMIT {
+                        return Err(SearchError::InvalidAggregationRequest(format!(
+                            "Date histogram `{}` has too many buckets (limit: \
+                             {AGGREGATION_BUCKET_LIMIT}).",
+                            self.field
+                        )));
+                    }
+                    buckets.entry(bucket_start).or_default();
+                }
+            }
+        }
+        let buckets_json = buckets
+            .into_iter()
+            .filter(|(_, bucket)| bucket.doc_count >= self.min_doc_count)
+            .map(|(bucket_start, bucket)| {
+                let key_as_string = rounding.format(bucket_start);
+                let mut bucket_json = JsonMap::new();
+                bucket_json.insert("key".to_string(), json!(bucket_start));
+                bucket_json.insert("key_as_string".to_string(), json!(key_as_string));
+                (
+                    key_as_string,
+                    bucket.into_final_result(sub_aggregations, bucket_json),
+                )
+            });
+        let buckets_json = if self.keyed {
+            JsonValue::Object(buckets_json.collect())
+        } else {
+            JsonValue::Array(buckets_json.map(|(_, bucket_json)| bucket_json).collect())
+        };
+        Ok(json!({ "buckets": buckets_json }))
+    }
+}
+
+/// Parses a duration such as `30m` into milliseconds.
+fn parse_duration_millis(duration: &str) -> Option<i64> {
+    let unit_pos = duration.find(|ch: char| !ch.is_ascii_digit())?;
+    let (value, unit) = duration.split_at(unit_pos);
+    let value: i64 = value.parse().ok()?;
+    let unit_millis = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => MINUTE_MILLIS,
+        "h" => HOUR_MILLIS,
+        "d" => DAY_MILLIS,
+        _ => return None,
+    };
+    value.checked_mul(unit_millis)
+}
+
+/// Parses an optionally signed duration such as `+6h` or `-30m` into milliseconds.
+fn parse_offset_millis(offset: &str) -> Option<i64> {
+    if let Some(duration) = offset.strip_prefix('-') {
+        parse_duration_millis(duration).map(|duration_millis| -duration_millis)
+    } else {
+        parse_duration_millis(offset.strip_prefix('+').unwrap_or(offset))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CalendarUnit {
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl CalendarUnit {
+    fn parse(calendar_interval: &str) -> Option<CalendarUnit> {
+        let unit = match calendar_interval {
+            "minute" | "1m" => CalendarUnit::Minute,
+            "hour" | "1h" => CalendarUnit::Hour,
+            "day" | "1d" => CalendarUnit::Day,
+            "week" | "1w" => CalendarUnit::Week,
+            "month" | "1M" => CalendarUnit::Month,
+            "quarter" | "1q" => CalendarUnit::Quarter,
+            "year" | "1y" => CalendarUnit::Year,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Returns the first day of the unit containing `date`.
+    fn truncate(&self, date: NaiveDate) -> NaiveDate {
+        let first_day_opt = match self {
+            CalendarUnit::Minute | CalendarUnit::Hour | CalendarUnit::Day => Some(date),
+            CalendarUnit::Week => {
+                Some(date - Duration::days(date.weekday().num_days_from_monday() as i64))
+            }
+            CalendarUnit::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1),
+            CalendarUnit::Quarter => {
+                NaiveDate::from_ymd_opt(date.year(), (date.month() - 1) / 3 * 3 + 1, 1)
+            }
+            CalendarUnit::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1),
+        };
+        first_day_opt.unwrap_or(date)
+    }
+
+    /// Returns a duration longer than any unit but shorter than any two units, whatever the DST
+    /// transitions.
+    fn stride_millis(&self) -> i64 {
+        match self {
+            CalendarUnit::Minute => MINUTE_MILLIS,
+            CalendarUnit::Hour => HOUR_MILLIS,
+            CalendarUnit::Day => DAY_MILLIS + 2 * HOUR_MILLIS,
+            CalendarUnit::Week => 7 * DAY_MILLIS + 2 * HOUR_MILLIS,
+            CalendarUnit::Month => 32 * DAY_MILLIS,
+            CalendarUnit::Quarter => 93 * DAY_MILLIS,
+            CalendarUnit::Year => 367 * DAY_MILLIS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateInterval {
+    Fixed(i64),
+    Calendar(CalendarUnit),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateTimeZone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl DateTimeZone {
+    fn utc() -> DateTimeZone {
+        DateTimeZone::Fixed(FixedOffset::east_opt(0).expect("UTC should be a valid offset."))
+    }
+
+    fn parse(time_zone: &str) -> Result<DateTimeZone, String> {
+        if time_zone == "Z" || time_zone.eq_ignore_ascii_case("utc") {
+            return Ok(DateTimeZone::utc());
+        }
+        let error = || {
+            format!(
+                "Time zone `{time_zone}` is invalid: expected an IANA time zone name such as \
+                 `Europe/Paris` or a UTC offset such as `+01:00`."
+            )
+        };
+        if let Some(sign) = time_zone
+            .chars()
+            .next()
+            .filter(|ch| *ch == '+' || *ch == '-')
+        {
+            let digits: String = time_zone[1..].chars().filter(|ch| *ch != ':').collect();
+            if !digits.chars().all(|ch| ch.is_ascii_digit()) {
+                return Err(error());
+            }
+            let (hours, minutes) = match digits.len() {
+                2 => (digits.as_str(), "0"),
+                4 => digits.split_at(2),
+                _ => return Err(error()),
+            };
+            let offset_secs = hours.parse::<i32>().map_err(|_| error())? * 3_600
+                + minutes.parse::<i32>().map_err(|_| error())? * 60;
+            let offset_secs = if sign == '-' {
+                -offset_secs
+            } else {
+                offset_secs
+            };
+            return FixedOffset::east_opt(offset_secs)
+                .map(DateTimeZone::Fixed)
+                .ok_or_else(error);
+        }
+        time_zone
+            .parse::<Tz>()
+            .map(DateTimeZone::Named)
+            .map_err(|_| error())
+    }
+
+    fn utc_offset_secs(&self, utc_datetime: &NaiveDateTime) -> i32 {
+        match self {
+            DateTimeZone::Fixed(offset) => offset.local_minus_utc(),
+            DateTimeZone::Named(tz) => tz
+                .offset_from_utc_datetime(utc_datetime)
+                .fix()
+                .local_minus_utc(),
+        }
+    }
+
+    /// Returns the first instant of the local datetime. If the local datetime is skipped by a DST
+    /// transition, returns the instant of the transition instead.
+    fn local_to_utc(&self, local_datetime: &NaiveDateTime) -> NaiveDateTime {
+        let utc_datetime_opt = match self {
+            DateTimeZone::Fixed(offset) => offset
+                .from_local_datetime(local_datetime)
+                .earliest()
+                .map(|datetime| datetime.naive_utc()),
+            DateTimeZone::Named(tz) => tz
+                .from_local_datetime(local_datetime)
+                .earliest()
+                .map(|datetime| datetime.naive_utc()),
+        };
+        utc_datetime_opt.unwrap_or_else(|| {
+            // The skipped local datetimes are mapped with the UTC offset in effect before the
+            // transition, which lands on the transition.
+            let offset_before_transition_secs =
+                self.utc_offset_secs(&(*local_datetime - Duration::days(1)));
+            *local_datetime - Duration::seconds(offset_before_transition_secs as i64)
+        })
+    }
+}
+
+fn utc_datetime(timestamp_millis: i64) -> Option<NaiveDateTime> {
+    NaiveDateTime::from_timestamp_opt(
+        timestamp_millis.div_euclid(1_000),
+        timestamp_millis.rem_euclid(1_000) as u32 * 1_000_000,
+    )
+}
+
+/// Rounds timestamps, expressed in milliseconds since the Unix epoch, down to the start of their
+/// date histogram bucket.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct DateRounding {
+    interval: DateInterval,
+    time_zone: DateTimeZone,
+    offset_millis: i64,
+}
+
+impl DateRounding {
+    /// Parses the parameters of a date histogram into a rounding.
+    pub fn new(
+        fixed_interval: Option<&str>,
+        calendar_interval: Option<&str>,
+        time_zone: Option<&str>,
+        offset: Option<&str>,
+    ) -> Result<DateRounding, String> {
+        let interval = match (fixed_interval, calendar_interval) {
+            (Some(fixed_interval), None) => parse_duration_millis(fixed_interval)
+                .filter(|interval_millis| *interval_millis > 0)
+                .map(DateInterval::Fixed)
+                .ok_or_else(|| {
+                    format!(
+                        "`fixed_interval` `{fixed_interval}` is invalid: expected a positive \
+                         duration such as `30m`, `1h`, or `1d`. Weeks, months, quarters, and \
+                         years are calendar intervals."
+                    )
+                })?,
+            (None, Some(calendar_interval)) => CalendarUnit::parse(calendar_interval)
+                .map(DateInterval::Calendar)
+                .ok_or_else(|| {
+                    format!(
+                        "`calendar_interval` `{calendar_interval}` is invalid: expected one of \
+                         `minute`, `hour`, `day`, `week`, `month`, `quarter`, or `year`."
+                    )
+                })?,
+            (None, None) => {
+                return Err(
+                    "Either `fixed_interval` or `calendar_interval` must be set.".to_string(),
+                )
+            }
+            (Some(_), Some(_)) => {
+                return Err(
+                    "`fixed_interval` and `calendar_interval` are mutually exclusive.".to_string(),
+                )
+            }
+        };
+        let time_zone = match time_zone {
+            Some(time_zone) => DateTimeZone::parse(time_zone)?,
+            None => DateTimeZone::utc(),
+        };
+        let offset_millis = match offset {
+            Some(offset) => parse_offset_millis(offset).ok_or_else(|| {
+                format!("`offset` `{offset}` is invalid: expected a duration such as `+6h`.")
+            })?,
+            None => 0,
+        };
+        Ok(DateRounding {
+            interval,
+            time_zone,
+            offset_millis,
+        })
+    }
+
+    /// Returns the start of the bucket containing `timestamp_millis`.
+    pub fn round_down(&self, timestamp_millis: i64) -> i64 {
+        self.round_down_without_offset(timestamp_millis - self.offset_millis) + self.offset_millis
+    }
+
+    fn round_down_without_offset(&self, timestamp_millis: i64) -> i64 {
+        let Some(utc_datetime) = utc_datetime(timestamp_millis) else {
+            return timestamp_millis;
+        };
+        let utc_offset_millis = self.time_zone.utc_offset_secs(&utc_datetime) as i64 * 1_000;
+
+        let interval_millis = match self.interval {
+            DateInterval::Fixed(interval_millis) => interval_millis,
+            DateInterval::Calendar(CalendarUnit::Minute) => MINUTE_MILLIS,
+            DateInterval::Calendar(CalendarUnit::Hour) => HOUR_MILLIS,
+            DateInterval::Calendar(unit) => {
+                let local_date = (utc_datetime + Duration::milliseconds(utc_offset_millis)).date();
+                let local_midnight = unit
+                    .truncate(local_date)
+                    .and_hms_opt(0, 0, 0)
+                    .expect("Midnight should be a valid time.");
+                return self
+                    .time_zone
+                    .local_to_utc(&local_midnight)
+                    .timestamp_millis();
+            }
+        };
+        // Minutes, hours, and fixed intervals are aligned on the local time, which keeps the
+        // buckets distinct when the clocks are set back.
+        timestamp_millis - (timestamp_millis + utc_offset_millis).rem_euclid(interval_millis)
+    }
+
+    /// Returns the start of the bucket following the bucket starting at `bucket_start_millis`.
+    pub fn next_bucket_start(&self, bucket_start_millis: i64) -> i64 {
+        let stride_millis = match self.interval {
+            DateInterval::Fixed(interval_millis) => interval_millis,
+            DateInterval::Calendar(unit) => unit.stride_millis(),
+        };
+        let next_bucket_start_millis = self.round_down(bucket_start_millis + stride_millis);
+
+        if next_bucket_start_millis > bucket_start_millis {
+            next_bucket_start_millis
+        } else {
+            bucket_start_millis + stride_millis
+        }
+    }
+
+    /// Formats a timestamp as an RFC 3339 datetime in the time zone of the rounding.
+    pub fn format(&self, timestamp_millis: i64) -> String {
+        let Some(utc_datetime) = utc_datetime(timestamp_millis) else {
+            return timestamp_millis.to_string();
+        };
+        let utc_offset_secs = self.time_zone.utc_offset_secs(&utc_datetime);
+        let Some(utc_offset) = FixedOffset::east_opt(utc_offset_secs) else {
+            return timestamp_millis.to_string();
+        };
+        utc_offset
+            .from_utc_datetime(&utc_datetime)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::aggregations::{IntermediateMetricResult, PercentilesSketch};
+
+    fn millis(rfc3339: &str) -> i64 {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn rounding(aggregation_json: JsonValue) -> DateRounding {
+        serde_json::from_value::<DateHistogramAggregation>(aggregation_json)
+            .unwrap()
+            .rounding()
+            .unwrap()
+    }
+
+    #[track_caller]
+    fn assert_bucket(rounding: &DateRounding, timestamp: &str, bucket: (&str, &str)) {
+        let bucket_start_millis = rounding.round_down(millis(timestamp));
+        assert_eq!(rounding.format(bucket_start_millis), bucket.0);
+        assert_eq!(
+            rounding.format(rounding.next_bucket_start(bucket_start_millis)),
+            bucket.1
+        );
+    }
+
+    #[test]
+    fn test_date_rounding_calendar_day_dst() {
+        let rounding = rounding(json!({
+            "field": "timestamp",
+            "calendar_interval": "day",
+            "time_zone": "Europe/Paris"
+        }));
+        // The clocks were set forward on 2023-03-26 and back on 2023-10-29.
+        assert_bucket(
+            &rounding,
+            "2023-03-26T12:00:00Z",
+            ("2023-03-26T00:00:00+01:00", "2023-03-27T00:00:00+02:00"),
+        );
+        assert_bucket(
+            &rounding,
+            "2023-03-26T22:30:00Z",
+            ("2023-03-27T00:00:00+02:00", "2023-03-28T00:00:00+02:00"),
+        );
+        assert_bucket(
+            &rounding,
+            "2023-10-29T22:30:00Z",
+            ("2023-10-29T00:00:00+02:00", "2023-10-30T00:00:00+01:00"),
+        );
+        let bucket_start_millis = rounding.round_down(millis("2023-03-26T12:00:00Z"));
+        assert_eq!(
+            rounding.next_bucket_start(bucket_start_millis) - bucket_start_millis,
+            23 * HOUR_MILLIS
+        );
+    }
+
+    #[test]
+    fn test_date_rounding_calendar_hour_dst() {
+        let rounding = rounding(json!({
+            "field": "timestamp",
+            "calendar_interval": "1h",
+            "time_zone": "Europe/Paris"
+        }));
+        // 2:30 AM happened twice on 2023-10-29.
+        assert_bucket(
+            &rounding,
+            "2023-10-29T00:30:00Z",
+            ("2023-10-29T02:00:00+02:00", "2023-10-29T02:00:00+01:00"),
+        );
+        assert_bucket(
+            &rounding,
+            "2023-10-29T01:30:00Z",
+            ("2023-10-29T02:00:00+01:00", "2023-10-29T03:00:00+01:00"),
+        );
+        // 2:30 AM never happened on 2023-03-26.
+        assert_bucket(
+            &rounding,
+            "2023-03-26T01:30:00Z",
+            ("2023-03-26T03:00:00+02:00", "2023-03-26T04:00:00+02:00"),
+        );
+    }
+
+    #[test]
+    fn test_date_rounding_calendar_week_month_quarter_year() {
+        let week_rounding = rounding(json!({"field": "timestamp", "calendar_interval": "week"}));
+        // 2023-01-01 is a Sunday.
+        assert_bucket(
+            &week_rounding,
+            "2023-01-01T12:00:00Z",
+            ("2022-12-26T00:00:00Z", "2023-01-02T00:00:00Z"),
+        );
+        let month_rounding = rounding(json!({
+            "field": "timestamp",
+            "calendar_interval": "1M",
+            "time_zone": "America/New_York"
+        }));
+        assert_bucket(
+            &month_rounding,
+            "2023-03-01T03:00:00Z",
+            ("2023-02-01T00:00:00-05:00", "2023-03-01T00:00:00-05:00"),
+        );
+        assert_bucket(
+            &month_rounding,
+            "2023-03-20T03:00:00Z",
+            ("2023-03-01T00:00:00-05:00", "2023-04-01T00:00:00-04:00"),
+        );
+        let quarter_rounding =
+            rounding(json!({"field": "timestamp", "calendar_interval": "quarter"}));
+        assert_bucket(
+            &quarter_rounding,
+            "2023-06-30T23:59:59Z",
+            ("2023-04-01T00:00:00Z", "2023-07-01T00:00:00Z"),
+        );
+        let year_rounding = rounding(json!({"field": "timestamp", "calendar_interval": "1y"}));
+        assert_bucket(
+            &year_rounding,
+            "2024-12-31T23:59:59Z",
+            ("2024-01-01T00:00:00Z", "2025-01-01T00:00:00Z"),
+        );
+    }
+
+    #[test]
+    fn test_date_rounding_fixed_interval_and_offset() {
+        let rounding_with_fixed_time_zone = rounding(json!({
+            "field": "timestamp",
+            "fixed_interval": "30m",
+            "time_zone": "+05:45"
+        }));
+        assert_bucket(
+            &rounding_with_fixed_time_zone,
+            "2023-01-01T00:10:00Z",
+            ("2023-01-01T05:30:00+05:45", "2023-01-01T06:00:00+05:45"),
+        );
+        let rounding_with_offset = rounding(json!({
+            "field": "timestamp",
+            "calendar_interval": "day",
+            "offset": "+6h"
+        }));
+        assert_bucket(
+            &rounding_with_offset,
+            "2023-01-02T03:00:00Z",
+            ("2023-01-01T06:00:00Z", "2023-01-02T06:00:00Z"),
+        );
+        let rounding_with_negative_offset = rounding(json!({
+            "field": "timestamp",
+            "fixed_interval": "1h",
+            "offset": "-15m"
+        }));
+        assert_bucket(
+            &rounding_with_negative_offset,
+            "2023-01-01T00:50:00Z",
+            ("2023-01-01T00:45:00Z", "2023-01-01T01:45:00Z"),
+        );
+    }
+
+    #[test]
+    fn test_date_histogram_aggregation_invalid_parameters() {
+        for (aggregation_json, expected_error) in [
+            (
+                json!({"field": "timestamp"}),
+                "Either `fixed_interval` or `calendar_interval` must be set.",
+            ),
+            (
+                json!({"field": "timestamp", "fixed_interval": "1h", "calendar_interval": "1h"}),
+                "`fixed_interval` and `calendar_interval` are mutually exclusive.",
+            ),
+            (
+                json!({"field": "timestamp", "fixed_interval": "1M"}),
+                "`fixed_interval` `1M` is invalid: expected a positive duration such as `30m`, \
+                 `1h`, or `1d`. Weeks, months, quarters, and years are calendar intervals.",
+            ),
+            (
+                json!({"field": "timestamp", "calendar_interval": "2d"}),
+                "`calendar_interval` `2d` is invalid: expected one of `minute`, `hour`, `day`, \
+                 `week`, `month`, `quarter`, or `year`.",
+            ),
+            (
+                json!({"field": "timestamp", "calendar_interval": "day", "time_zone": "Mars/Olympus"}),
+                "Time zone `Mars/Olympus` is invalid: expected an IANA time zone name such as \
+                 `Europe/Paris` or a UTC offset such as `+01:00`.",
+            ),
+            (
+                json!({"field": "timestamp", "calendar_interval": "day", "offset": "6 hours"}),
+                "`offset` `6 hours` is invalid: expected a duration such as `+6h`.",
+            ),
+        ] {
+            let aggregation: DateHistogramAggregation =
+                serde_json::from_value(aggregation_json).unwrap();
+            assert_eq!(aggregation.rounding().unwrap_err(), expected_error);
+        }
+    }
+
+    #[test]
+    fn test_date_histogram_final_result() {
+        let aggregation: DateHistogramAggregation = serde_json::from_value(json!({
+            "field": "timestamp",
+            "calendar_interval": "month",
+            "time_zone": "Europe/Paris",
+            "keyed": true
+        }))
+        .unwrap();
+        let mut sketch = PercentilesSketch::default();
+        sketch.add(42.0);
+        let sub_aggregations = BTreeMap::from_iter([(
+            "latency".to_string(),
+            serde_json::from_value(json!({"percentiles": {"field": "latency", "percents": [50]}}))
+                .unwrap(),
+        )]);
+        let buckets = BTreeMap::from_iter([
+            (
+                millis("2023-01-01T00:00:00+01:00"),
+                IntermediateBucket {
+                    doc_count: 2,
+                    sub_results: BTreeMap::from_iter([(
+                        "latency".to_string(),
+                        IntermediateMetricResult::Percentiles(sketch),
+                    )]),
+                },
+            ),
+            (
+                millis("2023-04-01T00:00:00+02:00"),
+                IntermediateBucket {
+                    doc_count: 1,
+                    sub_results: BTreeMap::new(),
+                },
+            ),
+        ]);
+        let final_result_json = aggregation
+            .into_final_result(&sub_aggregations, buckets)
+            .unwrap();
+        let buckets_json = final_result_json["buckets"].as_object().unwrap();
+        assert_eq!(
+            buckets_json.keys().collect::<Vec<_>>(),
+            [
+                "2023-01-01T00:00:00+01:00",
+                "2023-02-01T00:00:00+01:00",
+                "2023-03-01T00:00:00+01:00",
+                "2023-04-01T00:00:00+02:00"
+            ]
+        );
+        let first_bucket_json = &buckets_json["2023-01-01T00:00:00+01:00"];
+        assert_eq!(first_bucket_json["key"], 1_672_527_600_000i64);
+        assert_eq!(first_bucket_json["doc_count"], 2);
+        let latency_percentile = first_bucket_json["latency"]["values"]["50.0"]
+            .as_f64()
+            .unwrap();
+        assert!((latency_percentile - 42.0).abs() < 0.5);
+        assert_eq!(buckets_json["2023-02-01T00:00:00+01:00"]["doc_count"], 0);
+    }
+}
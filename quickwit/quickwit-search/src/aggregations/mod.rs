@@ -0,0 +1,1041 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Aggregations computed by Quickwit on top of the aggregations supported by tantivy.
+
+mod bucket;
+mod cardinality;
+mod column;
+mod composite;
+mod date_histogram;
+mod percentiles;
+mod pipeline;
+mod stats;
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashSet};
+
+pub use bucket::{
+    BucketAggregation, HistogramAggregation, Order, QuickwitBucketAggregation, TermsAggregation,
+    TermsOrder,
+};
+use bucket::{BucketSegmentCollector, IntermediateBucketResult};
+pub use cardinality::{CardinalityAggregation, CardinalitySketch};
+use cardinality::{CardinalitySegmentCollector, CardinalitySegmentState};
+pub(crate) use column::{fast_field_cardinality, format_numeric_value, AggregationColumn};
+pub use composite::{
+    CompositeAggregation, CompositeKeyValue, CompositeSource, DateHistogramCompositeSource,
+    HistogramCompositeSource, TermsCompositeSource,
+};
+use composite::{CompositeSegmentCollector, IntermediateCompositeResult};
+pub use date_histogram::DateHistogramAggregation;
+use percentiles::PercentilesSegmentCollector;
+pub use percentiles::{PercentilesAggregation, PercentilesSketch};
+use pipeline::{apply_pipeline_aggregations, extract_pipeline_aggregations};
+pub use pipeline::{
+    CumulativeSumAggregation, DerivativeAggregation, GapPolicy, MovingFnAggregation,
+    PipelineAggregation,
+};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use stats::StatsSegmentCollector;
+pub use stats::{IntermediateStats, StatsAggregation};
+use tantivy::aggregation::agg_req::{
+    get_fast_field_names, get_term_dict_field_names, Aggregations,
+};
+use tantivy::aggregation::agg_result::AggregationResults;
+use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use tantivy::aggregation::AggregationSegmentCollector;
+use tantivy::collector::SegmentCollector;
+use tantivy::schema::{Cardinality, FieldType, Schema};
+use tantivy::{DocId, Score, SegmentReader};
+
+/// Metric aggregations computed by Quickwit rather than by tantivy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickwitMetricAggregation {
+    /// Approximate percentiles of a numeric field.
+    Percentiles(PercentilesAggregation),
+    /// Approximate number of distinct values of a field.
+    Cardinality(CardinalityAggregation),
+    /// Average of the values of a numeric field.
+    Avg(StatsAggregation),
+    /// Minimum of the values of a numeric field.
+    Min(StatsAggregation),
+    /// Maximum of the values of a numeric field.
+    Max(StatsAggregation),
+    /// Sum of the values of a numeric field.
+    Sum(StatsAggregation),
+    /// Number, sum, minimum, maximum, and average of the values of a numeric field.
+    Stats(StatsAggregation),
+    /// Number of values of a numeric field.
+    ValueCount(StatsAggregation),
+}
+
+impl QuickwitMetricAggregation {
+    const NAMES: [&'static str; 2] = ["percentiles", "cardinality"];
+
+    /// Names of the metric aggregations computed by tantivy, except over the multi-valued fields
+    /// and in the buckets of the aggregations computed by Quickwit.
+    const STATS_NAMES: [&'static str; 6] = ["avg", "min", "max", "sum", "stats", "value_count"];
+
+    /// Returns whether `aggregation_json` is a metric aggregation computed by Quickwit: a
+    /// percentiles or cardinality aggregation, or, if the `schema` is known, a stats aggregation
+    /// over a multi-valued field.
+    fn is_metric_aggregation(aggregation_json: &JsonValue, schema_opt: Option<&Schema>) -> bool {
+        let Some(aggregation_obj) = aggregation_json.as_object() else {
+            return false;
+        };
+        aggregation_obj.iter().any(|(key, value)| {
+            if Self::NAMES.contains(&key.as_str()) {
+                return true;
+            }
+            let Some(schema) = schema_opt else {
+                return false;
+            };
+            Self::STATS_NAMES.contains(&key.as_str())
+                && value
+                    .get("field")
+                    .and_then(JsonValue::as_str)
+                    .map_or(false, |field_name| {
+                        is_multi_valued_fast_field(schema, field_name)
+                    })
+        })
+    }
+
+    /// Returns whether `aggregation_json` is a metric aggregation that Quickwit can compute in
+    /// the buckets of its bucket aggregations.
+    fn is_sub_aggregation(aggregation_json: &JsonValue) -> bool {
+        aggregation_json
+            .as_object()
+            .map(|aggregation_obj| {
+                aggregation_obj.keys().any(|key| {
+                    Self::NAMES.contains(&key.as_str()) || Self::STATS_NAMES.contains(&key.as_str())
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    fn field_name(&self) -> &str {
+        match self {
+            QuickwitMetricAggregation::Percentiles(aggregation) => &aggregation.field,
+            QuickwitMetricAggregation::Cardinality(aggregation) => &aggregation.field,
+            QuickwitMetricAggregation::Avg(aggregation)
+            | QuickwitMetricAggregation::Min(aggregation)
+            | QuickwitMetricAggregation::Max(aggregation)
+            | QuickwitMetricAggregation::Sum(aggregation)
+            | QuickwitMetricAggregation::Stats(aggregation)
+            | QuickwitMetricAggregation::ValueCount(aggregation) => &aggregation.field,
+        }
+    }
+
+    /// Returns the field whose term dictionary is read by the aggregation, if any.
+    fn term_dict_field_name(&self) -> Option<&str> {
+        match self {
+            QuickwitMetricAggregation::Cardinality(aggregation) => Some(&aggregation.field),
+            _ => None,
+        }
+    }
+
+    fn validate(&self, schema: &Schema) -> Result<(), String> {
+        match self {
+            QuickwitMetricAggregation::Percentiles(aggregation) => {
+                aggregation.validate()?;
+                validate_numeric_fast_field(schema, &aggregation.field)
+            }
+            QuickwitMetricAggregation::Cardinality(aggregation) => {
+                validate_fast_field(schema, &aggregation.field)
+            }
+            _ => validate_numeric_fast_field(schema, self.field_name()),
+        }
+    }
+
+    fn into_final_result(
+        self,
+        intermediate_result_opt: Option<IntermediateMetricResult>,
+    ) -> JsonValue {
+        match self {
+            QuickwitMetricAggregation::Percentiles(aggregation) => {
+                let sketch = match intermediate_result_opt {
+                    Some(IntermediateMetricResult::Percentiles(sketch)) => sketch,
+                    _ => PercentilesSketch::default(),
+                };
+                aggregation.into_final_result(&sketch)
+            }
+            QuickwitMetricAggregation::Cardinality(aggregation) => {
+                let sketch = match intermediate_result_opt {
+                    Some(IntermediateMetricResult::Cardinality(sketch)) => sketch,
+                    _ => CardinalitySketch::default(),
+                };
+                aggregation.into_final_result(&sketch)
+            }
+            _ => {
+                let stats = match intermediate_result_opt {
+                    Some(IntermediateMetricResult::Stats(stats)) => stats,
+                    _ => IntermediateStats::default(),
+                };
+                match self {
+                    QuickwitMetricAggregation::Avg(_) => json!({ "value": stats.avg() }),
+                    QuickwitMetricAggregation::Min(_) => json!({ "value": stats.min() }),
+                    QuickwitMetricAggregation::Max(_) => json!({ "value": stats.max() }),
+                    QuickwitMetricAggregation::Sum(_) => json!({ "value": stats.sum() }),
+                    QuickwitMetricAggregation::ValueCount(_) => {
+                        json!({ "value": stats.count() as f64 })
+                    }
+                    _ => stats.into_final_result(),
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether `field_name` is a multi-valued numeric, bool, or datetime fast field, whose
+/// stats and histograms tantivy cannot compute.
+fn is_multi_valued_fast_field(schema: &Schema, field_name: &str) -> bool {
+    let Ok(field) = schema.get_field(field_name) else {
+        return false;
+    };
+    let field_type = schema.get_field_entry(field).field_type();
+    fast_field_cardinality(field_type) == Some(Cardinality::MultiValues)
+}
+
+fn validate_fast_field(schema: &Schema, field_name: &str) -> Result<(), String> {
+    let field = schema
+        .get_field(field_name)
+        .map_err(|_| format!("Field `{field_name}` does not exist."))?;
+    let field_entry = schema.get_field_entry(field);
+    let is_supported_type = matches!(
+        field_entry.field_type(),
+        FieldType::U64(_)
+            | FieldType::I64(_)
+            | FieldType::F64(_)
+            | FieldType::Bool(_)
+            | FieldType::Date(_)
+            | FieldType::Str(_)
+    );
+    if !is_supported_type || !field_entry.is_fast() {
+        return Err(format!(
+            "Field `{field_name}` is not a text, numeric, bool, or datetime fast field."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_datetime_fast_field(schema: &Schema, field_name: &str) -> Result<(), String> {
+    let field = schema
+        .get_field(field_name)
+        .map_err(|_| format!("Field `{field_name}` does not exist."))?;
+    let field_entry = schema.get_field_entry(field);
+    if !matches!(field_entry.field_type(), FieldType::Date(_)) || !field_entry.is_fast() {
+        return Err(format!(
+            "Field `{field_name}` is not a datetime fast field."
+        ));
+    }
+    Ok(())
+}
+
+fn validate_numeric_fast_field(schema: &Schema, field_name: &str) -> Result<(), String> {
+    let field = schema
+        .get_field(field_name)
+        .map_err(|_| format!("Field `{field_name}` does not exist."))?;
+    let field_entry = schema.get_field_entry(field);
+    let is_numeric = matches!(
+        field_entry.field_type(),
+        FieldType::U64(_) | FieldType::I64(_) | FieldType::F64(_) | FieldType::Date(_)
+    );
+    if !is_numeric || !field_entry.is_fast() {
+        return Err(format!(
+            "Field `{field_name}` is not a numeric or datetime fast field."
+        ));
+    }
+    Ok(())
+}
+
+/// Aggregations of a search request: the aggregations supported by tantivy, the metric
+/// aggregations computed by Quickwit, and the composite and date histogram aggregations and the
+/// histogram and terms aggregations with Quickwit metric sub-aggregations, which are computed by
+/// Quickwit as well. The pipeline sub-aggregations of the bucket aggregations are computed by the root
+/// searcher from the final results of the other aggregations.
+#[derive(Debug, Clone)]
+pub struct SearchAggregations {
+    /// Aggregations computed by tantivy.
+    pub tantivy_aggregations: Aggregations,
+    /// Metric aggregations computed by Quickwit, by name.
+    pub metric_aggregations: BTreeMap<String, QuickwitMetricAggregation>,
+    /// Bucket aggregations computed by Quickwit, by name.
+    pub bucket_aggregations: BTreeMap<String, QuickwitBucketAggregation>,
+    /// Composite aggregations, by name.
+    pub composite_aggregations: BTreeMap<String, CompositeAggregation>,
+    /// Pipeline sub-aggregations by name of their parent bucket aggregation, in computation
+    /// order.
+    pub pipeline_aggregations: BTreeMap<String, Vec<(String, PipelineAggregation)>>,
+}
+
+impl<'de> Deserialize<'de> for SearchAggregations {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let aggregations_json = JsonMap::<String, JsonValue>::deserialize(deserializer)?;
+        SearchAggregations::from_json(aggregations_json, None).map_err(D::Error::custom)
+    }
+}
+
+impl SearchAggregations {
+    /// Parses the aggregations of a request. When the index `schema` is known, the stats and
+    /// histogram aggregations over its multi-valued fields, which tantivy cannot compute, are
+    /// computed by Quickwit.
+    pub(crate) fn from_json(
+        aggregations_json: JsonMap<String, JsonValue>,
+        schema_opt: Option<&Schema>,
+    ) -> serde_json::Result<Self> {
+        let mut tantivy_aggregations_json = JsonMap::new();
+        let mut metric_aggregations = BTreeMap::new();
+        let mut bucket_aggregations = BTreeMap::new();
+        let mut composite_aggregations = BTreeMap::new();
+        let mut pipeline_aggregations = BTreeMap::new();
+
+        for (name, mut aggregation_json) in aggregations_json {
+            let sub_pipeline_aggregations = extract_pipeline_aggregations(&mut aggregation_json)
+                .map_err(|error| {
+                    serde_json::Error::custom(format!("aggregation `{name}` is invalid: {error}"))
+                })?;
+            if !sub_pipeline_aggregations.is_empty() {
+                pipeline_aggregations.insert(name.clone(), sub_pipeline_aggregations);
+            }
+            if CompositeAggregation::is_composite_aggregation(&aggregation_json) {
+                let composite_aggregation = serde_json::from_value(aggregation_json)?;
+                composite_aggregations.insert(name, composite_aggregation);
+            } else if QuickwitMetricAggregation::is_metric_aggregation(
+                &aggregation_json,
+                schema_opt,
+            ) {
+                let metric_aggregation = serde_json::from_value(aggregation_json)?;
+                metric_aggregations.insert(name, metric_aggregation);
+            } else if QuickwitBucketAggregation::is_quickwit_bucket_aggregation(
+                &aggregation_json,
+                schema_opt,
+            ) {
+                let bucket_aggregation = serde_json::from_value(aggregation_json)?;
+                bucket_aggregations.insert(name, bucket_aggregation);
+            } else {
+                tantivy_aggregations_json.insert(name, aggregation_json);
+            }
+        }
+        let tantivy_aggregations =
+            serde_json::from_value(JsonValue::Object(tantivy_aggregations_json))?;
+        Ok(SearchAggregations {
+            tantivy_aggregations,
+            metric_aggregations,
+            bucket_aggregations,
+            composite_aggregations,
+            pipeline_aggregations,
+        })
+    }
+
+    pub(crate) fn fast_field_names(&self) -> HashSet<String> {
+        let mut fast_field_names = get_fast_field_names(&self.tantivy_aggregations);
+        fast_field_names.extend(
+            self.metric_aggregations
+                .values()
+                .map(|aggregation| aggregation.field_name().to_string()),
+        );
+        fast_field_names.extend(
+            self.bucket_aggregations
+                .values()
+                .flat_map(QuickwitBucketAggregation::field_names)
+                .map(str::to_string),
+        );
+        fast_field_names.extend(
+            self.composite_aggregations
+                .values()
+                .flat_map(CompositeAggregation::field_names)
+                .map(str::to_string),
+        );
+        fast_field_names
+    }
+
+    pub(crate) fn term_dict_field_names(&self) -> HashSet<String> {
+        let mut term_dict_field_names = get_term_dict_field_names(&self.tantivy_aggregations);
+        term_dict_field_names.extend(
+            self.metric_aggregations
+                .values()
+                .filter_map(QuickwitMetricAggregation::term_dict_field_name)
+                .map(str::to_string),
+        );
+        term_dict_field_names.extend(
+            self.bucket_aggregations
+                .values()
+                .flat_map(QuickwitBucketAggregation::term_dict_field_names)
+                .map(str::to_string),
+        );
+        term_dict_field_names.extend(
+            self.composite_aggregations
+                .values()
+                .flat_map(CompositeAggregation::term_dict_field_names)
+                .map(str::to_string),
+        );
+        term_dict_field_names
+    }
+
+    /// Checks that the aggregations computed by Quickwit are valid for the index `schema`.
+    pub(crate) fn validate(&self, schema: &Schema) -> Result<(), String> {
+        for (name, aggregation) in &self.metric_aggregations {
+            aggregation
+                .validate(schema)
+                .map_err(|error| format!("Aggregation `{name}` is invalid: {error}"))?;
+        }
+        for (name, aggregation) in &self.bucket_aggregations {
+            aggregation
+                .validate(schema)
+                .map_err(|error| format!("Aggregation `{name}` is invalid: {error}"))?;
+        }
+        for (name, aggregation) in &self.composite_aggregations {
+            aggregation
+                .validate(schema)
+                .map_err(|error| format!("Aggregation `{name}` is invalid: {error}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Intermediate result of a metric aggregation, mergeable across segments, splits, and leaves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntermediateMetricResult {
+    /// Sketch of the values of a percentiles aggregation.
+    Percentiles(PercentilesSketch),
+    /// Sketch of the values of a cardinality aggregation.
+    Cardinality(CardinalitySketch),
+    /// Stats of the values of an avg, min, max, sum, stats, or value count aggregation.
+    Stats(IntermediateStats),
+}
+
+impl IntermediateMetricResult {
+    fn merge(&mut self, other: IntermediateMetricResult) {
+        match (self, other) {
+            (
+                IntermediateMetricResult::Percentiles(sketch),
+                IntermediateMetricResult::Percentiles(other_sketch),
+            ) => sketch.merge(other_sketch),
+            (
+                IntermediateMetricResult::Cardinality(sketch),
+                IntermediateMetricResult::Cardinality(other_sketch),
+            ) => sketch.merge(other_sketch),
+            (
+                IntermediateMetricResult::Stats(stats),
+                IntermediateMetricResult::Stats(other_stats),
+            ) => stats.merge(other_stats),
+            _ => {}
+        }
+    }
+}
+
+fn merge_metric_results(
+    results: &mut BTreeMap<String, IntermediateMetricResult>,
+    other_results: BTreeMap<String, IntermediateMetricResult>,
+) {
+    for (name, other_result) in other_results {
+        match results.entry(name) {
+            Entry::Occupied(mut entry) => entry.get_mut().merge(other_result),
+            Entry::Vacant(entry) => {
+                entry.insert(other_result);
+            }
+        }
+    }
+}
+
+/// Intermediate results of [`SearchAggregations`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IntermediateSearchAggregationResults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tantivy: Option<IntermediateAggregationResults>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metrics: BTreeMap<String, IntermediateMetricResult>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    buckets: BTreeMap<String, IntermediateBucketResult>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    composites: BTreeMap<String, IntermediateCompositeResult>,
+}
+
+impl IntermediateSearchAggregationResults {
+    /// Merges `other` into these results.
+    pub fn merge_fruits(&mut self, other: IntermediateSearchAggregationResults) {
+        match (self.tantivy.as_mut(), other.tantivy) {
+            (Some(tantivy_results), Some(other_tantivy_results)) => {
+                tantivy_results.merge_fruits(other_tantivy_results)
+            }
+            (None, other_tantivy_results_opt) => self.tantivy = other_tantivy_results_opt,
+            (Some(_), None) => {}
+        }
+        merge_metric_results(&mut self.metrics, other.metrics);
+
+        for (name, other_result) in other.buckets {
+            match self.buckets.entry(name) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(other_result),
+                Entry::Vacant(entry) => {
+                    entry.insert(other_result);
+                }
+            }
+        }
+        for (name, other_result) in other.composites {
+            match self.composites.entry(name) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(other_result),
+                Entry::Vacant(entry) => {
+                    entry.insert(other_result);
+                }
+            }
+        }
+    }
+
+    /// Computes the final results of the aggregations, serialized as a JSON object keyed by
+    /// aggregation name.
+    pub fn into_final_result(
+        mut self,
+        aggregations: SearchAggregations,
+        schema: &Schema,
+    ) -> crate::Result<JsonValue> {
+        let mut final_result_json = match self.tantivy {
+            Some(tantivy_results) => {
+                let final_tantivy_results: AggregationResults = tantivy_results
+                    .into_final_bucket_result(aggregations.tantivy_aggregations, schema)?;
+                match serde_json::to_value(final_tantivy_results)? {
+                    JsonValue::Object(final_tantivy_results_json) => final_tantivy_results_json,
+                    _ => JsonMap::new(),
+                }
+            }
+            None => JsonMap::new(),
+        };
+        for (name, aggregation) in aggregations.metric_aggregations {
+            let intermediate_result_opt = self.metrics.remove(&name);
+            final_result_json.insert(name, aggregation.into_final_result(intermediate_result_opt));
+        }
+        for (name, aggregation) in aggregations.bucket_aggregations {
+            let intermediate_result_opt = self.buckets.remove(&name);
+            let final_result = aggregation.into_final_result(intermediate_result_opt, schema)?;
+            final_result_json.insert(name, final_result);
+        }
+        for (name, aggregation) in aggregations.composite_aggregations {
+            let intermediate_result_opt = self.composites.remove(&name);
+            final_result_json.insert(name, aggregation.into_final_result(intermediate_result_opt));
+        }
+        for (name, pipeline_aggregations) in &aggregations.pipeline_aggregations {
+            if let Some(result_json) = final_result_json.get_mut(name) {
+                apply_pipeline_aggregations(pipeline_aggregations, result_json);
+            }
+        }
+        Ok(JsonValue::Object(final_result_json))
+    }
+}
+
+/// Collects a metric aggregation at the scale of the segment. The values are recorded in a
+/// separate [`MetricSegmentState`] so that the same collector can serve several buckets.
+enum MetricSegmentCollector {
+    Percentiles(PercentilesSegmentCollector),
+    Cardinality(CardinalitySegmentCollector),
+    Stats(StatsSegmentCollector),
+}
+
+enum MetricSegmentState {
+    Percentiles(PercentilesSketch),
+    Cardinality(CardinalitySegmentState),
+    Stats(IntermediateStats),
+}
+
+impl MetricSegmentCollector {
+    fn new(
+        aggregation: &QuickwitMetricAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let collector = match aggregation {
+            QuickwitMetricAggregation::Percentiles(aggregation) => {
+                MetricSegmentCollector::Percentiles(PercentilesSegmentCollector::new(
+                    aggregation,
+                    segment_reader,
+                )?)
+            }
+            QuickwitMetricAggregation::Cardinality(aggregation) => {
+                MetricSegmentCollector::Cardinality(CardinalitySegmentCollector::new(
+                    aggregation,
+                    segment_reader,
+                )?)
+            }
+            QuickwitMetricAggregation::Avg(aggregation)
+            | QuickwitMetricAggregation::Min(aggregation)
+            | QuickwitMetricAggregation::Max(aggregation)
+            | QuickwitMetricAggregation::Sum(aggregation)
+            | QuickwitMetricAggregation::Stats(aggregation)
+            | QuickwitMetricAggregation::ValueCount(aggregation) => MetricSegmentCollector::Stats(
+                StatsSegmentCollector::new(aggregation, segment_reader)?,
+            ),
+        };
+        Ok(collector)
+    }
+
+    fn new_state(&self) -> MetricSegmentState {
+        match self {
+            MetricSegmentCollector::Percentiles(_) => {
+                MetricSegmentState::Percentiles(PercentilesSketch::default())
+            }
+            MetricSegmentCollector::Cardinality(_) => {
+                MetricSegmentState::Cardinality(CardinalitySegmentState::default())
+            }
+            MetricSegmentCollector::Stats(_) => {
+                MetricSegmentState::Stats(IntermediateStats::default())
+            }
+        }
+    }
+
+    fn collect(&mut self, doc_id: DocId, state: &mut MetricSegmentState) {
+        match (self, state) {
+            (
+                MetricSegmentCollector::Percentiles(collector),
+                MetricSegmentState::Percentiles(sketch),
+            ) => collector.collect(doc_id, sketch),
+            (
+                MetricSegmentCollector::Cardinality(collector),
+                MetricSegmentState::Cardinality(state),
+            ) => collector.collect(doc_id, state),
+            (MetricSegmentCollector::Stats(collector), MetricSegmentState::Stats(stats)) => {
+                collector.collect(doc_id, stats)
+            }
+            _ => unreachable!("The state should have been created by the collector."),
+        }
+    }
+
+    fn harvest(&self, state: MetricSegmentState) -> tantivy::Result<IntermediateMetricResult> {
+        let result = match (self, state) {
+            (MetricSegmentCollector::Percentiles(_), MetricSegmentState::Percentiles(sketch)) => {
+                IntermediateMetricResult::Percentiles(sketch)
+            }
+            (
+                MetricSegmentCollector::Cardinality(collector),
+                MetricSegmentState::Cardinality(state),
+            ) => IntermediateMetricResult::Cardinality(collector.harvest(state)?),
+            (MetricSegmentCollector::Stats(_), MetricSegmentState::Stats(stats)) => {
+                IntermediateMetricResult::Stats(stats)
+            }
+            _ => unreachable!("The state should have been created by the collector."),
+        };
+        Ok(result)
+    }
+}
+
+/// Collects the [`SearchAggregations`] at the scale of the segment.
+pub(crate) struct SearchAggregationSegmentCollector {
+    tantivy_collector_opt: Option<AggregationSegmentCollector>,
+    metric_collectors: Vec<(String, MetricSegmentCollector, MetricSegmentState)>,
+    bucket_collectors: Vec<(String, BucketSegmentCollector)>,
+    composite_collectors: Vec<(String, CompositeSegmentCollector)>,
+}
+
+impl SearchAggregationSegmentCollector {
+    pub fn from_aggregations_and_reader(
+        aggregations: &SearchAggregations,
+        segment_reader: &SegmentReader,
+        bucket_limit: u32,
+    ) -> tantivy::Result<Self> {
+        let tantivy_collector_opt = if aggregations.tantivy_aggregations.is_empty() {
+            None
+        } else {
+            Some(AggregationSegmentCollector::from_agg_req_and_reader(
+                &aggregations.tantivy_aggregations,
+                segment_reader,
+                bucket_limit,
+            )?)
+        };
+        let metric_collectors = aggregations
+            .metric_aggregations
+            .iter()
+            .map(|(name, aggregation)| {
+                let collector = MetricSegmentCollector::new(aggregation, segment_reader)?;
+                let state = collector.new_state();
+                Ok((name.clone(), collector, state))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        let bucket_collectors = aggregations
+            .bucket_aggregations
+            .iter()
+            .map(|(name, aggregation)| {
+                let collector =
+                    BucketSegmentCollector::new(aggregation, segment_reader, bucket_limit)?;
+                Ok((name.clone(), collector))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        let composite_collectors = aggregations
+            .composite_aggregations
+            .iter()
+            .map(|(name, aggregation)| {
+                let collector = CompositeSegmentCollector::new(aggregation, segment_reader)?;
+                Ok((name.clone(), collector))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(SearchAggregationSegmentCollector {
+            tantivy_collector_opt,
+            metric_collectors,
+            bucket_collectors,
+            composite_collectors,
+        })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId, score: Score) {
+        if let Some(tantivy_collector) = self.tantivy_collector_opt.as_mut() {
+            tantivy_collector.collect(doc_id, score);
+        }
+        for (_, metric_collector, state) in self.metric_collectors.iter_mut() {
+            metric_collector.collect(doc_id, state);
+        }
+        for (_, bucket_collector) in self.bucket_collectors.iter_mut() {
+            bucket_collector.collect(doc_id);
+        }
+        for (_, composite_collector) in self.composite_collectors.iter_mut() {
+            composite_collector.collect(doc_id);
+        }
+    }
+
+    pub fn harvest(self) -> tantivy::Result<IntermediateSearchAggregationResults> {
+        let tantivy = self
+            .tantivy_collector_opt
+            .map(|tantivy_collector| tantivy_collector.harvest())
+            .transpose()?;
+        let metrics = self
+            .metric_collectors
+            .into_iter()
+            .map(|(name, metric_collector, state)| Ok((name, metric_collector.harvest(state)?)))
+            .collect::<tantivy::Result<_>>()?;
+        let buckets = self
+            .bucket_collectors
+            .into_iter()
+            .map(|(name, bucket_collector)| Ok((name, bucket_collector.harvest()?)))
+            .collect::<tantivy::Result<_>>()?;
+        let composites = self
+            .composite_collectors
+            .into_iter()
+            .map(|(name, composite_collector)| Ok((name, composite_collector.harvest()?)))
+            .collect::<tantivy::Result<_>>()?;
+        Ok(IntermediateSearchAggregationResults {
+            tantivy,
+            metrics,
+            buckets,
+            composites,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::{NumericOptions, SchemaBuilder, FAST, INDEXED};
+
+    use super::*;
+
+    #[test]
+    fn test_search_aggregations_deserialize() {
+        let aggregations: SearchAggregations = serde_json::from_value(json!({
+            "latency_percentiles": {
+                "percentiles": {"field": "latency", "percents": [50, 99]}
+            },
+            "max_latency": {"max": {"field": "latency"}}
+        }))
+        .unwrap();
+        assert_eq!(aggregations.tantivy_aggregations.len(), 1);
+        assert!(aggregations
+            .tantivy_aggregations
+            .contains_key("max_latency"));
+        assert_eq!(
+            aggregations.metric_aggregations["latency_percentiles"],
+            QuickwitMetricAggregation::Percentiles(PercentilesAggregation {
+                field: "latency".to_string(),
+                percents: vec![50.0, 99.0],
+                keyed: true,
+            })
+        );
+        assert_eq!(
+            aggregations.fast_field_names(),
+            HashSet::from_iter(["latency".to_string()])
+        );
+        serde_json::from_value::<SearchAggregations>(json!({
+            "latency_percentiles": {"percentiles": {"field": "latency", "unknown": true}}
+        }))
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_search_aggregations_deserialize_bucket_aggregations() {
+        let aggregations: SearchAggregations = serde_json::from_value(json!({
+            "unique_users": {"cardinality": {"field": "user_id"}},
+            "hourly": {
+                "histogram": {"field": "timestamp", "interval": 3_600_000_000u64},
+                "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+            },
+            "services": {
+                "terms": {"field": "service"},
+                "aggs": {"max_latency": {"max": {"field": "latency"}}}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            aggregations.metric_aggregations["unique_users"],
+            QuickwitMetricAggregation::Cardinality(CardinalityAggregation {
+                field: "user_id".to_string(),
+            })
+        );
+        let hourly_aggregation = &aggregations.bucket_aggregations["hourly"];
+        assert!(matches!(
+            hourly_aggregation.bucket_aggregation,
+            BucketAggregation::Histogram(_)
+        ));
+        assert_eq!(hourly_aggregation.sub_aggregations.len(), 1);
+        // The buckets without Quickwit metric sub-aggregations are computed by tantivy.
+        assert!(aggregations.tantivy_aggregations.contains_key("services"));
+
+        assert_eq!(
+            aggregations.fast_field_names(),
+            HashSet::from_iter(["user_id", "timestamp", "service", "latency"].map(str::to_string))
+        );
+        assert_eq!(
+            aggregations.term_dict_field_names(),
+            HashSet::from_iter(["user_id", "service"].map(str::to_string))
+        );
+        // Date histograms are always computed by Quickwit.
+        let aggregations: SearchAggregations = serde_json::from_value(json!({
+            "daily": {
+                "date_histogram": {
+                    "field": "timestamp",
+                    "calendar_interval": "day",
+                    "time_zone": "Europe/Paris"
+                }
+            }
+        }))
+        .unwrap();
+        assert!(aggregations.tantivy_aggregations.is_empty());
+        assert!(matches!(
+            aggregations.bucket_aggregations["daily"].bucket_aggregation,
+            BucketAggregation::DateHistogram(_)
+        ));
+        // Tantivy metric aggregations cannot be mixed with Quickwit metric aggregations in the
+        // buckets computed by Quickwit.
+        serde_json::from_value::<SearchAggregations>(json!({
+            "hourly": {
+                "histogram": {"field": "timestamp", "interval": 3_600_000_000u64},
+                "aggs": {
+                    "unique_users": {"cardinality": {"field": "user_id"}},
+                    "max_latency": {"max": {"field": "latency"}}
+                }
+            }
+        }))
+        .unwrap_err();
+    }
+
+    #[test]
+    fn test_search_aggregations_deserialize_pipeline_aggregations() {
+        let aggregations: SearchAggregations = serde_json::from_value(json!({
+            "hourly": {
+                "histogram": {"field": "timestamp", "interval": 3_600_000_000u64},
+                "aggs": {
+                    "max_latency": {"max": {"field": "latency"}},
+                    "max_latency_rate": {"derivative": {"buckets_path": "max_latency"}}
+                }
+            }
+        }))
+        .unwrap();
+        // The pipeline aggregations are removed from the aggregations computed by tantivy.
+        assert!(aggregations.tantivy_aggregations.contains_key("hourly"));
+        assert_eq!(
+            aggregations.pipeline_aggregations["hourly"],
+            [(
+                "max_latency_rate".to_string(),
+                PipelineAggregation::Derivative(DerivativeAggregation {
+                    buckets_path: "max_latency".to_string(),
+                    gap_policy: GapPolicy::Skip,
+                })
+            )]
+        );
+        let error = serde_json::from_value::<SearchAggregations>(json!({
+            "hourly": {
+                "histogram": {"field": "timestamp", "interval": 3_600_000_000u64},
+                "aggs": {"latency_rate": {"derivative": {"buckets_path": "latency"}}}
+            }
+        }))
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("aggregation `hourly` is invalid: `buckets_path` `latency`"));
+    }
+
+    #[test]
+    fn test_search_aggregations_from_json_multi_valued_fields() {
+        let mut schema_builder = SchemaBuilder::new();
+        schema_builder.add_u64_field("latency", FAST);
+        schema_builder.add_f64_field(
+            "latencies",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let schema = schema_builder.build();
+
+        let aggregations_json = json!({
+            "max_latency": {"max": {"field": "latency"}},
+            "sum_latencies": {"sum": {"field": "latencies"}},
+            "latencies_histogram": {"histogram": {"field": "latencies", "interval": 10}},
+            "latency_histogram": {
+                "histogram": {"field": "latency", "interval": 10},
+                "aggs": {"latencies_stats": {"stats": {"field": "latencies"}}}
+            }
+        });
+        let JsonValue::Object(aggregations_json) = aggregations_json else {
+            panic!("The aggregations should be a JSON object.");
+        };
+        let aggregations =
+            SearchAggregations::from_json(aggregations_json.clone(), Some(&schema)).unwrap();
+        assert_eq!(aggregations.tantivy_aggregations.len(), 1);
+        assert!(aggregations
+            .tantivy_aggregations
+            .contains_key("max_latency"));
+        assert_eq!(
+            aggregations.metric_aggregations["sum_latencies"],
+            QuickwitMetricAggregation::Sum(StatsAggregation {
+                field: "latencies".to_string(),
+            })
+        );
+        assert!(aggregations
+            .bucket_aggregations
+            .contains_key("latencies_histogram"));
+        assert_eq!(
+            aggregations.bucket_aggregations["latency_histogram"].sub_aggregations
+                ["latencies_stats"],
+            QuickwitMetricAggregation::Stats(StatsAggregation {
+                field: "latencies".to_string(),
+            })
+        );
+        aggregations.validate(&schema).unwrap();
+
+        // Without the schema, the aggregations are left to tantivy.
+        let aggregations = SearchAggregations::from_json(aggregations_json, None).unwrap();
+        assert_eq!(aggregations.tantivy_aggregations.len(), 4);
+    }
+
+    #[test]
+    fn test_quickwit_metric_aggregation_stats_final_result() {
+        let mut stats = IntermediateStats::default();
+        for value in [2.0, 4.0, 9.0] {
+            stats.add(value);
+        }
+        let aggregation = StatsAggregation {
+            field: "latencies".to_string(),
+        };
+        let final_result = |metric_aggregation: QuickwitMetricAggregation| {
+            metric_aggregation.into_final_result(Some(IntermediateMetricResult::Stats(stats)))
+        };
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::Avg(aggregation.clone())),
+            json!({"value": 5.0})
+        );
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::Min(aggregation.clone())),
+            json!({"value": 2.0})
+        );
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::Max(aggregation.clone())),
+            json!({"value": 9.0})
+        );
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::Sum(aggregation.clone())),
+            json!({"value": 15.0})
+        );
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::ValueCount(aggregation.clone())),
+            json!({"value": 3.0})
+        );
+        assert_eq!(
+            final_result(QuickwitMetricAggregation::Stats(aggregation.clone())),
+            json!({"count": 3, "sum": 15.0, "min": 2.0, "max": 9.0, "avg": 5.0})
+        );
+        assert_eq!(
+            QuickwitMetricAggregation::Avg(aggregation).into_final_result(None),
+            json!({ "value": null })
+        );
+    }
+
+    #[test]
+    fn test_search_aggregations_validate() {
+        let mut schema_builder = SchemaBuilder::new();
+        schema_builder.add_u64_field("latency", FAST);
+        schema_builder.add_u64_field("status", INDEXED);
+        let schema = schema_builder.build();
+
+        let aggregations_json =
+            |field_name: &str| json!({"percentiles": {"percentiles": {"field": field_name}}});
+        let aggregations: SearchAggregations =
+            serde_json::from_value(aggregations_json("latency")).unwrap();
+        aggregations.validate(&schema).unwrap();
+
+        let aggregations: SearchAggregations =
+            serde_json::from_value(aggregations_json("status")).unwrap();
+        assert_eq!(
+            aggregations.validate(&schema).unwrap_err(),
+            "Aggregation `percentiles` is invalid: Field `status` is not a numeric or datetime \
+             fast field."
+        );
+        let aggregations: SearchAggregations =
+            serde_json::from_value(aggregations_json("unknown")).unwrap();
+        assert!(aggregations.validate(&schema).is_err());
+    }
+
+    #[test]
+    fn test_intermediate_search_aggregation_results_merge() {
+        let mut left_sketch = PercentilesSketch::default();
+        left_sketch.add(1.0);
+        let mut right_sketch = PercentilesSketch::default();
+        right_sketch.add(3.0);
+
+        let mut left_results = IntermediateSearchAggregationResults {
+            tantivy: None,
+            metrics: BTreeMap::from_iter([(
+                "percentiles".to_string(),
+                IntermediateMetricResult::Percentiles(left_sketch),
+            )]),
+            buckets: BTreeMap::new(),
+            composites: BTreeMap::new(),
+        };
+        let right_results = IntermediateSearchAggregationResults {
+            tantivy: None,
+            metrics: BTreeMap::from_iter([(
+                "percentiles".to_string(),
+                IntermediateMetricResult::Percentiles(right_sketch),
+            )]),
+            buckets: BTreeMap::new(),
+            composites: BTreeMap::new(),
+        };
+        left_results.merge_fruits(right_results);
+
+        let aggregations: SearchAggregations = serde_json::from_value(json!({
+            "percentiles": {"percentiles": {"field": "latency", "percents": [0, 100]}},
+            "missing_percentiles": {"percentiles": {"field": "latency", "percents": [50]}}
+        }))
+        .unwrap();
+        let final_result_json = left_results
+            .into_final_result(aggregations, &SchemaBuilder::new().build())
+            .unwrap();
+        assert_eq!(
+            final_result_json,
+            json!({
+                "percentiles": {"values": {"0.0": 1.0, "100.0": 3.0}},
+                "missing_percentiles": {"values": {"50.0": null}}
+            })
+        );
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::{AggregationColumn, NumericColumn};
+
+/// Relative error of the percentiles computed by a [`PercentilesSketch`].
+const RELATIVE_ACCURACY: f64 = 0.01;
+
+/// Values whose magnitude is below this threshold are counted as zeros.
+const MIN_INDEXABLE_VALUE: f64 = 1e-9;
+
+const DEFAULT_PERCENTS: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+fn default_percents() -> Vec<f64> {
+    DEFAULT_PERCENTS.to_vec()
+}
+
+fn default_keyed() -> bool {
+    true
+}
+
+/// Computes approximate percentiles of a numeric or datetime fast field. Datetimes are read as
+/// microseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PercentilesAggregation {
+    /// Name of the field to compute the percentiles of.
+    pub field: String,
+    /// Percentiles to compute, between 0 and 100.
+    #[serde(default = "default_percents")]
+    pub percents: Vec<f64>,
+    /// Whether the percentiles are returned as a map keyed by percent or as a list.
+    #[serde(default = "default_keyed")]
+    pub keyed: bool,
+}
+
+impl PercentilesAggregation {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.percents.is_empty() {
+            return Err("`percents` must not be empty.".to_string());
+        }
+        if let Some(percent) = self
+            .percents
+            .iter()
+            .find(|percent| !(0.0..=100.0).contains(*percent))
+        {
+            return Err(format!(
+                "Percent `{percent}` is invalid: percents must be between 0 and 100."
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_final_result(self, sketch: &PercentilesSketch) -> JsonValue {
+        let values = self
+            .percents
+            .iter()
+            .map(|percent| (*percent, sketch.quantile(percent / 100.0)));
+        if self.keyed {
+            let values_json: JsonMap<String, JsonValue> = values
+                .map(|(percent, value_opt)| (format!("{percent:?}"), json!(value_opt)))
+                .collect();
+            json!({ "values": values_json })
+        } else {
+            let values_json: Vec<JsonValue> = values
+                .map(|(percent, value_opt)| json!({"key": percent, "value": value_opt}))
+                .collect();
+            json!({ "values": values_json })
+        }
+    }
+}
+
+fn ln_gamma() -> f64 {
+    ((1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)).ln()
+}
+
+/// Returns the index of the bucket containing `value`, which must be strictly positive. Bucket
+/// `i` holds the values in `(gamma^(i-1), gamma^i]`.
+fn bucket_index(value: f64) -> i32 {
+    (value.ln() / ln_gamma()).ceil() as i32
+}
+
+/// Returns the value representing the bucket `index`, which is within `RELATIVE_ACCURACY` of all
+/// the values of the bucket.
+fn bucket_value(index: i32) -> f64 {
+    let gamma = ln_gamma().exp();
+    2.0 * gamma.powi(index) / (gamma + 1.0)
+}
+
+/// Sketch of the distribution of a set of values, recorded in logarithmically sized buckets so
+/// that the percentiles are computed within a relative error of 1%.
+///
+/// Sketches are merged by adding up their bucket counts, so merging the sketches of the segments,
+/// splits, and leaves is lossless.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PercentilesSketch {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    positive_buckets: BTreeMap<i32, u64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    negative_buckets: BTreeMap<i32, u64>,
+    #[serde(default)]
+    zero_count: u64,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl PercentilesSketch {
+    /// Records a value. `NaN` values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if value > MIN_INDEXABLE_VALUE {
+            *self
+                .positive_buckets
+                .entry(bucket_index(value))
+                .or_default() += 1;
+        } else if value < -MIN_INDEXABLE_VALUE {
+            *self
+                .negative_buckets
+                .entry(bucket_index(-value))
+                .or_default() += 1;
+        } else {
+            self.zero_count += 1;
+        }
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+    }
+
+    /// Merges `other` into this sketch.
+    pub fn merge(&mut self, other: PercentilesSketch) {
+        if other.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            *self = other;
+            return;
+        }
+        for (index, count) in other.positive_buckets {
+            *self.positive_buckets.entry(index).or_default() += count;
+        }
+        for (index, count) in other.negative_buckets {
+            *self.negative_buckets.entry(index).or_default() += count;
+        }
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Returns the approximate value of the quantile `quantile`, between 0 and 1, or `None` if the
+    /// sketch is empty.
+    pub fn quantile(&self, quantile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if quantile <= 0.0 {
+            return Some(self.min);
+        }
+        if quantile >= 1.0 {
+            return Some(self.max);
+        }
+        let rank = quantile * (self.count - 1) as f64;
+        let mut cumulative_count = 0;
+
+        // The negative buckets are indexed by the magnitude of their values, so we walk them in
+        // reverse order to iterate over the values in ascending order.
+        let negative_values = self
+            .negative_buckets
+            .iter()
+            .rev()
+            .map(|(index, count)| (-bucket_value(*index), *count));
+        let zero_values = std::iter::once((0.0, self.zero_count));
+        let positive_values = self
+            .positive_buckets
+            .iter()
+            .map(|(index, count)| (bucket_value(*index), *count));
+
+        for (value, count) in negative_values.chain(zero_values).chain(positive_values) {
+            cumulative_count += count;
+            if cumulative_count as f64 > rank {
+                return Some(value.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Records the values of a field into a [`PercentilesSketch`] for the documents of a segment.
+pub(crate) struct PercentilesSegmentCollector {
+    column_opt: Option<NumericColumn>,
+    values_buffer: Vec<f64>,
+}
+
+impl PercentilesSegmentCollector {
+    pub fn new(
+        aggregation: &PercentilesAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let column_opt = AggregationColumn::open_numeric(segment_reader, &aggregation.field)?;
+        Ok(PercentilesSegmentCollector {
+            column_opt,
+            values_buffer: Vec::new(),
+        })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId, sketch: &mut PercentilesSketch) {
+        if let Some(column) = &self.column_opt {
+            column.get_vals(doc_id, &mut self.values_buffer);
+
+            for value in &self.values_buffer {
+                sketch.add(*value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_within_accuracy(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * RELATIVE_ACCURACY,
+            "{actual} is not within 1% of {expected}"
+        );
+    }
+
+    #[test]
+    fn test_percentiles_sketch() {
+        let mut sketch = PercentilesSketch::default();
+        assert_eq!(sketch.quantile(0.5), None);
+
+        for value in 1..=1_000 {
+            sketch.add(value as f64);
+        }
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(1.0), Some(1_000.0));
+        assert_within_accuracy(sketch.quantile(0.5).unwrap(), 500.0);
+        assert_within_accuracy(sketch.quantile(0.95).unwrap(), 950.0);
+        assert_within_accuracy(sketch.quantile(0.99).unwrap(), 990.0);
+    }
+
+    #[test]
+    fn test_percentiles_sketch_negative_and_zero_values() {
+        let mut sketch = PercentilesSketch::default();
+        for value in -100..=100 {
+            sketch.add(value as f64);
+        }
+        sketch.add(f64::NAN);
+        assert_eq!(sketch.quantile(0.5), Some(0.0));
+        assert_within_accuracy(sketch.quantile(0.25).unwrap(), -50.0);
+        assert_within_accuracy(sketch.quantile(0.75).unwrap(), 50.0);
+        assert_eq!(sketch.quantile(0.0), Some(-100.0));
+    }
+
+    #[test]
+    fn test_percentiles_sketch_merge() {
+        let mut left_sketch = PercentilesSketch::default();
+        let mut right_sketch = PercentilesSketch::default();
+        let mut sketch = PercentilesSketch::default();
+
+        for value in 1..=1_000 {
+            if value % 3 == 0 {
+                left_sketch.add(value as f64);
+            } else {
+                right_sketch.add(value as f64);
+            }
+            sketch.add(value as f64);
+        }
+        let right_sketch_json = serde_json::to_string(&right_sketch).unwrap();
+        let right_sketch: PercentilesSketch = serde_json::from_str(&right_sketch_json).unwrap();
+
+        left_sketch.merge(right_sketch);
+        left_sketch.merge(PercentilesSketch::default());
+        assert_eq!(left_sketch, sketch);
+    }
+
+    #[test]
+    fn test_percentiles_aggregation_final_result() {
+        let mut sketch = PercentilesSketch::default();
+        for value in [10.0, 20.0, 30.0] {
+            sketch.add(value);
+        }
+        let aggregation: PercentilesAggregation =
+            serde_json::from_value(json!({"field": "latency", "percents": [0, 100]})).unwrap();
+        assert!(aggregation.keyed);
+        assert_eq!(
+            aggregation.clone().into_final_result(&sketch),
+            json!({"values": {"0.0": 10.0, "100.0": 30.0}})
+        );
+        let aggregation = PercentilesAggregation {
+            keyed: false,
+            ..aggregation
+        };
+        assert_eq!(
+            aggregation.into_final_result(&PercentilesSketch::default()),
+            json!({"values": [{"key": 0.0, "value": null}, {"key": 100.0, "value": null}]})
+        );
+    }
+
+    #[test]
+    fn test_percentiles_aggregation_validate() {
+        let aggregation: PercentilesAggregation =
+            serde_json::from_value(json!({"field": "latency"})).unwrap();
+        assert_eq!(aggregation.percents, DEFAULT_PERCENTS);
+        aggregation.validate().unwrap();
+
+        let aggregation: PercentilesAggregation =
+            serde_json::from_value(json!({"field": "latency", "percents": [50, 101]})).unwrap();
+        assert!(aggregation.validate().is_err());
+    }
+}
@@ -0,0 +1,593 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::bucket::SUB_AGGREGATIONS_KEYS;
+
+/// Path of the document count of the buckets.
+const DOC_COUNT_PATH: &str = "_count";
+
+/// Policy applied to the buckets whose value is missing, e.g. the empty buckets of a histogram.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapPolicy {
+    /// Ignores the buckets whose value is missing.
+    #[default]
+    Skip,
+    /// Replaces the missing values with zero.
+    InsertZeros,
+}
+
+/// Computes the difference between the value of each bucket and the value of the previous bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DerivativeAggregation {
+    /// Path of the value to differentiate.
+    pub buckets_path: String,
+    /// Policy applied to the buckets whose value is missing.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+/// Computes the sum of the values of each bucket and all the previous buckets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CumulativeSumAggregation {
+    /// Path of the value to sum.
+    pub buckets_path: String,
+}
+
+/// Applies a function to a sliding window of bucket values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MovingFnAggregation {
+    /// Path of the values of the window.
+    pub buckets_path: String,
+    /// Number of buckets of the window.
+    pub window: usize,
+    /// Function applied to the window, among `MovingFunctions.max(values)`,
+    /// `MovingFunctions.min(values)`, `MovingFunctions.sum(values)`, and
+    /// `MovingFunctions.unweightedAvg(values)`.
+    pub script: String,
+    /// Shift of the window. By default, the window of a bucket ends right before the bucket.
+    #[serde(default)]
+    pub shift: usize,
+    /// Policy applied to the buckets whose value is missing.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MovingFunction {
+    Max,
+    Min,
+    Sum,
+    UnweightedAvg,
+}
+
+impl MovingFunction {
+    fn parse(script: &str) -> Option<MovingFunction> {
+        let function_name = script
+            .trim()
+            .strip_prefix("MovingFunctions.")?
+            .strip_suffix("(values)")?;
+        let function = match function_name {
+            "max" => MovingFunction::Max,
+            "min" => MovingFunction::Min,
+            "sum" => MovingFunction::Sum,
+            "unweightedAvg" => MovingFunction::UnweightedAvg,
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    fn apply(&self, values: impl Iterator<Item = f64>) -> Option<f64> {
+        match self {
+            MovingFunction::Max => values.reduce(f64::max),
+            MovingFunction::Min => values.reduce(f64::min),
+            MovingFunction::Sum => Some(values.sum()),
+            MovingFunction::UnweightedAvg => {
+                let (count, sum) = values.fold((0usize, 0.0), |(count, sum), value| {
+                    (count + 1, sum + value)
+                });
+                if count == 0 {
+                    None
+                } else {
+                    Some(sum / count as f64)
+                }
+            }
+        }
+    }
+}
+
+/// Aggregation computed by the root searcher from the final results of the other
+/// sub-aggregations of a histogram or date histogram aggregation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineAggregation {
+    /// Difference between consecutive buckets.
+    Derivative(DerivativeAggregation),
+    /// Running sum over the buckets.
+    CumulativeSum(CumulativeSumAggregation),
+    /// Function of a sliding window of buckets.
+    MovingFn(MovingFnAggregation),
+}
+
+impl PipelineAggregation {
+    const NAMES: [&'static str; 3] = ["derivative", "cumulative_sum", "moving_fn"];
+
+    fn is_pipeline_aggregation(aggregation_json: &JsonValue) -> bool {
+        aggregation_json
+            .as_object()
+            .map(|aggregation_obj| {
+                aggregation_obj
+                    .keys()
+                    .any(|key| Self::NAMES.contains(&key.as_str()))
+            })
+            .unwrap_or(false)
+    }
+
+    fn buckets_path(&self) -> &str {
+        match self {
+            PipelineAggregation::Derivative(aggregation) => &aggregation.buckets_path,
+            PipelineAggregation::CumulativeSum(aggregation) => &aggregation.buckets_path,
+            PipelineAggregation::MovingFn(aggregation) => &aggregation.buckets_path,
+        }
+    }
+
+    /// Returns the name of the sibling aggregation the aggregation reads its values from.
+    fn buckets_path_root(&self) -> &str {
+        let buckets_path = self.buckets_path();
+        buckets_path
+            .split_once('.')
+            .map(|(root, _)| root)
+            .unwrap_or(buckets_path)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if let PipelineAggregation::MovingFn(aggregation) = self {
+            if aggregation.window == 0 {
+                return Err("`window` must be strictly positive.".to_string());
+            }
+            if MovingFunction::parse(&aggregation.script).is_none() {
+                return Err(format!(
+                    "`script` `{}` is not supported: expected one of \
+                     `MovingFunctions.max(values)`, `MovingFunctions.min(values)`, \
+                     `MovingFunctions.sum(values)`, or `MovingFunctions.unweightedAvg(values)`.",
+                    aggregation.script
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes the result of each bucket from the values of the buckets, in bucket order.
+    /// `None` results are not returned.
+    fn compute(&self, values: &[Option<f64>]) -> Vec<Option<JsonValue>> {
+        match self {
+            PipelineAggregation::Derivative(aggregation) => {
+                let mut previous_value_opt: Option<f64> = None;
+                values
+                    .iter()
+                    .map(|value_opt| {
+                        let value = apply_gap_policy(*value_opt, aggregation.gap_policy)?;
+                        let derivative_opt =
+                            previous_value_opt.map(|previous_value| value - previous_value);
+                        previous_value_opt = Some(value);
+                        derivative_opt.map(|derivative| json!({ "value": derivative }))
+                    })
+                    .collect()
+            }
+            PipelineAggregation::CumulativeSum(_) => {
+                let mut cumulative_sum = 0.0;
+                values
+                    .iter()
+                    .map(|value_opt| {
+                        cumulative_sum += value_opt.unwrap_or(0.0);
+                        Some(json!({ "value": cumulative_sum }))
+                    })
+                    .collect()
+            }
+            PipelineAggregation::MovingFn(aggregation) => {
+                let function = MovingFunction::parse(&aggregation.script)
+                    .expect("The script should have been validated.");
+                (0..values.len())
+                    .map(|bucket_idx| {
+                        let window_end = (bucket_idx + aggregation.shift).min(values.len());
+                        let window_start = (bucket_idx + aggregation.shift)
+                            .saturating_sub(aggregation.window)
+                            .min(window_end);
+                        let window_values =
+                            values[window_start..window_end]
+                                .iter()
+                                .filter_map(|value_opt| {
+                                    apply_gap_policy(*value_opt, aggregation.gap_policy)
+                                });
+                        Some(json!({ "value": function.apply(window_values) }))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn apply_gap_policy(value_opt: Option<f64>, gap_policy: GapPolicy) -> Option<f64> {
+    match gap_policy {
+        GapPolicy::Skip => value_opt,
+        GapPolicy::InsertZeros => Some(value_opt.unwrap_or(0.0)),
+    }
+}
+
+/// Removes the pipeline sub-aggregations of `aggregation_json` and returns them in an order in
+/// which the pipeline aggregations are computed after the pipeline aggregations they read.
+pub(crate) fn extract_pipeline_aggregations(
+    aggregation_json: &mut JsonValue,
+) -> Result<Vec<(String, PipelineAggregation)>, String> {
+    let Some(aggregation_obj) = aggregation_json.as_object_mut() else {
+        return Ok(Vec::new());
+    };
+    let mut pipeline_aggregations = Vec::new();
+    let mut sibling_names = HashSet::new();
+
+    for key in SUB_AGGREGATIONS_KEYS {
+        let Some(JsonValue::Object(sub_aggregations_obj)) = aggregation_obj.get_mut(key) else {
+            continue;
+        };
+        let pipeline_names: Vec<String> = sub_aggregations_obj
+            .iter()
+            .filter(|(_, sub_aggregation_json)| {
+                PipelineAggregation::is_pipeline_aggregation(sub_aggregation_json)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for name in pipeline_names {
+            let sub_aggregation_json = sub_aggregations_obj
+                .remove(&name)
+                .expect("The pipeline aggregation should be present.");
+            let pipeline_aggregation: PipelineAggregation =
+                serde_json::from_value(sub_aggregation_json).map_err(|error| {
+                    format!("pipeline aggregation `{name}` is invalid: {error}")
+                })?;
+            pipeline_aggregation
+                .validate()
+                .map_err(|error| format!("pipeline aggregation `{name}` is invalid: {error}"))?;
+            pipeline_aggregations.push((name, pipeline_aggregation));
+        }
+        sibling_names.extend(sub_aggregations_obj.keys().cloned());
+
+        if sub_aggregations_obj.is_empty() {
+            aggregation_obj.remove(key);
+        }
+    }
+    if pipeline_aggregations.is_empty() {
+        return Ok(pipeline_aggregations);
+    }
+    if !aggregation_obj.contains_key("histogram") && !aggregation_obj.contains_key("date_histogram")
+    {
+        return Err(
+            "pipeline aggregations are only supported in histogram and date histogram \
+             aggregations"
+                .to_string(),
+        );
+    }
+    sibling_names.extend(pipeline_aggregations.iter().map(|(name, _)| name.clone()));
+
+    for (name, pipeline_aggregation) in &pipeline_aggregations {
+        let buckets_path_root = pipeline_aggregation.buckets_path_root();
+
+        if buckets_path_root != DOC_COUNT_PATH && !sibling_names.contains(buckets_path_root) {
+            return Err(format!(
+                "`buckets_path` `{}` of pipeline aggregation `{name}` does not refer to `_count` \
+                 or to a sibling aggregation",
+                pipeline_aggregation.buckets_path()
+            ));
+        }
+    }
+    // Orders the pipeline aggregations so that they are computed after the pipeline
+    // aggregations they read.
+    let mut sorted_pipeline_aggregations = Vec::with_capacity(pipeline_aggregations.len());
+
+    while !pipeline_aggregations.is_empty() {
+        let pending_names: HashSet<String> = pipeline_aggregations
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect();
+        let (ready_pipeline_aggregations, pending_pipeline_aggregations): (Vec<_>, Vec<_>) =
+            pipeline_aggregations
+                .into_iter()
+                .partition(|(_, pipeline_aggregation)| {
+                    !pending_names.contains(pipeline_aggregation.buckets_path_root())
+                });
+        if ready_pipeline_aggregations.is_empty() {
+            return Err("the `buckets_path` of the pipeline aggregations form a cycle".to_string());
+        }
+        sorted_pipeline_aggregations.extend(ready_pipeline_aggregations);
+        pipeline_aggregations = pending_pipeline_aggregations;
+    }
+    Ok(sorted_pipeline_aggregations)
+}
+
+/// Returns the value at `buckets_path` of a bucket: `_count` for the document count, `<name>` for
+/// the value of a single-value metric, or `<name>.<property>` for a property of a multi-value
+/// metric, e.g. `latency_stats.avg` or `latency_percentiles.99.0`.
+fn bucket_value(bucket_json: &JsonMap<String, JsonValue>, buckets_path: &str) -> Option<f64> {
+    if buckets_path == DOC_COUNT_PATH {
+        return bucket_json.get("doc_count")?.as_f64();
+    }
+    let Some((name, property)) = buckets_path.split_once('.') else {
+        return bucket_json.get(buckets_path)?.get("value")?.as_f64();
+    };
+    let result_json = bucket_json.get(name)?;
+    result_json
+        .get(property)
+        .or_else(|| result_json.get("values")?.get(property))?
+        .as_f64()
+}
+
+fn bucket_key(bucket_json: &JsonMap<String, JsonValue>) -> f64 {
+    bucket_json
+        .get("key")
+        .and_then(JsonValue::as_f64)
+        .unwrap_or(f64::NAN)
+}
+
+/// Computes the pipeline aggregations of the final result of a histogram or date histogram
+/// aggregation and inserts their results into its buckets.
+pub(crate) fn apply_pipeline_aggregations(
+    pipeline_aggregations: &[(String, PipelineAggregation)],
+    result_json: &mut JsonValue,
+) {
+    let Some(buckets_json) = result_json.get_mut("buckets") else {
+        return;
+    };
+    let mut buckets: Vec<&mut JsonMap<String, JsonValue>> = match buckets_json {
+        JsonValue::Array(buckets) => buckets
+            .iter_mut()
+            .filter_map(JsonValue::as_object_mut)
+            .collect(),
+        JsonValue::Object(keyed_buckets) => {
+            let mut buckets: Vec<&mut JsonMap<String, JsonValue>> = keyed_buckets
+                .values_mut()
+                .filter_map(JsonValue::as_object_mut)
+                .collect();
+            // The keyed buckets are not necessarily in key order.
+            buckets.sort_by(|left, right| bucket_key(left).total_cmp(&bucket_key(right)));
+            buckets
+        }
+        _ => return,
+    };
+    for (name, pipeline_aggregation) in pipeline_aggregations {
+        let values: Vec<Option<f64>> = buckets
+            .iter()
+            .map(|bucket_json| bucket_value(bucket_json, pipeline_aggregation.buckets_path()))
+            .collect();
+        let results = pipeline_aggregation.compute(&values);
+
+        for (bucket_json, result_opt) in buckets.iter_mut().zip(results) {
+            if let Some(result_json) = result_opt {
+                bucket_json.insert(name.clone(), result_json);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline_aggregation(aggregation_json: JsonValue) -> PipelineAggregation {
+        let pipeline_aggregation: PipelineAggregation =
+            serde_json::from_value(aggregation_json).unwrap();
+        pipeline_aggregation.validate().unwrap();
+        pipeline_aggregation
+    }
+
+    fn result_values(results: Vec<Option<JsonValue>>) -> Vec<Option<JsonValue>> {
+        results
+            .into_iter()
+            .map(|result_opt| result_opt.map(|result_json| result_json["value"].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_derivative() {
+        let values = [Some(1.0), Some(4.0), None, Some(10.0)];
+        let derivative = pipeline_aggregation(json!({"derivative": {"buckets_path": "_count"}}));
+        assert_eq!(
+            result_values(derivative.compute(&values)),
+            [None, Some(json!(3.0)), None, Some(json!(6.0))]
+        );
+        let derivative = pipeline_aggregation(json!({
+            "derivative": {"buckets_path": "_count", "gap_policy": "insert_zeros"}
+        }));
+        assert_eq!(
+            result_values(derivative.compute(&values)),
+            [None, Some(json!(3.0)), Some(json!(-4.0)), Some(json!(10.0))]
+        );
+    }
+
+    #[test]
+    fn test_cumulative_sum() {
+        let cumulative_sum =
+            pipeline_aggregation(json!({"cumulative_sum": {"buckets_path": "sales"}}));
+        assert_eq!(
+            result_values(cumulative_sum.compute(&[Some(1.0), None, Some(2.5)])),
+            [Some(json!(1.0)), Some(json!(1.0)), Some(json!(3.5))]
+        );
+    }
+
+    #[test]
+    fn test_moving_fn() {
+        let values = [Some(1.0), Some(2.0), Some(6.0), None, Some(4.0)];
+        let moving_avg = pipeline_aggregation(json!({
+            "moving_fn": {
+                "buckets_path": "sales",
+                "window": 2,
+                "script": "MovingFunctions.unweightedAvg(values)"
+            }
+        }));
+        assert_eq!(
+            result_values(moving_avg.compute(&values)),
+            [
+                Some(JsonValue::Null),
+                Some(json!(1.0)),
+                Some(json!(1.5)),
+                Some(json!(4.0)),
+                Some(json!(6.0))
+            ]
+        );
+        let moving_max = pipeline_aggregation(json!({
+            "moving_fn": {
+                "buckets_path": "sales",
+                "window": 2,
+                "script": "MovingFunctions.max(values)",
+                "shift": 1
+            }
+        }));
+        assert_eq!(
+            result_values(moving_max.compute(&values)),
+            [
+                Some(json!(1.0)),
+                Some(json!(2.0)),
+                Some(json!(6.0)),
+                Some(json!(6.0)),
+                Some(json!(4.0))
+            ]
+        );
+        let invalid_moving_fn: PipelineAggregation = serde_json::from_value(json!({
+            "moving_fn": {"buckets_path": "sales", "window": 2, "script": "values[0]"}
+        }))
+        .unwrap();
+        assert!(invalid_moving_fn.validate().is_err());
+    }
+
+    #[test]
+    fn test_extract_pipeline_aggregations() {
+        let mut aggregation_json = json!({
+            "histogram": {"field": "timestamp", "interval": 10},
+            "aggs": {
+                "sales": {"sum": {"field": "price"}},
+                "sales_rate": {"derivative": {"buckets_path": "total_sales"}},
+                "total_sales": {"cumulative_sum": {"buckets_path": "sales"}}
+            }
+        });
+        let pipeline_aggregations = extract_pipeline_aggregations(&mut aggregation_json).unwrap();
+        let pipeline_names: Vec<&str> = pipeline_aggregations
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(pipeline_names, ["total_sales", "sales_rate"]);
+        assert_eq!(
+            aggregation_json,
+            json!({
+                "histogram": {"field": "timestamp", "interval": 10},
+                "aggs": {"sales": {"sum": {"field": "price"}}}
+            })
+        );
+
+        let mut aggregation_json = json!({
+            "histogram": {"field": "timestamp", "interval": 10},
+            "aggs": {"count_rate": {"derivative": {"buckets_path": "_count"}}}
+        });
+        extract_pipeline_aggregations(&mut aggregation_json).unwrap();
+        assert_eq!(
+            aggregation_json,
+            json!({"histogram": {"field": "timestamp", "interval": 10}})
+        );
+
+        for (mut aggregation_json, expected_error) in [
+            (
+                json!({
+                    "terms": {"field": "service"},
+                    "aggs": {"count_rate": {"derivative": {"buckets_path": "_count"}}}
+                }),
+                "pipeline aggregations are only supported in histogram and date histogram \
+                 aggregations",
+            ),
+            (
+                json!({
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {"sales_rate": {"derivative": {"buckets_path": "sales"}}}
+                }),
+                "`buckets_path` `sales` of pipeline aggregation `sales_rate` does not refer to \
+                 `_count` or to a sibling aggregation",
+            ),
+            (
+                json!({
+                    "histogram": {"field": "timestamp", "interval": 10},
+                    "aggs": {
+                        "left": {"derivative": {"buckets_path": "right"}},
+                        "right": {"cumulative_sum": {"buckets_path": "left"}}
+                    }
+                }),
+                "the `buckets_path` of the pipeline aggregations form a cycle",
+            ),
+        ] {
+            assert_eq!(
+                extract_pipeline_aggregations(&mut aggregation_json).unwrap_err(),
+                expected_error
+            );
+        }
+    }
+
+    #[test]
+    fn test_apply_pipeline_aggregations() {
+        let mut aggregation_json = json!({
+            "histogram": {"field": "timestamp", "interval": 10},
+            "aggs": {
+                "latency": {"percentiles": {"field": "latency", "percents": [99]}},
+                "p99_rate": {"derivative": {"buckets_path": "latency.99.0"}},
+                "total_count": {"cumulative_sum": {"buckets_path": "_count"}}
+            }
+        });
+        let pipeline_aggregations = extract_pipeline_aggregations(&mut aggregation_json).unwrap();
+        let mut result_json = json!({
+            "buckets": {
+                "10": {"key": 10.0, "doc_count": 3, "latency": {"values": {"99.0": 30.0}}},
+                "0": {"key": 0.0, "doc_count": 1, "latency": {"values": {"99.0": 20.0}}}
+            }
+        });
+        apply_pipeline_aggregations(&pipeline_aggregations, &mut result_json);
+        assert_eq!(
+            result_json,
+            json!({
+                "buckets": {
+                    "0": {
+                        "key": 0.0,
+                        "doc_count": 1,
+                        "latency": {"values": {"99.0": 20.0}},
+                        "total_count": {"value": 1.0}
+                    },
+                    "10": {
+                        "key": 10.0,
+                        "doc_count": 3,
+                        "latency": {"values": {"99.0": 30.0}},
+                        "p99_rate": {"value": 10.0},
+                        "total_count": {"value": 4.0}
+                    }
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,180 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use tantivy::{DocId, SegmentReader};
+
+use super::column::{AggregationColumn, NumericColumn};
+
+/// Computes the average, minimum, maximum, sum, or number of the values of a numeric or datetime
+/// fast field, or all of them for a `stats` aggregation. Datetimes are read as microseconds since
+/// the Unix epoch.
+///
+/// Quickwit only computes these aggregations over the multi-valued fields, whose values are all
+/// taken into account, and leaves the other fields to tantivy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatsAggregation {
+    /// Name of the field to compute the metric of.
+    pub field: String,
+}
+
+/// Number, sum, minimum, and maximum of the values of a field, mergeable across segments,
+/// splits, and leaves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateStats {
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl IntermediateStats {
+    /// Records `value`. `NaN` values are ignored.
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    /// Merges `other` into these stats.
+    pub fn merge(&mut self, other: IntermediateStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.min = match (self.min, other.min) {
+            (Some(min), Some(other_min)) => Some(min.min(other_min)),
+            (min_opt, other_min_opt) => min_opt.or(other_min_opt),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(max), Some(other_max)) => Some(max.max(other_max)),
+            (max_opt, other_max_opt) => max_opt.or(other_max_opt),
+        };
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// Returns the average of the values, or `None` if there is none.
+    pub fn avg(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.sum / self.count as f64)
+    }
+
+    /// Returns the final result of a `stats` aggregation.
+    pub(crate) fn into_final_result(self) -> JsonValue {
+        json!({
+            "count": self.count,
+            "sum": self.sum,
+            "min": self.min,
+            "max": self.max,
+            "avg": self.avg(),
+        })
+    }
+}
+
+/// Records the values of a field into [`IntermediateStats`] for the documents of a segment.
+pub(crate) struct StatsSegmentCollector {
+    column_opt: Option<NumericColumn>,
+    values_buffer: Vec<f64>,
+}
+
+impl StatsSegmentCollector {
+    pub fn new(
+        aggregation: &StatsAggregation,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self> {
+        let column_opt = AggregationColumn::open_numeric(segment_reader, &aggregation.field)?;
+        Ok(StatsSegmentCollector {
+            column_opt,
+            values_buffer: Vec::new(),
+        })
+    }
+
+    pub fn collect(&mut self, doc_id: DocId, stats: &mut IntermediateStats) {
+        if let Some(column) = &self.column_opt {
+            column.get_vals(doc_id, &mut self.values_buffer);
+
+            for value in &self.values_buffer {
+                stats.add(*value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intermediate_stats() {
+        let mut stats = IntermediateStats::default();
+        assert_eq!(stats.avg(), None);
+        assert_eq!(
+            stats.into_final_result(),
+            json!({"count": 0, "sum": 0.0, "min": null, "max": null, "avg": null})
+        );
+        for value in [3.0, -1.0, f64::NAN, 4.0] {
+            stats.add(value);
+        }
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.sum(), 6.0);
+        assert_eq!(stats.min(), Some(-1.0));
+        assert_eq!(stats.max(), Some(4.0));
+        assert_eq!(stats.avg(), Some(2.0));
+    }
+
+    #[test]
+    fn test_intermediate_stats_merge() {
+        let mut left_stats = IntermediateStats::default();
+        left_stats.add(1.0);
+        left_stats.add(5.0);
+
+        let mut right_stats = IntermediateStats::default();
+        right_stats.add(-2.0);
+        let right_stats_json = serde_json::to_string(&right_stats).unwrap();
+        let right_stats: IntermediateStats = serde_json::from_str(&right_stats_json).unwrap();
+
+        left_stats.merge(right_stats);
+        left_stats.merge(IntermediateStats::default());
+        assert_eq!(
+            left_stats.into_final_result(),
+            json!({"count": 3, "sum": 4.0, "min": -2.0, "max": 5.0, "avg": 4.0 / 3.0})
+        );
+    }
+}
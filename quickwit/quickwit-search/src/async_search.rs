@@ -0,0 +1,732 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Async search, running a search in the background so that clients poll its progress and fetch
+//! its results later instead of waiting for the whole search on a single connection.
+//!
+//! The splits are searched in batches, and the number of hits and the aggregations of the splits
+//! searched so far are updated after each batch. The progress of a running search is kept in
+//! memory by the node that received the search, while the response of a completed search is
+//! stored in the storage of the index until it expires, so that it can be fetched from any node.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use quickwit_metastore::Metastore;
+use quickwit_proto::{LeafSearchResponse, SearchRequest, SearchResponse};
+use quickwit_storage::{Storage, StorageErrorKind, StorageUriResolver};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{error, instrument, warn, Instrument};
+use ulid::Ulid;
+
+use crate::admission::AdmissionPermit;
+use crate::root::{
+    merge_leaf_search_responses, root_fetch_docs, root_leaf_search, RootSearchContext,
+};
+use crate::{
+    list_relevant_splits, ClusterClient, SearchError, SearchJobPlacer, SearchResponseRest,
+};
+
+/// Default duration an async search and its results are kept.
+const DEFAULT_ASYNC_SEARCH_KEEP_ALIVE: Duration = Duration::from_secs(24 * 60 * 60); // 1 day
+
+/// Maximum duration an async search and its results are kept.
+const MAX_ASYNC_SEARCH_KEEP_ALIVE: Duration = Duration::from_secs(7 * 24 * 60 * 60); // 7 days
+
+/// Number of splits searched before the progress of an async search is updated.
+const ASYNC_SEARCH_BATCH_NUM_SPLITS: usize = 100;
+
+/// Directory of the storage of the index holding the responses of the async searches.
+const ASYNC_SEARCH_RESPONSES_DIR: &str = "async-searches";
+
+/// Status of an async search, returned by the async search API.
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct AsyncSearchResponse {
+    /// ID of the async search.
+    pub id: String,
+    /// Whether the search is still running.
+    pub is_running: bool,
+    /// Number of splits to search. Zero until the splits have been listed.
+    pub num_splits: usize,
+    /// Number of splits searched so far.
+    pub num_searched_splits: usize,
+    /// Unix timestamp, in seconds, after which the search and its response are deleted.
+    pub expiration_timestamp: u64,
+    /// While the search is running, the number of hits and the aggregations of the splits
+    /// searched so far, without hits. Once the search has completed, its full response.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<SearchResponseRest>,
+    /// Cause of the failure of the search.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The in-memory state of an async search started on this node.
+struct AsyncSearchEntry {
+    index_id: String,
+    is_running: bool,
+    num_splits: usize,
+    num_searched_splits: usize,
+    partial_response_opt: Option<SearchResponse>,
+    error_opt: Option<String>,
+    expiration_timestamp: u64,
+    task_handle_opt: Option<JoinHandle<()>>,
+}
+
+impl AsyncSearchEntry {
+    fn to_response(&self, async_search_id: &str) -> crate::Result<AsyncSearchResponse> {
+        let response = self
+            .partial_response_opt
+            .clone()
+            .map(SearchResponseRest::try_from)
+            .transpose()?;
+        Ok(AsyncSearchResponse {
+            id: async_search_id.to_string(),
+            is_running: self.is_running,
+            num_splits: self.num_splits,
+            num_searched_splits: self.num_searched_splits,
+            expiration_timestamp: self.expiration_timestamp,
+            response,
+            error: self.error_opt.clone(),
+        })
+    }
+}
+
+/// The async searches started on this node. Expired searches are evicted lazily, along with their
+/// stored response.
+#[derive(Default)]
+pub(crate) struct AsyncSearches {
+    entries: Mutex<HashMap<String, AsyncSearchEntry>>,
+}
+
+impl AsyncSearches {
+    fn insert(&self, async_search_id: &str, entry: AsyncSearchEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(async_search_id.to_string(), entry);
+    }
+
+    /// Removes the expired searches and returns their index ID and search ID.
+    fn evict_expired(&self, now_timestamp: u64) -> Vec<(String, String)> {
+        let mut entries = self.entries.lock().unwrap();
+        let expired_ids: Vec<String> = entries
+            .iter()
+            .filter(|(_, entry)| entry.expiration_timestamp <= now_timestamp)
+            .map(|(async_search_id, _)| async_search_id.clone())
+            .collect();
+        expired_ids
+            .into_iter()
+            .filter_map(|async_search_id| {
+                let entry = entries.remove(&async_search_id)?;
+                if let Some(task_handle) = entry.task_handle_opt {
+                    task_handle.abort();
+                }
+                Some((entry.index_id, async_search_id))
+            })
+            .collect()
+    }
+
+    fn update(&self, async_search_id: &str, update_fn: impl FnOnce(&mut AsyncSearchEntry)) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(async_search_id) {
+            update_fn(entry);
+        }
+    }
+
+    /// Returns the status of the search if it is running or has failed. The response of a
+    /// completed search is read from the storage.
+    fn get_in_progress(
+        &self,
+        index_id: &str,
+        async_search_id: &str,
+    ) -> crate::Result<Option<AsyncSearchResponse>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(async_search_id) {
+            Some(entry) if entry.index_id == index_id && !is_completed(entry) => {
+                entry.to_response(async_search_id).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn remove(&self, index_id: &str, async_search_id: &str) -> Option<AsyncSearchEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.get(async_search_id)?.index_id != index_id {
+            return None;
+        }
+        let entry = entries.remove(async_search_id)?;
+        if let Some(task_handle) = &entry.task_handle_opt {
+            task_handle.abort();
+        }
+        Some(entry)
+    }
+}
+
+fn is_completed(entry: &AsyncSearchEntry) -> bool {
+    !entry.is_running && entry.error_opt.is_none()
+}
+
+fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn async_search_keep_alive(keep_alive_secs_opt: Option<u32>) -> crate::Result<Duration> {
+    let Some(keep_alive_secs) = keep_alive_secs_opt else {
+        return Ok(DEFAULT_ASYNC_SEARCH_KEEP_ALIVE);
+    };
+    let keep_alive = Duration::from_secs(keep_alive_secs as u64);
+    if keep_alive.is_zero() || keep_alive > MAX_ASYNC_SEARCH_KEEP_ALIVE {
+        return Err(SearchError::InvalidArgument(format!(
+            "Async search keep alive must be between 1 and {} seconds, but got \
+             {keep_alive_secs}.",
+            MAX_ASYNC_SEARCH_KEEP_ALIVE.as_secs()
+        )));
+    }
+    Ok(keep_alive)
+}
+
+fn async_search_response_path(async_search_id: &str) -> PathBuf {
+    PathBuf::from(ASYNC_SEARCH_RESPONSES_DIR).join(format!("{async_search_id}.json"))
+}
+
+async fn resolve_index_storage(
+    index_id: &str,
+    metastore: &dyn Metastore,
+    storage_uri_resolver: &StorageUriResolver,
+) -> crate::Result<Arc<dyn Storage>> {
+    let index_metadata = metastore.index_metadata(index_id).await?;
+    let storage = storage_uri_resolver.resolve(index_metadata.index_uri())?;
+    Ok(storage)
+}
+
+/// Searches the splits in batches, updating the progress of the async search after each batch.
+/// Returns the search response along with the number of searched splits.
+async fn run_async_search(
+    async_search_id: &str,
+    root_search_context: &RootSearchContext,
+    async_searches: &AsyncSearches,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<(SearchResponse, usize)> {
+    let start_instant = tokio::time::Instant::now();
+    let search_request = &root_search_context.search_request;
+    let split_metadatas = list_relevant_splits(search_request, metastore).await?;
+    async_searches.update(async_search_id, |entry| {
+        entry.num_splits = split_metadatas.len();
+    });
+    let mut leaf_search_response_opt: Option<LeafSearchResponse> = None;
+    let mut num_searched_splits = 0;
+
+    for split_metadatas_batch in split_metadatas.chunks(ASYNC_SEARCH_BATCH_NUM_SPLITS) {
+        let batch_leaf_search_response = root_leaf_search(
+            root_search_context,
+            split_metadatas_batch,
+            cluster_client,
+            search_job_placer,
+        )
+        .await?;
+        let leaf_search_response = match leaf_search_response_opt {
+            Some(leaf_search_response) => {
+                merge_leaf_search_responses(
+                    search_request,
+                    vec![leaf_search_response, batch_leaf_search_response],
+                )
+                .await?
+            }
+            None => batch_leaf_search_response,
+        };
+        num_searched_splits += split_metadatas_batch.len();
+
+        let aggregation = leaf_search_response
+            .intermediate_aggregation_result
+            .clone()
+            .map(|intermediate_aggregation_result| {
+                root_search_context.finalize_aggregation(intermediate_aggregation_result)
+            })
+            .transpose()?;
+        let partial_response = SearchResponse {
+            num_hits: root_search_context.num_hits(leaf_search_response.num_hits),
+            aggregation,
+            elapsed_time_micros: start_instant.elapsed().as_micros() as u64,
+            ..Default::default()
+        };
+        async_searches.update(async_search_id, |entry| {
+            entry.num_searched_splits = num_searched_splits;
+            entry.partial_response_opt = Some(partial_response);
+        });
+        leaf_search_response_opt = Some(leaf_search_response);
+    }
+    let leaf_search_response = leaf_search_response_opt.unwrap_or_default();
+    let mut search_response = root_fetch_docs(
+        root_search_context,
+        leaf_search_response,
+        &split_metadatas,
+        cluster_client,
+        search_job_placer,
+    )
+    .await?;
+    search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+    Ok((search_response, split_metadatas.len()))
+}
+
+/// Stores the response of a completed async search in the storage of the index.
+async fn store_async_search_response(
+    async_search_id: &str,
+    num_splits: usize,
+    expiration_timestamp: u64,
+    search_response: SearchResponse,
+    storage: &dyn Storage,
+) -> crate::Result<()> {
+    let async_search_response = AsyncSearchResponse {
+        id: async_search_id.to_string(),
+        is_running: false,
+        num_splits,
+        num_searched_splits: num_splits,
+        expiration_timestamp,
+        response: Some(SearchResponseRest::try_from(search_response)?),
+        error: None,
+    };
+    let payload = serde_json::to_vec(&async_search_response)?;
+    storage
+        .put(
+            &async_search_response_path(async_search_id),
+            Box::new(payload),
+        )
+        .await
+        .map_err(|storage_error| {
+            SearchError::InternalError(format!(
+                "Failed to store the response of async search `{async_search_id}`: \
+                 {storage_error}"
+            ))
+        })
+}
+
+/// Deletes the stored responses of the expired async searches.
+async fn delete_expired_async_searches(
+    async_searches: &AsyncSearches,
+    metastore: &dyn Metastore,
+    storage_uri_resolver: &StorageUriResolver,
+) {
+    for (index_id, async_search_id) in async_searches.evict_expired(now_timestamp()) {
+        let delete_result = async {
+            let storage = resolve_index_storage(&index_id, metastore, storage_uri_resolver).await?;
+            storage
+                .delete(&async_search_response_path(&async_search_id))
+                .await
+                .map_err(|storage_error| SearchError::InternalError(storage_error.to_string()))
+        }
+        .await;
+        if let Err(error) = delete_result {
+            warn!(index_id = %index_id, async_search_id = %async_search_id, error = ?error, "Failed to delete the response of an expired async search.");
+        }
+    }
+}
+
+/// Starts an async search in the background and returns its ID along with its initial status.
+#[instrument(skip(
+    search_request,
+    async_searches,
+    metastore,
+    storage_uri_resolver,
+    cluster_client,
+    search_job_placer
+))]
+pub(crate) async fn submit_async_search(
+    search_request: SearchRequest,
+    keep_alive_secs_opt: Option<u32>,
+    admission_permit: AdmissionPermit,
+    async_searches: Arc<AsyncSearches>,
+    metastore: Arc<dyn Metastore>,
+    storage_uri_resolver: StorageUriResolver,
+    cluster_client: ClusterClient,
+    search_job_placer: SearchJobPlacer,
+) -> crate::Result<AsyncSearchResponse> {
+    let keep_alive = async_search_keep_alive(keep_alive_secs_opt)?;
+    if search_request.scroll_ttl_secs.is_some()
+        || search_request.hybrid_ranking.is_some()
+        || search_request.cross_cluster
+    {
+        return Err(SearchError::InvalidArgument(
+            "Async search does not support `scroll`, `hybrid`, and `cross_cluster`.".to_string(),
+        ));
+    }
+    delete_expired_async_searches(&async_searches, &*metastore, &storage_uri_resolver).await;
+
+    // Invalid requests are rejected before the search starts.
+    let root_search_context = RootSearchContext::new(&search_request, &*metastore).await?;
+    let storage =
+        resolve_index_storage(&search_request.index_id, &*metastore, &storage_uri_resolver).await?;
+    let async_search_id = Ulid::new().to_string();
+    let expiration_timestamp = now_timestamp() + keep_alive.as_secs();
+
+    let entry = AsyncSearchEntry {
+        index_id: search_request.index_id.clone(),
+        is_running: true,
+        num_splits: 0,
+        num_searched_splits: 0,
+        partial_response_opt: None,
+        error_opt: None,
+        expiration_timestamp,
+        task_handle_opt: None,
+    };
+    let async_search_response = entry.to_response(&async_search_id)?;
+    async_searches.insert(&async_search_id, entry);
+
+    let task_async_search_id = async_search_id.clone();
+    let task_async_searches = async_searches.clone();
+    let task_handle = tokio::spawn(async move {
+        let async_search_id = task_async_search_id;
+        let async_searches = task_async_searches;
+        // The admission permit is held until the search completes.
+        let _admission_permit = admission_permit;
+        let search_result = run_async_search(
+            &async_search_id,
+            &root_search_context,
+            &async_searches,
+            &*metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await;
+        let store_result = match search_result {
+            Ok((search_response, num_splits)) => {
+                store_async_search_response(
+                    &async_search_id,
+                    num_splits,
+                    expiration_timestamp,
+                    search_response,
+                    &*storage,
+                )
+                .await
+            }
+            Err(search_error) => Err(search_error),
+        };
+        async_searches.update(&async_search_id, |entry| {
+            entry.is_running = false;
+            entry.partial_response_opt = None;
+            entry.task_handle_opt = None;
+            if let Err(search_error) = store_result {
+                error!(async_search_id = %async_search_id, error = ?search_error, "Async search failed.");
+                entry.error_opt = Some(search_error.to_string());
+            }
+        });
+    }.in_current_span());
+    async_searches.update(&async_search_id, |entry| {
+        // The search may have already completed.
+        if entry.is_running {
+            entry.task_handle_opt = Some(task_handle);
+        }
+    });
+    Ok(async_search_response)
+}
+
+/// Returns the status of an async search, and its response once it has completed.
+pub(crate) async fn get_async_search(
+    index_id: &str,
+    async_search_id: &str,
+    async_searches: &AsyncSearches,
+    metastore: &dyn Metastore,
+    storage_uri_resolver: &StorageUriResolver,
+) -> crate::Result<AsyncSearchResponse> {
+    if let Some(async_search_response) =
+        async_searches.get_in_progress(index_id, async_search_id)?
+    {
+        return Ok(async_search_response);
+    }
+    let storage = resolve_index_storage(index_id, metastore, storage_uri_resolver).await?;
+    let response_path = async_search_response_path(async_search_id);
+    let response_bytes = match storage.get_all(&response_path).await {
+        Ok(response_bytes) => response_bytes,
+        Err(storage_error) if storage_error.kind() == StorageErrorKind::DoesNotExist => {
+            return Err(SearchError::AsyncSearchDoesNotExist(
+                async_search_id.to_string(),
+            ));
+        }
+        Err(storage_error) => {
+            return Err(SearchError::InternalError(format!(
+                "Failed to read the response of async search `{async_search_id}`: \
+                 {storage_error}"
+            )));
+        }
+    };
+    let async_search_response: AsyncSearchResponse =
+        serde_json::from_slice(response_bytes.as_slice())?;
+    if async_search_response.expiration_timestamp <= now_timestamp() {
+        if let Err(storage_error) = storage.delete(&response_path).await {
+            warn!(async_search_id = %async_search_id, error = ?storage_error, "Failed to delete the response of an expired async search.");
+        }
+        return Err(SearchError::AsyncSearchDoesNotExist(
+            async_search_id.to_string(),
+        ));
+    }
+    Ok(async_search_response)
+}
+
+/// Cancels an async search if it is running, and deletes its response.
+pub(crate) async fn delete_async_search(
+    index_id: &str,
+    async_search_id: &str,
+    async_searches: &AsyncSearches,
+    metastore: &dyn Metastore,
+    storage_uri_resolver: &StorageUriResolver,
+) -> crate::Result<()> {
+    let entry_opt = async_searches.remove(index_id, async_search_id);
+    let storage = resolve_index_storage(index_id, metastore, storage_uri_resolver).await?;
+    let response_path = async_search_response_path(async_search_id);
+    let response_exists = storage
+        .exists(&response_path)
+        .await
+        .map_err(|storage_error| SearchError::InternalError(storage_error.to_string()))?;
+    if entry_opt.is_none() && !response_exists {
+        return Err(SearchError::AsyncSearchDoesNotExist(
+            async_search_id.to_string(),
+        ));
+    }
+    if response_exists {
+        storage
+            .delete(&response_path)
+            .await
+            .map_err(|storage_error| SearchError::InternalError(storage_error.to_string()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::SearchAdmissionConfig;
+    use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
+    use quickwit_indexing::mock_split;
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::{FetchDocsResponse, LeafHit, PartialHit};
+
+    use super::*;
+    use crate::admission::{SearchAdmissionController, SearchPriority};
+    use crate::{MockSearchService, SearchServiceClient};
+
+    fn async_search_entry(expiration_timestamp: u64) -> AsyncSearchEntry {
+        AsyncSearchEntry {
+            index_id: "test-index".to_string(),
+            is_running: true,
+            num_splits: 0,
+            num_searched_splits: 0,
+            partial_response_opt: None,
+            error_opt: None,
+            expiration_timestamp,
+            task_handle_opt: None,
+        }
+    }
+
+    #[test]
+    fn test_async_search_keep_alive() {
+        assert_eq!(
+            async_search_keep_alive(None).unwrap(),
+            DEFAULT_ASYNC_SEARCH_KEEP_ALIVE
+        );
+        assert_eq!(
+            async_search_keep_alive(Some(60)).unwrap(),
+            Duration::from_secs(60)
+        );
+        assert!(matches!(
+            async_search_keep_alive(Some(0)).unwrap_err(),
+            SearchError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            async_search_keep_alive(Some(8 * 24 * 60 * 60)).unwrap_err(),
+            SearchError::InvalidArgument(_)
+        ));
+    }
+
+    #[test]
+    fn test_async_searches_evict_expired() {
+        let async_searches = AsyncSearches::default();
+        async_searches.insert("async_search_1", async_search_entry(100));
+        async_searches.insert("async_search_2", async_search_entry(200));
+
+        assert!(async_searches
+            .get_in_progress("test-index", "async_search_1")
+            .unwrap()
+            .is_some());
+        assert!(async_searches
+            .get_in_progress("other-index", "async_search_1")
+            .unwrap()
+            .is_none());
+
+        let expired = async_searches.evict_expired(150);
+        assert_eq!(
+            expired,
+            vec![("test-index".to_string(), "async_search_1".to_string())]
+        );
+        assert!(async_searches
+            .get_in_progress("test-index", "async_search_1")
+            .unwrap()
+            .is_none());
+        assert!(async_searches
+            .get_in_progress("test-index", "async_search_2")
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_async_search() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_index_metadata().returning(|index_id| {
+            Ok(IndexMetadata::for_test(
+                index_id,
+                &format!("ram:///indexes/{index_id}"),
+            ))
+        });
+        metastore.expect_list_splits().returning(|_filter| {
+            Ok((0..150)
+                .map(|split_ord| mock_split(&format!("split_{split_ord}")))
+                .collect())
+        });
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .returning(|leaf_search_request| {
+                let num_splits = leaf_search_request.split_offsets.len();
+                let partial_hits = leaf_search_request
+                    .split_offsets
+                    .iter()
+                    .map(|split_offsets| PartialHit {
+                        sorting_field_value: 1,
+                        split_id: split_offsets.split_id.clone(),
+                        segment_ord: 0,
+                        doc_id: 0,
+                        secondary_sorting_field_values: Vec::new(),
+                        collapse_value: None,
+                        num_collapsed_hits: None,
+                    })
+                    .collect();
+                Ok(LeafSearchResponse {
+                    num_hits: num_splits as u64,
+                    partial_hits,
+                    num_attempted_splits: num_splits as u64,
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+            .expect_fetch_docs()
+            .returning(|fetch_docs_request| {
+                let hits = fetch_docs_request
+                    .partial_hits
+                    .into_iter()
+                    .map(|partial_hit| LeafHit {
+                        leaf_json: r#"{"body": "hello"}"#.to_string(),
+                        partial_hit: Some(partial_hit),
+                        leaf_snippet_json: None,
+                    })
+                    .collect();
+                Ok(FetchDocsResponse { hits })
+            });
+        let client_pool =
+            ServiceClientPool::for_clients_list(vec![SearchServiceClient::from_service(
+                Arc::new(mock_search_service),
+                ([127, 0, 0, 1], 1000).into(),
+            )]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let metastore: Arc<dyn Metastore> = Arc::new(metastore);
+        let storage_uri_resolver = StorageUriResolver::for_test();
+        let async_searches = Arc::new(AsyncSearches::default());
+
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            max_hits: 5,
+            ..Default::default()
+        };
+        let admission_permit = SearchAdmissionController::new(SearchAdmissionConfig::default())
+            .admit("test-index", SearchPriority::Batch)
+            .await?;
+        let async_search_response = submit_async_search(
+            search_request,
+            Some(60),
+            admission_permit,
+            async_searches.clone(),
+            metastore.clone(),
+            storage_uri_resolver.clone(),
+            cluster_client,
+            search_job_placer,
+        )
+        .await?;
+        assert!(async_search_response.is_running);
+        let async_search_id = async_search_response.id;
+
+        let async_search_response = loop {
+            let async_search_response = get_async_search(
+                "test-index",
+                &async_search_id,
+                &async_searches,
+                &*metastore,
+                &storage_uri_resolver,
+            )
+            .await?;
+            if !async_search_response.is_running {
+                break async_search_response;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert!(async_search_response.error.is_none());
+        assert_eq!(async_search_response.num_splits, 150);
+        assert_eq!(async_search_response.num_searched_splits, 150);
+        let search_response_rest = async_search_response.response.unwrap();
+        assert_eq!(search_response_rest.num_hits, 150);
+        assert_eq!(search_response_rest.hits.len(), 5);
+
+        let get_error = get_async_search(
+            "other-index",
+            &async_search_id,
+            &async_searches,
+            &*metastore,
+            &storage_uri_resolver,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(get_error, SearchError::AsyncSearchDoesNotExist(_)));
+
+        delete_async_search(
+            "test-index",
+            &async_search_id,
+            &async_searches,
+            &*metastore,
+            &storage_uri_resolver,
+        )
+        .await?;
+        let get_error = get_async_search(
+            "test-index",
+            &async_search_id,
+            &async_searches,
+            &*metastore,
+            &storage_uri_resolver,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(get_error, SearchError::AsyncSearchDoesNotExist(_)));
+        Ok(())
+    }
+}
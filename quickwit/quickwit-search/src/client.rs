@@ -24,12 +24,11 @@ use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::{StreamExt, TryStreamExt};
-use http::Uri;
 use quickwit_config::service::QuickwitService;
 use quickwit_grpc_clients::service_client_pool::ServiceClient;
+use quickwit_grpc_clients::tls::grpc_endpoint;
 use quickwit_proto::tonic::codegen::InterceptedService;
-use quickwit_proto::tonic::transport::Endpoint;
-use quickwit_proto::{tonic, LeafSearchStreamResponse, SpanContextInterceptor};
+use quickwit_proto::{tonic, ClusterInterceptor, LeafSearchStreamResponse};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tonic::transport::Channel;
 use tonic::Request;
@@ -44,7 +43,7 @@ enum SearchServiceClientImpl {
     Local(Arc<dyn SearchService>),
     Grpc(
         quickwit_proto::search_service_client::SearchServiceClient<
-            InterceptedService<Channel, SpanContextInterceptor>,
+            InterceptedService<Channel, ClusterInterceptor>,
         >,
     ),
 }
@@ -90,7 +89,7 @@ impl SearchServiceClient {
     /// Create a search service client instance given a gRPC client and gRPC address.
     pub fn from_grpc_client(
         client: quickwit_proto::search_service_client::SearchServiceClient<
-            InterceptedService<Channel, SpanContextInterceptor>,
+            InterceptedService<Channel, ClusterInterceptor>,
         >,
         grpc_addr: SocketAddr,
     ) -> Self {
@@ -238,6 +237,24 @@ impl SearchServiceClient {
             SearchServiceClientImpl::Local(service) => service.leaf_list_terms(request).await,
         }
     }
+
+    /// Perform leaf warm-up.
+    pub async fn leaf_warmup(
+        &mut self,
+        request: quickwit_proto::LeafWarmupRequest,
+    ) -> crate::Result<quickwit_proto::WarmupResponse> {
+        match &mut self.client_impl {
+            SearchServiceClientImpl::Grpc(grpc_client) => {
+                let tonic_request = Request::new(request);
+                let tonic_response = grpc_client
+                    .leaf_warmup(tonic_request)
+                    .await
+                    .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+                Ok(tonic_response.into_inner())
+            }
+            SearchServiceClientImpl::Local(service) => service.leaf_warmup(request).await,
+        }
+    }
 }
 
 /// Creates a [`SearchServiceClient`] with SocketAddr as an argument.
@@ -245,18 +262,13 @@ impl SearchServiceClient {
 pub async fn create_search_service_client(
     grpc_addr: SocketAddr,
 ) -> anyhow::Result<SearchServiceClient> {
-    let uri = Uri::builder()
-        .scheme("http")
-        .authority(grpc_addr.to_string().as_str())
-        .path_and_query("/")
-        .build()?;
     // Create a channel with connect_lazy to automatically reconnect to the node.
-    let channel = Endpoint::from(uri)
+    let channel = grpc_endpoint(grpc_addr)
         .connect_timeout(Duration::from_secs(5))
         .connect_lazy();
     let client = quickwit_proto::search_service_client::SearchServiceClient::with_interceptor(
         channel,
-        SpanContextInterceptor,
+        ClusterInterceptor,
     );
     let client = SearchServiceClient::from_grpc_client(client, grpc_addr);
     Ok(client)
@@ -17,17 +17,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use futures::StreamExt;
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafListTermsRequest, LeafListTermsResponse,
     LeafSearchRequest, LeafSearchResponse, LeafSearchStreamRequest, LeafSearchStreamResponse,
 };
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::task::{JoinError, JoinHandle};
 use tokio_stream::wrappers::UnboundedReceiverStream;
-use tracing::debug;
+use tracing::{debug, Instrument};
 
+use crate::aggregations::IntermediateSearchAggregationResults;
+use crate::metrics::SEARCH_METRICS;
 use crate::retry::search::LeafSearchRetryPolicy;
 use crate::retry::search_stream::{LeafSearchStreamRetryPolicy, SuccessfulSplitIds};
 use crate::retry::{retry_client, DefaultRetryPolicy, RetryPolicy};
@@ -39,12 +43,27 @@ use crate::{SearchError, SearchJobPlacer, SearchServiceClient};
 #[derive(Clone)]
 pub struct ClusterClient {
     search_job_placer: SearchJobPlacer,
+    /// Delay after which a pending leaf search request is also sent to another client.
+    leaf_search_hedging_delay_opt: Option<Duration>,
 }
 
 impl ClusterClient {
     /// Instantiates [`ClusterClient`].
     pub fn new(search_job_placer: SearchJobPlacer) -> Self {
-        Self { search_job_placer }
+        Self {
+            search_job_placer,
+            leaf_search_hedging_delay_opt: None,
+        }
+    }
+
+    /// Hedges the leaf search requests still pending after `leaf_search_hedging_delay_opt`: the
+    /// request is also sent to another client and the first successful response is kept.
+    pub fn with_leaf_search_hedging_delay(
+        mut self,
+        leaf_search_hedging_delay_opt: Option<Duration>,
+    ) -> Self {
+        self.leaf_search_hedging_delay_opt = leaf_search_hedging_delay_opt;
+        self
     }
 
     /// Fetches docs with retry on another node client.
@@ -71,13 +90,13 @@ impl ClusterClient {
         response_res
     }
 
-    /// Leaf search with retry on another node client.
+    /// Leaf search with hedging and retry on another node client.
     pub async fn leaf_search(
         &self,
         request: LeafSearchRequest,
-        mut client: SearchServiceClient,
+        client: SearchServiceClient,
     ) -> crate::Result<LeafSearchResponse> {
-        let mut response_res = client.leaf_search(request.clone()).await;
+        let (mut response_res, mut client) = self.hedged_leaf_search(request.clone(), client).await;
         let retry_policy = LeafSearchRetryPolicy {};
         if let Some(retry_request) = retry_policy.retry_request(request, &response_res) {
             assert!(!retry_request.split_offsets.is_empty());
@@ -96,6 +115,61 @@ impl ClusterClient {
         response_res
     }
 
+    /// Sends the leaf search request to `client` and, if it has not responded after the hedging
+    /// delay, to another client as well. Returns the first successful response, or the last
+    /// error, along with the client that produced it.
+    async fn hedged_leaf_search(
+        &self,
+        request: LeafSearchRequest,
+        mut client: SearchServiceClient,
+    ) -> (crate::Result<LeafSearchResponse>, SearchServiceClient) {
+        let Some(hedging_delay) = self.leaf_search_hedging_delay_opt else {
+            let response_res = client.leaf_search(request).await;
+            return (response_res, client);
+        };
+        // The requests run in their own tasks so that a client blocking the thread polling it
+        // does not delay the other request.
+        let mut response_task = spawn_leaf_search(client.clone(), request.clone());
+        if let Ok(join_res) = tokio::time::timeout(hedging_delay, &mut response_task).await {
+            return (flatten_join_result(join_res), client);
+        }
+        let Some(split_offsets) = request.split_offsets.first() else {
+            return (flatten_join_result(response_task.await), client);
+        };
+        let hedged_client =
+            match retry_client(&self.search_job_placer, &client, &split_offsets.split_id) {
+                Ok(hedged_client) if hedged_client.grpc_addr() != client.grpc_addr() => {
+                    hedged_client
+                }
+                _ => return (flatten_join_result(response_task.await), client),
+            };
+        debug!(
+            "Leaf search request still pending after {:?}. Hedge it with {:?}",
+            hedging_delay, hedged_client
+        );
+        SEARCH_METRICS.hedged_leaf_searches_total.inc();
+        let mut hedged_response_task = spawn_leaf_search(hedged_client.clone(), request);
+
+        tokio::select! {
+            join_res = &mut response_task => {
+                let response_res = flatten_join_result(join_res);
+                if is_complete_leaf_search_response(&response_res) {
+                    hedged_response_task.abort();
+                    return (response_res, client);
+                }
+                (flatten_join_result(hedged_response_task.await), hedged_client)
+            }
+            join_res = &mut hedged_response_task => {
+                let hedged_response_res = flatten_join_result(join_res);
+                if is_complete_leaf_search_response(&hedged_response_res) {
+                    response_task.abort();
+                    return (hedged_response_res, hedged_client);
+                }
+                (flatten_join_result(response_task.await), client)
+            }
+        }
+    }
+
     /// Leaf search stream with retry on another node client.
     pub async fn leaf_search_stream(
         &self,
@@ -108,39 +182,45 @@ impl ClusterClient {
         let (result_sender, result_receiver) = unbounded_channel();
         let client_pool = self.search_job_placer.clone();
         let retry_policy = LeafSearchStreamRetryPolicy {};
-        tokio::spawn(async move {
-            let result_stream = client.leaf_search_stream(request.clone()).await;
-            // Forward only responses and not errors to the sender as we will make one retry on
-            // errors.
-            let forward_result =
-                forward_leaf_search_stream(result_stream, result_sender.clone(), false).await;
-            if let Some(retry_request) = retry_policy.retry_request(request, &forward_result) {
-                assert!(!retry_request.split_offsets.is_empty());
-                let retry_client_res = retry_client(
-                    &client_pool,
-                    &client,
-                    &retry_request.split_offsets[0].split_id,
-                );
-                let mut retry_client = match retry_client_res {
-                    Ok(retry_client) => retry_client,
-                    Err(error) => {
-                        // Propagates the error if we cannot get a new client and stops the task.
-                        let _ = result_sender.send(Err(SearchError::from(error)));
-                        return;
-                    }
-                };
-                debug!(
-                    "Leaf search stream response error. Retry once to execute {:?} with {:?}",
-                    retry_request, client
-                );
-                let retry_results_stream = retry_client.leaf_search_stream(retry_request).await;
-                // Forward all results to the result_sender as we won't do another retry.
-                // It is ok to ignore send errors, there is nothing else to do.
-                let _ =
-                    forward_leaf_search_stream(retry_results_stream, result_sender.clone(), true)
-                        .await;
+        tokio::spawn(
+            async move {
+                let result_stream = client.leaf_search_stream(request.clone()).await;
+                // Forward only responses and not errors to the sender as we will make one retry on
+                // errors.
+                let forward_result =
+                    forward_leaf_search_stream(result_stream, result_sender.clone(), false).await;
+                if let Some(retry_request) = retry_policy.retry_request(request, &forward_result) {
+                    assert!(!retry_request.split_offsets.is_empty());
+                    let retry_client_res = retry_client(
+                        &client_pool,
+                        &client,
+                        &retry_request.split_offsets[0].split_id,
+                    );
+                    let mut retry_client = match retry_client_res {
+                        Ok(retry_client) => retry_client,
+                        Err(error) => {
+                            // Propagates the error if we cannot get a new client and stops the task.
+                            let _ = result_sender.send(Err(SearchError::from(error)));
+                            return;
+                        }
+                    };
+                    debug!(
+                        "Leaf search stream response error. Retry once to execute {:?} with {:?}",
+                        retry_request, client
+                    );
+                    let retry_results_stream = retry_client.leaf_search_stream(retry_request).await;
+                    // Forward all results to the result_sender as we won't do another retry.
+                    // It is ok to ignore send errors, there is nothing else to do.
+                    let _ = forward_leaf_search_stream(
+                        retry_results_stream,
+                        result_sender.clone(),
+                        true,
+                    )
+                    .await;
+                }
             }
-        });
+            .in_current_span(),
+        );
 
         UnboundedReceiverStream::new(result_receiver)
     }
@@ -156,6 +236,27 @@ impl ClusterClient {
     }
 }
 
+fn spawn_leaf_search(
+    mut client: SearchServiceClient,
+    request: LeafSearchRequest,
+) -> JoinHandle<crate::Result<LeafSearchResponse>> {
+    tokio::spawn(async move { client.leaf_search(request).await }.in_current_span())
+}
+
+fn flatten_join_result(
+    join_res: Result<crate::Result<LeafSearchResponse>, JoinError>,
+) -> crate::Result<LeafSearchResponse> {
+    join_res.unwrap_or_else(|join_error| {
+        Err(SearchError::InternalError(format!(
+            "Leaf search task failed: {join_error}."
+        )))
+    })
+}
+
+fn is_complete_leaf_search_response(response_res: &crate::Result<LeafSearchResponse>) -> bool {
+    matches!(response_res, Ok(response) if response.failed_splits.is_empty())
+}
+
 // Merge initial leaf search results with results obtained from a retry.
 fn merge_leaf_search_results(
     initial_response_result: crate::Result<LeafSearchResponse>,
@@ -171,9 +272,10 @@ fn merge_leaf_search_results(
                 .map(|res1_str| {
                     if let Some(res2_str) = retry_response.intermediate_aggregation_result.as_ref()
                     {
-                        let mut res1: IntermediateAggregationResults =
+                        let mut res1: IntermediateSearchAggregationResults =
                             serde_json::from_str(&res1_str)?;
-                        let res2: IntermediateAggregationResults = serde_json::from_str(res2_str)?;
+                        let res2: IntermediateSearchAggregationResults =
+                            serde_json::from_str(res2_str)?;
                         res1.merge_fruits(res2);
                         serde_json::to_string(&res1)
                     } else {
@@ -245,6 +347,9 @@ mod tests {
             split_id: split_id.to_string(),
             segment_ord: 1,
             doc_id,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
         }
     }
 
@@ -257,6 +362,7 @@ mod tests {
                 split_id: split_id.to_string(),
                 split_footer_end: 100,
                 split_footer_start: 0,
+                ..Default::default()
             }],
             ..Default::default()
         }
@@ -282,11 +388,13 @@ mod tests {
                     split_id: "split_1".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    ..Default::default()
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    ..Default::default()
                 },
             ],
         }
@@ -313,11 +421,13 @@ mod tests {
                     split_id: "split_1".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    ..Default::default()
                 },
                 SplitIdAndFooterOffsets {
                     split_id: "split_2".to_string(),
                     split_footer_start: 0,
                     split_footer_end: 100,
+                    ..Default::default()
                 },
             ],
         }
@@ -487,6 +597,50 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cluster_client_leaf_search_hedging() {
+        let mut mock_service_1 = MockSearchService::new();
+        mock_service_1
+            .expect_leaf_search()
+            .return_once(|_: LeafSearchRequest| {
+                std::thread::sleep(Duration::from_millis(500));
+                Ok(LeafSearchResponse {
+                    num_hits: 1,
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        let mut mock_service_2 = MockSearchService::new();
+        mock_service_2
+            .expect_leaf_search()
+            .return_once(|_: LeafSearchRequest| {
+                Ok(LeafSearchResponse {
+                    num_hits: 2,
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        let client_pool = ServiceClientPool::for_clients_list(vec![
+            SearchServiceClient::from_service(
+                Arc::new(mock_service_1),
+                ([127, 0, 0, 1], 1000).into(),
+            ),
+            SearchServiceClient::from_service(
+                Arc::new(mock_service_2),
+                ([127, 0, 0, 1], 1001).into(),
+            ),
+        ]);
+        let first_grpc_addr: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let first_client = client_pool.all().remove(&first_grpc_addr).unwrap();
+        let cluster_client = ClusterClient::new(SearchJobPlacer::new(client_pool))
+            .with_leaf_search_hedging_delay(Some(Duration::from_millis(50)));
+        let response = cluster_client
+            .leaf_search(mock_leaf_search_request(), first_client)
+            .await
+            .unwrap();
+        assert_eq!(response.num_hits, 2);
+    }
+
     #[test]
     fn test_merge_leaf_search_retry_on_partial_success() -> anyhow::Result<()> {
         let split_error = SplitSearchError {
@@ -0,0 +1,468 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Collapsing of the hits by the value of a fast field.
+//!
+//! Each segment only keeps the top hit of its top `start_offset + max_hits` distinct values, and
+//! the merges of the segment, split, and leaf responses keep the top hit of each distinct value.
+//! A value in the global top `start_offset + max_hits` values is also in the top values of every
+//! segment holding its top hit, so the collapsed hits are exact.
+//!
+//! Counting the hits of each returned value requires a second leaf search phase, restricted to
+//! the returned values.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::{PartialHit, SearchRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tantivy::schema::{Cardinality, FieldType};
+use tantivy::{DocId, SegmentReader};
+
+use crate::aggregations::{format_numeric_value, AggregationColumn};
+use crate::SearchError;
+
+/// Collapse options of a search request.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Collapse {
+    /// Name of the fast field whose distinct values are collapsed.
+    pub field: String,
+    /// If set, the number of hits sharing the value of each collapsed hit is computed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub count: bool,
+    /// JSON serialized values the hits are restricted to. Set by the root to count the hits of
+    /// the returned values.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<String>>,
+}
+
+/// Parses the JSON serialized collapse options of the user request.
+pub fn parse_collapse(collapse_json_opt: Option<&str>) -> crate::Result<Option<Collapse>> {
+    let Some(collapse_json) = collapse_json_opt else {
+        return Ok(None);
+    };
+    let collapse: Collapse = serde_json::from_str(collapse_json).map_err(|error| {
+        SearchError::InvalidArgument(format!("Invalid collapse options: {error}"))
+    })?;
+    Ok(Some(collapse))
+}
+
+/// Checks that the collapse field is a single-valued numeric, bool, or datetime fast field, or a
+/// text fast field, and that the request does not paginate with a cursor.
+pub fn validate_collapse(
+    doc_mapper: &dyn DocMapper,
+    search_request: &SearchRequest,
+    collapse: &Collapse,
+) -> crate::Result<()> {
+    let schema = doc_mapper.schema();
+    let field_name = collapse.field.as_str();
+    let field = schema.get_field(field_name).map_err(|_| {
+        SearchError::InvalidArgument(format!("Collapse field `{field_name}` does not exist."))
+    })?;
+    let field_entry = schema.get_field_entry(field);
+    let is_supported_field = match field_entry.field_type() {
+        FieldType::U64(options)
+        | FieldType::I64(options)
+        | FieldType::F64(options)
+        | FieldType::Bool(options) => {
+            options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+        }
+        FieldType::Date(options) => {
+            options.get_fastfield_cardinality() == Some(Cardinality::SingleValue)
+        }
+        FieldType::Str(_) => field_entry.is_fast(),
+        _ => false,
+    };
+    if !is_supported_field {
+        return Err(SearchError::InvalidArgument(format!(
+            "Collapse field `{field_name}` is not a text fast field or a single-valued numeric, \
+             bool, or datetime fast field."
+        )));
+    }
+    // A value whose top hit is on a previous page may have other hits after the cursor.
+    if search_request.search_after.is_some() || search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Collapsed hits can only be paginated with `start_offset`, `search_after` and \
+             `scroll` cannot be set."
+                .to_string(),
+        ));
+    }
+    if search_request.knn_query.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "kNN search hits cannot be collapsed.".to_string(),
+        ));
+    }
+    if search_request.cross_cluster {
+        return Err(SearchError::InvalidArgument(
+            "Hits cannot be collapsed across clusters.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Keeps the first partial hit of each collapse value, summing the numbers of collapsed hits.
+/// The partial hits must be sorted, and the partial hits without a collapse value are all kept.
+pub(crate) fn collapse_partial_hits(partial_hits: Vec<PartialHit>) -> Vec<PartialHit> {
+    let mut collapsed_partial_hits: Vec<PartialHit> = Vec::with_capacity(partial_hits.len());
+    let mut collapsed_hit_ords: HashMap<String, usize> = HashMap::new();
+
+    for partial_hit in partial_hits {
+        let Some(collapse_value) = &partial_hit.collapse_value else {
+            collapsed_partial_hits.push(partial_hit);
+            continue;
+        };
+        if let Some(&collapsed_hit_ord) = collapsed_hit_ords.get(collapse_value) {
+            let collapsed_partial_hit = &mut collapsed_partial_hits[collapsed_hit_ord];
+            if let (Some(num_collapsed_hits), Some(num_hits)) = (
+                collapsed_partial_hit.num_collapsed_hits.as_mut(),
+                partial_hit.num_collapsed_hits,
+            ) {
+                *num_collapsed_hits += num_hits;
+            }
+            continue;
+        }
+        collapsed_hit_ords.insert(collapse_value.clone(), collapsed_partial_hits.len());
+        collapsed_partial_hits.push(partial_hit);
+    }
+    collapsed_partial_hits
+}
+
+/// Returns the request counting the hits of the collapse values of the partial hits, if the
+/// count of the collapsed hits is requested.
+pub(crate) fn collapse_count_request(
+    search_request: &SearchRequest,
+    partial_hits: &[PartialHit],
+) -> crate::Result<Option<SearchRequest>> {
+    let Some(collapse) = parse_collapse(search_request.collapse.as_deref())? else {
+        return Ok(None);
+    };
+    let values: Vec<String> = partial_hits
+        .iter()
+        .filter_map(|partial_hit| partial_hit.collapse_value.clone())
+        .collect();
+    if !collapse.count || values.is_empty() {
+        return Ok(None);
+    }
+    let count_collapse = Collapse {
+        field: collapse.field,
+        count: false,
+        values: Some(values),
+    };
+    let count_request = SearchRequest {
+        max_hits: partial_hits.len() as u64,
+        start_offset: 0,
+        aggregation_request: None,
+        snippet_fields: Vec::new(),
+        collapse: Some(serde_json::to_string(&count_collapse)?),
+        ..search_request.clone()
+    };
+    Ok(Some(count_request))
+}
+
+/// Sets the number of collapsed hits of the partial hits from the partial hits of the count
+/// request.
+pub(crate) fn set_num_collapsed_hits(
+    partial_hits: &mut [PartialHit],
+    count_partial_hits: &[PartialHit],
+) {
+    let num_collapsed_hits: HashMap<&str, u64> = count_partial_hits
+        .iter()
+        .filter_map(|partial_hit| {
+            let collapse_value = partial_hit.collapse_value.as_deref()?;
+            Some((collapse_value, partial_hit.num_collapsed_hits?))
+        })
+        .collect();
+    for partial_hit in partial_hits {
+        partial_hit.num_collapsed_hits = partial_hit
+            .collapse_value
+            .as_deref()
+            .and_then(|collapse_value| num_collapsed_hits.get(collapse_value).copied());
+    }
+}
+
+/// Value of the collapse field of a document, specific to a segment.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum CollapseKey {
+    Missing,
+    /// Bits of the value of a numeric field.
+    Numeric(u64),
+    /// Ordinal of the first term of a text field.
+    TermOrd(u64),
+}
+
+/// Keeps the top hit of the top distinct values of the collapse field of a segment.
+///
+/// Hits are ranked by increasing order, like the items of the top-k heap of the collector.
+pub(crate) struct SegmentCollapser<H> {
+    column_opt: Option<AggregationColumn>,
+    term_ords_buffer: Vec<u64>,
+    values_buffer: Vec<f64>,
+    allowed_keys_opt: Option<HashSet<CollapseKey>>,
+    count_hits: bool,
+    max_groups: usize,
+    groups: HashMap<CollapseKey, (H, u64)>,
+    ranking: BTreeSet<(H, CollapseKey)>,
+}
+
+impl<H: Clone + Ord> SegmentCollapser<H> {
+    pub fn new(
+        collapse: &Collapse,
+        segment_reader: &SegmentReader,
+        max_groups: usize,
+    ) -> tantivy::Result<Self> {
+        let column_opt = AggregationColumn::open(segment_reader, &collapse.field)?;
+        let allowed_keys_opt = collapse
+            .values
+            .as_ref()
+            .map(|values| {
+                let mut allowed_keys = HashSet::with_capacity(values.len());
+                for value in values {
+                    if let Some(key) = collapse_key_from_value(column_opt.as_ref(), value)? {
+                        allowed_keys.insert(key);
+                    }
+                }
+                tantivy::Result::Ok(allowed_keys)
+            })
+            .transpose()?;
+        Ok(SegmentCollapser {
+            column_opt,
+            term_ords_buffer: Vec::new(),
+            values_buffer: Vec::new(),
+            count_hits: collapse.values.is_some(),
+            allowed_keys_opt,
+            max_groups,
+            groups: HashMap::new(),
+            ranking: BTreeSet::new(),
+        })
+    }
+
+    fn collapse_key(&mut self, doc_id: DocId) -> CollapseKey {
+        match &self.column_opt {
+            None => CollapseKey::Missing,
+            Some(AggregationColumn::Numeric(column)) => {
+                column.get_vals(doc_id, &mut self.values_buffer);
+                self.values_buffer
+                    .first()
+                    .map_or(CollapseKey::Missing, |value| {
+                        CollapseKey::Numeric(value.to_bits())
+                    })
+            }
+            Some(AggregationColumn::Text(column)) => {
+                column.term_ords(doc_id, &mut self.term_ords_buffer);
+                self.term_ords_buffer
+                    .first()
+                    .map_or(CollapseKey::Missing, |&term_ord| {
+                        CollapseKey::TermOrd(term_ord)
+                    })
+            }
+        }
+    }
+
+    pub fn collect(&mut self, doc_id: DocId, hit: H) {
+        let key = self.collapse_key(doc_id);
+        if let Some(allowed_keys) = &self.allowed_keys_opt {
+            if !allowed_keys.contains(&key) {
+                return;
+            }
+        }
+        if let Some((top_hit, num_hits)) = self.groups.get_mut(&key) {
+            *num_hits += 1;
+            if hit < *top_hit {
+                self.ranking.remove(&(top_hit.clone(), key));
+                self.ranking.insert((hit.clone(), key));
+                *top_hit = hit;
+            }
+            return;
+        }
+        if self.ranking.len() >= self.max_groups {
+            match self.ranking.last() {
+                Some((lowest_ranked_hit, _)) if hit < *lowest_ranked_hit => {}
+                _ => return,
+            }
+            if let Some((_, evicted_key)) = self.ranking.pop_last() {
+                self.groups.remove(&evicted_key);
+            }
+        }
+        self.groups.insert(key, (hit.clone(), 1));
+        self.ranking.insert((hit, key));
+    }
+
+    /// Returns the top hit of the top values, sorted, along with their JSON serialized value and
+    /// their number of hits if the hits are counted.
+    pub fn harvest(self) -> tantivy::Result<Vec<(H, String, Option<u64>)>> {
+        let mut term_buffer = Vec::new();
+        let mut collapsed_hits = Vec::with_capacity(self.ranking.len());
+        for (hit, key) in self.ranking {
+            let value = match (key, &self.column_opt) {
+                (CollapseKey::Numeric(bits), _) => format_numeric_value(f64::from_bits(bits)),
+                (CollapseKey::TermOrd(term_ord), Some(AggregationColumn::Text(column))) => {
+                    JsonValue::String(column.term(term_ord, &mut term_buffer)?).to_string()
+                }
+                _ => JsonValue::Null.to_string(),
+            };
+            let num_hits_opt = if self.count_hits {
+                self.groups.get(&key).map(|(_, num_hits)| *num_hits)
+            } else {
+                None
+            };
+            collapsed_hits.push((hit, value, num_hits_opt));
+        }
+        Ok(collapsed_hits)
+    }
+}
+
+/// Resolves a JSON serialized collapse value to the key of the value in a segment, or returns
+/// `None` if no document of the segment has that value.
+fn collapse_key_from_value(
+    column_opt: Option<&AggregationColumn>,
+    value: &str,
+) -> tantivy::Result<Option<CollapseKey>> {
+    let Ok(json_value) = serde_json::from_str::<JsonValue>(value) else {
+        return Ok(None);
+    };
+    let key_opt = match (json_value, column_opt) {
+        (JsonValue::Null, _) => Some(CollapseKey::Missing),
+        (JsonValue::Number(number), Some(AggregationColumn::Numeric(_))) => number
+            .as_f64()
+            .map(|number| CollapseKey::Numeric(number.to_bits())),
+        (JsonValue::String(term), Some(AggregationColumn::Text(column))) => {
+            let (term_ord, is_exact_match) = column.seek_term(&term)?;
+            is_exact_match.then_some(CollapseKey::TermOrd(term_ord))
+        }
+        _ => None,
+    };
+    Ok(key_opt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial_hit(
+        sorting_field_value: u64,
+        collapse_value: Option<&str>,
+        num_collapsed_hits: Option<u64>,
+    ) -> PartialHit {
+        PartialHit {
+            sorting_field_value,
+            split_id: "split".to_string(),
+            segment_ord: 0,
+            doc_id: sorting_field_value as u32,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: collapse_value.map(ToString::to_string),
+            num_collapsed_hits,
+        }
+    }
+
+    #[test]
+    fn test_parse_collapse() {
+        assert!(parse_collapse(None).unwrap().is_none());
+        let collapse = parse_collapse(Some(r#"{"field": "host", "count": true}"#))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            collapse,
+            Collapse {
+                field: "host".to_string(),
+                count: true,
+                values: None,
+            }
+        );
+        parse_collapse(Some(r#"{"field": "host", "size": 3}"#)).unwrap_err();
+    }
+
+    #[test]
+    fn test_collapse_partial_hits() {
+        let partial_hits = vec![
+            partial_hit(9, Some(r#""a""#), Some(1)),
+            partial_hit(8, None, None),
+            partial_hit(7, Some(r#""b""#), Some(2)),
+            partial_hit(6, Some(r#""a""#), Some(3)),
+            partial_hit(5, None, None),
+        ];
+        assert_eq!(
+            collapse_partial_hits(partial_hits),
+            vec![
+                partial_hit(9, Some(r#""a""#), Some(4)),
+                partial_hit(8, None, None),
+                partial_hit(7, Some(r#""b""#), Some(2)),
+                partial_hit(5, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collapse_count_request() {
+        let partial_hits = vec![
+            partial_hit(9, Some(r#""a""#), None),
+            partial_hit(7, Some("42"), None),
+        ];
+        let search_request = SearchRequest {
+            max_hits: 10,
+            start_offset: 5,
+            collapse: Some(r#"{"field": "host"}"#.to_string()),
+            ..Default::default()
+        };
+        assert!(collapse_count_request(&search_request, &partial_hits)
+            .unwrap()
+            .is_none());
+
+        let search_request = SearchRequest {
+            collapse: Some(r#"{"field": "host", "count": true}"#.to_string()),
+            ..search_request
+        };
+        let count_request = collapse_count_request(&search_request, &partial_hits)
+            .unwrap()
+            .unwrap();
+        assert_eq!(count_request.max_hits, 2);
+        assert_eq!(count_request.start_offset, 0);
+        let count_collapse = parse_collapse(count_request.collapse.as_deref())
+            .unwrap()
+            .unwrap();
+        assert!(!count_collapse.count);
+        assert_eq!(
+            count_collapse.values.unwrap(),
+            [r#""a""#.to_string(), "42".to_string()]
+        );
+        assert!(collapse_count_request(&search_request, &[])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_set_num_collapsed_hits() {
+        let mut partial_hits = vec![
+            partial_hit(9, Some(r#""a""#), None),
+            partial_hit(7, Some(r#""b""#), None),
+        ];
+        set_num_collapsed_hits(
+            &mut partial_hits,
+            &[
+                partial_hit(3, Some(r#""b""#), Some(5)),
+                partial_hit(9, Some(r#""a""#), Some(2)),
+            ],
+        );
+        assert_eq!(partial_hits[0].num_collapsed_hits, Some(2));
+        assert_eq!(partial_hits[1].num_collapsed_hits, Some(5));
+    }
+}
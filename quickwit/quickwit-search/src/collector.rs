@@ -22,32 +22,69 @@ use std::collections::{BinaryHeap, HashSet};
 use std::sync::Arc;
 
 use itertools::Itertools;
-use quickwit_doc_mapper::{DocMapper, WarmupInfo};
+use quickwit_doc_mapper::{is_truthy, DocMapper, GeoPoint, RuntimeExpr, RuntimeFields, WarmupInfo};
 use quickwit_proto::{LeafSearchResponse, PartialHit, SearchRequest, SortOrder};
 use serde::Deserialize;
-use tantivy::aggregation::agg_req::{
-    get_fast_field_names, get_term_dict_field_names, Aggregations,
-};
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
-use tantivy::aggregation::AggregationSegmentCollector;
 use tantivy::collector::{Collector, SegmentCollector};
-use tantivy::fastfield::Column;
-use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
+use tantivy::fastfield::{Column, MultiValuedFastFieldReader};
+use tantivy::schema::{Cardinality, Schema};
+use tantivy::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader};
 
-use crate::filters::{create_timestamp_filter_builder, TimestampFilter, TimestampFilterBuilder};
+use crate::aggregations::{
+    fast_field_cardinality, IntermediateSearchAggregationResults,
+    SearchAggregationSegmentCollector, SearchAggregations,
+};
+use crate::collapse::{collapse_partial_hits, parse_collapse, Collapse, SegmentCollapser};
+use crate::filters::{
+    create_geo_point_filter_builder, create_timestamp_filter_builder, GeoPointFilter,
+    GeoPointFilterBuilder, RuntimeExprEvaluator, TimestampFilter, TimestampFilterBuilder,
+};
 use crate::find_trace_ids_collector::{FindTraceIdsCollector, FindTraceIdsSegmentCollector};
-use crate::partial_hit_sorting_key;
+use crate::sort::{parse_sort_fields, sort_field_to_sort_by, SortMode};
+use crate::{partial_hit_sorting_key, SearchError};
 
 #[derive(Clone, Debug)]
 pub(crate) enum SortBy {
     DocId,
+    /// Sorts by the value of a single-valued fast field, or by the minimum or maximum value of
+    /// a multi-valued fast field.
     FastField {
         field_name: String,
         order: SortOrder,
+        mode: SortMode,
+        missing_first: bool,
+    },
+    RuntimeField {
+        expr: RuntimeExpr,
+        order: SortOrder,
+        missing_first: bool,
     },
     Score {
         order: SortOrder,
     },
+    /// Sorts by the distance between the origin and the closest geo point of the document.
+    GeoDistance {
+        field_name: String,
+        origin: GeoPoint,
+        order: SortOrder,
+        missing_first: bool,
+    },
+}
+
+impl SortBy {
+    fn fast_field_names(&self) -> HashSet<String> {
+        match self {
+            SortBy::DocId | SortBy::Score { .. } => HashSet::new(),
+            SortBy::FastField { field_name, .. } | SortBy::GeoDistance { field_name, .. } => {
+                HashSet::from([field_name.clone()])
+            }
+            SortBy::RuntimeField { expr, .. } => expr.field_names().into_iter().collect(),
+        }
+    }
+
+    fn requires_scoring(&self) -> bool {
+        matches!(self, SortBy::Score { .. })
+    }
 }
 
 /// The `SortingFieldComputer` can be seen as the specialization of `SortBy` applied to a specific
@@ -55,18 +92,50 @@ pub(crate) enum SortBy {
 enum SortingFieldComputer {
     /// If undefined, we simply sort by DocIds.
     DocId,
+    /// All the documents of the segment share the same sorting field value, typically because
+    /// they are all missing the sort field.
+    Constant(u64),
     FastField {
         fast_field_reader: Arc<dyn Column<u64>>,
         order: SortOrder,
     },
+    MultiValuedFastField {
+        fast_field_reader: MultiValuedFastFieldReader<u64>,
+        values_buffer: Vec<u64>,
+        mode: SortMode,
+        order: SortOrder,
+        missing_first: bool,
+    },
+    RuntimeField {
+        evaluator: RuntimeExprEvaluator,
+        order: SortOrder,
+        missing_first: bool,
+    },
     Score {
         order: SortOrder,
     },
+    GeoDistance {
+        geo_point_column: MultiValuedFastFieldReader<u64>,
+        geo_points_buffer: Vec<u64>,
+        origin: GeoPoint,
+        order: SortOrder,
+        missing_first: bool,
+    },
+}
+
+/// Returns the sorting field value of the documents missing the sort field: they are ranked
+/// before or after all the other documents regardless of the sort order.
+fn missing_sorting_field_value(missing_first: bool) -> u64 {
+    if missing_first {
+        u64::MAX
+    } else {
+        0u64
+    }
 }
 
 impl SortingFieldComputer {
     /// Returns the ranking key for the given element
-    fn compute_sorting_field(&self, doc_id: DocId, score: Score) -> u64 {
+    fn compute_sorting_field(&mut self, doc_id: DocId, score: Score) -> u64 {
         match self {
             SortingFieldComputer::FastField {
                 fast_field_reader,
@@ -81,7 +150,66 @@ impl SortingFieldComputer {
                     SortOrder::Asc => u64::MAX - field_val,
                 }
             }
+            SortingFieldComputer::MultiValuedFastField {
+                fast_field_reader,
+                values_buffer,
+                mode,
+                order,
+                missing_first,
+            } => {
+                fast_field_reader.get_vals(doc_id, values_buffer);
+                let field_val_opt = match mode {
+                    SortMode::Min => values_buffer.iter().min(),
+                    SortMode::Max => values_buffer.iter().max(),
+                };
+                let Some(&field_val) = field_val_opt else {
+                    return missing_sorting_field_value(*missing_first);
+                };
+                match order {
+                    SortOrder::Desc => field_val,
+                    SortOrder::Asc => u64::MAX - field_val,
+                }
+            }
+            SortingFieldComputer::RuntimeField {
+                evaluator,
+                order,
+                missing_first,
+            } => {
+                let value = evaluator.eval(doc_id);
+                if value.is_nan() {
+                    return missing_sorting_field_value(*missing_first);
+                }
+                let u64_value = f64_to_u64(value);
+                match order {
+                    SortOrder::Desc => u64_value,
+                    SortOrder::Asc => u64::MAX - u64_value,
+                }
+            }
+            SortingFieldComputer::GeoDistance {
+                geo_point_column,
+                geo_points_buffer,
+                origin,
+                order,
+                missing_first,
+            } => {
+                geo_point_column.get_vals(doc_id, geo_points_buffer);
+                let Some(distance) = geo_points_buffer
+                    .iter()
+                    .map(|&encoded_geo_point| {
+                        origin.distance_meters(&GeoPoint::from_u64(encoded_geo_point))
+                    })
+                    .min_by(f64::total_cmp)
+                else {
+                    return missing_sorting_field_value(*missing_first);
+                };
+                let u64_distance = f64_to_u64(distance);
+                match order {
+                    SortOrder::Desc => u64_distance,
+                    SortOrder::Asc => u64::MAX - u64_distance,
+                }
+            }
             SortingFieldComputer::DocId => 0u64,
+            SortingFieldComputer::Constant(value) => *value,
             SortingFieldComputer::Score { order } => {
                 let u64_score = f32_to_u64(score);
                 match order {
@@ -95,13 +223,36 @@ impl SortingFieldComputer {
 
 /// Converts a float to an unsigned integer while preserving order.
 /// See `<https://lemire.me/blog/2020/12/14/converting-floating-point-numbers-to-integers-while-preserving-order/>`
-fn f32_to_u64(value: f32) -> u64 {
+pub(crate) fn f32_to_u64(value: f32) -> u64 {
     let value_u32 = u32::from_le_bytes(value.to_le_bytes());
     let mut mask = (value_u32 as i32 >> 31) as u32;
     mask |= 0x80000000;
     (value_u32 ^ mask) as u64
 }
 
+/// Converts back an unsigned integer obtained with [`f32_to_u64`] to a float.
+pub(crate) fn u64_to_f32(value: u64) -> f32 {
+    let value_u32 = value as u32;
+    let mask = if value_u32 & 0x80000000 != 0 {
+        0x80000000
+    } else {
+        u32::MAX
+    };
+    f32::from_le_bytes((value_u32 ^ mask).to_le_bytes())
+}
+
+/// Converts a double to an unsigned integer while preserving order, `NaN` being the lowest
+/// value.
+fn f64_to_u64(value: f64) -> u64 {
+    if value.is_nan() {
+        return 0u64;
+    }
+    let value_u64 = u64::from_le_bytes(value.to_le_bytes());
+    let mut mask = (value_u64 as i64 >> 63) as u64;
+    mask |= 0x8000000000000000;
+    value_u64 ^ mask
+}
+
 /// Takes a user-defined sorting criteria and resolves it to a
 /// segment specific `SortFieldComputer`.
 fn resolve_sort_by(
@@ -110,22 +261,77 @@ fn resolve_sort_by(
 ) -> tantivy::Result<SortingFieldComputer> {
     match sort_by {
         SortBy::DocId => Ok(SortingFieldComputer::DocId),
-        SortBy::FastField { field_name, order } => {
+        SortBy::FastField {
+            field_name,
+            order,
+            mode,
+            missing_first,
+        } => {
+            // Splits indexed with an older doc mapping may not have the field, in which case
+            // their documents are missing the sort field.
+            let schema = segment_reader.schema();
+            let Ok(field) = schema.get_field(field_name) else {
+                let missing_value = missing_sorting_field_value(*missing_first);
+                return Ok(SortingFieldComputer::Constant(missing_value));
+            };
+            let field_type = schema.get_field_entry(field).field_type();
+
+            if fast_field_cardinality(field_type) == Some(Cardinality::MultiValues) {
+                // The values are read in their order-preserving `u64` representation.
+                let fast_field_reader = segment_reader.fast_fields().u64s_lenient(field_name)?;
+                return Ok(SortingFieldComputer::MultiValuedFastField {
+                    fast_field_reader,
+                    values_buffer: Vec::new(),
+                    mode: *mode,
+                    order: *order,
+                    missing_first: *missing_first,
+                });
+            }
             let fast_field_reader = segment_reader.fast_fields().u64_lenient(field_name)?;
             Ok(SortingFieldComputer::FastField {
                 fast_field_reader,
                 order: *order,
             })
         }
+        SortBy::RuntimeField {
+            expr,
+            order,
+            missing_first,
+        } => Ok(SortingFieldComputer::RuntimeField {
+            evaluator: RuntimeExprEvaluator::new(expr, segment_reader)?,
+            order: *order,
+            missing_first: *missing_first,
+        }),
         SortBy::Score { order } => Ok(SortingFieldComputer::Score { order: *order }),
+        SortBy::GeoDistance {
+            field_name,
+            origin,
+            order,
+            missing_first,
+        } => {
+            if segment_reader.schema().get_field(field_name).is_err() {
+                let missing_value = missing_sorting_field_value(*missing_first);
+                return Ok(SortingFieldComputer::Constant(missing_value));
+            }
+            let geo_point_column = segment_reader.fast_fields().u64s(field_name)?;
+            Ok(SortingFieldComputer::GeoDistance {
+                geo_point_column,
+                geo_points_buffer: Vec::new(),
+                origin: *origin,
+                order: *order,
+                missing_first: *missing_first,
+            })
+        }
     }
 }
 
 /// PartialHitHeapItem order is the inverse of the natural order
 /// so that we actually have a min-heap.
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 struct PartialHitHeapItem {
     sorting_field_value: u64,
+    /// Values of the secondary sort fields, breaking the ties on `sorting_field_value`.
+    secondary_sorting_field_values: Vec<u64>,
     doc_id: DocId,
 }
 
@@ -138,10 +344,14 @@ impl PartialOrd for PartialHitHeapItem {
 impl Ord for PartialHitHeapItem {
     #[inline]
     fn cmp(&self, other: &Self) -> Ordering {
-        let by_sorting_field = other
-            .sorting_field_value
-            .partial_cmp(&self.sorting_field_value)
-            .unwrap_or(Ordering::Equal);
+        let by_sorting_field = (
+            other.sorting_field_value,
+            &other.secondary_sorting_field_values,
+        )
+            .cmp(&(
+                self.sorting_field_value,
+                &self.secondary_sorting_field_values,
+            ));
 
         let lazy_order_by_doc_id = || {
             self.doc_id
@@ -162,9 +372,57 @@ impl PartialEq for PartialHitHeapItem {
 
 impl Eq for PartialHitHeapItem {}
 
+/// The `SearchAfterFilter` is the specialization of the `search_after` partial hit applied to a
+/// specific segment. It only accepts the documents ranked strictly after that partial hit.
+#[derive(Clone)]
+struct SearchAfterFilter {
+    sorting_field_value: u64,
+    secondary_sorting_field_values: Vec<u64>,
+    /// Documents tied with the `search_after` partial hit on the sorting field are only
+    /// accepted if their `DocId` is greater than or equal to this bound.
+    min_tied_doc_id: u64,
+}
+
+impl SearchAfterFilter {
+    fn new(search_after: &PartialHit, split_id: &str, segment_ord: SegmentOrdinal) -> Self {
+        // Ties are broken by the increasing order of the split ID, the segment ordinal, and the
+        // doc ID, so the segment address alone tells whether some tied documents are accepted.
+        let min_tied_doc_id = match (split_id, segment_ord)
+            .cmp(&(search_after.split_id.as_str(), search_after.segment_ord))
+        {
+            Ordering::Less => u64::MAX,
+            Ordering::Equal => search_after.doc_id as u64 + 1,
+            Ordering::Greater => 0,
+        };
+        SearchAfterFilter {
+            sorting_field_value: search_after.sorting_field_value,
+            secondary_sorting_field_values: search_after.secondary_sorting_field_values.clone(),
+            min_tied_doc_id,
+        }
+    }
+
+    fn is_after(
+        &self,
+        sorting_field_value: u64,
+        secondary_sorting_field_values: &[u64],
+        doc_id: DocId,
+    ) -> bool {
+        let sorting_key = (sorting_field_value, secondary_sorting_field_values);
+        let search_after_key = (
+            self.sorting_field_value,
+            self.secondary_sorting_field_values.as_slice(),
+        );
+        match sorting_key.cmp(&search_after_key) {
+            Ordering::Less => true,
+            Ordering::Equal => doc_id as u64 >= self.min_tied_doc_id,
+            Ordering::Greater => false,
+        }
+    }
+}
+
 enum AggregationSegmentCollectors {
     FindTraceIdsSegmentCollector(FindTraceIdsSegmentCollector),
-    TantivyAggregationSegmentCollector(AggregationSegmentCollector),
+    TantivyAggregationSegmentCollector(SearchAggregationSegmentCollector),
 }
 
 /// Quickwit collector working at the scale of the segment.
@@ -172,10 +430,18 @@ pub struct QuickwitSegmentCollector {
     num_hits: u64,
     split_id: String,
     sort_by: SortingFieldComputer,
+    secondary_sort_by: Vec<SortingFieldComputer>,
+    search_after_opt: Option<SearchAfterFilter>,
     hits: BinaryHeap<PartialHitHeapItem>,
+    /// If the hits are collapsed, the top hits of each value of the collapse field are kept
+    /// there instead of `hits`.
+    collapser_opt: Option<SegmentCollapser<PartialHitHeapItem>>,
     max_hits: usize,
     segment_ord: u32,
     timestamp_filter_opt: Option<TimestampFilter>,
+    geo_point_filter_opt: Option<GeoPointFilter>,
+    nested_matches_opt: Option<HashSet<DocId>>,
+    runtime_filter_opt: Option<RuntimeExprEvaluator>,
     aggregation: Option<AggregationSegmentCollectors>,
 }
 
@@ -186,30 +452,59 @@ impl QuickwitSegmentCollector {
 
     fn collect_top_k(&mut self, doc_id: DocId, score: Score) {
         let sorting_field_value: u64 = self.sort_by.compute_sorting_field(doc_id, score);
-        if self.at_capacity() {
-            if let Some(limit_sorting_field) = self.hits.peek().map(|head| head.sorting_field_value)
+        let secondary_sorting_field_values: Vec<u64> = self
+            .secondary_sort_by
+            .iter_mut()
+            .map(|sort_by| sort_by.compute_sorting_field(doc_id, score))
+            .collect();
+        if let Some(search_after) = &self.search_after_opt {
+            if !search_after.is_after(sorting_field_value, &secondary_sorting_field_values, doc_id)
             {
-                // In case of a tie, we keep the document with a lower `DocId`.
-                if limit_sorting_field < sorting_field_value {
-                    if let Some(mut head) = self.hits.peek_mut() {
-                        head.sorting_field_value = sorting_field_value;
-                        head.doc_id = doc_id;
-                    }
+                return;
+            }
+        }
+        let hit = PartialHitHeapItem {
+            sorting_field_value,
+            secondary_sorting_field_values,
+            doc_id,
+        };
+        if let Some(collapser) = self.collapser_opt.as_mut() {
+            collapser.collect(doc_id, hit);
+            return;
+        }
+        if self.at_capacity() {
+            if let Some(mut head) = self.hits.peek_mut() {
+                // The head is the lowest ranked hit. In case of a tie, we keep the document with
+                // a lower `DocId`.
+                if hit < *head {
+                    *head = hit;
                 }
             }
         } else {
             // we have not reached capacity yet, so we can just push the
             // element.
-            self.hits.push(PartialHitHeapItem {
-                sorting_field_value,
-                doc_id,
-            });
+            self.hits.push(hit);
         }
     }
 
-    fn accept_document(&self, doc_id: DocId) -> bool {
+    fn accept_document(&mut self, doc_id: DocId) -> bool {
         if let Some(ref timestamp_filter) = self.timestamp_filter_opt {
-            return timestamp_filter.is_within_range(doc_id);
+            if !timestamp_filter.is_within_range(doc_id) {
+                return false;
+            }
+        }
+        if let Some(ref nested_matches) = self.nested_matches_opt {
+            if !nested_matches.contains(&doc_id) {
+                return false;
+            }
+        }
+        if let Some(ref runtime_filter) = self.runtime_filter_opt {
+            if !is_truthy(runtime_filter.eval(doc_id)) {
+                return false;
+            }
+        }
+        if let Some(ref mut geo_point_filter) = self.geo_point_filter_opt {
+            return geo_point_filter.is_within_shape(doc_id);
         }
         true
     }
@@ -241,17 +536,35 @@ impl SegmentCollector for QuickwitSegmentCollector {
         let segment_ord = self.segment_ord;
         // TODO use into_iter_sorted() once it gets stable.
         let split_id = self.split_id;
-        let partial_hits: Vec<PartialHit> = self
-            .hits
-            .into_sorted_vec()
-            .into_iter()
-            .map(|hit| PartialHit {
-                sorting_field_value: hit.sorting_field_value,
-                segment_ord,
-                doc_id: hit.doc_id,
-                split_id: split_id.clone(),
-            })
-            .collect();
+        let partial_hits: Vec<PartialHit> = match self.collapser_opt {
+            Some(collapser) => collapser
+                .harvest()?
+                .into_iter()
+                .map(|(hit, collapse_value, num_collapsed_hits)| PartialHit {
+                    sorting_field_value: hit.sorting_field_value,
+                    segment_ord,
+                    doc_id: hit.doc_id,
+                    split_id: split_id.clone(),
+                    secondary_sorting_field_values: hit.secondary_sorting_field_values,
+                    collapse_value: Some(collapse_value),
+                    num_collapsed_hits,
+                })
+                .collect(),
+            None => self
+                .hits
+                .into_sorted_vec()
+                .into_iter()
+                .map(|hit| PartialHit {
+                    sorting_field_value: hit.sorting_field_value,
+                    segment_ord,
+                    doc_id: hit.doc_id,
+                    split_id: split_id.clone(),
+                    secondary_sorting_field_values: hit.secondary_sorting_field_values,
+                    collapse_value: None,
+                    num_collapsed_hits: None,
+                })
+                .collect(),
+        };
 
         let intermediate_aggregation_result = match self.aggregation {
             Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(collector)) => Some(
@@ -283,18 +596,32 @@ pub enum QuickwitAggregations {
     /// Aggregation used by the Jaeger service to find trace IDs that match a
     /// [`quickwit_proto::jaeger::storage::v1::FindTraceIDsRequest`].
     FindTraceIdsAggregation(FindTraceIdsCollector),
-    /// Your classic Tantivy aggregation.
-    TantivyAggregations(Aggregations),
+    /// Your classic Tantivy aggregation, along with the metric aggregations computed by
+    /// Quickwit.
+    TantivyAggregations(SearchAggregations),
 }
 
 impl QuickwitAggregations {
-    fn fast_field_names(&self) -> HashSet<String> {
+    /// Parses an aggregation request against the index `schema`, so that the aggregations over
+    /// the multi-valued fields that tantivy cannot compute are computed by Quickwit.
+    pub(crate) fn parse(aggregation_request: &str, schema: &Schema) -> serde_json::Result<Self> {
+        match serde_json::from_str(aggregation_request)? {
+            QuickwitAggregations::TantivyAggregations(_) => {
+                let aggregations_json = serde_json::from_str(aggregation_request)?;
+                let aggregations = SearchAggregations::from_json(aggregations_json, Some(schema))?;
+                Ok(QuickwitAggregations::TantivyAggregations(aggregations))
+            }
+            aggregations => Ok(aggregations),
+        }
+    }
+
+    pub(crate) fn fast_field_names(&self) -> HashSet<String> {
         match self {
             QuickwitAggregations::FindTraceIdsAggregation(collector) => {
                 collector.fast_field_names()
             }
             QuickwitAggregations::TantivyAggregations(aggregations) => {
-                get_fast_field_names(aggregations)
+                aggregations.fast_field_names()
             }
         }
     }
@@ -305,7 +632,7 @@ impl QuickwitAggregations {
                 collector.term_dict_field_names()
             }
             QuickwitAggregations::TantivyAggregations(aggregations) => {
-                get_term_dict_field_names(aggregations)
+                aggregations.term_dict_field_names()
             }
         }
     }
@@ -321,18 +648,32 @@ pub(crate) struct QuickwitCollector {
     pub start_offset: usize,
     pub max_hits: usize,
     pub sort_by: SortBy,
+    /// Sort criteria breaking the ties of `sort_by`, in order.
+    pub secondary_sort_by: Vec<SortBy>,
+    /// Only the hits ranked strictly after this partial hit are collected.
+    search_after_opt: Option<PartialHit>,
     timestamp_filter_builder_opt: Option<TimestampFilterBuilder>,
+    geo_point_filter_builder_opt: Option<GeoPointFilterBuilder>,
+    /// Documents matching the nested query, if any. See [`crate::nested`].
+    nested_matches_opt: Option<Arc<HashSet<DocAddress>>>,
+    runtime_filter_opt: Option<RuntimeExpr>,
+    collapse_opt: Option<Collapse>,
     pub aggregation: Option<QuickwitAggregations>,
 }
 
 impl QuickwitCollector {
+    /// Restricts the collected documents to the documents matching the nested query.
+    pub fn set_nested_matches(&mut self, nested_matches: HashSet<DocAddress>) {
+        self.nested_matches_opt = Some(Arc::new(nested_matches));
+    }
+
     pub fn fast_field_names(&self) -> HashSet<String> {
-        let mut fast_field_names = HashSet::default();
-        match &self.sort_by {
-            SortBy::DocId | SortBy::Score { .. } => {}
-            SortBy::FastField { field_name, .. } => {
-                fast_field_names.insert(field_name.clone());
-            }
+        let mut fast_field_names = self.sort_by.fast_field_names();
+        for sort_by in &self.secondary_sort_by {
+            fast_field_names.extend(sort_by.fast_field_names());
+        }
+        if let Some(runtime_filter) = &self.runtime_filter_opt {
+            fast_field_names.extend(runtime_filter.field_names());
         }
         if let Some(aggregations) = &self.aggregation {
             fast_field_names.extend(aggregations.fast_field_names());
@@ -340,6 +681,12 @@ impl QuickwitCollector {
         if let Some(timestamp_filter_builder) = &self.timestamp_filter_builder_opt {
             fast_field_names.insert(timestamp_filter_builder.timestamp_field_name.clone());
         }
+        if let Some(geo_point_filter_builder) = &self.geo_point_filter_builder_opt {
+            fast_field_names.insert(geo_point_filter_builder.geo_point_field_name.clone());
+        }
+        if let Some(collapse) = &self.collapse_opt {
+            fast_field_names.insert(collapse.field.clone());
+        }
         fast_field_names
     }
 
@@ -348,6 +695,10 @@ impl QuickwitCollector {
         if let Some(aggregations) = &self.aggregation {
             term_dict_field_names.extend(aggregations.term_dict_field_names());
         }
+        // The values of text collapse fields are read from the term dictionary.
+        if let Some(collapse) = &self.collapse_opt {
+            term_dict_field_names.insert(collapse.field.clone());
+        }
         term_dict_field_names
     }
 
@@ -361,7 +712,7 @@ impl QuickwitCollector {
     }
 }
 
-const AGGREGATION_BUCKET_LIMIT: u32 = 1_000_000;
+pub(crate) const AGGREGATION_BUCKET_LIMIT: u32 = 1_000_000;
 
 impl Collector for QuickwitCollector {
     type Child = QuickwitSegmentCollector;
@@ -373,6 +724,15 @@ impl Collector for QuickwitCollector {
         segment_reader: &SegmentReader,
     ) -> tantivy::Result<Self::Child> {
         let sort_by = resolve_sort_by(&self.sort_by, segment_reader)?;
+        let secondary_sort_by = self
+            .secondary_sort_by
+            .iter()
+            .map(|sort_by| resolve_sort_by(sort_by, segment_reader))
+            .collect::<tantivy::Result<_>>()?;
+        let search_after_opt = self
+            .search_after_opt
+            .as_ref()
+            .map(|search_after| SearchAfterFilter::new(search_after, &self.split_id, segment_ord));
         // Regardless of the start_offset, we need to collect top-K
         // starting from 0 for every leaves.
         let leaf_max_hits = self.max_hits + self.start_offset;
@@ -381,6 +741,28 @@ impl Collector for QuickwitCollector {
             Some(timestamp_filter_builder) => timestamp_filter_builder.build(segment_reader)?,
             None => None,
         };
+        let geo_point_filter_opt = self
+            .geo_point_filter_builder_opt
+            .as_ref()
+            .map(|geo_point_filter_builder| geo_point_filter_builder.build(segment_reader))
+            .transpose()?;
+        let nested_matches_opt = self.nested_matches_opt.as_ref().map(|nested_matches| {
+            nested_matches
+                .iter()
+                .filter(|doc_addr| doc_addr.segment_ord == segment_ord)
+                .map(|doc_addr| doc_addr.doc_id)
+                .collect()
+        });
+        let runtime_filter_opt = self
+            .runtime_filter_opt
+            .as_ref()
+            .map(|runtime_filter| RuntimeExprEvaluator::new(runtime_filter, segment_reader))
+            .transpose()?;
+        let collapser_opt = self
+            .collapse_opt
+            .as_ref()
+            .map(|collapse| SegmentCollapser::new(collapse, segment_reader, leaf_max_hits))
+            .transpose()?;
         let aggregation = match &self.aggregation {
             Some(QuickwitAggregations::FindTraceIdsAggregation(collector)) => {
                 Some(AggregationSegmentCollectors::FindTraceIdsSegmentCollector(
@@ -389,7 +771,7 @@ impl Collector for QuickwitCollector {
             }
             Some(QuickwitAggregations::TantivyAggregations(aggs)) => Some(
                 AggregationSegmentCollectors::TantivyAggregationSegmentCollector(
-                    AggregationSegmentCollector::from_agg_req_and_reader(
+                    SearchAggregationSegmentCollector::from_aggregations_and_reader(
                         aggs,
                         segment_reader,
                         AGGREGATION_BUCKET_LIMIT,
@@ -402,10 +784,16 @@ impl Collector for QuickwitCollector {
             num_hits: 0u64,
             split_id: self.split_id.clone(),
             sort_by,
+            secondary_sort_by,
+            search_after_opt,
             hits: BinaryHeap::with_capacity(leaf_max_hits),
+            collapser_opt,
             segment_ord,
             max_hits: leaf_max_hits,
             timestamp_filter_opt,
+            geo_point_filter_opt,
+            nested_matches_opt,
+            runtime_filter_opt,
             aggregation,
         })
     }
@@ -414,10 +802,8 @@ impl Collector for QuickwitCollector {
         // We do not need BM25 scoring in Quickwit if it is not opted-in.
         // By returning false, we inform tantivy that it does not need to decompress
         // term frequencies.
-        match self.sort_by {
-            SortBy::DocId | SortBy::FastField { .. } => false,
-            SortBy::Score { .. } => true,
-        }
+        self.sort_by.requires_scoring()
+            || self.secondary_sort_by.iter().any(SortBy::requires_scoring)
     }
 
     fn merge_fruits(
@@ -473,7 +859,7 @@ fn merge_leaf_responses(
             Some(serde_json::to_string(&merged_fruit)?)
         }
         Some(QuickwitAggregations::TantivyAggregations(_)) => {
-            let fruits: Vec<IntermediateAggregationResults> = leaf_responses
+            let fruits: Vec<IntermediateSearchAggregationResults> = leaf_responses
                 .iter()
                 .filter_map(|leaf_response| {
                     leaf_response.intermediate_aggregation_result.as_ref().map(
@@ -533,6 +919,12 @@ fn top_k_partial_hits(mut partial_hits: Vec<PartialHit>, num_hits: usize) -> Vec
         let right_key = partial_hit_sorting_key(right);
         left_key.cmp(&right_key)
     });
+    if partial_hits
+        .iter()
+        .any(|partial_hit| partial_hit.collapse_value.is_some())
+    {
+        partial_hits = collapse_partial_hits(partial_hits);
+    }
     partial_hits.truncate(num_hits);
     partial_hits
 }
@@ -544,7 +936,10 @@ pub(crate) fn make_collector_for_split(
     search_request: &SearchRequest,
 ) -> crate::Result<QuickwitCollector> {
     let aggregation = match &search_request.aggregation_request {
-        Some(aggregation) => Some(serde_json::from_str(aggregation)?),
+        Some(aggregation) => Some(QuickwitAggregations::parse(
+            aggregation,
+            &doc_mapper.schema(),
+        )?),
         None => None,
     };
     let timestamp_filter_builder_opt = create_timestamp_filter_builder(
@@ -552,31 +947,66 @@ pub(crate) fn make_collector_for_split(
         search_request.start_timestamp,
         search_request.end_timestamp,
     );
+    let geo_point_filter_builder_opt =
+        create_geo_point_filter_builder(search_request.geo_filter.as_deref())?;
+    let collapse_opt = parse_collapse(search_request.collapse.as_deref())?;
+    let runtime_fields = RuntimeFields::parse(search_request.runtime_fields.as_deref())
+        .map_err(|error| SearchError::InvalidArgument(error.to_string()))?;
+    let runtime_filter_opt = search_request
+        .runtime_filter
+        .as_deref()
+        .map(|runtime_filter| runtime_fields.parse_filter(runtime_filter))
+        .transpose()
+        .map_err(|error| SearchError::InvalidArgument(error.to_string()))?;
     let sort_order = search_request
         .sort_order
         .and_then(SortOrder::from_i32)
         .unwrap_or(SortOrder::Desc);
-    let sort_by = search_request
+    let mut sort_by = search_request
         .sort_by_field
         .as_ref()
         .map(|field_name| {
             if field_name == "_score" {
                 SortBy::Score { order: sort_order }
+            } else if let Some(expr) = runtime_fields.get(field_name) {
+                SortBy::RuntimeField {
+                    expr: expr.clone(),
+                    order: sort_order,
+                    missing_first: false,
+                }
             } else {
                 SortBy::FastField {
                     field_name: field_name.clone(),
                     order: sort_order,
+                    mode: SortMode::default_for_order(sort_order),
+                    missing_first: false,
                 }
             }
         })
         .unwrap_or(SortBy::DocId);
+    let mut secondary_sort_by = Vec::new();
+    let sort_fields = parse_sort_fields(search_request.sort_fields.as_deref())?;
+    for (sort_field_ord, sort_field) in sort_fields.iter().enumerate() {
+        let sort_field_by = sort_field_to_sort_by(sort_field, &runtime_fields)?;
+        if sort_field_ord == 0 {
+            sort_by = sort_field_by;
+        } else {
+            secondary_sort_by.push(sort_field_by);
+        }
+    }
 
     Ok(QuickwitCollector {
         split_id,
         start_offset: search_request.start_offset as usize,
         max_hits: search_request.max_hits as usize,
         sort_by,
+        secondary_sort_by,
+        search_after_opt: search_request.search_after.clone(),
         timestamp_filter_builder_opt,
+        geo_point_filter_builder_opt,
+        nested_matches_opt: None,
+        runtime_filter_opt,
+        collapse_opt,
         aggregation,
     })
 }
@@ -597,7 +1027,13 @@ pub(crate) fn make_merge_collector(
         start_offset: search_request.start_offset as usize,
         max_hits: search_request.max_hits as usize,
         sort_by: SortBy::DocId,
+        secondary_sort_by: Vec::new(),
+        search_after_opt: None,
         timestamp_filter_builder_opt: None,
+        geo_point_filter_builder_opt: None,
+        nested_matches_opt: None,
+        runtime_filter_opt: None,
+        collapse_opt: None,
         aggregation,
     })
 }
@@ -609,22 +1045,62 @@ mod tests {
     use proptest::prelude::*;
     use quickwit_proto::PartialHit;
 
-    use super::PartialHitHeapItem;
-    use crate::collector::{f32_to_u64, top_k_partial_hits};
+    use super::{missing_sorting_field_value, PartialHitHeapItem, SearchAfterFilter};
+    use crate::collector::{f32_to_u64, top_k_partial_hits, u64_to_f32};
 
     #[test]
     fn test_partial_hit_ordered_by_sorting_field() {
         let lesser_score = PartialHitHeapItem {
             sorting_field_value: 1u64,
+            secondary_sorting_field_values: Vec::new(),
             doc_id: 1u32,
         };
         let higher_score = PartialHitHeapItem {
             sorting_field_value: 2u64,
+            secondary_sorting_field_values: Vec::new(),
             doc_id: 1u32,
         };
         assert_eq!(lesser_score.cmp(&higher_score), Ordering::Greater);
     }
 
+    #[test]
+    fn test_partial_hit_ties_broken_by_secondary_sorting_fields() {
+        let lesser_secondary = PartialHitHeapItem {
+            sorting_field_value: 2u64,
+            secondary_sorting_field_values: vec![1u64],
+            doc_id: 0u32,
+        };
+        let higher_secondary = PartialHitHeapItem {
+            sorting_field_value: 2u64,
+            secondary_sorting_field_values: vec![3u64],
+            doc_id: 1u32,
+        };
+        assert_eq!(lesser_secondary.cmp(&higher_secondary), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_merge_partial_hits_with_secondary_sorting_fields() {
+        let make_doc = |sorting_field_value: u64, secondary_value: u64| PartialHit {
+            sorting_field_value,
+            split_id: "split1".to_string(),
+            segment_ord: 0u32,
+            doc_id: 0u32,
+            secondary_sorting_field_values: vec![secondary_value],
+            collapse_value: None,
+            num_collapsed_hits: None,
+        };
+        assert_eq!(
+            top_k_partial_hits(vec![make_doc(1, 5), make_doc(2, 1), make_doc(2, 4)], 2),
+            vec![make_doc(2, 4), make_doc(2, 1)]
+        );
+    }
+
+    #[test]
+    fn test_missing_sorting_field_value() {
+        assert_eq!(missing_sorting_field_value(true), u64::MAX);
+        assert_eq!(missing_sorting_field_value(false), 0);
+    }
+
     #[test]
     fn test_merge_partial_hits_no_tie() {
         let make_doc = |sorting_field_value: u64| PartialHit {
@@ -632,6 +1108,9 @@ mod tests {
             split_id: "split1".to_string(),
             segment_ord: 0u32,
             doc_id: 0u32,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
         };
         assert_eq!(
             top_k_partial_hits(vec![make_doc(1u64), make_doc(3u64), make_doc(2u64),], 2),
@@ -646,6 +1125,9 @@ mod tests {
             split_id: format!("split_{split_id}"),
             segment_ord: 0u32,
             doc_id: 0u32,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
         };
         assert_eq!(
             top_k_partial_hits(
@@ -660,6 +1142,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_search_after_filter() {
+        let search_after = PartialHit {
+            sorting_field_value: 10u64,
+            split_id: "split_2".to_string(),
+            segment_ord: 1u32,
+            doc_id: 5u32,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
+        };
+        let same_segment_filter = SearchAfterFilter::new(&search_after, "split_2", 1);
+        assert!(!same_segment_filter.is_after(11, &[], 100));
+        assert!(!same_segment_filter.is_after(10, &[], 4));
+        assert!(!same_segment_filter.is_after(10, &[], 5));
+        assert!(same_segment_filter.is_after(10, &[], 6));
+        assert!(same_segment_filter.is_after(9, &[], 0));
+
+        let previous_segment_filter = SearchAfterFilter::new(&search_after, "split_2", 0);
+        assert!(!previous_segment_filter.is_after(10, &[], 100));
+        assert!(previous_segment_filter.is_after(9, &[], 0));
+
+        let next_split_filter = SearchAfterFilter::new(&search_after, "split_3", 0);
+        assert!(next_split_filter.is_after(10, &[], 0));
+        assert!(!next_split_filter.is_after(11, &[], 0));
+
+        let search_after = PartialHit {
+            secondary_sorting_field_values: vec![7u64],
+            ..search_after
+        };
+        let secondary_filter = SearchAfterFilter::new(&search_after, "split_2", 1);
+        assert!(secondary_filter.is_after(10, &[6], 0));
+        assert!(!secondary_filter.is_after(10, &[8], 100));
+        assert!(!secondary_filter.is_after(10, &[7], 5));
+        assert!(secondary_filter.is_after(10, &[7], 6));
+    }
+
     prop_compose! {
         // Turns out, zero's and negative zero's u64 representation is not same.
         // It is not relevant for our use case. For simplicity we filter the negative
@@ -675,5 +1194,10 @@ mod tests {
         fn test_proptest_f32_to_u64_compare_arbitrary(a in any_f32_without_negative_zero(), b in any_f32_without_negative_zero()) {
             prop_assert_eq!(a < b, f32_to_u64(a) < f32_to_u64(b))
         }
+
+        #[test]
+        fn test_proptest_u64_to_f32_roundtrip(a in any_f32_without_negative_zero().prop_filter("Value can't be NaN", |val| !val.is_nan())) {
+            prop_assert_eq!(u64_to_f32(f32_to_u64(a)), a)
+        }
     }
 }
@@ -0,0 +1,276 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Count-only searches, which return the number of hits without fetching any hit or computing
+//! any aggregation.
+
+use quickwit_metastore::{Metastore, SplitMetadata};
+use quickwit_proto::{SearchRequest, SearchResponse};
+use tracing::{debug, instrument};
+
+use crate::root::{root_leaf_search, RootSearchContext};
+use crate::{list_relevant_splits, ClusterClient, SearchError, SearchJobPlacer};
+
+/// Strips the request of everything but what is needed to count the hits.
+pub(crate) fn count_request(search_request: &SearchRequest) -> SearchRequest {
+    SearchRequest {
+        max_hits: 0,
+        start_offset: 0,
+        aggregation_request: None,
+        snippet_fields: Vec::new(),
+        snippet_max_num_chars: None,
+        snippet_pre_tag: None,
+        snippet_post_tag: None,
+        sort_order: None,
+        sort_by_field: None,
+        sort_fields: None,
+        collapse: None,
+        search_after: None,
+        scroll_ttl_secs: None,
+        count_only: true,
+        ..search_request.clone()
+    }
+}
+
+/// Returns whether the request matches all the documents of the index within its time range, in
+/// which case the number of documents of the splits fully covered by the time range is their
+/// number of hits.
+fn is_match_all_request(search_request: &SearchRequest) -> bool {
+    search_request.query.trim() == "*"
+        && search_request.geo_filter.is_none()
+        && search_request.nested_query.is_none()
+        && search_request.runtime_filter.is_none()
+        && search_request.knn_query.is_none()
+}
+
+/// Returns whether all the documents of the split are within the time range of the request.
+fn is_split_covered_by_time_range(
+    search_request: &SearchRequest,
+    split_metadata: &SplitMetadata,
+) -> bool {
+    if search_request.start_timestamp.is_none() && search_request.end_timestamp.is_none() {
+        return true;
+    }
+    let Some(split_time_range) = &split_metadata.time_range else {
+        return false;
+    };
+    let start_covered = search_request
+        .start_timestamp
+        .map_or(true, |start_timestamp| {
+            start_timestamp <= *split_time_range.start()
+        });
+    let end_covered = search_request.end_timestamp.map_or(true, |end_timestamp| {
+        *split_time_range.end() < end_timestamp
+    });
+    start_covered && end_covered
+}
+
+/// Counts the hits of the request. For match-all requests, the splits fully covered by the time
+/// range of the request are counted from their metadata instead of being searched.
+#[instrument(skip(search_request, metastore, cluster_client, search_job_placer))]
+pub(crate) async fn root_count(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<SearchResponse> {
+    let start_instant = tokio::time::Instant::now();
+    if search_request.hybrid_ranking.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Count-only searches do not support `hybrid`.".to_string(),
+        ));
+    }
+    let search_request = count_request(search_request);
+    let root_search_context = RootSearchContext::new(&search_request, metastore).await?;
+    let split_metadatas =
+        list_relevant_splits(&root_search_context.search_request, metastore).await?;
+
+    let (covered_splits, searched_splits): (Vec<SplitMetadata>, Vec<SplitMetadata>) =
+        if is_match_all_request(&root_search_context.search_request) {
+            split_metadatas.into_iter().partition(|split_metadata| {
+                is_split_covered_by_time_range(&root_search_context.search_request, split_metadata)
+            })
+        } else {
+            (Vec::new(), split_metadatas)
+        };
+    let num_covered_docs: u64 = covered_splits
+        .iter()
+        .map(|split_metadata| split_metadata.num_docs as u64)
+        .sum();
+    debug!(
+        num_covered_splits = covered_splits.len(),
+        num_searched_splits = searched_splits.len(),
+        "Counting hits."
+    );
+    let leaf_search_response = if searched_splits.is_empty() {
+        Default::default()
+    } else {
+        root_leaf_search(
+            &root_search_context,
+            &searched_splits,
+            cluster_client,
+            search_job_placer,
+        )
+        .await?
+    };
+    Ok(SearchResponse {
+        num_hits: root_search_context.num_hits(num_covered_docs + leaf_search_response.num_hits),
+        elapsed_time_micros: start_instant.elapsed().as_micros() as u64,
+        timed_out: !leaf_search_response.failed_splits.is_empty(),
+        failed_splits: leaf_search_response.failed_splits,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
+    use quickwit_indexing::mock_split;
+    use quickwit_metastore::{IndexMetadata, MockMetastore, Split, SplitState};
+    use quickwit_proto::LeafSearchResponse;
+
+    use super::*;
+    use crate::{MockSearchService, SearchServiceClient};
+
+    fn split_with_time_range(split_id: &str, num_docs: usize, start: i64, end: i64) -> Split {
+        let mut split = mock_split(split_id);
+        split.split_metadata.num_docs = num_docs;
+        split.split_metadata.time_range = Some(start..=end);
+        split.split_state = SplitState::Published;
+        split
+    }
+
+    #[test]
+    fn test_count_request() {
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            max_hits: 20,
+            start_offset: 10,
+            aggregation_request: Some("{}".to_string()),
+            snippet_fields: vec!["body".to_string()],
+            start_timestamp: Some(10),
+            ..Default::default()
+        };
+        let count_request = count_request(&search_request);
+        assert_eq!(
+            count_request,
+            SearchRequest {
+                index_id: "test-index".to_string(),
+                query: "*".to_string(),
+                start_timestamp: Some(10),
+                count_only: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_is_split_covered_by_time_range() {
+        let split_metadata = split_with_time_range("split_1", 10, 100, 200).split_metadata;
+        let search_request = |start_timestamp, end_timestamp| SearchRequest {
+            start_timestamp,
+            end_timestamp,
+            ..Default::default()
+        };
+        assert!(is_split_covered_by_time_range(
+            &search_request(None, None),
+            &split_metadata
+        ));
+        assert!(is_split_covered_by_time_range(
+            &search_request(Some(100), Some(201)),
+            &split_metadata
+        ));
+        assert!(!is_split_covered_by_time_range(
+            &search_request(Some(101), None),
+            &split_metadata
+        ));
+        assert!(!is_split_covered_by_time_range(
+            &search_request(None, Some(200)),
+            &split_metadata
+        ));
+        let mut split_metadata_without_time_range = split_metadata;
+        split_metadata_without_time_range.time_range = None;
+        assert!(!is_split_covered_by_time_range(
+            &search_request(Some(100), None),
+            &split_metadata_without_time_range
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_root_count() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_index_metadata().returning(|_index_id| {
+            Ok(IndexMetadata::for_test(
+                "test-index",
+                "ram:///indexes/test-index",
+            ))
+        });
+        metastore.expect_list_splits().returning(|_filter| {
+            Ok(vec![
+                split_with_time_range("split_1", 10, 100, 200),
+                split_with_time_range("split_2", 20, 150, 300),
+            ])
+        });
+        let mut mock_search_service = MockSearchService::new();
+        // Only `split_2` is not fully covered by the time range, so it is the only split searched.
+        mock_search_service
+            .expect_leaf_search()
+            .times(1)
+            .returning(|leaf_search_request| {
+                assert_eq!(leaf_search_request.split_offsets.len(), 1);
+                assert_eq!(leaf_search_request.split_offsets[0].split_id, "split_2");
+                let search_request = leaf_search_request.search_request.unwrap();
+                assert_eq!(search_request.max_hits, 0);
+                Ok(LeafSearchResponse {
+                    num_hits: 5,
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        let client_pool =
+            ServiceClientPool::for_clients_list(vec![SearchServiceClient::from_service(
+                Arc::new(mock_search_service),
+                ([127, 0, 0, 1], 1000).into(),
+            )]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            start_timestamp: Some(100),
+            end_timestamp: Some(250),
+            ..Default::default()
+        };
+        let search_response = root_count(
+            &search_request,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await?;
+        assert_eq!(search_response.num_hits, 15);
+        assert!(search_response.hits.is_empty());
+        Ok(())
+    }
+}
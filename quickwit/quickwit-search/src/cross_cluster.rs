@@ -0,0 +1,404 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Cross-cluster search, fanning out a search to the local cluster and to the remote clusters
+//! configured on the searcher, and merging their hits.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::future::{join, join_all};
+use quickwit_common::index_id_matches_pattern;
+use quickwit_config::RemoteClusterConfig;
+use quickwit_metastore::Metastore;
+use quickwit_proto::tonic::codegen::InterceptedService;
+use quickwit_proto::tonic::transport::{Channel, Endpoint};
+use quickwit_proto::{tonic, SearchRequest, SearchResponse, SpanContextInterceptor};
+use tracing::error;
+
+use crate::error::parse_grpc_error;
+use crate::{partial_hit_sorting_key, root_search, ClusterClient, SearchError, SearchJobPlacer};
+
+/// Name of the local cluster in the errors of cross-cluster searches.
+const LOCAL_CLUSTER_NAME: &str = "local";
+
+type GrpcSearchServiceClient = quickwit_proto::search_service_client::SearchServiceClient<
+    InterceptedService<Channel, SpanContextInterceptor>,
+>;
+
+/// A remote Quickwit cluster, searched through the gRPC endpoint of its searchers.
+#[derive(Clone)]
+pub(crate) struct RemoteCluster {
+    name: String,
+    index_patterns: Vec<String>,
+    client: GrpcSearchServiceClient,
+}
+
+impl RemoteCluster {
+    fn from_config(remote_cluster_config: &RemoteClusterConfig) -> anyhow::Result<Self> {
+        // Create a channel with connect_lazy to automatically reconnect to the cluster.
+        let channel = Endpoint::from_shared(remote_cluster_config.grpc_endpoint.clone())?
+            .connect_timeout(Duration::from_secs(5))
+            .connect_lazy();
+        let client = GrpcSearchServiceClient::with_interceptor(channel, SpanContextInterceptor);
+        Ok(Self {
+            name: remote_cluster_config.name.clone(),
+            index_patterns: remote_cluster_config.index_patterns.clone(),
+            client,
+        })
+    }
+
+    /// Builds the remote clusters of the searcher config. Invalid endpoints are rejected when the
+    /// config is loaded, so the remote clusters failing to build are only logged.
+    pub fn from_configs(remote_cluster_configs: &[RemoteClusterConfig]) -> Vec<Self> {
+        remote_cluster_configs
+            .iter()
+            .filter_map(
+                |remote_cluster_config| match Self::from_config(remote_cluster_config) {
+                    Ok(remote_cluster) => Some(remote_cluster),
+                    Err(error) => {
+                        error!(remote_cluster = %remote_cluster_config.name, error = ?error, "Failed to create remote cluster client.");
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    fn serves_index(&self, index_id: &str) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|index_pattern| index_id_matches_pattern(index_id, index_pattern))
+    }
+
+    async fn root_search(&self, search_request: SearchRequest) -> crate::Result<SearchResponse> {
+        let tonic_response = self
+            .client
+            .clone()
+            .root_search(tonic::Request::new(search_request))
+            .await
+            .map_err(|tonic_error| parse_grpc_error(&tonic_error))?;
+        Ok(tonic_response.into_inner())
+    }
+}
+
+fn validate_cross_cluster_search(search_request: &SearchRequest) -> crate::Result<()> {
+    if search_request.aggregation_request.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Cross-cluster search does not support aggregations.".to_string(),
+        ));
+    }
+    if search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Cross-cluster search does not support `scroll`.".to_string(),
+        ));
+    }
+    let page_end = search_request.start_offset + search_request.max_hits;
+    if page_end > 10_000 {
+        return Err(SearchError::InvalidArgument(format!(
+            "Cross-cluster search `start_offset + max_hits` must be at most 10_000, but got \
+             {page_end}."
+        )));
+    }
+    Ok(())
+}
+
+/// Runs the search on the local cluster and on the remote clusters serving the index, and merges
+/// their hits. The failures of some of the clusters are reported in the errors of the response.
+pub(crate) async fn root_cross_cluster_search(
+    search_request: &SearchRequest,
+    remote_clusters: &[RemoteCluster],
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<SearchResponse> {
+    validate_cross_cluster_search(search_request)?;
+    // Each cluster returns the top hits up to the end of the requested page.
+    let cluster_search_request = SearchRequest {
+        start_offset: 0,
+        max_hits: search_request.start_offset + search_request.max_hits,
+        cross_cluster: false,
+        ..search_request.clone()
+    };
+    let local_search = root_search(
+        &cluster_search_request,
+        metastore,
+        cluster_client,
+        search_job_placer,
+    );
+    let remote_searches = remote_clusters
+        .iter()
+        .filter(|remote_cluster| remote_cluster.serves_index(&search_request.index_id))
+        .map(|remote_cluster| async {
+            let search_result = remote_cluster
+                .root_search(cluster_search_request.clone())
+                .await;
+            (remote_cluster.name.clone(), search_result)
+        });
+    let (local_search_result, remote_search_results) =
+        join(local_search, join_all(remote_searches)).await;
+
+    let mut cluster_search_results = vec![(LOCAL_CLUSTER_NAME.to_string(), local_search_result)];
+    cluster_search_results.extend(remote_search_results);
+    merge_cluster_search_results(search_request, cluster_search_results)
+}
+
+/// Merges the search results of the clusters. A cluster that does not have the index is skipped,
+/// and the search only fails if it fails on every cluster.
+fn merge_cluster_search_results(
+    search_request: &SearchRequest,
+    cluster_search_results: Vec<(String, crate::Result<SearchResponse>)>,
+) -> crate::Result<SearchResponse> {
+    let mut search_responses = Vec::new();
+    let mut cluster_errors = Vec::new();
+
+    for (cluster_name, search_result) in cluster_search_results {
+        match search_result {
+            Ok(search_response) => search_responses.push((cluster_name, search_response)),
+            Err(search_error) => cluster_errors.push((cluster_name, search_error)),
+        }
+    }
+    if search_responses.is_empty() {
+        let first_error_pos = cluster_errors
+            .iter()
+            .position(|(_, search_error)| {
+                !matches!(search_error, SearchError::IndexDoesNotExist { .. })
+            })
+            .unwrap_or(0);
+        let (_, search_error) = cluster_errors.swap_remove(first_error_pos);
+        return Err(search_error);
+    }
+    let mut num_hits = 0;
+    let mut hits = Vec::new();
+    let mut elapsed_time_micros = 0;
+    let mut errors = Vec::new();
+    let mut timed_out = false;
+    let mut failed_splits = Vec::new();
+    let mut split_metadata = HashMap::new();
+
+    for (cluster_name, search_response) in search_responses {
+        num_hits += search_response.num_hits;
+        hits.extend(search_response.hits);
+        elapsed_time_micros = elapsed_time_micros.max(search_response.elapsed_time_micros);
+        timed_out |= search_response.timed_out;
+        failed_splits.extend(search_response.failed_splits);
+        split_metadata.extend(search_response.split_metadata);
+
+        if cluster_name == LOCAL_CLUSTER_NAME {
+            errors.extend(search_response.errors);
+        } else {
+            errors.extend(
+                search_response
+                    .errors
+                    .into_iter()
+                    .map(|error| format!("Cluster `{cluster_name}`: {error}")),
+            );
+        }
+    }
+    for (cluster_name, search_error) in cluster_errors {
+        if !matches!(search_error, SearchError::IndexDoesNotExist { .. }) {
+            errors.push(format!("Cluster `{cluster_name}` failed: {search_error}"));
+        }
+    }
+    hits.sort_by_cached_key(|hit| hit.partial_hit.as_ref().map(partial_hit_sorting_key));
+    let hits = hits
+        .into_iter()
+        .skip(search_request.start_offset as usize)
+        .take(search_request.max_hits as usize)
+        .collect();
+
+    Ok(SearchResponse {
+        num_hits,
+        hits,
+        elapsed_time_micros,
+        errors,
+        aggregation: None,
+        scroll_id: None,
+        timed_out,
+        failed_splits,
+        split_metadata,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::{Hit, PartialHit};
+
+    use super::*;
+
+    fn hit(split_id: &str, sorting_field_value: u64) -> Hit {
+        Hit {
+            json: "{}".to_string(),
+            partial_hit: Some(PartialHit {
+                sorting_field_value,
+                split_id: split_id.to_string(),
+                segment_ord: 0,
+                doc_id: 0,
+                secondary_sorting_field_values: Vec::new(),
+                collapse_value: None,
+                num_collapsed_hits: None,
+            }),
+            snippet: None,
+        }
+    }
+
+    fn hit_split_ids(search_response: &SearchResponse) -> Vec<&str> {
+        search_response
+            .hits
+            .iter()
+            .map(|hit| hit.partial_hit.as_ref().unwrap().split_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_cross_cluster_search() {
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            max_hits: 20,
+            cross_cluster: true,
+            ..Default::default()
+        };
+        validate_cross_cluster_search(&search_request).unwrap();
+
+        let invalid_search_requests = [
+            (
+                SearchRequest {
+                    aggregation_request: Some("{}".to_string()),
+                    ..search_request.clone()
+                },
+                "does not support aggregations",
+            ),
+            (
+                SearchRequest {
+                    scroll_ttl_secs: Some(60),
+                    ..search_request.clone()
+                },
+                "does not support `scroll`",
+            ),
+            (
+                SearchRequest {
+                    start_offset: 9_990,
+                    ..search_request.clone()
+                },
+                "must be at most 10_000, but got 10010",
+            ),
+        ];
+        for (invalid_search_request, expected_error) in invalid_search_requests {
+            let error = validate_cross_cluster_search(&invalid_search_request).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_merge_cluster_search_results() {
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            start_offset: 1,
+            max_hits: 3,
+            cross_cluster: true,
+            ..Default::default()
+        };
+        let cluster_search_results = vec![
+            (
+                LOCAL_CLUSTER_NAME.to_string(),
+                Ok(SearchResponse {
+                    num_hits: 10,
+                    hits: vec![hit("local-split", 9), hit("local-split", 5)],
+                    elapsed_time_micros: 100,
+                    errors: vec!["local split error".to_string()],
+                    ..Default::default()
+                }),
+            ),
+            (
+                "eu-west".to_string(),
+                Ok(SearchResponse {
+                    num_hits: 5,
+                    hits: vec![hit("eu-split", 7), hit("eu-split", 3)],
+                    elapsed_time_micros: 300,
+                    errors: vec!["eu split error".to_string()],
+                    ..Default::default()
+                }),
+            ),
+            (
+                "us-east".to_string(),
+                Err(SearchError::InternalError("connection refused".to_string())),
+            ),
+            (
+                "ap-south".to_string(),
+                Err(SearchError::IndexDoesNotExist {
+                    index_id: "logs".to_string(),
+                }),
+            ),
+        ];
+        let search_response =
+            merge_cluster_search_results(&search_request, cluster_search_results).unwrap();
+        assert_eq!(search_response.num_hits, 15);
+        assert_eq!(
+            hit_split_ids(&search_response),
+            ["eu-split", "local-split", "eu-split"]
+        );
+        assert_eq!(search_response.elapsed_time_micros, 300);
+        assert_eq!(
+            search_response.errors,
+            [
+                "local split error",
+                "Cluster `eu-west`: eu split error",
+                "Cluster `us-east` failed: Internal error: `connection refused`.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_cluster_search_results_all_clusters_failed() {
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            cross_cluster: true,
+            ..Default::default()
+        };
+        let cluster_search_results = vec![
+            (
+                LOCAL_CLUSTER_NAME.to_string(),
+                Err(SearchError::IndexDoesNotExist {
+                    index_id: "logs".to_string(),
+                }),
+            ),
+            (
+                "eu-west".to_string(),
+                Err(SearchError::InternalError("connection refused".to_string())),
+            ),
+        ];
+        let error =
+            merge_cluster_search_results(&search_request, cluster_search_results).unwrap_err();
+        assert_eq!(error.to_string(), "Internal error: `connection refused`.");
+
+        let cluster_search_results = vec![(
+            LOCAL_CLUSTER_NAME.to_string(),
+            Err(SearchError::IndexDoesNotExist {
+                index_id: "logs".to_string(),
+            }),
+        )];
+        let error =
+            merge_cluster_search_results(&search_request, cluster_search_results).unwrap_err();
+        assert!(matches!(error, SearchError::IndexDoesNotExist { .. }));
+    }
+}
@@ -42,6 +42,14 @@ pub enum SearchError {
     InvalidArgument(String),
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
+    #[error("Scroll context `{0}` does not exist or has expired.")]
+    ScrollContextDoesNotExist(String),
+    #[error("Async search `{0}` does not exist or has expired.")]
+    AsyncSearchDoesNotExist(String),
+    #[error("Search view `{0}` does not exist or has expired.")]
+    SearchViewDoesNotExist(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
 }
 
 impl ServiceError for SearchError {
@@ -53,6 +61,10 @@ impl ServiceError for SearchError {
             SearchError::InvalidQuery(_) => ServiceErrorCode::BadRequest,
             SearchError::InvalidArgument(_) => ServiceErrorCode::BadRequest,
             SearchError::InvalidAggregationRequest(_) => ServiceErrorCode::BadRequest,
+            SearchError::ScrollContextDoesNotExist(_) => ServiceErrorCode::NotFound,
+            SearchError::AsyncSearchDoesNotExist(_) => ServiceErrorCode::NotFound,
+            SearchError::SearchViewDoesNotExist(_) => ServiceErrorCode::NotFound,
+            SearchError::TooManyRequests(_) => ServiceErrorCode::RateLimited,
         }
     }
 }
@@ -0,0 +1,290 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Search explanation, describing how a search is executed to debug slow searches or missing
+//! documents: the query as parsed against the doc mapping, the splits pruned before searching and
+//! why, and the time spent searching each of the remaining splits.
+//!
+//! The remaining splits are searched on the node serving the explanation rather than dispatched
+//! to the searchers of the cluster, so the reported cache hits and misses are the ones of this
+//! node.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use futures::future::join_all;
+use quickwit_doc_mapper::bloom_filter::{extract_bloom_filter_from_query, BloomFilterAst};
+use quickwit_doc_mapper::range_pruning::{extract_range_filter_from_query, RangeFilterAst};
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_metastore::{split_tag_filter, ListSplitsQuery, Metastore, Split, SplitState};
+use quickwit_proto::SearchRequest;
+use quickwit_storage::{Storage, StorageUriResolver};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::leaf::leaf_search;
+use crate::root::RootSearchContext;
+use crate::{extract_split_and_footer_offsets, SearcherContext};
+
+/// Explanation of a search, returned by the explain API.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SearchExplanation {
+    /// ID of the searched index.
+    pub index_id: String,
+    /// Query, as written in the search request.
+    pub query: String,
+    /// Query, as parsed against the doc mapping of the index.
+    pub parsed_query: String,
+    /// Number of published splits of the index.
+    pub num_published_splits: usize,
+    /// Splits discarded without being searched.
+    pub pruned_splits: Vec<PrunedSplit>,
+    /// Splits searched, from the slowest to the fastest.
+    pub searched_splits: Vec<SplitExplanation>,
+    /// Number of hits of the search.
+    pub num_hits: u64,
+    /// Time spent explaining the search, in microseconds.
+    pub elapsed_time_micros: u64,
+}
+
+/// Reason for discarding a split without searching it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PruningReason {
+    /// The time range of the split does not overlap the time range of the search.
+    TimeRange,
+    /// The tags of the split do not match the tag clauses of the query.
+    Tags,
+    /// The value ranges of the fields of the split do not match the range clauses of the query.
+    FieldRanges,
+    /// The bloom filters of the split rule out the terms required by the query.
+    BloomFilter,
+}
+
+/// Split discarded without being searched.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct PrunedSplit {
+    /// ID of the split.
+    pub split_id: String,
+    /// Why the split was discarded.
+    pub reason: PruningReason,
+}
+
+/// Execution of the search on a split.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SplitExplanation {
+    /// ID of the split.
+    pub split_id: String,
+    /// Number of documents of the split.
+    pub num_docs: u64,
+    /// Number of documents of the split matching the query.
+    pub num_hits: u64,
+    /// Time spent searching the split, in microseconds, including the time spent waiting for a
+    /// search permit.
+    pub elapsed_time_micros: u64,
+    /// Whether the response of the split was served by the leaf search cache.
+    pub leaf_search_cache_hit: bool,
+    /// Whether the footer of the split was served by the split footer cache.
+    pub split_footer_cache_hit: bool,
+    /// Cause of the failure of the search on the split.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Explains a search: prunes the splits of the index, then searches the remaining splits on this
+/// node, one leaf search per split.
+#[instrument(skip(metastore, storage_uri_resolver, searcher_context))]
+pub(crate) async fn root_explain(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    storage_uri_resolver: &StorageUriResolver,
+    searcher_context: &Arc<SearcherContext>,
+) -> crate::Result<SearchExplanation> {
+    let start_instant = Instant::now();
+    let root_search_context = RootSearchContext::new(search_request, metastore).await?;
+    let search_request = &root_search_context.search_request;
+    let doc_mapper = root_search_context.doc_mapper();
+    let (query, _) = doc_mapper.query(doc_mapper.schema(), search_request)?;
+    let parsed_query = format!("{query:?}");
+
+    let list_splits_query = ListSplitsQuery::for_index(&search_request.index_id)
+        .with_split_state(SplitState::Published);
+    let splits = metastore.list_splits(list_splits_query).await?;
+    let num_published_splits = splits.len();
+    let split_pruner = SplitPruner::new(search_request)?;
+    let mut pruned_splits = Vec::new();
+    let mut splits_to_search = Vec::new();
+
+    for split in splits {
+        if let Some(reason) = split_pruner.pruning_reason(&split) {
+            pruned_splits.push(PrunedSplit {
+                split_id: split.split_metadata.split_id,
+                reason,
+            });
+        } else {
+            splits_to_search.push(split);
+        }
+    }
+    let index_storage = storage_uri_resolver.resolve(root_search_context.index_uri())?;
+
+    // Same request as the one sent to the leaves by the root search.
+    let mut leaf_search_request = search_request.clone();
+    leaf_search_request.start_offset = 0;
+    leaf_search_request.max_hits += search_request.start_offset;
+
+    let split_explanation_futures = splits_to_search.iter().map(|split| {
+        explain_split(
+            searcher_context,
+            &leaf_search_request,
+            index_storage.clone(),
+            &root_search_context,
+            split,
+        )
+    });
+    let mut searched_splits = join_all(split_explanation_futures).await;
+    searched_splits
+        .sort_by_key(|split_explanation| std::cmp::Reverse(split_explanation.elapsed_time_micros));
+    let num_hits = searched_splits
+        .iter()
+        .map(|split_explanation| split_explanation.num_hits)
+        .sum();
+
+    Ok(SearchExplanation {
+        index_id: search_request.index_id.clone(),
+        query: search_request.query.clone(),
+        parsed_query,
+        num_published_splits,
+        pruned_splits,
+        searched_splits,
+        num_hits,
+        elapsed_time_micros: start_instant.elapsed().as_micros() as u64,
+    })
+}
+
+async fn explain_split(
+    searcher_context: &Arc<SearcherContext>,
+    leaf_search_request: &SearchRequest,
+    index_storage: Arc<dyn Storage>,
+    root_search_context: &RootSearchContext,
+    split: &Split,
+) -> SplitExplanation {
+    let split_metadata = &split.split_metadata;
+    let doc_mapper = root_search_context.doc_mapper();
+    let leaf_search_cache_hit = searcher_context
+        .leaf_search_cache
+        .request_fingerprint(leaf_search_request, &**doc_mapper)
+        .map(|request_fingerprint| {
+            searcher_context
+                .leaf_search_cache
+                .get(&split_metadata.split_id, &request_fingerprint)
+                .is_some()
+        })
+        .unwrap_or(false);
+    let split_footer_cache_hit = searcher_context
+        .split_footer_cache
+        .get(&split_metadata.split_id)
+        .is_some();
+    let start_instant = Instant::now();
+    let leaf_search_result = leaf_search(
+        searcher_context.clone(),
+        leaf_search_request,
+        index_storage,
+        &[extract_split_and_footer_offsets(split_metadata)],
+        doc_mapper.clone(),
+    )
+    .await;
+    let elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+    let (num_hits, error) = match leaf_search_result {
+        Ok(leaf_search_response) => {
+            let error = leaf_search_response
+                .failed_splits
+                .into_iter()
+                .next()
+                .map(|split_search_error| split_search_error.error);
+            (leaf_search_response.num_hits, error)
+        }
+        Err(search_error) => (0, Some(search_error.to_string())),
+    };
+    SplitExplanation {
+        split_id: split_metadata.split_id.clone(),
+        num_docs: split_metadata.num_docs as u64,
+        num_hits,
+        elapsed_time_micros,
+        leaf_search_cache_hit,
+        split_footer_cache_hit,
+        error,
+    }
+}
+
+/// Applies the same pruning as the search, in the same order: first the time range and the tags,
+/// which are applied by the metastore, then the field ranges and the bloom filters.
+struct SplitPruner<'a> {
+    list_splits_query: ListSplitsQuery<'a>,
+    range_filter_opt: Option<RangeFilterAst>,
+    bloom_filter_opt: Option<BloomFilterAst>,
+}
+
+impl<'a> SplitPruner<'a> {
+    fn new(search_request: &'a SearchRequest) -> crate::Result<Self> {
+        let mut list_splits_query = ListSplitsQuery::for_index(&search_request.index_id);
+        if let Some(start_ts) = search_request.start_timestamp {
+            list_splits_query = list_splits_query.with_time_range_start_gte(start_ts);
+        }
+        if let Some(end_ts) = search_request.end_timestamp {
+            list_splits_query = list_splits_query.with_time_range_end_lt(end_ts);
+        }
+        if let Some(tags_filter) = extract_tags_from_query(&search_request.query)? {
+            list_splits_query = list_splits_query.with_tags_filter(tags_filter);
+        }
+        Ok(Self {
+            list_splits_query,
+            range_filter_opt: extract_range_filter_from_query(&search_request.query)?,
+            bloom_filter_opt: extract_bloom_filter_from_query(&search_request.query)?,
+        })
+    }
+
+    fn pruning_reason(&self, split: &Split) -> Option<PruningReason> {
+        let split_metadata = &split.split_metadata;
+
+        if let Some(time_range) = &split_metadata.time_range {
+            if !self
+                .list_splits_query
+                .time_range
+                .overlaps_with(time_range.clone())
+            {
+                return Some(PruningReason::TimeRange);
+            }
+        }
+        if !split_tag_filter(split, self.list_splits_query.tags.as_ref()) {
+            return Some(PruningReason::Tags);
+        }
+        if let Some(range_filter) = &self.range_filter_opt {
+            if !range_filter.evaluate(&split_metadata.field_ranges) {
+                return Some(PruningReason::FieldRanges);
+            }
+        }
+        if let Some(bloom_filter) = &self.bloom_filter_opt {
+            if !bloom_filter.evaluate(&split_metadata.bloom_filters) {
+                return Some(PruningReason::BloomFilter);
+            }
+        }
+        None
+    }
+}
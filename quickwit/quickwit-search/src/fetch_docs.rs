@@ -18,6 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::sync::Arc;
 
 use anyhow::{Context, Ok};
@@ -28,14 +29,14 @@ use quickwit_proto::{FetchDocsResponse, PartialHit, SearchRequest, SplitIdAndFoo
 use quickwit_storage::Storage;
 use tantivy::query::Query;
 use tantivy::schema::{Field, Value};
-use tantivy::{ReloadPolicy, Score, Searcher, SnippetGenerator, Term};
+use tantivy::{ReloadPolicy, Score, Searcher, Snippet, SnippetGenerator, Term};
 use tracing::error;
 
 use crate::leaf::open_index_with_caches;
 use crate::service::SearcherContext;
 use crate::{convert_document_to_json_string, GlobalDocAddress};
 
-const SNIPPET_MAX_NUM_CHARS: usize = 150;
+const DEFAULT_SNIPPET_MAX_NUM_CHARS: usize = 150;
 
 /// Given a list of global doc address, fetches all the documents and
 /// returns them as a hashmap.
@@ -170,9 +171,11 @@ async fn fetch_docs_in_split(
     global_doc_addrs.sort_by_key(|doc| doc.doc_addr);
     // Opens the index without the ephemeral unbounded cache, this cache is indeed not useful
     // when fetching docs as we will fetch them only once.
-    let index = open_index_with_caches(&searcher_context, index_storage, split, false)
+    let mut index = open_index_with_caches(&searcher_context, index_storage, split, false)
         .await
         .with_context(|| "open-index-for-split")?;
+    // The snippets are generated with the tokenizers of the doc mapper.
+    index.set_tokenizers(doc_mapper.tokenizer_manager().clone());
     let index_reader = index
         .reader_builder()
         // the docs are presorted so a cache size of NUM_CONCURRENT_REQUESTS is fine
@@ -249,6 +252,9 @@ async fn fetch_docs_in_split(
 #[derive(Clone)]
 struct FieldsSnippetGenerator {
     field_generators: Arc<HashMap<String, SnippetGenerator>>,
+    // Tags surrounding the highlighted terms. If `None`, the default `<b>` and `</b>` tags are
+    // used.
+    highlight_tags_opt: Option<Arc<(String, String)>>,
 }
 
 impl FieldsSnippetGenerator {
@@ -265,7 +271,7 @@ impl FieldsSnippetGenerator {
                     value.as_text().and_then(|text| {
                         let snippet = snippet_generator.snippet(text);
                         match snippet.is_empty() {
-                            false => Some(snippet.to_html()),
+                            false => Some(self.render_snippet(&snippet)),
                             _ => None,
                         }
                     })
@@ -277,11 +283,53 @@ impl FieldsSnippetGenerator {
         }
     }
 
+    fn render_snippet(&self, snippet: &Snippet) -> String {
+        let Some(highlight_tags) = &self.highlight_tags_opt else {
+            return snippet.to_html();
+        };
+        let (pre_tag, post_tag) = highlight_tags.as_ref();
+        render_snippet_with_tags(snippet.fragment(), snippet.highlighted(), pre_tag, post_tag)
+    }
+
     fn is_empty(&self) -> bool {
         self.field_generators.is_empty()
     }
 }
 
+/// Renders a snippet fragment as HTML, surrounding the highlighted ranges with the given tags.
+/// Like `Snippet::to_html`, the text of the fragment is escaped but the tags are not.
+fn render_snippet_with_tags(
+    fragment: &str,
+    highlighted: &[Range<usize>],
+    pre_tag: &str,
+    post_tag: &str,
+) -> String {
+    let mut html = String::with_capacity(fragment.len());
+    let mut start_from = 0;
+    for range in highlighted {
+        escape_html_into(&fragment[start_from..range.start], &mut html);
+        html.push_str(pre_tag);
+        escape_html_into(&fragment[range.clone()], &mut html);
+        html.push_str(post_tag);
+        start_from = range.end;
+    }
+    escape_html_into(&fragment[start_from..], &mut html);
+    html
+}
+
+fn escape_html_into(text: &str, html: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#x27;"),
+            _ => html.push(ch),
+        }
+    }
+}
+
 // Creates FieldsSnippetGenerator.
 async fn create_fields_snippet_generator(
     searcher: &Searcher,
@@ -290,15 +338,28 @@ async fn create_fields_snippet_generator(
 ) -> anyhow::Result<FieldsSnippetGenerator> {
     let schema = searcher.schema();
     let (query, _) = doc_mapper.query(schema.clone(), search_request)?;
+    let max_num_chars = search_request
+        .snippet_max_num_chars
+        .map(|max_num_chars| max_num_chars as usize)
+        .unwrap_or(DEFAULT_SNIPPET_MAX_NUM_CHARS);
     let mut snippet_generators = HashMap::new();
     for field_name in &search_request.snippet_fields {
         let field = schema.get_field(field_name)?;
-        let snippet_generator = create_snippet_generator(searcher, &*query, field).await?;
+        let snippet_generator =
+            create_snippet_generator(searcher, &*query, field, max_num_chars).await?;
         snippet_generators.insert(field_name.clone(), snippet_generator);
     }
-
+    let highlight_tags_opt =
+        if search_request.snippet_pre_tag.is_some() || search_request.snippet_post_tag.is_some() {
+            let pre_tag = search_request.snippet_pre_tag.as_deref().unwrap_or("<b>");
+            let post_tag = search_request.snippet_post_tag.as_deref().unwrap_or("</b>");
+            Some(Arc::new((pre_tag.to_string(), post_tag.to_string())))
+        } else {
+            None
+        };
     Ok(FieldsSnippetGenerator {
         field_generators: Arc::new(snippet_generators),
+        highlight_tags_opt,
     })
 }
 
@@ -307,6 +368,7 @@ async fn create_snippet_generator(
     searcher: &Searcher,
     query: &dyn Query,
     field: Field,
+    max_num_chars: usize,
 ) -> anyhow::Result<SnippetGenerator> {
     let mut terms: Vec<&Term> = Vec::new();
     // TODO ok with termset?
@@ -331,6 +393,21 @@ async fn create_snippet_generator(
         terms_text,
         tokenizer,
         field,
-        SNIPPET_MAX_NUM_CHARS,
+        max_num_chars,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_snippet_with_tags() {
+        assert_eq!(
+            render_snippet_with_tags("a <quick> fox & a dog", &[3..8, 18..21], "<em>", "</em>"),
+            "a &lt;<em>quick</em>&gt; fox &amp; a <em>dog</em>"
+        );
+        assert_eq!(render_snippet_with_tags("fox", &[], "[", "]"), "fox");
+        assert_eq!(render_snippet_with_tags("fox", &[0..3], "[", "]"), "[fox]");
+    }
+}
@@ -0,0 +1,296 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::Arc;
+
+use quickwit_common::index_id_matches_pattern;
+use quickwit_config::{FieldMaskActionConfig, FieldMaskConfig};
+use quickwit_proto::{FieldMask, FieldMaskAction, Hit, SearchRequest};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::SearchError;
+
+tokio::task_local! {
+    static CALLER_FIELD_MASKS: Arc<Vec<FieldMaskConfig>>;
+}
+
+/// Runs `future`, masking the fields of `field_masks` in the documents returned by the searches
+/// it performs.
+pub async fn with_caller_field_masks<F: Future>(
+    field_masks: Vec<FieldMaskConfig>,
+    future: F,
+) -> F::Output {
+    CALLER_FIELD_MASKS
+        .scope(Arc::new(field_masks), future)
+        .await
+}
+
+/// Adds to `search_request` the field masks of the caller of the current task that apply to the
+/// index it targets.
+///
+/// The masks are carried by the request, so that they are applied by the searches that outlive
+/// the task, such as async searches, scrolls, and live tails, and by the remote clusters.
+pub fn add_caller_field_masks(search_request: &mut SearchRequest) {
+    let Ok(caller_field_masks) = CALLER_FIELD_MASKS.try_with(Arc::clone) else {
+        return;
+    };
+    for field_mask_config in caller_field_masks.iter() {
+        if !field_mask_applies_to_index(field_mask_config, &search_request.index_id) {
+            continue;
+        }
+        let action = match field_mask_config.action {
+            FieldMaskActionConfig::Exclude => FieldMaskAction::Exclude,
+            FieldMaskActionConfig::Hash => FieldMaskAction::Hash,
+        };
+        let field_mask = FieldMask {
+            field: field_mask_config.field.clone(),
+            action: action as i32,
+        };
+        if !search_request.field_masks.contains(&field_mask) {
+            search_request.field_masks.push(field_mask);
+        }
+    }
+}
+
+/// Returns whether a field mask applies to the comma-separated list of index IDs or patterns of
+/// a search request. Masks always apply to the requests targeting index patterns, since the
+/// indexes they match are not known yet.
+fn field_mask_applies_to_index(field_mask_config: &FieldMaskConfig, index_ids: &str) -> bool {
+    index_ids.split(',').any(|index_id| {
+        index_id.contains('*')
+            || field_mask_config
+                .index_patterns
+                .iter()
+                .any(|index_pattern| index_id_matches_pattern(index_id, index_pattern))
+    })
+}
+
+/// Masks the fields of the documents and of the snippets of the hits. The snippets of the masked
+/// fields are removed whatever the action of the mask.
+pub(crate) fn apply_field_masks(hits: &mut [Hit], field_masks: &[FieldMask]) -> crate::Result<()> {
+    if field_masks.is_empty() {
+        return Ok(());
+    }
+    for hit in hits {
+        let mut doc: JsonValue = serde_json::from_str(&hit.json).map_err(|error| {
+            SearchError::InternalError(format!("Failed to parse document: {error}"))
+        })?;
+        for field_mask in field_masks {
+            let path: Vec<&str> = field_mask.field.split('.').collect();
+            let action =
+                FieldMaskAction::from_i32(field_mask.action).unwrap_or(FieldMaskAction::Exclude);
+            mask_field(&mut doc, &path, action);
+        }
+        hit.json = doc.to_string();
+
+        if let Some(snippet_json) = &hit.snippet {
+            let mut snippets: HashMap<String, JsonValue> = serde_json::from_str(snippet_json)
+                .map_err(|error| {
+                    SearchError::InternalError(format!("Failed to parse snippet: {error}"))
+                })?;
+            snippets.retain(|snippet_field, _| {
+                !field_masks
+                    .iter()
+                    .any(|field_mask| is_same_or_nested_field(snippet_field, &field_mask.field))
+            });
+            hit.snippet = Some(serde_json::to_string(&snippets)?);
+        }
+    }
+    Ok(())
+}
+
+/// Masks the field at `path` in `value`, descending into the arrays of objects along the way.
+fn mask_field(value: &mut JsonValue, path: &[&str], action: FieldMaskAction) {
+    match value {
+        JsonValue::Array(values) => {
+            for value in values {
+                mask_field(value, path, action);
+            }
+        }
+        JsonValue::Object(object) => {
+            let [key, sub_path @ ..] = path else {
+                return;
+            };
+            if sub_path.is_empty() {
+                match action {
+                    FieldMaskAction::Exclude => {
+                        object.remove(*key);
+                    }
+                    FieldMaskAction::Hash => {
+                        if let Some(field_value) = object.get_mut(*key) {
+                            hash_value(field_value);
+                        }
+                    }
+                }
+            } else if let Some(field_value) = object.get_mut(*key) {
+                mask_field(field_value, sub_path, action);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces a value with the hex-encoded SHA-256 hash of its string representation. The values
+/// of arrays are hashed one by one, and nulls are left untouched.
+fn hash_value(value: &mut JsonValue) {
+    let value_str = match value {
+        JsonValue::Null => return,
+        JsonValue::Array(values) => {
+            for value in values {
+                hash_value(value);
+            }
+            return;
+        }
+        JsonValue::String(value_str) => value_str.clone(),
+        _ => value.to_string(),
+    };
+    let digest = Sha256::digest(value_str.as_bytes());
+    let mut hash = String::with_capacity(2 * digest.len());
+    for byte in digest {
+        write!(hash, "{byte:02x}").unwrap();
+    }
+    *value = JsonValue::String(hash);
+}
+
+/// Returns whether the snippet of `snippet_field` may reveal `masked_field`: the fields are the
+/// same, or one is nested in the other.
+fn is_same_or_nested_field(snippet_field: &str, masked_field: &str) -> bool {
+    let is_nested = |field: &str, parent_field: &str| {
+        field
+            .strip_prefix(parent_field)
+            .map_or(false, |suffix| suffix.is_empty() || suffix.starts_with('.'))
+    };
+    is_nested(snippet_field, masked_field) || is_nested(masked_field, snippet_field)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn field_mask(field: &str, action: FieldMaskAction) -> FieldMask {
+        FieldMask {
+            field: field.to_string(),
+            action: action as i32,
+        }
+    }
+
+    #[test]
+    fn test_apply_field_masks() {
+        let mut hits = vec![Hit {
+            json: json!({
+                "body": "hello",
+                "user": {"email": "alice@example.com", "name": "Alice", "ids": [1, 2]},
+                "contacts": [{"phone": "555-0100"}, {"phone": "555-0101", "name": "Bob"}],
+            })
+            .to_string(),
+            partial_hit: None,
+            snippet: Some(
+                json!({"body": ["<b>hello</b>"], "user.email": ["<b>alice</b>@example.com"]})
+                    .to_string(),
+            ),
+        }];
+        let field_masks = [
+            field_mask("user.email", FieldMaskAction::Hash),
+            field_mask("user.ids", FieldMaskAction::Hash),
+            field_mask("contacts.phone", FieldMaskAction::Exclude),
+            field_mask("missing.field", FieldMaskAction::Exclude),
+        ];
+        apply_field_masks(&mut hits, &field_masks).unwrap();
+
+        let doc: JsonValue = serde_json::from_str(&hits[0].json).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "body": "hello",
+                "user": {
+                    "email": "ff8d9819fc0e12bf0d24892e45987e249a28dce836a85cad60e28eaaa8c6d976",
+                    "name": "Alice",
+                    "ids": [
+                        "6b86b273ff34fce19d6b804eff5a3f5747ada4eaa22f1d49c01e52ddb7875b4b",
+                        "d4735e3a265e16eee03f59718b9b5d03019c07d8b6c51f90da3a666eec13ab35",
+                    ],
+                },
+                "contacts": [{}, {"name": "Bob"}],
+            })
+        );
+        let snippets: JsonValue = serde_json::from_str(hits[0].snippet.as_ref().unwrap()).unwrap();
+        assert_eq!(snippets, json!({"body": ["<b>hello</b>"]}));
+    }
+
+    #[test]
+    fn test_is_same_or_nested_field() {
+        assert!(is_same_or_nested_field("user.email", "user.email"));
+        assert!(is_same_or_nested_field("user.email", "user"));
+        assert!(is_same_or_nested_field("attributes", "attributes.secret"));
+        assert!(!is_same_or_nested_field("user.emails", "user.email"));
+        assert!(!is_same_or_nested_field("body", "user.email"));
+    }
+
+    #[tokio::test]
+    async fn test_add_caller_field_masks() {
+        let caller_field_masks = vec![
+            FieldMaskConfig {
+                field: "user.email".to_string(),
+                action: FieldMaskActionConfig::Hash,
+                index_patterns: vec!["logs-*".to_string()],
+            },
+            FieldMaskConfig {
+                field: "user.phone".to_string(),
+                action: FieldMaskActionConfig::Exclude,
+                index_patterns: vec!["traces".to_string()],
+            },
+        ];
+        let mut search_request = SearchRequest {
+            index_id: "logs-eu".to_string(),
+            ..Default::default()
+        };
+        add_caller_field_masks(&mut search_request);
+        assert!(search_request.field_masks.is_empty());
+
+        with_caller_field_masks(caller_field_masks, async {
+            add_caller_field_masks(&mut search_request);
+            // Adding the masks twice does not duplicate them.
+            add_caller_field_masks(&mut search_request);
+            assert_eq!(
+                search_request.field_masks,
+                [field_mask("user.email", FieldMaskAction::Hash)]
+            );
+            let mut search_request = SearchRequest {
+                index_id: "logs-eu,traces".to_string(),
+                ..Default::default()
+            };
+            add_caller_field_masks(&mut search_request);
+            assert_eq!(search_request.field_masks.len(), 2);
+
+            let mut search_request = SearchRequest {
+                index_id: "*".to_string(),
+                ..Default::default()
+            };
+            add_caller_field_masks(&mut search_request);
+            assert_eq!(search_request.field_masks.len(), 2);
+        })
+        .await;
+    }
+}
@@ -21,7 +21,12 @@ use std::ops::{Bound, RangeBounds, RangeInclusive};
 use std::sync::Arc;
 
 use fastfield_codecs::Column;
-use tantivy::{DateTime, DocId, SegmentReader};
+use quickwit_doc_mapper::{GeoFilter, GeoPoint, GeoShape, RuntimeExpr};
+use tantivy::fastfield::MultiValuedFastFieldReader;
+use tantivy::schema::FieldType;
+use tantivy::{DateTime, DocId, SegmentReader, TantivyError};
+
+use crate::SearchError;
 
 /// A filter that only retains docs within a time range.
 #[derive(Clone)]
@@ -106,6 +111,141 @@ impl TimestampFilterBuilder {
     }
 }
 
+/// A filter that only retains docs with at least one geo point located within a shape.
+pub struct GeoPointFilter {
+    shape: GeoShape,
+    /// The geo point fast field reader.
+    geo_point_column: MultiValuedFastFieldReader<u64>,
+    /// Buffer holding the encoded geo points of the doc being filtered.
+    geo_points_buffer: Vec<u64>,
+}
+
+impl GeoPointFilter {
+    pub fn is_within_shape(&mut self, doc_id: DocId) -> bool {
+        self.geo_point_column
+            .get_vals(doc_id, &mut self.geo_points_buffer);
+        self.geo_points_buffer
+            .iter()
+            .any(|&encoded_geo_point| self.shape.contains(&GeoPoint::from_u64(encoded_geo_point)))
+    }
+}
+
+/// Creates a geo point filter builder from the JSON serialized geo filter of the user request.
+pub fn create_geo_point_filter_builder(
+    geo_filter_json_opt: Option<&str>,
+) -> crate::Result<Option<GeoPointFilterBuilder>> {
+    let Some(geo_filter_json) = geo_filter_json_opt else {
+        return Ok(None);
+    };
+    let geo_filter = GeoFilter::parse(geo_filter_json)
+        .map_err(|error| SearchError::InvalidArgument(format!("Invalid geo filter: {error}")))?;
+    Ok(Some(GeoPointFilterBuilder {
+        geo_point_field_name: geo_filter.field,
+        shape: geo_filter.shape,
+    }))
+}
+
+#[derive(Clone, Debug)]
+pub struct GeoPointFilterBuilder {
+    pub geo_point_field_name: String,
+    shape: GeoShape,
+}
+
+impl GeoPointFilterBuilder {
+    pub fn build(&self, segment_reader: &SegmentReader) -> tantivy::Result<GeoPointFilter> {
+        let geo_point_column = segment_reader
+            .fast_fields()
+            .u64s(&self.geo_point_field_name)?;
+        Ok(GeoPointFilter {
+            shape: self.shape.clone(),
+            geo_point_column,
+            geo_points_buffer: Vec::new(),
+        })
+    }
+}
+
+/// A fast field column read as `f64` by runtime expressions.
+enum RuntimeColumn {
+    U64(Arc<dyn Column<u64>>),
+    I64(Arc<dyn Column<i64>>),
+    F64(Arc<dyn Column<f64>>),
+    Bool(Arc<dyn Column<bool>>),
+    DateTime(Arc<dyn Column<DateTime>>),
+}
+
+impl RuntimeColumn {
+    fn open(segment_reader: &SegmentReader, field_name: &str) -> tantivy::Result<RuntimeColumn> {
+        let schema = segment_reader.schema();
+        let field = schema.get_field(field_name)?;
+        let fast_fields = segment_reader.fast_fields();
+        let column = match schema.get_field_entry(field).field_type() {
+            FieldType::U64(_) => RuntimeColumn::U64(fast_fields.u64(field_name)?),
+            FieldType::I64(_) => RuntimeColumn::I64(fast_fields.i64(field_name)?),
+            FieldType::F64(_) => RuntimeColumn::F64(fast_fields.f64(field_name)?),
+            FieldType::Bool(_) => RuntimeColumn::Bool(fast_fields.bool(field_name)?),
+            FieldType::Date(_) => RuntimeColumn::DateTime(fast_fields.date(field_name)?),
+            _ => {
+                return Err(TantivyError::SchemaError(format!(
+                    "Field `{field_name}` cannot be used in a runtime expression."
+                )))
+            }
+        };
+        Ok(column)
+    }
+
+    fn get_val(&self, doc_id: DocId) -> f64 {
+        match self {
+            RuntimeColumn::U64(column) => column.get_val(doc_id) as f64,
+            RuntimeColumn::I64(column) => column.get_val(doc_id) as f64,
+            RuntimeColumn::F64(column) => column.get_val(doc_id),
+            RuntimeColumn::Bool(column) => {
+                if column.get_val(doc_id) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            // Datetimes are read as milliseconds since the Unix epoch.
+            RuntimeColumn::DateTime(column) => {
+                column.get_val(doc_id).into_timestamp_micros() as f64 / 1_000.0
+            }
+        }
+    }
+}
+
+/// Evaluates a runtime expression over the fast fields of a segment.
+pub struct RuntimeExprEvaluator {
+    expr: RuntimeExpr,
+    columns: Vec<(String, RuntimeColumn)>,
+}
+
+impl RuntimeExprEvaluator {
+    pub fn new(expr: &RuntimeExpr, segment_reader: &SegmentReader) -> tantivy::Result<Self> {
+        let columns = expr
+            .field_names()
+            .into_iter()
+            .map(|field_name| {
+                let column = RuntimeColumn::open(segment_reader, &field_name)?;
+                Ok((field_name, column))
+            })
+            .collect::<tantivy::Result<_>>()?;
+        Ok(RuntimeExprEvaluator {
+            expr: expr.clone(),
+            columns,
+        })
+    }
+
+    pub fn eval(&self, doc_id: DocId) -> f64 {
+        self.expr.eval(&mut |field_name| {
+            self.columns
+                .iter()
+                .find(|(column_field_name, _)| column_field_name == field_name)
+                .map(|(_, column)| column.get_val(doc_id))
+                .unwrap_or(f64::NAN)
+        })
+    }
+}
+
 /// Determine if all docs of a segment always satisfy the requested timestamp range.
 ///
 /// Note:
@@ -0,0 +1,500 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Hybrid search, ranking documents by both their lexical relevance to the query and the
+//! similarity of their vectors to the query vector of a kNN query.
+//!
+//! Two searches are run on the same splits: a lexical search retrieving the documents matching
+//! the query, sorted by BM25 score, and a kNN search retrieving the nearest neighbors among the
+//! documents matching the filters of the request. The two rankings are then fused into a single
+//! one, either with reciprocal rank fusion or with a weighted sum of the normalized scores.
+
+use std::collections::HashMap;
+
+use quickwit_proto::{Hit, SearchRequest, SearchResponse, SortOrder};
+use serde::Deserialize;
+
+use crate::collector::{f32_to_u64, u64_to_f32};
+use crate::{GlobalDocAddress, SearchError};
+
+/// Maximum number of lexical hits fused with the nearest neighbors.
+const MAX_WINDOW_SIZE: u64 = 10_000;
+
+fn default_boost() -> f32 {
+    1.0
+}
+
+fn default_rank_constant() -> u64 {
+    60
+}
+
+/// Method used to fuse the lexical and the vector rankings.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion: a document is scored `boost / (rank_constant + rank)` in each
+    /// ranking it appears in.
+    #[default]
+    #[serde(rename = "rrf")]
+    ReciprocalRankFusion,
+    /// Weighted sum of the scores of each ranking, min-max normalized to `[0, 1]`.
+    WeightedSum,
+}
+
+/// Options of a hybrid search, combining the lexical query and the kNN query of the request.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct HybridRanking {
+    /// Fusion method.
+    #[serde(default)]
+    pub method: FusionMethod,
+    /// Weight of the lexical ranking.
+    #[serde(default = "default_boost")]
+    pub lexical_boost: f32,
+    /// Weight of the vector ranking.
+    #[serde(default = "default_boost")]
+    pub vector_boost: f32,
+    /// Constant dampening the contribution of the top ranks in reciprocal rank fusion.
+    #[serde(default = "default_rank_constant")]
+    pub rank_constant: u64,
+    /// Number of lexical hits fused with the nearest neighbors. Defaults to
+    /// `start_offset + max_hits`.
+    #[serde(default)]
+    pub window_size: Option<u64>,
+}
+
+impl HybridRanking {
+    fn window_size(&self, search_request: &SearchRequest) -> u64 {
+        self.window_size
+            .unwrap_or(search_request.start_offset + search_request.max_hits)
+    }
+
+    /// Returns the search request retrieving the top lexical hits, sorted by BM25 score.
+    pub fn lexical_search_request(&self, search_request: &SearchRequest) -> SearchRequest {
+        SearchRequest {
+            start_offset: 0,
+            max_hits: self.window_size(search_request),
+            sort_by_field: Some("_score".to_string()),
+            sort_order: Some(SortOrder::Desc as i32),
+            knn_query: None,
+            hybrid_ranking: None,
+            ..search_request.clone()
+        }
+    }
+
+    /// Returns the search request retrieving the nearest neighbors among the documents matching
+    /// the filters of the request. The query only contributes to the lexical ranking.
+    pub fn vector_search_request(&self, search_request: &SearchRequest) -> SearchRequest {
+        SearchRequest {
+            query: "*".to_string(),
+            search_fields: Vec::new(),
+            start_offset: 0,
+            max_hits: MAX_WINDOW_SIZE,
+            aggregation_request: None,
+            snippet_fields: Vec::new(),
+            hybrid_ranking: None,
+            ..search_request.clone()
+        }
+    }
+
+    /// Fuses the responses of the lexical and the vector search requests and returns the
+    /// requested page of the fused ranking. Aggregations are computed over the lexical hits.
+    pub fn fuse_search_responses(
+        &self,
+        search_request: &SearchRequest,
+        lexical_response: SearchResponse,
+        vector_response: SearchResponse,
+    ) -> SearchResponse {
+        let mut fused_hits: HashMap<GlobalDocAddress, (Hit, f32)> = HashMap::new();
+        let rankings = [
+            (self.lexical_boost, lexical_response.hits),
+            (self.vector_boost, vector_response.hits),
+        ];
+        for (boost, hits) in rankings {
+            let scores: Vec<f32> = hits.iter().map(hit_score).collect();
+            let min_score = scores.iter().copied().fold(f32::INFINITY, f32::min);
+            let max_score = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+            for (rank, (hit, score)) in hits.into_iter().zip(scores).enumerate() {
+                let Some(partial_hit) = hit.partial_hit.as_ref() else {
+                    continue;
+                };
+                let fused_score = match self.method {
+                    FusionMethod::ReciprocalRankFusion => {
+                        boost / (self.rank_constant + rank as u64 + 1) as f32
+                    }
+                    FusionMethod::WeightedSum if max_score > min_score => {
+                        boost * (score - min_score) / (max_score - min_score)
+                    }
+                    FusionMethod::WeightedSum => boost,
+                };
+                // Lexical hits are fused first so their snippets are kept.
+                fused_hits
+                    .entry(GlobalDocAddress::from_partial_hit(partial_hit))
+                    .or_insert((hit, 0.0))
+                    .1 += fused_score;
+            }
+        }
+        let num_hits = fused_hits.len() as u64;
+        let mut fused_hits: Vec<(GlobalDocAddress, Hit, f32)> = fused_hits
+            .into_iter()
+            .map(|(doc_addr, (hit, score))| (doc_addr, hit, score))
+            .collect();
+        fused_hits.sort_unstable_by(|(left_addr, _, left_score), (right_addr, _, right_score)| {
+            right_score
+                .total_cmp(left_score)
+                .then_with(|| left_addr.cmp(right_addr))
+        });
+        let hits: Vec<Hit> = fused_hits
+            .into_iter()
+            .skip(search_request.start_offset as usize)
+            .take(search_request.max_hits as usize)
+            .map(|(_, mut hit, score)| {
+                if let Some(partial_hit) = hit.partial_hit.as_mut() {
+                    partial_hit.sorting_field_value = f32_to_u64(score);
+                }
+                hit
+            })
+            .collect();
+        let mut errors = lexical_response.errors;
+        errors.extend(vector_response.errors);
+        let mut failed_splits = lexical_response.failed_splits;
+        failed_splits.extend(vector_response.failed_splits);
+        let mut split_metadata = lexical_response.split_metadata;
+        split_metadata.extend(vector_response.split_metadata);
+
+        SearchResponse {
+            num_hits,
+            hits,
+            elapsed_time_micros: lexical_response.elapsed_time_micros
+                + vector_response.elapsed_time_micros,
+            errors,
+            aggregation: lexical_response.aggregation,
+            scroll_id: None,
+            timed_out: lexical_response.timed_out || vector_response.timed_out,
+            failed_splits,
+            split_metadata,
+        }
+    }
+}
+
+fn hit_score(hit: &Hit) -> f32 {
+    hit.partial_hit
+        .as_ref()
+        .map(|partial_hit| u64_to_f32(partial_hit.sorting_field_value))
+        .unwrap_or_default()
+}
+
+/// Parses the JSON serialized hybrid ranking of the user request.
+pub fn parse_hybrid_ranking(
+    hybrid_ranking_json_opt: Option<&str>,
+) -> crate::Result<Option<HybridRanking>> {
+    let Some(hybrid_ranking_json) = hybrid_ranking_json_opt else {
+        return Ok(None);
+    };
+    let hybrid_ranking: HybridRanking =
+        serde_json::from_str(hybrid_ranking_json).map_err(|error| {
+            SearchError::InvalidArgument(format!("Invalid hybrid ranking: {error}"))
+        })?;
+    Ok(Some(hybrid_ranking))
+}
+
+/// Checks that the hybrid ranking is combined with a kNN query and that the requested page fits
+/// in the fused window.
+pub fn validate_hybrid_ranking(
+    search_request: &SearchRequest,
+    hybrid_ranking: &HybridRanking,
+) -> crate::Result<()> {
+    if search_request.knn_query.is_none() {
+        return Err(SearchError::InvalidArgument(
+            "Hybrid ranking requires a kNN query.".to_string(),
+        ));
+    }
+    if search_request.sort_by_field.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Hybrid search hits are sorted by fused score, `sort_by_field` cannot be set."
+                .to_string(),
+        ));
+    }
+    if search_request.search_after.is_some() || search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Hybrid search does not support `search_after` and `scroll`.".to_string(),
+        ));
+    }
+    for (boost_name, boost) in [
+        ("lexical_boost", hybrid_ranking.lexical_boost),
+        ("vector_boost", hybrid_ranking.vector_boost),
+    ] {
+        if !boost.is_finite() || boost < 0.0 {
+            return Err(SearchError::InvalidArgument(format!(
+                "Hybrid ranking `{boost_name}` must be a non-negative number, but got {boost}."
+            )));
+        }
+    }
+    if hybrid_ranking.rank_constant == 0 {
+        return Err(SearchError::InvalidArgument(
+            "Hybrid ranking `rank_constant` must be at least 1.".to_string(),
+        ));
+    }
+    let window_size = hybrid_ranking.window_size(search_request);
+    let page_end = search_request.start_offset + search_request.max_hits;
+    if window_size < page_end || window_size > MAX_WINDOW_SIZE {
+        return Err(SearchError::InvalidArgument(format!(
+            "Hybrid ranking `window_size` must be between `start_offset + max_hits` \
+             ({page_end}) and {MAX_WINDOW_SIZE}, but got {window_size}."
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::PartialHit;
+
+    use super::*;
+
+    fn hit(doc_id: u32, score: f32, snippet_opt: Option<&str>) -> Hit {
+        Hit {
+            json: format!(r#"{{"id": {doc_id}}}"#),
+            partial_hit: Some(PartialHit {
+                sorting_field_value: f32_to_u64(score),
+                split_id: "split".to_string(),
+                segment_ord: 0,
+                doc_id,
+                secondary_sorting_field_values: Vec::new(),
+                collapse_value: None,
+                num_collapsed_hits: None,
+            }),
+            snippet: snippet_opt.map(ToString::to_string),
+        }
+    }
+
+    fn search_response(hits: Vec<Hit>) -> SearchResponse {
+        SearchResponse {
+            num_hits: hits.len() as u64,
+            hits,
+            ..Default::default()
+        }
+    }
+
+    fn hit_doc_ids(search_response: &SearchResponse) -> Vec<u32> {
+        search_response
+            .hits
+            .iter()
+            .map(|hit| hit.partial_hit.as_ref().unwrap().doc_id)
+            .collect()
+    }
+
+    fn search_request_for_test() -> SearchRequest {
+        SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "body:quickwit".to_string(),
+            max_hits: 10,
+            knn_query: Some(
+                r#"{"field": "embedding", "query_vector": [1, 0, 0], "k": 10}"#.to_string(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_hybrid_ranking() {
+        assert!(parse_hybrid_ranking(None).unwrap().is_none());
+        let hybrid_ranking = parse_hybrid_ranking(Some("{}")).unwrap().unwrap();
+        assert_eq!(
+            hybrid_ranking,
+            HybridRanking {
+                method: FusionMethod::ReciprocalRankFusion,
+                lexical_boost: 1.0,
+                vector_boost: 1.0,
+                rank_constant: 60,
+                window_size: None,
+            }
+        );
+        let hybrid_ranking = parse_hybrid_ranking(Some(
+            r#"{"method": "weighted_sum", "lexical_boost": 0.3, "vector_boost": 0.7}"#,
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(hybrid_ranking.method, FusionMethod::WeightedSum);
+        assert_eq!(hybrid_ranking.lexical_boost, 0.3);
+
+        let error = parse_hybrid_ranking(Some(r#"{"method": "max"}"#)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid hybrid ranking: unknown variant `max`"));
+    }
+
+    #[test]
+    fn test_validate_hybrid_ranking() {
+        let search_request = search_request_for_test();
+        let hybrid_ranking = parse_hybrid_ranking(Some("{}")).unwrap().unwrap();
+        validate_hybrid_ranking(&search_request, &hybrid_ranking).unwrap();
+
+        let invalid_search_requests = [
+            (
+                SearchRequest {
+                    knn_query: None,
+                    ..search_request.clone()
+                },
+                "requires a kNN query",
+            ),
+            (
+                SearchRequest {
+                    sort_by_field: Some("timestamp".to_string()),
+                    ..search_request.clone()
+                },
+                "`sort_by_field` cannot be set",
+            ),
+            (
+                SearchRequest {
+                    scroll_ttl_secs: Some(60),
+                    ..search_request.clone()
+                },
+                "does not support `search_after` and `scroll`",
+            ),
+        ];
+        for (invalid_search_request, expected_error) in invalid_search_requests {
+            let error =
+                validate_hybrid_ranking(&invalid_search_request, &hybrid_ranking).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+        let invalid_hybrid_rankings = [
+            (
+                r#"{"lexical_boost": -1}"#,
+                "`lexical_boost` must be a non-negative number",
+            ),
+            (
+                r#"{"rank_constant": 0}"#,
+                "`rank_constant` must be at least 1",
+            ),
+            (
+                r#"{"window_size": 5}"#,
+                "`window_size` must be between `start_offset + max_hits` (10) and 10000, but \
+                 got 5",
+            ),
+        ];
+        for (invalid_hybrid_ranking_json, expected_error) in invalid_hybrid_rankings {
+            let invalid_hybrid_ranking = parse_hybrid_ranking(Some(invalid_hybrid_ranking_json))
+                .unwrap()
+                .unwrap();
+            let error =
+                validate_hybrid_ranking(&search_request, &invalid_hybrid_ranking).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_hybrid_search_requests() {
+        let search_request = SearchRequest {
+            start_offset: 5,
+            snippet_fields: vec!["body".to_string()],
+            hybrid_ranking: Some("{}".to_string()),
+            ..search_request_for_test()
+        };
+        let hybrid_ranking = parse_hybrid_ranking(Some("{}")).unwrap().unwrap();
+
+        let lexical_search_request = hybrid_ranking.lexical_search_request(&search_request);
+        assert_eq!(lexical_search_request.query, "body:quickwit");
+        assert_eq!(lexical_search_request.start_offset, 0);
+        assert_eq!(lexical_search_request.max_hits, 15);
+        assert_eq!(
+            lexical_search_request.sort_by_field.as_deref(),
+            Some("_score")
+        );
+        assert!(lexical_search_request.knn_query.is_none());
+        assert!(lexical_search_request.hybrid_ranking.is_none());
+
+        let vector_search_request = hybrid_ranking.vector_search_request(&search_request);
+        assert_eq!(vector_search_request.query, "*");
+        assert_eq!(vector_search_request.start_offset, 0);
+        assert!(vector_search_request.snippet_fields.is_empty());
+        assert!(vector_search_request.knn_query.is_some());
+        assert!(vector_search_request.hybrid_ranking.is_none());
+    }
+
+    #[test]
+    fn test_fuse_search_responses_rrf() {
+        let search_request = search_request_for_test();
+        let hybrid_ranking = parse_hybrid_ranking(Some(r#"{"rank_constant": 1}"#))
+            .unwrap()
+            .unwrap();
+        let lexical_response = search_response(vec![
+            hit(1, 12.0, Some("lexical snippet")),
+            hit(2, 8.0, None),
+            hit(3, 1.0, None),
+        ]);
+        let vector_response = search_response(vec![hit(3, 0.9, None), hit(4, 0.8, None)]);
+        let fused_response = hybrid_ranking.fuse_search_responses(
+            &search_request,
+            lexical_response,
+            vector_response,
+        );
+        // Doc 3: 1/4 + 1/2, doc 1: 1/2, doc 2: 1/3, doc 4: 1/3.
+        assert_eq!(fused_response.num_hits, 4);
+        assert_eq!(hit_doc_ids(&fused_response), [3, 1, 2, 4]);
+        assert_eq!(hit_score(&fused_response.hits[0]), 0.75);
+        assert_eq!(
+            fused_response.hits[1].snippet.as_deref(),
+            Some("lexical snippet")
+        );
+
+        let search_request = SearchRequest {
+            start_offset: 1,
+            max_hits: 2,
+            ..search_request
+        };
+        let fused_response = hybrid_ranking.fuse_search_responses(
+            &search_request,
+            search_response(vec![hit(1, 12.0, None), hit(2, 8.0, None)]),
+            search_response(vec![hit(2, 0.9, None)]),
+        );
+        assert_eq!(fused_response.num_hits, 2);
+        assert_eq!(hit_doc_ids(&fused_response), [1]);
+    }
+
+    #[test]
+    fn test_fuse_search_responses_weighted_sum() {
+        let search_request = search_request_for_test();
+        let hybrid_ranking = parse_hybrid_ranking(Some(
+            r#"{"method": "weighted_sum", "lexical_boost": 0.25, "vector_boost": 0.75}"#,
+        ))
+        .unwrap()
+        .unwrap();
+        let lexical_response = search_response(vec![
+            hit(1, 12.0, None),
+            hit(2, 7.0, None),
+            hit(3, 2.0, None),
+        ]);
+        let vector_response = search_response(vec![
+            hit(3, 1.0, None),
+            hit(2, 0.5, None),
+            hit(4, 0.0, None),
+        ]);
+        let fused_response = hybrid_ranking.fuse_search_responses(
+            &search_request,
+            lexical_response,
+            vector_response,
+        );
+        // Doc 3: 0.75, doc 2: 0.125 + 0.375, doc 1: 0.25, doc 4: 0.
+        assert_eq!(fused_response.num_hits, 4);
+        assert_eq!(hit_doc_ids(&fused_response), [3, 2, 1, 4]);
+        assert_eq!(hit_score(&fused_response.hits[1]), 0.5);
+    }
+}
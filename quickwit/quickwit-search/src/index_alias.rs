@@ -0,0 +1,256 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Resolution of the index aliases, names pointing at one or several indexes, and of the index ID
+//! patterns into the indexes they target. A search targeting several indexes is fanned out to
+//! each of them and their hits are merged.
+
+use quickwit_common::index_id_matches_pattern;
+use quickwit_metastore::Metastore;
+use quickwit_proto::{SearchRequest, SearchResponse};
+
+use crate::{partial_hit_sorting_key, SearchError};
+
+/// Resolves `index_id` into the IDs of the indexes to search: the indexes matching the pattern if
+/// `index_id` contains the `*` wildcard, the indexes the alias points to if `index_id` is an
+/// alias, or `index_id` itself otherwise.
+pub async fn resolve_index_ids(
+    metastore: &dyn Metastore,
+    index_id: &str,
+) -> crate::Result<Vec<String>> {
+    if index_id.contains('*') {
+        let mut index_ids: Vec<String> = metastore
+            .list_indexes_metadatas()
+            .await?
+            .into_iter()
+            .map(|index_metadata| index_metadata.index_config.index_id)
+            .filter(|candidate_index_id| index_id_matches_pattern(candidate_index_id, index_id))
+            .collect();
+        if index_ids.is_empty() {
+            return Err(SearchError::IndexDoesNotExist {
+                index_id: index_id.to_string(),
+            });
+        }
+        index_ids.sort();
+        return Ok(index_ids);
+    }
+    let index_alias_opt = metastore
+        .list_index_aliases()
+        .await?
+        .into_iter()
+        .find(|index_alias| index_alias.alias_id == index_id);
+    match index_alias_opt {
+        Some(index_alias) => Ok(index_alias.index_ids),
+        None => Ok(vec![index_id.to_string()]),
+    }
+}
+
+/// Resolves `index_id` into the ID of the index to search, failing if `index_id` is an alias or a
+/// pattern targeting several indexes.
+pub async fn resolve_single_index_id(
+    metastore: &dyn Metastore,
+    index_id: &str,
+) -> crate::Result<String> {
+    let mut index_ids = resolve_index_ids(metastore, index_id).await?;
+    if index_ids.len() > 1 {
+        return Err(SearchError::InvalidArgument(format!(
+            "`{index_id}` targets several indexes ({}), which this operation does not support.",
+            index_ids.join(", ")
+        )));
+    }
+    Ok(index_ids.swap_remove(0))
+}
+
+/// Checks that the search request can be fanned out to the several indexes it targets.
+pub(crate) fn validate_multi_index_search(search_request: &SearchRequest) -> crate::Result<()> {
+    if search_request.aggregation_request.is_some() {
+        return Err(SearchError::InvalidArgument(format!(
+            "Searching `{}`, which targets several indexes, does not support aggregations.",
+            search_request.index_id
+        )));
+    }
+    if search_request.scroll_ttl_secs.is_some() {
+        return Err(SearchError::InvalidArgument(format!(
+            "Searching `{}`, which targets several indexes, does not support `scroll`.",
+            search_request.index_id
+        )));
+    }
+    if search_request.view_id.is_some() {
+        return Err(SearchError::InvalidArgument(format!(
+            "Searching `{}`, which targets several indexes, does not support `view_id`.",
+            search_request.index_id
+        )));
+    }
+    Ok(())
+}
+
+/// Builds the search request sent to each of the indexes targeted by the search. Each index returns
+/// the top hits up to the end of the requested page.
+pub(crate) fn index_search_request(
+    search_request: &SearchRequest,
+    index_id: &str,
+) -> SearchRequest {
+    SearchRequest {
+        index_id: index_id.to_string(),
+        start_offset: 0,
+        max_hits: search_request.start_offset + search_request.max_hits,
+        ..search_request.clone()
+    }
+}
+
+/// Merges the search responses of the targeted indexes into the requested page of hits.
+pub(crate) fn merge_index_search_responses(
+    search_request: &SearchRequest,
+    search_responses: Vec<SearchResponse>,
+) -> SearchResponse {
+    let mut merged_search_response = SearchResponse::default();
+    let mut hits = Vec::new();
+
+    for search_response in search_responses {
+        merged_search_response.num_hits += search_response.num_hits;
+        hits.extend(search_response.hits);
+        merged_search_response.elapsed_time_micros = merged_search_response
+            .elapsed_time_micros
+            .max(search_response.elapsed_time_micros);
+        merged_search_response.errors.extend(search_response.errors);
+        merged_search_response.timed_out |= search_response.timed_out;
+        merged_search_response
+            .failed_splits
+            .extend(search_response.failed_splits);
+        merged_search_response
+            .split_metadata
+            .extend(search_response.split_metadata);
+    }
+    hits.sort_by_cached_key(|hit| hit.partial_hit.as_ref().map(partial_hit_sorting_key));
+    merged_search_response.hits = hits
+        .into_iter()
+        .skip(search_request.start_offset as usize)
+        .take(search_request.max_hits as usize)
+        .collect();
+    merged_search_response
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexAlias, IndexMetadata, MockMetastore};
+    use quickwit_proto::{Hit, PartialHit};
+
+    use super::*;
+
+    fn hit(split_id: &str, sorting_field_value: u64) -> Hit {
+        Hit {
+            partial_hit: Some(PartialHit {
+                sorting_field_value,
+                split_id: split_id.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_index_ids() {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_index_aliases().returning(|| {
+            Ok(vec![IndexAlias {
+                alias_id: "logs".to_string(),
+                index_ids: vec!["logs-v1".to_string(), "logs-v2".to_string()],
+            }])
+        });
+        assert_eq!(
+            resolve_index_ids(&metastore, "logs").await.unwrap(),
+            ["logs-v1", "logs-v2"]
+        );
+        assert_eq!(
+            resolve_index_ids(&metastore, "logs-v1").await.unwrap(),
+            ["logs-v1"]
+        );
+        assert_eq!(
+            resolve_single_index_id(&metastore, "logs-v2")
+                .await
+                .unwrap(),
+            "logs-v2"
+        );
+        let error = resolve_single_index_id(&metastore, "logs")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::InvalidArgument(_)));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_index_ids_with_pattern() {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_indexes_metadatas().returning(|| {
+            Ok(vec![
+                IndexMetadata::for_test("logs-000002", "ram:///indexes/logs-000002"),
+                IndexMetadata::for_test("traces", "ram:///indexes/traces"),
+                IndexMetadata::for_test("logs-000001", "ram:///indexes/logs-000001"),
+            ])
+        });
+        assert_eq!(
+            resolve_index_ids(&metastore, "logs-*").await.unwrap(),
+            ["logs-000001", "logs-000002"]
+        );
+        assert_eq!(
+            resolve_single_index_id(&metastore, "tra*").await.unwrap(),
+            "traces"
+        );
+        let error = resolve_index_ids(&metastore, "metrics-*")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, SearchError::IndexDoesNotExist { .. }));
+    }
+
+    #[test]
+    fn test_merge_index_search_responses() {
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            start_offset: 1,
+            max_hits: 2,
+            ..Default::default()
+        };
+        let index_search_request = index_search_request(&search_request, "logs-v1");
+        assert_eq!(index_search_request.index_id, "logs-v1");
+        assert_eq!(index_search_request.start_offset, 0);
+        assert_eq!(index_search_request.max_hits, 3);
+
+        let search_responses = vec![
+            SearchResponse {
+                num_hits: 10,
+                hits: vec![hit("split-1", 4), hit("split-1", 1)],
+                ..Default::default()
+            },
+            SearchResponse {
+                num_hits: 5,
+                hits: vec![hit("split-2", 3), hit("split-2", 2)],
+                errors: vec!["error".to_string()],
+                ..Default::default()
+            },
+        ];
+        let search_response = merge_index_search_responses(&search_request, search_responses);
+        assert_eq!(search_response.num_hits, 15);
+        let sorting_field_values: Vec<u64> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.partial_hit.as_ref().unwrap().sorting_field_value)
+            .collect();
+        assert_eq!(sorting_field_values, [3, 2]);
+        assert_eq!(search_response.errors, ["error"]);
+    }
+}
@@ -0,0 +1,469 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Approximate k-nearest neighbors (kNN) search over `vector` fields.
+//!
+//! Each split holds an IVF index of its vectors, built when the split is packaged. For each split,
+//! the leaf scores the vectors of the lists closest to the query vector, and collects the
+//! candidates that match the query and the filters of the request, sorted by similarity. The
+//! root then merges the top-k candidates of all the splits.
+//!
+//! Splits packaged before the introduction of the vector index are scored exhaustively from the
+//! vector fast field.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use futures::future::try_join_all;
+use quickwit_doc_mapper::{
+    decode_vector, DocMapper, VectorIndexFooter, VectorSimilarity, DEFAULT_NUM_PROBES,
+    VECTOR_INDEX_FILE_NAME, VECTOR_INDEX_FOOTER_LEN_NUM_BYTES,
+};
+use quickwit_proto::{LeafSearchResponse, SearchRequest, SortOrder};
+use quickwit_storage::{BundleStorage, Storage};
+use serde::Deserialize;
+use tantivy::collector::{Collector, SegmentCollector};
+use tantivy::fastfield::BytesFastFieldReader;
+use tantivy::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+use crate::collector::{QuickwitCollector, QuickwitSegmentCollector};
+use crate::SearchError;
+
+/// Maximum number of nearest neighbors of a kNN query.
+const MAX_K: u64 = 10_000;
+
+fn default_num_probes() -> usize {
+    DEFAULT_NUM_PROBES
+}
+
+/// A query retrieving the documents whose vectors are the most similar to a query vector.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KnnQuery {
+    /// Name of the `vector` field.
+    pub field: String,
+    /// Query vector, with as many dimensions as the field.
+    pub query_vector: Vec<f32>,
+    /// Number of nearest neighbors to return.
+    pub k: u64,
+    /// Number of lists of the vector index scored in each split. Probing more lists improves
+    /// the recall at the expense of the latency.
+    #[serde(default = "default_num_probes")]
+    pub num_probes: usize,
+}
+
+impl KnnQuery {
+    /// Returns the search request collecting the nearest neighbors, sorted by decreasing
+    /// similarity. Pagination is applied within the `k` nearest neighbors.
+    pub fn neighbors_search_request(&self, search_request: &SearchRequest) -> SearchRequest {
+        SearchRequest {
+            max_hits: search_request
+                .max_hits
+                .min(self.k.saturating_sub(search_request.start_offset)),
+            sort_by_field: Some("_score".to_string()),
+            sort_order: Some(SortOrder::Desc as i32),
+            ..search_request.clone()
+        }
+    }
+}
+
+/// Parses the JSON serialized kNN query of the user request.
+pub fn parse_knn_query(knn_query_json_opt: Option<&str>) -> crate::Result<Option<KnnQuery>> {
+    let Some(knn_query_json) = knn_query_json_opt else {
+        return Ok(None);
+    };
+    let knn_query: KnnQuery = serde_json::from_str(knn_query_json)
+        .map_err(|error| SearchError::InvalidArgument(format!("Invalid kNN query: {error}")))?;
+    Ok(Some(knn_query))
+}
+
+/// Checks that the kNN query targets a `vector` field with a query vector of the same number of
+/// dimensions.
+pub fn validate_knn_query(
+    doc_mapper: &dyn DocMapper,
+    search_request: &SearchRequest,
+    knn_query: &KnnQuery,
+) -> crate::Result<()> {
+    let Some(vector_field) = doc_mapper.vector_fields().get(&knn_query.field).copied() else {
+        return Err(SearchError::InvalidArgument(format!(
+            "kNN query field `{}` is not a `vector` field.",
+            knn_query.field
+        )));
+    };
+    if knn_query.query_vector.len() != vector_field.dims {
+        return Err(SearchError::InvalidArgument(format!(
+            "kNN query vector has {} dimensions, but field `{}` has {} dimensions.",
+            knn_query.query_vector.len(),
+            knn_query.field,
+            vector_field.dims
+        )));
+    }
+    if knn_query.k == 0 || knn_query.k > MAX_K {
+        return Err(SearchError::InvalidArgument(format!(
+            "kNN query `k` must be between 1 and {MAX_K}, but got {}.",
+            knn_query.k
+        )));
+    }
+    if knn_query.num_probes == 0 {
+        return Err(SearchError::InvalidArgument(
+            "kNN query `num_probes` must be at least 1.".to_string(),
+        ));
+    }
+    if search_request.sort_by_field.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "kNN search hits are sorted by similarity, `sort_by_field` cannot be set.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Candidate neighbors of a split, scored against the query vector.
+pub(crate) enum KnnCandidates {
+    /// Similarity of the candidates of each segment, keyed by doc ID.
+    Approximate(Vec<Arc<HashMap<DocId, Score>>>),
+    /// The split has no vector index: all its vectors are scored.
+    Exhaustive,
+}
+
+/// Reads the vector index of the split and scores the vectors of the lists closest to the query
+/// vector.
+pub(crate) async fn fetch_knn_candidates(
+    bundle_storage: &BundleStorage,
+    segment_readers: &[SegmentReader],
+    knn_query: &KnnQuery,
+) -> anyhow::Result<KnnCandidates> {
+    let no_candidates = || KnnCandidates::Approximate(vec![Arc::default(); segment_readers.len()]);
+    let Some(segment_reader) = segment_readers.first() else {
+        return Ok(no_candidates());
+    };
+    // Splits indexed before the field was added to the doc mapping have no vectors.
+    if segment_reader.schema().get_field(&knn_query.field).is_err() {
+        return Ok(no_candidates());
+    }
+    let vector_index_path = Path::new(VECTOR_INDEX_FILE_NAME);
+    if !bundle_storage.exists(vector_index_path).await? {
+        return Ok(KnnCandidates::Exhaustive);
+    }
+    let file_num_bytes = bundle_storage.file_num_bytes(vector_index_path).await? as usize;
+    let footer_len_start = file_num_bytes
+        .checked_sub(VECTOR_INDEX_FOOTER_LEN_NUM_BYTES)
+        .context("Vector index file is truncated.")?;
+    let footer_len_bytes = bundle_storage
+        .get_slice(vector_index_path, footer_len_start..file_num_bytes)
+        .await?;
+    let footer_num_bytes = VectorIndexFooter::footer_num_bytes(footer_len_bytes.as_slice())?;
+    let footer_start = footer_len_start
+        .checked_sub(footer_num_bytes)
+        .context("Vector index file is truncated.")?;
+    let footer_bytes = bundle_storage
+        .get_slice(vector_index_path, footer_start..footer_len_start)
+        .await?;
+    let footer = VectorIndexFooter::deserialize(footer_bytes.as_slice())?;
+
+    // The vector index only holds the fields having at least one vector in the split.
+    let Some(field_index) = footer.field(&knn_query.field) else {
+        return Ok(no_candidates());
+    };
+    let centroid_bytes = bundle_storage
+        .get_slice(vector_index_path, field_index.centroids_byte_range())
+        .await?;
+    let list_ranges = field_index.probe(
+        centroid_bytes.as_slice(),
+        &knn_query.query_vector,
+        knn_query.num_probes,
+    )?;
+    let lists_bytes = try_join_all(
+        list_ranges
+            .into_iter()
+            .map(|list_range| bundle_storage.get_slice(vector_index_path, list_range)),
+    )
+    .await?;
+
+    let segment_ords: Vec<Option<usize>> = footer
+        .segment_ids
+        .iter()
+        .map(|segment_id| {
+            segment_readers
+                .iter()
+                .position(|segment_reader| segment_reader.segment_id().uuid_string() == *segment_id)
+        })
+        .collect();
+    let mut candidates: Vec<HashMap<DocId, Score>> = vec![HashMap::new(); segment_readers.len()];
+    for list_bytes in lists_bytes {
+        let scored_vectors =
+            field_index.score_list(list_bytes.as_slice(), &knn_query.query_vector)?;
+        for scored_vector in scored_vectors {
+            if let Some(Some(segment_ord)) = segment_ords.get(scored_vector.segment_idx as usize) {
+                candidates[*segment_ord].insert(scored_vector.doc_id, scored_vector.score);
+            }
+        }
+    }
+    Ok(KnnCandidates::Approximate(
+        candidates.into_iter().map(Arc::new).collect(),
+    ))
+}
+
+/// Collector passing the candidate neighbors matching the query to the [`QuickwitCollector`],
+/// scored by their similarity with the query vector.
+pub(crate) struct KnnCollector {
+    inner: QuickwitCollector,
+    field_name: String,
+    query_vector: Arc<Vec<f32>>,
+    similarity: VectorSimilarity,
+    candidates: KnnCandidates,
+}
+
+impl KnnCollector {
+    pub fn new(
+        inner: QuickwitCollector,
+        knn_query: &KnnQuery,
+        similarity: VectorSimilarity,
+        candidates: KnnCandidates,
+    ) -> Self {
+        Self {
+            inner,
+            field_name: knn_query.field.clone(),
+            query_vector: Arc::new(knn_query.query_vector.clone()),
+            similarity,
+            candidates,
+        }
+    }
+}
+
+enum SegmentKnnScorer {
+    Approximate(Arc<HashMap<DocId, Score>>),
+    Exhaustive {
+        vector_reader: BytesFastFieldReader,
+        query_vector: Arc<Vec<f32>>,
+        similarity: VectorSimilarity,
+    },
+}
+
+impl SegmentKnnScorer {
+    fn score(&self, doc_id: DocId) -> Option<Score> {
+        match self {
+            SegmentKnnScorer::Approximate(candidates) => candidates.get(&doc_id).copied(),
+            SegmentKnnScorer::Exhaustive {
+                vector_reader,
+                query_vector,
+                similarity,
+            } => {
+                let vector = decode_vector(vector_reader.get_bytes(doc_id))?;
+                // Documents without a vector have an empty value.
+                if vector.len() != query_vector.len() {
+                    return None;
+                }
+                Some(similarity.score(query_vector, &vector))
+            }
+        }
+    }
+}
+
+pub(crate) struct KnnSegmentCollector {
+    inner: QuickwitSegmentCollector,
+    scorer: SegmentKnnScorer,
+}
+
+impl Collector for KnnCollector {
+    type Child = KnnSegmentCollector;
+    type Fruit = LeafSearchResponse;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let scorer = match &self.candidates {
+            KnnCandidates::Approximate(candidates) => SegmentKnnScorer::Approximate(
+                candidates
+                    .get(segment_ord as usize)
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            KnnCandidates::Exhaustive => SegmentKnnScorer::Exhaustive {
+                vector_reader: segment_reader.fast_fields().bytes(&self.field_name)?,
+                query_vector: self.query_vector.clone(),
+                similarity: self.similarity,
+            },
+        };
+        Ok(KnnSegmentCollector {
+            inner: self.inner.for_segment(segment_ord, segment_reader)?,
+            scorer,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        // The documents are scored by their similarity with the query vector.
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<tantivy::Result<LeafSearchResponse>>,
+    ) -> tantivy::Result<Self::Fruit> {
+        self.inner.merge_fruits(segment_fruits)
+    }
+}
+
+impl SegmentCollector for KnnSegmentCollector {
+    type Fruit = tantivy::Result<LeafSearchResponse>;
+
+    fn collect(&mut self, doc_id: DocId, _score: Score) {
+        if let Some(similarity_score) = self.scorer.score(doc_id) {
+            self.inner.collect(doc_id, similarity_score);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.inner.harvest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::DefaultDocMapper;
+
+    use super::*;
+
+    fn doc_mapper_for_test() -> DefaultDocMapper {
+        serde_json::from_str(
+            r#"{
+                "field_mappings": [
+                    {"name": "body", "type": "text"},
+                    {"name": "embedding", "type": "vector<f32, 3>"}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_knn_query() {
+        assert!(parse_knn_query(None).unwrap().is_none());
+        let knn_query = parse_knn_query(Some(
+            r#"{"field": "embedding", "query_vector": [1, 0.5, 0], "k": 5}"#,
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            knn_query,
+            KnnQuery {
+                field: "embedding".to_string(),
+                query_vector: vec![1.0, 0.5, 0.0],
+                k: 5,
+                num_probes: DEFAULT_NUM_PROBES,
+            }
+        );
+        let error = parse_knn_query(Some(r#"{"field": "embedding", "k": 5}"#)).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Invalid kNN query: missing field `query_vector`"));
+    }
+
+    #[test]
+    fn test_validate_knn_query() {
+        let doc_mapper = doc_mapper_for_test();
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            ..Default::default()
+        };
+        let knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![1.0, 0.5, 0.0],
+            k: 5,
+            num_probes: 4,
+        };
+        validate_knn_query(&doc_mapper, &search_request, &knn_query).unwrap();
+
+        let invalid_knn_queries = [
+            (
+                KnnQuery {
+                    field: "body".to_string(),
+                    ..knn_query.clone()
+                },
+                "kNN query field `body` is not a `vector` field.",
+            ),
+            (
+                KnnQuery {
+                    query_vector: vec![1.0],
+                    ..knn_query.clone()
+                },
+                "kNN query vector has 1 dimensions, but field `embedding` has 3 dimensions.",
+            ),
+            (
+                KnnQuery {
+                    k: 0,
+                    ..knn_query.clone()
+                },
+                "kNN query `k` must be between 1 and 10000, but got 0.",
+            ),
+            (
+                KnnQuery {
+                    num_probes: 0,
+                    ..knn_query.clone()
+                },
+                "kNN query `num_probes` must be at least 1.",
+            ),
+        ];
+        for (invalid_knn_query, expected_error) in invalid_knn_queries {
+            let error =
+                validate_knn_query(&doc_mapper, &search_request, &invalid_knn_query).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("Invalid argument: {expected_error}")
+            );
+        }
+        let sorted_search_request = SearchRequest {
+            sort_by_field: Some("timestamp".to_string()),
+            ..search_request
+        };
+        validate_knn_query(&doc_mapper, &sorted_search_request, &knn_query).unwrap_err();
+    }
+
+    #[test]
+    fn test_neighbors_search_request() {
+        let knn_query = KnnQuery {
+            field: "embedding".to_string(),
+            query_vector: vec![1.0, 0.5, 0.0],
+            k: 15,
+            num_probes: 4,
+        };
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            start_offset: 10,
+            ..Default::default()
+        };
+        let neighbors_search_request = knn_query.neighbors_search_request(&search_request);
+        assert_eq!(neighbors_search_request.max_hits, 5);
+        assert_eq!(neighbors_search_request.start_offset, 10);
+        assert_eq!(
+            neighbors_search_request.sort_by_field.as_deref(),
+            Some("_score")
+        );
+        assert_eq!(
+            neighbors_search_request.sort_order,
+            Some(SortOrder::Desc as i32)
+        );
+    }
+}
@@ -29,7 +29,7 @@ use futures::future::try_join_all;
 use futures::Future;
 use itertools::{Either, Itertools};
 use quickwit_directories::{CachingDirectory, HotDirectory, StorageDirectory};
-use quickwit_doc_mapper::{DocMapper, WarmupInfo, QUICKWIT_TOKENIZER_MANAGER};
+use quickwit_doc_mapper::{DocMapper, TermAutomaton, WarmupInfo, QUICKWIT_TOKENIZER_MANAGER};
 use quickwit_proto::{
     LeafListTermsResponse, LeafSearchResponse, ListTermsRequest, SearchRequest,
     SplitIdAndFooterOffsets, SplitSearchError,
@@ -37,15 +37,25 @@ use quickwit_proto::{
 use quickwit_storage::{
     wrap_storage_with_long_term_cache, BundleStorage, MemorySizedCache, OwnedBytes, Storage,
 };
-use tantivy::collector::Collector;
+use tantivy::collector::{Collector, DocSetCollector};
 use tantivy::directory::FileSlice;
-use tantivy::schema::{Cardinality, Field, FieldType};
-use tantivy::{Index, ReloadPolicy, Searcher, Term};
+use tantivy::schema::{Cardinality, Field, FieldType, IndexRecordOption};
+use tantivy::{
+    DocAddress, DocSet, Index, InvertedIndexReader, ReloadPolicy, Searcher, SegmentOrdinal, Term,
+    TERMINATED,
+};
 use tokio::task::spawn_blocking;
 use tracing::*;
 
 use crate::collector::{make_collector_for_split, make_merge_collector};
+use crate::knn::{fetch_knn_candidates, parse_knn_query, KnnCandidates, KnnCollector};
+use crate::list_terms::{
+    list_terms_search_request, merge_term_doc_counts, truncate_leaf_term_doc_counts,
+    zip_term_doc_counts, TermDocCounts,
+};
+use crate::nested::{find_nested_matches, parse_nested_query};
 use crate::service::SearcherContext;
+use crate::split_searcher_cache::SplitSearcher;
 use crate::SearchError;
 
 async fn get_split_footer_from_cache_or_fetch(
@@ -59,7 +69,7 @@ async fn get_split_footer_from_cache_or_fetch(
             return Ok(footer_data);
         }
     }
-    let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
+    let split_file = PathBuf::from(split_and_footer_offsets.split_file());
     let footer_data_opt = index_storage
         .get_slice(
             &split_file,
@@ -83,29 +93,41 @@ async fn get_split_footer_from_cache_or_fetch(
     Ok(footer_data_opt)
 }
 
-/// Opens a `tantivy::Index` for the given split with several cache layers:
-/// - A split footer cache given by `SearcherContext.split_footer_cache`.
-/// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
-/// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
-pub(crate) async fn open_index_with_caches(
+/// Opens the bundle of the given split, which gives access to the files of the split.
+///
+/// Returns the hotcache along with the bundle storage.
+pub(crate) async fn open_split_bundle(
     searcher_context: &Arc<SearcherContext>,
     index_storage: Arc<dyn Storage>,
     split_and_footer_offsets: &SplitIdAndFooterOffsets,
-    ephemeral_unbounded_cache: bool,
-) -> anyhow::Result<Index> {
-    let split_file = PathBuf::from(format!("{}.split", split_and_footer_offsets.split_id));
+) -> anyhow::Result<(FileSlice, BundleStorage)> {
+    let split_file = PathBuf::from(split_and_footer_offsets.split_file());
     let footer_data = get_split_footer_from_cache_or_fetch(
         index_storage.clone(),
         split_and_footer_offsets,
         &searcher_context.split_footer_cache,
     )
     .await?;
-
-    let (hotcache_bytes, bundle_storage) = BundleStorage::open_from_split_data(
+    let split_bundle = BundleStorage::open_from_split_data(
         index_storage,
         split_file,
         FileSlice::new(Arc::new(footer_data)),
     )?;
+    Ok(split_bundle)
+}
+
+/// Opens a `tantivy::Index` for the given split with several cache layers:
+/// - A split footer cache given by `SearcherContext.split_footer_cache`.
+/// - A fast fields cache given by `SearcherContext.storage_long_term_cache`.
+/// - An ephemeral unbounded cache directory whose lifetime is tied to the returned `Index`.
+pub(crate) async fn open_index_with_caches(
+    searcher_context: &Arc<SearcherContext>,
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+    ephemeral_unbounded_cache: bool,
+) -> anyhow::Result<Index> {
+    let (hotcache_bytes, bundle_storage) =
+        open_split_bundle(searcher_context, index_storage, split_and_footer_offsets).await?;
     let bundle_storage_with_cache = wrap_storage_with_long_term_cache(
         searcher_context.fast_fields_cache.clone(),
         Arc::new(bundle_storage),
@@ -171,6 +193,46 @@ pub(crate) async fn warmup(searcher: &Searcher, warmup_info: &WarmupInfo) -> any
     Ok(())
 }
 
+/// Checks that the term automatons of the query do not match more than `max_term_expansions`
+/// terms in any segment of the split. Their term dictionaries must have been warmed up.
+fn check_term_expansions(
+    searcher: &Searcher,
+    term_automatons: &[(Field, TermAutomaton)],
+    max_term_expansions: usize,
+) -> crate::Result<()> {
+    for (field, term_automaton) in term_automatons {
+        let matches = term_automaton
+            .matcher()
+            .map_err(|error| SearchError::InvalidQuery(error.to_string()))?;
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader
+                .inverted_index(*field)
+                .map_err(|error| SearchError::InternalError(error.to_string()))?;
+            let mut term_stream = inverted_index
+                .terms()
+                .stream()
+                .map_err(|error| SearchError::InternalError(error.to_string()))?;
+            let mut num_term_expansions = 0;
+
+            while term_stream.advance() {
+                if !matches(term_stream.key()) {
+                    continue;
+                }
+                num_term_expansions += 1;
+
+                if num_term_expansions > max_term_expansions {
+                    return Err(SearchError::InvalidQuery(format!(
+                        "Clause `{}:{term_automaton}` matches more than {max_term_expansions} \
+                         terms, which exceeds the `max_term_expansions` limit.",
+                        searcher.schema().get_field_name(*field)
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn warm_up_term_dict_fields(
     searcher: &Searcher,
     term_dict_field_names: &HashSet<String>,
@@ -345,6 +407,45 @@ async fn warm_up_fieldnorms(searcher: &Searcher, requires_scoring: bool) -> anyh
     Ok(())
 }
 
+/// Returns the searcher of the given split, from the split searcher cache if it is there.
+/// Otherwise, the split is opened like in [`open_index_with_caches`], with an unbounded cache
+/// directory kept along with the searcher.
+///
+/// The searcher is expected to be put back in the cache once it has been used, so that the
+/// cache accounts for the data read by the search.
+pub(crate) async fn open_split_searcher(
+    searcher_context: &Arc<SearcherContext>,
+    index_storage: Arc<dyn Storage>,
+    split_and_footer_offsets: &SplitIdAndFooterOffsets,
+) -> anyhow::Result<SplitSearcher> {
+    if let Some(split_searcher) = searcher_context
+        .split_searcher_cache
+        .get(&split_and_footer_offsets.split_id)
+    {
+        return Ok(split_searcher);
+    }
+    let (hotcache_bytes, bundle_storage) =
+        open_split_bundle(searcher_context, index_storage, split_and_footer_offsets).await?;
+    let bundle_storage_with_cache = wrap_storage_with_long_term_cache(
+        searcher_context.fast_fields_cache.clone(),
+        Arc::new(bundle_storage),
+    );
+    let directory = StorageDirectory::new(bundle_storage_with_cache);
+    let caching_directory = CachingDirectory::new_unbounded(Arc::new(directory));
+    let hot_directory =
+        HotDirectory::open(caching_directory.clone(), hotcache_bytes.read_bytes()?)?;
+    let mut index = Index::open(hot_directory)?;
+    index.set_tokenizers(QUICKWIT_TOKENIZER_MANAGER.clone());
+    let reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    Ok(SplitSearcher {
+        searcher: Arc::new(reader.searcher()),
+        caching_directory,
+    })
+}
+
 /// Apply a leaf search on a single split.
 #[instrument(skip(searcher_context, search_request, storage, split, doc_mapper))]
 async fn leaf_search_single_split(
@@ -355,30 +456,89 @@ async fn leaf_search_single_split(
     doc_mapper: Arc<dyn DocMapper>,
 ) -> crate::Result<LeafSearchResponse> {
     let split_id = split.split_id.to_string();
-    let index = open_index_with_caches(searcher_context, storage, &split, true).await?;
-    let split_schema = index.schema();
-    let quickwit_collector =
+    let split_searcher = open_split_searcher(searcher_context, storage.clone(), &split).await?;
+    let searcher = split_searcher.searcher.clone();
+    let split_schema = searcher.schema().clone();
+    let knn_query_opt = parse_knn_query(search_request.knn_query.as_deref())?;
+    let mut quickwit_collector =
         make_collector_for_split(split_id.clone(), doc_mapper.as_ref(), search_request)?;
-    let (query, mut warmup_info) = doc_mapper.query(split_schema, search_request)?;
-    let reader = index
-        .reader_builder()
-        .reload_policy(ReloadPolicy::Manual)
-        .try_into()?;
-    let searcher = reader.searcher();
-
-    let collector_warmup_info = quickwit_collector.warmup_info();
+    let nested_query_opt = parse_nested_query(search_request.nested_query.as_deref())?;
+    let (query, mut warmup_info) = match &nested_query_opt {
+        Some(nested_query) => doc_mapper.query(
+            split_schema.clone(),
+            &nested_query.candidate_search_request(search_request),
+        )?,
+        None => doc_mapper.query(split_schema.clone(), search_request)?,
+    };
+    let mut collector_warmup_info = quickwit_collector.warmup_info();
+    // Splits indexed with an older doc mapping may not have the fields the collector relies on.
+    collector_warmup_info
+        .fast_field_names
+        .retain(|fast_field_name| split_schema.get_field(fast_field_name).is_ok());
+    collector_warmup_info
+        .term_dict_field_names
+        .retain(|term_dict_field_name| split_schema.get_field(term_dict_field_name).is_ok());
     warmup_info.merge(collector_warmup_info);
 
+    let knn_candidates_opt = match &knn_query_opt {
+        Some(knn_query) => {
+            let (_, split_bundle) = open_split_bundle(searcher_context, storage, &split).await?;
+            let candidates =
+                fetch_knn_candidates(&split_bundle, searcher.segment_readers(), knn_query).await?;
+            if let KnnCandidates::Exhaustive = candidates {
+                warmup_info.fast_field_names.insert(knn_query.field.clone());
+            }
+            let similarity = doc_mapper
+                .vector_fields()
+                .get(&knn_query.field)
+                .map(|vector_field| vector_field.similarity)
+                .unwrap_or_default();
+            Some((similarity, candidates))
+        }
+        None => None,
+    };
+
     warmup(&searcher, &warmup_info).await?;
+    let term_automatons = std::mem::take(&mut warmup_info.term_automatons);
+    // The index may lower the limit of the searcher, but not raise it.
+    let max_term_expansions = doc_mapper.max_term_expansions().map_or(
+        searcher_context.searcher_config.max_term_expansions,
+        |max_term_expansions| {
+            max_term_expansions.min(searcher_context.searcher_config.max_term_expansions)
+        },
+    );
+    if let Some(nested_query) = &nested_query_opt {
+        let nested_matches = find_nested_matches(
+            searcher.clone(),
+            query.box_clone(),
+            doc_mapper.clone(),
+            search_request,
+            nested_query,
+        )
+        .await?;
+        quickwit_collector.set_nested_matches(nested_matches);
+    }
     let span = info_span!( "tantivy_search", split_id = %split.split_id);
     let leaf_search_response = crate::run_cpu_intensive(move || {
         let _span_guard = span.enter();
-        searcher.search(&query, &quickwit_collector)
+        check_term_expansions(&searcher, &term_automatons, max_term_expansions)?;
+        let leaf_search_response = match (knn_query_opt, knn_candidates_opt) {
+            (Some(knn_query), Some((similarity, candidates))) => {
+                let knn_collector =
+                    KnnCollector::new(quickwit_collector, &knn_query, similarity, candidates);
+                searcher.search(&query, &knn_collector)?
+            }
+            _ => searcher.search(&query, &quickwit_collector)?,
+        };
+        crate::Result::Ok(leaf_search_response)
     })
     .await
     .map_err(|_| {
         crate::SearchError::InternalError(format!("Leaf search panicked. split={split_id}"))
     })??;
+    searcher_context
+        .split_searcher_cache
+        .put(&split_id, split_searcher);
     Ok(leaf_search_response)
 }
 
@@ -395,13 +555,25 @@ pub async fn leaf_search(
     splits: &[SplitIdAndFooterOffsets],
     doc_mapper: Arc<dyn DocMapper>,
 ) -> Result<LeafSearchResponse, SearchError> {
+    let request_fingerprint_opt = searcher_context
+        .leaf_search_cache
+        .request_fingerprint(request, &*doc_mapper);
     let leaf_search_single_split_futures: Vec<_> = splits
         .iter()
         .map(|split| {
             let doc_mapper_clone = doc_mapper.clone();
             let index_storage_clone = index_storage.clone();
             let searcher_context_clone = searcher_context.clone();
+            let request_fingerprint_opt = request_fingerprint_opt.clone();
             async move {
+                if let Some(request_fingerprint) = &request_fingerprint_opt {
+                    if let Some(cached_response) = searcher_context_clone
+                        .leaf_search_cache
+                        .get(&split.split_id, request_fingerprint)
+                    {
+                        return Ok(cached_response);
+                    }
+                }
                 let _leaf_split_search_permit = searcher_context_clone.leaf_search_split_semaphore
                     .acquire()
                     .await
@@ -419,6 +591,15 @@ pub async fn leaf_search(
                 )
                 .await;
                 timer.observe_duration();
+                if let (Some(request_fingerprint), Ok(leaf_search_response)) =
+                    (request_fingerprint_opt, &leaf_search_single_split_res)
+                {
+                    searcher_context_clone.leaf_search_cache.put(
+                        &split.split_id,
+                        request_fingerprint,
+                        leaf_search_response,
+                    );
+                }
                 leaf_search_single_split_res.map_err(|err| (split.split_id.clone(), err))
             }
         })
@@ -458,21 +639,42 @@ pub async fn leaf_search(
     Ok(merged_search_response)
 }
 
+/// Counts the documents of the term that are in `matching_docs`.
+fn count_matching_docs(
+    inverted_index: &InvertedIndexReader,
+    term: &Term,
+    segment_ord: SegmentOrdinal,
+    matching_docs: &HashSet<DocAddress>,
+) -> crate::Result<u64> {
+    let Some(mut postings) = inverted_index
+        .read_postings(term, IndexRecordOption::Basic)
+        .with_context(|| "Failed to read postings")?
+    else {
+        return Ok(0);
+    };
+    let mut num_matching_docs = 0;
+    let mut doc = postings.doc();
+    while doc != TERMINATED {
+        if matching_docs.contains(&DocAddress::new(segment_ord, doc)) {
+            num_matching_docs += 1;
+        }
+        doc = postings.advance();
+    }
+    Ok(num_matching_docs)
+}
+
 /// Apply a leaf list terms on a single split.
-#[instrument(skip(searcher_context, search_request, storage, split))]
+#[instrument(skip(searcher_context, search_request, storage, split, doc_mapper_opt))]
 async fn leaf_list_terms_single_split(
     searcher_context: &Arc<SearcherContext>,
     search_request: &ListTermsRequest,
     storage: Arc<dyn Storage>,
     split: SplitIdAndFooterOffsets,
+    doc_mapper_opt: Option<Arc<dyn DocMapper>>,
 ) -> crate::Result<LeafListTermsResponse> {
-    let index = open_index_with_caches(searcher_context, storage, &split, true).await?;
-    let split_schema = index.schema();
-    let reader = index
-        .reader_builder()
-        .reload_policy(ReloadPolicy::Manual)
-        .try_into()?;
-    let searcher = reader.searcher();
+    let split_searcher = open_split_searcher(searcher_context, storage, &split).await?;
+    let searcher = split_searcher.searcher.clone();
+    let split_schema = searcher.schema().clone();
 
     let field = split_schema
         .get_field(&search_request.field)
@@ -493,8 +695,41 @@ async fn leaf_list_terms_single_split(
         .as_ref()
         .map(|data| term_from_data(field, field_type, data));
 
+    // The documents matching the query, whose postings are then intersected with the postings of
+    // the terms.
+    let matching_docs_opt: Option<HashSet<DocAddress>> = match &search_request.query {
+        Some(query) => {
+            let doc_mapper = doc_mapper_opt.ok_or_else(|| {
+                SearchError::InternalError("No doc mapper to parse the query.".to_string())
+            })?;
+            let (query, mut warmup_info) = doc_mapper.query(
+                split_schema.clone(),
+                &list_terms_search_request(search_request, query),
+            )?;
+            warmup_info
+                .posting_field_names
+                .insert(search_request.field.clone());
+            warmup(&searcher, &warmup_info).await?;
+            let query_searcher = searcher.clone();
+            let matching_docs =
+                crate::run_cpu_intensive(move || query_searcher.search(&query, &DocSetCollector))
+                    .await
+                    .map_err(|_| {
+                        SearchError::InternalError("List terms query search panicked.".to_string())
+                    })??;
+            Some(matching_docs)
+        }
+        None => None,
+    };
+    // Unless the first terms of the range are returned, the whole range is scanned.
+    let scan_limit = if search_request.sort_by_doc_count || matching_docs_opt.is_some() {
+        None
+    } else {
+        search_request.max_hits
+    };
+
     let mut segment_results = Vec::new();
-    for segment_reader in searcher.segment_readers() {
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
         let inverted_index = segment_reader.inverted_index(field)?.clone();
         let dict = inverted_index.terms();
         dict.file_slice_for_range(
@@ -510,14 +745,14 @@ async fn leaf_list_terms_single_split(
                     .map(Bound::Excluded)
                     .unwrap_or(Bound::Unbounded),
             ),
-            search_request.max_hits,
+            scan_limit,
         )
         .read_bytes_async()
         .await
         .with_context(|| "Failed to load sstable range")?;
 
         let mut range = dict.range();
-        if let Some(limit) = search_request.max_hits {
+        if let Some(limit) = scan_limit {
             range = range.limit(limit);
         }
         if let Some(start_term) = &start_term {
@@ -529,26 +764,44 @@ async fn leaf_list_terms_single_split(
         let mut stream = range
             .into_stream()
             .with_context(|| "Failed to create stream over sstable")?;
-        let mut segment_result: Vec<Vec<u8>> =
-            Vec::with_capacity(search_request.max_hits.unwrap_or(0) as usize);
+        let mut segment_result: TermDocCounts =
+            Vec::with_capacity(scan_limit.unwrap_or(0) as usize);
         while stream.advance() {
-            segment_result.push(term_to_data(field, field_type, stream.key()));
+            let doc_count = match &matching_docs_opt {
+                Some(matching_docs) => count_matching_docs(
+                    &inverted_index,
+                    &term_from_data(field, field_type, stream.key()),
+                    segment_ord as SegmentOrdinal,
+                    matching_docs,
+                )?,
+                None => stream.value().doc_freq as u64,
+            };
+            if doc_count == 0 {
+                continue;
+            }
+            segment_result.push((term_to_data(field, field_type, stream.key()), doc_count));
+            if !search_request.sort_by_doc_count
+                && search_request.max_hits == Some(segment_result.len() as u64)
+            {
+                break;
+            }
         }
         segment_results.push(segment_result);
     }
 
-    let merged_iter = segment_results.into_iter().kmerge().dedup();
-    let merged_results: Vec<Vec<u8>> = if let Some(limit) = search_request.max_hits {
-        merged_iter.take(limit as usize).collect()
-    } else {
-        merged_iter.collect()
-    };
+    let mut merged_results = merge_term_doc_counts(segment_results);
+    truncate_leaf_term_doc_counts(&mut merged_results, search_request);
+    let (terms, doc_counts): (Vec<Vec<u8>>, Vec<u64>) = merged_results.into_iter().unzip();
+    searcher_context
+        .split_searcher_cache
+        .put(&split.split_id, split_searcher);
 
     Ok(LeafListTermsResponse {
-        num_hits: merged_results.len() as u64,
-        terms: merged_results,
+        num_hits: terms.len() as u64,
+        terms,
         num_attempted_splits: 1,
         failed_splits: Vec::new(),
+        doc_counts,
     })
 }
 
@@ -567,17 +820,21 @@ fn term_to_data(field: Field, field_type: &FieldType, field_value: &[u8]) -> Vec
 }
 
 /// `leaf` step of list terms.
+///
+/// The doc mapper is required if the request has a query.
 pub async fn leaf_list_terms(
     searcher_context: Arc<SearcherContext>,
     request: &ListTermsRequest,
     index_storage: Arc<dyn Storage>,
     splits: &[SplitIdAndFooterOffsets],
+    doc_mapper_opt: Option<Arc<dyn DocMapper>>,
 ) -> Result<LeafListTermsResponse, SearchError> {
     let leaf_search_single_split_futures: Vec<_> = splits
         .iter()
         .map(|split| {
             let index_storage_clone = index_storage.clone();
             let searcher_context_clone = searcher_context.clone();
+            let doc_mapper_opt_clone = doc_mapper_opt.clone();
             async move {
                 let _leaf_split_search_permit = searcher_context_clone.leaf_search_split_semaphore
                     .acquire()
@@ -593,6 +850,7 @@ pub async fn leaf_list_terms(
                     request,
                     index_storage_clone,
                     split.clone(),
+                    doc_mapper_opt_clone,
                 )
                 .await;
                 timer.observe_duration();
@@ -611,16 +869,13 @@ pub async fn leaf_list_terms(
                 Err(err) => Either::Right(err),
             });
 
-    let merged_iter = split_search_responses
-        .into_iter()
-        .map(|leaf_search_response| leaf_search_response.terms)
-        .kmerge()
-        .dedup();
-    let terms: Vec<Vec<u8>> = if let Some(limit) = request.max_hits {
-        merged_iter.take(limit as usize).collect()
-    } else {
-        merged_iter.collect()
-    };
+    let mut merged_results = merge_term_doc_counts(split_search_responses.into_iter().map(
+        |leaf_search_response| {
+            zip_term_doc_counts(leaf_search_response.terms, leaf_search_response.doc_counts)
+        },
+    ));
+    truncate_leaf_term_doc_counts(&mut merged_results, request);
+    let (terms, doc_counts): (Vec<Vec<u8>>, Vec<u64>) = merged_results.into_iter().unzip();
 
     let failed_splits = errors
         .into_iter()
@@ -635,6 +890,7 @@ pub async fn leaf_list_terms(
         terms,
         num_attempted_splits: splits.len() as u64,
         failed_splits,
+        doc_counts,
     };
 
     Ok(merged_search_response)
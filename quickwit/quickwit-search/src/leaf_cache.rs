@@ -0,0 +1,259 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::{LeafSearchResponse, SearchRequest};
+use quickwit_storage::{MemorySizedCache, OwnedBytes};
+use tracing::warn;
+
+/// A cache of the leaf search responses of single splits.
+///
+/// Splits are immutable, so the response of a split to a given request never changes, and
+/// dashboards refreshing the same queries over and over can skip scanning the splits they
+/// have already searched. The responses are cached as JSON within a size budget, and evicted
+/// in LRU order.
+pub struct LeafSearchCache {
+    content_opt: Option<MemorySizedCache<LeafSearchCacheKey>>,
+}
+
+/// The key of a cached split response: the ID of the split, and the canonicalized request
+/// along with the fingerprint of the doc mapper it was searched with.
+#[derive(Debug, Hash, Eq, PartialEq)]
+struct LeafSearchCacheKey {
+    split_id: String,
+    request_fingerprint: RequestFingerprint,
+}
+
+/// The parts of a leaf search request that affect the response of a split.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub(crate) struct RequestFingerprint {
+    /// The JSON request, without the parameters that only affect the root.
+    request_json: String,
+    /// The hash of the JSON doc mapper, which may change when the index is updated.
+    doc_mapper_hash: u64,
+}
+
+impl RequestFingerprint {
+    /// Canonicalizes the request by clearing the parameters handled by the root only, such as
+    /// the snippets, which are generated when the documents are fetched.
+    pub fn new(search_request: &SearchRequest, doc_mapper: &dyn DocMapper) -> Option<Self> {
+        let canonical_request = SearchRequest {
+            snippet_fields: Vec::new(),
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            scroll_ttl_secs: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            ..search_request.clone()
+        };
+        let request_json = serde_json::to_string(&canonical_request).ok()?;
+        let doc_mapper_json = serde_json::to_string(doc_mapper).ok()?;
+        let mut hasher = DefaultHasher::new();
+        doc_mapper_json.hash(&mut hasher);
+        Some(RequestFingerprint {
+            request_json,
+            doc_mapper_hash: hasher.finish(),
+        })
+    }
+}
+
+impl LeafSearchCache {
+    /// Creates a cache of the given capacity. A capacity of zero disables the cache.
+    pub fn with_capacity_in_bytes(capacity_in_bytes: usize) -> Self {
+        let content_opt = (capacity_in_bytes > 0).then(|| {
+            MemorySizedCache::with_capacity_in_bytes(
+                capacity_in_bytes,
+                &quickwit_storage::STORAGE_METRICS.leaf_search_cache,
+            )
+        });
+        LeafSearchCache { content_opt }
+    }
+
+    /// Changes the capacity of the cache. A cache disabled at startup remains disabled.
+    pub fn set_capacity_in_bytes(&self, capacity_in_bytes: usize) {
+        if let Some(content) = &self.content_opt {
+            content.set_capacity_in_bytes(capacity_in_bytes);
+        }
+    }
+
+    /// Returns the fingerprint of the request, or `None` if the cache is disabled.
+    pub(crate) fn request_fingerprint(
+        &self,
+        search_request: &SearchRequest,
+        doc_mapper: &dyn DocMapper,
+    ) -> Option<RequestFingerprint> {
+        if self.content_opt.is_none() {
+            return None;
+        }
+        RequestFingerprint::new(search_request, doc_mapper)
+    }
+
+    /// Returns the cached response of the split to the request, if any.
+    pub(crate) fn get(
+        &self,
+        split_id: &str,
+        request_fingerprint: &RequestFingerprint,
+    ) -> Option<LeafSearchResponse> {
+        let content = self.content_opt.as_ref()?;
+        let cache_key = LeafSearchCacheKey {
+            split_id: split_id.to_string(),
+            request_fingerprint: request_fingerprint.clone(),
+        };
+        let response_bytes = content.get(&cache_key)?;
+        match serde_json::from_slice(response_bytes.as_slice()) {
+            Ok(leaf_search_response) => Some(leaf_search_response),
+            Err(error) => {
+                warn!(split_id = %split_id, error = ?error, "Failed to deserialize cached leaf search response.");
+                None
+            }
+        }
+    }
+
+    /// Caches the response of the split to the request. Responses with failed splits are not
+    /// cached.
+    pub(crate) fn put(
+        &self,
+        split_id: &str,
+        request_fingerprint: RequestFingerprint,
+        leaf_search_response: &LeafSearchResponse,
+    ) {
+        let Some(content) = self.content_opt.as_ref() else {
+            return;
+        };
+        if !leaf_search_response.failed_splits.is_empty() {
+            return;
+        }
+        let Ok(response_bytes) = serde_json::to_vec(leaf_search_response) else {
+            return;
+        };
+        let cache_key = LeafSearchCacheKey {
+            split_id: split_id.to_string(),
+            request_fingerprint,
+        };
+        content.put(cache_key, OwnedBytes::new(response_bytes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::DefaultDocMapper;
+    use quickwit_proto::PartialHit;
+
+    use super::*;
+
+    fn doc_mapper_for_test() -> DefaultDocMapper {
+        serde_json::from_str(r#"{"field_mappings": [{"name": "body", "type": "text"}]}"#).unwrap()
+    }
+
+    fn leaf_search_response(split_id: &str) -> LeafSearchResponse {
+        LeafSearchResponse {
+            num_hits: 1,
+            partial_hits: vec![PartialHit {
+                sorting_field_value: 1,
+                split_id: split_id.to_string(),
+                segment_ord: 0,
+                doc_id: 2,
+                secondary_sorting_field_values: Vec::new(),
+                collapse_value: None,
+                num_collapsed_hits: None,
+            }],
+            num_attempted_splits: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_leaf_search_cache() {
+        let doc_mapper = doc_mapper_for_test();
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "body:test".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let cache = LeafSearchCache::with_capacity_in_bytes(1_000_000);
+        let request_fingerprint = cache
+            .request_fingerprint(&search_request, &doc_mapper)
+            .unwrap();
+        assert!(cache.get("split_1", &request_fingerprint).is_none());
+
+        cache.put(
+            "split_1",
+            request_fingerprint.clone(),
+            &leaf_search_response("split_1"),
+        );
+        assert_eq!(
+            cache.get("split_1", &request_fingerprint),
+            Some(leaf_search_response("split_1"))
+        );
+        assert!(cache.get("split_2", &request_fingerprint).is_none());
+
+        // The snippets are generated by the root, so they don't change the response of a split.
+        let snippet_search_request = SearchRequest {
+            snippet_fields: vec!["body".to_string()],
+            ..search_request.clone()
+        };
+        let snippet_request_fingerprint = cache
+            .request_fingerprint(&snippet_search_request, &doc_mapper)
+            .unwrap();
+        assert!(cache.get("split_1", &snippet_request_fingerprint).is_some());
+
+        let other_search_request = SearchRequest {
+            max_hits: 20,
+            ..search_request
+        };
+        let other_request_fingerprint = cache
+            .request_fingerprint(&other_search_request, &doc_mapper)
+            .unwrap();
+        assert!(cache.get("split_1", &other_request_fingerprint).is_none());
+    }
+
+    #[test]
+    fn test_leaf_search_cache_skips_failed_splits() {
+        let doc_mapper = doc_mapper_for_test();
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "body:test".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let cache = LeafSearchCache::with_capacity_in_bytes(1_000_000);
+        let request_fingerprint = cache
+            .request_fingerprint(&search_request, &doc_mapper)
+            .unwrap();
+        let mut response = leaf_search_response("split_1");
+        response.failed_splits.push(Default::default());
+        cache.put("split_1", request_fingerprint.clone(), &response);
+        assert!(cache.get("split_1", &request_fingerprint).is_none());
+    }
+
+    #[test]
+    fn test_leaf_search_cache_disabled() {
+        let doc_mapper = doc_mapper_for_test();
+        let cache = LeafSearchCache::with_capacity_in_bytes(0);
+        assert!(cache
+            .request_fingerprint(&SearchRequest::default(), &doc_mapper)
+            .is_none());
+    }
+}
@@ -22,61 +22,109 @@
 #![allow(clippy::bool_assert_comparison)]
 #![deny(clippy::disallowed_methods)]
 
+mod admission;
+mod aggregations;
+mod async_search;
 mod client;
 mod cluster_client;
+mod collapse;
 mod collector;
+mod count;
+mod cross_cluster;
 mod error;
+mod explain;
 mod fetch_docs;
+mod field_mask;
 mod filters;
 mod find_trace_ids_collector;
+mod hybrid;
+mod index_alias;
+mod knn;
 mod leaf;
+mod leaf_cache;
+mod list_terms;
+mod nested;
+mod query_log;
 mod retry;
 mod root;
+mod scroll;
 mod search_job_placer;
 mod search_response_rest;
 mod search_stream;
+mod search_view;
 mod service;
+mod sort;
+mod split_searcher_cache;
+mod split_warmup;
+mod sql;
 mod thread_pool;
 
 mod metrics;
 #[cfg(test)]
 mod tests;
 
+pub use aggregations::{
+    BucketAggregation, CardinalityAggregation, CompositeAggregation, DateHistogramAggregation,
+    HistogramAggregation, Order, PercentilesAggregation, PipelineAggregation,
+    QuickwitBucketAggregation, QuickwitMetricAggregation, SearchAggregations, TermsAggregation,
+    TermsOrder,
+};
 pub use collector::QuickwitAggregations;
-use metrics::SEARCH_METRICS;
-use quickwit_doc_mapper::DocMapper;
-use root::validate_request;
-use service::SearcherContext;
+pub use metrics::SEARCH_METRICS;
+use quickwit_doc_mapper::{resolve_field_aliases, DocMapper};
+use root::{hits_split_metadata, validate_request};
 use tantivy::schema::NamedFieldDocument;
 
 /// Refer to this as `crate::Result<T>`.
 pub type Result<T> = std::result::Result<T, SearchError>;
 
 use std::cmp::Reverse;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Context;
 pub use find_trace_ids_collector::FindTraceIdsCollector;
 use itertools::Itertools;
+use quickwit_common::split_file;
+use quickwit_common::uri::Uri;
 use quickwit_config::{build_doc_mapper, QuickwitConfig, SearcherConfig};
+use quickwit_directories::read_split_footer;
+use quickwit_doc_mapper::bloom_filter::extract_bloom_filter_from_query;
+use quickwit_doc_mapper::range_pruning::extract_range_filter_from_query;
 use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
 use quickwit_metastore::{ListSplitsQuery, Metastore, SplitMetadata, SplitState};
 use quickwit_proto::{Hit, PartialHit, SearchRequest, SearchResponse, SplitIdAndFooterOffsets};
-use quickwit_storage::StorageUriResolver;
-use tantivy::aggregation::agg_result::AggregationResults;
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use quickwit_storage::{Storage, StorageUriResolver};
 use tantivy::DocAddress;
 
+use crate::aggregations::IntermediateSearchAggregationResults;
+pub use crate::async_search::AsyncSearchResponse;
 pub use crate::client::{create_search_service_client, SearchServiceClient};
 pub use crate::cluster_client::ClusterClient;
+use crate::collapse::{collapse_count_request, set_num_collapsed_hits};
 pub use crate::error::{parse_grpc_error, SearchError};
+pub use crate::explain::{PrunedSplit, PruningReason, SearchExplanation, SplitExplanation};
 use crate::fetch_docs::fetch_docs;
+use crate::field_mask::apply_field_masks;
+pub use crate::field_mask::{add_caller_field_masks, with_caller_field_masks};
+use crate::hybrid::{parse_hybrid_ranking, validate_hybrid_ranking};
+pub use crate::index_alias::{resolve_index_ids, resolve_single_index_id};
+use crate::knn::{parse_knn_query, KnnQuery};
 use crate::leaf::{leaf_list_terms, leaf_search};
+pub use crate::leaf_cache::LeafSearchCache;
+pub use crate::list_terms::{prefix_key_range, term_to_json};
+pub use crate::query_log::{with_search_caller, QueryLogEvent, QueryLogger, SearchCaller};
 pub use crate::root::{jobs_to_leaf_request, root_list_terms, root_search, SearchJob};
 pub use crate::search_job_placer::SearchJobPlacer;
-pub use crate::search_response_rest::SearchResponseRest;
+pub use crate::search_response_rest::{
+    decode_search_after_cursor, encode_search_after_cursor, SearchResponseRest,
+};
 pub use crate::search_stream::root_search_stream;
-pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl};
+pub use crate::search_view::SearchViewResponse;
+pub use crate::service::{MockSearchService, SearchService, SearchServiceImpl, SearcherContext};
+pub use crate::split_searcher_cache::SplitSearcherCache;
+use crate::sort::apply_default_sort;
+pub use crate::sql::{sql_search, SqlResponse};
 use crate::thread_pool::run_cpu_intensive;
 
 /// GlobalDocAddress serves as a hit address.
@@ -98,9 +146,12 @@ impl GlobalDocAddress {
     }
 }
 
-fn partial_hit_sorting_key(partial_hit: &PartialHit) -> (Reverse<u64>, GlobalDocAddress) {
+fn partial_hit_sorting_key(
+    partial_hit: &PartialHit,
+) -> (Reverse<u64>, Reverse<Vec<u64>>, GlobalDocAddress) {
     (
         Reverse(partial_hit.sorting_field_value),
+        Reverse(partial_hit.secondary_sorting_field_values.clone()),
         GlobalDocAddress::from_partial_hit(partial_hit),
     )
 }
@@ -110,6 +161,7 @@ fn extract_split_and_footer_offsets(split_metadata: &SplitMetadata) -> SplitIdAn
         split_id: split_metadata.split_id.clone(),
         split_footer_start: split_metadata.footer_offsets.start,
         split_footer_end: split_metadata.footer_offsets.end,
+        split_file: split_metadata.split_file(),
     }
 }
 
@@ -118,6 +170,17 @@ async fn list_relevant_splits(
     search_request: &SearchRequest,
     metastore: &dyn Metastore,
 ) -> crate::Result<Vec<SplitMetadata>> {
+    let (split_metadatas, _num_pruned_splits) =
+        list_relevant_splits_and_count_pruned(search_request, metastore).await?;
+    Ok(split_metadatas)
+}
+
+/// Same as [`list_relevant_splits`], but also returns the number of splits returned by the
+/// metastore that were pruned with the field ranges and the bloom filters of the splits.
+async fn list_relevant_splits_and_count_pruned(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+) -> crate::Result<(Vec<SplitMetadata>, usize)> {
     let mut query = ListSplitsQuery::for_index(&search_request.index_id)
         .with_split_state(SplitState::Published);
 
@@ -134,10 +197,45 @@ async fn list_relevant_splits(
     }
 
     let split_metas = metastore.list_splits(query).await?;
-    Ok(split_metas
+    let num_listed_splits = split_metas.len();
+    let split_metadatas: Vec<SplitMetadata> = split_metas
         .into_iter()
         .map(|metadata| metadata.split_metadata)
-        .collect::<Vec<_>>())
+        .collect();
+    let split_metadatas = prune_splits_by_query(search_request, split_metadatas)?;
+    let num_pruned_splits = num_listed_splits - split_metadatas.len();
+    Ok((split_metadatas, num_pruned_splits))
+}
+
+/// Discards the splits that cannot hold any document matching the query of a search request,
+/// based on their field ranges and bloom filters.
+fn prune_splits_by_query(
+    search_request: &SearchRequest,
+    split_metadatas: Vec<SplitMetadata>,
+) -> crate::Result<Vec<SplitMetadata>> {
+    let range_filter_opt = extract_range_filter_from_query(&search_request.query)?;
+    let bloom_filter_opt = extract_bloom_filter_from_query(&search_request.query)?;
+
+    let split_metadatas = split_metadatas
+        .into_iter()
+        .filter(|split_metadata| {
+            // Splits that cannot hold any document matching the range clauses of the query are
+            // discarded.
+            range_filter_opt
+                .as_ref()
+                .map(|range_filter| range_filter.evaluate(&split_metadata.field_ranges))
+                .unwrap_or(true)
+        })
+        .filter(|split_metadata| {
+            // Splits whose bloom filters rule out the exact terms required by the query are
+            // discarded as well.
+            bloom_filter_opt
+                .as_ref()
+                .map(|bloom_filter| bloom_filter.evaluate(&split_metadata.bloom_filters))
+                .unwrap_or(true)
+        })
+        .collect();
+    Ok(split_metadatas)
 }
 
 /// Converts a Tantivy `NamedFieldDocument` into a json string using the
@@ -162,6 +260,31 @@ pub async fn single_node_search(
     search_request: &SearchRequest,
     metastore: &dyn Metastore,
     storage_resolver: StorageUriResolver,
+) -> crate::Result<SearchResponse> {
+    let hybrid_ranking_opt = parse_hybrid_ranking(search_request.hybrid_ranking.as_deref())?;
+    let Some(hybrid_ranking) = hybrid_ranking_opt else {
+        return single_node_search_inner(search_request, metastore, storage_resolver).await;
+    };
+    validate_hybrid_ranking(search_request, &hybrid_ranking)?;
+    let lexical_response = single_node_search_inner(
+        &hybrid_ranking.lexical_search_request(search_request),
+        metastore,
+        storage_resolver.clone(),
+    )
+    .await?;
+    let vector_response = single_node_search_inner(
+        &hybrid_ranking.vector_search_request(search_request),
+        metastore,
+        storage_resolver,
+    )
+    .await?;
+    Ok(hybrid_ranking.fuse_search_responses(search_request, lexical_response, vector_response))
+}
+
+async fn single_node_search_inner(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    storage_resolver: StorageUriResolver,
 ) -> crate::Result<SearchResponse> {
     let start_instant = tokio::time::Instant::now();
     let index_config = metastore
@@ -177,29 +300,156 @@ pub async fn single_node_search(
     //
     // TODO see if it can be improved.
     let index_storage = storage_resolver.resolve(&index_config.index_uri)?;
-    let metas = list_relevant_splits(search_request, metastore).await?;
-    let split_metadata: Vec<SplitIdAndFooterOffsets> =
-        metas.iter().map(extract_split_and_footer_offsets).collect();
     let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
         .map_err(|err| {
             SearchError::InternalError(format!("Failed to build doc mapper. Cause: {err}"))
         })?;
+    let (search_request, knn_query_opt) = prepare_search_request(search_request, &*doc_mapper)?;
+
+    let metas = list_relevant_splits(&search_request, metastore).await?;
+    let split_metadata: Vec<SplitIdAndFooterOffsets> =
+        metas.iter().map(extract_split_and_footer_offsets).collect();
+
+    let mut search_response = search_splits(
+        start_instant,
+        &search_request,
+        knn_query_opt.as_ref(),
+        doc_mapper,
+        index_storage,
+        &split_metadata,
+    )
+    .await?;
+    search_response.split_metadata =
+        hits_split_metadata(&search_request, &search_response.hits, &metas);
+    Ok(search_response)
+}
 
-    validate_request(search_request)?;
+/// Performs a search directly over split files, without a metastore or a running cluster.
+///
+/// The split files must be located in the same directory and the doc mapper must match the doc
+/// mapping of the index the splits were created for. The index ID of the request is ignored.
+pub async fn local_search(
+    search_request: &SearchRequest,
+    doc_mapper: Arc<dyn DocMapper>,
+    split_uris: &[Uri],
+    storage_resolver: StorageUriResolver,
+) -> crate::Result<SearchResponse> {
+    let start_instant = tokio::time::Instant::now();
 
+    if search_request.hybrid_ranking.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "Hybrid ranking is not supported by local search.".to_string(),
+        ));
+    }
+    let Some(split_directory_uri) = split_uris.first().and_then(Uri::parent) else {
+        return Err(SearchError::InvalidArgument(
+            "At least one split file URI must be provided.".to_string(),
+        ));
+    };
+    let split_storage = storage_resolver.resolve(&split_directory_uri)?;
+    let mut split_metadata: Vec<SplitIdAndFooterOffsets> = Vec::with_capacity(split_uris.len());
+
+    for split_uri in split_uris {
+        if split_uri.parent().as_ref() != Some(&split_directory_uri) {
+            return Err(SearchError::InvalidArgument(format!(
+                "Split files must be located in the same directory: `{split_uri}` is not located \
+                 in `{split_directory_uri}`."
+            )));
+        }
+        let split_id = split_uri
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(|file_name| file_name.strip_suffix(".split"))
+            .ok_or_else(|| {
+                SearchError::InvalidArgument(format!(
+                    "`{split_uri}` is not a valid split file URI."
+                ))
+            })?;
+        let split_file = PathBuf::from(split_file(split_id));
+        let split_footer_end = split_storage
+            .file_num_bytes(&split_file)
+            .await
+            .with_context(|| format!("Failed to read split file `{split_uri}`."))?;
+        let (split_footer, _) = read_split_footer(split_storage.clone(), &split_file)
+            .await
+            .with_context(|| format!("Failed to read footer of split file `{split_uri}`."))?;
+        split_metadata.push(SplitIdAndFooterOffsets {
+            split_id: split_id.to_string(),
+            split_footer_start: split_footer_end - split_footer.len() as u64,
+            split_footer_end,
+            ..Default::default()
+        });
+    }
+    let (search_request, knn_query_opt) = prepare_search_request(search_request, &*doc_mapper)?;
+
+    search_splits(
+        start_instant,
+        &search_request,
+        knn_query_opt.as_ref(),
+        doc_mapper,
+        split_storage,
+        &split_metadata,
+    )
+    .await
+}
+
+/// Resolves the field aliases of the search request, validates it, and rewrites it into a
+/// neighbors search request if it carries a kNN query.
+fn prepare_search_request(
+    search_request: &SearchRequest,
+    doc_mapper: &dyn DocMapper,
+) -> crate::Result<(SearchRequest, Option<KnnQuery>)> {
+    let mut search_request = search_request.clone();
+    resolve_field_aliases(&mut search_request, &doc_mapper.field_aliases());
+    apply_default_sort(&mut search_request, doc_mapper);
+    validate_request(doc_mapper, &search_request)?;
+    let knn_query_opt = parse_knn_query(search_request.knn_query.as_deref())?;
+    if let Some(knn_query) = &knn_query_opt {
+        search_request = knn_query.neighbors_search_request(&search_request);
+    }
+    Ok((search_request, knn_query_opt))
+}
+
+/// Searches the given splits of an index storage and fetches the documents of the hits.
+async fn search_splits(
+    start_instant: tokio::time::Instant,
+    search_request: &SearchRequest,
+    knn_query_opt: Option<&KnnQuery>,
+    doc_mapper: Arc<dyn DocMapper>,
+    index_storage: Arc<dyn Storage>,
+    split_metadata: &[SplitIdAndFooterOffsets],
+) -> crate::Result<SearchResponse> {
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), search_request)?;
     let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
-    let leaf_search_response = leaf_search(
+    let mut leaf_search_response = leaf_search(
         searcher_context.clone(),
         search_request,
         index_storage.clone(),
-        &split_metadata[..],
+        split_metadata,
         doc_mapper.clone(),
     )
     .await
     .context("Failed to perform leaf search.")?;
 
+    if let Some(count_request) =
+        collapse_count_request(search_request, &leaf_search_response.partial_hits)?
+    {
+        let count_response = leaf_search(
+            searcher_context.clone(),
+            &count_request,
+            index_storage.clone(),
+            split_metadata,
+            doc_mapper.clone(),
+        )
+        .await
+        .context("Failed to count collapsed hits.")?;
+        set_num_collapsed_hits(
+            &mut leaf_search_response.partial_hits,
+            &count_response.partial_hits,
+        );
+    }
+
     let search_request_opt = if !search_request.snippet_fields.is_empty() {
         Some(search_request)
     } else {
@@ -211,13 +461,13 @@ pub async fn single_node_search(
         searcher_context.clone(),
         leaf_search_response.partial_hits,
         index_storage,
-        &split_metadata,
+        split_metadata,
         doc_mapper,
         search_request_opt,
     )
     .await
     .context("Failed to perform fetch docs.")?;
-    let hits: Vec<Hit> = fetch_docs_response
+    let mut hits: Vec<Hit> = fetch_docs_response
         .hits
         .into_iter()
         .map(|leaf_hit| Hit {
@@ -226,34 +476,40 @@ pub async fn single_node_search(
             snippet: leaf_hit.leaf_snippet_json,
         })
         .collect();
+    apply_field_masks(&mut hits, &search_request.field_masks)?;
     let elapsed = start_instant.elapsed();
     let aggregation = if let Some(intermediate_aggregation_result) =
         leaf_search_response.intermediate_aggregation_result
     {
-        let aggregations: QuickwitAggregations =
-            serde_json::from_str(search_request.aggregation_request.as_ref().expect(
+        let aggregations = QuickwitAggregations::parse(
+            search_request.aggregation_request.as_ref().expect(
                 "Aggregation should be present since we are processing an intermediate \
                  aggregation result.",
-            ))?;
+            ),
+            &schema,
+        )?;
         match aggregations {
             QuickwitAggregations::FindTraceIdsAggregation(_) => {
                 // There is nothing to merge here because there is only one leaf response.
                 Some(intermediate_aggregation_result)
             }
             QuickwitAggregations::TantivyAggregations(aggregations) => {
-                let res: IntermediateAggregationResults =
+                let res: IntermediateSearchAggregationResults =
                     serde_json::from_str(&intermediate_aggregation_result)?;
-                let res: AggregationResults =
-                    res.into_final_bucket_result(aggregations, &schema)?;
-                Some(serde_json::to_string(&res)?)
+                let res_json = res.into_final_result(aggregations, &schema)?;
+                Some(serde_json::to_string(&res_json)?)
             }
         }
     } else {
         None
     };
+    // The hits are the `k` nearest neighbors at most.
+    let num_hits = knn_query_opt.map_or(leaf_search_response.num_hits, |knn_query| {
+        leaf_search_response.num_hits.min(knn_query.k)
+    });
     Ok(SearchResponse {
         aggregation,
-        num_hits: leaf_search_response.num_hits,
+        num_hits,
         hits,
         elapsed_time_micros: elapsed.as_micros() as u64,
         errors: leaf_search_response
@@ -261,6 +517,10 @@ pub async fn single_node_search(
             .iter()
             .map(|error| format!("{error:?}"))
             .collect_vec(),
+        scroll_id: None,
+        timed_out: false,
+        failed_splits: Vec::new(),
+        split_metadata: Default::default(),
     })
 }
 
@@ -270,16 +530,24 @@ pub async fn start_searcher_service(
     metastore: Arc<dyn Metastore>,
     storage_uri_resolver: StorageUriResolver,
     search_job_placer: SearchJobPlacer,
+    searcher_context: Arc<SearcherContext>,
+    query_logger_opt: Option<QueryLogger>,
 ) -> anyhow::Result<Arc<dyn SearchService>> {
-    let cluster_client = ClusterClient::new(search_job_placer.clone());
-    let search_service = Arc::new(SearchServiceImpl::new(
+    let cluster_client = ClusterClient::new(search_job_placer.clone())
+        .with_leaf_search_hedging_delay(
+            quickwit_config.searcher_config.leaf_search_hedging_delay(),
+        );
+    let mut search_service = SearchServiceImpl::new(
         metastore,
         storage_uri_resolver,
         cluster_client,
         search_job_placer,
-        quickwit_config.searcher_config.clone(),
-    ));
-    Ok(search_service)
+        searcher_context,
+    );
+    if let Some(query_logger) = query_logger_opt {
+        search_service = search_service.with_query_logger(query_logger);
+    }
+    Ok(Arc::new(search_service))
 }
 
 /// Creates a tantivy Term from a &str.
@@ -0,0 +1,250 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers of the list terms requests, which enumerate the terms of a field, optionally
+//! restricted to the documents matching a query and sorted by number of documents.
+
+use std::cmp::Reverse;
+
+use itertools::Itertools;
+use quickwit_proto::{ListTermsRequest, SearchRequest};
+use serde_json::{json, Value as JsonValue};
+use tantivy::Term;
+
+/// Terms along with their number of documents.
+pub(crate) type TermDocCounts = Vec<(Vec<u8>, u64)>;
+
+/// Returns the search request of the documents whose terms are listed.
+pub(crate) fn list_terms_search_request(
+    list_terms_request: &ListTermsRequest,
+    query: &str,
+) -> SearchRequest {
+    SearchRequest {
+        index_id: list_terms_request.index_id.clone(),
+        query: query.to_string(),
+        start_timestamp: list_terms_request.start_timestamp,
+        end_timestamp: list_terms_request.end_timestamp,
+        ..Default::default()
+    }
+}
+
+/// Zips the terms of a response with their number of documents. Responses of nodes that do not
+/// count the documents of the terms have no document counts, which are then assumed to be 0.
+pub(crate) fn zip_term_doc_counts(terms: Vec<Vec<u8>>, doc_counts: Vec<u64>) -> TermDocCounts {
+    if terms.len() != doc_counts.len() {
+        return terms.into_iter().map(|term| (term, 0)).collect();
+    }
+    terms.into_iter().zip(doc_counts).collect()
+}
+
+/// Merges lists of terms sorted by term into a single list sorted by term, summing the number of
+/// documents of the terms present in several lists.
+pub(crate) fn merge_term_doc_counts(
+    term_doc_counts: impl IntoIterator<Item = TermDocCounts>,
+) -> TermDocCounts {
+    term_doc_counts
+        .into_iter()
+        .kmerge_by(|(left_term, _), (right_term, _)| left_term < right_term)
+        .coalesce(|(left_term, left_count), (right_term, right_count)| {
+            if left_term == right_term {
+                Ok((left_term, left_count + right_count))
+            } else {
+                Err(((left_term, left_count), (right_term, right_count)))
+            }
+        })
+        .collect()
+}
+
+fn sort_by_doc_count(term_doc_counts: &mut TermDocCounts) {
+    term_doc_counts.sort_by(|(left_term, left_count), (right_term, right_count)| {
+        (Reverse(left_count), left_term).cmp(&(Reverse(right_count), right_term))
+    });
+}
+
+/// Number of terms returned by a leaf when the terms are sorted by number of documents. Like the
+/// `shard_size` of Elasticsearch terms aggregations, the leaves return more terms than requested
+/// so that the top terms are less likely to miss documents counted by other leaves.
+fn leaf_num_terms(max_terms: u64) -> usize {
+    (max_terms as usize).saturating_mul(3) / 2 + 10
+}
+
+/// Truncates the terms found by a leaf, which remain sorted by term so that the root can merge
+/// them.
+pub(crate) fn truncate_leaf_term_doc_counts(
+    term_doc_counts: &mut TermDocCounts,
+    list_terms_request: &ListTermsRequest,
+) {
+    let Some(max_terms) = list_terms_request.max_hits else {
+        return;
+    };
+    if !list_terms_request.sort_by_doc_count {
+        term_doc_counts.truncate(max_terms as usize);
+        return;
+    }
+    let num_terms = leaf_num_terms(max_terms);
+    if term_doc_counts.len() <= num_terms {
+        return;
+    }
+    sort_by_doc_count(term_doc_counts);
+    term_doc_counts.truncate(num_terms);
+    term_doc_counts.sort_by(|(left_term, _), (right_term, _)| left_term.cmp(right_term));
+}
+
+/// Sorts and truncates the merged terms of the leaves into the terms of the response.
+pub(crate) fn finalize_term_doc_counts(
+    term_doc_counts: &mut TermDocCounts,
+    list_terms_request: &ListTermsRequest,
+) {
+    if list_terms_request.sort_by_doc_count {
+        sort_by_doc_count(term_doc_counts);
+    }
+    if let Some(max_terms) = list_terms_request.max_hits {
+        term_doc_counts.truncate(max_terms as usize);
+    }
+}
+
+/// Returns the range of keys, `start_key` included and `end_key` excluded, of the terms starting
+/// with `prefix`.
+pub fn prefix_key_range(prefix: &[u8]) -> (Vec<u8>, Option<Vec<u8>>) {
+    let mut end_key = prefix.to_vec();
+    while let Some(last_byte) = end_key.pop() {
+        if last_byte < u8::MAX {
+            end_key.push(last_byte + 1);
+            return (prefix.to_vec(), Some(end_key));
+        }
+    }
+    // The prefix is only made of `0xFF` bytes, so all the terms after it start with it.
+    (prefix.to_vec(), None)
+}
+
+/// Converts a term of a list terms response into its JSON value.
+pub fn term_to_json(term_bytes: &[u8]) -> JsonValue {
+    let term = Term::wrap(term_bytes);
+    if let Some(text) = term.as_str() {
+        return JsonValue::String(text.to_string());
+    }
+    if let Some(value) = term.as_u64() {
+        return json!(value);
+    }
+    if let Some(value) = term.as_i64() {
+        return json!(value);
+    }
+    if let Some(value) = term.as_f64() {
+        return json!(value);
+    }
+    if let Some(value) = term.as_bool() {
+        return json!(value);
+    }
+    JsonValue::String(String::from_utf8_lossy(term.value_bytes()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use tantivy::schema::Field;
+
+    use super::*;
+
+    fn term_doc_counts(term_doc_counts: &[(&str, u64)]) -> TermDocCounts {
+        term_doc_counts
+            .iter()
+            .map(|(term, doc_count)| (term.as_bytes().to_vec(), *doc_count))
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_term_doc_counts() {
+        let merged = merge_term_doc_counts(vec![
+            term_doc_counts(&[("apple", 1), ("cherry", 2)]),
+            term_doc_counts(&[("banana", 3), ("cherry", 4)]),
+            Vec::new(),
+        ]);
+        assert_eq!(
+            merged,
+            term_doc_counts(&[("apple", 1), ("banana", 3), ("cherry", 6)])
+        );
+    }
+
+    #[test]
+    fn test_zip_term_doc_counts() {
+        assert_eq!(
+            zip_term_doc_counts(vec![b"apple".to_vec()], vec![3]),
+            term_doc_counts(&[("apple", 3)])
+        );
+        assert_eq!(
+            zip_term_doc_counts(vec![b"apple".to_vec()], Vec::new()),
+            term_doc_counts(&[("apple", 0)])
+        );
+    }
+
+    #[test]
+    fn test_truncate_and_finalize_term_doc_counts() {
+        let list_terms_request = ListTermsRequest {
+            max_hits: Some(2),
+            ..Default::default()
+        };
+        let mut terms = term_doc_counts(&[("apple", 1), ("banana", 3), ("cherry", 6)]);
+        truncate_leaf_term_doc_counts(&mut terms, &list_terms_request);
+        assert_eq!(terms, term_doc_counts(&[("apple", 1), ("banana", 3)]));
+
+        let list_terms_request = ListTermsRequest {
+            max_hits: Some(2),
+            sort_by_doc_count: true,
+            ..Default::default()
+        };
+        let mut terms = term_doc_counts(&[("apple", 1), ("banana", 3), ("cherry", 6)]);
+        // The leaves keep more terms than requested.
+        truncate_leaf_term_doc_counts(&mut terms, &list_terms_request);
+        assert_eq!(terms.len(), 3);
+        finalize_term_doc_counts(&mut terms, &list_terms_request);
+        assert_eq!(terms, term_doc_counts(&[("cherry", 6), ("banana", 3)]));
+
+        let mut terms: TermDocCounts = (0..20u64)
+            .map(|doc_count| (format!("term-{doc_count:02}").into_bytes(), doc_count))
+            .collect();
+        truncate_leaf_term_doc_counts(&mut terms, &list_terms_request);
+        assert_eq!(terms.len(), leaf_num_terms(2));
+        assert_eq!(terms[0].0, b"term-07".to_vec());
+    }
+
+    #[test]
+    fn test_prefix_key_range() {
+        assert_eq!(
+            prefix_key_range(b"err"),
+            (b"err".to_vec(), Some(b"ers".to_vec()))
+        );
+        assert_eq!(
+            prefix_key_range(&[b'a', u8::MAX]),
+            (vec![b'a', u8::MAX], Some(vec![b'b']))
+        );
+        assert_eq!(prefix_key_range(&[u8::MAX]), (vec![u8::MAX], None));
+    }
+
+    #[test]
+    fn test_term_to_json() {
+        let field = Field::from_field_id(0);
+        let term = Term::from_field_text(field, "error");
+        assert_eq!(term_to_json(term.as_slice()), json!("error"));
+        let term = Term::from_field_u64(field, 42);
+        assert_eq!(term_to_json(term.as_slice()), json!(42));
+        let term = Term::from_field_i64(field, -42);
+        assert_eq!(term_to_json(term.as_slice()), json!(-42));
+        let term = Term::from_field_bool(field, true);
+        assert_eq!(term_to_json(term.as_slice()), json!(true));
+    }
+}
@@ -28,6 +28,10 @@ pub struct SearchMetrics {
     pub leaf_searches_splits_total: IntCounter,
     pub leaf_search_split_duration_secs: Histogram,
     pub active_search_threads_count: IntGauge,
+    pub queued_searches: IntGauge,
+    pub running_searches: IntGauge,
+    pub rejected_searches_total: IntCounter,
+    pub hedged_leaf_searches_total: IntCounter,
 }
 
 impl Default for SearchMetrics {
@@ -49,6 +53,27 @@ impl Default for SearchMetrics {
                 "Number of threads in use in the CPU thread pool",
                 "quickwit_search",
             ),
+            queued_searches: new_gauge(
+                "queued_searches",
+                "Number of searches waiting to be admitted.",
+                "quickwit_search",
+            ),
+            running_searches: new_gauge(
+                "running_searches",
+                "Number of searches admitted and running.",
+                "quickwit_search",
+            ),
+            rejected_searches_total: new_counter(
+                "rejected_searches_total",
+                "Number of searches rejected by the admission control.",
+                "quickwit_search",
+            ),
+            hedged_leaf_searches_total: new_counter(
+                "hedged_leaf_searches_total",
+                "Number of leaf search requests sent again to another searcher because the first \
+                 searcher was too slow to respond.",
+                "quickwit_search",
+            ),
         }
     }
 }
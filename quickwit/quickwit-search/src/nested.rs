@@ -0,0 +1,268 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Nested queries.
+//!
+//! The values of the elements of a `nested` field are all indexed in their parent document, so
+//! the split index alone cannot tell whether several clauses matched the same element. Nested
+//! queries are therefore resolved in two steps:
+//! - the candidate documents are the documents matching both the request query and the nested
+//!   query on the values of all their elements;
+//! - the elements of each candidate document are indexed in a small in-memory index, one
+//!   document per element, and the nested query is run again against it to retain the candidates
+//!   having at least one matching element.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use futures::stream::{StreamExt, TryStreamExt};
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::SearchRequest;
+use serde::Deserialize;
+use tantivy::collector::DocSetCollector;
+use tantivy::fastfield::Column;
+use tantivy::query::Query;
+use tantivy::schema::{Schema, FAST};
+use tantivy::{DocAddress, Document, Index, ReloadPolicy, Searcher};
+
+use crate::SearchError;
+
+/// Name of the field holding the ordinal of the parent document of the elements.
+const NESTED_PARENT_FIELD_NAME: &str = "_nested_parent";
+
+const NUM_CONCURRENT_DOC_FETCHES: usize = 30;
+
+const NESTED_INDEX_WRITER_HEAP_SIZE: usize = 15_000_000;
+
+/// A query that must match a single element of a `nested` field.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct NestedQuery {
+    /// Path of the `nested` field, e.g. `spans`.
+    pub path: String,
+    /// Query on the sub-fields of the `nested` field, e.g. `spans.name:foo AND
+    /// spans.duration:>100`.
+    pub query: String,
+}
+
+impl NestedQuery {
+    /// Returns the search request selecting the candidate documents, i.e. the documents
+    /// matching both the request query and the nested query on the values of all their
+    /// elements.
+    pub fn candidate_search_request(&self, search_request: &SearchRequest) -> SearchRequest {
+        let query = if search_request.query.trim() == "*" {
+            self.query.clone()
+        } else {
+            format!("({}) AND ({})", search_request.query, self.query)
+        };
+        SearchRequest {
+            query,
+            ..search_request.clone()
+        }
+    }
+
+    /// Returns the search request running the nested query against the elements.
+    fn element_search_request(&self, search_request: &SearchRequest) -> SearchRequest {
+        SearchRequest {
+            index_id: search_request.index_id.clone(),
+            query: self.query.clone(),
+            search_fields: search_request.search_fields.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses the JSON serialized nested query of the user request.
+pub fn parse_nested_query(
+    nested_query_json_opt: Option<&str>,
+) -> crate::Result<Option<NestedQuery>> {
+    let Some(nested_query_json) = nested_query_json_opt else {
+        return Ok(None);
+    };
+    let nested_query: NestedQuery = serde_json::from_str(nested_query_json)
+        .map_err(|error| SearchError::InvalidArgument(format!("Invalid nested query: {error}")))?;
+    Ok(Some(nested_query))
+}
+
+/// Checks that the nested query targets a `nested` field and can be parsed.
+pub fn validate_nested_query(
+    doc_mapper: &dyn DocMapper,
+    search_request: &SearchRequest,
+    nested_query: &NestedQuery,
+) -> crate::Result<()> {
+    if !doc_mapper.nested_field_names().contains(&nested_query.path) {
+        return Err(SearchError::InvalidArgument(format!(
+            "Nested query path `{}` is not a `nested` field.",
+            nested_query.path
+        )));
+    }
+    doc_mapper.query(
+        doc_mapper.schema(),
+        &nested_query.element_search_request(search_request),
+    )?;
+    Ok(())
+}
+
+/// Returns the documents matching `candidate_query` that have at least one element of the
+/// nested field matching the nested query on its own.
+pub(crate) async fn find_nested_matches(
+    searcher: Arc<Searcher>,
+    candidate_query: Box<dyn Query>,
+    doc_mapper: Arc<dyn DocMapper>,
+    search_request: &SearchRequest,
+    nested_query: &NestedQuery,
+) -> crate::Result<HashSet<DocAddress>> {
+    let candidate_searcher = searcher.clone();
+    let candidate_doc_addrs = crate::run_cpu_intensive(move || {
+        candidate_searcher.search(&candidate_query, &DocSetCollector)
+    })
+    .await
+    .map_err(|_| {
+        SearchError::InternalError("Nested query candidates search panicked.".to_string())
+    })??;
+
+    let candidate_docs: Vec<(DocAddress, Document)> = futures::stream::iter(candidate_doc_addrs)
+        .map(|doc_addr| {
+            let searcher = searcher.clone();
+            async move {
+                let doc = searcher.doc_async(doc_addr).await?;
+                Ok::<_, SearchError>((doc_addr, doc))
+            }
+        })
+        .buffer_unordered(NUM_CONCURRENT_DOC_FETCHES)
+        .try_collect()
+        .await?;
+    if candidate_docs.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let split_schema = searcher.schema().clone();
+    let element_search_request = nested_query.element_search_request(search_request);
+    let nested_path = nested_query.path.clone();
+    crate::run_cpu_intensive(move || -> crate::Result<HashSet<DocAddress>> {
+        let mut element_docs = Vec::new();
+        for (parent_ord, (_, candidate_doc)) in candidate_docs.iter().enumerate() {
+            let named_doc = split_schema.to_named_doc(candidate_doc);
+            let doc_json = doc_mapper.doc_to_json(named_doc.0)?;
+            for element_doc in doc_mapper.nested_docs_from_json(&nested_path, doc_json)? {
+                element_docs.push((parent_ord as u64, element_doc));
+            }
+        }
+        let matching_parent_ords =
+            search_elements(doc_mapper.as_ref(), &element_search_request, element_docs)?;
+        let nested_matches = matching_parent_ords
+            .into_iter()
+            .map(|parent_ord| candidate_docs[parent_ord as usize].0)
+            .collect();
+        Ok(nested_matches)
+    })
+    .await
+    .map_err(|_| SearchError::InternalError("Nested query search panicked.".to_string()))?
+}
+
+/// Indexes the elements in an in-memory index sharing the doc mapper schema, and returns the
+/// ordinals of the parents of the elements matching the element search request.
+fn search_elements(
+    doc_mapper: &dyn DocMapper,
+    element_search_request: &SearchRequest,
+    element_docs: Vec<(u64, Document)>,
+) -> crate::Result<HashSet<u64>> {
+    let mut schema_builder = Schema::builder();
+    for (_field, field_entry) in doc_mapper.schema().fields() {
+        schema_builder.add_field(field_entry.clone());
+    }
+    let parent_field = schema_builder.add_u64_field(NESTED_PARENT_FIELD_NAME, FAST);
+    let index = Index::create_in_ram(schema_builder.build());
+    index.set_tokenizers(doc_mapper.tokenizer_manager().clone());
+
+    let mut index_writer = index.writer_with_num_threads(1, NESTED_INDEX_WRITER_HEAP_SIZE)?;
+    for (parent_ord, mut element_doc) in element_docs {
+        element_doc.add_u64(parent_field, parent_ord);
+        index_writer.add_document(element_doc)?;
+    }
+    index_writer.commit()?;
+
+    let (element_query, _) = doc_mapper.query(index.schema(), element_search_request)?;
+    let reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()?;
+    let searcher = reader.searcher();
+    let mut matching_parent_ords = HashSet::new();
+    for element_doc_addr in searcher.search(&element_query, &DocSetCollector)? {
+        let parent_column = searcher
+            .segment_reader(element_doc_addr.segment_ord)
+            .fast_fields()
+            .u64(NESTED_PARENT_FIELD_NAME)?;
+        matching_parent_ords.insert(parent_column.get_val(element_doc_addr.doc_id));
+    }
+    Ok(matching_parent_ords)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::SearchRequest;
+
+    use super::{parse_nested_query, NestedQuery};
+
+    #[test]
+    fn test_parse_nested_query() {
+        assert_eq!(parse_nested_query(None).unwrap(), None);
+        let nested_query =
+            parse_nested_query(Some(r#"{"path": "spans", "query": "spans.name:foo"}"#))
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            nested_query,
+            NestedQuery {
+                path: "spans".to_string(),
+                query: "spans.name:foo".to_string(),
+            }
+        );
+        assert!(parse_nested_query(Some(r#"{"path": "spans"}"#)).is_err());
+        assert!(parse_nested_query(Some(r#"{"path": "spans", "query": "*", "foo": 1}"#)).is_err());
+    }
+
+    #[test]
+    fn test_nested_query_candidate_search_request() {
+        let nested_query = NestedQuery {
+            path: "spans".to_string(),
+            query: "spans.name:foo AND spans.duration:>100".to_string(),
+        };
+        let search_request = SearchRequest {
+            query: "*".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let candidate_search_request = nested_query.candidate_search_request(&search_request);
+        assert_eq!(
+            candidate_search_request.query,
+            "spans.name:foo AND spans.duration:>100"
+        );
+        assert_eq!(candidate_search_request.max_hits, 10);
+        let search_request = SearchRequest {
+            query: "service:api".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            nested_query.candidate_search_request(&search_request).query,
+            "(service:api) AND (spans.name:foo AND spans.duration:>100)"
+        );
+    }
+}
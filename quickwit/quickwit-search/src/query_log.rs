@@ -0,0 +1,279 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use quickwit_config::QueryLogConfig;
+use quickwit_metastore::SplitMetadata;
+use quickwit_proto::{SearchRequest, SearchResponse};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+const EVENT_CHANNEL_CAPACITY: usize = 10_000;
+
+tokio::task_local! {
+    static SEARCH_CALLER: SearchCaller;
+}
+
+/// Identity of the caller of a search, recorded in the query log.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct SearchCaller {
+    /// Principal of the API key used by the caller, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    /// IP address of the client that sent the search request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_ip: Option<String>,
+    /// Value of the `User-Agent` header of the search request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+/// Runs `future`, attributing the searches it performs to `caller` in the query log.
+pub async fn with_search_caller<F: Future>(caller: SearchCaller, future: F) -> F::Output {
+    SEARCH_CALLER.scope(caller, future).await
+}
+
+fn current_search_caller() -> SearchCaller {
+    SEARCH_CALLER
+        .try_with(|caller| caller.clone())
+        .unwrap_or_default()
+}
+
+/// Time spent in each phase of a root search, and splits it searched.
+#[derive(Debug, Default)]
+pub(crate) struct SearchProfile {
+    /// Validation of the request and listing of the relevant splits.
+    pub planning: Duration,
+    pub leaf_search: Duration,
+    pub fetch_docs: Duration,
+    pub searched_split_ids: Vec<String>,
+    /// Number of splits pruned with their field ranges and bloom filters. The splits discarded by
+    /// the metastore with the time range and the tags of the query are not counted.
+    pub num_pruned_splits: usize,
+}
+
+impl SearchProfile {
+    pub fn record_searched_splits(&mut self, split_metadatas: &[SplitMetadata]) {
+        self.searched_split_ids.extend(
+            split_metadatas
+                .iter()
+                .map(|split_metadata| split_metadata.split_id.clone()),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Success,
+    Failure,
+}
+
+/// Record of a root search, ingested into the query log index.
+#[derive(Debug, Serialize)]
+pub struct QueryLogEvent {
+    timestamp: i64,
+    index_id: String,
+    query: String,
+    #[serde(flatten)]
+    caller: SearchCaller,
+    outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    num_hits: u64,
+    latency_ms: u64,
+    planning_ms: u64,
+    leaf_search_ms: u64,
+    fetch_docs_ms: u64,
+    num_splits_scanned: u64,
+    num_splits_pruned: u64,
+    /// Whether the search took longer than the slow query threshold.
+    slow: bool,
+    /// Full search request, serialized in JSON, only recorded for slow queries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    search_request: Option<String>,
+    /// IDs of the searched splits, only recorded for slow queries.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    split_ids: Vec<String>,
+}
+
+/// Records the root searches of a searcher. The events are sent to a sink task in charge of
+/// ingesting them into the query log index.
+#[derive(Clone)]
+pub struct QueryLogger {
+    slow_query_threshold: Duration,
+    event_tx: mpsc::Sender<QueryLogEvent>,
+}
+
+impl QueryLogger {
+    /// Creates a query logger along with the receiver of its events.
+    pub fn new(query_log_config: &QueryLogConfig) -> (Self, mpsc::Receiver<QueryLogEvent>) {
+        let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let query_logger = Self {
+            slow_query_threshold: Duration::from_millis(query_log_config.slow_query_threshold_ms),
+            event_tx,
+        };
+        (query_logger, event_rx)
+    }
+
+    pub(crate) fn record(
+        &self,
+        search_request: &SearchRequest,
+        search_result: &crate::Result<SearchResponse>,
+        elapsed: Duration,
+        search_profile: SearchProfile,
+    ) {
+        let event = self.build_event(search_request, search_result, elapsed, search_profile);
+        // Searches never wait for the query log: the event is dropped if the sink lags behind.
+        if self.event_tx.try_send(event).is_err() {
+            warn!(index_id=%search_request.index_id, "Failed to record search in query log.");
+        }
+    }
+
+    fn build_event(
+        &self,
+        search_request: &SearchRequest,
+        search_result: &crate::Result<SearchResponse>,
+        elapsed: Duration,
+        search_profile: SearchProfile,
+    ) -> QueryLogEvent {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        let (outcome, error, num_hits) = match search_result {
+            Ok(search_response) => (Outcome::Success, None, search_response.num_hits),
+            Err(search_error) => (Outcome::Failure, Some(search_error.to_string()), 0),
+        };
+        let slow = elapsed >= self.slow_query_threshold;
+        let (search_request_json, split_ids) = if slow {
+            let search_request_json = serde_json::to_string(search_request)
+                .expect("Serializing a search request should never fail.");
+            (Some(search_request_json), search_profile.searched_split_ids)
+        } else {
+            (None, Vec::new())
+        };
+        QueryLogEvent {
+            timestamp,
+            index_id: search_request.index_id.clone(),
+            query: search_request.query.clone(),
+            caller: current_search_caller(),
+            outcome,
+            error,
+            num_hits,
+            latency_ms: elapsed.as_millis() as u64,
+            planning_ms: search_profile.planning.as_millis() as u64,
+            leaf_search_ms: search_profile.leaf_search.as_millis() as u64,
+            fetch_docs_ms: search_profile.fetch_docs.as_millis() as u64,
+            num_splits_scanned: search_profile.searched_split_ids.len() as u64,
+            num_splits_pruned: search_profile.num_pruned_splits as u64,
+            slow,
+            search_request: search_request_json,
+            split_ids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchError;
+
+    fn search_profile() -> SearchProfile {
+        SearchProfile {
+            planning: Duration::from_millis(2),
+            leaf_search: Duration::from_millis(30),
+            fetch_docs: Duration::from_millis(5),
+            searched_split_ids: vec!["split-1".to_string(), "split-2".to_string()],
+            num_pruned_splits: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_logger_records_searches() {
+        let query_log_config = QueryLogConfig {
+            slow_query_threshold_ms: 100,
+        };
+        let (query_logger, mut event_rx) = QueryLogger::new(&query_log_config);
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "severity:ERROR".to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let search_response = SearchResponse {
+            num_hits: 42,
+            ..Default::default()
+        };
+        let caller = SearchCaller {
+            principal: Some("alice".to_string()),
+            remote_ip: Some("127.0.0.1".to_string()),
+            user_agent: None,
+        };
+        with_search_caller(caller, async {
+            query_logger.record(
+                &search_request,
+                &Ok(search_response),
+                Duration::from_millis(40),
+                search_profile(),
+            );
+        })
+        .await;
+        let event = event_rx.recv().await.unwrap();
+        let event_json = serde_json::to_value(&event).unwrap();
+        assert_eq!(event_json["index_id"], "test-index");
+        assert_eq!(event_json["query"], "severity:ERROR");
+        assert_eq!(event_json["principal"], "alice");
+        assert_eq!(event_json["remote_ip"], "127.0.0.1");
+        assert!(event_json.get("user_agent").is_none());
+        assert_eq!(event_json["outcome"], "success");
+        assert_eq!(event_json["num_hits"], 42);
+        assert_eq!(event_json["latency_ms"], 40);
+        assert_eq!(event_json["leaf_search_ms"], 30);
+        assert_eq!(event_json["num_splits_scanned"], 2);
+        assert_eq!(event_json["num_splits_pruned"], 3);
+        assert_eq!(event_json["slow"], false);
+        assert!(event_json.get("search_request").is_none());
+        assert!(event_json.get("split_ids").is_none());
+
+        query_logger.record(
+            &search_request,
+            &Err(SearchError::InvalidQuery("boom".to_string())),
+            Duration::from_millis(250),
+            search_profile(),
+        );
+        let event = event_rx.recv().await.unwrap();
+        let event_json = serde_json::to_value(&event).unwrap();
+        assert!(event_json.get("principal").is_none());
+        assert_eq!(event_json["outcome"], "failure");
+        assert!(event_json["error"].as_str().unwrap().contains("boom"));
+        assert_eq!(event_json["slow"], true);
+        assert!(event_json["search_request"]
+            .as_str()
+            .unwrap()
+            .contains("severity:ERROR"));
+        assert_eq!(
+            event_json["split_ids"],
+            serde_json::json!(["split-1", "split-2"])
+        );
+    }
+}
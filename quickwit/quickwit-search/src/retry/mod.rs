@@ -125,6 +125,7 @@ mod tests {
             split_id: "split_1".to_string(),
             split_footer_end: 100,
             split_footer_start: 0,
+            ..Default::default()
         };
         let client_for_retry = retry_client(
             &search_job_placer,
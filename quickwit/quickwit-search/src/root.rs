@@ -17,31 +17,50 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::cmp::Reverse;
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 
-use futures::future::try_join_all;
+use futures::future::{self, try_join_all};
+use futures::stream::{FuturesUnordered, Stream, StreamExt};
 use itertools::Itertools;
+use quickwit_common::uri::Uri;
 use quickwit_config::{build_doc_mapper, IndexConfig};
+use quickwit_doc_mapper::{resolve_field_aliases, DocMapper, RuntimeFields};
 use quickwit_metastore::{Metastore, SplitMetadata};
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, Hit, LeafHit, LeafListTermsRequest, LeafListTermsResponse,
     LeafSearchRequest, LeafSearchResponse, ListTermsRequest, ListTermsResponse, PartialHit,
-    SearchRequest, SearchResponse, SplitIdAndFooterOffsets,
+    SearchHitsChunk, SearchRequest, SearchResponse, SplitIdAndFooterOffsets, SplitSearchError,
 };
-use tantivy::aggregation::agg_result::AggregationResults;
-use tantivy::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+use serde_json::Value as JsonValue;
 use tantivy::collector::Collector;
 use tantivy::TantivyError;
 use tokio::task::spawn_blocking;
-use tracing::{debug, error, instrument};
+use tokio::time::{timeout_at, Instant};
+use tracing::{debug, error, info_span, instrument, Instrument};
 
+use crate::aggregations::IntermediateSearchAggregationResults;
 use crate::cluster_client::ClusterClient;
+use crate::collapse::{
+    collapse_count_request, parse_collapse, set_num_collapsed_hits, validate_collapse,
+};
 use crate::collector::{make_merge_collector, QuickwitAggregations};
+use crate::field_mask::apply_field_masks;
+use crate::filters::create_geo_point_filter_builder;
+use crate::hybrid::{parse_hybrid_ranking, validate_hybrid_ranking, HybridRanking};
+use crate::knn::{parse_knn_query, validate_knn_query, KnnQuery};
+use crate::list_terms::{
+    finalize_term_doc_counts, list_terms_search_request, merge_term_doc_counts, zip_term_doc_counts,
+};
+use crate::nested::{parse_nested_query, validate_nested_query};
+use crate::query_log::SearchProfile;
 use crate::search_job_placer::Job;
+use crate::sort::{apply_default_sort, parse_sort_fields, validate_sort_fields};
 use crate::{
-    extract_split_and_footer_offsets, list_relevant_splits, SearchError, SearchJobPlacer,
-    SearchServiceClient,
+    extract_split_and_footer_offsets, list_relevant_splits, list_relevant_splits_and_count_pruned,
+    partial_hit_sorting_key, SearchError, SearchJobPlacer, SearchServiceClient,
 };
 
 /// SearchJob to be assigned to search clients by the [`SearchJobPlacer`].
@@ -110,12 +129,63 @@ impl From<FetchDocsJob> for SplitIdAndFooterOffsets {
     }
 }
 
-pub(crate) fn validate_request(search_request: &SearchRequest) -> crate::Result<()> {
+pub(crate) fn validate_request(
+    doc_mapper: &dyn DocMapper,
+    search_request: &SearchRequest,
+) -> crate::Result<()> {
+    let runtime_fields = RuntimeFields::parse(search_request.runtime_fields.as_deref())
+        .map_err(|err| SearchError::InvalidArgument(err.to_string()))?;
+
     if let Some(agg) = search_request.aggregation_request.as_ref() {
-        let _aggs: QuickwitAggregations = serde_json::from_str(agg)
+        let aggs = QuickwitAggregations::parse(agg, &doc_mapper.schema())
             .map_err(|err| SearchError::InvalidAggregationRequest(err.to_string()))?;
+        if let QuickwitAggregations::TantivyAggregations(search_aggregations) = &aggs {
+            search_aggregations
+                .validate(&doc_mapper.schema())
+                .map_err(SearchError::InvalidAggregationRequest)?;
+        }
+        let agg_field_names = aggs.fast_field_names();
+        if let Some(runtime_field_name) = runtime_fields
+            .field_names()
+            .find(|field_name| agg_field_names.contains(*field_name))
+        {
+            return Err(SearchError::InvalidAggregationRequest(format!(
+                "Aggregations on runtime field `{runtime_field_name}` are not supported."
+            )));
+        }
     };
 
+    if let Some(geo_point_filter_builder) =
+        create_geo_point_filter_builder(search_request.geo_filter.as_deref())?
+    {
+        let geo_point_field_name = geo_point_filter_builder.geo_point_field_name;
+        if !doc_mapper
+            .geo_point_field_names()
+            .contains(&geo_point_field_name)
+        {
+            return Err(SearchError::InvalidArgument(format!(
+                "Geo filter field `{geo_point_field_name}` is not a `geo_point` field."
+            )));
+        }
+    }
+
+    if let Some(nested_query) = parse_nested_query(search_request.nested_query.as_deref())? {
+        validate_nested_query(doc_mapper, search_request, &nested_query)?;
+    }
+
+    if let Some(knn_query) = parse_knn_query(search_request.knn_query.as_deref())? {
+        validate_knn_query(doc_mapper, search_request, &knn_query)?;
+    }
+
+    if search_request.sort_fields.is_some() {
+        let sort_fields = parse_sort_fields(search_request.sort_fields.as_deref())?;
+        validate_sort_fields(doc_mapper, search_request, &sort_fields)?;
+    }
+
+    if let Some(collapse) = parse_collapse(search_request.collapse.as_deref())? {
+        validate_collapse(doc_mapper, search_request, &collapse)?;
+    }
+
     if search_request.start_offset > 10_000 {
         return Err(SearchError::InvalidArgument(format!(
             "max value for start_offset is 10_000, but got {}",
@@ -130,6 +200,29 @@ pub(crate) fn validate_request(search_request: &SearchRequest) -> crate::Result<
         )));
     }
 
+    if let Some(timeout_ms) = search_request.timeout_ms {
+        if timeout_ms == 0 {
+            return Err(SearchError::InvalidArgument(
+                "timeout_ms must be greater than 0".to_string(),
+            ));
+        }
+        // A page missing the hits of the timed out splits would make the scroll skip them.
+        if search_request.scroll_ttl_secs.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "timeout_ms is not supported with scroll".to_string(),
+            ));
+        }
+    }
+
+    if let Some(snippet_max_num_chars) = search_request.snippet_max_num_chars {
+        if snippet_max_num_chars == 0 || snippet_max_num_chars > 10_000 {
+            return Err(SearchError::InvalidArgument(format!(
+                "snippet_max_num_chars must be between 1 and 10_000, but got \
+                 {snippet_max_num_chars}"
+            )));
+        }
+    }
+
     Ok(())
 }
 
@@ -145,63 +238,390 @@ pub async fn root_search(
     cluster_client: &ClusterClient,
     search_job_placer: &SearchJobPlacer,
 ) -> crate::Result<SearchResponse> {
+    root_search_with_profile(
+        search_request,
+        metastore,
+        cluster_client,
+        search_job_placer,
+        &mut SearchProfile::default(),
+    )
+    .await
+}
+
+/// Same as [`root_search`], but also records the time spent in each phase of the search and the
+/// searched splits into `search_profile`.
+pub(crate) async fn root_search_with_profile(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+    search_profile: &mut SearchProfile,
+) -> crate::Result<SearchResponse> {
+    if let Some(hybrid_ranking) = parse_hybrid_ranking(search_request.hybrid_ranking.as_deref())? {
+        return root_hybrid_search(
+            search_request,
+            &hybrid_ranking,
+            metastore,
+            cluster_client,
+            search_job_placer,
+            search_profile,
+        )
+        .await;
+    }
+    let (search_response, _) = root_search_on_splits(
+        search_request,
+        None,
+        metastore,
+        cluster_client,
+        search_job_placer,
+        search_profile,
+    )
+    .await?;
+    Ok(search_response)
+}
+
+/// Runs the lexical and the vector searches of a hybrid search on the same splits, and fuses
+/// their rankings.
+async fn root_hybrid_search(
+    search_request: &SearchRequest,
+    hybrid_ranking: &HybridRanking,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+    search_profile: &mut SearchProfile,
+) -> crate::Result<SearchResponse> {
+    validate_hybrid_ranking(search_request, hybrid_ranking)?;
+    let (lexical_response, split_metadatas) = root_search_on_splits(
+        &hybrid_ranking.lexical_search_request(search_request),
+        None,
+        metastore,
+        cluster_client,
+        search_job_placer,
+        search_profile,
+    )
+    .await?;
+    // Both searches run on the same splits: the profile accumulates the time spent in the
+    // phases of both, but only records the searched splits once.
+    let mut vector_search_profile = SearchProfile::default();
+    let (vector_response, _) = root_search_on_splits(
+        &hybrid_ranking.vector_search_request(search_request),
+        Some(split_metadatas),
+        metastore,
+        cluster_client,
+        search_job_placer,
+        &mut vector_search_profile,
+    )
+    .await?;
+    search_profile.planning += vector_search_profile.planning;
+    search_profile.leaf_search += vector_search_profile.leaf_search;
+    search_profile.fetch_docs += vector_search_profile.fetch_docs;
+    Ok(hybrid_ranking.fuse_search_responses(search_request, lexical_response, vector_response))
+}
+
+/// Same as [`root_search`], except that the given splits are searched instead of the relevant
+/// splits of the index if set. Returns the searched splits along with the search response.
+pub(crate) async fn root_search_on_splits(
+    search_request: &SearchRequest,
+    split_metadatas_opt: Option<Vec<SplitMetadata>>,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+    search_profile: &mut SearchProfile,
+) -> crate::Result<(SearchResponse, Vec<SplitMetadata>)> {
     let start_instant = tokio::time::Instant::now();
 
-    let index_config: IndexConfig = metastore
-        .index_metadata(&search_request.index_id)
-        .await?
-        .into_index_config();
+    let root_search_context = RootSearchContext::new(search_request, metastore).await?;
 
-    let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
-        .map_err(|err| {
-            SearchError::InternalError(format!("Failed to build doc mapper. Cause: {err}"))
+    let split_metadatas: Vec<SplitMetadata> = match split_metadatas_opt {
+        Some(split_metadatas) => split_metadatas,
+        None => {
+            let (split_metadatas, num_pruned_splits) = list_relevant_splits_and_count_pruned(
+                &root_search_context.search_request,
+                metastore,
+            )
+            .await?;
+            search_profile.num_pruned_splits += num_pruned_splits;
+            search_profile.record_searched_splits(&split_metadatas);
+            split_metadatas
+        }
+    };
+    let leaf_search_start_instant = tokio::time::Instant::now();
+    search_profile.planning += leaf_search_start_instant - start_instant;
+
+    let mut leaf_search_response = root_leaf_search(
+        &root_search_context,
+        &split_metadatas,
+        cluster_client,
+        search_job_placer,
+    )
+    .await?;
+    root_count_collapsed_hits(
+        &root_search_context,
+        &mut leaf_search_response,
+        &split_metadatas,
+        cluster_client,
+        search_job_placer,
+    )
+    .await?;
+    let fetch_docs_start_instant = tokio::time::Instant::now();
+    search_profile.leaf_search += fetch_docs_start_instant - leaf_search_start_instant;
+
+    let mut search_response = root_fetch_docs(
+        &root_search_context,
+        leaf_search_response,
+        &split_metadatas,
+        cluster_client,
+        search_job_placer,
+    )
+    .await?;
+    search_profile.fetch_docs += fetch_docs_start_instant.elapsed();
+    search_response.elapsed_time_micros = start_instant.elapsed().as_micros() as u64;
+    Ok((search_response, split_metadatas))
+}
+
+/// The validated request of a root search and the doc mapper of the index, shared by the leaf
+/// search and the fetch docs phases of the search.
+pub(crate) struct RootSearchContext {
+    /// The search request, rewritten for kNN queries.
+    pub search_request: SearchRequest,
+    doc_mapper: Arc<dyn DocMapper>,
+    doc_mapper_str: String,
+    index_uri: Uri,
+    knn_query_opt: Option<KnnQuery>,
+    /// Instant after which the leaf searches still running are cancelled.
+    deadline_opt: Option<Instant>,
+}
+
+impl RootSearchContext {
+    /// Validates the search request against the doc mapper of the index.
+    pub async fn new(
+        search_request: &SearchRequest,
+        metastore: &dyn Metastore,
+    ) -> crate::Result<RootSearchContext> {
+        let deadline_opt = search_request
+            .timeout_ms
+            .map(|timeout_ms| Instant::now() + Duration::from_millis(timeout_ms));
+        let index_config: IndexConfig = metastore
+            .index_metadata(&search_request.index_id)
+            .await?
+            .into_index_config();
+
+        let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+            .map_err(|err| {
+                SearchError::InternalError(format!("Failed to build doc mapper. Cause: {err}"))
+            })?;
+
+        let mut search_request = search_request.clone();
+        resolve_field_aliases(&mut search_request, &doc_mapper.field_aliases());
+        apply_default_sort(&mut search_request, &*doc_mapper);
+        validate_request(&*doc_mapper, &search_request)?;
+        let knn_query_opt = parse_knn_query(search_request.knn_query.as_deref())?;
+        if let Some(knn_query) = &knn_query_opt {
+            search_request = knn_query.neighbors_search_request(&search_request);
+        }
+
+        // Validates the query by effectively building it against the current schema.
+        doc_mapper.query(doc_mapper.schema(), &search_request)?;
+
+        let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|err| {
+            SearchError::InternalError(format!("Failed to serialize doc mapper: Cause {err}"))
         })?;
 
-    validate_request(search_request)?;
+        Ok(RootSearchContext {
+            search_request,
+            doc_mapper,
+            doc_mapper_str,
+            index_uri: index_config.index_uri,
+            knn_query_opt,
+            deadline_opt,
+        })
+    }
 
-    // Validates the query by effectively building it against the current schema.
-    doc_mapper.query(doc_mapper.schema(), search_request)?;
+    pub fn doc_mapper(&self) -> &Arc<dyn DocMapper> {
+        &self.doc_mapper
+    }
 
-    let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|err| {
-        SearchError::InternalError(format!("Failed to serialize doc mapper: Cause {err}"))
-    })?;
+    pub fn index_uri(&self) -> &Uri {
+        &self.index_uri
+    }
 
-    let split_metadatas: Vec<SplitMetadata> =
-        list_relevant_splits(search_request, metastore).await?;
+    /// Returns the context of another search request on the same index.
+    fn with_search_request(&self, search_request: SearchRequest) -> RootSearchContext {
+        RootSearchContext {
+            search_request,
+            doc_mapper: self.doc_mapper.clone(),
+            doc_mapper_str: self.doc_mapper_str.clone(),
+            index_uri: self.index_uri.clone(),
+            knn_query_opt: self.knn_query_opt.clone(),
+            deadline_opt: self.deadline_opt,
+        }
+    }
 
-    let split_offsets_map: HashMap<String, SplitIdAndFooterOffsets> = split_metadatas
-        .iter()
-        .map(|metadata| {
-            (
-                metadata.split_id().to_string(),
-                extract_split_and_footer_offsets(metadata),
-            )
+    /// Turns the merged intermediate aggregation result of the leaves into the final result.
+    pub fn finalize_aggregation(
+        &self,
+        intermediate_aggregation_result: String,
+    ) -> crate::Result<String> {
+        let aggregation_request = self.search_request.aggregation_request.as_ref().expect(
+            "Aggregation should be present since we are processing an intermediate aggregation \
+             result.",
+        );
+        let aggregations =
+            QuickwitAggregations::parse(aggregation_request, &self.doc_mapper.schema())?;
+        match aggregations {
+            QuickwitAggregations::FindTraceIdsAggregation(_) => {
+                // The merge collector has already merged the intermediate results.
+                Ok(intermediate_aggregation_result)
+            }
+            QuickwitAggregations::TantivyAggregations(aggregations) => {
+                let res: IntermediateSearchAggregationResults =
+                    serde_json::from_str(&intermediate_aggregation_result)?;
+                let res_json = res.into_final_result(aggregations, &self.doc_mapper.schema())?;
+                Ok(serde_json::to_string(&res_json)?)
+            }
+        }
+    }
+
+    /// Returns the number of hits of the search, given the number of documents matched by the
+    /// leaves.
+    pub fn num_hits(&self, leaf_num_hits: u64) -> u64 {
+        // The hits are the `k` nearest neighbors at most.
+        self.knn_query_opt
+            .as_ref()
+            .map_or(leaf_num_hits, |knn_query| leaf_num_hits.min(knn_query.k))
+    }
+}
+
+/// Error of the splits whose leaf search was cancelled because the search timed out.
+const LEAF_SEARCH_TIMED_OUT_ERROR: &str = "Leaf search timed out.";
+
+fn timed_out_leaf_search_response(split_ids: Vec<String>) -> LeafSearchResponse {
+    let failed_splits: Vec<SplitSearchError> = split_ids
+        .into_iter()
+        .map(|split_id| SplitSearchError {
+            error: LEAF_SEARCH_TIMED_OUT_ERROR.to_string(),
+            split_id,
+            retryable_error: false,
         })
         .collect();
+    LeafSearchResponse {
+        num_attempted_splits: failed_splits.len() as u64,
+        failed_splits,
+        ..Default::default()
+    }
+}
 
-    let index_uri = &index_config.index_uri;
-
+/// Runs the leaf search phase of a root search on the given splits, and merges the responses of
+/// the leaves.
+///
+/// If the request has a timeout, the leaf searches still running past the deadline are cancelled
+/// and their splits are returned as failed splits instead of failing the search.
+pub(crate) async fn root_leaf_search(
+    root_search_context: &RootSearchContext,
+    split_metadatas: &[SplitMetadata],
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<LeafSearchResponse> {
+    let search_request = &root_search_context.search_request;
     let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
     let assigned_leaf_search_jobs = search_job_placer.assign_jobs(jobs, &HashSet::default())?;
     debug!(assigned_leaf_search_jobs=?assigned_leaf_search_jobs, "Assigned leaf search jobs.");
+    let deadline_opt = root_search_context.deadline_opt;
     let leaf_search_responses: Vec<LeafSearchResponse> = try_join_all(
         assigned_leaf_search_jobs
             .into_iter()
             .map(|(client, client_jobs)| {
                 let leaf_request = jobs_to_leaf_request(
                     search_request,
-                    &doc_mapper_str,
-                    index_uri.as_ref(),
+                    &root_search_context.doc_mapper_str,
+                    root_search_context.index_uri.as_ref(),
                     client_jobs,
                 );
-                cluster_client.leaf_search(leaf_request, client)
+                let split_ids: Vec<String> = leaf_request
+                    .split_offsets
+                    .iter()
+                    .map(|split_offsets| split_offsets.split_id.clone())
+                    .collect();
+                let span = info_span!(
+                    "root:leaf_search",
+                    grpc_addr = %client.grpc_addr(),
+                    num_splits = split_ids.len(),
+                );
+                let leaf_search_future = cluster_client.leaf_search(leaf_request, client);
+                async move {
+                    let Some(deadline) = deadline_opt else {
+                        return leaf_search_future.await;
+                    };
+                    match timeout_at(deadline, leaf_search_future).await {
+                        Ok(leaf_search_result) => leaf_search_result,
+                        Err(_elapsed) => Ok(timed_out_leaf_search_response(split_ids)),
+                    }
+                }
+                .instrument(span)
             }),
     )
     .await?;
 
+    let leaf_search_response =
+        merge_leaf_search_responses(search_request, leaf_search_responses).await?;
+    debug!(leaf_search_response = ?leaf_search_response, "Merged leaf search response.");
+
+    let failed_splits: Vec<&SplitSearchError> = leaf_search_response
+        .failed_splits
+        .iter()
+        .filter(|failed_split| failed_split.error != LEAF_SEARCH_TIMED_OUT_ERROR)
+        .collect();
+    if !failed_splits.is_empty() {
+        error!(failed_splits = ?failed_splits, "Leaf search response contains at least one failed split.");
+        let errors: String = failed_splits
+            .iter()
+            .map(|splits| format!("{splits}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(SearchError::InternalError(errors));
+    }
+    Ok(leaf_search_response)
+}
+
+/// Counts the hits sharing the collapse value of each hit of the merged leaf search response with
+/// a second leaf search phase, if the hits are collapsed and their count is requested.
+async fn root_count_collapsed_hits(
+    root_search_context: &RootSearchContext,
+    leaf_search_response: &mut LeafSearchResponse,
+    split_metadatas: &[SplitMetadata],
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<()> {
+    let Some(count_request) = collapse_count_request(
+        &root_search_context.search_request,
+        &leaf_search_response.partial_hits,
+    )?
+    else {
+        return Ok(());
+    };
+    let count_search_context = root_search_context.with_search_request(count_request);
+    let count_response = root_leaf_search(
+        &count_search_context,
+        split_metadatas,
+        cluster_client,
+        search_job_placer,
+    )
+    .await?;
+    set_num_collapsed_hits(
+        &mut leaf_search_response.partial_hits,
+        &count_response.partial_hits,
+    );
+    Ok(())
+}
+
+/// Merges leaf search responses into one, keeping the top hits of the request and merging the
+/// intermediate aggregation results.
+pub(crate) async fn merge_leaf_search_responses(
+    search_request: &SearchRequest,
+    leaf_search_responses: Vec<LeafSearchResponse>,
+) -> crate::Result<LeafSearchResponse> {
     // Creates a collector which merges responses into one
     let merge_collector = make_merge_collector(search_request)?;
-    let aggregations = merge_collector.aggregation.clone();
 
     // Merging is a cpu-bound task.
     // It should be executed by Tokio's blocking threads.
@@ -215,18 +635,31 @@ pub async fn root_search(
             .map_err(|merge_error: TantivyError| {
                 crate::SearchError::InternalError(format!("{merge_error}"))
             })?;
-    debug!(leaf_search_response = ?leaf_search_response, "Merged leaf search response.");
+    Ok(leaf_search_response)
+}
 
-    if !leaf_search_response.failed_splits.is_empty() {
-        error!(failed_splits = ?leaf_search_response.failed_splits, "Leaf search response contains at least one failed split.");
-        let errors: String = leaf_search_response
-            .failed_splits
-            .iter()
-            .map(|splits| format!("{splits}"))
-            .collect::<Vec<_>>()
-            .join(", ");
-        return Err(SearchError::InternalError(errors));
-    }
+/// Fetches the documents of the hits of the merged leaf search response, and builds the search
+/// response.
+pub(crate) async fn root_fetch_docs(
+    root_search_context: &RootSearchContext,
+    leaf_search_response: LeafSearchResponse,
+    split_metadatas: &[SplitMetadata],
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<SearchResponse> {
+    let search_request = &root_search_context.search_request;
+    let doc_mapper = &root_search_context.doc_mapper;
+    let index_uri = &root_search_context.index_uri;
+
+    let split_offsets_map: HashMap<String, SplitIdAndFooterOffsets> = split_metadatas
+        .iter()
+        .map(|metadata| {
+            (
+                metadata.split_id().to_string(),
+                extract_split_and_footer_offsets(metadata),
+            )
+        })
+        .collect();
 
     let client_fetch_docs_task: Vec<(SearchServiceClient, Vec<FetchDocsJob>)> =
         assign_client_fetch_doc_tasks(
@@ -259,9 +692,16 @@ pub async fn root_search(
                     split_offsets,
                     index_uri: index_uri.to_string(),
                     search_request: search_request_opt,
-                    doc_mapper: doc_mapper_str.clone(),
+                    doc_mapper: root_search_context.doc_mapper_str.clone(),
                 };
-                cluster_client.fetch_docs(fetch_docs_req, client)
+                let span = info_span!(
+                    "root:fetch_docs",
+                    grpc_addr = %client.grpc_addr(),
+                    num_hits = fetch_docs_req.partial_hits.len(),
+                );
+                cluster_client
+                    .fetch_docs(fetch_docs_req, client)
+                    .instrument(span)
             });
 
     let fetch_docs_resps: Vec<FetchDocsResponse> = try_join_all(fetch_docs_resp_futures).await?;
@@ -279,49 +719,291 @@ pub async fn root_search(
         })
         .collect();
 
-    hits.sort_unstable_by_key(|hit| {
-        Reverse(
-            hit.partial_hit
-                .as_ref()
-                .map(|hit| hit.sorting_field_value)
-                .unwrap_or(0),
-        )
-    });
+    // Ties are broken by the document address, so that the last hit can be used as the
+    // `search_after` partial hit of the next page.
+    hits.sort_by_cached_key(|hit| hit.partial_hit.as_ref().map(partial_hit_sorting_key));
+
+    // Upserted documents are only deleted once the delete tasks superseding their previous
+    // versions have been executed. Until then, the deduplication is best-effort: only the latest
+    // version of each document among the hits of the page is returned, so the page may be shorter
+    // than `max_hits`, `num_hits` still counts the previous versions, and a previous version is
+    // returned whenever its latest version falls outside of the page.
+    if doc_mapper.upserts_enabled() {
+        if let (Some(doc_unique_id_field), Some(timestamp_field)) = (
+            doc_mapper.doc_unique_id_field_name(),
+            doc_mapper.timestamp_field_name(),
+        ) {
+            hits = dedup_hits_by_doc_unique_id(hits, doc_unique_id_field, timestamp_field);
+        }
+    }
 
-    let elapsed = start_instant.elapsed();
+    // The fields are masked once the hits are deduplicated, which requires their doc unique ID.
+    apply_field_masks(&mut hits, &search_request.field_masks)?;
 
-    let aggregation = if let Some(intermediate_aggregation_result) =
-        leaf_search_response.intermediate_aggregation_result
-    {
-        match aggregations.expect(
-            "Aggregation should be present since we are processing an intermediate aggregation \
-             result.",
-        ) {
-            QuickwitAggregations::FindTraceIdsAggregation(_) => {
-                // The merge collector has already merged the intermediate results.
-                Some(intermediate_aggregation_result)
-            }
-            QuickwitAggregations::TantivyAggregations(aggregations) => {
-                let res: IntermediateAggregationResults =
-                    serde_json::from_str(&intermediate_aggregation_result)?;
-                let res: AggregationResults =
-                    res.into_final_bucket_result(aggregations, &doc_mapper.schema())?;
-                Some(serde_json::to_string(&res)?)
+    let aggregation = leaf_search_response
+        .intermediate_aggregation_result
+        .map(|intermediate_aggregation_result| {
+            root_search_context.finalize_aggregation(intermediate_aggregation_result)
+        })
+        .transpose()?;
+
+    let split_metadata = hits_split_metadata(search_request, &hits, split_metadatas);
+
+    // The only failed splits left are the ones whose leaf search timed out.
+    let search_response = SearchResponse {
+        aggregation,
+        num_hits: root_search_context.num_hits(leaf_search_response.num_hits),
+        hits,
+        elapsed_time_micros: 0,
+        errors: vec![],
+        scroll_id: None,
+        timed_out: !leaf_search_response.failed_splits.is_empty(),
+        failed_splits: leaf_search_response.failed_splits,
+        split_metadata,
+    };
+    Ok(search_response)
+}
+
+/// Returns the json serialized custom metadata of the splits of the hits, keyed by split ID, if
+/// the request asks for them.
+pub(crate) fn hits_split_metadata(
+    search_request: &SearchRequest,
+    hits: &[Hit],
+    split_metadatas: &[SplitMetadata],
+) -> HashMap<String, String> {
+    if !search_request.include_split_metadata {
+        return HashMap::new();
+    }
+    let hit_split_ids: HashSet<&str> = hits
+        .iter()
+        .filter_map(|hit| hit.partial_hit.as_ref())
+        .map(|partial_hit| partial_hit.split_id.as_str())
+        .collect();
+    split_metadatas
+        .iter()
+        .filter(|split_metadata| hit_split_ids.contains(split_metadata.split_id()))
+        .map(|split_metadata| {
+            let custom_metadata_json = serde_json::to_string(&split_metadata.custom_metadata)
+                .expect("Serializing a map of strings should never fail.");
+            (split_metadata.split_id().to_string(), custom_metadata_json)
+        })
+        .collect()
+}
+
+/// Performs a distributed search streaming the hits.
+///
+/// Instead of merging the hits of all the leaves before fetching their documents, the documents of
+/// the hits of each leaf are fetched as soon as the leaf responds, and emitted as a chunk. The hits
+/// are sorted within a chunk, but not across chunks. The stream ends once `max_hits` hits have
+/// been emitted, cancelling the leaf searches still running.
+#[instrument(skip(search_request, cluster_client, search_job_placer, metastore))]
+pub async fn root_search_hits_stream(
+    search_request: &SearchRequest,
+    metastore: &dyn Metastore,
+    cluster_client: ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<impl Stream<Item = crate::Result<SearchHitsChunk>>> {
+    validate_search_hits_stream_request(search_request)?;
+    let root_search_context = RootSearchContext::new(search_request, metastore).await?;
+    let search_request = &root_search_context.search_request;
+    let split_metadatas = list_relevant_splits(search_request, metastore).await?;
+
+    let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
+    let assigned_leaf_search_jobs = search_job_placer.assign_jobs(jobs, &HashSet::default())?;
+    debug!(assigned_leaf_search_jobs=?assigned_leaf_search_jobs, "Assigned leaf search jobs.");
+
+    let leaf_futures: FuturesUnordered<_> = assigned_leaf_search_jobs
+        .into_iter()
+        .map(|(client, client_jobs)| {
+            let leaf_request = jobs_to_leaf_request(
+                search_request,
+                &root_search_context.doc_mapper_str,
+                root_search_context.index_uri.as_ref(),
+                client_jobs,
+            );
+            leaf_search_and_fetch_docs(leaf_request, cluster_client.clone(), client)
+        })
+        .collect();
+    let hits_stream = leaf_futures
+        .scan(search_request.max_hits, |num_hits_left, chunk_result| {
+            if *num_hits_left == 0 {
+                return future::ready(None);
             }
-        }
+            let chunk_result = match chunk_result {
+                Ok(mut chunk) => {
+                    chunk.hits.truncate(*num_hits_left as usize);
+                    *num_hits_left -= chunk.hits.len() as u64;
+                    Ok(chunk)
+                }
+                Err(error) => {
+                    // The stream ends on the first error.
+                    *num_hits_left = 0;
+                    Err(error)
+                }
+            };
+            future::ready(Some(chunk_result))
+        })
+        .filter(|chunk_result| {
+            let is_empty_chunk = chunk_result
+                .as_ref()
+                .map_or(false, |chunk| chunk.hits.is_empty() && chunk.num_hits == 0);
+            future::ready(!is_empty_chunk)
+        });
+    Ok(hits_stream)
+}
+
+/// Checks that the search request does not require the hits of all the leaves to be merged.
+fn validate_search_hits_stream_request(search_request: &SearchRequest) -> crate::Result<()> {
+    let unsupported_parameter_opt = if search_request.start_offset > 0 {
+        Some("start_offset")
+    } else if search_request.aggregation_request.is_some() {
+        Some("aggregations")
+    } else if search_request.hybrid_ranking.is_some() {
+        Some("hybrid")
+    } else if search_request.collapse.is_some() {
+        Some("collapse")
+    } else if search_request.scroll_ttl_secs.is_some() {
+        Some("scroll")
+    } else if search_request.cross_cluster {
+        Some("cross_cluster")
     } else {
         None
     };
+    if let Some(unsupported_parameter) = unsupported_parameter_opt {
+        return Err(SearchError::InvalidArgument(format!(
+            "Parameter `{unsupported_parameter}` is not supported when streaming hits."
+        )));
+    }
+    Ok(())
+}
 
-    Ok(SearchResponse {
-        aggregation,
+/// Runs the leaf search of a leaf, and fetches the documents of its hits from the same leaf.
+async fn leaf_search_and_fetch_docs(
+    leaf_search_request: LeafSearchRequest,
+    cluster_client: ClusterClient,
+    client: SearchServiceClient,
+) -> crate::Result<SearchHitsChunk> {
+    let leaf_search_response = cluster_client
+        .leaf_search(leaf_search_request.clone(), client.clone())
+        .await?;
+    if !leaf_search_response.failed_splits.is_empty() {
+        let errors: String = leaf_search_response
+            .failed_splits
+            .iter()
+            .map(|failed_split| format!("{failed_split}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(SearchError::InternalError(errors));
+    }
+    if leaf_search_response.partial_hits.is_empty() {
+        return Ok(SearchHitsChunk {
+            num_hits: leaf_search_response.num_hits,
+            hits: Vec::new(),
+        });
+    }
+    let LeafSearchRequest {
+        search_request,
+        split_offsets,
+        doc_mapper,
+        index_uri,
+    } = leaf_search_request;
+    let search_request = search_request.expect("Leaf search requests have a search request.");
+    let field_masks = search_request.field_masks.clone();
+    let fetch_docs_request = FetchDocsRequest {
+        partial_hits: leaf_search_response.partial_hits,
+        index_id: search_request.index_id.clone(),
+        split_offsets,
+        index_uri,
+        search_request: (!search_request.snippet_fields.is_empty()).then_some(search_request),
+        doc_mapper,
+    };
+    let fetch_docs_response = cluster_client
+        .fetch_docs(fetch_docs_request, client)
+        .await?;
+    let mut hits: Vec<Hit> = fetch_docs_response
+        .hits
+        .into_iter()
+        .map(|leaf_hit: LeafHit| Hit {
+            json: leaf_hit.leaf_json,
+            partial_hit: leaf_hit.partial_hit,
+            snippet: leaf_hit.leaf_snippet_json,
+        })
+        .collect();
+    hits.sort_by_cached_key(|hit| hit.partial_hit.as_ref().map(partial_hit_sorting_key));
+    apply_field_masks(&mut hits, &field_masks)?;
+    Ok(SearchHitsChunk {
         num_hits: leaf_search_response.num_hits,
         hits,
-        elapsed_time_micros: elapsed.as_micros() as u64,
-        errors: vec![],
     })
 }
 
+/// Removes the hits superseded by another hit sharing the same doc unique ID and a more recent
+/// timestamp. The order of the remaining hits is preserved.
+fn dedup_hits_by_doc_unique_id(
+    hits: Vec<Hit>,
+    doc_unique_id_field: &str,
+    timestamp_field: &str,
+) -> Vec<Hit> {
+    let keys: Vec<Option<(String, JsonValue)>> = hits
+        .iter()
+        .map(|hit| {
+            let json_doc: JsonValue = serde_json::from_str(&hit.json).ok()?;
+            let doc_unique_id = get_json_value_at_path(&json_doc, doc_unique_id_field)?;
+            let timestamp = get_json_value_at_path(&json_doc, timestamp_field)?;
+            Some((doc_unique_id.to_string(), timestamp.clone()))
+        })
+        .collect();
+    let mut latest_hit_ords: HashMap<&str, usize> = HashMap::new();
+    for (hit_ord, (doc_unique_id, timestamp)) in keys
+        .iter()
+        .enumerate()
+        .filter_map(|(hit_ord, key_opt)| Some((hit_ord, key_opt.as_ref()?)))
+    {
+        latest_hit_ords
+            .entry(doc_unique_id)
+            .and_modify(|latest_hit_ord| {
+                let (_, latest_timestamp) = keys[*latest_hit_ord]
+                    .as_ref()
+                    .expect("The key of a tracked hit should be set.");
+                if cmp_json_timestamps(timestamp, latest_timestamp) == Ordering::Greater {
+                    *latest_hit_ord = hit_ord;
+                }
+            })
+            .or_insert(hit_ord);
+    }
+    hits.into_iter()
+        .enumerate()
+        .filter(|(hit_ord, _)| match &keys[*hit_ord] {
+            Some((doc_unique_id, _)) => {
+                latest_hit_ords.get(doc_unique_id.as_str()) == Some(hit_ord)
+            }
+            None => true,
+        })
+        .map(|(_, hit)| hit)
+        .collect()
+}
+
+fn get_json_value_at_path<'a>(json_doc: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    if let Some(json_value) = json_doc.get(path) {
+        return Some(json_value);
+    }
+    path.split('.')
+        .try_fold(json_doc, |json_value, key| json_value.get(key))
+}
+
+/// Timestamps are all rendered with the output format of the timestamp field, so numeric
+/// timestamps are compared by value and string timestamps lexicographically.
+fn cmp_json_timestamps(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => left
+            .as_f64()
+            .partial_cmp(&right.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+        _ => Ordering::Equal,
+    }
+}
+
 /// Performs a distributed list terms.
 /// 1. Sends leaf request over gRPC to multiple leaf nodes.
 /// 2. Merges the search results.
@@ -361,6 +1043,21 @@ pub async fn root_list_terms(
         ));
     }
 
+    // The leaves need the doc mapper to build the query, which is validated beforehand.
+    let doc_mapper_str_opt = match &list_terms_request.query {
+        Some(query) => {
+            doc_mapper.query(
+                schema.clone(),
+                &list_terms_search_request(list_terms_request, query),
+            )?;
+            let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|err| {
+                SearchError::InternalError(format!("Failed to serialize doc mapper: Cause {err}"))
+            })?;
+            Some(doc_mapper_str)
+        }
+        None => None,
+    };
+
     let mut query = quickwit_metastore::ListSplitsQuery::for_index(&list_terms_request.index_id)
         .with_split_state(quickwit_metastore::SplitState::Published);
 
@@ -393,6 +1090,7 @@ pub async fn root_list_terms(
                         list_terms_request: Some(list_terms_request.clone()),
                         split_offsets: client_jobs.into_iter().map(|job| job.offsets).collect(),
                         index_uri: index_uri.to_string(),
+                        doc_mapper: doc_mapper_str_opt.clone(),
                     },
                     client,
                 )
@@ -418,26 +1116,27 @@ pub async fn root_list_terms(
     // Merging is a cpu-bound task, but probably fast enough to not require
     // spawning it on a blocking thread.
 
-    let merged_iter = leaf_search_responses
-        .into_iter()
-        .map(|leaf_search_response| leaf_search_response.terms)
-        .kmerge()
-        .dedup();
-    let leaf_list_terms_response: Vec<Vec<u8>> = if let Some(limit) = list_terms_request.max_hits {
-        merged_iter.take(limit as usize).collect()
-    } else {
-        merged_iter.collect()
-    };
+    let mut leaf_list_terms_response = merge_term_doc_counts(
+        leaf_search_responses
+            .into_iter()
+            .map(|leaf_search_response| {
+                zip_term_doc_counts(leaf_search_response.terms, leaf_search_response.doc_counts)
+            }),
+    );
+    finalize_term_doc_counts(&mut leaf_list_terms_response, list_terms_request);
 
     debug!(leaf_list_terms_response = ?leaf_list_terms_response, "Merged leaf search response.");
 
     let elapsed = start_instant.elapsed();
+    let (terms, doc_counts): (Vec<Vec<u8>>, Vec<u64>) =
+        leaf_list_terms_response.into_iter().unzip();
 
     Ok(ListTermsResponse {
-        num_hits: leaf_list_terms_response.len() as u64,
-        terms: leaf_list_terms_response,
+        num_hits: terms.len() as u64,
+        terms,
         elapsed_time_micros: elapsed.as_micros() as u64,
         errors: vec![],
+        doc_counts,
     })
 }
 
@@ -505,6 +1204,7 @@ pub fn jobs_to_leaf_request(
 mod tests {
     use std::sync::Arc;
 
+    use futures::TryStreamExt;
     use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
     use quickwit_indexing::mock_split;
     use quickwit_metastore::{IndexMetadata, MockMetastore};
@@ -523,6 +1223,9 @@ mod tests {
             split_id: split_id.to_string(),
             segment_ord: 1,
             doc_id,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
         }
     }
 
@@ -545,6 +1248,134 @@ mod tests {
             .collect()
     }
 
+    #[test]
+    fn test_dedup_hits_by_doc_unique_id() {
+        let hits: Vec<Hit> = [
+            r#"{"user": {"id": "alice"}, "ts": 1000, "version": 1}"#,
+            r#"{"user": {"id": "bob"}, "ts": 1000, "version": 1}"#,
+            r#"{"user": {"id": "alice"}, "ts": 2000, "version": 2}"#,
+            r#"{"ts": 3000, "version": 1}"#,
+            r#"{"user": {"id": "bob"}, "ts": 500, "version": 0}"#,
+        ]
+        .into_iter()
+        .map(|json| Hit {
+            json: json.to_string(),
+            partial_hit: None,
+            snippet: None,
+        })
+        .collect();
+        let deduped_hits = dedup_hits_by_doc_unique_id(hits, "user.id", "ts");
+        let deduped_jsons: Vec<&str> = deduped_hits.iter().map(|hit| hit.json.as_str()).collect();
+        assert_eq!(
+            deduped_jsons,
+            [
+                r#"{"user": {"id": "bob"}, "ts": 1000, "version": 1}"#,
+                r#"{"user": {"id": "alice"}, "ts": 2000, "version": 2}"#,
+                r#"{"ts": 3000, "version": 1}"#,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_root_search_dedups_upserted_hits_within_the_page() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            max_hits: 3,
+            ..Default::default()
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                index_metadata.index_config.doc_mapping = serde_json::from_str(
+                    r#"{
+                        "doc_unique_id_field": "user_id",
+                        "timestamp_field": "ts",
+                        "enable_upserts": true,
+                        "field_mappings": [
+                            {"name": "user_id", "type": "text", "tokenizer": "raw"},
+                            {"name": "ts", "type": "datetime", "fast": true}
+                        ]
+                    }"#,
+                )
+                .unwrap();
+                Ok(index_metadata)
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_filter| Ok(vec![mock_split("split1")]));
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().returning(
+            |_leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 4,
+                    partial_hits: vec![
+                        mock_partial_hit("split1", 3, 1),
+                        mock_partial_hit("split1", 2, 2),
+                        mock_partial_hit("split1", 1, 3),
+                    ],
+                    failed_splits: Vec::new(),
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                let hits = fetch_docs_req
+                    .partial_hits
+                    .into_iter()
+                    .map(|partial_hit| {
+                        let json_doc = match partial_hit.doc_id {
+                            1 => r#"{"user_id": "alice", "ts": 2000}"#,
+                            2 => r#"{"user_id": "alice", "ts": 1000}"#,
+                            _ => r#"{"user_id": "bob", "ts": 1000}"#,
+                        };
+                        quickwit_proto::LeafHit {
+                            leaf_json: json_doc.to_string(),
+                            partial_hit: Some(partial_hit),
+                            leaf_snippet_json: None,
+                        }
+                    })
+                    .collect();
+                Ok(quickwit_proto::FetchDocsResponse { hits })
+            },
+        );
+        let client_pool =
+            ServiceClientPool::for_clients_list(vec![SearchServiceClient::from_service(
+                Arc::new(mock_search_service),
+                ([127, 0, 0, 1], 1000).into(),
+            )]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await?;
+        // The previous version of `alice` is dropped from the page, which is left with fewer
+        // hits than `max_hits`, while `num_hits` still counts it.
+        assert_eq!(search_response.num_hits, 4);
+        let hit_jsons: Vec<&str> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.json.as_str())
+            .collect();
+        assert_eq!(
+            hit_jsons,
+            [
+                r#"{"user_id": "alice", "ts": 2000}"#,
+                r#"{"user_id": "bob", "ts": 1000}"#,
+            ]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_offset_out_of_bounds_1085() -> anyhow::Result<()> {
         let search_request = quickwit_proto::SearchRequest {
@@ -793,6 +1624,125 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_root_search_hits_stream() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 3,
+            ..Default::default()
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_filter| Ok(vec![mock_split("split1"), mock_split("split2")]));
+        let mut mock_search_service1 = MockSearchService::new();
+        mock_search_service1.expect_leaf_search().returning(
+            |_leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 2,
+                    partial_hits: vec![
+                        mock_partial_hit("split1", 1, 3),
+                        mock_partial_hit("split1", 3, 1),
+                    ],
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service1.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let mut mock_search_service2 = MockSearchService::new();
+        mock_search_service2.expect_leaf_search().returning(
+            |_leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                Ok(quickwit_proto::LeafSearchResponse {
+                    num_hits: 2,
+                    partial_hits: vec![
+                        mock_partial_hit("split2", 4, 1),
+                        mock_partial_hit("split2", 2, 2),
+                    ],
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        mock_search_service2.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let client_pool = ServiceClientPool::for_clients_list(vec![
+            SearchServiceClient::from_service(
+                Arc::new(mock_search_service1),
+                ([127, 0, 0, 1], 1000).into(),
+            ),
+            SearchServiceClient::from_service(
+                Arc::new(mock_search_service2),
+                ([127, 0, 0, 1], 1001).into(),
+            ),
+        ]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let chunks: Vec<SearchHitsChunk> = root_search_hits_stream(
+            &search_request,
+            &metastore,
+            cluster_client.clone(),
+            &search_job_placer,
+        )
+        .await?
+        .try_collect()
+        .await?;
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks.iter().map(|chunk| chunk.num_hits).sum::<u64>(), 4);
+        assert_eq!(
+            chunks.iter().map(|chunk| chunk.hits.len()).sum::<usize>(),
+            3
+        );
+        for chunk in &chunks {
+            let sorting_field_values: Vec<u64> = chunk
+                .hits
+                .iter()
+                .map(|hit| hit.partial_hit.as_ref().unwrap().sorting_field_value)
+                .collect();
+            assert!(sorting_field_values
+                .windows(2)
+                .all(|window| window[0] >= window[1]));
+        }
+
+        let aggregation_request = quickwit_proto::SearchRequest {
+            aggregation_request: Some(r#"{"count": {"value_count": {"field": "body"}}}"#.into()),
+            ..search_request
+        };
+        let Err(error) = root_search_hits_stream(
+            &aggregation_request,
+            &metastore,
+            cluster_client,
+            &search_job_placer,
+        )
+        .await
+        else {
+            panic!("Streaming the hits of an aggregation request should fail.");
+        };
+        assert!(matches!(error, SearchError::InvalidArgument(_)));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_root_search_multiple_splits_retry_on_other_node() -> anyhow::Result<()> {
         let search_request = quickwit_proto::SearchRequest {
@@ -1557,6 +2507,99 @@ mod tests {
             "Invalid argument: max value for max_hits is 10_000, but got 20000",
         );
 
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 10,
+            timeout_ms: Some(0),
+            ..Default::default()
+        };
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await;
+        assert_eq!(
+            search_response.unwrap_err().to_string(),
+            "Invalid argument: timeout_ms must be greater than 0",
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_root_search_timed_out_splits() -> anyhow::Result<()> {
+        let search_request = quickwit_proto::SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 10,
+            timeout_ms: Some(1_000),
+            ..Default::default()
+        };
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_filter| Ok(vec![mock_split("split1"), mock_split("split2")]));
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_leaf_search().returning(
+            |leaf_search_req: quickwit_proto::LeafSearchRequest| {
+                // The leaf search of `split2` is reported as timed out, also when retried.
+                let (split_ids_1, split_ids_2): (Vec<String>, Vec<String>) = leaf_search_req
+                    .split_offsets
+                    .into_iter()
+                    .map(|split_offsets| split_offsets.split_id)
+                    .partition(|split_id| split_id == "split1");
+                let mut leaf_search_response = timed_out_leaf_search_response(split_ids_2);
+                leaf_search_response.num_attempted_splits += split_ids_1.len() as u64;
+                if !split_ids_1.is_empty() {
+                    leaf_search_response.num_hits = 1;
+                    leaf_search_response.partial_hits = vec![mock_partial_hit("split1", 3, 1)];
+                }
+                Ok(leaf_search_response)
+            },
+        );
+        mock_search_service.expect_fetch_docs().returning(
+            |fetch_docs_req: quickwit_proto::FetchDocsRequest| {
+                Ok(quickwit_proto::FetchDocsResponse {
+                    hits: get_doc_for_fetch_req(fetch_docs_req),
+                })
+            },
+        );
+        let client_pool =
+            ServiceClientPool::for_clients_list(vec![SearchServiceClient::from_service(
+                Arc::new(mock_search_service),
+                ([127, 0, 0, 1], 1000).into(),
+            )]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let search_response = root_search(
+            &search_request,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await?;
+        assert_eq!(search_response.num_hits, 1);
+        assert_eq!(search_response.hits.len(), 1);
+        assert!(search_response.timed_out);
+        assert_eq!(search_response.failed_splits.len(), 1);
+        assert_eq!(search_response.failed_splits[0].split_id, "split2");
+        assert_eq!(
+            search_response.failed_splits[0].error,
+            LEAF_SEARCH_TIMED_OUT_ERROR
+        );
         Ok(())
     }
 }
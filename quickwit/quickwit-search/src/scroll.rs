@@ -0,0 +1,427 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use quickwit_metastore::{Metastore, SplitMetadata};
+use quickwit_proto::{ScrollRequest, SearchRequest, SearchResponse};
+use tracing::instrument;
+use ulid::Ulid;
+
+use crate::query_log::SearchProfile;
+use crate::root::root_search_on_splits;
+use crate::{ClusterClient, SearchError, SearchJobPlacer};
+
+/// Maximum duration a scroll context is kept without being used.
+const MAX_SCROLL_TTL: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// The state of a scroll: the request fetching the next page, and the splits pinned when the
+/// search started.
+#[derive(Clone)]
+struct ScrollContext {
+    search_request: SearchRequest,
+    split_metadatas: Vec<SplitMetadata>,
+    ttl: Duration,
+}
+
+impl ScrollContext {
+    /// Moves the scroll past the hits of the given response.
+    fn advance(&mut self, search_response: &SearchResponse) {
+        if let Some(last_hit) = search_response.hits.last() {
+            self.search_request.search_after = last_hit.partial_hit.clone();
+        }
+    }
+}
+
+struct ScrollContextEntry {
+    scroll_context: ScrollContext,
+    expires_at: Instant,
+}
+
+/// The scroll contexts of the searches started on this node. A context expires if it is not used
+/// within its TTL, and expired contexts are evicted lazily.
+#[derive(Default)]
+pub(crate) struct ScrollContexts {
+    entries: Mutex<HashMap<String, ScrollContextEntry>>,
+}
+
+impl ScrollContexts {
+    fn put(&self, scroll_id: &str, scroll_context: ScrollContext) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires_at > now);
+        let expires_at = now + scroll_context.ttl;
+        entries.insert(
+            scroll_id.to_string(),
+            ScrollContextEntry {
+                scroll_context,
+                expires_at,
+            },
+        );
+    }
+
+    fn get(&self, scroll_id: &str) -> Option<ScrollContext> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(scroll_id)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.scroll_context.clone())
+    }
+
+    /// Returns the ID of the index of a scroll, if its context has not expired.
+    pub(crate) fn index_id(&self, scroll_id: &str) -> Option<String> {
+        self.get(scroll_id)
+            .map(|scroll_context| scroll_context.search_request.index_id)
+    }
+}
+
+fn scroll_ttl(scroll_ttl_secs: u32) -> crate::Result<Duration> {
+    let scroll_ttl = Duration::from_secs(scroll_ttl_secs as u64);
+    if scroll_ttl.is_zero() || scroll_ttl > MAX_SCROLL_TTL {
+        return Err(SearchError::InvalidArgument(format!(
+            "Scroll TTL must be between 1 and {} seconds, but got {scroll_ttl_secs}.",
+            MAX_SCROLL_TTL.as_secs()
+        )));
+    }
+    Ok(scroll_ttl)
+}
+
+/// Performs a distributed search like [`crate::root_search`], and keeps a scroll context pinning
+/// the searched splits, so that the next pages are fetched with [`root_scroll`] from the same
+/// set of splits. The splits are listed from the metastore unless `split_metadatas_opt` is set.
+#[instrument(skip(
+    search_request,
+    split_metadatas_opt,
+    scroll_contexts,
+    metastore,
+    cluster_client,
+    search_job_placer
+))]
+pub(crate) async fn root_search_with_scroll(
+    search_request: &SearchRequest,
+    split_metadatas_opt: Option<Vec<SplitMetadata>>,
+    scroll_contexts: &ScrollContexts,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+    search_profile: &mut SearchProfile,
+) -> crate::Result<SearchResponse> {
+    let ttl = scroll_ttl(search_request.scroll_ttl_secs.unwrap_or_default())?;
+    let (mut search_response, split_metadatas) = root_search_on_splits(
+        search_request,
+        split_metadatas_opt,
+        metastore,
+        cluster_client,
+        search_job_placer,
+        search_profile,
+    )
+    .await?;
+    let mut next_search_request = search_request.clone();
+    // The aggregations are only returned with the first page.
+    next_search_request.aggregation_request = None;
+    next_search_request.start_offset = 0;
+    next_search_request.scroll_ttl_secs = None;
+
+    let mut scroll_context = ScrollContext {
+        search_request: next_search_request,
+        split_metadatas,
+        ttl,
+    };
+    scroll_context.advance(&search_response);
+
+    let scroll_id = Ulid::new().to_string();
+    scroll_contexts.put(&scroll_id, scroll_context);
+    search_response.scroll_id = Some(scroll_id);
+    Ok(search_response)
+}
+
+/// Returns the next page of a search started with [`root_search_with_scroll`].
+#[instrument(skip(scroll_contexts, metastore, cluster_client, search_job_placer))]
+pub(crate) async fn root_scroll(
+    scroll_request: &ScrollRequest,
+    scroll_contexts: &ScrollContexts,
+    metastore: &dyn Metastore,
+    cluster_client: &ClusterClient,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<SearchResponse> {
+    let scroll_id = &scroll_request.scroll_id;
+    let mut scroll_context = scroll_contexts
+        .get(scroll_id)
+        .ok_or_else(|| SearchError::ScrollContextDoesNotExist(scroll_id.clone()))?;
+    if let Some(scroll_ttl_secs) = scroll_request.scroll_ttl_secs {
+        scroll_context.ttl = scroll_ttl(scroll_ttl_secs)?;
+    }
+    let (mut search_response, _) = root_search_on_splits(
+        &scroll_context.search_request,
+        Some(scroll_context.split_metadatas.clone()),
+        metastore,
+        cluster_client,
+        search_job_placer,
+        &mut SearchProfile::default(),
+    )
+    .await?;
+    scroll_context.advance(&search_response);
+    scroll_contexts.put(scroll_id, scroll_context);
+    search_response.scroll_id = Some(scroll_id.clone());
+    Ok(search_response)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
+    use quickwit_indexing::mock_split;
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::{FetchDocsResponse, Hit, LeafHit, LeafSearchResponse, PartialHit};
+
+    use super::*;
+    use crate::{MockSearchService, SearchServiceClient};
+
+    fn scroll_context(ttl: Duration) -> ScrollContext {
+        ScrollContext {
+            search_request: SearchRequest {
+                index_id: "test-index".to_string(),
+                query: "*".to_string(),
+                max_hits: 10,
+                ..Default::default()
+            },
+            split_metadatas: vec![SplitMetadata::for_test("split_1".to_string())],
+            ttl,
+        }
+    }
+
+    #[test]
+    fn test_scroll_ttl() {
+        assert_eq!(scroll_ttl(60).unwrap(), Duration::from_secs(60));
+        assert!(matches!(
+            scroll_ttl(0).unwrap_err(),
+            SearchError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            scroll_ttl(60 * 60 + 1).unwrap_err(),
+            SearchError::InvalidArgument(_)
+        ));
+    }
+
+    #[test]
+    fn test_scroll_contexts_expire() {
+        let scroll_contexts = ScrollContexts::default();
+        scroll_contexts.put("scroll_1", scroll_context(Duration::from_secs(60)));
+        scroll_contexts.put("scroll_2", scroll_context(Duration::ZERO));
+
+        let scroll_context = scroll_contexts.get("scroll_1").unwrap();
+        assert_eq!(scroll_context.split_metadatas.len(), 1);
+        assert!(scroll_contexts.get("scroll_2").is_none());
+        assert!(scroll_contexts.get("scroll_3").is_none());
+
+        scroll_contexts.put("scroll_3", scroll_context);
+        assert!(!scroll_contexts
+            .entries
+            .lock()
+            .unwrap()
+            .contains_key("scroll_2"));
+    }
+
+    #[tokio::test]
+    async fn test_root_scroll() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_index_metadata().returning(|_index_id| {
+            Ok(IndexMetadata::for_test(
+                "test-index",
+                "ram:///indexes/test-index",
+            ))
+        });
+        // The splits are only listed once, when the scroll starts.
+        metastore
+            .expect_list_splits()
+            .times(1)
+            .returning(|_filter| Ok(vec![mock_split("split_1")]));
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .returning(|leaf_search_request| {
+                let search_request = leaf_search_request.search_request.unwrap();
+                let partial_hits = (0..5u32)
+                    .map(|doc_id| PartialHit {
+                        sorting_field_value: 10 - doc_id as u64,
+                        split_id: "split_1".to_string(),
+                        segment_ord: 0,
+                        doc_id,
+                        secondary_sorting_field_values: Vec::new(),
+                        collapse_value: None,
+                        num_collapsed_hits: None,
+                    })
+                    .filter(|partial_hit| match &search_request.search_after {
+                        Some(search_after) => {
+                            partial_hit.sorting_field_value < search_after.sorting_field_value
+                        }
+                        None => true,
+                    })
+                    .take(search_request.max_hits as usize)
+                    .collect();
+                Ok(LeafSearchResponse {
+                    num_hits: 5,
+                    partial_hits,
+                    num_attempted_splits: 1,
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+            .expect_fetch_docs()
+            .returning(|fetch_docs_request| {
+                let hits = fetch_docs_request
+                    .partial_hits
+                    .into_iter()
+                    .map(|partial_hit| LeafHit {
+                        leaf_json: format!(r#"{{"doc_id": {}}}"#, partial_hit.doc_id),
+                        partial_hit: Some(partial_hit),
+                        leaf_snippet_json: None,
+                    })
+                    .collect();
+                Ok(FetchDocsResponse { hits })
+            });
+        let client_pool =
+            ServiceClientPool::for_clients_list(vec![SearchServiceClient::from_service(
+                Arc::new(mock_search_service),
+                ([127, 0, 0, 1], 1000).into(),
+            )]);
+        let search_job_placer = SearchJobPlacer::new(client_pool);
+        let cluster_client = ClusterClient::new(search_job_placer.clone());
+        let scroll_contexts = ScrollContexts::default();
+
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "test".to_string(),
+            search_fields: vec!["body".to_string()],
+            max_hits: 2,
+            scroll_ttl_secs: Some(60),
+            ..Default::default()
+        };
+        let search_response = root_search_with_scroll(
+            &search_request,
+            None,
+            &scroll_contexts,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+            &mut SearchProfile::default(),
+        )
+        .await?;
+        let scroll_id = search_response.scroll_id.unwrap();
+        let mut doc_ids: Vec<u32> = search_response
+            .hits
+            .iter()
+            .map(|hit| hit.partial_hit.as_ref().unwrap().doc_id)
+            .collect();
+
+        let scroll_request = ScrollRequest {
+            scroll_id: scroll_id.clone(),
+            scroll_ttl_secs: None,
+        };
+        loop {
+            let search_response = root_scroll(
+                &scroll_request,
+                &scroll_contexts,
+                &metastore,
+                &cluster_client,
+                &search_job_placer,
+            )
+            .await?;
+            assert_eq!(search_response.num_hits, 5);
+            assert_eq!(search_response.scroll_id.as_ref(), Some(&scroll_id));
+
+            if search_response.hits.is_empty() {
+                break;
+            }
+            doc_ids.extend(
+                search_response
+                    .hits
+                    .iter()
+                    .map(|hit| hit.partial_hit.as_ref().unwrap().doc_id),
+            );
+        }
+        assert_eq!(doc_ids, [0, 1, 2, 3, 4]);
+
+        let unknown_scroll_request = ScrollRequest {
+            scroll_id: "unknown".to_string(),
+            scroll_ttl_secs: None,
+        };
+        let error = root_scroll(
+            &unknown_scroll_request,
+            &scroll_contexts,
+            &metastore,
+            &cluster_client,
+            &search_job_placer,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(error, SearchError::ScrollContextDoesNotExist(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_context_advance() {
+        let mut scroll_context = scroll_context(Duration::from_secs(60));
+        let last_partial_hit = PartialHit {
+            sorting_field_value: 3,
+            split_id: "split_1".to_string(),
+            segment_ord: 0,
+            doc_id: 7,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
+        };
+        let search_response = SearchResponse {
+            hits: vec![
+                Hit {
+                    partial_hit: Some(PartialHit {
+                        sorting_field_value: 5,
+                        split_id: "split_1".to_string(),
+                        segment_ord: 0,
+                        doc_id: 2,
+                        secondary_sorting_field_values: Vec::new(),
+                        collapse_value: None,
+                        num_collapsed_hits: None,
+                    }),
+                    ..Default::default()
+                },
+                Hit {
+                    partial_hit: Some(last_partial_hit.clone()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        scroll_context.advance(&search_response);
+        assert_eq!(
+            scroll_context.search_request.search_after.as_ref(),
+            Some(&last_partial_hit)
+        );
+        // The scroll does not move past an empty page.
+        scroll_context.advance(&SearchResponse::default());
+        assert_eq!(
+            scroll_context.search_request.search_after,
+            Some(last_partial_hit)
+        );
+    }
+}
@@ -44,18 +44,38 @@ pub trait Job {
     fn cost(&self) -> u32;
 }
 
+const DEFAULT_SPLIT_REPLICATION_FACTOR: usize = 2;
+
 /// Search job placer.
 /// It assigns jobs to search clients.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct SearchJobPlacer {
     /// Search clients pool.
     clients_pool: ServiceClientPool<SearchServiceClient>,
+    /// Number of candidate nodes of each split, among which the least loaded one is chosen.
+    split_replication_factor: usize,
+}
+
+impl Default for SearchJobPlacer {
+    fn default() -> Self {
+        Self::new(ServiceClientPool::default())
+    }
 }
 
 impl SearchJobPlacer {
     /// Returns an [`SearchJobPlacer`] from a search service client pool.
     pub fn new(clients_pool: ServiceClientPool<SearchServiceClient>) -> Self {
-        Self { clients_pool }
+        Self {
+            clients_pool,
+            split_replication_factor: DEFAULT_SPLIT_REPLICATION_FACTOR,
+        }
+    }
+
+    /// Sets the number of candidate nodes of each split. The candidates of a split are the first
+    /// nodes in the rendezvous hashing order of its split ID.
+    pub fn with_split_replication_factor(mut self, split_replication_factor: usize) -> Self {
+        self.split_replication_factor = split_replication_factor.max(1);
+        self
     }
 
     /// Returns a copy of the entire clients map.
@@ -132,14 +152,14 @@ impl SearchJobPlacer {
             job_order_key(left).cmp(&job_order_key(right))
         });
 
+        let num_candidates = self.split_replication_factor.min(nodes.len());
+
         for job in jobs {
             sort_by_rendez_vous_hash(&mut nodes, job.split_id());
-            // choose one of the the first two nodes based on least loaded
-            let chosen_node_index: usize = if nodes.len() >= 2 {
-                usize::from(nodes[0].load > nodes[1].load)
-            } else {
-                0
-            };
+            // choose the least loaded of the candidate nodes
+            let chosen_node_index: usize = (0..num_candidates)
+                .min_by_key(|&node_index| nodes[node_index].load)
+                .unwrap_or(0);
 
             // update node load for next round
             nodes[chosen_node_index].load += job.cost() as u64;
@@ -198,7 +218,7 @@ mod tests {
 
     use crate::client::create_search_service_client;
     use crate::root::SearchJob;
-    use crate::SearchJobPlacer;
+    use crate::{MockSearchService, SearchJobPlacer, SearchServiceClient};
 
     async fn create_cluster_simple_for_test(
         transport: &dyn Transport,
@@ -292,4 +312,32 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_search_job_placer_split_replication_factor() {
+        let client_pool = ServiceClientPool::for_clients_list(
+            (0..3)
+                .map(|port| {
+                    SearchServiceClient::from_service(
+                        Arc::new(MockSearchService::new()),
+                        ([127, 0, 0, 1], 1000 + port).into(),
+                    )
+                })
+                .collect(),
+        );
+        let jobs = || {
+            vec![
+                SearchJob::for_test("split1", 1),
+                SearchJob::for_test("split1", 1),
+                SearchJob::for_test("split1", 1),
+            ]
+        };
+        let job_placer = SearchJobPlacer::new(client_pool.clone()).with_split_replication_factor(1);
+        let assigned_jobs = job_placer.assign_jobs(jobs(), &HashSet::default()).unwrap();
+        assert_eq!(assigned_jobs.len(), 1);
+
+        let job_placer = SearchJobPlacer::new(client_pool).with_split_replication_factor(3);
+        let assigned_jobs = job_placer.assign_jobs(jobs(), &HashSet::default()).unwrap();
+        assert_eq!(assigned_jobs.len(), 3);
+    }
 }
@@ -19,8 +19,9 @@
 
 use std::convert::TryFrom;
 
+use itertools::Itertools;
 use quickwit_common::truncate_str;
-use quickwit_proto::SearchResponse;
+use quickwit_proto::{PartialHit, SearchResponse, SplitSearchError};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
@@ -39,6 +40,16 @@ pub struct SearchResponseRest {
     #[schema(value_type = Vec<Object>)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snippets: Option<Vec<JsonValue>>,
+    /// Number of hits sharing the collapse value of each hit, if the hits are collapsed and
+    /// their count is requested.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_collapsed_hits: Option<Vec<u64>>,
+    /// Custom metadata of the split of each hit, if requested with `include_split_metadata`.
+    #[schema(value_type = Vec<Object>)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split_metadata: Option<Vec<JsonValue>>,
     /// Elapsed time.
     pub elapsed_time_micros: u64,
     /// Search errors.
@@ -47,6 +58,71 @@ pub struct SearchResponseRest {
     #[schema(value_type = Object)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub aggregations: Option<JsonValue>,
+    /// Cursor of the last hit, to pass as the `search_after` parameter of the request fetching
+    /// the next page.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_after: Option<String>,
+    /// Scroll ID, to pass to the scroll API to fetch the next page.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll_id: Option<String>,
+    /// Whether some leaf searches were cancelled because the search timed out, in which case the
+    /// response only covers the splits searched before the timeout.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// The splits that could not be searched.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_splits: Vec<SplitSearchError>,
+}
+
+/// Encodes a partial hit into a `search_after` cursor of the form
+/// `<sorting_field_value>:<split_id>:<segment_ord>:<doc_id>`.
+///
+/// When the hits are sorted by several fields, the values of the secondary sort fields follow
+/// the sorting field value, separated by commas.
+pub fn encode_search_after_cursor(partial_hit: &PartialHit) -> String {
+    let sorting_field_values = std::iter::once(&partial_hit.sorting_field_value)
+        .chain(&partial_hit.secondary_sorting_field_values)
+        .join(",");
+    format!(
+        "{}:{}:{}:{}",
+        sorting_field_values, partial_hit.split_id, partial_hit.segment_ord, partial_hit.doc_id
+    )
+}
+
+/// Decodes a `search_after` cursor produced by [`encode_search_after_cursor`].
+pub fn decode_search_after_cursor(cursor: &str) -> Result<PartialHit, SearchError> {
+    let invalid_cursor_error =
+        || SearchError::InvalidArgument(format!("Invalid `search_after` cursor `{cursor}`."));
+    let (sorting_field_values, address) =
+        cursor.split_once(':').ok_or_else(invalid_cursor_error)?;
+    let mut sorting_field_values = sorting_field_values
+        .split(',')
+        .map(|value| value.parse::<u64>().map_err(|_| invalid_cursor_error()));
+    let sorting_field_value = sorting_field_values
+        .next()
+        .ok_or_else(invalid_cursor_error)??;
+    let secondary_sorting_field_values = sorting_field_values.collect::<Result<_, _>>()?;
+    let (split_id_and_segment_ord, doc_id) =
+        address.rsplit_once(':').ok_or_else(invalid_cursor_error)?;
+    let (split_id, segment_ord) = split_id_and_segment_ord
+        .rsplit_once(':')
+        .ok_or_else(invalid_cursor_error)?;
+    if split_id.is_empty() {
+        return Err(invalid_cursor_error());
+    }
+    Ok(PartialHit {
+        sorting_field_value,
+        split_id: split_id.to_string(),
+        segment_ord: segment_ord.parse().map_err(|_| invalid_cursor_error())?,
+        doc_id: doc_id.parse().map_err(|_| invalid_cursor_error())?,
+        secondary_sorting_field_values,
+        collapse_value: None,
+        num_collapsed_hits: None,
+    })
 }
 
 impl TryFrom<SearchResponse> for SearchResponseRest {
@@ -55,6 +131,52 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
     fn try_from(search_response: SearchResponse) -> Result<Self, Self::Error> {
         let mut documents = Vec::with_capacity(search_response.hits.len());
         let mut snippets = Vec::new();
+        let search_after_opt = search_response
+            .hits
+            .last()
+            .and_then(|hit| hit.partial_hit.as_ref())
+            .map(encode_search_after_cursor);
+        let is_collapsed_hits_count = search_response.hits.iter().any(|hit| {
+            hit.partial_hit.as_ref().map_or(false, |partial_hit| {
+                partial_hit.num_collapsed_hits.is_some()
+            })
+        });
+        let num_collapsed_hits_opt = is_collapsed_hits_count.then(|| {
+            search_response
+                .hits
+                .iter()
+                .map(|hit| {
+                    hit.partial_hit
+                        .as_ref()
+                        .and_then(|partial_hit| partial_hit.num_collapsed_hits)
+                        .unwrap_or_default()
+                })
+                .collect()
+        });
+        let split_metadata_opt = if !search_response.split_metadata.is_empty() {
+            let split_metadata = search_response
+                .hits
+                .iter()
+                .map(|hit| {
+                    let split_metadata_json_opt = hit.partial_hit.as_ref().and_then(|partial_hit| {
+                        search_response.split_metadata.get(&partial_hit.split_id)
+                    });
+                    match split_metadata_json_opt {
+                        Some(split_metadata_json) => serde_json::from_str(split_metadata_json)
+                            .map_err(|err| {
+                                SearchError::InternalError(format!(
+                                    "Failed to deserialize split metadata \
+                                     `{split_metadata_json}`: `{err}`."
+                                ))
+                            }),
+                        None => Ok(JsonValue::Null),
+                    }
+                })
+                .collect::<Result<Vec<JsonValue>, SearchError>>()?;
+            Some(split_metadata)
+        } else {
+            None
+        };
         for hit in search_response.hits {
             let document: JsonValue = serde_json::from_str(&hit.json).map_err(|err| {
                 SearchError::InternalError(format!(
@@ -94,9 +216,102 @@ impl TryFrom<SearchResponse> for SearchResponseRest {
             num_hits: search_response.num_hits,
             hits: documents,
             snippets: snippet_opt,
+            num_collapsed_hits: num_collapsed_hits_opt,
+            split_metadata: split_metadata_opt,
             elapsed_time_micros: search_response.elapsed_time_micros,
             errors: search_response.errors,
             aggregations: aggregations_opt,
+            search_after: search_after_opt,
+            scroll_id: search_response.scroll_id,
+            timed_out: search_response.timed_out,
+            failed_splits: search_response.failed_splits,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use quickwit_proto::Hit;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_search_after_cursor_roundtrip() {
+        let partial_hit = PartialHit {
+            sorting_field_value: 1_678_000_000,
+            split_id: "01GTQ5ZP7J3M8N6D0YJ4V7X9AB".to_string(),
+            segment_ord: 2,
+            doc_id: 42,
+            secondary_sorting_field_values: Vec::new(),
+            collapse_value: None,
+            num_collapsed_hits: None,
+        };
+        let cursor = encode_search_after_cursor(&partial_hit);
+        assert_eq!(cursor, "1678000000:01GTQ5ZP7J3M8N6D0YJ4V7X9AB:2:42");
+        assert_eq!(decode_search_after_cursor(&cursor).unwrap(), partial_hit);
+
+        let partial_hit = PartialHit {
+            secondary_sorting_field_values: vec![3, 0],
+            ..partial_hit
+        };
+        let cursor = encode_search_after_cursor(&partial_hit);
+        assert_eq!(cursor, "1678000000,3,0:01GTQ5ZP7J3M8N6D0YJ4V7X9AB:2:42");
+        assert_eq!(decode_search_after_cursor(&cursor).unwrap(), partial_hit);
+    }
+
+    #[test]
+    fn test_decode_invalid_search_after_cursor() {
+        for cursor in [
+            "",
+            "12",
+            "12:split:1",
+            "12::1:2",
+            "-1:split:1:2",
+            "12:split:1:foo",
+            "12,:split:1:2",
+            "12,foo:split:1:2",
+        ] {
+            let error = decode_search_after_cursor(cursor).unwrap_err();
+            assert!(matches!(error, SearchError::InvalidArgument(_)));
+        }
+    }
+
+    #[test]
+    fn test_search_response_rest_split_metadata() {
+        let hit = |split_id: &str| Hit {
+            json: "{}".to_string(),
+            partial_hit: Some(PartialHit {
+                split_id: split_id.to_string(),
+                ..Default::default()
+            }),
+            snippet: None,
+        };
+        let search_response = SearchResponse {
+            num_hits: 3,
+            hits: vec![hit("split-1"), hit("split-2"), hit("split-1")],
+            ..Default::default()
+        };
+        let search_response_rest = SearchResponseRest::try_from(search_response.clone()).unwrap();
+        assert!(search_response_rest.split_metadata.is_none());
+
+        let search_response = SearchResponse {
+            split_metadata: HashMap::from_iter([(
+                "split-1".to_string(),
+                r#"{"pipeline_version": "v2"}"#.to_string(),
+            )]),
+            ..search_response
+        };
+        let search_response_rest = SearchResponseRest::try_from(search_response).unwrap();
+        assert_eq!(
+            search_response_rest.split_metadata.unwrap(),
+            [
+                json!({"pipeline_version": "v2"}),
+                JsonValue::Null,
+                json!({"pipeline_version": "v2"})
+            ]
+        );
+    }
+}
@@ -477,6 +477,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                split_file: split_meta.split_metadata.split_file(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -555,6 +556,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                split_file: split_meta.split_metadata.split_file(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -612,6 +614,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                split_file: split_meta.split_metadata.split_file(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -705,6 +708,7 @@ mod tests {
                 split_id: split_meta.split_id().to_string(),
                 split_footer_start: split_meta.split_metadata.footer_offsets.start,
                 split_footer_end: split_meta.split_metadata.footer_offsets.end,
+                split_file: split_meta.split_metadata.split_file(),
             })
             .collect();
         let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -17,12 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
 use bytes::Bytes;
 use futures::{StreamExt, TryStreamExt};
 use quickwit_common::uri::Uri;
 use quickwit_config::{build_doc_mapper, IndexConfig};
+use quickwit_doc_mapper::{resolve_field_alias, resolve_query_field_aliases};
 use quickwit_metastore::Metastore;
 use quickwit_proto::{LeafSearchStreamRequest, SearchRequest, SearchStreamRequest};
 use tokio_stream::StreamMap;
@@ -35,7 +36,7 @@ use crate::{list_relevant_splits, SearchError, SearchJobPlacer, SearchServiceCli
 /// Perform a distributed search stream.
 #[instrument(skip(metastore, cluster_client, search_job_placer))]
 pub async fn root_search_stream(
-    search_stream_request: SearchStreamRequest,
+    mut search_stream_request: SearchStreamRequest,
     metastore: &dyn Metastore,
     cluster_client: ClusterClient,
     search_job_placer: &SearchJobPlacer,
@@ -43,16 +44,18 @@ pub async fn root_search_stream(
     // TODO: building a search request should not be necessary for listing splits.
     // This needs some refactoring: relevant splits, metadata_map, jobs...
 
-    let search_request = SearchRequest::from(search_stream_request.clone());
     let index_config: IndexConfig = metastore
-        .index_metadata(&search_request.index_id)
+        .index_metadata(&search_stream_request.index_id)
         .await?
         .into_index_config();
-    let split_metadatas = list_relevant_splits(&search_request, metastore).await?;
     let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
         .map_err(|err| {
             SearchError::InternalError(format!("Failed to build doc mapper. Cause: {err}"))
         })?;
+    resolve_stream_field_aliases(&mut search_stream_request, &doc_mapper.field_aliases());
+
+    let search_request = SearchRequest::from(search_stream_request.clone());
+    let split_metadatas = list_relevant_splits(&search_request, metastore).await?;
 
     // Validates the query by effectively building it against the current schema.
     doc_mapper.query(doc_mapper.schema(), &search_request)?;
@@ -86,6 +89,28 @@ pub async fn root_search_stream(
         .map_ok(|leaf_response| Bytes::from(leaf_response.data)))
 }
 
+/// Replaces the aliases targeted by a search stream request with the path of the field they refer
+/// to.
+fn resolve_stream_field_aliases(
+    search_stream_request: &mut SearchStreamRequest,
+    field_aliases: &BTreeMap<String, String>,
+) {
+    if field_aliases.is_empty() {
+        return;
+    }
+    search_stream_request.query =
+        resolve_query_field_aliases(&search_stream_request.query, field_aliases);
+    for field_name in search_stream_request
+        .search_fields
+        .iter_mut()
+        .chain(search_stream_request.snippet_fields.iter_mut())
+        .chain(std::iter::once(&mut search_stream_request.fast_field))
+        .chain(search_stream_request.partition_by_field.iter_mut())
+    {
+        *field_name = resolve_field_alias(field_name, field_aliases).to_string();
+    }
+}
+
 fn jobs_to_leaf_request(
     request: &SearchStreamRequest,
     doc_mapper_str: &str,
@@ -0,0 +1,331 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Search views, pinning the splits published in an index at a point in time so that several
+//! searches run against exactly the same set of splits, whatever is published, merged, or deleted
+//! in the meantime.
+//!
+//! Views are kept in memory by the node that created them, and only pin the metadata of the
+//! splits: the files of the splits replaced by a merge are deleted by the garbage collector once
+//! the deletion grace period of the index has elapsed, so views should not outlive it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use quickwit_doc_mapper::tag_pruning::extract_tags_from_query;
+use quickwit_metastore::{ListSplitsQuery, Metastore, SplitMetadata, SplitState};
+use quickwit_proto::SearchRequest;
+use serde::{Deserialize, Serialize};
+use ulid::Ulid;
+
+use crate::{prune_splits_by_query, SearchError};
+
+/// Default duration a search view is kept without being used.
+const DEFAULT_SEARCH_VIEW_TTL: Duration = Duration::from_secs(5 * 60); // 5 minutes
+
+/// Maximum duration a search view is kept without being used.
+const MAX_SEARCH_VIEW_TTL: Duration = Duration::from_secs(60 * 60); // 1 hour
+
+/// Search view, returned by the search view API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SearchViewResponse {
+    /// ID of the view, passed as `view_id` to the searches running against it.
+    pub view_id: String,
+    /// ID of the index of the view.
+    pub index_id: String,
+    /// Number of splits pinned by the view.
+    pub num_splits: usize,
+    /// Unix timestamp, in seconds, after which the view expires unless it is used again.
+    pub expiration_timestamp: u64,
+}
+
+#[derive(Clone)]
+struct SearchView {
+    index_id: String,
+    split_metadatas: Arc<Vec<SplitMetadata>>,
+    ttl: Duration,
+}
+
+struct SearchViewEntry {
+    search_view: SearchView,
+    expires_at: Instant,
+}
+
+/// The search views created on this node. A view expires if it is not used within its TTL, and
+/// expired views are evicted lazily.
+#[derive(Default)]
+pub(crate) struct SearchViews {
+    entries: Mutex<HashMap<String, SearchViewEntry>>,
+}
+
+impl SearchViews {
+    fn put(&self, view_id: &str, search_view: SearchView) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires_at > now);
+        let expires_at = now + search_view.ttl;
+        entries.insert(
+            view_id.to_string(),
+            SearchViewEntry {
+                search_view,
+                expires_at,
+            },
+        );
+    }
+
+    /// Returns a view if it has not expired, and extends its expiration by its TTL.
+    fn get(&self, view_id: &str) -> Option<SearchView> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries
+            .get_mut(view_id)
+            .filter(|entry| entry.expires_at > now)?;
+        entry.expires_at = now + entry.search_view.ttl;
+        Some(entry.search_view.clone())
+    }
+
+    /// Removes a view of an index, returning whether it existed and had not expired.
+    fn remove(&self, index_id: &str, view_id: &str) -> bool {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let is_view_of_index = entries.get(view_id).map_or(false, |entry| {
+            entry.search_view.index_id == index_id && entry.expires_at > now
+        });
+        if is_view_of_index {
+            entries.remove(view_id);
+        }
+        is_view_of_index
+    }
+}
+
+fn search_view_ttl(ttl_secs_opt: Option<u32>) -> crate::Result<Duration> {
+    let Some(ttl_secs) = ttl_secs_opt else {
+        return Ok(DEFAULT_SEARCH_VIEW_TTL);
+    };
+    let ttl = Duration::from_secs(ttl_secs as u64);
+    if ttl.is_zero() || ttl > MAX_SEARCH_VIEW_TTL {
+        return Err(SearchError::InvalidArgument(format!(
+            "Search view TTL must be between 1 and {} seconds, but got {ttl_secs}.",
+            MAX_SEARCH_VIEW_TTL.as_secs()
+        )));
+    }
+    Ok(ttl)
+}
+
+fn expiration_timestamp(ttl: Duration) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| (duration + ttl).as_secs())
+        .unwrap_or_default()
+}
+
+/// Creates a view pinning the splits currently published in an index.
+pub(crate) async fn create_search_view(
+    index_id: &str,
+    ttl_secs_opt: Option<u32>,
+    search_views: &SearchViews,
+    metastore: &dyn Metastore,
+) -> crate::Result<SearchViewResponse> {
+    let ttl = search_view_ttl(ttl_secs_opt)?;
+    let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+    let split_metadatas: Vec<SplitMetadata> = metastore
+        .list_splits(query)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .collect();
+    let num_splits = split_metadatas.len();
+    let search_view = SearchView {
+        index_id: index_id.to_string(),
+        split_metadatas: Arc::new(split_metadatas),
+        ttl,
+    };
+    let view_id = Ulid::new().to_string();
+    search_views.put(&view_id, search_view);
+    Ok(SearchViewResponse {
+        view_id,
+        index_id: index_id.to_string(),
+        num_splits,
+        expiration_timestamp: expiration_timestamp(ttl),
+    })
+}
+
+/// Deletes a search view of an index.
+pub(crate) fn delete_search_view(
+    index_id: &str,
+    view_id: &str,
+    search_views: &SearchViews,
+) -> crate::Result<()> {
+    if !search_views.remove(index_id, view_id) {
+        return Err(SearchError::SearchViewDoesNotExist(view_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Returns the splits of the view of a search request that may hold documents matching it: the
+/// splits are pruned with the time range, the tags, the field ranges, and the bloom filters, like
+/// the splits listed from the metastore.
+pub(crate) fn search_view_splits(
+    search_request: &SearchRequest,
+    search_views: &SearchViews,
+) -> crate::Result<Vec<SplitMetadata>> {
+    let view_id = search_request.view_id.as_deref().unwrap_or_default();
+    let search_view = search_views
+        .get(view_id)
+        .filter(|search_view| search_view.index_id == search_request.index_id)
+        .ok_or_else(|| SearchError::SearchViewDoesNotExist(view_id.to_string()))?;
+    let tags_filter_opt = extract_tags_from_query(&search_request.query)?;
+    let split_metadatas: Vec<SplitMetadata> = search_view
+        .split_metadatas
+        .iter()
+        .filter(|split_metadata| is_split_in_time_range(search_request, split_metadata))
+        .filter(|split_metadata| {
+            tags_filter_opt
+                .as_ref()
+                .map(|tags_filter| tags_filter.evaluate(&split_metadata.tags))
+                .unwrap_or(true)
+        })
+        .cloned()
+        .collect();
+    prune_splits_by_query(search_request, split_metadatas)
+}
+
+fn is_split_in_time_range(search_request: &SearchRequest, split_metadata: &SplitMetadata) -> bool {
+    let Some(time_range) = &split_metadata.time_range else {
+        return true;
+    };
+    let is_after_start = search_request
+        .start_timestamp
+        .map_or(true, |start_timestamp| *time_range.end() >= start_timestamp);
+    let is_before_end = search_request
+        .end_timestamp
+        .map_or(true, |end_timestamp| *time_range.start() < end_timestamp);
+    is_after_start && is_before_end
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_indexing::mock_split;
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+
+    fn split_metadata(split_id: &str, time_range: std::ops::RangeInclusive<i64>) -> SplitMetadata {
+        let mut split_metadata = mock_split(split_id).split_metadata;
+        split_metadata.time_range = Some(time_range);
+        split_metadata
+    }
+
+    #[tokio::test]
+    async fn test_search_views() {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_splits().times(1).returning(|_| {
+            let mut split_1 = mock_split("split-1");
+            split_1.split_metadata.time_range = Some(0..=99);
+            let mut split_2 = mock_split("split-2");
+            split_2.split_metadata.time_range = Some(100..=199);
+            Ok(vec![split_1, split_2])
+        });
+        let search_views = SearchViews::default();
+        let search_view_response =
+            create_search_view("test-index", Some(60), &search_views, &metastore)
+                .await
+                .unwrap();
+        assert_eq!(search_view_response.index_id, "test-index");
+        assert_eq!(search_view_response.num_splits, 2);
+
+        let search_request = SearchRequest {
+            index_id: "test-index".to_string(),
+            query: "*".to_string(),
+            start_timestamp: Some(150),
+            view_id: Some(search_view_response.view_id.clone()),
+            ..Default::default()
+        };
+        let split_metadatas = search_view_splits(&search_request, &search_views).unwrap();
+        assert_eq!(split_metadatas.len(), 1);
+        assert_eq!(split_metadatas[0].split_id, "split-2");
+
+        let other_index_search_request = SearchRequest {
+            index_id: "other-index".to_string(),
+            ..search_request.clone()
+        };
+        let error = search_view_splits(&other_index_search_request, &search_views).unwrap_err();
+        assert!(matches!(error, SearchError::SearchViewDoesNotExist(_)));
+
+        let error = delete_search_view("other-index", &search_view_response.view_id, &search_views)
+            .unwrap_err();
+        assert!(matches!(error, SearchError::SearchViewDoesNotExist(_)));
+
+        delete_search_view("test-index", &search_view_response.view_id, &search_views).unwrap();
+        let error = search_view_splits(&search_request, &search_views).unwrap_err();
+        assert!(matches!(error, SearchError::SearchViewDoesNotExist(_)));
+    }
+
+    #[test]
+    fn test_search_views_expire() {
+        let search_views = SearchViews::default();
+        let search_view = SearchView {
+            index_id: "test-index".to_string(),
+            split_metadatas: Arc::new(vec![split_metadata("split-1", 0..=99)]),
+            ttl: Duration::ZERO,
+        };
+        search_views.put("expired-view", search_view);
+        assert!(search_views.get("expired-view").is_none());
+        assert!(!search_views.remove("test-index", "expired-view"));
+    }
+
+    #[test]
+    fn test_search_view_ttl() {
+        assert_eq!(search_view_ttl(None).unwrap(), DEFAULT_SEARCH_VIEW_TTL);
+        assert_eq!(search_view_ttl(Some(60)).unwrap(), Duration::from_secs(60));
+        search_view_ttl(Some(0)).unwrap_err();
+        search_view_ttl(Some(2 * 60 * 60)).unwrap_err();
+    }
+
+    #[test]
+    fn test_is_split_in_time_range() {
+        let split_metadata = split_metadata("split-1", 100..=199);
+        let search_request = |start_timestamp, end_timestamp| SearchRequest {
+            start_timestamp,
+            end_timestamp,
+            ..Default::default()
+        };
+        assert!(is_split_in_time_range(
+            &search_request(None, None),
+            &split_metadata
+        ));
+        assert!(is_split_in_time_range(
+            &search_request(Some(199), None),
+            &split_metadata
+        ));
+        assert!(!is_split_in_time_range(
+            &search_request(Some(200), None),
+            &split_metadata
+        ));
+        assert!(!is_split_in_time_range(
+            &search_request(None, Some(100)),
+            &split_metadata
+        ));
+        assert!(is_split_in_time_range(
+            &search_request(Some(0), Some(101)),
+            &split_metadata
+        ));
+    }
+}
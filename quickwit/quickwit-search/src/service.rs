@@ -19,9 +19,12 @@
 
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::future::try_join_all;
+use futures::StreamExt;
 use quickwit_common::uri::Uri;
 use quickwit_config::SearcherConfig;
 use quickwit_doc_mapper::DocMapper;
@@ -29,17 +32,39 @@ use quickwit_metastore::Metastore;
 use quickwit_proto::{
     FetchDocsRequest, FetchDocsResponse, LeafListTermsRequest, LeafListTermsResponse,
     LeafSearchRequest, LeafSearchResponse, LeafSearchStreamRequest, LeafSearchStreamResponse,
-    ListTermsRequest, ListTermsResponse, SearchRequest, SearchResponse, SearchStreamRequest,
+    LeafWarmupRequest, ListTermsRequest, ListTermsResponse, ScrollRequest, SearchHitsChunk,
+    SearchRequest, SearchResponse, SearchStreamRequest, WarmupRequest, WarmupResponse,
 };
 use quickwit_storage::{Cache, MemorySizedCache, QuickwitCache, StorageUriResolver};
 use tokio::sync::Semaphore;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::info;
 
+use crate::admission::{SearchAdmissionController, SearchPriority};
+use crate::async_search::{
+    delete_async_search, get_async_search, submit_async_search, AsyncSearchResponse, AsyncSearches,
+};
+use crate::count::{count_request, root_count};
+use crate::cross_cluster::{root_cross_cluster_search, RemoteCluster};
+use crate::explain::{root_explain, SearchExplanation};
+use crate::field_mask::add_caller_field_masks;
+use crate::index_alias::{
+    index_search_request, merge_index_search_responses, resolve_index_ids, resolve_single_index_id,
+    validate_multi_index_search,
+};
+use crate::leaf_cache::LeafSearchCache;
+use crate::query_log::{QueryLogger, SearchProfile};
+use crate::root::{root_search_hits_stream, root_search_on_splits, root_search_with_profile};
+use crate::scroll::{root_scroll, root_search_with_scroll, ScrollContexts};
 use crate::search_stream::{leaf_search_stream, root_search_stream};
+use crate::search_view::{
+    create_search_view, delete_search_view, search_view_splits, SearchViewResponse, SearchViews,
+};
+use crate::split_searcher_cache::SplitSearcherCache;
+use crate::split_warmup::{leaf_warmup, root_warmup};
 use crate::{
-    fetch_docs, leaf_list_terms, leaf_search, root_list_terms, root_search, ClusterClient,
-    SearchError, SearchJobPlacer,
+    fetch_docs, leaf_list_terms, leaf_search, root_list_terms, ClusterClient, SearchError,
+    SearchJobPlacer,
 };
 
 #[derive(Clone)]
@@ -50,6 +75,12 @@ pub struct SearchServiceImpl {
     cluster_client: ClusterClient,
     search_job_placer: SearchJobPlacer,
     searcher_context: Arc<SearcherContext>,
+    scroll_contexts: Arc<ScrollContexts>,
+    remote_clusters: Arc<Vec<RemoteCluster>>,
+    async_searches: Arc<AsyncSearches>,
+    search_views: Arc<SearchViews>,
+    admission_controller: SearchAdmissionController,
+    query_logger_opt: Option<QueryLogger>,
 }
 
 /// Trait representing a search service.
@@ -93,6 +124,13 @@ pub trait SearchService: 'static + Send + Sync {
         request: LeafSearchStreamRequest,
     ) -> crate::Result<UnboundedReceiverStream<crate::Result<LeafSearchStreamResponse>>>;
 
+    /// Performs a root search streaming the hits of each leaf as soon as they are fetched,
+    /// instead of merging the hits of all the leaves.
+    async fn root_search_hits_stream(
+        &self,
+        request: SearchRequest,
+    ) -> crate::Result<Pin<Box<dyn futures::Stream<Item = crate::Result<SearchHitsChunk>> + Send>>>;
+
     /// Root search API.
     /// This RPC identifies the set of splits on which the query should run on,
     /// and dispatches the multiple calls to `LeafSearch`.
@@ -111,6 +149,55 @@ pub trait SearchService: 'static + Send + Sync {
         &self,
         request: LeafListTermsRequest,
     ) -> crate::Result<LeafListTermsResponse>;
+
+    /// Scroll API.
+    /// Returns the next page of hits of a search started with `scroll_ttl_secs`, searching the
+    /// splits pinned when the search started.
+    async fn scroll(&self, request: ScrollRequest) -> crate::Result<SearchResponse>;
+
+    /// Starts an async search in the background, kept for `keep_alive_secs` or a day by default.
+    async fn submit_async_search(
+        &self,
+        request: SearchRequest,
+        keep_alive_secs: Option<u32>,
+    ) -> crate::Result<AsyncSearchResponse>;
+
+    /// Returns the progress of an async search, and its response once it has completed.
+    async fn get_async_search(
+        &self,
+        index_id: String,
+        async_search_id: String,
+    ) -> crate::Result<AsyncSearchResponse>;
+
+    /// Cancels an async search if it is running, and deletes its response.
+    async fn delete_async_search(
+        &self,
+        index_id: String,
+        async_search_id: String,
+    ) -> crate::Result<()>;
+
+    /// Explains how a search is executed: the parsed query, the pruned splits, and the time spent
+    /// searching each split.
+    async fn explain(&self, request: SearchRequest) -> crate::Result<SearchExplanation>;
+
+    /// Creates a view pinning the splits currently published in an index, kept for `ttl_secs` or
+    /// five minutes by default after its last use. The searches setting `view_id` run against
+    /// these splits only.
+    async fn create_search_view(
+        &self,
+        index_id: String,
+        ttl_secs: Option<u32>,
+    ) -> crate::Result<SearchViewResponse>;
+
+    /// Deletes a search view, releasing the splits it pins.
+    async fn delete_search_view(&self, index_id: String, view_id: String) -> crate::Result<()>;
+
+    /// Warms up splits of an index on the searchers that the searches will send them to, within
+    /// the download budget of the request.
+    async fn root_warmup(&self, request: WarmupRequest) -> crate::Result<WarmupResponse>;
+
+    /// Warms up the given splits on this searcher, within the download budget of the request.
+    async fn leaf_warmup(&self, request: LeafWarmupRequest) -> crate::Result<WarmupResponse>;
 }
 
 impl SearchServiceImpl {
@@ -120,17 +207,185 @@ impl SearchServiceImpl {
         storage_uri_resolver: StorageUriResolver,
         cluster_client: ClusterClient,
         search_job_placer: SearchJobPlacer,
-        searcher_config: SearcherConfig,
+        searcher_context: Arc<SearcherContext>,
     ) -> Self {
-        let searcher_context = Arc::new(SearcherContext::new(searcher_config));
+        let searcher_config = &searcher_context.searcher_config;
+        let remote_clusters = Arc::new(RemoteCluster::from_configs(
+            &searcher_config.remote_clusters,
+        ));
+        let admission_controller =
+            SearchAdmissionController::new(searcher_config.admission.clone());
         SearchServiceImpl {
             metastore,
             storage_uri_resolver,
             cluster_client,
             search_job_placer,
             searcher_context,
+            scroll_contexts: Arc::default(),
+            remote_clusters,
+            async_searches: Arc::default(),
+            search_views: Arc::default(),
+            admission_controller,
+            query_logger_opt: None,
         }
     }
+
+    /// Records the root searches handled by this service into the query log.
+    pub fn with_query_logger(mut self, query_logger: QueryLogger) -> Self {
+        self.query_logger_opt = Some(query_logger);
+        self
+    }
+
+    /// Resolves the index alias or index ID pattern targeted by the root search, if any, and
+    /// dispatches the search to the index or indexes it targets.
+    async fn dispatch_root_search(
+        &self,
+        search_request: &SearchRequest,
+        search_profile: &mut SearchProfile,
+    ) -> crate::Result<SearchResponse> {
+        let _admission_permit = self
+            .admission_controller
+            .admit(&search_request.index_id, SearchPriority::Interactive)
+            .await?;
+        let index_ids =
+            resolve_index_ids(self.metastore.as_ref(), &search_request.index_id).await?;
+        if let [index_id] = &index_ids[..] {
+            let mut index_search_request = SearchRequest {
+                index_id: index_id.clone(),
+                ..search_request.clone()
+            };
+            add_caller_field_masks(&mut index_search_request);
+            return self
+                .dispatch_index_root_search(&index_search_request, search_profile)
+                .await;
+        }
+        validate_multi_index_search(search_request)?;
+        let index_searches = index_ids.iter().map(|index_id| async move {
+            // The phases of the searches run concurrently, so they are not profiled.
+            let mut index_search_request = index_search_request(search_request, index_id);
+            add_caller_field_masks(&mut index_search_request);
+            self.dispatch_index_root_search(&index_search_request, &mut SearchProfile::default())
+                .await
+        });
+        let search_responses = try_join_all(index_searches).await?;
+        Ok(merge_index_search_responses(
+            search_request,
+            search_responses,
+        ))
+    }
+
+    /// Dispatches a root search on a single index to the cross-cluster, count, scroll, or regular
+    /// search, recording the phases of the latter two into `search_profile`.
+    async fn dispatch_index_root_search(
+        &self,
+        search_request: &SearchRequest,
+        search_profile: &mut SearchProfile,
+    ) -> crate::Result<SearchResponse> {
+        if search_request.view_id.is_some() {
+            return self
+                .dispatch_search_view_root_search(search_request, search_profile)
+                .await;
+        }
+        if search_request.cross_cluster {
+            let search_request = if search_request.count_only {
+                count_request(search_request)
+            } else {
+                search_request.clone()
+            };
+            return root_cross_cluster_search(
+                &search_request,
+                &self.remote_clusters,
+                self.metastore.as_ref(),
+                &self.cluster_client,
+                &self.search_job_placer,
+            )
+            .await;
+        }
+        if search_request.count_only {
+            return root_count(
+                search_request,
+                self.metastore.as_ref(),
+                &self.cluster_client,
+                &self.search_job_placer,
+            )
+            .await;
+        }
+        if search_request.scroll_ttl_secs.is_some() {
+            return root_search_with_scroll(
+                search_request,
+                None,
+                &self.scroll_contexts,
+                self.metastore.as_ref(),
+                &self.cluster_client,
+                &self.search_job_placer,
+                search_profile,
+            )
+            .await;
+        }
+        root_search_with_profile(
+            search_request,
+            self.metastore.as_ref(),
+            &self.cluster_client,
+            &self.search_job_placer,
+            search_profile,
+        )
+        .await
+    }
+
+    /// Dispatches a root search against the splits pinned by a search view to the count, scroll,
+    /// or regular search.
+    async fn dispatch_search_view_root_search(
+        &self,
+        search_request: &SearchRequest,
+        search_profile: &mut SearchProfile,
+    ) -> crate::Result<SearchResponse> {
+        if search_request.cross_cluster || search_request.hybrid_ranking.is_some() {
+            return Err(SearchError::InvalidArgument(
+                "Searches against a search view do not support `cross_cluster` or `hybrid`."
+                    .to_string(),
+            ));
+        }
+        let split_metadatas = search_view_splits(search_request, &self.search_views)?;
+        search_profile.record_searched_splits(&split_metadatas);
+
+        if search_request.scroll_ttl_secs.is_some() {
+            return root_search_with_scroll(
+                search_request,
+                Some(split_metadatas),
+                &self.scroll_contexts,
+                self.metastore.as_ref(),
+                &self.cluster_client,
+                &self.search_job_placer,
+                search_profile,
+            )
+            .await;
+        }
+        let search_request = if search_request.count_only {
+            count_request(search_request)
+        } else {
+            search_request.clone()
+        };
+        let (search_response, _) = root_search_on_splits(
+            &search_request,
+            Some(split_metadatas),
+            self.metastore.as_ref(),
+            &self.cluster_client,
+            &self.search_job_placer,
+            search_profile,
+        )
+        .await?;
+        Ok(search_response)
+    }
+}
+
+/// Rejects the searches setting `view_id` sent to an API that does not support search views.
+fn reject_search_view(search_request: &SearchRequest, api: &str) -> crate::Result<()> {
+    if search_request.view_id.is_some() {
+        return Err(SearchError::InvalidArgument(format!(
+            "The {api} does not support `view_id`."
+        )));
+    }
+    Ok(())
 }
 
 fn deserialize_doc_mapper(doc_mapper_str: &str) -> crate::Result<Arc<dyn DocMapper>> {
@@ -143,15 +398,20 @@ fn deserialize_doc_mapper(doc_mapper_str: &str) -> crate::Result<Arc<dyn DocMapp
 #[async_trait]
 impl SearchService for SearchServiceImpl {
     async fn root_search(&self, search_request: SearchRequest) -> crate::Result<SearchResponse> {
-        let search_result = root_search(
-            &search_request,
-            self.metastore.as_ref(),
-            &self.cluster_client,
-            &self.search_job_placer,
-        )
-        .await?;
-
-        Ok(search_result)
+        let start_instant = Instant::now();
+        let mut search_profile = SearchProfile::default();
+        let search_result = self
+            .dispatch_root_search(&search_request, &mut search_profile)
+            .await;
+        if let Some(query_logger) = &self.query_logger_opt {
+            query_logger.record(
+                &search_request,
+                &search_result,
+                start_instant.elapsed(),
+                search_profile,
+            );
+        }
+        search_result
     }
 
     async fn leaf_search(
@@ -206,6 +466,15 @@ impl SearchService for SearchServiceImpl {
         &self,
         stream_request: SearchStreamRequest,
     ) -> crate::Result<Pin<Box<dyn futures::Stream<Item = crate::Result<Bytes>> + Send>>> {
+        let admission_permit = self
+            .admission_controller
+            .admit(&stream_request.index_id, SearchPriority::Batch)
+            .await?;
+        let stream_request = SearchStreamRequest {
+            index_id: resolve_single_index_id(self.metastore.as_ref(), &stream_request.index_id)
+                .await?,
+            ..stream_request
+        };
         let data = root_search_stream(
             stream_request,
             self.metastore.as_ref(),
@@ -213,6 +482,11 @@ impl SearchService for SearchServiceImpl {
             &self.search_job_placer,
         )
         .await?;
+        // The permit is released once the stream is consumed or dropped.
+        let data = data.map(move |item| {
+            let _admission_permit = &admission_permit;
+            item
+        });
         Ok(Box::pin(data))
     }
 
@@ -239,10 +513,53 @@ impl SearchService for SearchServiceImpl {
         Ok(leaf_receiver)
     }
 
+    async fn root_search_hits_stream(
+        &self,
+        search_request: SearchRequest,
+    ) -> crate::Result<Pin<Box<dyn futures::Stream<Item = crate::Result<SearchHitsChunk>> + Send>>>
+    {
+        reject_search_view(&search_request, "hits stream API")?;
+        let admission_permit = self
+            .admission_controller
+            .admit(&search_request.index_id, SearchPriority::Batch)
+            .await?;
+        let mut search_request = SearchRequest {
+            index_id: resolve_single_index_id(self.metastore.as_ref(), &search_request.index_id)
+                .await?,
+            ..search_request
+        };
+        add_caller_field_masks(&mut search_request);
+        let hits_stream = root_search_hits_stream(
+            &search_request,
+            self.metastore.as_ref(),
+            self.cluster_client.clone(),
+            &self.search_job_placer,
+        )
+        .await?;
+        // The permit is released once the stream is consumed or dropped.
+        let hits_stream = hits_stream.map(move |item| {
+            let _admission_permit = &admission_permit;
+            item
+        });
+        Ok(Box::pin(hits_stream))
+    }
+
     async fn root_list_terms(
         &self,
         list_terms_request: ListTermsRequest,
     ) -> crate::Result<ListTermsResponse> {
+        let _admission_permit = self
+            .admission_controller
+            .admit(&list_terms_request.index_id, SearchPriority::Interactive)
+            .await?;
+        let list_terms_request = ListTermsRequest {
+            index_id: resolve_single_index_id(
+                self.metastore.as_ref(),
+                &list_terms_request.index_id,
+            )
+            .await?,
+            ..list_terms_request
+        };
         let search_result = root_list_terms(
             &list_terms_request,
             self.metastore.as_ref(),
@@ -267,17 +584,170 @@ impl SearchService for SearchServiceImpl {
             .storage_uri_resolver
             .resolve(&Uri::from_well_formed(leaf_search_request.index_uri))?;
         let split_ids = leaf_search_request.split_offsets;
+        let doc_mapper_opt = leaf_search_request
+            .doc_mapper
+            .as_deref()
+            .map(deserialize_doc_mapper)
+            .transpose()?;
 
         let leaf_search_response = leaf_list_terms(
             self.searcher_context.clone(),
             &search_request,
             storage.clone(),
             &split_ids[..],
+            doc_mapper_opt,
         )
         .await?;
 
         Ok(leaf_search_response)
     }
+
+    async fn scroll(&self, scroll_request: ScrollRequest) -> crate::Result<SearchResponse> {
+        let index_id = self
+            .scroll_contexts
+            .index_id(&scroll_request.scroll_id)
+            .unwrap_or_default();
+        let _admission_permit = self
+            .admission_controller
+            .admit(&index_id, SearchPriority::Interactive)
+            .await?;
+        root_scroll(
+            &scroll_request,
+            &self.scroll_contexts,
+            self.metastore.as_ref(),
+            &self.cluster_client,
+            &self.search_job_placer,
+        )
+        .await
+    }
+
+    async fn submit_async_search(
+        &self,
+        search_request: SearchRequest,
+        keep_alive_secs: Option<u32>,
+    ) -> crate::Result<AsyncSearchResponse> {
+        reject_search_view(&search_request, "async search API")?;
+        let admission_permit = self
+            .admission_controller
+            .admit(&search_request.index_id, SearchPriority::Batch)
+            .await?;
+        let mut search_request = SearchRequest {
+            index_id: resolve_single_index_id(self.metastore.as_ref(), &search_request.index_id)
+                .await?,
+            ..search_request
+        };
+        add_caller_field_masks(&mut search_request);
+        submit_async_search(
+            search_request,
+            keep_alive_secs,
+            admission_permit,
+            self.async_searches.clone(),
+            self.metastore.clone(),
+            self.storage_uri_resolver.clone(),
+            self.cluster_client.clone(),
+            self.search_job_placer.clone(),
+        )
+        .await
+    }
+
+    async fn get_async_search(
+        &self,
+        index_id: String,
+        async_search_id: String,
+    ) -> crate::Result<AsyncSearchResponse> {
+        let index_id = resolve_single_index_id(self.metastore.as_ref(), &index_id).await?;
+        get_async_search(
+            &index_id,
+            &async_search_id,
+            &self.async_searches,
+            self.metastore.as_ref(),
+            &self.storage_uri_resolver,
+        )
+        .await
+    }
+
+    async fn delete_async_search(
+        &self,
+        index_id: String,
+        async_search_id: String,
+    ) -> crate::Result<()> {
+        let index_id = resolve_single_index_id(self.metastore.as_ref(), &index_id).await?;
+        delete_async_search(
+            &index_id,
+            &async_search_id,
+            &self.async_searches,
+            self.metastore.as_ref(),
+            &self.storage_uri_resolver,
+        )
+        .await
+    }
+
+    async fn explain(&self, search_request: SearchRequest) -> crate::Result<SearchExplanation> {
+        reject_search_view(&search_request, "explain API")?;
+        let _admission_permit = self
+            .admission_controller
+            .admit(&search_request.index_id, SearchPriority::Interactive)
+            .await?;
+        let search_request = SearchRequest {
+            index_id: resolve_single_index_id(self.metastore.as_ref(), &search_request.index_id)
+                .await?,
+            ..search_request
+        };
+        root_explain(
+            &search_request,
+            self.metastore.as_ref(),
+            &self.storage_uri_resolver,
+            &self.searcher_context,
+        )
+        .await
+    }
+
+    async fn create_search_view(
+        &self,
+        index_id: String,
+        ttl_secs: Option<u32>,
+    ) -> crate::Result<SearchViewResponse> {
+        let index_id = resolve_single_index_id(self.metastore.as_ref(), &index_id).await?;
+        create_search_view(
+            &index_id,
+            ttl_secs,
+            &self.search_views,
+            self.metastore.as_ref(),
+        )
+        .await
+    }
+
+    async fn delete_search_view(&self, index_id: String, view_id: String) -> crate::Result<()> {
+        let index_id = resolve_single_index_id(self.metastore.as_ref(), &index_id).await?;
+        delete_search_view(&index_id, &view_id, &self.search_views)
+    }
+
+    async fn root_warmup(&self, warmup_request: WarmupRequest) -> crate::Result<WarmupResponse> {
+        let warmup_request = WarmupRequest {
+            index_id: resolve_single_index_id(self.metastore.as_ref(), &warmup_request.index_id)
+                .await?,
+            ..warmup_request
+        };
+        root_warmup(
+            &warmup_request,
+            self.metastore.as_ref(),
+            &self.search_job_placer,
+        )
+        .await
+    }
+
+    async fn leaf_warmup(
+        &self,
+        leaf_warmup_request: LeafWarmupRequest,
+    ) -> crate::Result<WarmupResponse> {
+        info!(splits=?leaf_warmup_request.split_offsets, "leaf_warmup");
+        let storage = self
+            .storage_uri_resolver
+            .resolve(&Uri::from_well_formed(&leaf_warmup_request.index_uri))?;
+        let warmup_response =
+            leaf_warmup(self.searcher_context.clone(), storage, leaf_warmup_request).await;
+        Ok(warmup_response)
+    }
 }
 
 /// [`SearcherContext`] provides a common set of variables
@@ -294,6 +764,12 @@ pub struct SearcherContext {
     pub split_footer_cache: MemorySizedCache<String>,
     /// Fast fields cache.
     pub fast_fields_cache: Arc<dyn Cache>,
+    /// Storage of the fast fields cache, kept to resize it.
+    fast_fields_slice_cache: Arc<MemorySizedCache>,
+    /// Leaf search responses cache.
+    pub leaf_search_cache: LeafSearchCache,
+    /// Opened split searchers cache.
+    pub split_searcher_cache: SplitSearcherCache,
 }
 
 impl SearcherContext {
@@ -309,13 +785,49 @@ impl SearcherContext {
             Semaphore::new(searcher_config.max_num_concurrent_split_streams);
         let fast_field_cache_capacity =
             searcher_config.fast_field_cache_capacity.get_bytes() as usize;
-        let storage_long_term_cache = Arc::new(QuickwitCache::new(fast_field_cache_capacity));
+        let fast_fields_slice_cache = Arc::new(MemorySizedCache::with_capacity_in_bytes(
+            fast_field_cache_capacity,
+            &quickwit_storage::STORAGE_METRICS.fast_field_cache,
+        ));
+        let storage_long_term_cache = Arc::new(QuickwitCache::with_fast_field_cache(
+            fast_fields_slice_cache.clone(),
+        ));
+        let leaf_search_cache = LeafSearchCache::with_capacity_in_bytes(
+            searcher_config.leaf_search_cache_capacity.get_bytes() as usize,
+        );
+        let split_searcher_cache = SplitSearcherCache::with_capacity_in_bytes(
+            searcher_config.split_searcher_cache_capacity.get_bytes() as usize,
+        );
         Self {
             searcher_config,
             split_footer_cache: global_split_footer_cache,
             leaf_search_split_semaphore,
             split_stream_semaphore,
             fast_fields_cache: storage_long_term_cache,
+            fast_fields_slice_cache,
+            leaf_search_cache,
+            split_searcher_cache,
         }
     }
+
+    /// Resizes the caches to the capacities of the given config. The other settings of the
+    /// searcher require a restart.
+    pub fn update_cache_capacities(&self, searcher_config: &SearcherConfig) {
+        let split_footer_cache_capacity =
+            searcher_config.split_footer_cache_capacity.get_bytes() as usize;
+        self.split_footer_cache
+            .set_capacity_in_bytes(split_footer_cache_capacity);
+        let fast_field_cache_capacity =
+            searcher_config.fast_field_cache_capacity.get_bytes() as usize;
+        self.fast_fields_slice_cache
+            .set_capacity_in_bytes(fast_field_cache_capacity);
+        let leaf_search_cache_capacity =
+            searcher_config.leaf_search_cache_capacity.get_bytes() as usize;
+        self.leaf_search_cache
+            .set_capacity_in_bytes(leaf_search_cache_capacity);
+        let split_searcher_cache_capacity =
+            searcher_config.split_searcher_cache_capacity.get_bytes() as usize;
+        self.split_searcher_cache
+            .set_capacity_in_bytes(split_searcher_cache_capacity);
+    }
 }
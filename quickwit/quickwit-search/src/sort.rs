@@ -0,0 +1,423 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Sorting of the hits by a list of sort fields.
+//!
+//! The hits are sorted by the first sort field, and the ties are broken by the following sort
+//! fields, then by the hit address. Each sort field has its own order and places the documents
+//! missing the field first or last. A sort field with an `origin` sorts the hits by the distance
+//! between the origin and the closest point of a `geo_point` field.
+//!
+//! Single-valued fast fields store a default value for the documents missing the field, so the
+//! missing placement only applies to multi-valued fast fields, runtime fields, `geo_point` fields,
+//! and splits indexed before the field was added to the doc mapping. The documents are sorted by
+//! the minimum or the maximum of the values of a multi-valued fast field, according to the mode
+//! of the sort field.
+
+use quickwit_doc_mapper::{DocMapper, GeoPoint, RuntimeFields};
+use quickwit_proto::{SearchRequest, SortOrder};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tantivy::schema::FieldType;
+
+use crate::collector::SortBy;
+use crate::SearchError;
+
+/// Maximum number of sort fields of a search request.
+const MAX_NUM_SORT_FIELDS: usize = 8;
+
+/// Order of a sort field.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortFieldOrder {
+    Asc,
+    Desc,
+}
+
+impl From<SortFieldOrder> for SortOrder {
+    fn from(order: SortFieldOrder) -> Self {
+        match order {
+            SortFieldOrder::Asc => SortOrder::Asc,
+            SortFieldOrder::Desc => SortOrder::Desc,
+        }
+    }
+}
+
+/// Placement of the documents missing a sort field.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingPlacement {
+    First,
+    #[default]
+    Last,
+}
+
+/// Value of a multi-valued field the documents are sorted by.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    Min,
+    Max,
+}
+
+impl SortMode {
+    /// Returns the mode of a sort in the order `order` that does not specify any: the lowest
+    /// value for the ascending order and the highest value for the descending order.
+    pub(crate) fn default_for_order(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Asc => SortMode::Min,
+            SortOrder::Desc => SortMode::Max,
+        }
+    }
+}
+
+/// A sort criterion of a search request.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SortField {
+    /// Name of a fast field, a runtime field, a `geo_point` field if `origin` is set, or
+    /// `_score`.
+    pub field: String,
+    /// Sort order. Defaults to descending, or to ascending for a geo distance.
+    #[serde(default)]
+    pub order: Option<SortFieldOrder>,
+    /// Placement of the documents missing the field, regardless of the order.
+    #[serde(default)]
+    pub missing: MissingPlacement,
+    /// Value of a multi-valued fast field the documents are sorted by. Defaults to the minimum
+    /// for the ascending order and to the maximum for the descending order.
+    #[serde(default)]
+    pub mode: Option<SortMode>,
+    /// Geo point the distances are computed from, in any of the geo point formats.
+    #[serde(default)]
+    pub origin: Option<JsonValue>,
+}
+
+impl SortField {
+    fn sort_order(&self) -> SortOrder {
+        match self.order {
+            Some(order) => order.into(),
+            None if self.origin.is_some() => SortOrder::Asc,
+            None => SortOrder::Desc,
+        }
+    }
+
+    fn sort_mode(&self) -> SortMode {
+        self.mode
+            .unwrap_or_else(|| SortMode::default_for_order(self.sort_order()))
+    }
+
+    fn origin(&self) -> crate::Result<Option<GeoPoint>> {
+        self.origin
+            .as_ref()
+            .map(|origin| {
+                GeoPoint::from_json(origin).map_err(|error| {
+                    SearchError::InvalidArgument(format!(
+                        "Invalid origin of sort field `{}`: {error}",
+                        self.field
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+/// Parses the JSON serialized sort fields of the user request.
+pub fn parse_sort_fields(sort_fields_json_opt: Option<&str>) -> crate::Result<Vec<SortField>> {
+    let Some(sort_fields_json) = sort_fields_json_opt else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(sort_fields_json)
+        .map_err(|error| SearchError::InvalidArgument(format!("Invalid sort fields: {error}")))
+}
+
+/// Checks that the sort fields can be resolved against the doc mapping and the runtime fields
+/// of the request.
+pub fn validate_sort_fields(
+    doc_mapper: &dyn DocMapper,
+    search_request: &SearchRequest,
+    sort_fields: &[SortField],
+) -> crate::Result<()> {
+    if sort_fields.is_empty() || sort_fields.len() > MAX_NUM_SORT_FIELDS {
+        return Err(SearchError::InvalidArgument(format!(
+            "The number of sort fields must be between 1 and {MAX_NUM_SORT_FIELDS}, but got {}.",
+            sort_fields.len()
+        )));
+    }
+    if search_request.sort_by_field.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "`sort_by_field` and sort fields cannot be set at the same time.".to_string(),
+        ));
+    }
+    if search_request.knn_query.is_some() {
+        return Err(SearchError::InvalidArgument(
+            "kNN search hits are sorted by similarity, sort fields cannot be set.".to_string(),
+        ));
+    }
+    let runtime_fields = RuntimeFields::parse(search_request.runtime_fields.as_deref())
+        .map_err(|error| SearchError::InvalidArgument(error.to_string()))?;
+    let geo_point_field_names = doc_mapper.geo_point_field_names();
+    let schema = doc_mapper.schema();
+    for sort_field in sort_fields {
+        let field_name = sort_field.field.as_str();
+        if sort_field.origin()?.is_some() {
+            if !geo_point_field_names.contains(field_name) {
+                return Err(SearchError::InvalidArgument(format!(
+                    "Sort field `{field_name}` has an origin but is not a `geo_point` field."
+                )));
+            }
+            continue;
+        }
+        if geo_point_field_names.contains(field_name) {
+            return Err(SearchError::InvalidArgument(format!(
+                "Sorting by `geo_point` field `{field_name}` requires an origin."
+            )));
+        }
+        if field_name == "_score" || runtime_fields.get(field_name).is_some() {
+            continue;
+        }
+        let field = schema.get_field(field_name).map_err(|_| {
+            SearchError::InvalidArgument(format!("Sort field `{field_name}` does not exist."))
+        })?;
+        let is_fast_field = match schema.get_field_entry(field).field_type() {
+            FieldType::U64(options)
+            | FieldType::I64(options)
+            | FieldType::F64(options)
+            | FieldType::Bool(options) => options.get_fastfield_cardinality().is_some(),
+            FieldType::Date(options) => options.get_fastfield_cardinality().is_some(),
+            _ => false,
+        };
+        if !is_fast_field {
+            return Err(SearchError::InvalidArgument(format!(
+                "Sort field `{field_name}` is not a numeric, bool, or datetime fast field."
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Resolves a sort field to the sorting criterion of the collector.
+pub(crate) fn sort_field_to_sort_by(
+    sort_field: &SortField,
+    runtime_fields: &RuntimeFields,
+) -> crate::Result<SortBy> {
+    let order = sort_field.sort_order();
+    let missing_first = sort_field.missing == MissingPlacement::First;
+    if let Some(origin) = sort_field.origin()? {
+        return Ok(SortBy::GeoDistance {
+            field_name: sort_field.field.clone(),
+            origin,
+            order,
+            missing_first,
+        });
+    }
+    if sort_field.field == "_score" {
+        return Ok(SortBy::Score { order });
+    }
+    if let Some(expr) = runtime_fields.get(&sort_field.field) {
+        return Ok(SortBy::RuntimeField {
+            expr: expr.clone(),
+            order,
+            missing_first,
+        });
+    }
+    Ok(SortBy::FastField {
+        field_name: sort_field.field.clone(),
+        order,
+        mode: sort_field.sort_mode(),
+        missing_first,
+    })
+}
+
+/// Sorts the hits by the default sort field of the index when the request does not specify any
+/// sort. kNN, hybrid, and count-only searches are left untouched.
+pub(crate) fn apply_default_sort(search_request: &mut SearchRequest, doc_mapper: &dyn DocMapper) {
+    if search_request.sort_by_field.is_some()
+        || search_request.sort_fields.is_some()
+        || search_request.knn_query.is_some()
+        || search_request.hybrid_ranking.is_some()
+        || search_request.count_only
+    {
+        return;
+    }
+    if let Some((field_name, sort_order)) = doc_mapper.default_sort_by() {
+        search_request.sort_by_field = Some(field_name);
+        search_request.sort_order = Some(sort_order as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::DefaultDocMapper;
+
+    use super::*;
+
+    fn doc_mapper_for_test() -> DefaultDocMapper {
+        serde_json::from_str(
+            r#"{
+                "field_mappings": [
+                    {"name": "severity", "type": "u64", "fast": true},
+                    {"name": "ts", "type": "datetime", "fast": true},
+                    {"name": "latencies", "type": "array<f64>", "fast": true},
+                    {"name": "body", "type": "text"},
+                    {"name": "location", "type": "geo_point"}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn validate(sort_fields_json: &str) -> crate::Result<()> {
+        let sort_fields = parse_sort_fields(Some(sort_fields_json))?;
+        let search_request = SearchRequest {
+            sort_fields: Some(sort_fields_json.to_string()),
+            ..Default::default()
+        };
+        validate_sort_fields(&doc_mapper_for_test(), &search_request, &sort_fields)
+    }
+
+    #[test]
+    fn test_apply_default_sort() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+                "field_mappings": [{"name": "ts", "type": "datetime", "fast": true}],
+                "default_sort_by_field": "-ts"
+            }"#,
+        )
+        .unwrap();
+        let mut search_request = SearchRequest::default();
+        apply_default_sort(&mut search_request, &doc_mapper);
+        assert_eq!(search_request.sort_by_field.as_deref(), Some("ts"));
+        assert_eq!(search_request.sort_order, Some(SortOrder::Desc as i32));
+
+        let mut search_request = SearchRequest {
+            sort_fields: Some(r#"[{"field": "ts"}]"#.to_string()),
+            ..Default::default()
+        };
+        apply_default_sort(&mut search_request, &doc_mapper);
+        assert!(search_request.sort_by_field.is_none());
+
+        let mut search_request = SearchRequest::default();
+        apply_default_sort(&mut search_request, &doc_mapper_for_test());
+        assert!(search_request.sort_by_field.is_none());
+    }
+
+    #[test]
+    fn test_parse_sort_fields() {
+        assert!(parse_sort_fields(None).unwrap().is_empty());
+        let sort_fields = parse_sort_fields(Some(
+            r#"[{"field": "severity"}, {"field": "ts", "order": "asc", "missing": "first"}]"#,
+        ))
+        .unwrap();
+        assert_eq!(sort_fields.len(), 2);
+        assert_eq!(sort_fields[0].sort_order(), SortOrder::Desc);
+        assert_eq!(sort_fields[0].missing, MissingPlacement::Last);
+        assert_eq!(sort_fields[1].sort_order(), SortOrder::Asc);
+        assert_eq!(sort_fields[1].missing, MissingPlacement::First);
+        assert_eq!(sort_fields[0].sort_mode(), SortMode::Max);
+        assert_eq!(sort_fields[1].sort_mode(), SortMode::Min);
+
+        let sort_fields =
+            parse_sort_fields(Some(r#"[{"field": "latencies", "mode": "min"}]"#)).unwrap();
+        assert_eq!(sort_fields[0].sort_mode(), SortMode::Min);
+
+        let geo_sort_fields = parse_sort_fields(Some(
+            r#"[{"field": "location", "origin": {"lat": 48.85, "lon": 2.35}}]"#,
+        ))
+        .unwrap();
+        assert_eq!(geo_sort_fields[0].sort_order(), SortOrder::Asc);
+
+        parse_sort_fields(Some(r#"[{"field": "ts", "unknown": 1}]"#)).unwrap_err();
+        parse_sort_fields(Some(r#"[{"field": "ts", "order": "up"}]"#)).unwrap_err();
+        parse_sort_fields(Some(r#"[{"field": "ts", "mode": "avg"}]"#)).unwrap_err();
+    }
+
+    #[test]
+    fn test_validate_sort_fields() {
+        validate(r#"[{"field": "severity"}, {"field": "ts"}, {"field": "_score"}]"#).unwrap();
+        validate(r#"[{"field": "location", "origin": "48.85,2.35"}]"#).unwrap();
+        validate(r#"[{"field": "latencies", "mode": "max"}]"#).unwrap();
+        validate("[]").unwrap_err();
+        validate(r#"[{"field": "body"}]"#).unwrap_err();
+        validate(r#"[{"field": "unknown"}]"#).unwrap_err();
+        validate(r#"[{"field": "location"}]"#).unwrap_err();
+        validate(r#"[{"field": "ts", "origin": "48.85,2.35"}]"#).unwrap_err();
+        validate(r#"[{"field": "location", "origin": "100,2.35"}]"#).unwrap_err();
+    }
+
+    #[test]
+    fn test_validate_sort_fields_with_sort_by_field() {
+        let sort_fields_json = r#"[{"field": "severity"}]"#;
+        let sort_fields = parse_sort_fields(Some(sort_fields_json)).unwrap();
+        let search_request = SearchRequest {
+            sort_fields: Some(sort_fields_json.to_string()),
+            sort_by_field: Some("ts".to_string()),
+            ..Default::default()
+        };
+        let error = validate_sort_fields(&doc_mapper_for_test(), &search_request, &sort_fields)
+            .unwrap_err();
+        assert!(error.to_string().contains("cannot be set at the same time"));
+    }
+
+    #[test]
+    fn test_sort_field_to_sort_by() {
+        let runtime_fields =
+            RuntimeFields::parse(Some(r#"{"double_severity": "severity * 2"}"#)).unwrap();
+        let sort_fields = parse_sort_fields(Some(
+            r#"[
+                {"field": "severity", "missing": "first"},
+                {"field": "latencies", "order": "asc", "mode": "max"},
+                {"field": "double_severity", "order": "asc"},
+                {"field": "location", "origin": [2.35, 48.85], "order": "desc"}
+            ]"#,
+        ))
+        .unwrap();
+        let sort_bys: Vec<SortBy> = sort_fields
+            .iter()
+            .map(|sort_field| sort_field_to_sort_by(sort_field, &runtime_fields).unwrap())
+            .collect();
+        assert!(matches!(
+            &sort_bys[0],
+            SortBy::FastField {
+                field_name,
+                order: SortOrder::Desc,
+                mode: SortMode::Max,
+                missing_first: true,
+            } if field_name == "severity"
+        ));
+        assert!(matches!(
+            &sort_bys[1],
+            SortBy::FastField { field_name, order: SortOrder::Asc, mode: SortMode::Max, .. }
+                if field_name == "latencies"
+        ));
+        assert!(matches!(
+            &sort_bys[2],
+            SortBy::RuntimeField {
+                order: SortOrder::Asc,
+                missing_first: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &sort_bys[3],
+            SortBy::GeoDistance { origin, order: SortOrder::Desc, .. }
+                if origin.lat == 48.85 && origin.lon == 2.35
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+use quickwit_directories::CachingDirectory;
+use quickwit_storage::STORAGE_METRICS;
+use tantivy::Searcher;
+
+/// The opened searcher of a split, along with the cache directory holding the data read from
+/// the split.
+///
+/// The segment readers of the searcher keep the term dictionaries and fast field readers
+/// decoded by the searches, while the cache directory serves the split data fetched by their
+/// warmup.
+#[derive(Clone)]
+pub(crate) struct SplitSearcher {
+    pub searcher: Arc<Searcher>,
+    pub caching_directory: CachingDirectory,
+}
+
+impl SplitSearcher {
+    fn num_bytes(&self) -> usize {
+        self.caching_directory.num_bytes() as usize
+    }
+}
+
+struct CachedSplitSearcher {
+    split_searcher: SplitSearcher,
+    /// Size of the entry when it was last put in the cache.
+    num_bytes: usize,
+}
+
+/// A cache of the opened searchers of splits.
+///
+/// Opening a split and decoding the term dictionaries and fast fields of its segments is
+/// repeated by every search, even though splits are immutable. Keeping the searchers of the
+/// hot splits spares these costs to the following searches. Unlike the fast field cache, which
+/// holds byte ranges, this cache holds decoded structures. The size of an entry is the amount of
+/// split data held by its cache directory, which grows as the split is searched, and entries
+/// are evicted in LRU order.
+pub struct SplitSearcherCache {
+    inner_opt: Option<Mutex<InnerSplitSearcherCache>>,
+}
+
+struct InnerSplitSearcherCache {
+    capacity_in_bytes: usize,
+    num_bytes: usize,
+    lru_cache: LruCache<String, CachedSplitSearcher>,
+}
+
+impl InnerSplitSearcherCache {
+    fn remove(&mut self, split_id: &str) {
+        if let Some(cached_split_searcher) = self.lru_cache.pop(split_id) {
+            self.record_removal(cached_split_searcher.num_bytes);
+        }
+    }
+
+    fn record_removal(&mut self, num_bytes: usize) {
+        self.num_bytes -= num_bytes;
+        let metrics = &STORAGE_METRICS.split_searcher_cache;
+        metrics.in_cache_count.dec();
+        metrics.in_cache_num_bytes.sub(num_bytes as i64);
+    }
+
+    fn evict_until_within_capacity(&mut self) {
+        while self.num_bytes > self.capacity_in_bytes {
+            let Some((_, cached_split_searcher)) = self.lru_cache.pop_lru() else {
+                break;
+            };
+            self.record_removal(cached_split_searcher.num_bytes);
+        }
+    }
+}
+
+impl SplitSearcherCache {
+    /// Creates a cache of the given capacity. A capacity of zero disables the cache.
+    pub fn with_capacity_in_bytes(capacity_in_bytes: usize) -> Self {
+        let inner_opt = (capacity_in_bytes > 0).then(|| {
+            Mutex::new(InnerSplitSearcherCache {
+                capacity_in_bytes,
+                num_bytes: 0,
+                lru_cache: LruCache::unbounded(),
+            })
+        });
+        SplitSearcherCache { inner_opt }
+    }
+
+    /// Changes the capacity of the cache. A cache disabled at startup remains disabled.
+    pub fn set_capacity_in_bytes(&self, capacity_in_bytes: usize) {
+        if let Some(inner) = &self.inner_opt {
+            let mut inner_guard = inner.lock().unwrap();
+            inner_guard.capacity_in_bytes = capacity_in_bytes;
+            inner_guard.evict_until_within_capacity();
+        }
+    }
+
+    /// Returns the cached searcher of the split, if any.
+    pub(crate) fn get(&self, split_id: &str) -> Option<SplitSearcher> {
+        let inner = self.inner_opt.as_ref()?;
+        let mut inner_guard = inner.lock().unwrap();
+        let metrics = &STORAGE_METRICS.split_searcher_cache;
+        let Some(cached_split_searcher) = inner_guard.lru_cache.get(split_id) else {
+            metrics.misses_num_items.inc();
+            return None;
+        };
+        metrics.hits_num_items.inc();
+        metrics
+            .hits_num_bytes
+            .inc_by(cached_split_searcher.num_bytes as u64);
+        Some(cached_split_searcher.split_searcher.clone())
+    }
+
+    /// Caches the searcher of the split, or updates the size of its entry after a search. A
+    /// searcher larger than the capacity of the cache is not cached.
+    pub(crate) fn put(&self, split_id: &str, split_searcher: SplitSearcher) {
+        let Some(inner) = self.inner_opt.as_ref() else {
+            return;
+        };
+        let num_bytes = split_searcher.num_bytes();
+        let mut inner_guard = inner.lock().unwrap();
+        inner_guard.remove(split_id);
+
+        if num_bytes > inner_guard.capacity_in_bytes {
+            return;
+        }
+        let cached_split_searcher = CachedSplitSearcher {
+            split_searcher,
+            num_bytes,
+        };
+        inner_guard
+            .lru_cache
+            .put(split_id.to_string(), cached_split_searcher);
+        inner_guard.num_bytes += num_bytes;
+
+        let metrics = &STORAGE_METRICS.split_searcher_cache;
+        metrics.in_cache_count.inc();
+        metrics.in_cache_num_bytes.add(num_bytes as i64);
+        inner_guard.evict_until_within_capacity();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tantivy::directory::RamDirectory;
+    use tantivy::schema::{Schema, TEXT};
+    use tantivy::{Directory, Index};
+
+    use super::*;
+
+    fn split_searcher_for_test(num_bytes: usize) -> SplitSearcher {
+        let ram_directory = RamDirectory::create();
+        ram_directory
+            .atomic_write(Path::new("split-data"), &vec![0u8; num_bytes])
+            .unwrap();
+        let caching_directory = CachingDirectory::new_unbounded(Arc::new(ram_directory));
+        caching_directory
+            .atomic_read(Path::new("split-data"))
+            .unwrap();
+        let mut schema_builder = Schema::builder();
+        schema_builder.add_text_field("body", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader().unwrap().searcher();
+        SplitSearcher {
+            searcher: Arc::new(searcher),
+            caching_directory,
+        }
+    }
+
+    #[test]
+    fn test_split_searcher_cache() {
+        let cache = SplitSearcherCache::with_capacity_in_bytes(250);
+        assert!(cache.get("split_1").is_none());
+
+        cache.put("split_1", split_searcher_for_test(100));
+        cache.put("split_2", split_searcher_for_test(100));
+        assert!(cache.get("split_1").is_some());
+
+        // `split_2` is the least recently used split.
+        cache.put("split_3", split_searcher_for_test(100));
+        assert!(cache.get("split_2").is_none());
+        assert!(cache.get("split_1").is_some());
+        assert!(cache.get("split_3").is_some());
+
+        // A split larger than the capacity is not cached, and replaces the previous entry.
+        cache.put("split_1", split_searcher_for_test(300));
+        assert!(cache.get("split_1").is_none());
+        assert!(cache.get("split_3").is_some());
+
+        cache.set_capacity_in_bytes(50);
+        assert!(cache.get("split_3").is_none());
+    }
+
+    #[test]
+    fn test_split_searcher_cache_disabled() {
+        let cache = SplitSearcherCache::with_capacity_in_bytes(0);
+        cache.put("split_1", split_searcher_for_test(100));
+        assert!(cache.get("split_1").is_none());
+    }
+}
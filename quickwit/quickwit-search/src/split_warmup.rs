@@ -0,0 +1,292 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::try_join_all;
+use itertools::Itertools;
+use quickwit_config::{build_doc_mapper, IndexConfig};
+use quickwit_doc_mapper::WarmupInfo;
+use quickwit_metastore::{ListSplitsQuery, Metastore, SplitState};
+use quickwit_proto::{
+    LeafWarmupRequest, SplitIdAndFooterOffsets, SplitSearchError, WarmupRequest, WarmupResponse,
+};
+use quickwit_storage::Storage;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::leaf::{open_split_searcher, warmup};
+use crate::root::SearchJob;
+use crate::{SearchError, SearchJobPlacer, SearcherContext};
+
+/// Warms up the splits of an index on the searchers that the searches will send them to, so
+/// that an expected investigation does not pay the latency of cold reads.
+///
+/// The splits are either the given splits or the published splits overlapping the time range of
+/// the request.
+pub(crate) async fn root_warmup(
+    warmup_request: &WarmupRequest,
+    metastore: &dyn Metastore,
+    search_job_placer: &SearchJobPlacer,
+) -> crate::Result<WarmupResponse> {
+    let index_config: IndexConfig = metastore
+        .index_metadata(&warmup_request.index_id)
+        .await?
+        .into_index_config();
+    let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+        .map_err(|err| {
+            SearchError::InternalError(format!("Failed to build doc mapper. Cause: {err}"))
+        })?;
+    let schema = doc_mapper.schema();
+
+    for fast_field_name in &warmup_request.fast_field_names {
+        let is_fast = schema
+            .get_field(fast_field_name)
+            .map(|field| schema.get_field_entry(field).is_fast())
+            .unwrap_or(false);
+        if !is_fast {
+            return Err(SearchError::InvalidArgument(format!(
+                "Field `{fast_field_name}` is not a fast field."
+            )));
+        }
+    }
+    for term_dict_field_name in &warmup_request.term_dict_field_names {
+        let is_indexed = schema
+            .get_field(term_dict_field_name)
+            .map(|field| schema.get_field_entry(field).is_indexed())
+            .unwrap_or(false);
+        if !is_indexed {
+            return Err(SearchError::InvalidArgument(format!(
+                "Field `{term_dict_field_name}` is not an indexed field."
+            )));
+        }
+    }
+    let mut fast_field_names = warmup_request.fast_field_names.clone();
+    if let Some(timestamp_field_name) = doc_mapper.timestamp_field_name() {
+        fast_field_names.push(timestamp_field_name.to_string());
+    }
+    let fast_field_names: Vec<String> = fast_field_names.into_iter().unique().collect();
+
+    let mut query = ListSplitsQuery::for_index(&warmup_request.index_id)
+        .with_split_state(SplitState::Published);
+
+    if let Some(start_ts) = warmup_request.start_timestamp {
+        query = query.with_time_range_start_gte(start_ts);
+    }
+
+    if let Some(end_ts) = warmup_request.end_timestamp {
+        query = query.with_time_range_end_lt(end_ts);
+    }
+
+    let mut split_metadatas: Vec<_> = metastore
+        .list_splits(query)
+        .await?
+        .into_iter()
+        .map(|split| split.split_metadata)
+        .collect();
+
+    if !warmup_request.split_ids.is_empty() {
+        let split_ids: HashSet<&String> = warmup_request.split_ids.iter().collect();
+        split_metadatas.retain(|split_metadata| split_ids.contains(&split_metadata.split_id));
+
+        if split_metadatas.len() < split_ids.len() {
+            let published_split_ids: HashSet<&String> = split_metadatas
+                .iter()
+                .map(|split_metadata| &split_metadata.split_id)
+                .collect();
+            let missing_split_ids = split_ids
+                .difference(&published_split_ids)
+                .sorted()
+                .join(", ");
+            return Err(SearchError::InvalidArgument(format!(
+                "Splits `{missing_split_ids}` are not published in index `{}`.",
+                warmup_request.index_id
+            )));
+        }
+    }
+    if split_metadatas.is_empty() {
+        return Ok(WarmupResponse::default());
+    }
+    // The splits are placed like the splits of a search, so that they are warmed up on the
+    // searchers the following searches will query.
+    let jobs: Vec<SearchJob> = split_metadatas.iter().map(SearchJob::from).collect();
+    let assigned_leaf_warmup_jobs = search_job_placer.assign_jobs(jobs, &HashSet::default())?;
+    debug!(assigned_leaf_warmup_jobs=?assigned_leaf_warmup_jobs, "Assigned leaf warm-up jobs.");
+
+    let index_uri = index_config.index_uri.to_string();
+    let leaf_warmup_responses = try_join_all(assigned_leaf_warmup_jobs.into_iter().map(
+        |(mut client, client_jobs)| {
+            let leaf_warmup_request = LeafWarmupRequest {
+                split_offsets: client_jobs.into_iter().map(Into::into).collect(),
+                index_uri: index_uri.clone(),
+                fast_field_names: fast_field_names.clone(),
+                term_dict_field_names: warmup_request.term_dict_field_names.clone(),
+                max_num_bytes: warmup_request.max_num_bytes,
+                max_num_bytes_per_sec: warmup_request.max_num_bytes_per_sec,
+            };
+            async move { client.leaf_warmup(leaf_warmup_request).await }
+        },
+    ))
+    .await?;
+
+    let mut warmup_response = WarmupResponse::default();
+    for leaf_warmup_response in leaf_warmup_responses {
+        warmup_response.num_warmed_splits += leaf_warmup_response.num_warmed_splits;
+        warmup_response.num_skipped_splits += leaf_warmup_response.num_skipped_splits;
+        warmup_response.num_downloaded_bytes += leaf_warmup_response.num_downloaded_bytes;
+        warmup_response
+            .failed_splits
+            .extend(leaf_warmup_response.failed_splits);
+    }
+    Ok(warmup_response)
+}
+
+/// Warms up the given splits one after the other, until the searcher has downloaded
+/// `max_num_bytes`. The splits that remain are skipped. The warmed up searchers are kept in the
+/// split searcher cache, where they are subject to its capacity like the searchers of the
+/// searches.
+///
+/// The splits are warmed up one at a time so that the warm-up competes as little as possible with
+/// the live searches, and the warm-up pauses between two splits to keep its download rate under
+/// `max_num_bytes_per_sec`.
+pub(crate) async fn leaf_warmup(
+    searcher_context: Arc<SearcherContext>,
+    index_storage: Arc<dyn Storage>,
+    leaf_warmup_request: LeafWarmupRequest,
+) -> WarmupResponse {
+    let warmup_info = WarmupInfo {
+        fast_field_names: leaf_warmup_request.fast_field_names.into_iter().collect(),
+        term_dict_field_names: leaf_warmup_request
+            .term_dict_field_names
+            .into_iter()
+            .collect(),
+        ..Default::default()
+    };
+    let max_num_bytes = leaf_warmup_request.max_num_bytes.unwrap_or(u64::MAX);
+    let num_splits = leaf_warmup_request.split_offsets.len();
+    let start_instant = Instant::now();
+    let mut warmup_response = WarmupResponse::default();
+
+    for (split_ord, split) in leaf_warmup_request.split_offsets.iter().enumerate() {
+        if warmup_response.num_downloaded_bytes >= max_num_bytes {
+            warmup_response.num_skipped_splits = (num_splits - split_ord) as u64;
+            break;
+        }
+        match warm_up_split(
+            &searcher_context,
+            index_storage.clone(),
+            split,
+            &warmup_info,
+        )
+        .await
+        {
+            Ok(num_downloaded_bytes) => {
+                warmup_response.num_warmed_splits += 1;
+                warmup_response.num_downloaded_bytes += num_downloaded_bytes;
+            }
+            Err(error) => {
+                warn!(split_id = %split.split_id, error = ?error, "Failed to warm up split.");
+                warmup_response.failed_splits.push(SplitSearchError {
+                    error: error.to_string(),
+                    split_id: split.split_id.clone(),
+                    retryable_error: true,
+                });
+            }
+        }
+        if let Some(max_num_bytes_per_sec) = leaf_warmup_request.max_num_bytes_per_sec {
+            let delay = throttle_delay(
+                warmup_response.num_downloaded_bytes,
+                max_num_bytes_per_sec,
+                start_instant.elapsed(),
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+    info!(
+        num_warmed_splits = warmup_response.num_warmed_splits,
+        num_skipped_splits = warmup_response.num_skipped_splits,
+        num_downloaded_bytes = warmup_response.num_downloaded_bytes,
+        "Warmed up splits."
+    );
+    warmup_response
+}
+
+/// Opens the searcher of the split, warms it up, and puts it in the split searcher cache.
+/// Returns the number of bytes downloaded, which does not include the split footer.
+async fn warm_up_split(
+    searcher_context: &Arc<SearcherContext>,
+    index_storage: Arc<dyn Storage>,
+    split: &SplitIdAndFooterOffsets,
+    warmup_info: &WarmupInfo,
+) -> anyhow::Result<u64> {
+    let _split_search_permit = searcher_context
+        .leaf_search_split_semaphore
+        .acquire()
+        .await
+        .expect("Failed to acquire permit. This should never happen! Please, report on https://github.com/quickwit-oss/quickwit/issues.");
+    let split_searcher = open_split_searcher(searcher_context, index_storage, split).await?;
+    let num_bytes_before = split_searcher.caching_directory.num_bytes();
+    warmup(&split_searcher.searcher, warmup_info).await?;
+    let num_downloaded_bytes = split_searcher
+        .caching_directory
+        .num_bytes()
+        .saturating_sub(num_bytes_before);
+    searcher_context
+        .split_searcher_cache
+        .put(&split.split_id, split_searcher);
+    Ok(num_downloaded_bytes)
+}
+
+/// Returns how long to pause so that downloading `num_downloaded_bytes` in `elapsed` does not
+/// exceed `max_num_bytes_per_sec`.
+fn throttle_delay(
+    num_downloaded_bytes: u64,
+    max_num_bytes_per_sec: u64,
+    elapsed: Duration,
+) -> Duration {
+    if max_num_bytes_per_sec == 0 {
+        return Duration::ZERO;
+    }
+    let min_elapsed =
+        Duration::from_secs_f64(num_downloaded_bytes as f64 / max_num_bytes_per_sec as f64);
+    min_elapsed.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_delay() {
+        assert_eq!(
+            throttle_delay(1_000, 100, Duration::from_secs(4)),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            throttle_delay(1_000, 100, Duration::from_secs(12)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            throttle_delay(1_000, 0, Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+}
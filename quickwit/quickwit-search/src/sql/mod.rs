@@ -0,0 +1,780 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Translation of SQL queries into search requests, for the BI tools and the analysts who do not
+//! speak the query language.
+
+mod parser;
+
+use std::cmp::Ordering;
+
+use quickwit_proto::{SearchRequest, SearchResponse, SortOrder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use self::parser::{
+    invalid_sql, parse_sql_query, AggregateFunction, ComparisonOperator, Expr, Literal, SelectExpr,
+    SqlQuery,
+};
+use crate::{SearchError, SearchService};
+
+/// Number of rows returned by the queries without a `LIMIT` clause.
+const DEFAULT_SQL_LIMIT: u64 = 100;
+
+/// Maximum number of groups computed for each column of the `GROUP BY` clause.
+const MAX_NUM_GROUPS: u64 = 1_000;
+
+/// SqlResponse represents the response returned by the REST SQL API and is meant to be
+/// serialized into JSON.
+#[derive(Serialize, Deserialize, PartialEq, Debug, utoipa::ToSchema)]
+pub struct SqlResponse {
+    /// Names of the columns.
+    pub columns: Vec<String>,
+    /// Rows of the result, holding one value per column.
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub rows: Vec<Vec<JsonValue>>,
+    /// Overall number of documents matching the query.
+    pub num_hits: u64,
+    /// Elapsed time.
+    pub elapsed_time_micros: u64,
+    /// Search errors.
+    pub errors: Vec<String>,
+}
+
+/// Runs a SQL query by translating it into a search request.
+pub async fn sql_search(
+    sql: &str,
+    search_service: &dyn SearchService,
+) -> Result<SqlResponse, SearchError> {
+    let sql_plan = SqlPlan::new(parse_sql_query(sql)?)?;
+    let search_request = sql_plan.search_request()?;
+    let search_response = search_service.root_search(search_request).await?;
+    sql_plan.sql_response(search_response)
+}
+
+/// A validated SQL query. Queries with a `GROUP BY` clause or aggregate functions are translated
+/// into aggregations, the other ones into a search for the top hits.
+struct SqlPlan {
+    sql_query: SqlQuery,
+    is_aggregation: bool,
+    /// Ordinals of the columns the rows of an aggregation query are sorted by, once all the
+    /// groups are computed.
+    sort_columns: Vec<(usize, SortOrder)>,
+}
+
+impl SqlPlan {
+    fn new(sql_query: SqlQuery) -> Result<Self, SearchError> {
+        let is_aggregation = !sql_query.group_by.is_empty()
+            || sql_query
+                .select_items
+                .iter()
+                .any(|select_item| matches!(select_item.expr, SelectExpr::Aggregate { .. }));
+        let mut sort_columns = Vec::new();
+
+        if is_aggregation {
+            for select_item in &sql_query.select_items {
+                match &select_item.expr {
+                    SelectExpr::Wildcard => {
+                        return Err(invalid_sql(
+                            "`*` cannot be selected along with aggregate functions or a `GROUP \
+                             BY` clause.",
+                        ));
+                    }
+                    SelectExpr::Column(column) if !sql_query.group_by.contains(column) => {
+                        return Err(invalid_sql(format!(
+                            "column `{column}` must appear in the `GROUP BY` clause or be used \
+                             in an aggregate function."
+                        )));
+                    }
+                    _ => {}
+                }
+            }
+            for order_by_item in &sql_query.order_by {
+                let column_ord = sql_query
+                    .select_items
+                    .iter()
+                    .position(|select_item| {
+                        if select_item.expr == order_by_item.expr {
+                            return true;
+                        }
+                        let SelectExpr::Column(column) = &order_by_item.expr else {
+                            return false;
+                        };
+                        select_item.alias_opt.as_ref() == Some(column)
+                    })
+                    .ok_or_else(|| {
+                        invalid_sql(format!(
+                            "`ORDER BY {}` must refer to a selected column.",
+                            order_by_item.expr
+                        ))
+                    })?;
+                sort_columns.push((column_ord, order_by_item.sort_order));
+            }
+        }
+        Ok(SqlPlan {
+            sql_query,
+            is_aggregation,
+            sort_columns,
+        })
+    }
+
+    fn search_request(&self) -> Result<SearchRequest, SearchError> {
+        let query = match &self.sql_query.where_expr_opt {
+            Some(where_expr) => expr_to_query(where_expr)?,
+            None => "*".to_string(),
+        };
+        let mut search_request = SearchRequest {
+            index_id: self.sql_query.index_id.clone(),
+            query,
+            ..Default::default()
+        };
+        if self.is_aggregation {
+            let aggregation_request = self.aggregation_request();
+            if !aggregation_request.is_empty() {
+                search_request.aggregation_request =
+                    Some(JsonValue::Object(aggregation_request).to_string());
+            }
+            return Ok(search_request);
+        }
+        search_request.max_hits = self.sql_query.limit_opt.unwrap_or(DEFAULT_SQL_LIMIT);
+        search_request.start_offset = self.sql_query.offset_opt.unwrap_or_default();
+
+        let mut sort_fields = Vec::with_capacity(self.sql_query.order_by.len());
+        for order_by_item in &self.sql_query.order_by {
+            let SelectExpr::Column(column) = &order_by_item.expr else {
+                return Err(invalid_sql(format!(
+                    "`ORDER BY {}` requires a `GROUP BY` clause.",
+                    order_by_item.expr
+                )));
+            };
+            sort_fields.push((self.resolve_alias(column), order_by_item.sort_order));
+        }
+        match sort_fields.as_slice() {
+            [] => {}
+            [(column, sort_order)] => {
+                search_request.sort_by_field = Some(column.to_string());
+                search_request.sort_order = Some(*sort_order as i32);
+            }
+            _ => {
+                let sort_fields_json: Vec<JsonValue> = sort_fields
+                    .iter()
+                    .map(|(column, sort_order)| {
+                        let order = match sort_order {
+                            SortOrder::Asc => "asc",
+                            SortOrder::Desc => "desc",
+                        };
+                        json!({"field": column, "order": order})
+                    })
+                    .collect();
+                search_request.sort_fields = Some(JsonValue::Array(sort_fields_json).to_string());
+            }
+        }
+        Ok(search_request)
+    }
+
+    /// Returns the column a column alias of the hits query refers to.
+    fn resolve_alias<'a>(&'a self, column: &'a str) -> &'a str {
+        for select_item in &self.sql_query.select_items {
+            if let (SelectExpr::Column(aliased_column), Some(alias)) =
+                (&select_item.expr, &select_item.alias_opt)
+            {
+                if alias == column {
+                    return aliased_column;
+                }
+            }
+        }
+        column
+    }
+
+    /// Builds the aggregation request: one nested `terms` aggregation per column of the
+    /// `GROUP BY` clause, and one `stats` aggregation per aggregate function applied to a column.
+    fn aggregation_request(&self) -> JsonMap<String, JsonValue> {
+        let mut aggregations = JsonMap::new();
+
+        for (column_ord, select_item) in self.sql_query.select_items.iter().enumerate() {
+            if let SelectExpr::Aggregate {
+                column_opt: Some(column),
+                ..
+            } = &select_item.expr
+            {
+                aggregations.insert(
+                    metric_aggregation_name(column_ord),
+                    json!({ "stats": { "field": column } }),
+                );
+            }
+        }
+        for (level, column) in self.sql_query.group_by.iter().enumerate().rev() {
+            let mut group_aggregation =
+                json!({ "terms": { "field": column, "size": MAX_NUM_GROUPS } });
+            if !aggregations.is_empty() {
+                group_aggregation["aggs"] = JsonValue::Object(aggregations);
+            }
+            aggregations = JsonMap::new();
+            aggregations.insert(group_aggregation_name(level), group_aggregation);
+        }
+        aggregations
+    }
+
+    fn sql_response(&self, search_response: SearchResponse) -> Result<SqlResponse, SearchError> {
+        let (columns, rows) = if self.is_aggregation {
+            self.aggregation_rows(&search_response)?
+        } else {
+            hit_rows(&self.sql_query, &search_response)?
+        };
+        Ok(SqlResponse {
+            columns,
+            rows,
+            num_hits: search_response.num_hits,
+            elapsed_time_micros: search_response.elapsed_time_micros,
+            errors: search_response.errors,
+        })
+    }
+
+    fn aggregation_rows(
+        &self,
+        search_response: &SearchResponse,
+    ) -> Result<(Vec<String>, Vec<Vec<JsonValue>>), SearchError> {
+        let aggregations: JsonValue = match &search_response.aggregation {
+            Some(aggregation_json) => serde_json::from_str(aggregation_json)
+                .map_err(|error| SearchError::InternalError(error.to_string()))?,
+            None => JsonValue::Null,
+        };
+        let mut rows = Vec::new();
+        self.collect_group_rows(
+            &aggregations,
+            search_response.num_hits,
+            &mut Vec::new(),
+            &mut rows,
+        );
+        rows.sort_by(|left_row, right_row| {
+            for (column_ord, sort_order) in &self.sort_columns {
+                let ordering = compare_json_values(&left_row[*column_ord], &right_row[*column_ord]);
+                let ordering = match sort_order {
+                    SortOrder::Asc => ordering,
+                    SortOrder::Desc => ordering.reverse(),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+        let offset = self.sql_query.offset_opt.unwrap_or_default() as usize;
+        let limit = self.sql_query.limit_opt.unwrap_or(DEFAULT_SQL_LIMIT) as usize;
+        let rows = rows.into_iter().skip(offset).take(limit).collect();
+        let columns = self
+            .sql_query
+            .select_items
+            .iter()
+            .map(|select_item| select_item.column_name())
+            .collect();
+        Ok((columns, rows))
+    }
+
+    /// Walks down the nested `terms` aggregations, emitting one row per group.
+    fn collect_group_rows(
+        &self,
+        aggregations: &JsonValue,
+        doc_count: u64,
+        group_keys: &mut Vec<JsonValue>,
+        rows: &mut Vec<Vec<JsonValue>>,
+    ) {
+        let level = group_keys.len();
+
+        if level == self.sql_query.group_by.len() {
+            rows.push(self.make_row(aggregations, doc_count, group_keys));
+            return;
+        }
+        let Some(buckets) = aggregations[group_aggregation_name(level)]["buckets"].as_array() else {
+            return;
+        };
+        for bucket in buckets {
+            let bucket_doc_count = bucket["doc_count"].as_u64().unwrap_or_default();
+            group_keys.push(bucket["key"].clone());
+            self.collect_group_rows(bucket, bucket_doc_count, group_keys, rows);
+            group_keys.pop();
+        }
+    }
+
+    fn make_row(
+        &self,
+        aggregations: &JsonValue,
+        doc_count: u64,
+        group_keys: &[JsonValue],
+    ) -> Vec<JsonValue> {
+        self.sql_query
+            .select_items
+            .iter()
+            .enumerate()
+            .map(|(column_ord, select_item)| match &select_item.expr {
+                SelectExpr::Column(column) => self
+                    .sql_query
+                    .group_by
+                    .iter()
+                    .position(|group_column| group_column == column)
+                    .map(|level| group_keys[level].clone())
+                    .unwrap_or_default(),
+                SelectExpr::Aggregate {
+                    function: AggregateFunction::Count,
+                    column_opt: None,
+                } => json!(doc_count),
+                SelectExpr::Aggregate { function, .. } => {
+                    aggregations[metric_aggregation_name(column_ord)][function.name()].clone()
+                }
+                SelectExpr::Wildcard => JsonValue::Null,
+            })
+            .collect()
+    }
+}
+
+fn hit_rows(
+    sql_query: &SqlQuery,
+    search_response: &SearchResponse,
+) -> Result<(Vec<String>, Vec<Vec<JsonValue>>), SearchError> {
+    let documents: Vec<JsonValue> = search_response
+        .hits
+        .iter()
+        .map(|hit| serde_json::from_str::<JsonValue>(&hit.json))
+        .collect::<Result<_, _>>()
+        .map_err(|error| SearchError::InternalError(error.to_string()))?;
+
+    // Pairs of column name and field path.
+    let mut columns: Vec<(String, String)> = Vec::new();
+    for select_item in &sql_query.select_items {
+        match &select_item.expr {
+            SelectExpr::Wildcard => {
+                for document in &documents {
+                    let Some(fields) = document.as_object() else {
+                        continue;
+                    };
+                    for field_name in fields.keys() {
+                        if !columns.iter().any(|(column, _)| column == field_name) {
+                            columns.push((field_name.clone(), field_name.clone()));
+                        }
+                    }
+                }
+            }
+            SelectExpr::Column(column) => {
+                columns.push((select_item.column_name(), column.clone()));
+            }
+            SelectExpr::Aggregate { .. } => {}
+        }
+    }
+    let rows = documents
+        .iter()
+        .map(|document| {
+            columns
+                .iter()
+                .map(|(_, field_path)| lookup_field(document, field_path).cloned())
+                .map(Option::unwrap_or_default)
+                .collect()
+        })
+        .collect();
+    let column_names = columns.into_iter().map(|(column, _)| column).collect();
+    Ok((column_names, rows))
+}
+
+/// Looks up the value of a field of a document, in which a dot either separates the keys of
+/// nested objects or is part of a key.
+fn lookup_field<'a>(document: &'a JsonValue, field_path: &str) -> Option<&'a JsonValue> {
+    if let Some(value) = document.get(field_path) {
+        return Some(value);
+    }
+    for (dot_pos, _) in field_path.match_indices('.') {
+        let value_opt = document
+            .get(&field_path[..dot_pos])
+            .and_then(|child| lookup_field(child, &field_path[dot_pos + 1..]));
+        if value_opt.is_some() {
+            return value_opt;
+        }
+    }
+    None
+}
+
+/// Compares two values of a column. Nulls come after the other values.
+fn compare_json_values(left: &JsonValue, right: &JsonValue) -> Ordering {
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => left
+            .as_f64()
+            .partial_cmp(&right.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+        (JsonValue::Bool(left), JsonValue::Bool(right)) => left.cmp(right),
+        (JsonValue::Null, JsonValue::Null) => Ordering::Equal,
+        (JsonValue::Null, _) => Ordering::Greater,
+        (_, JsonValue::Null) => Ordering::Less,
+        _ => Ordering::Equal,
+    }
+}
+
+fn group_aggregation_name(level: usize) -> String {
+    format!("group_{level}")
+}
+
+fn metric_aggregation_name(column_ord: usize) -> String {
+    format!("metric_{column_ord}")
+}
+
+/// Translates the condition of the `WHERE` clause into the query language.
+fn expr_to_query(expr: &Expr) -> Result<String, SearchError> {
+    let query = match expr {
+        Expr::And(left_expr, right_expr) => format!(
+            "({} AND {})",
+            expr_to_query(left_expr)?,
+            expr_to_query(right_expr)?
+        ),
+        Expr::Or(left_expr, right_expr) => format!(
+            "({} OR {})",
+            expr_to_query(left_expr)?,
+            expr_to_query(right_expr)?
+        ),
+        // Negations only exclude documents, so the documents are first all included.
+        Expr::Not(expr) => format!("(* AND NOT {})", expr_to_query(expr)?),
+        Expr::Comparison {
+            column,
+            operator,
+            value,
+        } => {
+            let field = query_field(column)?;
+            match operator {
+                ComparisonOperator::Eq => format!("{field}:{}", literal_to_term(value)?),
+                ComparisonOperator::NotEq => {
+                    format!("(* AND NOT {field}:{})", literal_to_term(value)?)
+                }
+                ComparisonOperator::Lt => format!("{field}:<{}", literal_to_bound(value)?),
+                ComparisonOperator::Lte => format!("{field}:<={}", literal_to_bound(value)?),
+                ComparisonOperator::Gt => format!("{field}:>{}", literal_to_bound(value)?),
+                ComparisonOperator::Gte => format!("{field}:>={}", literal_to_bound(value)?),
+            }
+        }
+        Expr::Between {
+            column,
+            lower_bound,
+            upper_bound,
+        } => format!(
+            "{}:[{} TO {}]",
+            query_field(column)?,
+            literal_to_bound(lower_bound)?,
+            literal_to_bound(upper_bound)?
+        ),
+        Expr::In { column, values } => {
+            let field = query_field(column)?;
+            let clauses = values
+                .iter()
+                .map(|value| Ok(format!("{field}:{}", literal_to_term(value)?)))
+                .collect::<Result<Vec<String>, SearchError>>()?;
+            format!("({})", clauses.join(" OR "))
+        }
+        Expr::Like { column, pattern } => like_to_query(query_field(column)?, pattern)?,
+    };
+    Ok(query)
+}
+
+fn query_field(column: &str) -> Result<&str, SearchError> {
+    if column
+        .chars()
+        .any(|ch| ch.is_whitespace() || "\"():^[]{}\\".contains(ch))
+    {
+        return Err(invalid_sql(format!(
+            "column `{column}` cannot be used in the `WHERE` clause."
+        )));
+    }
+    Ok(column)
+}
+
+/// Translates a literal into a term matched exactly, i.e. a phrase for strings.
+fn literal_to_term(literal: &Literal) -> Result<String, SearchError> {
+    match literal {
+        Literal::String(string) if string.contains('"') => Err(invalid_sql(format!(
+            "string `{string}` contains double quotes, which are not supported."
+        ))),
+        Literal::String(string) => Ok(format!("\"{string}\"")),
+        Literal::Number(number) => Ok(number.clone()),
+        Literal::Boolean(boolean) => Ok(boolean.to_string()),
+    }
+}
+
+/// Translates a literal into a bound of a range, which cannot be quoted.
+fn literal_to_bound(literal: &Literal) -> Result<String, SearchError> {
+    match literal {
+        Literal::String(string)
+            if string.is_empty()
+                || string
+                    .chars()
+                    .any(|ch| ch.is_whitespace() || "\"()[]{}^".contains(ch)) =>
+        {
+            Err(invalid_sql(format!(
+                "string `{string}` cannot be used as the bound of a range."
+            )))
+        }
+        Literal::String(string) => Ok(string.clone()),
+        Literal::Number(number) => Ok(number.clone()),
+        Literal::Boolean(boolean) => Ok(boolean.to_string()),
+    }
+}
+
+/// Translates a `LIKE` pattern into a wildcard clause, or a phrase if it has no wildcard.
+fn like_to_query(field: &str, pattern: &str) -> Result<String, SearchError> {
+    if pattern.chars().all(|ch| ch == '%') {
+        return Ok("*".to_string());
+    }
+    if !pattern.contains(['%', '_']) {
+        return literal_to_term(&Literal::String(pattern.to_string()))
+            .map(|term| format!("{field}:{term}"));
+    }
+    if pattern
+        .chars()
+        .any(|ch| ch.is_whitespace() || "\"():^[]{}\\~/*?".contains(ch))
+    {
+        return Err(invalid_sql(format!(
+            "pattern `{pattern}` contains characters which are not supported."
+        )));
+    }
+    let wildcard_pattern: String = pattern
+        .chars()
+        .map(|ch| match ch {
+            '%' => '*',
+            '_' => '?',
+            _ => ch,
+        })
+        .collect();
+    Ok(format!("{field}:{wildcard_pattern}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::Hit;
+
+    use super::*;
+
+    fn search_request(sql: &str) -> Result<SearchRequest, SearchError> {
+        SqlPlan::new(parse_sql_query(sql)?)?.search_request()
+    }
+
+    #[test]
+    fn test_sql_hits_search_request() {
+        let search_request = search_request(
+            "SELECT * FROM logs WHERE level = 'ERROR' AND (latency >= 100 OR host LIKE 'web-%') \
+             ORDER BY ts DESC LIMIT 10 OFFSET 20",
+        )
+        .unwrap();
+        assert_eq!(search_request.index_id, "logs");
+        assert_eq!(
+            search_request.query,
+            "(level:\"ERROR\" AND (latency:>=100 OR host:web-*))"
+        );
+        assert_eq!(search_request.max_hits, 10);
+        assert_eq!(search_request.start_offset, 20);
+        assert_eq!(search_request.sort_by_field.as_deref(), Some("ts"));
+        assert_eq!(search_request.sort_order, Some(SortOrder::Desc as i32));
+        assert!(search_request.aggregation_request.is_none());
+
+        let search_request = search_request(
+            "SELECT msg AS message FROM logs WHERE level NOT IN ('DEBUG', 'INFO') AND ts BETWEEN \
+             '2023-01-10T15:13:35Z' AND '2023-01-11T00:00:00Z' ORDER BY message",
+        )
+        .unwrap();
+        assert_eq!(
+            search_request.query,
+            "((* AND NOT (level:\"DEBUG\" OR level:\"INFO\")) AND \
+             ts:[2023-01-10T15:13:35Z TO 2023-01-11T00:00:00Z])"
+        );
+        assert_eq!(search_request.max_hits, DEFAULT_SQL_LIMIT);
+        assert_eq!(search_request.sort_by_field.as_deref(), Some("msg"));
+
+        let search_request = search_request("SELECT a FROM logs").unwrap();
+        assert_eq!(search_request.query, "*");
+
+        let search_request =
+            search_request("SELECT * FROM logs ORDER BY severity DESC, ts ASC").unwrap();
+        assert!(search_request.sort_by_field.is_none());
+        let sort_fields: JsonValue =
+            serde_json::from_str(search_request.sort_fields.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            sort_fields,
+            json!([
+                {"field": "severity", "order": "desc"},
+                {"field": "ts", "order": "asc"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_sql_aggregation_search_request() {
+        let search_request = search_request(
+            "SELECT service, host, COUNT(*), AVG(latency) FROM logs WHERE level != 'DEBUG' GROUP \
+             BY service, host",
+        )
+        .unwrap();
+        assert_eq!(search_request.query, "(* AND NOT level:\"DEBUG\")");
+        assert_eq!(search_request.max_hits, 0);
+        let aggregation_request: JsonValue =
+            serde_json::from_str(search_request.aggregation_request.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            aggregation_request,
+            json!({
+                "group_0": {
+                    "terms": { "field": "service", "size": MAX_NUM_GROUPS },
+                    "aggs": {
+                        "group_1": {
+                            "terms": { "field": "host", "size": MAX_NUM_GROUPS },
+                            "aggs": {
+                                "metric_3": { "stats": { "field": "latency" } }
+                            }
+                        }
+                    }
+                }
+            })
+        );
+        let search_request = search_request("SELECT COUNT(*) FROM logs").unwrap();
+        assert!(search_request.aggregation_request.is_none());
+    }
+
+    #[test]
+    fn test_invalid_sql_plan() {
+        for (sql, expected_error) in [
+            ("SELECT *, COUNT(*) FROM logs", "`*` cannot be selected"),
+            (
+                "SELECT host, COUNT(*) FROM logs",
+                "column `host` must appear in the `GROUP BY` clause",
+            ),
+            (
+                "SELECT service FROM logs GROUP BY service ORDER BY COUNT(*)",
+                "`ORDER BY count(*)` must refer to a selected column",
+            ),
+            (
+                "SELECT a FROM logs ORDER BY COUNT(*)",
+                "requires a `GROUP BY` clause",
+            ),
+            (
+                "SELECT a FROM logs WHERE a = 'b\"c'",
+                "contains double quotes",
+            ),
+            (
+                "SELECT a FROM logs WHERE a > 'b c'",
+                "cannot be used as the bound",
+            ),
+        ] {
+            let error = search_request(sql).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{sql}: {error}");
+        }
+    }
+
+    #[test]
+    fn test_sql_aggregation_response() {
+        let sql_plan = SqlPlan::new(
+            parse_sql_query(
+                "SELECT service, COUNT(*) AS num_docs, MAX(latency) FROM logs GROUP BY service \
+                 ORDER BY num_docs, service DESC LIMIT 2",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let aggregations = json!({
+            "group_0": {
+                "buckets": [
+                    { "key": "api", "doc_count": 5, "metric_2": { "max": 30.0 } },
+                    { "key": "db", "doc_count": 2, "metric_2": { "max": 12.0 } },
+                    { "key": "web", "doc_count": 2, "metric_2": { "max": null } },
+                ],
+                "sum_other_doc_count": 0
+            }
+        });
+        let search_response = SearchResponse {
+            num_hits: 9,
+            aggregation: Some(aggregations.to_string()),
+            elapsed_time_micros: 10,
+            ..Default::default()
+        };
+        let sql_response = sql_plan.sql_response(search_response).unwrap();
+        assert_eq!(
+            sql_response,
+            SqlResponse {
+                columns: vec![
+                    "service".to_string(),
+                    "num_docs".to_string(),
+                    "max(latency)".to_string()
+                ],
+                rows: vec![
+                    vec![json!("web"), json!(2), JsonValue::Null],
+                    vec![json!("db"), json!(2), json!(12.0)],
+                ],
+                num_hits: 9,
+                elapsed_time_micros: 10,
+                errors: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_sql_hits_response() {
+        let sql_plan = SqlPlan::new(
+            parse_sql_query("SELECT attributes.host AS host, level, missing FROM logs").unwrap(),
+        )
+        .unwrap();
+        let search_response = SearchResponse {
+            num_hits: 1,
+            hits: vec![Hit {
+                json: r#"{"attributes": {"host": "web-1"}, "level": "ERROR"}"#.to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let sql_response = sql_plan.sql_response(search_response).unwrap();
+        assert_eq!(sql_response.columns, ["host", "level", "missing"]);
+        assert_eq!(
+            sql_response.rows,
+            [vec![json!("web-1"), json!("ERROR"), JsonValue::Null]]
+        );
+
+        let sql_plan = SqlPlan::new(parse_sql_query("SELECT * FROM logs").unwrap()).unwrap();
+        let search_response = SearchResponse {
+            num_hits: 2,
+            hits: vec![
+                Hit {
+                    json: r#"{"level": "ERROR"}"#.to_string(),
+                    ..Default::default()
+                },
+                Hit {
+                    json: r#"{"host": "web-1", "level": "INFO"}"#.to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let sql_response = sql_plan.sql_response(search_response).unwrap();
+        assert_eq!(sql_response.columns, ["level", "host"]);
+        assert_eq!(
+            sql_response.rows,
+            [
+                vec![json!("ERROR"), JsonValue::Null],
+                vec![json!("INFO"), json!("web-1")]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lookup_field() {
+        let document = json!({"a.b": {"c": 1}, "a": {"b": 2}, "d": {"e.f": 3}});
+        assert_eq!(lookup_field(&document, "a.b"), Some(&json!({"c": 1})));
+        assert_eq!(lookup_field(&document, "a.b.c"), Some(&json!(1)));
+        assert_eq!(lookup_field(&document, "d.e.f"), Some(&json!(3)));
+        assert_eq!(lookup_field(&document, "d.e"), None);
+    }
+}
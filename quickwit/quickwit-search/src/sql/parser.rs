@@ -0,0 +1,751 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser of the SQL subset supported by the SQL endpoint:
+//!
+//! ```sql
+//! SELECT <columns or aggregates> FROM <index_id>
+//! [WHERE <condition>] [GROUP BY <columns>] [ORDER BY <columns>] [LIMIT <n>] [OFFSET <n>]
+//! ```
+
+use std::fmt;
+
+use quickwit_proto::SortOrder;
+
+use crate::SearchError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SqlQuery {
+    pub select_items: Vec<SelectItem>,
+    pub index_id: String,
+    pub where_expr_opt: Option<Expr>,
+    pub group_by: Vec<String>,
+    pub order_by: Vec<OrderByItem>,
+    pub limit_opt: Option<u64>,
+    pub offset_opt: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SelectItem {
+    pub expr: SelectExpr,
+    pub alias_opt: Option<String>,
+}
+
+impl SelectItem {
+    /// Returns the name of the column of the item in the response.
+    pub fn column_name(&self) -> String {
+        self.alias_opt
+            .clone()
+            .unwrap_or_else(|| self.expr.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum SelectExpr {
+    /// `*`, i.e. all the fields of the documents.
+    Wildcard,
+    Column(String),
+    /// Aggregate function applied to a column, or to all the documents for `COUNT(*)`.
+    Aggregate {
+        function: AggregateFunction,
+        column_opt: Option<String>,
+    },
+}
+
+impl fmt::Display for SelectExpr {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectExpr::Wildcard => write!(formatter, "*"),
+            SelectExpr::Column(column) => write!(formatter, "{column}"),
+            SelectExpr::Aggregate {
+                function,
+                column_opt,
+            } => {
+                let column = column_opt.as_deref().unwrap_or("*");
+                write!(formatter, "{}({column})", function.name())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "avg" => Some(AggregateFunction::Avg),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the function, which is also the name of the corresponding value of
+    /// the `stats` aggregation.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::Count => "count",
+            AggregateFunction::Sum => "sum",
+            AggregateFunction::Avg => "avg",
+            AggregateFunction::Min => "min",
+            AggregateFunction::Max => "max",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct OrderByItem {
+    pub expr: SelectExpr,
+    pub sort_order: SortOrder,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Comparison {
+        column: String,
+        operator: ComparisonOperator,
+        value: Literal,
+    },
+    Between {
+        column: String,
+        lower_bound: Literal,
+        upper_bound: Literal,
+    },
+    In {
+        column: String,
+        values: Vec<Literal>,
+    },
+    Like {
+        column: String,
+        pattern: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ComparisonOperator {
+    Eq,
+    NotEq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Literal {
+    String(String),
+    Number(String),
+    Boolean(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// Unquoted identifier or keyword.
+    Word(String),
+    /// Identifier between double quotes or backticks.
+    QuotedIdent(String),
+    String(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(formatter, "{word}"),
+            Token::QuotedIdent(ident) => write!(formatter, "\"{ident}\""),
+            Token::String(string) => write!(formatter, "'{string}'"),
+            Token::Number(number) => write!(formatter, "{number}"),
+            Token::Symbol(symbol) => write!(formatter, "{symbol}"),
+        }
+    }
+}
+
+const KEYWORDS: [&str; 19] = [
+    "AND", "AS", "ASC", "BETWEEN", "BY", "DESC", "FALSE", "FROM", "GROUP", "IN", "LIKE", "LIMIT",
+    "NOT", "OFFSET", "OR", "ORDER", "SELECT", "TRUE", "WHERE",
+];
+
+const SYMBOLS: [&str; 12] = [
+    "<=", ">=", "!=", "<>", "*", ",", "(", ")", "=", "<", ">", ";",
+];
+
+pub(crate) fn invalid_sql(message: impl fmt::Display) -> SearchError {
+    SearchError::InvalidQuery(format!("Invalid SQL query: {message}"))
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, SearchError> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some(&(pos, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if ch.is_ascii_alphabetic() || ch == '_' {
+            let mut end = pos;
+            while let Some(&(next_pos, next_ch)) = chars.peek() {
+                if !(next_ch.is_ascii_alphanumeric() || ['_', '.', '-'].contains(&next_ch)) {
+                    break;
+                }
+                end = next_pos + next_ch.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Word(sql[pos..end].to_string()));
+            continue;
+        }
+        let is_negative_number = ch == '-'
+            && sql[pos + 1..]
+                .chars()
+                .next()
+                .map_or(false, |next_ch| next_ch.is_ascii_digit());
+        if ch.is_ascii_digit() || is_negative_number {
+            chars.next();
+            let mut end = pos + 1;
+            while let Some(&(next_pos, next_ch)) = chars.peek() {
+                if !(next_ch.is_ascii_alphanumeric() || next_ch == '.') {
+                    break;
+                }
+                end = next_pos + 1;
+                chars.next();
+            }
+            let number = &sql[pos..end];
+            if number.parse::<f64>().is_err() {
+                return Err(invalid_sql(format_args!(
+                    "`{number}` is not a valid number."
+                )));
+            }
+            tokens.push(Token::Number(number.to_string()));
+            continue;
+        }
+        if ['\'', '"', '`'].contains(&ch) {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    // A doubled quote stands for the quote itself.
+                    Some((_, next_ch)) if next_ch == ch => {
+                        if chars.peek().map_or(false, |&(_, next_ch)| next_ch == ch) {
+                            chars.next();
+                            text.push(ch);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some((_, next_ch)) => text.push(next_ch),
+                    None => {
+                        return Err(invalid_sql(format_args!(
+                            "unterminated quoted text at position {pos}."
+                        )))
+                    }
+                }
+            }
+            if ch == '\'' {
+                tokens.push(Token::String(text));
+            } else {
+                tokens.push(Token::QuotedIdent(text));
+            }
+            continue;
+        }
+        let Some(symbol) = SYMBOLS
+            .iter()
+            .find(|symbol| sql[pos..].starts_with(**symbol))
+        else {
+            return Err(invalid_sql(format_args!(
+                "unexpected character `{ch}` at position {pos}."
+            )));
+        };
+        for _ in 0..symbol.len() {
+            chars.next();
+        }
+        tokens.push(Token::Symbol(*symbol));
+    }
+    Ok(tokens)
+}
+
+/// Parses a SQL query.
+pub(crate) fn parse_sql_query(sql: &str) -> Result<SqlQuery, SearchError> {
+    let tokens = tokenize(sql)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let sql_query = parser.parse_query()?;
+    parser.consume_symbol(";");
+
+    if let Some(token) = parser.peek() {
+        return Err(invalid_sql(format_args!("unexpected `{token}`.")));
+    }
+    Ok(sql_query)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token_opt = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token_opt
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SearchError> {
+        if !self.consume_keyword(keyword) {
+            return Err(self.unexpected(keyword));
+        }
+        Ok(())
+    }
+
+    fn consume_symbol(&mut self, symbol: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Symbol(next_symbol)) if *next_symbol == symbol) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), SearchError> {
+        if !self.consume_symbol(symbol) {
+            return Err(self.unexpected(&format!("`{symbol}`")));
+        }
+        Ok(())
+    }
+
+    fn unexpected(&self, expected: &str) -> SearchError {
+        match self.peek() {
+            Some(token) => invalid_sql(format_args!("expected {expected}, found `{token}`.")),
+            None => invalid_sql(format_args!(
+                "expected {expected}, found the end of the query."
+            )),
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, SearchError> {
+        match self.peek() {
+            Some(Token::Word(word))
+                if !KEYWORDS
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword)) =>
+            {
+                let ident = word.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            Some(Token::QuotedIdent(ident)) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    fn parse_u64(&mut self) -> Result<u64, SearchError> {
+        match self.next() {
+            Some(Token::Number(number)) => number
+                .parse()
+                .map_err(|_| invalid_sql(format_args!("`{number}` is not a positive integer."))),
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("a positive integer"))
+            }
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<SqlQuery, SearchError> {
+        self.expect_keyword("SELECT")?;
+        let mut select_items = vec![self.parse_select_item()?];
+        while self.consume_symbol(",") {
+            select_items.push(self.parse_select_item()?);
+        }
+        self.expect_keyword("FROM")?;
+        let index_id = self.parse_ident()?;
+
+        let where_expr_opt = if self.consume_keyword("WHERE") {
+            Some(self.parse_or_expr()?)
+        } else {
+            None
+        };
+        let mut group_by = Vec::new();
+        if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_ident()?);
+            while self.consume_symbol(",") {
+                group_by.push(self.parse_ident()?);
+            }
+        }
+        let mut order_by = Vec::new();
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.parse_order_by_item()?);
+            while self.consume_symbol(",") {
+                order_by.push(self.parse_order_by_item()?);
+            }
+        }
+        let limit_opt = if self.consume_keyword("LIMIT") {
+            Some(self.parse_u64()?)
+        } else {
+            None
+        };
+        let offset_opt = if self.consume_keyword("OFFSET") {
+            Some(self.parse_u64()?)
+        } else {
+            None
+        };
+        Ok(SqlQuery {
+            select_items,
+            index_id,
+            where_expr_opt,
+            group_by,
+            order_by,
+            limit_opt,
+            offset_opt,
+        })
+    }
+
+    fn parse_select_expr(&mut self) -> Result<SelectExpr, SearchError> {
+        if self.consume_symbol("*") {
+            return Ok(SelectExpr::Wildcard);
+        }
+        let ident = self.parse_ident()?;
+
+        if !self.consume_symbol("(") {
+            return Ok(SelectExpr::Column(ident));
+        }
+        let function = AggregateFunction::from_name(&ident)
+            .ok_or_else(|| invalid_sql(format_args!("unsupported function `{ident}`.")))?;
+        let column_opt = if function == AggregateFunction::Count && self.consume_symbol("*") {
+            None
+        } else {
+            Some(self.parse_ident()?)
+        };
+        self.expect_symbol(")")?;
+        Ok(SelectExpr::Aggregate {
+            function,
+            column_opt,
+        })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem, SearchError> {
+        let expr = self.parse_select_expr()?;
+        let alias_opt = if self.consume_keyword("AS") {
+            Some(self.parse_ident()?)
+        } else {
+            None
+        };
+        Ok(SelectItem { expr, alias_opt })
+    }
+
+    fn parse_order_by_item(&mut self) -> Result<OrderByItem, SearchError> {
+        let expr = self.parse_select_expr()?;
+        if expr == SelectExpr::Wildcard {
+            return Err(invalid_sql("cannot order by `*`."));
+        }
+        let sort_order = if self.consume_keyword("DESC") {
+            SortOrder::Desc
+        } else {
+            self.consume_keyword("ASC");
+            SortOrder::Asc
+        };
+        Ok(OrderByItem { expr, sort_order })
+    }
+
+    fn parse_or_expr(&mut self) -> Result<Expr, SearchError> {
+        let mut expr = self.parse_and_expr()?;
+        while self.consume_keyword("OR") {
+            let right_expr = self.parse_and_expr()?;
+            expr = Expr::Or(Box::new(expr), Box::new(right_expr));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and_expr(&mut self) -> Result<Expr, SearchError> {
+        let mut expr = self.parse_not_expr()?;
+        while self.consume_keyword("AND") {
+            let right_expr = self.parse_not_expr()?;
+            expr = Expr::And(Box::new(expr), Box::new(right_expr));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not_expr(&mut self) -> Result<Expr, SearchError> {
+        if self.consume_keyword("NOT") {
+            let expr = self.parse_not_expr()?;
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        if self.consume_symbol("(") {
+            let expr = self.parse_or_expr()?;
+            self.expect_symbol(")")?;
+            return Ok(expr);
+        }
+        self.parse_predicate()
+    }
+
+    fn parse_predicate(&mut self) -> Result<Expr, SearchError> {
+        let column = self.parse_ident()?;
+        let negated = self.consume_keyword("NOT");
+
+        let expr = if self.consume_keyword("BETWEEN") {
+            let lower_bound = self.parse_literal()?;
+            self.expect_keyword("AND")?;
+            let upper_bound = self.parse_literal()?;
+            Expr::Between {
+                column,
+                lower_bound,
+                upper_bound,
+            }
+        } else if self.consume_keyword("IN") {
+            self.expect_symbol("(")?;
+            let mut values = vec![self.parse_literal()?];
+            while self.consume_symbol(",") {
+                values.push(self.parse_literal()?);
+            }
+            self.expect_symbol(")")?;
+            Expr::In { column, values }
+        } else if self.consume_keyword("LIKE") {
+            let pattern = match self.next() {
+                Some(Token::String(pattern)) => pattern,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected("a string pattern"));
+                }
+            };
+            Expr::Like { column, pattern }
+        } else if negated {
+            return Err(self.unexpected("`BETWEEN`, `IN`, or `LIKE`"));
+        } else {
+            let operator = match self.next() {
+                Some(Token::Symbol("=")) => ComparisonOperator::Eq,
+                Some(Token::Symbol("!=")) | Some(Token::Symbol("<>")) => ComparisonOperator::NotEq,
+                Some(Token::Symbol("<")) => ComparisonOperator::Lt,
+                Some(Token::Symbol("<=")) => ComparisonOperator::Lte,
+                Some(Token::Symbol(">")) => ComparisonOperator::Gt,
+                Some(Token::Symbol(">=")) => ComparisonOperator::Gte,
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.unexpected("a comparison operator"));
+                }
+            };
+            let value = self.parse_literal()?;
+            Expr::Comparison {
+                column,
+                operator,
+                value,
+            }
+        };
+        if negated {
+            return Ok(Expr::Not(Box::new(expr)));
+        }
+        Ok(expr)
+    }
+
+    fn parse_literal(&mut self) -> Result<Literal, SearchError> {
+        match self.next() {
+            Some(Token::String(string)) => Ok(Literal::String(string)),
+            Some(Token::Number(number)) => Ok(Literal::Number(number)),
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("TRUE") => {
+                Ok(Literal::Boolean(true))
+            }
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("FALSE") => {
+                Ok(Literal::Boolean(false))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("a literal value"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sql_query() {
+        let sql_query = parse_sql_query(
+            "SELECT service, COUNT(*), avg(latency) AS avg_latency FROM \"hdfs-logs\" WHERE \
+             level IN ('ERROR', 'WARN') AND NOT (latency < 10 OR host LIKE 'test%') AND ts \
+             BETWEEN 10 AND 20 GROUP BY service ORDER BY COUNT(*) DESC, service LIMIT 5 OFFSET \
+             2;",
+        )
+        .unwrap();
+        assert_eq!(
+            sql_query,
+            SqlQuery {
+                select_items: vec![
+                    SelectItem {
+                        expr: SelectExpr::Column("service".to_string()),
+                        alias_opt: None,
+                    },
+                    SelectItem {
+                        expr: SelectExpr::Aggregate {
+                            function: AggregateFunction::Count,
+                            column_opt: None,
+                        },
+                        alias_opt: None,
+                    },
+                    SelectItem {
+                        expr: SelectExpr::Aggregate {
+                            function: AggregateFunction::Avg,
+                            column_opt: Some("latency".to_string()),
+                        },
+                        alias_opt: Some("avg_latency".to_string()),
+                    },
+                ],
+                index_id: "hdfs-logs".to_string(),
+                where_expr_opt: Some(Expr::And(
+                    Box::new(Expr::And(
+                        Box::new(Expr::In {
+                            column: "level".to_string(),
+                            values: vec![
+                                Literal::String("ERROR".to_string()),
+                                Literal::String("WARN".to_string()),
+                            ],
+                        }),
+                        Box::new(Expr::Not(Box::new(Expr::Or(
+                            Box::new(Expr::Comparison {
+                                column: "latency".to_string(),
+                                operator: ComparisonOperator::Lt,
+                                value: Literal::Number("10".to_string()),
+                            }),
+                            Box::new(Expr::Like {
+                                column: "host".to_string(),
+                                pattern: "test%".to_string(),
+                            }),
+                        )))),
+                    )),
+                    Box::new(Expr::Between {
+                        column: "ts".to_string(),
+                        lower_bound: Literal::Number("10".to_string()),
+                        upper_bound: Literal::Number("20".to_string()),
+                    }),
+                )),
+                group_by: vec!["service".to_string()],
+                order_by: vec![
+                    OrderByItem {
+                        expr: SelectExpr::Aggregate {
+                            function: AggregateFunction::Count,
+                            column_opt: None,
+                        },
+                        sort_order: SortOrder::Desc,
+                    },
+                    OrderByItem {
+                        expr: SelectExpr::Column("service".to_string()),
+                        sort_order: SortOrder::Asc,
+                    },
+                ],
+                limit_opt: Some(5),
+                offset_opt: Some(2),
+            }
+        );
+        assert_eq!(sql_query.select_items[1].column_name(), "count(*)");
+        assert_eq!(sql_query.select_items[2].column_name(), "avg_latency");
+    }
+
+    #[test]
+    fn test_parse_sql_query_literals() {
+        let sql_query =
+            parse_sql_query("select * from logs where msg = 'it''s' and ok = true and x > -1.5")
+                .unwrap();
+        assert_eq!(sql_query.select_items[0].expr, SelectExpr::Wildcard);
+        assert_eq!(
+            sql_query.where_expr_opt.unwrap(),
+            Expr::And(
+                Box::new(Expr::And(
+                    Box::new(Expr::Comparison {
+                        column: "msg".to_string(),
+                        operator: ComparisonOperator::Eq,
+                        value: Literal::String("it's".to_string()),
+                    }),
+                    Box::new(Expr::Comparison {
+                        column: "ok".to_string(),
+                        operator: ComparisonOperator::Eq,
+                        value: Literal::Boolean(true),
+                    }),
+                )),
+                Box::new(Expr::Comparison {
+                    column: "x".to_string(),
+                    operator: ComparisonOperator::Gt,
+                    value: Literal::Number("-1.5".to_string()),
+                }),
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_sql_query() {
+        for (sql, expected_error) in [
+            ("SELECT FROM logs", "expected an identifier, found `FROM`"),
+            (
+                "SELECT a FROM",
+                "expected an identifier, found the end of the query",
+            ),
+            (
+                "SELECT a FROM logs WHERE a",
+                "expected a comparison operator",
+            ),
+            (
+                "SELECT a FROM logs WHERE a = 'b",
+                "unterminated quoted text",
+            ),
+            (
+                "SELECT median(a) FROM logs",
+                "unsupported function `median`",
+            ),
+            (
+                "SELECT a FROM logs LIMIT -1",
+                "`-1` is not a positive integer",
+            ),
+            ("SELECT a FROM logs extra", "unexpected `extra`"),
+            ("SELECT a FROM logs WHERE a ~ 1", "unexpected character `~`"),
+        ] {
+            let error = parse_sql_query(sql).unwrap_err();
+            assert!(error.to_string().contains(expected_error), "{sql}: {error}");
+        }
+    }
+}
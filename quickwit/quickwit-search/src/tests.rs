@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, BTreeSet};
 
 use assert_json_diff::{assert_json_eq, assert_json_include};
@@ -31,6 +32,7 @@ use tantivy::time::OffsetDateTime;
 use tantivy::Term;
 
 use super::*;
+use crate::explain::root_explain;
 use crate::find_trace_ids_collector::Span;
 use crate::single_node_search;
 
@@ -81,6 +83,80 @@ async fn test_single_node_simple() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_local_search() -> anyhow::Result<()> {
+    let index_id = "local-search-1";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"title": "snoopy", "body": "Snoopy is an anthropomorphic beagle."}),
+            json!({"title": "beagle", "body": "The beagle is a breed of small scent hound."}),
+        ])
+        .await?;
+    test_sandbox
+        .add_documents(vec![
+            json!({"title": "charlie", "body": "Charlie Brown owns a beagle named Snoopy."}),
+        ])
+        .await?;
+    let metastore = test_sandbox.metastore();
+    let index_uri = metastore
+        .index_metadata(index_id)
+        .await?
+        .index_uri()
+        .clone();
+    let split_uris: Vec<Uri> = metastore
+        .list_all_splits(index_id)
+        .await?
+        .iter()
+        .map(|split| index_uri.join(split_file(split.split_id())))
+        .collect::<anyhow::Result<_>>()?;
+    assert_eq!(split_uris.len(), 2);
+
+    let search_request = SearchRequest {
+        index_id: "unknown-index".to_string(),
+        query: "beagle".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = local_search(
+        &search_request,
+        test_sandbox.doc_mapper(),
+        &split_uris,
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 3);
+    assert_eq!(search_response.hits.len(), 3);
+
+    let search_response = local_search(
+        &search_request,
+        test_sandbox.doc_mapper(),
+        &split_uris[..1],
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert!(search_response.num_hits < 3);
+
+    let error = local_search(
+        &search_request,
+        test_sandbox.doc_mapper(),
+        &[index_uri.join("not-a-split.json")?],
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidArgument(_)));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_termset() -> anyhow::Result<()> {
     let index_id = "single-node-termset-1";
@@ -605,6 +681,268 @@ async fn test_single_node_invalid_sorting_with_query() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_search_after() -> anyhow::Result<()> {
+    let index_id = "single-node-search-after";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: id
+                type: u64
+                fast: true
+              - name: priority
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    for split_ord in 0..2u64 {
+        let docs: Vec<JsonValue> = (0..25u64)
+            .map(|i| json!({"body": "info", "id": split_ord * 25 + i, "priority": i % 4}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let mut search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        max_hits: 7,
+        sort_by_field: Some("priority".to_string()),
+        sort_order: Some(SortOrder::Asc as i32),
+        ..Default::default()
+    };
+    let mut ids = Vec::new();
+    let mut priorities = Vec::new();
+
+    loop {
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        assert_eq!(single_node_result.num_hits, 50);
+
+        let Some(last_hit) = single_node_result.hits.last() else {
+            break;
+        };
+        search_request.search_after = last_hit.partial_hit.clone();
+
+        for hit in &single_node_result.hits {
+            let hit_json: JsonValue = serde_json::from_str(&hit.json)?;
+            ids.push(hit_json["id"].as_u64().unwrap());
+            priorities.push(hit_json["priority"].as_u64().unwrap());
+        }
+    }
+    assert!(is_sorted(priorities.iter()));
+    ids.sort_unstable();
+    assert_eq!(ids, (0..50).collect::<Vec<u64>>());
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_sort_by_several_fields() -> anyhow::Result<()> {
+    let index_id = "single-node-sort-by-several-fields";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: id
+                type: u64
+                fast: true
+              - name: severity
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    for split_ord in 0..2u64 {
+        let docs: Vec<JsonValue> = (0..25u64)
+            .map(|i| json!({"body": "info", "id": split_ord * 25 + i, "severity": i % 3}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let mut search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        max_hits: 7,
+        sort_fields: Some(
+            json!([{"field": "severity"}, {"field": "id", "order": "asc"}]).to_string(),
+        ),
+        ..Default::default()
+    };
+    let mut hits = Vec::new();
+
+    loop {
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        assert_eq!(single_node_result.num_hits, 50);
+
+        let Some(last_hit) = single_node_result.hits.last() else {
+            break;
+        };
+        search_request.search_after = last_hit.partial_hit.clone();
+
+        for hit in &single_node_result.hits {
+            let hit_json: JsonValue = serde_json::from_str(&hit.json)?;
+            let severity = hit_json["severity"].as_u64().unwrap();
+            hits.push((Reverse(severity), hit_json["id"].as_u64().unwrap()));
+        }
+    }
+    assert_eq!(hits.len(), 50);
+    assert!(is_sorted(hits.iter()));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_sort_by_geo_distance() -> anyhow::Result<()> {
+    let index_id = "single-node-sort-by-geo-distance";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: city
+                type: text
+              - name: location
+                type: array<geo_point>
+        "#;
+    let docs = vec![
+        json!({"city": "paris", "location": ["48.8566,2.3522"]}),
+        json!({"city": "sydney", "location": ["-33.8688,151.2093"]}),
+        json!({"city": "nowhere"}),
+        json!({"city": "london", "location": ["51.5072,-0.1276", "-33.8688,151.2093"]}),
+        json!({"city": "versailles", "location": ["48.8049,2.1204"]}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["city"]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let sorted_cities = |sort_fields: JsonValue| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            sort_fields: Some(sort_fields.to_string()),
+            ..Default::default()
+        };
+        let test_sandbox = &test_sandbox;
+        async move {
+            let search_response = single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await?;
+            let cities: Vec<String> = search_response
+                .hits
+                .iter()
+                .map(|hit| {
+                    let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                    hit_json["city"].as_str().unwrap().to_string()
+                })
+                .collect();
+            anyhow::Ok(cities)
+        }
+    };
+    // Versailles is closer to Paris than London, and the closest point of London is the one
+    // in London.
+    assert_eq!(
+        sorted_cities(json!([{"field": "location", "origin": "48.86,2.29"}])).await?,
+        ["paris", "versailles", "london", "sydney", "nowhere"]
+    );
+    assert_eq!(
+        sorted_cities(json!([
+            {"field": "location", "origin": "48.86,2.29", "order": "desc", "missing": "first"}
+        ]))
+        .await?,
+        ["nowhere", "sydney", "london", "versailles", "paris"]
+    );
+    let error = sorted_cities(json!([{"field": "location"}]))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("requires an origin"));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_collapse() -> anyhow::Result<()> {
+    let index_id = "single-node-collapse";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: host
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: id
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    for split_ord in 0..2u64 {
+        let docs: Vec<JsonValue> = (0..20u64)
+            .map(|i| {
+                let host = format!("web-{}", i % 4);
+                json!({"body": "info", "host": host, "id": split_ord * 20 + i})
+            })
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        max_hits: 10,
+        sort_fields: Some(json!([{"field": "id"}]).to_string()),
+        collapse: Some(json!({"field": "host", "count": true}).to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(single_node_result.num_hits, 40);
+    let hits: Vec<(u64, String, Option<u64>)> = single_node_result
+        .hits
+        .iter()
+        .map(|hit| {
+            let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+            let partial_hit = hit.partial_hit.as_ref().unwrap();
+            (
+                hit_json["id"].as_u64().unwrap(),
+                hit_json["host"].as_str().unwrap().to_string(),
+                partial_hit.num_collapsed_hits,
+            )
+        })
+        .collect();
+    assert_eq!(
+        hits,
+        [
+            (39, "web-3".to_string(), Some(10)),
+            (38, "web-2".to_string(), Some(10)),
+            (37, "web-1".to_string(), Some(10)),
+            (36, "web-0".to_string(), Some(10)),
+        ]
+    );
+    let error = single_node_search(
+        &SearchRequest {
+            collapse: Some(json!({"field": "body"}).to_string()),
+            ..search_request
+        },
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidArgument(_)));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
     let doc_mapping_yaml = r#"
@@ -675,83 +1013,269 @@ async fn test_single_node_split_pruning_by_tags() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn test_search_dynamic_util(test_sandbox: &TestSandbox, query: &str) -> Vec<u32> {
-    let splits = test_sandbox
-        .metastore()
-        .list_all_splits(test_sandbox.index_id())
-        .await
-        .unwrap();
-    let splits_offsets: Vec<_> = splits
-        .into_iter()
-        .map(|split_meta| SplitIdAndFooterOffsets {
-            split_id: split_meta.split_id().to_string(),
-            split_footer_start: split_meta.split_metadata.footer_offsets.start,
-            split_footer_end: split_meta.split_metadata.footer_offsets.end,
-        })
-        .collect();
-    let request = quickwit_proto::SearchRequest {
-        index_id: test_sandbox.index_id().to_string(),
-        query: query.to_string(),
-        max_hits: 100,
-        ..Default::default()
-    };
-    let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
-    let search_response = leaf_search(
-        searcher_context,
-        &request,
-        test_sandbox.storage(),
-        &splits_offsets,
-        test_sandbox.doc_mapper(),
-    )
-    .await
-    .unwrap();
-    search_response
-        .partial_hits
-        .into_iter()
-        .map(|partial_hit| partial_hit.doc_id)
-        .collect::<Vec<u32>>()
-}
-
 #[tokio::test]
-async fn test_search_dynamic_mode() -> anyhow::Result<()> {
+async fn test_single_node_split_pruning_by_field_ranges() -> anyhow::Result<()> {
     let doc_mapping_yaml = r#"
+            range_pruning_fields:
+              - status
             field_mappings:
-              - name: body
-                type: text
-                tokenizer: default
-                indexed: true
-            mode: dynamic
-            dynamic_mapping:
-                tokenizer: raw
+              - name: status
+                type: u64
+                fast: true
         "#;
-    let test_sandbox = TestSandbox::create("search_dynamic_mode", doc_mapping_yaml, "{}", &[])
-        .await
-        .unwrap();
-    let docs = vec![
-        json!({"body": "hello happy tax payer"}),
-        json!({"body": "hello"}),
-        json!({"body_dynamic": "hello happy tax payer"}),
-        json!({"body_dynamic": "hello"}),
-    ];
-    test_sandbox.add_documents(docs).await.unwrap();
-    {
-        let docs = test_search_dynamic_util(&test_sandbox, "body:hello").await;
-        assert_eq!(&docs[..], &[0u32, 1u32]);
-    }
-    {
-        let docs = test_search_dynamic_util(&test_sandbox, "body_dynamic:hello").await;
-        assert_eq!(&docs[..], &[3u32]); // 1 is not matched due to the raw tokenizer
+    let index_id = "single-node-pruning-by-field-ranges";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    for statuses in [[200, 204], [404, 503]] {
+        let docs = statuses
+            .iter()
+            .map(|status| json!({"body": "content", "status": status}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
     }
+    let list_relevant_split_ranges = |query: &str| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let metastore = test_sandbox.metastore();
+        async move {
+            let mut split_ranges: Vec<String> = list_relevant_splits(&search_request, &*metastore)
+                .await
+                .unwrap()
+                .iter()
+                .map(|split| {
+                    let status_range = &split.field_ranges["status"];
+                    format!("{}-{}", status_range.start(), status_range.end())
+                })
+                .collect();
+            split_ranges.sort();
+            split_ranges
+        }
+    };
+    assert_eq!(
+        list_relevant_split_ranges("status:[500 TO 599]").await,
+        ["404-503"]
+    );
+    assert_eq!(list_relevant_split_ranges("status:204").await, ["200-204"]);
+    assert!(list_relevant_split_ranges("status:[300 TO 400]")
+        .await
+        .is_empty());
+    assert_eq!(
+        list_relevant_split_ranges("status:200 OR status:404").await,
+        ["200-204", "404-503"]
+    );
+    assert_eq!(
+        list_relevant_split_ranges("body:content AND NOT status:200").await,
+        ["200-204", "404-503"]
+    );
     test_sandbox.assert_quit().await;
     Ok(())
 }
 
 #[tokio::test]
-async fn test_search_dynamic_mode_expand_dots() -> anyhow::Result<()> {
+async fn test_single_node_split_pruning_by_bloom_filters() -> anyhow::Result<()> {
     let doc_mapping_yaml = r#"
-            field_mappings: []
-            mode: dynamic
-            #dynamic_mapping:
+            bloom_filter_fields:
+              - trace_id
+            field_mappings:
+              - name: trace_id
+                type: text
+                tokenizer: raw
+        "#;
+    let index_id = "single-node-pruning-by-bloom-filters";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    for trace_ids in [["trace-1", "trace-2"], ["trace-3", "trace-4"]] {
+        let docs = trace_ids
+            .iter()
+            .map(|trace_id| json!({"body": "content", "trace_id": trace_id}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let count_relevant_splits = |query: &str| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        };
+        let metastore = test_sandbox.metastore();
+        async move {
+            let splits = list_relevant_splits(&search_request, &*metastore)
+                .await
+                .unwrap();
+            assert!(splits
+                .iter()
+                .all(|split| split.bloom_filters.contains_key("trace_id")));
+            splits.len()
+        }
+    };
+    assert_eq!(count_relevant_splits("trace_id:trace-1").await, 1);
+    assert_eq!(count_relevant_splits("trace_id:trace-5").await, 0);
+    assert_eq!(
+        count_relevant_splits("trace_id:trace-1 OR trace_id:trace-4").await,
+        2
+    );
+    assert_eq!(
+        count_relevant_splits("body:content AND NOT trace_id:trace-1").await,
+        2
+    );
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "trace_id:trace-3".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_explain() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            range_pruning_fields:
+              - status
+            field_mappings:
+              - name: status
+                type: u64
+                fast: true
+        "#;
+    let index_id = "single-node-explain";
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    for statuses in [[200, 204], [404, 503]] {
+        let docs = statuses
+            .iter()
+            .map(|status| json!({"body": "content", "status": status}))
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "status:[500 TO 599]".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+    let explanation = root_explain(
+        &search_request,
+        &*test_sandbox.metastore(),
+        &test_sandbox.storage_uri_resolver(),
+        &searcher_context,
+    )
+    .await?;
+    assert_eq!(explanation.query, "status:[500 TO 599]");
+    assert!(explanation.parsed_query.contains("status"));
+    assert_eq!(explanation.num_published_splits, 2);
+    assert_eq!(explanation.pruned_splits.len(), 1);
+    assert_eq!(
+        explanation.pruned_splits[0].reason,
+        PruningReason::FieldRanges
+    );
+    assert_eq!(explanation.searched_splits.len(), 1);
+    assert_eq!(explanation.num_hits, 1);
+
+    let searched_split = &explanation.searched_splits[0];
+    assert_eq!(searched_split.num_docs, 2);
+    assert_eq!(searched_split.num_hits, 1);
+    assert!(!searched_split.split_footer_cache_hit);
+    assert!(searched_split.error.is_none());
+
+    let explanation = root_explain(
+        &search_request,
+        &*test_sandbox.metastore(),
+        &test_sandbox.storage_uri_resolver(),
+        &searcher_context,
+    )
+    .await?;
+    assert!(explanation.searched_splits[0].split_footer_cache_hit);
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+async fn test_search_dynamic_util(test_sandbox: &TestSandbox, query: &str) -> Vec<u32> {
+    let splits = test_sandbox
+        .metastore()
+        .list_all_splits(test_sandbox.index_id())
+        .await
+        .unwrap();
+    let splits_offsets: Vec<_> = splits
+        .into_iter()
+        .map(|split_meta| SplitIdAndFooterOffsets {
+            split_id: split_meta.split_id().to_string(),
+            split_footer_start: split_meta.split_metadata.footer_offsets.start,
+            split_footer_end: split_meta.split_metadata.footer_offsets.end,
+            split_file: split_meta.split_metadata.split_file(),
+        })
+        .collect();
+    let request = quickwit_proto::SearchRequest {
+        index_id: test_sandbox.index_id().to_string(),
+        query: query.to_string(),
+        max_hits: 100,
+        ..Default::default()
+    };
+    let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+    let search_response = leaf_search(
+        searcher_context,
+        &request,
+        test_sandbox.storage(),
+        &splits_offsets,
+        test_sandbox.doc_mapper(),
+    )
+    .await
+    .unwrap();
+    search_response
+        .partial_hits
+        .into_iter()
+        .map(|partial_hit| partial_hit.doc_id)
+        .collect::<Vec<u32>>()
+}
+
+#[tokio::test]
+async fn test_search_dynamic_mode() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+                tokenizer: default
+                indexed: true
+            mode: dynamic
+            dynamic_mapping:
+                tokenizer: raw
+        "#;
+    let test_sandbox = TestSandbox::create("search_dynamic_mode", doc_mapping_yaml, "{}", &[])
+        .await
+        .unwrap();
+    let docs = vec![
+        json!({"body": "hello happy tax payer"}),
+        json!({"body": "hello"}),
+        json!({"body_dynamic": "hello happy tax payer"}),
+        json!({"body_dynamic": "hello"}),
+    ];
+    test_sandbox.add_documents(docs).await.unwrap();
+    {
+        let docs = test_search_dynamic_util(&test_sandbox, "body:hello").await;
+        assert_eq!(&docs[..], &[0u32, 1u32]);
+    }
+    {
+        let docs = test_search_dynamic_util(&test_sandbox, "body_dynamic:hello").await;
+        assert_eq!(&docs[..], &[3u32]); // 1 is not matched due to the raw tokenizer
+    }
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_dynamic_mode_expand_dots() -> anyhow::Result<()> {
+    let doc_mapping_yaml = r#"
+            field_mappings: []
+            mode: dynamic
+            #dynamic_mapping:
             #  expand_dots: true -- that's the default value.
         "#;
     let test_sandbox = TestSandbox::create(
@@ -1013,10 +1537,498 @@ async fn test_single_node_aggregation() -> anyhow::Result<()> {
     test_sandbox.add_documents(docs.clone()).await?;
     let search_request = SearchRequest {
         index_id: index_id.to_string(),
-        query: "*".to_string(),
-        search_fields: vec!["color".to_string()],
-        max_hits: 2,
-        start_offset: 0,
+        query: "*".to_string(),
+        search_fields: vec!["color".to_string()],
+        max_hits: 2,
+        start_offset: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(
+        agg_res_json["expensive_colors"]["buckets"][0]["key"],
+        "white"
+    );
+    assert_eq!(
+        agg_res_json["expensive_colors"]["buckets"][1]["key"],
+        "blue"
+    );
+    assert_eq!(
+        agg_res_json["expensive_colors"]["buckets"][2]["key"],
+        "green"
+    );
+    assert!(single_node_result.elapsed_time_micros > 10);
+    assert!(single_node_result.elapsed_time_micros < 1_000_000);
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_percentiles_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-percentiles";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: service
+                type: text
+                tokenizer: raw
+              - name: latency
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["service"]).await?;
+    let docs: Vec<JsonValue> = (1..=100)
+        .map(|latency| json!({"service": "frontend", "latency": latency}))
+        .collect();
+    test_sandbox.add_documents(docs).await?;
+    let agg_req = json!({
+        "latency_percentiles": {
+            "percentiles": {"field": "latency", "percents": [0, 50, 100]}
+        },
+        "max_latency": {"max": {"field": "latency"}}
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "service:frontend".to_string(),
+        max_hits: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    let percentiles_json = &agg_res_json["latency_percentiles"]["values"];
+    assert_eq!(percentiles_json["0.0"], 1.0);
+    assert_eq!(percentiles_json["100.0"], 100.0);
+    let median = percentiles_json["50.0"].as_f64().unwrap();
+    assert!((49.0..=52.0).contains(&median));
+    assert_eq!(agg_res_json["max_latency"]["value"], 100.0);
+
+    let agg_req = json!({
+        "service_percentiles": {"percentiles": {"field": "service"}}
+    });
+    let search_request = SearchRequest {
+        aggregation_request: Some(agg_req.to_string()),
+        ..search_request
+    };
+    let search_error = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        search_error,
+        SearchError::InvalidAggregationRequest(_)
+    ));
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_cardinality_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-cardinality";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: service
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: user_id
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: status
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["service"]).await?;
+    let docs: Vec<JsonValue> = (0..100)
+        .map(|doc_id| {
+            let service = if doc_id % 4 == 0 {
+                "backend"
+            } else {
+                "frontend"
+            };
+            json!({
+                "service": service,
+                "user_id": format!("user-{}", doc_id % 20),
+                "status": 200 + doc_id % 3,
+            })
+        })
+        .collect();
+    test_sandbox.add_documents(docs).await?;
+    let agg_req = json!({
+        "unique_users": {"cardinality": {"field": "user_id"}},
+        "unique_statuses": {"cardinality": {"field": "status"}},
+        "services": {
+            "terms": {"field": "service"},
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        max_hits: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(agg_res_json["unique_users"]["value"], 20);
+    assert_eq!(agg_res_json["unique_statuses"]["value"], 3);
+    assert_eq!(
+        agg_res_json["services"]["buckets"],
+        json!([
+            {"key": "frontend", "doc_count": 75, "unique_users": {"value": 15}},
+            {"key": "backend", "doc_count": 25, "unique_users": {"value": 5}}
+        ])
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_date_histogram_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-date-histogram";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: timestamp
+                type: datetime
+                fast: true
+              - name: user_id
+                type: text
+                tokenizer: raw
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["user_id"]).await?;
+    // The clocks were set forward in Paris on 2023-03-26.
+    let docs = vec![
+        json!({"timestamp": "2023-03-25T23:30:00Z", "user_id": "alice"}),
+        json!({"timestamp": "2023-03-26T12:00:00Z", "user_id": "bob"}),
+        json!({"timestamp": "2023-03-26T21:30:00Z", "user_id": "alice"}),
+        json!({"timestamp": "2023-03-26T22:30:00Z", "user_id": "carol"}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let agg_req = json!({
+        "daily": {
+            "date_histogram": {
+                "field": "timestamp",
+                "calendar_interval": "day",
+                "time_zone": "Europe/Paris"
+            },
+            "aggs": {"unique_users": {"cardinality": {"field": "user_id"}}}
+        }
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        max_hits: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(
+        agg_res_json["daily"]["buckets"],
+        json!([
+            {
+                "key": 1_679_785_200_000i64,
+                "key_as_string": "2023-03-26T00:00:00+01:00",
+                "doc_count": 3,
+                "unique_users": {"value": 2}
+            },
+            {
+                "key": 1_679_868_000_000i64,
+                "key_as_string": "2023-03-27T00:00:00+02:00",
+                "doc_count": 1,
+                "unique_users": {"value": 1}
+            }
+        ])
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_pipeline_aggregations() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-pipeline";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: timestamp
+                type: datetime
+                fast: true
+              - name: latency
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    let docs = vec![
+        json!({"timestamp": "2023-01-01T00:10:00Z", "latency": 10}),
+        json!({"timestamp": "2023-01-01T01:10:00Z", "latency": 30}),
+        json!({"timestamp": "2023-01-01T01:20:00Z", "latency": 50}),
+        json!({"timestamp": "2023-01-01T03:10:00Z", "latency": 20}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+    let agg_req = json!({
+        "hourly": {
+            "date_histogram": {"field": "timestamp", "fixed_interval": "1h"},
+            "aggs": {
+                "total_count": {"cumulative_sum": {"buckets_path": "_count"}},
+                "count_rate": {"derivative": {"buckets_path": "_count"}}
+            }
+        },
+        "latency_histogram": {
+            "histogram": {"field": "latency", "interval": 20},
+            "aggs": {
+                "max_latency": {"max": {"field": "latency"}},
+                "max_latency_rate": {"derivative": {"buckets_path": "max_latency"}}
+            }
+        }
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        max_hits: 0,
+        aggregation_request: Some(agg_req.to_string()),
+        ..Default::default()
+    };
+    let single_node_result = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    let hourly_buckets = agg_res_json["hourly"]["buckets"].as_array().unwrap();
+    let total_counts: Vec<&JsonValue> = hourly_buckets
+        .iter()
+        .map(|bucket| &bucket["total_count"]["value"])
+        .collect();
+    assert_eq!(
+        total_counts,
+        [&json!(1.0), &json!(3.0), &json!(3.0), &json!(4.0)]
+    );
+    let count_rates: Vec<&JsonValue> = hourly_buckets
+        .iter()
+        .map(|bucket| &bucket["count_rate"]["value"])
+        .collect();
+    assert_eq!(
+        count_rates,
+        [&JsonValue::Null, &json!(1.0), &json!(-2.0), &json!(1.0)]
+    );
+    let latency_buckets = agg_res_json["latency_histogram"]["buckets"]
+        .as_array()
+        .unwrap();
+    assert_eq!(latency_buckets[1]["max_latency_rate"]["value"], 20.0);
+    assert_eq!(latency_buckets[2]["max_latency_rate"]["value"], 20.0);
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_composite_aggregation() -> anyhow::Result<()> {
+    let index_id = "single-node-agg-composite";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: service
+                type: text
+                tokenizer: raw
+                fast: true
+              - name: status
+                type: u64
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["service"]).await?;
+    for services in [
+        ["backend", "frontend", "database"],
+        ["frontend", "auth", "backend"],
+    ] {
+        let docs: Vec<JsonValue> = (0..30)
+            .map(|doc_id| {
+                json!({
+                    "service": services[doc_id % 3],
+                    "status": [200, 404][doc_id % 2],
+                })
+            })
+            .collect();
+        test_sandbox.add_documents(docs).await?;
+    }
+    let mut after_opt: Option<JsonValue> = None;
+    let mut keys = Vec::new();
+
+    loop {
+        let mut composite_json = json!({
+            "size": 3,
+            "sources": [
+                {"service": {"terms": {"field": "service"}}},
+                {"status": {"terms": {"field": "status", "order": "desc"}}}
+            ]
+        });
+        if let Some(after) = after_opt.take() {
+            composite_json["after"] = after;
+        }
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: "*".to_string(),
+            max_hits: 0,
+            aggregation_request: Some(
+                json!({ "pages": { "composite": composite_json } }).to_string(),
+            ),
+            ..Default::default()
+        };
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        let agg_res_json: JsonValue =
+            serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+        let buckets = agg_res_json["pages"]["buckets"].as_array().unwrap();
+
+        if buckets.is_empty() {
+            break;
+        }
+        for bucket in buckets {
+            keys.push((
+                bucket["key"]["service"].as_str().unwrap().to_string(),
+                bucket["key"]["status"].as_u64().unwrap(),
+                bucket["doc_count"].as_u64().unwrap(),
+            ));
+        }
+        after_opt = Some(agg_res_json["pages"]["after_key"].clone());
+    }
+    assert_eq!(
+        keys,
+        [
+            ("auth".to_string(), 404, 5),
+            ("auth".to_string(), 200, 5),
+            ("backend".to_string(), 404, 10),
+            ("backend".to_string(), 200, 10),
+            ("database".to_string(), 404, 5),
+            ("database".to_string(), 200, 5),
+            ("frontend".to_string(), 404, 10),
+            ("frontend".to_string(), 200, 10),
+        ]
+    );
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_multi_valued_fast_field() -> anyhow::Result<()> {
+    let index_id = "single-node-multi-valued-fast-field";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: id
+                type: u64
+                fast: true
+              - name: latencies
+                type: array<u64>
+                fast: true
+        "#;
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"]).await?;
+    let docs = vec![
+        json!({"body": "info", "id": 0, "latencies": [5, 30]}),
+        json!({"body": "info", "id": 1, "latencies": [12]}),
+        json!({"body": "info", "id": 2}),
+        json!({"body": "info", "id": 3, "latencies": [1, 50, 20]}),
+    ];
+    test_sandbox.add_documents(docs).await?;
+
+    async fn search_ids(
+        test_sandbox: &TestSandbox,
+        search_request: SearchRequest,
+    ) -> anyhow::Result<Vec<u64>> {
+        let single_node_result = single_node_search(
+            &search_request,
+            &*test_sandbox.metastore(),
+            test_sandbox.storage_uri_resolver(),
+        )
+        .await?;
+        let ids = single_node_result
+            .hits
+            .iter()
+            .map(|hit| {
+                let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                hit_json["id"].as_u64().unwrap()
+            })
+            .collect();
+        Ok(ids)
+    }
+    // A document matches a range query if any of its values is in the range.
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "latencies:[25 TO 40]".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    assert_eq!(search_ids(&test_sandbox, search_request).await?, [0]);
+
+    // The documents are sorted by their highest value in the descending order and by their lowest
+    // value in the ascending order, unless the mode says otherwise.
+    for (sort_field, expected_ids) in [
+        (json!({"field": "latencies"}), [3, 0, 1, 2]),
+        (json!({"field": "latencies", "order": "asc"}), [3, 0, 1, 2]),
+        (
+            json!({"field": "latencies", "order": "asc", "mode": "max"}),
+            [1, 0, 3, 2],
+        ),
+        (
+            json!({"field": "latencies", "mode": "min", "missing": "first"}),
+            [2, 1, 0, 3],
+        ),
+    ] {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: "info".to_string(),
+            max_hits: 10,
+            sort_fields: Some(json!([sort_field]).to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            search_ids(&test_sandbox, search_request).await?,
+            expected_ids
+        );
+    }
+
+    // The aggregations take all the values into account.
+    let agg_req = json!({
+        "latencies_sum": {"sum": {"field": "latencies"}},
+        "latencies_stats": {"stats": {"field": "latencies"}},
+        "latencies_histogram": {"histogram": {"field": "latencies", "interval": 10}},
+        "id_histogram": {
+            "histogram": {"field": "id", "interval": 2},
+            "aggs": {"max_latency": {"max": {"field": "latencies"}}}
+        }
+    });
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "info".to_string(),
+        max_hits: 0,
         aggregation_request: Some(agg_req.to_string()),
         ..Default::default()
     };
@@ -1027,20 +2039,40 @@ async fn test_single_node_aggregation() -> anyhow::Result<()> {
     )
     .await?;
     let agg_res_json: JsonValue = serde_json::from_str(&single_node_result.aggregation.unwrap())?;
+    assert_eq!(agg_res_json["latencies_sum"]["value"], 118.0);
     assert_eq!(
-        agg_res_json["expensive_colors"]["buckets"][0]["key"],
-        "white"
-    );
-    assert_eq!(
-        agg_res_json["expensive_colors"]["buckets"][1]["key"],
-        "blue"
+        agg_res_json["latencies_stats"],
+        json!({"count": 6, "sum": 118.0, "min": 1.0, "max": 50.0, "avg": 118.0 / 6.0})
     );
+    let histogram_doc_counts: Vec<(f64, u64)> = agg_res_json["latencies_histogram"]["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| {
+            (
+                bucket["key"].as_f64().unwrap(),
+                bucket["doc_count"].as_u64().unwrap(),
+            )
+        })
+        .collect();
     assert_eq!(
-        agg_res_json["expensive_colors"]["buckets"][2]["key"],
-        "green"
+        histogram_doc_counts,
+        [
+            (0.0, 2),
+            (10.0, 1),
+            (20.0, 1),
+            (30.0, 1),
+            (40.0, 0),
+            (50.0, 1)
+        ]
     );
-    assert!(single_node_result.elapsed_time_micros > 10);
-    assert!(single_node_result.elapsed_time_micros < 1_000_000);
+    let max_latencies: Vec<JsonValue> = agg_res_json["id_histogram"]["buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket["max_latency"]["value"].clone())
+        .collect();
+    assert_eq!(max_latencies, [json!(30.0), json!(50.0)]);
     test_sandbox.assert_quit().await;
     Ok(())
 }
@@ -1312,6 +2344,434 @@ async fn test_single_node_range_queries() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_single_node_geo_filter() -> anyhow::Result<()> {
+    let index_id = "single-node-geo-filter";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: city
+                type: text
+              - name: location
+                type: geo_point
+              - name: route
+                type: array<geo_point>
+        "#;
+    let docs = vec![
+        json!({"city": "paris", "location": {"lat": 48.8566, "lon": 2.3522}, "route": ["48.8566,2.3522", "51.5072,-0.1276"]}),
+        json!({"city": "versailles", "location": [2.1204, 48.8049]}),
+        json!({"city": "london", "location": "51.5072,-0.1276"}),
+        json!({"city": "sydney", "location": {"type": "Point", "coordinates": [151.2093, -33.8688]}}),
+        json!({"city": "nowhere"}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["city"]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let geo_filter_search = |geo_filter: JsonValue| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            geo_filter: Some(geo_filter.to_string()),
+            ..Default::default()
+        };
+        let test_sandbox = &test_sandbox;
+        async move {
+            single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await
+        }
+    };
+    let matching_cities = |search_response: SearchResponse| -> Vec<String> {
+        search_response
+            .hits
+            .iter()
+            .map(|hit| {
+                let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                hit_json["city"].as_str().unwrap().to_string()
+            })
+            .sorted()
+            .collect()
+    };
+    let search_response = geo_filter_search(json!({
+        "field": "location",
+        "bounding_box": {"top_left": {"lat": 60, "lon": -10}, "bottom_right": {"lat": 40, "lon": 10}}
+    }))
+    .await?;
+    assert_eq!(
+        matching_cities(search_response),
+        ["london", "paris", "versailles"]
+    );
+
+    let search_response = geo_filter_search(json!({
+        "field": "location",
+        "distance": {"center": "48.8566,2.3522", "radius_meters": 50000}
+    }))
+    .await?;
+    assert_eq!(matching_cities(search_response), ["paris", "versailles"]);
+
+    let search_response = geo_filter_search(json!({
+        "field": "location",
+        "polygon": {"points": [[140, -20], [160, -20], [160, -40], [140, -40]]}
+    }))
+    .await?;
+    assert_eq!(matching_cities(search_response), ["sydney"]);
+
+    let search_response = geo_filter_search(json!({
+        "field": "route",
+        "distance": {"center": "51.5,-0.12", "radius_meters": 10000}
+    }))
+    .await?;
+    assert_eq!(matching_cities(search_response), ["paris"]);
+
+    let error = geo_filter_search(json!({
+        "field": "city",
+        "distance": {"center": "51.5,-0.12", "radius_meters": 10000}
+    }))
+    .await
+    .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidArgument(_)));
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_nested_query() -> anyhow::Result<()> {
+    let index_id = "single-node-nested-query";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: trace_id
+                type: text
+                tokenizer: raw
+              - name: spans
+                type: nested
+                field_mappings:
+                  - name: name
+                    type: text
+                  - name: duration
+                    type: u64
+                    fast: true
+        "#;
+    let docs = vec![
+        json!({"trace_id": "1", "spans": [{"name": "foo", "duration": 150}, {"name": "bar", "duration": 50}]}),
+        json!({"trace_id": "2", "spans": [{"name": "foo", "duration": 50}, {"name": "bar", "duration": 150}]}),
+        json!({"trace_id": "3", "spans": {"name": "foo", "duration": 200}}),
+        json!({"trace_id": "4"}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["trace_id"]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let nested_query_search = |query: &str, nested_query: JsonValue| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            max_hits: 10,
+            nested_query: Some(nested_query.to_string()),
+            ..Default::default()
+        };
+        let test_sandbox = &test_sandbox;
+        async move {
+            single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await
+        }
+    };
+    let matching_trace_ids = |search_response: SearchResponse| -> Vec<String> {
+        search_response
+            .hits
+            .iter()
+            .map(|hit| {
+                let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                hit_json["trace_id"].as_str().unwrap().to_string()
+            })
+            .sorted()
+            .collect()
+    };
+
+    // Without the nested query, the clauses can match different spans.
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "spans.name:foo AND spans.duration:>100".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 3);
+
+    let search_response = nested_query_search(
+        "*",
+        json!({"path": "spans", "query": "spans.name:foo AND spans.duration:>100"}),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 2);
+    assert_eq!(matching_trace_ids(search_response), ["1", "3"]);
+
+    let search_response = nested_query_search(
+        "trace_id:2 OR trace_id:3",
+        json!({"path": "spans", "query": "spans.name:foo AND spans.duration:>100"}),
+    )
+    .await?;
+    assert_eq!(matching_trace_ids(search_response), ["3"]);
+
+    let search_response = nested_query_search(
+        "*",
+        json!({"path": "spans", "query": "spans.name:bar AND spans.duration:>100"}),
+    )
+    .await?;
+    assert_eq!(matching_trace_ids(search_response), ["2"]);
+
+    // The nested elements are returned as indexed.
+    let search_response = nested_query_search(
+        "trace_id:2",
+        json!({"path": "spans", "query": "spans.name:bar"}),
+    )
+    .await?;
+    let hit_json: JsonValue = serde_json::from_str(&search_response.hits[0].json)?;
+    assert_eq!(
+        hit_json["spans"],
+        json!([{"name": "foo", "duration": 50}, {"name": "bar", "duration": 150}])
+    );
+
+    let error = nested_query_search("*", json!({"path": "trace_id", "query": "trace_id:1"}))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidArgument(_)));
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_runtime_fields() -> anyhow::Result<()> {
+    let index_id = "single-node-runtime-fields";
+    let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: name
+                type: text
+                tokenizer: raw
+              - name: start
+                type: datetime
+                input_formats: [unix_timestamp]
+                fast: true
+              - name: end
+                type: datetime
+                input_formats: [unix_timestamp]
+                fast: true
+              - name: retries
+                type: u64
+                fast: true
+        "#;
+    let docs = vec![
+        json!({"name": "a", "start": 1000, "end": 1002, "retries": 0}),
+        json!({"name": "b", "start": 1000, "end": 1010, "retries": 3}),
+        json!({"name": "c", "start": 1000, "end": 1005, "retries": 1}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["name"]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let runtime_fields_search = |runtime_filter: Option<&str>, sort_by_field: Option<&str>| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: "*".to_string(),
+            max_hits: 10,
+            sort_by_field: sort_by_field.map(ToString::to_string),
+            sort_order: Some(SortOrder::Asc as i32),
+            runtime_fields: Some(
+                json!({"duration_ms": "end - start", "attempts": "retries + 1"}).to_string(),
+            ),
+            runtime_filter: runtime_filter.map(ToString::to_string),
+            ..Default::default()
+        };
+        let test_sandbox = &test_sandbox;
+        async move {
+            single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await
+        }
+    };
+    let hit_names = |search_response: SearchResponse| -> Vec<String> {
+        search_response
+            .hits
+            .iter()
+            .map(|hit| {
+                let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+                hit_json["name"].as_str().unwrap().to_string()
+            })
+            .collect()
+    };
+
+    let search_response = runtime_fields_search(None, Some("duration_ms")).await?;
+    assert_eq!(hit_names(search_response), ["a", "c", "b"]);
+
+    let search_response = runtime_fields_search(
+        Some("duration_ms > 3000 AND attempts < 4"),
+        Some("attempts"),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    assert_eq!(hit_names(search_response), ["c"]);
+
+    let error = runtime_fields_search(Some("unknown > 1"), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidQuery(_)));
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "*".to_string(),
+        runtime_fields: Some(json!({"duration_ms": "end - start"}).to_string()),
+        aggregation_request: Some(
+            json!({"max_duration": {"max": {"field": "duration_ms"}}}).to_string(),
+        ),
+        ..Default::default()
+    };
+    let error = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidAggregationRequest(_)));
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_with_custom_tokenizer() -> anyhow::Result<()> {
+    let index_id = "single-node-custom-tokenizer";
+    let doc_mapping_yaml = r#"
+            tokenizers:
+              - name: url_path
+                type: regex
+                pattern: "[^/]+"
+                filters: [lower_case]
+            field_mappings:
+              - name: path
+                type: text
+                tokenizer: url_path
+        "#;
+    let docs = vec![
+        json!({"path": "/API/v1/Users"}),
+        json!({"path": "/api/v2/user-groups"}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["path"]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let custom_tokenizer_search = |query: &str| {
+        let search_request = SearchRequest {
+            index_id: index_id.to_string(),
+            query: query.to_string(),
+            max_hits: 10,
+            ..Default::default()
+        };
+        let test_sandbox = &test_sandbox;
+        async move {
+            single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await
+        }
+    };
+    assert_eq!(custom_tokenizer_search("users").await?.num_hits, 1);
+    assert_eq!(custom_tokenizer_search("user-groups").await?.num_hits, 1);
+    assert_eq!(custom_tokenizer_search("path:API").await?.num_hits, 2);
+    assert_eq!(custom_tokenizer_search("user").await?.num_hits, 0);
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_single_node_search_with_field_aliases() -> anyhow::Result<()> {
+    let index_id = "single-node-field-aliases";
+    let doc_mapping_yaml = r#"
+            field_aliases:
+              message: body.text
+              level: severity_number
+            field_mappings:
+              - name: body
+                type: object
+                field_mappings:
+                  - name: text
+                    type: text
+              - name: severity_number
+                type: u64
+                fast: true
+        "#;
+    let docs = vec![
+        json!({"body": {"text": "disk full"}, "severity_number": 17}),
+        json!({"body": {"text": "disk ok"}, "severity_number": 9}),
+        json!({"body": {"text": "network down"}, "severity_number": 21}),
+    ];
+    let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &[]).await?;
+    test_sandbox.add_documents(docs).await?;
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "message:disk AND level:>10".to_string(),
+        max_hits: 10,
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    assert_eq!(search_response.num_hits, 1);
+    let hit_json: JsonValue = serde_json::from_str(&search_response.hits[0].json)?;
+    assert_eq!(hit_json["body"]["text"], "disk full");
+
+    let search_request = SearchRequest {
+        index_id: index_id.to_string(),
+        query: "disk".to_string(),
+        search_fields: vec!["message".to_string()],
+        max_hits: 10,
+        sort_by_field: Some("level".to_string()),
+        sort_order: Some(SortOrder::Asc as i32),
+        aggregation_request: Some(json!({"max_level": {"max": {"field": "level"}}}).to_string()),
+        ..Default::default()
+    };
+    let search_response = single_node_search(
+        &search_request,
+        &*test_sandbox.metastore(),
+        test_sandbox.storage_uri_resolver(),
+    )
+    .await?;
+    let severity_numbers: Vec<u64> = search_response
+        .hits
+        .iter()
+        .map(|hit| {
+            let hit_json: JsonValue = serde_json::from_str(&hit.json).unwrap();
+            hit_json["severity_number"].as_u64().unwrap()
+        })
+        .collect();
+    assert_eq!(severity_numbers, [9, 17]);
+    let aggregation_json: JsonValue = serde_json::from_str(&search_response.aggregation.unwrap())?;
+    assert_eq!(aggregation_json["max_level"]["value"], 17.0);
+
+    test_sandbox.assert_quit().await;
+    Ok(())
+}
+
 fn collect_str_terms(response: LeafListTermsResponse) -> Vec<String> {
     response
         .terms
@@ -1352,6 +2812,7 @@ async fn test_single_node_list_terms() -> anyhow::Result<()> {
             split_id: split_meta.split_id().to_string(),
             split_footer_start: split_meta.split_metadata.footer_offsets.start,
             split_footer_end: split_meta.split_metadata.footer_offsets.end,
+            split_file: split_meta.split_metadata.split_file(),
         })
         .collect();
     let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
@@ -1365,12 +2826,15 @@ async fn test_single_node_list_terms() -> anyhow::Result<()> {
             start_timestamp: None,
             end_timestamp: None,
             max_hits: Some(100),
+            query: None,
+            sort_by_doc_count: false,
         };
         let search_response = leaf_list_terms(
             searcher_context.clone(),
             &request,
             test_sandbox.storage(),
             &splits_offsets,
+            None,
         )
         .await
         .unwrap();
@@ -1386,12 +2850,15 @@ async fn test_single_node_list_terms() -> anyhow::Result<()> {
             start_timestamp: None,
             end_timestamp: None,
             max_hits: Some(1),
+            query: None,
+            sort_by_doc_count: false,
         };
         let search_response = leaf_list_terms(
             searcher_context.clone(),
             &request,
             test_sandbox.storage(),
             &splits_offsets,
+            None,
         )
         .await
         .unwrap();
@@ -1407,12 +2874,15 @@ async fn test_single_node_list_terms() -> anyhow::Result<()> {
             start_timestamp: None,
             end_timestamp: None,
             max_hits: Some(100),
+            query: None,
+            sort_by_doc_count: false,
         };
         let search_response = leaf_list_terms(
             searcher_context.clone(),
             &request,
             test_sandbox.storage(),
             &splits_offsets,
+            None,
         )
         .await
         .unwrap();
@@ -1428,18 +2898,79 @@ async fn test_single_node_list_terms() -> anyhow::Result<()> {
             start_timestamp: None,
             end_timestamp: None,
             max_hits: Some(100),
+            query: None,
+            sort_by_doc_count: false,
+        };
+        let search_response = leaf_list_terms(
+            searcher_context.clone(),
+            &request,
+            test_sandbox.storage(),
+            &splits_offsets,
+            None,
+        )
+        .await
+        .unwrap();
+        let terms = collect_str_terms(search_response);
+        assert_eq!(terms, &["beagle"]);
+    }
+    {
+        let request = quickwit_proto::ListTermsRequest {
+            index_id: test_sandbox.index_id().to_string(),
+            field: "title".to_string(),
+            start_key: None,
+            end_key: None,
+            start_timestamp: None,
+            end_timestamp: None,
+            max_hits: Some(100),
+            query: Some("body:hound".to_string()),
+            sort_by_doc_count: false,
         };
         let search_response = leaf_list_terms(
             searcher_context.clone(),
             &request,
             test_sandbox.storage(),
             &splits_offsets,
+            Some(test_sandbox.doc_mapper()),
         )
         .await
         .unwrap();
+        assert_eq!(search_response.doc_counts, &[1]);
         let terms = collect_str_terms(search_response);
         assert_eq!(terms, &["beagle"]);
     }
+    {
+        let request = quickwit_proto::ListTermsRequest {
+            index_id: test_sandbox.index_id().to_string(),
+            field: "body".to_string(),
+            start_key: None,
+            end_key: None,
+            start_timestamp: None,
+            end_timestamp: None,
+            max_hits: Some(100),
+            query: None,
+            sort_by_doc_count: true,
+        };
+        let search_response = leaf_list_terms(
+            searcher_context.clone(),
+            &request,
+            test_sandbox.storage(),
+            &splits_offsets,
+            None,
+        )
+        .await
+        .unwrap();
+        let mut term_doc_counts = crate::list_terms::zip_term_doc_counts(
+            search_response.terms,
+            search_response.doc_counts,
+        );
+        crate::list_terms::finalize_term_doc_counts(&mut term_doc_counts, &request);
+        let top_term = Term::wrap(&term_doc_counts[0].0)
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(top_term, "beagle");
+        assert_eq!(term_doc_counts[0].1, 2);
+    }
     test_sandbox.assert_quit().await;
     Ok(())
 }
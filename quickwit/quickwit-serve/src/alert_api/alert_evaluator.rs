@@ -0,0 +1,396 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use quickwit_config::{AlertRule, AlertWebhookConfig};
+use quickwit_metastore::Metastore;
+use quickwit_proto::SearchRequest;
+use quickwit_search::SearchService;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use tracing::{debug, info, warn};
+
+/// ID of the lease held by the searcher evaluating the alert rules of the cluster.
+const ALERT_EVALUATOR_LEASE_ID: &str = "alert-evaluator";
+
+const ALERT_EVALUATOR_LEASE_DURATION: Duration = Duration::from_secs(30);
+
+/// Interval at which the evaluator checks which alert rules are due for evaluation. It bounds the
+/// delay with which a rule is evaluated after its evaluation interval has elapsed.
+const EVALUATION_TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Body of the request posted to the webhook of an alert rule when the rule fires.
+#[derive(Debug, Serialize)]
+pub(crate) struct AlertNotification {
+    pub rule_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub index_id: String,
+    pub query: String,
+    pub condition: String,
+    pub num_hits: u64,
+    pub window_start_timestamp: i64,
+    pub window_end_timestamp: i64,
+    pub sample_hits: Vec<JsonValue>,
+}
+
+/// Evaluates the alert rules stored in the metastore on their schedule and notifies their
+/// webhooks when they fire. Every searcher runs an evaluator, but only the one holding the
+/// evaluator lease evaluates the rules, so that each rule fires once per evaluation.
+pub(crate) struct AlertEvaluator {
+    node_id: String,
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+    http_client: reqwest::Client,
+    lease_deadline_opt: Option<Instant>,
+    next_evaluation_instants: HashMap<String, Instant>,
+}
+
+impl AlertEvaluator {
+    pub fn new(
+        node_id: String,
+        metastore: Arc<dyn Metastore>,
+        search_service: Arc<dyn SearchService>,
+    ) -> Self {
+        Self {
+            node_id,
+            metastore,
+            search_service,
+            http_client: reqwest::Client::new(),
+            lease_deadline_opt: None,
+            next_evaluation_instants: HashMap::new(),
+        }
+    }
+
+    fn holds_lease(&self) -> bool {
+        self.lease_deadline_opt
+            .map(|lease_deadline| Instant::now() < lease_deadline)
+            .unwrap_or(false)
+    }
+
+    async fn renew_lease(&mut self) {
+        let held_lease = self.holds_lease();
+        let request_instant = Instant::now();
+
+        match self
+            .metastore
+            .acquire_lease(
+                ALERT_EVALUATOR_LEASE_ID,
+                &self.node_id,
+                ALERT_EVALUATOR_LEASE_DURATION.as_secs(),
+            )
+            .await
+        {
+            Ok(lease) if lease.is_held_by(&self.node_id) => {
+                self.lease_deadline_opt =
+                    Some(request_instant + ALERT_EVALUATOR_LEASE_DURATION - Duration::from_secs(1));
+            }
+            Ok(lease) => {
+                debug!(
+                    holder_id=%lease.holder_id,
+                    "Alert evaluator lease is held by another node."
+                );
+                self.lease_deadline_opt = None;
+            }
+            Err(error) => {
+                warn!(error=?error, "Failed to renew the alert evaluator lease.");
+            }
+        }
+        let holds_lease = self.holds_lease();
+
+        if holds_lease && !held_lease {
+            info!(node_id=%self.node_id, "Node started evaluating the alert rules.");
+        } else if !holds_lease && held_lease {
+            info!(node_id=%self.node_id, "Node stopped evaluating the alert rules.");
+            // The schedule restarts from scratch if the node acquires the lease again.
+            self.next_evaluation_instants.clear();
+        }
+    }
+
+    /// Evaluates the enabled alert rules whose evaluation interval has elapsed since their last
+    /// evaluation, provided the node holds the evaluator lease.
+    pub async fn evaluate_due_alert_rules(&mut self) -> anyhow::Result<()> {
+        self.renew_lease().await;
+
+        if !self.holds_lease() {
+            return Ok(());
+        }
+        let alert_rules = self.metastore.list_alert_rules().await?;
+        self.next_evaluation_instants.retain(|rule_id, _| {
+            alert_rules
+                .iter()
+                .any(|alert_rule| alert_rule.rule_id == *rule_id)
+        });
+        let now = Instant::now();
+
+        for alert_rule in alert_rules.iter().filter(|alert_rule| alert_rule.enabled) {
+            let is_due = self
+                .next_evaluation_instants
+                .get(&alert_rule.rule_id)
+                .map(|next_evaluation_instant| *next_evaluation_instant <= now)
+                .unwrap_or(true);
+            if !is_due {
+                continue;
+            }
+            self.next_evaluation_instants.insert(
+                alert_rule.rule_id.clone(),
+                now + alert_rule.evaluation_interval,
+            );
+
+            if let Err(error) = self.evaluate_alert_rule(alert_rule).await {
+                warn!(rule_id=%alert_rule.rule_id, error=?error, "Failed to evaluate alert rule.");
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluates the alert rule over its time window ending now and notifies its webhook if it
+    /// fires. Returns the notification sent, if any.
+    async fn evaluate_alert_rule(
+        &self,
+        alert_rule: &AlertRule,
+    ) -> anyhow::Result<Option<AlertNotification>> {
+        let window_end_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        let window_start_timestamp = window_end_timestamp - alert_rule.window.as_secs() as i64;
+        let search_request = SearchRequest {
+            index_id: alert_rule.index_id.clone(),
+            query: alert_rule.query.clone(),
+            search_fields: alert_rule.search_fields.clone(),
+            start_timestamp: Some(window_start_timestamp),
+            end_timestamp: Some(window_end_timestamp),
+            max_hits: alert_rule.num_sample_hits as u64,
+            ..Default::default()
+        };
+        let search_response = self.search_service.root_search(search_request).await?;
+
+        if !alert_rule.condition.is_met(search_response.num_hits) {
+            debug!(
+                rule_id=%alert_rule.rule_id,
+                num_hits=search_response.num_hits,
+                "Alert rule did not fire."
+            );
+            return Ok(None);
+        }
+        let sample_hits = search_response
+            .hits
+            .into_iter()
+            .map(|hit| serde_json::from_str(&hit.json).unwrap_or(JsonValue::String(hit.json)))
+            .collect();
+        let notification = AlertNotification {
+            rule_id: alert_rule.rule_id.clone(),
+            description: alert_rule.description.clone(),
+            index_id: alert_rule.index_id.clone(),
+            query: alert_rule.query.clone(),
+            condition: alert_rule.condition.to_string(),
+            num_hits: search_response.num_hits,
+            window_start_timestamp,
+            window_end_timestamp,
+            sample_hits,
+        };
+        info!(rule_id=%alert_rule.rule_id, num_hits=notification.num_hits, "Alert rule fired.");
+        self.notify_webhook(&alert_rule.webhook, &notification)
+            .await?;
+        Ok(Some(notification))
+    }
+
+    async fn notify_webhook(
+        &self,
+        webhook: &AlertWebhookConfig,
+        notification: &AlertNotification,
+    ) -> anyhow::Result<()> {
+        let mut request = self
+            .http_client
+            .post(&webhook.url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(notification);
+        for (header_name, header_value) in &webhook.headers {
+            request = request.header(header_name, header_value);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("Failed to notify webhook `{}`.", webhook.url))?;
+        Ok(())
+    }
+}
+
+pub(crate) fn spawn_alert_evaluation_task(mut alert_evaluator: AlertEvaluator) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EVALUATION_TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = alert_evaluator.evaluate_due_alert_rules().await {
+                warn!(error=?error, "Failed to evaluate the alert rules.");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use quickwit_config::{AlertComparison, AlertCondition};
+    use quickwit_metastore::metastore_for_test;
+    use quickwit_proto::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use tokio::sync::mpsc;
+    use warp::Filter;
+
+    use super::*;
+
+    fn alert_rule_for_test(rule_id: &str, webhook_url: String) -> AlertRule {
+        AlertRule {
+            rule_id: rule_id.to_string(),
+            description: None,
+            index_id: "app-logs".to_string(),
+            query: "severity_text:ERROR".to_string(),
+            search_fields: Vec::new(),
+            window: Duration::from_secs(300),
+            evaluation_interval: Duration::from_secs(60),
+            condition: AlertCondition {
+                comparison: AlertComparison::GreaterThanOrEqual,
+                threshold: 2,
+            },
+            num_sample_hits: 1,
+            webhook: AlertWebhookConfig {
+                url: webhook_url,
+                headers: [("Authorization".to_string(), "Bearer token".to_string())]
+                    .into_iter()
+                    .collect(),
+            },
+            enabled: true,
+        }
+    }
+
+    /// Starts a webhook receiver forwarding the authorization header and the body of the
+    /// notifications it receives.
+    fn start_webhook_receiver() -> (SocketAddr, mpsc::UnboundedReceiver<(String, JsonValue)>) {
+        let (notification_tx, notification_rx) = mpsc::unbounded_channel();
+        let webhook = warp::post()
+            .and(warp::header::<String>("authorization"))
+            .and(warp::body::json())
+            .map(move |authorization: String, notification: JsonValue| {
+                notification_tx.send((authorization, notification)).unwrap();
+                warp::reply()
+            });
+        let (webhook_addr, webhook_server) =
+            warp::serve(webhook).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(webhook_server);
+        (webhook_addr, notification_rx)
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_notifies_webhook() {
+        let (webhook_addr, mut notification_rx) = start_webhook_receiver();
+        let metastore = metastore_for_test();
+        let alert_rule = alert_rule_for_test("errors", format!("http://{webhook_addr}/alerts"));
+        metastore
+            .create_alert_rule(alert_rule, false)
+            .await
+            .unwrap();
+        let quiet_alert_rule = AlertRule {
+            condition: AlertCondition {
+                comparison: AlertComparison::GreaterThan,
+                threshold: 100,
+            },
+            ..alert_rule_for_test("quiet", format!("http://{webhook_addr}/quiet"))
+        };
+        metastore
+            .create_alert_rule(quiet_alert_rule, false)
+            .await
+            .unwrap();
+
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id, "app-logs");
+                assert_eq!(search_request.query, "severity_text:ERROR");
+                assert_eq!(search_request.max_hits, 1);
+                assert_eq!(
+                    search_request.end_timestamp.unwrap() - search_request.start_timestamp.unwrap(),
+                    300
+                );
+                Ok(SearchResponse {
+                    num_hits: 3,
+                    hits: vec![Hit {
+                        json: r#"{"severity_text": "ERROR"}"#.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
+        let mut alert_evaluator = AlertEvaluator::new(
+            "test-node".to_string(),
+            metastore,
+            Arc::new(mock_search_service),
+        );
+        alert_evaluator.evaluate_due_alert_rules().await.unwrap();
+
+        let (authorization, notification) = notification_rx.recv().await.unwrap();
+        assert_eq!(authorization, "Bearer token");
+        assert_eq!(notification["rule_id"], "errors");
+        assert_eq!(notification["condition"], "num_hits >= 2");
+        assert_eq!(notification["num_hits"], 3);
+        assert_eq!(
+            notification["sample_hits"],
+            serde_json::json!([{"severity_text": "ERROR"}])
+        );
+        assert!(notification_rx.try_recv().is_err());
+
+        // The rules are not due again before their evaluation interval has elapsed.
+        alert_evaluator.evaluate_due_alert_rules().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_alert_evaluator_requires_lease() {
+        let metastore = metastore_for_test();
+        metastore
+            .acquire_lease(ALERT_EVALUATOR_LEASE_ID, "other-node", 60)
+            .await
+            .unwrap();
+        let alert_rule = alert_rule_for_test("errors", "http://127.0.0.1:1/alerts".to_string());
+        metastore
+            .create_alert_rule(alert_rule, false)
+            .await
+            .unwrap();
+
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_root_search().never();
+        let mut alert_evaluator = AlertEvaluator::new(
+            "test-node".to_string(),
+            metastore,
+            Arc::new(mock_search_service),
+        );
+        alert_evaluator.evaluate_due_alert_rules().await.unwrap();
+        assert!(!alert_evaluator.holds_lease());
+    }
+}
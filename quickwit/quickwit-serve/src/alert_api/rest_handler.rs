@@ -0,0 +1,301 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use quickwit_config::{load_alert_rule_from_user_config, AlertRule, ConfigFormat};
+use quickwit_metastore::{Metastore, MetastoreError};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use crate::format::{extract_format_from_qs, make_response};
+use crate::index_api::config_format_filter;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(create_alert_rule, list_alert_rules, get_alert_rule, delete_alert_rule))]
+pub struct AlertApi;
+
+#[derive(Debug, Error)]
+pub enum AlertApiError {
+    #[error("Invalid alert rule: {0}")]
+    InvalidAlertRule(String),
+    #[error("{0}")]
+    Metastore(#[from] MetastoreError),
+}
+
+impl ServiceError for AlertApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::InvalidAlertRule(_) => ServiceErrorCode::BadRequest,
+            Self::Metastore(metastore_error) => metastore_error.status_code(),
+        }
+    }
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CreateAlertRuleQueryParams {
+    /// If true, replaces the existing rule with the same ID.
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// Alert rules management handlers.
+pub(crate) fn alert_api_handlers(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    create_alert_rule_handler(metastore.clone())
+        .or(list_alert_rules_handler(metastore.clone()))
+        .or(get_alert_rule_handler(metastore.clone()))
+        .or(delete_alert_rule_handler(metastore))
+}
+
+fn create_alert_rule_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("alerts")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(config_format_filter())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::filters::body::bytes())
+        .and(with_arg(metastore))
+        .then(create_alert_rule)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Alerts",
+    path = "/alerts",
+    request_body = VersionedAlertRule,
+    responses(
+        (status = 200, description = "Successfully created the alert rule.", body = VersionedAlertRule)
+    ),
+    params(
+        CreateAlertRuleQueryParams,
+    )
+)]
+/// Create Alert Rule
+///
+/// Creates an alert rule. The searchers evaluate the rule query over its time window every
+/// evaluation interval, and post a notification to the rule webhook whenever the number of
+/// matching documents meets the rule condition.
+async fn create_alert_rule(
+    create_alert_rule_query_params: CreateAlertRuleQueryParams,
+    config_format: ConfigFormat,
+    alert_rule_bytes: Bytes,
+    metastore: Arc<dyn Metastore>,
+) -> Result<AlertRule, AlertApiError> {
+    let alert_rule = load_alert_rule_from_user_config(config_format, &alert_rule_bytes)
+        .map_err(|error| AlertApiError::InvalidAlertRule(format!("{error:#}")))?;
+    let overwrite = create_alert_rule_query_params.overwrite;
+    info!(rule_id = %alert_rule.rule_id, overwrite = overwrite, "create-alert-rule");
+    metastore
+        .create_alert_rule(alert_rule.clone(), overwrite)
+        .await?;
+    Ok(alert_rule)
+}
+
+fn list_alert_rules_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("alerts")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(list_alert_rules)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Alerts",
+    path = "/alerts",
+    responses(
+        (status = 200, description = "Successfully fetched the alert rules.", body = [VersionedAlertRule])
+    ),
+)]
+/// List Alert Rules
+async fn list_alert_rules(metastore: Arc<dyn Metastore>) -> Result<Vec<AlertRule>, AlertApiError> {
+    let alert_rules = metastore.list_alert_rules().await?;
+    Ok(alert_rules)
+}
+
+fn get_alert_rule_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("alerts" / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_alert_rule)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Alerts",
+    path = "/alerts/{rule_id}",
+    responses(
+        (status = 200, description = "Successfully fetched the alert rule.", body = VersionedAlertRule)
+    ),
+    params(
+        ("rule_id" = String, Path, description = "The ID of the alert rule to get."),
+    )
+)]
+/// Get Alert Rule
+async fn get_alert_rule(
+    rule_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<AlertRule, AlertApiError> {
+    let alert_rule = metastore
+        .list_alert_rules()
+        .await?
+        .into_iter()
+        .find(|alert_rule| alert_rule.rule_id == rule_id)
+        .ok_or(MetastoreError::AlertRuleDoesNotExist { rule_id })?;
+    Ok(alert_rule)
+}
+
+fn delete_alert_rule_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("alerts" / String)
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_alert_rule)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Alerts",
+    path = "/alerts/{rule_id}",
+    responses(
+        (status = 200, description = "Successfully deleted the alert rule.")
+    ),
+    params(
+        ("rule_id" = String, Path, description = "The ID of the alert rule to delete."),
+    )
+)]
+/// Delete Alert Rule
+async fn delete_alert_rule(
+    rule_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<(), AlertApiError> {
+    info!(rule_id = %rule_id, "delete-alert-rule");
+    metastore.delete_alert_rule(&rule_id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_alert_api() {
+        let metastore = metastore_for_test();
+        let alert_api_handler = alert_api_handlers(metastore.clone()).recover(recover_fn);
+
+        let alert_rule_yaml = r#"
+            version: 0.4
+            rule_id: too-many-errors
+            index_id: app-logs
+            query: "severity_text:ERROR"
+            window: 5m
+            condition:
+              comparison: gt
+              threshold: 100
+            webhook:
+              url: https://hooks.example.com/alerts
+        "#;
+        let resp = warp::test::request()
+            .path("/alerts")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(alert_rule_yaml)
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json["rule_id"], "too-many-errors");
+        assert_eq!(resp_json["window"], "5m");
+        assert_eq!(resp_json["evaluation_interval"], "1m");
+
+        let resp = warp::test::request()
+            .path("/alerts")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(alert_rule_yaml)
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/alerts?overwrite=true")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(alert_rule_yaml.replace("threshold: 100", "threshold: 10"))
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/alerts")
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let alert_rules: Vec<AlertRule> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(alert_rules.len(), 1);
+        assert_eq!(alert_rules[0].condition.threshold, 10);
+
+        let resp = warp::test::request()
+            .path("/alerts/too-many-errors")
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let alert_rule: AlertRule = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(alert_rule, alert_rules[0]);
+
+        let resp = warp::test::request()
+            .path("/alerts/too-many-errors")
+            .method("DELETE")
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert!(metastore.list_alert_rules().await.unwrap().is_empty());
+
+        let resp = warp::test::request()
+            .path("/alerts/too-many-errors")
+            .reply(&alert_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+}
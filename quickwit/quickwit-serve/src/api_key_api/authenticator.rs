@@ -0,0 +1,992 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use hyper::Method;
+use quickwit_common::index_id_matches_pattern;
+use quickwit_config::{AuthConfig, FieldMaskConfig, RoleConfig};
+use quickwit_grpc_clients::tls::is_grpc_tls_enabled;
+use quickwit_metastore::{ApiKey, ApiKeyScope, Metastore, MetastoreResult};
+use quickwit_proto::tonic::metadata::MetadataMap;
+use quickwit_proto::tonic::{Request, Status};
+use quickwit_proto::{ServiceError, ServiceErrorCode, CLUSTER_SECRET_METADATA_KEY};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::error;
+
+use super::oidc::OidcValidator;
+use crate::prometheus_api::PROMETHEUS_METRICS_INDEX_ID;
+
+/// Prefix of the API keys, which are formatted as `qw_<key ID>_<secret>`.
+const API_KEY_PREFIX: &str = "qw_";
+
+const KEY_ID_LEN: usize = 16;
+
+const SECRET_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub(crate) enum AuthError {
+    #[error(
+        "Missing credentials: requests must carry an `Authorization: Bearer <API key or JWT>` \
+         header."
+    )]
+    MissingCredentials,
+    #[error("Invalid API key.")]
+    InvalidApiKey,
+    #[error("Invalid token: {message}.")]
+    InvalidToken { message: String },
+    #[error("Forbidden: {message}")]
+    Forbidden { message: String },
+}
+
+impl ServiceError for AuthError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::MissingCredentials | Self::InvalidApiKey | Self::InvalidToken { .. } => {
+                ServiceErrorCode::Unauthenticated
+            }
+            Self::Forbidden { .. } => ServiceErrorCode::Forbidden,
+        }
+    }
+}
+
+/// Operations granted on a set of indexes to the bearer of an API key, of a role, or of a JWT.
+pub(crate) struct Grant<'a> {
+    pub scopes: &'a BTreeSet<ApiKeyScope>,
+    pub index_patterns: &'a [String],
+}
+
+impl Grant<'_> {
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&scope) || self.scopes.contains(&ApiKeyScope::Admin)
+    }
+
+    pub fn can_access_index(&self, index_id: &str) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|index_pattern| index_id_matches_pattern(index_id, index_pattern))
+    }
+
+    fn can_access_all_indexes(&self) -> bool {
+        self.index_patterns
+            .iter()
+            .any(|index_pattern| index_pattern == "*")
+    }
+}
+
+impl warp::reject::Reject for AuthError {}
+
+/// Indexes targeted by a request.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum IndexTarget<'a> {
+    /// The request does not target any index.
+    None,
+    /// The request targets the indexes of a comma-separated list of index IDs or patterns.
+    Indexes(&'a str),
+    /// The request targets indexes that cannot be told from the request path, for instance
+    /// because they are in the request body, or that span the whole cluster.
+    AllIndexes,
+}
+
+/// Authenticates and authorizes requests with the API keys stored in the metastore or with the
+/// JWTs issued by the configured OpenID Connect provider.
+///
+/// API keys and JWTs are granted permissions either directly, with the scopes and index patterns
+/// of a key or with the role mappings of the provider, or through the roles defined in the node
+/// configuration, which they are assigned by name.
+///
+/// The keys are cached in memory, so that requests can be authorized without a round-trip to the
+/// metastore, and reloaded periodically by [`spawn_api_keys_refresh_task`].
+///
+/// The nodes of the cluster call the internal gRPC services of each other with the cluster
+/// secret, unless they are authenticated by mutual TLS.
+pub(crate) struct ApiKeyAuthenticator {
+    metastore: Arc<dyn Metastore>,
+    enabled: bool,
+    bootstrap_admin_key_hash_opt: Option<String>,
+    cluster_secret_hash_opt: Option<String>,
+    api_keys: RwLock<HashMap<String, ApiKey>>,
+    oidc_validator_opt: Option<OidcValidator>,
+    roles: HashMap<String, RoleConfig>,
+}
+
+impl ApiKeyAuthenticator {
+    pub fn new(metastore: Arc<dyn Metastore>, auth_config: &AuthConfig) -> Self {
+        let bootstrap_admin_key_hash_opt = auth_config
+            .bootstrap_admin_key
+            .as_ref()
+            .map(|bootstrap_admin_key| hash_secret(bootstrap_admin_key));
+        let cluster_secret_hash_opt = auth_config
+            .cluster_secret
+            .as_ref()
+            .map(|cluster_secret| hash_secret(cluster_secret));
+        let oidc_validator_opt = auth_config.oidc.clone().map(OidcValidator::new);
+        let roles = auth_config
+            .roles
+            .iter()
+            .map(|role| (role.name.clone(), role.clone()))
+            .collect();
+        Self {
+            metastore,
+            enabled: auth_config.is_enabled(),
+            bootstrap_admin_key_hash_opt,
+            cluster_secret_hash_opt,
+            api_keys: RwLock::default(),
+            oidc_validator_opt,
+            roles,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns whether the role `role_name` is defined in the node configuration.
+    pub fn has_role(&self, role_name: &str) -> bool {
+        self.roles.contains_key(role_name)
+    }
+
+    /// Returns the grants of the permissions of the roles among `role_names`. Undefined roles,
+    /// for instance roles removed from the configuration after being assigned to a key, grant
+    /// nothing.
+    fn role_grants<'a>(
+        &'a self,
+        role_names: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<Grant<'a>> {
+        role_names
+            .into_iter()
+            .filter_map(|role_name| self.roles.get(role_name))
+            .flat_map(|role| role.permissions.iter())
+            .map(|permission| Grant {
+                scopes: &permission.scopes,
+                index_patterns: &permission.index_patterns,
+            })
+            .collect()
+    }
+
+    /// Reloads the API keys from the metastore.
+    pub async fn refresh_api_keys(&self) -> MetastoreResult<()> {
+        let api_keys = self
+            .metastore
+            .list_api_keys()
+            .await?
+            .into_iter()
+            .map(|api_key| (api_key.key_id.clone(), api_key))
+            .collect();
+        *self.api_keys.write().unwrap() = api_keys;
+        Ok(())
+    }
+
+    /// Creates an API key, returning the key metadata and the key itself. The key is not stored
+    /// and cannot be retrieved afterwards.
+    pub async fn create_api_key(
+        &self,
+        description: Option<String>,
+        scopes: Vec<ApiKeyScope>,
+        index_patterns: Vec<String>,
+        roles: Vec<String>,
+        create_timestamp: i64,
+    ) -> MetastoreResult<(ApiKey, String)> {
+        let key_id = random_alphanumeric(KEY_ID_LEN).to_lowercase();
+        let secret = random_alphanumeric(SECRET_LEN);
+        let api_key = ApiKey {
+            key_id: key_id.clone(),
+            key_hash: hash_secret(&secret),
+            description,
+            scopes: scopes.into_iter().collect(),
+            index_patterns,
+            roles,
+            create_timestamp,
+        };
+        self.metastore.create_api_key(api_key.clone()).await?;
+        self.api_keys
+            .write()
+            .unwrap()
+            .insert(key_id.clone(), api_key.clone());
+        Ok((api_key, format!("{API_KEY_PREFIX}{key_id}_{secret}")))
+    }
+
+    /// Reloads the JSON Web Key Set of the OpenID Connect provider, if any.
+    pub async fn refresh_jwks(&self) -> anyhow::Result<()> {
+        if let Some(oidc_validator) = &self.oidc_validator_opt {
+            oidc_validator.refresh_jwks().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_api_keys(&self) -> MetastoreResult<Vec<ApiKey>> {
+        self.metastore.list_api_keys().await
+    }
+
+    /// Deletes an API key. The key is immediately rejected by this node and by the other nodes
+    /// after their next refresh.
+    pub async fn delete_api_key(&self, key_id: &str) -> MetastoreResult<()> {
+        self.metastore.delete_api_key(key_id).await?;
+        self.api_keys.write().unwrap().remove(key_id);
+        Ok(())
+    }
+
+    /// Checks that the API key or the JWT carried by the `authorization` header grants `scope` on
+    /// the indexes targeted by the request. Always succeeds when authentication is disabled.
+    pub fn authorize(
+        &self,
+        authorization_opt: Option<&str>,
+        scope: ApiKeyScope,
+        index_target: IndexTarget,
+    ) -> Result<(), AuthError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        let authorization = authorization_opt.ok_or(AuthError::MissingCredentials)?;
+        let credentials = authorization
+            .strip_prefix("Bearer ")
+            .ok_or(AuthError::MissingCredentials)?
+            .trim();
+        if let Some(bootstrap_admin_key_hash) = &self.bootstrap_admin_key_hash_opt {
+            if hash_secret(credentials) == *bootstrap_admin_key_hash {
+                return Ok(());
+            }
+        }
+        if let Some(key_id_and_secret) = credentials.strip_prefix(API_KEY_PREFIX) {
+            let (key_id, secret) = key_id_and_secret
+                .split_once('_')
+                .ok_or(AuthError::InvalidApiKey)?;
+            let api_keys = self.api_keys.read().unwrap();
+            let api_key = api_keys
+                .get(key_id)
+                .filter(|api_key| api_key.key_hash == hash_secret(secret))
+                .ok_or(AuthError::InvalidApiKey)?;
+            let mut grants = self.role_grants(&api_key.roles);
+            grants.push(Grant {
+                scopes: &api_key.scopes,
+                index_patterns: &api_key.index_patterns,
+            });
+            let principal = format!("API key `{key_id}`");
+            return check_grants(&principal, &grants, scope, index_target);
+        }
+        if let Some(oidc_validator) = &self.oidc_validator_opt {
+            let (subject, roles) = oidc_validator.validate(credentials)?;
+            let mut grants = oidc_validator.role_mapping_grants(&roles);
+            grants.extend(self.role_grants(&roles));
+            let principal = format!("token of `{subject}`");
+            return check_grants(&principal, &grants, scope, index_target);
+        }
+        Err(AuthError::InvalidApiKey)
+    }
+
+    /// Returns the identity of the bearer of the `authorization` header, as recorded in the audit
+    /// log: `bootstrap-admin-key`, `api-key:<key ID>`, or `oidc:<subject>`. Returns `None` when
+    /// the credentials are missing or invalid.
+    pub fn identify(&self, authorization_opt: Option<&str>) -> Option<String> {
+        let credentials = authorization_opt?.strip_prefix("Bearer ")?.trim();
+        if let Some(bootstrap_admin_key_hash) = &self.bootstrap_admin_key_hash_opt {
+            if hash_secret(credentials) == *bootstrap_admin_key_hash {
+                return Some("bootstrap-admin-key".to_string());
+            }
+        }
+        if let Some(key_id_and_secret) = credentials.strip_prefix(API_KEY_PREFIX) {
+            let (key_id, secret) = key_id_and_secret.split_once('_')?;
+            self.api_keys
+                .read()
+                .unwrap()
+                .get(key_id)
+                .filter(|api_key| api_key.key_hash == hash_secret(secret))?;
+            return Some(format!("api-key:{key_id}"));
+        }
+        let oidc_validator = self.oidc_validator_opt.as_ref()?;
+        let (subject, _roles) = oidc_validator.validate(credentials).ok()?;
+        Some(format!("oidc:{subject}"))
+    }
+
+    /// Returns the field masks of the roles held by the bearer of the `authorization` header,
+    /// which apply to the documents returned by their searches. The masks of all the roles add
+    /// up. Returns no mask when authentication is disabled or the credentials are invalid.
+    pub fn field_masks(&self, authorization_opt: Option<&str>) -> Vec<FieldMaskConfig> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        let role_names = self.role_names(authorization_opt).unwrap_or_default();
+        role_names
+            .iter()
+            .filter_map(|role_name| self.roles.get(role_name))
+            .flat_map(|role| role.field_masks.iter().cloned())
+            .collect()
+    }
+
+    /// Returns the names of the roles held by the bearer of the `authorization` header: the roles
+    /// assigned to their API key, or the roles carried by their JWT.
+    fn role_names(&self, authorization_opt: Option<&str>) -> Option<Vec<String>> {
+        let credentials = authorization_opt?.strip_prefix("Bearer ")?.trim();
+        if let Some(key_id_and_secret) = credentials.strip_prefix(API_KEY_PREFIX) {
+            let (key_id, secret) = key_id_and_secret.split_once('_')?;
+            let api_keys = self.api_keys.read().unwrap();
+            let api_key = api_keys
+                .get(key_id)
+                .filter(|api_key| api_key.key_hash == hash_secret(secret))?;
+            return Some(api_key.roles.clone());
+        }
+        let oidc_validator = self.oidc_validator_opt.as_ref()?;
+        let (_subject, roles) = oidc_validator.validate(credentials).ok()?;
+        Some(roles.into_iter().collect())
+    }
+
+    /// Checks that a gRPC request carries either the cluster secret or credentials granting
+    /// `scope` on the indexes it targets. The nodes present the cluster secret when they forward
+    /// requests, whose field masks are then carried by the request.
+    pub fn authorize_grpc_request(
+        &self,
+        metadata: &MetadataMap,
+        scope: ApiKeyScope,
+        index_target: IndexTarget,
+    ) -> Result<(), AuthError> {
+        if self.has_cluster_secret(metadata) {
+            return Ok(());
+        }
+        self.authorize(grpc_authorization(metadata), scope, index_target)
+    }
+
+    /// Checks that a request to an internal gRPC service comes from another node of the cluster.
+    /// The nodes are authenticated either by mutual TLS, whose handshake already verified the
+    /// certificate of the connection, or by the cluster secret. Credentials granting the `admin`
+    /// scope on all indexes are accepted too, so that operators can call the internal services.
+    pub fn authorize_cluster_request(&self, metadata: &MetadataMap) -> Result<(), AuthError> {
+        if !self.is_enabled() || is_grpc_tls_enabled() {
+            return Ok(());
+        }
+        self.authorize_grpc_request(metadata, ApiKeyScope::Admin, IndexTarget::AllIndexes)
+    }
+
+    fn has_cluster_secret(&self, metadata: &MetadataMap) -> bool {
+        let Some(cluster_secret_hash) = &self.cluster_secret_hash_opt else {
+            return false;
+        };
+        metadata
+            .get(CLUSTER_SECRET_METADATA_KEY)
+            .and_then(|cluster_secret| cluster_secret.to_str().ok())
+            .map(|cluster_secret| hash_secret(cluster_secret) == *cluster_secret_hash)
+            .unwrap_or(false)
+    }
+
+    /// Authorizes a REST request given its method and path.
+    pub fn authorize_rest_request(
+        &self,
+        method: &Method,
+        path: &str,
+        authorization_opt: Option<&str>,
+    ) -> Result<(), AuthError> {
+        if let Some((scope, index_target)) = rest_request_permission(method, path) {
+            self.authorize(authorization_opt, scope, index_target)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Checks that at least one of the grants of `principal` allows `scope` on each of the indexes
+/// targeted by the request.
+fn check_grants(
+    principal: &str,
+    grants: &[Grant],
+    scope: ApiKeyScope,
+    index_target: IndexTarget,
+) -> Result<(), AuthError> {
+    let scoped_grants: Vec<&Grant> = grants
+        .iter()
+        .filter(|grant| grant.has_scope(scope))
+        .collect();
+    if scoped_grants.is_empty() {
+        return Err(AuthError::Forbidden {
+            message: format!("{principal} does not grant the `{scope}` scope."),
+        });
+    }
+    match index_target {
+        IndexTarget::None => Ok(()),
+        IndexTarget::Indexes(index_ids) => {
+            for index_id in index_ids.split(',') {
+                let can_access_index = scoped_grants.iter().any(|grant| {
+                    if index_id.contains('*') {
+                        grant.can_access_all_indexes()
+                    } else {
+                        grant.can_access_index(index_id)
+                    }
+                });
+                if !can_access_index {
+                    return Err(AuthError::Forbidden {
+                        message: format!(
+                            "{principal} does not grant `{scope}` access to index `{index_id}`."
+                        ),
+                    });
+                }
+            }
+            Ok(())
+        }
+        IndexTarget::AllIndexes => {
+            if scoped_grants
+                .iter()
+                .any(|grant| grant.can_access_all_indexes())
+            {
+                Ok(())
+            } else {
+                Err(AuthError::Forbidden {
+                    message: format!(
+                        "{principal} does not grant `{scope}` access to all indexes, which this \
+                         request requires."
+                    ),
+                })
+            }
+        }
+    }
+}
+
+/// Returns the scope and the indexes a REST request requires, or `None` for the requests that do
+/// not require authentication, such as the health checks, the metrics, and the UI.
+pub(crate) fn rest_request_permission<'a>(
+    method: &Method,
+    path: &'a str,
+) -> Option<(ApiKeyScope, IndexTarget<'a>)> {
+    let api_path = path.strip_prefix("/api/v1/")?;
+    let segments: Vec<&str> = api_path.trim_end_matches('/').split('/').collect();
+    // The OpenAPI specification is public, like the one served at `/openapi.json`.
+    if method == Method::OPTIONS || segments == ["openapi.json"] {
+        return None;
+    }
+    let permission = match *segments.as_slice() {
+        ["indexes", index_id, ..] => (ApiKeyScope::Admin, IndexTarget::Indexes(index_id)),
+        [index_id, "delete-by-query"] | [index_id, "delete-tasks", ..] => {
+            (ApiKeyScope::Admin, IndexTarget::Indexes(index_id))
+        }
+        // The documents of a bulk request may target other indexes than the one in the path.
+        ["_bulk"] | [_, "_bulk"] | ["_ingest"] => (ApiKeyScope::Ingest, IndexTarget::AllIndexes),
+        [index_id, "ingest"] => (ApiKeyScope::Ingest, IndexTarget::Indexes(index_id)),
+        [index_id, "search", ..]
+        | [index_id, "async_search", ..]
+        | [index_id, "views", ..]
+        | [index_id, "explain"]
+        | [index_id, "terms", _]
+        | [index_id, "tail", ..] => (ApiKeyScope::Search, IndexTarget::Indexes(index_id)),
+        // Scroll IDs are only handed out by authorized searches.
+        ["_search", "scroll"] => (ApiKeyScope::Search, IndexTarget::None),
+        ["_sql"] | ["_elastic", "_search"] | ["_elastic", "_cat", ..] => {
+            (ApiKeyScope::Search, IndexTarget::AllIndexes)
+        }
+        ["_elastic", index_id, "_search" | "_mapping" | "_settings"] => {
+            (ApiKeyScope::Search, IndexTarget::Indexes(index_id))
+        }
+        ["_loki", index_id, "loki", ..] => (ApiKeyScope::Search, IndexTarget::Indexes(index_id)),
+        ["_prometheus", "api", "v1", "write"] => (
+            ApiKeyScope::Ingest,
+            IndexTarget::Indexes(PROMETHEUS_METRICS_INDEX_ID),
+        ),
+        _ => (ApiKeyScope::Admin, IndexTarget::AllIndexes),
+    };
+    Some(permission)
+}
+
+/// Returns a gRPC interceptor checking that the requests carry an API key granting `scope` on
+/// `index_id`.
+pub(crate) fn grpc_auth_interceptor(
+    authenticator: Arc<ApiKeyAuthenticator>,
+    scope: ApiKeyScope,
+    index_id: &'static str,
+) -> impl FnMut(Request<()>) -> Result<Request<()>, Status> + Clone {
+    move |request: Request<()>| {
+        authenticator
+            .authorize(
+                grpc_authorization(request.metadata()),
+                scope,
+                IndexTarget::Indexes(index_id),
+            )
+            .map_err(|auth_error| auth_error.grpc_error())?;
+        Ok(request)
+    }
+}
+
+/// Returns a gRPC interceptor checking that the requests come from another node of the cluster,
+/// for the internal gRPC services.
+pub(crate) fn grpc_cluster_auth_interceptor(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl FnMut(Request<()>) -> Result<Request<()>, Status> + Clone {
+    move |request: Request<()>| {
+        authenticator
+            .authorize_cluster_request(request.metadata())
+            .map_err(|auth_error| auth_error.grpc_error())?;
+        Ok(request)
+    }
+}
+
+/// Returns the `authorization` metadata of a gRPC request.
+pub(crate) fn grpc_authorization(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get("authorization")
+        .and_then(|authorization| authorization.to_str().ok())
+}
+
+/// Periodically reloads the JSON Web Key Set of the OpenID Connect provider, so that rotated
+/// signing keys are taken into account.
+pub(crate) fn spawn_jwks_refresh_task(
+    authenticator: Arc<ApiKeyAuthenticator>,
+    refresh_interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh_interval);
+        loop {
+            interval.tick().await;
+            if let Err(error) = authenticator.refresh_jwks().await {
+                error!(error=?error, "Failed to refresh OIDC JSON Web Key Set.");
+            }
+        }
+    });
+}
+
+/// Periodically reloads the API keys from the metastore, so that the keys created or deleted on
+/// other nodes are eventually taken into account.
+pub(crate) fn spawn_api_keys_refresh_task(
+    authenticator: Arc<ApiKeyAuthenticator>,
+    refresh_interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh_interval);
+        loop {
+            interval.tick().await;
+            if let Err(error) = authenticator.refresh_api_keys().await {
+                error!(error=?error, "Failed to refresh API keys.");
+            }
+        }
+    });
+}
+
+/// Returns the ID of the API key carried by an `authorization` header, if any. The key is not
+/// validated.
+pub(crate) fn parse_api_key_id(authorization: &str) -> Option<&str> {
+    let (key_id, _secret) = authorization
+        .strip_prefix("Bearer ")?
+        .trim()
+        .strip_prefix(API_KEY_PREFIX)?
+        .split_once('_')?;
+    Some(key_id)
+}
+
+fn random_alphanumeric(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Returns the hex-encoded SHA-256 hash of `secret`.
+fn hash_secret(secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    let mut hash = String::with_capacity(2 * digest.len());
+    for byte in digest {
+        write!(hash, "{byte:02x}").unwrap();
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::{FieldMaskActionConfig, PermissionConfig};
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+
+    fn authenticator_for_test() -> ApiKeyAuthenticator {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore.expect_create_api_key().returning(|_| Ok(()));
+        let auth_config = AuthConfig {
+            enable_api_keys: true,
+            bootstrap_admin_key: Some("bootstrap-key".to_string()),
+            ..Default::default()
+        };
+        ApiKeyAuthenticator::new(Arc::new(mock_metastore), &auth_config)
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_authorize() {
+        let authenticator = authenticator_for_test();
+        let (_, key) = authenticator
+            .create_api_key(
+                None,
+                vec![ApiKeyScope::Search],
+                vec!["logs-*".to_string()],
+                Vec::new(),
+                0,
+            )
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {key}");
+
+        authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Search,
+                IndexTarget::Indexes("logs-eu,logs-us"),
+            )
+            .unwrap();
+        let error = authenticator
+            .authorize(None, ApiKeyScope::Search, IndexTarget::None)
+            .unwrap_err();
+        assert!(matches!(error, AuthError::MissingCredentials));
+
+        let error = authenticator
+            .authorize(
+                Some("Bearer qw_unknown_secret"),
+                ApiKeyScope::Search,
+                IndexTarget::None,
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::InvalidApiKey));
+
+        let tampered_authorization = format!("{authorization}0");
+        let error = authenticator
+            .authorize(
+                Some(&tampered_authorization),
+                ApiKeyScope::Search,
+                IndexTarget::None,
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::InvalidApiKey));
+
+        let error = authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Ingest,
+                IndexTarget::Indexes("logs-eu"),
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+
+        let error = authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Search,
+                IndexTarget::Indexes("traces"),
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+
+        let error = authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Search,
+                IndexTarget::AllIndexes,
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+
+        authenticator
+            .authorize(
+                Some("Bearer bootstrap-key"),
+                ApiKeyScope::Admin,
+                IndexTarget::AllIndexes,
+            )
+            .unwrap();
+
+        let key_id = parse_api_key_id(&authorization).unwrap();
+        assert_eq!(
+            authenticator.identify(Some(&authorization)),
+            Some(format!("api-key:{key_id}"))
+        );
+        assert_eq!(
+            authenticator.identify(Some("Bearer bootstrap-key")),
+            Some("bootstrap-admin-key".to_string())
+        );
+        assert_eq!(authenticator.identify(Some(&tampered_authorization)), None);
+        assert_eq!(authenticator.identify(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_authorize_with_roles() {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore.expect_create_api_key().returning(|_| Ok(()));
+        let auth_config = AuthConfig {
+            enable_api_keys: true,
+            roles: vec![
+                RoleConfig {
+                    name: "team-a".to_string(),
+                    permissions: vec![
+                        PermissionConfig {
+                            scopes: BTreeSet::from([ApiKeyScope::Search, ApiKeyScope::Ingest]),
+                            index_patterns: vec!["team-a-*".to_string()],
+                        },
+                        PermissionConfig {
+                            scopes: BTreeSet::from([ApiKeyScope::Search]),
+                            index_patterns: vec!["shared".to_string()],
+                        },
+                    ],
+                    field_masks: vec![FieldMaskConfig {
+                        field: "user.email".to_string(),
+                        action: FieldMaskActionConfig::Hash,
+                        index_patterns: vec!["*".to_string()],
+                    }],
+                },
+                RoleConfig {
+                    name: "team-b".to_string(),
+                    permissions: vec![PermissionConfig {
+                        scopes: BTreeSet::from([ApiKeyScope::Admin]),
+                        index_patterns: vec!["team-b-*".to_string()],
+                    }],
+                    field_masks: Vec::new(),
+                },
+            ],
+            ..Default::default()
+        };
+        let authenticator = ApiKeyAuthenticator::new(Arc::new(mock_metastore), &auth_config);
+        assert!(authenticator.has_role("team-a"));
+        assert!(!authenticator.has_role("team-c"));
+
+        let (_, key) = authenticator
+            .create_api_key(
+                None,
+                Vec::new(),
+                vec!["*".to_string()],
+                vec!["team-a".to_string(), "removed-role".to_string()],
+                0,
+            )
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {key}");
+
+        authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Ingest,
+                IndexTarget::Indexes("team-a-logs"),
+            )
+            .unwrap();
+        authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Search,
+                IndexTarget::Indexes("team-a-logs,shared"),
+            )
+            .unwrap();
+        let error = authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Ingest,
+                IndexTarget::Indexes("shared"),
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+
+        let error = authenticator
+            .authorize(
+                Some(&authorization),
+                ApiKeyScope::Search,
+                IndexTarget::Indexes("team-b-logs"),
+            )
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+
+        let field_masks = authenticator.field_masks(Some(&authorization));
+        assert_eq!(field_masks.len(), 1);
+        assert_eq!(field_masks[0].field, "user.email");
+        assert!(authenticator.field_masks(None).is_empty());
+        assert!(authenticator
+            .field_masks(Some("Bearer qw_unknown_secret"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_check_grants() {
+        let search_scopes = BTreeSet::from([ApiKeyScope::Search]);
+        let logs_index_patterns = vec!["logs-*".to_string()];
+        let admin_scopes = BTreeSet::from([ApiKeyScope::Admin]);
+        let traces_index_patterns = vec!["traces".to_string()];
+        let grants = [
+            Grant {
+                scopes: &search_scopes,
+                index_patterns: &logs_index_patterns,
+            },
+            Grant {
+                scopes: &admin_scopes,
+                index_patterns: &traces_index_patterns,
+            },
+        ];
+        check_grants(
+            "test",
+            &grants,
+            ApiKeyScope::Search,
+            IndexTarget::Indexes("logs-eu,traces"),
+        )
+        .unwrap();
+        check_grants(
+            "test",
+            &grants,
+            ApiKeyScope::Admin,
+            IndexTarget::Indexes("traces"),
+        )
+        .unwrap();
+        let error = check_grants(
+            "test",
+            &grants,
+            ApiKeyScope::Admin,
+            IndexTarget::Indexes("logs-eu"),
+        )
+        .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+        let error = check_grants(
+            "test",
+            &grants,
+            ApiKeyScope::Search,
+            IndexTarget::AllIndexes,
+        )
+        .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_authenticator_authorize_cluster_request() {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore.expect_create_api_key().returning(|_| Ok(()));
+        let auth_config = AuthConfig {
+            enable_api_keys: true,
+            bootstrap_admin_key: Some("bootstrap-key".to_string()),
+            cluster_secret: Some("cluster-secret".to_string()),
+            ..Default::default()
+        };
+        let authenticator = ApiKeyAuthenticator::new(Arc::new(mock_metastore), &auth_config);
+        let (_, key) = authenticator
+            .create_api_key(
+                None,
+                vec![ApiKeyScope::Search],
+                vec!["*".to_string()],
+                Vec::new(),
+                0,
+            )
+            .await
+            .unwrap();
+
+        let error = authenticator
+            .authorize_cluster_request(&MetadataMap::new())
+            .unwrap_err();
+        assert!(matches!(error, AuthError::MissingCredentials));
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", format!("Bearer {key}").parse().unwrap());
+        let error = authenticator
+            .authorize_cluster_request(&metadata)
+            .unwrap_err();
+        assert!(matches!(error, AuthError::Forbidden { .. }));
+        authenticator
+            .authorize_grpc_request(&metadata, ApiKeyScope::Search, IndexTarget::Indexes("logs"))
+            .unwrap();
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", "Bearer bootstrap-key".parse().unwrap());
+        authenticator.authorize_cluster_request(&metadata).unwrap();
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert(CLUSTER_SECRET_METADATA_KEY, "wrong-secret".parse().unwrap());
+        authenticator
+            .authorize_cluster_request(&metadata)
+            .unwrap_err();
+
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            CLUSTER_SECRET_METADATA_KEY,
+            "cluster-secret".parse().unwrap(),
+        );
+        authenticator.authorize_cluster_request(&metadata).unwrap();
+        authenticator
+            .authorize_grpc_request(&metadata, ApiKeyScope::Search, IndexTarget::AllIndexes)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_authenticator_disabled() {
+        let authenticator =
+            ApiKeyAuthenticator::new(Arc::new(MockMetastore::new()), &AuthConfig::default());
+        authenticator
+            .authorize(None, ApiKeyScope::Admin, IndexTarget::AllIndexes)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rest_request_permission() {
+        assert_eq!(rest_request_permission(&Method::GET, "/health/livez"), None);
+        assert_eq!(rest_request_permission(&Method::GET, "/metrics"), None);
+        assert_eq!(
+            rest_request_permission(&Method::GET, "/api/v1/openapi.json"),
+            None
+        );
+        assert_eq!(
+            rest_request_permission(&Method::GET, "/api/v1/logs/search"),
+            Some((ApiKeyScope::Search, IndexTarget::Indexes("logs")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::DELETE, "/api/v1/logs/views/view-1"),
+            Some((ApiKeyScope::Search, IndexTarget::Indexes("logs")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::POST, "/api/v1/logs/ingest"),
+            Some((ApiKeyScope::Ingest, IndexTarget::Indexes("logs")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::POST, "/api/v1/logs/_bulk"),
+            Some((ApiKeyScope::Ingest, IndexTarget::AllIndexes))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::GET, "/api/v1/_elastic/logs,traces/_search"),
+            Some((ApiKeyScope::Search, IndexTarget::Indexes("logs,traces")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::GET, "/api/v1/_loki/logs/loki/api/v1/query_range"),
+            Some((ApiKeyScope::Search, IndexTarget::Indexes("logs")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::POST, "/api/v1/_prometheus/api/v1/write"),
+            Some((
+                ApiKeyScope::Ingest,
+                IndexTarget::Indexes("prometheus-metrics-v0")
+            ))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::DELETE, "/api/v1/indexes/logs"),
+            Some((ApiKeyScope::Admin, IndexTarget::Indexes("logs")))
+        );
+        assert_eq!(
+            rest_request_permission(&Method::POST, "/api/v1/api-keys"),
+            Some((ApiKeyScope::Admin, IndexTarget::AllIndexes))
+        );
+    }
+
+    #[test]
+    fn test_parse_api_key_id() {
+        assert_eq!(parse_api_key_id("Bearer qw_abc_secret"), Some("abc"));
+        assert_eq!(parse_api_key_id("Bearer eyJhbGciOi"), None);
+        assert_eq!(parse_api_key_id("qw_abc_secret"), None);
+    }
+
+    #[test]
+    fn test_hash_secret() {
+        assert_eq!(
+            hash_secret("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use anyhow::Context;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use quickwit_config::OidcConfig;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use super::authenticator::{AuthError, Grant};
+
+#[derive(Deserialize)]
+struct OidcDiscoveryDocument {
+    jwks_uri: String,
+}
+
+/// Validates the JWTs issued by an OpenID Connect provider and maps the roles they carry to
+/// grants.
+pub(crate) struct OidcValidator {
+    oidc_config: OidcConfig,
+    http_client: reqwest::Client,
+    jwks: RwLock<JwkSet>,
+}
+
+impl OidcValidator {
+    pub fn new(oidc_config: OidcConfig) -> Self {
+        Self {
+            oidc_config,
+            http_client: reqwest::Client::new(),
+            jwks: RwLock::new(JwkSet { keys: Vec::new() }),
+        }
+    }
+
+    #[cfg(test)]
+    fn with_jwks(oidc_config: OidcConfig, jwks: JwkSet) -> Self {
+        Self {
+            oidc_config,
+            http_client: reqwest::Client::new(),
+            jwks: RwLock::new(jwks),
+        }
+    }
+
+    /// Fetches the JSON Web Key Set of the provider, discovering its URL first if it is not
+    /// configured.
+    pub async fn refresh_jwks(&self) -> anyhow::Result<()> {
+        let jwks_uri = if let Some(jwks_uri) = &self.oidc_config.jwks_uri {
+            jwks_uri.clone()
+        } else {
+            let discovery_url = format!(
+                "{}/.well-known/openid-configuration",
+                self.oidc_config.issuer_url.trim_end_matches('/')
+            );
+            let discovery_document: OidcDiscoveryDocument = self
+                .http_client
+                .get(&discovery_url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .with_context(|| {
+                    format!("Failed to parse OIDC discovery document `{discovery_url}`.")
+                })?;
+            discovery_document.jwks_uri
+        };
+        let jwks: JwkSet = self
+            .http_client
+            .get(&jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse JSON Web Key Set `{jwks_uri}`."))?;
+        *self.jwks.write().unwrap() = jwks;
+        Ok(())
+    }
+
+    /// Validates the signature, the issuer, the audience, and the expiration of `token`, then
+    /// returns the subject of the token and the roles it carries.
+    pub fn validate(&self, token: &str) -> Result<(String, HashSet<String>), AuthError> {
+        let header = decode_header(token).map_err(|error| AuthError::InvalidToken {
+            message: error.to_string(),
+        })?;
+        // Symmetric algorithms would let anyone holding the public key forge tokens.
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(AuthError::InvalidToken {
+                message: format!("unsupported signing algorithm `{:?}`", header.alg),
+            });
+        }
+        let decoding_key = {
+            let jwks = self.jwks.read().unwrap();
+            let jwk_opt = match &header.kid {
+                Some(kid) => jwks.find(kid),
+                None if jwks.keys.len() == 1 => jwks.keys.first(),
+                None => None,
+            };
+            let jwk = jwk_opt.ok_or_else(|| AuthError::InvalidToken {
+                message: "unknown signing key".to_string(),
+            })?;
+            DecodingKey::from_jwk(jwk).map_err(|error| AuthError::InvalidToken {
+                message: error.to_string(),
+            })?
+        };
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.oidc_config.audience]);
+        validation.set_issuer(&[&self.oidc_config.issuer_url]);
+        let claims = decode::<JsonValue>(token, &decoding_key, &validation)
+            .map_err(|error| AuthError::InvalidToken {
+                message: error.to_string(),
+            })?
+            .claims;
+        let subject = claims
+            .get("sub")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let roles = extract_roles(&claims, &self.oidc_config.roles_claim)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        Ok((subject, roles))
+    }
+
+    /// Returns the grants the role mappings associate with `roles`.
+    pub fn role_mapping_grants<'a>(&'a self, roles: &HashSet<String>) -> Vec<Grant<'a>> {
+        self.oidc_config
+            .role_mappings
+            .iter()
+            .filter(|role_mapping| roles.contains(&role_mapping.role))
+            .map(|role_mapping| Grant {
+                scopes: &role_mapping.scopes,
+                index_patterns: &role_mapping.index_patterns,
+            })
+            .collect()
+    }
+}
+
+/// Extracts the roles held by the claim at `roles_claim_path`, which is either a string or an
+/// array of strings.
+fn extract_roles<'a>(claims: &'a JsonValue, roles_claim_path: &str) -> HashSet<&'a str> {
+    let mut claim_opt = Some(claims);
+    for claim_name in roles_claim_path.split('.') {
+        claim_opt = claim_opt.and_then(|claim| claim.get(claim_name));
+    }
+    match claim_opt {
+        Some(JsonValue::String(role)) => HashSet::from([role.as_str()]),
+        Some(JsonValue::Array(roles)) => roles.iter().filter_map(JsonValue::as_str).collect(),
+        _ => HashSet::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use quickwit_config::ApiKeyScope;
+    use serde_json::json;
+
+    use super::*;
+
+    fn oidc_validator_for_test() -> OidcValidator {
+        let oidc_config: OidcConfig = serde_json::from_value(json!({
+            "issuer_url": "https://sso.example.com",
+            "audience": "quickwit",
+            "roles_claim": "realm_access.roles",
+            "role_mappings": [
+                {"role": "analyst", "scopes": ["search"], "index_patterns": ["logs-*"]},
+                {"role": "ops", "scopes": ["admin"]},
+            ],
+        }))
+        .unwrap();
+        let jwks: JwkSet =
+            serde_json::from_str(include_str!("../../resources/tests/oidc/jwks.json")).unwrap();
+        OidcValidator::with_jwks(oidc_config, jwks)
+    }
+
+    fn make_token(claims: JsonValue) -> String {
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = Some("test-key".to_string());
+        let encoding_key =
+            EncodingKey::from_rsa_pem(include_bytes!("../../resources/tests/oidc/private_key.pem"))
+                .unwrap();
+        encode(&header, &claims, &encoding_key).unwrap()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_oidc_validator_validate() {
+        let oidc_validator = oidc_validator_for_test();
+        let token = make_token(json!({
+            "iss": "https://sso.example.com",
+            "aud": "quickwit",
+            "sub": "alice",
+            "exp": now() + 60,
+            "realm_access": {"roles": ["analyst", "unmapped"]},
+        }));
+        let (subject, roles) = oidc_validator.validate(&token).unwrap();
+        assert_eq!(subject, "alice");
+        assert_eq!(
+            roles,
+            HashSet::from(["analyst".to_string(), "unmapped".to_string()])
+        );
+        let grants = oidc_validator.role_mapping_grants(&roles);
+        assert_eq!(grants.len(), 1);
+        assert!(grants[0].has_scope(ApiKeyScope::Search));
+        assert!(!grants[0].has_scope(ApiKeyScope::Ingest));
+        assert!(grants[0].can_access_index("logs-eu"));
+
+        let expired_token = make_token(json!({
+            "iss": "https://sso.example.com",
+            "aud": "quickwit",
+            "sub": "alice",
+            "exp": now() - 3600,
+        }));
+        let error = oidc_validator.validate(&expired_token).unwrap_err();
+        assert!(matches!(error, AuthError::InvalidToken { .. }));
+
+        let wrong_audience_token = make_token(json!({
+            "iss": "https://sso.example.com",
+            "aud": "another-service",
+            "sub": "alice",
+            "exp": now() + 60,
+        }));
+        let error = oidc_validator.validate(&wrong_audience_token).unwrap_err();
+        assert!(matches!(error, AuthError::InvalidToken { .. }));
+
+        let hs256_token = encode(
+            &Header::new(Algorithm::HS256),
+            &json!({"iss": "https://sso.example.com", "aud": "quickwit", "exp": now() + 60}),
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        let error = oidc_validator.validate(&hs256_token).unwrap_err();
+        assert!(matches!(error, AuthError::InvalidToken { .. }));
+    }
+
+    #[test]
+    fn test_extract_roles() {
+        let claims = json!({
+            "groups": "ops",
+            "realm_access": {"roles": ["analyst", 1]},
+        });
+        assert_eq!(extract_roles(&claims, "groups"), HashSet::from(["ops"]));
+        assert_eq!(
+            extract_roles(&claims, "realm_access.roles"),
+            HashSet::from(["analyst"])
+        );
+        assert!(extract_roles(&claims, "roles").is_empty());
+    }
+}
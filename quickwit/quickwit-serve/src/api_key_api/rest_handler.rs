@@ -0,0 +1,418 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hyper::Method;
+use quickwit_metastore::{ApiKey, ApiKeyScope, MetastoreError};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+use super::ApiKeyAuthenticator;
+use crate::format::{extract_format_from_qs, make_response};
+use crate::json_body::json_body;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(create_api_key, list_api_keys, delete_api_key),
+    components(schemas(CreateApiKeyRequest, CreateApiKeyResponse, ApiKeyInfo))
+)]
+pub struct ApiKeyApi;
+
+#[derive(Debug, Error)]
+pub enum ApiKeyApiError {
+    #[error("Invalid API key request: {0}")]
+    InvalidRequest(String),
+    #[error("{0}")]
+    Metastore(#[from] MetastoreError),
+}
+
+impl ServiceError for ApiKeyApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::InvalidRequest(_) => ServiceErrorCode::BadRequest,
+            Self::Metastore(metastore_error) => metastore_error.status_code(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CreateApiKeyRequest {
+    /// Free-form description of the key.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Operations granted to the key.
+    #[serde(default)]
+    pub scopes: Vec<ApiKeyScope>,
+    /// Patterns of the index IDs the key grants the `scopes` operations on. Defaults to all the
+    /// indexes.
+    #[serde(default = "default_index_patterns")]
+    pub index_patterns: Vec<String>,
+    /// Names of the roles, defined in the node configuration, assigned to the key.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+fn default_index_patterns() -> Vec<String> {
+    vec!["*".to_string()]
+}
+
+/// Metadata of an API key, as returned by the API. The hash of the key is never exposed.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ApiKeyInfo {
+    pub key_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub scopes: Vec<ApiKeyScope>,
+    pub index_patterns: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    pub create_timestamp: i64,
+}
+
+impl From<ApiKey> for ApiKeyInfo {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            key_id: api_key.key_id,
+            description: api_key.description,
+            scopes: api_key.scopes.into_iter().collect(),
+            index_patterns: api_key.index_patterns,
+            roles: api_key.roles,
+            create_timestamp: api_key.create_timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key_info: ApiKeyInfo,
+    /// The API key. It is returned only once and cannot be retrieved afterwards.
+    pub api_key: String,
+}
+
+/// Rejects the requests that do not carry an API key granting the permissions they require. Lets
+/// all the requests through when API keys are disabled.
+pub(crate) fn rest_auth_filter(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(with_arg(authenticator))
+        .and_then(
+            |method: Method,
+             full_path: FullPath,
+             authorization_opt: Option<String>,
+             authenticator: Arc<ApiKeyAuthenticator>| async move {
+                authenticator
+                    .authorize_rest_request(
+                        &method,
+                        full_path.as_str(),
+                        authorization_opt.as_deref(),
+                    )
+                    .map_err(warp::reject::custom)
+            },
+        )
+        .untuple_one()
+}
+
+/// API keys management handlers.
+pub(crate) fn api_key_api_handlers(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    create_api_key_handler(authenticator.clone())
+        .or(list_api_keys_handler(authenticator.clone()))
+        .or(delete_api_key_handler(authenticator))
+}
+
+fn create_api_key_handler(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(authenticator))
+        .then(create_api_key)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "API Keys",
+    path = "/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 200, description = "Successfully created the API key.", body = CreateApiKeyResponse)
+    ),
+)]
+/// Create API Key
+///
+/// Creates an API key granting the `scopes` operations on the indexes matching `index_patterns`,
+/// as well as the permissions of `roles`. The key is returned only once and cannot be retrieved
+/// afterwards.
+async fn create_api_key(
+    create_api_key_request: CreateApiKeyRequest,
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> Result<CreateApiKeyResponse, ApiKeyApiError> {
+    if create_api_key_request.scopes.is_empty() && create_api_key_request.roles.is_empty() {
+        return Err(ApiKeyApiError::InvalidRequest(
+            "`scopes` and `roles` must not be both empty.".to_string(),
+        ));
+    }
+    if create_api_key_request.index_patterns.is_empty() {
+        return Err(ApiKeyApiError::InvalidRequest(
+            "`index_patterns` must not be empty.".to_string(),
+        ));
+    }
+    if let Some(role_name) = create_api_key_request
+        .roles
+        .iter()
+        .find(|role_name| !authenticator.has_role(role_name))
+    {
+        return Err(ApiKeyApiError::InvalidRequest(format!(
+            "role `{role_name}` is not defined."
+        )));
+    }
+    let create_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    let (api_key, api_key_str) = authenticator
+        .create_api_key(
+            create_api_key_request.description,
+            create_api_key_request.scopes,
+            create_api_key_request.index_patterns,
+            create_api_key_request.roles,
+            create_timestamp,
+        )
+        .await?;
+    Ok(CreateApiKeyResponse {
+        api_key_info: api_key.into(),
+        api_key: api_key_str,
+    })
+}
+
+fn list_api_keys_handler(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys")
+        .and(warp::get())
+        .and(with_arg(authenticator))
+        .then(list_api_keys)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "API Keys",
+    path = "/api-keys",
+    responses(
+        (status = 200, description = "Successfully fetched the API keys.", body = [ApiKeyInfo])
+    ),
+)]
+/// List API Keys
+///
+/// Returns the metadata of the API keys.
+async fn list_api_keys(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> Result<Vec<ApiKeyInfo>, ApiKeyApiError> {
+    let api_keys = authenticator.list_api_keys().await?;
+    Ok(api_keys.into_iter().map(ApiKeyInfo::from).collect())
+}
+
+fn delete_api_key_handler(
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("api-keys" / String)
+        .and(warp::delete())
+        .and(with_arg(authenticator))
+        .then(delete_api_key)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "API Keys",
+    path = "/api-keys/{key_id}",
+    responses(
+        (status = 200, description = "Successfully deleted the API key.")
+    ),
+    params(
+        ("key_id" = String, Path, description = "The ID of the API key to delete."),
+    )
+)]
+/// Delete API Key
+///
+/// Deletes an API key. Other nodes reject the key after their next API keys refresh.
+async fn delete_api_key(
+    key_id: String,
+    authenticator: Arc<ApiKeyAuthenticator>,
+) -> Result<(), ApiKeyApiError> {
+    authenticator.delete_api_key(&key_id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use quickwit_config::{AuthConfig, PermissionConfig, RoleConfig};
+    use quickwit_metastore::MockMetastore;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_api_key_api_and_auth_filter() {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore.expect_create_api_key().returning(|_| Ok(()));
+        let auth_config = AuthConfig {
+            enable_api_keys: true,
+            bootstrap_admin_key: Some("bootstrap-key".to_string()),
+            roles: vec![RoleConfig {
+                name: "traces-reader".to_string(),
+                permissions: vec![PermissionConfig {
+                    scopes: BTreeSet::from([ApiKeyScope::Search]),
+                    index_patterns: vec!["traces".to_string()],
+                }],
+                field_masks: Vec::new(),
+            }],
+            ..Default::default()
+        };
+        let authenticator = Arc::new(ApiKeyAuthenticator::new(
+            Arc::new(mock_metastore),
+            &auth_config,
+        ));
+        let api_v1_routes = warp::path!("api" / "v1" / ..)
+            .and(api_key_api_handlers(authenticator.clone()))
+            .or(warp::path!("api" / "v1" / String / "search").map(|_| "search"));
+        let routes = rest_auth_filter(authenticator)
+            .and(api_v1_routes)
+            .recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"scopes": ["search"], "index_patterns": ["logs"]}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 401);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .method("POST")
+            .header("authorization", "Bearer bootstrap-key")
+            .json(&true)
+            .body(r#"{"scopes": ["search"], "index_patterns": ["logs"]}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let create_api_key_response: CreateApiKeyResponse =
+            serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            create_api_key_response.api_key_info.scopes,
+            [ApiKeyScope::Search]
+        );
+        let authorization = format!("Bearer {}", create_api_key_response.api_key);
+
+        let resp = warp::test::request()
+            .path("/api/v1/logs/search")
+            .header("authorization", &authorization)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/api/v1/traces/search")
+            .header("authorization", &authorization)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 403);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .header("authorization", &authorization)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 403);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .method("POST")
+            .header("authorization", "Bearer bootstrap-key")
+            .json(&true)
+            .body(r#"{"scopes": []}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .method("POST")
+            .header("authorization", "Bearer bootstrap-key")
+            .json(&true)
+            .body(r#"{"roles": ["unknown-role"]}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/api/v1/api-keys")
+            .method("POST")
+            .header("authorization", "Bearer bootstrap-key")
+            .json(&true)
+            .body(r#"{"roles": ["traces-reader"]}"#)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let create_api_key_response: CreateApiKeyResponse =
+            serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            create_api_key_response.api_key_info.roles,
+            ["traces-reader"]
+        );
+        let authorization = format!("Bearer {}", create_api_key_response.api_key);
+
+        let resp = warp::test::request()
+            .path("/api/v1/traces/search")
+            .header("authorization", &authorization)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/api/v1/logs/search")
+            .header("authorization", &authorization)
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 403);
+    }
+}
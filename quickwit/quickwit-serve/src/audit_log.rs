@@ -0,0 +1,534 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use hyper::header::AUTHORIZATION;
+use hyper::{Body, Method, Request, Response};
+use quickwit_common::index_id_matches_pattern;
+use quickwit_common::uri::Uri;
+use quickwit_config::{
+    load_index_config_from_user_config, AuditLogConfig, AuditLogSinkConfig, ConfigFormat,
+};
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_ingest_api::{DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient};
+use quickwit_metastore::{ApiKeyScope, MetastoreError};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tower::{Service, ServiceExt};
+use tracing::error;
+use warp::Reply;
+
+use crate::api_key_api::{rest_request_permission, ApiKeyAuthenticator, IndexTarget};
+use crate::rate_limiter::RemoteAddr;
+
+const EVENT_CHANNEL_CAPACITY: usize = 10_000;
+
+const MAX_BATCH_SIZE: usize = 1_000;
+
+/// Number of results of a request, attached to the extensions of its response so that it can be
+/// recorded in the audit log.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ResultCount(pub u64);
+
+/// Converts `reply` into a response carrying the number of results of the request, if any.
+pub(crate) fn with_result_count(
+    reply: impl Reply,
+    num_results_opt: Option<u64>,
+) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    if let Some(num_results) = num_results_opt {
+        response.extensions_mut().insert(ResultCount(num_results));
+    }
+    response
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Outcome {
+    Success,
+    Failure,
+}
+
+/// Record of an API call.
+#[derive(Debug, Serialize)]
+struct AuditEvent {
+    timestamp: i64,
+    /// Identity of the caller, absent when the credentials are missing or invalid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_ip: Option<String>,
+    method: String,
+    path: String,
+    action: ApiKeyScope,
+    /// Indexes targeted by the call, empty when they cannot be told from the path.
+    index_ids: Vec<String>,
+    /// Hex-encoded SHA-256 hash of the query string and the body of search calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_results: Option<u64>,
+    latency_ms: u64,
+    status_code: u16,
+    outcome: Outcome,
+}
+
+/// Records the REST API calls matching the audit log filters and hands them over to the task
+/// writing them to the configured sink.
+pub(crate) struct AuditLogger {
+    audit_log_config: AuditLogConfig,
+    authenticator: Arc<ApiKeyAuthenticator>,
+    event_tx: mpsc::Sender<AuditEvent>,
+}
+
+impl AuditLogger {
+    /// Opens the audit log file or creates the audit log index, then spawns the task writing the
+    /// events to it.
+    pub async fn start(
+        audit_log_config: AuditLogConfig,
+        authenticator: Arc<ApiKeyAuthenticator>,
+        index_service: &IndexService,
+        ingest_service: IngestServiceClient,
+        default_index_root_uri: &Uri,
+    ) -> anyhow::Result<Self> {
+        let sink = match &audit_log_config.sink {
+            AuditLogSinkConfig::File { path } => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to open audit log file `{}`.", path.display())
+                    })?;
+                AuditLogSink::File(file)
+            }
+            AuditLogSinkConfig::Index { index_id } => {
+                create_audit_log_index(index_id, index_service, default_index_root_uri).await?;
+                AuditLogSink::Index {
+                    index_id: index_id.clone(),
+                    ingest_service,
+                }
+            }
+        };
+        let (event_tx, event_rx) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        tokio::spawn(sink.run(event_rx));
+        Ok(Self {
+            audit_log_config,
+            authenticator,
+            event_tx,
+        })
+    }
+
+    /// Returns the action and the indexes of a call if it matches the audit log filters.
+    fn audited_action(&self, method: &Method, path: &str) -> Option<(ApiKeyScope, Vec<String>)> {
+        let (action, index_target) = rest_request_permission(method, path)?;
+        if !self.audit_log_config.actions.contains(&action) {
+            return None;
+        }
+        let index_ids: Vec<String> = match index_target {
+            IndexTarget::Indexes(index_ids) => index_ids.split(',').map(str::to_string).collect(),
+            IndexTarget::None | IndexTarget::AllIndexes => Vec::new(),
+        };
+        let is_audited_index = index_ids.is_empty()
+            || index_ids.iter().any(|index_id| {
+                self.audit_log_config
+                    .index_patterns
+                    .iter()
+                    .any(|index_pattern| index_id_matches_pattern(index_id, index_pattern))
+            });
+        if !is_audited_index {
+            return None;
+        }
+        Some((action, index_ids))
+    }
+
+    async fn record(&self, event: AuditEvent) {
+        if self.event_tx.send(event).await.is_err() {
+            error!("Failed to record audit event: the audit log sink is closed.");
+        }
+    }
+}
+
+/// Calls `service` with `request`, recording the call in the audit log if it matches the audit log
+/// filters.
+pub(crate) async fn audit_request<S>(
+    audit_logger_opt: Option<Arc<AuditLogger>>,
+    service: S,
+    request: Request<Body>,
+) -> Result<Response<Body>, S::Error>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    let Some(audit_logger) = audit_logger_opt else {
+        return service.oneshot(request).await;
+    };
+    let Some((action, index_ids)) =
+        audit_logger.audited_action(request.method(), request.uri().path())
+    else {
+        return service.oneshot(request).await;
+    };
+    let start = Instant::now();
+    let authorization_opt = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok());
+    let principal = audit_logger.authenticator.identify(authorization_opt);
+    let remote_ip = request
+        .extensions()
+        .get::<RemoteAddr>()
+        .map(|RemoteAddr(remote_addr)| remote_addr.ip().to_string());
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let (request, query_hash) = if action == ApiKeyScope::Search {
+        hash_search_query(request).await
+    } else {
+        (request, None)
+    };
+    let response = service.oneshot(request).await?;
+
+    let status_code = response.status();
+    let outcome = if status_code.is_success() {
+        Outcome::Success
+    } else {
+        Outcome::Failure
+    };
+    if outcome == Outcome::Success && !audit_logger.audit_log_config.include_successful {
+        return Ok(response);
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default();
+    let event = AuditEvent {
+        timestamp,
+        principal,
+        remote_ip,
+        method,
+        path,
+        action,
+        index_ids,
+        query_hash,
+        num_results: response
+            .extensions()
+            .get::<ResultCount>()
+            .map(|ResultCount(num_results)| *num_results),
+        latency_ms: start.elapsed().as_millis() as u64,
+        status_code: status_code.as_u16(),
+        outcome,
+    };
+    audit_logger.record(event).await;
+    Ok(response)
+}
+
+/// Hashes the query string and the body of a search request, which is buffered for this purpose.
+async fn hash_search_query(request: Request<Body>) -> (Request<Body>, Option<String>) {
+    let (parts, body) = request.into_parts();
+    let Ok(body_bytes) = hyper::body::to_bytes(body).await else {
+        return (Request::from_parts(parts, Body::empty()), None);
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(parts.uri.query().unwrap_or_default().as_bytes());
+    hasher.update(&body_bytes);
+    let query_hash = format!("{:x}", hasher.finalize());
+    (
+        Request::from_parts(parts, Body::from(body_bytes)),
+        Some(query_hash),
+    )
+}
+
+enum AuditLogSink {
+    File(File),
+    Index {
+        index_id: String,
+        ingest_service: IngestServiceClient,
+    },
+}
+
+impl AuditLogSink {
+    async fn run(mut self, mut event_rx: mpsc::Receiver<AuditEvent>) {
+        while let Some(event) = event_rx.recv().await {
+            let mut events = vec![event];
+            while events.len() < MAX_BATCH_SIZE {
+                let Ok(event) = event_rx.try_recv() else {
+                    break;
+                };
+                events.push(event);
+            }
+            if let Err(error) = self.write(&events).await {
+                error!(error=?error, num_events=events.len(), "Failed to write audit events.");
+            }
+        }
+    }
+
+    async fn write(&mut self, events: &[AuditEvent]) -> anyhow::Result<()> {
+        match self {
+            Self::File(file) => {
+                let mut buffer = Vec::new();
+                for event in events {
+                    serde_json::to_writer(&mut buffer, event)?;
+                    buffer.push(b'\n');
+                }
+                file.write_all(&buffer).await?;
+                file.flush().await?;
+            }
+            Self::Index {
+                index_id,
+                ingest_service,
+            } => {
+                let mut doc_batch = DocBatchBuilder::new(index_id.clone()).json_writer();
+                for event in events {
+                    doc_batch.ingest_doc(event)?;
+                }
+                let ingest_request = IngestRequest {
+                    doc_batches: vec![doc_batch.build()],
+                };
+                ingest_service.ingest(ingest_request).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn audit_log_index_config(index_id: &str) -> String {
+    format!(
+        r#"
+version: 0.4
+
+index_id: {index_id}
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp
+      type: datetime
+      input_formats: [unix_timestamp]
+      fast: true
+      precision: seconds
+    - name: principal
+      type: text
+      tokenizer: raw
+    - name: remote_ip
+      type: ip
+    - name: method
+      type: text
+      tokenizer: raw
+    - name: path
+      type: text
+      tokenizer: raw
+    - name: action
+      type: text
+      tokenizer: raw
+    - name: index_ids
+      type: array<text>
+      tokenizer: raw
+    - name: query_hash
+      type: text
+      tokenizer: raw
+    - name: num_results
+      type: u64
+    - name: latency_ms
+      type: u64
+      fast: true
+    - name: status_code
+      type: u64
+    - name: outcome
+      type: text
+      tokenizer: raw
+  timestamp_field: timestamp
+
+indexing_settings:
+  commit_timeout_secs: 30
+
+search_settings:
+  default_search_fields: [principal, path, index_ids]
+"#
+    )
+}
+
+async fn create_audit_log_index(
+    index_id: &str,
+    index_service: &IndexService,
+    default_index_root_uri: &Uri,
+) -> anyhow::Result<()> {
+    let index_config = load_index_config_from_user_config(
+        ConfigFormat::Yaml,
+        audit_log_index_config(index_id).as_bytes(),
+        default_index_root_uri,
+    )?;
+    match index_service.create_index(index_config, false).await {
+        Ok(_)
+        | Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })) => {
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use quickwit_config::AuthConfig;
+    use quickwit_metastore::MockMetastore;
+    use warp::Filter;
+
+    use super::*;
+
+    fn audit_logger_for_test(
+        audit_log_config: AuditLogConfig,
+    ) -> (Arc<AuditLogger>, mpsc::Receiver<AuditEvent>) {
+        let authenticator = Arc::new(ApiKeyAuthenticator::new(
+            Arc::new(MockMetastore::new()),
+            &AuthConfig::default(),
+        ));
+        let (event_tx, event_rx) = mpsc::channel(10);
+        let audit_logger = AuditLogger {
+            audit_log_config,
+            authenticator,
+            event_tx,
+        };
+        (Arc::new(audit_logger), event_rx)
+    }
+
+    fn audit_log_config_for_test() -> AuditLogConfig {
+        AuditLogConfig {
+            sink: AuditLogSinkConfig::Index {
+                index_id: "audit".to_string(),
+            },
+            actions: BTreeSet::from([ApiKeyScope::Search, ApiKeyScope::Admin]),
+            index_patterns: vec!["logs-*".to_string()],
+            include_successful: true,
+        }
+    }
+
+    #[test]
+    fn test_audit_log_index_config() {
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            audit_log_index_config("audit").as_bytes(),
+            &Uri::from_well_formed("ram:///indexes"),
+        )
+        .unwrap();
+        assert_eq!(index_config.index_id, "audit");
+    }
+
+    #[test]
+    fn test_audit_logger_audited_action() {
+        let (audit_logger, _event_rx) = audit_logger_for_test(audit_log_config_for_test());
+        assert_eq!(
+            audit_logger.audited_action(&Method::GET, "/api/v1/logs-eu,logs-us/search"),
+            Some((
+                ApiKeyScope::Search,
+                vec!["logs-eu".to_string(), "logs-us".to_string()]
+            ))
+        );
+        assert_eq!(
+            audit_logger.audited_action(&Method::POST, "/api/v1/_elastic/_search"),
+            Some((ApiKeyScope::Search, Vec::new()))
+        );
+        assert_eq!(
+            audit_logger.audited_action(&Method::GET, "/api/v1/traces/search"),
+            None
+        );
+        assert_eq!(
+            audit_logger.audited_action(&Method::POST, "/api/v1/logs-eu/ingest"),
+            None
+        );
+        assert_eq!(
+            audit_logger.audited_action(&Method::GET, "/health/livez"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audit_request() {
+        let (audit_logger, mut event_rx) = audit_logger_for_test(audit_log_config_for_test());
+        let routes = warp::path!("api" / "v1" / String / "search")
+            .map(|_index_id| with_result_count(warp::reply(), Some(3)))
+            .or(warp::path!("api" / "v1" / "indexes" / String)
+                .map(|_index_id| warp::http::StatusCode::NOT_FOUND));
+        let service = warp::service(routes);
+
+        let request = Request::post("/api/v1/logs-eu/search?query=error")
+            .body(Body::from(r#"{"max_hits": 10}"#))
+            .unwrap();
+        let response = audit_request(Some(audit_logger.clone()), service.clone(), request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event.principal, None);
+        assert_eq!(event.method, "POST");
+        assert_eq!(event.path, "/api/v1/logs-eu/search");
+        assert_eq!(event.action, ApiKeyScope::Search);
+        assert_eq!(event.index_ids, ["logs-eu"]);
+        assert_eq!(event.num_results, Some(3));
+        assert_eq!(event.status_code, 200);
+        assert_eq!(event.outcome, Outcome::Success);
+        let query_hash = event.query_hash.unwrap();
+        assert_eq!(query_hash.len(), 64);
+
+        let request = Request::post("/api/v1/logs-eu/search?query=error")
+            .body(Body::from(r#"{"max_hits": 10}"#))
+            .unwrap();
+        audit_request(Some(audit_logger.clone()), service.clone(), request)
+            .await
+            .unwrap();
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event.query_hash.unwrap(), query_hash);
+
+        let request = Request::delete("/api/v1/indexes/logs-eu")
+            .body(Body::empty())
+            .unwrap();
+        let response = audit_request(Some(audit_logger), service, request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        let event = event_rx.recv().await.unwrap();
+        assert_eq!(event.action, ApiKeyScope::Admin);
+        assert_eq!(event.query_hash, None);
+        assert_eq!(event.num_results, None);
+        assert_eq!(event.outcome, Outcome::Failure);
+    }
+
+    #[tokio::test]
+    async fn test_audit_request_excludes_successful_calls() {
+        let audit_log_config = AuditLogConfig {
+            include_successful: false,
+            ..audit_log_config_for_test()
+        };
+        let (audit_logger, mut event_rx) = audit_logger_for_test(audit_log_config);
+        let service = warp::service(warp::any().map(warp::reply));
+        let request = Request::get("/api/v1/logs-eu/search")
+            .body(Body::empty())
+            .unwrap();
+        audit_request(Some(audit_logger), service, request)
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use warp::{Filter, Rejection};
+
+use super::signals_sampler::{
+    AutoscalingSignals, AutoscalingSignalsSampler, IndexingSignals, SearchSignals,
+};
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(get_autoscaling_signals),
+    components(schemas(AutoscalingSignals, IndexingSignals, SearchSignals))
+)]
+pub struct AutoscalingApi;
+
+pub(crate) fn autoscaling_api_handlers(
+    signals_sampler: Arc<AutoscalingSignalsSampler>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("autoscaling" / "signals")
+        .and(warp::get())
+        .and(with_arg(signals_sampler))
+        .then(get_autoscaling_signals)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Autoscaling",
+    path = "/autoscaling/signals",
+    responses(
+        (status = 200, description = "Successfully fetched the autoscaling signals of the node.", body = AutoscalingSignals)
+    ),
+)]
+/// Get Autoscaling Signals
+///
+/// Returns the ingestion lag, the backpressure time, and the indexing CPU usage of each index on
+/// the node, as well as the depth of the search queue. The indexing signals are sampled every 10
+/// seconds.
+async fn get_autoscaling_signals(
+    signals_sampler: Arc<AutoscalingSignalsSampler>,
+) -> Result<AutoscalingSignals, Infallible> {
+    Ok(signals_sampler.signals())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_autoscaling_api() {
+        let signals_sampler = Arc::new(AutoscalingSignalsSampler::new(
+            "test-node".to_string(),
+            None,
+            None,
+        ));
+        let autoscaling_api_handlers =
+            autoscaling_api_handlers(signals_sampler).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/autoscaling/signals")
+            .reply(&autoscaling_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let signals: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(signals["node_id"], "test-node");
+        assert_eq!(signals["indexing"]["num_pipelines"], 0);
+        assert_eq!(signals["indexing"]["ingest_lag_records"], 0);
+        assert_eq!(signals["indexes"], serde_json::json!({}));
+        assert!(signals["search"]["queued_searches"].is_u64());
+    }
+}
@@ -0,0 +1,339 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use quickwit_actors::Mailbox;
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::ListPipelines;
+use quickwit_ingest_api::{parse_shard_queue_id, GetPendingRecords, IngestApiService};
+use quickwit_search::SEARCH_METRICS;
+use serde::Serialize;
+use tracing::warn;
+
+const SIGNALS_SAMPLING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Indexing signals of the node or of one of its indexes. The rates are computed over the last
+/// sampling window.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct IndexingSignals {
+    /// Number of indexing pipelines running on the node.
+    pub num_pipelines: usize,
+    /// Number of records of the ingest API queues not indexed yet.
+    pub ingest_lag_records: usize,
+    /// Time spent by the actors of the pipelines waiting for room in the queue of the next actor,
+    /// per second.
+    pub backpressure_ratio: f64,
+    /// Time spent by the doc processors and the indexers processing documents per second. It
+    /// approximates the number of CPU cores used for indexing.
+    pub indexing_cpu_cores: f64,
+}
+
+impl IndexingSignals {
+    fn add(&mut self, other: &IndexingSignals) {
+        self.num_pipelines += other.num_pipelines;
+        self.ingest_lag_records += other.ingest_lag_records;
+        self.backpressure_ratio += other.backpressure_ratio;
+        self.indexing_cpu_cores += other.indexing_cpu_cores;
+    }
+}
+
+/// Search signals of the node.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct SearchSignals {
+    /// Number of searches waiting to be admitted.
+    pub queued_searches: u64,
+    /// Number of searches admitted and running.
+    pub running_searches: u64,
+}
+
+/// Signals meant to drive the autoscaling of the node, e.g. with a KEDA `metrics-api` scaler.
+#[derive(Clone, Debug, Serialize, utoipa::ToSchema)]
+pub struct AutoscalingSignals {
+    pub node_id: String,
+    /// Duration of the window over which the rates are computed, in seconds.
+    pub sampling_window_secs: f64,
+    /// Indexing signals of all the indexes.
+    pub indexing: IndexingSignals,
+    /// Indexing signals of each index, keyed by index ID.
+    pub indexes: BTreeMap<String, IndexingSignals>,
+    pub search: SearchSignals,
+}
+
+/// Cumulative counters of the pipelines of an index.
+#[derive(Clone, Copy, Debug, Default)]
+struct IndexCounters {
+    num_pipelines: usize,
+    backpressure_micros: u64,
+    processing_micros: u64,
+}
+
+struct IndexingSample {
+    sampled_at: Instant,
+    counters_per_index: HashMap<String, IndexCounters>,
+    num_pending_records_per_queue: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+struct SamplerState {
+    last_sample_opt: Option<IndexingSample>,
+    sampling_window: Duration,
+    indexing_signals_per_index: BTreeMap<String, IndexingSignals>,
+}
+
+/// Periodically samples the indexing pipelines and the ingest API queues of the node to compute
+/// its autoscaling signals.
+pub(crate) struct AutoscalingSignalsSampler {
+    node_id: String,
+    indexing_service_opt: Option<Mailbox<IndexingService>>,
+    ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
+    state: Mutex<SamplerState>,
+}
+
+impl AutoscalingSignalsSampler {
+    pub fn new(
+        node_id: String,
+        indexing_service_opt: Option<Mailbox<IndexingService>>,
+        ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
+    ) -> Self {
+        Self {
+            node_id,
+            indexing_service_opt,
+            ingest_api_service_opt,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the signals as of the last sample. The search signals are always up to date.
+    pub fn signals(&self) -> AutoscalingSignals {
+        let state_guard = self.state.lock().unwrap();
+        let mut indexing_signals = IndexingSignals::default();
+
+        for index_signals in state_guard.indexing_signals_per_index.values() {
+            indexing_signals.add(index_signals);
+        }
+        let search_signals = SearchSignals {
+            queued_searches: SEARCH_METRICS.queued_searches.get().max(0) as u64,
+            running_searches: SEARCH_METRICS.running_searches.get().max(0) as u64,
+        };
+        AutoscalingSignals {
+            node_id: self.node_id.clone(),
+            sampling_window_secs: state_guard.sampling_window.as_secs_f64(),
+            indexing: indexing_signals,
+            indexes: state_guard.indexing_signals_per_index.clone(),
+            search: search_signals,
+        }
+    }
+
+    async fn sample(&self) {
+        let mut counters_per_index: HashMap<String, IndexCounters> = HashMap::new();
+
+        if let Some(indexing_service) = &self.indexing_service_opt {
+            let pipeline_statuses = match indexing_service.ask_for_res(ListPipelines).await {
+                Ok(pipeline_statuses) => pipeline_statuses,
+                Err(error) => {
+                    warn!(error=?error, "Failed to sample the indexing pipelines.");
+                    return;
+                }
+            };
+            for pipeline_status in pipeline_statuses {
+                let index_counters = counters_per_index
+                    .entry(pipeline_status.index_id)
+                    .or_default();
+                index_counters.num_pipelines += 1;
+                // The times are already accumulated over the pipelines of the index.
+                index_counters.backpressure_micros =
+                    pipeline_status.backpressure_micros.values().sum();
+                index_counters.processing_micros = pipeline_status.processing_micros.values().sum();
+            }
+        }
+        let num_pending_records_per_queue = match &self.ingest_api_service_opt {
+            Some(ingest_api_service) => match ingest_api_service.ask(GetPendingRecords).await {
+                Ok(num_pending_records_per_queue) => num_pending_records_per_queue,
+                Err(error) => {
+                    warn!(error=?error, "Failed to sample the ingest API queues.");
+                    return;
+                }
+            },
+            None => HashMap::new(),
+        };
+        let sample = IndexingSample {
+            sampled_at: Instant::now(),
+            counters_per_index,
+            num_pending_records_per_queue,
+        };
+        let mut state_guard = self.state.lock().unwrap();
+        state_guard.indexing_signals_per_index =
+            compute_indexing_signals(state_guard.last_sample_opt.as_ref(), &sample);
+        state_guard.sampling_window = state_guard
+            .last_sample_opt
+            .as_ref()
+            .map(|last_sample| sample.sampled_at.duration_since(last_sample.sampled_at))
+            .unwrap_or_default();
+        state_guard.last_sample_opt = Some(sample);
+    }
+}
+
+/// Computes the indexing signals of each index from two consecutive samples. The indexes without
+/// pipelines on the node are only reported when their ingest API queue is not empty.
+fn compute_indexing_signals(
+    previous_sample_opt: Option<&IndexingSample>,
+    current_sample: &IndexingSample,
+) -> BTreeMap<String, IndexingSignals> {
+    let mut indexing_signals_per_index: BTreeMap<String, IndexingSignals> = BTreeMap::new();
+
+    for (index_id, index_counters) in &current_sample.counters_per_index {
+        let index_signals = indexing_signals_per_index
+            .entry(index_id.clone())
+            .or_default();
+        index_signals.num_pipelines = index_counters.num_pipelines;
+
+        let Some(previous_sample) = previous_sample_opt else {
+            continue;
+        };
+        let Some(previous_index_counters) = previous_sample.counters_per_index.get(index_id) else {
+            continue;
+        };
+        let elapsed_micros = current_sample
+            .sampled_at
+            .duration_since(previous_sample.sampled_at)
+            .as_micros() as f64;
+        if elapsed_micros == 0.0 {
+            continue;
+        }
+        let backpressure_micros = index_counters
+            .backpressure_micros
+            .saturating_sub(previous_index_counters.backpressure_micros);
+        let processing_micros = index_counters
+            .processing_micros
+            .saturating_sub(previous_index_counters.processing_micros);
+        index_signals.backpressure_ratio = backpressure_micros as f64 / elapsed_micros;
+        index_signals.indexing_cpu_cores = processing_micros as f64 / elapsed_micros;
+    }
+    // The records of the shards of an index add up.
+    for (queue_id, &num_pending_records) in &current_sample.num_pending_records_per_queue {
+        let Some((index_id, _shard_ord)) = parse_shard_queue_id(queue_id) else {
+            continue;
+        };
+        if num_pending_records == 0 && !indexing_signals_per_index.contains_key(index_id) {
+            continue;
+        }
+        indexing_signals_per_index
+            .entry(index_id.to_string())
+            .or_default()
+            .ingest_lag_records += num_pending_records;
+    }
+    indexing_signals_per_index
+}
+
+pub(crate) fn spawn_autoscaling_signals_sampling_task(sampler: Arc<AutoscalingSignalsSampler>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SIGNALS_SAMPLING_INTERVAL);
+        loop {
+            interval.tick().await;
+            sampler.sample().await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_indexing_signals() {
+        let now = Instant::now();
+        let previous_sample = IndexingSample {
+            sampled_at: now,
+            counters_per_index: HashMap::from_iter([(
+                "index-1".to_string(),
+                IndexCounters {
+                    num_pipelines: 2,
+                    backpressure_micros: 1_000_000,
+                    processing_micros: 2_000_000,
+                },
+            )]),
+            num_pending_records_per_queue: HashMap::new(),
+        };
+        let current_sample = IndexingSample {
+            sampled_at: now + Duration::from_secs(10),
+            counters_per_index: HashMap::from_iter([
+                (
+                    "index-1".to_string(),
+                    IndexCounters {
+                        num_pipelines: 2,
+                        backpressure_micros: 6_000_000,
+                        processing_micros: 17_000_000,
+                    },
+                ),
+                (
+                    "index-2".to_string(),
+                    IndexCounters {
+                        num_pipelines: 1,
+                        backpressure_micros: 1_000_000,
+                        processing_micros: 1_000_000,
+                    },
+                ),
+            ]),
+            num_pending_records_per_queue: HashMap::from_iter([
+                ("index-1".to_string(), 100),
+                ("index-1/shard-1".to_string(), 20),
+                ("index-3".to_string(), 0),
+                ("index-4".to_string(), 10),
+            ]),
+        };
+        let indexing_signals_per_index = compute_indexing_signals(None, &previous_sample);
+        assert_eq!(indexing_signals_per_index.len(), 1);
+        assert_eq!(indexing_signals_per_index["index-1"].num_pipelines, 2);
+        assert_eq!(
+            indexing_signals_per_index["index-1"].indexing_cpu_cores,
+            0.0
+        );
+
+        let indexing_signals_per_index =
+            compute_indexing_signals(Some(&previous_sample), &current_sample);
+        assert_eq!(indexing_signals_per_index.len(), 3);
+        assert_eq!(
+            indexing_signals_per_index["index-1"],
+            IndexingSignals {
+                num_pipelines: 2,
+                ingest_lag_records: 120,
+                backpressure_ratio: 0.5,
+                indexing_cpu_cores: 1.5,
+            }
+        );
+        // The rates of the indexes without previous counters are unknown.
+        assert_eq!(
+            indexing_signals_per_index["index-2"],
+            IndexingSignals {
+                num_pipelines: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            indexing_signals_per_index["index-4"],
+            IndexingSignals {
+                ingest_lag_records: 10,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use quickwit_actors::Mailbox;
+use quickwit_common::reload_log_filter;
+use quickwit_common::uri::Uri;
+use quickwit_config::{ConfigFormat, QuickwitConfig};
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::RefreshIndexConfigs;
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use quickwit_search::SearcherContext;
+use quickwit_storage::load_file;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::rate_limiter::RateLimiter;
+
+/// Interval at which the config file is read to detect changes.
+const CONFIG_FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Interval at which the indexing pipelines check whether the config of their index changed.
+const INDEX_CONFIGS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Settings of the searcher applied without restarting the node.
+const RELOADABLE_SEARCHER_SETTINGS: [&str; 4] = [
+    "searcher.split_footer_cache_capacity",
+    "searcher.fast_field_cache_capacity",
+    "searcher.leaf_search_cache_capacity",
+    "searcher.split_searcher_cache_capacity",
+];
+
+#[derive(Debug, Error)]
+pub(crate) enum ConfigReloadError {
+    #[error("The node was not started from a config file.")]
+    MissingConfigFile,
+    #[error("Failed to reload node config: {0}")]
+    InvalidConfig(String),
+}
+
+impl ServiceError for ConfigReloadError {
+    fn status_code(&self) -> ServiceErrorCode {
+        ServiceErrorCode::BadRequest
+    }
+}
+
+/// Outcome of a reload of the node config.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct ConfigReloadReport {
+    /// Changed settings applied by the node.
+    pub applied_settings: Vec<String>,
+    /// Changed settings that only take effect once the node is restarted.
+    pub restart_required_settings: Vec<String>,
+    /// Number of indexing pipelines respawned to apply the updated configs of their index.
+    pub num_respawned_pipelines: usize,
+}
+
+struct ConfigReloaderState {
+    /// The config in effect, which may differ from the config file for the settings requiring a
+    /// restart.
+    config: QuickwitConfig,
+    config_content_opt: Option<Vec<u8>>,
+}
+
+/// Applies the changes of the node config file to the running node: the rate limits, the log
+/// filter, and the capacity of the searcher caches. It also has the indexing pipelines pick up the
+/// updated configs of their index.
+pub(crate) struct ConfigReloader {
+    config_uri_opt: Option<Uri>,
+    state: Mutex<ConfigReloaderState>,
+    rate_limiter: Arc<RateLimiter>,
+    searcher_context: Arc<SearcherContext>,
+    indexing_service_opt: Option<Mailbox<IndexingService>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config_uri_opt: Option<Uri>,
+        config: QuickwitConfig,
+        rate_limiter: Arc<RateLimiter>,
+        searcher_context: Arc<SearcherContext>,
+        indexing_service_opt: Option<Mailbox<IndexingService>>,
+    ) -> Self {
+        let state = ConfigReloaderState {
+            config,
+            config_content_opt: None,
+        };
+        Self {
+            config_uri_opt,
+            state: Mutex::new(state),
+            rate_limiter,
+            searcher_context,
+            indexing_service_opt,
+        }
+    }
+
+    /// Reloads the config file and refreshes the configs of the indexes.
+    pub async fn reload(&self) -> Result<ConfigReloadReport, ConfigReloadError> {
+        let config_uri = self
+            .config_uri_opt
+            .as_ref()
+            .ok_or(ConfigReloadError::MissingConfigFile)?;
+        let config_content = load_file(config_uri)
+            .await
+            .map_err(|error| ConfigReloadError::InvalidConfig(format!("{error:#}")))?;
+        let mut state_guard = self.state.lock().await;
+        let mut report = self
+            .apply_config_content(&mut state_guard, config_uri, config_content.to_vec())
+            .await?;
+        report.num_respawned_pipelines = self.refresh_index_configs().await;
+        Ok(report)
+    }
+
+    /// Reloads the config file if its content changed since the last time it was read.
+    async fn reload_if_changed(&self, config_uri: &Uri) {
+        let config_content = match load_file(config_uri).await {
+            Ok(config_content) => config_content.to_vec(),
+            Err(error) => {
+                warn!(config_uri=%config_uri, error=?error, "Failed to read node config file.");
+                return;
+            }
+        };
+        let mut state_guard = self.state.lock().await;
+
+        let Some(previous_config_content) = &state_guard.config_content_opt else {
+            state_guard.config_content_opt = Some(config_content);
+            return;
+        };
+        if *previous_config_content == config_content {
+            return;
+        }
+        if let Err(error) = self
+            .apply_config_content(&mut state_guard, config_uri, config_content)
+            .await
+        {
+            error!(config_uri=%config_uri, error=%error, "Failed to reload node config.");
+        }
+    }
+
+    async fn apply_config_content(
+        &self,
+        state: &mut ConfigReloaderState,
+        config_uri: &Uri,
+        config_content: Vec<u8>,
+    ) -> Result<ConfigReloadReport, ConfigReloadError> {
+        let config_format = ConfigFormat::sniff_from_uri(config_uri)
+            .map_err(|error| ConfigReloadError::InvalidConfig(error.to_string()))?;
+        let mut new_config = QuickwitConfig::load(config_format, &config_content)
+            .await
+            .map_err(|error| ConfigReloadError::InvalidConfig(format!("{error:#}")))?;
+        // The enabled services may be overridden on the command line.
+        new_config.enabled_services = state.config.enabled_services.clone();
+        state.config_content_opt = Some(config_content);
+
+        let report = self.apply_config(&mut state.config, new_config)?;
+        if !report.applied_settings.is_empty() {
+            info!(settings=?report.applied_settings, "Applied node config changes.");
+        }
+        if !report.restart_required_settings.is_empty() {
+            warn!(
+                settings=?report.restart_required_settings,
+                "Some node config changes only take effect once the node is restarted."
+            );
+        }
+        Ok(report)
+    }
+
+    fn apply_config(
+        &self,
+        config: &mut QuickwitConfig,
+        new_config: QuickwitConfig,
+    ) -> Result<ConfigReloadReport, ConfigReloadError> {
+        let mut report = ConfigReloadReport::default();
+        let mut update_cache_capacities = false;
+
+        for setting in changed_settings(config, &new_config) {
+            match setting.as_str() {
+                "log_level" => {
+                    let Some(log_level) = &new_config.log_level else {
+                        // The log filter the node started with is not known anymore.
+                        report.restart_required_settings.push(setting);
+                        continue;
+                    };
+                    reload_log_filter(log_level)
+                        .map_err(|error| ConfigReloadError::InvalidConfig(format!("{error:#}")))?;
+                    config.log_level = new_config.log_level.clone();
+                }
+                "rate_limits" => {
+                    self.rate_limiter
+                        .update_config(new_config.rate_limits_config.clone());
+                    config.rate_limits_config = new_config.rate_limits_config.clone();
+                }
+                _ if RELOADABLE_SEARCHER_SETTINGS.contains(&setting.as_str()) => {
+                    update_cache_capacities = true;
+                }
+                _ => {
+                    report.restart_required_settings.push(setting);
+                    continue;
+                }
+            }
+            report.applied_settings.push(setting);
+        }
+        if update_cache_capacities {
+            config.searcher_config.split_footer_cache_capacity =
+                new_config.searcher_config.split_footer_cache_capacity;
+            config.searcher_config.fast_field_cache_capacity =
+                new_config.searcher_config.fast_field_cache_capacity;
+            config.searcher_config.leaf_search_cache_capacity =
+                new_config.searcher_config.leaf_search_cache_capacity;
+            config.searcher_config.split_searcher_cache_capacity =
+                new_config.searcher_config.split_searcher_cache_capacity;
+            self.searcher_context
+                .update_cache_capacities(&config.searcher_config);
+        }
+        Ok(report)
+    }
+
+    /// Has the indexing pipelines of the node respawned if the config of their index changed.
+    /// Returns the number of respawned pipelines.
+    async fn refresh_index_configs(&self) -> usize {
+        let Some(indexing_service) = &self.indexing_service_opt else {
+            return 0;
+        };
+        match indexing_service.ask_for_res(RefreshIndexConfigs).await {
+            Ok(num_respawned_pipelines) => num_respawned_pipelines,
+            Err(error) => {
+                warn!(error=?error, "Failed to refresh the configs of the indexes.");
+                0
+            }
+        }
+    }
+}
+
+/// Returns the names of the settings that differ between the two configs, e.g. `rate_limits`, or
+/// `searcher.fast_field_cache_capacity` for the searcher settings.
+fn changed_settings(config: &QuickwitConfig, new_config: &QuickwitConfig) -> Vec<String> {
+    let (Ok(JsonValue::Object(settings)), Ok(JsonValue::Object(new_settings))) =
+        (serde_json::to_value(config), serde_json::to_value(new_config))
+    else {
+        return Vec::new();
+    };
+    let keys: BTreeSet<&String> = settings.keys().chain(new_settings.keys()).collect();
+    let mut changed_settings = Vec::new();
+
+    for key in keys {
+        let value = settings.get(key).unwrap_or(&JsonValue::Null);
+        let new_value = new_settings.get(key).unwrap_or(&JsonValue::Null);
+        if value == new_value {
+            continue;
+        }
+        // The sections of the config are named after the fields of `QuickwitConfig`, without the
+        // `_config` suffix.
+        let section = key.strip_suffix("_config").unwrap_or(key);
+
+        if let ("searcher", JsonValue::Object(fields), JsonValue::Object(new_fields)) =
+            (section, value, new_value)
+        {
+            let field_keys: BTreeSet<&String> = fields.keys().chain(new_fields.keys()).collect();
+            for field_key in field_keys {
+                if fields.get(field_key) != new_fields.get(field_key) {
+                    changed_settings.push(format!("searcher.{field_key}"));
+                }
+            }
+        } else {
+            changed_settings.push(section.to_string());
+        }
+    }
+    changed_settings
+}
+
+pub(crate) fn spawn_config_reload_tasks(config_reloader: Arc<ConfigReloader>) {
+    if let Some(config_uri) = config_reloader.config_uri_opt.clone() {
+        if config_uri.protocol().is_file() {
+            let config_reloader = config_reloader.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(CONFIG_FILE_POLL_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    config_reloader.reload_if_changed(&config_uri).await;
+                }
+            });
+        }
+    }
+    if config_reloader.indexing_service_opt.is_some() {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(INDEX_CONFIGS_REFRESH_INTERVAL);
+            loop {
+                interval.tick().await;
+                config_reloader.refresh_index_configs().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::SearcherConfig;
+
+    use super::*;
+
+    async fn load_config(config_yaml: &str) -> QuickwitConfig {
+        QuickwitConfig::load(ConfigFormat::Yaml, config_yaml.as_bytes())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_config_reloader_reload() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_path = temp_dir.path().join("quickwit.yaml");
+        let config_yaml = format!(
+            "version: 0.4\nnode_id: test-node\ndata_dir: {}\n",
+            temp_dir.path().display()
+        );
+        std::fs::write(&config_path, &config_yaml).unwrap();
+        let config = load_config(&config_yaml).await;
+        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits_config.clone()));
+        let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+
+        let config_reloader = ConfigReloader::new(
+            None,
+            config.clone(),
+            rate_limiter.clone(),
+            searcher_context.clone(),
+            None,
+        );
+        let error = config_reloader.reload().await.unwrap_err();
+        assert!(matches!(error, ConfigReloadError::MissingConfigFile));
+
+        let config_uri = Uri::from_well_formed(format!("file://{}", config_path.display()));
+        let config_reloader = ConfigReloader::new(
+            Some(config_uri),
+            config,
+            rate_limiter,
+            searcher_context,
+            None,
+        );
+        let report = config_reloader.reload().await.unwrap();
+        assert_eq!(report, ConfigReloadReport::default());
+
+        let new_config_yaml = format!(
+            r#"{config_yaml}
+indexer:
+  max_concurrent_split_uploads: 3
+searcher:
+  fast_field_cache_capacity: 2G
+rate_limits:
+  search:
+    per_ip:
+      requests_per_sec: 10
+"#
+        );
+        std::fs::write(&config_path, new_config_yaml).unwrap();
+        let report = config_reloader.reload().await.unwrap();
+        assert_eq!(
+            report.applied_settings,
+            ["rate_limits", "searcher.fast_field_cache_capacity"]
+        );
+        assert_eq!(report.restart_required_settings, ["indexer"]);
+
+        // The settings requiring a restart keep being reported until the node restarts.
+        let report = config_reloader.reload().await.unwrap();
+        assert!(report.applied_settings.is_empty());
+        assert_eq!(report.restart_required_settings, ["indexer"]);
+
+        std::fs::write(&config_path, "version: 0.4\nnode_id: invalid node id\n").unwrap();
+        let error = config_reloader.reload().await.unwrap_err();
+        assert!(matches!(error, ConfigReloadError::InvalidConfig(_)));
+    }
+
+    #[tokio::test]
+    async fn test_changed_settings() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_yaml = format!("version: 0.4\ndata_dir: {}\n", temp_dir.path().display());
+        let config = load_config(&config_yaml).await;
+        assert!(changed_settings(&config, &config).is_empty());
+
+        let new_config_yaml = format!(
+            "{config_yaml}log_level: debug\nsearcher:\n  leaf_search_cache_capacity: 1G\n  \
+             max_num_concurrent_split_searches: 10\n"
+        );
+        let new_config = load_config(&new_config_yaml).await;
+        assert_eq!(
+            changed_settings(&config, &new_config),
+            [
+                "log_level",
+                "searcher.leaf_search_cache_capacity",
+                "searcher.max_num_concurrent_split_searches"
+            ]
+        );
+    }
+}
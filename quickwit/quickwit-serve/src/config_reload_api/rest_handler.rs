@@ -0,0 +1,98 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use warp::{Filter, Rejection};
+
+use super::config_reloader::{ConfigReloadError, ConfigReloadReport, ConfigReloader};
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(reload_config), components(schemas(ConfigReloadReport)))]
+pub struct ConfigReloadApi;
+
+pub(crate) fn config_reload_api_handlers(
+    config_reloader: Arc<ConfigReloader>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("config" / "reload")
+        .and(warp::post())
+        .and(with_arg(config_reloader))
+        .then(reload_config)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Node Config",
+    path = "/config/reload",
+    responses(
+        (status = 200, description = "Successfully reloaded the node config.", body = ConfigReloadReport)
+    ),
+)]
+/// Reload Node Config
+///
+/// Reloads the config file of the node and applies the changed settings that do not require a
+/// restart. The indexing pipelines of the node also pick up the updated configs of their index.
+async fn reload_config(
+    config_reloader: Arc<ConfigReloader>,
+) -> Result<ConfigReloadReport, ConfigReloadError> {
+    config_reloader.reload().await
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::{QuickwitConfig, SearcherConfig};
+    use quickwit_search::SearcherContext;
+
+    use super::*;
+    use crate::rate_limiter::RateLimiter;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_config_reload_api() {
+        let config = QuickwitConfig::for_test();
+        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits_config.clone()));
+        let searcher_context = Arc::new(SearcherContext::new(SearcherConfig::default()));
+        let config_reloader = Arc::new(ConfigReloader::new(
+            None,
+            config,
+            rate_limiter,
+            searcher_context,
+            None,
+        ));
+        let config_reload_api_handlers =
+            config_reload_api_handlers(config_reloader).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/config/reload")
+            .reply(&config_reload_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 405);
+
+        let resp = warp::test::request()
+            .path("/config/reload")
+            .method("POST")
+            .reply(&config_reload_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
@@ -19,21 +19,38 @@
 
 use std::sync::Arc;
 
+use futures::future::try_join_all;
 use quickwit_config::{build_doc_mapper, IndexConfig};
+use quickwit_doc_mapper::DocMapper;
 use quickwit_janitor::error::JanitorError;
-use quickwit_metastore::{Metastore, MetastoreError};
+use quickwit_metastore::{ListSplitsQuery, Metastore, MetastoreError, SplitState};
 use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
 use quickwit_proto::SearchRequest;
-use serde::Deserialize;
+use quickwit_search::{jobs_to_leaf_request, SearchJob, SearchService};
+use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
 use crate::format::{extract_format_from_qs, make_response};
+use crate::json_body::json_body;
 use crate::with_arg;
 
 #[derive(utoipa::OpenApi)]
 #[openapi(
-    paths(get_delete_tasks, post_delete_request),
-    components(schemas(DeleteQueryRequest, DeleteTask, DeleteQuery,))
+    paths(
+        get_delete_tasks,
+        get_delete_task_status,
+        post_delete_request,
+        delete_by_query
+    ),
+    components(schemas(
+        DeleteQueryRequest,
+        DeleteTask,
+        DeleteQuery,
+        DeleteTaskState,
+        DeleteTaskStatus,
+        DeleteByQueryDryRunResponse,
+        SplitMatchedDocs,
+    ))
 )]
 pub struct DeleteTaskApi;
 
@@ -53,11 +70,70 @@ pub struct DeleteQueryRequest {
     pub end_timestamp: Option<i64>,
 }
 
+/// This struct represents the QueryString passed to the delete-by-query REST API.
+#[derive(Debug, Clone, Default, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteByQueryParams {
+    /// If set, the documents matching the query are counted but no delete task is created.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Number of documents matching a delete query in a split.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SplitMatchedDocs {
+    pub split_id: String,
+    pub num_matched_docs: u64,
+}
+
+/// Response of a delete-by-query request executed in dry-run mode.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeleteByQueryDryRunResponse {
+    /// Total number of documents matching the delete query.
+    pub num_matched_docs: u64,
+    /// Published splits containing at least one document matching the delete query.
+    pub splits: Vec<SplitMatchedDocs>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DeleteByQueryResponse {
+    DryRun(DeleteByQueryDryRunResponse),
+    DeleteTask(DeleteTask),
+}
+
+/// Progress of a delete task.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteTaskState {
+    /// The delete task has not been applied to any split yet.
+    Pending,
+    /// The delete task has been applied to some of the published splits.
+    InProgress,
+    /// The delete task has been applied to all the published splits.
+    Completed,
+}
+
+/// A delete task along with its progress over the published splits of the index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct DeleteTaskStatus {
+    pub delete_task: DeleteTask,
+    pub state: DeleteTaskState,
+    /// Number of published splits in the index.
+    pub num_published_splits: usize,
+    /// Number of published splits the delete task has not been applied to yet.
+    pub num_pending_splits: usize,
+}
+
 /// Delete query API handlers.
 pub fn delete_task_api_handlers(
     metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    get_delete_tasks_handler(metastore.clone()).or(post_delete_tasks_handler(metastore.clone()))
+    get_delete_tasks_handler(metastore.clone())
+        .or(get_delete_task_status_handler(metastore.clone()))
+        .or(post_delete_tasks_handler(metastore.clone()))
+        .or(delete_by_query_handler(metastore, search_service))
 }
 
 pub fn get_delete_tasks_handler(
@@ -97,11 +173,75 @@ pub async fn get_delete_tasks(
     Ok(delete_tasks)
 }
 
+pub fn get_delete_task_status_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "delete-tasks" / u64)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_delete_task_status)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Delete Tasks",
+    path = "/{index_id}/delete-tasks/{opstamp}",
+    responses(
+        (status = 200, description = "Successfully fetched the delete task status.", body = DeleteTaskStatus)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the delete task."),
+        ("opstamp" = u64, Path, description = "The opstamp of the delete task."),
+    )
+)]
+/// Get Delete Task Status
+///
+/// Returns the delete task of opstamp `opstamp` along with its progress. A delete task is applied
+/// to a split once the split's delete opstamp is greater than or equal to the task's opstamp.
+pub async fn get_delete_task_status(
+    index_id: String,
+    opstamp: u64,
+    metastore: Arc<dyn Metastore>,
+) -> Result<DeleteTaskStatus, JanitorError> {
+    let delete_task = metastore
+        .list_delete_tasks(&index_id, opstamp.saturating_sub(1))
+        .await?
+        .into_iter()
+        .find(|delete_task| delete_task.opstamp == opstamp)
+        .ok_or_else(|| JanitorError::DeleteTaskDoesNotExist {
+            index_id: index_id.clone(),
+            opstamp,
+        })?;
+    let published_splits = metastore
+        .list_splits(ListSplitsQuery::for_index(&index_id).with_split_state(SplitState::Published))
+        .await?;
+    let num_published_splits = published_splits.len();
+    let num_pending_splits = published_splits
+        .iter()
+        .filter(|split| split.split_metadata.delete_opstamp < opstamp)
+        .count();
+    let state = if num_pending_splits == 0 {
+        DeleteTaskState::Completed
+    } else if num_pending_splits == num_published_splits {
+        DeleteTaskState::Pending
+    } else {
+        DeleteTaskState::InProgress
+    };
+    Ok(DeleteTaskStatus {
+        delete_task,
+        state,
+        num_published_splits,
+        num_pending_splits,
+    })
+}
+
 pub fn post_delete_tasks_handler(
     metastore: Arc<dyn Metastore>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     warp::path!(String / "delete-tasks")
-        .and(warp::body::json())
+        .and(json_body())
         .and(warp::post())
         .and(with_arg(metastore))
         .then(post_delete_request)
@@ -130,8 +270,20 @@ pub async fn post_delete_request(
     delete_request: DeleteQueryRequest,
     metastore: Arc<dyn Metastore>,
 ) -> Result<DeleteTask, JanitorError> {
+    let (delete_query, _, _) =
+        validate_delete_request(index_id, delete_request, metastore.as_ref()).await?;
+    let delete_task = metastore.create_delete_task(delete_query).await?;
+    Ok(delete_task)
+}
+
+/// Builds the delete query and validates it against the index doc mapping.
+async fn validate_delete_request(
+    index_id: String,
+    delete_request: DeleteQueryRequest,
+    metastore: &dyn Metastore,
+) -> Result<(DeleteQuery, IndexConfig, Arc<dyn DocMapper>), JanitorError> {
     let delete_query = DeleteQuery {
-        index_id: index_id.clone(),
+        index_id,
         start_timestamp: delete_request.start_timestamp,
         end_timestamp: delete_request.end_timestamp,
         query: delete_request.query,
@@ -141,6 +293,12 @@ pub async fn post_delete_request(
         .index_metadata(&delete_query.index_id)
         .await?
         .into_index_config();
+    if index_config.is_mounted() {
+        return Err(JanitorError::InvalidDeleteQuery(format!(
+            "Index `{}` is mounted and read-only.",
+            delete_query.index_id
+        )));
+    }
     // TODO should it be something else than a JanitorError?
     let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
         .map_err(|error| JanitorError::InternalError(error.to_string()))?;
@@ -149,16 +307,131 @@ pub async fn post_delete_request(
     doc_mapper
         .query(doc_mapper.schema(), &delete_search_request)
         .map_err(|error| JanitorError::InvalidDeleteQuery(error.to_string()))?;
-    let delete_task = metastore.create_delete_task(delete_query).await?;
-    Ok(delete_task)
+    Ok((delete_query, index_config, doc_mapper))
+}
+
+pub fn delete_by_query_handler(
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "delete-by-query")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(json_body())
+        .and(with_arg(metastore))
+        .and(with_arg(search_service))
+        .then(delete_by_query)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Delete Tasks",
+    path = "/{index_id}/delete-by-query",
+    request_body = DeleteQueryRequest,
+    responses(
+        (status = 200, description = "Successfully added a new delete task, or counted the matching documents in dry-run mode.", body = DeleteTask)
+    ),
+    params(
+        DeleteByQueryParams,
+        ("index_id" = String, Path, description = "The index ID to delete documents from."),
+    )
+)]
+/// Delete By Query
+///
+/// Creates a delete task deleting the documents matching the query. In dry-run mode, the
+/// documents matching the query are counted in each published split instead, and no delete task
+/// is created.
+pub async fn delete_by_query(
+    index_id: String,
+    delete_by_query_params: DeleteByQueryParams,
+    delete_request: DeleteQueryRequest,
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+) -> Result<DeleteByQueryResponse, JanitorError> {
+    let (delete_query, index_config, doc_mapper) =
+        validate_delete_request(index_id, delete_request, metastore.as_ref()).await?;
+    if !delete_by_query_params.dry_run {
+        let delete_task = metastore.create_delete_task(delete_query).await?;
+        return Ok(DeleteByQueryResponse::DeleteTask(delete_task));
+    }
+    let dry_run_response = count_matched_docs_per_split(
+        delete_query,
+        &index_config,
+        doc_mapper,
+        metastore.as_ref(),
+        search_service.as_ref(),
+    )
+    .await?;
+    Ok(DeleteByQueryResponse::DryRun(dry_run_response))
+}
+
+/// Executes one leaf search per published split overlapping the time range of the delete query
+/// and returns the number of documents matching the query in each split.
+async fn count_matched_docs_per_split(
+    delete_query: DeleteQuery,
+    index_config: &IndexConfig,
+    doc_mapper: Arc<dyn DocMapper>,
+    metastore: &dyn Metastore,
+    search_service: &dyn SearchService,
+) -> Result<DeleteByQueryDryRunResponse, JanitorError> {
+    let mut list_splits_query =
+        ListSplitsQuery::for_index(&delete_query.index_id).with_split_state(SplitState::Published);
+    if let Some(start_timestamp) = delete_query.start_timestamp {
+        list_splits_query = list_splits_query.with_time_range_start_gte(start_timestamp);
+    }
+    if let Some(end_timestamp) = delete_query.end_timestamp {
+        list_splits_query = list_splits_query.with_time_range_end_lt(end_timestamp);
+    }
+    let splits = metastore.list_splits(list_splits_query).await?;
+    let doc_mapper_str = serde_json::to_string(&doc_mapper).map_err(|error| {
+        JanitorError::InternalError(format!("Failed to serialize doc mapper: {error}"))
+    })?;
+    let search_request = SearchRequest::from(delete_query);
+    let leaf_search_futures = splits.iter().map(|split| {
+        let leaf_search_request = jobs_to_leaf_request(
+            &search_request,
+            &doc_mapper_str,
+            index_config.index_uri.as_str(),
+            vec![SearchJob::from(&split.split_metadata)],
+        );
+        search_service.leaf_search(leaf_search_request)
+    });
+    let leaf_search_responses = try_join_all(leaf_search_futures)
+        .await
+        .map_err(|error| JanitorError::InternalError(error.to_string()))?;
+    let mut dry_run_response = DeleteByQueryDryRunResponse {
+        num_matched_docs: 0,
+        splits: Vec::new(),
+    };
+    for (split, leaf_search_response) in splits.iter().zip(leaf_search_responses) {
+        if let Some(failed_split) = leaf_search_response.failed_splits.first() {
+            return Err(JanitorError::InternalError(failed_split.to_string()));
+        }
+        if leaf_search_response.num_hits == 0 {
+            continue;
+        }
+        dry_run_response.num_matched_docs += leaf_search_response.num_hits;
+        dry_run_response.splits.push(SplitMatchedDocs {
+            split_id: split.split_id().to_string(),
+            num_matched_docs: leaf_search_response.num_hits,
+        });
+    }
+    Ok(dry_run_response)
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use quickwit_indexing::TestSandbox;
     use quickwit_proto::metastore_api::DeleteTask;
+    use quickwit_proto::LeafSearchResponse;
+    use quickwit_search::MockSearchService;
     use warp::Filter;
 
+    use super::{DeleteByQueryDryRunResponse, DeleteTaskState, DeleteTaskStatus};
     use crate::rest::recover_fn;
 
     #[tokio::test]
@@ -178,7 +451,8 @@ mod tests {
             .unwrap();
         let metastore = test_sandbox.metastore();
         let delete_query_api_handlers =
-            super::delete_task_api_handlers(metastore).recover(recover_fn);
+            super::delete_task_api_handlers(metastore, Arc::new(MockSearchService::new()))
+                .recover(recover_fn);
         let resp = warp::test::request()
             .path("/test-delete-task-rest/delete-tasks")
             .method("POST")
@@ -216,4 +490,94 @@ mod tests {
         assert_eq!(delete_tasks.len(), 1);
         test_sandbox.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_delete_by_query_api() {
+        quickwit_common::setup_logging_for_tests();
+        let index_id = "test-delete-by-query-rest";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: body
+                type: text
+              - name: ts
+                type: i64
+                fast: true
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"])
+            .await
+            .unwrap();
+        test_sandbox
+            .add_documents(vec![
+                serde_json::json!({"body": "trash", "ts": 1}),
+                serde_json::json!({"body": "treasure", "ts": 2}),
+            ])
+            .await
+            .unwrap();
+        let metastore = test_sandbox.metastore();
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_leaf_search()
+            .times(1)
+            .returning(|leaf_search_request| {
+                assert_eq!(leaf_search_request.split_offsets.len(), 1);
+                assert_eq!(
+                    leaf_search_request.search_request.unwrap().query,
+                    "body:trash"
+                );
+                Ok(LeafSearchResponse {
+                    num_hits: 1,
+                    ..Default::default()
+                })
+            });
+        let delete_query_api_handlers =
+            super::delete_task_api_handlers(metastore, Arc::new(mock_search_service))
+                .recover(recover_fn);
+
+        // Dry-run a delete by query.
+        let resp = warp::test::request()
+            .path("/test-delete-by-query-rest/delete-by-query?dry_run=true")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"query": "body:trash"}"#)
+            .reply(&delete_query_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let dry_run_response: DeleteByQueryDryRunResponse =
+            serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(dry_run_response.num_matched_docs, 1);
+        assert_eq!(dry_run_response.splits.len(), 1);
+        assert_eq!(dry_run_response.splits[0].num_matched_docs, 1);
+
+        // Delete by query.
+        let resp = warp::test::request()
+            .path("/test-delete-by-query-rest/delete-by-query")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"query": "body:trash"}"#)
+            .reply(&delete_query_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let created_delete_task: DeleteTask = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(created_delete_task.opstamp, 1);
+
+        // GET the delete task status.
+        let resp = warp::test::request()
+            .path("/test-delete-by-query-rest/delete-tasks/1")
+            .reply(&delete_query_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let delete_task_status: DeleteTaskStatus = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(delete_task_status.delete_task, created_delete_task);
+        assert_eq!(delete_task_status.state, DeleteTaskState::Pending);
+        assert_eq!(delete_task_status.num_published_splits, 1);
+        assert_eq!(delete_task_status.num_pending_splits, 1);
+
+        // GET the status of a delete task that does not exist.
+        let resp = warp::test::request()
+            .path("/test-delete-by-query-rest/delete-tasks/2")
+            .reply(&delete_query_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+        test_sandbox.assert_quit().await;
+    }
 }
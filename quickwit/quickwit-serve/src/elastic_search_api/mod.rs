@@ -20,24 +20,48 @@
 mod api_specs;
 mod rest_handler;
 
+use std::sync::Arc;
+
+use quickwit_cluster::Cluster;
+use quickwit_metastore::Metastore;
 use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
 use self::rest_handler::{
-    elastic_get_index_search_handler, elastic_get_search_handler,
+    elastic_cat_health_handler, elastic_cat_indices_handler, elastic_get_index_search_handler,
+    elastic_get_search_handler, elastic_index_mapping_handler, elastic_index_settings_handler,
     elastic_post_index_search_handler, elastic_post_search_handler,
 };
 
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    api_specs::elastic_get_search_filter,
+    api_specs::elastic_post_search_filter,
+    api_specs::elastic_get_index_search_filter,
+    api_specs::elastic_post_index_search_filter,
+    rest_handler::elastic_cat_indices_handler,
+    rest_handler::elastic_cat_health_handler,
+    rest_handler::elastic_index_mapping_handler,
+    rest_handler::elastic_index_settings_handler,
+))]
+pub struct ElasticCompatibleApi;
+
 /// Setup Elasticsearch API handlers
 ///
 /// This is where all newly supported Elasticsearch handlers
 /// should be registered.
 pub fn elastic_api_handlers(
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     elastic_get_search_handler()
         .or(elastic_post_search_handler())
         .or(elastic_get_index_search_handler())
         .or(elastic_post_index_search_handler())
+        .or(elastic_cat_indices_handler(metastore.clone()))
+        .or(elastic_cat_health_handler(cluster, metastore.clone()))
+        .or(elastic_index_mapping_handler(metastore.clone()))
+        .or(elastic_index_settings_handler(metastore))
     // Register newly created handlers here.
 }
 
@@ -17,13 +17,22 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use quickwit_cluster::Cluster;
 use quickwit_common::simple_list::SimpleList;
+use quickwit_doc_mapper::ModeType;
+use quickwit_metastore::{IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, SplitState};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
 use warp::{Filter, Rejection};
 
 use super::api_specs::{
     elastic_get_index_search_filter, elastic_get_search_filter, elastic_post_index_search_filter,
     elastic_post_search_filter, SearchQueryParams,
 };
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
 
 /// GET _elastic/_search
 pub fn elastic_get_search_handler(
@@ -80,3 +89,425 @@ pub fn elastic_post_index_search_handler(
         },
     )
 }
+
+#[utoipa::path(
+    get,
+    tag = "Elasticsearch compatible API",
+    path = "/_cat/indices",
+    responses(
+        (status = 200, description = "Successfully listed the indexes.")
+    ),
+)]
+/// GET _elastic/_cat/indices
+pub fn elastic_cat_indices_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_cat" / "indices")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(elastic_cat_indices)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Elasticsearch compatible API",
+    path = "/_cat/health",
+    responses(
+        (status = 200, description = "Successfully fetched the cluster health.")
+    ),
+)]
+/// GET _elastic/_cat/health
+pub fn elastic_cat_health_handler(
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / "_cat" / "health")
+        .and(warp::get())
+        .and(with_arg(cluster))
+        .and(with_arg(metastore))
+        .then(elastic_cat_health)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Elasticsearch compatible API",
+    path = "/{index}/_mapping",
+    responses(
+        (status = 200, description = "Successfully fetched the index mapping.")
+    ),
+    params(
+        ("index" = String, Path, description = "The index ID."),
+    ),
+)]
+/// GET _elastic/{index}/_mapping
+pub fn elastic_index_mapping_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_mapping")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(elastic_index_mapping)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Elasticsearch compatible API",
+    path = "/{index}/_settings",
+    responses(
+        (status = 200, description = "Successfully fetched the index settings.")
+    ),
+    params(
+        ("index" = String, Path, description = "The index ID."),
+    ),
+)]
+/// GET _elastic/{index}/_settings
+pub fn elastic_index_settings_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_elastic" / String / "_settings")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(elastic_index_settings)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+/// Returns the number of documents and the size in bytes of the published splits of an index.
+async fn published_docs_stats(
+    index_id: &str,
+    metastore: &dyn Metastore,
+) -> Result<(u64, u64), MetastoreError> {
+    let query = ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+    let splits = metastore.list_splits(query).await?;
+    let num_docs = splits
+        .iter()
+        .map(|split| split.split_metadata.num_docs as u64)
+        .sum();
+    let num_bytes = splits
+        .iter()
+        .map(|split| split.split_metadata.footer_offsets.end)
+        .sum();
+    Ok((num_docs, num_bytes))
+}
+
+/// Quickwit indexes are always available for search, and have neither shards nor replicas in the
+/// Elasticsearch sense: each index is reported as a healthy index with a single primary shard.
+async fn elastic_cat_indices(metastore: Arc<dyn Metastore>) -> Result<JsonValue, MetastoreError> {
+    let mut indexes_metadatas = metastore.list_indexes_metadatas().await?;
+    indexes_metadatas.sort_by(|left, right| left.index_id().cmp(right.index_id()));
+    let mut cat_indices = Vec::with_capacity(indexes_metadatas.len());
+    for index_metadata in indexes_metadatas {
+        let index_id = index_metadata.index_id();
+        let (num_docs, num_bytes) = published_docs_stats(index_id, &*metastore).await?;
+        cat_indices.push(json!({
+            "health": "green",
+            "status": "open",
+            "index": index_id,
+            "uuid": index_id,
+            "pri": "1",
+            "rep": "0",
+            "docs.count": num_docs.to_string(),
+            "docs.deleted": "0",
+            "store.size": num_bytes.to_string(),
+            "pri.store.size": num_bytes.to_string(),
+        }));
+    }
+    Ok(JsonValue::Array(cat_indices))
+}
+
+async fn elastic_cat_health(
+    cluster: Arc<Cluster>,
+    metastore: Arc<dyn Metastore>,
+) -> Result<JsonValue, MetastoreError> {
+    let num_indexes = metastore.list_indexes_metadatas().await?.len();
+    let num_nodes = cluster.ready_members().await.len();
+    let status = if cluster.is_self_node_ready().await {
+        "green"
+    } else {
+        "red"
+    };
+    let epoch_secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let secs_of_day = epoch_secs % 86_400;
+    Ok(json!([{
+        "epoch": epoch_secs.to_string(),
+        "timestamp": format!(
+            "{:02}:{:02}:{:02}",
+            secs_of_day / 3_600,
+            secs_of_day % 3_600 / 60,
+            secs_of_day % 60
+        ),
+        "cluster": cluster.cluster_id,
+        "status": status,
+        "node.total": num_nodes.to_string(),
+        "node.data": num_nodes.to_string(),
+        "shards": num_indexes.to_string(),
+        "pri": num_indexes.to_string(),
+        "relo": "0",
+        "init": "0",
+        "unassign": "0",
+        "pending_tasks": "0",
+        "max_task_wait_time": "-",
+        "active_shards_percent": "100.0%",
+    }]))
+}
+
+async fn elastic_index_mapping(
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<JsonValue, MetastoreError> {
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let mappings = elastic_mappings(&index_metadata);
+    Ok(json!({ index_id: { "mappings": mappings } }))
+}
+
+async fn elastic_index_settings(
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<JsonValue, MetastoreError> {
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let creation_date_millis = index_metadata.create_timestamp * 1_000;
+    Ok(json!({
+        index_id.clone(): {
+            "settings": {
+                "index": {
+                    "number_of_shards": "1",
+                    "number_of_replicas": "0",
+                    "uuid": index_id,
+                    "provided_name": index_id,
+                    "creation_date": creation_date_millis.to_string(),
+                }
+            }
+        }
+    }))
+}
+
+/// Translates the doc mapping of an index into an Elasticsearch mapping.
+fn elastic_mappings(index_metadata: &IndexMetadata) -> JsonValue {
+    let doc_mapping = &index_metadata.index_config.doc_mapping;
+    let field_mappings: Vec<JsonValue> = doc_mapping
+        .field_mappings
+        .iter()
+        .map(|field_mapping| {
+            serde_json::to_value(field_mapping).expect("Field mappings should be serializable.")
+        })
+        .collect();
+    let dynamic = match doc_mapping.mode {
+        ModeType::Dynamic => JsonValue::from(true),
+        ModeType::Lenient => JsonValue::from(false),
+        ModeType::Strict => JsonValue::from("strict"),
+    };
+    json!({
+        "dynamic": dynamic,
+        "properties": elastic_properties(&field_mappings),
+    })
+}
+
+fn elastic_properties(field_mappings: &[JsonValue]) -> JsonMap<String, JsonValue> {
+    let mut properties = JsonMap::new();
+    for field_mapping in field_mappings {
+        let Some(field_name) = field_mapping.get("name").and_then(JsonValue::as_str) else {
+            continue;
+        };
+        let type_id = field_mapping
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default();
+        let type_id = type_id
+            .strip_prefix("array<")
+            .and_then(|type_id| type_id.strip_suffix('>'))
+            .unwrap_or(type_id);
+        let sub_field_mappings = field_mapping
+            .get("field_mappings")
+            .and_then(JsonValue::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let property = match type_id {
+            "text" => {
+                let tokenizer_opt = field_mapping.get("tokenizer").and_then(JsonValue::as_str);
+                if tokenizer_opt == Some("raw") {
+                    json!({"type": "keyword"})
+                } else {
+                    json!({"type": "text"})
+                }
+            }
+            "i64" => json!({"type": "long"}),
+            "u64" => json!({"type": "unsigned_long"}),
+            "f64" => json!({"type": "double"}),
+            "bool" => json!({"type": "boolean"}),
+            "ip" => json!({"type": "ip"}),
+            "datetime" => json!({"type": "date"}),
+            "bytes" => json!({"type": "binary"}),
+            "json" => json!({"type": "object", "dynamic": true}),
+            "geo_point" => json!({"type": "geo_point"}),
+            "object" => json!({ "properties": elastic_properties(sub_field_mappings) }),
+            "nested" => {
+                json!({"type": "nested", "properties": elastic_properties(sub_field_mappings)})
+            }
+            _ => {
+                if let Some(dims_str) = type_id
+                    .strip_prefix("vector<f32, ")
+                    .and_then(|type_id| type_id.strip_suffix('>'))
+                {
+                    json!({"type": "dense_vector", "dims": dims_str.parse::<usize>().ok()})
+                } else {
+                    continue;
+                }
+            }
+        };
+        properties.insert(field_name.to_string(), property);
+    }
+    properties
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use quickwit_metastore::{
+        IndexMetadata, ListSplitsQuery, MetastoreError, MockMetastore, Split, SplitMetadata,
+        SplitState,
+    };
+    use serde_json::{json, Value as JsonValue};
+    use warp::Filter;
+
+    use super::{
+        elastic_cat_indices_handler, elastic_index_mapping_handler, elastic_index_settings_handler,
+    };
+    use crate::recover_fn;
+
+    fn mock_split(split_id: &str) -> Split {
+        Split {
+            split_state: SplitState::Published,
+            split_metadata: SplitMetadata {
+                split_id: split_id.to_string(),
+                num_docs: 10,
+                footer_offsets: 700..800,
+                ..Default::default()
+            },
+            update_timestamp: 0,
+            publish_timestamp: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_elastic_cat_indices() {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_indexes_metadatas().return_once(|| {
+            Ok(vec![
+                IndexMetadata::for_test("test-index-2", "ram:///indexes/test-index-2"),
+                IndexMetadata::for_test("test-index-1", "ram:///indexes/test-index-1"),
+            ])
+        });
+        metastore
+            .expect_list_splits()
+            .returning(|list_splits_query: ListSplitsQuery| {
+                assert_eq!(list_splits_query.split_states, vec![SplitState::Published]);
+                if list_splits_query.index_id == "test-index-1" {
+                    return Ok(vec![mock_split("split-1"), mock_split("split-2")]);
+                }
+                Ok(Vec::new())
+            });
+        let handler = elastic_cat_indices_handler(Arc::new(metastore)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_elastic/_cat/indices?format=json")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let cat_indices: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(cat_indices.as_array().unwrap().len(), 2);
+        assert_eq!(cat_indices[0]["index"], "test-index-1");
+        assert_eq!(cat_indices[0]["health"], "green");
+        assert_eq!(cat_indices[0]["docs.count"], "20");
+        assert_eq!(cat_indices[0]["store.size"], "1600");
+        assert_eq!(cat_indices[1]["index"], "test-index-2");
+        assert_eq!(cat_indices[1]["docs.count"], "0");
+    }
+
+    #[tokio::test]
+    async fn test_elastic_index_mapping() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                if index_id == "test-index" {
+                    return Ok(IndexMetadata::for_test(
+                        "test-index",
+                        "ram:///indexes/test-index",
+                    ));
+                }
+                Err(MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                })
+            });
+        let handler = elastic_index_mapping_handler(Arc::new(metastore)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_elastic/test-index/_mapping")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let mapping: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_mapping = json!({
+            "test-index": {
+                "mappings": {
+                    "dynamic": false,
+                    "properties": {
+                        "timestamp": {"type": "date"},
+                        "body": {"type": "text"},
+                        "response_date": {"type": "date"},
+                        "response_time": {"type": "double"},
+                        "response_payload": {"type": "binary"},
+                        "owner": {"type": "keyword"},
+                        "attributes": {
+                            "properties": {
+                                "tags": {"type": "long"},
+                                "server": {"type": "text"},
+                                "server.status": {"type": "text"},
+                                "server.payload": {"type": "binary"},
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        assert_eq!(mapping, expected_mapping);
+
+        let resp = warp::test::request()
+            .path("/_elastic/unknown-index/_mapping")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_elastic_index_settings() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .return_once(|_index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+                index_metadata.create_timestamp = 1_680_000_000;
+                Ok(index_metadata)
+            });
+        let handler = elastic_index_settings_handler(Arc::new(metastore)).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_elastic/test-index/_settings")
+            .reply(&handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let settings: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let index_settings = &settings["test-index"]["settings"]["index"];
+        assert_eq!(index_settings["number_of_shards"], "1");
+        assert_eq!(index_settings["provided_name"], "test-index");
+        assert_eq!(index_settings["creation_date"], "1680000000000");
+    }
+}
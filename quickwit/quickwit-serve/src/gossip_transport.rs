@@ -0,0 +1,283 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+//! Gossip transport used when mutual TLS is enabled.
+//!
+//! Chitchat gossips over UDP by default. With mutual TLS, the messages are exchanged over TLS
+//! connections instead, on a TCP port bound to the gossip listen address. Each message is framed
+//! with its length. A node replies to a message on the connection it was received on, so the
+//! address a message is received from is the address of the connection rather than the gossip
+//! address of its sender. As with UDP, messages are dropped rather than queued indefinitely when
+//! a peer is unreachable or slow.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use chitchat::transport::{Socket, Transport};
+use chitchat::{ChitchatMessage, Serializable};
+use quickwit_grpc_clients::tls::{connect_tls, is_peer_allowed, tls_acceptor};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsStream;
+use tracing::*;
+
+/// Maximum length of a gossip message. Chitchat keeps its messages under the UDP payload limit,
+/// so larger frames can only come from a misbehaving peer.
+const MAX_MESSAGE_LEN: usize = 65_507;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of messages buffered per connection before messages are dropped.
+const CONNECTION_QUEUE_CAPACITY: usize = 32;
+
+const RECV_QUEUE_CAPACITY: usize = 1_024;
+
+/// Senders of the framed messages to write on each connection, keyed by peer address.
+type Connections = Arc<Mutex<HashMap<SocketAddr, mpsc::Sender<Vec<u8>>>>>;
+
+/// Chitchat transport exchanging gossip messages over mutual TLS connections.
+pub(crate) struct TlsTransport;
+
+#[async_trait]
+impl Transport for TlsTransport {
+    async fn open(&self, listen_addr: SocketAddr) -> anyhow::Result<Box<dyn Socket>> {
+        let tcp_listener = TcpListener::bind(listen_addr)
+            .await
+            .with_context(|| format!("Failed to bind gossip TCP listener on `{listen_addr}`."))?;
+        let (message_tx, message_rx) = mpsc::channel(RECV_QUEUE_CAPACITY);
+        let connections = Connections::default();
+        tokio::spawn(accept_loop(
+            tcp_listener,
+            message_tx.clone(),
+            connections.clone(),
+        ));
+        let tls_socket = TlsSocket {
+            message_tx,
+            message_rx,
+            connections,
+        };
+        Ok(Box::new(tls_socket))
+    }
+}
+
+struct TlsSocket {
+    message_tx: mpsc::Sender<(SocketAddr, ChitchatMessage)>,
+    message_rx: mpsc::Receiver<(SocketAddr, ChitchatMessage)>,
+    connections: Connections,
+}
+
+#[async_trait]
+impl Socket for TlsSocket {
+    async fn send(&mut self, to: SocketAddr, message: ChitchatMessage) -> anyhow::Result<()> {
+        let frame = encode_frame(&message)?;
+        let frame_tx = {
+            let mut connections = self.connections.lock().unwrap();
+            match connections.get(&to) {
+                Some(frame_tx) if !frame_tx.is_closed() => frame_tx.clone(),
+                _ => {
+                    let (frame_tx, frame_rx) = mpsc::channel(CONNECTION_QUEUE_CAPACITY);
+                    connections.insert(to, frame_tx.clone());
+                    tokio::spawn(connect_and_run(
+                        to,
+                        frame_rx,
+                        self.message_tx.clone(),
+                        self.connections.clone(),
+                    ));
+                    frame_tx
+                }
+            }
+        };
+        if frame_tx.try_send(frame).is_err() {
+            debug!(peer_addr=%to, "Dropped gossip message.");
+        }
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> anyhow::Result<(SocketAddr, ChitchatMessage)> {
+        self.message_rx
+            .recv()
+            .await
+            .context("The gossip listener stopped.")
+    }
+}
+
+async fn accept_loop(
+    tcp_listener: TcpListener,
+    message_tx: mpsc::Sender<(SocketAddr, ChitchatMessage)>,
+    connections: Connections,
+) {
+    loop {
+        let (tcp_stream, peer_addr) = match tcp_listener.accept().await {
+            Ok(tcp_stream_and_peer_addr) => tcp_stream_and_peer_addr,
+            Err(error) => {
+                warn!(error=?error, "Failed to accept gossip connection.");
+                continue;
+            }
+        };
+        if message_tx.is_closed() {
+            break;
+        }
+        // The acceptor is fetched for each connection to pick up reloaded certificates.
+        let Some(tls_acceptor) = tls_acceptor() else {
+            break;
+        };
+        let message_tx = message_tx.clone();
+        let connections = connections.clone();
+        tokio::spawn(async move {
+            let tls_stream = match tls_acceptor.accept(tcp_stream).await {
+                Ok(tls_stream) => tls_stream,
+                Err(error) => {
+                    warn!(peer_addr=%peer_addr, error=?error, "Gossip TLS handshake failed.");
+                    return;
+                }
+            };
+            if !is_peer_allowed(tls_stream.get_ref().1.peer_certificates()) {
+                warn!(peer_addr=%peer_addr, "Rejected gossip peer with unexpected SPIFFE ID.");
+                return;
+            }
+            let (frame_tx, frame_rx) = mpsc::channel(CONNECTION_QUEUE_CAPACITY);
+            connections.lock().unwrap().insert(peer_addr, frame_tx);
+            run_connection(TlsStream::from(tls_stream), peer_addr, frame_rx, message_tx).await;
+            remove_closed_connections(&connections);
+        });
+    }
+}
+
+async fn connect_and_run(
+    peer_addr: SocketAddr,
+    frame_rx: mpsc::Receiver<Vec<u8>>,
+    message_tx: mpsc::Sender<(SocketAddr, ChitchatMessage)>,
+    connections: Connections,
+) {
+    match tokio::time::timeout(CONNECT_TIMEOUT, connect_tls(peer_addr)).await {
+        Ok(Ok(tls_stream)) => {
+            run_connection(TlsStream::from(tls_stream), peer_addr, frame_rx, message_tx).await;
+        }
+        Ok(Err(error)) => {
+            debug!(peer_addr=%peer_addr, error=?error, "Failed to connect to gossip peer.");
+            drop(frame_rx);
+        }
+        Err(_) => {
+            debug!(peer_addr=%peer_addr, "Timed out connecting to gossip peer.");
+            drop(frame_rx);
+        }
+    }
+    remove_closed_connections(&connections);
+}
+
+/// Forwards the messages read from the connection to the socket and writes the frames sent by
+/// the socket, until either side of the connection fails or the socket is dropped.
+async fn run_connection(
+    tls_stream: TlsStream<TcpStream>,
+    peer_addr: SocketAddr,
+    mut frame_rx: mpsc::Receiver<Vec<u8>>,
+    message_tx: mpsc::Sender<(SocketAddr, ChitchatMessage)>,
+) {
+    let (mut reader, mut writer) = tokio::io::split(tls_stream);
+    let read_loop = async {
+        loop {
+            let message = read_message(&mut reader).await?;
+            if message_tx.send((peer_addr, message)).await.is_err() {
+                return anyhow::Ok(());
+            }
+        }
+    };
+    let write_loop = async {
+        while let Some(frame) = frame_rx.recv().await {
+            write_frame(&mut writer, &frame).await?;
+        }
+        anyhow::Ok(())
+    };
+    let connection_result = tokio::select! {
+        read_result = read_loop => read_result,
+        write_result = write_loop => write_result,
+    };
+    if let Err(error) = connection_result {
+        debug!(peer_addr=%peer_addr, error=?error, "Gossip connection closed.");
+    }
+}
+
+fn remove_closed_connections(connections: &Connections) {
+    connections
+        .lock()
+        .unwrap()
+        .retain(|_, frame_tx| !frame_tx.is_closed());
+}
+
+fn encode_frame(message: &ChitchatMessage) -> anyhow::Result<Vec<u8>> {
+    let mut frame = vec![0u8; 4];
+    message.serialize(&mut frame);
+    let message_len = frame.len() - 4;
+    if message_len > MAX_MESSAGE_LEN {
+        bail!("Gossip message of {message_len} bytes exceeds the maximum length.");
+    }
+    frame[..4].copy_from_slice(&(message_len as u32).to_be_bytes());
+    Ok(frame)
+}
+
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, frame: &[u8]) -> anyhow::Result<()> {
+    writer.write_all(frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> anyhow::Result<ChitchatMessage> {
+    let message_len = reader.read_u32().await? as usize;
+    if message_len > MAX_MESSAGE_LEN {
+        bail!("Gossip message of {message_len} bytes exceeds the maximum length.");
+    }
+    let mut message_bytes = vec![0u8; message_len];
+    reader.read_exact(&mut message_bytes).await?;
+    ChitchatMessage::deserialize(&mut &message_bytes[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gossip_message_framing() {
+        let (mut client, mut server) = tokio::io::duplex(1_024);
+        let frame = encode_frame(&ChitchatMessage::BadCluster).unwrap();
+        write_frame(&mut client, &frame).await.unwrap();
+        write_frame(&mut client, &frame).await.unwrap();
+
+        for _ in 0..2 {
+            let message = read_message(&mut server).await.unwrap();
+            assert!(matches!(message, ChitchatMessage::BadCluster));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gossip_message_framing_rejects_oversized_messages() {
+        let (mut client, mut server) = tokio::io::duplex(1_024);
+        let frame_header = (MAX_MESSAGE_LEN as u32 + 1).to_be_bytes();
+        write_frame(&mut client, &frame_header).await.unwrap();
+
+        let error = read_message(&mut server).await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Gossip message of 65508 bytes exceeds the maximum length."
+        );
+    }
+}
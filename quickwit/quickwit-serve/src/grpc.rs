@@ -18,26 +18,40 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeSet;
+use std::io;
 use std::net::SocketAddr;
 
 use quickwit_config::service::QuickwitService;
 use quickwit_control_plane::control_plane_service_grpc_server::ControlPlaneServiceGrpcServer;
 use quickwit_control_plane::ControlPlaneServiceGrpcServerAdapter;
+use quickwit_grpc_clients::tls::{is_grpc_tls_enabled, is_peer_allowed, tls_acceptor};
 use quickwit_indexing::grpc_adapter::GrpcIndexingAdapter;
+use quickwit_ingest_api::ingest_service_grpc_server::IngestServiceGrpcServer;
+use quickwit_ingest_api::IngestServiceGrpcServerAdapter;
 use quickwit_jaeger::JaegerService;
-use quickwit_metastore::GrpcMetastoreAdapter;
-use quickwit_opentelemetry::otlp::{OtlpGrpcLogsService, OtlpGrpcTraceService};
+use quickwit_metastore::{ApiKeyScope, GrpcMetastoreAdapter};
+use quickwit_opentelemetry::otlp::{
+    OtlpGrpcLogsService, OtlpGrpcMetricsService, OtlpGrpcTraceService, OTEL_LOGS_INDEX_ID,
+    OTEL_METRICS_INDEX_ID, OTEL_TRACE_INDEX_ID,
+};
 use quickwit_proto::indexing_api::indexing_service_server::IndexingServiceServer;
+use quickwit_proto::jaeger::storage::v1::dependencies_reader_plugin_server::DependenciesReaderPluginServer;
 use quickwit_proto::jaeger::storage::v1::span_reader_plugin_server::SpanReaderPluginServer;
 use quickwit_proto::metastore_api::metastore_api_service_server::MetastoreApiServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::logs::v1::logs_service_server::LogsServiceServer;
+use quickwit_proto::opentelemetry::proto::collector::metrics::v1::metrics_service_server::MetricsServiceServer;
 use quickwit_proto::opentelemetry::proto::collector::trace::v1::trace_service_server::TraceServiceServer;
 use quickwit_proto::search_service_server::SearchServiceServer;
 use quickwit_proto::tonic;
-use quickwit_proto::tonic::codegen::CompressionEncoding;
+use quickwit_proto::tonic::codegen::{CompressionEncoding, InterceptedService};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::server::TlsStream;
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::transport::Server;
 use tracing::*;
 
+use crate::api_key_api::{grpc_auth_interceptor, grpc_cluster_auth_interceptor};
 use crate::search_api::GrpcSearchAdapter;
 use crate::QuickwitServices;
 
@@ -48,34 +62,61 @@ pub(crate) async fn start_grpc_server(
 ) -> anyhow::Result<()> {
     let mut enabled_grpc_services = BTreeSet::new();
     let mut server = Server::builder();
+    // The internal services are only called by the other nodes of the cluster.
+    let cluster_auth_interceptor =
+        grpc_cluster_auth_interceptor(services.api_key_authenticator.clone());
 
     // Mount gRPC metastore service if `QuickwitService::Metastore` is enabled on node.
     let metastore_grpc_service = if services.services.contains(&QuickwitService::Metastore) {
         enabled_grpc_services.insert("metastore");
         let metastore = services.metastore.clone();
         let grpc_metastore = GrpcMetastoreAdapter::from(metastore);
-        Some(MetastoreApiServiceServer::new(grpc_metastore))
+        Some(InterceptedService::new(
+            MetastoreApiServiceServer::new(grpc_metastore),
+            cluster_auth_interceptor.clone(),
+        ))
     } else {
         None
     };
-    // Mount gRPC indexing service if `QuickwitService::Indexer` is enabled on node.
-    let indexing_grpc_service = if services.services.contains(&QuickwitService::Indexer) {
+    // Mount gRPC indexing service if `QuickwitService::Indexer` or `QuickwitService::Merger` is
+    // enabled on node.
+    let indexing_grpc_service = if services.services.contains(&QuickwitService::Indexer)
+        || services.services.contains(&QuickwitService::Merger)
+    {
         if let Some(indexing_service) = services.indexing_service.as_ref() {
             enabled_grpc_services.insert("indexing");
             let grpc_indexing = GrpcIndexingAdapter::from(indexing_service.clone());
-            Some(IndexingServiceServer::new(grpc_indexing))
+            Some(InterceptedService::new(
+                IndexingServiceServer::new(grpc_indexing),
+                cluster_auth_interceptor.clone(),
+            ))
         } else {
             None
         }
     } else {
         None
     };
+    // Mount gRPC ingest service if `QuickwitService::Indexer` is enabled on node. The other nodes
+    // forward their ingest requests and replicate the documents of their leader through it.
+    let ingest_grpc_service = if services.services.contains(&QuickwitService::Indexer) {
+        enabled_grpc_services.insert("ingest");
+        let grpc_ingest = IngestServiceGrpcServerAdapter::new(services.ingest_service.clone());
+        Some(InterceptedService::new(
+            IngestServiceGrpcServer::new(grpc_ingest),
+            cluster_auth_interceptor.clone(),
+        ))
+    } else {
+        None
+    };
     // Mount gRPC control plane service if `QuickwitService::ControlPlane` is enabled on node.
     let control_plane_grpc_service = if services.services.contains(&QuickwitService::ControlPlane) {
         if let Some(control_plane_client) = &services.control_plane_client {
             enabled_grpc_services.insert("control-plane");
             let adapter = ControlPlaneServiceGrpcServerAdapter::new(control_plane_client.clone());
-            Some(ControlPlaneServiceGrpcServer::new(adapter))
+            Some(InterceptedService::new(
+                ControlPlaneServiceGrpcServer::new(adapter),
+                cluster_auth_interceptor,
+            ))
         } else {
             None
         }
@@ -90,9 +131,20 @@ pub(crate) async fn start_grpc_server(
     {
         enabled_grpc_services.insert("otlp-trace");
         let ingest_service = services.ingest_service.clone();
-        let trace_service = TraceServiceServer::new(OtlpGrpcTraceService::new(ingest_service))
+        let otlp_trace_service = match &services.config.indexer_config.trace_sampling {
+            Some(trace_sampling_config) => {
+                OtlpGrpcTraceService::with_sampling(ingest_service, trace_sampling_config.clone())
+            }
+            None => OtlpGrpcTraceService::new(ingest_service),
+        };
+        let trace_service = TraceServiceServer::new(otlp_trace_service)
             .accept_compressed(CompressionEncoding::Gzip);
-        Some(trace_service)
+        let auth_interceptor = grpc_auth_interceptor(
+            services.api_key_authenticator.clone(),
+            ApiKeyScope::Ingest,
+            OTEL_TRACE_INDEX_ID,
+        );
+        Some(InterceptedService::new(trace_service, auth_interceptor))
     } else {
         None
     };
@@ -103,7 +155,29 @@ pub(crate) async fn start_grpc_server(
         let ingest_service = services.ingest_service.clone();
         let logs_service = LogsServiceServer::new(OtlpGrpcLogsService::new(ingest_service))
             .accept_compressed(CompressionEncoding::Gzip);
-        Some(logs_service)
+        let auth_interceptor = grpc_auth_interceptor(
+            services.api_key_authenticator.clone(),
+            ApiKeyScope::Ingest,
+            OTEL_LOGS_INDEX_ID,
+        );
+        Some(InterceptedService::new(logs_service, auth_interceptor))
+    } else {
+        None
+    };
+    let otlp_metrics_grpc_service = if enable_opentelemetry_otlp_grpc_service
+        && services.services.contains(&QuickwitService::Indexer)
+    {
+        enabled_grpc_services.insert("otlp-metrics");
+        let ingest_service = services.ingest_service.clone();
+        let metrics_service =
+            MetricsServiceServer::new(OtlpGrpcMetricsService::new(ingest_service))
+                .accept_compressed(CompressionEncoding::Gzip);
+        let auth_interceptor = grpc_auth_interceptor(
+            services.api_key_authenticator.clone(),
+            ApiKeyScope::Ingest,
+            OTEL_METRICS_INDEX_ID,
+        );
+        Some(InterceptedService::new(metrics_service, auth_interceptor))
     } else {
         None
     };
@@ -111,33 +185,104 @@ pub(crate) async fn start_grpc_server(
     let search_grpc_service = if services.services.contains(&QuickwitService::Searcher) {
         enabled_grpc_services.insert("search");
         let search_service = services.search_service.clone();
-        let grpc_search_service = GrpcSearchAdapter::from(search_service);
+        // The search service authorizes each method, since it serves both the clients and the
+        // other nodes of the cluster.
+        let grpc_search_service = GrpcSearchAdapter::from(search_service)
+            .with_live_tail(
+                services.metastore.clone(),
+                services.split_publish_notifier.clone(),
+            )
+            .with_authenticator(services.api_key_authenticator.clone());
         Some(SearchServiceServer::new(grpc_search_service))
     } else {
         None
     };
     let enable_jaeger_endpoint = services.config.jaeger_config.enable_endpoint;
-    let jaeger_grpc_service =
+    let (jaeger_grpc_service, jaeger_dependencies_grpc_service) =
         if enable_jaeger_endpoint && services.services.contains(&QuickwitService::Searcher) {
             enabled_grpc_services.insert("jaeger");
             let search_service = services.search_service.clone();
-            Some(SpanReaderPluginServer::new(JaegerService::new(
-                services.config.jaeger_config.clone(),
-                search_service,
-            )))
+            let jaeger_service =
+                JaegerService::new(services.config.jaeger_config.clone(), search_service);
+            jaeger_service.spawn_dependencies_materialization();
+            let auth_interceptor = grpc_auth_interceptor(
+                services.api_key_authenticator.clone(),
+                ApiKeyScope::Search,
+                OTEL_TRACE_INDEX_ID,
+            );
+            let span_reader_service = InterceptedService::new(
+                SpanReaderPluginServer::new(jaeger_service.clone()),
+                auth_interceptor.clone(),
+            );
+            let dependencies_reader_service = InterceptedService::new(
+                DependenciesReaderPluginServer::new(jaeger_service),
+                auth_interceptor,
+            );
+            (Some(span_reader_service), Some(dependencies_reader_service))
         } else {
-            None
+            (None, None)
         };
     let server_router = server
         .add_optional_service(metastore_grpc_service)
         .add_optional_service(control_plane_grpc_service)
         .add_optional_service(indexing_grpc_service)
+        .add_optional_service(ingest_grpc_service)
         .add_optional_service(otlp_log_grpc_service)
+        .add_optional_service(otlp_metrics_grpc_service)
         .add_optional_service(otlp_trace_service)
         .add_optional_service(search_grpc_service)
-        .add_optional_service(jaeger_grpc_service);
+        .add_optional_service(jaeger_grpc_service)
+        .add_optional_service(jaeger_dependencies_grpc_service);
 
     info!(enabled_grpc_services=?enabled_grpc_services, grpc_listen_addr=?grpc_listen_addr, "Starting gRPC server.");
-    server_router.serve(grpc_listen_addr).await?;
+    if is_grpc_tls_enabled() {
+        let tls_incoming = tls_incoming(grpc_listen_addr).await?;
+        server_router.serve_with_incoming(tls_incoming).await?;
+    } else {
+        server_router.serve(grpc_listen_addr).await?;
+    }
     Ok(())
 }
+
+/// Accepts the TCP connections on `grpc_listen_addr` and completes their TLS handshake. The
+/// handshakes are performed concurrently so that a slow client does not hold back the others.
+async fn tls_incoming(
+    grpc_listen_addr: SocketAddr,
+) -> anyhow::Result<ReceiverStream<io::Result<TlsStream<TcpStream>>>> {
+    let tcp_listener = TcpListener::bind(grpc_listen_addr).await?;
+    let (tls_stream_tx, tls_stream_rx) = mpsc::channel(128);
+    tokio::spawn(async move {
+        loop {
+            let (tcp_stream, peer_addr) = match tcp_listener.accept().await {
+                Ok(tcp_stream_and_peer_addr) => tcp_stream_and_peer_addr,
+                Err(error) => {
+                    warn!(error=?error, "Failed to accept gRPC connection.");
+                    continue;
+                }
+            };
+            if tls_stream_tx.is_closed() {
+                break;
+            }
+            // The acceptor is fetched for each connection to pick up reloaded certificates.
+            let Some(tls_acceptor) = tls_acceptor() else {
+                break;
+            };
+            let tls_stream_tx = tls_stream_tx.clone();
+            tokio::spawn(async move {
+                let tls_stream = match tls_acceptor.accept(tcp_stream).await {
+                    Ok(tls_stream) => tls_stream,
+                    Err(error) => {
+                        warn!(peer_addr=%peer_addr, error=?error, "gRPC TLS handshake failed.");
+                        return;
+                    }
+                };
+                if !is_peer_allowed(tls_stream.get_ref().1.peer_certificates()) {
+                    warn!(peer_addr=%peer_addr, "Rejected gRPC client with unexpected SPIFFE ID.");
+                    return;
+                }
+                let _ = tls_stream_tx.send(Ok(tls_stream)).await;
+            });
+        }
+    });
+    Ok(ReceiverStream::new(tls_stream_rx))
+}
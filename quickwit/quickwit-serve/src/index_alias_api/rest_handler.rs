@@ -0,0 +1,482 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use quickwit_config::{validate_identifier, QuickwitConfig, RolloverConditions};
+use quickwit_janitor::error::JanitorError;
+use quickwit_janitor::{rollover_index_alias, RolloverReport};
+use quickwit_metastore::{IndexAlias, IndexAliasAction, Metastore, MetastoreError};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use crate::format::{extract_format_from_qs, make_response};
+use crate::json_body::json_body;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(
+        update_index_aliases,
+        list_index_aliases,
+        get_index_alias,
+        delete_index_alias,
+        rollover_index_alias_endpoint
+    ),
+    components(schemas(UpdateIndexAliasesRequest, RolloverRequest))
+)]
+pub struct IndexAliasApi;
+
+#[derive(Debug, Error)]
+pub enum IndexAliasApiError {
+    #[error("Invalid index alias: {0}")]
+    InvalidAlias(String),
+    #[error("{0}")]
+    Metastore(#[from] MetastoreError),
+    #[error("{0}")]
+    Rollover(#[from] JanitorError),
+}
+
+impl ServiceError for IndexAliasApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::InvalidAlias(_) => ServiceErrorCode::BadRequest,
+            Self::Metastore(metastore_error) => metastore_error.status_code(),
+            Self::Rollover(janitor_error) => janitor_error.status_code(),
+        }
+    }
+}
+
+/// Actions applied atomically to the index aliases.
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct UpdateIndexAliasesRequest {
+    actions: Vec<IndexAliasAction>,
+}
+
+/// Conditions under which the index the alias points to is rolled over.
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct RolloverRequest {
+    /// Without conditions, the alias is rolled over unconditionally.
+    #[serde(default)]
+    conditions: RolloverConditions,
+    /// Evaluates the conditions without rolling the alias over.
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Index aliases management handlers.
+pub(crate) fn index_alias_api_handlers(
+    metastore: Arc<dyn Metastore>,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    update_index_aliases_handler(metastore.clone())
+        .or(list_index_aliases_handler(metastore.clone()))
+        .or(get_index_alias_handler(metastore.clone()))
+        .or(delete_index_alias_handler(metastore.clone()))
+        .or(rollover_index_alias_handler(metastore, quickwit_config))
+}
+
+fn update_index_aliases_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("aliases")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(metastore))
+        .then(update_index_aliases)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Index Aliases",
+    path = "/aliases",
+    request_body = UpdateIndexAliasesRequest,
+    responses(
+        (status = 200, description = "Successfully updated the index aliases.", body = [IndexAlias])
+    ),
+)]
+/// Update Index Aliases
+///
+/// Applies a list of `add` and `remove` actions to the index aliases atomically: either all the
+/// actions are applied or none is. Removing an index from an alias and adding another one in the
+/// same request swaps the index the alias points to without searches ever seeing an empty alias.
+async fn update_index_aliases(
+    update_request: UpdateIndexAliasesRequest,
+    metastore: Arc<dyn Metastore>,
+) -> Result<Vec<IndexAlias>, IndexAliasApiError> {
+    for action in &update_request.actions {
+        if let IndexAliasAction::Add { alias_id, .. } = action {
+            validate_identifier("Index alias ID", alias_id)
+                .map_err(|error| IndexAliasApiError::InvalidAlias(error.to_string()))?;
+        }
+    }
+    info!(actions = ?update_request.actions, "update-index-aliases");
+    metastore
+        .update_index_aliases(update_request.actions)
+        .await?;
+    let index_aliases = metastore.list_index_aliases().await?;
+    Ok(index_aliases)
+}
+
+fn list_index_aliases_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("aliases")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(list_index_aliases)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Index Aliases",
+    path = "/aliases",
+    responses(
+        (status = 200, description = "Successfully fetched the index aliases.", body = [IndexAlias])
+    ),
+)]
+/// List Index Aliases
+async fn list_index_aliases(
+    metastore: Arc<dyn Metastore>,
+) -> Result<Vec<IndexAlias>, IndexAliasApiError> {
+    let index_aliases = metastore.list_index_aliases().await?;
+    Ok(index_aliases)
+}
+
+fn get_index_alias_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("aliases" / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_index_alias)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Index Aliases",
+    path = "/aliases/{alias_id}",
+    responses(
+        (status = 200, description = "Successfully fetched the index alias.", body = IndexAlias)
+    ),
+    params(
+        ("alias_id" = String, Path, description = "The ID of the index alias to get."),
+    )
+)]
+/// Get Index Alias
+async fn get_index_alias(
+    alias_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<IndexAlias, IndexAliasApiError> {
+    let index_alias = metastore
+        .list_index_aliases()
+        .await?
+        .into_iter()
+        .find(|index_alias| index_alias.alias_id == alias_id)
+        .ok_or(MetastoreError::IndexAliasDoesNotExist { alias_id })?;
+    Ok(index_alias)
+}
+
+fn delete_index_alias_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("aliases" / String)
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_index_alias)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Index Aliases",
+    path = "/aliases/{alias_id}",
+    responses(
+        (status = 200, description = "Successfully deleted the index alias.")
+    ),
+    params(
+        ("alias_id" = String, Path, description = "The ID of the index alias to delete."),
+    )
+)]
+/// Delete Index Alias
+///
+/// Deletes an index alias. The indexes it points to are left untouched.
+async fn delete_index_alias(
+    alias_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<(), IndexAliasApiError> {
+    info!(alias_id = %alias_id, "delete-index-alias");
+    let index_alias = get_index_alias(alias_id, metastore.clone()).await?;
+    let actions = index_alias
+        .index_ids
+        .into_iter()
+        .map(|index_id| IndexAliasAction::Remove {
+            alias_id: index_alias.alias_id.clone(),
+            index_id,
+        })
+        .collect();
+    metastore.update_index_aliases(actions).await?;
+    Ok(())
+}
+
+fn rollover_index_alias_handler(
+    metastore: Arc<dyn Metastore>,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("aliases" / String / "rollover")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(metastore))
+        .and(with_arg(quickwit_config))
+        .then(rollover_index_alias_endpoint)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Index Aliases",
+    path = "/aliases/{alias_id}/rollover",
+    request_body = RolloverRequest,
+    responses(
+        (status = 200, description = "Successfully evaluated the rollover conditions.", body = RolloverReport)
+    ),
+    params(
+        ("alias_id" = String, Path, description = "The ID of the write alias to roll over."),
+    )
+)]
+/// Roll Over Index Alias
+///
+/// Rolls the write alias over to a new index if the index it points to meets any of the rollover
+/// conditions. The new index is named after the current one with its sequence number incremented
+/// (`logs-000002` succeeds `logs-000001`) and is created from the matching index template, or
+/// from the config of the current index if no template matches. The alias is swapped to the new
+/// index atomically, and the previous indexes of the series remain searchable with an index ID
+/// pattern such as `logs-*`.
+///
+/// If the alias does not exist yet, the first index of the series, `<alias_id>-000001`, is
+/// created from the matching index template and the alias is pointed at it.
+async fn rollover_index_alias_endpoint(
+    alias_id: String,
+    rollover_request: RolloverRequest,
+    metastore: Arc<dyn Metastore>,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> Result<RolloverReport, IndexAliasApiError> {
+    validate_identifier("Index alias ID", &alias_id)
+        .map_err(|error| IndexAliasApiError::InvalidAlias(error.to_string()))?;
+    info!(
+        alias_id = %alias_id,
+        conditions = ?rollover_request.conditions,
+        dry_run = rollover_request.dry_run,
+        "rollover-index-alias"
+    );
+    let rollover_report = rollover_index_alias(
+        &*metastore,
+        &alias_id,
+        &rollover_request.conditions,
+        &quickwit_config.default_index_root_uri,
+        rollover_request.dry_run,
+    )
+    .await?;
+    Ok(rollover_report)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{load_index_template_from_user_config, ConfigFormat, IndexConfig};
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_index_alias_api() {
+        let metastore = metastore_for_test();
+        for index_id in ["logs-v1", "logs-v2"] {
+            let index_uri = format!("ram:///indexes/{index_id}");
+            let index_config = IndexConfig::for_test(index_id, &index_uri);
+            metastore.create_index(index_config).await.unwrap();
+        }
+        let index_alias_api_handler =
+            index_alias_api_handlers(metastore.clone(), Arc::new(QuickwitConfig::for_test()))
+                .recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/aliases")
+            .method("POST")
+            .json(&serde_json::json!({
+                "actions": [{"add": {"alias_id": "logs-current", "index_id": "logs-v1"}}]
+            }))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/aliases")
+            .method("POST")
+            .json(&serde_json::json!({
+                "actions": [
+                    {"remove": {"alias_id": "logs-current", "index_id": "logs-v1"}},
+                    {"add": {"alias_id": "logs-current", "index_id": "logs-v2"}},
+                ]
+            }))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_aliases: Vec<IndexAlias> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            index_aliases,
+            [IndexAlias {
+                alias_id: "logs-current".to_string(),
+                index_ids: vec!["logs-v2".to_string()],
+            }]
+        );
+
+        let resp = warp::test::request()
+            .path("/aliases")
+            .method("POST")
+            .json(&serde_json::json!({
+                "actions": [{"add": {"alias_id": "logs current", "index_id": "logs-v1"}}]
+            }))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/aliases")
+            .method("POST")
+            .json(&serde_json::json!({
+                "actions": [{"add": {"alias_id": "logs-current", "index_id": "logs-v3"}}]
+            }))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = warp::test::request()
+            .path("/aliases/logs-current")
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_alias: IndexAlias = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(index_alias.index_ids, ["logs-v2"]);
+
+        let resp = warp::test::request()
+            .path("/aliases/logs-current")
+            .method("DELETE")
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert!(metastore.list_index_aliases().await.unwrap().is_empty());
+
+        let resp = warp::test::request()
+            .path("/aliases/logs-current")
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_rollover_index_alias_api() {
+        let metastore = metastore_for_test();
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping: {}
+        "#;
+        let index_template = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap();
+        metastore
+            .create_index_template(index_template, false)
+            .await
+            .unwrap();
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let index_alias_api_handler =
+            index_alias_api_handlers(metastore.clone(), Arc::new(quickwit_config))
+                .recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/aliases/logs/rollover")
+            .method("POST")
+            .json(&serde_json::json!({}))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let rollover_report: RolloverReport = serde_json::from_slice(resp.body()).unwrap();
+        assert!(rollover_report.rolled_over);
+        assert_eq!(rollover_report.new_index_id, "logs-000001");
+
+        let resp = warp::test::request()
+            .path("/aliases/logs/rollover")
+            .method("POST")
+            .json(&serde_json::json!({"conditions": {"max_docs": 1000}}))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let rollover_report: RolloverReport = serde_json::from_slice(resp.body()).unwrap();
+        assert!(!rollover_report.rolled_over);
+
+        let resp = warp::test::request()
+            .path("/aliases/logs/rollover")
+            .method("POST")
+            .json(&serde_json::json!({"conditions": {"max_age": "0s"}, "dry_run": true}))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let rollover_report: RolloverReport = serde_json::from_slice(resp.body()).unwrap();
+        assert!(rollover_report.rolled_over);
+        assert_eq!(rollover_report.met_conditions, ["max_age"]);
+        assert!(!metastore.index_exists("logs-000002").await.unwrap());
+
+        let resp = warp::test::request()
+            .path("/aliases/logs/rollover")
+            .method("POST")
+            .json(&serde_json::json!({"conditions": {"max_age": "0s"}}))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_aliases = metastore.list_index_aliases().await.unwrap();
+        assert_eq!(index_aliases[0].index_ids, ["logs-000002"]);
+
+        let resp = warp::test::request()
+            .path("/aliases/metrics/rollover")
+            .method("POST")
+            .json(&serde_json::json!({}))
+            .reply(&index_alias_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+}
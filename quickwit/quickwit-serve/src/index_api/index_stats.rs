@@ -0,0 +1,465 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use itertools::Itertools;
+use quickwit_actors::Mailbox;
+use quickwit_indexing::actors::{IndexingPipelineStatus, IndexingService};
+use quickwit_indexing::merge_policy::merge_policy_from_settings;
+use quickwit_indexing::models::ListPipelines;
+use quickwit_metastore::{
+    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, Split, SplitMetadata, SplitState,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tracing::{info, warn};
+use warp::{Filter, Rejection};
+
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(index_stats),
+    components(schemas(IndexOperationalStats, MergeBacklog, SourceStats, SourcePipelinesStats))
+)]
+pub struct IndexStatsApi;
+
+/// Operational statistics of an index, aggregated from the metastore and from the indexing
+/// pipelines running on the node serving the request.
+#[derive(Debug, Serialize, Deserialize, PartialEq, utoipa::ToSchema)]
+pub struct IndexOperationalStats {
+    pub index_id: String,
+    /// Number of documents in the published splits.
+    pub num_published_docs: u64,
+    /// Size of the documents of the published splits before indexing, in bytes.
+    pub num_published_bytes: u64,
+    /// Number of splits per split state.
+    pub num_splits_by_state: BTreeMap<String, usize>,
+    /// Size of the split files of the index in bytes, whatever their state.
+    pub storage_size_bytes: u64,
+    pub merge_backlog: MergeBacklog,
+    /// Statistics of the sources of the index, and of the sources that are gone but still have
+    /// splits.
+    pub sources: Vec<SourceStats>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+pub struct MergeBacklog {
+    /// Number of published splits that the merge policy may still merge.
+    pub num_immature_splits: usize,
+    /// Number of merges the merge policy would plan on the published splits right now.
+    pub num_pending_merges: usize,
+    /// Number of merges in progress on the node.
+    pub num_ongoing_merges: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+pub struct SourceStats {
+    pub source_id: String,
+    /// Whether the source is configured on the index and enabled.
+    pub enabled: bool,
+    /// Number of documents in the published splits of the source.
+    pub num_published_docs: u64,
+    pub num_published_splits: usize,
+    /// Time of the last publication of a split of the source, as a Unix timestamp in seconds.
+    pub last_publish_timestamp: Option<i64>,
+    /// Seconds elapsed since the timestamp of the most recent published document of the source.
+    /// Only available for indexes with a timestamp field.
+    pub ingest_lag_secs: Option<i64>,
+    /// Statistics of the pipelines of the source running on the node, if any.
+    pub pipelines: Option<SourcePipelinesStats>,
+}
+
+/// Statistics accumulated by the indexing pipelines of a source since they were started on the
+/// node.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+pub struct SourcePipelinesStats {
+    pub num_pipelines: usize,
+    pub num_paused_pipelines: usize,
+    /// Number of documents processed, valid or not.
+    pub num_docs_processed: u64,
+    pub num_bytes_processed: u64,
+    /// Number of documents that failed to parse or lack a timestamp.
+    pub num_invalid_docs: u64,
+    /// Number of documents indexed but not committed yet.
+    pub num_docs_in_workbench: u64,
+    /// Number of split batches committed but not published yet.
+    pub num_in_flight_split_batches: u64,
+    /// Number of times the indexing pipelines were restarted after a failure.
+    pub num_pipeline_restarts: u64,
+    /// Number of times the merge pipeline was restarted after a failure.
+    pub num_merge_pipeline_restarts: u64,
+}
+
+pub fn index_stats_handler(
+    metastore: Arc<dyn Metastore>,
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "stats")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .and(with_arg(indexing_service_mailbox_opt))
+        .then(index_stats)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexes",
+    path = "/indexes/{index_id}/stats",
+    responses(
+        (status = 200, description = "Successfully fetched the operational statistics of the index.", body = IndexOperationalStats)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID."),
+    )
+)]
+/// Get Index Stats
+///
+/// Returns the operational statistics of an index: the published documents, the splits per
+/// state, the storage footprint, the merge backlog, and the ingest lag and errors of each source.
+/// The statistics of the indexing pipelines only cover the pipelines running on the node serving
+/// the request.
+async fn index_stats(
+    index_id: String,
+    metastore: Arc<dyn Metastore>,
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> Result<IndexOperationalStats, MetastoreError> {
+    info!(index_id = %index_id, "index-stats");
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let splits = metastore
+        .list_splits(ListSplitsQuery::for_index(&index_id))
+        .await?;
+    let pipeline_statuses = if let Some(indexing_service_mailbox) = indexing_service_mailbox_opt {
+        match indexing_service_mailbox.ask_for_res(ListPipelines).await {
+            Ok(pipeline_statuses) => pipeline_statuses
+                .into_iter()
+                .filter(|pipeline_status| pipeline_status.index_id == index_id)
+                .collect(),
+            Err(error) => {
+                warn!(index_id = %index_id, error = ?error, "Failed to list the indexing pipelines.");
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let index_stats = compute_index_stats(&index_metadata, &splits, &pipeline_statuses, now);
+    Ok(index_stats)
+}
+
+fn compute_index_stats(
+    index_metadata: &IndexMetadata,
+    splits: &[Split],
+    pipeline_statuses: &[IndexingPipelineStatus],
+    now: i64,
+) -> IndexOperationalStats {
+    let index_config = &index_metadata.index_config;
+    let has_timestamp_field = index_config.doc_mapping.timestamp_field.is_some();
+    let merge_policy = merge_policy_from_settings(&index_config.indexing_settings);
+
+    let mut num_splits_by_state: BTreeMap<String, usize> = BTreeMap::new();
+    let mut storage_size_bytes = 0;
+    let mut num_published_docs = 0;
+    let mut num_published_bytes = 0;
+    let mut source_stats: BTreeMap<&str, SourceStats> = index_metadata
+        .sources
+        .values()
+        .map(|source_config| {
+            let source_stats =
+                SourceStats::new(source_config.source_id.clone(), source_config.enabled);
+            (source_config.source_id.as_str(), source_stats)
+        })
+        .collect();
+    // Merges only happen between splits of the same partition and doc mapping version.
+    let mut immature_splits: HashMap<(u64, u64), Vec<SplitMetadata>> = HashMap::new();
+
+    for split in splits {
+        *num_splits_by_state
+            .entry(split.split_state.as_str().to_string())
+            .or_default() += 1;
+        storage_size_bytes += split.split_metadata.footer_offsets.end;
+
+        if split.split_state != SplitState::Published {
+            continue;
+        }
+        let split_metadata = &split.split_metadata;
+        num_published_docs += split_metadata.num_docs as u64;
+        num_published_bytes += split_metadata.uncompressed_docs_size_in_bytes;
+
+        let stats = source_stats
+            .entry(split_metadata.source_id.as_str())
+            .or_insert_with(|| SourceStats::new(split_metadata.source_id.clone(), false));
+        stats.num_published_docs += split_metadata.num_docs as u64;
+        stats.num_published_splits += 1;
+        stats.last_publish_timestamp = stats.last_publish_timestamp.max(split.publish_timestamp);
+
+        if has_timestamp_field {
+            if let Some(time_range) = &split_metadata.time_range {
+                let ingest_lag_secs = (now - time_range.end()).max(0);
+                stats.ingest_lag_secs = Some(
+                    stats
+                        .ingest_lag_secs
+                        .map_or(ingest_lag_secs, |lag_secs| lag_secs.min(ingest_lag_secs)),
+                );
+            }
+        }
+        if !merge_policy.is_mature(split_metadata) {
+            immature_splits
+                .entry((
+                    split_metadata.partition_id,
+                    split_metadata.doc_mapping_version,
+                ))
+                .or_default()
+                .push(split_metadata.clone());
+        }
+    }
+    let num_immature_splits = immature_splits.values().map(Vec::len).sum();
+    let num_pending_merges = immature_splits
+        .into_values()
+        .map(|mut splits| merge_policy.operations(&mut splits).len())
+        .sum();
+    let mut num_ongoing_merges = 0;
+
+    for (source_id, source_pipeline_statuses) in &pipeline_statuses
+        .iter()
+        .sorted_by_key(|pipeline_status| (&pipeline_status.source_id, pipeline_status.pipeline_ord))
+        .group_by(|pipeline_status| pipeline_status.source_id.as_str())
+    {
+        let mut pipelines_stats = SourcePipelinesStats::default();
+
+        for (pipeline_status_idx, pipeline_status) in source_pipeline_statuses.enumerate() {
+            let statistics = &pipeline_status.statistics;
+            pipelines_stats.num_pipelines += 1;
+            pipelines_stats.num_paused_pipelines += pipeline_status.is_source_paused as usize;
+            pipelines_stats.num_docs_processed += statistics.num_docs;
+            pipelines_stats.num_bytes_processed += statistics.total_bytes_processed;
+            pipelines_stats.num_invalid_docs += statistics.num_invalid_docs;
+            pipelines_stats.num_docs_in_workbench += statistics.num_docs_in_workbench;
+            pipelines_stats.num_in_flight_split_batches += statistics.num_in_flight_split_batches;
+            pipelines_stats.num_pipeline_restarts += statistics.generation.saturating_sub(1) as u64;
+
+            // The pipelines of a source share the same merge pipeline.
+            if pipeline_status_idx == 0 {
+                if let Some(merge_statistics) = &pipeline_status.merge_statistics {
+                    num_ongoing_merges += merge_statistics.num_ongoing_merges;
+                    pipelines_stats.num_merge_pipeline_restarts =
+                        merge_statistics.generation.saturating_sub(1) as u64;
+                }
+            }
+        }
+        source_stats
+            .entry(source_id)
+            .or_insert_with(|| SourceStats::new(source_id.to_string(), false))
+            .pipelines = Some(pipelines_stats);
+    }
+    IndexOperationalStats {
+        index_id: index_config.index_id.clone(),
+        num_published_docs,
+        num_published_bytes,
+        num_splits_by_state,
+        storage_size_bytes,
+        merge_backlog: MergeBacklog {
+            num_immature_splits,
+            num_pending_merges,
+            num_ongoing_merges,
+        },
+        sources: source_stats.into_values().collect(),
+    }
+}
+
+impl SourceStats {
+    fn new(source_id: String, enabled: bool) -> Self {
+        Self {
+            source_id,
+            enabled,
+            num_published_docs: 0,
+            num_published_splits: 0,
+            last_publish_timestamp: None,
+            ingest_lag_secs: None,
+            pipelines: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::SourceConfig;
+    use quickwit_indexing::mock_split;
+    use quickwit_indexing::models::MergeStatistics;
+    use quickwit_indexing::IndexingStatistics;
+    use quickwit_metastore::MockMetastore;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    fn split_for_test(split_id: &str, split_state: SplitState, source_id: &str) -> Split {
+        let mut split = mock_split(split_id);
+        split.split_state = split_state;
+        split.split_metadata.source_id = source_id.to_string();
+        // Recent splits are not mature yet.
+        split.split_metadata.create_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        split
+    }
+
+    #[test]
+    fn test_compute_index_stats() {
+        let mut index_metadata = IndexMetadata::for_test("test-index", "ram:///indexes/test-index");
+        let ingest_api_source_id = SourceConfig::ingest_api_default().source_id;
+        index_metadata
+            .add_source(SourceConfig::ingest_api_default())
+            .unwrap();
+
+        let mut split_1 = split_for_test("split_1", SplitState::Published, &ingest_api_source_id);
+        split_1.publish_timestamp = Some(1_000);
+        let mut split_2 = split_for_test("split_2", SplitState::Published, &ingest_api_source_id);
+        split_2.publish_timestamp = Some(2_000);
+        split_2.split_metadata.time_range = Some(121_000..=130_100);
+        let split_3 = split_for_test("split_3", SplitState::Published, "_ingest-cli-source");
+        let split_4 = split_for_test("split_4", SplitState::Staged, &ingest_api_source_id);
+        let split_5 = split_for_test(
+            "split_5",
+            SplitState::MarkedForDeletion,
+            &ingest_api_source_id,
+        );
+        let splits = [split_1, split_2, split_3, split_4, split_5];
+
+        let pipeline_status = |pipeline_ord: usize| IndexingPipelineStatus {
+            index_id: "test-index".to_string(),
+            source_id: ingest_api_source_id.clone(),
+            pipeline_ord,
+            is_source_paused: pipeline_ord == 1,
+            statistics: IndexingStatistics {
+                num_docs: 5,
+                num_invalid_docs: 2,
+                total_bytes_processed: 100,
+                num_docs_in_workbench: 3,
+                generation: 2,
+                ..Default::default()
+            },
+            merge_statistics: Some(MergeStatistics {
+                num_ongoing_merges: 1,
+                generation: 1,
+                ..Default::default()
+            }),
+            checkpoint: Default::default(),
+            backpressure_micros: BTreeMap::new(),
+            processing_micros: BTreeMap::new(),
+        };
+        let pipeline_statuses = [pipeline_status(1), pipeline_status(0)];
+
+        let index_stats =
+            compute_index_stats(&index_metadata, &splits, &pipeline_statuses, 130_200);
+        assert_eq!(index_stats.index_id, "test-index");
+        assert_eq!(index_stats.num_published_docs, 30);
+        assert_eq!(index_stats.num_published_bytes, 768);
+        assert_eq!(
+            index_stats.num_splits_by_state,
+            BTreeMap::from_iter([
+                ("MarkedForDeletion".to_string(), 1),
+                ("Published".to_string(), 3),
+                ("Staged".to_string(), 1),
+            ])
+        );
+        assert_eq!(index_stats.storage_size_bytes, 4_000);
+        assert_eq!(
+            index_stats.merge_backlog,
+            MergeBacklog {
+                num_immature_splits: 3,
+                num_pending_merges: 0,
+                num_ongoing_merges: 1,
+            }
+        );
+        assert_eq!(index_stats.sources.len(), 2);
+
+        let ingest_api_source_stats = &index_stats.sources[0];
+        assert_eq!(ingest_api_source_stats.source_id, ingest_api_source_id);
+        assert!(ingest_api_source_stats.enabled);
+        assert_eq!(ingest_api_source_stats.num_published_docs, 20);
+        assert_eq!(ingest_api_source_stats.num_published_splits, 2);
+        assert_eq!(ingest_api_source_stats.last_publish_timestamp, Some(2_000));
+        assert_eq!(ingest_api_source_stats.ingest_lag_secs, Some(2));
+        assert_eq!(
+            ingest_api_source_stats.pipelines,
+            Some(SourcePipelinesStats {
+                num_pipelines: 2,
+                num_paused_pipelines: 1,
+                num_docs_processed: 10,
+                num_bytes_processed: 200,
+                num_invalid_docs: 4,
+                num_docs_in_workbench: 6,
+                num_in_flight_split_batches: 0,
+                num_pipeline_restarts: 2,
+                num_merge_pipeline_restarts: 0,
+            })
+        );
+        let cli_source_stats = &index_stats.sources[1];
+        assert_eq!(cli_source_stats.source_id, "_ingest-cli-source");
+        assert!(!cli_source_stats.enabled);
+        assert_eq!(cli_source_stats.num_published_splits, 1);
+        assert_eq!(cli_source_stats.last_publish_timestamp, None);
+        assert_eq!(cli_source_stats.ingest_lag_secs, Some(2));
+        assert!(cli_source_stats.pipelines.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_index_stats_handler() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                if index_id == "test-index" {
+                    return Ok(IndexMetadata::for_test(
+                        "test-index",
+                        "ram:///indexes/test-index",
+                    ));
+                }
+                Err(MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                })
+            });
+        metastore
+            .expect_list_splits()
+            .returning(|_| Ok(vec![mock_split("split_1")]));
+        let index_stats_handler =
+            index_stats_handler(Arc::new(metastore), None).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/indexes/test-index/stats")
+            .reply(&index_stats_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_stats_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(index_stats_json["num_published_docs"], 10);
+        assert_eq!(index_stats_json["num_splits_by_state"]["Published"], 1);
+        assert_eq!(index_stats_json["sources"][0]["pipelines"], JsonValue::Null);
+
+        let resp = warp::test::request()
+            .path("/indexes/other-index/stats")
+            .reply(&index_stats_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+}
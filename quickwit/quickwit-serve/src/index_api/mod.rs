@@ -17,8 +17,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod index_stats;
 mod rest_handler;
 
+pub use self::index_stats::{index_stats_handler, IndexStatsApi};
 pub use self::rest_handler::{
-    index_management_handlers, IndexApi, ListSplitsQueryParams, UnsupportedContentType,
+    config_format_filter, index_management_handlers, IndexApi, ListSplitsQueryParams,
+    UnsupportedContentType,
 };
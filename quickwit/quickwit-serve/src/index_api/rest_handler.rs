@@ -29,9 +29,13 @@ use quickwit_config::{
     load_source_config_from_user_config, ConfigFormat, QuickwitConfig, SourceConfig, SourceParams,
     CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID,
 };
-use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_core::{
+    IndexBackupSummary, IndexService, IndexServiceError, MountedIndexRefreshSummary,
+    SplitStorageLayoutMigrationSummary,
+};
+use quickwit_janitor::StorageReconciliationReport;
 use quickwit_metastore::{
-    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, Split, SplitState,
+    IndexMetadata, ListSplitsQuery, Metastore, MetastoreError, Split, SplitMetadata, SplitState,
 };
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -46,7 +50,17 @@ use crate::with_arg;
 #[openapi(
     paths(
         create_index,
+        update_index,
+        toggle_garbage_collection,
         clear_index,
+        clone_index,
+        backup_index,
+        restore_index,
+        mount_index,
+        refresh_mounted_index,
+        reconcile_index_storage,
+        migrate_split_storage_layout,
+        rehydrate_splits,
         delete_index,
         get_indexes_metadatas,
         list_splits,
@@ -57,7 +71,20 @@ use crate::with_arg;
         toggle_source,
         delete_source,
     ),
-    components(schemas(ToggleSource, SplitsForDeletion, IndexStats))
+    components(schemas(
+        ToggleSource,
+        ToggleGarbageCollection,
+        SplitsForDeletion,
+        IndexStats,
+        CloneIndex,
+        BackupIndex,
+        RestoreIndex,
+        IndexBackupSummary,
+        MountIndex,
+        MountedIndexRefreshSummary,
+        SplitStorageLayoutMigrationSummary,
+        RehydrateSplits
+    ))
 )]
 pub struct IndexApi;
 
@@ -68,8 +95,21 @@ pub fn index_management_handlers(
     // Indexes handlers.
     get_index_metadata_handler(index_service.metastore())
         .or(get_indexes_metadatas_handler(index_service.metastore()))
-        .or(create_index_handler(index_service.clone(), quickwit_config))
+        .or(create_index_handler(
+            index_service.clone(),
+            quickwit_config.clone(),
+        ))
+        .or(update_index_handler(index_service.clone(), quickwit_config))
+        .or(toggle_garbage_collection_handler(index_service.clone()))
         .or(clear_index_handler(index_service.clone()))
+        .or(clone_index_handler(index_service.clone()))
+        .or(backup_index_handler(index_service.clone()))
+        .or(restore_index_handler(index_service.clone()))
+        .or(mount_index_handler(index_service.clone()))
+        .or(refresh_mounted_index_handler(index_service.clone()))
+        .or(reconcile_index_storage_handler(index_service.clone()))
+        .or(migrate_split_storage_layout_handler(index_service.clone()))
+        .or(rehydrate_splits_handler(index_service.clone()))
         .or(delete_index_handler(index_service.clone()))
         // Splits handlers
         .or(list_splits_handler(index_service.metastore()))
@@ -85,7 +125,7 @@ pub fn index_management_handlers(
 
 fn json_body<T: DeserializeOwned + Send>(
 ) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
-    warp::body::content_length_limit(1024 * 1024).and(warp::body::json())
+    warp::body::content_length_limit(1024 * 1024).and(crate::json_body::json_body())
 }
 
 #[derive(Debug, Error)]
@@ -423,13 +463,18 @@ async fn create_index(
         .await
 }
 
-fn clear_index_handler(
+fn update_index_handler(
     index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "clear")
+    warp::path!("indexes" / String)
         .and(warp::put())
+        .and(config_format_filter())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::filters::body::bytes())
         .and(with_arg(index_service))
-        .then(clear_index)
+        .and(with_arg(quickwit_config))
+        .then(update_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
@@ -437,248 +482,708 @@ fn clear_index_handler(
 #[utoipa::path(
     put,
     tag = "Indexes",
-    path = "indexes/{index_id}/clear",
+    path = "/indexes/{index_id}",
+    request_body = VersionedIndexConfig,
     responses(
-        (status = 200, description = "Successfully cleared index.")
+        // We return `VersionedIndexMetadata` as it's the serialized model view.
+        (status = 200, description = "Successfully updated index.", body = VersionedIndexMetadata)
     ),
     params(
-        ("index_id" = String, Path, description = "The index ID to clear."),
+        ("index_id" = String, Path, description = "The index ID to update."),
     )
 )]
-/// Clears index.
-async fn clear_index(
+/// Updates index.
+async fn update_index(
     index_id: String,
+    config_format: ConfigFormat,
+    index_config_bytes: Bytes,
     index_service: Arc<IndexService>,
-) -> Result<(), IndexServiceError> {
-    info!(index_id = %index_id, "clear-index");
-    index_service.clear_index(&index_id).await
-}
-
-#[derive(Deserialize, utoipa::IntoParams, utoipa::ToSchema)]
-#[into_params(parameter_in = Query)]
-struct DeleteIndexQueryParam {
-    #[serde(default)]
-    dry_run: bool,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> Result<IndexMetadata, IndexServiceError> {
+    let index_config = quickwit_config::load_index_config_from_user_config(
+        config_format,
+        &index_config_bytes,
+        &quickwit_config.default_index_root_uri,
+    )
+    .map_err(IndexServiceError::InvalidConfig)?;
+    info!(index_id = %index_id, "update-index");
+    index_service.update_index(&index_id, index_config).await
 }
 
-fn delete_index_handler(
+fn toggle_garbage_collection_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String)
-        .and(warp::delete())
-        .and(serde_qs::warp::query(serde_qs::Config::default()))
+    warp::path!("indexes" / String / "garbage-collection" / "toggle")
+        .and(warp::put())
+        .and(json_body())
         .and(with_arg(index_service))
-        .then(delete_index)
+        .then(toggle_garbage_collection)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct ToggleGarbageCollection {
+    enable: bool,
+}
+
 #[utoipa::path(
-    delete,
+    put,
     tag = "Indexes",
-    path = "indexes/{index_id}",
+    path = "/indexes/{index_id}/garbage-collection/toggle",
+    request_body = ToggleGarbageCollection,
     responses(
         // We return `VersionedIndexMetadata` as it's the serialized model view.
-        (status = 200, description = "Successfully deleted index.", body = [FileEntry])
+        (status = 200, description = "Successfully toggled the index garbage collection.", body = VersionedIndexMetadata)
     ),
     params(
-        DeleteIndexQueryParam,
-        ("index_id" = String, Path, description = "The index ID to delete."),
+        ("index_id" = String, Path, description = "The index ID to pause or resume the garbage collection of."),
     )
 )]
-/// Deletes index.
-async fn delete_index(
+/// Pauses or resumes the garbage collection of an index.
+async fn toggle_garbage_collection(
     index_id: String,
-    delete_index_query_param: DeleteIndexQueryParam,
+    toggle_garbage_collection: ToggleGarbageCollection,
     index_service: Arc<IndexService>,
-) -> Result<Vec<FileEntry>, IndexServiceError> {
-    info!(index_id = %index_id, dry_run = delete_index_query_param.dry_run, "delete-index");
+) -> Result<IndexMetadata, IndexServiceError> {
+    info!(index_id = %index_id, enable = toggle_garbage_collection.enable, "toggle-garbage-collection");
     index_service
-        .delete_index(&index_id, delete_index_query_param.dry_run)
+        .toggle_garbage_collection(&index_id, toggle_garbage_collection.enable)
         .await
 }
 
-fn create_source_handler(
+fn clear_index_handler(
     index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "sources")
-        .and(warp::post())
-        .and(config_format_filter())
-        .and(warp::body::content_length_limit(1024 * 1024))
-        .and(warp::filters::body::bytes())
+    warp::path!("indexes" / String / "clear")
+        .and(warp::put())
         .and(with_arg(index_service))
-        .then(create_source)
+        .then(clear_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
 #[utoipa::path(
-    post,
-    tag = "Sources",
-    path = "indexes/{index_id}/sources",
-    request_body = VersionedSourceConfig,
+    put,
+    tag = "Indexes",
+    path = "indexes/{index_id}/clear",
     responses(
-        // We return `VersionedSourceConfig` as it's the serialized model view.
-        (status = 200, description = "Successfully created source.", body = VersionedSourceConfig)
+        (status = 200, description = "Successfully cleared index.")
     ),
     params(
-        ("index_id" = String, Path, description = "The index ID to create a source for."),
+        ("index_id" = String, Path, description = "The index ID to clear."),
     )
 )]
-/// Creates Source.
-async fn create_source(
+/// Clears index.
+async fn clear_index(
     index_id: String,
-    config_format: ConfigFormat,
-    source_config_bytes: Bytes,
     index_service: Arc<IndexService>,
-) -> Result<SourceConfig, IndexServiceError> {
-    let source_config: SourceConfig =
-        load_source_config_from_user_config(config_format, &source_config_bytes)
-            .map_err(IndexServiceError::InvalidConfig)?;
-    if let SourceParams::File(_) = &source_config.source_params {
-        return Err(IndexServiceError::OperationNotAllowed(
-            "File sources are limited to a local usage. Please use the CLI command `quickwit tool \
-             local-ingest` to ingest data from a file."
-                .to_string(),
-        ));
-    }
-    info!(index_id = %index_id, source_id = %source_config.source_id, "create-source");
-    index_service.create_source(&index_id, source_config).await
+) -> Result<(), IndexServiceError> {
+    info!(index_id = %index_id, "clear-index");
+    index_service.clear_index(&index_id).await
 }
 
-fn get_source_handler(
-    metastore: Arc<dyn Metastore>,
+fn clone_index_handler(
+    index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "sources" / String)
-        .and(warp::get())
-        .and(with_arg(metastore))
-        .then(get_source)
+    warp::path!("indexes" / String / "clone")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(index_service))
+        .then(clone_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
-async fn get_source(
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct CloneIndex {
+    /// The ID of the new index.
+    target_index_id: String,
+    /// The URI of the new index. Defaults to a sibling of the source index URI.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    target_index_uri: Option<Uri>,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/clone",
+    request_body = CloneIndex,
+    responses(
+        // We return `VersionedIndexMetadata` as it's the serialized model view.
+        (status = 200, description = "Successfully cloned index.", body = VersionedIndexMetadata)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to clone."),
+    )
+)]
+/// Clones index.
+async fn clone_index(
     index_id: String,
-    source_id: String,
-    metastore: Arc<dyn Metastore>,
-) -> Result<SourceConfig, MetastoreError> {
-    info!(index_id = %index_id, source_id = %source_id, "get-source");
-    let source_config = metastore
-        .index_metadata(&index_id)
-        .await?
-        .sources
-        .get(&source_id)
-        .ok_or_else(|| MetastoreError::SourceDoesNotExist {
-            source_id: source_id.to_string(),
-        })?
-        .clone();
-    Ok(source_config)
+    clone_index: CloneIndex,
+    index_service: Arc<IndexService>,
+) -> Result<IndexMetadata, IndexServiceError> {
+    info!(index_id = %index_id, target_index_id = %clone_index.target_index_id, "clone-index");
+    index_service
+        .clone_index(
+            &index_id,
+            &clone_index.target_index_id,
+            clone_index.target_index_uri,
+        )
+        .await
 }
 
-fn reset_source_checkpoint_handler(
-    metastore: Arc<dyn Metastore>,
+fn backup_index_handler(
+    index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "sources" / String / "reset-checkpoint")
-        .and(warp::put())
-        .and(with_arg(metastore))
-        .then(reset_source_checkpoint)
+    warp::path!("indexes" / String / "backup")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(index_service))
+        .then(backup_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct BackupIndex {
+    /// The URI of the backup.
+    #[schema(value_type = String)]
+    backup_uri: Uri,
+    /// Copies the split files to the backup.
+    #[serde(default)]
+    include_split_files: bool,
+}
+
 #[utoipa::path(
-    put,
-    tag = "Sources",
-    path = "indexes/{index_id}/sources/{source_id}/reset-checkpoint",
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/backup",
+    request_body = BackupIndex,
     responses(
-        (status = 200, description = "Successfully reset source checkpoint.")
+        (status = 200, description = "Successfully backed up index.", body = IndexBackupSummary)
     ),
     params(
-        ("index_id" = String, Path, description = "The index ID of the source."),
-        ("source_id" = String, Path, description = "The source ID whose checkpoint is reset."),
+        ("index_id" = String, Path, description = "The index ID to back up."),
     )
 )]
-/// Resets source checkpoint.
-async fn reset_source_checkpoint(
+/// Backs up index.
+///
+/// Writes a manifest holding the index metadata and the metadata of the published splits to the
+/// backup storage, along with the split files if requested.
+async fn backup_index(
     index_id: String,
-    source_id: String,
-    metastore: Arc<dyn Metastore>,
-) -> Result<(), MetastoreError> {
-    info!(index_id = %index_id, source_id = %source_id, "reset-checkpoint");
-    metastore
-        .reset_source_checkpoint(&index_id, &source_id)
+    backup_index: BackupIndex,
+    index_service: Arc<IndexService>,
+) -> Result<IndexBackupSummary, IndexServiceError> {
+    info!(index_id = %index_id, backup_uri = %backup_index.backup_uri, "backup-index");
+    index_service
+        .backup_index(
+            &index_id,
+            backup_index.backup_uri,
+            backup_index.include_split_files,
+        )
         .await
 }
 
-fn toggle_source_handler(
-    metastore: Arc<dyn Metastore>,
+fn restore_index_handler(
+    index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "sources" / String / "toggle")
-        .and(warp::put())
+    warp::path!("indexes" / "restore")
+        .and(warp::post())
         .and(json_body())
-        .and(with_arg(metastore))
-        .then(toggle_source)
+        .and(with_arg(index_service))
+        .then(restore_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
 #[derive(Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
-struct ToggleSource {
-    enable: bool,
+struct RestoreIndex {
+    /// The URI of the backup.
+    #[schema(value_type = String)]
+    backup_uri: Uri,
+    /// The ID of the restored index. Defaults to the ID of the backed up index.
+    #[serde(default)]
+    index_id: Option<String>,
+    /// The URI of the restored index. Defaults to the URI of the backed up index.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    index_uri: Option<Uri>,
 }
 
 #[utoipa::path(
-    put,
-    tag = "Sources",
-    path = "indexes/{index_id}/sources/{source_id}/toggle",
-    request_body = ToggleSource,
+    post,
+    tag = "Indexes",
+    path = "indexes/restore",
+    request_body = RestoreIndex,
     responses(
-        (status = 200, description = "Successfully toggled source.")
+        // We return `VersionedIndexMetadata` as it's the serialized model view.
+        (status = 200, description = "Successfully restored index.", body = VersionedIndexMetadata)
     ),
-    params(
-        ("index_id" = String, Path, description = "The index ID of the source."),
-        ("source_id" = String, Path, description = "The source ID to toggle."),
-    )
 )]
-/// Toggles source.
-async fn toggle_source(
-    index_id: String,
-    source_id: String,
-    toggle_source: ToggleSource,
-    metastore: Arc<dyn Metastore>,
-) -> Result<(), IndexServiceError> {
-    info!(index_id = %index_id, source_id = %source_id, enable = toggle_source.enable, "toggle-source");
-    metastore.index_exists(&index_id).await?;
-    if [CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID].contains(&source_id.as_str()) {
-        return Err(IndexServiceError::OperationNotAllowed(format!(
-            "Source `{source_id}` is managed by Quickwit, you cannot enable or disable a source \
-             managed by Quickwit."
-        )));
-    }
-    metastore
-        .toggle_source(&index_id, &source_id, toggle_source.enable)
-        .await?;
-    Ok(())
+/// Restores index from a backup.
+async fn restore_index(
+    restore_index: RestoreIndex,
+    index_service: Arc<IndexService>,
+) -> Result<IndexMetadata, IndexServiceError> {
+    info!(backup_uri = %restore_index.backup_uri, index_id = ?restore_index.index_id, "restore-index");
+    index_service
+        .restore_index(
+            &restore_index.backup_uri,
+            restore_index.index_id,
+            restore_index.index_uri,
+        )
+        .await
 }
 
-fn delete_source_handler(
-    metastore: Arc<dyn Metastore>,
+fn mount_index_handler(
+    index_service: Arc<IndexService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    warp::path!("indexes" / String / "sources" / String)
-        .and(warp::delete())
-        .and(with_arg(metastore))
-        .then(delete_source)
+    warp::path!("indexes" / "mount")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(index_service))
+        .then(mount_index)
         .and(extract_format_from_qs())
         .map(make_response)
 }
 
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct MountIndex {
+    /// The URI of the snapshot: an index backup or the index directory of a file-backed
+    /// metastore.
+    #[schema(value_type = String)]
+    snapshot_uri: Uri,
+    /// The ID of the mounted index. Defaults to the ID of the snapshotted index.
+    #[serde(default)]
+    index_id: Option<String>,
+}
+
 #[utoipa::path(
-    delete,
-    tag = "Sources",
-    path = "indexes/{index_id}/sources/{source_id}",
+    post,
+    tag = "Indexes",
+    path = "indexes/mount",
+    request_body = MountIndex,
     responses(
-        (status = 200, description = "Successfully deleted source.")
+        // We return `VersionedIndexMetadata` as it's the serialized model view.
+        (status = 200, description = "Successfully mounted index.", body = VersionedIndexMetadata)
+    ),
+)]
+/// Mounts a read-only index from a snapshot.
+///
+/// The splits recorded in the snapshot are searched in place, without copying the split files.
+async fn mount_index(
+    mount_index: MountIndex,
+    index_service: Arc<IndexService>,
+) -> Result<IndexMetadata, IndexServiceError> {
+    info!(snapshot_uri = %mount_index.snapshot_uri, index_id = ?mount_index.index_id, "mount-index");
+    index_service
+        .mount_index(&mount_index.snapshot_uri, mount_index.index_id)
+        .await
+}
+
+fn refresh_mounted_index_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "refresh-mount")
+        .and(warp::post())
+        .and(with_arg(index_service))
+        .then(refresh_mounted_index)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/refresh-mount",
+    responses(
+        (status = 200, description = "Successfully refreshed mounted index.", body = MountedIndexRefreshSummary)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The mounted index ID to refresh."),
+    )
+)]
+/// Refreshes mounted index.
+///
+/// Reloads the snapshot the index is mounted from and publishes or removes the splits that changed
+/// since the previous refresh.
+async fn refresh_mounted_index(
+    index_id: String,
+    index_service: Arc<IndexService>,
+) -> Result<MountedIndexRefreshSummary, IndexServiceError> {
+    info!(index_id = %index_id, "refresh-mounted-index");
+    index_service.refresh_mounted_index(&index_id).await
+}
+
+#[derive(Deserialize, utoipa::IntoParams, utoipa::ToSchema)]
+#[into_params(parameter_in = Query)]
+struct ReconcileIndexStorageQueryParam {
+    /// Deletes the split files unknown to the metastore.
+    #[serde(default)]
+    delete_orphans: bool,
+}
+
+fn reconcile_index_storage_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "reconcile")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(index_service))
+        .then(reconcile_index_storage)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/reconcile",
+    responses(
+        (status = 200, description = "Successfully reconciled index storage.", body = StorageReconciliationReport)
+    ),
+    params(
+        ReconcileIndexStorageQueryParam,
+        ("index_id" = String, Path, description = "The index ID to reconcile."),
+    )
+)]
+/// Reconciles index storage with the metastore.
+///
+/// Lists the split files stored in the index storage and reports the files unknown to the
+/// metastore (orphans) and the published splits whose file is missing.
+async fn reconcile_index_storage(
+    index_id: String,
+    reconcile_query_param: ReconcileIndexStorageQueryParam,
+    index_service: Arc<IndexService>,
+) -> Result<StorageReconciliationReport, IndexServiceError> {
+    info!(index_id = %index_id, delete_orphans = reconcile_query_param.delete_orphans, "reconcile-index-storage");
+    index_service
+        .reconcile_index_storage(&index_id, reconcile_query_param.delete_orphans)
+        .await
+}
+
+fn migrate_split_storage_layout_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "migrate-split-storage-layout")
+        .and(warp::post())
+        .and(with_arg(index_service))
+        .then(migrate_split_storage_layout)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/migrate-split-storage-layout",
+    responses(
+        (status = 200, description = "Successfully migrated the split storage layout of the index.", body = SplitStorageLayoutMigrationSummary)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to migrate."),
+    )
+)]
+/// Migrates an index to the content-addressed split storage layout.
+///
+/// Switches the index to the content-addressed layout and replaces its published splits named
+/// after their split ID with splits named after the hash of their content. The files of the
+/// replaced splits are deleted by the garbage collector.
+async fn migrate_split_storage_layout(
+    index_id: String,
+    index_service: Arc<IndexService>,
+) -> Result<SplitStorageLayoutMigrationSummary, IndexServiceError> {
+    info!(index_id = %index_id, "migrate-split-storage-layout");
+    index_service.migrate_split_storage_layout(&index_id).await
+}
+
+fn rehydrate_splits_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "rehydrate")
+        .and(warp::post())
+        .and(json_body())
+        .and(with_arg(index_service))
+        .then(rehydrate_splits)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct RehydrateSplits {
+    /// Start of the time range of the splits to rehydrate (inclusive), in seconds.
+    #[serde(default)]
+    start_timestamp: Option<i64>,
+    /// End of the time range of the splits to rehydrate (exclusive), in seconds.
+    #[serde(default)]
+    end_timestamp: Option<i64>,
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexes",
+    path = "indexes/{index_id}/rehydrate",
+    request_body = RehydrateSplits,
+    responses(
+        (status = 200, description = "Successfully rehydrated splits.", body = [SplitMetadata])
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to rehydrate the splits of."),
+    )
+)]
+/// Rehydrates archived splits.
+///
+/// Moves the archived splits overlapping the time range from the archive storage of the index
+/// back to the index storage, making them searchable again.
+async fn rehydrate_splits(
+    index_id: String,
+    rehydrate_splits: RehydrateSplits,
+    index_service: Arc<IndexService>,
+) -> Result<Vec<SplitMetadata>, IndexServiceError> {
+    info!(index_id = %index_id, start_timestamp = ?rehydrate_splits.start_timestamp, end_timestamp = ?rehydrate_splits.end_timestamp, "rehydrate-splits");
+    index_service
+        .rehydrate_splits(
+            &index_id,
+            rehydrate_splits.start_timestamp,
+            rehydrate_splits.end_timestamp,
+        )
+        .await
+}
+
+#[derive(Deserialize, utoipa::IntoParams, utoipa::ToSchema)]
+#[into_params(parameter_in = Query)]
+struct DeleteIndexQueryParam {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+fn delete_index_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String)
+        .and(warp::delete())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(index_service))
+        .then(delete_index)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Indexes",
+    path = "indexes/{index_id}",
+    responses(
+        // We return `VersionedIndexMetadata` as it's the serialized model view.
+        (status = 200, description = "Successfully deleted index.", body = [FileEntry])
+    ),
+    params(
+        DeleteIndexQueryParam,
+        ("index_id" = String, Path, description = "The index ID to delete."),
+    )
+)]
+/// Deletes index.
+async fn delete_index(
+    index_id: String,
+    delete_index_query_param: DeleteIndexQueryParam,
+    index_service: Arc<IndexService>,
+) -> Result<Vec<FileEntry>, IndexServiceError> {
+    info!(index_id = %index_id, dry_run = delete_index_query_param.dry_run, "delete-index");
+    index_service
+        .delete_index(&index_id, delete_index_query_param.dry_run)
+        .await
+}
+
+fn create_source_handler(
+    index_service: Arc<IndexService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources")
+        .and(warp::post())
+        .and(config_format_filter())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::filters::body::bytes())
+        .and(with_arg(index_service))
+        .then(create_source)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Sources",
+    path = "indexes/{index_id}/sources",
+    request_body = VersionedSourceConfig,
+    responses(
+        // We return `VersionedSourceConfig` as it's the serialized model view.
+        (status = 200, description = "Successfully created source.", body = VersionedSourceConfig)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to create a source for."),
+    )
+)]
+/// Creates Source.
+async fn create_source(
+    index_id: String,
+    config_format: ConfigFormat,
+    source_config_bytes: Bytes,
+    index_service: Arc<IndexService>,
+) -> Result<SourceConfig, IndexServiceError> {
+    let source_config: SourceConfig =
+        load_source_config_from_user_config(config_format, &source_config_bytes)
+            .map_err(IndexServiceError::InvalidConfig)?;
+    if let SourceParams::File(_) = &source_config.source_params {
+        return Err(IndexServiceError::OperationNotAllowed(
+            "File sources are limited to a local usage. Please use the CLI command `quickwit tool \
+             local-ingest` to ingest data from a file."
+                .to_string(),
+        ));
+    }
+    info!(index_id = %index_id, source_id = %source_config.source_id, "create-source");
+    index_service.create_source(&index_id, source_config).await
+}
+
+fn get_source_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_source)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+async fn get_source(
+    index_id: String,
+    source_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<SourceConfig, MetastoreError> {
+    info!(index_id = %index_id, source_id = %source_id, "get-source");
+    let source_config = metastore
+        .index_metadata(&index_id)
+        .await?
+        .sources
+        .get(&source_id)
+        .ok_or_else(|| MetastoreError::SourceDoesNotExist {
+            source_id: source_id.to_string(),
+        })?
+        .clone();
+    Ok(source_config)
+}
+
+fn reset_source_checkpoint_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String / "reset-checkpoint")
+        .and(warp::put())
+        .and(with_arg(metastore))
+        .then(reset_source_checkpoint)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    put,
+    tag = "Sources",
+    path = "indexes/{index_id}/sources/{source_id}/reset-checkpoint",
+    responses(
+        (status = 200, description = "Successfully reset source checkpoint.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the source."),
+        ("source_id" = String, Path, description = "The source ID whose checkpoint is reset."),
+    )
+)]
+/// Resets source checkpoint.
+async fn reset_source_checkpoint(
+    index_id: String,
+    source_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<(), MetastoreError> {
+    info!(index_id = %index_id, source_id = %source_id, "reset-checkpoint");
+    metastore
+        .reset_source_checkpoint(&index_id, &source_id)
+        .await
+}
+
+fn toggle_source_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String / "toggle")
+        .and(warp::put())
+        .and(json_body())
+        .and(with_arg(metastore))
+        .then(toggle_source)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+struct ToggleSource {
+    enable: bool,
+}
+
+#[utoipa::path(
+    put,
+    tag = "Sources",
+    path = "indexes/{index_id}/sources/{source_id}/toggle",
+    request_body = ToggleSource,
+    responses(
+        (status = 200, description = "Successfully toggled source.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the source."),
+        ("source_id" = String, Path, description = "The source ID to toggle."),
+    )
+)]
+/// Toggles source.
+async fn toggle_source(
+    index_id: String,
+    source_id: String,
+    toggle_source: ToggleSource,
+    metastore: Arc<dyn Metastore>,
+) -> Result<(), IndexServiceError> {
+    info!(index_id = %index_id, source_id = %source_id, enable = toggle_source.enable, "toggle-source");
+    metastore.index_exists(&index_id).await?;
+    if [CLI_INGEST_SOURCE_ID, INGEST_API_SOURCE_ID].contains(&source_id.as_str()) {
+        return Err(IndexServiceError::OperationNotAllowed(format!(
+            "Source `{source_id}` is managed by Quickwit, you cannot enable or disable a source \
+             managed by Quickwit."
+        )));
+    }
+    metastore
+        .toggle_source(&index_id, &source_id, toggle_source.enable)
+        .await?;
+    Ok(())
+}
+
+fn delete_source_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "sources" / String)
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_source)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Sources",
+    path = "indexes/{index_id}/sources/{source_id}",
+    responses(
+        (status = 200, description = "Successfully deleted source.")
     ),
     params(
         ("index_id" = String, Path, description = "The index ID to remove the source from."),
@@ -706,10 +1211,13 @@ async fn delete_source(
 #[cfg(test)]
 mod tests {
     use std::ops::{Bound, RangeInclusive};
+    use std::path::Path;
 
     use assert_json_diff::assert_json_include;
     use quickwit_common::uri::{Protocol, Uri};
-    use quickwit_config::{SourceParams, VecSourceParams};
+    use quickwit_config::{
+        IndexConfig, RetentionPolicy, SourceParams, SplitStorageLayout, VecSourceParams,
+    };
     use quickwit_indexing::mock_split;
     use quickwit_metastore::file_backed_metastore::FileBackedMetastoreFactory;
     use quickwit_metastore::{
@@ -754,52 +1262,193 @@ mod tests {
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/test-index")
+            .path("/indexes/test-index")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "index_id": "test-index",
+            "index_uri": "ram:///indexes/test-index",
+        });
+        assert_json_include!(
+            actual: actual_response_json.get("index_config").unwrap(),
+            expected: expected_response_json
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_non_existing_index() {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore, StorageUriResolver::for_test());
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/test-index")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_get_splits() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_splits()
+            .returning(|list_split_query: ListSplitsQuery| {
+                if list_split_query.index_id == "quickwit-demo-index"
+                    && list_split_query.split_states
+                        == vec![SplitState::Published, SplitState::Staged]
+                    && list_split_query.time_range.start == Bound::Included(10)
+                    && list_split_query.time_range.end == Bound::Excluded(20)
+                    && list_split_query.create_timestamp.end == Bound::Excluded(2)
+                {
+                    return Ok(vec![mock_split("split_1")]);
+                }
+                Err(MetastoreError::InternalError {
+                    message: "".to_string(),
+                    cause: "".to_string(),
+                })
+            })
+            .times(2);
+        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        {
+            let resp = warp::test::request()
+                .path(
+                    "/indexes/quickwit-demo-index/splits?split_states=Published,Staged&\
+                     start_timestamp=10&end_timestamp=20&end_create_timestamp=2",
+                )
+                .reply(&index_management_handler)
+                .await;
+            assert_eq!(resp.status(), 200);
+            let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+            let expected_response_json = serde_json::json!([{
+                "create_timestamp": 0,
+                "split_id": "split_1",
+            }]);
+            assert_json_include!(
+                actual: actual_response_json,
+                expected: expected_response_json
+            );
+        }
+        {
+            let resp = warp::test::request()
+                .path(
+                    "/indexes/quickwit-demo-index/splits?split_states=Published&\
+                     start_timestamp=11&end_timestamp=20&end_create_timestamp=2",
+                )
+                .reply(&index_management_handler)
+                .await;
+            assert_eq!(resp.status(), 500);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_index() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .return_once(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                ))
+            });
+        let split_1 = mock_split("split_1");
+        let split_1_time_range = split_1.split_metadata.time_range.clone().unwrap();
+        let mut split_2 = mock_split("split_2");
+        split_2.split_metadata.time_range = Some(RangeInclusive::new(
+            split_1_time_range.start() - 10,
+            split_1_time_range.end() + 10,
+        ));
+        metastore
+            .expect_list_splits()
+            .return_once(|list_split_query: ListSplitsQuery| {
+                if list_split_query.index_id == "test-index" {
+                    return Ok(vec![split_1, split_2]);
+                }
+                Err(MetastoreError::InternalError {
+                    message: "".to_string(),
+                    cause: "".to_string(),
+                })
+            });
+
+        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/test-index/describe")
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
-        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
         let expected_response_json = serde_json::json!({
             "index_id": "test-index",
             "index_uri": "ram:///indexes/test-index",
+            "num_published_splits": 2,
+            "num_published_docs": 20,
+            "size_published_docs": 1600,
+            "timestamp_field_name": "timestamp",
+            "min_timestamp": split_1_time_range.start() - 10,
+            "max_timestamp": split_1_time_range.end() + 10,
         });
-        assert_json_include!(
-            actual: actual_response_json.get("index_config").unwrap(),
-            expected: expected_response_json
-        );
+
+        assert_eq!(actual_response_json, expected_response_json);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_get_non_existing_index() {
-        let metastore = build_metastore_for_test().await;
-        let index_service = IndexService::new(metastore, StorageUriResolver::for_test());
+    async fn test_get_all_splits() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_splits()
+            .return_once(|list_split_query: ListSplitsQuery| {
+                if list_split_query.index_id == "quickwit-demo-index"
+                    && list_split_query.split_states.is_empty()
+                    && list_split_query.time_range.is_unbounded()
+                    && list_split_query.create_timestamp.is_unbounded()
+                {
+                    return Ok(vec![]);
+                }
+                Err(MetastoreError::InternalError {
+                    message: "".to_string(),
+                    cause: "".to_string(),
+                })
+            });
+        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/test-index")
+            .path("/indexes/quickwit-demo-index/splits")
             .reply(&index_management_handler)
             .await;
-        assert_eq!(resp.status(), 404);
+        assert_eq!(resp.status(), 200);
     }
 
     #[tokio::test]
-    async fn test_get_splits() {
+    async fn test_mark_splits_for_deletion() -> anyhow::Result<()> {
         let mut metastore = MockMetastore::new();
         metastore
-            .expect_list_splits()
-            .returning(|list_split_query: ListSplitsQuery| {
-                if list_split_query.index_id == "quickwit-demo-index"
-                    && list_split_query.split_states
-                        == vec![SplitState::Published, SplitState::Staged]
-                    && list_split_query.time_range.start == Bound::Included(10)
-                    && list_split_query.time_range.end == Bound::Excluded(20)
-                    && list_split_query.create_timestamp.end == Bound::Excluded(2)
-                {
-                    return Ok(vec![mock_split("split_1")]);
+            .expect_mark_splits_for_deletion()
+            .returning(|index_id: &str, split_ids: &[&str]| {
+                if index_id == "quickwit-demo-index" && split_ids == ["split-1", "split-2"] {
+                    return Ok(());
                 }
                 Err(MetastoreError::InternalError {
                     message: "".to_string(),
@@ -813,236 +1462,560 @@ mod tests {
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
-        {
-            let resp = warp::test::request()
-                .path(
-                    "/indexes/quickwit-demo-index/splits?split_states=Published,Staged&\
-                     start_timestamp=10&end_timestamp=20&end_create_timestamp=2",
-                )
-                .reply(&index_management_handler)
-                .await;
-            assert_eq!(resp.status(), 200);
-            let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
-            let expected_response_json = serde_json::json!([{
-                "create_timestamp": 0,
-                "split_id": "split_1",
-            }]);
-            assert_json_include!(
-                actual: actual_response_json,
-                expected: expected_response_json
-            );
-        }
-        {
-            let resp = warp::test::request()
-                .path(
-                    "/indexes/quickwit-demo-index/splits?split_states=Published&\
-                     start_timestamp=11&end_timestamp=20&end_create_timestamp=2",
-                )
-                .reply(&index_management_handler)
-                .await;
-            assert_eq!(resp.status(), 500);
-        }
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/splits/mark-for-deletion")
+            .method("PUT")
+            .json(&true)
+            .body(r#"{"split_ids": ["split-1", "split-2"]}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/splits/mark-for-deletion")
+            .json(&true)
+            .body(r#"{"split_ids": [""]}"#)
+            .method("PUT")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 500);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_list_indexes() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_indexes_metadatas().return_once(|| {
+            Ok(vec![IndexMetadata::for_test(
+                "test-index",
+                "ram:///indexes/test-index",
+            )])
+        });
+        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let actual_response_arr: &Vec<JsonValue> = actual_response_json.as_array().unwrap();
+        assert_eq!(actual_response_arr.len(), 1);
+        let actual_index_metadata_json: &JsonValue = &actual_response_arr[0];
+        let expected_response_json = serde_json::json!({
+            "index_id": "test-index",
+            "index_uri": "ram:///indexes/test-index",
+        });
+        assert_json_include!(
+            actual: actual_index_metadata_json.get("index_config").unwrap(),
+            expected: expected_response_json
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clear_index() -> anyhow::Result<()> {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_index_metadata()
+            .return_once(|_index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    "quickwit-demo-index",
+                    "file:///path/to/index/quickwit-demo-index",
+                ))
+            });
+        metastore
+            .expect_list_all_splits()
+            .return_once(|_| Ok(vec![mock_split("split_1")]));
+        metastore
+            .expect_mark_splits_for_deletion()
+            .return_once(|_index_id: &str, _splits: &[&str]| Ok(()));
+        metastore
+            .expect_delete_splits()
+            .return_once(|_index_id: &str, _splits: &[&str]| Ok(()));
+        metastore
+            .expect_reset_source_checkpoint()
+            .return_once(|_index_id: &str, _source_id: &str| Ok(()));
+        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/quickwit-demo-index/clear")
+            .method("PUT")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_index() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let index_management_handler =
+            super::index_management_handlers(Arc::new(index_service), Arc::new(quickwit_config))
+                .recover(recover_fn);
+        let index_config_json = |field_mappings: JsonValue| {
+            serde_json::json!({
+                "version": "0.5",
+                "index_id": "hdfs-logs",
+                "doc_mapping": {"field_mappings": field_mappings},
+            })
+            .to_string()
+        };
+        let resp = warp::test::request()
+            .path("/indexes")
+            .method("POST")
+            .body(index_config_json(serde_json::json!([
+                {"name": "body", "type": "text", "tokenizer": "default"}
+            ])))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs")
+            .method("PUT")
+            .body(index_config_json(serde_json::json!([
+                {"name": "body", "type": "text", "tokenizer": "raw"},
+                {"name": "severity", "type": "text", "tokenizer": "raw"}
+            ])))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "index_config": {
+                "index_id": "hdfs-logs",
+                "doc_mapping": {"doc_mapping_version": 1}
+            }
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs")
+            .method("PUT")
+            .body(index_config_json(serde_json::json!([
+                {"name": "body", "type": "u64"}
+            ])))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/indexes/other-index")
+            .method("PUT")
+            .body(index_config_json(serde_json::json!([])))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_toggle_garbage_collection() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let index_management_handler =
+            super::index_management_handlers(Arc::new(index_service), Arc::new(quickwit_config))
+                .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes")
+            .method("POST")
+            .body(r#"{"version": "0.5", "index_id": "hdfs-logs", "doc_mapping": {}}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/garbage-collection/toggle")
+            .method("PUT")
+            .json(&true)
+            .body(r#"{"enable": false}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_metadata = metastore.index_metadata("hdfs-logs").await?;
+        assert!(index_metadata.index_config.garbage_collection.paused);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/garbage-collection/toggle")
+            .method("PUT")
+            .json(&true)
+            .body(r#"{"enable": true}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_metadata = metastore.index_metadata("hdfs-logs").await?;
+        assert!(!index_metadata.index_config.garbage_collection.paused);
+
+        let resp = warp::test::request()
+            .path("/indexes/hdfs-logs/garbage-collection/toggle")
+            .method("PUT")
+            .json(&true)
+            .body(r#"{"enabled": true}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_describe_index() -> anyhow::Result<()> {
-        let mut metastore = MockMetastore::new();
-        metastore
-            .expect_index_metadata()
-            .return_once(|_index_id: &str| {
-                Ok(IndexMetadata::for_test(
-                    "test-index",
-                    "ram:///indexes/test-index",
-                ))
-            });
-        let split_1 = mock_split("split_1");
-        let split_1_time_range = split_1.split_metadata.time_range.clone().unwrap();
-        let mut split_2 = mock_split("split_2");
-        split_2.split_metadata.time_range = Some(RangeInclusive::new(
-            split_1_time_range.start() - 10,
-            split_1_time_range.end() + 10,
-        ));
-        metastore
-            .expect_list_splits()
-            .return_once(|list_split_query: ListSplitsQuery| {
-                if list_split_query.index_id == "test-index" {
-                    return Ok(vec![split_1, split_2]);
-                }
-                Err(MetastoreError::InternalError {
-                    message: "".to_string(),
-                    cause: "".to_string(),
-                })
-            });
+    async fn test_clone_index() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        index_service
+            .create_index(
+                IndexConfig::for_test("source-index", "ram:///indexes/source-index"),
+                false,
+            )
+            .await?;
+        let index_management_handler = super::index_management_handlers(
+            Arc::new(index_service),
+            Arc::new(QuickwitConfig::for_test()),
+        )
+        .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/indexes/source-index/clone")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"target_index_id": "target-index"}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "index_id": "target-index",
+            "index_uri": "ram:///indexes/target-index",
+        });
+        assert_json_include!(
+            actual: actual_response_json.get("index_config").unwrap(),
+            expected: expected_response_json
+        );
+        metastore.index_metadata("target-index").await?;
 
-        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+        let resp = warp::test::request()
+            .path("/indexes/source-index/clone")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"target_index_id": "source-index"}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_index() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        index_service
+            .create_index(
+                IndexConfig::for_test("backed-up-index", "ram:///indexes/backed-up-index"),
+                false,
+            )
+            .await?;
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/test-index/describe")
+            .path("/indexes/backed-up-index/backup")
+            .method("POST")
+            .body(r#"{"backup_uri": "ram:///backups/backed-up-index"}"#)
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "backup_uri": "ram:///backups/backed-up-index",
+            "num_splits": 0,
+            "num_docs": 0,
+            "num_bytes": 0,
+            "includes_split_files": false,
+        });
+        assert_eq!(actual_response_json, expected_response_json);
 
-        let actual_response_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let resp = warp::test::request()
+            .path("/indexes/restore")
+            .method("POST")
+            .json(&serde_json::json!({
+                "backup_uri": "ram:///backups/backed-up-index",
+                "index_id": "restored-index",
+                "index_uri": "ram:///indexes/restored-index",
+            }))
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
         let expected_response_json = serde_json::json!({
-            "index_id": "test-index",
-            "index_uri": "ram:///indexes/test-index",
-            "num_published_splits": 2,
-            "num_published_docs": 20,
-            "size_published_docs": 1600,
-            "timestamp_field_name": "timestamp",
-            "min_timestamp": split_1_time_range.start() - 10,
-            "max_timestamp": split_1_time_range.end() + 10,
+            "index_id": "restored-index",
+            "index_uri": "ram:///indexes/restored-index",
         });
+        assert_json_include!(
+            actual: actual_response_json.get("index_config").unwrap(),
+            expected: expected_response_json
+        );
+        metastore.index_metadata("restored-index").await?;
 
-        assert_eq!(actual_response_json, expected_response_json);
+        let resp = warp::test::request()
+            .path("/indexes/restore")
+            .method("POST")
+            .body(r#"{"backup_uri": "ram:///backups/unknown-backup"}"#)
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_get_all_splits() {
-        let mut metastore = MockMetastore::new();
-        metastore
-            .expect_list_splits()
-            .return_once(|list_split_query: ListSplitsQuery| {
-                if list_split_query.index_id == "quickwit-demo-index"
-                    && list_split_query.split_states.is_empty()
-                    && list_split_query.time_range.is_unbounded()
-                    && list_split_query.create_timestamp.is_unbounded()
-                {
-                    return Ok(vec![]);
-                }
-                Err(MetastoreError::InternalError {
-                    message: "".to_string(),
-                    cause: "".to_string(),
-                })
-            });
-        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+    async fn test_mount_and_refresh_index() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        index_service
+            .create_index(
+                IndexConfig::for_test("external-index", "ram:///indexes/external-index"),
+                false,
+            )
+            .await?;
+        index_service
+            .backup_index(
+                "external-index",
+                Uri::from_well_formed("ram:///snapshots/external-index"),
+                false,
+            )
+            .await?;
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/quickwit-demo-index/splits")
+            .path("/indexes/mount")
+            .method("POST")
+            .json(&serde_json::json!({
+                "snapshot_uri": "ram:///snapshots/external-index",
+                "index_id": "mounted-index",
+            }))
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "index_id": "mounted-index",
+            "index_uri": "ram:///indexes/external-index",
+            "mounted_from": "ram:///snapshots/external-index",
+        });
+        assert_json_include!(
+            actual: actual_response_json.get("index_config").unwrap(),
+            expected: expected_response_json
+        );
+
+        let resp = warp::test::request()
+            .path("/indexes/mounted-index/refresh-mount")
+            .method("POST")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "snapshot_uri": "ram:///snapshots/external-index",
+            "num_added_splits": 0,
+            "num_removed_splits": 0,
+        });
+        assert_eq!(actual_response_json, expected_response_json);
+
+        let resp = warp::test::request()
+            .path("/indexes/external-index/refresh-mount")
+            .method("POST")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 405);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_mark_splits_for_deletion() -> anyhow::Result<()> {
-        let mut metastore = MockMetastore::new();
-        metastore
-            .expect_mark_splits_for_deletion()
-            .returning(|index_id: &str, split_ids: &[&str]| {
-                if index_id == "quickwit-demo-index" && split_ids == ["split-1", "split-2"] {
-                    return Ok(());
-                }
-                Err(MetastoreError::InternalError {
-                    message: "".to_string(),
-                    cause: "".to_string(),
-                })
-            })
-            .times(2);
-        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+    async fn test_reconcile_index_storage() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let storage_resolver = StorageUriResolver::for_test();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver.clone());
+        let index_uri = Uri::from_well_formed("ram:///indexes/reconciled-index");
+        index_service
+            .create_index(
+                IndexConfig::for_test("reconciled-index", index_uri.as_str()),
+                false,
+            )
+            .await?;
+        let storage = storage_resolver.resolve(&index_uri)?;
+        storage
+            .put(Path::new("orphan.split"), Box::new(b"orphan".to_vec()))
+            .await?;
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/quickwit-demo-index/splits/mark-for-deletion")
-            .method("PUT")
-            .json(&true)
-            .body(r#"{"split_ids": ["split-1", "split-2"]}"#)
+            .path("/indexes/reconciled-index/reconcile")
+            .method("POST")
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "orphan_files": [{"file_name": "orphan.split", "file_size_in_bytes": 6}],
+            "missing_split_ids": [],
+            "deleted_orphan_files": [],
+        });
+        assert_eq!(actual_response_json, expected_response_json);
+        assert!(storage.exists(Path::new("orphan.split")).await?);
+
         let resp = warp::test::request()
-            .path("/indexes/quickwit-demo-index/splits/mark-for-deletion")
-            .json(&true)
-            .body(r#"{"split_ids": [""]}"#)
-            .method("PUT")
+            .path("/indexes/reconciled-index/reconcile?delete_orphans=true")
+            .method("POST")
             .reply(&index_management_handler)
             .await;
-        assert_eq!(resp.status(), 500);
+        assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(
+            actual_response_json["deleted_orphan_files"],
+            serde_json::json!(["orphan.split"])
+        );
+        assert!(!storage.exists(Path::new("orphan.split")).await?);
+
+        let resp = warp::test::request()
+            .path("/indexes/unknown-index/reconcile")
+            .method("POST")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_get_list_indexes() -> anyhow::Result<()> {
-        let mut metastore = MockMetastore::new();
-        metastore.expect_list_indexes_metadatas().return_once(|| {
-            Ok(vec![IndexMetadata::for_test(
-                "test-index",
-                "ram:///indexes/test-index",
-            )])
-        });
-        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+    async fn test_migrate_split_storage_layout() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let storage_resolver = StorageUriResolver::for_test();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver.clone());
+        let index_uri = Uri::from_well_formed("ram:///indexes/migrated-index");
+        index_service
+            .create_index(
+                IndexConfig::for_test("migrated-index", index_uri.as_str()),
+                false,
+            )
+            .await?;
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes")
+            .path("/indexes/migrated-index/migrate-split-storage-layout")
+            .method("POST")
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
         let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
-        let actual_response_arr: &Vec<JsonValue> = actual_response_json.as_array().unwrap();
-        assert_eq!(actual_response_arr.len(), 1);
-        let actual_index_metadata_json: &JsonValue = &actual_response_arr[0];
         let expected_response_json = serde_json::json!({
-            "index_id": "test-index",
-            "index_uri": "ram:///indexes/test-index",
+            "num_migrated_splits": 0,
+            "num_deduplicated_splits": 0,
+            "num_uploaded_bytes": 0,
         });
-        assert_json_include!(
-            actual: actual_index_metadata_json.get("index_config").unwrap(),
-            expected: expected_response_json
+        assert_eq!(actual_response_json, expected_response_json);
+        let index_config = metastore
+            .index_metadata("migrated-index")
+            .await?
+            .into_index_config();
+        assert_eq!(
+            index_config.indexing_settings.split_storage_layout,
+            SplitStorageLayout::ContentAddressed
         );
+
+        let resp = warp::test::request()
+            .path("/indexes/unknown-index/migrate-split-storage-layout")
+            .method("POST")
+            .reply(&index_management_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_clear_index() -> anyhow::Result<()> {
-        let mut metastore = MockMetastore::new();
-        metastore
-            .expect_index_metadata()
-            .return_once(|_index_id: &str| {
-                Ok(IndexMetadata::for_test(
-                    "quickwit-demo-index",
-                    "file:///path/to/index/quickwit-demo-index",
-                ))
-            });
-        metastore
-            .expect_list_all_splits()
-            .return_once(|_| Ok(vec![mock_split("split_1")]));
+    async fn test_rehydrate_splits() -> anyhow::Result<()> {
+        let metastore = build_metastore_for_test().await;
+        let storage_resolver = StorageUriResolver::for_test();
+        let index_service = IndexService::new(metastore.clone(), storage_resolver.clone());
+        let index_uri = Uri::from_well_formed("ram:///indexes/archived-index");
+        let archive_uri = Uri::from_well_formed("ram:///archive/archived-index");
+        let mut index_config = IndexConfig::for_test("archived-index", index_uri.as_str());
+        index_config.retention_policy = Some(
+            RetentionPolicy::new("1 day".to_string(), "daily".to_string())
+                .with_archive_action(archive_uri.clone()),
+        );
+        index_service.create_index(index_config, false).await?;
+
+        let mut split_metadata_1 = mock_split("split-1").split_metadata;
+        split_metadata_1.time_range = Some(1000..=1999);
+        let mut split_metadata_2 = mock_split("split-2").split_metadata;
+        split_metadata_2.time_range = Some(2000..=2999);
         metastore
-            .expect_mark_splits_for_deletion()
-            .return_once(|_index_id: &str, _splits: &[&str]| Ok(()));
+            .stage_splits("archived-index", vec![split_metadata_1, split_metadata_2])
+            .await?;
         metastore
-            .expect_delete_splits()
-            .return_once(|_index_id: &str, _splits: &[&str]| Ok(()));
+            .publish_splits("archived-index", &["split-1", "split-2"], &[], None)
+            .await?;
         metastore
-            .expect_reset_source_checkpoint()
-            .return_once(|_index_id: &str, _source_id: &str| Ok(()));
-        let index_service = IndexService::new(Arc::new(metastore), StorageUriResolver::for_test());
+            .archive_splits("archived-index", &["split-1", "split-2"])
+            .await?;
+        let archive_storage = storage_resolver.resolve(&archive_uri)?;
+        for split_file in ["split-1.split", "split-2.split"] {
+            archive_storage
+                .put(Path::new(split_file), Box::new(b"split".to_vec()))
+                .await?;
+        }
         let index_management_handler = super::index_management_handlers(
             Arc::new(index_service),
             Arc::new(QuickwitConfig::for_test()),
         )
         .recover(recover_fn);
         let resp = warp::test::request()
-            .path("/indexes/quickwit-demo-index/clear")
-            .method("PUT")
+            .path("/indexes/archived-index/rehydrate")
+            .method("POST")
+            .body(r#"{"start_timestamp": 2000}"#)
             .reply(&index_management_handler)
             .await;
         assert_eq!(resp.status(), 200);
+        let actual_response_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(actual_response_json.as_array().unwrap().len(), 1);
+        assert_eq!(actual_response_json[0]["split_id"], "split-2");
+
+        let index_storage = storage_resolver.resolve(&index_uri)?;
+        assert!(index_storage.exists(Path::new("split-2.split")).await?);
+        assert!(!archive_storage.exists(Path::new("split-2.split")).await?);
+        assert!(archive_storage.exists(Path::new("split-1.split")).await?);
+
+        let splits = metastore
+            .list_splits(ListSplitsQuery::for_index("archived-index"))
+            .await?;
+        for split in splits {
+            let expected_split_state = if split.split_id() == "split-2" {
+                SplitState::Published
+            } else {
+                SplitState::Archived
+            };
+            assert_eq!(split.split_state, expected_split_state);
+        }
         Ok(())
     }
 
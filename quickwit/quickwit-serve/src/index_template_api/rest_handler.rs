@@ -0,0 +1,335 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use quickwit_config::{load_index_template_from_user_config, ConfigFormat, IndexTemplate};
+use quickwit_metastore::{Metastore, MetastoreError};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use crate::format::{extract_format_from_qs, make_response};
+use crate::index_api::config_format_filter;
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    create_index_template,
+    list_index_templates,
+    get_index_template,
+    delete_index_template
+))]
+pub struct IndexTemplateApi;
+
+#[derive(Debug, Error)]
+pub enum IndexTemplateApiError {
+    #[error("Invalid index template: {0}")]
+    InvalidTemplate(String),
+    #[error("{0}")]
+    Metastore(#[from] MetastoreError),
+}
+
+impl ServiceError for IndexTemplateApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::InvalidTemplate(_) => ServiceErrorCode::BadRequest,
+            Self::Metastore(metastore_error) => metastore_error.status_code(),
+        }
+    }
+}
+
+#[derive(Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CreateIndexTemplateQueryParams {
+    /// If true, replaces the existing template with the same ID.
+    #[serde(default)]
+    overwrite: bool,
+}
+
+/// Index templates management handlers.
+pub(crate) fn index_template_api_handlers(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    create_index_template_handler(metastore.clone())
+        .or(list_index_templates_handler(metastore.clone()))
+        .or(get_index_template_handler(metastore.clone()))
+        .or(delete_index_template_handler(metastore))
+}
+
+fn create_index_template_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("templates")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(config_format_filter())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(warp::filters::body::bytes())
+        .and(with_arg(metastore))
+        .then(create_index_template)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Index Templates",
+    path = "/templates",
+    request_body = VersionedIndexTemplate,
+    responses(
+        (status = 200, description = "Successfully created the index template.", body = VersionedIndexTemplate)
+    ),
+    params(
+        CreateIndexTemplateQueryParams,
+    )
+)]
+/// Create Index Template
+///
+/// Creates an index template. Documents ingested into an index that does not exist yet and whose
+/// ID matches one of the template `index_id_patterns` trigger the creation of the index from the
+/// template.
+async fn create_index_template(
+    create_index_template_query_params: CreateIndexTemplateQueryParams,
+    config_format: ConfigFormat,
+    index_template_bytes: Bytes,
+    metastore: Arc<dyn Metastore>,
+) -> Result<IndexTemplate, IndexTemplateApiError> {
+    let index_template = load_index_template_from_user_config(config_format, &index_template_bytes)
+        .map_err(|error| IndexTemplateApiError::InvalidTemplate(format!("{error:#}")))?;
+    let overwrite = create_index_template_query_params.overwrite;
+    info!(template_id = %index_template.template_id, overwrite = overwrite, "create-index-template");
+    metastore
+        .create_index_template(index_template.clone(), overwrite)
+        .await?;
+    Ok(index_template)
+}
+
+fn list_index_templates_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("templates")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(list_index_templates)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Index Templates",
+    path = "/templates",
+    responses(
+        (status = 200, description = "Successfully fetched the index templates.", body = [VersionedIndexTemplate])
+    ),
+)]
+/// List Index Templates
+async fn list_index_templates(
+    metastore: Arc<dyn Metastore>,
+) -> Result<Vec<IndexTemplate>, IndexTemplateApiError> {
+    let index_templates = metastore.list_index_templates().await?;
+    Ok(index_templates)
+}
+
+fn get_index_template_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("templates" / String)
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(get_index_template)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Index Templates",
+    path = "/templates/{template_id}",
+    responses(
+        (status = 200, description = "Successfully fetched the index template.", body = VersionedIndexTemplate)
+    ),
+    params(
+        ("template_id" = String, Path, description = "The ID of the index template to get."),
+    )
+)]
+/// Get Index Template
+async fn get_index_template(
+    template_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<IndexTemplate, IndexTemplateApiError> {
+    let index_template = metastore
+        .list_index_templates()
+        .await?
+        .into_iter()
+        .find(|index_template| index_template.template_id == template_id)
+        .ok_or(MetastoreError::IndexTemplateDoesNotExist { template_id })?;
+    Ok(index_template)
+}
+
+fn delete_index_template_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("templates" / String)
+        .and(warp::delete())
+        .and(with_arg(metastore))
+        .then(delete_index_template)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Index Templates",
+    path = "/templates/{template_id}",
+    responses(
+        (status = 200, description = "Successfully deleted the index template.")
+    ),
+    params(
+        ("template_id" = String, Path, description = "The ID of the index template to delete."),
+    )
+)]
+/// Delete Index Template
+///
+/// Deletes an index template. The indexes created from the template are left untouched.
+async fn delete_index_template(
+    template_id: String,
+    metastore: Arc<dyn Metastore>,
+) -> Result<(), IndexTemplateApiError> {
+    info!(template_id = %template_id, "delete-index-template");
+    metastore.delete_index_template(&template_id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::metastore_for_test;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_index_template_api() {
+        let metastore = metastore_for_test();
+        let index_template_api_handler =
+            index_template_api_handlers(metastore.clone()).recover(recover_fn);
+
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: [logs-*]
+            doc_mapping:
+              field_mappings:
+                - name: timestamp
+                  type: datetime
+                  fast: true
+              timestamp_field: timestamp
+        "#;
+        let resp = warp::test::request()
+            .path("/templates")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(index_template_yaml)
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json["template_id"], "logs");
+        assert_eq!(
+            resp_json["index_id_patterns"],
+            serde_json::json!(["logs-*"])
+        );
+
+        let resp = warp::test::request()
+            .path("/templates")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(index_template_yaml)
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/templates?overwrite=true")
+            .method("POST")
+            .header("content-type", "application/yaml")
+            .body(index_template_yaml.replace("logs-*", "logs-*-v2"))
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/templates")
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_templates: Vec<IndexTemplate> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(index_templates.len(), 1);
+        assert_eq!(index_templates[0].index_id_patterns, ["logs-*-v2"]);
+
+        let resp = warp::test::request()
+            .path("/templates/logs")
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_template: IndexTemplate = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(index_template, index_templates[0]);
+
+        let resp = warp::test::request()
+            .path("/templates/logs")
+            .method("DELETE")
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert!(metastore.list_index_templates().await.unwrap().is_empty());
+
+        let resp = warp::test::request()
+            .path("/templates/logs")
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_index_template_api_rejects_invalid_template() {
+        let metastore = metastore_for_test();
+        let index_template_api_handler = index_template_api_handlers(metastore).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/templates")
+            .method("POST")
+            .json(&serde_json::json!({
+                "version": "0.4",
+                "template_id": "logs",
+                "index_id_patterns": [],
+                "doc_mapping": {},
+            }))
+            .reply(&index_template_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert!(resp_json["message"]
+            .as_str()
+            .unwrap()
+            .contains("at least one index ID pattern"));
+    }
+}
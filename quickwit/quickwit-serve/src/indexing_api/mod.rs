@@ -19,4 +19,7 @@
 
 mod rest_handler;
 
-pub use rest_handler::{indexing_get_handler, IndexingApi};
+pub use rest_handler::{
+    control_pipelines_handler, indexing_get_handler, list_pipelines_handler,
+    throttle_merges_handler, IndexingApi,
+};
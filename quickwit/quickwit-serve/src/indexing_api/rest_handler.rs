@@ -19,18 +19,90 @@
 
 use std::convert::Infallible;
 
+use byte_unit::Byte;
 use quickwit_actors::{AskError, Mailbox};
-use quickwit_indexing::actors::{IndexingService, IndexingServiceCounters};
-use quickwit_indexing::models::Observe;
+use quickwit_indexing::actors::{
+    IndexingPipelineStatus, IndexingService, IndexingServiceCounters, IndexingServiceError,
+};
+use quickwit_indexing::models::{ControlPipelines, ListPipelines, Observe, PipelineCommand};
+use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
 use crate::format::{extract_format_from_qs, make_response};
+use crate::json_body::json_body;
 use crate::require;
 
 #[derive(utoipa::OpenApi)]
-#[openapi(paths(indexing_endpoint))]
+#[openapi(
+    paths(
+        indexing_endpoint,
+        list_pipelines_endpoint,
+        control_pipelines_endpoint,
+        throttle_merges_endpoint,
+    ),
+    components(schemas(
+        IndexingPipelineStatus,
+        PipelineAction,
+        ControlPipelinesResponse,
+        MergeThrottle
+    ))
+)]
 pub struct IndexingApi;
 
+/// Action applied to the indexing pipelines of an index and a source.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineAction {
+    /// Stops fetching documents from the source.
+    Pause,
+    /// Resumes fetching documents from the source.
+    Resume,
+    /// Commits the documents indexed so far.
+    Commit,
+    /// Plans merges right away.
+    Merge,
+}
+
+impl std::str::FromStr for PipelineAction {
+    type Err = ();
+
+    fn from_str(action_str: &str) -> Result<Self, Self::Err> {
+        match action_str {
+            "pause" => Ok(PipelineAction::Pause),
+            "resume" => Ok(PipelineAction::Resume),
+            "commit" => Ok(PipelineAction::Commit),
+            "merge" => Ok(PipelineAction::Merge),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<PipelineAction> for PipelineCommand {
+    fn from(action: PipelineAction) -> Self {
+        match action {
+            PipelineAction::Pause => PipelineCommand::PauseSource,
+            PipelineAction::Resume => PipelineCommand::ResumeSource,
+            PipelineAction::Commit => PipelineCommand::Commit,
+            PipelineAction::Merge => PipelineCommand::PlanMerges,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, utoipa::ToSchema)]
+pub struct ControlPipelinesResponse {
+    /// Number of pipelines the command was sent to.
+    pub num_pipelines: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MergeThrottle {
+    /// Maximum write throughput of the merges, e.g. `50MB`. Lifts the limit when absent.
+    #[schema(value_type = Option<String>)]
+    #[serde(default)]
+    pub max_merge_write_throughput: Option<Byte>,
+}
+
 #[utoipa::path(
     get,
     tag = "Indexing",
@@ -60,3 +132,140 @@ pub fn indexing_get_handler(
         .and(extract_format_from_qs())
         .map(make_response)
 }
+
+fn list_pipelines_filter() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines").and(warp::get())
+}
+
+pub fn list_pipelines_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    list_pipelines_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .then(list_pipelines_endpoint)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Indexing",
+    path = "/indexing/pipelines",
+    responses(
+        (status = 200, description = "Successfully listed the indexing pipelines.", body = [IndexingPipelineStatus])
+    ),
+)]
+/// List Indexing Pipelines
+///
+/// Lists the indexing pipelines running on the node, along with their statistics, the published
+/// checkpoint of their source, and their backpressure.
+async fn list_pipelines_endpoint(
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<Vec<IndexingPipelineStatus>, AskError<IndexingServiceError>> {
+    indexing_service_mailbox.ask_for_res(ListPipelines).await
+}
+
+fn control_pipelines_filter(
+) -> impl Filter<Extract = (String, String, PipelineAction), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines" / String / String / PipelineAction).and(warp::post())
+}
+
+pub fn control_pipelines_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    control_pipelines_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .then(control_pipelines_endpoint)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Indexing",
+    path = "/indexing/pipelines/{index_id}/{source_id}/{action}",
+    responses(
+        (status = 200, description = "Successfully sent the command to the pipelines.", body = ControlPipelinesResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the pipelines."),
+        ("source_id" = String, Path, description = "The source ID of the pipelines."),
+        ("action" = PipelineAction, Path, description = "The action applied to the pipelines: `pause`, `resume`, `commit`, or `merge`."),
+    )
+)]
+/// Control Indexing Pipelines
+///
+/// Applies an action to the indexing pipelines of the index and source running on the node. The
+/// action is applied asynchronously. A paused source remains paused until it is resumed or the
+/// pipeline is shut down.
+async fn control_pipelines_endpoint(
+    index_id: String,
+    source_id: String,
+    action: PipelineAction,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<ControlPipelinesResponse, AskError<IndexingServiceError>> {
+    let control_pipelines = ControlPipelines {
+        index_id,
+        source_id,
+        command: action.into(),
+    };
+    let num_pipelines = indexing_service_mailbox
+        .ask_for_res(control_pipelines)
+        .await?;
+    Ok(ControlPipelinesResponse { num_pipelines })
+}
+
+fn throttle_merges_filter(
+) -> impl Filter<Extract = (String, String, MergeThrottle), Error = Rejection> + Clone {
+    warp::path!("indexing" / "pipelines" / String / String / "merge-throttle")
+        .and(warp::put())
+        .and(warp::body::content_length_limit(1024))
+        .and(json_body())
+}
+
+pub fn throttle_merges_handler(
+    indexing_service_mailbox_opt: Option<Mailbox<IndexingService>>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    throttle_merges_filter()
+        .and(require(indexing_service_mailbox_opt))
+        .then(throttle_merges_endpoint)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    put,
+    tag = "Indexing",
+    path = "/indexing/pipelines/{index_id}/{source_id}/merge-throttle",
+    request_body = MergeThrottle,
+    responses(
+        (status = 200, description = "Successfully updated the merge throughput limit.", body = ControlPipelinesResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the pipelines."),
+        ("source_id" = String, Path, description = "The source ID of the pipelines."),
+    )
+)]
+/// Throttle Merges
+///
+/// Updates the write throughput limit of the merges of the index and source on the node,
+/// including the ongoing merges. The limit overrides the `max_merge_write_throughput` indexing
+/// setting until the merge pipeline is shut down.
+async fn throttle_merges_endpoint(
+    index_id: String,
+    source_id: String,
+    merge_throttle: MergeThrottle,
+    indexing_service_mailbox: Mailbox<IndexingService>,
+) -> Result<ControlPipelinesResponse, AskError<IndexingServiceError>> {
+    let control_pipelines = ControlPipelines {
+        index_id,
+        source_id,
+        command: PipelineCommand::ThrottleMerges {
+            max_merge_write_throughput: merge_throttle.max_merge_write_throughput,
+        },
+    };
+    let num_pipelines = indexing_service_mailbox
+        .ask_for_res(control_pipelines)
+        .await?;
+    Ok(ControlPipelinesResponse { num_pipelines })
+}
@@ -17,7 +17,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+mod replication;
 mod rest_handler;
+mod routing;
+mod upsert;
 
-pub(crate) use rest_handler::ingest_api_handlers;
+pub(crate) use replication::{grpc_ingest_service_client, IngestReplicator};
+pub(crate) use rest_handler::{ingest_api_handlers, ContentEncodingError};
 pub use rest_handler::{IngestApi, IngestApiSchemas};
@@ -0,0 +1,319 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::try_join_all;
+use quickwit_cluster::{Cluster, ClusterMember, NodeDrainStatus};
+use quickwit_config::service::QuickwitService;
+use quickwit_config::QuickwitConfig;
+use quickwit_grpc_clients::tls::grpc_endpoint;
+use quickwit_ingest_api::{
+    ingest_service_grpc_client, replica_queue_id, shard_partition_id, DocBatch, IngestRequest,
+    IngestResponse, IngestService, IngestServiceClient, IngestServiceError,
+    IngestServiceGrpcClientAdapter, LeaderPosition,
+};
+use quickwit_proto::tonic::transport::Channel;
+use quickwit_proto::ClusterInterceptor;
+use tower::timeout::Timeout;
+use tracing::warn;
+
+/// Timeout of the ingest requests sent to the leader and replica indexers.
+const REPLICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Ingests documents on a leader indexer and replicates them to `replication_factor` peer
+/// indexers, acknowledging the request once all of them have persisted the documents in their
+/// write-ahead log.
+///
+/// The documents are replicated once the leader has appended them, tagged with their position in
+/// the queue of the leader, so that a failed request never leaves replicas unknown to the leader
+/// and the replicas of a retried request are deduplicated upon recovery.
+///
+/// The leader is the node itself if it runs the indexer service, or one of the indexers of the
+/// cluster, picked in a round-robin fashion, otherwise. The replicas are appended to replica
+/// queues, which the peers do not index unless they are asked to recover the replicas of a lost
+/// leader.
+pub(crate) struct IngestReplicator {
+    self_node_id: String,
+    self_availability_zone: Option<String>,
+    is_indexer: bool,
+    replication_factor: usize,
+    cluster: Arc<Cluster>,
+    clients: Mutex<HashMap<SocketAddr, IngestServiceClient>>,
+    next_leader_idx: AtomicUsize,
+}
+
+impl IngestReplicator {
+    pub fn new(config: &QuickwitConfig, cluster: Arc<Cluster>) -> Self {
+        Self {
+            self_node_id: config.node_id.clone(),
+            self_availability_zone: config.availability_zone.clone(),
+            is_indexer: config.enabled_services.contains(&QuickwitService::Indexer),
+            replication_factor: config.ingest_api_config.replication_factor,
+            cluster,
+            clients: Mutex::new(HashMap::new()),
+            next_leader_idx: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn ingest(
+        &self,
+        ingest_service: &mut IngestServiceClient,
+        ingest_request: IngestRequest,
+    ) -> Result<IngestResponse, IngestServiceError> {
+        let mut peers: Vec<ClusterMember> = self
+            .cluster
+            .ready_members()
+            .await
+            .into_iter()
+            .filter(|member| {
+                member.node_id != self.self_node_id
+                    && member.enabled_services.contains(&QuickwitService::Indexer)
+                    && member.drain_status == NodeDrainStatus::Active
+            })
+            .collect();
+        peers.sort_by(|left, right| left.node_id.cmp(&right.node_id));
+
+        let (leader_node_id, leader_availability_zone, mut leader_client) = if self.is_indexer {
+            (
+                self.self_node_id.clone(),
+                self.self_availability_zone.clone(),
+                ingest_service.clone(),
+            )
+        } else {
+            if peers.is_empty() {
+                warn!("No indexer available to ingest documents.");
+                return Err(IngestServiceError::Unavailable);
+            }
+            let leader_idx = self.next_leader_idx.fetch_add(1, Ordering::Relaxed) % peers.len();
+            let leader = peers.remove(leader_idx);
+            let leader_client = self.client(leader.grpc_advertise_addr);
+            (leader.node_id, leader.availability_zone, leader_client)
+        };
+        let replicas = select_replicas(
+            peers,
+            &leader_node_id,
+            leader_availability_zone.as_deref(),
+            self.replication_factor,
+        );
+        if replicas.len() < self.replication_factor {
+            warn!(
+                num_replicas = replicas.len(),
+                replication_factor = self.replication_factor,
+                "Not enough indexers available to replicate documents."
+            );
+            return Err(IngestServiceError::Unavailable);
+        }
+        let ingest_response = leader_client.ingest(ingest_request.clone()).await?;
+        let replica_request = replica_request(&leader_node_id, ingest_request, &ingest_response);
+
+        if !replica_request.doc_batches.is_empty() {
+            let replica_futures = replicas.iter().map(|replica| {
+                let mut replica_client = self.client(replica.grpc_advertise_addr);
+                let replica_request = replica_request.clone();
+                async move { replica_client.ingest(replica_request).await }
+            });
+            try_join_all(replica_futures).await?;
+        }
+        Ok(ingest_response)
+    }
+
+    fn client(&self, grpc_addr: SocketAddr) -> IngestServiceClient {
+        let mut clients = self.clients.lock().unwrap();
+        clients
+            .entry(grpc_addr)
+            .or_insert_with(|| {
+                let channel = grpc_endpoint(grpc_addr)
+                    .connect_timeout(Duration::from_secs(5))
+                    .connect_lazy();
+                grpc_ingest_service_client(Timeout::new(channel, REPLICATION_TIMEOUT))
+            })
+            .clone()
+    }
+}
+
+/// Returns a client of the ingest service served over `channel`, presenting the cluster secret of
+/// the node.
+pub(crate) fn grpc_ingest_service_client(channel: Timeout<Channel>) -> IngestServiceClient {
+    let grpc_client = ingest_service_grpc_client::IngestServiceGrpcClient::with_interceptor(
+        channel,
+        ClusterInterceptor,
+    );
+    IngestServiceClient::new(IngestServiceGrpcClientAdapter::new(grpc_client))
+}
+
+/// Builds the request replicating the batches appended by the leader `leader_node_id` to the
+/// replica queues of its peers. Each batch is tagged with the partition of the shard it was
+/// appended to and with the position of its last document in that shard.
+fn replica_request(
+    leader_node_id: &str,
+    ingest_request: IngestRequest,
+    leader_response: &IngestResponse,
+) -> IngestRequest {
+    let doc_batches = ingest_request
+        .doc_batches
+        .into_iter()
+        .enumerate()
+        .filter_map(|(batch_idx, doc_batch)| {
+            let last_position = *leader_response
+                .batch_last_positions
+                .get(&(batch_idx as u32))?;
+            let shard_ord = leader_response
+                .batch_shard_ords
+                .get(&(batch_idx as u32))
+                .copied()
+                .unwrap_or_default();
+            Some(DocBatch {
+                index_id: replica_queue_id(leader_node_id, &doc_batch.index_id),
+                leader_position: Some(LeaderPosition {
+                    partition_id: shard_partition_id(
+                        &leader_response.partition_id,
+                        shard_ord as usize,
+                    ),
+                    last_position,
+                }),
+                ..doc_batch
+            })
+        })
+        .collect();
+    IngestRequest { doc_batches }
+}
+
+/// Selects the `num_replicas` peers the documents of the leader `leader_node_id` are replicated
+/// to. Peers located in another availability zone than the leader are preferred. Otherwise, the
+/// peers are ranked by rendezvous hashing so that the replicas of the different leaders are
+/// spread across the cluster.
+fn select_replicas(
+    mut peers: Vec<ClusterMember>,
+    leader_node_id: &str,
+    leader_availability_zone_opt: Option<&str>,
+    num_replicas: usize,
+) -> Vec<ClusterMember> {
+    peers.sort_by_cached_key(|peer| {
+        let is_same_availability_zone = leader_availability_zone_opt.is_some()
+            && peer.availability_zone.as_deref() == leader_availability_zone_opt;
+        let mut hasher = DefaultHasher::new();
+        leader_node_id.hash(&mut hasher);
+        peer.node_id.hash(&mut hasher);
+        (is_same_availability_zone, hasher.finish())
+    });
+    peers.truncate(num_replicas);
+    peers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn indexer(node_id: &str, availability_zone: &str) -> ClusterMember {
+        let addr: SocketAddr = "127.0.0.1:7280".parse().unwrap();
+        ClusterMember::new(
+            node_id.to_string(),
+            0,
+            HashSet::from([QuickwitService::Indexer]),
+            addr,
+            addr,
+            Vec::new(),
+        )
+        .with_availability_zone(Some(availability_zone.to_string()))
+    }
+
+    #[test]
+    fn test_select_replicas() {
+        let peers = vec![
+            indexer("node-1", "az-1"),
+            indexer("node-2", "az-1"),
+            indexer("node-3", "az-2"),
+        ];
+        let replicas = select_replicas(peers.clone(), "node-0", Some("az-1"), 1);
+        assert_eq!(replicas.len(), 1);
+        assert_eq!(replicas[0].node_id, "node-3");
+
+        let replicas = select_replicas(peers.clone(), "node-0", Some("az-1"), 2);
+        assert_eq!(replicas.len(), 2);
+        assert_eq!(replicas[0].node_id, "node-3");
+
+        let replicas = select_replicas(peers.clone(), "node-0", Some("az-1"), 4);
+        assert_eq!(replicas.len(), 3);
+
+        // The selection is stable.
+        assert_eq!(
+            select_replicas(peers.clone(), "node-0", None, 2),
+            select_replicas(peers, "node-0", None, 2)
+        );
+    }
+
+    #[test]
+    fn test_replica_request() {
+        let doc_batch = |index_id: &str| DocBatch {
+            index_id: index_id.to_string(),
+            concat_docs: vec![1; 60].into(),
+            doc_lens: vec![30; 2],
+            idempotency_key: Some("batch-1".to_string()),
+            leader_position: None,
+        };
+        let ingest_request = IngestRequest {
+            doc_batches: vec![
+                doc_batch("index-1"),
+                doc_batch("index-2"),
+                doc_batch("index-3"),
+            ],
+        };
+        // The leader has no position for the first batch, so it is not replicated.
+        let leader_response = IngestResponse {
+            num_docs_for_processing: 2,
+            num_duplicate_docs: 0,
+            partition_id: "partition-0".to_string(),
+            batch_last_positions: HashMap::from([(1, 5), (2, 3)]),
+            batch_shard_ords: HashMap::from([(2, 1)]),
+        };
+        let replica_request = replica_request("node-0", ingest_request, &leader_response);
+        assert_eq!(replica_request.doc_batches.len(), 2);
+
+        let replica_batch = &replica_request.doc_batches[0];
+        assert_eq!(
+            replica_batch.index_id,
+            replica_queue_id("node-0", "index-2")
+        );
+        assert_eq!(replica_batch.idempotency_key.as_deref(), Some("batch-1"));
+        assert_eq!(
+            replica_batch.leader_position,
+            Some(LeaderPosition {
+                partition_id: "partition-0".to_string(),
+                last_position: 5,
+            })
+        );
+        // The batch appended to the second shard is tagged with the partition of the shard.
+        assert_eq!(
+            replica_request.doc_batches[1].leader_position,
+            Some(LeaderPosition {
+                partition_id: shard_partition_id("partition-0", 1),
+                last_position: 3,
+            })
+        );
+    }
+}
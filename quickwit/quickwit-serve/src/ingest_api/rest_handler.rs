@@ -17,28 +17,48 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Instant;
 
 use bytes::Bytes;
+use quickwit_config::{build_doc_mapper, QuickwitConfig, INGEST_API_SOURCE_ID};
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_doc_mapper::CsvDocParser;
 use quickwit_ingest_api::{
-    DocBatchBuilder, FetchResponse, IngestRequest, IngestResponse, IngestService,
-    IngestServiceClient, IngestServiceError, TailRequest,
+    get_ingest_api_service, DocBatchBuilder, FetchResponse, IngestRequest, IngestResponse,
+    IngestService, IngestServiceClient, IngestServiceError, RecoverReplicas, TailRequest,
+    QUEUES_DIR_NAME,
 };
+use quickwit_metastore::{Metastore, MetastoreError};
 use quickwit_proto::{ServiceError, ServiceErrorCode};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use thiserror::Error;
+use tracing::warn;
 use warp::{reject, Filter, Rejection};
 
+use super::replication::IngestReplicator;
+use super::routing::{
+    create_missing_indexes, create_queues, resolve_write_aliases, resolve_write_index_id,
+    route_docs, RoutingOptions,
+};
+use super::upsert::build_upsert_delete_queries;
 use crate::format::{extract_format_from_qs, make_response};
+use crate::quota_api::{enforce_ingest_quotas, QuotaExceeded, QuotaTracker};
 use crate::{with_arg, BodyFormat};
 
 #[derive(utoipa::OpenApi)]
-#[openapi(paths(ingest, tail_endpoint, elastic_ingest,))]
+#[openapi(paths(ingest, routed_ingest, tail_endpoint, recover_replicas, elastic_ingest,))]
 pub struct IngestApi;
 
 #[derive(utoipa::OpenApi)]
 #[openapi(components(schemas(
+    ElasticBulkResponse,
+    IngestAck,
+    IngestOp,
+    RecoverReplicasResponse,
     quickwit_ingest_api::DocBatch,
     quickwit_ingest_api::FetchResponse,
     quickwit_ingest_api::IngestResponse,
@@ -51,6 +71,20 @@ struct InvalidUtf8;
 
 impl warp::reject::Reject for InvalidUtf8 {}
 
+/// Rejection returned when a request body cannot be decoded according to its `Content-Encoding`
+/// header.
+#[derive(Debug, Error)]
+pub(crate) enum ContentEncodingError {
+    #[error("Unsupported content encoding `{0}`. Supported encodings are `gzip` and `zstd`.")]
+    Unsupported(String),
+    #[error("Failed to decompress body: {0}")]
+    InvalidBody(String),
+    #[error("Decompressed body exceeds the limit of {0} bytes.")]
+    BodyTooLarge(u64),
+}
+
+impl warp::reject::Reject for ContentEncodingError {}
+
 const CONTENT_LENGTH_LIMIT: u64 = 10 * 1024 * 1024; // 10MiB
 
 #[derive(Error, Debug)]
@@ -59,8 +93,24 @@ pub enum IngestRestApiError {
     BulkInvalidAction(String),
     #[error("Failed to parse source `{0}`.")]
     BulkInvalidSource(String),
+    #[error("Invalid upsert request: {0}")]
+    InvalidUpsert(String),
+    #[error("Invalid CSV payload: {0}")]
+    InvalidCsv(String),
+    #[error("Failed to route documents: {0}")]
+    InvalidRouting(String),
+    #[error("Failed to create index: {0}")]
+    IndexCreation(String),
+    #[error("Invalid alias: {0}")]
+    InvalidAlias(String),
+    #[error(transparent)]
+    IndexService(#[from] IndexServiceError),
     #[error(transparent)]
     IngestApi(#[from] IngestServiceError),
+    #[error(transparent)]
+    Metastore(#[from] MetastoreError),
+    #[error(transparent)]
+    Quota(#[from] QuotaExceeded),
 }
 
 impl ServiceError for IngestRestApiError {
@@ -68,7 +118,15 @@ impl ServiceError for IngestRestApiError {
         match self {
             Self::BulkInvalidAction(_) => ServiceErrorCode::BadRequest,
             Self::BulkInvalidSource(_) => ServiceErrorCode::BadRequest,
+            Self::InvalidUpsert(_) => ServiceErrorCode::BadRequest,
+            Self::InvalidCsv(_) => ServiceErrorCode::BadRequest,
+            Self::InvalidRouting(_) => ServiceErrorCode::BadRequest,
+            Self::IndexCreation(_) => ServiceErrorCode::BadRequest,
+            Self::InvalidAlias(_) => ServiceErrorCode::BadRequest,
+            Self::IndexService(index_service_error) => index_service_error.status_code(),
             Self::IngestApi(ingest_api_error) => ingest_api_error.status_code(),
+            Self::Metastore(metastore_error) => metastore_error.status_code(),
+            Self::Quota(quota_error) => quota_error.status_code(),
         }
     }
 }
@@ -78,52 +136,267 @@ impl ServiceError for IngestRestApiError {
 enum BulkAction {
     Index(BulkActionMeta),
     Create(BulkActionMeta),
+    Update(BulkActionMeta),
+    Delete(BulkActionMeta),
 }
 
 impl BulkAction {
-    fn into_index(self) -> String {
+    fn name(&self) -> &'static str {
+        match self {
+            BulkAction::Index(_) => "index",
+            BulkAction::Create(_) => "create",
+            BulkAction::Update(_) => "update",
+            BulkAction::Delete(_) => "delete",
+        }
+    }
+
+    /// Only the `index` and `create` actions are supported. They both append the document to the
+    /// index.
+    fn is_supported(&self) -> bool {
+        matches!(self, BulkAction::Index(_) | BulkAction::Create(_))
+    }
+
+    /// Returns whether the action line is followed by a source line.
+    fn has_source(&self) -> bool {
+        !matches!(self, BulkAction::Delete(_))
+    }
+
+    fn into_meta(self) -> BulkActionMeta {
         match self {
-            BulkAction::Index(meta) => meta.index,
-            BulkAction::Create(meta) => meta.index,
+            BulkAction::Index(meta)
+            | BulkAction::Create(meta)
+            | BulkAction::Update(meta)
+            | BulkAction::Delete(meta) => meta,
         }
     }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq)]
 struct BulkActionMeta {
+    /// Target index of the action. Defaults to the index of the request path.
     #[serde(alias = "_index")]
-    index: String,
+    #[serde(default)]
+    index: Option<String>,
     #[serde(alias = "_id")]
     #[serde(default)]
     id: Option<String>,
 }
 
+/// Response of the Elasticsearch compatible bulk API.
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct ElasticBulkResponse {
+    /// Time spent processing the request in milliseconds.
+    took: u64,
+    /// Whether at least one action failed.
+    errors: bool,
+    /// Outcome of each action, in the order of the request, keyed by the action name.
+    #[schema(value_type = Vec<Object>)]
+    items: Vec<BTreeMap<&'static str, ElasticBulkItem>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ElasticBulkItem {
+    #[serde(rename = "_index")]
+    index_id_opt: Option<String>,
+    #[serde(rename = "_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    doc_id_opt: Option<String>,
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ElasticBulkError>,
+}
+
+impl ElasticBulkItem {
+    fn set_error(&mut self, status: u16, error_type: &'static str, reason: String) {
+        self.status = status;
+        self.result = None;
+        self.error = Some(ElasticBulkError { error_type, reason });
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ElasticBulkError {
+    #[serde(rename = "type")]
+    error_type: &'static str,
+    reason: String,
+}
+
+/// The operation applied to the documents of an ingest request.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestOp {
+    /// Appends the documents to the index.
+    #[default]
+    Index,
+    /// Appends the documents to the index and supersedes the previous versions of the documents
+    /// sharing the same doc unique ID.
+    Upsert,
+}
+
+/// The durability guarantee of an ingest request when it is acknowledged.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestAck {
+    /// The request is acknowledged as soon as it is validated, before the documents are
+    /// persisted. Documents may be lost if they fail to be ingested afterwards.
+    None,
+    /// The request is acknowledged once the documents are persisted in the write-ahead log of the
+    /// indexer ingesting them.
+    #[default]
+    Leader,
+    /// The request is acknowledged once the documents are persisted in the write-ahead logs of
+    /// the indexer ingesting them and of `replication_factor` peer indexers.
+    Replicas,
+}
+
+/// This struct represents the QueryString passed to the ingest REST API.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IngestOptions {
+    /// The operation applied to the ingested documents: `index` (default) or `upsert`.
+    #[serde(default)]
+    pub op: IngestOp,
+    /// When the request is acknowledged: `none`, `leader` (default), or `replicas`.
+    #[serde(default)]
+    pub ack: IngestAck,
+}
+
 pub(crate) fn ingest_api_handlers(
     ingest_service: IngestServiceClient,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+    ingest_replicator: Arc<IngestReplicator>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    ingest_handler(ingest_service.clone())
-        .or(tail_handler(ingest_service.clone()))
-        .or(elastic_bulk_handler(ingest_service))
+    let max_decompressed_body_size = quickwit_config
+        .ingest_api_config
+        .max_decompressed_body_size
+        .get_bytes();
+    ingest_handler(
+        ingest_service.clone(),
+        index_service.clone(),
+        quickwit_config.clone(),
+        quota_tracker.clone(),
+        ingest_replicator,
+        max_decompressed_body_size,
+    )
+    .or(routed_ingest_handler(
+        ingest_service.clone(),
+        index_service.clone(),
+        quickwit_config.clone(),
+        quota_tracker.clone(),
+        max_decompressed_body_size,
+    ))
+    .or(tail_handler(ingest_service.clone()))
+    .or(recover_replicas_handler(
+        index_service.clone(),
+        quickwit_config.clone(),
+    ))
+    .or(elastic_bulk_handler(
+        ingest_service,
+        index_service,
+        quickwit_config,
+        quota_tracker,
+        max_decompressed_body_size,
+    ))
+}
+
+/// Decompresses a request body according to its `Content-Encoding` header. The decompressed body
+/// is limited to `max_decompressed_body_size` bytes.
+fn decompress_body(
+    content_encoding_opt: Option<&str>,
+    body: Bytes,
+    max_decompressed_body_size: u64,
+) -> Result<Bytes, ContentEncodingError> {
+    let content_encoding = match content_encoding_opt.map(str::trim) {
+        None | Some("") | Some("identity") => return Ok(body),
+        Some(content_encoding) => content_encoding.to_ascii_lowercase(),
+    };
+    let decoder: Box<dyn Read + '_> = match content_encoding.as_str() {
+        "gzip" | "x-gzip" => Box::new(flate2::read::MultiGzDecoder::new(body.as_ref())),
+        "zstd" => {
+            let decoder = zstd::stream::read::Decoder::new(body.as_ref())
+                .map_err(|error| ContentEncodingError::InvalidBody(error.to_string()))?;
+            Box::new(decoder)
+        }
+        _ => return Err(ContentEncodingError::Unsupported(content_encoding)),
+    };
+    let mut decompressed_body = Vec::new();
+    decoder
+        .take(max_decompressed_body_size + 1)
+        .read_to_end(&mut decompressed_body)
+        .map_err(|error| ContentEncodingError::InvalidBody(error.to_string()))?;
+    if decompressed_body.len() as u64 > max_decompressed_body_size {
+        return Err(ContentEncodingError::BodyTooLarge(
+            max_decompressed_body_size,
+        ));
+    }
+    Ok(Bytes::from(decompressed_body))
+}
+
+/// Extracts the request body, decompressed if necessary, as a UTF-8 string.
+fn body_filter(
+    max_decompressed_body_size: u64,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("content-encoding")
+        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+        .and(warp::body::bytes())
+        .and_then(
+            move |content_encoding_opt: Option<String>, body: Bytes| async move {
+                let body = decompress_body(
+                    content_encoding_opt.as_deref(),
+                    body,
+                    max_decompressed_body_size,
+                )
+                .map_err(reject::custom)?;
+                if let Ok(body_str) = std::str::from_utf8(&body) {
+                    Ok(body_str.to_string())
+                } else {
+                    Err(reject::custom(InvalidUtf8))
+                }
+            },
+        )
 }
 
-fn ingest_filter() -> impl Filter<Extract = (String, String), Error = Rejection> + Clone {
+/// Header carrying the idempotency key of an ingest request.
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+fn ingest_filter(
+    max_decompressed_body_size: u64,
+) -> impl Filter<
+    Extract = (
+        String,
+        IngestOptions,
+        Option<String>,
+        Option<String>,
+        String,
+    ),
+    Error = Rejection,
+> + Clone {
     warp::path!(String / "ingest")
         .and(warp::post())
-        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
-        .and(warp::body::bytes().and_then(|body: Bytes| async move {
-            if let Ok(body_str) = std::str::from_utf8(&body) {
-                Ok(body_str.to_string())
-            } else {
-                Err(reject::custom(InvalidUtf8))
-            }
-        }))
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::header::optional::<String>(IDEMPOTENCY_KEY_HEADER))
+        .and(body_filter(max_decompressed_body_size))
 }
 
 fn ingest_handler(
     ingest_service: IngestServiceClient,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+    ingest_replicator: Arc<IngestReplicator>,
+    max_decompressed_body_size: u64,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    ingest_filter()
+    ingest_filter(max_decompressed_body_size)
         .and(with_arg(ingest_service))
+        .and(with_arg(index_service))
+        .and(with_arg(quickwit_config))
+        .and(with_arg(quota_tracker))
+        .and(with_arg(ingest_replicator))
         .then(ingest)
         .map(|result| BodyFormat::default().make_rest_reply(result))
 }
@@ -138,35 +411,423 @@ fn lines(body: &str) -> impl Iterator<Item = &str> {
     })
 }
 
+fn is_csv_content_type(content_type_opt: Option<&str>) -> bool {
+    content_type_opt
+        .map(|content_type| content_type.trim_start().starts_with("text/csv"))
+        .unwrap_or(false)
+}
+
+/// Converts a CSV payload, whose first row is the header, into JSON documents. The payload is
+/// rejected as a whole if any of its rows is invalid.
+async fn csv_payload_to_json_docs(
+    index_id: &str,
+    payload: &str,
+    metastore: &dyn Metastore,
+) -> Result<Vec<String>, IngestRestApiError> {
+    let index_config = metastore
+        .index_metadata(index_id)
+        .await?
+        .into_index_config();
+    let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+        .map_err(|error| IngestRestApiError::InvalidCsv(error.to_string()))?;
+    let csv_doc_parser = CsvDocParser::new(doc_mapper.schema(), b',');
+    let (docs, row_errors) = csv_doc_parser.parse_payload(payload.as_bytes(), None);
+    if !row_errors.is_empty() {
+        let row_errors_str = row_errors
+            .iter()
+            .map(|row_error| row_error.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Err(IngestRestApiError::InvalidCsv(row_errors_str));
+    }
+    let json_docs = docs
+        .into_iter()
+        .map(|doc| JsonValue::Object(doc).to_string())
+        .collect();
+    Ok(json_docs)
+}
+
 #[utoipa::path(
     post,
     tag = "Ingest",
     path = "{index_id}/ingest",
-    request_body(content = String, description = "Documents to ingest in NDJSON format and limited to 10MB", content_type = "application/json"),
+    request_body(content = String, description = "Documents to ingest in NDJSON format, or in CSV format with a header row if the content type is `text/csv`, and limited to 10MB. The body may be compressed with `gzip` or `zstd`", content_type = "application/json"),
     responses(
         (status = 200, description = "Successfully ingested documents.", body = IngestResponse)
     ),
     params(
+        IngestOptions,
         ("index_id" = String, Path, description = "The index ID to add docs to."),
     )
 )]
 /// Ingest documents
+///
+/// With `op=upsert`, each document supersedes the documents of the index sharing the same doc
+/// unique ID and a timestamp in an older second. The index must enable upserts with
+/// `doc_mapping.enable_upserts`. The delete tasks superseding the previous versions are created
+/// before the documents are ingested: the request fails if they cannot be created, and the
+/// previous versions may be deleted even if the ingestion fails afterwards, in which case the
+/// request should be retried.
+///
+/// With the `text/csv` content type, the columns named in the header row are mapped to the fields
+/// of the doc mapping and the values are converted to the fields' types. The request is rejected
+/// if any row is invalid.
+///
+/// Bodies compressed with `gzip` or `zstd` are decompressed according to the `Content-Encoding`
+/// header.
+///
+/// If the index ID is an alias pointing to a single index, such as the write alias of a rollover
+/// policy, the documents are ingested into that index. Otherwise, if the index does not exist, it
+/// is created from the matching index template with the highest priority.
+///
+/// Requests exceeding the quota of the index are rejected with a `429 Too Many Requests` status
+/// code, or delayed if the quota throttles the ingestion.
+///
+/// With `ack=replicas`, the request is acknowledged once the documents are also persisted by
+/// `replication_factor` peer indexers, and is rejected with a `503 Service Unavailable` status
+/// code if not enough indexers are available. With `ack=none`, the request is acknowledged as
+/// soon as it is validated.
+///
+/// With `op=upsert`, the delete tasks superseding the previous versions of the documents are
+/// created once the documents are ingested. If they cannot be created, the request fails but the
+/// documents are ingested, and retrying it, ideally with an `Idempotency-Key` header, is safe.
+///
+/// The documents of a request with an `Idempotency-Key` header are dropped if a request with the
+/// same key was already ingested into the index by the indexer within
+/// `idempotency_key_retention_secs`, so that retried requests are not ingested twice.
 async fn ingest(
     index_id: String,
+    ingest_options: IngestOptions,
+    content_type_opt: Option<String>,
+    idempotency_key_opt: Option<String>,
     payload: String,
     mut ingest_service: IngestServiceClient,
-) -> Result<IngestResponse, IngestServiceError> {
-    let mut doc_batch = DocBatchBuilder::new(index_id);
-    for doc_payload in lines(&payload) {
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+    ingest_replicator: Arc<IngestReplicator>,
+) -> Result<IngestResponse, IngestRestApiError> {
+    let metastore = index_service.metastore();
+    let is_csv = is_csv_content_type(content_type_opt.as_deref());
+
+    // The CSV and upsert paths need the index config before ingesting anything.
+    let index_id = if is_csv || ingest_options.op == IngestOp::Upsert {
+        let index_id = resolve_write_index_id(&*metastore, index_id).await?;
+        create_missing_indexes(
+            std::iter::once(index_id.as_str()),
+            None,
+            &index_service,
+            &quickwit_config,
+        )
+        .await?;
+        index_id
+    } else {
+        index_id
+    };
+    let csv_docs;
+    let doc_payloads: Vec<&str> = if is_csv {
+        csv_docs = csv_payload_to_json_docs(&index_id, &payload, &*metastore).await?;
+        csv_docs.iter().map(String::as_str).collect()
+    } else {
+        lines(&payload).collect()
+    };
+    // Upserted documents are validated before anything gets ingested.
+    let delete_queries = if ingest_options.op == IngestOp::Upsert {
+        let index_config = metastore
+            .index_metadata(&index_id)
+            .await?
+            .into_index_config();
+        let doc_mapper = build_doc_mapper(&index_config.doc_mapping, &index_config.search_settings)
+            .map_err(|error| IngestRestApiError::InvalidUpsert(error.to_string()))?;
+        build_upsert_delete_queries(&index_id, &*doc_mapper, doc_payloads.iter().copied())?
+    } else {
+        Vec::new()
+    };
+    let num_docs = doc_payloads.len();
+    let mut doc_batch = DocBatchBuilder::new(index_id.clone());
+    for doc_payload in doc_payloads {
         doc_batch.ingest_doc(doc_payload.as_bytes());
     }
+    let mut doc_batch = doc_batch.build();
+    doc_batch.idempotency_key = idempotency_key_opt;
     let ingest_req = IngestRequest {
-        doc_batches: vec![doc_batch.build()],
+        doc_batches: vec![doc_batch],
+    };
+    enforce_ingest_quotas(&quota_tracker, &ingest_req).await?;
+
+    let ingest_replicator_opt = if ingest_options.ack == IngestAck::Replicas {
+        Some(ingest_replicator)
+    } else {
+        None
+    };
+    let ingest_future = async move {
+        let ingest_response = ingest_creating_missing_indexes(
+            &mut ingest_service,
+            ingest_req,
+            &index_service,
+            &quickwit_config,
+            ingest_replicator_opt.as_deref(),
+        )
+        .await?;
+        // The delete tasks superseding the previous versions are only created once the new
+        // versions are ingested, so that a failed ingestion never loses the previous versions.
+        // Their exclusive `end_timestamp` guarantees they never match the upserted documents.
+        for delete_query in delete_queries {
+            metastore.create_delete_task(delete_query).await?;
+        }
+        Ok::<_, IngestRestApiError>(ingest_response)
     };
-    let ingest_response = ingest_service.ingest(ingest_req).await?;
+    if ingest_options.ack == IngestAck::None {
+        tokio::spawn(async move {
+            if let Err(error) = ingest_future.await {
+                warn!(
+                    index_id=%index_id,
+                    error=?error,
+                    "Failed to ingest unacknowledged documents."
+                );
+            }
+        });
+        return Ok(IngestResponse {
+            num_docs_for_processing: num_docs as u64,
+            ..Default::default()
+        });
+    }
+    ingest_future.await
+}
+
+/// Ingests the documents within the quotas of the target indexes, resolving the target aliases and
+/// creating the target indexes that do not exist yet from the matching index templates.
+async fn ingest_or_create_indexes(
+    ingest_service: &mut IngestServiceClient,
+    ingest_request: IngestRequest,
+    index_service: &IndexService,
+    quickwit_config: &QuickwitConfig,
+    quota_tracker: &QuotaTracker,
+) -> Result<IngestResponse, IngestRestApiError> {
+    enforce_ingest_quotas(quota_tracker, &ingest_request).await?;
+    ingest_creating_missing_indexes(
+        ingest_service,
+        ingest_request,
+        index_service,
+        quickwit_config,
+        None,
+    )
+    .await
+}
+
+/// Ingests the documents, replicating them with the ingest replicator if any. The target aliases
+/// are resolved into the indexes they point to, and the target indexes that do not exist yet are
+/// created from the matching index templates.
+async fn ingest_creating_missing_indexes(
+    ingest_service: &mut IngestServiceClient,
+    ingest_request: IngestRequest,
+    index_service: &IndexService,
+    quickwit_config: &QuickwitConfig,
+    ingest_replicator_opt: Option<&IngestReplicator>,
+) -> Result<IngestResponse, IngestRestApiError> {
+    match ingest_with_replicator(
+        ingest_service,
+        ingest_request.clone(),
+        ingest_replicator_opt,
+    )
+    .await
+    {
+        Err(IngestServiceError::IndexNotFound { .. }) => {
+            let mut ingest_request = ingest_request;
+            let write_index_ids =
+                resolve_write_aliases(&mut ingest_request, &*index_service.metastore()).await?;
+            let index_ids = ingest_request
+                .doc_batches
+                .iter()
+                .map(|doc_batch| doc_batch.index_id.as_str());
+            create_missing_indexes(index_ids, None, index_service, quickwit_config).await?;
+            create_queues(write_index_ids.iter().map(String::as_str), quickwit_config).await?;
+            let ingest_response =
+                ingest_with_replicator(ingest_service, ingest_request, ingest_replicator_opt)
+                    .await?;
+            Ok(ingest_response)
+        }
+        ingest_result => Ok(ingest_result?),
+    }
+}
+
+async fn ingest_with_replicator(
+    ingest_service: &mut IngestServiceClient,
+    ingest_request: IngestRequest,
+    ingest_replicator_opt: Option<&IngestReplicator>,
+) -> Result<IngestResponse, IngestServiceError> {
+    match ingest_replicator_opt {
+        Some(ingest_replicator) => {
+            ingest_replicator
+                .ingest(ingest_service, ingest_request)
+                .await
+        }
+        None => ingest_service.ingest(ingest_request).await,
+    }
+}
+
+fn routed_ingest_filter(
+    max_decompressed_body_size: u64,
+) -> impl Filter<Extract = (RoutingOptions, Option<String>, String), Error = Rejection> + Clone {
+    warp::path!("_ingest")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::header::optional::<String>(IDEMPOTENCY_KEY_HEADER))
+        .and(body_filter(max_decompressed_body_size))
+}
+
+fn routed_ingest_handler(
+    ingest_service: IngestServiceClient,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+    max_decompressed_body_size: u64,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    routed_ingest_filter(max_decompressed_body_size)
+        .and(with_arg(ingest_service))
+        .and(with_arg(index_service))
+        .and(with_arg(quickwit_config))
+        .and(with_arg(quota_tracker))
+        .then(routed_ingest)
+        .map(|result| BodyFormat::default().make_rest_reply(result))
+}
+
+#[utoipa::path(
+    post,
+    tag = "Ingest",
+    path = "/_ingest",
+    request_body(content = String, description = "Documents to ingest in NDJSON format, limited to 10MB. The body may be compressed with `gzip` or `zstd`", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Successfully ingested documents.", body = IngestResponse)
+    ),
+    params(RoutingOptions),
+)]
+/// Routed Ingest
+///
+/// Routes each document to the index whose ID is built from the index ID template and the value
+/// of the routing field. With `template_index_id`, the target indexes that do not exist yet are
+/// created with the config of the template index.
+///
+/// With an `Idempotency-Key` header, the documents routed to an index are dropped if a request
+/// with the same key was already ingested into that index.
+async fn routed_ingest(
+    routing_options: RoutingOptions,
+    idempotency_key_opt: Option<String>,
+    payload: String,
+    mut ingest_service: IngestServiceClient,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+) -> Result<IngestResponse, IngestRestApiError> {
+    let doc_payloads_per_index = route_docs(&routing_options, lines(&payload))?;
+    create_missing_indexes(
+        doc_payloads_per_index.keys().map(String::as_str),
+        routing_options.template_index_id.as_deref(),
+        &index_service,
+        &quickwit_config,
+    )
+    .await?;
+    let doc_batches = doc_payloads_per_index
+        .into_iter()
+        .map(|(index_id, doc_payloads)| {
+            let mut doc_batch = DocBatchBuilder::new(index_id);
+            for doc_payload in doc_payloads {
+                doc_batch.ingest_doc(doc_payload.as_bytes());
+            }
+            let mut doc_batch = doc_batch.build();
+            doc_batch.idempotency_key = idempotency_key_opt.clone();
+            doc_batch
+        })
+        .collect();
+    let ingest_request = IngestRequest { doc_batches };
+    enforce_ingest_quotas(&quota_tracker, &ingest_request).await?;
+    let ingest_response = ingest_service.ingest(ingest_request).await?;
     Ok(ingest_response)
 }
 
+/// Response of the replica recovery REST API.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct RecoverReplicasResponse {
+    /// Number of replicated documents appended to the queues of their indexes.
+    pub num_recovered_docs: u64,
+}
+
+fn recover_replicas_handler(
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_ingest" / "replicas" / String / "recover")
+        .and(warp::post())
+        .and(with_arg(index_service))
+        .and(with_arg(quickwit_config))
+        .then(recover_replicas)
+        .map(|result| BodyFormat::default().make_rest_reply(result))
+}
+
+#[utoipa::path(
+    post,
+    tag = "Ingest",
+    path = "/_ingest/replicas/{leader_node_id}/recover",
+    responses(
+        (status = 200, description = "Successfully recovered replicated documents.", body = RecoverReplicasResponse)
+    ),
+    params(
+        ("leader_node_id" = String, Path, description = "The ID of the lost leader indexer."),
+    )
+)]
+/// Recover Replicas
+///
+/// Appends the documents replicated on this indexer by the lost leader indexer to the queues of
+/// their indexes so that they get indexed, then deletes the replicas. The documents that the
+/// leader had already published are skipped, while the documents that it had indexed but not
+/// published yet are indexed again.
+async fn recover_replicas(
+    leader_node_id: String,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+) -> Result<RecoverReplicasResponse, IngestRestApiError> {
+    let queues_dir_path = quickwit_config.data_dir_path.join(QUEUES_DIR_NAME);
+    let ingest_api_service = get_ingest_api_service(&queues_dir_path)
+        .await
+        .map_err(|_| IngestServiceError::Unavailable)?;
+    let published_positions = ingest_api_published_positions(&*index_service.metastore()).await?;
+    let num_recovered_docs = ingest_api_service
+        .ask_for_res(RecoverReplicas {
+            leader_node_id,
+            published_positions,
+        })
+        .await
+        .map_err(IngestServiceError::from)?;
+    Ok(RecoverReplicasResponse {
+        num_recovered_docs: num_recovered_docs as u64,
+    })
+}
+
+/// Returns the last positions published by the ingest API source, per index ID and partition ID.
+async fn ingest_api_published_positions(
+    metastore: &dyn Metastore,
+) -> Result<HashMap<String, HashMap<String, u64>>, MetastoreError> {
+    let mut published_positions = HashMap::new();
+
+    for index_metadata in metastore.list_indexes_metadatas().await? {
+        let Some(source_checkpoint) = index_metadata
+            .checkpoint
+            .source_checkpoint(INGEST_API_SOURCE_ID)
+        else {
+            continue;
+        };
+        let partition_positions: HashMap<String, u64> = source_checkpoint
+            .iter()
+            .filter_map(|(partition_id, position)| {
+                let position = position.as_str().parse::<u64>().ok()?;
+                Some((partition_id.0.to_string(), position))
+            })
+            .collect();
+        published_positions.insert(index_metadata.index_id().to_string(), partition_positions);
+    }
+    Ok(published_positions)
+}
+
 pub fn tail_handler(
     ingest_service: IngestServiceClient,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
@@ -202,24 +863,29 @@ async fn tail_endpoint(
     Ok(fetch_response)
 }
 
-fn elastic_bulk_filter() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+fn elastic_bulk_filter(
+    max_decompressed_body_size: u64,
+) -> impl Filter<Extract = (Option<String>, String), Error = Rejection> + Clone {
     warp::path!("_bulk")
+        .map(|| None::<String>)
+        .or(warp::path!(String / "_bulk").map(Some))
+        .unify()
         .and(warp::post())
-        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
-        .and(warp::body::bytes().and_then(|body: Bytes| async move {
-            if let Ok(body_str) = std::str::from_utf8(&body) {
-                Ok(body_str.to_string())
-            } else {
-                Err(reject::custom(InvalidUtf8))
-            }
-        }))
+        .and(body_filter(max_decompressed_body_size))
 }
 
 pub fn elastic_bulk_handler(
     ingest_service: IngestServiceClient,
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+    max_decompressed_body_size: u64,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    elastic_bulk_filter()
+    elastic_bulk_filter(max_decompressed_body_size)
         .and(with_arg(ingest_service))
+        .and(with_arg(index_service))
+        .and(with_arg(quickwit_config))
+        .and(with_arg(quota_tracker))
         .then(elastic_ingest)
         .and(extract_format_from_qs())
         .map(make_response)
@@ -229,61 +895,168 @@ pub fn elastic_bulk_handler(
     post,
     tag = "Ingest",
     path = "/_bulk",
-    request_body(content = String, description = "Elasticsearch compatible bulk request body limited to 10MB", content_type = "application/json"),
+    request_body(content = String, description = "Elasticsearch compatible bulk request body limited to 10MB. The body may be compressed with `gzip` or `zstd`", content_type = "application/json"),
     responses(
-        (status = 200, description = "Successfully ingested documents.", body = IngestResponse)
+        (status = 200, description = "Successfully processed the bulk request. The outcome of each action is reported in `items`.", body = ElasticBulkResponse)
     ),
 )]
 /// Elasticsearch Bulk Ingest
+///
+/// Also served on `/{index_id}/_bulk`, in which case `index_id` is the default target index of
+/// the actions. The `index` and `create` actions append the document to the index, the other
+/// actions are reported as failed. The target indexes that do not exist are created from the
+/// matching index templates.
 async fn elastic_ingest(
+    default_index_id_opt: Option<String>,
     payload: String,
     mut ingest_service: IngestServiceClient,
-) -> Result<IngestResponse, IngestRestApiError> {
-    let mut batches = HashMap::new();
+    index_service: Arc<IndexService>,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+) -> Result<ElasticBulkResponse, IngestRestApiError> {
+    let start = Instant::now();
+    let mut items: Vec<(&'static str, ElasticBulkItem)> = Vec::new();
+    // Documents and item ordinals of each target index.
+    let mut batches: BTreeMap<String, (DocBatchBuilder, Vec<usize>)> = BTreeMap::new();
     let mut payload_lines = lines(&payload);
 
     while let Some(json_str) = payload_lines.next() {
         let action = serde_json::from_str::<BulkAction>(json_str)
             .map_err(|e| IngestRestApiError::BulkInvalidAction(e.to_string()))?;
-        let source = payload_lines
-            .next()
-            .ok_or_else(|| {
+        let source_opt = if action.has_source() {
+            let source = payload_lines.next().ok_or_else(|| {
                 IngestRestApiError::BulkInvalidSource("Expected source for the action.".to_string())
-            })
-            .and_then(|source| {
-                serde_json::from_str::<JsonValue>(source)
-                    .map_err(|err| IngestRestApiError::BulkInvalidSource(err.to_string()))
             })?;
-
-        let index_id = action.into_index();
-        let doc_batch = batches
-            .entry(index_id.clone())
-            .or_insert(DocBatchBuilder::new(index_id));
-
-        doc_batch.ingest_doc(source.to_string().as_bytes());
+            Some(source)
+        } else {
+            None
+        };
+        let action_name = action.name();
+        let is_supported = action.is_supported();
+        let meta = action.into_meta();
+        let index_id_opt = meta.index.or_else(|| default_index_id_opt.clone());
+        let mut item = ElasticBulkItem {
+            index_id_opt: index_id_opt.clone(),
+            doc_id_opt: meta.id,
+            status: 201,
+            result: Some("created"),
+            error: None,
+        };
+        let Some(index_id) = index_id_opt else {
+            item.set_error(
+                400,
+                "action_request_validation_exception",
+                "The target index is missing.".to_string(),
+            );
+            items.push((action_name, item));
+            continue;
+        };
+        if !is_supported {
+            item.set_error(
+                400,
+                "illegal_argument_exception",
+                format!("The `{action_name}` action is not supported."),
+            );
+            items.push((action_name, item));
+            continue;
+        }
+        let source = source_opt.expect("The `index` and `create` actions have a source.");
+        match serde_json::from_str::<JsonValue>(source) {
+            Ok(JsonValue::Object(_)) => {
+                let (doc_batch, item_ords) = batches
+                    .entry(index_id.clone())
+                    .or_insert_with(|| (DocBatchBuilder::new(index_id), Vec::new()));
+                doc_batch.ingest_doc(source.as_bytes());
+                item_ords.push(items.len());
+            }
+            Ok(_) => item.set_error(
+                400,
+                "mapper_parsing_exception",
+                "The source must be a JSON object.".to_string(),
+            ),
+            Err(error) => item.set_error(400, "mapper_parsing_exception", error.to_string()),
+        }
+        items.push((action_name, item));
     }
-
-    let ingest_request = IngestRequest {
-        doc_batches: batches
-            .into_values()
-            .map(|builder| builder.build())
-            .collect(),
-    };
-    let ingest_response = ingest_service.ingest(ingest_request).await?;
-    Ok(ingest_response)
+    // Each index is ingested separately so that a failure only affects the actions targeting
+    // the failing index.
+    for (doc_batch, item_ords) in batches.into_values() {
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch.build()],
+        };
+        if let Err(ingest_error) = ingest_or_create_indexes(
+            &mut ingest_service,
+            ingest_request,
+            &index_service,
+            &quickwit_config,
+            &quota_tracker,
+        )
+        .await
+        {
+            let status_code = ingest_error.status_code();
+            let error_type = match status_code {
+                ServiceErrorCode::NotFound => "index_not_found_exception",
+                ServiceErrorCode::RateLimited => "es_rejected_execution_exception",
+                _ => "exception",
+            };
+            for item_ord in item_ords {
+                items[item_ord].1.set_error(
+                    status_code.to_http_status_code().as_u16(),
+                    error_type,
+                    ingest_error.to_string(),
+                );
+            }
+        }
+    }
+    let errors = items.iter().any(|(_, item)| item.error.is_some());
+    let items = items
+        .into_iter()
+        .map(|(action_name, item)| BTreeMap::from([(action_name, item)]))
+        .collect();
+    Ok(ElasticBulkResponse {
+        took: start.elapsed().as_millis() as u64,
+        errors,
+        items,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::io::Write;
+    use std::sync::Arc;
+
     use byte_unit::Byte;
+    use bytes::Bytes;
+    use chitchat::transport::ChannelTransport;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
     use quickwit_actors::Universe;
-    use quickwit_config::IngestApiConfig;
+    use quickwit_cluster::create_cluster_for_test;
+    use quickwit_common::uri::Uri;
+    use quickwit_config::{
+        load_index_template_from_user_config, ConfigFormat, DocMapping, IndexConfig,
+        IngestApiConfig, QuickwitConfig, QuotaConfig, QuotaExceededAction,
+    };
+    use quickwit_core::IndexService;
     use quickwit_ingest_api::{
-        init_ingest_api, CreateQueueIfNotExistsRequest, FetchResponse, IngestResponse,
+        init_ingest_api, CreateQueueIfNotExistsRequest, DocCommand, FetchResponse, IngestResponse,
         IngestServiceClient, QUEUES_DIR_NAME,
     };
+    use quickwit_metastore::{
+        metastore_for_test, IndexAliasAction, IndexMetadata, Metastore, MetastoreError,
+        MockMetastore,
+    };
+    use quickwit_proto::metastore_api::{DeleteQuery, DeleteTask};
+    use quickwit_storage::StorageUriResolver;
+    use serde_json::Value as JsonValue;
+    use warp::{Filter, Rejection, Reply};
 
-    use super::{ingest_api_handlers, BulkAction, BulkActionMeta};
+    use super::{
+        decompress_body, ingest_api_handlers, BulkAction, BulkActionMeta, ContentEncodingError,
+    };
+    use crate::ingest_api::IngestReplicator;
+    use crate::quota_api::QuotaTracker;
+    use crate::recover_fn;
 
     #[test]
     fn test_bulk_action_serde() {
@@ -298,7 +1071,7 @@ mod tests {
             assert_eq!(
                 bulk_action,
                 BulkAction::Create(BulkActionMeta {
-                    index: "test".to_string(),
+                    index: Some("test".to_string()),
                     id: Some("2".to_string()),
                 })
             );
@@ -313,10 +1086,25 @@ mod tests {
             assert_eq!(
                 bulk_action,
                 BulkAction::Create(BulkActionMeta {
-                    index: "test".to_string(),
+                    index: Some("test".to_string()),
+                    id: None,
+                })
+            );
+        }
+        {
+            let bulk_action_json = r#"{
+                "index": {}
+            }"#;
+            let bulk_action = serde_json::from_str::<BulkAction>(bulk_action_json).unwrap();
+            assert_eq!(
+                bulk_action,
+                BulkAction::Index(BulkActionMeta {
+                    index: None,
                     id: None,
                 })
             );
+            assert!(bulk_action.is_supported());
+            assert!(bulk_action.has_source());
         }
         {
             let bulk_action_json = r#"{
@@ -325,6 +1113,16 @@ mod tests {
                     "_id": "2"
                 }
             }"#;
+            let bulk_action = serde_json::from_str::<BulkAction>(bulk_action_json).unwrap();
+            assert!(!bulk_action.is_supported());
+            assert!(!bulk_action.has_source());
+        }
+        {
+            let bulk_action_json = r#"{
+                "upsert": {
+                    "_index": "test"
+                }
+            }"#;
             serde_json::from_str::<BulkAction>(bulk_action_json).unwrap_err();
         }
     }
@@ -352,11 +1150,38 @@ mod tests {
         (universe, temp_dir, ingest_service)
     }
 
+    async fn ingest_api_handlers_for_test(
+        ingest_service: IngestServiceClient,
+        metastore: Arc<dyn Metastore>,
+        quickwit_config: QuickwitConfig,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let index_service = IndexService::new(metastore.clone(), StorageUriResolver::for_test());
+        let quota_tracker = QuotaTracker::new(metastore, quickwit_config.quotas_config.clone());
+        let transport = ChannelTransport::default();
+        let cluster = create_cluster_for_test(Vec::new(), &["indexer"], &transport, true)
+            .await
+            .unwrap();
+        let ingest_replicator = IngestReplicator::new(&quickwit_config, Arc::new(cluster));
+        ingest_api_handlers(
+            ingest_service,
+            Arc::new(index_service),
+            Arc::new(quickwit_config),
+            Arc::new(quota_tracker),
+            Arc::new(ingest_replicator),
+        )
+        .recover(recover_fn)
+    }
+
     #[tokio::test]
     async fn test_ingest_api_returns_200_when_ingest_json_and_fetch() {
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let resp = warp::test::request()
             .path("/my-index/ingest")
             .method("POST")
@@ -390,7 +1215,12 @@ mod tests {
     async fn test_ingest_api_returns_200_when_ingest_ndjson_and_fetch() {
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let payload = r#"
             {"id": 1, "message": "push"}
             {"id": 2, "message": "push"}
@@ -410,10 +1240,109 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_ingest_api_bulk_request_returns_404_if_index_id_does_not_exist() {
+    async fn test_ingest_api_idempotency_key() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        for (idempotency_key, expected_num_docs, expected_num_duplicate_docs) in
+            [("batch-1", 1, 0), ("batch-1", 0, 1), ("batch-2", 1, 0)]
+        {
+            let resp = warp::test::request()
+                .path("/my-index/ingest")
+                .method("POST")
+                .header("idempotency-key", idempotency_key)
+                .body(r#"{"id": 1, "message": "push"}"#)
+                .reply(&ingest_api_handlers)
+                .await;
+            assert_eq!(resp.status(), 200);
+            let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+            assert_eq!(ingest_response.num_docs_for_processing, expected_num_docs);
+            assert_eq!(
+                ingest_response.num_duplicate_docs,
+                expected_num_duplicate_docs
+            );
+        }
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_ack() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let payload = r#"
+            {"id": 1, "message": "push"}
+            {"id": 2, "message": "push"}
+        "#;
+        let resp = warp::test::request()
+            .path("/my-index/ingest?ack=none")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+
+        let resp = warp::test::request()
+            .path("/my-index/ingest?ack=leader")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        // The node has no peer indexer to replicate the documents to.
+        let resp = warp::test::request()
+            .path("/my-index/ingest?ack=replicas")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 503);
+
+        let resp = warp::test::request()
+            .path("/my-index/ingest?ack=all")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_bulk_request_reports_404_if_index_id_does_not_exist() {
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_exists()
+            .withf(|index_id| index_id == "index-2")
+            .returning(|_| Ok(false));
+        metastore
+            .expect_list_index_templates()
+            .returning(|| Ok(Vec::new()));
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let payload = r#"
             { "create" : { "_index" : "my-index", "_id" : "1"} }
             {"id": 1, "message": "push"}
@@ -426,19 +1355,66 @@ mod tests {
             .body(payload)
             .reply(&ingest_api_handlers)
             .await;
-        assert_eq!(resp.status(), 404);
+        assert_eq!(resp.status(), 200);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], true);
+        let items = bulk_response["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["create"]["_index"], "my-index");
+        assert_eq!(items[0]["create"]["status"], 201);
+        assert_eq!(items[0]["create"]["result"], "created");
+        assert_eq!(items[1]["create"]["_index"], "index-2");
+        assert_eq!(items[1]["create"]["status"], 404);
+        assert_eq!(
+            items[1]["create"]["error"]["type"],
+            "index_not_found_exception"
+        );
         universe.assert_quit().await;
     }
 
     #[tokio::test]
-    async fn test_ingest_api_bulk_request_returns_400_if_malformed_source() {
+    async fn test_ingest_api_bulk_request_reports_400_if_malformed_source() {
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let payload = r#"
             { "create" : { "_index" : "my-index", "_id" : "1" } }
             {"id": 1, "message": "bad json}
         "#;
+        let resp = warp::test::request()
+            .path("/_bulk")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], true);
+        let item = &bulk_response["items"][0]["create"];
+        assert_eq!(item["_id"], "1");
+        assert_eq!(item["status"], 400);
+        assert_eq!(item["error"]["type"], "mapper_parsing_exception");
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_bulk_request_returns_400_if_missing_source() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let payload = r#"
+            { "create" : { "_index" : "my-index", "_id" : "1" } }
+        "#;
         let resp = warp::test::request()
             .path("/_bulk")
             .method("POST")
@@ -453,7 +1429,12 @@ mod tests {
     async fn test_ingest_api_bulk_returns_200() {
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index-1", "my-index-2"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let payload = r#"
             { "create" : { "_index" : "my-index-1", "_id" : "1"} }
             {"id": 1, "message": "push"}
@@ -469,20 +1450,83 @@ mod tests {
             .reply(&ingest_api_handlers)
             .await;
         assert_eq!(resp.status(), 200);
-        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
-        assert_eq!(ingest_response.num_docs_for_processing, 3);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], false);
+        let items = bulk_response["items"].as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["create"]["_index"], "my-index-1");
+        assert_eq!(items[0]["create"]["_id"], "1");
+        assert_eq!(items[1]["create"]["_index"], "my-index-2");
+        assert_eq!(items[2]["create"]["_index"], "my-index-1");
+        assert!(items[2]["create"].get("_id").is_none());
+        for item in items {
+            assert_eq!(item["create"]["status"], 201);
+        }
         universe.assert_quit().await;
     }
 
     #[tokio::test]
-    async fn test_ingest_api_return_429_if_above_limits() {
-        let config = IngestApiConfig {
-            max_queue_memory_usage: Byte::from_bytes(1),
+    async fn test_ingest_api_index_bulk_returns_200() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index-1", "my-index-2"], &IngestApiConfig::default()).await;
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let payload = r#"
+            { "index" : {} }
+            {"id": 1, "message": "push"}
+            { "index" : { "_index" : "my-index-2" } }
+            {"id": 2, "message": "push"}
+            { "delete" : { "_id" : "1" } }
+            { "update" : { "_id" : "2" } }
+            { "doc" : {"message": "pull"} }
+        "#;
+        let resp = warp::test::request()
+            .path("/my-index-1/_bulk")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], true);
+        let items = bulk_response["items"].as_array().unwrap();
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0]["index"]["_index"], "my-index-1");
+        assert_eq!(items[0]["index"]["status"], 201);
+        assert_eq!(items[1]["index"]["_index"], "my-index-2");
+        assert_eq!(items[1]["index"]["status"], 201);
+        assert_eq!(items[2]["delete"]["_index"], "my-index-1");
+        assert_eq!(items[2]["delete"]["status"], 400);
+        assert_eq!(items[3]["update"]["status"], 400);
+
+        let resp = warp::test::request()
+            .path("/my-index-1/tail")
+            .method("GET")
+            .reply(&ingest_api_handlers)
+            .await;
+        let fetch_response: FetchResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(fetch_response.doc_batch.unwrap().doc_lens.len(), 1);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_return_429_if_above_limits() {
+        let config = IngestApiConfig {
+            max_queue_memory_usage: Byte::from_bytes(1),
             ..Default::default()
         };
         let (universe, _temp_dir, ingest_service) =
             setup_ingest_service(&["my-index"], &config).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            QuickwitConfig::for_test(),
+        )
+        .await;
         let resp = warp::test::request()
             .path("/my-index/ingest")
             .method("POST")
@@ -493,4 +1537,620 @@ mod tests {
         assert_eq!(resp.status(), 429);
         universe.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_ingest_api_returns_429_if_quota_exceeded() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.quotas_config.rules.push(QuotaConfig {
+            tenant_id: None,
+            index_id_patterns: vec!["my-*".to_string()],
+            max_ingest_bytes_per_day: Some(Byte::from_bytes(200)),
+            max_stored_bytes: None,
+            on_exceeded: QuotaExceededAction::Reject,
+            throttled_bytes_per_sec: None,
+        });
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            quickwit_config,
+        )
+        .await;
+        let payload = r#"{"id": 1, "message": "push"}"#.repeat(2);
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .body(format!("{payload}\n{payload}"))
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .body(format!("{payload}\n{payload}"))
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 429);
+        let error_body: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert!(error_body["message"]
+            .as_str()
+            .unwrap()
+            .contains("index `my-index` exceeded its daily ingest quota of 200 bytes"));
+
+        let resp = warp::test::request()
+            .path("/_bulk")
+            .method("POST")
+            .body("{\"index\": {\"_index\": \"my-index\"}}\n{\"id\": 2}\n")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], true);
+        assert_eq!(bulk_response["items"][0]["index"]["status"], 429);
+        universe.assert_quit().await;
+    }
+
+    fn index_metadata_for_upsert_test() -> IndexMetadata {
+        let mut index_metadata = IndexMetadata::for_test("my-index", "ram:///indexes/my-index");
+        index_metadata.index_config.doc_mapping = serde_json::from_str::<DocMapping>(
+            r#"{
+                "doc_unique_id_field": "user_id",
+                "timestamp_field": "ts",
+                "enable_upserts": true,
+                "field_mappings": [
+                    {"name": "user_id", "type": "text", "tokenizer": "raw"},
+                    {"name": "ts", "type": "datetime", "fast": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        index_metadata
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_upsert_creates_delete_tasks() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| Ok(index_metadata_for_upsert_test()));
+        metastore
+            .expect_create_delete_task()
+            .withf(|delete_query: &DeleteQuery| {
+                delete_query.index_id == "my-index"
+                    && delete_query.query == r#"user_id:"alice""#
+                    && delete_query.start_timestamp.is_none()
+                    && delete_query.end_timestamp == Some(1000)
+            })
+            .times(1)
+            .returning(|delete_query| {
+                Ok(DeleteTask {
+                    create_timestamp: 0,
+                    opstamp: 1,
+                    delete_query: Some(delete_query),
+                })
+            });
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let resp = warp::test::request()
+            .path("/my-index/ingest?op=upsert")
+            .method("POST")
+            .body(r#"{"user_id": "alice", "ts": 1000}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 1);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_upsert_reports_delete_task_failure_after_ingesting() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| Ok(index_metadata_for_upsert_test()));
+        metastore
+            .expect_create_delete_task()
+            .times(1)
+            .returning(|_delete_query| {
+                Err(MetastoreError::InternalError {
+                    message: "Failed to create delete task.".to_string(),
+                    cause: "".to_string(),
+                })
+            });
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let resp = warp::test::request()
+            .path("/my-index/ingest?op=upsert")
+            .method("POST")
+            .body(r#"{"user_id": "alice", "ts": 1000}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 500);
+
+        // The new version is ingested nonetheless, so the previous one is never lost.
+        let resp = warp::test::request()
+            .path("/my-index/tail")
+            .method("GET")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let fetch_response: FetchResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(fetch_response.doc_batch.unwrap().doc_lens.len(), 1);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_upsert_does_not_create_delete_tasks_if_ingest_fails() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| Ok(index_metadata_for_upsert_test()));
+        metastore.expect_create_delete_task().never();
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        // The node has no peer indexer to replicate the documents to.
+        let resp = warp::test::request()
+            .path("/my-index/ingest?op=upsert&ack=replicas")
+            .method("POST")
+            .body(r#"{"user_id": "alice", "ts": 1000}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 503);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_upsert_returns_400_if_upserts_are_disabled() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| {
+                let mut index_metadata = index_metadata_for_upsert_test();
+                index_metadata.index_config.doc_mapping.enable_upserts = false;
+                Ok(index_metadata)
+            });
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let resp = warp::test::request()
+            .path("/my-index/ingest?op=upsert")
+            .method("POST")
+            .body(r#"{"user_id": "alice", "ts": 1000}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_upsert_returns_400_if_doc_unique_id_is_missing() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| Ok(index_metadata_for_upsert_test()));
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let resp = warp::test::request()
+            .path("/my-index/ingest?op=upsert")
+            .method("POST")
+            .body(r#"{"ts": 1000}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_csv() {
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|_index_id: &str| Ok(index_metadata_for_upsert_test()));
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(metastore),
+            QuickwitConfig::for_test(),
+        )
+        .await;
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .header("content-type", "text/csv")
+            .body("user_id,ts,extra\nalice,1000,a\nbob,2000,\n")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+
+        let resp = warp::test::request()
+            .path("/my-index/tail")
+            .method("GET")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let fetch_response: FetchResponse = serde_json::from_slice(resp.body()).unwrap();
+        let doc_batch = fetch_response.doc_batch.unwrap();
+        let docs: Vec<JsonValue> = doc_batch
+            .iter()
+            .filter_map(|doc_command| match doc_command {
+                DocCommand::Ingest { payload } => Some(serde_json::from_slice(&payload).unwrap()),
+                DocCommand::Commit => None,
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![
+                serde_json::json!({"user_id": "alice", "ts": 1000, "extra": "a"}),
+                serde_json::json!({"user_id": "bob", "ts": 2000}),
+            ]
+        );
+
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .header("content-type", "text/csv; charset=utf-8")
+            .body("user_id,ts\ncarol,1000\ndave\n")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        let error_body = std::str::from_utf8(resp.body()).unwrap();
+        assert!(error_body.contains("row 3"), "{error_body}");
+        universe.assert_quit().await;
+    }
+
+    fn gzip_compress(payload: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(payload).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decompress_body() {
+        let payload = Bytes::from_static(b"{\"id\": 1}\n{\"id\": 2}\n");
+        assert_eq!(
+            decompress_body(None, payload.clone(), 1024).unwrap(),
+            payload
+        );
+        assert_eq!(
+            decompress_body(Some("identity"), payload.clone(), 1024).unwrap(),
+            payload
+        );
+        let gzip_payload = Bytes::from(gzip_compress(&payload));
+        assert_eq!(
+            decompress_body(Some("gzip"), gzip_payload.clone(), 1024).unwrap(),
+            payload
+        );
+        let zstd_payload = Bytes::from(zstd::encode_all(payload.as_ref(), 0).unwrap());
+        assert_eq!(
+            decompress_body(Some("zstd"), zstd_payload, 1024).unwrap(),
+            payload
+        );
+        assert!(matches!(
+            decompress_body(Some("gzip"), gzip_payload, 4).unwrap_err(),
+            ContentEncodingError::BodyTooLarge(4)
+        ));
+        assert!(matches!(
+            decompress_body(Some("gzip"), payload.clone(), 1024).unwrap_err(),
+            ContentEncodingError::InvalidBody(_)
+        ));
+        assert!(matches!(
+            decompress_body(Some("br"), payload, 1024).unwrap_err(),
+            ContentEncodingError::Unsupported(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_compressed_bodies() {
+        let config = IngestApiConfig {
+            max_decompressed_body_size: Byte::from_bytes(1024),
+            ..Default::default()
+        };
+        let (universe, _temp_dir, ingest_service) =
+            setup_ingest_service(&["my-index"], &config).await;
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.ingest_api_config = config;
+        let ingest_api_handlers = ingest_api_handlers_for_test(
+            ingest_service,
+            Arc::new(MockMetastore::new()),
+            quickwit_config,
+        )
+        .await;
+        let payload = r#"
+            {"id": 1, "message": "push"}
+            {"id": 2, "message": "push"}
+        "#;
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .header("content-encoding", "gzip")
+            .body(gzip_compress(payload.as_bytes()))
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+
+        let bulk_payload = r#"
+            { "create" : { "_index" : "my-index" } }
+            {"id": 3, "message": "push"}
+        "#;
+        let resp = warp::test::request()
+            .path("/_bulk")
+            .method("POST")
+            .header("content-encoding", "zstd")
+            .body(zstd::encode_all(bulk_payload.as_bytes(), 0).unwrap())
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 1);
+
+        let large_payload = payload.repeat(100);
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .header("content-encoding", "gzip")
+            .body(gzip_compress(large_payload.as_bytes()))
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path("/my-index/ingest")
+            .method("POST")
+            .header("content-encoding", "br")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 415);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_routed_ingest() {
+        let (universe, temp_dir, ingest_service) =
+            setup_ingest_service(&["logs-billing"], &IngestApiConfig::default()).await;
+        let metastore = metastore_for_test();
+        metastore
+            .create_index(IndexConfig::for_test(
+                "logs-billing",
+                "ram:///indexes/logs-billing",
+            ))
+            .await
+            .unwrap();
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.data_dir_path = temp_dir.path().to_path_buf();
+        quickwit_config.default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let ingest_api_handlers =
+            ingest_api_handlers_for_test(ingest_service, metastore.clone(), quickwit_config).await;
+        let payload = r#"
+            {"service": {"name": "billing"}, "message": "push"}
+            {"service.name": "auth", "message": "push"}
+            {"service": {"name": "billing"}, "message": "push"}
+        "#;
+        // Without a template index, documents cannot be routed to the missing `logs-auth` index.
+        let resp = warp::test::request()
+            .path("/_ingest?routing_field=service.name&index_id_template=logs-%7Bvalue%7D")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = warp::test::request()
+            .path(
+                "/_ingest?routing_field=service.name&index_id_template=logs-%7Bvalue%7D&\
+                 template_index_id=logs-billing",
+            )
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 3);
+
+        let index_metadata = metastore.index_metadata("logs-auth").await.unwrap();
+        assert_eq!(
+            index_metadata.index_uri(),
+            &Uri::from_well_formed("ram:///indexes/logs-auth")
+        );
+        for (index_id, num_docs) in [("logs-billing", 2), ("logs-auth", 1)] {
+            let resp = warp::test::request()
+                .path(&format!("/{index_id}/tail"))
+                .method("GET")
+                .reply(&ingest_api_handlers)
+                .await;
+            assert_eq!(resp.status(), 200);
+            let fetch_response: FetchResponse = serde_json::from_slice(resp.body()).unwrap();
+            assert_eq!(fetch_response.doc_batch.unwrap().doc_lens.len(), num_docs);
+        }
+
+        let resp = warp::test::request()
+            .path("/_ingest?routing_field=tenant_id&index_id_template=logs-%7Bvalue%7D")
+            .method("POST")
+            .body(payload)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_creates_index_from_template() {
+        let (universe, temp_dir, ingest_service) =
+            setup_ingest_service(&[], &IngestApiConfig::default()).await;
+        let metastore = metastore_for_test();
+        let index_template_yaml = r#"
+            version: 0.4
+            template_id: logs
+            index_id_patterns: ["logs-*"]
+            doc_mapping:
+              field_mappings:
+                - name: body
+                  type: text
+        "#;
+        let index_template = load_index_template_from_user_config(
+            ConfigFormat::Yaml,
+            index_template_yaml.as_bytes(),
+        )
+        .unwrap();
+        metastore
+            .create_index_template(index_template, false)
+            .await
+            .unwrap();
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.data_dir_path = temp_dir.path().to_path_buf();
+        quickwit_config.default_index_root_uri = Uri::from_well_formed("ram:///indexes");
+        let ingest_api_handlers =
+            ingest_api_handlers_for_test(ingest_service, metastore.clone(), quickwit_config).await;
+
+        let resp = warp::test::request()
+            .path("/logs-myapp-2024.06/ingest")
+            .method("POST")
+            .body(r#"{"body": "push"}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let index_metadata = metastore
+            .index_metadata("logs-myapp-2024.06")
+            .await
+            .unwrap();
+        assert_eq!(
+            index_metadata.index_uri(),
+            &Uri::from_well_formed("ram:///indexes/logs-myapp-2024.06")
+        );
+        let resp = warp::test::request()
+            .path("/_bulk")
+            .method("POST")
+            .body("{\"create\": {\"_index\": \"logs-auth\"}}\n{\"body\": \"push\"}\n")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let bulk_response: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(bulk_response["errors"], false);
+        assert!(metastore.index_exists("logs-auth").await.unwrap());
+
+        // Indexes matching no template are not created.
+        let resp = warp::test::request()
+            .path("/metrics-myapp/ingest")
+            .method("POST")
+            .body(r#"{"body": "push"}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_ingest_into_alias() {
+        let (universe, temp_dir, ingest_service) =
+            setup_ingest_service(&["logs-000001"], &IngestApiConfig::default()).await;
+        let metastore = metastore_for_test();
+        for index_id in ["logs-000001", "logs-000002"] {
+            let index_config =
+                IndexConfig::for_test(index_id, &format!("ram:///indexes/{index_id}"));
+            metastore.create_index(index_config).await.unwrap();
+        }
+        metastore
+            .update_index_aliases(vec![
+                IndexAliasAction::Add {
+                    alias_id: "logs".to_string(),
+                    index_id: "logs-000001".to_string(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: "all-logs".to_string(),
+                    index_id: "logs-000001".to_string(),
+                },
+                IndexAliasAction::Add {
+                    alias_id: "all-logs".to_string(),
+                    index_id: "logs-000002".to_string(),
+                },
+            ])
+            .await
+            .unwrap();
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.data_dir_path = temp_dir.path().to_path_buf();
+        let ingest_api_handlers =
+            ingest_api_handlers_for_test(ingest_service, metastore, quickwit_config).await;
+
+        let resp = warp::test::request()
+            .path("/logs/ingest")
+            .method("POST")
+            .body(r#"{"body": "push"}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/logs-000001/tail")
+            .method("GET")
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let fetch_response: FetchResponse = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(fetch_response.doc_batch.unwrap().doc_lens.len(), 1);
+
+        let resp = warp::test::request()
+            .path("/all-logs/ingest")
+            .method("POST")
+            .body(r#"{"body": "push"}"#)
+            .reply(&ingest_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+        universe.assert_quit().await;
+    }
 }
@@ -0,0 +1,416 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_config::{
+    find_matching_index_template, validate_identifier, IndexConfig, IndexTemplate, QuickwitConfig,
+};
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_ingest_api::{
+    get_ingest_api_service, CreateQueueIfNotExistsRequest, IngestRequest, IngestServiceError,
+    QUEUES_DIR_NAME,
+};
+use quickwit_metastore::{IndexAlias, Metastore, MetastoreError};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use tracing::info;
+
+use super::rest_handler::IngestRestApiError;
+
+/// Placeholder of the index ID template substituted with the value of the routing field.
+const ROUTING_VALUE_PLACEHOLDER: &str = "{value}";
+
+/// This struct represents the QueryString passed to the routed ingest REST API.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingOptions {
+    /// The document field whose value selects the target index. Nested fields are targeted with
+    /// dotted paths such as `service.name`.
+    pub routing_field: String,
+    /// The template of the target index IDs, in which `{value}` is substituted with the value of
+    /// the routing field.
+    pub index_id_template: String,
+    /// The ID of the index whose config is used to create the target indexes that do not exist
+    /// yet. Without it, the target indexes are created from the matching index templates, and
+    /// documents routed to a missing index matching no template are rejected.
+    #[serde(default)]
+    pub template_index_id: Option<String>,
+}
+
+/// Groups the documents by target index. The request is rejected as a whole if a document cannot
+/// be routed.
+pub(crate) fn route_docs<'a>(
+    routing_options: &RoutingOptions,
+    doc_payloads: impl Iterator<Item = &'a str>,
+) -> Result<BTreeMap<String, Vec<&'a str>>, IngestRestApiError> {
+    if !routing_options
+        .index_id_template
+        .contains(ROUTING_VALUE_PLACEHOLDER)
+    {
+        return Err(IngestRestApiError::InvalidRouting(format!(
+            "Index ID template `{}` does not contain the `{ROUTING_VALUE_PLACEHOLDER}` \
+             placeholder.",
+            routing_options.index_id_template
+        )));
+    }
+    let mut doc_payloads_per_index: BTreeMap<String, Vec<&'a str>> = BTreeMap::new();
+
+    for doc_payload in doc_payloads {
+        let doc: JsonValue = serde_json::from_str(doc_payload)
+            .map_err(|error| IngestRestApiError::InvalidRouting(error.to_string()))?;
+        let routing_value =
+            find_routing_value(&doc, &routing_options.routing_field).ok_or_else(|| {
+                IngestRestApiError::InvalidRouting(format!(
+                    "Document is missing routing field `{}` or its value is not a string, a \
+                     number, or a boolean.",
+                    routing_options.routing_field
+                ))
+            })?;
+        let index_id = build_index_id(&routing_options.index_id_template, &routing_value);
+        if !doc_payloads_per_index.contains_key(&index_id) {
+            validate_identifier("Index ID", &index_id)
+                .map_err(|error| IngestRestApiError::InvalidRouting(error.to_string()))?;
+        }
+        doc_payloads_per_index
+            .entry(index_id)
+            .or_default()
+            .push(doc_payload);
+    }
+    Ok(doc_payloads_per_index)
+}
+
+/// Looks up the routing field, first as a key containing dots as emitted by many log collectors,
+/// then as a path of nested objects.
+fn find_routing_value(doc: &JsonValue, routing_field: &str) -> Option<String> {
+    let value = match doc.as_object()?.get(routing_field) {
+        Some(value) => value,
+        None => {
+            let mut current = doc;
+            for key in routing_field.split('.') {
+                current = current.as_object()?.get(key)?;
+            }
+            current
+        }
+    };
+    match value {
+        JsonValue::String(text) => Some(text.clone()),
+        JsonValue::Number(number) => Some(number.to_string()),
+        JsonValue::Bool(boolean) => Some(boolean.to_string()),
+        _ => None,
+    }
+}
+
+/// Substitutes the routing value in the index ID template. Characters that are not allowed in
+/// index IDs are replaced with dashes.
+fn build_index_id(index_id_template: &str, routing_value: &str) -> String {
+    let sanitized_routing_value: String = routing_value
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || character == '-' || character == '_' {
+                character
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    index_id_template.replace(ROUTING_VALUE_PLACEHOLDER, &sanitized_routing_value)
+}
+
+/// Resolves `index_id` into the index the documents are written to: the index the alias points
+/// to if `index_id` is an alias, such as the write alias of a rollover policy, or `index_id`
+/// itself otherwise.
+pub(crate) async fn resolve_write_index_id(
+    metastore: &dyn Metastore,
+    index_id: String,
+) -> Result<String, IngestRestApiError> {
+    let index_aliases = metastore.list_index_aliases().await?;
+    let write_index_id_opt = find_write_index_id(&index_aliases, &index_id)?;
+    Ok(write_index_id_opt.unwrap_or(index_id))
+}
+
+/// Rewrites the target indexes of the ingest request that are aliases into the indexes they point
+/// to, and returns the IDs of these indexes. The aliases are only listed once the ingest service
+/// has rejected the request, so that ingesting into regular indexes does not pay for it.
+pub(crate) async fn resolve_write_aliases(
+    ingest_request: &mut IngestRequest,
+    metastore: &dyn Metastore,
+) -> Result<Vec<String>, IngestRestApiError> {
+    let index_aliases = metastore.list_index_aliases().await?;
+    let mut write_index_ids = Vec::new();
+
+    for doc_batch in &mut ingest_request.doc_batches {
+        if let Some(write_index_id) = find_write_index_id(&index_aliases, &doc_batch.index_id)? {
+            doc_batch.index_id = write_index_id.clone();
+            write_index_ids.push(write_index_id);
+        }
+    }
+    Ok(write_index_ids)
+}
+
+/// Returns the index the alias `index_id` points to, if `index_id` is an alias. Documents cannot
+/// be ingested into an alias pointing to several indexes.
+fn find_write_index_id(
+    index_aliases: &[IndexAlias],
+    index_id: &str,
+) -> Result<Option<String>, IngestRestApiError> {
+    let Some(index_alias) = index_aliases
+        .iter()
+        .find(|index_alias| index_alias.alias_id == index_id)
+    else {
+        return Ok(None);
+    };
+    match &index_alias.index_ids[..] {
+        [write_index_id] => Ok(Some(write_index_id.clone())),
+        index_ids => Err(IngestRestApiError::InvalidAlias(format!(
+            "Alias `{index_id}` points to several indexes ({}), so documents cannot be ingested \
+             into it.",
+            index_ids.join(", ")
+        ))),
+    }
+}
+
+/// Creates the target indexes that do not exist yet from the config of the template index, along
+/// with their ingest queues on this node. Without a template index, the indexes are created from
+/// the matching index template with the highest priority.
+pub(crate) async fn create_missing_indexes<'a>(
+    index_ids: impl Iterator<Item = &'a str>,
+    template_index_id_opt: Option<&str>,
+    index_service: &IndexService,
+    quickwit_config: &QuickwitConfig,
+) -> Result<(), IngestRestApiError> {
+    let metastore = index_service.metastore();
+    let mut template_index_config_opt: Option<IndexConfig> = None;
+    let mut index_templates_opt: Option<Vec<IndexTemplate>> = None;
+
+    for index_id in index_ids {
+        if metastore.index_exists(index_id).await? {
+            continue;
+        }
+        let Some(template_index_id) = template_index_id_opt else {
+            if index_templates_opt.is_none() {
+                index_templates_opt = Some(metastore.list_index_templates().await?);
+            }
+            let index_templates = index_templates_opt
+                .as_deref()
+                .expect("The index templates should be loaded.");
+            let Some(index_template) = find_matching_index_template(index_templates, index_id)
+            else {
+                return Err(MetastoreError::IndexDoesNotExist {
+                    index_id: index_id.to_string(),
+                }
+                .into());
+            };
+            let index_config = index_template
+                .apply_template(index_id.to_string(), &quickwit_config.default_index_root_uri)
+                .map_err(|error| IngestRestApiError::IndexCreation(error.to_string()))?;
+            if create_index_and_queue(index_config, index_service, quickwit_config).await? {
+                info!(
+                    index_id = %index_id,
+                    template_id = %index_template.template_id,
+                    "ingest-create-index-from-template"
+                );
+            }
+            continue;
+        };
+        if template_index_config_opt.is_none() {
+            let template_index_config = metastore
+                .index_metadata(template_index_id)
+                .await?
+                .into_index_config();
+            template_index_config_opt = Some(template_index_config);
+        }
+        let mut index_config = template_index_config_opt
+            .clone()
+            .expect("The template index config should be loaded.");
+        index_config.index_id = index_id.to_string();
+        index_config.mounted_from = None;
+        index_config.index_uri = quickwit_config
+            .default_index_root_uri
+            .join(index_id)
+            .map_err(|error| IngestRestApiError::InvalidRouting(error.to_string()))?;
+
+        if create_index_and_queue(index_config, index_service, quickwit_config).await? {
+            info!(
+                index_id = %index_id,
+                template_index_id = %template_index_id,
+                "routed-ingest-create-index"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Creates the index and its ingest queue on this node. Returns `false` if the index was created
+/// by a concurrent request in the meantime.
+async fn create_index_and_queue(
+    index_config: IndexConfig,
+    index_service: &IndexService,
+    quickwit_config: &QuickwitConfig,
+) -> Result<bool, IngestRestApiError> {
+    let queues_dir_path = quickwit_config.data_dir_path.join(QUEUES_DIR_NAME);
+    let ingest_api_service = get_ingest_api_service(&queues_dir_path)
+        .await
+        .map_err(|_| {
+            IngestRestApiError::InvalidRouting(
+                "Creating indexes on first sight requires the node to run the indexer service."
+                    .to_string(),
+            )
+        })?;
+    let queue_id = index_config.index_id.clone();
+    let created = match index_service.create_index(index_config, false).await {
+        Ok(_) => true,
+        // The index may have been created by a concurrent request.
+        Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })) => false,
+        Err(error) => return Err(error.into()),
+    };
+    let create_queue_req = CreateQueueIfNotExistsRequest { queue_id };
+    ingest_api_service
+        .ask_for_res(create_queue_req)
+        .await
+        .map_err(IngestServiceError::from)?;
+    Ok(created)
+}
+
+/// Creates the ingest queues of the existing indexes on this node, so that documents can be
+/// ingested into an index freshly rolled over before its indexing pipeline creates the queue.
+pub(crate) async fn create_queues<'a>(
+    index_ids: impl Iterator<Item = &'a str>,
+    quickwit_config: &QuickwitConfig,
+) -> Result<(), IngestRestApiError> {
+    let queues_dir_path = quickwit_config.data_dir_path.join(QUEUES_DIR_NAME);
+    let Ok(ingest_api_service) = get_ingest_api_service(&queues_dir_path).await else {
+        return Ok(());
+    };
+    for index_id in index_ids {
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: index_id.to_string(),
+        };
+        ingest_api_service
+            .ask_for_res(create_queue_req)
+            .await
+            .map_err(IngestServiceError::from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routing_options_for_test(index_id_template: &str) -> RoutingOptions {
+        RoutingOptions {
+            routing_field: "service.name".to_string(),
+            index_id_template: index_id_template.to_string(),
+            template_index_id: None,
+        }
+    }
+
+    #[test]
+    fn test_find_routing_value() {
+        let doc = serde_json::json!({
+            "service.name": "flat",
+            "service": {"name": "nested", "port": 8080},
+            "tenant": {"id": 42, "tags": ["a"]},
+            "enabled": true,
+        });
+        assert_eq!(find_routing_value(&doc, "service.name").unwrap(), "flat");
+        assert_eq!(find_routing_value(&doc, "service.port").unwrap(), "8080");
+        assert_eq!(find_routing_value(&doc, "tenant.id").unwrap(), "42");
+        assert_eq!(find_routing_value(&doc, "enabled").unwrap(), "true");
+        assert!(find_routing_value(&doc, "tenant.tags").is_none());
+        assert!(find_routing_value(&doc, "tenant").is_none());
+        assert!(find_routing_value(&doc, "missing.field").is_none());
+    }
+
+    #[test]
+    fn test_find_write_index_id() {
+        let index_aliases = vec![
+            IndexAlias {
+                alias_id: "logs".to_string(),
+                index_ids: vec!["logs-000002".to_string()],
+            },
+            IndexAlias {
+                alias_id: "all-logs".to_string(),
+                index_ids: vec!["logs-000001".to_string(), "logs-000002".to_string()],
+            },
+        ];
+        assert_eq!(
+            find_write_index_id(&index_aliases, "logs")
+                .unwrap()
+                .unwrap(),
+            "logs-000002"
+        );
+        assert!(find_write_index_id(&index_aliases, "logs-000001")
+            .unwrap()
+            .is_none());
+        let error = find_write_index_id(&index_aliases, "all-logs").unwrap_err();
+        assert!(matches!(error, IngestRestApiError::InvalidAlias(_)));
+    }
+
+    #[test]
+    fn test_build_index_id() {
+        assert_eq!(build_index_id("logs-{value}", "my-app"), "logs-my-app");
+        assert_eq!(
+            build_index_id("logs-{value}", "my.app/v1"),
+            "logs-my-app-v1"
+        );
+        assert_eq!(build_index_id("{value}-{value}", "app"), "app-app");
+    }
+
+    #[test]
+    fn test_route_docs() {
+        let routing_options = routing_options_for_test("logs-{value}");
+        let doc_payloads = [
+            r#"{"service": {"name": "billing"}, "message": "1"}"#,
+            r#"{"service.name": "auth", "message": "2"}"#,
+            r#"{"service": {"name": "billing"}, "message": "3"}"#,
+        ];
+        let doc_payloads_per_index =
+            route_docs(&routing_options, doc_payloads.iter().copied()).unwrap();
+        assert_eq!(doc_payloads_per_index.len(), 2);
+        assert_eq!(
+            doc_payloads_per_index["logs-billing"],
+            [doc_payloads[0], doc_payloads[2]]
+        );
+        assert_eq!(doc_payloads_per_index["logs-auth"], [doc_payloads[1]]);
+
+        let error = route_docs(
+            &routing_options,
+            [r#"{"message": "missing routing field"}"#].into_iter(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, IngestRestApiError::InvalidRouting(_)));
+
+        let error = route_docs(
+            &routing_options_for_test("{value}-logs"),
+            [r#"{"service.name": "1"}"#].into_iter(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, IngestRestApiError::InvalidRouting(_)));
+
+        let error = route_docs(
+            &routing_options_for_test("logs"),
+            [r#"{"service.name": "auth"}"#].into_iter(),
+        )
+        .unwrap_err();
+        assert!(matches!(error, IngestRestApiError::InvalidRouting(_)));
+    }
+}
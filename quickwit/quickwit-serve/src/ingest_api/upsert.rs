@@ -0,0 +1,268 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use quickwit_doc_mapper::DocMapper;
+use quickwit_proto::metastore_api::DeleteQuery;
+use tantivy::schema::Value;
+
+use super::rest_handler::IngestRestApiError;
+
+/// Builds the delete queries superseding the previous versions of the upserted documents.
+///
+/// For each document, the previous versions are the documents sharing the same unique ID with a
+/// timestamp in a strictly older second. The `end_timestamp` of the delete queries is exclusive,
+/// so they never match the upserted documents, even when a delete query is applied to the split
+/// the upserted documents end up in. Conversely, a version sharing the same second as the
+/// upserted document is not deleted. Documents sharing the same timestamp second are grouped in
+/// a single delete query.
+pub(crate) fn build_upsert_delete_queries<'a>(
+    index_id: &str,
+    doc_mapper: &dyn DocMapper,
+    doc_payloads: impl Iterator<Item = &'a str>,
+) -> Result<Vec<DeleteQuery>, IngestRestApiError> {
+    if !doc_mapper.upserts_enabled() {
+        return Err(IngestRestApiError::InvalidUpsert(format!(
+            "Index `{index_id}` does not accept upserts. Set `doc_mapping.enable_upserts` to \
+             `true` to enable them."
+        )));
+    }
+    let doc_unique_id_field_name = doc_mapper.doc_unique_id_field_name().ok_or_else(|| {
+        IngestRestApiError::InvalidUpsert(format!(
+            "Index `{index_id}` does not declare a `doc_unique_id_field`."
+        ))
+    })?;
+    let timestamp_field_name = doc_mapper.timestamp_field_name().ok_or_else(|| {
+        IngestRestApiError::InvalidUpsert(format!(
+            "Index `{index_id}` does not declare a `timestamp_field`."
+        ))
+    })?;
+    let schema = doc_mapper.schema();
+    let doc_unique_id_field = schema
+        .get_field(doc_unique_id_field_name)
+        .map_err(|error| IngestRestApiError::InvalidUpsert(error.to_string()))?;
+    let timestamp_field = schema
+        .get_field(timestamp_field_name)
+        .map_err(|error| IngestRestApiError::InvalidUpsert(error.to_string()))?;
+
+    let mut doc_id_terms_per_timestamp: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+
+    for doc_payload in doc_payloads {
+        let (_partition, doc) = doc_mapper
+            .doc_from_json_str(doc_payload)
+            .map_err(|error| IngestRestApiError::InvalidUpsert(error.to_string()))?;
+        let timestamp = doc
+            .get_first(timestamp_field)
+            .and_then(Value::as_date)
+            .ok_or_else(|| {
+                IngestRestApiError::InvalidUpsert(format!(
+                    "Upserted document is missing timestamp field `{timestamp_field_name}`."
+                ))
+            })?
+            .into_timestamp_secs();
+        let doc_id_term = match doc.get_first(doc_unique_id_field) {
+            Some(Value::Str(text)) => format!("\"{}\"", escape_phrase(text)),
+            Some(Value::U64(val)) => val.to_string(),
+            Some(Value::I64(val)) => val.to_string(),
+            Some(_) => {
+                return Err(IngestRestApiError::InvalidUpsert(format!(
+                    "Upserts are only supported on text, u64, or i64 doc unique ID fields, but \
+                     `{doc_unique_id_field_name}` is not."
+                )))
+            }
+            None => {
+                return Err(IngestRestApiError::InvalidUpsert(format!(
+                    "Upserted document is missing doc unique ID field \
+                     `{doc_unique_id_field_name}`."
+                )))
+            }
+        };
+        doc_id_terms_per_timestamp
+            .entry(timestamp)
+            .or_default()
+            .push(doc_id_term);
+    }
+    let delete_queries = doc_id_terms_per_timestamp
+        .into_iter()
+        .map(|(timestamp, doc_id_terms)| {
+            let query = doc_id_terms
+                .into_iter()
+                .unique()
+                .map(|doc_id_term| format!("{doc_unique_id_field_name}:{doc_id_term}"))
+                .join(" OR ");
+            DeleteQuery {
+                index_id: index_id.to_string(),
+                start_timestamp: None,
+                end_timestamp: Some(timestamp),
+                query,
+                search_fields: Vec::new(),
+            }
+        })
+        .collect();
+    Ok(delete_queries)
+}
+
+fn escape_phrase(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_doc_mapper::DefaultDocMapper;
+    use quickwit_indexing::TestSandbox;
+    use quickwit_proto::SearchRequest;
+    use quickwit_search::single_node_search;
+    use serde_json::json;
+
+    use super::*;
+
+    const DOC_MAPPER_JSON: &str = r#"{
+        "doc_unique_id_field": "user_id",
+        "timestamp_field": "ts",
+        "enable_upserts": true,
+        "field_mappings": [
+            {"name": "user_id", "type": "text", "tokenizer": "raw"},
+            {"name": "ts", "type": "datetime", "fast": true},
+            {"name": "name", "type": "text"}
+        ]
+    }"#;
+
+    #[test]
+    fn test_build_upsert_delete_queries() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(DOC_MAPPER_JSON).unwrap();
+        let docs = [
+            r#"{"user_id": "alice", "ts": 1000, "name": "Alice"}"#,
+            r#"{"user_id": "bob", "ts": 1000, "name": "Bob"}"#,
+            r#"{"user_id": "bo\"b", "ts": 2000, "name": "Bob"}"#,
+        ];
+        let delete_queries =
+            build_upsert_delete_queries("my-index", &doc_mapper, docs.into_iter()).unwrap();
+        assert_eq!(delete_queries.len(), 2);
+        assert_eq!(delete_queries[0].index_id, "my-index");
+        assert_eq!(
+            delete_queries[0].query,
+            r#"user_id:"alice" OR user_id:"bob""#
+        );
+        assert_eq!(delete_queries[0].end_timestamp, Some(1000));
+        assert_eq!(delete_queries[0].start_timestamp, None);
+        assert_eq!(delete_queries[1].query, r#"user_id:"bo\"b""#);
+        assert_eq!(delete_queries[1].end_timestamp, Some(2000));
+    }
+
+    #[test]
+    fn test_build_upsert_delete_queries_invalid_docs() {
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(DOC_MAPPER_JSON).unwrap();
+        let error = build_upsert_delete_queries(
+            "my-index",
+            &doc_mapper,
+            [r#"{"ts": 1000, "name": "Alice"}"#].into_iter(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("missing doc unique ID field"));
+
+        let doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+                "doc_unique_id_field": "user_id",
+                "timestamp_field": "ts",
+                "field_mappings": [
+                    {"name": "user_id", "type": "text", "tokenizer": "raw"},
+                    {"name": "ts", "type": "datetime", "fast": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let error = build_upsert_delete_queries(
+            "my-index",
+            &doc_mapper,
+            [r#"{"user_id": "alice", "ts": 1000}"#].into_iter(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("does not accept upserts"));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_delete_queries_spare_versions_of_the_same_second() {
+        let index_id = "test-upsert-same-second";
+        let doc_mapping_yaml = r#"
+            doc_unique_id_field: user_id
+            timestamp_field: ts
+            enable_upserts: true
+            field_mappings:
+              - name: user_id
+                type: text
+                tokenizer: raw
+              - name: ts
+                type: datetime
+                fast: true
+                precision: milliseconds
+              - name: name
+                type: text
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["user_id"])
+            .await
+            .unwrap();
+        // Two upserts of `alice` land in the same second, after a version of the previous second.
+        let previous_version = json!({"user_id": "alice", "ts": "1970-01-01T00:16:39.500Z"});
+        let first_upsert = json!({"user_id": "alice", "ts": "1970-01-01T00:16:40.200Z"});
+        let second_upsert = json!({"user_id": "alice", "ts": "1970-01-01T00:16:40.700Z"});
+        test_sandbox
+            .add_documents(vec![
+                previous_version,
+                first_upsert.clone(),
+                second_upsert.clone(),
+            ])
+            .await
+            .unwrap();
+
+        for upsert in [first_upsert, second_upsert] {
+            let doc_payload = upsert.to_string();
+            let delete_queries = build_upsert_delete_queries(
+                index_id,
+                &*test_sandbox.doc_mapper(),
+                std::iter::once(doc_payload.as_str()),
+            )
+            .unwrap();
+            assert_eq!(delete_queries.len(), 1);
+            assert_eq!(delete_queries[0].end_timestamp, Some(1000));
+
+            // The delete query only matches the version of the previous second, never the
+            // upserted documents.
+            let search_request = SearchRequest {
+                index_id: index_id.to_string(),
+                query: delete_queries[0].query.clone(),
+                start_timestamp: delete_queries[0].start_timestamp,
+                end_timestamp: delete_queries[0].end_timestamp,
+                max_hits: 10,
+                ..Default::default()
+            };
+            let search_response = single_node_search(
+                &search_request,
+                &*test_sandbox.metastore(),
+                test_sandbox.storage_uri_resolver(),
+            )
+            .await
+            .unwrap();
+            assert_eq!(search_response.num_hits, 1);
+            assert!(search_response.hits[0].json.contains("16:39.5"));
+        }
+        test_sandbox.assert_quit().await;
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use warp::{Filter, Rejection};
+
+/// Error returned when a JSON request body does not match the schema of the endpoint. The path of
+/// the offending field is reported along with the error, e.g. `doc_mapping.field_mappings[1].type`.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Request body deserialize error{}: {message}", display_path(.path))]
+pub(crate) struct InvalidJsonBody {
+    /// Path of the field that could not be deserialized, `.` for the root of the document.
+    pub path: String,
+    pub message: String,
+}
+
+fn display_path(path: &str) -> String {
+    if path == "." {
+        String::new()
+    } else {
+        format!(" at `{path}`")
+    }
+}
+
+impl warp::reject::Reject for InvalidJsonBody {}
+
+/// Deserializes the JSON body of a request, rejecting the request with an [`InvalidJsonBody`]
+/// error if it does not match the schema of `T`.
+pub(crate) fn json_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::body::bytes()
+        .and_then(|body: Bytes| async move { parse_json_body(&body).map_err(warp::reject::custom) })
+}
+
+fn parse_json_body<T: DeserializeOwned>(body: &[u8]) -> Result<T, InvalidJsonBody> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|error| {
+        let path = error.path().to_string();
+        InvalidJsonBody {
+            path,
+            message: error.into_inner().to_string(),
+        }
+    })?;
+    // Rejects trailing characters.
+    deserializer.end().map_err(|error| InvalidJsonBody {
+        path: ".".to_string(),
+        message: error.to_string(),
+    })?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Request {
+        query: String,
+        #[serde(default)]
+        max_hits: u64,
+        #[serde(default)]
+        field_mappings: Vec<FieldMapping>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct FieldMapping {
+        name: String,
+        fast: bool,
+    }
+
+    #[test]
+    fn test_parse_json_body() {
+        let request: Request =
+            parse_json_body(br#"{"query": "*", "field_mappings": [{"name": "a", "fast": true}]}"#)
+                .unwrap();
+        assert_eq!(request.query, "*");
+        assert_eq!(request.field_mappings.len(), 1);
+
+        let error =
+            parse_json_body::<Request>(br#"{"query": "*", "max_hits": "ten"}"#).unwrap_err();
+        assert_eq!(error.path, "max_hits");
+        assert!(error
+            .message
+            .starts_with(r#"invalid type: string "ten", expected u64"#));
+        assert!(error
+            .to_string()
+            .starts_with("Request body deserialize error at `max_hits`: invalid type"));
+
+        let error = parse_json_body::<Request>(
+            br#"{"query": "*", "field_mappings": [{"name": "a", "fast": true}, {"name": "b", "fast": 1}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.path, "field_mappings[1].fast");
+        assert!(error
+            .message
+            .starts_with("invalid type: integer `1`, expected a boolean"));
+
+        let error = parse_json_body::<Request>(br#"{"max_hits": 10}"#).unwrap_err();
+        assert_eq!(error.path, ".");
+        assert!(error
+            .to_string()
+            .starts_with("Request body deserialize error: missing field `query`"));
+
+        let error = parse_json_body::<Request>(br#"{"query": "*"} {}"#).unwrap_err();
+        assert_eq!(error.path, ".");
+        assert!(error.message.starts_with("trailing characters"));
+    }
+
+    #[tokio::test]
+    async fn test_json_body_filter() {
+        let filter = json_body::<Request>();
+        let request = warp::test::request()
+            .method("POST")
+            .body(r#"{"query": "*"}"#)
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(request.query, "*");
+
+        let rejection = warp::test::request()
+            .method("POST")
+            .body(r#"{"query": 1}"#)
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        let error = rejection.find::<InvalidJsonBody>().unwrap();
+        assert_eq!(error.path, "query");
+    }
+}
@@ -19,22 +19,40 @@
 
 #![deny(clippy::disallowed_methods)]
 
+mod api_key_api;
 mod args;
+mod audit_log;
 mod format;
+mod gossip_transport;
 mod metrics;
 
 mod grpc;
+mod rate_limiter;
+mod request_tracing;
+mod response_headers;
 mod rest;
+mod rollup_executor;
 
+mod alert_api;
+mod autoscaling_api;
 mod cluster_api;
+mod config_reload_api;
 mod delete_task_api;
 mod elastic_search_api;
 mod health_check_api;
+mod index_alias_api;
 mod index_api;
+mod index_template_api;
 mod indexing_api;
 mod ingest_api;
+mod json_body;
+mod loki_api;
+mod node_drain_api;
 mod node_info_handler;
 mod openapi;
+mod prometheus_api;
+mod query_log;
+mod quota_api;
 mod search_api;
 #[cfg(test)]
 mod test_utils;
@@ -50,45 +68,71 @@ use std::time::Duration;
 
 use anyhow::anyhow;
 use byte_unit::n_mib_bytes;
+use chitchat::transport::{Transport, UdpTransport};
 use format::BodyFormat;
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
 use quickwit_actors::{Mailbox, Universe};
 use quickwit_cluster::{Cluster, ClusterMember};
 use quickwit_common::pubsub::EventBroker;
+use quickwit_common::reload_log_filter;
 use quickwit_common::tower::{
     BufferLayer, ConstantRate, EstimateRateLayer, Rate, RateLimitLayer, SmaRateEstimator,
 };
+use quickwit_common::uri::Uri;
 use quickwit_config::service::QuickwitService;
 use quickwit_config::{load_index_config_from_user_config, ConfigFormat, QuickwitConfig};
 use quickwit_control_plane::{start_control_plane_service, ControlPlaneServiceClient};
 use quickwit_core::{IndexService, IndexServiceError};
 use quickwit_grpc_clients::create_balance_channel_from_watched_members;
 use quickwit_grpc_clients::service_client_pool::ServiceClientPool;
+use quickwit_grpc_clients::tls::{init_grpc_tls, is_grpc_tls_enabled};
 use quickwit_indexing::actors::IndexingService;
-use quickwit_indexing::start_indexing_service;
+use quickwit_indexing::{start_indexing_service, start_merger_service};
 use quickwit_ingest_api::{
     start_ingest_api_service, GetMemoryCapacity, IngestRequest, IngestServiceClient, MemoryCapacity,
 };
 use quickwit_janitor::{start_janitor_service, JanitorService};
 use quickwit_metastore::{
     quickwit_metastore_uri_resolver, Metastore, MetastoreError, MetastoreEvent,
-    MetastoreEventPublisher, MetastoreGrpcClient, RetryingMetastore,
+    MetastoreEventPublisher, MetastoreGrpcClient, PublishSplitsEvent, RetryingMetastore,
 };
-use quickwit_opentelemetry::otlp::{OTEL_LOGS_INDEX_CONFIG, OTEL_TRACE_INDEX_CONFIG};
-use quickwit_search::{start_searcher_service, SearchJobPlacer, SearchService};
+use quickwit_opentelemetry::otlp::{
+    OTEL_LOGS_INDEX_CONFIG, OTEL_METRICS_INDEX_CONFIG, OTEL_TRACE_INDEX_CONFIG,
+};
+use quickwit_proto::set_cluster_secret;
+use quickwit_search::{start_searcher_service, SearchJobPlacer, SearchService, SearcherContext};
 use quickwit_storage::quickwit_storage_uri_resolver;
 use serde::{Deserialize, Serialize};
 use tower::ServiceBuilder;
 use tracing::{error, warn};
 use warp::{Filter, Rejection};
 
+use crate::alert_api::{spawn_alert_evaluation_task, AlertEvaluator};
+use crate::api_key_api::{
+    spawn_api_keys_refresh_task, spawn_jwks_refresh_task, ApiKeyAuthenticator,
+};
 pub use crate::args::ServeArgs;
+use crate::audit_log::AuditLogger;
+use crate::autoscaling_api::{spawn_autoscaling_signals_sampling_task, AutoscalingSignalsSampler};
+use crate::config_reload_api::{spawn_config_reload_tasks, ConfigReloader};
+use crate::gossip_transport::TlsTransport;
 pub use crate::index_api::ListSplitsQueryParams;
+use crate::ingest_api::{grpc_ingest_service_client, IngestReplicator};
 pub use crate::metrics::SERVE_METRICS;
+pub use crate::node_drain_api::NodeDrainState;
+use crate::node_drain_api::NodeDrainer;
+use crate::prometheus_api::PROMETHEUS_METRICS_INDEX_CONFIG;
+use crate::query_log::start_query_logger;
+use crate::quota_api::{spawn_quota_usage_refresh_task, QuotaTracker};
+use crate::rate_limiter::RateLimiter;
 #[cfg(test)]
 use crate::rest::recover_fn;
-pub use crate::search_api::{SearchRequestQueryString, SortByField};
+use crate::rollup_executor::{spawn_rollup_task, RollupExecutor};
+use crate::search_api::SplitPublishNotifier;
+pub use crate::search_api::{
+    search_request_from_query_string, SearchRequestQueryString, SortByField, SqlRequest,
+};
 
 const READINESS_REPORTING_INTERVAL: Duration = if cfg!(any(test, feature = "testsuite")) {
     Duration::from_millis(25)
@@ -109,8 +153,17 @@ struct QuickwitServices {
     pub indexing_service: Option<Mailbox<IndexingService>>,
     pub janitor_service: Option<Mailbox<JanitorService>>,
     pub ingest_service: IngestServiceClient,
+    pub ingest_replicator: Arc<IngestReplicator>,
     pub index_service: Arc<IndexService>,
     pub services: HashSet<QuickwitService>,
+    pub api_key_authenticator: Arc<ApiKeyAuthenticator>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub quota_tracker: Arc<QuotaTracker>,
+    pub node_drainer: Arc<NodeDrainer>,
+    pub autoscaling_signals_sampler: Arc<AutoscalingSignalsSampler>,
+    pub config_reloader: Arc<ConfigReloader>,
+    pub audit_logger_opt: Option<Arc<AuditLogger>>,
+    pub split_publish_notifier: SplitPublishNotifier,
 }
 
 fn has_node_with_metastore_service(members: &[ClusterMember]) -> bool {
@@ -121,12 +174,40 @@ fn has_node_with_metastore_service(members: &[ClusterMember]) -> bool {
     })
 }
 
-pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
+/// Starts the services of the node. `config_uri_opt` is the URI of the config file the node was
+/// started from, reloaded on changes.
+pub async fn serve_quickwit(
+    config: QuickwitConfig,
+    config_uri_opt: Option<Uri>,
+) -> anyhow::Result<()> {
+    if let Some(log_level) = &config.log_level {
+        if let Err(error) = reload_log_filter(log_level) {
+            warn!(error=?error, "Failed to apply the log level of the node config.");
+        }
+    }
+    if let Some(grpc_tls_config) = &config.grpc_tls_config {
+        init_grpc_tls(grpc_tls_config.clone()).await?;
+    }
+    if let Some(cluster_secret) = &config.auth_config.cluster_secret {
+        set_cluster_secret(cluster_secret).map_err(|_| {
+            anyhow!("The cluster secret must only contain visible ASCII characters.")
+        })?;
+    }
     let universe = Universe::new();
     let event_broker = EventBroker::default();
     let storage_resolver = quickwit_storage_uri_resolver().clone();
-    let cluster =
-        quickwit_cluster::start_cluster_service(&config, &config.enabled_services).await?;
+    // With mutual TLS, the gossip messages are exchanged over TLS connections rather than UDP.
+    let gossip_transport: &dyn Transport = if is_grpc_tls_enabled() {
+        &TlsTransport
+    } else {
+        &UdpTransport
+    };
+    let cluster = quickwit_cluster::start_cluster_service(
+        &config,
+        &config.enabled_services,
+        gossip_transport,
+    )
+    .await?;
 
     // Instantiate either a file-backed or postgresql [`Metastore`] if the node runs a `Metastore`
     // service, else instantiate a [`MetastoreGrpcClient`].
@@ -182,8 +263,13 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         .enabled_services
         .contains(&QuickwitService::ControlPlane)
     {
-        let control_plane_mailbox =
-            start_control_plane_service(&universe, cluster.clone(), metastore.clone()).await?;
+        let control_plane_mailbox = start_control_plane_service(
+            &universe,
+            cluster.clone(),
+            metastore.clone(),
+            config.ingest_api_config.max_num_shards_per_indexer,
+        )
+        .await?;
         let control_plane_service = ControlPlaneServiceClient::from_mailbox(control_plane_mailbox);
         event_broker.subscribe::<MetastoreEvent>(control_plane_service.clone());
         Some(control_plane_service)
@@ -191,7 +277,7 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         None
     };
 
-    let (ingest_service, indexing_service) = if config
+    let (ingest_service, ingest_api_service_opt, indexing_service) = if config
         .enabled_services
         .contains(&QuickwitService::Indexer)
     {
@@ -199,21 +285,18 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
             start_ingest_api_service(&universe, &config.data_dir_path, &config.ingest_api_config)
                 .await?;
         if config.indexer_config.enable_otlp_endpoint {
-            for index_config_content in [OTEL_LOGS_INDEX_CONFIG, OTEL_TRACE_INDEX_CONFIG] {
-                let index_config = load_index_config_from_user_config(
-                    ConfigFormat::Yaml,
-                    index_config_content.as_bytes(),
-                    &config.default_index_root_uri,
-                )?;
-                match index_service.create_index(index_config, false).await {
-                    Ok(_)
-                    | Err(IndexServiceError::MetastoreError(
-                        MetastoreError::IndexAlreadyExists { .. },
-                    )) => Ok(()),
-                    Err(error) => Err(error),
-                }?;
+            for index_config_content in [
+                OTEL_LOGS_INDEX_CONFIG,
+                OTEL_METRICS_INDEX_CONFIG,
+                OTEL_TRACE_INDEX_CONFIG,
+            ] {
+                create_index_if_not_exists(&index_service, index_config_content, &config).await?;
             }
         }
+        if config.indexer_config.enable_prometheus_endpoint {
+            create_index_if_not_exists(&index_service, PROMETHEUS_METRICS_INDEX_CONFIG, &config)
+                .await?;
+        }
         let indexing_service = start_indexing_service(
             &universe,
             &config,
@@ -241,21 +324,41 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
                     .layer(RateLimitLayer::new(rate_modulator))
                     .into(),
             )
-            .service(IngestServiceClient::from_mailbox(ingest_api_service));
-        (ingest_service, Some(indexing_service))
+            .service(IngestServiceClient::from_mailbox(
+                ingest_api_service.clone(),
+            ));
+        (
+            ingest_service,
+            Some(ingest_api_service),
+            Some(indexing_service),
+        )
     } else {
         let (channel, _) = create_balance_channel_from_watched_members(
             cluster.ready_member_change_watcher(),
             QuickwitService::Indexer,
         )
         .await?;
-        let ingest_service = IngestServiceClient::from_channel(channel);
-        (ingest_service, None)
+        let ingest_service = grpc_ingest_service_client(channel);
+        let indexing_service_opt = if config.enabled_services.contains(&QuickwitService::Merger) {
+            let indexing_service = start_merger_service(
+                &universe,
+                &config,
+                cluster.clone(),
+                metastore.clone(),
+                storage_resolver.clone(),
+            )
+            .await?;
+            Some(indexing_service)
+        } else {
+            None
+        };
+        (ingest_service, None, indexing_service_opt)
     };
 
     let search_job_placer = SearchJobPlacer::new(
         ServiceClientPool::create_and_update_members(cluster.ready_member_change_watcher()).await?,
-    );
+    )
+    .with_split_replication_factor(config.searcher_config.split_replication_factor);
 
     let janitor_service = if config.enabled_services.contains(&QuickwitService::Janitor) {
         let janitor_service = start_janitor_service(
@@ -271,14 +374,109 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         None
     };
 
+    let query_logger_opt = if let Some(query_log_config) = &config.query_log_config {
+        let query_logger = start_query_logger(
+            query_log_config,
+            &index_service,
+            ingest_service.clone(),
+            &config.default_index_root_uri,
+        )
+        .await?;
+        Some(query_logger)
+    } else {
+        None
+    };
+    let searcher_context = Arc::new(SearcherContext::new(config.searcher_config.clone()));
     let search_service: Arc<dyn SearchService> = start_searcher_service(
         &config,
         metastore.clone(),
         storage_resolver,
         search_job_placer,
+        searcher_context.clone(),
+        query_logger_opt,
     )
     .await?;
+    if config.enabled_services.contains(&QuickwitService::Searcher) {
+        let alert_evaluator = AlertEvaluator::new(
+            config.node_id.clone(),
+            metastore.clone(),
+            search_service.clone(),
+        );
+        spawn_alert_evaluation_task(alert_evaluator);
+
+        let rollup_executor = RollupExecutor::new(
+            config.node_id.clone(),
+            metastore.clone(),
+            search_service.clone(),
+            ingest_service.clone(),
+        );
+        spawn_rollup_task(rollup_executor);
+    }
 
+    let api_key_authenticator = Arc::new(ApiKeyAuthenticator::new(
+        metastore.clone(),
+        &config.auth_config,
+    ));
+    if api_key_authenticator.is_enabled() {
+        spawn_api_keys_refresh_task(
+            api_key_authenticator.clone(),
+            config.auth_config.api_keys_refresh_interval(),
+        );
+    }
+    if let Some(oidc_config) = &config.auth_config.oidc {
+        spawn_jwks_refresh_task(
+            api_key_authenticator.clone(),
+            oidc_config.jwks_refresh_interval(),
+        );
+    }
+    let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits_config.clone()));
+    let config_reloader = Arc::new(ConfigReloader::new(
+        config_uri_opt,
+        config.clone(),
+        rate_limiter.clone(),
+        searcher_context,
+        indexing_service.clone(),
+    ));
+    spawn_config_reload_tasks(config_reloader.clone());
+    let quota_tracker = Arc::new(QuotaTracker::new(
+        metastore.clone(),
+        config.quotas_config.clone(),
+    ));
+    if quota_tracker.is_enabled() {
+        spawn_quota_usage_refresh_task(
+            quota_tracker.clone(),
+            config.quotas_config.usage_refresh_interval(),
+        );
+    }
+    let autoscaling_signals_sampler = Arc::new(AutoscalingSignalsSampler::new(
+        config.node_id.clone(),
+        indexing_service.clone(),
+        ingest_api_service_opt.clone(),
+    ));
+    spawn_autoscaling_signals_sampling_task(autoscaling_signals_sampler.clone());
+    let node_drainer = Arc::new(NodeDrainer::new(
+        cluster.clone(),
+        indexing_service.clone(),
+        ingest_api_service_opt,
+    ));
+    let audit_logger_opt = if let Some(audit_log_config) = &config.audit_log_config {
+        let audit_logger = AuditLogger::start(
+            audit_log_config.clone(),
+            api_key_authenticator.clone(),
+            &index_service,
+            ingest_service.clone(),
+            &config.default_index_root_uri,
+        )
+        .await?;
+        Some(Arc::new(audit_logger))
+    } else {
+        None
+    };
+    let ingest_replicator = Arc::new(IngestReplicator::new(&config, cluster.clone()));
+    let split_publish_notifier = SplitPublishNotifier::new();
+    // The subscription lasts as long as the servers.
+    let _split_publish_subscription =
+        event_broker.subscribe::<PublishSplitsEvent>(split_publish_notifier.clone());
     let grpc_listen_addr = config.grpc_listen_addr;
     let rest_listen_addr = config.rest_listen_addr;
     let services = config.enabled_services.clone();
@@ -292,8 +490,17 @@ pub async fn serve_quickwit(config: QuickwitConfig) -> anyhow::Result<()> {
         indexing_service,
         janitor_service,
         ingest_service,
+        ingest_replicator,
         index_service,
         services,
+        api_key_authenticator,
+        rate_limiter,
+        quota_tracker,
+        node_drainer,
+        autoscaling_signals_sampler,
+        config_reloader,
+        audit_logger_opt,
+        split_publish_notifier,
     };
     let grpc_server = grpc::start_grpc_server(grpc_listen_addr, &quickwit_services);
     let rest_server = rest::start_rest_server(rest_listen_addr, &quickwit_services);
@@ -386,6 +593,26 @@ fn with_arg<T: Clone + Send>(arg: T) -> impl Filter<Extract = (T,), Error = Infa
     warp::any().map(move || arg.clone())
 }
 
+/// Creates the index described by the YAML `index_config_content`, unless it already exists.
+async fn create_index_if_not_exists(
+    index_service: &IndexService,
+    index_config_content: &str,
+    config: &QuickwitConfig,
+) -> anyhow::Result<()> {
+    let index_config = load_index_config_from_user_config(
+        ConfigFormat::Yaml,
+        index_config_content.as_bytes(),
+        &config.default_index_root_uri,
+    )?;
+    match index_service.create_index(index_config, false).await {
+        Ok(_)
+        | Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })) => {
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
 /// Reports node readiness to chitchat cluster every 10 seconds (25 ms for tests).
 async fn node_readiness_reporting_task(cluster: Arc<Cluster>, metastore: Arc<dyn Metastore>) {
     let mut interval = tokio::time::interval(READINESS_REPORTING_INTERVAL);
@@ -0,0 +1,381 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Parser of the LogQL subset supported by the Loki-compatible API, i.e. log queries made of a
+//! stream selector followed by line filters:
+//!
+//! ```text
+//! {label="value", label!="value", label=~"regex", label!~"regex"} |= "text" != "text" |~ "regex"
+//! ```
+//!
+//! Metric queries and the pipeline stages other than line filters (`| json`, `| logfmt`, ...) are
+//! rejected.
+
+use std::fmt;
+
+use quickwit_search::SearchError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LogQuery {
+    pub matchers: Vec<LabelMatcher>,
+    pub line_filters: Vec<LineFilter>,
+}
+
+/// Matcher of the stream selector, e.g. `level="error"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LabelMatcher {
+    pub label: String,
+    pub operator: MatchOperator,
+    pub value: String,
+}
+
+/// Line filter, e.g. `|= "timeout"`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LineFilter {
+    pub operator: MatchOperator,
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MatchOperator {
+    /// `=` for label matchers, `|=` for line filters.
+    Equal,
+    /// `!=` for label matchers and line filters.
+    NotEqual,
+    /// `=~` for label matchers, `|~` for line filters.
+    Regex,
+    /// `!~` for label matchers and line filters.
+    NotRegex,
+}
+
+impl MatchOperator {
+    pub fn is_negative(&self) -> bool {
+        matches!(self, MatchOperator::NotEqual | MatchOperator::NotRegex)
+    }
+}
+
+impl fmt::Display for MatchOperator {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self {
+            MatchOperator::Equal => "=",
+            MatchOperator::NotEqual => "!=",
+            MatchOperator::Regex => "=~",
+            MatchOperator::NotRegex => "!~",
+        };
+        write!(formatter, "{operator}")
+    }
+}
+
+pub(crate) fn invalid_logql(message: impl fmt::Display) -> SearchError {
+    SearchError::InvalidQuery(format!("Invalid LogQL query: {message}"))
+}
+
+/// Parses a LogQL log query.
+pub(crate) fn parse_log_query(logql: &str) -> Result<LogQuery, SearchError> {
+    let mut parser = Parser::new(logql);
+    parser.skip_whitespaces();
+
+    if !parser.consume("{") {
+        return Err(invalid_logql(
+            "only log queries starting with a stream selector `{...}` are supported, metric \
+             queries are not.",
+        ));
+    }
+    let matchers = parser.parse_matchers()?;
+    let mut line_filters = Vec::new();
+
+    loop {
+        parser.skip_whitespaces();
+
+        if parser.is_at_end() {
+            break;
+        }
+        let operator = if parser.consume("|=") {
+            MatchOperator::Equal
+        } else if parser.consume("!=") {
+            MatchOperator::NotEqual
+        } else if parser.consume("|~") {
+            MatchOperator::Regex
+        } else if parser.consume("!~") {
+            MatchOperator::NotRegex
+        } else if parser.peek() == Some('|') {
+            return Err(invalid_logql(format!(
+                "unsupported pipeline stage `{}`, only line filters are supported.",
+                parser.remaining().trim()
+            )));
+        } else {
+            return Err(invalid_logql(format!(
+                "unexpected `{}`.",
+                parser.remaining().trim()
+            )));
+        };
+        parser.skip_whitespaces();
+        let value = parser.parse_string()?;
+        line_filters.push(LineFilter { operator, value });
+    }
+    Ok(LogQuery {
+        matchers,
+        line_filters,
+    })
+}
+
+/// Parses a stream selector, e.g. the `match[]` or `query` parameter of the label endpoints.
+pub(crate) fn parse_stream_selector(logql: &str) -> Result<Vec<LabelMatcher>, SearchError> {
+    let log_query = parse_log_query(logql)?;
+
+    if !log_query.line_filters.is_empty() {
+        return Err(invalid_logql(
+            "expected a stream selector without line filters.",
+        ));
+    }
+    Ok(log_query.matchers)
+}
+
+struct Parser<'a> {
+    logql: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(logql: &'a str) -> Self {
+        Self { logql, position: 0 }
+    }
+
+    fn remaining(&self) -> &'a str {
+        &self.logql[self.position..]
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.position == self.logql.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.remaining().chars().next()
+    }
+
+    fn skip_whitespaces(&mut self) {
+        let remaining = self.remaining();
+        self.position += remaining.len() - remaining.trim_start().len();
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        if self.remaining().starts_with(token) {
+            self.position += token.len();
+            return true;
+        }
+        false
+    }
+
+    /// Parses the matchers of a stream selector, after its opening brace.
+    fn parse_matchers(&mut self) -> Result<Vec<LabelMatcher>, SearchError> {
+        let mut matchers = Vec::new();
+
+        loop {
+            self.skip_whitespaces();
+
+            if self.consume("}") {
+                return Ok(matchers);
+            }
+            if !matchers.is_empty() {
+                if !self.consume(",") {
+                    return Err(invalid_logql("expected `,` or `}` after a label matcher."));
+                }
+                self.skip_whitespaces();
+            }
+            let label = self.parse_label()?;
+            self.skip_whitespaces();
+
+            let operator = if self.consume("=~") {
+                MatchOperator::Regex
+            } else if self.consume("!~") {
+                MatchOperator::NotRegex
+            } else if self.consume("!=") {
+                MatchOperator::NotEqual
+            } else if self.consume("=") {
+                MatchOperator::Equal
+            } else {
+                return Err(invalid_logql(format!(
+                    "expected `=`, `!=`, `=~`, or `!~` after label `{label}`."
+                )));
+            };
+            self.skip_whitespaces();
+            let value = self.parse_string()?;
+            matchers.push(LabelMatcher {
+                label,
+                operator,
+                value,
+            });
+        }
+    }
+
+    fn parse_label(&mut self) -> Result<String, SearchError> {
+        let label_len = self
+            .remaining()
+            .find(|character: char| !(character.is_ascii_alphanumeric() || character == '_'))
+            .unwrap_or(self.remaining().len());
+        let label = &self.remaining()[..label_len];
+
+        if label.is_empty() || label.starts_with(|character: char| character.is_ascii_digit()) {
+            return Err(invalid_logql("expected a label name."));
+        }
+        self.position += label_len;
+        Ok(label.to_string())
+    }
+
+    /// Parses a double-quoted string with Go-like escape sequences, or a backquoted raw string.
+    fn parse_string(&mut self) -> Result<String, SearchError> {
+        if self.consume("`") {
+            let Some(string_len) = self.remaining().find('`') else {
+                return Err(invalid_logql("unterminated raw string."));
+            };
+            let string = self.remaining()[..string_len].to_string();
+            self.position += string_len + 1;
+            return Ok(string);
+        }
+        if !self.consume("\"") {
+            return Err(invalid_logql("expected a string literal."));
+        }
+        let mut string = String::new();
+        let mut characters = self.remaining().char_indices();
+
+        while let Some((index, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.position += index + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped_character = match characters.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'r')) => '\r',
+                        Some((_, escaped_character @ ('"' | '\\'))) => escaped_character,
+                        Some((_, escaped_character)) => {
+                            // Keeps the backslash of the regex escape sequences, e.g. `\d`.
+                            string.push('\\');
+                            escaped_character
+                        }
+                        None => break,
+                    };
+                    string.push(escaped_character);
+                }
+                _ => string.push(character),
+            }
+        }
+        Err(invalid_logql("unterminated string."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_query() {
+        assert_eq!(
+            parse_log_query(r#"{app="api", level!="debug"}"#).unwrap(),
+            LogQuery {
+                matchers: vec![
+                    LabelMatcher {
+                        label: "app".to_string(),
+                        operator: MatchOperator::Equal,
+                        value: "api".to_string(),
+                    },
+                    LabelMatcher {
+                        label: "level".to_string(),
+                        operator: MatchOperator::NotEqual,
+                        value: "debug".to_string(),
+                    },
+                ],
+                line_filters: Vec::new(),
+            }
+        );
+        assert_eq!(
+            parse_log_query(
+                r#" { host =~ "web-\\d+|db" } |= "time\"out" != `GET /health` |~ "err.*" !~ "x" "#
+            )
+            .unwrap(),
+            LogQuery {
+                matchers: vec![LabelMatcher {
+                    label: "host".to_string(),
+                    operator: MatchOperator::Regex,
+                    value: r"web-\d+|db".to_string(),
+                }],
+                line_filters: vec![
+                    LineFilter {
+                        operator: MatchOperator::Equal,
+                        value: r#"time"out"#.to_string(),
+                    },
+                    LineFilter {
+                        operator: MatchOperator::NotEqual,
+                        value: "GET /health".to_string(),
+                    },
+                    LineFilter {
+                        operator: MatchOperator::Regex,
+                        value: "err.*".to_string(),
+                    },
+                    LineFilter {
+                        operator: MatchOperator::NotRegex,
+                        value: "x".to_string(),
+                    },
+                ],
+            }
+        );
+        assert_eq!(parse_log_query("{}").unwrap().matchers, Vec::new());
+    }
+
+    #[test]
+    fn test_parse_log_query_errors() {
+        for (logql, expected_error) in [
+            (r#"rate({app="api"}[5m])"#, "metric queries are not."),
+            (
+                r#"{app="api"} | json"#,
+                "unsupported pipeline stage `| json`",
+            ),
+            (r#"{app="api" level="info"}"#, "expected `,` or `}`"),
+            (
+                r#"{app>"api"}"#,
+                "expected `=`, `!=`, `=~`, or `!~` after label `app`.",
+            ),
+            (r#"{app="api}"#, "unterminated string."),
+            (r#"{1app="api"}"#, "expected a label name."),
+            (r#"{app=api}"#, "expected a string literal."),
+            (r#"{app="api"} "error""#, "unexpected `\"error\"`."),
+        ] {
+            let error = parse_log_query(logql).unwrap_err().to_string();
+            assert!(
+                error.contains(expected_error),
+                "`{logql}` failed with `{error}`."
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_stream_selector() {
+        assert_eq!(
+            parse_stream_selector(r#"{app="api"}"#).unwrap(),
+            [LabelMatcher {
+                label: "app".to_string(),
+                operator: MatchOperator::Equal,
+                value: "api".to_string(),
+            }]
+        );
+        parse_stream_selector(r#"{app="api"} |= "error""#).unwrap_err();
+    }
+}
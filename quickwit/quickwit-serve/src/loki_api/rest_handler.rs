@@ -0,0 +1,946 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use quickwit_config::IndexConfig;
+use quickwit_doc_mapper::{FieldMappingEntry, FieldMappingType};
+use quickwit_metastore::Metastore;
+use quickwit_proto::{ListTermsRequest, SearchRequest, SortOrder};
+use quickwit_search::{resolve_single_index_id, term_to_json, SearchError, SearchService};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tracing::info;
+use warp::{Filter, Rejection};
+
+use super::logql::{
+    invalid_logql, parse_log_query, parse_stream_selector, LogQuery, MatchOperator,
+};
+use crate::{with_arg, BodyFormat};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(
+    loki_query_range_handler,
+    loki_labels_handler,
+    loki_label_values_handler
+))]
+pub struct LokiApi;
+
+/// Number of log lines returned by the queries without a `limit` parameter.
+const DEFAULT_LIMIT: u64 = 100;
+
+/// Maximum number of log lines returned by a query, like Loki's default
+/// `max_entries_limit_per_query`.
+const MAX_LIMIT: u64 = 5_000;
+
+/// Time range of the requests without a `start` parameter.
+const DEFAULT_LOOKBACK_NANOS: i64 = 3_600 * NANOS_PER_SEC;
+
+/// Maximum number of values returned by the label values endpoint.
+const MAX_LABEL_VALUES: u64 = 1_000;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+pub(crate) fn loki_api_handlers(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    loki_query_range_handler(search_service.clone(), metastore.clone())
+        .or(loki_labels_handler(metastore.clone()))
+        .or(loki_label_values_handler(search_service, metastore))
+}
+
+/// Order of the log lines returned by a query.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Returns the most recent log lines first.
+    #[default]
+    Backward,
+    /// Returns the oldest log lines first.
+    Forward,
+}
+
+/// This struct represents the QueryString passed to the Loki query range REST API.
+#[derive(Debug, Deserialize, PartialEq, Eq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QueryRangeQueryString {
+    /// LogQL log query, e.g. `{service_name="api"} |= "timeout"`.
+    pub query: String,
+    /// Start of the time range, as a Unix timestamp in nanoseconds or seconds, or as an RFC 3339
+    /// datetime. Defaults to one hour before `end`.
+    #[serde(default)]
+    pub start: Option<String>,
+    /// End of the time range, in the same formats as `start`. Defaults to now.
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Maximum number of log lines returned.
+    #[serde(default = "default_limit")]
+    pub limit: u64,
+    /// Order of the log lines.
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+fn default_limit() -> u64 {
+    DEFAULT_LIMIT
+}
+
+/// This struct represents the QueryString passed to the Loki label values REST API.
+#[derive(Debug, Deserialize, PartialEq, Eq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LabelValuesQueryString {
+    /// Start of the time range. Defaults to one hour before `end`.
+    #[serde(default)]
+    pub start: Option<String>,
+    /// End of the time range. Defaults to now.
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Stream selector restricting the documents whose label values are returned.
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct LokiResponse<T> {
+    status: &'static str,
+    data: T,
+}
+
+impl<T> LokiResponse<T> {
+    fn success(data: T) -> Self {
+        Self {
+            status: "success",
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct StreamsData {
+    result_type: &'static str,
+    result: Vec<LokiStream>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct LokiStream {
+    stream: BTreeMap<String, String>,
+    /// Pairs of timestamp in nanoseconds and log line.
+    values: Vec<(String, String)>,
+}
+
+/// How the documents of an index are exposed as Loki streams.
+#[derive(Debug)]
+struct LokiIndex {
+    index_id: String,
+    timestamp_field: String,
+    /// Labels of the streams, mapped to the path of the field holding their value. The labels
+    /// are the indexed text fields with the `raw` tokenizer.
+    labels: BTreeMap<String, Vec<String>>,
+    /// Field holding the log line, the first default search field of the index. When unset, the
+    /// whole document is the log line.
+    line_field_opt: Option<String>,
+}
+
+impl LokiIndex {
+    fn from_index_config(index_config: &IndexConfig) -> Result<Self, SearchError> {
+        let index_id = index_config.index_id.clone();
+        let Some(timestamp_field) = index_config.doc_mapping.timestamp_field.clone() else {
+            return Err(SearchError::InvalidArgument(format!(
+                "Index `{index_id}` has no timestamp field, which the Loki API requires."
+            )));
+        };
+        let mut labels = BTreeMap::new();
+        collect_labels(
+            &index_config.doc_mapping.field_mappings,
+            &mut Vec::new(),
+            &mut labels,
+        );
+        let line_field_opt = index_config
+            .search_settings
+            .default_search_fields
+            .first()
+            .cloned();
+        Ok(Self {
+            index_id,
+            timestamp_field,
+            labels,
+            line_field_opt,
+        })
+    }
+
+    /// Translates a log query into a query of the query language.
+    fn search_query(&self, log_query: &LogQuery) -> Result<String, SearchError> {
+        let mut clauses = Vec::new();
+        let mut has_positive_clause = false;
+
+        for matcher in &log_query.matchers {
+            let Some(label_path) = self.labels.get(&matcher.label) else {
+                return Err(invalid_logql(format!(
+                    "unknown label `{}`, the labels of index `{}` are: {}.",
+                    matcher.label,
+                    self.index_id,
+                    self.labels.keys().map(String::as_str).collect::<Vec<_>>().join(", ")
+                )));
+            };
+            if matcher.value.is_empty() {
+                return Err(invalid_logql(format!(
+                    "matching the empty value of label `{}` is not supported.",
+                    matcher.label
+                )));
+            }
+            let field = field_path(label_path);
+            let clause = match matcher.operator {
+                MatchOperator::Equal | MatchOperator::NotEqual => {
+                    format!("{field}:{}", quote_phrase(&matcher.value))
+                }
+                MatchOperator::Regex | MatchOperator::NotRegex => {
+                    format!("{field}:{}", quote_regex(&matcher.value))
+                }
+            };
+            has_positive_clause |= !matcher.operator.is_negative();
+            clauses.push(negate_if(clause, matcher.operator.is_negative()));
+        }
+        for line_filter in &log_query.line_filters {
+            // Like in Loki, an empty line filter matches all the lines.
+            if line_filter.value.is_empty() {
+                continue;
+            }
+            let clause = match (line_filter.operator, &self.line_field_opt) {
+                (MatchOperator::Equal | MatchOperator::NotEqual, Some(line_field)) => {
+                    format!("{line_field}:{}", quote_phrase(&line_filter.value))
+                }
+                (MatchOperator::Equal | MatchOperator::NotEqual, None) => {
+                    quote_phrase(&line_filter.value)
+                }
+                (MatchOperator::Regex | MatchOperator::NotRegex, Some(line_field)) => {
+                    let regex = format!(".*{}.*", line_filter.value);
+                    format!("{line_field}:{}", quote_regex(&regex))
+                }
+                (MatchOperator::Regex | MatchOperator::NotRegex, None) => {
+                    return Err(invalid_logql(format!(
+                        "regex line filters require index `{}` to have a default search field.",
+                        self.index_id
+                    )));
+                }
+            };
+            has_positive_clause |= !line_filter.operator.is_negative();
+            clauses.push(negate_if(clause, line_filter.operator.is_negative()));
+        }
+        if !has_positive_clause {
+            clauses.insert(0, "*".to_string());
+        }
+        Ok(clauses.join(" AND "))
+    }
+
+    /// Returns the labels of the stream the document belongs to.
+    fn stream_labels(&self, doc: &JsonMap<String, JsonValue>) -> BTreeMap<String, String> {
+        self.labels
+            .iter()
+            .filter_map(|(label, label_path)| {
+                let label_value = match doc_value(doc, label_path)? {
+                    JsonValue::String(label_value) => label_value.clone(),
+                    JsonValue::Array(_) | JsonValue::Object(_) | JsonValue::Null => return None,
+                    label_value => label_value.to_string(),
+                };
+                Some((label.clone(), label_value))
+            })
+            .collect()
+    }
+
+    fn log_line(&self, doc: &JsonMap<String, JsonValue>, doc_json: &str) -> String {
+        let Some(line_field) = &self.line_field_opt else {
+            return doc_json.to_string();
+        };
+        match doc_value(doc, &split_field_path(line_field)) {
+            Some(JsonValue::String(line)) => line.clone(),
+            Some(line_value) => line_value.to_string(),
+            None => doc_json.to_string(),
+        }
+    }
+
+    /// Returns the timestamp of the document in nanoseconds. The timestamp field is only
+    /// returned if it is stored, so the documents of the OpenTelemetry logs index fall back on
+    /// their `timestamp_nanos` field.
+    fn timestamp_nanos(&self, doc: &JsonMap<String, JsonValue>) -> Option<i64> {
+        doc_value(doc, &split_field_path(&self.timestamp_field))
+            .or_else(|| doc.get("timestamp_nanos"))
+            .and_then(json_timestamp_nanos)
+    }
+}
+
+/// Collects the indexed text fields with the `raw` tokenizer. Their label is their path, in
+/// which the characters not allowed in Loki label names are replaced with underscores.
+fn collect_labels(
+    field_mappings: &[FieldMappingEntry],
+    parent_path: &mut Vec<String>,
+    labels: &mut BTreeMap<String, Vec<String>>,
+) {
+    for field_mapping in field_mappings {
+        parent_path.push(field_mapping.name.clone());
+
+        match &field_mapping.mapping_type {
+            FieldMappingType::Text(text_options, _)
+                if text_options.indexed
+                    && text_options
+                        .tokenizer
+                        .as_ref()
+                        .map(|tokenizer| tokenizer.get_name() == "raw")
+                        .unwrap_or(false) =>
+            {
+                let label = parent_path
+                    .join("_")
+                    .replace(|character: char| !character.is_ascii_alphanumeric(), "_");
+                labels.entry(label).or_insert_with(|| parent_path.clone());
+            }
+            FieldMappingType::Object(object_options) => {
+                collect_labels(&object_options.field_mappings, parent_path, labels);
+            }
+            _ => {}
+        }
+        parent_path.pop();
+    }
+}
+
+/// Returns the path of a field in the query language, in which the dots of the field names are
+/// escaped.
+fn field_path(field_names: &[String]) -> String {
+    field_names
+        .iter()
+        .map(|field_name| field_name.replace('.', r"\."))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Splits a field path of the query language into field names.
+fn split_field_path(field_path: &str) -> Vec<String> {
+    let mut field_names = vec![String::new()];
+    let mut characters = field_path.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => {
+                if let Some(escaped_character) = characters.next() {
+                    field_names.last_mut().unwrap().push(escaped_character);
+                }
+            }
+            '.' => field_names.push(String::new()),
+            _ => field_names.last_mut().unwrap().push(character),
+        }
+    }
+    field_names
+}
+
+fn doc_value<'a>(
+    doc: &'a JsonMap<String, JsonValue>,
+    field_names: &[String],
+) -> Option<&'a JsonValue> {
+    let (last_field_name, parent_field_names) = field_names.split_last()?;
+    let mut object = doc;
+
+    for field_name in parent_field_names {
+        object = object.get(field_name)?.as_object()?;
+    }
+    object.get(last_field_name)
+}
+
+fn quote_phrase(phrase: &str) -> String {
+    format!("\"{}\"", phrase.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+fn quote_regex(regex: &str) -> String {
+    format!("/{}/", regex.replace('/', r"\/").replace('"', "\\\""))
+}
+
+fn negate_if(clause: String, negate: bool) -> String {
+    if negate {
+        format!("NOT {clause}")
+    } else {
+        clause
+    }
+}
+
+/// Converts a timestamp of a document into nanoseconds. Numeric timestamps are interpreted as
+/// seconds, milliseconds, microseconds, or nanoseconds depending on their magnitude.
+fn json_timestamp_nanos(timestamp: &JsonValue) -> Option<i64> {
+    match timestamp {
+        JsonValue::Number(number) => {
+            if let Some(timestamp) = number.as_i64() {
+                let scale = match timestamp.unsigned_abs() {
+                    0..=99_999_999_999 => NANOS_PER_SEC,
+                    100_000_000_000..=99_999_999_999_999 => 1_000_000,
+                    100_000_000_000_000..=99_999_999_999_999_999 => 1_000,
+                    _ => 1,
+                };
+                return timestamp.checked_mul(scale);
+            }
+            let timestamp = number.as_f64()?;
+            let scale = match timestamp.abs() {
+                abs_timestamp if abs_timestamp < 1e11 => 1e9,
+                abs_timestamp if abs_timestamp < 1e14 => 1e6,
+                abs_timestamp if abs_timestamp < 1e17 => 1e3,
+                _ => 1.0,
+            };
+            Some((timestamp * scale) as i64)
+        }
+        JsonValue::String(datetime) => OffsetDateTime::parse(datetime, &Rfc3339)
+            .ok()
+            .map(|datetime| datetime.unix_timestamp_nanos() as i64),
+        _ => None,
+    }
+}
+
+/// Parses a timestamp parameter: a Unix timestamp in nanoseconds, in seconds if it has at most
+/// 10 digits or a fractional part, or an RFC 3339 datetime.
+fn parse_loki_timestamp(timestamp: &str) -> Result<i64, SearchError> {
+    if timestamp.contains('.') {
+        if let Ok(timestamp_secs) = timestamp.parse::<f64>() {
+            return Ok((timestamp_secs * 1e9) as i64);
+        }
+    }
+    if let Ok(timestamp_int) = timestamp.parse::<i64>() {
+        if timestamp.trim_start_matches('-').len() <= 10 {
+            return Ok(timestamp_int.saturating_mul(NANOS_PER_SEC));
+        }
+        return Ok(timestamp_int);
+    }
+    OffsetDateTime::parse(timestamp, &Rfc3339)
+        .map(|datetime| datetime.unix_timestamp_nanos() as i64)
+        .map_err(|_| {
+            SearchError::InvalidArgument(format!(
+                "Invalid timestamp `{timestamp}`: expected a Unix timestamp or an RFC 3339 \
+                 datetime."
+            ))
+        })
+}
+
+/// Returns the time range `[start, end]` of a request in nanoseconds.
+fn parse_time_range(
+    start_opt: Option<&str>,
+    end_opt: Option<&str>,
+) -> Result<(i64, i64), SearchError> {
+    let end = match end_opt {
+        Some(end) => parse_loki_timestamp(end)?,
+        None => OffsetDateTime::now_utc().unix_timestamp_nanos() as i64,
+    };
+    let start = match start_opt {
+        Some(start) => parse_loki_timestamp(start)?,
+        None => end - DEFAULT_LOOKBACK_NANOS,
+    };
+    if start > end {
+        return Err(SearchError::InvalidArgument(
+            "The start of the time range must not be after its end.".to_string(),
+        ));
+    }
+    Ok((start, end))
+}
+
+/// Returns the time range `[start, end]` in nanoseconds as the semi-open interval of seconds
+/// covering it.
+fn time_range_secs(start: i64, end: i64) -> (i64, i64) {
+    (
+        start.div_euclid(NANOS_PER_SEC),
+        end.div_euclid(NANOS_PER_SEC) + 1,
+    )
+}
+
+async fn loki_index(index_id: &str, metastore: &dyn Metastore) -> Result<LokiIndex, SearchError> {
+    let index_id = resolve_single_index_id(metastore, index_id).await?;
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    LokiIndex::from_index_config(index_metadata.index_config())
+}
+
+async fn query_range_endpoint(
+    index_id: String,
+    query_range_query: QueryRangeQueryString,
+    search_service: &dyn SearchService,
+    metastore: &dyn Metastore,
+) -> Result<LokiResponse<StreamsData>, SearchError> {
+    if query_range_query.limit > MAX_LIMIT {
+        return Err(SearchError::InvalidArgument(format!(
+            "`limit` cannot exceed {MAX_LIMIT}."
+        )));
+    }
+    let (start, end) = parse_time_range(
+        query_range_query.start.as_deref(),
+        query_range_query.end.as_deref(),
+    )?;
+    let log_query = parse_log_query(&query_range_query.query)?;
+    let loki_index = loki_index(&index_id, metastore).await?;
+    let query = loki_index.search_query(&log_query)?;
+    let sort_order = match query_range_query.direction {
+        Direction::Backward => SortOrder::Desc,
+        Direction::Forward => SortOrder::Asc,
+    };
+    let (start_timestamp, end_timestamp) = time_range_secs(start, end);
+    let search_request = SearchRequest {
+        index_id,
+        query,
+        max_hits: query_range_query.limit,
+        start_timestamp: Some(start_timestamp),
+        end_timestamp: Some(end_timestamp),
+        sort_order: Some(sort_order as i32),
+        sort_by_field: Some(loki_index.timestamp_field.clone()),
+        ..Default::default()
+    };
+    let search_response = search_service.root_search(search_request).await?;
+    let mut streams: BTreeMap<BTreeMap<String, String>, Vec<(String, String)>> = BTreeMap::new();
+
+    for hit in search_response.hits {
+        let doc: JsonMap<String, JsonValue> = serde_json::from_str(&hit.json)?;
+        let Some(timestamp) = loki_index.timestamp_nanos(&doc) else {
+            continue;
+        };
+        if timestamp < start || timestamp > end {
+            continue;
+        }
+        streams
+            .entry(loki_index.stream_labels(&doc))
+            .or_default()
+            .push((timestamp.to_string(), loki_index.log_line(&doc, &hit.json)));
+    }
+    let result = streams
+        .into_iter()
+        .map(|(stream, values)| LokiStream { stream, values })
+        .collect();
+    Ok(LokiResponse::success(StreamsData {
+        result_type: "streams",
+        result,
+    }))
+}
+
+async fn loki_query_range(
+    index_id: String,
+    query_range_query: QueryRangeQueryString,
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, request =? query_range_query, "loki-query-range");
+    BodyFormat::Json.make_rest_reply(
+        query_range_endpoint(index_id, query_range_query, &*search_service, &*metastore).await,
+    )
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki compatible API",
+    path = "/{index_id}/loki/api/v1/query_range",
+    responses(
+        (status = 200, description = "Successfully executed the log query.")
+    ),
+    params(
+        QueryRangeQueryString,
+        ("index_id" = String, Path, description = "The index ID to query."),
+    )
+)]
+/// Loki Query Range
+///
+/// Runs a LogQL log query over the time range, returning the matching log lines grouped by
+/// stream. Only stream selectors and line filters are supported.
+pub fn loki_query_range_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_loki" / String / "loki" / "api" / "v1" / "query_range")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(loki_query_range)
+}
+
+async fn labels_endpoint(
+    index_id: String,
+    metastore: &dyn Metastore,
+) -> Result<LokiResponse<Vec<String>>, SearchError> {
+    let loki_index = loki_index(&index_id, metastore).await?;
+    let labels = loki_index.labels.into_keys().collect();
+    Ok(LokiResponse::success(labels))
+}
+
+async fn loki_labels(index_id: String, metastore: Arc<dyn Metastore>) -> impl warp::Reply {
+    info!(index_id = %index_id, "loki-labels");
+    BodyFormat::Json.make_rest_reply(labels_endpoint(index_id, &*metastore).await)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki compatible API",
+    path = "/{index_id}/loki/api/v1/labels",
+    responses(
+        (status = 200, description = "Successfully listed the labels.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID."),
+    )
+)]
+/// Loki Labels
+///
+/// Lists the labels of the index, i.e. its indexed text fields with the `raw` tokenizer.
+pub fn loki_labels_handler(
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_loki" / String / "loki" / "api" / "v1" / "labels")
+        .and(warp::get())
+        .and(with_arg(metastore))
+        .then(loki_labels)
+}
+
+async fn label_values_endpoint(
+    index_id: String,
+    label: String,
+    label_values_query: LabelValuesQueryString,
+    search_service: &dyn SearchService,
+    metastore: &dyn Metastore,
+) -> Result<LokiResponse<Vec<String>>, SearchError> {
+    let (start, end) = parse_time_range(
+        label_values_query.start.as_deref(),
+        label_values_query.end.as_deref(),
+    )?;
+    let loki_index = loki_index(&index_id, metastore).await?;
+    let Some(label_path) = loki_index.labels.get(&label) else {
+        return Err(SearchError::InvalidArgument(format!(
+            "Unknown label `{label}`."
+        )));
+    };
+    let query = match label_values_query.query.as_deref() {
+        Some(stream_selector) => {
+            let log_query = LogQuery {
+                matchers: parse_stream_selector(stream_selector)?,
+                line_filters: Vec::new(),
+            };
+            Some(loki_index.search_query(&log_query)?)
+        }
+        None => None,
+    };
+    let (start_timestamp, end_timestamp) = time_range_secs(start, end);
+    let list_terms_request = ListTermsRequest {
+        index_id,
+        field: field_path(label_path),
+        max_hits: Some(MAX_LABEL_VALUES),
+        start_timestamp: Some(start_timestamp),
+        end_timestamp: Some(end_timestamp),
+        start_key: None,
+        end_key: None,
+        query,
+        sort_by_doc_count: false,
+    };
+    let list_terms_response = search_service.root_list_terms(list_terms_request).await?;
+    let label_values = list_terms_response
+        .terms
+        .iter()
+        .filter_map(|term| match term_to_json(term) {
+            JsonValue::String(label_value) => Some(label_value),
+            _ => None,
+        })
+        .collect();
+    Ok(LokiResponse::success(label_values))
+}
+
+async fn loki_label_values(
+    index_id: String,
+    label: String,
+    label_values_query: LabelValuesQueryString,
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, label = %label, request =? label_values_query, "loki-label-values");
+    BodyFormat::Json.make_rest_reply(
+        label_values_endpoint(
+            index_id,
+            label,
+            label_values_query,
+            &*search_service,
+            &*metastore,
+        )
+        .await,
+    )
+}
+
+#[utoipa::path(
+    get,
+    tag = "Loki compatible API",
+    path = "/{index_id}/loki/api/v1/label/{label}/values",
+    responses(
+        (status = 200, description = "Successfully listed the label values.")
+    ),
+    params(
+        LabelValuesQueryString,
+        ("index_id" = String, Path, description = "The index ID."),
+        ("label" = String, Path, description = "The label whose values are listed."),
+    )
+)]
+/// Loki Label Values
+///
+/// Lists the values of a label over the time range, optionally restricted to the streams matching
+/// a stream selector.
+pub fn loki_label_values_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_loki" / String / "loki" / "api" / "v1" / "label" / String / "values")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .then(loki_label_values)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::{Hit, ListTermsResponse, SearchResponse};
+    use quickwit_search::{encode_term_for_test, MockSearchService};
+    use serde_json::json;
+
+    use super::*;
+    use crate::recover_fn;
+
+    fn test_loki_index() -> LokiIndex {
+        let index_config = IndexConfig::for_test("test-index", "ram:///indexes/test-index");
+        LokiIndex::from_index_config(&index_config).unwrap()
+    }
+
+    fn test_metastore() -> MockMetastore {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                Ok(IndexMetadata::for_test(
+                    index_id,
+                    "ram:///indexes/test-index",
+                ))
+            });
+        metastore
+    }
+
+    #[test]
+    fn test_loki_index_labels() {
+        let loki_index = test_loki_index();
+        assert_eq!(loki_index.timestamp_field, "timestamp");
+        assert_eq!(
+            loki_index.labels,
+            BTreeMap::from_iter([("owner".to_string(), vec!["owner".to_string()])])
+        );
+        assert_eq!(loki_index.line_field_opt.as_deref(), Some("body"));
+    }
+
+    #[test]
+    fn test_loki_index_search_query() {
+        let loki_index = test_loki_index();
+        let search_query = |logql: &str| loki_index.search_query(&parse_log_query(logql).unwrap());
+        assert_eq!(search_query("{}").unwrap(), "*");
+        assert_eq!(
+            search_query(r#"{owner="alice"} |= "connection \"reset\"""#).unwrap(),
+            r#"owner:"alice" AND body:"connection \"reset\"""#
+        );
+        assert_eq!(
+            search_query(r#"{owner!~"bob|carol"} != "debug" |~ "err/\\d+""#).unwrap(),
+            r#"NOT owner:/bob|carol/ AND NOT body:"debug" AND body:/.*err\/\d+.*/"#
+        );
+        assert_eq!(
+            search_query(r#"{owner=~"a.*"} |= """#).unwrap(),
+            "owner:/a.*/"
+        );
+
+        let error = search_query(r#"{app="api"}"#).unwrap_err();
+        assert!(error.to_string().contains("unknown label `app`"));
+        search_query(r#"{owner=""}"#).unwrap_err();
+        assert_eq!(
+            search_query(r#"{owner!="alice"}"#).unwrap(),
+            r#"* AND NOT owner:"alice""#
+        );
+    }
+
+    #[test]
+    fn test_split_field_path() {
+        assert_eq!(split_field_path("body"), ["body"]);
+        assert_eq!(
+            split_field_path(r"attributes.server\.status"),
+            ["attributes", "server.status"]
+        );
+        assert_eq!(
+            field_path(&["attributes".to_string(), "server.status".to_string()]),
+            r"attributes.server\.status"
+        );
+    }
+
+    #[test]
+    fn test_json_timestamp_nanos() {
+        let expected_timestamp = 1_680_000_000 * NANOS_PER_SEC;
+        for timestamp in [
+            json!(1_680_000_000),
+            json!(1_680_000_000_000u64),
+            json!(1_680_000_000_000_000u64),
+            json!(1_680_000_000_000_000_000u64),
+            json!(1_680_000_000.0),
+            json!("2023-03-28T10:40:00Z"),
+        ] {
+            assert_eq!(
+                json_timestamp_nanos(&timestamp),
+                Some(expected_timestamp),
+                "{timestamp}"
+            );
+        }
+        assert_eq!(json_timestamp_nanos(&json!("yesterday")), None);
+    }
+
+    #[test]
+    fn test_parse_loki_timestamp() {
+        let expected_timestamp = 1_680_000_000 * NANOS_PER_SEC;
+        assert_eq!(
+            parse_loki_timestamp("1680000000000000000").unwrap(),
+            expected_timestamp
+        );
+        assert_eq!(
+            parse_loki_timestamp("1680000000").unwrap(),
+            expected_timestamp
+        );
+        assert_eq!(
+            parse_loki_timestamp("1680000000.5").unwrap(),
+            expected_timestamp + NANOS_PER_SEC / 2
+        );
+        assert_eq!(
+            parse_loki_timestamp("2023-03-28T10:40:00Z").unwrap(),
+            expected_timestamp
+        );
+        parse_loki_timestamp("yesterday").unwrap_err();
+        parse_time_range(Some("2"), Some("1")).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_loki_query_range_api() {
+        let mut search_service = MockSearchService::new();
+        search_service
+            .expect_root_search()
+            .withf(|search_request| {
+                search_request.index_id == "test-index"
+                    && search_request.query == r#"owner:/alice|bob/ AND body:"error""#
+                    && search_request.start_timestamp == Some(1_680_000_000)
+                    && search_request.end_timestamp == Some(1_680_003_601)
+                    && search_request.max_hits == 10
+                    && search_request.sort_order == Some(SortOrder::Desc as i32)
+                    && search_request.sort_by_field.as_deref() == Some("timestamp")
+            })
+            .returning(|_| {
+                let hits = [
+                    ("2023-03-28T10:40:02Z", "bob", "error 2"),
+                    ("2023-03-28T10:40:01Z", "alice", "error 1"),
+                    ("2023-03-28T10:40:00Z", "bob", "error 0"),
+                ]
+                .into_iter()
+                .map(|(timestamp, owner, body)| Hit {
+                    json: json!({"timestamp": timestamp, "owner": owner, "body": body}).to_string(),
+                    partial_hit: None,
+                    snippet: None,
+                })
+                .collect();
+                Ok(SearchResponse {
+                    num_hits: 3,
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let loki_api_handlers =
+            loki_api_handlers(Arc::new(search_service), Arc::new(test_metastore()))
+                .recover(recover_fn);
+        let resp = warp::test::request()
+            .path(
+                "/_loki/test-index/loki/api/v1/query_range?query=%7Bowner%3D~%22alice%7Cbob%22%7D%20%7C%3D%20%22error%22&start=1680000000&end=1680003600&limit=10",
+            )
+            .reply(&loki_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_resp_json = json!({
+            "status": "success",
+            "data": {
+                "resultType": "streams",
+                "result": [
+                    {
+                        "stream": {"owner": "alice"},
+                        "values": [["1680000001000000000", "error 1"]],
+                    },
+                    {
+                        "stream": {"owner": "bob"},
+                        "values": [
+                            ["1680000002000000000", "error 2"],
+                            ["1680000000000000000", "error 0"],
+                        ],
+                    },
+                ],
+            },
+        });
+        assert_eq!(resp_json, expected_resp_json);
+
+        let resp = warp::test::request()
+            .path("/_loki/test-index/loki/api/v1/query_range?query=rate(%7Bowner%3D%22a%22%7D%5B5m%5D)")
+            .reply(&loki_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_loki_labels_api() {
+        let mut search_service = MockSearchService::new();
+        search_service
+            .expect_root_list_terms()
+            .withf(|list_terms_request| {
+                list_terms_request.field == "owner"
+                    && list_terms_request.query.as_deref() == Some(r#"* AND NOT owner:"carol""#)
+            })
+            .returning(|_| {
+                Ok(ListTermsResponse {
+                    num_hits: 2,
+                    terms: vec![encode_term_for_test!("alice"), encode_term_for_test!("bob")],
+                    elapsed_time_micros: 0,
+                    errors: Vec::new(),
+                    doc_counts: Vec::new(),
+                })
+            });
+        let loki_api_handlers =
+            loki_api_handlers(Arc::new(search_service), Arc::new(test_metastore()))
+                .recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/_loki/test-index/loki/api/v1/labels")
+            .reply(&loki_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(resp_json, json!({"status": "success", "data": ["owner"]}));
+
+        let resp = warp::test::request()
+            .path("/_loki/test-index/loki/api/v1/label/owner/values?query=%7Bowner%21%3D%22carol%22%7D")
+            .reply(&loki_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            resp_json,
+            json!({"status": "success", "data": ["alice", "bob"]})
+        );
+    }
+}
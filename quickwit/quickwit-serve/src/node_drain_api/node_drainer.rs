@@ -0,0 +1,191 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_actors::Mailbox;
+use quickwit_cluster::{Cluster, NodeDrainStatus};
+use quickwit_indexing::actors::IndexingService;
+use quickwit_indexing::models::DrainPipelines;
+use quickwit_ingest_api::{DrainQueues, IngestApiService};
+use quickwit_proto::indexing_api::ApplyIndexingPlanRequest;
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{info, warn};
+
+const DRAIN_CHECK_INTERVAL: Duration = if cfg!(test) {
+    Duration::from_millis(10)
+} else {
+    Duration::from_secs(1)
+};
+
+/// Number of consecutive checks that must find no in-flight data before the node is considered
+/// drained. The statistics of the indexing pipelines are observed periodically, so a single check
+/// may rely on a stale observation.
+const NUM_IDLE_CHECKS: usize = 3;
+
+#[derive(Debug, Error)]
+#[error("Node `{requested_node_id}` cannot be drained from node `{self_node_id}`.")]
+pub(crate) struct NodeDrainError {
+    requested_node_id: String,
+    self_node_id: String,
+}
+
+impl ServiceError for NodeDrainError {
+    fn status_code(&self) -> ServiceErrorCode {
+        ServiceErrorCode::BadRequest
+    }
+}
+
+/// Progress of the drain of a node.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct NodeDrainState {
+    pub node_id: String,
+    pub status: NodeDrainStatus,
+    /// Number of records of the ingest API queues not indexed yet, as of the last check.
+    pub num_pending_ingest_records: usize,
+    /// Number of indexing pipelines holding documents or splits not published yet, as of the
+    /// last check.
+    pub num_pipelines_with_in_flight_data: usize,
+}
+
+/// Drains the node before it is decommissioned: the node stops accepting new documents and
+/// fetching documents from its sources, its ingest queues are emptied, its in-flight splits are
+/// published, and it finally leaves the indexing plan and the search and ingest routing.
+pub(crate) struct NodeDrainer {
+    cluster: Arc<Cluster>,
+    indexing_service_opt: Option<Mailbox<IndexingService>>,
+    ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
+    state: Arc<Mutex<NodeDrainState>>,
+}
+
+impl NodeDrainer {
+    pub fn new(
+        cluster: Arc<Cluster>,
+        indexing_service_opt: Option<Mailbox<IndexingService>>,
+        ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
+    ) -> Self {
+        let state = NodeDrainState {
+            node_id: cluster.node_id.id.clone(),
+            status: NodeDrainStatus::Active,
+            num_pending_ingest_records: 0,
+            num_pipelines_with_in_flight_data: 0,
+        };
+        Self {
+            cluster,
+            indexing_service_opt,
+            ingest_api_service_opt,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    pub fn state(&self, node_id: &str) -> Result<NodeDrainState, NodeDrainError> {
+        self.check_node_id(node_id)?;
+        Ok(self.state.lock().unwrap().clone())
+    }
+
+    /// Starts draining the node. Draining a node already being drained is a no-op.
+    pub async fn drain(&self, node_id: &str) -> Result<NodeDrainState, NodeDrainError> {
+        self.check_node_id(node_id)?;
+        {
+            let mut state_guard = self.state.lock().unwrap();
+            if state_guard.status != NodeDrainStatus::Active {
+                return Ok(state_guard.clone());
+            }
+            state_guard.status = NodeDrainStatus::Draining;
+        }
+        info!(node_id = %node_id, "Draining node.");
+        self.cluster
+            .set_self_node_drain_status(NodeDrainStatus::Draining)
+            .await;
+        tokio::spawn(drain_node(
+            self.cluster.clone(),
+            self.indexing_service_opt.clone(),
+            self.ingest_api_service_opt.clone(),
+            self.state.clone(),
+        ));
+        Ok(self.state.lock().unwrap().clone())
+    }
+
+    fn check_node_id(&self, node_id: &str) -> Result<(), NodeDrainError> {
+        if node_id != self.cluster.node_id.id {
+            return Err(NodeDrainError {
+                requested_node_id: node_id.to_string(),
+                self_node_id: self.cluster.node_id.id.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn drain_node(
+    cluster: Arc<Cluster>,
+    indexing_service_opt: Option<Mailbox<IndexingService>>,
+    ingest_api_service_opt: Option<Mailbox<IngestApiService>>,
+    state: Arc<Mutex<NodeDrainState>>,
+) {
+    let mut num_idle_checks = 0;
+
+    while num_idle_checks < NUM_IDLE_CHECKS {
+        // The ingest API sources keep running until their queue is empty, the other sources are
+        // paused.
+        let num_pending_ingest_records_opt = match &ingest_api_service_opt {
+            Some(ingest_api_service) => ingest_api_service.ask(DrainQueues).await.ok(),
+            None => Some(0),
+        };
+        let num_pipelines_with_in_flight_data_opt = match &indexing_service_opt {
+            Some(indexing_service) => indexing_service.ask(DrainPipelines).await.ok(),
+            None => Some(0),
+        };
+        let (Some(num_pending_ingest_records), Some(num_pipelines_with_in_flight_data)) =
+            (num_pending_ingest_records_opt, num_pipelines_with_in_flight_data_opt)
+        else {
+            warn!("Failed to check the in-flight data of the node being drained.");
+            num_idle_checks = 0;
+            tokio::time::sleep(DRAIN_CHECK_INTERVAL).await;
+            continue;
+        };
+        if num_pending_ingest_records == 0 && num_pipelines_with_in_flight_data == 0 {
+            num_idle_checks += 1;
+        } else {
+            num_idle_checks = 0;
+        }
+        {
+            let mut state_guard = state.lock().unwrap();
+            state_guard.num_pending_ingest_records = num_pending_ingest_records;
+            state_guard.num_pipelines_with_in_flight_data = num_pipelines_with_in_flight_data;
+        }
+        tokio::time::sleep(DRAIN_CHECK_INTERVAL).await;
+    }
+    // The control plane does not assign tasks to the nodes being drained, so the pipelines are
+    // already running on other indexers by now.
+    if let Some(indexing_service) = &indexing_service_opt {
+        let apply_plan_request = ApplyIndexingPlanRequest::default();
+        if let Err(error) = indexing_service.ask_for_res(apply_plan_request).await {
+            warn!(error = ?error, "Failed to shut down the indexing pipelines of the node.");
+        }
+    }
+    cluster
+        .set_self_node_drain_status(NodeDrainStatus::Drained)
+        .await;
+    state.lock().unwrap().status = NodeDrainStatus::Drained;
+    info!(node_id = %cluster.node_id.id, "Node drained, it can now be terminated.");
+}
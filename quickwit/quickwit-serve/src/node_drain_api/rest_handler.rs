@@ -0,0 +1,170 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use quickwit_cluster::NodeDrainStatus;
+use warp::{Filter, Rejection};
+
+use super::node_drainer::{NodeDrainError, NodeDrainState, NodeDrainer};
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(drain_node, get_node_drain_state),
+    components(schemas(NodeDrainState, NodeDrainStatus))
+)]
+pub struct NodeDrainApi;
+
+pub(crate) fn node_drain_api_handlers(
+    node_drainer: Arc<NodeDrainer>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    drain_node_handler(node_drainer.clone()).or(get_node_drain_state_handler(node_drainer))
+}
+
+fn drain_node_handler(
+    node_drainer: Arc<NodeDrainer>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("nodes" / String / "drain")
+        .and(warp::post())
+        .and(with_arg(node_drainer))
+        .then(drain_node)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+fn get_node_drain_state_handler(
+    node_drainer: Arc<NodeDrainer>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("nodes" / String / "drain")
+        .and(warp::get())
+        .and(with_arg(node_drainer))
+        .then(get_node_drain_state)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Node Drain",
+    path = "/nodes/{node_id}/drain",
+    responses(
+        (status = 200, description = "Successfully started draining the node.", body = NodeDrainState)
+    ),
+    params(
+        ("node_id" = String, Path, description = "The ID of the node to drain. The request must be sent to that node."),
+    )
+)]
+/// Drain Node
+///
+/// Starts draining the node: the node rejects new documents, pauses its sources, empties its
+/// ingest queues, publishes its in-flight splits, and finally leaves the indexing plan and the
+/// search and ingest routing. The node can be terminated once its drain status is `drained`.
+async fn drain_node(
+    node_id: String,
+    node_drainer: Arc<NodeDrainer>,
+) -> Result<NodeDrainState, NodeDrainError> {
+    node_drainer.drain(&node_id).await
+}
+
+#[utoipa::path(
+    get,
+    tag = "Node Drain",
+    path = "/nodes/{node_id}/drain",
+    responses(
+        (status = 200, description = "Successfully fetched the drain state of the node.", body = NodeDrainState)
+    ),
+    params(
+        ("node_id" = String, Path, description = "The ID of the node. The request must be sent to that node."),
+    )
+)]
+/// Get Node Drain State
+async fn get_node_drain_state(
+    node_id: String,
+    node_drainer: Arc<NodeDrainer>,
+) -> Result<NodeDrainState, NodeDrainError> {
+    node_drainer.state(&node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chitchat::transport::ChannelTransport;
+    use quickwit_cluster::create_cluster_for_test;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_node_drain_api() {
+        let transport = ChannelTransport::default();
+        let cluster = Arc::new(
+            create_cluster_for_test(Vec::new(), &["searcher"], &transport, true)
+                .await
+                .unwrap(),
+        );
+        let node_id = cluster.node_id.id.clone();
+        let node_drainer = Arc::new(NodeDrainer::new(cluster.clone(), None, None));
+        let node_drain_api_handlers = node_drain_api_handlers(node_drainer).recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path(&format!("/nodes/{node_id}/drain"))
+            .reply(&node_drain_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let state: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(state["status"], "active");
+
+        let resp = warp::test::request()
+            .path("/nodes/another-node/drain")
+            .method("POST")
+            .reply(&node_drain_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        let resp = warp::test::request()
+            .path(&format!("/nodes/{node_id}/drain"))
+            .method("POST")
+            .reply(&node_drain_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let state: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(state["status"], "draining");
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let resp = warp::test::request()
+                    .path(&format!("/nodes/{node_id}/drain"))
+                    .reply(&node_drain_api_handlers)
+                    .await;
+                let state: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+                if state["status"] == "drained" {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(!cluster.is_self_node_ready().await);
+    }
+}
@@ -25,6 +25,10 @@ use warp::{Filter, Rejection};
 
 use crate::{with_arg, QuickwitBuildInfo};
 
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(node_version_handler, node_config_handler))]
+pub struct NodeInfoApi;
+
 pub fn node_info_handler(
     build_info: &'static QuickwitBuildInfo,
     config: Arc<QuickwitConfig>,
@@ -32,6 +36,15 @@ pub fn node_info_handler(
     node_version_handler(build_info).or(node_config_handler(config))
 }
 
+#[utoipa::path(
+    get,
+    tag = "Node Info",
+    path = "/version",
+    responses(
+        (status = 200, description = "Successfully fetched the build information of the node.")
+    ),
+)]
+/// Get Node Version
 fn node_version_handler(
     build_info: &'static QuickwitBuildInfo,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
@@ -45,6 +58,17 @@ async fn get_version(build_info: &'static QuickwitBuildInfo) -> impl warp::Reply
     warp::reply::json(build_info)
 }
 
+#[utoipa::path(
+    get,
+    tag = "Node Info",
+    path = "/config",
+    responses(
+        (status = 200, description = "Successfully fetched the configuration of the node.")
+    ),
+)]
+/// Get Node Config
+///
+/// The credentials of the metastore URI are redacted.
 fn node_config_handler(
     config: Arc<QuickwitConfig>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
@@ -26,13 +26,25 @@ use quickwit_metastore::MetastoreApiSchemas;
 use utoipa::openapi::Server;
 use utoipa::OpenApi;
 
+use crate::alert_api::AlertApi;
+use crate::api_key_api::ApiKeyApi;
+use crate::autoscaling_api::AutoscalingApi;
 use crate::cluster_api::ClusterApi;
+use crate::config_reload_api::ConfigReloadApi;
 use crate::delete_task_api::DeleteTaskApi;
+use crate::elastic_search_api::ElasticCompatibleApi;
 use crate::health_check_api::HealthCheckApi;
-use crate::index_api::IndexApi;
+use crate::index_alias_api::IndexAliasApi;
+use crate::index_api::{IndexApi, IndexStatsApi};
+use crate::index_template_api::IndexTemplateApi;
 use crate::indexing_api::IndexingApi;
 use crate::ingest_api::{IngestApi, IngestApiSchemas};
-use crate::search_api::SearchApi;
+use crate::loki_api::LokiApi;
+use crate::node_drain_api::NodeDrainApi;
+use crate::node_info_handler::NodeInfoApi;
+use crate::prometheus_api::PrometheusApi;
+use crate::quota_api::QuotaApi;
+use crate::search_api::{LiveTailApi, SearchApi};
 
 /// Builds the OpenApi docs structure using the registered/merged docs.
 pub fn build_docs() -> utoipa::openapi::OpenApi {
@@ -60,12 +72,30 @@ pub fn build_docs() -> utoipa::openapi::OpenApi {
     // Routing
     docs_base.merge_components_and_paths(HealthCheckApi::openapi().with_path_prefix("/health"));
     docs_base.merge_components_and_paths(MetricsApi::openapi().with_path_prefix("/metrics"));
+    docs_base.merge_components_and_paths(AlertApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(ApiKeyApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(AutoscalingApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(ClusterApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(ConfigReloadApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(DeleteTaskApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(
+        ElasticCompatibleApi::openapi().with_path_prefix("/api/v1/_elastic"),
+    );
+    docs_base.merge_components_and_paths(IndexAliasApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(IndexApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(IndexStatsApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(IndexTemplateApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(IndexingApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(IngestApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(LokiApi::openapi().with_path_prefix("/api/v1/_loki"));
+    docs_base.merge_components_and_paths(NodeDrainApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(NodeInfoApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(
+        PrometheusApi::openapi().with_path_prefix("/api/v1/_prometheus"),
+    );
+    docs_base.merge_components_and_paths(QuotaApi::openapi().with_path_prefix("/api/v1"));
     docs_base.merge_components_and_paths(SearchApi::openapi().with_path_prefix("/api/v1"));
+    docs_base.merge_components_and_paths(LiveTailApi::openapi().with_path_prefix("/api/v1"));
 
     // Schemas
     docs_base.merge_components_and_paths(MetastoreApiSchemas::openapi());
@@ -115,3 +145,51 @@ impl OpenApiMerger for utoipa::openapi::OpenApi {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_docs_cover_rest_routes() {
+        let docs = build_docs();
+        for (path, server) in [
+            ("/livez", "/health"),
+            ("/api-keys", "/api/v1"),
+            ("/autoscaling/signals", "/api/v1"),
+            ("/cluster", "/api/v1"),
+            ("/config/reload", "/api/v1"),
+            ("/indexes", "/api/v1"),
+            (
+                "/indexing/pipelines/{index_id}/{source_id}/{action}",
+                "/api/v1",
+            ),
+            ("/indexes/{index_id}/stats", "/api/v1"),
+            ("/nodes/{node_id}/drain", "/api/v1"),
+            ("/quotas/usage", "/api/v1"),
+            ("/templates/{template_id}", "/api/v1"),
+            ("/aliases/{alias_id}", "/api/v1"),
+            ("/aliases/{alias_id}/rollover", "/api/v1"),
+            ("/{index_id}/search", "/api/v1"),
+            ("/{index_id}/search/hits-stream", "/api/v1"),
+            ("/{index_id}/tail/ws", "/api/v1"),
+            ("/_search", "/api/v1/_elastic"),
+            ("/{index}/_mapping", "/api/v1/_elastic"),
+            ("/{index_id}/loki/api/v1/query_range", "/api/v1/_loki"),
+            ("/api/v1/write", "/api/v1/_prometheus"),
+            ("/version", "/api/v1"),
+            ("/config", "/api/v1"),
+        ] {
+            let path_item = docs
+                .paths
+                .paths
+                .get(path)
+                .unwrap_or_else(|| panic!("Path `{path}` should be documented."));
+            let servers = path_item.servers.as_ref().unwrap();
+            assert_eq!(servers[0].url, server);
+        }
+        let docs_json = serde_json::to_value(&docs).unwrap();
+        assert_eq!(docs_json["openapi"], "3.0.3");
+        assert!(docs_json["components"]["schemas"]["SearchRequestQueryString"].is_object());
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use quickwit_ingest_api::{DocBatch, DocBatchBuilder};
+use serde::Serialize;
+
+pub const PROMETHEUS_METRICS_INDEX_ID: &str = "prometheus-metrics-v0";
+
+pub const PROMETHEUS_METRICS_INDEX_CONFIG: &str = r#"
+version: 0.4
+
+index_id: prometheus-metrics-v0
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp
+      type: datetime
+      input_formats: [unix_timestamp]
+      fast: true
+      precision: milliseconds
+    - name: metric_name
+      type: text
+      tokenizer: raw
+    - name: job
+      type: text
+      tokenizer: raw
+    - name: instance
+      type: text
+      tokenizer: raw
+    - name: labels
+      type: json
+      tokenizer: raw
+    - name: value
+      type: f64
+      fast: true
+
+  timestamp_field: timestamp
+
+  partition_key: hash_mod(metric_name, 100)
+  tag_fields: [metric_name, job]
+
+indexing_settings:
+  commit_timeout_secs: 30
+
+search_settings:
+  default_search_fields: [metric_name]
+"#;
+
+/// Label holding the name of the metric of a time series.
+const METRIC_NAME_LABEL: &str = "__name__";
+
+/// Remote-write request, as defined in the `remote.proto` file of Prometheus. The metric
+/// metadata, exemplars, and native histograms of the request are ignored.
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct WriteRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub timeseries: Vec<TimeSeries>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct TimeSeries {
+    #[prost(message, repeated, tag = "1")]
+    pub labels: Vec<Label>,
+    #[prost(message, repeated, tag = "2")]
+    pub samples: Vec<Sample>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Label {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub value: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(crate) struct Sample {
+    #[prost(double, tag = "1")]
+    pub value: f64,
+    /// Timestamp in milliseconds.
+    #[prost(int64, tag = "2")]
+    pub timestamp: i64,
+}
+
+/// A sample of a time series, as indexed in the `prometheus-metrics-v0` index. The `job` and
+/// `instance` labels are duplicated into dedicated fields so that they can be used as tags.
+#[derive(Debug, Serialize)]
+struct MetricSample<'a> {
+    timestamp: i64,
+    metric_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instance: Option<&'a str>,
+    labels: &'a BTreeMap<&'a str, &'a str>,
+    value: f64,
+}
+
+#[derive(Debug)]
+pub(crate) struct ParsedSamples {
+    pub doc_batch: DocBatch,
+    pub num_samples: u64,
+    /// Number of samples with a `NaN` value, such as the staleness markers, which are not indexed.
+    pub num_skipped_samples: u64,
+}
+
+/// Converts the samples of a remote-write request into documents of `index_id`. The request is
+/// rejected as a whole if one of its time series has no metric name.
+pub(crate) fn parse_write_request(
+    write_request: &WriteRequest,
+    index_id: &str,
+) -> Result<ParsedSamples, String> {
+    let mut doc_batch = DocBatchBuilder::new(index_id.to_string()).json_writer();
+    let mut num_samples = 0;
+    let mut num_skipped_samples = 0;
+
+    for time_series in &write_request.timeseries {
+        let mut labels: BTreeMap<&str, &str> = time_series
+            .labels
+            .iter()
+            .filter(|label| !label.value.is_empty())
+            .map(|label| (label.name.as_str(), label.value.as_str()))
+            .collect();
+        let Some(metric_name) = labels.remove(METRIC_NAME_LABEL) else {
+            return Err(format!(
+                "Time series `{labels:?}` has no `{METRIC_NAME_LABEL}` label."
+            ));
+        };
+        for sample in &time_series.samples {
+            num_samples += 1;
+
+            if sample.value.is_nan() {
+                num_skipped_samples += 1;
+                continue;
+            }
+            let metric_sample = MetricSample {
+                timestamp: sample.timestamp,
+                metric_name,
+                job: labels.get("job").copied(),
+                instance: labels.get("instance").copied(),
+                labels: &labels,
+                value: sample.value,
+            };
+            doc_batch
+                .ingest_doc(&metric_sample)
+                .map_err(|error| format!("Failed to serialize sample: {error}"))?;
+        }
+    }
+    let parsed_samples = ParsedSamples {
+        doc_batch: doc_batch.build(),
+        num_samples,
+        num_skipped_samples,
+    };
+    Ok(parsed_samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_ingest_api::DocCommand;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+
+    fn label(name: &str, value: &str) -> Label {
+        Label {
+            name: name.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_write_request() {
+        let write_request = WriteRequest {
+            timeseries: vec![
+                TimeSeries {
+                    labels: vec![
+                        label("__name__", "http_requests_total"),
+                        label("job", "api"),
+                        label("instance", "api-0:9090"),
+                        label("method", "GET"),
+                        label("empty", ""),
+                    ],
+                    samples: vec![
+                        Sample {
+                            value: 42.0,
+                            timestamp: 1_678_974_011_123,
+                        },
+                        Sample {
+                            value: f64::NAN,
+                            timestamp: 1_678_974_026_123,
+                        },
+                    ],
+                },
+                TimeSeries {
+                    labels: vec![label("__name__", "up")],
+                    samples: vec![Sample {
+                        value: 1.0,
+                        timestamp: 1_678_974_011_000,
+                    }],
+                },
+            ],
+        };
+        let parsed_samples = parse_write_request(&write_request, "my-index").unwrap();
+        assert_eq!(parsed_samples.num_samples, 3);
+        assert_eq!(parsed_samples.num_skipped_samples, 1);
+
+        let doc_batch = parsed_samples.doc_batch;
+        assert_eq!(doc_batch.index_id, "my-index");
+        assert_eq!(doc_batch.num_docs(), 2);
+
+        let docs: Vec<JsonValue> = doc_batch
+            .iter()
+            .map(|doc_command| match doc_command {
+                DocCommand::Ingest { payload } => serde_json::from_slice(&payload).unwrap(),
+                DocCommand::Commit => panic!("Doc batch should not contain commit commands."),
+            })
+            .collect();
+        assert_eq!(
+            docs[0],
+            serde_json::json!({
+                "timestamp": 1_678_974_011_123i64,
+                "metric_name": "http_requests_total",
+                "job": "api",
+                "instance": "api-0:9090",
+                "labels": {"instance": "api-0:9090", "job": "api", "method": "GET"},
+                "value": 42.0,
+            })
+        );
+        assert_eq!(
+            docs[1],
+            serde_json::json!({
+                "timestamp": 1_678_974_011_000i64,
+                "metric_name": "up",
+                "labels": {},
+                "value": 1.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_write_request_rejects_time_series_without_metric_name() {
+        let write_request = WriteRequest {
+            timeseries: vec![TimeSeries {
+                labels: vec![label("job", "api")],
+                samples: vec![Sample {
+                    value: 1.0,
+                    timestamp: 1_678_974_011_000,
+                }],
+            }],
+        };
+        let error = parse_write_request(&write_request, "my-index").unwrap_err();
+        assert!(error.contains("__name__"));
+    }
+}
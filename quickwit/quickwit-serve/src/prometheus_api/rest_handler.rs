@@ -0,0 +1,344 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use prost::Message;
+use quickwit_config::QuickwitConfig;
+use quickwit_ingest_api::{IngestRequest, IngestService, IngestServiceClient, IngestServiceError};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use warp::{Filter, Rejection};
+
+use super::remote_write::{parse_write_request, WriteRequest, PROMETHEUS_METRICS_INDEX_ID};
+use crate::quota_api::{enforce_ingest_quotas, QuotaExceeded, QuotaTracker};
+use crate::{with_arg, BodyFormat};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(prometheus_remote_write_handler))]
+pub struct PrometheusApi;
+
+const CONTENT_LENGTH_LIMIT: u64 = 10 * 1024 * 1024; // 10MiB
+
+#[derive(Debug, Error)]
+pub enum PrometheusApiError {
+    #[error(
+        "The Prometheus remote-write endpoint is disabled. Set the indexer setting \
+         `enable_prometheus_endpoint` to `true` to enable it."
+    )]
+    Disabled,
+    #[error(
+        "Unsupported content encoding `{0}`. Remote-write requests must be compressed with \
+         `snappy`."
+    )]
+    UnsupportedContentEncoding(String),
+    #[error("Invalid remote-write request: {0}")]
+    InvalidRequest(String),
+    #[error(transparent)]
+    IngestApi(#[from] IngestServiceError),
+    #[error(transparent)]
+    Quota(#[from] QuotaExceeded),
+}
+
+impl ServiceError for PrometheusApiError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::Disabled => ServiceErrorCode::NotFound,
+            Self::UnsupportedContentEncoding(_) => ServiceErrorCode::UnsupportedMediaType,
+            Self::InvalidRequest(_) => ServiceErrorCode::BadRequest,
+            Self::IngestApi(ingest_api_error) => ingest_api_error.status_code(),
+            Self::Quota(quota_error) => quota_error.status_code(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RemoteWriteResponse {
+    /// Number of samples in the request.
+    pub num_samples: u64,
+    /// Number of samples that were not indexed because their value is `NaN`, like the staleness
+    /// markers.
+    pub num_skipped_samples: u64,
+}
+
+pub(crate) fn prometheus_api_handlers(
+    ingest_service: IngestServiceClient,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    prometheus_remote_write_handler(ingest_service, quickwit_config, quota_tracker)
+}
+
+/// Decodes the body of a remote-write request: a `WriteRequest` protobuf message compressed with
+/// the block format of Snappy.
+fn decode_write_request(
+    content_encoding_opt: Option<&str>,
+    body: &[u8],
+    max_decompressed_body_size: u64,
+) -> Result<WriteRequest, PrometheusApiError> {
+    // Some clients omit the `Content-Encoding` header, although the body is always compressed.
+    if let Some(content_encoding) = content_encoding_opt.map(str::trim) {
+        if !content_encoding.is_empty() && !content_encoding.eq_ignore_ascii_case("snappy") {
+            return Err(PrometheusApiError::UnsupportedContentEncoding(
+                content_encoding.to_string(),
+            ));
+        }
+    }
+    let decompressed_len = snap::raw::decompress_len(body).map_err(|error| {
+        PrometheusApiError::InvalidRequest(format!("Failed to decompress body: {error}"))
+    })?;
+    if decompressed_len as u64 > max_decompressed_body_size {
+        return Err(PrometheusApiError::InvalidRequest(format!(
+            "Decompressed body exceeds the limit of {max_decompressed_body_size} bytes."
+        )));
+    }
+    let decompressed_body = snap::raw::Decoder::new()
+        .decompress_vec(body)
+        .map_err(|error| {
+            PrometheusApiError::InvalidRequest(format!("Failed to decompress body: {error}"))
+        })?;
+    WriteRequest::decode(decompressed_body.as_slice()).map_err(|error| {
+        PrometheusApiError::InvalidRequest(format!("Failed to decode protobuf message: {error}"))
+    })
+}
+
+async fn remote_write(
+    content_encoding_opt: Option<String>,
+    body: Bytes,
+    mut ingest_service: IngestServiceClient,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+) -> Result<RemoteWriteResponse, PrometheusApiError> {
+    if !quickwit_config.indexer_config.enable_prometheus_endpoint {
+        return Err(PrometheusApiError::Disabled);
+    }
+    let max_decompressed_body_size = quickwit_config
+        .ingest_api_config
+        .max_decompressed_body_size
+        .get_bytes();
+    let write_request = decode_write_request(
+        content_encoding_opt.as_deref(),
+        &body,
+        max_decompressed_body_size,
+    )?;
+    let parsed_samples = parse_write_request(&write_request, PROMETHEUS_METRICS_INDEX_ID)
+        .map_err(PrometheusApiError::InvalidRequest)?;
+    let response = RemoteWriteResponse {
+        num_samples: parsed_samples.num_samples,
+        num_skipped_samples: parsed_samples.num_skipped_samples,
+    };
+    if parsed_samples.doc_batch.is_empty() {
+        return Ok(response);
+    }
+    let ingest_request = IngestRequest {
+        doc_batches: vec![parsed_samples.doc_batch],
+    };
+    enforce_ingest_quotas(&quota_tracker, &ingest_request).await?;
+    ingest_service.ingest(ingest_request).await?;
+    Ok(response)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Prometheus compatible API",
+    path = "/api/v1/write",
+    request_body(content = String, description = "`WriteRequest` protobuf message of the Prometheus remote-write protocol, compressed with Snappy and limited to 10MB.", content_type = "application/x-protobuf"),
+    responses(
+        (status = 200, description = "Successfully ingested the samples.")
+    ),
+)]
+/// Prometheus Remote Write
+///
+/// Ingests the samples of a Prometheus remote-write request into the `prometheus-metrics-v0`
+/// index. The metric name and the `job` label are indexed as tags, and all the labels are
+/// searchable in the `labels` field.
+///
+/// Requests exceeding the quota of the index are rejected with a `429 Too Many Requests` status
+/// code, so that Prometheus retries them later.
+pub fn prometheus_remote_write_handler(
+    ingest_service: IngestServiceClient,
+    quickwit_config: Arc<QuickwitConfig>,
+    quota_tracker: Arc<QuotaTracker>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("_prometheus" / "api" / "v1" / "write")
+        .and(warp::post())
+        .and(warp::header::optional::<String>("content-encoding"))
+        .and(warp::body::content_length_limit(CONTENT_LENGTH_LIMIT))
+        .and(warp::body::bytes())
+        .and(with_arg(ingest_service))
+        .and(with_arg(quickwit_config))
+        .and(with_arg(quota_tracker))
+        .then(remote_write)
+        .map(|result| BodyFormat::default().make_rest_reply(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_actors::Universe;
+    use quickwit_config::IngestApiConfig;
+    use quickwit_ingest_api::{init_ingest_api, CreateQueueIfNotExistsRequest, QUEUES_DIR_NAME};
+    use quickwit_metastore::MockMetastore;
+    use warp::Reply;
+
+    use super::*;
+    use crate::prometheus_api::remote_write::{Label, Sample, TimeSeries};
+    use crate::recover_fn;
+
+    async fn setup_ingest_service() -> (Universe, tempfile::TempDir, IngestServiceClient) {
+        let universe = Universe::with_accelerated_time();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let queues_dir_path = temp_dir.path().join(QUEUES_DIR_NAME);
+        let ingest_service_mailbox =
+            init_ingest_api(&universe, &queues_dir_path, &IngestApiConfig::default())
+                .await
+                .unwrap();
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: PROMETHEUS_METRICS_INDEX_ID.to_string(),
+        };
+        ingest_service_mailbox
+            .ask_for_res(create_queue_req)
+            .await
+            .unwrap();
+        let ingest_service = IngestServiceClient::from_mailbox(ingest_service_mailbox);
+        (universe, temp_dir, ingest_service)
+    }
+
+    fn prometheus_api_handlers_for_test(
+        ingest_service: IngestServiceClient,
+        quickwit_config: QuickwitConfig,
+    ) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let quota_tracker = QuotaTracker::new(
+            Arc::new(MockMetastore::new()),
+            quickwit_config.quotas_config.clone(),
+        );
+        prometheus_api_handlers(
+            ingest_service,
+            Arc::new(quickwit_config),
+            Arc::new(quota_tracker),
+        )
+        .recover(recover_fn)
+    }
+
+    fn snappy_encoded_write_request() -> Vec<u8> {
+        let write_request = WriteRequest {
+            timeseries: vec![TimeSeries {
+                labels: vec![
+                    Label {
+                        name: "__name__".to_string(),
+                        value: "up".to_string(),
+                    },
+                    Label {
+                        name: "job".to_string(),
+                        value: "api".to_string(),
+                    },
+                ],
+                samples: vec![
+                    Sample {
+                        value: 1.0,
+                        timestamp: 1_678_974_011_000,
+                    },
+                    Sample {
+                        value: f64::NAN,
+                        timestamp: 1_678_974_026_000,
+                    },
+                ],
+            }],
+        };
+        snap::raw::Encoder::new()
+            .compress_vec(&write_request.encode_to_vec())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decode_write_request() {
+        let body = snappy_encoded_write_request();
+        let write_request = decode_write_request(Some("snappy"), &body, 1_000).unwrap();
+        assert_eq!(write_request.timeseries.len(), 1);
+
+        let write_request = decode_write_request(None, &body, 1_000).unwrap();
+        assert_eq!(write_request.timeseries.len(), 1);
+
+        let error = decode_write_request(Some("gzip"), &body, 1_000).unwrap_err();
+        assert!(matches!(
+            error,
+            PrometheusApiError::UnsupportedContentEncoding(_)
+        ));
+        let error = decode_write_request(Some("snappy"), &body, 10).unwrap_err();
+        assert!(matches!(error, PrometheusApiError::InvalidRequest(_)));
+
+        let error = decode_write_request(Some("snappy"), b"not snappy", 1_000).unwrap_err();
+        assert!(matches!(error, PrometheusApiError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_remote_write() {
+        let (universe, _temp_dir, ingest_service) = setup_ingest_service().await;
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.indexer_config.enable_prometheus_endpoint = true;
+        let prometheus_api_handlers =
+            prometheus_api_handlers_for_test(ingest_service, quickwit_config);
+        let resp = warp::test::request()
+            .path("/_prometheus/api/v1/write")
+            .method("POST")
+            .header("content-encoding", "snappy")
+            .header("content-type", "application/x-protobuf")
+            .body(snappy_encoded_write_request())
+            .reply(&prometheus_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let remote_write_response: RemoteWriteResponse =
+            serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            remote_write_response,
+            RemoteWriteResponse {
+                num_samples: 2,
+                num_skipped_samples: 1,
+            }
+        );
+        let resp = warp::test::request()
+            .path("/_prometheus/api/v1/write")
+            .method("POST")
+            .body("not snappy")
+            .reply(&prometheus_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 400);
+
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_remote_write_disabled() {
+        let (universe, _temp_dir, ingest_service) = setup_ingest_service().await;
+        let mut quickwit_config = QuickwitConfig::for_test();
+        quickwit_config.indexer_config.enable_prometheus_endpoint = false;
+        let prometheus_api_handlers =
+            prometheus_api_handlers_for_test(ingest_service, quickwit_config);
+        let resp = warp::test::request()
+            .path("/_prometheus/api/v1/write")
+            .method("POST")
+            .body(snappy_encoded_write_request())
+            .reply(&prometheus_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        universe.assert_quit().await;
+    }
+}
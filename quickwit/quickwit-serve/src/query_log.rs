@@ -0,0 +1,209 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+
+use hyper::header::{AUTHORIZATION, USER_AGENT};
+use hyper::{Body, Request};
+use quickwit_common::uri::Uri;
+use quickwit_config::{
+    load_index_config_from_user_config, ConfigFormat, QueryLogConfig, QUERY_LOG_INDEX_ID,
+};
+use quickwit_core::{IndexService, IndexServiceError};
+use quickwit_ingest_api::{DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient};
+use quickwit_metastore::MetastoreError;
+use quickwit_search::{with_search_caller, QueryLogEvent, QueryLogger, SearchCaller};
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::api_key_api::ApiKeyAuthenticator;
+use crate::rate_limiter::RemoteAddr;
+
+const MAX_BATCH_SIZE: usize = 1_000;
+
+/// Creates the query log index, then spawns the task ingesting the searches recorded by the
+/// returned query logger into it.
+pub(crate) async fn start_query_logger(
+    query_log_config: &QueryLogConfig,
+    index_service: &IndexService,
+    ingest_service: IngestServiceClient,
+    default_index_root_uri: &Uri,
+) -> anyhow::Result<QueryLogger> {
+    create_query_log_index(index_service, default_index_root_uri).await?;
+    let (query_logger, event_rx) = QueryLogger::new(query_log_config);
+    tokio::spawn(run_query_log_sink(ingest_service, event_rx));
+    Ok(query_logger)
+}
+
+/// Identifies the caller of a REST request, so that the searches it triggers can be attributed
+/// to them in the query log.
+pub(crate) fn search_caller(
+    authenticator: &ApiKeyAuthenticator,
+    request: &Request<Body>,
+) -> SearchCaller {
+    let header_value = |header_name| {
+        request
+            .headers()
+            .get(header_name)
+            .and_then(|header_value| header_value.to_str().ok())
+    };
+    SearchCaller {
+        principal: authenticator.identify(header_value(AUTHORIZATION)),
+        remote_ip: request
+            .extensions()
+            .get::<RemoteAddr>()
+            .map(|RemoteAddr(remote_addr)| remote_addr.ip().to_string()),
+        user_agent: header_value(USER_AGENT).map(str::to_string),
+    }
+}
+
+/// Runs `future`, attributing the searches it performs to `search_caller_opt` if set.
+pub(crate) async fn attribute_searches<F: Future>(
+    search_caller_opt: Option<SearchCaller>,
+    future: F,
+) -> F::Output {
+    match search_caller_opt {
+        Some(search_caller) => with_search_caller(search_caller, future).await,
+        None => future.await,
+    }
+}
+
+async fn run_query_log_sink(
+    mut ingest_service: IngestServiceClient,
+    mut event_rx: mpsc::Receiver<QueryLogEvent>,
+) {
+    while let Some(event) = event_rx.recv().await {
+        let mut events = vec![event];
+        while events.len() < MAX_BATCH_SIZE {
+            let Ok(event) = event_rx.try_recv() else {
+                break;
+            };
+            events.push(event);
+        }
+        if let Err(error) = ingest_query_log_events(&mut ingest_service, &events).await {
+            error!(error=?error, num_events=events.len(), "Failed to ingest query log events.");
+        }
+    }
+}
+
+async fn ingest_query_log_events(
+    ingest_service: &mut IngestServiceClient,
+    events: &[QueryLogEvent],
+) -> anyhow::Result<()> {
+    let mut doc_batch = DocBatchBuilder::new(QUERY_LOG_INDEX_ID.to_string()).json_writer();
+    for event in events {
+        doc_batch.ingest_doc(event)?;
+    }
+    let ingest_request = IngestRequest {
+        doc_batches: vec![doc_batch.build()],
+    };
+    ingest_service.ingest(ingest_request).await?;
+    Ok(())
+}
+
+fn query_log_index_config() -> String {
+    format!(
+        r#"
+version: 0.4
+
+index_id: {QUERY_LOG_INDEX_ID}
+
+doc_mapping:
+  mode: strict
+  field_mappings:
+    - name: timestamp
+      type: datetime
+      input_formats: [unix_timestamp]
+      fast: true
+      precision: seconds
+    - name: index_id
+      type: text
+      tokenizer: raw
+    - name: query
+      type: text
+    - name: principal
+      type: text
+      tokenizer: raw
+    - name: remote_ip
+      type: ip
+    - name: user_agent
+      type: text
+      tokenizer: raw
+    - name: outcome
+      type: text
+      tokenizer: raw
+    - name: error
+      type: text
+    - name: num_hits
+      type: u64
+    - name: latency_ms
+      type: u64
+      fast: true
+    - name: planning_ms
+      type: u64
+      fast: true
+    - name: leaf_search_ms
+      type: u64
+      fast: true
+    - name: fetch_docs_ms
+      type: u64
+      fast: true
+    - name: num_splits_scanned
+      type: u64
+      fast: true
+    - name: num_splits_pruned
+      type: u64
+      fast: true
+    - name: slow
+      type: bool
+      fast: true
+    - name: search_request
+      type: text
+      indexed: false
+    - name: split_ids
+      type: array<text>
+      tokenizer: raw
+  timestamp_field: timestamp
+
+indexing_settings:
+  commit_timeout_secs: 30
+
+search_settings:
+  default_search_fields: [query, index_id, principal]
+"#
+    )
+}
+
+async fn create_query_log_index(
+    index_service: &IndexService,
+    default_index_root_uri: &Uri,
+) -> anyhow::Result<()> {
+    let index_config = load_index_config_from_user_config(
+        ConfigFormat::Yaml,
+        query_log_index_config().as_bytes(),
+        default_index_root_uri,
+    )?;
+    match index_service.create_index(index_config, false).await {
+        Ok(_)
+        | Err(IndexServiceError::MetastoreError(MetastoreError::IndexAlreadyExists { .. })) => {
+            Ok(())
+        }
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,487 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use quickwit_config::{QuotaConfig, QuotaExceededAction, QuotasConfig};
+use quickwit_ingest_api::IngestRequest;
+use quickwit_metastore::{ListSplitsQuery, Metastore, MetastoreResult, SplitState};
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::error;
+
+use crate::rate_limiter::TokenBucket;
+
+/// Longest delay imposed on a throttled ingest request. The requests that would be delayed longer
+/// are rejected.
+const MAX_THROTTLE_DELAY: Duration = Duration::from_secs(10);
+
+const SECONDS_PER_DAY: u64 = 24 * 3_600;
+
+#[derive(Debug, Error)]
+#[error("Quota exceeded: {0}")]
+pub(crate) struct QuotaExceeded(String);
+
+impl ServiceError for QuotaExceeded {
+    fn status_code(&self) -> ServiceErrorCode {
+        ServiceErrorCode::RateLimited
+    }
+}
+
+/// Usage of a quota shared by the indexes of a tenant, or of the quota of a single index.
+#[derive(Debug, Serialize, utoipa::ToSchema)]
+pub struct QuotaUsage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub index_ids: Vec<String>,
+    /// Bytes ingested through this node since the start of the UTC day.
+    pub ingested_bytes_today: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_ingest_bytes_per_day: Option<u64>,
+    /// Bytes of the published splits, as of the last refresh.
+    pub stored_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_stored_bytes: Option<u64>,
+    pub is_exceeded: bool,
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum QuotaKey {
+    Tenant(String),
+    Index(String),
+}
+
+impl fmt::Display for QuotaKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tenant(tenant_id) => write!(formatter, "tenant `{tenant_id}`"),
+            Self::Index(index_id) => write!(formatter, "index `{index_id}`"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    /// Days elapsed since the UNIX epoch, at which the ingested bytes were last reset.
+    day: u64,
+    ingested_bytes: HashMap<QuotaKey, u64>,
+    stored_bytes: HashMap<QuotaKey, u64>,
+    index_ids: BTreeMap<QuotaKey, BTreeSet<String>>,
+    throttle_buckets: HashMap<QuotaKey, TokenBucket>,
+}
+
+impl QuotaState {
+    fn reset_if_new_day(&mut self, day: u64) {
+        if self.day != day {
+            self.day = day;
+            self.ingested_bytes.clear();
+            self.throttle_buckets.clear();
+        }
+    }
+}
+
+/// Tracks the bytes ingested per day and stored by the indexes subject to a quota, and enforces
+/// the quotas on the ingest requests. The ingested bytes are counted by each node independently.
+pub(crate) struct QuotaTracker {
+    metastore: Arc<dyn Metastore>,
+    quotas_config: QuotasConfig,
+    state: Mutex<QuotaState>,
+}
+
+impl QuotaTracker {
+    pub fn new(metastore: Arc<dyn Metastore>, quotas_config: QuotasConfig) -> Self {
+        Self {
+            metastore,
+            quotas_config,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.quotas_config.is_enabled()
+    }
+
+    /// Returns the first quota matching the index and the key its usage is accounted under.
+    fn find_quota(&self, index_id: &str) -> Option<(&QuotaConfig, QuotaKey)> {
+        let quota_config = self
+            .quotas_config
+            .rules
+            .iter()
+            .find(|quota_config| quota_config.matches(index_id))?;
+        let quota_key = if let Some(tenant_id) = &quota_config.tenant_id {
+            QuotaKey::Tenant(tenant_id.clone())
+        } else {
+            QuotaKey::Index(index_id.to_string())
+        };
+        Some((quota_config, quota_key))
+    }
+
+    /// Recomputes the bytes stored by the indexes subject to a quota from their published splits.
+    pub async fn refresh_stored_bytes(&self) -> MetastoreResult<()> {
+        let mut stored_bytes: HashMap<QuotaKey, u64> = HashMap::new();
+        let mut index_ids: BTreeMap<QuotaKey, BTreeSet<String>> = BTreeMap::new();
+
+        for index_metadata in self.metastore.list_indexes_metadatas().await? {
+            let index_id = index_metadata.index_id();
+            let Some((_, quota_key)) = self.find_quota(index_id) else {
+                continue;
+            };
+            let query =
+                ListSplitsQuery::for_index(index_id).with_split_state(SplitState::Published);
+            let index_stored_bytes: u64 = self
+                .metastore
+                .list_splits(query)
+                .await?
+                .iter()
+                .map(|split| split.split_metadata.footer_offsets.end)
+                .sum();
+            *stored_bytes.entry(quota_key.clone()).or_default() += index_stored_bytes;
+            index_ids
+                .entry(quota_key)
+                .or_default()
+                .insert(index_id.to_string());
+        }
+        let mut state = self.state.lock().unwrap();
+        state.stored_bytes = stored_bytes;
+        state.index_ids = index_ids;
+        Ok(())
+    }
+
+    /// Records `num_bytes` ingested into the index and returns how long the ingestion must be
+    /// delayed, or fails if the quota of the index is exceeded.
+    pub fn acquire(&self, index_id: &str, num_bytes: u64) -> Result<Duration, QuotaExceeded> {
+        self.acquire_at(index_id, num_bytes, current_day(), Instant::now())
+    }
+
+    fn acquire_at(
+        &self,
+        index_id: &str,
+        num_bytes: u64,
+        day: u64,
+        now: Instant,
+    ) -> Result<Duration, QuotaExceeded> {
+        let Some((quota_config, quota_key)) = self.find_quota(index_id) else {
+            return Ok(Duration::ZERO);
+        };
+        let mut state = self.state.lock().unwrap();
+        state.reset_if_new_day(day);
+        state
+            .index_ids
+            .entry(quota_key.clone())
+            .or_default()
+            .insert(index_id.to_string());
+
+        let ingested_bytes = state.ingested_bytes.get(&quota_key).copied().unwrap_or(0);
+        let stored_bytes = state.stored_bytes.get(&quota_key).copied().unwrap_or(0);
+        let delay = if let Some(exceeded_quota) =
+            exceeded_quota(quota_config, ingested_bytes + num_bytes, stored_bytes)
+        {
+            match quota_config.on_exceeded {
+                QuotaExceededAction::Reject => {
+                    return Err(QuotaExceeded(format!(
+                        "{quota_key} exceeded its {exceeded_quota}."
+                    )));
+                }
+                QuotaExceededAction::Throttle => {
+                    let throttled_bytes_per_sec = quota_config
+                        .throttled_bytes_per_sec
+                        .expect("Throttled quotas should have a throttled throughput.")
+                        .get_bytes();
+                    let bucket = state
+                        .throttle_buckets
+                        .entry(quota_key.clone())
+                        .or_insert_with(|| TokenBucket::new(throttled_bytes_per_sec, now));
+                    let wait_time = bucket.wait_time(num_bytes, now);
+
+                    if wait_time > MAX_THROTTLE_DELAY {
+                        return Err(QuotaExceeded(format!(
+                            "{quota_key} exceeded its {exceeded_quota} and is throttled to \
+                             {throttled_bytes_per_sec} bytes per second."
+                        )));
+                    }
+                    bucket.take(num_bytes);
+                    wait_time
+                }
+            }
+        } else {
+            Duration::ZERO
+        };
+        *state.ingested_bytes.entry(quota_key).or_default() += num_bytes;
+        Ok(delay)
+    }
+
+    /// Returns the usage of the quotas, ordered by tenant and then by index.
+    pub fn usage(&self) -> Vec<QuotaUsage> {
+        let mut state = self.state.lock().unwrap();
+        state.reset_if_new_day(current_day());
+
+        let mut usages = Vec::with_capacity(state.index_ids.len());
+        for (quota_key, index_ids) in &state.index_ids {
+            let Some(index_id) = index_ids.iter().next() else {
+                continue;
+            };
+            let Some((quota_config, _)) = self.find_quota(index_id) else {
+                continue;
+            };
+            let ingested_bytes = state.ingested_bytes.get(quota_key).copied().unwrap_or(0);
+            let stored_bytes = state.stored_bytes.get(quota_key).copied().unwrap_or(0);
+            let usage = QuotaUsage {
+                tenant_id: quota_config.tenant_id.clone(),
+                index_ids: index_ids.iter().cloned().collect(),
+                ingested_bytes_today: ingested_bytes,
+                max_ingest_bytes_per_day: quota_config
+                    .max_ingest_bytes_per_day
+                    .map(|max_bytes| max_bytes.get_bytes()),
+                stored_bytes,
+                max_stored_bytes: quota_config
+                    .max_stored_bytes
+                    .map(|max_bytes| max_bytes.get_bytes()),
+                is_exceeded: exceeded_quota(quota_config, ingested_bytes, stored_bytes).is_some(),
+            };
+            usages.push(usage);
+        }
+        usages
+    }
+}
+
+/// Returns a description of the first quota exceeded by the usage, if any.
+fn exceeded_quota(
+    quota_config: &QuotaConfig,
+    ingested_bytes: u64,
+    stored_bytes: u64,
+) -> Option<String> {
+    if let Some(max_ingest_bytes_per_day) = quota_config.max_ingest_bytes_per_day {
+        if ingested_bytes > max_ingest_bytes_per_day.get_bytes() {
+            return Some(format!(
+                "daily ingest quota of {} bytes",
+                max_ingest_bytes_per_day.get_bytes()
+            ));
+        }
+    }
+    if let Some(max_stored_bytes) = quota_config.max_stored_bytes {
+        if stored_bytes >= max_stored_bytes.get_bytes() {
+            return Some(format!(
+                "storage quota of {} bytes",
+                max_stored_bytes.get_bytes()
+            ));
+        }
+    }
+    None
+}
+
+/// Returns the number of days elapsed since the UNIX epoch, in UTC.
+fn current_day() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_secs() / SECONDS_PER_DAY
+}
+
+/// Enforces the quotas of the indexes targeted by an ingest request, delaying the request if one
+/// of them is throttled.
+pub(crate) async fn enforce_ingest_quotas(
+    quota_tracker: &QuotaTracker,
+    ingest_request: &IngestRequest,
+) -> Result<(), QuotaExceeded> {
+    if !quota_tracker.is_enabled() {
+        return Ok(());
+    }
+    let mut delay = Duration::ZERO;
+    for doc_batch in &ingest_request.doc_batches {
+        let num_bytes = doc_batch.num_bytes() as u64;
+        delay = delay.max(quota_tracker.acquire(&doc_batch.index_id, num_bytes)?);
+    }
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    Ok(())
+}
+
+pub(crate) fn spawn_quota_usage_refresh_task(
+    quota_tracker: Arc<QuotaTracker>,
+    refresh_interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(refresh_interval);
+        loop {
+            interval.tick().await;
+            if let Err(error) = quota_tracker.refresh_stored_bytes().await {
+                error!(error=?error, "Failed to refresh the quota usage.");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+    use quickwit_metastore::{IndexMetadata, MockMetastore, Split, SplitMetadata};
+
+    use super::*;
+
+    fn quota_config(index_id_pattern: &str) -> QuotaConfig {
+        QuotaConfig {
+            tenant_id: None,
+            index_id_patterns: vec![index_id_pattern.to_string()],
+            max_ingest_bytes_per_day: None,
+            max_stored_bytes: None,
+            on_exceeded: QuotaExceededAction::Reject,
+            throttled_bytes_per_sec: None,
+        }
+    }
+
+    fn quota_tracker_for_test(rules: Vec<QuotaConfig>) -> QuotaTracker {
+        let quotas_config = QuotasConfig {
+            rules,
+            ..Default::default()
+        };
+        QuotaTracker::new(Arc::new(MockMetastore::new()), quotas_config)
+    }
+
+    #[test]
+    fn test_quota_tracker_rejects_ingest_above_daily_quota() {
+        let tenant_quota = QuotaConfig {
+            tenant_id: Some("tenant-a".to_string()),
+            max_ingest_bytes_per_day: Some(Byte::from_bytes(1_000)),
+            ..quota_config("tenant-a-*")
+        };
+        let index_quota = QuotaConfig {
+            max_ingest_bytes_per_day: Some(Byte::from_bytes(500)),
+            ..quota_config("*")
+        };
+        let quota_tracker = quota_tracker_for_test(vec![tenant_quota, index_quota]);
+        let now = Instant::now();
+
+        quota_tracker
+            .acquire_at("tenant-a-logs", 600, 1, now)
+            .unwrap();
+        // The indexes of a tenant share the tenant quota.
+        let error = quota_tracker
+            .acquire_at("tenant-a-traces", 600, 1, now)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Quota exceeded: tenant `tenant-a` exceeded its daily ingest quota of 1000 bytes."
+        );
+        quota_tracker
+            .acquire_at("tenant-a-traces", 400, 1, now)
+            .unwrap();
+
+        // The other indexes have their own quota.
+        quota_tracker.acquire_at("logs", 500, 1, now).unwrap();
+        quota_tracker.acquire_at("logs", 1, 1, now).unwrap_err();
+        quota_tracker.acquire_at("traces", 500, 1, now).unwrap();
+
+        // The daily usage is reset on the next day.
+        quota_tracker.acquire_at("logs", 500, 2, now).unwrap();
+
+        let usages = quota_tracker.usage();
+        assert_eq!(usages.len(), 3);
+        assert_eq!(usages[0].tenant_id.as_deref(), Some("tenant-a"));
+        assert_eq!(usages[0].index_ids, ["tenant-a-logs", "tenant-a-traces"]);
+        assert_eq!(usages[1].index_ids, ["logs"]);
+        assert_eq!(usages[1].max_ingest_bytes_per_day, Some(500));
+    }
+
+    #[test]
+    fn test_quota_tracker_throttles_ingest_above_quota() {
+        let throttled_quota = QuotaConfig {
+            max_ingest_bytes_per_day: Some(Byte::from_bytes(1_000)),
+            on_exceeded: QuotaExceededAction::Throttle,
+            throttled_bytes_per_sec: Some(Byte::from_bytes(100)),
+            ..quota_config("logs")
+        };
+        let quota_tracker = quota_tracker_for_test(vec![throttled_quota]);
+        let day = current_day();
+        let now = Instant::now();
+
+        let delay = quota_tracker.acquire_at("logs", 1_000, day, now).unwrap();
+        assert_eq!(delay, Duration::ZERO);
+
+        let delay = quota_tracker.acquire_at("logs", 100, day, now).unwrap();
+        assert_eq!(delay, Duration::ZERO);
+        let delay = quota_tracker.acquire_at("logs", 100, day, now).unwrap();
+        assert_eq!(delay, Duration::from_secs(1));
+
+        // Large requests are delayed until the bucket is full, and then leave it in debt.
+        let delay = quota_tracker.acquire_at("logs", 1_000, day, now).unwrap();
+        assert_eq!(delay, Duration::from_secs(2));
+        let error = quota_tracker.acquire_at("logs", 100, day, now).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("is throttled to 100 bytes per second"));
+        assert_eq!(quota_tracker.usage()[0].ingested_bytes_today, 2_200);
+        assert!(quota_tracker.usage()[0].is_exceeded);
+    }
+
+    #[tokio::test]
+    async fn test_quota_tracker_refresh_stored_bytes() {
+        let mut metastore = MockMetastore::new();
+        metastore.expect_list_indexes_metadatas().returning(|| {
+            Ok(vec![
+                IndexMetadata::for_test("logs", "ram:///indexes/logs"),
+                IndexMetadata::for_test("traces", "ram:///indexes/traces"),
+            ])
+        });
+        metastore
+            .expect_list_splits()
+            .returning(|list_splits_query: ListSplitsQuery| {
+                assert_eq!(list_splits_query.index_id, "logs");
+                assert_eq!(list_splits_query.split_states, vec![SplitState::Published]);
+                let split = Split {
+                    split_state: SplitState::Published,
+                    update_timestamp: 0,
+                    publish_timestamp: None,
+                    split_metadata: SplitMetadata {
+                        footer_offsets: 700..800,
+                        ..Default::default()
+                    },
+                };
+                Ok(vec![split.clone(), split])
+            });
+        let storage_quota = QuotaConfig {
+            max_stored_bytes: Some(Byte::from_bytes(1_500)),
+            ..quota_config("logs")
+        };
+        let quotas_config = QuotasConfig {
+            rules: vec![storage_quota],
+            ..Default::default()
+        };
+        let quota_tracker = QuotaTracker::new(Arc::new(metastore), quotas_config);
+        quota_tracker.acquire("logs", 10).unwrap();
+
+        quota_tracker.refresh_stored_bytes().await.unwrap();
+        let error = quota_tracker.acquire("logs", 10).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("index `logs` exceeded its storage quota of 1500 bytes"));
+        // Indexes without a quota are not limited.
+        quota_tracker.acquire("traces", 10).unwrap();
+
+        let usages = quota_tracker.usage();
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].stored_bytes, 1_600);
+        assert_eq!(usages[0].ingested_bytes_today, 10);
+        assert!(usages[0].is_exceeded);
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use warp::{Filter, Rejection};
+
+use super::quota_tracker::{QuotaTracker, QuotaUsage};
+use crate::format::{extract_format_from_qs, make_response};
+use crate::with_arg;
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(get_quota_usage), components(schemas(QuotaUsage)))]
+pub struct QuotaApi;
+
+pub(crate) fn quota_api_handlers(
+    quota_tracker: Arc<QuotaTracker>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("quotas" / "usage")
+        .and(warp::get())
+        .and(with_arg(quota_tracker))
+        .then(get_quota_usage)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Quotas",
+    path = "/quotas/usage",
+    responses(
+        (status = 200, description = "Successfully fetched the usage of the quotas.", body = [QuotaUsage])
+    ),
+)]
+/// Get Quota Usage
+///
+/// Returns the bytes ingested today through the node and the bytes stored by the indexes subject
+/// to a quota, grouped by tenant for the tenant quotas and by index otherwise.
+async fn get_quota_usage(quota_tracker: Arc<QuotaTracker>) -> Result<Vec<QuotaUsage>, Infallible> {
+    Ok(quota_tracker.usage())
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+    use quickwit_config::{QuotaConfig, QuotaExceededAction, QuotasConfig};
+    use quickwit_metastore::MockMetastore;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[tokio::test]
+    async fn test_quota_usage_api() {
+        let quotas_config = QuotasConfig {
+            rules: vec![QuotaConfig {
+                tenant_id: Some("tenant-a".to_string()),
+                index_id_patterns: vec!["tenant-a-*".to_string()],
+                max_ingest_bytes_per_day: Some(Byte::from_bytes(1_000)),
+                max_stored_bytes: None,
+                on_exceeded: QuotaExceededAction::Reject,
+                throttled_bytes_per_sec: None,
+            }],
+            ..Default::default()
+        };
+        let quota_tracker = Arc::new(QuotaTracker::new(
+            Arc::new(MockMetastore::new()),
+            quotas_config,
+        ));
+        quota_tracker.acquire("tenant-a-logs", 100).unwrap();
+
+        let quota_api_handlers = quota_api_handlers(quota_tracker).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/quotas/usage")
+            .reply(&quota_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let usages: JsonValue = serde_json::from_slice(resp.body()).unwrap();
+        let expected_usages = serde_json::json!([{
+            "tenant_id": "tenant-a",
+            "index_ids": ["tenant-a-logs"],
+            "ingested_bytes_today": 100,
+            "max_ingest_bytes_per_day": 1000,
+            "stored_bytes": 0,
+            "is_exceeded": false,
+        }]);
+        assert_eq!(usages, expected_usages);
+    }
+}
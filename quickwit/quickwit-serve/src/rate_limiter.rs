@@ -0,0 +1,471 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use hyper::Method;
+use quickwit_config::{RateLimitConfig, RateLimitsConfig};
+use quickwit_metastore::ApiKeyScope;
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use thiserror::Error;
+use warp::path::FullPath;
+use warp::{Filter, Rejection};
+
+use crate::api_key_api::{parse_api_key_id, rest_request_permission, IndexTarget};
+use crate::with_arg;
+
+/// Number of buckets above which the full buckets, which carry no state, are evicted.
+const MAX_NUM_BUCKETS: usize = 100_000;
+
+/// Address of the peer of the connection a request was received on, inserted in the request
+/// extensions.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RemoteAddr(pub SocketAddr);
+
+#[derive(Debug, Error)]
+pub(crate) enum RateLimitError {
+    #[error("Rate limit exceeded: {message}")]
+    Exceeded {
+        message: String,
+        retry_after: Duration,
+    },
+    #[error("The requests rate limited in bytes per second must carry a `Content-Length` header.")]
+    ContentLengthRequired,
+}
+
+impl RateLimitError {
+    /// Returns the value of the `Retry-After` header, in whole seconds.
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            Self::Exceeded { retry_after, .. } => {
+                Some(retry_after.as_secs_f64().ceil().max(1.0) as u64)
+            }
+            Self::ContentLengthRequired => None,
+        }
+    }
+}
+
+impl ServiceError for RateLimitError {
+    fn status_code(&self) -> ServiceErrorCode {
+        match self {
+            Self::Exceeded { .. } => ServiceErrorCode::RateLimited,
+            Self::ContentLengthRequired => ServiceErrorCode::BadRequest,
+        }
+    }
+}
+
+impl warp::reject::Reject for RateLimitError {}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Endpoint {
+    Ingest,
+    Search,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum RateLimitKey {
+    ApiKey(String),
+    Ip(IpAddr),
+    Index(String),
+}
+
+impl fmt::Display for RateLimitKey {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ApiKey(key_id) => write!(formatter, "API key `{key_id}`"),
+            Self::Ip(ip) => write!(formatter, "IP address `{ip}`"),
+            Self::Index(index_id) => write!(formatter, "index `{index_id}`"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Unit {
+    Requests,
+    Bytes,
+}
+
+type BucketKey = (Endpoint, RateLimitKey, Unit);
+
+/// Token bucket refilled continuously at `rate` tokens per second and holding at most one second
+/// worth of tokens.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u64, now: Instant) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+        self.last_refill = now;
+    }
+
+    /// Returns how long to wait before `amount` tokens can be taken. Amounts larger than the
+    /// bucket capacity only require a full bucket and leave it in debt, so that large requests are
+    /// throttled rather than rejected forever.
+    pub fn wait_time(&mut self, amount: u64, now: Instant) -> Duration {
+        self.refill(now);
+        let required = (amount as f64).min(self.rate);
+        if self.tokens >= required {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((required - self.tokens) / self.rate)
+        }
+    }
+
+    pub fn take(&mut self, amount: u64) {
+        self.tokens -= amount as f64;
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.rate
+    }
+}
+
+/// Enforces the token-bucket rate limits of the REST ingest and search endpoints.
+pub(crate) struct RateLimiter {
+    rate_limits_config: RwLock<RateLimitsConfig>,
+    buckets: Mutex<HashMap<BucketKey, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limits_config: RateLimitsConfig) -> Self {
+        Self {
+            rate_limits_config: RwLock::new(rate_limits_config),
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Replaces the rate limits. The buckets are reset, so the clients start over with full
+    /// buckets.
+    pub fn update_config(&self, rate_limits_config: RateLimitsConfig) {
+        *self.rate_limits_config.write().unwrap() = rate_limits_config;
+        self.buckets.lock().unwrap().clear();
+    }
+
+    /// Takes the tokens a REST request requires from the buckets of its API key, of its client IP
+    /// address, and of its target indexes, or fails if one of them is exhausted. The size of the
+    /// request is its `Content-Length`: requests without one are rejected when a bytes per second
+    /// limit applies to them, since their size is only known once their body has been read.
+    pub fn check_rest_request(
+        &self,
+        method: &Method,
+        path: &str,
+        authorization_opt: Option<&str>,
+        remote_ip_opt: Option<IpAddr>,
+        num_bytes_opt: Option<u64>,
+    ) -> Result<(), RateLimitError> {
+        let rate_limits_config = self.rate_limits_config.read().unwrap();
+        if !rate_limits_config.is_enabled() {
+            return Ok(());
+        }
+        let Some((scope, index_target)) = rest_request_permission(method, path) else {
+            return Ok(());
+        };
+        let (endpoint, endpoint_rate_limits) = match scope {
+            ApiKeyScope::Ingest => (Endpoint::Ingest, &rate_limits_config.ingest),
+            ApiKeyScope::Search => (Endpoint::Search, &rate_limits_config.search),
+            ApiKeyScope::Admin => return Ok(()),
+        };
+        let mut rate_limits: Vec<(RateLimitKey, &RateLimitConfig)> = Vec::new();
+        if let Some(rate_limit) = &endpoint_rate_limits.per_api_key {
+            if let Some(key_id) = authorization_opt.and_then(parse_api_key_id) {
+                rate_limits.push((RateLimitKey::ApiKey(key_id.to_string()), rate_limit));
+            }
+        }
+        if let Some(rate_limit) = &endpoint_rate_limits.per_ip {
+            if let Some(remote_ip) = remote_ip_opt {
+                rate_limits.push((RateLimitKey::Ip(remote_ip), rate_limit));
+            }
+        }
+        if let Some(rate_limit) = &endpoint_rate_limits.per_index {
+            if let IndexTarget::Indexes(index_ids) = index_target {
+                for index_id in index_ids.split(',') {
+                    rate_limits.push((RateLimitKey::Index(index_id.to_string()), rate_limit));
+                }
+            }
+        }
+        self.acquire(endpoint, rate_limits, num_bytes_opt, Instant::now())
+    }
+
+    fn acquire(
+        &self,
+        endpoint: Endpoint,
+        rate_limits: Vec<(RateLimitKey, &RateLimitConfig)>,
+        num_bytes_opt: Option<u64>,
+        now: Instant,
+    ) -> Result<(), RateLimitError> {
+        let mut acquisitions: Vec<(BucketKey, u64, u64)> = Vec::new();
+        for (rate_limit_key, rate_limit) in rate_limits {
+            if let Some(requests_per_sec) = rate_limit.requests_per_sec {
+                let bucket_key = (endpoint, rate_limit_key.clone(), Unit::Requests);
+                acquisitions.push((bucket_key, requests_per_sec.get(), 1));
+            }
+            if let Some(bytes_per_sec) = rate_limit.bytes_per_sec {
+                let Some(num_bytes) = num_bytes_opt else {
+                    return Err(RateLimitError::ContentLengthRequired);
+                };
+                let bucket_key = (endpoint, rate_limit_key, Unit::Bytes);
+                acquisitions.push((bucket_key, bytes_per_sec.get_bytes(), num_bytes));
+            }
+        }
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > MAX_NUM_BUCKETS {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+        // All the buckets are checked before taking any token, so that rejected requests do not
+        // consume the budget of the other buckets.
+        for (bucket_key, rate, amount) in &acquisitions {
+            let bucket = buckets
+                .entry(bucket_key.clone())
+                .or_insert_with(|| TokenBucket::new(*rate, now));
+            let wait_time = bucket.wait_time(*amount, now);
+
+            if !wait_time.is_zero() {
+                let (endpoint, rate_limit_key, unit) = bucket_key;
+                let unit_str = match unit {
+                    Unit::Requests => "requests",
+                    Unit::Bytes => "bytes",
+                };
+                let endpoint_str = match endpoint {
+                    Endpoint::Ingest => "ingest",
+                    Endpoint::Search => "search",
+                };
+                return Err(RateLimitError::Exceeded {
+                    message: format!(
+                        "{rate_limit_key} exceeded its {endpoint_str} limit of {rate} {unit_str} \
+                         per second."
+                    ),
+                    retry_after: wait_time,
+                });
+            }
+        }
+        for (bucket_key, _, amount) in &acquisitions {
+            buckets
+                .get_mut(bucket_key)
+                .expect("The bucket should have been created above.")
+                .take(*amount);
+        }
+        Ok(())
+    }
+}
+
+/// Rejects the ingest and search requests exceeding the configured rate limits with a `429 Too
+/// Many Requests` status code.
+pub(crate) fn rest_rate_limit_filter(
+    rate_limiter: Arc<RateLimiter>,
+) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::method()
+        .and(warp::path::full())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<u64>("content-length"))
+        .and(warp::ext::optional::<RemoteAddr>())
+        .and(with_arg(rate_limiter))
+        .and_then(
+            |method: Method,
+             full_path: FullPath,
+             authorization_opt: Option<String>,
+             content_length_opt: Option<u64>,
+             remote_addr_opt: Option<RemoteAddr>,
+             rate_limiter: Arc<RateLimiter>| async move {
+                rate_limiter
+                    .check_rest_request(
+                        &method,
+                        full_path.as_str(),
+                        authorization_opt.as_deref(),
+                        remote_addr_opt.map(|RemoteAddr(remote_addr)| remote_addr.ip()),
+                        content_length_opt,
+                    )
+                    .map_err(warp::reject::custom)
+            },
+        )
+        .untuple_one()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use byte_unit::Byte;
+    use hyper::header::RETRY_AFTER;
+    use quickwit_config::EndpointRateLimitsConfig;
+
+    use super::*;
+    use crate::recover_fn;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(10, now);
+        assert!(bucket.is_full(now));
+        assert_eq!(bucket.wait_time(10, now), Duration::ZERO);
+        bucket.take(10);
+        assert_eq!(bucket.wait_time(1, now), Duration::from_millis(100));
+        assert_eq!(
+            bucket.wait_time(1, now + Duration::from_millis(100)),
+            Duration::ZERO
+        );
+        // Amounts exceeding the capacity only require a full bucket.
+        let later = now + Duration::from_secs(5);
+        assert!(bucket.is_full(later));
+        assert_eq!(bucket.wait_time(25, later), Duration::ZERO);
+        bucket.take(25);
+        assert_eq!(bucket.wait_time(1, later), Duration::from_millis(1600));
+    }
+
+    #[test]
+    fn test_rate_limiter_check_rest_request() {
+        let rate_limits_config = RateLimitsConfig {
+            ingest: EndpointRateLimitsConfig {
+                per_ip: Some(RateLimitConfig {
+                    requests_per_sec: None,
+                    bytes_per_sec: Some(Byte::from_bytes(1_000)),
+                }),
+                ..Default::default()
+            },
+            search: EndpointRateLimitsConfig {
+                per_api_key: Some(RateLimitConfig {
+                    requests_per_sec: NonZeroU64::new(2),
+                    bytes_per_sec: None,
+                }),
+                per_index: Some(RateLimitConfig {
+                    requests_per_sec: NonZeroU64::new(3),
+                    bytes_per_sec: None,
+                }),
+                ..Default::default()
+            },
+        };
+        let rate_limiter = RateLimiter::new(rate_limits_config);
+        let remote_ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let search = |path: &str, authorization: &str| {
+            rate_limiter.check_rest_request(&Method::GET, path, Some(authorization), None, None)
+        };
+        search("/api/v1/logs/search", "Bearer qw_key1_secret").unwrap();
+        search("/api/v1/logs/search", "Bearer qw_key1_secret").unwrap();
+        let error = search("/api/v1/logs/search", "Bearer qw_key1_secret").unwrap_err();
+        assert!(error.to_string().contains("API key `key1`"));
+        assert_eq!(error.retry_after_secs(), Some(1));
+
+        search("/api/v1/logs/search", "Bearer qw_key2_secret").unwrap();
+        let error = search("/api/v1/logs/search", "Bearer qw_key2_secret").unwrap_err();
+        assert!(error.to_string().contains("index `logs`"));
+        search("/api/v1/traces/search", "Bearer qw_key2_secret").unwrap();
+
+        rate_limiter
+            .check_rest_request(
+                &Method::POST,
+                "/api/v1/logs/ingest",
+                None,
+                Some(remote_ip),
+                Some(1_000),
+            )
+            .unwrap();
+        let error = rate_limiter
+            .check_rest_request(
+                &Method::POST,
+                "/api/v1/logs/ingest",
+                None,
+                Some(remote_ip),
+                Some(1),
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("IP address `10.0.0.1`"));
+        rate_limiter
+            .check_rest_request(
+                &Method::POST,
+                "/api/v1/logs/ingest",
+                None,
+                Some("10.0.0.2".parse().unwrap()),
+                Some(1),
+            )
+            .unwrap();
+        // Requests without a `Content-Length` header cannot be metered in bytes.
+        let error = rate_limiter
+            .check_rest_request(
+                &Method::POST,
+                "/api/v1/logs/ingest",
+                None,
+                Some("10.0.0.3".parse().unwrap()),
+                None,
+            )
+            .unwrap_err();
+        assert!(matches!(error, RateLimitError::ContentLengthRequired));
+        assert_eq!(error.retry_after_secs(), None);
+
+        // Management requests are not rate limited.
+        for _ in 0..10 {
+            rate_limiter
+                .check_rest_request(&Method::GET, "/api/v1/indexes", None, Some(remote_ip), None)
+                .unwrap();
+        }
+
+        // Updating the rate limits applies them immediately.
+        rate_limiter.update_config(RateLimitsConfig::default());
+        for _ in 0..10 {
+            search("/api/v1/logs/search", "Bearer qw_key1_secret").unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rest_rate_limit_filter() {
+        let rate_limits_config = RateLimitsConfig {
+            search: EndpointRateLimitsConfig {
+                per_index: Some(RateLimitConfig {
+                    requests_per_sec: NonZeroU64::new(1),
+                    bytes_per_sec: None,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rate_limiter = Arc::new(RateLimiter::new(rate_limits_config));
+        let routes = rest_rate_limit_filter(rate_limiter)
+            .and(warp::path!("api" / "v1" / String / "search").map(|_| "search"))
+            .recover(recover_fn);
+
+        let resp = warp::test::request()
+            .path("/api/v1/logs/search")
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/api/v1/logs/search")
+            .reply(&routes)
+            .await;
+        assert_eq!(resp.status(), 429);
+        assert_eq!(resp.headers()[RETRY_AFTER], "1");
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use hyper::header::HeaderMap;
+use hyper::{Body, Request};
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use tracing::{info_span, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Extracts the trace context propagated in the headers of an HTTP request, such as the W3C
+/// `traceparent` and `tracestate` headers.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Returns the span of a REST request. When the request carries a trace context, the span is a
+/// child of the span of the caller, so that the request can be followed from the client through
+/// all the nodes it reaches.
+pub(crate) fn rest_request_span(request: &Request<Body>) -> Span {
+    let span = info_span!(
+        "rest_request",
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+    let parent_cx = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    span.set_parent(parent_cx);
+    span
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::sdk::propagation::TraceContextPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    use super::*;
+
+    #[test]
+    fn test_header_extractor() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+        );
+        let extractor = HeaderExtractor(&headers);
+        assert_eq!(extractor.keys(), ["traceparent"]);
+
+        let parent_cx = TraceContextPropagator::new().extract(&extractor);
+        let span_context = parent_cx.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "0af7651916cd43dd8448eb211c80319c"
+        );
+        assert_eq!(span_context.span_id().to_string(), "b7ad6b7169203331");
+
+        let parent_cx = TraceContextPropagator::new().extract(&HeaderExtractor(&HeaderMap::new()));
+        assert!(!parent_cx.span().span_context().is_valid());
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use hyper::header::{
+    HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+    X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use quickwit_config::{CorsConfig, SecurityHeadersConfig};
+use warp::reply::Response;
+
+/// Builds the CORS policy wrapped around the REST routes. Preflight requests are answered
+/// directly by the policy, before authentication, and requests from origins that are not allowed
+/// are rejected with a `CorsForbidden` rejection.
+pub(crate) fn cors_filter(cors_config: &CorsConfig) -> warp::cors::Builder {
+    let cors = warp::cors()
+        .allow_methods(cors_config.allowed_methods.iter().map(String::as_str))
+        .allow_headers(cors_config.allowed_headers.iter().map(String::as_str))
+        .allow_credentials(cors_config.allow_credentials);
+    let cors = if cors_config.allows_any_origin() {
+        cors.allow_any_origin()
+    } else {
+        cors.allow_origins(cors_config.allowed_origins.iter().map(String::as_str))
+    };
+    if let Some(max_age_secs) = cors_config.max_age_secs {
+        cors.max_age(Duration::from_secs(max_age_secs))
+    } else {
+        cors
+    }
+}
+
+/// Security headers added to every REST response that does not set them already.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SecurityHeaders {
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl SecurityHeaders {
+    pub fn from_config(security_headers_config: &SecurityHeadersConfig) -> Self {
+        if !security_headers_config.enabled {
+            return Self::default();
+        }
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+        ];
+        if let Some(hsts_max_age_secs) = security_headers_config.hsts_max_age_secs {
+            let hsts_value = format!("max-age={hsts_max_age_secs}");
+            headers.push((
+                STRICT_TRANSPORT_SECURITY,
+                HeaderValue::from_str(&hsts_value).expect("HSTS value should be valid."),
+            ));
+        }
+        // The policy is validated when the node config is loaded.
+        if let Some(content_security_policy) = &security_headers_config.content_security_policy {
+            if let Ok(header_value) = HeaderValue::from_str(content_security_policy) {
+                headers.push((CONTENT_SECURITY_POLICY, header_value));
+            }
+        }
+        Self { headers }
+    }
+
+    pub fn apply(&self, mut response: Response) -> Response {
+        let response_headers = response.headers_mut();
+        for (header_name, header_value) in &self.headers {
+            if !response_headers.contains_key(header_name) {
+                response_headers.insert(header_name.clone(), header_value.clone());
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+        ORIGIN,
+    };
+    use hyper::StatusCode;
+    use warp::{Filter, Reply};
+
+    use super::*;
+
+    fn cors_config(allowed_origins: &[&str]) -> CorsConfig {
+        serde_json::from_value(serde_json::json!({
+            "allowed_origins": allowed_origins,
+            "allow_credentials": !allowed_origins.contains(&"*"),
+            "max_age_secs": 600,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cors_filter() {
+        let routes = warp::path!("api" / "v1" / "indexes")
+            .map(|| "indexes")
+            .with(cors_filter(&cors_config(&["https://app.example.com"])));
+
+        let preflight_response = warp::test::request()
+            .method("OPTIONS")
+            .path("/api/v1/indexes")
+            .header(ORIGIN, "https://app.example.com")
+            .header("access-control-request-method", "POST")
+            .header("access-control-request-headers", "authorization")
+            .reply(&routes)
+            .await;
+        assert_eq!(preflight_response.status(), StatusCode::OK);
+        let headers = preflight_response.headers();
+        assert_eq!(
+            headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://app.example.com"
+        );
+        assert_eq!(
+            headers.get(ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(),
+            "true"
+        );
+        assert_eq!(headers.get(ACCESS_CONTROL_MAX_AGE).unwrap(), "600");
+
+        let response = warp::test::request()
+            .path("/api/v1/indexes")
+            .header(ORIGIN, "https://app.example.com")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://app.example.com"
+        );
+
+        let forbidden_preflight_response = warp::test::request()
+            .method("OPTIONS")
+            .path("/api/v1/indexes")
+            .header(ORIGIN, "https://evil.example.com")
+            .header("access-control-request-method", "POST")
+            .reply(&routes)
+            .await;
+        assert_eq!(forbidden_preflight_response.status(), StatusCode::FORBIDDEN);
+
+        let forbidden_method_response = warp::test::request()
+            .method("OPTIONS")
+            .path("/api/v1/indexes")
+            .header(ORIGIN, "https://app.example.com")
+            .header("access-control-request-method", "PATCH")
+            .reply(&routes)
+            .await;
+        assert_eq!(forbidden_method_response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_cors_filter_any_origin() {
+        let routes = warp::any()
+            .map(|| "indexes")
+            .with(cors_filter(&cors_config(&["*"])));
+        let response = warp::test::request()
+            .path("/api/v1/indexes")
+            .header(ORIGIN, "https://app.example.com")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "*"
+        );
+    }
+
+    #[test]
+    fn test_security_headers() {
+        let disabled_security_headers = SecurityHeaders::from_config(&Default::default());
+        let response = disabled_security_headers.apply("hello".into_response());
+        assert!(response.headers().get(X_CONTENT_TYPE_OPTIONS).is_none());
+
+        let security_headers_config = SecurityHeadersConfig {
+            enabled: true,
+            hsts_max_age_secs: Some(31_536_000),
+            content_security_policy: Some("default-src 'self'".to_string()),
+        };
+        let security_headers = SecurityHeaders::from_config(&security_headers_config);
+        let response = security_headers.apply(
+            warp::reply::with_header("hello", X_FRAME_OPTIONS, "SAMEORIGIN").into_response(),
+        );
+        let headers = response.headers();
+        assert_eq!(headers.get(X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert_eq!(headers.get(X_FRAME_OPTIONS).unwrap(), "SAMEORIGIN");
+        assert_eq!(headers.get(REFERRER_POLICY).unwrap(), "no-referrer");
+        assert_eq!(
+            headers.get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000"
+        );
+        assert_eq!(
+            headers.get(CONTENT_SECURITY_POLICY).unwrap(),
+            "default-src 'self'"
+        );
+    }
+}
@@ -17,32 +17,60 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use hyper::header::CONTENT_TYPE;
-use hyper::{http, Response, StatusCode, Uri};
+use hyper::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+use hyper::server::conn::AddrStream;
+use hyper::service::make_service_fn;
+use hyper::{http, Body, Request, Response, StatusCode, Uri};
 use quickwit_common::metrics;
-use quickwit_proto::ServiceErrorCode;
-use tower::make::Shared;
+use quickwit_proto::{ServiceError, ServiceErrorCode};
+use quickwit_search::with_caller_field_masks;
 use tower::ServiceBuilder;
 use tower_http::compression::predicate::{DefaultPredicate, Predicate, SizeAbove};
 use tower_http::compression::CompressionLayer;
-use tracing::{error, info};
+use tracing::{error, info, Instrument};
 use utoipa_swagger_ui::Config;
 use warp::path::{FullPath, Tail};
 use warp::{redirect, Filter, Rejection, Reply};
 
+use crate::alert_api::alert_api_handlers;
+use crate::api_key_api::{api_key_api_handlers, rest_auth_filter, AuthError};
+use crate::audit_log::audit_request;
+use crate::autoscaling_api::autoscaling_api_handlers;
 use crate::cluster_api::cluster_handler;
+use crate::config_reload_api::config_reload_api_handlers;
 use crate::delete_task_api::delete_task_api_handlers;
 use crate::elastic_search_api::elastic_api_handlers;
 use crate::format::ApiError;
 use crate::health_check_api::health_check_handlers;
-use crate::index_api::index_management_handlers;
-use crate::indexing_api::indexing_get_handler;
+use crate::index_alias_api::index_alias_api_handlers;
+use crate::index_api::{index_management_handlers, index_stats_handler};
+use crate::index_template_api::index_template_api_handlers;
+use crate::indexing_api::{
+    control_pipelines_handler, indexing_get_handler, list_pipelines_handler,
+    throttle_merges_handler,
+};
 use crate::ingest_api::ingest_api_handlers;
+use crate::json_body::InvalidJsonBody;
+use crate::loki_api::loki_api_handlers;
+use crate::node_drain_api::node_drain_api_handlers;
 use crate::node_info_handler::node_info_handler;
-use crate::search_api::{search_get_handler, search_post_handler, search_stream_handler};
+use crate::prometheus_api::prometheus_api_handlers;
+use crate::query_log::{attribute_searches, search_caller};
+use crate::quota_api::quota_api_handlers;
+use crate::rate_limiter::{rest_rate_limit_filter, RateLimitError, RemoteAddr};
+use crate::request_tracing::rest_request_span;
+use crate::response_headers::{cors_filter, SecurityHeaders};
+use crate::search_api::{
+    count_handler, create_search_view_handler, delete_async_search_handler,
+    delete_search_view_handler, explain_handler, get_async_search_handler, list_terms_handler,
+    live_tail_handler, scroll_get_handler, scroll_post_handler, search_get_handler,
+    search_hits_stream_get_handler, search_hits_stream_post_handler, search_post_handler,
+    search_stream_handler, sql_handler, submit_async_search_handler, warmup_handler,
+};
 use crate::ui_handler::ui_handler;
 use crate::{with_arg, BodyFormat, QuickwitServices};
 
@@ -61,7 +89,9 @@ pub(crate) async fn start_rest_server(
     });
 
     // Docs routes
-    let api_doc = warp::path("openapi.json")
+    let api_doc = warp::path!("openapi.json")
+        .or(warp::path!("api" / "v1" / "openapi.json"))
+        .unify()
         .and(warp::get())
         .map(|| warp::reply::json(&crate::openapi::build_docs()));
 
@@ -98,40 +128,183 @@ pub(crate) async fn start_rest_server(
         .or(indexing_get_handler(
             quickwit_services.indexing_service.clone(),
         ))
+        .or(list_pipelines_handler(
+            quickwit_services.indexing_service.clone(),
+        ))
+        .or(control_pipelines_handler(
+            quickwit_services.indexing_service.clone(),
+        ))
+        .or(throttle_merges_handler(
+            quickwit_services.indexing_service.clone(),
+        ))
         .or(search_get_handler(quickwit_services.search_service.clone()))
         .or(search_post_handler(
             quickwit_services.search_service.clone(),
         ))
+        .or(count_handler(quickwit_services.search_service.clone()))
         .or(search_stream_handler(
             quickwit_services.search_service.clone(),
         ))
-        .or(ingest_api_handlers(ingest_service.clone()))
+        .or(search_hits_stream_get_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(search_hits_stream_post_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(scroll_get_handler(quickwit_services.search_service.clone()))
+        .or(scroll_post_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(submit_async_search_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(get_async_search_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(delete_async_search_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(create_search_view_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(delete_search_view_handler(
+            quickwit_services.search_service.clone(),
+        ))
+        .or(warmup_handler(quickwit_services.search_service.clone()))
+        .or(explain_handler(quickwit_services.search_service.clone()))
+        .or(list_terms_handler(quickwit_services.search_service.clone()))
+        .or(sql_handler(quickwit_services.search_service.clone()))
+        .or(live_tail_handler(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore.clone(),
+            quickwit_services.split_publish_notifier.clone(),
+        ))
+        .or(ingest_api_handlers(
+            ingest_service.clone(),
+            quickwit_services.index_service.clone(),
+            quickwit_services.config.clone(),
+            quickwit_services.quota_tracker.clone(),
+            quickwit_services.ingest_replicator.clone(),
+        ))
         .or(index_management_handlers(
             quickwit_services.index_service.clone(),
             quickwit_services.config.clone(),
         ))
+        .or(index_stats_handler(
+            quickwit_services.metastore.clone(),
+            quickwit_services.indexing_service.clone(),
+        ))
+        .or(index_template_api_handlers(
+            quickwit_services.metastore.clone(),
+        ))
+        .or(index_alias_api_handlers(
+            quickwit_services.metastore.clone(),
+            quickwit_services.config.clone(),
+        ))
+        .or(alert_api_handlers(quickwit_services.metastore.clone()))
         .or(delete_task_api_handlers(
             quickwit_services.metastore.clone(),
+            quickwit_services.search_service.clone(),
+        ))
+        .or(elastic_api_handlers(
+            quickwit_services.cluster.clone(),
+            quickwit_services.metastore.clone(),
+        ))
+        .or(loki_api_handlers(
+            quickwit_services.search_service.clone(),
+            quickwit_services.metastore.clone(),
+        ))
+        .or(prometheus_api_handlers(
+            ingest_service.clone(),
+            quickwit_services.config.clone(),
+            quickwit_services.quota_tracker.clone(),
+        ))
+        .or(api_key_api_handlers(
+            quickwit_services.api_key_authenticator.clone(),
         ))
-        .or(elastic_api_handlers());
+        .or(quota_api_handlers(quickwit_services.quota_tracker.clone()))
+        .or(node_drain_api_handlers(
+            quickwit_services.node_drainer.clone(),
+        ))
+        .or(autoscaling_api_handlers(
+            quickwit_services.autoscaling_signals_sampler.clone(),
+        ))
+        .or(config_reload_api_handlers(
+            quickwit_services.config_reloader.clone(),
+        ));
 
     let api_v1_root_route = api_v1_root_url.and(api_v1_routes);
     let redirect_root_to_ui_route = warp::path::end()
         .and(warp::get())
         .map(|| redirect(http::Uri::from_static("/ui/search")));
 
-    // Combine all the routes together.
-    let rest_routes = api_v1_root_route
-        .or(api_doc)
-        .or(swagger_ui)
-        .or(redirect_root_to_ui_route)
-        .or(ui_handler())
-        .or(health_check_routes)
-        .or(metrics_routes)
+    // Combine all the routes together. Requests are authorized and rate limited before being
+    // routed.
+    let rest_routes = rest_auth_filter(quickwit_services.api_key_authenticator.clone())
+        .and(rest_rate_limit_filter(
+            quickwit_services.rate_limiter.clone(),
+        ))
+        .and(
+            api_v1_root_route
+                .or(api_doc)
+                .or(swagger_ui)
+                .or(redirect_root_to_ui_route)
+                .or(ui_handler())
+                .or(health_check_routes)
+                .or(metrics_routes),
+        )
         .with(request_counter)
-        .recover(recover_fn);
+        .map(Reply::into_response);
+
+    // The CORS policy wraps the whole API so that preflight requests are answered before being
+    // authorized.
+    let rest_routes = if let Some(cors_config) = &quickwit_services.config.rest_config.cors {
+        rest_routes
+            .with(cors_filter(cors_config))
+            .map(Reply::into_response)
+            .boxed()
+    } else {
+        rest_routes.boxed()
+    };
+    let security_headers =
+        SecurityHeaders::from_config(&quickwit_services.config.rest_config.security_headers);
+    let rest_routes = rest_routes
+        .recover(recover_fn)
+        .unify()
+        .map(move |response| security_headers.apply(response));
 
     let warp_service = warp::service(rest_routes);
+    let audit_logger_opt = quickwit_services.audit_logger_opt.clone();
+    // The caller of the searches is only identified when they are recorded in the query log.
+    let query_log_authenticator_opt = quickwit_services
+        .config
+        .query_log_config
+        .as_ref()
+        .map(|_| quickwit_services.api_key_authenticator.clone());
+    // The fields masked by the roles of the caller are applied to the documents returned by the
+    // searches of the request.
+    let field_masks_authenticator = quickwit_services.api_key_authenticator.clone();
+    let audited_service = tower::service_fn(move |request: Request<Body>| {
+        // The request is traced as a child of the span of the caller, if it propagates one.
+        let span = rest_request_span(&request);
+        let search_caller_opt = query_log_authenticator_opt
+            .as_ref()
+            .map(|authenticator| search_caller(authenticator, &request));
+        let field_masks = field_masks_authenticator.field_masks(
+            request
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|authorization| authorization.to_str().ok()),
+        );
+        with_caller_field_masks(
+            field_masks,
+            attribute_searches(
+                search_caller_opt,
+                audit_request(audit_logger_opt.clone(), warp_service.clone(), request),
+            ),
+        )
+        .instrument(span)
+    });
     let compression_predicate =
         DefaultPredicate::new().and(SizeAbove::new(MINIMUM_RESPONSE_COMPRESSION_SIZE));
 
@@ -141,12 +314,24 @@ pub(crate) async fn start_rest_server(
                 .gzip(true)
                 .compress_when(compression_predicate),
         )
-        .service(warp_service);
+        .service(audited_service);
 
     info!("Searcher ready to accept requests at http://{rest_listen_addr}/");
 
+    // The address of the client is made available to the rate limiter through the request
+    // extensions.
+    let make_service = make_service_fn(move |conn: &AddrStream| {
+        let remote_addr = RemoteAddr(conn.remote_addr());
+        let service = ServiceBuilder::new()
+            .map_request(move |mut request: Request<Body>| {
+                request.extensions_mut().insert(remote_addr);
+                request
+            })
+            .service(service.clone());
+        async move { Ok::<_, Infallible>(service) }
+    });
     hyper::Server::bind(&rest_listen_addr)
-        .serve(Shared::new(service))
+        .serve(make_service)
         .await?;
     Ok(())
 }
@@ -193,13 +378,39 @@ async fn swagger_ui_handler(
 // our own logic and return a proper reply.
 // More on this here: https://github.com/seanmonstar/warp/issues/388.
 // We may use this work on the PR is merged: https://github.com/seanmonstar/warp/pull/909.
-pub async fn recover_fn(rejection: Rejection) -> Result<impl Reply, Rejection> {
+pub async fn recover_fn(rejection: Rejection) -> Result<Response<Body>, Rejection> {
+    let retry_after_secs_opt = rejection
+        .find::<RateLimitError>()
+        .and_then(RateLimitError::retry_after_secs);
     let err = get_status_with_error(rejection);
-    Ok(BodyFormat::PrettyJson.make_reply_for_err(err))
+    let mut response = BodyFormat::PrettyJson
+        .make_reply_for_err(err)
+        .into_response();
+    if let Some(retry_after_secs) = retry_after_secs_opt {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+    }
+    Ok(response)
 }
 
 fn get_status_with_error(rejection: Rejection) -> ApiError {
-    if let Some(error) = rejection.find::<crate::index_api::UnsupportedContentType>() {
+    if let Some(error) = rejection.find::<AuthError>() {
+        ApiError {
+            code: error.status_code(),
+            message: error.to_string(),
+        }
+    } else if let Some(error) = rejection.find::<RateLimitError>() {
+        ApiError {
+            code: error.status_code(),
+            message: error.to_string(),
+        }
+    } else if let Some(error) = rejection.find::<warp::cors::CorsForbidden>() {
+        ApiError {
+            code: ServiceErrorCode::Forbidden,
+            message: error.to_string(),
+        }
+    } else if let Some(error) = rejection.find::<crate::index_api::UnsupportedContentType>() {
         ApiError {
             code: ServiceErrorCode::UnsupportedMediaType,
             message: error.to_string(),
@@ -214,6 +425,11 @@ fn get_status_with_error(rejection: Rejection) -> ApiError {
             code: ServiceErrorCode::BadRequest,
             message: error.to_string(),
         }
+    } else if let Some(error) = rejection.find::<InvalidJsonBody>() {
+        ApiError {
+            code: ServiceErrorCode::BadRequest,
+            message: error.to_string(),
+        }
     } else if let Some(error) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
         // Happens when the request body could not be deserialized correctly.
         ApiError {
@@ -250,6 +466,17 @@ fn get_status_with_error(rejection: Rejection) -> ApiError {
             code: ServiceErrorCode::MethodNotAllowed,
             message: error.to_string(),
         }
+    } else if let Some(error) = rejection.find::<crate::ingest_api::ContentEncodingError>() {
+        let code = match error {
+            crate::ingest_api::ContentEncodingError::Unsupported(_) => {
+                ServiceErrorCode::UnsupportedMediaType
+            }
+            _ => ServiceErrorCode::BadRequest,
+        };
+        ApiError {
+            code,
+            message: error.to_string(),
+        }
     } else if let Some(error) = rejection.find::<warp::reject::PayloadTooLarge>() {
         ApiError {
             code: ServiceErrorCode::BadRequest,
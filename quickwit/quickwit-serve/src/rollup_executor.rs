@@ -0,0 +1,672 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use quickwit_config::{IndexConfig, RollupConfig, RollupFunction};
+use quickwit_ingest_api::{DocBatchBuilder, IngestRequest, IngestService, IngestServiceClient};
+use quickwit_metastore::{ListSplitsQuery, Metastore, SplitState};
+use quickwit_proto::SearchRequest;
+use quickwit_search::SearchService;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use tracing::{debug, info, warn};
+
+/// ID of the lease held by the searcher maintaining the rollup indexes of the cluster.
+const ROLLUP_EXECUTOR_LEASE_ID: &str = "rollup-executor";
+
+const ROLLUP_EXECUTOR_LEASE_DURATION: Duration = Duration::from_secs(30);
+
+const ROLLUP_TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of intervals rolled up per index and per tick, so that catching up with the
+/// backlog of a new rollup index does not starve the other ones.
+const MAX_INTERVALS_PER_TICK: usize = 60;
+
+/// Maintains the rollup indexes of the cluster: every closed interval of the source index of a
+/// rollup index is aggregated with a search and the resulting documents are ingested into the
+/// rollup index. Every searcher runs an executor, but only the one holding the executor lease
+/// rolls up the indexes.
+///
+/// The start of the next interval to roll up is kept in memory and recovered from the time range
+/// of the published splits of the rollup index when the lease changes hands. The last interval
+/// is then rolled up again, and the documents of the intervals ingested but not yet published are
+/// dropped by the ingest API thanks to their idempotency key.
+pub(crate) struct RollupExecutor {
+    node_id: String,
+    metastore: Arc<dyn Metastore>,
+    search_service: Arc<dyn SearchService>,
+    ingest_service: IngestServiceClient,
+    lease_deadline_opt: Option<Instant>,
+    /// Start timestamp of the next interval to roll up, per rollup index.
+    next_interval_starts: HashMap<String, i64>,
+}
+
+impl RollupExecutor {
+    pub fn new(
+        node_id: String,
+        metastore: Arc<dyn Metastore>,
+        search_service: Arc<dyn SearchService>,
+        ingest_service: IngestServiceClient,
+    ) -> Self {
+        Self {
+            node_id,
+            metastore,
+            search_service,
+            ingest_service,
+            lease_deadline_opt: None,
+            next_interval_starts: HashMap::new(),
+        }
+    }
+
+    fn holds_lease(&self) -> bool {
+        self.lease_deadline_opt
+            .map(|lease_deadline| Instant::now() < lease_deadline)
+            .unwrap_or(false)
+    }
+
+    async fn renew_lease(&mut self) {
+        let held_lease = self.holds_lease();
+        let request_instant = Instant::now();
+
+        match self
+            .metastore
+            .acquire_lease(
+                ROLLUP_EXECUTOR_LEASE_ID,
+                &self.node_id,
+                ROLLUP_EXECUTOR_LEASE_DURATION.as_secs(),
+            )
+            .await
+        {
+            Ok(lease) if lease.is_held_by(&self.node_id) => {
+                self.lease_deadline_opt = Some(
+                    request_instant + ROLLUP_EXECUTOR_LEASE_DURATION - Duration::from_secs(1),
+                );
+            }
+            Ok(lease) => {
+                debug!(
+                    holder_id=%lease.holder_id,
+                    "Rollup executor lease is held by another node."
+                );
+                self.lease_deadline_opt = None;
+            }
+            Err(error) => {
+                warn!(error=?error, "Failed to renew the rollup executor lease.");
+            }
+        }
+        let holds_lease = self.holds_lease();
+
+        if holds_lease && !held_lease {
+            info!(node_id=%self.node_id, "Node started maintaining the rollup indexes.");
+        } else if !holds_lease && held_lease {
+            info!(node_id=%self.node_id, "Node stopped maintaining the rollup indexes.");
+            // Another node may have rolled up intervals in the meantime.
+            self.next_interval_starts.clear();
+        }
+    }
+
+    /// Rolls up the closed intervals of the source indexes of the rollup indexes, provided the
+    /// node holds the executor lease.
+    pub async fn roll_up_indexes(&mut self) -> anyhow::Result<()> {
+        self.renew_lease().await;
+
+        if !self.holds_lease() {
+            return Ok(());
+        }
+        let now_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        let index_metadatas = self.metastore.list_indexes_metadatas().await?;
+        let mut rollup_index_ids = HashSet::new();
+
+        for index_metadata in index_metadatas {
+            let index_config = index_metadata.index_config;
+
+            if index_config.rollup.is_none() || index_config.is_mounted() {
+                continue;
+            }
+            rollup_index_ids.insert(index_config.index_id.clone());
+
+            if let Err(error) = self.roll_up_index(&index_config, now_timestamp).await {
+                warn!(index_id=%index_config.index_id, error=?error, "Failed to roll up index.");
+            }
+        }
+        self.next_interval_starts
+            .retain(|index_id, _| rollup_index_ids.contains(index_id));
+        Ok(())
+    }
+
+    /// Rolls up the intervals of the source index that ended at least `delay` before
+    /// `now_timestamp`. Returns the number of intervals rolled up.
+    async fn roll_up_index(
+        &mut self,
+        index_config: &IndexConfig,
+        now_timestamp: i64,
+    ) -> anyhow::Result<usize> {
+        let rollup = index_config
+            .rollup
+            .as_ref()
+            .expect("The index should be a rollup index.");
+        let interval_secs = rollup.interval()?.as_secs() as i64;
+        let delay_secs = rollup.delay()?.as_secs() as i64;
+        let closed_intervals_end = align_timestamp(now_timestamp - delay_secs, interval_secs);
+
+        let mut interval_start = match self.next_interval_starts.get(&index_config.index_id) {
+            Some(interval_start) => *interval_start,
+            None => {
+                let Some(interval_start) =
+                    self.recover_next_interval_start(index_config, interval_secs).await? else {
+                    // The source index does not hold any document yet.
+                    return Ok(0);
+                };
+                self.next_interval_starts
+                    .insert(index_config.index_id.clone(), interval_start);
+                interval_start
+            }
+        };
+        let mut num_intervals = 0;
+
+        while interval_start + interval_secs <= closed_intervals_end
+            && num_intervals < MAX_INTERVALS_PER_TICK
+        {
+            self.roll_up_interval(index_config, interval_start, interval_secs)
+                .await?;
+            interval_start += interval_secs;
+            num_intervals += 1;
+            self.next_interval_starts
+                .insert(index_config.index_id.clone(), interval_start);
+        }
+        if num_intervals > 0 {
+            debug!(
+                index_id=%index_config.index_id,
+                num_intervals=num_intervals,
+                "Rolled up index."
+            );
+        }
+        Ok(num_intervals)
+    }
+
+    /// Returns the start of the last interval found in the published splits of the rollup index
+    /// or, if the rollup index is empty, the start of the first interval of the source index.
+    async fn recover_next_interval_start(
+        &self,
+        index_config: &IndexConfig,
+        interval_secs: i64,
+    ) -> anyhow::Result<Option<i64>> {
+        let rollup = index_config
+            .rollup
+            .as_ref()
+            .expect("The index should be a rollup index.");
+        let query = ListSplitsQuery::for_index(&index_config.index_id)
+            .with_split_state(SplitState::Published);
+        let last_rolled_up_timestamp_opt = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .filter_map(|split| split.split_metadata.time_range)
+            .map(|time_range| *time_range.end())
+            .max();
+
+        if let Some(last_rolled_up_timestamp) = last_rolled_up_timestamp_opt {
+            return Ok(Some(align_timestamp(
+                last_rolled_up_timestamp,
+                interval_secs,
+            )));
+        }
+        let query = ListSplitsQuery::for_index(&rollup.source_index_id)
+            .with_split_state(SplitState::Published);
+        let first_source_timestamp_opt = self
+            .metastore
+            .list_splits(query)
+            .await?
+            .into_iter()
+            .filter_map(|split| split.split_metadata.time_range)
+            .map(|time_range| *time_range.start())
+            .min();
+        Ok(first_source_timestamp_opt
+            .map(|first_source_timestamp| align_timestamp(first_source_timestamp, interval_secs)))
+    }
+
+    async fn roll_up_interval(
+        &mut self,
+        index_config: &IndexConfig,
+        interval_start: i64,
+        interval_secs: i64,
+    ) -> anyhow::Result<()> {
+        let rollup = index_config
+            .rollup
+            .as_ref()
+            .expect("The index should be a rollup index.");
+        let timestamp_field = index_config
+            .doc_mapping
+            .timestamp_field
+            .as_ref()
+            .expect("The rollup index should have a timestamp field.");
+        let aggregation_request = build_aggregation_request(rollup);
+        let search_request = SearchRequest {
+            index_id: rollup.source_index_id.clone(),
+            query: "*".to_string(),
+            start_timestamp: Some(interval_start),
+            end_timestamp: Some(interval_start + interval_secs),
+            max_hits: 0,
+            aggregation_request: (!aggregation_request.is_empty())
+                .then(|| JsonValue::Object(aggregation_request).to_string()),
+            ..Default::default()
+        };
+        let search_response = self.search_service.root_search(search_request).await?;
+
+        if search_response.num_hits == 0 {
+            return Ok(());
+        }
+        let aggregation = match &search_response.aggregation {
+            Some(aggregation_json) => serde_json::from_str(aggregation_json)
+                .context("Failed to parse the aggregation of the rollup search.")?,
+            None => JsonValue::Object(JsonMap::new()),
+        };
+        let mut rollup_doc = JsonMap::new();
+        rollup_doc.insert(timestamp_field.clone(), json!(interval_start));
+        let mut rollup_docs = Vec::new();
+        collect_rollup_docs(
+            rollup,
+            &aggregation,
+            search_response.num_hits,
+            0,
+            &mut rollup_doc,
+            &mut rollup_docs,
+        );
+        let mut doc_batch_builder =
+            DocBatchBuilder::new(index_config.index_id.clone()).json_writer();
+        for rollup_doc in &rollup_docs {
+            doc_batch_builder.ingest_doc(rollup_doc)?;
+        }
+        let mut doc_batch = doc_batch_builder.build();
+        doc_batch.idempotency_key = Some(format!("rollup-{interval_start}"));
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch],
+        };
+        self.ingest_service.ingest(ingest_request).await?;
+        Ok(())
+    }
+}
+
+/// Aligns `timestamp` on the start of the interval it belongs to.
+fn align_timestamp(timestamp: i64, interval_secs: i64) -> i64 {
+    timestamp - timestamp.rem_euclid(interval_secs)
+}
+
+/// Builds the aggregation grouping the documents of an interval: one level of `terms`
+/// aggregation per `group_by` field, with a `stats` aggregation per aggregated field at the
+/// innermost level.
+fn build_aggregation_request(rollup: &RollupConfig) -> JsonMap<String, JsonValue> {
+    let aggregated_fields: BTreeSet<&str> = rollup
+        .metrics
+        .iter()
+        .filter_map(|metric| metric.field.as_deref())
+        .collect();
+    let mut aggregation_request: JsonMap<String, JsonValue> = aggregated_fields
+        .into_iter()
+        .map(|field| (field.to_string(), json!({"stats": {"field": field}})))
+        .collect();
+
+    for group_by_field in rollup.group_by.iter().rev() {
+        let mut terms_aggregation = json!({
+            "terms": {"field": group_by_field, "size": rollup.max_groups}
+        });
+        if !aggregation_request.is_empty() {
+            terms_aggregation["aggs"] = JsonValue::Object(aggregation_request);
+        }
+        aggregation_request = JsonMap::new();
+        aggregation_request.insert(group_by_field.clone(), terms_aggregation);
+    }
+    aggregation_request
+}
+
+/// Walks down the `terms` buckets of the aggregation and emits a rollup document per innermost
+/// bucket. `rollup_doc` holds the timestamp and the group values of the enclosing buckets.
+fn collect_rollup_docs(
+    rollup: &RollupConfig,
+    bucket: &JsonValue,
+    doc_count: u64,
+    depth: usize,
+    rollup_doc: &mut JsonMap<String, JsonValue>,
+    rollup_docs: &mut Vec<JsonMap<String, JsonValue>>,
+) {
+    if doc_count == 0 {
+        return;
+    }
+    let Some(group_by_field) = rollup.group_by.get(depth) else {
+        let mut rollup_doc = rollup_doc.clone();
+
+        for metric in &rollup.metrics {
+            let stat_name = match metric.function {
+                RollupFunction::Count => {
+                    rollup_doc.insert(metric.name.clone(), json!(doc_count));
+                    continue;
+                }
+                RollupFunction::Sum => "sum",
+                RollupFunction::Min => "min",
+                RollupFunction::Max => "max",
+            };
+            let field = metric.field.as_deref().unwrap_or_default();
+            let value = &bucket[field][stat_name];
+            // The stats of a field missing from all the documents of the group are null.
+            if !value.is_null() {
+                rollup_doc.insert(metric.name.clone(), value.clone());
+            }
+        }
+        rollup_docs.push(rollup_doc);
+        return;
+    };
+    let Some(sub_buckets) = bucket[group_by_field]["buckets"].as_array() else {
+        return;
+    };
+    for sub_bucket in sub_buckets {
+        let sub_doc_count = sub_bucket["doc_count"].as_u64().unwrap_or_default();
+        rollup_doc.insert(group_by_field.clone(), sub_bucket["key"].clone());
+        collect_rollup_docs(
+            rollup,
+            sub_bucket,
+            sub_doc_count,
+            depth + 1,
+            rollup_doc,
+            rollup_docs,
+        );
+    }
+    rollup_doc.remove(group_by_field);
+}
+
+pub(crate) fn spawn_rollup_task(mut rollup_executor: RollupExecutor) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ROLLUP_TICK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(error) = rollup_executor.roll_up_indexes().await {
+                warn!(error=?error, "Failed to roll up the indexes.");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_config::RollupMetric;
+    use quickwit_ingest_api::{DocCommand, IngestResponse};
+    use quickwit_metastore::{metastore_for_test, SplitMetadata};
+    use quickwit_proto::SearchResponse;
+    use quickwit_search::MockSearchService;
+
+    use super::*;
+
+    fn rollup_for_test() -> RollupConfig {
+        RollupConfig {
+            source_index_id: "app-logs".to_string(),
+            interval: "1 minute".to_string(),
+            group_by: vec!["service".to_string(), "status".to_string()],
+            metrics: vec![
+                RollupMetric {
+                    name: "num_logs".to_string(),
+                    function: RollupFunction::Count,
+                    field: None,
+                },
+                RollupMetric {
+                    name: "total_latency".to_string(),
+                    function: RollupFunction::Sum,
+                    field: Some("latency".to_string()),
+                },
+                RollupMetric {
+                    name: "max_latency".to_string(),
+                    function: RollupFunction::Max,
+                    field: Some("latency".to_string()),
+                },
+            ],
+            delay: "1 minute".to_string(),
+            max_groups: 100,
+        }
+    }
+
+    #[test]
+    fn test_align_timestamp() {
+        assert_eq!(align_timestamp(0, 60), 0);
+        assert_eq!(align_timestamp(119, 60), 60);
+        assert_eq!(align_timestamp(120, 60), 120);
+        assert_eq!(align_timestamp(-1, 60), -60);
+    }
+
+    #[test]
+    fn test_build_aggregation_request() {
+        let aggregation_request = build_aggregation_request(&rollup_for_test());
+        assert_eq!(
+            JsonValue::Object(aggregation_request),
+            json!({
+                "service": {
+                    "terms": {"field": "service", "size": 100},
+                    "aggs": {
+                        "status": {
+                            "terms": {"field": "status", "size": 100},
+                            "aggs": {"latency": {"stats": {"field": "latency"}}}
+                        }
+                    }
+                }
+            })
+        );
+        let rollup = RollupConfig {
+            group_by: Vec::new(),
+            metrics: vec![RollupMetric {
+                name: "num_logs".to_string(),
+                function: RollupFunction::Count,
+                field: None,
+            }],
+            ..rollup_for_test()
+        };
+        assert!(build_aggregation_request(&rollup).is_empty());
+    }
+
+    #[test]
+    fn test_collect_rollup_docs() {
+        let aggregation = json!({
+            "service": {
+                "buckets": [
+                    {
+                        "key": "api",
+                        "doc_count": 3,
+                        "status": {
+                            "buckets": [
+                                {
+                                    "key": 200,
+                                    "doc_count": 2,
+                                    "latency": {"count": 2, "sum": 30.0, "min": 10.0, "max": 20.0}
+                                },
+                                {
+                                    "key": 500,
+                                    "doc_count": 1,
+                                    "latency": {"count": 0, "sum": 0.0, "min": null, "max": null}
+                                }
+                            ]
+                        }
+                    },
+                    {
+                        "key": "web",
+                        "doc_count": 1,
+                        "status": {
+                            "buckets": [
+                                {
+                                    "key": 200,
+                                    "doc_count": 1,
+                                    "latency": {"count": 1, "sum": 5.0, "min": 5.0, "max": 5.0}
+                                }
+                            ]
+                        }
+                    }
+                ]
+            }
+        });
+        let mut rollup_doc = JsonMap::new();
+        rollup_doc.insert("timestamp".to_string(), json!(60));
+        let mut rollup_docs = Vec::new();
+        collect_rollup_docs(
+            &rollup_for_test(),
+            &aggregation,
+            4,
+            0,
+            &mut rollup_doc,
+            &mut rollup_docs,
+        );
+        assert_eq!(
+            JsonValue::Array(rollup_docs.into_iter().map(JsonValue::Object).collect()),
+            json!([
+                {
+                    "timestamp": 60,
+                    "service": "api",
+                    "status": 200,
+                    "num_logs": 2,
+                    "total_latency": 30.0,
+                    "max_latency": 20.0
+                },
+                {
+                    "timestamp": 60,
+                    "service": "api",
+                    "status": 500,
+                    "num_logs": 1,
+                    "total_latency": 0.0
+                },
+                {
+                    "timestamp": 60,
+                    "service": "web",
+                    "status": 200,
+                    "num_logs": 1,
+                    "total_latency": 5.0,
+                    "max_latency": 5.0
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollup_executor_rolls_up_closed_intervals() {
+        let metastore = metastore_for_test();
+        let source_index_config = IndexConfig::for_test("app-logs", "ram:///indexes/app-logs");
+        metastore.create_index(source_index_config).await.unwrap();
+
+        let now_timestamp = 10 * 60 + 30;
+        let split_metadata = SplitMetadata {
+            split_id: "split-1".to_string(),
+            index_id: "app-logs".to_string(),
+            time_range: Some(7 * 60 + 10..=now_timestamp),
+            ..Default::default()
+        };
+        metastore
+            .stage_splits("app-logs", vec![split_metadata])
+            .await
+            .unwrap();
+        metastore
+            .publish_splits("app-logs", &["split-1"], &[], None)
+            .await
+            .unwrap();
+
+        let mut rollup_index_config =
+            IndexConfig::for_test("app-logs-rollup", "ram:///indexes/app-logs-rollup");
+        rollup_index_config.rollup = Some(RollupConfig {
+            group_by: vec!["service".to_string()],
+            metrics: vec![RollupMetric {
+                name: "num_logs".to_string(),
+                function: RollupFunction::Count,
+                field: None,
+            }],
+            ..rollup_for_test()
+        });
+        metastore
+            .create_index(rollup_index_config.clone())
+            .await
+            .unwrap();
+
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(2)
+            .returning(|search_request| {
+                assert_eq!(search_request.index_id, "app-logs");
+                assert_eq!(
+                    search_request.end_timestamp.unwrap() - search_request.start_timestamp.unwrap(),
+                    60
+                );
+                let aggregation = json!({
+                    "service": {"buckets": [{"key": "api", "doc_count": 2}]}
+                });
+                Ok(SearchResponse {
+                    num_hits: 2,
+                    aggregation: Some(aggregation.to_string()),
+                    ..Default::default()
+                })
+            });
+        let mut mock_ingest_service = IngestServiceClient::mock();
+        mock_ingest_service
+            .expect_ingest()
+            .times(2)
+            .returning(|ingest_request| {
+                let doc_batch = &ingest_request.doc_batches[0];
+                assert_eq!(doc_batch.index_id, "app-logs-rollup");
+                let interval_start = doc_batch
+                    .idempotency_key
+                    .as_ref()
+                    .unwrap()
+                    .strip_prefix("rollup-")
+                    .unwrap()
+                    .to_string();
+                let doc = match doc_batch.iter().next().unwrap() {
+                    DocCommand::Ingest { payload } => payload,
+                    _ => panic!("Expected an ingest command."),
+                };
+                let doc: JsonValue = serde_json::from_slice(&doc).unwrap();
+                assert_eq!(doc["timestamp"].to_string(), interval_start);
+                assert_eq!(doc["service"], "api");
+                assert_eq!(doc["num_logs"], 2);
+                Ok(IngestResponse {
+                    num_docs_for_processing: 1,
+                    ..Default::default()
+                })
+            });
+        let mut rollup_executor = RollupExecutor::new(
+            "test-node".to_string(),
+            metastore,
+            Arc::new(mock_search_service),
+            mock_ingest_service.into(),
+        );
+        rollup_executor.renew_lease().await;
+
+        // The intervals [7m, 8m) and [8m, 9m) are closed, [9m, 10m) ends within the delay.
+        let num_intervals = rollup_executor
+            .roll_up_index(&rollup_index_config, now_timestamp)
+            .await
+            .unwrap();
+        assert_eq!(num_intervals, 2);
+        assert_eq!(
+            rollup_executor.next_interval_starts["app-logs-rollup"],
+            9 * 60
+        );
+        let num_intervals = rollup_executor
+            .roll_up_index(&rollup_index_config, now_timestamp)
+            .await
+            .unwrap();
+        assert_eq!(num_intervals, 0);
+    }
+}
@@ -21,19 +21,87 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::TryStreamExt;
+use quickwit_config::FieldMaskConfig;
+use quickwit_metastore::{ApiKeyScope, Metastore};
 use quickwit_proto::{
     convert_to_grpc_result, search_service_server as grpc, set_parent_span_from_request_metadata,
-    tonic, LeafSearchStreamRequest, LeafSearchStreamResponse, ServiceError,
+    tonic, LeafSearchStreamRequest, LeafSearchStreamResponse, LiveTailRequest, LiveTailResponse,
+    SearchHitsChunk, ServiceError,
 };
-use quickwit_search::SearchService;
+use quickwit_search::{with_caller_field_masks, SearchService};
+use tokio_stream::wrappers::ReceiverStream;
 use tracing::instrument;
 
+use super::live_tail::{start_grpc_live_tail, SplitPublishNotifier};
+use crate::api_key_api::{grpc_authorization, ApiKeyAuthenticator, IndexTarget};
+
 #[derive(Clone)]
-pub struct GrpcSearchAdapter(Arc<dyn SearchService>);
+pub struct GrpcSearchAdapter {
+    search_service: Arc<dyn SearchService>,
+    live_tail_opt: Option<(Arc<dyn Metastore>, SplitPublishNotifier)>,
+    authenticator_opt: Option<Arc<ApiKeyAuthenticator>>,
+}
+
+impl GrpcSearchAdapter {
+    /// Authenticates the requests with `authenticator`: the root methods require the `search`
+    /// scope on the searched indexes, and the leaf methods, which other nodes call, require the
+    /// cluster credentials.
+    pub(crate) fn with_authenticator(mut self, authenticator: Arc<ApiKeyAuthenticator>) -> Self {
+        self.authenticator_opt = Some(authenticator);
+        self
+    }
+
+    fn authorize_search<T>(
+        &self,
+        request: &tonic::Request<T>,
+        index_target: IndexTarget,
+    ) -> Result<(), tonic::Status> {
+        if let Some(authenticator) = &self.authenticator_opt {
+            authenticator
+                .authorize_grpc_request(request.metadata(), ApiKeyScope::Search, index_target)
+                .map_err(|auth_error| auth_error.grpc_error())?;
+        }
+        Ok(())
+    }
+
+    fn authorize_cluster<T>(&self, request: &tonic::Request<T>) -> Result<(), tonic::Status> {
+        if let Some(authenticator) = &self.authenticator_opt {
+            authenticator
+                .authorize_cluster_request(request.metadata())
+                .map_err(|auth_error| auth_error.grpc_error())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the field masks of the roles of the caller, which apply to the documents returned
+    /// by its searches.
+    fn caller_field_masks<T>(&self, request: &tonic::Request<T>) -> Vec<FieldMaskConfig> {
+        match &self.authenticator_opt {
+            Some(authenticator) => {
+                authenticator.field_masks(grpc_authorization(request.metadata()))
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Enables the live tail API, which resolves the tailed index with the metastore.
+    pub fn with_live_tail(
+        mut self,
+        metastore: Arc<dyn Metastore>,
+        split_publish_notifier: SplitPublishNotifier,
+    ) -> Self {
+        self.live_tail_opt = Some((metastore, split_publish_notifier));
+        self
+    }
+}
 
 impl From<Arc<dyn SearchService>> for GrpcSearchAdapter {
     fn from(search_service_arc: Arc<dyn SearchService>) -> Self {
-        GrpcSearchAdapter(search_service_arc)
+        GrpcSearchAdapter {
+            search_service: search_service_arc,
+            live_tail_opt: None,
+            authenticator_opt: None,
+        }
     }
 }
 
@@ -45,8 +113,13 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<quickwit_proto::SearchRequest>,
     ) -> Result<tonic::Response<quickwit_proto::SearchResponse>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_search(&request, IndexTarget::Indexes(&request.get_ref().index_id))?;
+        let field_masks = self.caller_field_masks(&request);
         let search_request = request.into_inner();
-        let search_res = self.0.root_search(search_request).await;
+        let search_res = with_caller_field_masks(field_masks, async {
+            self.search_service.root_search(search_request).await
+        })
+        .await;
         convert_to_grpc_result(search_res)
     }
 
@@ -56,8 +129,9 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<quickwit_proto::LeafSearchRequest>,
     ) -> Result<tonic::Response<quickwit_proto::LeafSearchResponse>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_cluster(&request)?;
         let leaf_search_request = request.into_inner();
-        let leaf_search_res = self.0.leaf_search(leaf_search_request).await;
+        let leaf_search_res = self.search_service.leaf_search(leaf_search_request).await;
         convert_to_grpc_result(leaf_search_res)
     }
 
@@ -67,8 +141,9 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<quickwit_proto::FetchDocsRequest>,
     ) -> Result<tonic::Response<quickwit_proto::FetchDocsResponse>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_cluster(&request)?;
         let fetch_docs_request = request.into_inner();
-        let fetch_docs_res = self.0.fetch_docs(fetch_docs_request).await;
+        let fetch_docs_res = self.search_service.fetch_docs(fetch_docs_request).await;
         convert_to_grpc_result(fetch_docs_res)
     }
 
@@ -85,9 +160,10 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<LeafSearchStreamRequest>,
     ) -> Result<tonic::Response<Self::LeafSearchStreamStream>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_cluster(&request)?;
         let leaf_search_request = request.into_inner();
         let leaf_search_result = self
-            .0
+            .search_service
             .leaf_search_stream(leaf_search_request)
             .await
             .map_err(|err| err.grpc_error())?
@@ -101,8 +177,9 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<quickwit_proto::ListTermsRequest>,
     ) -> Result<tonic::Response<quickwit_proto::ListTermsResponse>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_search(&request, IndexTarget::Indexes(&request.get_ref().index_id))?;
         let search_request = request.into_inner();
-        let search_res = self.0.root_list_terms(search_request).await;
+        let search_res = self.search_service.root_list_terms(search_request).await;
         convert_to_grpc_result(search_res)
     }
 
@@ -112,8 +189,418 @@ impl grpc::SearchService for GrpcSearchAdapter {
         request: tonic::Request<quickwit_proto::LeafListTermsRequest>,
     ) -> Result<tonic::Response<quickwit_proto::LeafListTermsResponse>, tonic::Status> {
         set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_cluster(&request)?;
         let leaf_search_request = request.into_inner();
-        let leaf_search_res = self.0.leaf_list_terms(leaf_search_request).await;
+        let leaf_search_res = self
+            .search_service
+            .leaf_list_terms(leaf_search_request)
+            .await;
         convert_to_grpc_result(leaf_search_res)
     }
+
+    #[instrument(skip(self, request))]
+    async fn scroll(
+        &self,
+        request: tonic::Request<quickwit_proto::ScrollRequest>,
+    ) -> Result<tonic::Response<quickwit_proto::SearchResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        // Scroll IDs are only handed out by authorized searches.
+        self.authorize_search(&request, IndexTarget::None)?;
+        let scroll_request = request.into_inner();
+        let scroll_res = self.search_service.scroll(scroll_request).await;
+        convert_to_grpc_result(scroll_res)
+    }
+
+    type RootSearchHitsStreamStream = std::pin::Pin<
+        Box<dyn futures::Stream<Item = Result<SearchHitsChunk, tonic::Status>> + Send>,
+    >;
+    #[instrument(name = "search_adapter:root_search_hits_stream", skip(self, request))]
+    async fn root_search_hits_stream(
+        &self,
+        request: tonic::Request<quickwit_proto::SearchRequest>,
+    ) -> Result<tonic::Response<Self::RootSearchHitsStreamStream>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_search(&request, IndexTarget::Indexes(&request.get_ref().index_id))?;
+        let field_masks = self.caller_field_masks(&request);
+        let search_request = request.into_inner();
+        // The masks are added to the request before the stream is returned.
+        let hits_stream = with_caller_field_masks(field_masks, async {
+            self.search_service
+                .root_search_hits_stream(search_request)
+                .await
+        })
+        .await
+        .map_err(|err| err.grpc_error())?
+        .map_err(|err| err.grpc_error());
+        Ok(tonic::Response::new(Box::pin(hits_stream)))
+    }
+
+    type LiveTailStream = ReceiverStream<Result<LiveTailResponse, tonic::Status>>;
+    #[instrument(name = "search_adapter:live_tail", skip(self, request))]
+    async fn live_tail(
+        &self,
+        request: tonic::Request<LiveTailRequest>,
+    ) -> Result<tonic::Response<Self::LiveTailStream>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_search(&request, IndexTarget::Indexes(&request.get_ref().index_id))?;
+        let Some((metastore, split_publish_notifier)) = &self.live_tail_opt else {
+            return Err(tonic::Status::unimplemented(
+                "The live tail API is not enabled on this node.",
+            ));
+        };
+        let field_masks = self.caller_field_masks(&request);
+        let live_tail_request = request.into_inner();
+        let response_stream = with_caller_field_masks(
+            field_masks,
+            start_grpc_live_tail(
+                live_tail_request,
+                self.search_service.clone(),
+                &**metastore,
+                split_publish_notifier,
+            ),
+        )
+        .await
+        .map_err(|err| err.grpc_error())?;
+        Ok(tonic::Response::new(response_stream))
+    }
+
+    #[instrument(skip(self, request))]
+    async fn leaf_warmup(
+        &self,
+        request: tonic::Request<quickwit_proto::LeafWarmupRequest>,
+    ) -> Result<tonic::Response<quickwit_proto::WarmupResponse>, tonic::Status> {
+        set_parent_span_from_request_metadata(request.metadata());
+        self.authorize_cluster(&request)?;
+        let leaf_warmup_request = request.into_inner();
+        let leaf_warmup_res = self.search_service.leaf_warmup(leaf_warmup_request).await;
+        convert_to_grpc_result(leaf_warmup_res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use futures::StreamExt;
+    use quickwit_config::{AuthConfig, FieldMaskActionConfig, PermissionConfig, RoleConfig};
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::search_service_server::SearchService as _;
+    use quickwit_proto::{
+        Hit, LeafSearchRequest, LeafSearchResponse, ListTermsRequest, ListTermsResponse,
+        PartialHit, SearchRequest, SearchResponse, CLUSTER_SECRET_METADATA_KEY,
+    };
+    use quickwit_search::{add_caller_field_masks, MockSearchService};
+
+    use super::*;
+
+    fn authenticated_search_adapter(
+        mock_search_service: MockSearchService,
+    ) -> (GrpcSearchAdapter, Arc<ApiKeyAuthenticator>) {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore.expect_create_api_key().returning(|_| Ok(()));
+        let auth_config = AuthConfig {
+            enable_api_keys: true,
+            bootstrap_admin_key: Some("bootstrap-key".to_string()),
+            cluster_secret: Some("cluster-secret".to_string()),
+            roles: vec![RoleConfig {
+                name: "support".to_string(),
+                permissions: vec![PermissionConfig {
+                    scopes: BTreeSet::from([ApiKeyScope::Search]),
+                    index_patterns: vec!["*".to_string()],
+                }],
+                field_masks: vec![FieldMaskConfig {
+                    field: "user.email".to_string(),
+                    action: FieldMaskActionConfig::Hash,
+                    index_patterns: vec!["*".to_string()],
+                }],
+            }],
+            ..Default::default()
+        };
+        let authenticator = Arc::new(ApiKeyAuthenticator::new(
+            Arc::new(mock_metastore),
+            &auth_config,
+        ));
+        let search_service: Arc<dyn SearchService> = Arc::new(mock_search_service);
+        let search_adapter =
+            GrpcSearchAdapter::from(search_service).with_authenticator(authenticator.clone());
+        (search_adapter, authenticator)
+    }
+
+    fn with_authorization<T>(message: T, authorization: &str) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        request
+            .metadata_mut()
+            .insert("authorization", authorization.parse().unwrap());
+        request
+    }
+
+    #[tokio::test]
+    async fn test_grpc_search_adapter_rejects_unauthenticated_requests() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(1)
+            .returning(|_| Ok(SearchResponse::default()));
+        mock_search_service
+            .expect_leaf_search()
+            .times(1)
+            .returning(|_| Ok(LeafSearchResponse::default()));
+        let (search_adapter, _authenticator) = authenticated_search_adapter(mock_search_service);
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            ..Default::default()
+        };
+        let status = search_adapter
+            .root_search(tonic::Request::new(search_request.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut request = tonic::Request::new(search_request);
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer bootstrap-key".parse().unwrap());
+        search_adapter.root_search(request).await.unwrap();
+
+        // The leaf methods are reserved to the nodes of the cluster.
+        let mut request = tonic::Request::new(LeafSearchRequest::default());
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer qw_unknown_secret".parse().unwrap());
+        let status = search_adapter.leaf_search(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut request = tonic::Request::new(LeafSearchRequest::default());
+        request.metadata_mut().insert(
+            CLUSTER_SECRET_METADATA_KEY,
+            "cluster-secret".parse().unwrap(),
+        );
+        search_adapter.leaf_search(request).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_search_adapter_checks_index_permissions() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(1)
+            .returning(|_| Ok(SearchResponse::default()));
+        mock_search_service
+            .expect_root_list_terms()
+            .times(1)
+            .returning(|_| Ok(ListTermsResponse::default()));
+        let (search_adapter, authenticator) = authenticated_search_adapter(mock_search_service);
+        let (_, key) = authenticator
+            .create_api_key(
+                None,
+                vec![ApiKeyScope::Search],
+                vec!["team-a-*".to_string()],
+                Vec::new(),
+                0,
+            )
+            .await
+            .unwrap();
+        let authorization = format!("Bearer {key}");
+
+        let search_request = |index_id: &str| SearchRequest {
+            index_id: index_id.to_string(),
+            query: "*".to_string(),
+            ..Default::default()
+        };
+        search_adapter
+            .root_search(with_authorization(
+                search_request("team-a-logs"),
+                &authorization,
+            ))
+            .await
+            .unwrap();
+        for index_id in ["team-b-logs", "team-a-logs,team-b-logs"] {
+            let status = search_adapter
+                .root_search(with_authorization(search_request(index_id), &authorization))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        }
+        let status = search_adapter
+            .root_search_hits_stream(with_authorization(
+                search_request("team-b-logs"),
+                &authorization,
+            ))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let list_terms_request = |index_id: &str| ListTermsRequest {
+            index_id: index_id.to_string(),
+            field: "severity".to_string(),
+            ..Default::default()
+        };
+        search_adapter
+            .root_list_terms(with_authorization(
+                list_terms_request("team-a-logs"),
+                &authorization,
+            ))
+            .await
+            .unwrap();
+        let status = search_adapter
+            .root_list_terms(with_authorization(
+                list_terms_request("team-b-logs"),
+                &authorization,
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+
+        let live_tail_request = LiveTailRequest {
+            index_id: "team-b-logs".to_string(),
+            ..Default::default()
+        };
+        let status = search_adapter
+            .live_tail(with_authorization(live_tail_request, &authorization))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    /// Creates an API key holding the `support` role, whose searches hash `user.email`.
+    async fn support_authorization(authenticator: &ApiKeyAuthenticator) -> String {
+        let (_, key) = authenticator
+            .create_api_key(
+                None,
+                Vec::new(),
+                vec!["*".to_string()],
+                vec!["support".to_string()],
+                0,
+            )
+            .await
+            .unwrap();
+        format!("Bearer {key}")
+    }
+
+    fn assert_support_field_masks(mut search_request: SearchRequest) {
+        add_caller_field_masks(&mut search_request);
+        assert_eq!(search_request.field_masks.len(), 1);
+        assert_eq!(search_request.field_masks[0].field, "user.email");
+    }
+
+    #[tokio::test]
+    async fn test_grpc_search_adapter_masks_fields_of_caller() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .times(1)
+            .returning(|search_request| {
+                assert_support_field_masks(search_request);
+                Ok(SearchResponse::default())
+            });
+        let (search_adapter, authenticator) = authenticated_search_adapter(mock_search_service);
+        let authorization = support_authorization(&authenticator).await;
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            ..Default::default()
+        };
+        search_adapter
+            .root_search(with_authorization(search_request, &authorization))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_search_adapter_hits_stream() {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search_hits_stream()
+            .times(1)
+            .returning(|search_request| {
+                assert_support_field_masks(search_request);
+                Ok(Box::pin(futures::stream::empty()))
+            });
+        let (search_adapter, authenticator) = authenticated_search_adapter(mock_search_service);
+        let search_request = SearchRequest {
+            index_id: "logs".to_string(),
+            query: "*".to_string(),
+            ..Default::default()
+        };
+        let status = search_adapter
+            .root_search_hits_stream(tonic::Request::new(search_request.clone()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let authorization = support_authorization(&authenticator).await;
+        search_adapter
+            .root_search_hits_stream(with_authorization(search_request, &authorization))
+            .await
+            .ok()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_search_adapter_live_tail() {
+        let mut mock_metastore = MockMetastore::new();
+        mock_metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        mock_metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                Ok(IndexMetadata::for_test(index_id, "ram:///indexes/logs"))
+            });
+        let mut mock_search_service = MockSearchService::new();
+        let mut num_searches = 0;
+        mock_search_service
+            .expect_root_search()
+            .returning(move |search_request: SearchRequest| {
+                // The searches run in another task, so the masks are carried by the request.
+                assert_eq!(search_request.field_masks.len(), 1);
+                assert_eq!(search_request.field_masks[0].field, "user.email");
+                num_searches += 1;
+                let hits = if num_searches == 1 {
+                    vec![Hit {
+                        json: r#"{"id": 1}"#.to_string(),
+                        partial_hit: Some(PartialHit {
+                            split_id: "split-1".to_string(),
+                            doc_id: 1,
+                            ..Default::default()
+                        }),
+                        snippet: None,
+                    }]
+                } else {
+                    Vec::new()
+                };
+                Ok(SearchResponse {
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let (search_adapter, authenticator) = authenticated_search_adapter(mock_search_service);
+        let search_adapter =
+            search_adapter.with_live_tail(Arc::new(mock_metastore), SplitPublishNotifier::new());
+        let live_tail_request = LiveTailRequest {
+            index_id: "logs".to_string(),
+            start_timestamp: Some(1_000),
+            poll_interval_ms: Some(100),
+            ..Default::default()
+        };
+        let status = search_adapter
+            .live_tail(tonic::Request::new(live_tail_request.clone()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let authorization = support_authorization(&authenticator).await;
+        let mut response_stream = search_adapter
+            .live_tail(with_authorization(live_tail_request, &authorization))
+            .await
+            .unwrap()
+            .into_inner();
+        let response = response_stream.next().await.unwrap().unwrap();
+        assert_eq!(response.hits.len(), 1);
+    }
 }
@@ -0,0 +1,677 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use quickwit_common::pubsub::EventSubscriber;
+use quickwit_metastore::{Metastore, PublishSplitsEvent};
+use quickwit_proto::{
+    tonic, Hit, LiveTailRequest, LiveTailResponse, PartialHit, SearchRequest, ServiceError,
+    SortOrder,
+};
+use quickwit_search::{
+    add_caller_field_masks, resolve_single_index_id, SearchError, SearchService,
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, warn};
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{Filter, Rejection, Reply};
+
+use crate::{with_arg, BodyFormat};
+
+#[derive(utoipa::OpenApi)]
+#[openapi(paths(live_tail_handler))]
+pub struct LiveTailApi;
+
+/// Maximum number of documents fetched by a single search of the index.
+const MAX_HITS_PER_POLL: u64 = 1_000;
+
+const MIN_POLL_INTERVAL_MS: u64 = 100;
+
+const DEFAULT_MAX_HITS_PER_MESSAGE: u32 = 100;
+
+const DEFAULT_MAX_BUFFERED_MESSAGES: u32 = 16;
+
+const MAX_BUFFERED_MESSAGES: u32 = 1_024;
+
+/// Number of split publication notifications a live tail can lag behind before missing some.
+const SPLIT_PUBLISH_CHANNEL_CAPACITY: usize = 1_024;
+
+/// Wakes up the live tails when splits get published, so that they search the index right away
+/// instead of waiting for the end of their poll interval.
+///
+/// The splits publication events are only dispatched on the nodes running the metastore service:
+/// on the other nodes, the live tails fall back to polling.
+#[derive(Debug, Clone)]
+pub struct SplitPublishNotifier {
+    index_id_tx: broadcast::Sender<String>,
+}
+
+impl SplitPublishNotifier {
+    pub fn new() -> Self {
+        let (index_id_tx, _index_id_rx) = broadcast::channel(SPLIT_PUBLISH_CHANNEL_CAPACITY);
+        Self { index_id_tx }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.index_id_tx.subscribe()
+    }
+}
+
+impl Default for SplitPublishNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl EventSubscriber<PublishSplitsEvent> for SplitPublishNotifier {
+    async fn handle_event(&mut self, event: PublishSplitsEvent) {
+        // Sending only fails when no live tail is running.
+        let _ = self.index_id_tx.send(event.index_id);
+    }
+}
+
+/// Waits for splits to get published on the index, or for the poll interval to elapse.
+async fn wait_for_new_splits(
+    publish_rx: &mut broadcast::Receiver<String>,
+    index_id: &str,
+    poll_interval: Duration,
+) {
+    let wait_for_publication = async {
+        loop {
+            match publish_rx.recv().await {
+                Ok(published_index_id) if published_index_id == index_id => return,
+                Ok(_) => continue,
+                // The missed notifications may concern the index.
+                Err(broadcast::error::RecvError::Lagged(_)) => return,
+                Err(broadcast::error::RecvError::Closed) => futures::future::pending::<()>().await,
+            }
+        }
+    };
+    let _ = tokio::time::timeout(poll_interval, wait_for_publication).await;
+}
+
+/// Searches the index for the pages of documents following the last returned one. The documents
+/// tied on the timestamp are paginated with the `search_after` partial hit.
+struct LiveTailCursor {
+    search_request: SearchRequest,
+    search_after_opt: Option<PartialHit>,
+}
+
+impl LiveTailCursor {
+    fn new(search_request: SearchRequest) -> Self {
+        Self {
+            search_request,
+            search_after_opt: None,
+        }
+    }
+
+    fn index_id(&self) -> &str {
+        &self.search_request.index_id
+    }
+
+    async fn next_page(
+        &mut self,
+        search_service: &dyn SearchService,
+    ) -> Result<Vec<Hit>, SearchError> {
+        let mut search_request = self.search_request.clone();
+        search_request.search_after = self.search_after_opt.clone();
+        let search_response = search_service.root_search(search_request).await?;
+
+        if let Some(partial_hit) = search_response
+            .hits
+            .iter()
+            .rev()
+            .find_map(|hit| hit.partial_hit.clone())
+        {
+            self.search_after_opt = Some(partial_hit);
+        }
+        Ok(search_response.hits)
+    }
+
+    /// Returns whether the page is full, in which case more documents may follow it.
+    fn is_full_page(&self, hits: &[Hit]) -> bool {
+        hits.len() as u64 >= self.search_request.max_hits
+    }
+}
+
+/// This struct represents the QueryString passed to the live tail REST API.
+#[derive(Debug, Deserialize, PartialEq, Eq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct LiveTailQueryString {
+    /// Query filtering the streamed documents. Defaults to all the documents.
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Streams the documents whose timestamp is greater than or equal to this value, in seconds
+    /// since the Unix epoch. Defaults to the time of the connection.
+    #[serde(default)]
+    pub start_timestamp: Option<i64>,
+    /// Maximum interval between two searches of the index, in milliseconds.
+    #[serde(default = "LiveTailQueryString::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+}
+
+impl LiveTailQueryString {
+    fn default_poll_interval_ms() -> u64 {
+        1_000
+    }
+}
+
+/// Builds the search request polling the index for the documents published since the last
+/// streamed document, in timestamp order.
+async fn live_tail_search_request(
+    index_id: String,
+    live_tail_query: &LiveTailQueryString,
+    metastore: &dyn Metastore,
+) -> Result<SearchRequest, SearchError> {
+    if live_tail_query.poll_interval_ms < MIN_POLL_INTERVAL_MS {
+        return Err(SearchError::InvalidArgument(format!(
+            "The poll interval must be at least {MIN_POLL_INTERVAL_MS}ms."
+        )));
+    }
+    let index_id = resolve_single_index_id(metastore, &index_id).await?;
+    let index_metadata = metastore.index_metadata(&index_id).await?;
+    let Some(timestamp_field) = index_metadata.index_config().doc_mapping.timestamp_field.clone()
+    else {
+        return Err(SearchError::InvalidArgument(format!(
+            "Index `{index_id}` has no timestamp field, which live tailing requires."
+        )));
+    };
+    let start_timestamp = live_tail_query.start_timestamp.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default()
+    });
+    let query = live_tail_query
+        .query
+        .clone()
+        .unwrap_or_else(|| "*".to_string());
+    let mut search_request = SearchRequest {
+        index_id,
+        query,
+        start_timestamp: Some(start_timestamp),
+        max_hits: MAX_HITS_PER_POLL,
+        sort_order: Some(SortOrder::Asc as i32),
+        sort_by_field: Some(timestamp_field),
+        ..Default::default()
+    };
+    // The searches of the live tail run after the request is handled, in another task.
+    add_caller_field_masks(&mut search_request);
+    Ok(search_request)
+}
+
+/// Pushes the documents matching the search request to the WebSocket as they get published, one
+/// JSON document per text message, until the client closes the connection.
+async fn live_tail(
+    websocket: WebSocket,
+    search_request: SearchRequest,
+    poll_interval: Duration,
+    search_service: Arc<dyn SearchService>,
+    mut publish_rx: broadcast::Receiver<String>,
+) {
+    let (mut websocket_tx, mut websocket_rx) = websocket.split();
+    let mut cursor = LiveTailCursor::new(search_request);
+    loop {
+        loop {
+            let hits = match cursor.next_page(&*search_service).await {
+                Ok(hits) => hits,
+                Err(search_error) => {
+                    warn!(error=?search_error, "Live tail search failed.");
+                    let error_message = json!({ "error": search_error.to_string() }).to_string();
+                    let _ = websocket_tx.send(Message::text(error_message)).await;
+                    let _ = websocket_tx.close().await;
+                    return;
+                }
+            };
+            let is_full_page = cursor.is_full_page(&hits);
+
+            for hit in hits {
+                if websocket_tx.send(Message::text(hit.json)).await.is_err() {
+                    return;
+                }
+            }
+            if !is_full_page {
+                break;
+            }
+        }
+        loop {
+            tokio::select! {
+                _ = wait_for_new_splits(&mut publish_rx, cursor.index_id(), poll_interval) => break,
+                message_opt = websocket_rx.next() => {
+                    match message_opt {
+                        Some(Ok(message)) if !message.is_close() => continue,
+                        _ => return,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sends the documents matching the search request to the gRPC client as they get published,
+/// until the client cancels the call.
+///
+/// The response messages are buffered in a bounded channel: when the client does not keep up,
+/// sending blocks and the index is not searched until the client catches up.
+async fn grpc_live_tail(
+    search_request: SearchRequest,
+    poll_interval: Duration,
+    search_service: Arc<dyn SearchService>,
+    mut publish_rx: broadcast::Receiver<String>,
+    response_tx: mpsc::Sender<Result<LiveTailResponse, tonic::Status>>,
+) {
+    let mut cursor = LiveTailCursor::new(search_request);
+    loop {
+        loop {
+            let hits = match cursor.next_page(&*search_service).await {
+                Ok(hits) => hits,
+                Err(search_error) => {
+                    warn!(error=?search_error, "Live tail search failed.");
+                    let _ = response_tx.send(Err(search_error.grpc_error())).await;
+                    return;
+                }
+            };
+            let is_full_page = cursor.is_full_page(&hits);
+
+            if !hits.is_empty()
+                && response_tx
+                    .send(Ok(LiveTailResponse { hits }))
+                    .await
+                    .is_err()
+            {
+                return;
+            }
+            if !is_full_page {
+                break;
+            }
+        }
+        tokio::select! {
+            _ = wait_for_new_splits(&mut publish_rx, cursor.index_id(), poll_interval) => {}
+            _ = response_tx.closed() => return,
+        }
+    }
+}
+
+/// Validates the live tail gRPC request and starts tailing the index in the background. Returns
+/// the stream of response messages.
+pub(crate) async fn start_grpc_live_tail(
+    live_tail_request: LiveTailRequest,
+    search_service: Arc<dyn SearchService>,
+    metastore: &dyn Metastore,
+    split_publish_notifier: &SplitPublishNotifier,
+) -> Result<ReceiverStream<Result<LiveTailResponse, tonic::Status>>, SearchError> {
+    info!(request =? live_tail_request, "grpc_live_tail");
+    let max_hits_per_message = live_tail_request
+        .max_hits_per_message
+        .unwrap_or(DEFAULT_MAX_HITS_PER_MESSAGE);
+    if max_hits_per_message == 0 || max_hits_per_message as u64 > MAX_HITS_PER_POLL {
+        return Err(SearchError::InvalidArgument(format!(
+            "The maximum number of hits per message must be between 1 and {MAX_HITS_PER_POLL}."
+        )));
+    }
+    let max_buffered_messages = live_tail_request
+        .max_buffered_messages
+        .unwrap_or(DEFAULT_MAX_BUFFERED_MESSAGES);
+    if max_buffered_messages == 0 || max_buffered_messages > MAX_BUFFERED_MESSAGES {
+        return Err(SearchError::InvalidArgument(format!(
+            "The maximum number of buffered messages must be between 1 and \
+             {MAX_BUFFERED_MESSAGES}."
+        )));
+    }
+    let live_tail_query = LiveTailQueryString {
+        query: live_tail_request.query,
+        start_timestamp: live_tail_request.start_timestamp,
+        poll_interval_ms: live_tail_request
+            .poll_interval_ms
+            .unwrap_or_else(LiveTailQueryString::default_poll_interval_ms),
+    };
+    let mut search_request =
+        live_tail_search_request(live_tail_request.index_id, &live_tail_query, metastore).await?;
+    search_request.max_hits = max_hits_per_message as u64;
+
+    let poll_interval = Duration::from_millis(live_tail_query.poll_interval_ms);
+    let publish_rx = split_publish_notifier.subscribe();
+    let (response_tx, response_rx) = mpsc::channel(max_buffered_messages as usize);
+    tokio::spawn(grpc_live_tail(
+        search_request,
+        poll_interval,
+        search_service,
+        publish_rx,
+        response_tx,
+    ));
+    Ok(ReceiverStream::new(response_rx))
+}
+
+async fn live_tail_endpoint(
+    index_id: String,
+    live_tail_query: LiveTailQueryString,
+    ws: Ws,
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+    split_publish_notifier: SplitPublishNotifier,
+) -> warp::reply::Response {
+    info!(index_id = %index_id, request =? live_tail_query, "live_tail");
+    let poll_interval = Duration::from_millis(live_tail_query.poll_interval_ms);
+    match live_tail_search_request(index_id, &live_tail_query, &*metastore).await {
+        Ok(search_request) => {
+            let publish_rx = split_publish_notifier.subscribe();
+            ws.on_upgrade(move |websocket| {
+                live_tail(
+                    websocket,
+                    search_request,
+                    poll_interval,
+                    search_service,
+                    publish_rx,
+                )
+            })
+            .into_response()
+        }
+        Err(search_error) => BodyFormat::default()
+            .make_rest_reply::<(), _>(Err(search_error))
+            .into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/tail/ws",
+    responses(
+        (status = 101, description = "Switched to the WebSocket protocol. The matching documents are pushed as JSON text messages as they get published.")
+    ),
+    params(
+        LiveTailQueryString,
+        ("index_id" = String, Path, description = "The index ID to tail."),
+    )
+)]
+/// Live Tail
+///
+/// Upgrades the connection to a WebSocket and pushes the documents matching the query as they get
+/// published, in timestamp order. The index is searched for the documents following the last pushed
+/// one as soon as splits get published, and at least every `poll_interval_ms`. The index must have
+/// a timestamp field.
+pub fn live_tail_handler(
+    search_service: Arc<dyn SearchService>,
+    metastore: Arc<dyn Metastore>,
+    split_publish_notifier: SplitPublishNotifier,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "tail" / "ws")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::ws())
+        .and(with_arg(search_service))
+        .and(with_arg(metastore))
+        .and(with_arg(split_publish_notifier))
+        .then(live_tail_endpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_metastore::{IndexMetadata, MockMetastore};
+    use quickwit_proto::{Hit, SearchResponse};
+    use quickwit_search::MockSearchService;
+    use serde_json::Value as JsonValue;
+
+    use super::*;
+    use crate::recover_fn;
+
+    fn hit(json: &str, doc_id: u32) -> Hit {
+        Hit {
+            json: json.to_string(),
+            partial_hit: Some(PartialHit {
+                split_id: "split-1".to_string(),
+                doc_id,
+                ..Default::default()
+            }),
+            snippet: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_tail_pushes_published_documents() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                Ok(IndexMetadata::for_test(index_id, "ram:///indexes/my-index"))
+            });
+        let mut search_service = MockSearchService::new();
+        let mut num_searches = 0;
+        search_service
+            .expect_root_search()
+            .returning(move |search_request: SearchRequest| {
+                assert_eq!(search_request.query, "severity:error");
+                assert_eq!(search_request.start_timestamp, Some(1_000));
+                assert_eq!(search_request.sort_by_field.as_deref(), Some("timestamp"));
+                num_searches += 1;
+                let hits = match num_searches {
+                    1 => {
+                        assert!(search_request.search_after.is_none());
+                        vec![hit(r#"{"id": 1}"#, 1), hit(r#"{"id": 2}"#, 2)]
+                    }
+                    _ => {
+                        let search_after = search_request.search_after.unwrap();
+                        assert_eq!(search_after.doc_id, 2);
+                        Vec::new()
+                    }
+                };
+                Ok(SearchResponse {
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let live_tail_handler = live_tail_handler(
+            Arc::new(search_service),
+            Arc::new(metastore),
+            SplitPublishNotifier::new(),
+        )
+        .recover(recover_fn);
+        let mut websocket = warp::test::ws()
+            .path(
+                "/my-index/tail/ws?query=severity:error&start_timestamp=1000&poll_interval_ms=100",
+            )
+            .handshake(live_tail_handler)
+            .await
+            .unwrap();
+        for expected_id in [1, 2] {
+            let message = websocket.recv().await.unwrap();
+            let document: JsonValue = serde_json::from_str(message.to_str().unwrap()).unwrap();
+            assert_eq!(document["id"], expected_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_live_tail_rejects_invalid_requests() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                let mut index_metadata =
+                    IndexMetadata::for_test(index_id, "ram:///indexes/my-index");
+                index_metadata.index_config.doc_mapping.timestamp_field = None;
+                Ok(index_metadata)
+            });
+        let live_tail_query: LiveTailQueryString = serde_qs::from_str("").unwrap();
+        assert_eq!(live_tail_query.poll_interval_ms, 1_000);
+        let error = live_tail_search_request("my-index".to_string(), &live_tail_query, &metastore)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("has no timestamp field"));
+
+        let live_tail_query: LiveTailQueryString =
+            serde_qs::from_str("poll_interval_ms=10").unwrap();
+        let error = live_tail_search_request("my-index".to_string(), &live_tail_query, &metastore)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("must be at least 100ms"));
+
+        let live_tail_handler = live_tail_handler(
+            Arc::new(MockSearchService::new()),
+            Arc::new(metastore),
+            SplitPublishNotifier::new(),
+        )
+        .recover(recover_fn);
+        warp::test::ws()
+            .path("/my-index/tail/ws")
+            .handshake(live_tail_handler)
+            .await
+            .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_live_tail_wakes_up_on_split_publication() {
+        let mut split_publish_notifier = SplitPublishNotifier::new();
+        let mut publish_rx = split_publish_notifier.subscribe();
+
+        let poll_interval = Duration::from_millis(100);
+        let now = tokio::time::Instant::now();
+        wait_for_new_splits(&mut publish_rx, "my-index", poll_interval).await;
+        assert!(now.elapsed() >= poll_interval);
+
+        for index_id in ["other-index", "my-index"] {
+            split_publish_notifier
+                .handle_event(PublishSplitsEvent {
+                    index_id: index_id.to_string(),
+                    split_ids: vec!["split-1".to_string()],
+                })
+                .await;
+        }
+        let poll_interval = Duration::from_secs(3_600);
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            wait_for_new_splits(&mut publish_rx, "my-index", poll_interval),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_grpc_live_tail_streams_pages_of_hits() {
+        let mut metastore = MockMetastore::new();
+        metastore
+            .expect_list_index_aliases()
+            .returning(|| Ok(Vec::new()));
+        metastore
+            .expect_index_metadata()
+            .returning(|index_id: &str| {
+                Ok(IndexMetadata::for_test(index_id, "ram:///indexes/my-index"))
+            });
+        let mut search_service = MockSearchService::new();
+        let mut num_searches = 0;
+        search_service
+            .expect_root_search()
+            .returning(move |search_request: SearchRequest| {
+                assert_eq!(search_request.query, "severity:error");
+                assert_eq!(search_request.max_hits, 2);
+                num_searches += 1;
+                let hits = match num_searches {
+                    1 => {
+                        assert!(search_request.search_after.is_none());
+                        vec![hit(r#"{"id": 1}"#, 1), hit(r#"{"id": 2}"#, 2)]
+                    }
+                    2 => {
+                        assert_eq!(search_request.search_after.unwrap().doc_id, 2);
+                        vec![hit(r#"{"id": 3}"#, 3)]
+                    }
+                    _ => {
+                        assert_eq!(search_request.search_after.unwrap().doc_id, 3);
+                        Vec::new()
+                    }
+                };
+                Ok(SearchResponse {
+                    hits,
+                    ..Default::default()
+                })
+            });
+        let live_tail_request = LiveTailRequest {
+            index_id: "my-index".to_string(),
+            query: Some("severity:error".to_string()),
+            start_timestamp: Some(1_000),
+            poll_interval_ms: Some(100),
+            max_hits_per_message: Some(2),
+            max_buffered_messages: Some(1),
+        };
+        let mut response_stream = start_grpc_live_tail(
+            live_tail_request,
+            Arc::new(search_service),
+            &metastore,
+            &SplitPublishNotifier::new(),
+        )
+        .await
+        .unwrap();
+        let response = response_stream.next().await.unwrap().unwrap();
+        assert_eq!(response.hits.len(), 2);
+        assert_eq!(response.hits[1].json, r#"{"id": 2}"#);
+
+        let response = response_stream.next().await.unwrap().unwrap();
+        assert_eq!(response.hits.len(), 1);
+        assert_eq!(response.hits[0].json, r#"{"id": 3}"#);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_live_tail_rejects_invalid_requests() {
+        let metastore = MockMetastore::new();
+        let search_service = Arc::new(MockSearchService::new());
+
+        let live_tail_request = LiveTailRequest {
+            index_id: "my-index".to_string(),
+            max_hits_per_message: Some(0),
+            ..Default::default()
+        };
+        let error = start_grpc_live_tail(
+            live_tail_request,
+            search_service.clone(),
+            &metastore,
+            &SplitPublishNotifier::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("hits per message"));
+
+        let live_tail_request = LiveTailRequest {
+            index_id: "my-index".to_string(),
+            max_buffered_messages: Some(100_000),
+            ..Default::default()
+        };
+        let error = start_grpc_live_tail(
+            live_tail_request,
+            search_service,
+            &metastore,
+            &SplitPublishNotifier::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("buffered messages"));
+    }
+}
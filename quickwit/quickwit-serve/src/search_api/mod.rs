@@ -18,12 +18,18 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 mod grpc_adapter;
+mod live_tail;
 mod rest_handler;
 
 pub use self::grpc_adapter::GrpcSearchAdapter;
+pub use self::live_tail::{live_tail_handler, LiveTailApi, SplitPublishNotifier};
 pub use self::rest_handler::{
-    search_get_handler, search_post_handler, search_stream_handler, SearchApi,
-    SearchRequestQueryString, SortByField,
+    count_handler, create_search_view_handler, delete_async_search_handler,
+    delete_search_view_handler, explain_handler, get_async_search_handler, list_terms_handler,
+    scroll_get_handler, scroll_post_handler, search_get_handler, search_hits_stream_get_handler,
+    search_hits_stream_post_handler, search_post_handler, search_request_from_query_string,
+    search_stream_handler, sql_handler, submit_async_search_handler, warmup_handler, SearchApi,
+    SearchRequestQueryString, SortByField, SqlRequest,
 };
 
 #[cfg(test)]
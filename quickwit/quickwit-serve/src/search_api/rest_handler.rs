@@ -20,12 +20,20 @@
 use std::convert::TryFrom;
 use std::sync::Arc;
 
+use byte_unit::Byte;
+use bytes::Bytes;
 use futures::stream::StreamExt;
 use hyper::header::HeaderValue;
 use hyper::HeaderMap;
 use quickwit_common::simple_list::{from_simple_list, to_simple_list};
-use quickwit_proto::{OutputFormat, ServiceError, SortOrder};
-use quickwit_search::{SearchError, SearchResponseRest, SearchService};
+use quickwit_proto::{
+    Hit, OutputFormat, ServiceError, SortOrder, SplitSearchError, WarmupRequest, WarmupResponse,
+};
+use quickwit_search::{
+    decode_search_after_cursor, prefix_key_range, sql_search, term_to_json, AsyncSearchResponse,
+    PrunedSplit, PruningReason, SearchError, SearchExplanation, SearchResponseRest, SearchService,
+    SearchViewResponse, SplitExplanation, SqlResponse,
+};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value as JsonValue;
 use tracing::info;
@@ -33,14 +41,51 @@ use warp::hyper::header::CONTENT_TYPE;
 use warp::hyper::StatusCode;
 use warp::{reply, Filter, Rejection, Reply};
 
+use crate::audit_log::with_result_count;
+use crate::format::{extract_format_from_qs, make_response};
+use crate::json_body::json_body;
 use crate::{with_arg, BodyFormat};
 
 #[derive(utoipa::OpenApi)]
 #[openapi(
-    paths(search_get_handler, search_post_handler, search_stream_handler,),
+    paths(
+        search_get_handler,
+        search_post_handler,
+        count_handler,
+        search_stream_handler,
+        search_hits_stream_get_handler,
+        search_hits_stream_post_handler,
+        scroll_get_handler,
+        scroll_post_handler,
+        submit_async_search_handler,
+        get_async_search_handler,
+        delete_async_search_handler,
+        create_search_view_handler,
+        delete_search_view_handler,
+        warmup_handler,
+        explain_handler,
+        list_terms_handler,
+        sql_handler,
+    ),
     components(schemas(
         SearchRequestQueryString,
+        ScrollRequestQueryString,
         SearchResponseRest,
+        CountResponseRest,
+        SplitSearchError,
+        AsyncSearchResponse,
+        SearchViewResponse,
+        WarmupRequestBody,
+        WarmupResponse,
+        SearchExplanation,
+        PrunedSplit,
+        PruningReason,
+        SplitExplanation,
+        ListTermsResponseRest,
+        TermDocCountRest,
+        TermsSortBy,
+        SqlRequest,
+        SqlResponse,
         SortByField,
         SortOrder,
         OutputFormat,
@@ -167,6 +212,98 @@ pub struct SearchRequestQueryString {
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sort_by_field: Option<SortByField>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// The sort fields JSON list, e.g. `[{"field": "severity"}, {"field": "timestamp", "order":
+    /// "asc", "missing": "first"}]`. The hits are sorted by the first field, and the ties are
+    /// broken by the following ones. A `geo_point` field with an `origin` sorts the hits by
+    /// distance. Cannot be set along with `sort_by_field`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<JsonValue>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// The collapse options JSON object, e.g. `{"field": "trace_id", "count": true}`. Only the
+    /// top hit of each distinct value of the field is returned, along with the number of hits
+    /// sharing its value if `count` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapse: Option<JsonValue>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// The geo filter JSON string, restricting the results to the documents with a `geo_point`
+    /// located within a bounding box, a distance, or a polygon.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_filter: Option<JsonValue>,
+    /// The nested query JSON string, restricting the results to the documents with at least
+    /// one element of a `nested` field matching the query on its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nested_query: Option<JsonValue>,
+    /// The runtime fields JSON object, mapping field names to expressions computed at query time
+    /// over fast fields, e.g. `{"duration_ms": "end - start"}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_fields: Option<JsonValue>,
+    /// Filter expression over fast fields and runtime fields, e.g. `duration_ms > 100`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_filter: Option<String>,
+    /// Cursor returned as `search_after` in the response of the previous page. Only the hits
+    /// ranked after that cursor are returned, so the query and the sort must remain the same.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_after: Option<String>,
+    /// If set, the searched splits are pinned for this duration (e.g. `1m`), and the response
+    /// contains a `scroll_id` to pass to the scroll API to fetch the next pages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<String>,
+    /// Maximum number of characters of the snippets (by default 150).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_max_num_chars: Option<u32>,
+    /// Tag inserted before the highlighted terms of the snippets (by default `<b>`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_pre_tag: Option<String>,
+    /// Tag inserted after the highlighted terms of the snippets (by default `</b>`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_post_tag: Option<String>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// The kNN query JSON string, e.g. `{"field": "embedding", "query_vector": [0.1, 0.2], "k":
+    /// 10}`. The hits are then the `k` nearest neighbors of the query vector among the
+    /// documents matching the query, sorted by decreasing similarity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub knn: Option<JsonValue>,
+    #[param(value_type = Object)]
+    #[schema(value_type = Object)]
+    /// The hybrid ranking JSON string, e.g. `{"method": "rrf"}`. If set, the hits of the query
+    /// and the nearest neighbors of the kNN query are fused into a single ranking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hybrid: Option<JsonValue>,
+    /// If set, the search also runs on the remote clusters configured on the searcher, and their
+    /// hits are merged with the hits of the local cluster.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cross_cluster: bool,
+    /// If set, the leaf searches still running after this many milliseconds are cancelled, and
+    /// the hits of the splits searched so far are returned with `timed_out` set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// If set, only the number of hits is computed: no hit is fetched and no aggregation is
+    /// computed.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub count_only: bool,
+    /// If set, the custom metadata of the split of each hit is returned along with the hits.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub include_split_metadata: bool,
+    /// If set, the search runs on the splits pinned by this search view, created with the search
+    /// view API, instead of the splits currently published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_id: Option<String>,
+}
+
+/// Parses a scroll duration, e.g. `30s` or `5m`, into a number of seconds.
+fn parse_scroll_ttl_secs(scroll: &str) -> Result<u32, SearchError> {
+    let scroll_ttl = humantime::parse_duration(scroll).map_err(|_| {
+        SearchError::InvalidArgument(format!("Invalid scroll duration `{scroll}`."))
+    })?;
+    Ok(scroll_ttl.as_secs().try_into().unwrap_or(u32::MAX))
 }
 
 fn get_proto_search_by(search_request: &SearchRequestQueryString) -> (Option<i32>, Option<String>) {
@@ -180,12 +317,22 @@ fn get_proto_search_by(search_request: &SearchRequestQueryString) -> (Option<i32
     }
 }
 
-async fn search_endpoint(
+/// Builds the search request of the REST API into the proto search request.
+pub fn search_request_from_query_string(
     index_id: String,
     search_request: SearchRequestQueryString,
-    search_service: &dyn SearchService,
-) -> Result<SearchResponseRest, SearchError> {
+) -> Result<quickwit_proto::SearchRequest, SearchError> {
     let (sort_order, sort_by_field) = get_proto_search_by(&search_request);
+    let search_after = search_request
+        .search_after
+        .as_deref()
+        .map(decode_search_after_cursor)
+        .transpose()?;
+    let scroll_ttl_secs = search_request
+        .scroll
+        .as_deref()
+        .map(parse_scroll_ttl_secs)
+        .transpose()?;
     let search_request = quickwit_proto::SearchRequest {
         index_id,
         query: search_request.query,
@@ -200,7 +347,70 @@ async fn search_endpoint(
             .map(|agg| serde_json::to_string(&agg).expect("could not serialize JsonValue")),
         sort_order,
         sort_by_field,
+        sort_fields: search_request.sort.map(|sort| match sort {
+            // The sort fields are passed as a JSON string in GET requests query strings.
+            JsonValue::String(sort_json) => sort_json,
+            sort => sort.to_string(),
+        }),
+        collapse: search_request.collapse.map(|collapse| match collapse {
+            // The collapse options are passed as a JSON string in GET requests query strings.
+            JsonValue::String(collapse_json) => collapse_json,
+            collapse => collapse.to_string(),
+        }),
+        geo_filter: search_request
+            .geo_filter
+            .map(|geo_filter| match geo_filter {
+                // The filter is passed as a JSON string in GET requests query strings.
+                JsonValue::String(geo_filter_json) => geo_filter_json,
+                geo_filter => geo_filter.to_string(),
+            }),
+        nested_query: search_request
+            .nested_query
+            .map(|nested_query| match nested_query {
+                // The nested query is passed as a JSON string in GET requests query strings.
+                JsonValue::String(nested_query_json) => nested_query_json,
+                nested_query => nested_query.to_string(),
+            }),
+        runtime_fields: search_request
+            .runtime_fields
+            .map(|runtime_fields| match runtime_fields {
+                // The runtime fields are passed as a JSON string in GET requests query strings.
+                JsonValue::String(runtime_fields_json) => runtime_fields_json,
+                runtime_fields => runtime_fields.to_string(),
+            }),
+        runtime_filter: search_request.runtime_filter,
+        search_after,
+        scroll_ttl_secs,
+        snippet_max_num_chars: search_request.snippet_max_num_chars,
+        snippet_pre_tag: search_request.snippet_pre_tag,
+        snippet_post_tag: search_request.snippet_post_tag,
+        knn_query: search_request.knn.map(|knn| match knn {
+            // The kNN query is passed as a JSON string in GET requests query strings.
+            JsonValue::String(knn_json) => knn_json,
+            knn => knn.to_string(),
+        }),
+        hybrid_ranking: search_request.hybrid.map(|hybrid| match hybrid {
+            // The hybrid ranking is passed as a JSON string in GET requests query strings.
+            JsonValue::String(hybrid_json) => hybrid_json,
+            hybrid => hybrid.to_string(),
+        }),
+        cross_cluster: search_request.cross_cluster,
+        timeout_ms: search_request.timeout_ms,
+        count_only: search_request.count_only,
+        include_split_metadata: search_request.include_split_metadata,
+        // The field masks are set by the search service from the roles of the caller.
+        field_masks: Vec::new(),
+        view_id: search_request.view_id,
     };
+    Ok(search_request)
+}
+
+async fn search_endpoint(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<SearchResponseRest, SearchError> {
+    let search_request = search_request_from_query_string(index_id, search_request)?;
     let search_response = search_service.root_search(search_request).await?;
     let search_response_rest = SearchResponseRest::try_from(search_response)?;
     Ok(search_response_rest)
@@ -218,7 +428,7 @@ fn search_post_filter(
     warp::path!(String / "search")
         .and(warp::post())
         .and(warp::body::content_length_limit(1024 * 1024))
-        .and(warp::body::json())
+        .and(json_body())
 }
 
 async fn search(
@@ -227,9 +437,13 @@ async fn search(
     search_service: Arc<dyn SearchService>,
 ) -> impl warp::Reply {
     info!(index_id = %index_id, request =? search_request, "search");
-    search_request
-        .format
-        .make_rest_reply(search_endpoint(index_id, search_request, &*search_service).await)
+    let format = search_request.format;
+    let search_result = search_endpoint(index_id, search_request, &*search_service).await;
+    let num_hits_opt = search_result
+        .as_ref()
+        .ok()
+        .map(|search_response| search_response.num_hits);
+    with_result_count(format.make_rest_reply(search_result), num_hits_opt)
 }
 
 #[utoipa::path(
@@ -280,453 +494,2157 @@ pub fn search_post_handler(
         .then(search)
 }
 
+/// Number of hits of a count-only search returned by the REST API.
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct CountResponseRest {
+    /// Number of documents matching the query.
+    pub num_hits: u64,
+    /// Elapsed time in microseconds.
+    pub elapsed_time_micros: u64,
+    /// Whether the search timed out, in which case the hits of the failed splits are not counted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// Splits that could not be searched.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_splits: Vec<SplitSearchError>,
+}
+
+async fn count_endpoint(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<CountResponseRest, SearchError> {
+    let mut search_request = search_request_from_query_string(index_id, search_request)?;
+    search_request.count_only = true;
+    let search_response = search_service.root_search(search_request).await?;
+    Ok(CountResponseRest {
+        num_hits: search_response.num_hits,
+        elapsed_time_micros: search_response.elapsed_time_micros,
+        timed_out: search_response.timed_out,
+        failed_splits: search_response.failed_splits,
+    })
+}
+
+async fn count(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, request =? search_request, "count");
+    let format = search_request.format;
+    let count_result = count_endpoint(index_id, search_request, &*search_service).await;
+    let num_hits_opt = count_result
+        .as_ref()
+        .ok()
+        .map(|count_response| count_response.num_hits);
+    with_result_count(format.make_rest_reply(count_result), num_hits_opt)
+}
+
 #[utoipa::path(
     get,
     tag = "Search",
-    path = "/{index_id}/search/stream",
+    path = "/{index_id}/search/count",
     responses(
-        (status = 200, description = "Successfully executed search.")
+        (status = 200, description = "Successfully counted hits.", body = CountResponseRest)
     ),
     params(
-        SearchStreamRequestQueryString,
+        SearchRequestQueryString,
         ("index_id" = String, Path, description = "The index ID to search."),
     )
 )]
-/// Stream Search Index
-pub fn search_stream_handler(
+/// Count Hits
+///
+/// Returns the number of documents matching the query, without fetching any hit nor computing
+/// any aggregation.
+pub fn count_handler(
     search_service: Arc<dyn SearchService>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    search_stream_filter()
+    warp::path!(String / "search" / "count")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
         .and(with_arg(search_service))
-        .then(search_stream)
+        .then(count)
 }
 
-/// This struct represents the search stream query passed to
-/// the REST API.
-#[derive(Deserialize, Debug, Eq, PartialEq, utoipa::IntoParams)]
+/// This struct represents the scroll request passed to the REST API.
+#[derive(
+    Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::IntoParams, utoipa::ToSchema,
+)]
 #[into_params(parameter_in = Query)]
 #[serde(deny_unknown_fields)]
-struct SearchStreamRequestQueryString {
-    /// Query text. The query language is that of tantivy.
-    pub query: String,
-    // Fields to search on.
-    #[param(rename = "search_field")]
-    #[serde(default)]
-    #[serde(rename(deserialize = "search_field"))]
-    #[serde(deserialize_with = "from_simple_list")]
-    pub search_fields: Option<Vec<String>>,
-    /// Fields to extract snippet on
-    #[serde(default)]
-    #[serde(rename(deserialize = "snippet_fields"))] // TODO: Was this supposed to be `snippet_field`? - CF
-    #[serde(deserialize_with = "from_simple_list")]
-    pub snippet_fields: Option<Vec<String>>,
-    /// If set, restricts search to documents with a `timestamp >= start_timestamp`.
-    pub start_timestamp: Option<i64>,
-    /// If set, restricts search to documents with a `timestamp < end_timestamp``.
-    pub end_timestamp: Option<i64>,
-    /// The fast field to extract.
-    #[serde(deserialize_with = "deserialize_not_empty_string")]
-    pub fast_field: String,
-    /// The requested output format.
-    #[serde(default)]
-    pub output_format: OutputFormat,
+pub struct ScrollRequestQueryString {
+    /// Scroll ID returned by the previous search or scroll call.
+    pub scroll_id: String,
+    /// If set, the searched splits are pinned for this duration (e.g. `1m`) instead of the
+    /// duration of the previous call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<String>,
+    /// The output format.
     #[serde(default)]
-    pub partition_by_field: Option<String>,
+    pub format: BodyFormat,
 }
 
-async fn search_stream_endpoint(
-    index_id: String,
-    search_request: SearchStreamRequestQueryString,
+async fn scroll_endpoint(
+    scroll_request: ScrollRequestQueryString,
     search_service: &dyn SearchService,
-) -> Result<hyper::Body, SearchError> {
-    let request = quickwit_proto::SearchStreamRequest {
-        index_id,
-        query: search_request.query,
-        search_fields: search_request.search_fields.unwrap_or_default(),
-        snippet_fields: search_request.snippet_fields.unwrap_or_default(),
-        start_timestamp: search_request.start_timestamp,
-        end_timestamp: search_request.end_timestamp,
-        fast_field: search_request.fast_field,
-        output_format: search_request.output_format as i32,
-        partition_by_field: search_request.partition_by_field,
+) -> Result<SearchResponseRest, SearchError> {
+    let scroll_ttl_secs = scroll_request
+        .scroll
+        .as_deref()
+        .map(parse_scroll_ttl_secs)
+        .transpose()?;
+    let scroll_request = quickwit_proto::ScrollRequest {
+        scroll_id: scroll_request.scroll_id,
+        scroll_ttl_secs,
     };
-    let mut data = search_service.root_search_stream(request).await?;
-    let (mut sender, body) = hyper::Body::channel();
-    tokio::spawn(async move {
-        while let Some(result) = data.next().await {
-            match result {
-                Ok(bytes) => {
-                    if sender.send_data(bytes).await.is_err() {
-                        sender.abort();
-                        break;
-                    }
-                }
-                Err(error) => {
-                    // Add trailer to signal to the client that there is an error. Only works
-                    // if the request is made with an http2 client that can read it... and
-                    // actually this seems pretty rare, for example `curl` will not show this
-                    // trailer. Thus we also call `sender.abort()` so that the
-                    // client will see something wrong happened. But he will
-                    // need to look at the logs to understand that.
-                    tracing::error!(error=?error, "Error when streaming search results.");
-                    let header_value_str =
-                        format!("Error when streaming search results: {error:?}.");
-                    let header_value = HeaderValue::from_str(header_value_str.as_str())
-                        .unwrap_or_else(|_| HeaderValue::from_static("Search stream error"));
-                    let mut trailers = HeaderMap::new();
-                    trailers.insert("X-Stream-Error", header_value);
-                    let _ = sender.send_trailers(trailers).await;
-                    sender.abort();
-                    break;
-                }
-            };
-        }
-    });
-    Ok(body)
+    let search_response = search_service.scroll(scroll_request).await?;
+    let search_response_rest = SearchResponseRest::try_from(search_response)?;
+    Ok(search_response_rest)
 }
 
-fn make_streaming_reply(result: Result<hyper::Body, SearchError>) -> impl Reply {
-    let status_code: StatusCode;
-    let body = match result {
-        Ok(body) => {
-            status_code = StatusCode::OK;
-            warp::reply::Response::new(body)
-        }
-        Err(err) => {
-            status_code = err.status_code().to_http_status_code();
-            warp::reply::Response::new(hyper::Body::from(err.to_string()))
-        }
-    };
-    reply::with_status(body, status_code)
+fn scroll_get_filter(
+) -> impl Filter<Extract = (ScrollRequestQueryString,), Error = Rejection> + Clone {
+    warp::path!("_search" / "scroll")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
 }
 
-async fn search_stream(
-    index_id: String,
-    request: SearchStreamRequestQueryString,
+fn scroll_post_filter(
+) -> impl Filter<Extract = (ScrollRequestQueryString,), Error = Rejection> + Clone {
+    warp::path!("_search" / "scroll")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+}
+
+async fn scroll(
+    scroll_request: ScrollRequestQueryString,
     search_service: Arc<dyn SearchService>,
 ) -> impl warp::Reply {
-    info!(index_id=%index_id,request=?request, "search_stream");
-    let content_type = match request.output_format {
-        OutputFormat::ClickHouseRowBinary => "application/octet-stream",
-        OutputFormat::Csv => "text/csv",
-    };
-    let reply =
-        make_streaming_reply(search_stream_endpoint(index_id, request, &*search_service).await);
-    reply::with_header(reply, CONTENT_TYPE, content_type)
+    info!(request =? scroll_request, "scroll");
+    scroll_request
+        .format
+        .make_rest_reply(scroll_endpoint(scroll_request, &*search_service).await)
 }
 
-fn search_stream_filter(
-) -> impl Filter<Extract = (String, SearchStreamRequestQueryString), Error = Rejection> + Clone {
-    warp::path!(String / "search" / "stream")
-        .and(warp::get())
-        .and(serde_qs::warp::query(serde_qs::Config::default()))
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/_search/scroll",
+    responses(
+        (status = 200, description = "Successfully fetched the next page.", body = SearchResponseRest)
+    ),
+    params(ScrollRequestQueryString)
+)]
+/// Scroll (GET Variant)
+///
+/// Returns the next page of hits of a search started with the `scroll` parameter.
+pub fn scroll_get_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    scroll_get_filter()
+        .and(with_arg(search_service))
+        .then(scroll)
 }
 
-#[cfg(test)]
-mod tests {
-    use assert_json_diff::{assert_json_eq, assert_json_include};
-    use bytes::Bytes;
-    use mockall::predicate;
-    use quickwit_search::{MockSearchService, SearchError};
-    use serde_json::{json, Value as JsonValue};
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/_search/scroll",
+    request_body = ScrollRequestQueryString,
+    responses(
+        (status = 200, description = "Successfully fetched the next page.", body = SearchResponseRest)
+    ),
+)]
+/// Scroll (POST Variant)
+///
+/// Returns the next page of hits of a search started with the `scroll` parameter.
+pub fn scroll_post_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    scroll_post_filter()
+        .and(with_arg(search_service))
+        .then(scroll)
+}
 
-    use super::*;
-    use crate::recover_fn;
+/// This struct represents the query string of the async search submission passed to the REST
+/// API.
+#[derive(Debug, Default, Eq, PartialEq, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct AsyncSearchQueryString {
+    /// Duration the search and its response are kept for (e.g. `12h`). Defaults to one day, at
+    /// most seven days.
+    pub keep_alive: Option<String>,
+}
 
-    fn search_handler(
-        mock_search_service: MockSearchService,
-    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-        let mock_search_service_in_arc = Arc::new(mock_search_service);
-        search_get_handler(mock_search_service_in_arc.clone())
-            .or(search_post_handler(mock_search_service_in_arc.clone()))
-            .or(search_stream_handler(mock_search_service_in_arc))
-            .recover(recover_fn)
-    }
+/// Parses a keep alive duration, e.g. `12h` or `2d`, into a number of seconds.
+fn parse_keep_alive_secs(keep_alive: &str) -> Result<u32, SearchError> {
+    let keep_alive_duration = humantime::parse_duration(keep_alive).map_err(|_| {
+        SearchError::InvalidArgument(format!("Invalid keep alive duration `{keep_alive}`."))
+    })?;
+    Ok(keep_alive_duration.as_secs().try_into().unwrap_or(u32::MAX))
+}
 
-    #[test]
-    fn test_serialize_search_response() -> anyhow::Result<()> {
-        let search_response = SearchResponseRest {
-            num_hits: 55,
-            hits: Vec::new(),
-            snippets: None,
-            elapsed_time_micros: 0u64,
-            errors: Vec::new(),
-            aggregations: None,
-        };
-        let search_response_json: JsonValue = serde_json::to_value(&search_response)?;
-        let expected_search_response_json: JsonValue = json!({
-            "num_hits": 55,
-            "hits": [],
-            "elapsed_time_micros": 0,
-        });
-        assert_json_include!(
-            actual: search_response_json,
-            expected: expected_search_response_json
-        );
-        Ok(())
-    }
+async fn submit_async_search_endpoint(
+    index_id: String,
+    async_search_query_string: AsyncSearchQueryString,
+    search_request: SearchRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<AsyncSearchResponse, SearchError> {
+    let keep_alive_secs = async_search_query_string
+        .keep_alive
+        .as_deref()
+        .map(parse_keep_alive_secs)
+        .transpose()?;
+    let search_request = search_request_from_query_string(index_id, search_request)?;
+    search_service
+        .submit_async_search(search_request, keep_alive_secs)
+        .await
+}
 
-    #[tokio::test]
+async fn submit_async_search(
+    index_id: String,
+    async_search_query_string: AsyncSearchQueryString,
+    search_request: SearchRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, request =? search_request, "submit-async-search");
+    let format = search_request.format;
+    format.make_rest_reply(
+        submit_async_search_endpoint(
+            index_id,
+            async_search_query_string,
+            search_request,
+            &*search_service,
+        )
+        .await,
+    )
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/async_search",
+    request_body = SearchRequestQueryString,
+    responses(
+        (status = 200, description = "Successfully submitted async search.", body = AsyncSearchResponse)
+    ),
+    params(
+        AsyncSearchQueryString,
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Submit Async Search
+///
+/// Starts a search in the background and returns its ID, to poll its progress and fetch its
+/// response later.
+pub fn submit_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "async_search")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+        .and(with_arg(search_service))
+        .then(submit_async_search)
+}
+
+async fn get_async_search(
+    index_id: String,
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+) -> Result<AsyncSearchResponse, SearchError> {
+    info!(index_id = %index_id, async_search_id = %async_search_id, "get-async-search");
+    search_service
+        .get_async_search(index_id, async_search_id)
+        .await
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/async_search/{async_search_id}",
+    responses(
+        (status = 200, description = "Successfully fetched async search.", body = AsyncSearchResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The searched index ID."),
+        ("async_search_id" = String, Path, description = "The async search ID."),
+    )
+)]
+/// Get Async Search
+///
+/// Returns the progress of an async search, and its response once it has completed.
+pub fn get_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "async_search" / String)
+        .and(warp::get())
+        .and(with_arg(search_service))
+        .then(get_async_search)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+async fn delete_async_search(
+    index_id: String,
+    async_search_id: String,
+    search_service: Arc<dyn SearchService>,
+) -> Result<(), SearchError> {
+    info!(index_id = %index_id, async_search_id = %async_search_id, "delete-async-search");
+    search_service
+        .delete_async_search(index_id, async_search_id)
+        .await
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Search",
+    path = "/{index_id}/async_search/{async_search_id}",
+    responses(
+        (status = 200, description = "Successfully deleted async search.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The searched index ID."),
+        ("async_search_id" = String, Path, description = "The async search ID."),
+    )
+)]
+/// Delete Async Search
+///
+/// Cancels an async search if it is running, and deletes its response.
+pub fn delete_async_search_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "async_search" / String)
+        .and(warp::delete())
+        .and(with_arg(search_service))
+        .then(delete_async_search)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+/// This struct represents the query string of the search view creation passed to the REST API.
+#[derive(Debug, Default, Eq, PartialEq, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchViewQueryString {
+    /// Duration the view is kept after its last use (e.g. `10m`). Defaults to five minutes, at
+    /// most one hour.
+    pub ttl: Option<String>,
+}
+
+/// Parses a search view TTL, e.g. `30s` or `10m`, into a number of seconds.
+fn parse_search_view_ttl_secs(ttl: &str) -> Result<u32, SearchError> {
+    let ttl_duration = humantime::parse_duration(ttl)
+        .map_err(|_| SearchError::InvalidArgument(format!("Invalid search view TTL `{ttl}`.")))?;
+    Ok(ttl_duration.as_secs().try_into().unwrap_or(u32::MAX))
+}
+
+async fn create_search_view(
+    index_id: String,
+    search_view_query_string: SearchViewQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> Result<SearchViewResponse, SearchError> {
+    info!(index_id = %index_id, "create-search-view");
+    let ttl_secs = search_view_query_string
+        .ttl
+        .as_deref()
+        .map(parse_search_view_ttl_secs)
+        .transpose()?;
+    search_service.create_search_view(index_id, ttl_secs).await
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/views",
+    responses(
+        (status = 200, description = "Successfully created search view.", body = SearchViewResponse)
+    ),
+    params(
+        SearchViewQueryString,
+        ("index_id" = String, Path, description = "The index ID of the view."),
+    )
+)]
+/// Create Search View
+///
+/// Pins the splits currently published in an index, so that the searches passing the returned
+/// `view_id` run against exactly these splits.
+pub fn create_search_view_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "views")
+        .and(warp::post())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .then(create_search_view)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+async fn delete_search_view(
+    index_id: String,
+    view_id: String,
+    search_service: Arc<dyn SearchService>,
+) -> Result<(), SearchError> {
+    info!(index_id = %index_id, view_id = %view_id, "delete-search-view");
+    search_service.delete_search_view(index_id, view_id).await
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Search",
+    path = "/{index_id}/views/{view_id}",
+    responses(
+        (status = 200, description = "Successfully deleted search view.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the view."),
+        ("view_id" = String, Path, description = "The search view ID."),
+    )
+)]
+/// Delete Search View
+///
+/// Deletes a search view, releasing the splits it pins.
+pub fn delete_search_view_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "views" / String)
+        .and(warp::delete())
+        .and(with_arg(search_service))
+        .then(delete_search_view)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+/// This struct represents the body of the warm-up requests passed to the REST API.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WarmupRequestBody {
+    /// IDs of the splits to warm up. If empty, the splits overlapping the time range are warmed
+    /// up.
+    #[serde(default)]
+    pub split_ids: Vec<String>,
+    /// If set, restrict the warm-up to the splits with `timestamp >= start_timestamp`.
+    pub start_timestamp: Option<i64>,
+    /// If set, restrict the warm-up to the splits with `timestamp < end_timestamp`.
+    pub end_timestamp: Option<i64>,
+    /// Fast fields to warm up, in addition to the timestamp field.
+    #[serde(default)]
+    pub fast_fields: Vec<String>,
+    /// Fields whose term dictionary is warmed up.
+    #[serde(default)]
+    pub term_dict_fields: Vec<String>,
+    /// Maximum number of bytes each searcher downloads (`10 GB`, ...). Unbounded by default.
+    #[schema(value_type = Option<String>)]
+    pub max_bytes: Option<Byte>,
+    /// Maximum download rate of each searcher, in bytes per second (`50 MB`, ...). Unbounded by
+    /// default.
+    #[schema(value_type = Option<String>)]
+    pub max_bytes_per_sec: Option<Byte>,
+}
+
+async fn warmup(
+    index_id: String,
+    warmup_request_body: WarmupRequestBody,
+    search_service: Arc<dyn SearchService>,
+) -> Result<WarmupResponse, SearchError> {
+    info!(index_id = %index_id, "warmup");
+    let warmup_request = WarmupRequest {
+        index_id,
+        split_ids: warmup_request_body.split_ids,
+        start_timestamp: warmup_request_body.start_timestamp,
+        end_timestamp: warmup_request_body.end_timestamp,
+        fast_field_names: warmup_request_body.fast_fields,
+        term_dict_field_names: warmup_request_body.term_dict_fields,
+        max_num_bytes: warmup_request_body
+            .max_bytes
+            .map(|max_bytes| max_bytes.get_bytes() as u64),
+        max_num_bytes_per_sec: warmup_request_body
+            .max_bytes_per_sec
+            .map(|max_bytes_per_sec| max_bytes_per_sec.get_bytes() as u64),
+    };
+    search_service.root_warmup(warmup_request).await
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/indexes/{index_id}/warmup",
+    request_body = WarmupRequestBody,
+    responses(
+        (status = 200, description = "Successfully warmed up splits.", body = WarmupResponse)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID of the splits to warm up."),
+    )
+)]
+/// Warm Up Splits
+///
+/// Downloads the data of the given splits, or of the splits overlapping a time range, into the
+/// caches of the searchers that will search them, within a download budget per searcher.
+pub fn warmup_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!("indexes" / String / "warmup")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+        .and(with_arg(search_service))
+        .then(warmup)
+        .and(extract_format_from_qs())
+        .map(make_response)
+}
+
+async fn explain(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, request =? search_request, "explain");
+    let format = search_request.format;
+    let explain_result = match search_request_from_query_string(index_id, search_request) {
+        Ok(search_request) => search_service.explain(search_request).await,
+        Err(search_error) => Err(search_error),
+    };
+    format.make_rest_reply(explain_result)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/explain",
+    request_body = SearchRequestQueryString,
+    responses(
+        (status = 200, description = "Successfully explained search.", body = SearchExplanation)
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Explain Search
+///
+/// Explains how a search is executed: the query as parsed against the doc mapping, the splits
+/// pruned without being searched and why, and the time spent searching each remaining split.
+pub fn explain_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "explain")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+        .and(with_arg(search_service))
+        .then(explain)
+}
+
+/// Order of the terms returned by the list terms REST API.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TermsSortBy {
+    /// Sorts the terms in lexicographic order.
+    #[default]
+    Term,
+    /// Sorts the terms by decreasing number of documents.
+    DocCount,
+}
+
+const DEFAULT_MAX_TERMS: u64 = 10;
+const MAX_TERMS_LIMIT: u64 = 10_000;
+
+fn default_max_terms() -> u64 {
+    DEFAULT_MAX_TERMS
+}
+
+/// This struct represents the list terms query string passed to the REST API.
+#[derive(Debug, Eq, PartialEq, Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+pub struct ListTermsQueryString {
+    /// If set, only the terms starting with this prefix are returned.
+    pub prefix: Option<String>,
+    /// If set, only the terms of the documents matching this query are returned, with the number
+    /// of matching documents of each term.
+    pub query: Option<String>,
+    /// If set, restrict the terms to the documents with a `timestamp >= start_timestamp`.
+    pub start_timestamp: Option<i64>,
+    /// If set, restrict the terms to the documents with a `timestamp < end_timestamp`.
+    pub end_timestamp: Option<i64>,
+    /// Maximum number of terms to return (by default 10, at most 10,000).
+    #[serde(default = "default_max_terms")]
+    pub max_terms: u64,
+    /// Order of the terms: `term` (default) or `doc_count`.
+    #[serde(default)]
+    pub sort_by: TermsSortBy,
+    /// The output format.
+    #[serde(default)]
+    pub format: BodyFormat,
+}
+
+/// A term of the list terms REST API, along with its number of documents.
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct TermDocCountRest {
+    /// Value of the term.
+    #[schema(value_type = Object)]
+    pub term: JsonValue,
+    /// Number of documents containing the term. If a query is set, only the documents matching
+    /// the query are counted.
+    pub doc_count: u64,
+}
+
+/// Terms returned by the list terms REST API.
+#[derive(Debug, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ListTermsResponseRest {
+    /// Terms of the field.
+    pub terms: Vec<TermDocCountRest>,
+    /// Elapsed time in microseconds.
+    pub elapsed_time_micros: u64,
+}
+
+async fn list_terms_endpoint(
+    index_id: String,
+    field: String,
+    list_terms_query_string: ListTermsQueryString,
+    search_service: &dyn SearchService,
+) -> Result<ListTermsResponseRest, SearchError> {
+    if list_terms_query_string.max_terms > MAX_TERMS_LIMIT {
+        return Err(SearchError::InvalidArgument(format!(
+            "`max_terms` cannot exceed {MAX_TERMS_LIMIT}."
+        )));
+    }
+    let (start_key, end_key) = match list_terms_query_string.prefix.as_deref() {
+        Some(prefix) => prefix_key_range(prefix.as_bytes()),
+        None => (Vec::new(), None),
+    };
+    let list_terms_request = quickwit_proto::ListTermsRequest {
+        index_id,
+        field,
+        start_timestamp: list_terms_query_string.start_timestamp,
+        end_timestamp: list_terms_query_string.end_timestamp,
+        max_hits: Some(list_terms_query_string.max_terms),
+        start_key: Some(start_key).filter(|start_key| !start_key.is_empty()),
+        end_key,
+        query: list_terms_query_string.query,
+        sort_by_doc_count: list_terms_query_string.sort_by == TermsSortBy::DocCount,
+    };
+    let list_terms_response = search_service.root_list_terms(list_terms_request).await?;
+    let terms = list_terms_response
+        .terms
+        .iter()
+        .zip(list_terms_response.doc_counts)
+        .map(|(term, doc_count)| TermDocCountRest {
+            term: term_to_json(term),
+            doc_count,
+        })
+        .collect();
+    Ok(ListTermsResponseRest {
+        terms,
+        elapsed_time_micros: list_terms_response.elapsed_time_micros,
+    })
+}
+
+async fn list_terms(
+    index_id: String,
+    field: String,
+    list_terms_query_string: ListTermsQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, field = %field, request =? list_terms_query_string, "list-terms");
+    let format = list_terms_query_string.format;
+    format.make_rest_reply(
+        list_terms_endpoint(index_id, field, list_terms_query_string, &*search_service).await,
+    )
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/terms/{field}",
+    responses(
+        (status = 200, description = "Successfully listed the terms.", body = ListTermsResponseRest)
+    ),
+    params(
+        ListTermsQueryString,
+        ("index_id" = String, Path, description = "The index ID."),
+        ("field" = String, Path, description = "The indexed field whose terms are listed."),
+    )
+)]
+/// List Field Terms
+///
+/// Returns the first terms of an indexed field, or its terms with the most documents, optionally
+/// restricted to a prefix and to the documents matching a query.
+pub fn list_terms_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    warp::path!(String / "terms" / String)
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+        .and(with_arg(search_service))
+        .then(list_terms)
+}
+
+/// This struct represents the SQL request passed to the REST API.
+#[derive(Debug, Default, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SqlRequest {
+    /// SQL query, e.g. `SELECT service, COUNT(*) FROM logs GROUP BY service`.
+    pub query: String,
+    /// The output format.
+    #[serde(default)]
+    pub format: BodyFormat,
+}
+
+fn sql_filter() -> impl Filter<Extract = (SqlRequest,), Error = Rejection> + Clone {
+    warp::path!("_sql")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+}
+
+async fn sql(sql_request: SqlRequest, search_service: Arc<dyn SearchService>) -> impl warp::Reply {
+    info!(request =? sql_request, "sql");
+    sql_request
+        .format
+        .make_rest_reply(sql_search(&sql_request.query, &*search_service).await)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/_sql",
+    request_body = SqlRequest,
+    responses(
+        (status = 200, description = "Successfully executed the SQL query.", body = SqlResponse)
+    ),
+)]
+/// SQL Query
+///
+/// Translates a SQL query over one index into a search, returning the result as rows.
+pub fn sql_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    sql_filter().and(with_arg(search_service)).then(sql)
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/search/stream",
+    responses(
+        (status = 200, description = "Successfully executed search.")
+    ),
+    params(
+        SearchStreamRequestQueryString,
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Stream Search Index
+pub fn search_stream_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    search_stream_filter()
+        .and(with_arg(search_service))
+        .then(search_stream)
+}
+
+/// This struct represents the search stream query passed to
+/// the REST API.
+#[derive(Deserialize, Debug, Eq, PartialEq, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(deny_unknown_fields)]
+struct SearchStreamRequestQueryString {
+    /// Query text. The query language is that of tantivy.
+    pub query: String,
+    // Fields to search on.
+    #[param(rename = "search_field")]
+    #[serde(default)]
+    #[serde(rename(deserialize = "search_field"))]
+    #[serde(deserialize_with = "from_simple_list")]
+    pub search_fields: Option<Vec<String>>,
+    /// Fields to extract snippet on
+    #[serde(default)]
+    #[serde(rename(deserialize = "snippet_fields"))] // TODO: Was this supposed to be `snippet_field`? - CF
+    #[serde(deserialize_with = "from_simple_list")]
+    pub snippet_fields: Option<Vec<String>>,
+    /// If set, restricts search to documents with a `timestamp >= start_timestamp`.
+    pub start_timestamp: Option<i64>,
+    /// If set, restricts search to documents with a `timestamp < end_timestamp``.
+    pub end_timestamp: Option<i64>,
+    /// The fast field to extract.
+    #[serde(deserialize_with = "deserialize_not_empty_string")]
+    pub fast_field: String,
+    /// The requested output format.
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub partition_by_field: Option<String>,
+}
+
+async fn search_stream_endpoint(
+    index_id: String,
+    search_request: SearchStreamRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<hyper::Body, SearchError> {
+    let request = quickwit_proto::SearchStreamRequest {
+        index_id,
+        query: search_request.query,
+        search_fields: search_request.search_fields.unwrap_or_default(),
+        snippet_fields: search_request.snippet_fields.unwrap_or_default(),
+        start_timestamp: search_request.start_timestamp,
+        end_timestamp: search_request.end_timestamp,
+        fast_field: search_request.fast_field,
+        output_format: search_request.output_format as i32,
+        partition_by_field: search_request.partition_by_field,
+    };
+    let mut data = search_service.root_search_stream(request).await?;
+    let (mut sender, body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        while let Some(result) = data.next().await {
+            match result {
+                Ok(bytes) => {
+                    if sender.send_data(bytes).await.is_err() {
+                        sender.abort();
+                        break;
+                    }
+                }
+                Err(error) => {
+                    // Add trailer to signal to the client that there is an error. Only works
+                    // if the request is made with an http2 client that can read it... and
+                    // actually this seems pretty rare, for example `curl` will not show this
+                    // trailer. Thus we also call `sender.abort()` so that the
+                    // client will see something wrong happened. But he will
+                    // need to look at the logs to understand that.
+                    tracing::error!(error=?error, "Error when streaming search results.");
+                    let header_value_str =
+                        format!("Error when streaming search results: {error:?}.");
+                    let header_value = HeaderValue::from_str(header_value_str.as_str())
+                        .unwrap_or_else(|_| HeaderValue::from_static("Search stream error"));
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("X-Stream-Error", header_value);
+                    let _ = sender.send_trailers(trailers).await;
+                    sender.abort();
+                    break;
+                }
+            };
+        }
+    });
+    Ok(body)
+}
+
+fn make_streaming_reply(result: Result<hyper::Body, SearchError>) -> impl Reply {
+    let status_code: StatusCode;
+    let body = match result {
+        Ok(body) => {
+            status_code = StatusCode::OK;
+            warp::reply::Response::new(body)
+        }
+        Err(err) => {
+            status_code = err.status_code().to_http_status_code();
+            warp::reply::Response::new(hyper::Body::from(err.to_string()))
+        }
+    };
+    reply::with_status(body, status_code)
+}
+
+async fn search_stream(
+    index_id: String,
+    request: SearchStreamRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id=%index_id,request=?request, "search_stream");
+    let content_type = match request.output_format {
+        OutputFormat::ClickHouseRowBinary => "application/octet-stream",
+        OutputFormat::Csv => "text/csv",
+    };
+    let reply =
+        make_streaming_reply(search_stream_endpoint(index_id, request, &*search_service).await);
+    reply::with_header(reply, CONTENT_TYPE, content_type)
+}
+
+fn search_stream_filter(
+) -> impl Filter<Extract = (String, SearchStreamRequestQueryString), Error = Rejection> + Clone {
+    warp::path!(String / "search" / "stream")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+}
+
+/// Serializes the documents of the hits as newline-delimited JSON.
+fn hits_to_ndjson(hits: Vec<Hit>) -> Result<Bytes, SearchError> {
+    let mut ndjson = Vec::new();
+    for hit in hits {
+        let document: JsonValue = serde_json::from_str(&hit.json).map_err(|err| {
+            SearchError::InternalError(format!("Failed to deserialize document: `{err}`."))
+        })?;
+        serde_json::to_writer(&mut ndjson, &document)
+            .expect("Serializing a JSON value should never fail.");
+        ndjson.push(b'\n');
+    }
+    Ok(Bytes::from(ndjson))
+}
+
+async fn search_hits_stream_endpoint(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: &dyn SearchService,
+) -> Result<hyper::Body, SearchError> {
+    let search_request = search_request_from_query_string(index_id, search_request)?;
+    if !search_request.snippet_fields.is_empty() {
+        return Err(SearchError::InvalidArgument(
+            "Snippets are not supported when streaming hits.".to_string(),
+        ));
+    }
+    let hits_stream = search_service
+        .root_search_hits_stream(search_request)
+        .await?;
+    // The body is aborted on error, so that the client sees the response is truncated.
+    let ndjson_stream = hits_stream.map(|chunk_result| {
+        let chunk = chunk_result.map_err(|error| {
+            tracing::error!(error=?error, "Error when streaming search hits.");
+            error
+        })?;
+        hits_to_ndjson(chunk.hits)
+    });
+    Ok(hyper::Body::wrap_stream(ndjson_stream))
+}
+
+async fn search_hits_stream(
+    index_id: String,
+    search_request: SearchRequestQueryString,
+    search_service: Arc<dyn SearchService>,
+) -> impl warp::Reply {
+    info!(index_id = %index_id, request =? search_request, "search_hits_stream");
+    let reply = make_streaming_reply(
+        search_hits_stream_endpoint(index_id, search_request, &*search_service).await,
+    );
+    reply::with_header(reply, CONTENT_TYPE, "application/x-ndjson")
+}
+
+fn search_hits_stream_get_filter(
+) -> impl Filter<Extract = (String, SearchRequestQueryString), Error = Rejection> + Clone {
+    warp::path!(String / "search" / "hits-stream")
+        .and(warp::get())
+        .and(serde_qs::warp::query(serde_qs::Config::default()))
+}
+
+fn search_hits_stream_post_filter(
+) -> impl Filter<Extract = (String, SearchRequestQueryString), Error = Rejection> + Clone {
+    warp::path!(String / "search" / "hits-stream")
+        .and(warp::post())
+        .and(warp::body::content_length_limit(1024 * 1024))
+        .and(json_body())
+}
+
+#[utoipa::path(
+    get,
+    tag = "Search",
+    path = "/{index_id}/search/hits-stream",
+    responses(
+        (status = 200, description = "Successfully streamed the hits as newline-delimited JSON.")
+    ),
+    params(
+        SearchRequestQueryString,
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Stream Search Hits (GET Variant)
+///
+/// Streams the documents of the hits as newline-delimited JSON, as soon as the leaves return
+/// them. Documents are sorted within the hits of a leaf, but not across leaves.
+pub fn search_hits_stream_get_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    search_hits_stream_get_filter()
+        .and(with_arg(search_service))
+        .then(search_hits_stream)
+}
+
+#[utoipa::path(
+    post,
+    tag = "Search",
+    path = "/{index_id}/search/hits-stream",
+    request_body = SearchRequestQueryString,
+    responses(
+        (status = 200, description = "Successfully streamed the hits as newline-delimited JSON.")
+    ),
+    params(
+        ("index_id" = String, Path, description = "The index ID to search."),
+    )
+)]
+/// Stream Search Hits (POST Variant)
+///
+/// Streams the documents of the hits as newline-delimited JSON, parsing the search request from
+/// the request body.
+pub fn search_hits_stream_post_handler(
+    search_service: Arc<dyn SearchService>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+    search_hits_stream_post_filter()
+        .and(with_arg(search_service))
+        .then(search_hits_stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_json_diff::{assert_json_eq, assert_json_include};
+    use mockall::predicate;
+    use quickwit_search::{encode_term_for_test, MockSearchService, SearchError};
+    use serde_json::{json, Value as JsonValue};
+
+    use super::*;
+    use crate::recover_fn;
+
+    fn search_handler(
+        mock_search_service: MockSearchService,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
+        let mock_search_service_in_arc = Arc::new(mock_search_service);
+        search_get_handler(mock_search_service_in_arc.clone())
+            .or(search_post_handler(mock_search_service_in_arc.clone()))
+            .or(count_handler(mock_search_service_in_arc.clone()))
+            .or(search_stream_handler(mock_search_service_in_arc.clone()))
+            .or(search_hits_stream_get_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(search_hits_stream_post_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(scroll_get_handler(mock_search_service_in_arc.clone()))
+            .or(scroll_post_handler(mock_search_service_in_arc.clone()))
+            .or(submit_async_search_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(get_async_search_handler(mock_search_service_in_arc.clone()))
+            .or(delete_async_search_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(create_search_view_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(delete_search_view_handler(
+                mock_search_service_in_arc.clone(),
+            ))
+            .or(warmup_handler(mock_search_service_in_arc.clone()))
+            .or(explain_handler(mock_search_service_in_arc.clone()))
+            .or(list_terms_handler(mock_search_service_in_arc.clone()))
+            .or(sql_handler(mock_search_service_in_arc))
+            .recover(recover_fn)
+    }
+
+    #[test]
+    fn test_serialize_search_response() -> anyhow::Result<()> {
+        let search_response = SearchResponseRest {
+            num_hits: 55,
+            hits: Vec::new(),
+            snippets: None,
+            num_collapsed_hits: None,
+            split_metadata: None,
+            elapsed_time_micros: 0u64,
+            errors: Vec::new(),
+            aggregations: None,
+            search_after: None,
+            scroll_id: None,
+            timed_out: false,
+            failed_splits: Vec::new(),
+        };
+        let search_response_json: JsonValue = serde_json::to_value(&search_response)?;
+        let expected_search_response_json: JsonValue = json!({
+            "num_hits": 55,
+            "hits": [],
+            "elapsed_time_micros": 0,
+        });
+        assert_json_include!(
+            actual: search_response_json,
+            expected: expected_search_response_json
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
     async fn test_rest_search_api_route_post() {
         let rest_search_api_filter = search_post_filter();
         let (index, req) = warp::test::request()
             .method("POST")
             .path("/quickwit-demo-index/search?query=*&max_hits=10")
             .json(&true)
-            .body(r#"{"query": "*", "max_hits":10, "aggs": {"range":[]} }"#)
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(&index, "quickwit-demo-index");
+            .body(r#"{"query": "*", "max_hits":10, "aggs": {"range":[]} }"#)
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(&index, "quickwit-demo-index");
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                search_fields: None,
+                start_timestamp: None,
+                max_hits: 10,
+                format: BodyFormat::default(),
+                sort_by_field: None,
+                aggs: Some(json!({"range":[]})),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_simple() {
+        let rest_search_api_filter = search_get_filter();
+        let (index, req) = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&end_timestamp=1450720000&max_hits=10&\
+                 start_offset=22",
+            )
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(&index, "quickwit-demo-index");
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                search_fields: None,
+                start_timestamp: None,
+                end_timestamp: Some(1450720000),
+                max_hits: 10,
+                start_offset: 22,
+                format: BodyFormat::default(),
+                sort_by_field: None,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_simple_default_num_hits_default_offset() {
+        let rest_search_api_filter = search_get_filter();
+        let (index, req) = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&end_timestamp=1450720000&search_field=title,\
+                 body",
+            )
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(&index, "quickwit-demo-index");
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                search_fields: Some(vec!["title".to_string(), "body".to_string()]),
+                start_timestamp: None,
+                end_timestamp: Some(1450720000),
+                max_hits: 20,
+                start_offset: 0,
+                format: BodyFormat::default(),
+                sort_by_field: None,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_simple_format() {
+        let rest_search_api_filter = search_get_filter();
+        let (index, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&format=json")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(&index, "quickwit-demo-index");
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                start_timestamp: None,
+                end_timestamp: None,
+                max_hits: 20,
+                start_offset: 0,
+                format: BodyFormat::Json,
+                search_fields: None,
+                sort_by_field: None,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_sort_by() {
+        let rest_search_api_filter = search_get_filter();
+        let (_, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=field")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                start_timestamp: None,
+                end_timestamp: None,
+                max_hits: 20,
+                start_offset: 0,
+                format: BodyFormat::Json,
+                search_fields: None,
+                sort_by_field: Some(SortByField {
+                    field_name: "field".to_string(),
+                    order: SortOrder::Asc
+                }),
+                ..Default::default()
+            }
+        );
+
+        let rest_search_api_filter = search_get_filter();
+        let (_, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=+field")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            &req,
+            &super::SearchRequestQueryString {
+                query: "*".to_string(),
+                start_timestamp: None,
+                end_timestamp: None,
+                max_hits: 20,
+                start_offset: 0,
+                format: BodyFormat::Json,
+                search_fields: None,
+                sort_by_field: Some(SortByField {
+                    field_name: "field".to_string(),
+                    order: SortOrder::Asc
+                }),
+                ..Default::default()
+            }
+        );
+
+        let rest_search_api_filter = search_get_filter();
+        let (_, req) = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=-field")
+            .filter(&rest_search_api_filter)
+            .await
+            .unwrap();
+        assert_eq!(
+            &req,
+            &SearchRequestQueryString {
+                query: "*".to_string(),
+                start_timestamp: None,
+                end_timestamp: None,
+                max_hits: 20,
+                start_offset: 0,
+                format: BodyFormat::Json,
+                search_fields: None,
+                sort_by_field: Some(SortByField {
+                    field_name: "field".to_string(),
+                    order: SortOrder::Desc
+                }),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_invalid_key() -> anyhow::Result<()> {
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&end_unix_timestamp=1450720000")
+            .reply(&search_handler(MockSearchService::new()))
+            .await;
+        assert_eq!(resp.status(), 400);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let exp_resp_json = serde_json::json!({
+            "message": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`, `sort`, `collapse`, `geo_filter`, `nested_query`, `runtime_fields`, `runtime_filter`, `search_after`, `scroll`, `snippet_max_num_chars`, `snippet_pre_tag`, `snippet_post_tag`, `knn`, `hybrid`, `cross_cluster`, `timeout_ms`, `count_only`, `include_split_metadata`, `view_id`"
+        });
+        assert_eq!(resp_json, exp_resp_json);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_post_with_invalid_payload() -> anyhow::Result<()> {
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search?query=*&max_hits=10")
+            .json(&true)
+            .body(r#"{"query": "*", "bad_param":10, "aggs": {"range":[]} }"#)
+            .reply(&search_handler(MockSearchService::new()))
+            .await;
+        assert_eq!(resp.status(), 400);
+        let content = String::from_utf8_lossy(resp.body());
+        assert!(content
+            .contains("Request body deserialize error at `bad_param`: unknown field `bad_param`"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_route_serialize_with_results() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service.expect_root_search().returning(|_| {
+            Ok(quickwit_proto::SearchResponse {
+                hits: Vec::new(),
+                num_hits: 10,
+                elapsed_time_micros: 16,
+                errors: vec![],
+                ..Default::default()
+            })
+        });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        let expected_response_json = serde_json::json!({
+            "num_hits": 10,
+            "hits": [],
+            "elapsed_time_micros": 16,
+        });
+        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_start_offset_and_num_hits_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.start_offset == 5 && search_request.max_hits == 30
+                },
+            ))
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        assert_eq!(
+            warp::test::request()
+                .path("/quickwit-demo-index/search?query=*&start_offset=5&max_hits=30")
+                .reply(&rest_search_api_handler)
+                .await
+                .status(),
+            200
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_geo_filter_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let geo_filter: JsonValue =
+                        serde_json::from_str(search_request.geo_filter.as_ref().unwrap()).unwrap();
+                    geo_filter
+                        == json!({
+                            "field": "location",
+                            "distance": {"center": "48.85,2.35", "radius_meters": 1000}
+                        })
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let geo_filter =
+            r#"{"field":"location","distance":{"center":"48.85,2.35","radius_meters":1000}}"#;
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&geo_filter=%7B%22field%22%3A%22location%22%2C%22distance%22%3A%7B%22center%22%3A%2248.85%2C2.35%22%2C%22radius_meters%22%3A1000%7D%7D",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(format!(r#"{{"query": "*", "geo_filter": {geo_filter}}}"#))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_knn_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let knn_query: JsonValue =
+                        serde_json::from_str(search_request.knn_query.as_ref().unwrap()).unwrap();
+                    knn_query == json!({"field": "embedding", "query_vector": [0.5, 1.0], "k": 3})
+                },
+            ))
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(
+                r#"{"query": "*", "knn": {"field": "embedding", "query_vector": [0.5, 1.0], "k": 3}}"#,
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_sort_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let sort_fields: JsonValue =
+                        serde_json::from_str(search_request.sort_fields.as_ref().unwrap()).unwrap();
+                    sort_fields == json!([{"field": "severity"}, {"field": "ts", "order": "asc"}])
+                        && search_request.sort_by_field.is_none()
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(r#"{"query": "*", "sort": [{"field": "severity"}, {"field": "ts", "order": "asc"}]}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&sort=%5B%7B%22field%22%3A%22severity%22%7D%2C%7B%22field%22%3A%22ts%22%2C%22order%22%3A%22asc%22%7D%5D",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_collapse_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let collapse: JsonValue =
+                        serde_json::from_str(search_request.collapse.as_ref().unwrap()).unwrap();
+                    collapse == json!({"field": "host", "count": true})
+                },
+            ))
+            .times(1)
+            .returning(|_| {
+                let partial_hit = quickwit_proto::PartialHit {
+                    collapse_value: Some(r#""web-1""#.to_string()),
+                    num_collapsed_hits: Some(3),
+                    ..Default::default()
+                };
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 5,
+                    hits: vec![quickwit_proto::Hit {
+                        json: r#"{"host": "web-1"}"#.to_string(),
+                        partial_hit: Some(partial_hit),
+                        snippet: None,
+                    }],
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(r#"{"query": "*", "collapse": {"field": "host", "count": true}}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(resp_json["hits"], json!([{"host": "web-1"}]));
+        assert_eq!(resp_json["num_collapsed_hits"], json!([3]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_hybrid_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let hybrid_ranking: JsonValue =
+                        serde_json::from_str(search_request.hybrid_ranking.as_ref().unwrap())
+                            .unwrap();
+                    hybrid_ranking == json!({"method": "weighted_sum", "vector_boost": 2.0})
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(
+                r#"{"query": "body:quickwit", "knn": {"field": "embedding", "query_vector": [0.5, 1.0], "k": 3}, "hybrid": {"method": "weighted_sum", "vector_boost": 2.0}}"#,
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=body:quickwit&hybrid=%7B%22method%22%3A%22weighted_sum%22%2C%22vector_boost%22%3A2.0%7D",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_cross_cluster_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| search_request.cross_cluster,
+            ))
+            .times(1)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&cross_cluster=true")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_timeout_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.timeout_ms == Some(500)
+                },
+            ))
+            .times(1)
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 1,
+                    timed_out: true,
+                    failed_splits: vec![SplitSearchError {
+                        error: "Leaf search timed out.".to_string(),
+                        split_id: "split_2".to_string(),
+                        retryable_error: false,
+                    }],
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&timeout_ms=500")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(resp_json["timed_out"], true);
+        assert_eq!(resp_json["failed_splits"][0]["split_id"], "split_2");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_count_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.count_only
+                        && search_request.query == "*"
+                        && search_request.start_timestamp == Some(100)
+                },
+            ))
+            .times(1)
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 42,
+                    elapsed_time_micros: 10,
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search/count?query=*&start_timestamp=100")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
         assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                search_fields: None,
-                start_timestamp: None,
-                max_hits: 10,
-                format: BodyFormat::default(),
-                sort_by_field: None,
-                aggs: Some(json!({"range":[]})),
-                ..Default::default()
-            }
+            resp_json,
+            json!({
+                "num_hits": 42,
+                "elapsed_time_micros": 10,
+            })
         );
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_simple() {
-        let rest_search_api_filter = search_get_filter();
-        let (index, req) = warp::test::request()
+    async fn test_rest_list_terms_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_list_terms()
+            .with(predicate::function(
+                |list_terms_request: &quickwit_proto::ListTermsRequest| {
+                    list_terms_request.index_id == "quickwit-demo-index"
+                        && list_terms_request.field == "service"
+                        && list_terms_request.start_key.as_deref() == Some(b"api".as_slice())
+                        && list_terms_request.end_key.as_deref() == Some(b"apj".as_slice())
+                        && list_terms_request.query.as_deref() == Some("severity:ERROR")
+                        && list_terms_request.max_hits == Some(5)
+                        && list_terms_request.sort_by_doc_count
+                },
+            ))
+            .times(1)
+            .returning(|_| {
+                Ok(quickwit_proto::ListTermsResponse {
+                    num_hits: 2,
+                    terms: vec![
+                        encode_term_for_test!("api-gateway"),
+                        encode_term_for_test!("api-users"),
+                    ],
+                    elapsed_time_micros: 10,
+                    errors: Vec::new(),
+                    doc_counts: vec![12, 3],
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
             .path(
-                "/quickwit-demo-index/search?query=*&end_timestamp=1450720000&max_hits=10&\
-                 start_offset=22",
+                "/quickwit-demo-index/terms/service?prefix=api&query=severity:ERROR&max_terms=5&\
+                 sort_by=doc_count",
             )
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(&index, "quickwit-demo-index");
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
         assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                search_fields: None,
-                start_timestamp: None,
-                end_timestamp: Some(1450720000),
-                max_hits: 10,
-                start_offset: 22,
-                format: BodyFormat::default(),
-                sort_by_field: None,
-                ..Default::default()
-            }
+            resp_json,
+            json!({
+                "terms": [
+                    {"term": "api-gateway", "doc_count": 12},
+                    {"term": "api-users", "doc_count": 3},
+                ],
+                "elapsed_time_micros": 10,
+            })
         );
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/terms/service?max_terms=20000")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_simple_default_num_hits_default_offset() {
-        let rest_search_api_filter = search_get_filter();
-        let (index, req) = warp::test::request()
+    async fn test_rest_search_api_nested_query_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let nested_query: JsonValue =
+                        serde_json::from_str(search_request.nested_query.as_ref().unwrap())
+                            .unwrap();
+                    nested_query == json!({"path": "spans", "query": "spans.name:foo"})
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
             .path(
-                "/quickwit-demo-index/search?query=*&end_timestamp=1450720000&search_field=title,\
-                 body",
+                "/quickwit-demo-index/search?query=*&nested_query=%7B%22path%22%3A%22spans%22%2C%22query%22%3A%22spans.name%3Afoo%22%7D",
             )
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(&index, "quickwit-demo-index");
-        assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                search_fields: Some(vec!["title".to_string(), "body".to_string()]),
-                start_timestamp: None,
-                end_timestamp: Some(1450720000),
-                max_hits: 20,
-                start_offset: 0,
-                format: BodyFormat::default(),
-                sort_by_field: None,
-                ..Default::default()
-            }
-        );
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(r#"{"query": "*", "nested_query": {"path": "spans", "query": "spans.name:foo"}}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_runtime_fields_parameters() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    let runtime_fields: JsonValue =
+                        serde_json::from_str(search_request.runtime_fields.as_ref().unwrap())
+                            .unwrap();
+                    runtime_fields == json!({"duration_ms": "end - start"})
+                        && search_request.runtime_filter.as_deref() == Some("duration_ms > 100")
+                },
+            ))
+            .times(2)
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&runtime_fields=%7B%22duration_ms%22%3A%22end%20-%20start%22%7D&runtime_filter=duration_ms%20%3E%20100",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/search")
+            .json(&true)
+            .body(r#"{"query": "*", "runtime_fields": {"duration_ms": "end - start"}, "runtime_filter": "duration_ms > 100"}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_search_after_parameter() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.search_after
+                        == Some(quickwit_proto::PartialHit {
+                            sorting_field_value: 1678000000,
+                            split_id: "split_1".to_string(),
+                            segment_ord: 0,
+                            doc_id: 12,
+                            secondary_sorting_field_values: Vec::new(),
+                            collapse_value: None,
+                            num_collapsed_hits: None,
+                        })
+                },
+            ))
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&search_after=1678000000:split_1:0:12")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&search_after=not-a-cursor")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_snippet_parameters() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.snippet_fields == ["body"]
+                        && search_request.snippet_max_num_chars == Some(50)
+                        && search_request.snippet_pre_tag.as_deref() == Some("<em>")
+                        && search_request.snippet_post_tag.as_deref() == Some("</em>")
+                },
+            ))
+            .returning(|_| Ok(Default::default()));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path(
+                "/quickwit-demo-index/search?query=*&snippet_fields=body&snippet_max_num_chars=50&\
+                 snippet_pre_tag=%3Cem%3E&snippet_post_tag=%3C%2Fem%3E",
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rest_search_api_scroll() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search()
+            .with(predicate::function(
+                |search_request: &quickwit_proto::SearchRequest| {
+                    search_request.scroll_ttl_secs == Some(120)
+                },
+            ))
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    scroll_id: Some("scroll_1".to_string()),
+                    ..Default::default()
+                })
+            });
+        mock_search_service
+            .expect_scroll()
+            .with(predicate::eq(quickwit_proto::ScrollRequest {
+                scroll_id: "scroll_1".to_string(),
+                scroll_ttl_secs: Some(60),
+            }))
+            .times(2)
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    scroll_id: Some("scroll_1".to_string()),
+                    ..Default::default()
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&scroll=2m")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(resp_json["scroll_id"], "scroll_1");
+
+        let resp = warp::test::request()
+            .path("/_search/scroll?scroll_id=scroll_1&scroll=1m")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_search/scroll")
+            .json(&true)
+            .body(r#"{"scroll_id": "scroll_1", "scroll": "60s"}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&scroll=forever")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_simple_format() {
-        let rest_search_api_filter = search_get_filter();
-        let (index, req) = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*&format=json")
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(&index, "quickwit-demo-index");
+    async fn test_rest_async_search_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_submit_async_search()
+            .withf(|search_request, keep_alive_secs| {
+                search_request.index_id == "quickwit-demo-index"
+                    && search_request.query == "*"
+                    && *keep_alive_secs == Some(7200)
+            })
+            .returning(|_, keep_alive_secs| {
+                Ok(AsyncSearchResponse {
+                    id: "async_search_1".to_string(),
+                    is_running: true,
+                    num_splits: 0,
+                    num_searched_splits: 0,
+                    expiration_timestamp: keep_alive_secs.unwrap() as u64,
+                    response: None,
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_get_async_search()
+            .withf(|index_id, async_search_id| {
+                index_id == "quickwit-demo-index" && async_search_id == "async_search_1"
+            })
+            .returning(|_, async_search_id| {
+                Ok(AsyncSearchResponse {
+                    id: async_search_id,
+                    is_running: false,
+                    num_splits: 2,
+                    num_searched_splits: 2,
+                    expiration_timestamp: 7200,
+                    response: Some(SearchResponseRest::try_from(
+                        quickwit_proto::SearchResponse {
+                            num_hits: 10,
+                            ..Default::default()
+                        },
+                    )?),
+                    error: None,
+                })
+            });
+        mock_search_service
+            .expect_delete_async_search()
+            .returning(|_, async_search_id| {
+                Err(SearchError::AsyncSearchDoesNotExist(async_search_id))
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/async_search?keep_alive=2h")
+            .json(&true)
+            .body(r#"{"query": "*"}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
         assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                start_timestamp: None,
-                end_timestamp: None,
-                max_hits: 20,
-                start_offset: 0,
-                format: BodyFormat::Json,
-                search_fields: None,
-                sort_by_field: None,
-                ..Default::default()
-            }
+            resp_json,
+            json!({
+                "id": "async_search_1",
+                "is_running": true,
+                "num_splits": 0,
+                "num_searched_splits": 0,
+                "expiration_timestamp": 7200,
+            })
         );
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/async_search/async_search_1")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(resp_json["response"]["num_hits"], 10);
+
+        let resp = warp::test::request()
+            .method("DELETE")
+            .path("/quickwit-demo-index/async_search/async_search_2")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/async_search?keep_alive=forever")
+            .json(&true)
+            .body(r#"{"query": "*"}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_sort_by() {
-        let rest_search_api_filter = search_get_filter();
-        let (_, req) = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=field")
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                start_timestamp: None,
-                end_timestamp: None,
-                max_hits: 20,
-                start_offset: 0,
-                format: BodyFormat::Json,
-                search_fields: None,
-                sort_by_field: Some(SortByField {
-                    field_name: "field".to_string(),
-                    order: SortOrder::Asc
-                }),
-                ..Default::default()
-            }
-        );
-
-        let rest_search_api_filter = search_get_filter();
-        let (_, req) = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=+field")
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
+    async fn test_rest_search_view_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_create_search_view()
+            .withf(|index_id, ttl_secs| index_id == "quickwit-demo-index" && *ttl_secs == Some(600))
+            .returning(|index_id, _| {
+                Ok(SearchViewResponse {
+                    view_id: "view_1".to_string(),
+                    index_id,
+                    num_splits: 3,
+                    expiration_timestamp: 600,
+                })
+            });
+        mock_search_service
+            .expect_root_search()
+            .withf(|search_request| search_request.view_id.as_deref() == Some("view_1"))
+            .returning(|_| Ok(Default::default()));
+        mock_search_service
+            .expect_delete_search_view()
+            .returning(|_, view_id| Err(SearchError::SearchViewDoesNotExist(view_id)));
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/quickwit-demo-index/views?ttl=10m")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
         assert_eq!(
-            &req,
-            &super::SearchRequestQueryString {
-                query: "*".to_string(),
-                start_timestamp: None,
-                end_timestamp: None,
-                max_hits: 20,
-                start_offset: 0,
-                format: BodyFormat::Json,
-                search_fields: None,
-                sort_by_field: Some(SortByField {
-                    field_name: "field".to_string(),
-                    order: SortOrder::Asc
-                }),
-                ..Default::default()
-            }
+            resp_json,
+            json!({
+                "view_id": "view_1",
+                "index_id": "quickwit-demo-index",
+                "num_splits": 3,
+                "expiration_timestamp": 600,
+            })
         );
+        let resp = warp::test::request()
+            .path("/quickwit-demo-index/search?query=*&view_id=view_1")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
 
-        let rest_search_api_filter = search_get_filter();
-        let (_, req) = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*&format=json&sort_by_field=-field")
-            .filter(&rest_search_api_filter)
-            .await
-            .unwrap();
-        assert_eq!(
-            &req,
-            &SearchRequestQueryString {
-                query: "*".to_string(),
-                start_timestamp: None,
-                end_timestamp: None,
-                max_hits: 20,
-                start_offset: 0,
-                format: BodyFormat::Json,
-                search_fields: None,
-                sort_by_field: Some(SortByField {
-                    field_name: "field".to_string(),
-                    order: SortOrder::Desc
-                }),
-                ..Default::default()
-            }
-        );
-    }
+        let resp = warp::test::request()
+            .method("DELETE")
+            .path("/quickwit-demo-index/views/view_2")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 404);
 
-    #[tokio::test]
-    async fn test_rest_search_api_route_invalid_key() -> anyhow::Result<()> {
         let resp = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*&end_unix_timestamp=1450720000")
-            .reply(&search_handler(MockSearchService::new()))
+            .method("POST")
+            .path("/quickwit-demo-index/views?ttl=forever")
+            .reply(&rest_search_api_handler)
             .await;
         assert_eq!(resp.status(), 400);
-        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
-        let exp_resp_json = serde_json::json!({
-            "message": "unknown field `end_unix_timestamp`, expected one of `query`, `aggs`, `search_field`, `snippet_fields`, `start_timestamp`, `end_timestamp`, `max_hits`, `start_offset`, `format`, `sort_by_field`"
-        });
-        assert_eq!(resp_json, exp_resp_json);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_post_with_invalid_payload() -> anyhow::Result<()> {
+    async fn test_rest_warmup_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_warmup()
+            .withf(|warmup_request| {
+                warmup_request.index_id == "quickwit-demo-index"
+                    && warmup_request.start_timestamp == Some(1_000)
+                    && warmup_request.fast_field_names == ["status"]
+                    && warmup_request.max_num_bytes == Some(10_000_000)
+                    && warmup_request.max_num_bytes_per_sec.is_none()
+            })
+            .returning(|_| {
+                Ok(WarmupResponse {
+                    num_warmed_splits: 2,
+                    num_skipped_splits: 1,
+                    num_downloaded_bytes: 9_000_000,
+                    failed_splits: Vec::new(),
+                })
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
         let resp = warp::test::request()
             .method("POST")
-            .path("/quickwit-demo-index/search?query=*&max_hits=10")
-            .json(&true)
-            .body(r#"{"query": "*", "bad_param":10, "aggs": {"range":[]} }"#)
-            .reply(&search_handler(MockSearchService::new()))
+            .path("/indexes/quickwit-demo-index/warmup")
+            .json(&json!({
+                "start_timestamp": 1_000,
+                "fast_fields": ["status"],
+                "max_bytes": "10MB",
+            }))
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_eq!(
+            resp_json,
+            json!({
+                "num_warmed_splits": 2,
+                "num_skipped_splits": 1,
+                "num_downloaded_bytes": 9_000_000,
+                "failed_splits": [],
+            })
+        );
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/indexes/quickwit-demo-index/warmup")
+            .json(&json!({"max_budget": "10MB"}))
+            .reply(&rest_search_api_handler)
             .await;
         assert_eq!(resp.status(), 400);
-        let content = String::from_utf8_lossy(resp.body());
-        assert!(content.contains("Request body deserialize error: unknown field `bad_param`"));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_route_serialize_with_results() -> anyhow::Result<()> {
+    async fn test_rest_explain_api() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
-        mock_search_service.expect_root_search().returning(|_| {
-            Ok(quickwit_proto::SearchResponse {
-                hits: Vec::new(),
-                num_hits: 10,
-                elapsed_time_micros: 16,
-                errors: vec![],
-                ..Default::default()
+        mock_search_service
+            .expect_explain()
+            .withf(|search_request| {
+                search_request.index_id == "quickwit-demo-index"
+                    && search_request.query == "status:500"
             })
-        });
+            .returning(|search_request| {
+                Ok(SearchExplanation {
+                    index_id: search_request.index_id,
+                    query: search_request.query,
+                    parsed_query: "TermQuery(status:500)".to_string(),
+                    num_published_splits: 2,
+                    pruned_splits: vec![PrunedSplit {
+                        split_id: "split-1".to_string(),
+                        reason: PruningReason::FieldRanges,
+                    }],
+                    searched_splits: vec![SplitExplanation {
+                        split_id: "split-2".to_string(),
+                        num_docs: 10,
+                        num_hits: 1,
+                        elapsed_time_micros: 100,
+                        leaf_search_cache_hit: false,
+                        split_footer_cache_hit: true,
+                        error: None,
+                    }],
+                    num_hits: 1,
+                    elapsed_time_micros: 200,
+                })
+            });
         let rest_search_api_handler = search_handler(mock_search_service);
         let resp = warp::test::request()
-            .path("/quickwit-demo-index/search?query=*")
+            .method("POST")
+            .path("/quickwit-demo-index/explain")
+            .json(&true)
+            .body(r#"{"query": "status:500"}"#)
             .reply(&rest_search_api_handler)
             .await;
         assert_eq!(resp.status(), 200);
         let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
-        let expected_response_json = serde_json::json!({
-            "num_hits": 10,
-            "hits": [],
-            "elapsed_time_micros": 16,
-        });
-        assert_json_include!(actual: resp_json, expected: expected_response_json);
+        assert_eq!(resp_json["pruned_splits"][0]["reason"], "field_ranges");
+        assert_eq!(
+            resp_json["searched_splits"][0]["split_footer_cache_hit"],
+            true
+        );
+        assert!(resp_json["searched_splits"][0].get("error").is_none());
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_rest_search_api_start_offset_and_num_hits_parameter() -> anyhow::Result<()> {
+    async fn test_rest_sql_api() -> anyhow::Result<()> {
         let mut mock_search_service = MockSearchService::new();
         mock_search_service
             .expect_root_search()
             .with(predicate::function(
                 |search_request: &quickwit_proto::SearchRequest| {
-                    search_request.start_offset == 5 && search_request.max_hits == 30
+                    search_request.index_id == "quickwit-demo-index"
+                        && search_request.query == "severity:\"ERROR\""
+                        && search_request.max_hits == 5
                 },
             ))
-            .returning(|_| Ok(Default::default()));
+            .returning(|_| {
+                Ok(quickwit_proto::SearchResponse {
+                    num_hits: 1,
+                    hits: vec![quickwit_proto::Hit {
+                        json: r#"{"title": "foo", "body": "bar"}"#.to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })
+            });
         let rest_search_api_handler = search_handler(mock_search_service);
-        assert_eq!(
-            warp::test::request()
-                .path("/quickwit-demo-index/search?query=*&start_offset=5&max_hits=30")
-                .reply(&rest_search_api_handler)
-                .await
-                .status(),
-            200
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&true)
+            .body(
+                r#"{"query": "SELECT title FROM \"quickwit-demo-index\" WHERE severity = 'ERROR' LIMIT 5"}"#,
+            )
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let resp_json: JsonValue = serde_json::from_slice(resp.body())?;
+        assert_json_include!(
+            actual: resp_json,
+            expected: json!({"columns": ["title"], "rows": [["foo"]], "num_hits": 1})
         );
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/_sql")
+            .json(&true)
+            .body(r#"{"query": "SELECT FROM quickwit-demo-index"}"#)
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(resp.status(), 400);
         Ok(())
     }
 
@@ -807,6 +2725,61 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_rest_search_hits_stream_api() -> anyhow::Result<()> {
+        let mut mock_search_service = MockSearchService::new();
+        mock_search_service
+            .expect_root_search_hits_stream()
+            .withf(|search_request| search_request.max_hits == 3)
+            .return_once(|_| {
+                let chunks = vec![
+                    Ok(quickwit_proto::SearchHitsChunk {
+                        num_hits: 2,
+                        hits: vec![
+                            quickwit_proto::Hit {
+                                json: r#"{"title": "foo"}"#.to_string(),
+                                ..Default::default()
+                            },
+                            quickwit_proto::Hit {
+                                json: r#"{"title": "bar"}"#.to_string(),
+                                ..Default::default()
+                            },
+                        ],
+                    }),
+                    Ok(quickwit_proto::SearchHitsChunk {
+                        num_hits: 1,
+                        hits: vec![quickwit_proto::Hit {
+                            json: "{\n  \"title\": \"baz\"\n}".to_string(),
+                            ..Default::default()
+                        }],
+                    }),
+                ];
+                Ok(Box::pin(futures::stream::iter(chunks)))
+            });
+        let rest_search_api_handler = search_handler(mock_search_service);
+        let response = warp::test::request()
+            .path("/my-index/search/hits-stream?query=obama&max_hits=3")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/x-ndjson"
+        );
+        let body = String::from_utf8_lossy(response.body());
+        assert_eq!(
+            body,
+            "{\"title\":\"foo\"}\n{\"title\":\"bar\"}\n{\"title\":\"baz\"}\n"
+        );
+
+        let response = warp::test::request()
+            .path("/my-index/search/hits-stream?query=obama&snippet_fields=title")
+            .reply(&rest_search_api_handler)
+            .await;
+        assert_eq!(response.status(), 400);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_rest_search_stream_api_csv() {
         let (index, req) = warp::test::request()
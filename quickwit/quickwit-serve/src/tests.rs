@@ -26,9 +26,13 @@ use hyper::{Body, Method, Request, StatusCode};
 use quickwit_cluster::create_cluster_for_test;
 use quickwit_common::uri::Uri;
 use quickwit_config::service::QuickwitService;
+use quickwit_grpc_clients::tls::grpc_endpoint;
+use quickwit_ingest_api::{replica_queue_id, IngestService, TailRequest};
 use quickwit_metastore::{IndexMetadata, MockMetastore};
 use quickwit_proto::SearchRequest;
+use tower::timeout::Timeout;
 
+use crate::ingest_api::grpc_ingest_service_client;
 use crate::test_utils::ClusterSandbox;
 use crate::{check_cluster_configuration, node_readiness_reporting_task};
 
@@ -73,6 +77,25 @@ async fn test_standalone_server_no_indexer() {
             sort_by_field: None,
             sort_order: None,
             start_offset: 0,
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         })
         .await
         .unwrap();
@@ -132,6 +155,25 @@ async fn test_multi_nodes_cluster() {
             sort_order: None,
             start_offset: 0,
             snippet_fields: Vec::new(),
+            geo_filter: None,
+            nested_query: None,
+            runtime_fields: None,
+            runtime_filter: None,
+            search_after: None,
+            scroll_ttl_secs: None,
+            snippet_max_num_chars: None,
+            snippet_pre_tag: None,
+            snippet_post_tag: None,
+            knn_query: None,
+            hybrid_ranking: None,
+            cross_cluster: false,
+            timeout_ms: None,
+            count_only: false,
+            sort_fields: None,
+            collapse: None,
+            include_split_metadata: false,
+            field_masks: Vec::new(),
+            view_id: None,
         })
         .await
         .unwrap();
@@ -149,6 +191,60 @@ async fn test_multi_nodes_cluster() {
     assert_eq!(indexing_service_counters.num_running_pipelines, 1);
 }
 
+#[tokio::test]
+async fn test_ingest_replication_between_two_indexers() {
+    quickwit_common::setup_logging_for_tests();
+    let nodes_services = vec![
+        HashSet::from_iter([
+            QuickwitService::Searcher,
+            QuickwitService::Metastore,
+            QuickwitService::Indexer,
+            QuickwitService::ControlPlane,
+        ]),
+        HashSet::from_iter([QuickwitService::Indexer]),
+    ];
+    let sandbox = ClusterSandbox::start_cluster_nodes(&nodes_services)
+        .await
+        .unwrap();
+    sandbox.wait_for_cluster_num_ready_nodes(2).await.unwrap();
+
+    // The first indexer leads the ingestion and replicates the documents to the second one.
+    let ingest_uri = format!(
+        "{}/api/v1/{}/ingest?ack=replicas",
+        sandbox.rest_client.root_url(),
+        sandbox.index_id_for_test
+    )
+    .parse::<hyper::Uri>()
+    .unwrap();
+    let docs = r#"{"timestamp": 1684993001, "body": "foo"}
+{"timestamp": 1684993002, "body": "bar"}"#;
+    let ingest_request = Request::builder()
+        .uri(ingest_uri)
+        .method(Method::POST)
+        .body(Body::from(docs))
+        .unwrap();
+    let response = sandbox
+        .rest_client
+        .client()
+        .request(ingest_request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let leader_node_id = &sandbox.node_configs[0].quickwit_config.node_id;
+    let replica_grpc_addr = sandbox.node_configs[1].quickwit_config.grpc_listen_addr;
+    let channel = grpc_endpoint(replica_grpc_addr).connect_lazy();
+    let mut replica_client =
+        grpc_ingest_service_client(Timeout::new(channel, Duration::from_secs(5)));
+    let fetch_response = replica_client
+        .tail(TailRequest {
+            index_id: replica_queue_id(leader_node_id, &sandbox.index_id_for_test),
+        })
+        .await
+        .unwrap();
+    assert_eq!(fetch_response.doc_batch.unwrap().num_docs(), 2);
+}
+
 #[tokio::test]
 async fn test_readiness_updates() -> anyhow::Result<()> {
     let transport = ChannelTransport::default();
@@ -279,6 +279,11 @@ impl ByteRangeCache {
             .unwrap()
             .put_slice(path, byte_range, bytes)
     }
+
+    /// Returns the number of bytes held by the cache.
+    pub fn num_bytes(&self) -> u64 {
+        self.inner.lock().unwrap().num_bytes
+    }
 }
 
 #[cfg(test)]
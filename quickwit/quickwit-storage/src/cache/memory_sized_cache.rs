@@ -174,6 +174,18 @@ impl<K: Hash + Eq> NeedMutMemorySizedCache<K> {
         self.record_item(bytes.len() as u64);
         self.lru_cache.put(key, StoredItem::new(bytes, now));
     }
+
+    /// Changes the capacity of the cache. Unlike `put`, shrinking the cache evicts the least
+    /// recently used items regardless of their last access time.
+    fn set_capacity(&mut self, capacity: Capacity) {
+        self.capacity = capacity;
+        while self.capacity.exceeds_capacity(self.num_bytes as usize) {
+            let Some((_, bytes)) = self.lru_cache.pop_lru() else {
+                break;
+            };
+            self.drop_item(bytes.len() as u64);
+        }
+    }
 }
 
 /// A simple in-resident memory slice cache.
@@ -220,6 +232,15 @@ impl<K: Hash + Eq> MemorySizedCache<K> {
     pub fn put(&self, val: K, bytes: OwnedBytes) {
         self.inner.lock().unwrap().put(val, bytes);
     }
+
+    /// Changes the capacity of the cache, evicting the least recently used items if the cache
+    /// shrinks.
+    pub fn set_capacity_in_bytes(&self, capacity_in_bytes: usize) {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_capacity(Capacity::InBytes(capacity_in_bytes));
+    }
 }
 
 impl MemorySizedCache<SliceAddress> {
@@ -302,6 +323,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cache_set_capacity_in_bytes() {
+        let cache = MemorySizedCache::<String>::with_capacity_in_bytes(5, &CACHE_METRICS_FOR_TESTS);
+        cache.put("3".to_string(), OwnedBytes::new(&b"abc"[..]));
+        cache.put("2".to_string(), OwnedBytes::new(&b"de"[..]));
+
+        cache.set_capacity_in_bytes(10);
+        cache.put("5".to_string(), OwnedBytes::new(&b"fghij"[..]));
+        assert_eq!(cache.get(&"3".to_string()).unwrap(), &b"abc"[..]);
+        assert_eq!(cache.get(&"2".to_string()).unwrap(), &b"de"[..]);
+        assert_eq!(cache.get(&"5".to_string()).unwrap(), &b"fghij"[..]);
+
+        // Shrinking the cache evicts the least recently used items, even if they are young.
+        cache.set_capacity_in_bytes(7);
+        assert!(cache.get(&"3".to_string()).is_none());
+        assert_eq!(cache.get(&"2".to_string()).unwrap(), &b"de"[..]);
+        assert_eq!(cache.get(&"5".to_string()).unwrap(), &b"fghij"[..]);
+    }
+
     #[test]
     fn test_cache() {
         let cache = MemorySizedCache::with_capacity_in_bytes(10_000, &CACHE_METRICS_FOR_TESTS);
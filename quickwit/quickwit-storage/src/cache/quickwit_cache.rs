@@ -45,15 +45,24 @@ impl QuickwitCache {
     /// Creates a [`QuickwitCache`] with a cache on fast fields
     /// with a capacity of `fast_field_cache_capacity`.
     pub fn new(fast_field_cache_capacity: usize) -> Self {
-        let mut quickwit_cache = QuickwitCache::empty();
         let fast_field_cache_counters: &'static CacheMetrics =
             &crate::STORAGE_METRICS.fast_field_cache;
+        let fast_field_cache = Arc::new(MemorySizedCache::with_capacity_in_bytes(
+            fast_field_cache_capacity,
+            fast_field_cache_counters,
+        ));
+        QuickwitCache::with_fast_field_cache(fast_field_cache)
+    }
+
+    /// Creates a [`QuickwitCache`] storing the fast fields in `fast_field_cache`, which the caller
+    /// can resize.
+    pub fn with_fast_field_cache(fast_field_cache: Arc<MemorySizedCache>) -> Self {
+        let mut quickwit_cache = QuickwitCache::empty();
         quickwit_cache.add_route(
             ".fast",
-            Arc::new(SimpleCache::with_capacity_in_bytes(
-                fast_field_cache_capacity,
-                fast_field_cache_counters,
-            )),
+            Arc::new(SimpleCache {
+                slice_cache: fast_field_cache,
+            }),
         );
         quickwit_cache
     }
@@ -118,21 +127,7 @@ impl Cache for QuickwitCache {
 /// HACK! We use `0..usize::MAX` to signify the "entire file".
 /// TODO fixme
 struct SimpleCache {
-    slice_cache: MemorySizedCache,
-}
-
-impl SimpleCache {
-    fn with_capacity_in_bytes(
-        capacity_in_bytes: usize,
-        cache_counters: &'static CacheMetrics,
-    ) -> Self {
-        SimpleCache {
-            slice_cache: MemorySizedCache::with_capacity_in_bytes(
-                capacity_in_bytes,
-                cache_counters,
-            ),
-        }
-    }
+    slice_cache: Arc<MemorySizedCache>,
 }
 
 #[async_trait]
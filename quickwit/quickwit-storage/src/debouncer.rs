@@ -164,6 +164,10 @@ impl<T: Storage> Storage for DebouncedStorage<T> {
         self.underlying.bulk_delete(paths).await
     }
 
+    async fn abort_incomplete_uploads(&self, path: &Path) -> StorageResult<()> {
+        self.underlying.abort_incomplete_uploads(path).await
+    }
+
     async fn get_all(&self, path: &Path) -> StorageResult<OwnedBytes> {
         let (debouncer, underlying) = (self.slice_debouncer.clone(), self.underlying.clone());
         let key = (path.to_owned(), 0..usize::MAX);
@@ -182,6 +186,10 @@ impl<T: Storage> Storage for DebouncedStorage<T> {
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
         self.underlying.file_num_bytes(path).await
     }
+
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        self.underlying.list_dir(dir_path).await
+    }
 }
 
 #[cfg(test)]
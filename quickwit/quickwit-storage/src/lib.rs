@@ -67,7 +67,7 @@ pub use self::object_storage::{
     MultiPartPolicy, S3CompatibleObjectStorage, S3CompatibleObjectStorageFactory,
 };
 pub use self::ram_storage::{RamStorage, RamStorageBuilder};
-pub use self::split::{SplitPayload, SplitPayloadBuilder};
+pub use self::split::{FilePayload, SplitPayload, SplitPayloadBuilder, StoragePayload};
 #[cfg(any(test, feature = "testsuite"))]
 pub use self::storage::MockStorage;
 #[cfg(any(test, feature = "testsuite"))]
@@ -266,6 +266,26 @@ pub(crate) mod test_suite {
         Ok(())
     }
 
+    async fn test_list_dir(storage: &mut dyn Storage) -> anyhow::Result<()> {
+        let dir_path = Path::new("list_dir");
+        assert!(storage.list_dir(dir_path).await?.is_empty());
+        for file_path in ["list_dir/foo", "list_dir/bar", "list_dir/nested/baz"] {
+            storage
+                .put(Path::new(file_path), Box::new(b"list_dir".to_vec()))
+                .await?;
+        }
+        let mut file_paths = storage.list_dir(dir_path).await?;
+        file_paths.sort();
+        assert_eq!(
+            file_paths,
+            [Path::new("list_dir/bar"), Path::new("list_dir/foo")]
+        );
+        for file_path in ["list_dir/foo", "list_dir/bar", "list_dir/nested/baz"] {
+            storage.delete(Path::new(file_path)).await?;
+        }
+        Ok(())
+    }
+
     /// Generic test suite for a storage.
     pub async fn storage_test_suite(storage: &mut dyn Storage) -> anyhow::Result<()> {
         test_get_inexistent_file(storage)
@@ -289,6 +309,7 @@ pub(crate) mod test_suite {
             .await
             .context("write_and_delete_with_separator")?;
         test_file_size(storage).await.context("file_size")?;
+        test_list_dir(storage).await.context("list_dir")?;
         test_delete_missing_file(storage)
             .await
             .context("delete_missing_file")?;
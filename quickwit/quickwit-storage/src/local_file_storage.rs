@@ -320,6 +320,22 @@ impl Storage for LocalFileStorage {
             }
         }
     }
+
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        let full_dir_path = self.full_path(dir_path)?;
+        let mut read_dir = match fs::read_dir(&full_dir_path).await {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut file_paths = Vec::new();
+        while let Some(dir_entry) = read_dir.next_entry().await? {
+            if dir_entry.file_type().await?.is_file() {
+                file_paths.push(dir_path.join(dir_entry.file_name()));
+            }
+        }
+        Ok(file_paths)
+    }
 }
 
 /// A File storage resolver
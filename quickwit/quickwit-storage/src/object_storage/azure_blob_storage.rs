@@ -431,6 +431,29 @@ impl Storage for AzureBlobStorage {
         }
     }
 
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        let mut blob_prefix = self.blob_name(dir_path);
+        if !blob_prefix.is_empty() && !blob_prefix.ends_with('/') {
+            blob_prefix.push('/');
+        }
+        let mut list_blobs_stream = self
+            .container_client
+            .list_blobs()
+            .prefix(blob_prefix)
+            .delimiter("/")
+            .into_stream();
+        let mut file_paths = Vec::new();
+        while let Some(list_blobs_result) = list_blobs_stream.next().await {
+            let list_blobs_response = list_blobs_result.map_err(AzureErrorWrapper::from)?;
+            for blob in list_blobs_response.blobs.blobs {
+                if let Ok(relative_path) = Path::new(&blob.name).strip_prefix(&self.prefix) {
+                    file_paths.push(relative_path.to_path_buf());
+                }
+            }
+        }
+        Ok(file_paths)
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -22,8 +22,8 @@ use quickwit_aws::retry::Retryable;
 use rusoto_core::RusotoError;
 use rusoto_s3::{
     AbortMultipartUploadError, CompleteMultipartUploadError, CreateMultipartUploadError,
-    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError, PutObjectError,
-    UploadPartError,
+    DeleteObjectError, DeleteObjectsError, GetObjectError, HeadObjectError,
+    ListMultipartUploadsError, ListObjectsV2Error, PutObjectError, UploadPartError,
 };
 
 use crate::{StorageError, StorageErrorKind};
@@ -106,3 +106,17 @@ impl ToStorageErrorKind for HeadObjectError {
         StorageErrorKind::Service
     }
 }
+
+impl ToStorageErrorKind for ListMultipartUploadsError {
+    fn to_storage_error_kind(&self) -> StorageErrorKind {
+        StorageErrorKind::Service
+    }
+}
+
+impl ToStorageErrorKind for ListObjectsV2Error {
+    fn to_storage_error_kind(&self) -> StorageErrorKind {
+        match self {
+            ListObjectsV2Error::NoSuchBucket(_) => StorageErrorKind::DoesNotExist,
+        }
+    }
+}
@@ -39,8 +39,8 @@ use rusoto_s3::{
     AbortMultipartUploadRequest, CompleteMultipartUploadRequest, CompletedMultipartUpload,
     CompletedPart, CreateMultipartUploadError, CreateMultipartUploadRequest, Delete,
     DeleteObjectRequest, DeleteObjectsRequest, GetObjectRequest, HeadObjectError,
-    HeadObjectRequest, ListObjectsV2Request, ObjectIdentifier, PutObjectError, PutObjectRequest,
-    S3Client, UploadPartRequest, S3,
+    HeadObjectRequest, ListMultipartUploadsRequest, ListObjectsV2Request, ObjectIdentifier,
+    PutObjectError, PutObjectRequest, S3Client, UploadPartRequest, S3,
 };
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
 use tracing::{instrument, warn};
@@ -688,6 +688,43 @@ impl Storage for S3CompatibleObjectStorage {
         Ok(bytes)
     }
 
+    async fn abort_incomplete_uploads(&self, path: &Path) -> StorageResult<()> {
+        let key = self.key(path);
+        let mut key_marker: Option<String> = None;
+        let mut upload_id_marker: Option<String> = None;
+        loop {
+            let list_uploads_req = ListMultipartUploadsRequest {
+                bucket: self.bucket.clone(),
+                prefix: Some(key.clone()),
+                key_marker: key_marker.clone(),
+                upload_id_marker: upload_id_marker.clone(),
+                ..Default::default()
+            };
+            let list_uploads_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_multipart_uploads(list_uploads_req.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            })
+            .await?;
+            for upload in list_uploads_output.uploads.unwrap_or_default() {
+                // The prefix also matches the keys starting with the key of the file.
+                if upload.key.as_deref() != Some(key.as_str()) {
+                    continue;
+                }
+                if let Some(upload_id) = upload.upload_id {
+                    self.abort_multipart_upload(&key, &upload_id).await?;
+                }
+            }
+            if !list_uploads_output.is_truncated.unwrap_or(false) {
+                break;
+            }
+            key_marker = list_uploads_output.next_key_marker;
+            upload_id_marker = list_uploads_output.next_upload_id_marker;
+        }
+        Ok(())
+    }
+
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
         let key = self.key(path);
         let head_object_req = HeadObjectRequest {
@@ -736,6 +773,41 @@ impl Storage for S3CompatibleObjectStorage {
         }
     }
 
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        let mut key_prefix = self.key(dir_path);
+        if !key_prefix.is_empty() && !key_prefix.ends_with('/') {
+            key_prefix.push('/');
+        }
+        let mut file_paths = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let list_objects_req = ListObjectsV2Request {
+                bucket: self.bucket.clone(),
+                prefix: Some(key_prefix.clone()),
+                delimiter: Some("/".to_string()),
+                continuation_token: continuation_token.clone(),
+                ..Default::default()
+            };
+            let list_objects_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_objects_v2(list_objects_req.clone())
+                    .await
+                    .map_err(RusotoErrorWrapper::from)
+            })
+            .await?;
+            for object in list_objects_output.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    file_paths.push(self.relative_path(&key));
+                }
+            }
+            continuation_token = list_objects_output.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        Ok(file_paths)
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -927,4 +999,51 @@ mod tests {
         let delete_objects_error = bulk_delete_error.error.unwrap();
         assert!(delete_objects_error.to_string().contains("MalformedXML"));
     }
+
+    #[tokio::test]
+    async fn test_s3_compatible_storage_abort_incomplete_uploads() {
+        let request_dispatcher = MultipleMockRequestDispatcher::new([
+            MockRequestDispatcher::with_status(200).with_body(
+                r#"
+                <?xml version="1.0" encoding="UTF-8"?>
+                <ListMultipartUploadsResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+                    <Bucket>bucket</Bucket>
+                    <Prefix>foo.split</Prefix>
+                    <IsTruncated>false</IsTruncated>
+                    <Upload>
+                        <Key>foo.split</Key>
+                        <UploadId>upload-foo</UploadId>
+                    </Upload>
+                    <Upload>
+                        <Key>foo.split.bak</Key>
+                        <UploadId>upload-foo-bak</UploadId>
+                    </Upload>
+                </ListMultipartUploadsResult>"#,
+            ),
+            MockRequestDispatcher::with_status(204).with_request_checker(|request| {
+                assert_eq!(request.method, "DELETE");
+                assert_eq!(
+                    request.params.get("uploadId"),
+                    Some(&Some("upload-foo".to_string()))
+                );
+            }),
+        ]);
+        let s3_client = rusoto_s3::S3Client::new_with(
+            request_dispatcher,
+            MockCredentialsProvider,
+            Default::default(),
+        );
+        let s3_storage = S3CompatibleObjectStorage {
+            s3_client,
+            uri: Uri::for_test("s3://bucket"),
+            bucket: "bucket".to_string(),
+            prefix: PathBuf::new(),
+            multipart_policy: MultiPartPolicy::default(),
+            retry_params: RetryParams::default(),
+        };
+        s3_storage
+            .abort_incomplete_uploads(Path::new("foo.split"))
+            .await
+            .unwrap();
+    }
 }
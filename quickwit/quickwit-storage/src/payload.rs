@@ -21,7 +21,9 @@ use std::io;
 use std::ops::Range;
 
 use async_trait::async_trait;
+use futures::StreamExt;
 use rusoto_core::ByteStream;
+use sha2::{Digest, Sha256};
 use tantivy::directory::OwnedBytes;
 
 #[async_trait]
@@ -51,6 +53,17 @@ pub trait PutPayload: PutPayloadClone + Send + Sync {
 
         Ok(OwnedBytes::new(data))
     }
+
+    /// Returns the hex-encoded SHA-256 hash of the payload, computed without loading the whole
+    /// payload into memory.
+    async fn content_hash(&self) -> io::Result<String> {
+        let mut byte_stream = self.byte_stream().await?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = byte_stream.next().await {
+            hasher.update(&chunk?);
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
 }
 
 pub trait PutPayloadClone {
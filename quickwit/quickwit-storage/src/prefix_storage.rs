@@ -87,6 +87,12 @@ impl Storage for PrefixStorage {
         Ok(())
     }
 
+    async fn abort_incomplete_uploads(&self, path: &Path) -> crate::StorageResult<()> {
+        self.storage
+            .abort_incomplete_uploads(&self.prefix.join(path))
+            .await
+    }
+
     async fn exists(&self, path: &Path) -> crate::StorageResult<bool> {
         self.storage.exists(&self.prefix.join(path)).await
     }
@@ -98,6 +104,17 @@ impl Storage for PrefixStorage {
     async fn file_num_bytes(&self, path: &Path) -> crate::StorageResult<u64> {
         self.storage.file_num_bytes(&self.prefix.join(path)).await
     }
+
+    async fn list_dir(&self, dir_path: &Path) -> crate::StorageResult<Vec<PathBuf>> {
+        let file_paths = self
+            .storage
+            .list_dir(&self.prefix.join(dir_path))
+            .await?
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&self.prefix).ok().map(Path::to_path_buf))
+            .collect();
+        Ok(file_paths)
+    }
 }
 
 /// Creates a [`PrefixStorage`] using an underlying storage and a prefix.
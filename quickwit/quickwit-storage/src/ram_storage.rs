@@ -148,6 +148,18 @@ impl Storage for RamStorage {
             Err(StorageErrorKind::DoesNotExist.with_error(err))
         }
     }
+
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        let file_paths = self
+            .files
+            .read()
+            .await
+            .keys()
+            .filter(|path| path.parent() == Some(dir_path))
+            .cloned()
+            .collect();
+        Ok(file_paths)
+    }
 }
 
 /// Builder to create a prepopulated [`RamStorage`]. This is mostly useful for tests.
@@ -22,14 +22,21 @@ use std::fmt::Debug;
 use std::io::{self, ErrorKind, SeekFrom};
 use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::{stream, StreamExt};
 use rusoto_core::ByteStream;
 use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::io::ReaderStream;
 
-use crate::{BundleStorageFileOffsets, PutPayload};
+use crate::{BundleStorageFileOffsets, PutPayload, Storage, StorageResult};
+
+/// Size of the chunks a [`StoragePayload`] reads from its storage.
+const STORAGE_PAYLOAD_CHUNK_NUM_BYTES: u64 = 8 * 1024 * 1024; // 8 MiB
 
 /// Payload of a split which builds the split bundle and hotcache on the fly and streams it to the
 /// storage.
@@ -72,12 +79,21 @@ impl PutPayload for SplitPayload {
     }
 }
 
+/// Payload streaming the content of a local file to the storage.
 #[derive(Clone)]
-struct FilePayload {
+pub struct FilePayload {
     len: u64,
     path: PathBuf,
 }
 
+impl FilePayload {
+    /// Creates a payload for the file located at `path`.
+    pub async fn open(path: PathBuf) -> io::Result<FilePayload> {
+        let len = tokio::fs::metadata(&path).await?.len();
+        Ok(FilePayload { len, path })
+    }
+}
+
 #[async_trait]
 impl PutPayload for FilePayload {
     fn len(&self) -> u64 {
@@ -100,6 +116,63 @@ impl PutPayload for FilePayload {
     }
 }
 
+/// Payload of a file held by a storage, used to copy a file from one storage to another without
+/// going through the local disk. The file is streamed by chunks of
+/// [`STORAGE_PAYLOAD_CHUNK_NUM_BYTES`], so that at most a couple of chunks are held in memory.
+#[derive(Clone)]
+pub struct StoragePayload {
+    storage: Arc<dyn Storage>,
+    path: PathBuf,
+    len: u64,
+}
+
+impl StoragePayload {
+    /// Creates a payload for the file located at `path` in `storage`.
+    pub async fn open(storage: Arc<dyn Storage>, path: &Path) -> StorageResult<StoragePayload> {
+        let len = storage.file_num_bytes(path).await?;
+        Ok(StoragePayload {
+            storage,
+            path: path.to_path_buf(),
+            len,
+        })
+    }
+}
+
+#[async_trait]
+impl PutPayload for StoragePayload {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    async fn range_byte_stream(&self, range: Range<u64>) -> io::Result<ByteStream> {
+        assert!(!range.is_empty());
+        assert!(range.end <= self.len);
+        let storage = self.storage.clone();
+        let path = self.path.clone();
+        // The chunks are read by a task rather than by the stream itself, which must be `Sync`.
+        let (chunk_tx, chunk_rx) = mpsc::channel::<io::Result<Bytes>>(1);
+        tokio::spawn(async move {
+            let mut chunk_start = range.start;
+
+            while chunk_start < range.end {
+                let chunk_end = (chunk_start + STORAGE_PAYLOAD_CHUNK_NUM_BYTES).min(range.end);
+                let chunk_res = storage
+                    .get_slice(&path, chunk_start as usize..chunk_end as usize)
+                    .await
+                    .map(|chunk| Bytes::copy_from_slice(chunk.as_slice()))
+                    .map_err(io::Error::from);
+                let is_err = chunk_res.is_err();
+
+                if chunk_tx.send(chunk_res).await.is_err() || is_err {
+                    return;
+                }
+                chunk_start = chunk_end;
+            }
+        });
+        Ok(ByteStream::new(ReceiverStream::new(chunk_rx)))
+    }
+}
+
 /// SplitPayloadBuilder is used to create a `SplitPayload`.
 #[derive(Debug, Default)]
 pub struct SplitPayloadBuilder {
@@ -420,4 +493,49 @@ mod tests {
         assert_eq!(all_data[all_data.len() - 8..], 3_u64.to_le_bytes());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_split_payload_content_hash() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_filepath = temp_dir.path().join("f1");
+        let mut file = File::create(&test_filepath)?;
+        file.write_all(b"hello")?;
+
+        let split_payload = SplitPayloadBuilder::get_split_payload(&[test_filepath], b"abc")?;
+        let content_hash = split_payload.content_hash().await?;
+        assert_eq!(content_hash.len(), 64);
+
+        let split_bytes = split_payload.read_all().await?;
+        assert_eq!(content_hash, split_bytes.to_vec().content_hash().await?);
+        assert_ne!(content_hash, b"hello".to_vec().content_hash().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_storage_payload() -> anyhow::Result<()> {
+        let storage: Arc<dyn Storage> = Arc::new(crate::RamStorage::default());
+        let path = Path::new("f1");
+        storage.put(path, Box::new(b"hello world".to_vec())).await?;
+
+        let storage_payload = StoragePayload::open(storage.clone(), path).await?;
+        assert_eq!(storage_payload.len(), 11);
+        assert_eq!(storage_payload.read_all().await?.as_slice(), b"hello world");
+
+        let mut range_bytes = Vec::new();
+        storage_payload
+            .range_byte_stream(6..11)
+            .await?
+            .into_async_read()
+            .read_to_end(&mut range_bytes)
+            .await?;
+        assert_eq!(range_bytes, b"world");
+
+        let target_storage = crate::RamStorage::default();
+        target_storage.put(path, Box::new(storage_payload)).await?;
+        assert_eq!(
+            target_storage.get_all(path).await?.as_slice(),
+            b"hello world"
+        );
+        Ok(())
+    }
 }
@@ -111,6 +111,16 @@ pub trait Storage: Send + Sync + 'static {
     /// successfully deleted while others are not.
     async fn bulk_delete<'a>(&self, paths: &[&'a Path]) -> Result<(), BulkDeleteError>;
 
+    /// Aborts the uploads of the file that were started but never completed, for instance
+    /// because the node crashed in the middle of a multipart upload, and releases the storage
+    /// used by their parts.
+    ///
+    /// The default implementation does nothing, which suits the storages that do not keep the
+    /// parts of incomplete uploads.
+    async fn abort_incomplete_uploads(&self, _path: &Path) -> StorageResult<()> {
+        Ok(())
+    }
+
     /// Returns whether a file exists or not.
     async fn exists(&self, path: &Path) -> StorageResult<bool> {
         match self.file_num_bytes(path).await {
@@ -123,6 +133,20 @@ pub trait Storage: Send + Sync + 'static {
     /// Returns a file size.
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64>;
 
+    /// Lists the files located directly in the directory `dir_path`, excluding the files of its
+    /// subdirectories. The returned paths are relative to the storage root. Use an empty path to
+    /// list the files at the root of the storage.
+    ///
+    /// Listing a directory that does not exist returns an empty list. Storages that do not
+    /// support listing return an error.
+    async fn list_dir(&self, dir_path: &Path) -> StorageResult<Vec<PathBuf>> {
+        Err(StorageErrorKind::InternalError.with_error(anyhow::anyhow!(
+            "Storage `{}` does not support listing directory `{}`.",
+            self.uri(),
+            dir_path.display()
+        )))
+    }
+
     /// Returns an URI identifying the storage
     fn uri(&self) -> &Uri;
 }